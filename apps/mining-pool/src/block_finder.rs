@@ -0,0 +1,278 @@
+// Block template tracking
+// Polls the Nockchain node for the chain tip and hands miners a fresh job whenever it moves
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+use crate::{
+    broadcast_bus::{BroadcastBus, NewBlock},
+    config::Config,
+};
+
+// How often the node is asked for the current tip
+pub const TEMPLATE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Jobs buffered per subscriber; a lagging miner only needs the newest one
+const JOB_CHANNEL_CAPACITY: usize = 16;
+
+const TEMPLATE_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MiningJob {
+    pub job_id: u64,
+    pub prev_hash: String,
+    pub merkle_root: String,
+    pub bits: u32,
+    pub height: u64,
+}
+
+// Template as returned by the node's mining RPC
+#[derive(Debug, Deserialize)]
+struct TemplateResponse {
+    prev_hash: String,
+    merkle_root: String,
+    bits: u32,
+    height: u64,
+}
+
+pub struct BlockFinder {
+    rpc_url: String,
+    client: reqwest::Client,
+    poll_interval: Duration,
+    current_job: RwLock<Option<MiningJob>>,
+    jobs: broadcast::Sender<MiningJob>,
+    next_job_id: AtomicU64,
+    bus: BroadcastBus,
+}
+
+impl BlockFinder {
+    pub fn new(config: &Config) -> Self {
+        Self::with_poll_interval(config.payout.nockchain_rpc_url.clone(), TEMPLATE_POLL_INTERVAL)
+    }
+
+    pub fn with_poll_interval(rpc_url: String, poll_interval: Duration) -> Self {
+        let (jobs, _) = broadcast::channel(JOB_CHANNEL_CAPACITY);
+        Self {
+            rpc_url,
+            client: reqwest::Client::builder()
+                .timeout(TEMPLATE_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            poll_interval,
+            current_job: RwLock::new(None),
+            jobs,
+            next_job_id: AtomicU64::new(1),
+            bus: BroadcastBus::new(),
+        }
+    }
+
+    /// Publishes found blocks on a shared bus instead of a private one
+    pub fn with_bus(mut self, bus: BroadcastBus) -> Self {
+        self.bus = bus;
+        self
+    }
+
+    pub fn bus(&self) -> &BroadcastBus {
+        &self.bus
+    }
+
+    /// Polls the node until the task is dropped. RPC failures are logged and retried on
+    /// the next tick so a node restart does not take the pool down.
+    pub async fn start(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.poll_once().await {
+                warn!("Block template poll failed: {:#}", e);
+            }
+        }
+    }
+
+    /// Fetches the node's template and publishes a new job if the tip has moved.
+    /// Returns whether a new job was published.
+    pub async fn poll_once(&self) -> Result<bool> {
+        let template = self.fetch_template().await?;
+
+        let (job, tip_moved) = {
+            let mut current = self.current_job.write().await;
+            if current.as_ref().is_some_and(|job| job.prev_hash == template.prev_hash) {
+                return Ok(false);
+            }
+
+            let job = MiningJob {
+                job_id: self.next_job_id.fetch_add(1, Ordering::Relaxed),
+                prev_hash: template.prev_hash,
+                merkle_root: template.merkle_root,
+                bits: template.bits,
+                height: template.height,
+            };
+            // The first template only tells us where the chain is, not that a block was found
+            let tip_moved = current.replace(job.clone()).is_some();
+            (job, tip_moved)
+        };
+
+        info!("New chain tip {} at height {}, job {}", job.prev_hash, job.height, job.job_id);
+        if tip_moved {
+            self.bus.publish(NewBlock {
+                block_hash: job.prev_hash.clone(),
+                mining_height: job.height,
+                job_id: job.job_id,
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            });
+        }
+        // No receivers just means no miners are connected yet
+        let _ = self.jobs.send(job);
+        Ok(true)
+    }
+
+    pub async fn current_job(&self) -> Option<MiningJob> {
+        self.current_job.read().await.clone()
+    }
+
+    /// The cached job, if any, and a receiver for every job published after it. The
+    /// receiver is created first so a tip change in between is never missed.
+    pub async fn subscribe(&self) -> (Option<MiningJob>, broadcast::Receiver<MiningJob>) {
+        let receiver = self.jobs.subscribe();
+        (self.current_job().await, receiver)
+    }
+
+    async fn fetch_template(&self) -> Result<TemplateResponse> {
+        self.client
+            .get(format!("{}/mining/template", self.rpc_url.trim_end_matches('/')))
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected template request")?
+            .json()
+            .await
+            .context("Malformed block template response")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn template(prev_hash: &str, height: u64) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "prev_hash": prev_hash,
+            "merkle_root": format!("root-{}", height),
+            "bits": 0x1d00ffff,
+            "height": height,
+        }))
+    }
+
+    async fn mount_tip(server: &MockServer, prev_hash: &str, height: u64, polls: u64) {
+        Mock::given(method("GET"))
+            .and(path("/mining/template"))
+            .respond_with(template(prev_hash, height))
+            .up_to_n_times(polls)
+            .mount(server)
+            .await;
+    }
+
+    async fn next_job(receiver: &mut broadcast::Receiver<MiningJob>) -> MiningJob {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("No job published")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tip_changes_are_broadcast() {
+        let server = MockServer::start().await;
+        mount_tip(&server, "tip-a", 100, 3).await;
+        mount_tip(&server, "tip-b", 101, 3).await;
+        Mock::given(method("GET"))
+            .and(path("/mining/template"))
+            .respond_with(template("tip-c", 102))
+            .mount(&server)
+            .await;
+
+        let finder = Arc::new(BlockFinder::with_poll_interval(server.uri(), Duration::from_millis(10)));
+        let (cached, mut jobs) = finder.subscribe().await;
+        assert_eq!(cached, None);
+
+        let task = tokio::spawn({
+            let finder = finder.clone();
+            async move { finder.start().await }
+        });
+
+        let first = next_job(&mut jobs).await;
+        let second = next_job(&mut jobs).await;
+        let third = next_job(&mut jobs).await;
+        task.abort();
+
+        // Repeated polls of the same tip publish nothing in between
+        assert_eq!((first.prev_hash.as_str(), first.height, first.job_id), ("tip-a", 100, 1));
+        assert_eq!((second.prev_hash.as_str(), second.height, second.job_id), ("tip-b", 101, 2));
+        assert_eq!((third.prev_hash.as_str(), third.height, third.job_id), ("tip-c", 102, 3));
+        assert_eq!(second.merkle_root, "root-101");
+        assert_eq!(second.bits, 0x1d00ffff);
+        assert_eq!(finder.current_job().await, Some(third));
+    }
+
+    #[tokio::test]
+    async fn test_late_subscriber_gets_cached_job() {
+        let server = MockServer::start().await;
+        mount_tip(&server, "tip-a", 100, 2).await;
+        mount_tip(&server, "tip-b", 101, 1).await;
+
+        let finder = BlockFinder::with_poll_interval(server.uri(), Duration::from_millis(10));
+        assert!(finder.poll_once().await.unwrap());
+        assert!(!finder.poll_once().await.unwrap());
+
+        let (cached, mut jobs) = finder.subscribe().await;
+        let cached = cached.expect("Template should be cached");
+        assert_eq!(cached.prev_hash, "tip-a");
+
+        assert!(finder.poll_once().await.unwrap());
+        assert_eq!(next_job(&mut jobs).await.prev_hash, "tip-b");
+    }
+
+    #[tokio::test]
+    async fn test_rpc_failure_keeps_cached_job() {
+        let server = MockServer::start().await;
+        mount_tip(&server, "tip-a", 100, 1).await;
+        Mock::given(method("GET"))
+            .and(path("/mining/template"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let finder = BlockFinder::with_poll_interval(server.uri(), Duration::from_millis(10));
+        assert!(finder.poll_once().await.unwrap());
+        assert!(finder.poll_once().await.is_err());
+        assert_eq!(finder.current_job().await.map(|job| job.height), Some(100));
+    }
+
+    #[tokio::test]
+    async fn test_found_blocks_published_on_bus() {
+        let server = MockServer::start().await;
+        mount_tip(&server, "tip-a", 100, 1).await;
+        mount_tip(&server, "tip-b", 101, 1).await;
+
+        let bus = BroadcastBus::new();
+        let mut blocks = bus.subscribe();
+        let finder = BlockFinder::with_poll_interval(server.uri(), Duration::from_millis(10)).with_bus(bus);
+
+        // Learning the starting tip is not a found block
+        assert!(finder.poll_once().await.unwrap());
+        assert!(blocks.try_recv().is_err());
+
+        assert!(finder.poll_once().await.unwrap());
+        let block = blocks.try_recv().unwrap();
+        assert_eq!((block.block_hash.as_str(), block.mining_height, block.job_id), ("tip-b", 101, 2));
+    }
+}
@@ -0,0 +1,108 @@
+// Time-to-next-block estimation
+// Models block discovery as a Poisson process over the pool's share of network work
+
+use serde::{Deserialize, Serialize};
+
+// How often the cached estimate is refreshed
+pub const ESTIMATE_REFRESH_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTimeEstimate {
+    pub expected_seconds: f64,
+    pub p50_seconds: f64,
+    pub p90_seconds: f64,
+    pub p99_seconds: f64,
+    pub current_round_age_seconds: f64,
+    pub probability_in_next_hour: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlockTimeEstimator {
+    pub pool_hashrate: f64,
+    pub network_difficulty: u64,
+    pub current_round_age_seconds: f64,
+}
+
+impl BlockTimeEstimator {
+    pub fn new(pool_hashrate: f64, network_difficulty: u64, current_round_age_seconds: f64) -> Self {
+        Self {
+            pool_hashrate,
+            network_difficulty,
+            current_round_age_seconds,
+        }
+    }
+
+    // Expected blocks found per second
+    pub fn lambda(&self) -> f64 {
+        if self.network_difficulty == 0 || self.pool_hashrate <= 0.0 {
+            return 0.0;
+        }
+        self.pool_hashrate / self.network_difficulty as f64
+    }
+
+    pub fn estimate(&self) -> BlockTimeEstimate {
+        let lambda = self.lambda();
+
+        // Block discovery is memoryless, so the round age does not shift the quantiles
+        BlockTimeEstimate {
+            expected_seconds: if lambda > 0.0 { 1.0 / lambda } else { f64::INFINITY },
+            p50_seconds: Self::quantile(lambda, 0.50),
+            p90_seconds: Self::quantile(lambda, 0.90),
+            p99_seconds: Self::quantile(lambda, 0.99),
+            current_round_age_seconds: self.current_round_age_seconds,
+            probability_in_next_hour: 1.0 - (-lambda * 3600.0).exp(),
+        }
+    }
+
+    // Inverse CDF of the exponential distribution: t = -ln(1 - p) / lambda
+    fn quantile(lambda: f64, p: f64) -> f64 {
+        if lambda <= 0.0 {
+            return f64::INFINITY;
+        }
+        -(1.0 - p).ln() / lambda
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6 * b.max(1.0)
+    }
+
+    #[test]
+    fn test_expected_time_from_hashrate_ratio() {
+        // 1,000 H/s against difficulty 600,000 finds a block every 600s on average
+        let estimate = BlockTimeEstimator::new(1_000.0, 600_000, 42.0).estimate();
+
+        assert!(approx(estimate.expected_seconds, 600.0));
+        assert!(approx(estimate.current_round_age_seconds, 42.0));
+    }
+
+    #[test]
+    fn test_quantiles_follow_exponential_cdf() {
+        let estimate = BlockTimeEstimator::new(1.0, 100, 0.0).estimate();
+
+        assert!(approx(estimate.p50_seconds, 100.0 * std::f64::consts::LN_2));
+        assert!(approx(estimate.p90_seconds, 100.0 * 10f64.ln()));
+        assert!(approx(estimate.p99_seconds, 100.0 * 100f64.ln()));
+        assert!(estimate.p50_seconds < estimate.expected_seconds);
+    }
+
+    #[test]
+    fn test_probability_in_next_hour() {
+        // Expected time of exactly one hour gives 1 - e^-1
+        let estimate = BlockTimeEstimator::new(10.0, 36_000, 0.0).estimate();
+        assert!(approx(estimate.probability_in_next_hour, 1.0 - (-1.0f64).exp()));
+    }
+
+    #[test]
+    fn test_zero_hashrate_never_finds_block() {
+        let estimate = BlockTimeEstimator::new(0.0, 1_000, 10.0).estimate();
+
+        assert!(estimate.expected_seconds.is_infinite());
+        assert!(estimate.p99_seconds.is_infinite());
+        assert_eq!(estimate.probability_in_next_hour, 0.0);
+    }
+}
@@ -0,0 +1,49 @@
+// Pool-wide event bus
+// Components publish events once; every connected client subscribes and forwards them
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+// Events buffered per subscriber before it starts lagging
+pub const BROADCAST_BUS_CAPACITY: usize = 128;
+
+/// The chain tip moved: the block at `block_hash` was found and miners now build on it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewBlock {
+    pub block_hash: String,
+    pub mining_height: u64,
+    pub job_id: u64,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BroadcastBus {
+    sender: broadcast::Sender<NewBlock>,
+}
+
+impl BroadcastBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Returns how many subscribers the event reached
+    pub fn publish(&self, block: NewBlock) -> usize {
+        // No receivers just means no clients are connected
+        self.sender.send(block).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<NewBlock> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for BroadcastBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
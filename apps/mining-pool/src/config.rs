@@ -2,19 +2,25 @@
 // Handles environment variables, file configs, and validation
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
+    pub grpc: GrpcConfig,
     pub database_url: String,
+    // Months of monthly `shares` partitions kept before they are dropped
+    pub share_partition_retention_months: u32,
     pub redis_url: String,
     pub mining: MiningConfig,
     pub payout: PayoutConfig,
     pub security: SecurityConfig,
     pub metrics: MetricsConfig,
     pub logging: LoggingConfig,
+    pub maintenance: Option<MaintenanceWindow>,
+    pub hashrate_oracle_endpoints: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,6 +33,16 @@ pub struct ServerConfig {
     pub request_timeout: Duration,
 }
 
+// gRPC interface, served on its own port alongside the HTTP API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GrpcConfig {
+    pub enabled: bool,
+    pub port: u16,
+    // PEM certificate and key; the server runs plaintext when neither is set
+    pub tls_cert_path: Option<String>,
+    pub tls_key_path: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MiningConfig {
     pub pool_fee: f64,
@@ -49,6 +65,7 @@ pub struct PayoutConfig {
     pub transaction_fee: f64,
     pub auto_payout_enabled: bool,
     pub max_pending_payouts: usize,
+    pub nockchain_rpc_url: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +84,8 @@ pub struct SecurityConfig {
     pub ddos_protection: bool,
     pub rate_limit_window: Duration,
     pub max_connections_per_ip: usize,
+    // Bearer token for miner API key management; key endpoints are disabled without one
+    pub admin_api_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +105,46 @@ pub struct LoggingConfig {
     pub rotation_size: Option<u64>,
 }
 
+// Scheduled maintenance window during which shares are queued instead of processed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub reason: String,
+}
+
+impl MaintenanceWindow {
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        now >= self.start && now < self.end
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active_at(Utc::now())
+    }
+
+    fn from_env() -> Result<Option<Self>> {
+        let (start, end) = match (
+            std::env::var("MAINTENANCE_START").ok(),
+            std::env::var("MAINTENANCE_END").ok(),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            (None, None) => return Ok(None),
+            _ => anyhow::bail!("MAINTENANCE_START and MAINTENANCE_END must be set together"),
+        };
+
+        Ok(Some(MaintenanceWindow {
+            start: DateTime::parse_from_rfc3339(&start)
+                .context("Invalid MAINTENANCE_START")?
+                .with_timezone(&Utc),
+            end: DateTime::parse_from_rfc3339(&end)
+                .context("Invalid MAINTENANCE_END")?
+                .with_timezone(&Utc),
+            reason: std::env::var("MAINTENANCE_REASON")
+                .unwrap_or_else(|_| "Scheduled maintenance".to_string()),
+        }))
+    }
+}
+
 impl Config {
     pub fn from_env() -> Result<Self> {
         dotenvy::dotenv().ok(); // Load .env file if present
@@ -119,9 +178,27 @@ impl Config {
                 ),
             },
 
+            grpc: GrpcConfig {
+                enabled: std::env::var("GRPC_ENABLED")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .context("Invalid GRPC_ENABLED")?,
+                port: std::env::var("GRPC_PORT")
+                    .unwrap_or_else(|_| "9090".to_string())
+                    .parse()
+                    .context("Invalid GRPC_PORT")?,
+                tls_cert_path: std::env::var("GRPC_TLS_CERT_PATH").ok(),
+                tls_key_path: std::env::var("GRPC_TLS_KEY_PATH").ok(),
+            },
+
             database_url: std::env::var("DATABASE_URL")
                 .context("DATABASE_URL must be set")?,
 
+            share_partition_retention_months: std::env::var("SHARE_PARTITION_RETENTION_MONTHS")
+                .unwrap_or_else(|_| "6".to_string())
+                .parse()
+                .context("Invalid SHARE_PARTITION_RETENTION_MONTHS")?,
+
             redis_url: std::env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
 
@@ -209,6 +286,8 @@ impl Config {
                     .unwrap_or_else(|_| "1000".to_string())
                     .parse()
                     .context("Invalid MAX_PENDING_PAYOUTS")?,
+                nockchain_rpc_url: std::env::var("NOCKCHAIN_RPC_URL")
+                    .unwrap_or_else(|_| "http://127.0.0.1:9090".to_string()),
             },
 
             security: SecurityConfig {
@@ -240,6 +319,9 @@ impl Config {
                     .unwrap_or_else(|_| "10".to_string())
                     .parse()
                     .context("Invalid MAX_CONNECTIONS_PER_IP")?,
+                admin_api_token: std::env::var("ADMIN_API_TOKEN")
+                    .ok()
+                    .filter(|token| !token.is_empty()),
             },
 
             metrics: MetricsConfig {
@@ -279,6 +361,16 @@ impl Config {
                     .ok()
                     .and_then(|s| s.parse().ok()),
             },
+
+            maintenance: MaintenanceWindow::from_env()?,
+
+            // Comma-separated explorer APIs, tried in order
+            hashrate_oracle_endpoints: std::env::var("HASHRATE_ORACLE_ENDPOINTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|endpoint| endpoint.trim().to_string())
+                .filter(|endpoint| !endpoint.is_empty())
+                .collect(),
         };
 
         // Validate configuration
@@ -297,6 +389,21 @@ impl Config {
             anyhow::bail!("Max connections must be greater than 0");
         }
 
+        // Validate gRPC configuration
+        if self.grpc.enabled {
+            if self.grpc.port == 0 || self.grpc.port == self.server.port {
+                anyhow::bail!("gRPC port must be non-zero and differ from the server port");
+            }
+
+            if self.grpc.tls_cert_path.is_some() != self.grpc.tls_key_path.is_some() {
+                anyhow::bail!("GRPC_TLS_CERT_PATH and GRPC_TLS_KEY_PATH must be set together");
+            }
+        }
+
+        if self.share_partition_retention_months == 0 {
+            anyhow::bail!("Share partition retention must be at least one month");
+        }
+
         // Validate mining configuration
         if self.mining.pool_fee < 0.0 || self.mining.pool_fee > 1.0 {
             anyhow::bail!("Pool fee must be between 0.0 and 1.0");
@@ -324,6 +431,13 @@ impl Config {
             anyhow::bail!("Max connections per IP must be greater than 0");
         }
 
+        // Validate maintenance window
+        if let Some(window) = &self.maintenance {
+            if window.end <= window.start {
+                anyhow::bail!("Maintenance window end must be after its start");
+            }
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,392 @@
+// Pool share difficulty retargeting
+// Moves the pool difficulty, and each miner's own vardiff difficulty, towards the configured
+// share interval
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{config::MiningConfig, metrics::Metrics, mining::components::DifficultyAdjuster};
+
+// Accepted shares between per-miner retargets
+pub const MINER_RETARGET_SHARES: u32 = 16;
+
+// Weight of the newest share interval in a miner's moving average
+pub const MINER_EMA_ALPHA: f64 = 0.2;
+
+// A single interval counts as at most this many target intervals, so a reconnect gap
+// cannot collapse a miner's difficulty
+const MAX_INTERVAL_SAMPLE_FACTOR: f64 = 4.0;
+
+// Miners without a share for this long are forgotten
+const MINER_IDLE_TIMEOUT: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone)]
+pub struct VardiffSettings {
+    pub enabled: bool,
+    pub target_share_interval: Duration,
+    pub retarget_interval: Duration,
+    pub variance_percent: f64,
+    pub minimum_difficulty: u64,
+    pub maximum_difficulty: u64,
+    pub miner_retarget_shares: u32,
+    pub miner_ema_alpha: f64,
+}
+
+impl From<&MiningConfig> for VardiffSettings {
+    fn from(mining: &MiningConfig) -> Self {
+        Self {
+            enabled: mining.vardiff_enabled,
+            target_share_interval: mining.vardiff_target_time,
+            retarget_interval: mining.vardiff_retarget_time,
+            variance_percent: mining.vardiff_variance_percent,
+            minimum_difficulty: mining.minimum_difficulty,
+            maximum_difficulty: mining.maximum_difficulty,
+            miner_retarget_shares: MINER_RETARGET_SHARES,
+            miner_ema_alpha: MINER_EMA_ALPHA,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RetargetWindow {
+    started: Instant,
+    shares: u64,
+}
+
+/// One miner's vardiff state
+#[derive(Debug, Clone)]
+pub struct MinerDifficulty {
+    pub difficulty: u64,
+    // Exponential moving average of the seconds between the miner's shares
+    pub average_interval: Option<f64>,
+    pub last_share: Option<Instant>,
+    pub shares_since_retarget: u32,
+}
+
+impl MinerDifficulty {
+    fn new(difficulty: u64) -> Self {
+        Self {
+            difficulty,
+            average_interval: None,
+            last_share: None,
+            shares_since_retarget: 0,
+        }
+    }
+}
+
+pub struct PoolDifficultyAdjuster {
+    settings: VardiffSettings,
+    metrics: Arc<Metrics>,
+    is_running: AtomicBool,
+    difficulty: AtomicU64,
+    window: Mutex<RetargetWindow>,
+    miners: Mutex<HashMap<String, MinerDifficulty>>,
+}
+
+impl PoolDifficultyAdjuster {
+    pub fn new(settings: VardiffSettings, metrics: Arc<Metrics>) -> Self {
+        metrics.set_difficulty(settings.minimum_difficulty);
+        Self {
+            difficulty: AtomicU64::new(settings.minimum_difficulty),
+            settings,
+            metrics,
+            is_running: AtomicBool::new(false),
+            window: Mutex::new(RetargetWindow { started: Instant::now(), shares: 0 }),
+            miners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn miner_state(&self, miner_id: &str) -> Option<MinerDifficulty> {
+        self.miners.lock().get(miner_id).cloned()
+    }
+}
+
+#[async_trait]
+impl DifficultyAdjuster for PoolDifficultyAdjuster {
+    async fn start(&self) -> Result<()> {
+        self.window.lock().started = Instant::now();
+        self.is_running.store(true, Ordering::Relaxed);
+        tracing::info!("Difficulty adjuster started");
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        self.is_running.store(false, Ordering::Relaxed);
+        tracing::info!("Difficulty adjuster stopped");
+    }
+
+    async fn is_running(&self) -> bool {
+        self.is_running.load(Ordering::Relaxed)
+    }
+
+    async fn current_difficulty(&self) -> u64 {
+        self.difficulty.load(Ordering::Relaxed)
+    }
+
+    async fn record_share(&self, submitted_at: Instant) -> Option<u64> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        let mut window = self.window.lock();
+        window.shares += 1;
+
+        let elapsed = submitted_at.saturating_duration_since(window.started);
+        if elapsed < self.settings.retarget_interval {
+            return None;
+        }
+
+        let average_interval = elapsed / window.shares as u32;
+        *window = RetargetWindow { started: submitted_at, shares: 0 };
+
+        let current = self.difficulty.load(Ordering::Relaxed);
+        let next = retarget(current, average_interval, &self.settings)?;
+        self.difficulty.store(next, Ordering::Relaxed);
+        self.metrics.set_difficulty(next);
+        Some(next)
+    }
+
+    async fn miner_difficulty(&self, miner_id: &str) -> u64 {
+        self.miners
+            .lock()
+            .get(miner_id)
+            .map_or(self.settings.minimum_difficulty, |miner| miner.difficulty)
+    }
+
+    async fn record_miner_share(&self, miner_id: &str, submitted_at: Instant) -> Option<u64> {
+        if !self.settings.enabled {
+            return None;
+        }
+
+        let mut miners = self.miners.lock();
+        if !miners.contains_key(miner_id) {
+            miners.retain(|_, miner| {
+                miner.last_share.map_or(true, |last| submitted_at.saturating_duration_since(last) < MINER_IDLE_TIMEOUT)
+            });
+        }
+        let miner = miners
+            .entry(miner_id.to_string())
+            .or_insert_with(|| MinerDifficulty::new(self.settings.minimum_difficulty));
+
+        record_interval(miner, submitted_at, &self.settings);
+        if miner.shares_since_retarget < self.settings.miner_retarget_shares {
+            return None;
+        }
+        miner.shares_since_retarget = 0;
+
+        let average_interval = miner.average_interval?;
+        let current = miner.difficulty;
+        let next = retarget(current, Duration::from_secs_f64(average_interval), &self.settings)?;
+
+        // Shares at the new difficulty arrive proportionally further apart
+        miner.average_interval = Some(average_interval * next as f64 / current as f64);
+        miner.difficulty = next;
+        Some(next)
+    }
+}
+
+/// Folds the interval since the miner's previous share into its moving average
+fn record_interval(miner: &mut MinerDifficulty, submitted_at: Instant, settings: &VardiffSettings) {
+    if let Some(last) = miner.last_share {
+        let cap = settings.target_share_interval.as_secs_f64() * MAX_INTERVAL_SAMPLE_FACTOR;
+        let sample = submitted_at.saturating_duration_since(last).as_secs_f64().min(cap);
+        let alpha = settings.miner_ema_alpha;
+
+        miner.average_interval = Some(match miner.average_interval {
+            Some(average) => alpha * sample + (1.0 - alpha) * average,
+            None => sample,
+        });
+        miner.shares_since_retarget += 1;
+    }
+    miner.last_share = Some(submitted_at);
+}
+
+/// Scales difficulty so shares arrive at the target interval. Returns None when the observed
+/// interval is within the allowed variance or the clamped difficulty would not change.
+pub fn retarget(current: u64, average_interval: Duration, settings: &VardiffSettings) -> Option<u64> {
+    let target = settings.target_share_interval.as_secs_f64();
+    let observed = average_interval.as_secs_f64().max(f64::EPSILON);
+
+    let deviation_percent = (observed - target).abs() / target * 100.0;
+    if deviation_percent <= settings.variance_percent {
+        return None;
+    }
+
+    let scaled = (current as f64 * target / observed).round() as u64;
+    let next = scaled.clamp(settings.minimum_difficulty, settings.maximum_difficulty);
+    (next != current).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> VardiffSettings {
+        VardiffSettings {
+            enabled: true,
+            target_share_interval: Duration::from_secs(10),
+            retarget_interval: Duration::from_secs(60),
+            variance_percent: 30.0,
+            minimum_difficulty: 1_000,
+            maximum_difficulty: 1_000_000,
+            miner_retarget_shares: 16,
+            miner_ema_alpha: 0.2,
+        }
+    }
+
+    fn adjuster() -> PoolDifficultyAdjuster {
+        PoolDifficultyAdjuster::new(settings(), Arc::new(Metrics::new().unwrap()))
+    }
+
+    // Synthetic miner: at a fixed hashrate, shares arrive every difficulty / hashrate seconds
+    struct SimulatedMiner {
+        id: &'static str,
+        hashrate: f64,
+        clock: Instant,
+    }
+
+    impl SimulatedMiner {
+        fn new(id: &'static str, hashrate: f64, clock: Instant) -> Self {
+            Self { id, hashrate, clock }
+        }
+
+        // Submits `shares` shares, returning every difficulty the adjuster assigned
+        async fn mine(&mut self, adjuster: &PoolDifficultyAdjuster, shares: usize) -> Vec<u64> {
+            let mut retargets = Vec::new();
+            for _ in 0..shares {
+                let difficulty = adjuster.miner_difficulty(self.id).await;
+                self.clock += Duration::from_secs_f64(difficulty as f64 / self.hashrate);
+                if let Some(next) = adjuster.record_miner_share(self.id, self.clock).await {
+                    retargets.push(next);
+                }
+            }
+            retargets
+        }
+
+        fn disconnect(&mut self, gap: Duration) {
+            self.clock += gap;
+        }
+    }
+
+    fn within_variance(difficulty: u64, expected: u64) -> bool {
+        let deviation = (difficulty as f64 - expected as f64).abs() / expected as f64 * 100.0;
+        deviation <= settings().variance_percent
+    }
+
+    #[test]
+    fn test_retarget_scales_towards_target_interval() {
+        // Shares twice as fast as the target double the difficulty, half as fast halve it
+        assert_eq!(retarget(8_000, Duration::from_secs(5), &settings()), Some(16_000));
+        assert_eq!(retarget(8_000, Duration::from_secs(20), &settings()), Some(4_000));
+    }
+
+    #[test]
+    fn test_retarget_within_variance_is_ignored() {
+        assert_eq!(retarget(8_000, Duration::from_secs(12), &settings()), None);
+        assert_eq!(retarget(8_000, Duration::from_secs(8), &settings()), None);
+    }
+
+    #[test]
+    fn test_retarget_clamped_to_configured_bounds() {
+        assert_eq!(retarget(600_000, Duration::from_millis(100), &settings()), Some(1_000_000));
+        assert_eq!(retarget(2_000, Duration::from_secs(600), &settings()), Some(1_000));
+        assert_eq!(retarget(1_000, Duration::from_secs(600), &settings()), None);
+    }
+
+    #[tokio::test]
+    async fn test_retargets_once_per_window() {
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let adjuster = PoolDifficultyAdjuster::new(settings(), metrics.clone());
+        let start = Instant::now();
+        adjuster.window.lock().started = start;
+
+        // 12 shares in 60s average 5s apart, so difficulty doubles from the minimum
+        for i in 1..12 {
+            assert_eq!(adjuster.record_share(start + Duration::from_secs(i * 5)).await, None);
+        }
+        assert_eq!(adjuster.record_share(start + Duration::from_secs(60)).await, Some(2_000));
+        assert_eq!(adjuster.current_difficulty().await, 2_000);
+        assert!(metrics.render().unwrap().contains("pool_difficulty_current 2000"));
+
+        // A fresh window starts after each retarget
+        assert_eq!(adjuster.record_share(start + Duration::from_secs(61)).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_miner_difficulty_converges_to_target_interval() {
+        let adjuster = adjuster();
+        let start = Instant::now();
+        // 400 H/s mines a share every 10s at difficulty 4_000
+        let mut fast = SimulatedMiner::new("fast", 400.0, start);
+        // 50 H/s stays at the minimum: 20s shares at difficulty 1_000
+        let mut slow = SimulatedMiner::new("slow", 50.0, start);
+
+        fast.mine(&adjuster, 200).await;
+        slow.mine(&adjuster, 200).await;
+
+        assert!(within_variance(adjuster.miner_difficulty("fast").await, 4_000));
+        assert_eq!(adjuster.miner_difficulty("slow").await, 1_000);
+        assert_eq!(adjuster.miner_difficulty("unknown").await, 1_000);
+
+        // Per-miner retargets leave the pool difficulty alone
+        assert_eq!(adjuster.current_difficulty().await, 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_miner_retargets_every_n_shares() {
+        let adjuster = adjuster();
+        let mut miner = SimulatedMiner::new("miner-1", 400.0, Instant::now());
+
+        // The first share only starts the clock; the 16th interval triggers the retarget
+        assert!(miner.mine(&adjuster, 16).await.is_empty());
+        assert_eq!(miner.mine(&adjuster, 1).await, vec![4_000]);
+        assert_eq!(adjuster.miner_state("miner-1").unwrap().shares_since_retarget, 0);
+    }
+
+    #[tokio::test]
+    async fn test_miner_difficulty_survives_reconnect() {
+        let adjuster = adjuster();
+        let mut miner = SimulatedMiner::new("miner-1", 400.0, Instant::now());
+        miner.mine(&adjuster, 100).await;
+        let settled = adjuster.miner_difficulty("miner-1").await;
+
+        // A ten minute gap lands on every position in the retarget cycle
+        for _ in 0..16 {
+            miner.disconnect(Duration::from_secs(600));
+            let retargets = miner.mine(&adjuster, 1 + MINER_RETARGET_SHARES as usize).await;
+            assert!(retargets.iter().all(|&difficulty| difficulty >= settled / 2), "{:?}", retargets);
+        }
+
+        miner.mine(&adjuster, 100).await;
+        assert!(within_variance(adjuster.miner_difficulty("miner-1").await, 4_000));
+    }
+
+    #[tokio::test]
+    async fn test_idle_miners_are_forgotten() {
+        let adjuster = adjuster();
+        let start = Instant::now();
+        adjuster.record_miner_share("gone", start).await;
+
+        adjuster.record_miner_share("new", start + MINER_IDLE_TIMEOUT).await;
+        assert!(adjuster.miner_state("gone").is_none());
+        assert!(adjuster.miner_state("new").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_vardiff_never_retargets() {
+        let adjuster = PoolDifficultyAdjuster::new(
+            VardiffSettings { enabled: false, ..settings() },
+            Arc::new(Metrics::new().unwrap()),
+        );
+        let later = Instant::now() + Duration::from_secs(3600);
+
+        assert_eq!(adjuster.record_share(later).await, None);
+        assert_eq!(adjuster.record_miner_share("miner-1", later).await, None);
+        assert_eq!(adjuster.current_difficulty().await, 1_000);
+    }
+}
@@ -0,0 +1,67 @@
+// gRPC interface to the mining pool
+// Serves the same pool state as the HTTP API on a separate port, optionally over TLS
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Identity, Server, ServerTlsConfig};
+
+use crate::config::GrpcConfig;
+
+pub mod service;
+
+pub use service::{MiningPoolGrpc, MinerSnapshot, PoolBackend};
+
+pub mod proto {
+    tonic::include_proto!("nockchain.mining_pool.v1");
+}
+
+use proto::mining_pool_service_server::MiningPoolServiceServer;
+
+/// Serves the gRPC interface on `addr` until `shutdown` resolves
+pub async fn serve<B: PoolBackend>(
+    backend: Arc<B>,
+    addr: SocketAddr,
+    config: &GrpcConfig,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    let incoming = TcpIncoming::new(addr, true, None)
+        .map_err(|e| anyhow::anyhow!("Failed to bind gRPC listener on {}: {}", addr, e))?;
+    serve_with_incoming(backend, incoming, config, shutdown).await
+}
+
+pub async fn serve_with_incoming<B: PoolBackend>(
+    backend: Arc<B>,
+    incoming: TcpIncoming,
+    config: &GrpcConfig,
+    shutdown: impl Future<Output = ()>,
+) -> Result<()> {
+    let mut server = Server::builder();
+    if let Some(tls) = load_tls_config(config)? {
+        server = server.tls_config(tls).context("Invalid gRPC TLS configuration")?;
+    }
+
+    server
+        .add_service(MiningPoolServiceServer::new(MiningPoolGrpc::new(backend)))
+        .serve_with_incoming_shutdown(incoming, shutdown)
+        .await
+        .context("gRPC server failed")
+}
+
+/// Reads the PEM certificate and key named in the config; `None` when TLS is not configured
+pub fn load_tls_config(config: &GrpcConfig) -> Result<Option<ServerTlsConfig>> {
+    let (cert_path, key_path) = match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => anyhow::bail!("gRPC TLS needs both a certificate and a key"),
+    };
+
+    let cert = std::fs::read(cert_path)
+        .with_context(|| format!("Failed to read gRPC TLS certificate {}", cert_path))?;
+    let key = std::fs::read(key_path)
+        .with_context(|| format!("Failed to read gRPC TLS key {}", key_path))?;
+
+    Ok(Some(ServerTlsConfig::new().identity(Identity::from_pem(cert, key))))
+}
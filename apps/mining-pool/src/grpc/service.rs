@@ -0,0 +1,492 @@
+// MiningPoolService implementation
+// The service reaches the pool through PoolBackend so it can run against stubs in tests;
+// in production the backend is the AppState shared with the HTTP server
+
+use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tonic::{metadata::MetadataMap, Request, Response, Status};
+
+use super::proto::{
+    self, mining_pool_service_server::MiningPoolService, BlockUpdate, GetMinerStatsRequest,
+    GetPoolStatsRequest, StreamBlockUpdatesRequest, SubmitShareRequest, SubmitShareResponse,
+};
+use crate::{
+    block_finder::MiningJob,
+    miner_auth::{AuthOutcome, MinerIdentity},
+    mining::{PoolStats, Share, ShareStatus, ShareValidationResult},
+    AppState,
+};
+
+pub const USERNAME_METADATA_KEY: &str = "x-miner-username";
+pub const API_KEY_METADATA_KEY: &str = "x-api-key";
+
+/// Everything the gRPC interface knows about one miner
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MinerSnapshot {
+    pub miner_id: String,
+    pub online: bool,
+    pub hashrate: f64,
+    pub difficulty: u64,
+    pub confirmed_balance: f64,
+    pub unconfirmed_balance: f64,
+    pub total_earned: f64,
+    pub total_paid: f64,
+}
+
+#[async_trait]
+pub trait PoolBackend: Send + Sync + 'static {
+    async fn pool_stats(&self) -> PoolStats;
+    /// `None` for miners that are neither connected nor hold a balance
+    async fn miner_stats(&self, miner_id: &str) -> Option<MinerSnapshot>;
+    async fn authenticate(&self, username: &str, api_key: &str) -> anyhow::Result<AuthOutcome>;
+    /// Share difficulty currently assigned to a miner
+    async fn miner_difficulty(&self, miner_id: &str) -> u64;
+    async fn submit_share(&self, share: Share) -> anyhow::Result<ShareValidationResult>;
+    /// The current mining job, if any, and a receiver for the jobs that follow it
+    async fn subscribe_blocks(&self) -> (Option<MiningJob>, broadcast::Receiver<MiningJob>);
+}
+
+#[async_trait]
+impl PoolBackend for AppState {
+    async fn pool_stats(&self) -> PoolStats {
+        self.pool.get_pool_stats().await
+    }
+
+    async fn miner_stats(&self, miner_id: &str) -> Option<MinerSnapshot> {
+        let miner = self.pool.get_miner(miner_id).await;
+        let balance = self.pool.payout_engine.get_miner_balance(miner_id).await;
+        if miner.is_none() && balance.is_none() {
+            return None;
+        }
+
+        let mut snapshot = MinerSnapshot {
+            miner_id: miner_id.to_string(),
+            online: miner.is_some(),
+            hashrate: miner.map_or(0.0, |miner| miner.current_hashrate.load(Ordering::Relaxed)),
+            difficulty: self.pool.difficulty_adjuster.miner_difficulty(miner_id).await,
+            ..MinerSnapshot::default()
+        };
+        if let Some(balance) = balance {
+            snapshot.confirmed_balance = balance.confirmed_balance;
+            snapshot.unconfirmed_balance = balance.unconfirmed_balance;
+            snapshot.total_earned = balance.total_earned;
+            snapshot.total_paid = balance.total_paid;
+        }
+        Some(snapshot)
+    }
+
+    async fn authenticate(&self, username: &str, api_key: &str) -> anyhow::Result<AuthOutcome> {
+        self.miner_auth.authenticate(username, api_key).await
+    }
+
+    async fn miner_difficulty(&self, miner_id: &str) -> u64 {
+        self.pool.difficulty_adjuster.miner_difficulty(miner_id).await
+    }
+
+    async fn submit_share(&self, share: Share) -> anyhow::Result<ShareValidationResult> {
+        self.pool.submit_share(share).await
+    }
+
+    async fn subscribe_blocks(&self) -> (Option<MiningJob>, broadcast::Receiver<MiningJob>) {
+        self.pool.block_finder.subscribe().await
+    }
+}
+
+pub struct MiningPoolGrpc<B> {
+    backend: Arc<B>,
+}
+
+impl<B: PoolBackend> MiningPoolGrpc<B> {
+    pub fn new(backend: Arc<B>) -> Self {
+        Self { backend }
+    }
+
+    async fn authenticate(&self, metadata: &MetadataMap) -> Result<MinerIdentity, Status> {
+        let credential = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| Status::unauthenticated(format!("Missing {} metadata", key)))
+        };
+        let username = credential(USERNAME_METADATA_KEY)?;
+        let api_key = credential(API_KEY_METADATA_KEY)?;
+
+        let outcome = self.backend.authenticate(username, api_key).await.map_err(|e| {
+            tracing::error!("gRPC authentication of {} failed: {:#}", username, e);
+            Status::internal("Authentication unavailable")
+        })?;
+
+        match outcome {
+            AuthOutcome::Authenticated(identity) => Ok(identity),
+            AuthOutcome::InvalidCredentials => {
+                tracing::warn!("gRPC share submission failed authentication as {}", username);
+                Err(Status::unauthenticated("Invalid credentials"))
+            }
+            AuthOutcome::LockedOut { retry_after } => Err(Status::resource_exhausted(format!(
+                "Locked out, retry in {}s",
+                retry_after.as_secs()
+            ))),
+        }
+    }
+}
+
+impl From<PoolStats> for proto::PoolStats {
+    fn from(stats: PoolStats) -> Self {
+        Self {
+            total_hashrate: stats.total_hashrate,
+            active_miners: stats.active_miners as u64,
+            blocks_found: stats.blocks_found,
+            total_shares: stats.total_shares,
+            valid_shares: stats.valid_shares,
+            stale_shares: stats.stale_shares,
+            invalid_shares: stats.invalid_shares,
+            luck: stats.luck,
+            effort: stats.effort,
+            network_difficulty: stats.network_difficulty,
+            pool_difficulty: stats.pool_difficulty,
+            seconds_since_last_block: stats.last_block_time.map(|found| found.elapsed().as_secs()),
+            uptime_seconds: stats.uptime.as_secs(),
+        }
+    }
+}
+
+impl From<MinerSnapshot> for proto::MinerStats {
+    fn from(snapshot: MinerSnapshot) -> Self {
+        Self {
+            miner_id: snapshot.miner_id,
+            online: snapshot.online,
+            hashrate: snapshot.hashrate,
+            difficulty: snapshot.difficulty,
+            confirmed_balance: snapshot.confirmed_balance,
+            unconfirmed_balance: snapshot.unconfirmed_balance,
+            total_earned: snapshot.total_earned,
+            total_paid: snapshot.total_paid,
+        }
+    }
+}
+
+impl From<ShareValidationResult> for SubmitShareResponse {
+    fn from(result: ShareValidationResult) -> Self {
+        let status = match result.status {
+            ShareStatus::Valid => proto::ShareStatus::Valid,
+            ShareStatus::Invalid => proto::ShareStatus::Invalid,
+            ShareStatus::Stale => proto::ShareStatus::Stale,
+        };
+
+        Self {
+            status: status.into(),
+            error: result.error.unwrap_or_default(),
+            is_block_solution: result.is_block_solution,
+            difficulty_achieved: result.difficulty_achieved,
+            processing_time_micros: result.processing_time.as_micros() as u64,
+        }
+    }
+}
+
+impl From<MiningJob> for BlockUpdate {
+    fn from(job: MiningJob) -> Self {
+        Self {
+            job_id: job.job_id,
+            prev_hash: job.prev_hash,
+            merkle_root: job.merkle_root,
+            bits: job.bits,
+            height: job.height,
+        }
+    }
+}
+
+type BlockUpdateStream = Pin<Box<dyn Stream<Item = Result<BlockUpdate, Status>> + Send>>;
+
+#[async_trait]
+impl<B: PoolBackend> MiningPoolService for MiningPoolGrpc<B> {
+    async fn get_pool_stats(
+        &self,
+        _request: Request<GetPoolStatsRequest>,
+    ) -> Result<Response<proto::PoolStats>, Status> {
+        Ok(Response::new(self.backend.pool_stats().await.into()))
+    }
+
+    async fn get_miner_stats(
+        &self,
+        request: Request<GetMinerStatsRequest>,
+    ) -> Result<Response<proto::MinerStats>, Status> {
+        let miner_id = request.into_inner().miner_id;
+        match self.backend.miner_stats(&miner_id).await {
+            Some(snapshot) => Ok(Response::new(snapshot.into())),
+            None => Err(Status::not_found(format!("Unknown miner {}", miner_id))),
+        }
+    }
+
+    async fn submit_share(
+        &self,
+        request: Request<SubmitShareRequest>,
+    ) -> Result<Response<SubmitShareResponse>, Status> {
+        let identity = self.authenticate(request.metadata()).await?;
+        let submit = request.into_inner();
+
+        let share = Share {
+            difficulty: self.backend.miner_difficulty(&identity.miner_id).await,
+            miner_id: identity.miner_id,
+            nonce: submit.nonce,
+            prev_block_hash: submit.prev_block_hash,
+            timestamp: submit.timestamp.into(),
+        };
+
+        match self.backend.submit_share(share).await {
+            Ok(result) => Ok(Response::new(result.into())),
+            Err(e) => {
+                tracing::error!("gRPC share submission failed: {:#}", e);
+                Err(Status::internal("Share processing failed"))
+            }
+        }
+    }
+
+    type StreamBlockUpdatesStream = BlockUpdateStream;
+
+    async fn stream_block_updates(
+        &self,
+        _request: Request<StreamBlockUpdatesRequest>,
+    ) -> Result<Response<Self::StreamBlockUpdatesStream>, Status> {
+        let (current, receiver) = self.backend.subscribe_blocks().await;
+
+        // Subscribers start from the current job, then follow every tip change
+        let updates = stream::iter(current.map(|job| Ok(job.into()))).chain(stream::unfold(
+            receiver,
+            |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(job) => return Some((Ok(job.into()), receiver)),
+                        // Only the latest job matters to a miner, so skipped ones are dropped
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::debug!("gRPC block subscriber skipped {} jobs", skipped);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        ));
+
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GrpcConfig;
+    use crate::grpc::proto::mining_pool_service_client::MiningPoolServiceClient;
+    use crate::grpc::serve_with_incoming;
+    use crate::miner_auth::MinerAuth;
+    use crate::mining::components::ShareProcessor;
+    use crate::mining::stubs::{StubApiKeyStore, StubShareProcessor};
+    use std::time::{Duration, Instant};
+    use tokio::sync::oneshot;
+    use tonic::transport::{server::TcpIncoming, Channel};
+
+    struct StubBackend {
+        miner_auth: MinerAuth,
+        share_processor: StubShareProcessor,
+        jobs: broadcast::Sender<MiningJob>,
+    }
+
+    #[async_trait]
+    impl PoolBackend for StubBackend {
+        async fn pool_stats(&self) -> PoolStats {
+            PoolStats {
+                total_hashrate: 1_250.0,
+                active_miners: 3,
+                blocks_found: 2,
+                total_shares: 40,
+                valid_shares: 37,
+                stale_shares: 2,
+                invalid_shares: 1,
+                luck: 1.1,
+                effort: 0.9,
+                network_difficulty: 5_000_000,
+                pool_difficulty: 1_000,
+                last_block_time: Some(Instant::now()),
+                uptime: Duration::from_secs(600),
+            }
+        }
+
+        async fn miner_stats(&self, miner_id: &str) -> Option<MinerSnapshot> {
+            (miner_id == "miner-1").then(|| MinerSnapshot {
+                miner_id: miner_id.to_string(),
+                online: true,
+                hashrate: 400.0,
+                difficulty: 1_000,
+                confirmed_balance: 12.5,
+                ..MinerSnapshot::default()
+            })
+        }
+
+        async fn authenticate(&self, username: &str, api_key: &str) -> anyhow::Result<AuthOutcome> {
+            self.miner_auth.authenticate(username, api_key).await
+        }
+
+        async fn miner_difficulty(&self, _miner_id: &str) -> u64 {
+            1_000
+        }
+
+        async fn submit_share(&self, share: Share) -> anyhow::Result<ShareValidationResult> {
+            self.share_processor.process_share(share).await
+        }
+
+        async fn subscribe_blocks(&self) -> (Option<MiningJob>, broadcast::Receiver<MiningJob>) {
+            (Some(job(100)), self.jobs.subscribe())
+        }
+    }
+
+    fn job(height: u64) -> MiningJob {
+        MiningJob {
+            job_id: height,
+            prev_hash: format!("tip-{}", height),
+            merkle_root: format!("root-{}", height),
+            bits: 0x1d00ffff,
+            height,
+        }
+    }
+
+    struct TestServer {
+        backend: Arc<StubBackend>,
+        client: MiningPoolServiceClient<Channel>,
+        _shutdown: oneshot::Sender<()>,
+    }
+
+    async fn start_server() -> TestServer {
+        let backend = Arc::new(StubBackend {
+            miner_auth: MinerAuth::new(Arc::new(StubApiKeyStore::default())),
+            share_processor: StubShareProcessor::new(),
+            jobs: broadcast::channel(16).0,
+        });
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+        let config = GrpcConfig {
+            enabled: true,
+            port: addr.port(),
+            tls_cert_path: None,
+            tls_key_path: None,
+        };
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        let server_backend = backend.clone();
+        tokio::spawn(async move {
+            serve_with_incoming(server_backend, incoming, &config, async {
+                shutdown_signal.await.ok();
+            })
+            .await
+            .unwrap();
+        });
+
+        let channel = Channel::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+        TestServer {
+            backend,
+            client: MiningPoolServiceClient::new(channel),
+            _shutdown: shutdown,
+        }
+    }
+
+    fn submit_request(credentials: Option<(&str, &str)>) -> Request<SubmitShareRequest> {
+        let mut request = Request::new(SubmitShareRequest {
+            nonce: "deadbeef".to_string(),
+            prev_block_hash: "ab".repeat(32),
+            timestamp: 1_700_000_000,
+        });
+        if let Some((username, api_key)) = credentials {
+            request.metadata_mut().insert(USERNAME_METADATA_KEY, username.parse().unwrap());
+            request.metadata_mut().insert(API_KEY_METADATA_KEY, api_key.parse().unwrap());
+        }
+        request
+    }
+
+    #[tokio::test]
+    async fn test_pool_and_miner_stats_over_channel() {
+        let mut server = start_server().await;
+
+        let stats = server.client.get_pool_stats(GetPoolStatsRequest {}).await.unwrap().into_inner();
+        assert_eq!(stats.active_miners, 3);
+        assert_eq!(stats.valid_shares, 37);
+        assert_eq!(stats.uptime_seconds, 600);
+        assert_eq!(stats.seconds_since_last_block, Some(0));
+
+        let miner = server
+            .client
+            .get_miner_stats(GetMinerStatsRequest { miner_id: "miner-1".to_string() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(miner.online);
+        assert_eq!(miner.difficulty, 1_000);
+        assert_eq!(miner.confirmed_balance, 12.5);
+
+        let missing = server
+            .client
+            .get_miner_stats(GetMinerStatsRequest { miner_id: "miner-2".to_string() })
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_submit_share_requires_api_key() {
+        let mut server = start_server().await;
+        let key = server.backend.miner_auth.create_api_key("miner-1", "alice").await.unwrap();
+
+        let anonymous = server.client.submit_share(submit_request(None)).await.unwrap_err();
+        assert_eq!(anonymous.code(), tonic::Code::Unauthenticated);
+
+        let wrong_key = server.client.submit_share(submit_request(Some(("alice", "nock_guess")))).await.unwrap_err();
+        assert_eq!(wrong_key.code(), tonic::Code::Unauthenticated);
+
+        let accepted = server
+            .client
+            .submit_share(submit_request(Some(("alice", &key.api_key))))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(accepted.status(), proto::ShareStatus::Valid);
+        assert!(accepted.error.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_updates_stream_current_job_then_new_tips() {
+        let mut server = start_server().await;
+        let mut updates = server
+            .client
+            .stream_block_updates(StreamBlockUpdatesRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+
+        let first = updates.message().await.unwrap().unwrap();
+        assert_eq!(first.height, 100);
+
+        server.backend.jobs.send(job(101)).unwrap();
+        let next = tokio::time::timeout(Duration::from_secs(5), updates.message())
+            .await
+            .expect("No block update streamed")
+            .unwrap()
+            .unwrap();
+        assert_eq!(next, BlockUpdate::from(job(101)));
+    }
+
+    #[test]
+    fn test_tls_requires_readable_certificate() {
+        let config = GrpcConfig {
+            enabled: true,
+            port: 9090,
+            tls_cert_path: Some("/nonexistent/grpc.pem".to_string()),
+            tls_key_path: Some("/nonexistent/grpc.key".to_string()),
+        };
+        let error = crate::grpc::load_tls_config(&config).unwrap_err();
+        assert!(error.to_string().contains("grpc.pem"));
+
+        let plaintext = GrpcConfig { tls_cert_path: None, tls_key_path: None, ..config };
+        assert!(crate::grpc::load_tls_config(&plaintext).unwrap().is_none());
+    }
+}
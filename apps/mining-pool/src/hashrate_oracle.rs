@@ -0,0 +1,261 @@
+// Cross-pool hashrate oracle
+// Estimates the pool's share of network hashrate from public explorer APIs
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+// How long a fetched network hashrate is reused
+pub const MARKET_SHARE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+// Per-endpoint request timeout
+const ORACLE_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Field names recognised in explorer responses, in H/s
+const HASHRATE_FIELDS: [&str; 3] = ["network_hashrate", "networkHashrate", "hashrate"];
+
+const HASHES_PER_TERAHASH: f64 = 1e12;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkHashrateData {
+    pub network_hashrate_ths: f64,
+    pub pool_hashrate_ths: f64,
+    pub market_share_percent: f64,
+    pub last_updated: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedNetworkHashrate {
+    hashrate: f64, // H/s
+    fetched_at: Instant,
+    last_updated: DateTime<Utc>,
+}
+
+pub struct HashrateOracle {
+    endpoints: Vec<String>,
+    client: reqwest::Client,
+    cache_ttl: Duration,
+    cache: RwLock<Option<CachedNetworkHashrate>>,
+}
+
+impl HashrateOracle {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        Self::with_cache_ttl(endpoints, MARKET_SHARE_CACHE_TTL)
+    }
+
+    pub fn with_cache_ttl(endpoints: Vec<String>, cache_ttl: Duration) -> Self {
+        Self {
+            endpoints,
+            client: reqwest::Client::builder()
+                .timeout(ORACLE_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            cache_ttl,
+            cache: RwLock::new(None),
+        }
+    }
+
+    /// Network hashrate and the pool's share of it, given the pool hashrate in H/s.
+    /// Endpoints are tried in order; a stale cached value is served if all of them fail.
+    pub async fn fetch_network_hashrate(&self, pool_hashrate: f64) -> Result<NetworkHashrateData> {
+        if let Some(cached) = self.cache.read().await.as_ref() {
+            if cached.fetched_at.elapsed() < self.cache_ttl {
+                return Ok(market_share(pool_hashrate, cached.hashrate, cached.last_updated));
+            }
+        }
+
+        match self.fetch_from_endpoints().await {
+            Ok(hashrate) => {
+                let last_updated = Utc::now();
+                *self.cache.write().await = Some(CachedNetworkHashrate {
+                    hashrate,
+                    fetched_at: Instant::now(),
+                    last_updated,
+                });
+                Ok(market_share(pool_hashrate, hashrate, last_updated))
+            }
+            Err(e) => match self.cache.read().await.as_ref() {
+                Some(stale) => {
+                    warn!("Serving stale network hashrate: {}", e);
+                    Ok(market_share(pool_hashrate, stale.hashrate, stale.last_updated))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn fetch_from_endpoints(&self) -> Result<f64> {
+        if self.endpoints.is_empty() {
+            anyhow::bail!("No hashrate oracle endpoints configured");
+        }
+
+        let mut last_error = None;
+        for endpoint in &self.endpoints {
+            match self.fetch_from(endpoint).await {
+                Ok(hashrate) => return Ok(hashrate),
+                Err(e) => {
+                    warn!("Hashrate oracle endpoint {} failed: {:#}", endpoint, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap().context("All hashrate oracle endpoints failed"))
+    }
+
+    async fn fetch_from(&self, endpoint: &str) -> Result<f64> {
+        let body: Value = self.client
+            .get(endpoint)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Response is not JSON")?;
+
+        parse_network_hashrate(&body)
+    }
+}
+
+/// Extracts total network hashrate (H/s) from an explorer response, accepting the
+/// value at the top level or under `data`, as a number or numeric string
+pub fn parse_network_hashrate(body: &Value) -> Result<f64> {
+    let hashrate = [Some(body), body.get("data")]
+        .into_iter()
+        .flatten()
+        .flat_map(|object| HASHRATE_FIELDS.iter().filter_map(move |field| object.get(*field)))
+        .find_map(|value| match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        })
+        .context("Response does not contain a network hashrate")?;
+
+    if !hashrate.is_finite() || hashrate <= 0.0 {
+        anyhow::bail!("Invalid network hashrate: {}", hashrate);
+    }
+    Ok(hashrate)
+}
+
+fn market_share(pool_hashrate: f64, network_hashrate: f64, last_updated: DateTime<Utc>) -> NetworkHashrateData {
+    NetworkHashrateData {
+        network_hashrate_ths: network_hashrate / HASHES_PER_TERAHASH,
+        pool_hashrate_ths: pool_hashrate / HASHES_PER_TERAHASH,
+        market_share_percent: pool_hashrate / network_hashrate * 100.0,
+        last_updated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_endpoint(server: &MockServer, route: &str, response: ResponseTemplate) {
+        Mock::given(method("GET"))
+            .and(path(route))
+            .respond_with(response)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_market_share_from_primary_endpoint() {
+        let server = MockServer::start().await;
+        mock_endpoint(&server, "/network", ResponseTemplate::new(200)
+            .set_body_json(json!({ "network_hashrate": 200e12 }))).await;
+
+        let oracle = HashrateOracle::new(vec![format!("{}/network", server.uri())]);
+        let data = oracle.fetch_network_hashrate(5e12).await.unwrap();
+
+        assert_eq!(data.network_hashrate_ths, 200.0);
+        assert_eq!(data.pool_hashrate_ths, 5.0);
+        assert!((data.market_share_percent - 2.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_secondary_endpoint() {
+        let server = MockServer::start().await;
+        mock_endpoint(&server, "/primary", ResponseTemplate::new(503)).await;
+        mock_endpoint(&server, "/broken", ResponseTemplate::new(200)
+            .set_body_json(json!({ "status": "ok" }))).await;
+        mock_endpoint(&server, "/secondary", ResponseTemplate::new(200)
+            .set_body_json(json!({ "data": { "networkHashrate": "400000000000000" } }))).await;
+
+        let oracle = HashrateOracle::new(vec![
+            format!("{}/primary", server.uri()),
+            format!("{}/broken", server.uri()),
+            format!("{}/secondary", server.uri()),
+        ]);
+        let data = oracle.fetch_network_hashrate(10e12).await.unwrap();
+
+        assert_eq!(data.network_hashrate_ths, 400.0);
+        assert!((data.market_share_percent - 2.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_all_endpoints_failing_is_an_error() {
+        let server = MockServer::start().await;
+        mock_endpoint(&server, "/a", ResponseTemplate::new(500)).await;
+        mock_endpoint(&server, "/b", ResponseTemplate::new(200).set_body_string("not json")).await;
+
+        let oracle = HashrateOracle::new(vec![
+            format!("{}/a", server.uri()),
+            format!("{}/b", server.uri()),
+        ]);
+
+        assert!(oracle.fetch_network_hashrate(1e12).await.is_err());
+        assert!(HashrateOracle::new(Vec::new()).fetch_network_hashrate(1e12).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_results_are_cached() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/network"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "hashrate": 100e12 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let oracle = HashrateOracle::new(vec![format!("{}/network", server.uri())]);
+        let first = oracle.fetch_network_hashrate(1e12).await.unwrap();
+        let second = oracle.fetch_network_hashrate(2e12).await.unwrap();
+
+        // Network figure is reused while the pool share tracks the current pool hashrate
+        assert_eq!(first.last_updated, second.last_updated);
+        assert!((second.market_share_percent - 2.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_stale_cache_served_when_endpoints_fail() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/network"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "hashrate": 100e12 })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        mock_endpoint(&server, "/network", ResponseTemplate::new(502)).await;
+
+        let oracle = HashrateOracle::with_cache_ttl(vec![format!("{}/network", server.uri())], Duration::ZERO);
+        let fresh = oracle.fetch_network_hashrate(1e12).await.unwrap();
+        let stale = oracle.fetch_network_hashrate(1e12).await.unwrap();
+
+        assert_eq!(fresh.last_updated, stale.last_updated);
+        assert_eq!(stale.network_hashrate_ths, 100.0);
+    }
+
+    #[test]
+    fn test_rejects_non_positive_hashrate() {
+        assert!(parse_network_hashrate(&json!({ "hashrate": 0 })).is_err());
+        assert!(parse_network_hashrate(&json!({ "hashrate": -5.0 })).is_err());
+        assert!(parse_network_hashrate(&json!({ "hashrate": "fast" })).is_err());
+    }
+}
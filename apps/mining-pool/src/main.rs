@@ -7,10 +7,11 @@ use axum::{
     extract::{ws::WebSocketUpgrade, State},
     http::StatusCode,
     response::Response,
-    routing::{get, post},
+    Json,
+    routing::{delete, get, post},
     Router,
 };
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 use tokio::signal;
 use tower::ServiceBuilder;
 use tower_http::{
@@ -28,14 +29,28 @@ mod websocket;
 mod database;
 mod metrics;
 mod share_processor;
+mod share_validator;
+mod share_partitions;
 mod payout_engine;
+mod pplns_payout_engine;
 mod block_finder;
+mod broadcast_bus;
 mod difficulty_adjuster;
+mod maintenance;
+mod block_time_estimator;
+mod hashrate_oracle;
+mod miner_auth;
+mod grpc;
 
 use config::Config;
 use mining::MiningPool;
 use database::Database;
 use metrics::Metrics;
+use maintenance::MaintenanceStatus;
+use block_time_estimator::{BlockTimeEstimate, ESTIMATE_REFRESH_INTERVAL_SECS};
+use hashrate_oracle::NetworkHashrateData;
+use miner_auth::MinerAuth;
+use share_partitions::{PgPartitionStore, SharePartitionMaintainer};
 
 // Global allocator for performance
 #[global_allocator]
@@ -48,6 +63,7 @@ pub struct AppState {
     pub database: Arc<Database>,
     pub metrics: Arc<Metrics>,
     pub config: Arc<Config>,
+    pub miner_auth: Arc<MinerAuth>,
 }
 
 #[tokio::main]
@@ -93,8 +109,25 @@ async fn main() -> Result<()> {
         database: database.clone(),
         metrics: metrics.clone(),
         config: config.clone(),
+        miner_auth: Arc::new(MinerAuth::new(database.clone())),
     };
 
+    // Start the gRPC interface on its own port, sharing the HTTP server's state
+    if config.grpc.enabled {
+        let grpc_addr: SocketAddr = format!("{}:{}", config.server.host, config.grpc.port)
+            .parse()
+            .expect("Invalid gRPC address");
+        let grpc_state = Arc::new(state.clone());
+        let grpc_config = config.grpc.clone();
+        let scheme = if grpc_config.tls_cert_path.is_some() { "https" } else { "http" };
+        info!("📡 gRPC server starting on {}://{}", scheme, grpc_addr);
+        tokio::spawn(async move {
+            if let Err(e) = grpc::serve(grpc_state, grpc_addr, &grpc_config, shutdown_signal()).await {
+                error!("gRPC server error: {:#}", e);
+            }
+        });
+    }
+
     // Build application router
     let app = create_router(state).await?;
 
@@ -125,11 +158,16 @@ async fn create_router(state: AppState) -> Result<Router> {
         .route("/miners", get(api::miners::list_miners))
         .route("/miners/:id", get(api::miners::get_miner))
         .route("/miners/:id/stats", get(api::miners::get_miner_stats))
+        .route("/miners/:id/api-keys", post(miner_auth::create_api_key))
+        .route("/miners/:id/api-keys/:key_id", delete(miner_auth::revoke_api_key))
         
         // Pool endpoints
         .route("/pool/stats", get(api::pool::get_pool_stats))
         .route("/pool/blocks", get(api::pool::get_blocks))
         .route("/pool/hashrate", get(api::pool::get_hashrate_history))
+        .route("/pool/maintenance-status", get(maintenance_status))
+        .route("/pool/time-to-block", get(time_to_block))
+        .route("/pool/market-share", get(market_share))
         
         // Share submission (Stratum-like protocol)
         .route("/submit", post(api::shares::submit_share))
@@ -163,25 +201,55 @@ async fn start_background_tasks(
     metrics: Arc<Metrics>,
 ) -> Result<()> {
     // Start share processor
-    let share_processor = pool.share_processor.clone();
+    let share_pool = pool.clone();
     tokio::spawn(async move {
-        if let Err(e) = share_processor.start().await {
+        if let Err(e) = share_pool.share_processor.start().await {
             error!("Share processor error: {}", e);
         }
     });
 
+    // Drain shares queued during maintenance once the window closes
+    let maintenance_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(5));
+        loop {
+            interval.tick().await;
+            if let Err(e) = maintenance_pool.share_processor.drain_maintenance_queue().await {
+                error!("Maintenance queue drain error: {}", e);
+            }
+        }
+    });
+
+    // Refresh time-to-block estimate
+    let estimator_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(ESTIMATE_REFRESH_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            estimator_pool.refresh_block_time_estimate().await;
+        }
+    });
+
+    // Track the chain tip and publish mining jobs
+    let block_finder = pool.block_finder.clone();
+    tokio::spawn(async move {
+        if let Err(e) = block_finder.start().await {
+            error!("Block finder error: {}", e);
+        }
+    });
+
     // Start payout engine
-    let payout_engine = pool.payout_engine.clone();
+    let payout_pool = pool.clone();
     tokio::spawn(async move {
-        if let Err(e) = payout_engine.start().await {
+        if let Err(e) = payout_pool.payout_engine.start().await {
             error!("Payout engine error: {}", e);
         }
     });
 
     // Start difficulty adjuster
-    let difficulty_adjuster = pool.difficulty_adjuster.clone();
+    let difficulty_pool = pool.clone();
     tokio::spawn(async move {
-        if let Err(e) = difficulty_adjuster.start().await {
+        if let Err(e) = difficulty_pool.difficulty_adjuster.start().await {
             error!("Difficulty adjuster error: {}", e);
         }
     });
@@ -202,6 +270,18 @@ async fn start_background_tasks(
         }
     });
 
+    // Rotate the monthly shares partitions
+    let partition_maintainer = SharePartitionMaintainer::new(
+        Arc::new(PgPartitionStore::connect_lazy(&pool.config.database_url)?),
+        metrics.clone(),
+        pool.config.share_partition_retention_months,
+    );
+    tokio::spawn(async move {
+        if let Err(e) = partition_maintainer.start().await {
+            error!("Share partition maintenance error: {}", e);
+        }
+    });
+
     info!("🔄 Background tasks started");
     Ok(())
 }
@@ -227,6 +307,21 @@ async fn health_check(State(state): State<AppState>) -> Result<String, StatusCod
     Ok("OK".to_string())
 }
 
+async fn maintenance_status(State(state): State<AppState>) -> Json<MaintenanceStatus> {
+    Json(state.pool.share_processor.get_maintenance_status())
+}
+
+async fn time_to_block(State(state): State<AppState>) -> Json<BlockTimeEstimate> {
+    Json(state.pool.get_block_time_estimate().await)
+}
+
+async fn market_share(State(state): State<AppState>) -> Result<Json<NetworkHashrateData>, StatusCode> {
+    state.pool.get_market_share().await.map(Json).map_err(|e| {
+        error!("Failed to fetch network hashrate: {:#}", e);
+        StatusCode::BAD_GATEWAY
+    })
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
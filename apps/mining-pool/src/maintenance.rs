@@ -0,0 +1,142 @@
+// Maintenance mode share queueing
+// Accepts shares during scheduled maintenance and defers processing until the window closes
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::config::MaintenanceWindow;
+
+// Maximum number of shares held while the pool is in maintenance
+pub const MAINTENANCE_QUEUE_CAPACITY: usize = 100_000;
+
+// Bounded FIFO queue for shares submitted during a maintenance window
+#[derive(Debug)]
+pub struct MaintenanceQueue<T> {
+    in_memory_queue: VecDeque<T>,
+    capacity: usize,
+    rejected: u64,
+}
+
+impl<T> MaintenanceQueue<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(MAINTENANCE_QUEUE_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            in_memory_queue: VecDeque::new(),
+            capacity,
+            rejected: 0,
+        }
+    }
+
+    // Returns false when the queue is full and the item was rejected
+    pub fn enqueue(&mut self, item: T) -> bool {
+        if self.in_memory_queue.len() >= self.capacity {
+            self.rejected += 1;
+            return false;
+        }
+
+        self.in_memory_queue.push_back(item);
+        true
+    }
+
+    // Removes all queued items in submission order
+    pub fn drain(&mut self) -> Vec<T> {
+        self.in_memory_queue.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.in_memory_queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.in_memory_queue.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+}
+
+impl<T> Default for MaintenanceQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Response body for GET /api/v1/pool/maintenance-status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub active: bool,
+    pub window: Option<MaintenanceWindow>,
+    pub queued_shares: usize,
+    pub queue_capacity: usize,
+    pub rejected_shares: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_queue_fills_in_submission_order() {
+        let mut queue = MaintenanceQueue::with_capacity(10);
+
+        for i in 0..5 {
+            assert!(queue.enqueue(i));
+        }
+
+        assert_eq!(queue.len(), 5);
+        assert_eq!(queue.rejected(), 0);
+    }
+
+    #[test]
+    fn test_queue_cap_enforced() {
+        let mut queue = MaintenanceQueue::with_capacity(3);
+
+        assert!(queue.enqueue(1));
+        assert!(queue.enqueue(2));
+        assert!(queue.enqueue(3));
+        assert!(!queue.enqueue(4));
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.rejected(), 1);
+    }
+
+    #[test]
+    fn test_default_capacity() {
+        let queue: MaintenanceQueue<u64> = MaintenanceQueue::default();
+        assert_eq!(queue.capacity(), MAINTENANCE_QUEUE_CAPACITY);
+    }
+
+    #[test]
+    fn test_drain_empties_queue() {
+        let mut queue = MaintenanceQueue::with_capacity(10);
+        queue.enqueue("a");
+        queue.enqueue("b");
+
+        assert_eq!(queue.drain(), vec!["a", "b"]);
+        assert!(queue.is_empty());
+        assert!(queue.drain().is_empty());
+    }
+
+    #[test]
+    fn test_window_activity() {
+        let now = Utc::now();
+        let window = MaintenanceWindow {
+            start: now - Duration::minutes(5),
+            end: now + Duration::minutes(5),
+            reason: "Database migration".to_string(),
+        };
+
+        assert!(window.is_active_at(now));
+        assert!(!window.is_active_at(now + Duration::minutes(10)));
+        assert!(!window.is_active_at(now - Duration::minutes(10)));
+    }
+}
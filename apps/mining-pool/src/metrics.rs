@@ -0,0 +1,221 @@
+// Prometheus metrics for the mining pool
+// Pool components record events here; the /metrics route renders the text exposition format
+
+use anyhow::Result;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    Counter, Encoder, Gauge, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::{
+    mining::{PoolStats, ShareStatus},
+    AppState,
+};
+
+pub struct Metrics {
+    registry: Registry,
+    total_hashrate: Gauge,
+    active_miners: IntGauge,
+    shares_accepted: IntCounter,
+    shares_rejected: IntCounterVec,
+    blocks_found: IntCounter,
+    payout_total: Counter,
+    difficulty: IntGauge,
+    partition_size_bytes: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let total_hashrate = Gauge::new("pool_total_hashrate_hps", "Combined hashrate of active miners in H/s")?;
+        let active_miners = IntGauge::new("pool_active_miners", "Miners currently connected to the pool")?;
+        let shares_accepted = IntCounter::new("pool_shares_accepted_total", "Valid shares accepted")?;
+        let shares_rejected = IntCounterVec::new(
+            Opts::new("pool_shares_rejected_total", "Shares rejected, by reason"),
+            &["reason"],
+        )?;
+        let blocks_found = IntCounter::new("pool_blocks_found_total", "Blocks found by the pool")?;
+        let payout_total = Counter::new("pool_payout_total_nock", "NOCK paid out to miners")?;
+        let difficulty = IntGauge::new("pool_difficulty_current", "Current pool share difficulty")?;
+        let partition_size_bytes = IntGaugeVec::new(
+            Opts::new("database_partition_size_bytes", "On-disk size of each shares table partition"),
+            &["partition"],
+        )?;
+
+        registry.register(Box::new(total_hashrate.clone()))?;
+        registry.register(Box::new(active_miners.clone()))?;
+        registry.register(Box::new(shares_accepted.clone()))?;
+        registry.register(Box::new(shares_rejected.clone()))?;
+        registry.register(Box::new(blocks_found.clone()))?;
+        registry.register(Box::new(payout_total.clone()))?;
+        registry.register(Box::new(difficulty.clone()))?;
+        registry.register(Box::new(partition_size_bytes.clone()))?;
+
+        Ok(Self {
+            registry,
+            total_hashrate,
+            active_miners,
+            shares_accepted,
+            shares_rejected,
+            blocks_found,
+            payout_total,
+            difficulty,
+            partition_size_bytes,
+        })
+    }
+
+    pub fn record_share(&self, status: &ShareStatus) {
+        match status {
+            ShareStatus::Valid => self.shares_accepted.inc(),
+            ShareStatus::Invalid => self.shares_rejected.with_label_values(&["invalid"]).inc(),
+            ShareStatus::Stale => self.shares_rejected.with_label_values(&["stale"]).inc(),
+        }
+    }
+
+    pub fn record_duplicate_share(&self) {
+        self.shares_rejected.with_label_values(&["duplicate"]).inc();
+    }
+
+    pub async fn record_block_found(&self, miner_id: &str) {
+        self.blocks_found.inc();
+        tracing::debug!("Recorded block found by {}", miner_id);
+    }
+
+    pub fn record_payout(&self, amount: f64) {
+        if amount > 0.0 {
+            self.payout_total.inc_by(amount);
+        }
+    }
+
+    pub fn set_difficulty(&self, difficulty: u64) {
+        self.difficulty.set(i64::try_from(difficulty).unwrap_or(i64::MAX));
+    }
+
+    /// Replaces the per-partition sizes so dropped partitions stop being reported
+    pub fn set_partition_sizes(&self, sizes: &[(String, u64)]) {
+        self.partition_size_bytes.reset();
+        for (partition, bytes) in sizes {
+            self.partition_size_bytes
+                .with_label_values(&[partition])
+                .set(i64::try_from(*bytes).unwrap_or(i64::MAX));
+        }
+    }
+
+    pub async fn record_miner_connected(&self, miner_id: &str) {
+        self.active_miners.inc();
+        tracing::debug!("Recorded miner connected: {}", miner_id);
+    }
+
+    pub async fn record_miner_disconnected(&self, miner_id: &str) {
+        self.active_miners.dec();
+        tracing::debug!("Recorded miner disconnected: {}", miner_id);
+    }
+
+    pub async fn record_pool_stats(&self, stats: &PoolStats) {
+        self.total_hashrate.set(stats.total_hashrate);
+        self.active_miners.set(stats.active_miners as i64);
+    }
+
+    pub async fn record_block_reward_distributed(&self, net_reward: f64) {
+        tracing::debug!("Block reward of {} NOCK distributed", net_reward);
+    }
+
+    // Share counters are incremented per event; the periodic snapshot is only logged
+    pub async fn record_share_processing_stats(
+        &self,
+        total: u64,
+        valid: u64,
+        invalid: u64,
+        stale: u64,
+        blocks: u64,
+        processing_time_sum: u64,
+    ) -> Result<()> {
+        let average_ns = processing_time_sum.checked_div(total).unwrap_or(0);
+        tracing::debug!(
+            "Shares: {} total, {} valid, {} invalid, {} stale, {} blocks, {}ns average processing",
+            total, valid, invalid, stale, blocks, average_ns
+        );
+        Ok(())
+    }
+
+    // Metrics are pushed by the components that own them, so there is nothing to poll
+    pub async fn start_collection(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Every registered metric in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+pub async fn prometheus_metrics(State(state): State<AppState>) -> Response {
+    match state.metrics.render() {
+        Ok(body) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render metrics: {:#}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_value(output: &str, series: &str) -> Option<String> {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' ').map(str::to_string))
+    }
+
+    #[tokio::test]
+    async fn test_render_contains_recorded_events() {
+        let metrics = Metrics::new().unwrap();
+
+        metrics.record_share(&ShareStatus::Valid);
+        metrics.record_share(&ShareStatus::Valid);
+        metrics.record_share(&ShareStatus::Valid);
+        metrics.record_share(&ShareStatus::Stale);
+        metrics.record_share(&ShareStatus::Invalid);
+        metrics.record_share(&ShareStatus::Invalid);
+        metrics.record_block_found("miner-1").await;
+        metrics.record_payout(1250.5);
+        metrics.record_payout(49.5);
+        metrics.set_difficulty(4_096);
+        metrics.record_miner_connected("miner-1").await;
+        metrics.record_miner_connected("miner-2").await;
+        metrics.record_miner_disconnected("miner-2").await;
+        metrics.total_hashrate.set(1.5e9);
+
+        let output = metrics.render().unwrap();
+
+        assert_eq!(line_value(&output, "pool_shares_accepted_total").as_deref(), Some("3"));
+        assert_eq!(line_value(&output, "pool_shares_rejected_total{reason=\"invalid\"}").as_deref(), Some("2"));
+        assert_eq!(line_value(&output, "pool_shares_rejected_total{reason=\"stale\"}").as_deref(), Some("1"));
+        assert_eq!(line_value(&output, "pool_blocks_found_total").as_deref(), Some("1"));
+        assert_eq!(line_value(&output, "pool_payout_total_nock").as_deref(), Some("1300"));
+        assert_eq!(line_value(&output, "pool_difficulty_current").as_deref(), Some("4096"));
+        assert_eq!(line_value(&output, "pool_active_miners").as_deref(), Some("1"));
+        assert_eq!(line_value(&output, "pool_total_hashrate_hps").as_deref(), Some("1500000000"));
+        assert!(output.contains("# TYPE pool_shares_accepted_total counter"));
+        assert!(output.contains("# TYPE pool_difficulty_current gauge"));
+    }
+
+    #[test]
+    fn test_registries_are_independent() {
+        let first = Metrics::new().unwrap();
+        let second = Metrics::new().unwrap();
+        first.record_share(&ShareStatus::Valid);
+
+        let output = second.render().unwrap();
+        assert_eq!(line_value(&output, "pool_shares_accepted_total").as_deref(), Some("0"));
+    }
+}
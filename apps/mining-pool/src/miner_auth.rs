@@ -0,0 +1,341 @@
+// Miner authentication
+// Miners present a username and API key when setting up a Stratum connection. Keys are
+// stored only as BLAKE3 hashes, and repeated failures lock the username out.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use uuid::Uuid;
+
+use crate::{database::Database, AppState};
+
+// Failed attempts before a username is locked out
+pub const MAX_FAILED_ATTEMPTS: u32 = 10;
+
+pub const LOCKOUT_DURATION: Duration = Duration::from_secs(15 * 60);
+
+const API_KEY_PREFIX: &str = "nock_";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinerIdentity {
+    pub miner_id: String,
+    pub username: String,
+    pub key_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    pub miner_id: String,
+    pub username: String,
+    pub key_hash: String, // hex BLAKE3 of the key
+    pub created_at: SystemTime,
+    pub revoked_at: Option<SystemTime>,
+}
+
+/// A freshly created key; the only time the plaintext is available
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub key_id: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuthOutcome {
+    Authenticated(MinerIdentity),
+    InvalidCredentials,
+    LockedOut { retry_after: Duration },
+}
+
+#[async_trait]
+pub trait ApiKeyStore: Send + Sync {
+    /// Unrevoked key for the username with the given hash
+    async fn find_active_key(&self, username: &str, key_hash: &str) -> Result<Option<ApiKeyRecord>>;
+    async fn insert_key(&self, record: &ApiKeyRecord) -> Result<()>;
+    /// Revokes one of the miner's keys; false when the miner has no such active key
+    async fn revoke_key(&self, miner_id: &str, key_id: &str) -> Result<bool>;
+}
+
+#[async_trait]
+impl ApiKeyStore for Database {
+    async fn find_active_key(&self, username: &str, key_hash: &str) -> Result<Option<ApiKeyRecord>> {
+        self.find_miner_api_key(username, key_hash).await
+    }
+
+    async fn insert_key(&self, record: &ApiKeyRecord) -> Result<()> {
+        self.insert_miner_api_key(record).await
+    }
+
+    async fn revoke_key(&self, miner_id: &str, key_id: &str) -> Result<bool> {
+        self.revoke_miner_api_key(miner_id, key_id).await
+    }
+}
+
+pub fn hash_api_key(api_key: &str) -> String {
+    blake3::hash(api_key.as_bytes()).to_hex().to_string()
+}
+
+fn generate_api_key() -> String {
+    format!("{}{}{}", API_KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[derive(Debug)]
+struct FailedLogins {
+    count: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+pub struct MinerAuth {
+    store: Arc<dyn ApiKeyStore>,
+    failures: Mutex<HashMap<String, FailedLogins>>,
+}
+
+impl MinerAuth {
+    pub fn new(store: Arc<dyn ApiKeyStore>) -> Self {
+        Self {
+            store,
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn authenticate(&self, username: &str, api_key: &str) -> Result<AuthOutcome> {
+        self.authenticate_at(username, api_key, Instant::now()).await
+    }
+
+    async fn authenticate_at(&self, username: &str, api_key: &str, now: Instant) -> Result<AuthOutcome> {
+        // A locked username is rejected without checking the key
+        let locked_until = self.failures.lock().get(username).and_then(|f| f.locked_until);
+        if let Some(locked_until) = locked_until {
+            if now < locked_until {
+                return Ok(AuthOutcome::LockedOut { retry_after: locked_until - now });
+            }
+        }
+
+        let record = self.store.find_active_key(username, &hash_api_key(api_key)).await?;
+        let Some(record) = record else {
+            return Ok(self.record_failure(username, now));
+        };
+
+        self.failures.lock().remove(username);
+        Ok(AuthOutcome::Authenticated(MinerIdentity {
+            miner_id: record.miner_id,
+            username: record.username,
+            key_id: record.key_id,
+        }))
+    }
+
+    fn record_failure(&self, username: &str, now: Instant) -> AuthOutcome {
+        let mut failures = self.failures.lock();
+        if !failures.contains_key(username) {
+            failures.retain(|_, f| now.saturating_duration_since(f.last_failure) < LOCKOUT_DURATION);
+        }
+
+        let entry = failures.entry(username.to_string()).or_insert(FailedLogins {
+            count: 0,
+            last_failure: now,
+            locked_until: None,
+        });
+        // Lockouts that have expired start a fresh count
+        if entry.locked_until.is_some_and(|until| now >= until) {
+            entry.count = 0;
+            entry.locked_until = None;
+        }
+
+        entry.count += 1;
+        entry.last_failure = now;
+        if entry.count >= MAX_FAILED_ATTEMPTS {
+            entry.locked_until = Some(now + LOCKOUT_DURATION);
+            tracing::warn!("Miner username {} locked out after {} failed logins", username, entry.count);
+        }
+        AuthOutcome::InvalidCredentials
+    }
+
+    pub async fn create_api_key(&self, miner_id: &str, username: &str) -> Result<NewApiKey> {
+        let api_key = generate_api_key();
+        let record = ApiKeyRecord {
+            key_id: Uuid::new_v4().to_string(),
+            miner_id: miner_id.to_string(),
+            username: username.to_string(),
+            key_hash: hash_api_key(&api_key),
+            created_at: SystemTime::now(),
+            revoked_at: None,
+        };
+
+        self.store.insert_key(&record).await?;
+        tracing::info!("API key {} created for miner {}", record.key_id, miner_id);
+        Ok(NewApiKey { key_id: record.key_id, api_key })
+    }
+
+    pub async fn revoke_api_key(&self, miner_id: &str, key_id: &str) -> Result<bool> {
+        let revoked = self.store.revoke_key(miner_id, key_id).await?;
+        if revoked {
+            tracing::info!("API key {} revoked for miner {}", key_id, miner_id);
+        }
+        Ok(revoked)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub username: String,
+}
+
+// Key management requires the configured admin bearer token
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = state.config.security.admin_api_token.as_deref() else {
+        return Err(StatusCode::FORBIDDEN);
+    };
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    // blake3::Hash compares in constant time
+    if blake3::hash(presented.as_bytes()) == blake3::hash(expected.as_bytes()) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Path(miner_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<Json<NewApiKey>, StatusCode> {
+    authorize_admin(&state, &headers)?;
+    if request.username.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    state.miner_auth.create_api_key(&miner_id, request.username.trim()).await.map(Json).map_err(|e| {
+        tracing::error!("Failed to create API key for miner {}: {:#}", miner_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path((miner_id, key_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> StatusCode {
+    if let Err(status) = authorize_admin(&state, &headers) {
+        return status;
+    }
+
+    match state.miner_auth.revoke_api_key(&miner_id, &key_id).await {
+        Ok(true) => StatusCode::NO_CONTENT,
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(e) => {
+            tracing::error!("Failed to revoke API key {} for miner {}: {:#}", key_id, miner_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::stubs::StubApiKeyStore;
+
+    fn auth() -> (MinerAuth, Arc<StubApiKeyStore>) {
+        let store = Arc::new(StubApiKeyStore::default());
+        (MinerAuth::new(store.clone()), store)
+    }
+
+    #[tokio::test]
+    async fn test_created_key_authenticates_and_is_stored_hashed() {
+        let (auth, store) = auth();
+        let key = auth.create_api_key("miner-1", "alice").await.unwrap();
+
+        assert!(key.api_key.starts_with(API_KEY_PREFIX));
+        let stored = store.keys();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].key_hash, hash_api_key(&key.api_key));
+        assert!(!stored[0].key_hash.contains(&key.api_key));
+
+        assert_eq!(
+            auth.authenticate("alice", &key.api_key).await.unwrap(),
+            AuthOutcome::Authenticated(MinerIdentity {
+                miner_id: "miner-1".to_string(),
+                username: "alice".to_string(),
+                key_id: key.key_id,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wrong_username_key_or_revoked_key_rejected() {
+        let (auth, _) = auth();
+        let key = auth.create_api_key("miner-1", "alice").await.unwrap();
+
+        assert_eq!(auth.authenticate("bob", &key.api_key).await.unwrap(), AuthOutcome::InvalidCredentials);
+        assert_eq!(auth.authenticate("alice", "nock_guess").await.unwrap(), AuthOutcome::InvalidCredentials);
+
+        assert!(!auth.revoke_api_key("miner-2", &key.key_id).await.unwrap());
+        assert!(auth.revoke_api_key("miner-1", &key.key_id).await.unwrap());
+        assert_eq!(auth.authenticate("alice", &key.api_key).await.unwrap(), AuthOutcome::InvalidCredentials);
+    }
+
+    #[tokio::test]
+    async fn test_lockout_after_repeated_failures() {
+        let (auth, _) = auth();
+        let key = auth.create_api_key("miner-1", "alice").await.unwrap();
+        let start = Instant::now();
+
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            assert_eq!(auth.authenticate_at("alice", "wrong", start).await.unwrap(), AuthOutcome::InvalidCredentials);
+        }
+
+        // Even the right key is refused until the lockout expires
+        assert_eq!(
+            auth.authenticate_at("alice", &key.api_key, start + Duration::from_secs(60)).await.unwrap(),
+            AuthOutcome::LockedOut { retry_after: LOCKOUT_DURATION - Duration::from_secs(60) }
+        );
+
+        let later = start + LOCKOUT_DURATION;
+        assert!(matches!(
+            auth.authenticate_at("alice", &key.api_key, later).await.unwrap(),
+            AuthOutcome::Authenticated(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_failure_count() {
+        let (auth, _) = auth();
+        let key = auth.create_api_key("miner-1", "alice").await.unwrap();
+        let now = Instant::now();
+
+        for _ in 0..MAX_FAILED_ATTEMPTS - 1 {
+            auth.authenticate_at("alice", "wrong", now).await.unwrap();
+        }
+        assert!(matches!(auth.authenticate_at("alice", &key.api_key, now).await.unwrap(), AuthOutcome::Authenticated(_)));
+
+        assert_eq!(auth.authenticate_at("alice", "wrong", now).await.unwrap(), AuthOutcome::InvalidCredentials);
+        assert!(matches!(auth.authenticate_at("alice", &key.api_key, now).await.unwrap(), AuthOutcome::Authenticated(_)));
+    }
+
+    #[tokio::test]
+    async fn test_lockout_is_per_username() {
+        let (auth, _) = auth();
+        let key = auth.create_api_key("miner-2", "bob").await.unwrap();
+        let now = Instant::now();
+
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            auth.authenticate_at("alice", "wrong", now).await.unwrap();
+        }
+        assert!(matches!(auth.authenticate_at("bob", &key.api_key, now).await.unwrap(), AuthOutcome::Authenticated(_)));
+    }
+}
@@ -0,0 +1,153 @@
+// Pool sub-system interfaces
+// MiningPool drives its share processor, payout engine and difficulty adjuster through
+// these traits so each can be replaced by a stub in unit tests
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Instant;
+
+use crate::{
+    maintenance::MaintenanceStatus,
+    mining::{Share, ShareValidationResult},
+    payout_engine::{MinerBalance, Payout},
+    share_processor::ShareProcessingStats,
+};
+
+#[async_trait]
+pub trait ShareProcessor: Send + Sync {
+    async fn start(&self) -> Result<()>;
+    async fn stop(&self);
+    async fn is_running(&self) -> bool;
+
+    /// Validates and stores a share, or defers it while the pool is in maintenance
+    async fn process_share(&self, share: Share) -> Result<ShareValidationResult>;
+
+    fn is_in_maintenance(&self) -> bool;
+    /// Processes shares deferred during maintenance; returns how many were drained
+    async fn drain_maintenance_queue(&self) -> Result<usize>;
+    fn get_maintenance_status(&self) -> MaintenanceStatus;
+
+    async fn get_statistics(&self) -> ShareProcessingStats;
+    async fn update_block_target(&self, block_hash: String, target: Vec<u8>);
+}
+
+#[async_trait]
+pub trait PayoutEngine: Send + Sync {
+    async fn start(&self) -> Result<()>;
+    async fn stop(&self);
+    async fn is_running(&self) -> bool;
+
+    /// Credits a processed share towards the miner's next payout
+    async fn process_share(&self, share: &Share, is_valid: bool, is_block: bool) -> Result<()>;
+    /// Network difficulty sizes the PPLNS share window; other schemes ignore it
+    async fn update_network_difficulty(&self, _difficulty: u64) {}
+
+    async fn get_miner_balance(&self, miner_id: &str) -> Option<MinerBalance>;
+    async fn get_pending_payouts(&self, miner_id: Option<&str>) -> Vec<Payout>;
+    /// Total payouts processed and total amount paid
+    async fn get_payout_statistics(&self) -> (u64, f64);
+    /// Pays out a miner's confirmed balance regardless of the minimum payout
+    async fn force_payout(&self, miner_id: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait DifficultyAdjuster: Send + Sync {
+    async fn start(&self) -> Result<()>;
+    async fn stop(&self);
+    async fn is_running(&self) -> bool;
+
+    async fn current_difficulty(&self) -> u64;
+    /// Counts an accepted share; returns the new pool difficulty when this triggers a retarget
+    async fn record_share(&self, submitted_at: Instant) -> Option<u64>;
+
+    /// Share difficulty currently assigned to a miner
+    async fn miner_difficulty(&self, miner_id: &str) -> u64;
+    /// Counts an accepted share from one miner; returns the miner's new difficulty when this
+    /// triggers a retarget, which the caller sends down the miner's connection
+    async fn record_miner_share(&self, miner_id: &str, submitted_at: Instant) -> Option<u64>;
+}
+
+/// Whether every component the pool needs to accept work is running
+pub async fn components_running(
+    share_processor: &dyn ShareProcessor,
+    payout_engine: &dyn PayoutEngine,
+    difficulty_adjuster: &dyn DifficultyAdjuster,
+) -> bool {
+    share_processor.is_running().await
+        && payout_engine.is_running().await
+        && difficulty_adjuster.is_running().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::stubs::{StubDifficultyAdjuster, StubPayoutEngine, StubShareProcessor};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_components_running_requires_every_component() {
+        let share_processor = StubShareProcessor::new();
+        let payout_engine = StubPayoutEngine::new();
+        let difficulty_adjuster = StubDifficultyAdjuster::new(1_000);
+
+        assert!(!components_running(&share_processor, &payout_engine, &difficulty_adjuster).await);
+
+        share_processor.start().await.unwrap();
+        payout_engine.start().await.unwrap();
+        assert!(!components_running(&share_processor, &payout_engine, &difficulty_adjuster).await);
+
+        difficulty_adjuster.start().await.unwrap();
+        assert!(components_running(&share_processor, &payout_engine, &difficulty_adjuster).await);
+
+        payout_engine.stop().await;
+        assert!(!components_running(&share_processor, &payout_engine, &difficulty_adjuster).await);
+    }
+
+    #[tokio::test]
+    async fn test_share_processor_drains_only_after_maintenance() {
+        let share_processor: Box<dyn ShareProcessor + Send + Sync> =
+            Box::new(StubShareProcessor::in_maintenance(3));
+
+        assert!(share_processor.is_in_maintenance());
+        assert_eq!(share_processor.drain_maintenance_queue().await.unwrap(), 0);
+        assert_eq!(share_processor.get_maintenance_status().queued_shares, 3);
+    }
+
+    #[tokio::test]
+    async fn test_share_processor_drains_queued_shares() {
+        let stub = StubShareProcessor::in_maintenance(3);
+        stub.end_maintenance();
+        let share_processor: Box<dyn ShareProcessor + Send + Sync> = Box::new(stub);
+
+        assert_eq!(share_processor.drain_maintenance_queue().await.unwrap(), 3);
+        assert_eq!(share_processor.drain_maintenance_queue().await.unwrap(), 0);
+        assert_eq!(share_processor.get_maintenance_status().queued_shares, 0);
+    }
+
+    #[tokio::test]
+    async fn test_payout_engine_force_payout_clears_balance() {
+        let payout_engine: Box<dyn PayoutEngine + Send + Sync> =
+            Box::new(StubPayoutEngine::with_balance("miner-1", 12.5));
+
+        payout_engine.force_payout("miner-1").await.unwrap();
+        payout_engine.force_payout("miner-2").await.unwrap();
+
+        let pending = payout_engine.get_pending_payouts(Some("miner-1")).await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].amount, 12.5);
+        assert!(payout_engine.get_pending_payouts(Some("miner-2")).await.is_empty());
+        assert_eq!(payout_engine.get_miner_balance("miner-1").await.unwrap().confirmed_balance, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_difficulty_adjuster_reports_retarget_once() {
+        let difficulty_adjuster: Box<dyn DifficultyAdjuster + Send + Sync> =
+            Box::new(StubDifficultyAdjuster::retargeting_after(1_000, 2, 4_000));
+        let now = Instant::now();
+
+        assert_eq!(difficulty_adjuster.record_share(now).await, None);
+        assert_eq!(difficulty_adjuster.record_share(now + Duration::from_secs(1)).await, Some(4_000));
+        assert_eq!(difficulty_adjuster.current_difficulty().await, 4_000);
+        assert_eq!(difficulty_adjuster.record_share(now + Duration::from_secs(2)).await, None);
+    }
+}
@@ -9,24 +9,32 @@ use parking_lot::Mutex;
 use std::time::{Duration, Instant};
 
 use crate::{
-    config::Config,
+    block_time_estimator::{BlockTimeEstimate, BlockTimeEstimator},
+    hashrate_oracle::{HashrateOracle, NetworkHashrateData},
+    config::{Config, PayoutScheme},
     database::Database,
     metrics::Metrics,
-    share_processor::ShareProcessor,
-    payout_engine::PayoutEngine,
+    share_processor::PoolShareProcessor,
+    payout_engine::PoolPayoutEngine,
+    pplns_payout_engine::{InMemoryShareStore, NockchainPayoutSender, PplnsPayoutEngine, PplnsSettings},
     block_finder::BlockFinder,
-    difficulty_adjuster::DifficultyAdjuster,
+    broadcast_bus::BroadcastBus,
+    difficulty_adjuster::{PoolDifficultyAdjuster, VardiffSettings},
 };
 
 pub mod miner;
 pub mod share;
 pub mod block;
 pub mod difficulty;
+pub mod components;
+#[cfg(test)]
+pub mod stubs;
 
 pub use miner::{Miner, MinerStats, MinerConnection};
 pub use share::{Share, ShareStatus, ShareValidationResult};
 pub use block::{Block, BlockTemplate, BlockSolution};
 pub use difficulty::{DifficultyManager, VariableDifficulty};
+pub use components::{DifficultyAdjuster, PayoutEngine, ShareProcessor};
 
 // Core mining pool structure
 pub struct MiningPool {
@@ -35,10 +43,11 @@ pub struct MiningPool {
     pub metrics: Arc<Metrics>,
     
     // Core components
-    pub share_processor: Arc<ShareProcessor>,
-    pub payout_engine: Arc<PayoutEngine>,
+    pub share_processor: Box<dyn ShareProcessor + Send + Sync>,
+    pub payout_engine: Box<dyn PayoutEngine + Send + Sync>,
     pub block_finder: Arc<BlockFinder>,
-    pub difficulty_adjuster: Arc<DifficultyAdjuster>,
+    pub broadcast_bus: BroadcastBus,
+    pub difficulty_adjuster: Box<dyn DifficultyAdjuster + Send + Sync>,
     
     // Active miners and connections
     pub active_miners: Arc<DashMap<String, Arc<Miner>>>,
@@ -48,6 +57,8 @@ pub struct MiningPool {
     pub pool_stats: Arc<RwLock<PoolStats>>,
     pub current_difficulty: Arc<RwLock<u64>>,
     pub block_template: Arc<RwLock<Option<BlockTemplate>>>,
+    pub block_time_estimate: Arc<RwLock<Option<BlockTimeEstimate>>>,
+    pub cross_pool_hashrate_oracle: Arc<HashrateOracle>,
     
     // Performance tracking
     pub performance_metrics: Arc<Mutex<PerformanceMetrics>>,
@@ -87,36 +98,38 @@ impl MiningPool {
         database: Arc<Database>,
         metrics: Arc<Metrics>,
     ) -> Result<Self> {
-        let share_processor = Arc::new(
-            ShareProcessor::new(
+        let share_processor = Box::new(
+            PoolShareProcessor::new(
                 config.clone(),
                 database.clone(),
                 metrics.clone(),
             ).await?
         );
 
-        let payout_engine = Arc::new(
-            PayoutEngine::new(
-                config.clone(),
-                database.clone(),
-                metrics.clone(),
-            ).await?
-        );
+        let payout_engine: Box<dyn PayoutEngine + Send + Sync> = match config.payout.scheme {
+            PayoutScheme::PPLNS => Box::new(
+                PplnsPayoutEngine::new(
+                    PplnsSettings::from(&*config),
+                    Arc::new(InMemoryShareStore::new()),
+                    database.clone(),
+                    Arc::new(NockchainPayoutSender::new(config.payout.nockchain_rpc_url.clone())),
+                    metrics.clone(),
+                )
+            ),
+            _ => Box::new(
+                PoolPayoutEngine::new(
+                    config.clone(),
+                    database.clone(),
+                    metrics.clone(),
+                ).await?
+            ),
+        };
 
-        let block_finder = Arc::new(
-            BlockFinder::new(
-                config.clone(),
-                database.clone(),
-                metrics.clone(),
-            ).await?
-        );
+        let broadcast_bus = BroadcastBus::new();
+        let block_finder = Arc::new(BlockFinder::new(&config).with_bus(broadcast_bus.clone()));
 
-        let difficulty_adjuster = Arc::new(
-            DifficultyAdjuster::new(
-                config.clone(),
-                database.clone(),
-                metrics.clone(),
-            ).await?
+        let difficulty_adjuster = Box::new(
+            PoolDifficultyAdjuster::new(VardiffSettings::from(&config.mining), metrics.clone())
         );
 
         let pool_stats = PoolStats {
@@ -151,12 +164,15 @@ impl MiningPool {
             share_processor,
             payout_engine,
             block_finder,
+            broadcast_bus,
             difficulty_adjuster,
             active_miners: Arc::new(DashMap::new()),
             connections: Arc::new(DashMap::new()),
             pool_stats: Arc::new(RwLock::new(pool_stats)),
             current_difficulty: Arc::new(RwLock::new(config.mining.minimum_difficulty)),
             block_template: Arc::new(RwLock::new(None)),
+            block_time_estimate: Arc::new(RwLock::new(None)),
+            cross_pool_hashrate_oracle: Arc::new(HashrateOracle::new(config.hashrate_oracle_endpoints.clone())),
             performance_metrics: Arc::new(Mutex::new(performance_metrics)),
             start_time: Instant::now(),
         })
@@ -218,6 +234,11 @@ impl MiningPool {
         
         // Validate share
         let result = self.share_processor.process_share(share).await?;
+
+        // Shares queued during maintenance are counted once they are drained
+        if self.share_processor.is_in_maintenance() {
+            return Ok(result);
+        }
         
         // Update performance metrics
         let processing_time = start_time.elapsed();
@@ -225,6 +246,13 @@ impl MiningPool {
         
         // Update pool stats based on result
         self.update_stats_from_share_result(&result).await;
+
+        // Accepted shares drive vardiff retargeting
+        if matches!(result.status, ShareStatus::Valid) {
+            if let Some(difficulty) = self.difficulty_adjuster.record_share(Instant::now()).await {
+                self.set_difficulty(difficulty).await?;
+            }
+        }
         
         Ok(result)
     }
@@ -316,11 +344,11 @@ impl MiningPool {
         }
         
         // Check if core components are running
-        if !self.share_processor.is_running().await {
-            return false;
-        }
-        
-        if !self.payout_engine.is_running().await {
+        if !components::components_running(
+            &*self.share_processor,
+            &*self.payout_engine,
+            &*self.difficulty_adjuster,
+        ).await {
             return false;
         }
         
@@ -384,10 +412,44 @@ impl MiningPool {
         }
     }
 
+    pub async fn update_network_difficulty(&self, difficulty: u64) {
+        self.pool_stats.write().await.network_difficulty = difficulty;
+        self.payout_engine.update_network_difficulty(difficulty).await;
+    }
+
     pub async fn get_current_block_template(&self) -> Option<BlockTemplate> {
         self.block_template.read().await.clone()
     }
 
+    // Time-to-block estimation
+    pub async fn refresh_block_time_estimate(&self) -> BlockTimeEstimate {
+        let estimate = {
+            let stats = self.pool_stats.read().await;
+            let round_start = stats.last_block_time.unwrap_or(self.start_time);
+            BlockTimeEstimator::new(
+                stats.total_hashrate,
+                stats.network_difficulty,
+                round_start.elapsed().as_secs_f64(),
+            ).estimate()
+        };
+
+        *self.block_time_estimate.write().await = Some(estimate.clone());
+        estimate
+    }
+
+    pub async fn get_block_time_estimate(&self) -> BlockTimeEstimate {
+        if let Some(estimate) = self.block_time_estimate.read().await.clone() {
+            return estimate;
+        }
+        self.refresh_block_time_estimate().await
+    }
+
+    // Share of total network hashrate
+    pub async fn get_market_share(&self) -> Result<NetworkHashrateData> {
+        let pool_hashrate = self.pool_stats.read().await.total_hashrate;
+        self.cross_pool_hashrate_oracle.fetch_network_hashrate(pool_hashrate).await
+    }
+
     // Performance monitoring
     pub async fn get_performance_metrics(&self) -> PerformanceMetrics {
         let perf = self.performance_metrics.lock();
@@ -0,0 +1,323 @@
+// In-memory stand-ins for the pool sub-systems, used by unit tests
+
+use anyhow::Result;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{
+    maintenance::{MaintenanceStatus, MAINTENANCE_QUEUE_CAPACITY},
+    miner_auth::{hash_api_key, ApiKeyRecord, ApiKeyStore},
+    mining::{
+        components::{DifficultyAdjuster, PayoutEngine, ShareProcessor},
+        Share, ShareStatus, ShareValidationResult,
+    },
+    payout_engine::{MinerBalance, Payout, PayoutStatus},
+    share_processor::ShareProcessingStats,
+};
+
+// Accepts every share, counting shares deferred while in maintenance
+#[derive(Default)]
+pub struct StubShareProcessor {
+    running: AtomicBool,
+    maintenance: AtomicBool,
+    queued: AtomicUsize,
+    processed: AtomicU64,
+}
+
+impl StubShareProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn in_maintenance(queued: usize) -> Self {
+        Self {
+            maintenance: AtomicBool::new(true),
+            queued: AtomicUsize::new(queued),
+            ..Self::default()
+        }
+    }
+
+    pub fn end_maintenance(&self) {
+        self.maintenance.store(false, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl ShareProcessor for StubShareProcessor {
+    async fn start(&self) -> Result<()> {
+        self.running.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    async fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    async fn process_share(&self, _share: Share) -> Result<ShareValidationResult> {
+        if self.is_in_maintenance() {
+            self.queued.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.processed.fetch_add(1, Ordering::Relaxed);
+        }
+
+        Ok(ShareValidationResult {
+            status: ShareStatus::Valid,
+            error: None,
+            is_block_solution: false,
+            difficulty_achieved: 0,
+            processing_time: Duration::ZERO,
+        })
+    }
+
+    fn is_in_maintenance(&self) -> bool {
+        self.maintenance.load(Ordering::Relaxed)
+    }
+
+    async fn drain_maintenance_queue(&self) -> Result<usize> {
+        if self.is_in_maintenance() {
+            return Ok(0);
+        }
+
+        let drained = self.queued.swap(0, Ordering::Relaxed);
+        self.processed.fetch_add(drained as u64, Ordering::Relaxed);
+        Ok(drained)
+    }
+
+    fn get_maintenance_status(&self) -> MaintenanceStatus {
+        MaintenanceStatus {
+            active: self.is_in_maintenance(),
+            window: None,
+            queued_shares: self.queued.load(Ordering::Relaxed),
+            queue_capacity: MAINTENANCE_QUEUE_CAPACITY,
+            rejected_shares: 0,
+        }
+    }
+
+    async fn get_statistics(&self) -> ShareProcessingStats {
+        let stats = ShareProcessingStats::default();
+        stats.total_processed.store(self.processed.load(Ordering::Relaxed), Ordering::Relaxed);
+        stats.valid_shares.store(self.processed.load(Ordering::Relaxed), Ordering::Relaxed);
+        stats
+    }
+
+    async fn update_block_target(&self, _block_hash: String, _target: Vec<u8>) {}
+}
+
+// Holds balances in memory and ignores shares; forced payouts move the confirmed
+// balance into a pending payout
+#[derive(Default)]
+pub struct StubPayoutEngine {
+    running: AtomicBool,
+    balances: Mutex<HashMap<String, MinerBalance>>,
+    pending_payouts: Mutex<Vec<Payout>>,
+}
+
+impl StubPayoutEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_balance(miner_id: &str, confirmed_balance: f64) -> Self {
+        let engine = Self::default();
+        engine.balances.lock().insert(miner_id.to_string(), MinerBalance {
+            miner_id: miner_id.to_string(),
+            confirmed_balance,
+            unconfirmed_balance: 0.0,
+            total_earned: confirmed_balance,
+            total_paid: 0.0,
+            last_payout: None,
+            pending_payouts: Vec::new(),
+        });
+        engine
+    }
+}
+
+#[async_trait]
+impl PayoutEngine for StubPayoutEngine {
+    async fn start(&self) -> Result<()> {
+        self.running.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    async fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    async fn process_share(&self, _share: &Share, _is_valid: bool, _is_block: bool) -> Result<()> {
+        Ok(())
+    }
+
+    async fn get_miner_balance(&self, miner_id: &str) -> Option<MinerBalance> {
+        self.balances.lock().get(miner_id).cloned()
+    }
+
+    async fn get_pending_payouts(&self, miner_id: Option<&str>) -> Vec<Payout> {
+        self.pending_payouts
+            .lock()
+            .iter()
+            .filter(|payout| miner_id.map_or(true, |id| payout.miner_id == id))
+            .cloned()
+            .collect()
+    }
+
+    async fn get_payout_statistics(&self) -> (u64, f64) {
+        let pending = self.pending_payouts.lock();
+        (pending.len() as u64, pending.iter().map(|payout| payout.amount).sum())
+    }
+
+    async fn force_payout(&self, miner_id: &str) -> Result<()> {
+        let mut balances = self.balances.lock();
+        let Some(balance) = balances.get_mut(miner_id) else {
+            return Ok(());
+        };
+        if balance.confirmed_balance <= 0.0 {
+            return Ok(());
+        }
+
+        let payout = Payout {
+            id: format!("stub-payout-{}", self.pending_payouts.lock().len()),
+            miner_id: miner_id.to_string(),
+            amount: balance.confirmed_balance,
+            transaction_hash: None,
+            status: PayoutStatus::Pending,
+            created_at: SystemTime::now(),
+            completed_at: None,
+            failure_reason: None,
+        };
+        balance.confirmed_balance = 0.0;
+        balance.pending_payouts.push(payout.id.clone());
+        self.pending_payouts.lock().push(payout);
+        Ok(())
+    }
+}
+
+// Retargets to a fixed difficulty once a set number of shares has been recorded
+pub struct StubDifficultyAdjuster {
+    running: AtomicBool,
+    difficulty: AtomicU64,
+    retarget: Mutex<Option<(u64, u64)>>, // (shares remaining, new difficulty)
+}
+
+impl StubDifficultyAdjuster {
+    pub fn new(difficulty: u64) -> Self {
+        Self {
+            running: AtomicBool::new(false),
+            difficulty: AtomicU64::new(difficulty),
+            retarget: Mutex::new(None),
+        }
+    }
+
+    pub fn retargeting_after(difficulty: u64, shares: u64, new_difficulty: u64) -> Self {
+        let adjuster = Self::new(difficulty);
+        *adjuster.retarget.lock() = Some((shares, new_difficulty));
+        adjuster
+    }
+}
+
+#[async_trait]
+impl DifficultyAdjuster for StubDifficultyAdjuster {
+    async fn start(&self) -> Result<()> {
+        self.running.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    async fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    async fn current_difficulty(&self) -> u64 {
+        self.difficulty.load(Ordering::Relaxed)
+    }
+
+    async fn record_share(&self, _submitted_at: Instant) -> Option<u64> {
+        let mut retarget = self.retarget.lock();
+        let (remaining, new_difficulty) = retarget.as_mut()?;
+
+        *remaining = remaining.saturating_sub(1);
+        if *remaining > 0 {
+            return None;
+        }
+
+        let new_difficulty = *new_difficulty;
+        *retarget = None;
+        self.difficulty.store(new_difficulty, Ordering::Relaxed);
+        Some(new_difficulty)
+    }
+
+    // Every miner shares the one difficulty
+    async fn miner_difficulty(&self, _miner_id: &str) -> u64 {
+        self.current_difficulty().await
+    }
+
+    async fn record_miner_share(&self, _miner_id: &str, submitted_at: Instant) -> Option<u64> {
+        self.record_share(submitted_at).await
+    }
+}
+
+// API keys held in memory, as the miners table would hold them
+#[derive(Default)]
+pub struct StubApiKeyStore {
+    keys: Mutex<Vec<ApiKeyRecord>>,
+}
+
+impl StubApiKeyStore {
+    pub fn with_key(miner_id: &str, username: &str, api_key: &str) -> Self {
+        let store = Self::default();
+        store.keys.lock().push(ApiKeyRecord {
+            key_id: format!("stub-key-{}", username),
+            miner_id: miner_id.to_string(),
+            username: username.to_string(),
+            key_hash: hash_api_key(api_key),
+            created_at: SystemTime::now(),
+            revoked_at: None,
+        });
+        store
+    }
+
+    pub fn keys(&self) -> Vec<ApiKeyRecord> {
+        self.keys.lock().clone()
+    }
+}
+
+#[async_trait]
+impl ApiKeyStore for StubApiKeyStore {
+    async fn find_active_key(&self, username: &str, key_hash: &str) -> Result<Option<ApiKeyRecord>> {
+        Ok(self.keys
+            .lock()
+            .iter()
+            .find(|key| key.username == username && key.key_hash == key_hash && key.revoked_at.is_none())
+            .cloned())
+    }
+
+    async fn insert_key(&self, record: &ApiKeyRecord) -> Result<()> {
+        self.keys.lock().push(record.clone());
+        Ok(())
+    }
+
+    async fn revoke_key(&self, miner_id: &str, key_id: &str) -> Result<bool> {
+        let mut keys = self.keys.lock();
+        let Some(key) = keys
+            .iter_mut()
+            .find(|key| key.miner_id == miner_id && key.key_id == key_id && key.revoked_at.is_none())
+        else {
+            return Ok(false);
+        };
+        key.revoked_at = Some(SystemTime::now());
+        Ok(true)
+    }
+}
@@ -2,6 +2,7 @@
 // Supports multiple payout schemes with enterprise-grade accuracy
 
 use anyhow::Result;
+use async_trait::async_trait;
 use std::collections::HashMap;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use tokio::sync::RwLock;
@@ -13,7 +14,7 @@ use crate::{
     config::{Config, PayoutScheme},
     database::Database,
     metrics::Metrics,
-    mining::{Share, Miner},
+    mining::{components::PayoutEngine, Share, Miner},
     websocket::{broadcast_payout_sent, PayoutData},
 };
 
@@ -82,7 +83,7 @@ pub struct PPLNSShare {
     pub block_height: Option<u64>,
 }
 
-pub struct PayoutEngine {
+pub struct PoolPayoutEngine {
     config: Arc<Config>,
     database: Arc<Database>,
     metrics: Arc<Metrics>,
@@ -104,7 +105,7 @@ pub struct PayoutEngine {
     total_amount_paid: Arc<RwLock<f64>>,
 }
 
-impl PayoutEngine {
+impl PoolPayoutEngine {
     pub async fn new(
         config: Arc<Config>,
         database: Arc<Database>,
@@ -491,6 +492,7 @@ impl PayoutEngine {
                                 let mut total = total_paid.write().await;
                                 *total += payout.amount;
                             }
+                            metrics.record_payout(payout.amount);
                             
                             // Broadcast payout event
                             let payout_data = PayoutData {
@@ -654,4 +656,39 @@ impl PayoutEngine {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl PayoutEngine for PoolPayoutEngine {
+    async fn start(&self) -> Result<()> {
+        PoolPayoutEngine::start(self).await
+    }
+
+    async fn stop(&self) {
+        PoolPayoutEngine::stop(self).await
+    }
+
+    async fn is_running(&self) -> bool {
+        PoolPayoutEngine::is_running(self).await
+    }
+
+    async fn process_share(&self, share: &Share, is_valid: bool, is_block: bool) -> Result<()> {
+        PoolPayoutEngine::process_share(self, share, is_valid, is_block).await
+    }
+
+    async fn get_miner_balance(&self, miner_id: &str) -> Option<MinerBalance> {
+        PoolPayoutEngine::get_miner_balance(self, miner_id).await
+    }
+
+    async fn get_pending_payouts(&self, miner_id: Option<&str>) -> Vec<Payout> {
+        PoolPayoutEngine::get_pending_payouts(self, miner_id).await
+    }
+
+    async fn get_payout_statistics(&self) -> (u64, f64) {
+        PoolPayoutEngine::get_payout_statistics(self).await
+    }
+
+    async fn force_payout(&self, miner_id: &str) -> Result<()> {
+        PoolPayoutEngine::force_payout(self, miner_id).await
+    }
+}
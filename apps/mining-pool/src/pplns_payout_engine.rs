@@ -0,0 +1,840 @@
+// PPLNS (Pay Per Last N Shares) payout engine
+// Each found block's reward is split across the shares in the window ending at the block,
+// weighted by share difficulty. Credited rewards are paid out in batched Nockchain transactions.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    database::Database,
+    metrics::Metrics,
+    mining::{components::PayoutEngine, Share},
+    payout_engine::{MinerBalance, Payout, PayoutStatus},
+};
+
+// NOCK block reward
+pub const BLOCK_REWARD: f64 = 65536.0;
+
+// Window covers twice the shares expected per block at the current network difficulty
+pub const DEFAULT_WINDOW_FACTOR: f64 = 2.0;
+
+// Outputs per payout transaction
+pub const DEFAULT_MAX_TRANSFERS_PER_TRANSACTION: usize = 100;
+
+const PAYOUT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+pub struct PplnsSettings {
+    pub window_factor: f64,
+    pub block_reward: f64,
+    pub pool_fee: f64,
+    pub minimum_payout: f64,
+    pub payout_interval: Duration,
+    pub max_transfers_per_transaction: usize,
+    // Used to size the window until the first network difficulty update
+    pub initial_network_difficulty: u64,
+}
+
+impl From<&Config> for PplnsSettings {
+    fn from(config: &Config) -> Self {
+        Self {
+            window_factor: DEFAULT_WINDOW_FACTOR,
+            block_reward: BLOCK_REWARD,
+            pool_fee: config.mining.pool_fee,
+            minimum_payout: config.payout.minimum_payout,
+            payout_interval: config.payout.payout_interval,
+            max_transfers_per_transaction: DEFAULT_MAX_TRANSFERS_PER_TRANSACTION,
+            initial_network_difficulty: config.mining.minimum_difficulty,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowShare {
+    pub sequence: u64,
+    pub miner_id: String,
+    pub difficulty: u64,
+    pub received_at: SystemTime,
+}
+
+/// One miner's credit for one found block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutRecord {
+    pub id: String,
+    pub block_sequence: u64, // sequence of the block-solving share
+    pub block_parent_hash: String,
+    pub miner_id: String,
+    pub shares: u64,
+    pub difficulty: u64,
+    pub amount: f64,
+    pub created_at: SystemTime,
+    pub transaction_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PayoutTransfer {
+    pub address: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinerCredit {
+    pub miner_id: String,
+    pub shares: u64,
+    pub difficulty: u64,
+    pub amount: f64,
+}
+
+#[async_trait]
+pub trait ShareStore: Send + Sync {
+    /// Appends an accepted share and returns its sequence number
+    async fn append(&self, miner_id: &str, difficulty: u64) -> Result<u64>;
+    /// Shares up to and including `last_sequence`, newest first, until their summed
+    /// difficulty reaches `window_difficulty`
+    async fn window(&self, last_sequence: u64, window_difficulty: u64) -> Result<Vec<WindowShare>>;
+    /// Drops shares older than `sequence`
+    async fn prune_before(&self, sequence: u64) -> Result<()>;
+}
+
+#[async_trait]
+pub trait PayoutRecordStore: Send + Sync {
+    async fn write_records(&self, records: &[PayoutRecord]) -> Result<()>;
+    async fn mark_paid(&self, record_ids: &[String], transaction_hash: &str) -> Result<()>;
+}
+
+#[async_trait]
+pub trait PayoutSender: Send + Sync {
+    /// Sends one transaction paying every transfer; returns its hash
+    async fn send_batch(&self, transfers: &[PayoutTransfer]) -> Result<String>;
+}
+
+#[async_trait]
+impl PayoutRecordStore for Database {
+    async fn write_records(&self, records: &[PayoutRecord]) -> Result<()> {
+        self.insert_payout_records(records).await
+    }
+
+    async fn mark_paid(&self, record_ids: &[String], transaction_hash: &str) -> Result<()> {
+        self.mark_payout_records_paid(record_ids, transaction_hash).await
+    }
+}
+
+#[derive(Debug, Default)]
+struct ShareLog {
+    next_sequence: u64,
+    shares: VecDeque<WindowShare>,
+}
+
+// Share window kept in process memory; history is lost on restart
+#[derive(Debug, Default)]
+pub struct InMemoryShareStore {
+    log: Mutex<ShareLog>,
+}
+
+impl InMemoryShareStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.lock().shares.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl ShareStore for InMemoryShareStore {
+    async fn append(&self, miner_id: &str, difficulty: u64) -> Result<u64> {
+        let mut log = self.log.lock();
+        let sequence = log.next_sequence;
+        log.next_sequence += 1;
+        log.shares.push_back(WindowShare {
+            sequence,
+            miner_id: miner_id.to_string(),
+            difficulty,
+            received_at: SystemTime::now(),
+        });
+        Ok(sequence)
+    }
+
+    async fn window(&self, last_sequence: u64, window_difficulty: u64) -> Result<Vec<WindowShare>> {
+        let log = self.log.lock();
+        let mut window = Vec::new();
+        let mut covered = 0u64;
+
+        for share in log.shares.iter().rev().filter(|share| share.sequence <= last_sequence) {
+            if covered >= window_difficulty {
+                break;
+            }
+            covered = covered.saturating_add(share.difficulty);
+            window.push(share.clone());
+        }
+        Ok(window)
+    }
+
+    async fn prune_before(&self, sequence: u64) -> Result<()> {
+        self.log.lock().shares.retain(|share| share.sequence >= sequence);
+        Ok(())
+    }
+}
+
+// Posts payout batches to a Nockchain wallet RPC
+pub struct NockchainPayoutSender {
+    rpc_url: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct PayoutBatchResponse {
+    transaction_hash: String,
+}
+
+impl NockchainPayoutSender {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc_url,
+            client: reqwest::Client::builder()
+                .timeout(PAYOUT_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+}
+
+#[async_trait]
+impl PayoutSender for NockchainPayoutSender {
+    async fn send_batch(&self, transfers: &[PayoutTransfer]) -> Result<String> {
+        let response: PayoutBatchResponse = self.client
+            .post(format!("{}/payouts/batch", self.rpc_url.trim_end_matches('/')))
+            .json(&serde_json::json!({ "transfers": transfers }))
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected payout batch")?
+            .json()
+            .await
+            .context("Malformed payout batch response")?;
+
+        Ok(response.transaction_hash)
+    }
+}
+
+/// Splits a block reward across a newest-first window, weighted by share difficulty.
+/// The oldest share is only counted up to the window boundary. A window that is not yet
+/// full splits the whole reward across the shares it has.
+pub fn split_reward(shares: &[WindowShare], window_difficulty: u64, reward: f64) -> Vec<MinerCredit> {
+    let mut weights: BTreeMap<&str, (u64, u64)> = BTreeMap::new(); // miner -> (shares, difficulty)
+    let mut covered = 0u64;
+
+    for share in shares {
+        let remaining = window_difficulty.saturating_sub(covered);
+        if remaining == 0 {
+            break;
+        }
+        let counted = share.difficulty.min(remaining);
+        covered += counted;
+
+        let entry = weights.entry(&share.miner_id).or_default();
+        entry.0 += 1;
+        entry.1 += counted;
+    }
+
+    if covered == 0 {
+        return Vec::new();
+    }
+
+    weights
+        .into_iter()
+        .map(|(miner_id, (shares, difficulty))| MinerCredit {
+            miner_id: miner_id.to_string(),
+            shares,
+            difficulty,
+            amount: reward * difficulty as f64 / covered as f64,
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone)]
+struct MinerTotals {
+    total_earned: f64,
+    total_paid: f64,
+    last_payout: Option<SystemTime>,
+}
+
+struct PplnsState {
+    settings: PplnsSettings,
+    share_store: Arc<dyn ShareStore>,
+    record_store: Arc<dyn PayoutRecordStore>,
+    sender: Arc<dyn PayoutSender>,
+    metrics: Arc<Metrics>,
+    is_running: AtomicBool,
+    network_difficulty: AtomicU64,
+    unpaid: Mutex<Vec<PayoutRecord>>,
+    totals: Mutex<HashMap<String, MinerTotals>>,
+    transactions_sent: AtomicU64,
+    total_paid: Mutex<f64>,
+    // Serialises batch runs so a record is never paid twice
+    batch_lock: tokio::sync::Mutex<()>,
+}
+
+pub struct PplnsPayoutEngine {
+    state: Arc<PplnsState>,
+}
+
+impl PplnsPayoutEngine {
+    pub fn new(
+        settings: PplnsSettings,
+        share_store: Arc<dyn ShareStore>,
+        record_store: Arc<dyn PayoutRecordStore>,
+        sender: Arc<dyn PayoutSender>,
+        metrics: Arc<Metrics>,
+    ) -> Self {
+        Self {
+            state: Arc::new(PplnsState {
+                network_difficulty: AtomicU64::new(settings.initial_network_difficulty),
+                settings,
+                share_store,
+                record_store,
+                sender,
+                metrics,
+                is_running: AtomicBool::new(false),
+                unpaid: Mutex::new(Vec::new()),
+                totals: Mutex::new(HashMap::new()),
+                transactions_sent: AtomicU64::new(0),
+                total_paid: Mutex::new(0.0),
+                batch_lock: tokio::sync::Mutex::new(()),
+            }),
+        }
+    }
+
+    pub fn set_network_difficulty(&self, difficulty: u64) {
+        self.state.network_difficulty.store(difficulty, Ordering::Relaxed);
+    }
+
+    /// Difficulty the window covers: the configured multiple of the shares expected per block
+    pub fn window_difficulty(&self) -> u64 {
+        let network_difficulty = self.state.network_difficulty.load(Ordering::Relaxed);
+        ((network_difficulty as f64 * self.state.settings.window_factor).ceil() as u64).max(1)
+    }
+
+    /// Credits the block's reward to the window ending at `block_sequence`. Shares appended
+    /// after the block, including ones racing the block announcement, count towards the next one.
+    pub async fn distribute_block(&self, block_sequence: u64, block_parent_hash: &str) -> Result<Vec<PayoutRecord>> {
+        let state = &self.state;
+        let window_difficulty = self.window_difficulty();
+        let window = state.share_store.window(block_sequence, window_difficulty).await?;
+
+        let net_reward = state.settings.block_reward * (1.0 - state.settings.pool_fee);
+        let created_at = SystemTime::now();
+        let records: Vec<PayoutRecord> = split_reward(&window, window_difficulty, net_reward)
+            .into_iter()
+            .map(|credit| PayoutRecord {
+                id: Uuid::new_v4().to_string(),
+                block_sequence,
+                block_parent_hash: block_parent_hash.to_string(),
+                miner_id: credit.miner_id,
+                shares: credit.shares,
+                difficulty: credit.difficulty,
+                amount: credit.amount,
+                created_at,
+                transaction_hash: None,
+            })
+            .collect();
+
+        state.record_store.write_records(&records).await?;
+
+        {
+            let mut totals = state.totals.lock();
+            for record in &records {
+                totals.entry(record.miner_id.clone()).or_default().total_earned += record.amount;
+            }
+        }
+        state.unpaid.lock().extend(records.iter().cloned());
+
+        // Later blocks end later, so nothing before this window is needed again
+        if let Some(oldest) = window.last() {
+            state.share_store.prune_before(oldest.sequence).await?;
+        }
+
+        tracing::info!(
+            "Distributed {:.4} NOCK for block at share {} across {} miners",
+            net_reward, block_sequence, records.len()
+        );
+        Ok(records)
+    }
+
+    /// Pays every miner whose unpaid credit reaches the minimum payout, in transactions of
+    /// at most `max_transfers_per_transaction` outputs. Returns the transaction hashes.
+    pub async fn run_payout_batch(&self) -> Result<Vec<String>> {
+        let minimum_payout = self.state.settings.minimum_payout;
+        self.pay(|_, amount| amount >= minimum_payout).await
+    }
+
+    async fn pay(&self, due: impl Fn(&str, f64) -> bool) -> Result<Vec<String>> {
+        let state = &self.state;
+        let _batch = state.batch_lock.lock().await;
+
+        // miner -> (amount, record ids)
+        let mut owed: BTreeMap<String, (f64, Vec<String>)> = BTreeMap::new();
+        for record in state.unpaid.lock().iter() {
+            let entry = owed.entry(record.miner_id.clone()).or_default();
+            entry.0 += record.amount;
+            entry.1.push(record.id.clone());
+        }
+        owed.retain(|miner_id, (amount, _)| *amount > 0.0 && due(miner_id, *amount));
+
+        let owed: Vec<_> = owed.into_iter().collect();
+        let mut transaction_hashes = Vec::new();
+        let mut last_error = None;
+
+        for batch in owed.chunks(state.settings.max_transfers_per_transaction.max(1)) {
+            let transfers: Vec<PayoutTransfer> = batch
+                .iter()
+                .map(|(miner_id, (amount, _))| PayoutTransfer { address: miner_id.clone(), amount: *amount })
+                .collect();
+
+            let transaction_hash = match state.sender.send_batch(&transfers).await {
+                Ok(hash) => hash,
+                Err(e) => {
+                    // Records stay unpaid and are retried in the next run
+                    tracing::error!("Payout batch of {} transfers failed: {:#}", transfers.len(), e);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+
+            let record_ids: Vec<String> = batch.iter().flat_map(|(_, (_, ids))| ids.iter().cloned()).collect();
+            self.settle(&transfers, &record_ids);
+
+            // The transaction is already sent, so a failed write must not make the records payable again
+            if let Err(e) = state.record_store.mark_paid(&record_ids, &transaction_hash).await {
+                tracing::error!("Failed to mark payout records paid in {}: {:#}", transaction_hash, e);
+            }
+
+            tracing::info!("Payout transaction {} sent to {} miners", transaction_hash, transfers.len());
+            transaction_hashes.push(transaction_hash);
+        }
+
+        match last_error {
+            Some(e) if transaction_hashes.is_empty() => Err(e),
+            _ => Ok(transaction_hashes),
+        }
+    }
+
+    fn settle(&self, transfers: &[PayoutTransfer], record_ids: &[String]) {
+        let state = &self.state;
+        let now = SystemTime::now();
+
+        state.unpaid.lock().retain(|record| !record_ids.contains(&record.id));
+        {
+            let mut totals = state.totals.lock();
+            for transfer in transfers {
+                let miner = totals.entry(transfer.address.clone()).or_default();
+                miner.total_paid += transfer.amount;
+                miner.last_payout = Some(now);
+                state.metrics.record_payout(transfer.amount);
+            }
+        }
+
+        state.transactions_sent.fetch_add(1, Ordering::Relaxed);
+        *state.total_paid.lock() += transfers.iter().map(|transfer| transfer.amount).sum::<f64>();
+    }
+}
+
+#[async_trait]
+impl PayoutEngine for PplnsPayoutEngine {
+    async fn start(&self) -> Result<()> {
+        if self.state.is_running.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let engine = PplnsPayoutEngine { state: self.state.clone() };
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(engine.state.settings.payout_interval);
+            while engine.state.is_running.load(Ordering::Relaxed) {
+                interval.tick().await;
+                if let Err(e) = engine.run_payout_batch().await {
+                    tracing::error!("PPLNS payout run failed: {:#}", e);
+                }
+            }
+        });
+
+        tracing::info!("PPLNS payout engine started, window {} difficulty", self.window_difficulty());
+        Ok(())
+    }
+
+    async fn stop(&self) {
+        self.state.is_running.store(false, Ordering::Relaxed);
+        tracing::info!("PPLNS payout engine stopped");
+    }
+
+    async fn is_running(&self) -> bool {
+        self.state.is_running.load(Ordering::Relaxed)
+    }
+
+    async fn process_share(&self, share: &Share, is_valid: bool, is_block: bool) -> Result<()> {
+        if !is_valid {
+            return Ok(());
+        }
+
+        let sequence = self.state.share_store.append(&share.miner_id, share.difficulty).await?;
+        if is_block {
+            self.distribute_block(sequence, &share.prev_block_hash).await?;
+        }
+        Ok(())
+    }
+
+    async fn update_network_difficulty(&self, difficulty: u64) {
+        self.set_network_difficulty(difficulty);
+    }
+
+    async fn get_miner_balance(&self, miner_id: &str) -> Option<MinerBalance> {
+        let totals = self.state.totals.lock().get(miner_id).cloned()?;
+        let (confirmed_balance, pending_payouts) = self.state.unpaid
+            .lock()
+            .iter()
+            .filter(|record| record.miner_id == miner_id)
+            .fold((0.0, Vec::new()), |(amount, mut ids), record| {
+                ids.push(record.id.clone());
+                (amount + record.amount, ids)
+            });
+
+        Some(MinerBalance {
+            miner_id: miner_id.to_string(),
+            confirmed_balance,
+            unconfirmed_balance: 0.0,
+            total_earned: totals.total_earned,
+            total_paid: totals.total_paid,
+            last_payout: totals.last_payout,
+            pending_payouts,
+        })
+    }
+
+    async fn get_pending_payouts(&self, miner_id: Option<&str>) -> Vec<Payout> {
+        self.state.unpaid
+            .lock()
+            .iter()
+            .filter(|record| miner_id.map_or(true, |id| record.miner_id == id))
+            .map(|record| Payout {
+                id: record.id.clone(),
+                miner_id: record.miner_id.clone(),
+                amount: record.amount,
+                transaction_hash: None,
+                status: PayoutStatus::Pending,
+                created_at: record.created_at,
+                completed_at: None,
+                failure_reason: None,
+            })
+            .collect()
+    }
+
+    async fn get_payout_statistics(&self) -> (u64, f64) {
+        (self.state.transactions_sent.load(Ordering::Relaxed), *self.state.total_paid.lock())
+    }
+
+    async fn force_payout(&self, miner_id: &str) -> Result<()> {
+        self.pay(|id, _| id == miner_id).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryRecordStore {
+        records: Mutex<Vec<PayoutRecord>>,
+    }
+
+    #[async_trait]
+    impl PayoutRecordStore for MemoryRecordStore {
+        async fn write_records(&self, records: &[PayoutRecord]) -> Result<()> {
+            self.records.lock().extend(records.iter().cloned());
+            Ok(())
+        }
+
+        async fn mark_paid(&self, record_ids: &[String], transaction_hash: &str) -> Result<()> {
+            for record in self.records.lock().iter_mut().filter(|record| record_ids.contains(&record.id)) {
+                record.transaction_hash = Some(transaction_hash.to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSender {
+        batches: Mutex<Vec<Vec<PayoutTransfer>>>,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl PayoutSender for RecordingSender {
+        async fn send_batch(&self, transfers: &[PayoutTransfer]) -> Result<String> {
+            if self.failing.load(Ordering::Relaxed) {
+                anyhow::bail!("node unavailable");
+            }
+            let mut batches = self.batches.lock();
+            batches.push(transfers.to_vec());
+            Ok(format!("tx-{}", batches.len()))
+        }
+    }
+
+    struct Harness {
+        engine: PplnsPayoutEngine,
+        shares: Arc<InMemoryShareStore>,
+        records: Arc<MemoryRecordStore>,
+        sender: Arc<RecordingSender>,
+    }
+
+    // Window of 2 x 1_000 difficulty, no fee and a 1_000 NOCK reward to keep the arithmetic exact
+    fn settings() -> PplnsSettings {
+        PplnsSettings {
+            window_factor: 2.0,
+            block_reward: 1_000.0,
+            pool_fee: 0.0,
+            minimum_payout: 100.0,
+            payout_interval: Duration::from_secs(3600),
+            max_transfers_per_transaction: 2,
+            initial_network_difficulty: 1_000,
+        }
+    }
+
+    fn harness(settings: PplnsSettings) -> Harness {
+        let shares = Arc::new(InMemoryShareStore::new());
+        let records = Arc::new(MemoryRecordStore::default());
+        let sender = Arc::new(RecordingSender::default());
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let engine = PplnsPayoutEngine::new(settings, shares.clone(), records.clone(), sender.clone(), metrics);
+        Harness { engine, shares, records, sender }
+    }
+
+    fn share(miner_id: &str, difficulty: u64) -> Share {
+        Share {
+            miner_id: miner_id.to_string(),
+            nonce: "00000000".to_string(),
+            prev_block_hash: "ab".repeat(32),
+            timestamp: 1_700_000_000u32.into(),
+            difficulty,
+        }
+    }
+
+    async fn submit(engine: &PplnsPayoutEngine, miner_id: &str, difficulty: u64, count: usize) {
+        for _ in 0..count {
+            engine.process_share(&share(miner_id, difficulty), true, false).await.unwrap();
+        }
+    }
+
+    async fn find_block(engine: &PplnsPayoutEngine, miner_id: &str, difficulty: u64) {
+        engine.process_share(&share(miner_id, difficulty), true, true).await.unwrap();
+    }
+
+    fn amounts(records: &[PayoutRecord]) -> HashMap<String, f64> {
+        let mut amounts = HashMap::new();
+        for record in records {
+            *amounts.entry(record.miner_id.clone()).or_insert(0.0) += record.amount;
+        }
+        amounts
+    }
+
+    fn window_share(sequence: u64, miner_id: &str, difficulty: u64) -> WindowShare {
+        WindowShare {
+            sequence,
+            miner_id: miner_id.to_string(),
+            difficulty,
+            received_at: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_split_reward_clips_oldest_share_to_window() {
+        let shares = vec![
+            window_share(3, "alice", 600),
+            window_share(2, "bob", 300),
+            window_share(1, "carol", 500), // only 100 of it fits
+            window_share(0, "dave", 500),
+        ];
+
+        let credits = split_reward(&shares, 1_000, 1_000.0);
+        let by_miner: HashMap<_, _> = credits.iter().map(|c| (c.miner_id.as_str(), c)).collect();
+
+        assert_eq!(credits.len(), 3);
+        assert_eq!(by_miner["alice"].amount, 600.0);
+        assert_eq!(by_miner["bob"].amount, 300.0);
+        assert_eq!(by_miner["carol"].amount, 100.0);
+        assert_eq!(by_miner["carol"].difficulty, 100);
+    }
+
+    #[test]
+    fn test_split_reward_partial_window_pays_whole_reward() {
+        let shares = vec![window_share(1, "alice", 300), window_share(0, "bob", 100)];
+        let credits = split_reward(&shares, 2_000, 1_000.0);
+
+        assert_eq!(credits.iter().map(|c| c.amount).sum::<f64>(), 1_000.0);
+        assert_eq!(credits[0].amount, 750.0);
+        assert!(split_reward(&[], 2_000, 1_000.0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_reward_split_by_window_difficulty() {
+        let h = harness(settings());
+        submit(&h.engine, "alice", 100, 15).await;
+        submit(&h.engine, "bob", 100, 4).await;
+        find_block(&h.engine, "bob", 100).await;
+
+        let records = h.records.records.lock().clone();
+        let paid = amounts(&records);
+        assert_eq!(paid["alice"], 750.0);
+        assert_eq!(paid["bob"], 250.0);
+        assert!(records.iter().all(|record| record.block_sequence == 19));
+    }
+
+    #[tokio::test]
+    async fn test_miner_joining_mid_window_paid_for_own_shares_only() {
+        let h = harness(settings());
+        // Alice fills the window before Bob connects; her oldest shares fall out of it
+        submit(&h.engine, "alice", 100, 25).await;
+        submit(&h.engine, "bob", 100, 4).await;
+        find_block(&h.engine, "bob", 100).await;
+
+        let paid = amounts(&h.records.records.lock());
+        assert_eq!(paid["alice"], 750.0);
+        assert_eq!(paid["bob"], 250.0);
+        // Only the window and the shares after it are kept
+        assert_eq!(h.shares.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_blocks_each_pay_their_own_window() {
+        let h = harness(settings());
+        submit(&h.engine, "alice", 100, 19).await;
+        find_block(&h.engine, "alice", 100).await;
+
+        // A second block right after: Bob's shares push the oldest of Alice's out of the window
+        submit(&h.engine, "bob", 100, 4).await;
+        find_block(&h.engine, "bob", 100).await;
+
+        let records = h.records.records.lock().clone();
+        let first: Vec<_> = records.iter().filter(|r| r.block_sequence == 19).cloned().collect();
+        let second: Vec<_> = records.iter().filter(|r| r.block_sequence == 24).cloned().collect();
+
+        assert_eq!(amounts(&first)["alice"], 1_000.0);
+        assert_eq!(amounts(&second)["alice"], 750.0);
+        assert_eq!(amounts(&second)["bob"], 250.0);
+        assert_eq!(amounts(&records)["alice"], 1_750.0);
+    }
+
+    #[tokio::test]
+    async fn test_shares_after_block_count_towards_next_block() {
+        let h = harness(settings());
+        submit(&h.engine, "alice", 100, 9).await;
+        let block_sequence = h.shares.append("alice", 100).await.unwrap();
+
+        // Shares arriving before the block is distributed stay out of its window
+        h.shares.append("bob", 100).await.unwrap();
+        h.shares.append("bob", 100).await.unwrap();
+
+        let records = h.engine.distribute_block(block_sequence, "parent").await.unwrap();
+        assert_eq!(amounts(&records), HashMap::from([("alice".to_string(), 1_000.0)]));
+
+        find_block(&h.engine, "alice", 100).await;
+        let next = amounts(&h.records.records.lock()[1..]);
+        assert_eq!(next["bob"], 1_000.0 * 200.0 / 1_300.0);
+    }
+
+    #[tokio::test]
+    async fn test_window_follows_network_difficulty() {
+        let h = harness(settings());
+        h.engine.update_network_difficulty(500).await;
+        assert_eq!(h.engine.window_difficulty(), 1_000);
+
+        submit(&h.engine, "alice", 100, 10).await;
+        submit(&h.engine, "bob", 100, 9).await;
+        find_block(&h.engine, "bob", 100).await;
+
+        let paid = amounts(&h.records.records.lock());
+        assert!(!paid.contains_key("alice"));
+        assert_eq!(paid["bob"], 1_000.0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_shares_are_ignored() {
+        let h = harness(settings());
+        h.engine.process_share(&share("alice", 100), false, true).await.unwrap();
+
+        assert!(h.shares.is_empty());
+        assert!(h.records.records.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_payouts_batched_above_minimum() {
+        let h = harness(settings());
+        // 500, 300, 150 and 50 NOCK; the last is below the 100 NOCK minimum
+        submit(&h.engine, "alice", 100, 10).await;
+        submit(&h.engine, "bob", 100, 6).await;
+        submit(&h.engine, "carol", 100, 3).await;
+        find_block(&h.engine, "dave", 100).await;
+
+        let hashes = h.engine.run_payout_batch().await.unwrap();
+        assert_eq!(hashes, vec!["tx-1", "tx-2"]);
+
+        let batches = h.sender.batches.lock().clone();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].iter().map(|t| t.address.as_str()).collect::<Vec<_>>(), ["alice", "bob"]);
+        assert_eq!(batches[1], vec![PayoutTransfer { address: "carol".to_string(), amount: 150.0 }]);
+
+        let records = h.records.records.lock().clone();
+        assert!(records.iter().filter(|r| r.miner_id != "dave").all(|r| r.transaction_hash.is_some()));
+
+        let dave = h.engine.get_miner_balance("dave").await.unwrap();
+        assert_eq!(dave.confirmed_balance, 50.0);
+        assert_eq!(h.engine.get_pending_payouts(None).await.len(), 1);
+
+        let alice = h.engine.get_miner_balance("alice").await.unwrap();
+        assert_eq!((alice.confirmed_balance, alice.total_paid), (0.0, 500.0));
+        assert_eq!(h.engine.get_payout_statistics().await, (2, 950.0));
+
+        // Paid records are not paid again
+        assert!(h.engine.run_payout_batch().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_retried() {
+        let h = harness(settings());
+        submit(&h.engine, "alice", 100, 19).await;
+        find_block(&h.engine, "alice", 100).await;
+
+        h.sender.failing.store(true, Ordering::Relaxed);
+        assert!(h.engine.run_payout_batch().await.is_err());
+        assert_eq!(h.engine.get_miner_balance("alice").await.unwrap().confirmed_balance, 1_000.0);
+
+        h.sender.failing.store(false, Ordering::Relaxed);
+        assert_eq!(h.engine.run_payout_batch().await.unwrap().len(), 1);
+        assert_eq!(h.engine.get_miner_balance("alice").await.unwrap().confirmed_balance, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_force_payout_ignores_minimum() {
+        let h = harness(settings());
+        submit(&h.engine, "alice", 100, 19).await;
+        find_block(&h.engine, "bob", 100).await;
+
+        h.engine.force_payout("bob").await.unwrap();
+        assert_eq!(
+            h.sender.batches.lock().clone(),
+            vec![vec![PayoutTransfer { address: "bob".to_string(), amount: 50.0 }]]
+        );
+        assert_eq!(h.engine.get_miner_balance("alice").await.unwrap().confirmed_balance, 950.0);
+    }
+}
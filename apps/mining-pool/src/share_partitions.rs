@@ -0,0 +1,380 @@
+// Monthly partition management for the shares table
+// `shares` is range-partitioned on `submitted_at`, one partition per calendar month named
+// shares_YYYY_MM. An hourly job keeps this month's and next month's partitions in place,
+// reindexes the month that just stopped taking writes and drops months past retention.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate, Utc};
+use parking_lot::Mutex;
+use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::metrics::Metrics;
+
+pub const SHARES_TABLE: &str = "shares";
+
+pub const PARTITION_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MonthPartition {
+    pub year: i32,
+    pub month: u32, // 1-12
+}
+
+impl MonthPartition {
+    pub fn containing(date: NaiveDate) -> Self {
+        Self { year: date.year(), month: date.month() }
+    }
+
+    pub fn next(self) -> Self {
+        if self.month == 12 {
+            Self { year: self.year + 1, month: 1 }
+        } else {
+            Self { year: self.year, month: self.month + 1 }
+        }
+    }
+
+    pub fn months_before(self, months: u32) -> Self {
+        let index = self.year * 12 + self.month as i32 - 1 - months as i32;
+        Self { year: index.div_euclid(12), month: index.rem_euclid(12) as u32 + 1 }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{}_{:04}_{:02}", SHARES_TABLE, self.year, self.month)
+    }
+
+    /// Inverse of `name`; other children of the table, such as a default partition, are `None`
+    pub fn parse(name: &str) -> Option<Self> {
+        let suffix = name.strip_prefix(SHARES_TABLE)?.strip_prefix('_')?;
+        let (year, month) = suffix.split_once('_')?;
+        if year.len() != 4 || month.len() != 2 {
+            return None;
+        }
+        let partition = Self { year: year.parse().ok()?, month: month.parse().ok()? };
+        (1..=12).contains(&partition.month).then_some(partition)
+    }
+
+    /// First day of the month, the partition's inclusive lower bound
+    pub fn start(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.year, self.month, 1).expect("Month is always 1-12")
+    }
+
+    /// First day of the following month, the partition's exclusive upper bound
+    pub fn end(&self) -> NaiveDate {
+        self.next().start()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionPlan {
+    pub create: Vec<MonthPartition>,
+    /// Last month's partition, which no longer receives shares
+    pub rotated_out: MonthPartition,
+    pub drop: Vec<MonthPartition>,
+}
+
+/// Keeps the current and next month and the `retention_months` before the current one
+pub fn plan_partitions(today: NaiveDate, existing: &[String], retention_months: u32) -> PartitionPlan {
+    let current = MonthPartition::containing(today);
+    let existing: Vec<MonthPartition> = existing.iter().filter_map(|name| MonthPartition::parse(name)).collect();
+    let oldest_kept = current.months_before(retention_months);
+
+    let mut drop: Vec<MonthPartition> = existing.iter().copied().filter(|partition| *partition < oldest_kept).collect();
+    drop.sort();
+
+    PartitionPlan {
+        create: [current, current.next()]
+            .into_iter()
+            .filter(|partition| !existing.contains(partition))
+            .collect(),
+        rotated_out: current.months_before(1),
+        drop,
+    }
+}
+
+#[async_trait]
+pub trait PartitionStore: Send + Sync {
+    /// Names of every partition attached to the shares table
+    async fn list_partitions(&self) -> Result<Vec<String>>;
+    async fn create_partition(&self, partition: &MonthPartition) -> Result<()>;
+    async fn row_count(&self, partition: &str) -> Result<u64>;
+    async fn reindex(&self, partition: &str) -> Result<()>;
+    async fn drop_partition(&self, partition: &str) -> Result<()>;
+    /// Total on-disk size, indexes included, per partition
+    async fn partition_sizes(&self) -> Result<Vec<(String, u64)>>;
+}
+
+pub struct PgPartitionStore {
+    pool: PgPool,
+}
+
+impl PgPartitionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Small dedicated pool; maintenance statements can hold a connection for a while
+    pub fn connect_lazy(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_lazy(database_url)
+            .context("Invalid database URL for partition maintenance")?;
+        Ok(Self::new(pool))
+    }
+}
+
+// Partition names only ever come from MonthPartition::name or the catalog, so quoting them
+// as identifiers is enough to keep them out of the statement text
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+const PARTITION_SIZES_QUERY: &str = "SELECT child.relname::text, pg_total_relation_size(child.oid) \
+     FROM pg_inherits \
+     JOIN pg_class parent ON parent.oid = pg_inherits.inhparent \
+     JOIN pg_class child ON child.oid = pg_inherits.inhrelid \
+     WHERE parent.relname = 'shares' \
+     ORDER BY child.relname";
+
+#[async_trait]
+impl PartitionStore for PgPartitionStore {
+    async fn list_partitions(&self) -> Result<Vec<String>> {
+        Ok(self.partition_sizes().await?.into_iter().map(|(name, _)| name).collect())
+    }
+
+    async fn create_partition(&self, partition: &MonthPartition) -> Result<()> {
+        let statement = format!(
+            "CREATE TABLE IF NOT EXISTS {} PARTITION OF {} FOR VALUES FROM ('{} 00:00:00+00') TO ('{} 00:00:00+00')",
+            quote_identifier(&partition.name()),
+            SHARES_TABLE,
+            partition.start(),
+            partition.end(),
+        );
+        // DDL goes over the simple query protocol; it cannot be prepared
+        self.pool.execute(statement.as_str()).await?;
+        Ok(())
+    }
+
+    async fn row_count(&self, partition: &str) -> Result<u64> {
+        let statement = format!("SELECT COUNT(*) FROM {}", quote_identifier(partition));
+        let count: i64 = sqlx::query_scalar(&statement).fetch_one(&self.pool).await?;
+        Ok(count.max(0) as u64)
+    }
+
+    async fn reindex(&self, partition: &str) -> Result<()> {
+        // CONCURRENTLY cannot run inside a transaction, so this is its own statement
+        let statement = format!("REINDEX TABLE CONCURRENTLY {}", quote_identifier(partition));
+        self.pool.execute(statement.as_str()).await?;
+        Ok(())
+    }
+
+    async fn drop_partition(&self, partition: &str) -> Result<()> {
+        let statement = format!("DROP TABLE IF EXISTS {}", quote_identifier(partition));
+        self.pool.execute(statement.as_str()).await?;
+        Ok(())
+    }
+
+    async fn partition_sizes(&self) -> Result<Vec<(String, u64)>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(PARTITION_SIZES_QUERY).fetch_all(&self.pool).await?;
+        Ok(rows.into_iter().map(|(name, bytes)| (name, bytes.max(0) as u64)).collect())
+    }
+}
+
+pub struct SharePartitionMaintainer {
+    store: Arc<dyn PartitionStore>,
+    metrics: Arc<Metrics>,
+    retention_months: u32,
+    last_reindexed: Mutex<Option<MonthPartition>>,
+}
+
+impl SharePartitionMaintainer {
+    pub fn new(store: Arc<dyn PartitionStore>, metrics: Arc<Metrics>, retention_months: u32) -> Self {
+        Self {
+            store,
+            metrics,
+            retention_months,
+            last_reindexed: Mutex::new(None),
+        }
+    }
+
+    /// Runs every PARTITION_CHECK_INTERVAL until the task is dropped. Each run is idempotent,
+    /// so a failed run is logged and simply retried on the next tick.
+    pub async fn start(&self) -> Result<()> {
+        let mut interval = tokio::time::interval(PARTITION_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.run_once(Utc::now().date_naive()).await {
+                warn!("Share partition maintenance failed: {:#}", e);
+            }
+        }
+    }
+
+    pub async fn run_once(&self, today: NaiveDate) -> Result<PartitionPlan> {
+        let existing = self.store.list_partitions().await.context("Failed to list share partitions")?;
+        let plan = plan_partitions(today, &existing, self.retention_months);
+
+        for partition in &plan.create {
+            self.store
+                .create_partition(partition)
+                .await
+                .with_context(|| format!("Failed to create partition {}", partition.name()))?;
+            let rows = self.store.row_count(&partition.name()).await?;
+            info!(
+                "Created share partition {} for {} to {} ({} rows)",
+                partition.name(), partition.start(), partition.end(), rows
+            );
+        }
+
+        // Reindex last month once, after it has taken its final writes
+        let rotated_out = plan.rotated_out.name();
+        let already_reindexed = *self.last_reindexed.lock() == Some(plan.rotated_out);
+        if !already_reindexed && existing.contains(&rotated_out) {
+            let rows = self.store.row_count(&rotated_out).await?;
+            self.store
+                .reindex(&rotated_out)
+                .await
+                .with_context(|| format!("Failed to reindex partition {}", rotated_out))?;
+            *self.last_reindexed.lock() = Some(plan.rotated_out);
+            info!("Reindexed share partition {} ({} rows)", rotated_out, rows);
+        }
+
+        for partition in &plan.drop {
+            let name = partition.name();
+            let rows = self.store.row_count(&name).await?;
+            self.store
+                .drop_partition(&name)
+                .await
+                .with_context(|| format!("Failed to drop partition {}", name))?;
+            info!(
+                "Dropped share partition {} past {} month retention ({} rows)",
+                name, self.retention_months, rows
+            );
+        }
+
+        self.metrics.set_partition_sizes(&self.store.partition_sizes().await?);
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn month(year: i32, month: u32) -> MonthPartition {
+        MonthPartition { year, month }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Partition name -> row count
+    #[derive(Default)]
+    struct StubPartitionStore {
+        partitions: Mutex<BTreeMap<String, u64>>,
+        reindexed: Mutex<Vec<String>>,
+    }
+
+    impl StubPartitionStore {
+        fn with_months(months: &[MonthPartition]) -> Self {
+            let store = Self::default();
+            for partition in months {
+                store.partitions.lock().insert(partition.name(), 1_000);
+            }
+            store
+        }
+
+        fn names(&self) -> Vec<String> {
+            self.partitions.lock().keys().cloned().collect()
+        }
+    }
+
+    #[async_trait]
+    impl PartitionStore for StubPartitionStore {
+        async fn list_partitions(&self) -> Result<Vec<String>> {
+            Ok(self.names())
+        }
+
+        async fn create_partition(&self, partition: &MonthPartition) -> Result<()> {
+            self.partitions.lock().entry(partition.name()).or_insert(0);
+            Ok(())
+        }
+
+        async fn row_count(&self, partition: &str) -> Result<u64> {
+            self.partitions.lock().get(partition).copied().context("No such partition")
+        }
+
+        async fn reindex(&self, partition: &str) -> Result<()> {
+            self.reindexed.lock().push(partition.to_string());
+            Ok(())
+        }
+
+        async fn drop_partition(&self, partition: &str) -> Result<()> {
+            self.partitions.lock().remove(partition);
+            Ok(())
+        }
+
+        async fn partition_sizes(&self) -> Result<Vec<(String, u64)>> {
+            Ok(self.partitions.lock().iter().map(|(name, rows)| (name.clone(), rows * 128)).collect())
+        }
+    }
+
+    #[test]
+    fn test_month_partition_names_and_bounds() {
+        let december = month(2026, 12);
+        assert_eq!(december.name(), "shares_2026_12");
+        assert_eq!(MonthPartition::parse("shares_2026_12"), Some(december));
+        assert_eq!(december.next(), month(2027, 1));
+        assert_eq!((december.start(), december.end()), (date(2026, 12, 1), date(2027, 1, 1)));
+        assert_eq!(month(2026, 3).months_before(6), month(2025, 9));
+
+        assert_eq!(MonthPartition::parse("shares_default"), None);
+        assert_eq!(MonthPartition::parse("shares_2026_13"), None);
+        assert_eq!(MonthPartition::parse("payouts_2026_01"), None);
+    }
+
+    #[test]
+    fn test_plan_creates_next_month_and_drops_past_retention() {
+        let existing: Vec<String> = (2..=10).map(|m| month(2026, m).name()).chain(["shares_default".to_string()]).collect();
+
+        let plan = plan_partitions(date(2026, 10, 16), &existing, 6);
+        assert_eq!(plan.create, vec![month(2026, 11)]);
+        assert_eq!(plan.rotated_out, month(2026, 9));
+        // April through October stay: the current month plus six before it
+        assert_eq!(plan.drop, vec![month(2026, 2), month(2026, 3)]);
+
+        let fresh = plan_partitions(date(2027, 1, 1), &[], 6);
+        assert_eq!(fresh.create, vec![month(2027, 1), month(2027, 2)]);
+        assert!(fresh.drop.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_rotates_partitions_and_reports_sizes() {
+        let months: Vec<MonthPartition> = (3..=10).map(|m| month(2026, m)).collect();
+        let store = Arc::new(StubPartitionStore::with_months(&months));
+        let metrics = Arc::new(Metrics::new().unwrap());
+        let maintainer = SharePartitionMaintainer::new(store.clone(), metrics.clone(), 6);
+
+        let plan = maintainer.run_once(date(2026, 10, 1)).await.unwrap();
+        assert_eq!(plan.create, vec![month(2026, 11)]);
+        assert!(store.names().contains(&"shares_2026_11".to_string()));
+        assert!(!store.names().contains(&"shares_2026_03".to_string()));
+        assert_eq!(*store.reindexed.lock(), vec!["shares_2026_09".to_string()]);
+
+        // Later runs in the same month change nothing and do not reindex again
+        let again = maintainer.run_once(date(2026, 10, 2)).await.unwrap();
+        assert!(again.create.is_empty() && again.drop.is_empty());
+        assert_eq!(store.reindexed.lock().len(), 1);
+
+        let output = metrics.render().unwrap();
+        assert!(output.contains("database_partition_size_bytes{partition=\"shares_2026_10\"} 128000"));
+        assert!(output.contains("database_partition_size_bytes{partition=\"shares_2026_11\"} 0"));
+        assert!(!output.contains("shares_2026_03"));
+    }
+}
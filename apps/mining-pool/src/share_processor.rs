@@ -2,19 +2,22 @@
 // Designed for sub-millisecond share validation and processing
 
 use anyhow::Result;
+use async_trait::async_trait;
 use std::sync::{Arc, atomic::{AtomicBool, AtomicU64, Ordering}};
 use tokio::sync::{mpsc, RwLock};
 use dashmap::DashMap;
 use parking_lot::Mutex;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use sha2::{Sha256, Digest};
-use blake3;
 
 use crate::{
     config::Config,
     database::Database,
+    maintenance::{MaintenanceQueue, MaintenanceStatus},
     metrics::Metrics,
-    mining::{Share, ShareStatus, ShareValidationResult, Miner},
+    mining::{components::ShareProcessor, Share, ShareStatus, ShareValidationResult, Miner},
+    share_validator::{
+        difficulty_of_hash, pow_hash, MiningHeader, ShareValidationOutcome, ShareValidator, HEADER_VERSION,
+    },
 };
 
 // Share processing statistics
@@ -68,7 +71,7 @@ impl MinerRateLimit {
     }
 }
 
-pub struct ShareProcessor {
+pub struct PoolShareProcessor {
     config: Arc<Config>,
     database: Arc<Database>,
     metrics: Arc<Metrics>,
@@ -90,6 +93,9 @@ pub struct ShareProcessor {
     // Block template cache
     current_block_hash: Arc<RwLock<Option<String>>>,
     current_target: Arc<RwLock<Vec<u8>>>,
+
+    // Shares deferred during a maintenance window
+    maintenance_queue: Mutex<MaintenanceQueue<Share>>,
 }
 
 #[derive(Debug)]
@@ -98,7 +104,7 @@ struct ShareProcessingTask {
     response_sender: tokio::sync::oneshot::Sender<Result<ShareValidationResult>>,
 }
 
-impl ShareProcessor {
+impl PoolShareProcessor {
     pub async fn new(
         config: Arc<Config>,
         database: Arc<Database>,
@@ -118,6 +124,7 @@ impl ShareProcessor {
             _queue_receiver: share_receiver,
             current_block_hash: Arc::new(RwLock::new(None)),
             current_target: Arc::new(RwLock::new(vec![0u8; 32])),
+            maintenance_queue: Mutex::new(MaintenanceQueue::new()),
         })
     }
 
@@ -148,7 +155,16 @@ impl ShareProcessor {
 
     pub async fn process_share(&self, share: Share) -> Result<ShareValidationResult> {
         let start_time = Instant::now();
-        
+
+        // Accept but defer shares while the pool is in maintenance
+        if self.is_in_maintenance() {
+            return Ok(self.enqueue_for_maintenance(share, start_time));
+        }
+
+        self.validate_and_store_share(share, start_time).await
+    }
+
+    async fn validate_and_store_share(&self, share: Share, start_time: Instant) -> Result<ShareValidationResult> {
         // Rate limiting check
         if !self.check_rate_limit(&share.miner_id).await {
             return Ok(ShareValidationResult {
@@ -163,6 +179,7 @@ impl ShareProcessor {
         // Duplicate check
         if self.is_duplicate(&share).await {
             self.stats.duplicate_shares.fetch_add(1, Ordering::Relaxed);
+            self.metrics.record_duplicate_share();
             return Ok(ShareValidationResult {
                 status: ShareStatus::Invalid,
                 error: Some("Duplicate share".to_string()),
@@ -217,6 +234,69 @@ impl ShareProcessor {
         Ok(crypto_result)
     }
 
+    pub fn is_in_maintenance(&self) -> bool {
+        self.config
+            .maintenance
+            .as_ref()
+            .map(|window| window.is_active())
+            .unwrap_or(false)
+    }
+
+    fn enqueue_for_maintenance(&self, share: Share, start_time: Instant) -> ShareValidationResult {
+        let miner_id = share.miner_id.clone();
+        let queued = self.maintenance_queue.lock().enqueue(share);
+
+        if !queued {
+            tracing::warn!("Maintenance queue full, rejecting share from miner: {}", miner_id);
+        }
+
+        ShareValidationResult {
+            status: if queued { ShareStatus::Valid } else { ShareStatus::Invalid },
+            error: Some(if queued {
+                "Share queued for processing after maintenance".to_string()
+            } else {
+                "Maintenance queue full".to_string()
+            }),
+            is_block_solution: false,
+            difficulty_achieved: 0,
+            processing_time: start_time.elapsed(),
+        }
+    }
+
+    // Processes shares queued during maintenance once the window has closed
+    pub async fn drain_maintenance_queue(&self) -> Result<usize> {
+        if self.is_in_maintenance() {
+            return Ok(0);
+        }
+
+        let queued_shares = self.maintenance_queue.lock().drain();
+        let drained = queued_shares.len();
+
+        for share in queued_shares {
+            let miner_id = share.miner_id.clone();
+            if let Err(e) = self.validate_and_store_share(share, Instant::now()).await {
+                tracing::error!("Failed to process queued share from {}: {}", miner_id, e);
+            }
+        }
+
+        if drained > 0 {
+            tracing::info!("Drained {} shares queued during maintenance", drained);
+        }
+
+        Ok(drained)
+    }
+
+    pub fn get_maintenance_status(&self) -> MaintenanceStatus {
+        let queue = self.maintenance_queue.lock();
+        MaintenanceStatus {
+            active: self.is_in_maintenance(),
+            window: self.config.maintenance.clone(),
+            queued_shares: queue.len(),
+            queue_capacity: queue.capacity(),
+            rejected_shares: queue.rejected(),
+        }
+    }
+
     async fn check_rate_limit(&self, miner_id: &str) -> bool {
         let rate_limit = self.miner_rate_limits
             .entry(miner_id.to_string())
@@ -320,90 +400,68 @@ impl ShareProcessor {
 
     async fn validate_share_cryptography(&self, share: &Share) -> Result<ShareValidationResult> {
         let start_time = Instant::now();
-        
+        let invalid = |error: String| ShareValidationResult {
+            status: ShareStatus::Invalid,
+            error: Some(error),
+            is_block_solution: false,
+            difficulty_achieved: 0,
+            processing_time: start_time.elapsed(),
+        };
+
         // Construct block header for hashing
-        let block_header = self.construct_block_header(share).await?;
-        
-        // Calculate hash using Blake3 (Nockchain's hash function)
-        let hash = blake3::hash(&block_header);
-        let hash_bytes = hash.as_bytes();
-        
-        // Calculate difficulty achieved
-        let difficulty_achieved = self.calculate_difficulty_from_hash(hash_bytes);
-        
-        // Check if share meets minimum difficulty
-        let is_valid = difficulty_achieved >= share.difficulty;
-        
-        // Check if this is a block solution
+        let (header, nonce) = match self.construct_block_header(share) {
+            Ok(parts) => parts,
+            Err(e) => return Ok(invalid(e.to_string())),
+        };
+        let hash = pow_hash(&header, nonce);
+
+        // Network target decides block solutions; the share's difficulty decides credit
+        let mut network_target = [0u8; 32];
         let current_target = self.current_target.read().await;
-        let is_block_solution = self.hash_meets_target(hash_bytes, &current_target);
-        
-        let status = if is_valid {
-            ShareStatus::Valid
-        } else {
-            ShareStatus::Invalid
+        if current_target.len() == network_target.len() {
+            network_target.copy_from_slice(&current_target);
+        }
+        drop(current_target);
+
+        let validator = ShareValidator::new(share.difficulty);
+        let is_block_solution = match validator.validate_hash(&hash, &network_target) {
+            ShareValidationOutcome::Valid => true,
+            ShareValidationOutcome::PoolDifficulty => false,
+            ShareValidationOutcome::Invalid(reason) => return Ok(invalid(reason)),
         };
 
         Ok(ShareValidationResult {
-            status,
-            error: if is_valid { None } else { Some("Hash does not meet difficulty target".to_string()) },
+            status: ShareStatus::Valid,
+            error: None,
             is_block_solution,
-            difficulty_achieved,
+            difficulty_achieved: difficulty_of_hash(&hash),
             processing_time: start_time.elapsed(),
         })
     }
 
-    async fn construct_block_header(&self, share: &Share) -> Result<Vec<u8>> {
-        // Construct block header according to Nockchain protocol
-        let mut header = Vec::new();
-        
-        // Version (4 bytes)
-        header.extend_from_slice(&1u32.to_le_bytes());
-        
-        // Previous block hash (32 bytes)
-        let prev_hash = hex::decode(&share.prev_block_hash)?;
-        header.extend_from_slice(&prev_hash);
-        
-        // Merkle root (32 bytes) - simplified for now
-        header.extend_from_slice(&[0u8; 32]);
-        
-        // Timestamp (8 bytes)
-        header.extend_from_slice(&share.timestamp.to_le_bytes());
-        
-        // Difficulty target (4 bytes)
-        header.extend_from_slice(&(share.difficulty as u32).to_le_bytes());
-        
-        // Nonce (4 bytes)
-        let nonce_bytes = hex::decode(&share.nonce)?;
-        header.extend_from_slice(&nonce_bytes);
-        
-        Ok(header)
-    }
-
-    fn calculate_difficulty_from_hash(&self, hash: &[u8]) -> u64 {
-        // Calculate difficulty as 2^256 / hash_as_number
-        // Simplified implementation for demonstration
-        let mut difficulty = 0u64;
-        for (i, &byte) in hash.iter().take(8).enumerate() {
-            difficulty |= (byte as u64) << (i * 8);
-        }
-        
-        if difficulty == 0 {
-            return u64::MAX;
-        }
-        
-        u64::MAX / difficulty
-    }
+    fn construct_block_header(&self, share: &Share) -> Result<(MiningHeader, u64)> {
+        let prev_block_hash: [u8; 32] = hex::decode(&share.prev_block_hash)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow::anyhow!("Invalid previous block hash"))?;
 
-    fn hash_meets_target(&self, hash: &[u8], target: &[u8]) -> bool {
-        for (h, t) in hash.iter().zip(target.iter()) {
-            match h.cmp(t) {
-                std::cmp::Ordering::Less => return true,
-                std::cmp::Ordering::Greater => return false,
-                std::cmp::Ordering::Equal => continue,
-            }
+        // Nonces are hex of up to 8 little-endian bytes
+        let nonce_bytes = hex::decode(&share.nonce)?;
+        if nonce_bytes.len() > 8 {
+            anyhow::bail!("Nonce longer than 8 bytes");
         }
-        true
+        let mut nonce = [0u8; 8];
+        nonce[..nonce_bytes.len()].copy_from_slice(&nonce_bytes);
+
+        let header = MiningHeader {
+            version: HEADER_VERSION,
+            prev_block_hash,
+            // Merkle root - simplified for now
+            merkle_root: [0u8; 32],
+            timestamp: share.timestamp,
+            bits: share.difficulty as u32,
+        };
+        Ok((header, u64::from_le_bytes(nonce)))
     }
 
     async fn handle_block_solution(&self, share: &Share, result: &ShareValidationResult) -> Result<()> {
@@ -423,6 +481,7 @@ impl ShareProcessor {
 
     async fn update_statistics(&self, result: &ShareValidationResult, processing_time: Duration) {
         self.stats.total_processed.fetch_add(1, Ordering::Relaxed);
+        self.metrics.record_share(&result.status);
         
         match result.status {
             ShareStatus::Valid => {
@@ -522,4 +581,43 @@ impl ShareProcessor {
         *self.current_block_hash.write().await = Some(block_hash);
         *self.current_target.write().await = target;
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+impl ShareProcessor for PoolShareProcessor {
+    async fn start(&self) -> Result<()> {
+        PoolShareProcessor::start(self).await
+    }
+
+    async fn stop(&self) {
+        PoolShareProcessor::stop(self).await
+    }
+
+    async fn is_running(&self) -> bool {
+        PoolShareProcessor::is_running(self).await
+    }
+
+    async fn process_share(&self, share: Share) -> Result<ShareValidationResult> {
+        PoolShareProcessor::process_share(self, share).await
+    }
+
+    fn is_in_maintenance(&self) -> bool {
+        PoolShareProcessor::is_in_maintenance(self)
+    }
+
+    async fn drain_maintenance_queue(&self) -> Result<usize> {
+        PoolShareProcessor::drain_maintenance_queue(self).await
+    }
+
+    fn get_maintenance_status(&self) -> MaintenanceStatus {
+        PoolShareProcessor::get_maintenance_status(self)
+    }
+
+    async fn get_statistics(&self) -> ShareProcessingStats {
+        PoolShareProcessor::get_statistics(self).await
+    }
+
+    async fn update_block_target(&self, block_hash: String, target: Vec<u8>) {
+        PoolShareProcessor::update_block_target(self, block_hash, target).await
+    }
+}
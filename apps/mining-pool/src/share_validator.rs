@@ -0,0 +1,186 @@
+// Proof-of-work validation for submitted shares
+// A share's hash is compared as a big-endian 256-bit number against two targets: the
+// network target, which makes it a block solution, and the miner's share target, derived
+// from the difficulty the pool assigned to it
+
+pub const HEADER_VERSION: u32 = 1;
+
+// version + prev hash + merkle root + timestamp + bits + nonce
+pub const SERIALIZED_HEADER_LEN: usize = 4 + 32 + 32 + 8 + 4 + 8;
+
+// Target at difficulty 1; a share at difficulty d must hash at or below MAX_TARGET / d
+pub const MAX_TARGET: [u8; 32] = [0xff; 32];
+
+/// Block header fields a miner hashes, minus the nonce it searches over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MiningHeader {
+    pub version: u32,
+    pub prev_block_hash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub timestamp: u64,
+    pub bits: u32,
+}
+
+impl MiningHeader {
+    /// Header bytes in Nockchain's block header layout, little-endian integers, nonce last
+    pub fn serialize(&self, nonce: u64) -> [u8; SERIALIZED_HEADER_LEN] {
+        let mut bytes = [0u8; SERIALIZED_HEADER_LEN];
+        bytes[0..4].copy_from_slice(&self.version.to_le_bytes());
+        bytes[4..36].copy_from_slice(&self.prev_block_hash);
+        bytes[36..68].copy_from_slice(&self.merkle_root);
+        bytes[68..76].copy_from_slice(&self.timestamp.to_le_bytes());
+        bytes[76..80].copy_from_slice(&self.bits.to_le_bytes());
+        bytes[80..88].copy_from_slice(&nonce.to_le_bytes());
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareValidationOutcome {
+    /// Meets the network target: the share solves a block
+    Valid,
+    /// Meets the miner's share target only; credited but not a block
+    PoolDifficulty,
+    Invalid(String),
+}
+
+/// Validates shares for one miner at its assigned share difficulty
+#[derive(Debug, Clone)]
+pub struct ShareValidator {
+    share_difficulty: u64,
+    share_target: [u8; 32],
+}
+
+impl ShareValidator {
+    pub fn new(share_difficulty: u64) -> Self {
+        Self {
+            share_difficulty,
+            share_target: target_for_difficulty(share_difficulty),
+        }
+    }
+
+    pub fn share_target(&self) -> &[u8; 32] {
+        &self.share_target
+    }
+
+    pub fn validate_share(&self, header: &MiningHeader, nonce: u64, target: &[u8; 32]) -> ShareValidationOutcome {
+        self.validate_hash(&pow_hash(header, nonce), target)
+    }
+
+    pub fn validate_hash(&self, hash: &[u8; 32], target: &[u8; 32]) -> ShareValidationOutcome {
+        if self.share_difficulty == 0 {
+            return ShareValidationOutcome::Invalid("Share difficulty must be positive".to_string());
+        }
+
+        // Byte arrays order lexicographically, which is big-endian numeric order
+        if hash <= target {
+            ShareValidationOutcome::Valid
+        } else if hash <= &self.share_target {
+            ShareValidationOutcome::PoolDifficulty
+        } else {
+            ShareValidationOutcome::Invalid(format!(
+                "Hash does not meet share difficulty {}",
+                self.share_difficulty
+            ))
+        }
+    }
+}
+
+/// Blake3 over the serialized header and nonce, the hash connected miners compute.
+/// The protocol's Keccak-256 of a NOCK computation replaces it here once that computation
+/// is available; hashing the header with plain Keccak-256 would match neither.
+pub fn pow_hash(header: &MiningHeader, nonce: u64) -> [u8; 32] {
+    *blake3::hash(&header.serialize(nonce)).as_bytes()
+}
+
+/// MAX_TARGET / difficulty as a big-endian 256-bit number; zero difficulty maps to MAX_TARGET
+pub fn target_for_difficulty(difficulty: u64) -> [u8; 32] {
+    if difficulty <= 1 {
+        return MAX_TARGET;
+    }
+
+    // Schoolbook long division, one byte at a time
+    let divisor = difficulty as u128;
+    let mut target = [0u8; 32];
+    let mut remainder = 0u128;
+    for (quotient, &byte) in target.iter_mut().zip(MAX_TARGET.iter()) {
+        let dividend = (remainder << 8) | byte as u128;
+        *quotient = (dividend / divisor) as u8;
+        remainder = dividend % divisor;
+    }
+    target
+}
+
+/// Difficulty a hash satisfies, from its leading 64 bits
+pub fn difficulty_of_hash(hash: &[u8; 32]) -> u64 {
+    let mut leading = [0u8; 8];
+    leading.copy_from_slice(&hash[..8]);
+    u64::MAX / u64::from_be_bytes(leading).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> MiningHeader {
+        MiningHeader {
+            version: HEADER_VERSION,
+            prev_block_hash: [0xab; 32],
+            merkle_root: [0x11; 32],
+            timestamp: 1_700_000_000,
+            bits: 0x1d00ffff,
+        }
+    }
+
+    #[test]
+    fn test_header_layout() {
+        let bytes = header().serialize(0x0102030405060708);
+
+        assert_eq!(&bytes[0..4], &HEADER_VERSION.to_le_bytes());
+        assert_eq!(&bytes[4..36], &[0xab; 32]);
+        assert_eq!(&bytes[36..68], &[0x11; 32]);
+        assert_eq!(&bytes[68..76], &1_700_000_000u64.to_le_bytes());
+        assert_eq!(&bytes[76..80], &0x1d00ffffu32.to_le_bytes());
+        assert_eq!(&bytes[80..88], &[0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]);
+        assert_ne!(pow_hash(&header(), 1), pow_hash(&header(), 2));
+        assert_eq!(pow_hash(&header(), 1), *blake3::hash(&header().serialize(1)).as_bytes());
+    }
+
+    #[test]
+    fn test_target_for_difficulty() {
+        assert_eq!(target_for_difficulty(1), MAX_TARGET);
+
+        let half = target_for_difficulty(2);
+        assert_eq!(half[0], 0x7f);
+        assert!(half[1..].iter().all(|&byte| byte == 0xff));
+
+        let hard = target_for_difficulty(1 << 16);
+        assert_eq!(&hard[..2], &[0, 0]);
+        assert!(hard[2..].iter().all(|&byte| byte == 0xff));
+        assert_eq!(difficulty_of_hash(&hard), 1 << 16);
+    }
+
+    #[test]
+    fn test_outcome_depends_on_network_and_share_targets() {
+        let mut hash = [0u8; 32];
+        hash[1] = 0x80; // difficulty 512
+
+        let validator = ShareValidator::new(100);
+        assert_eq!(validator.validate_hash(&hash, &MAX_TARGET), ShareValidationOutcome::Valid);
+        assert_eq!(validator.validate_hash(&hash, &[0u8; 32]), ShareValidationOutcome::PoolDifficulty);
+
+        let strict = ShareValidator::new(1_000);
+        assert!(matches!(strict.validate_hash(&hash, &[0u8; 32]), ShareValidationOutcome::Invalid(_)));
+        assert!(matches!(ShareValidator::new(0).validate_hash(&hash, &MAX_TARGET), ShareValidationOutcome::Invalid(_)));
+    }
+
+    #[test]
+    fn test_validate_share_hashes_header() {
+        let validator = ShareValidator::new(1);
+        let hash = pow_hash(&header(), 42);
+
+        // Difficulty 1 accepts every hash; a target equal to the hash makes it a block
+        assert_eq!(validator.validate_share(&header(), 42, &[0u8; 32]), ShareValidationOutcome::PoolDifficulty);
+        assert_eq!(validator.validate_share(&header(), 42, &hash), ShareValidationOutcome::Valid);
+    }
+}
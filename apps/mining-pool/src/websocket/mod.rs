@@ -6,13 +6,24 @@ use axum::extract::ws::{Message, WebSocket};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use std::time::{Duration, Instant};
 use dashmap::DashMap;
 use futures::{sink::SinkExt, stream::StreamExt};
 
-use crate::{AppState, mining::{PoolStats, PerformanceMetrics}};
+use crate::{AppState, block_finder::MiningJob, broadcast_bus::NewBlock, mining::{PoolStats, PerformanceMetrics}};
+
+pub mod stratum_v2;
+
+use stratum_v2::StratumV2Connection;
+
+// Subscription entry recorded for connections receiving mining jobs
+const MINING_JOBS_CHANNEL: &str = "mining_jobs";
+
+// Messages queued for a client before it counts as too slow and is disconnected
+pub const OUTGOING_BUFFER_CAPACITY: usize = 256;
 
 // WebSocket message types for real-time updates
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +34,11 @@ pub enum WebSocketMessage {
     Unsubscribe { channels: Vec<String> },
     Ping,
     Pong,
+
+    // Work distribution
+    MiningSubscribe,
+    MiningJob(MiningJob),
+    NewBlock(NewBlock),
     
     // Pool data updates
     PoolStats(PoolStats),
@@ -112,6 +128,57 @@ pub struct AlertData {
     pub timestamp: u64,
 }
 
+// Bounded queue of messages waiting for a connection's writer task
+#[derive(Debug, Clone)]
+pub struct OutgoingQueue {
+    connection_id: String,
+    sender: mpsc::Sender<WebSocketMessage>,
+    disconnect: CancellationToken,
+}
+
+impl OutgoingQueue {
+    pub fn new(connection_id: String, capacity: usize) -> (Self, mpsc::Receiver<WebSocketMessage>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let queue = Self {
+            connection_id,
+            sender,
+            disconnect: CancellationToken::new(),
+        };
+        (queue, receiver)
+    }
+
+    /// Queues a message without waiting. A full queue means the client is not reading, so
+    /// the connection is disconnected rather than buffering without bound. Returns false
+    /// once the connection is gone.
+    pub fn send(&self, message: WebSocketMessage) -> bool {
+        match self.sender.try_send(message) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                if !self.is_closed() {
+                    tracing::warn!("Disconnecting WebSocket client {}: send buffer full", self.connection_id);
+                    self.close();
+                }
+                false
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// Stops the connection's reader, writer and forwarding tasks
+    pub fn close(&self) {
+        self.disconnect.cancel();
+    }
+
+    /// Resolves once the connection is closed, either by the client or for falling behind
+    pub async fn disconnected(&self) {
+        self.disconnect.cancelled().await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.disconnect.is_cancelled()
+    }
+}
+
 // WebSocket connection manager
 pub struct WebSocketManager {
     connections: Arc<DashMap<String, WebSocketConnection>>,
@@ -242,22 +309,32 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
         manager.add_connection(connection.clone()).await;
 
         let (mut sender, mut receiver) = socket.split();
-        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<WebSocketMessage>();
+        let (tx, mut rx) = OutgoingQueue::new(connection_id.clone(), OUTGOING_BUFFER_CAPACITY);
+        let (frame_tx, mut frame_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
+
+        // Every connected client hears about found blocks
+        forward_new_blocks(state.pool.broadcast_bus.subscribe(), tx.clone());
 
         // Spawn task to handle outgoing messages
         let connection_id_clone = connection_id.clone();
         let manager_clone = manager.clone();
+        let writer_queue = tx.clone();
         tokio::spawn(async move {
-            while let Some(message) = rx.recv().await {
-                let json = match serde_json::to_string(&message) {
-                    Ok(json) => json,
-                    Err(e) => {
-                        tracing::error!("Failed to serialize WebSocket message: {}", e);
-                        continue;
-                    }
+            loop {
+                let outgoing = tokio::select! {
+                    _ = writer_queue.disconnected() => break,
+                    Some(message) = rx.recv() => match serde_json::to_string(&message) {
+                        Ok(json) => Message::Text(json),
+                        Err(e) => {
+                            tracing::error!("Failed to serialize WebSocket message: {}", e);
+                            continue;
+                        }
+                    },
+                    Some(frame) = frame_rx.recv() => Message::Binary(frame),
+                    else => break,
                 };
 
-                if sender.send(Message::Text(json)).await.is_err() {
+                if sender.send(outgoing).await.is_err() {
                     break;
                 }
             }
@@ -265,8 +342,19 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
             manager_clone.remove_connection(&connection_id_clone).await;
         });
 
-        // Handle incoming messages
-        while let Some(msg) = receiver.next().await {
+        // Binary frames carry a Stratum v2 session, set up on the first one
+        let mut stratum: Option<StratumV2Connection> = None;
+
+        // Handle incoming messages until the client leaves or falls behind
+        loop {
+            let msg = tokio::select! {
+                _ = tx.disconnected() => break,
+                msg = receiver.next() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+            };
+
             match msg {
                 Ok(Message::Text(text)) => {
                     if let Err(e) = handle_websocket_message(
@@ -277,13 +365,40 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
                         &tx,
                     ).await {
                         tracing::error!("Error handling WebSocket message: {}", e);
-                        let _ = tx.send(WebSocketMessage::Error {
+                        tx.send(WebSocketMessage::Error {
                             message: "Failed to process message".to_string(),
                         });
                     }
                 },
-                Ok(Message::Binary(_)) => {
-                    tracing::warn!("Received binary message, ignoring");
+                Ok(Message::Binary(data)) => {
+                    let session = match stratum.as_mut() {
+                        Some(session) => session,
+                        None => match StratumV2Connection::new(connection_id.clone(), stratum_v2::pool_static_key()) {
+                            Ok(session) => stratum.insert(session),
+                            Err(e) => {
+                                tracing::error!("Failed to start Stratum v2 session: {}", e);
+                                break;
+                            }
+                        },
+                    };
+
+                    let handlers = stratum_v2::PoolHandlers {
+                        miner_auth: &*state.miner_auth,
+                        share_processor: &*state.pool.share_processor,
+                        difficulty_adjuster: &*state.pool.difficulty_adjuster,
+                    };
+                    match session.receive(&data, handlers).await {
+                        Ok(frames) => {
+                            if frames.into_iter().any(|frame| frame_tx.send(frame).is_err()) {
+                                break;
+                            }
+                        },
+                        Err(e) => {
+                            // The Noise session cannot recover from a bad frame
+                            tracing::warn!("Closing Stratum v2 connection {}: {:#}", connection_id, e);
+                            break;
+                        },
+                    }
                 },
                 Ok(Message::Ping(data)) => {
                     if sender.send(Message::Pong(data)).await.is_err() {
@@ -305,6 +420,7 @@ pub async fn handle_websocket(socket: WebSocket, state: AppState) {
             }
         }
 
+        tx.close();
         manager.remove_connection(&connection_id).await;
     }
 }
@@ -314,7 +430,7 @@ async fn handle_websocket_message(
     connection: &WebSocketConnection,
     manager: &WebSocketManager,
     state: &AppState,
-    sender: &tokio::sync::mpsc::UnboundedSender<WebSocketMessage>,
+    sender: &OutgoingQueue,
 ) -> Result<()> {
     let message: WebSocketMessage = serde_json::from_str(text)?;
     
@@ -337,7 +453,7 @@ async fn handle_websocket_message(
                 }
             }
             
-            let _ = sender.send(WebSocketMessage::Success {
+            sender.send(WebSocketMessage::Success {
                 message: "Subscribed to channels".to_string(),
             });
         },
@@ -348,13 +464,21 @@ async fn handle_websocket_message(
                 subscriptions.retain(|c| c != &channel);
             }
             
-            let _ = sender.send(WebSocketMessage::Success {
+            sender.send(WebSocketMessage::Success {
                 message: "Unsubscribed from channels".to_string(),
             });
         },
         
+        WebSocketMessage::MiningSubscribe => {
+            let mut subscriptions = connection.subscriptions.write().await;
+            if !subscriptions.iter().any(|c| c == MINING_JOBS_CHANNEL) {
+                subscriptions.push(MINING_JOBS_CHANNEL.to_string());
+                start_mining_job_subscription(state, sender.clone()).await;
+            }
+        },
+
         WebSocketMessage::Ping => {
-            let _ = sender.send(WebSocketMessage::Pong);
+            sender.send(WebSocketMessage::Pong);
             *connection.last_ping.write().await = Instant::now();
         },
         
@@ -370,14 +494,14 @@ async fn start_channel_subscription(
     connection_id: String,
     channel: String,
     manager: Arc<WebSocketManager>,
-    sender: tokio::sync::mpsc::UnboundedSender<WebSocketMessage>,
+    sender: OutgoingQueue,
 ) {
     if let Some(broadcaster) = manager.broadcasters.get(&channel) {
         let mut receiver = broadcaster.subscribe();
         
         tokio::spawn(async move {
             while let Ok(message) = receiver.recv().await {
-                if sender.send(message).is_err() {
+                if !sender.send(message) {
                     break;
                 }
             }
@@ -385,6 +509,62 @@ async fn start_channel_subscription(
     }
 }
 
+// Miners without work get the cached template straight away, then every new job
+async fn start_mining_job_subscription(
+    state: &AppState,
+    sender: OutgoingQueue,
+) {
+    let (cached, mut receiver) = state.pool.block_finder.subscribe().await;
+    if let Some(job) = cached {
+        if !sender.send(WebSocketMessage::MiningJob(job)) {
+            return;
+        }
+    }
+
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(job) => {
+                    if !sender.send(WebSocketMessage::MiningJob(job)) {
+                        break;
+                    }
+                },
+                // Only the newest job matters, so skipped ones are dropped
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+// Found blocks go out as soon as they are published; a client whose queue is full is
+// disconnected by the queue itself
+fn forward_new_blocks(
+    mut blocks: broadcast::Receiver<NewBlock>,
+    sender: OutgoingQueue,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let block = tokio::select! {
+                _ = sender.disconnected() => break,
+                block = blocks.recv() => block,
+            };
+
+            match block {
+                Ok(block) => {
+                    if !sender.send(WebSocketMessage::NewBlock(block)) {
+                        break;
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client {} missed {} block notifications", sender.connection_id, skipped);
+                },
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
 // Background task for real-time updates
 async fn start_real_time_updates(manager: Arc<WebSocketManager>, state: Arc<AppState>) {
     // Pool stats updates (every 5 seconds)
@@ -517,4 +697,68 @@ pub async fn broadcast_alert(alert_data: AlertData) {
         let message = WebSocketMessage::Alert(alert_data);
         let _ = manager.broadcast_to_channel("alerts", message).await;
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcast_bus::BroadcastBus;
+
+    fn new_block(job_id: u64) -> NewBlock {
+        NewBlock {
+            block_hash: format!("tip-{}", job_id),
+            mining_height: 100 + job_id,
+            job_id,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_block_reaches_every_client_quickly() {
+        let bus = BroadcastBus::new();
+        let mut clients = Vec::new();
+        for i in 0..50 {
+            let (queue, receiver) = OutgoingQueue::new(format!("client-{}", i), OUTGOING_BUFFER_CAPACITY);
+            forward_new_blocks(bus.subscribe(), queue.clone());
+            clients.push((queue, receiver));
+        }
+        assert_eq!(bus.subscriber_count(), 50);
+
+        let published_at = Instant::now();
+        assert_eq!(bus.publish(new_block(7)), 50);
+
+        let deliveries = clients.iter_mut().map(|(_, receiver)| async move {
+            tokio::time::timeout(Duration::from_millis(100), receiver.recv()).await
+        });
+        for delivery in futures::future::join_all(deliveries).await {
+            match delivery.expect("Block notification took longer than 100ms") {
+                Some(WebSocketMessage::NewBlock(block)) => assert_eq!(block, new_block(7)),
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+        assert!(published_at.elapsed() < Duration::from_millis(100));
+        assert!(clients.iter().all(|(queue, _)| !queue.is_closed()));
+    }
+
+    #[tokio::test]
+    async fn test_client_with_full_buffer_is_disconnected() {
+        let bus = BroadcastBus::new();
+        let (slow, _slow_receiver) = OutgoingQueue::new("slow".to_string(), 1);
+        let (fast, mut fast_receiver) = OutgoingQueue::new("fast".to_string(), 1);
+        let slow_forwarder = forward_new_blocks(bus.subscribe(), slow.clone());
+        forward_new_blocks(bus.subscribe(), fast.clone());
+
+        for job_id in 1..=2 {
+            bus.publish(new_block(job_id));
+            let message = tokio::time::timeout(Duration::from_secs(1), fast_receiver.recv()).await.unwrap();
+            assert!(matches!(message, Some(WebSocketMessage::NewBlock(block)) if block.job_id == job_id));
+        }
+
+        // The slow client never reads, so the second block overflows its buffer
+        tokio::time::timeout(Duration::from_secs(1), slow.disconnected())
+            .await
+            .expect("Slow client was not disconnected");
+        tokio::time::timeout(Duration::from_secs(1), slow_forwarder).await.unwrap().unwrap();
+        assert!(!fast.is_closed());
+        assert!(!slow.send(WebSocketMessage::Ping));
+    }
+}
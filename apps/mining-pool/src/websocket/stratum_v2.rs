@@ -0,0 +1,843 @@
+// Stratum v2 mining protocol over the pool websocket
+// A Noise_XX handshake sets up an encrypted channel carrying length-prefixed SV2 messages
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use snow::{HandshakeState, TransportState};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::{
+    miner_auth::{AuthOutcome, MinerAuth, MinerIdentity},
+    mining::{
+        components::{DifficultyAdjuster, ShareProcessor},
+        Share, ShareStatus,
+    },
+};
+
+pub const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_SHA256";
+
+// Largest Noise message, and so the largest frame payload
+pub const MAX_FRAME_PAYLOAD: usize = 65535;
+
+// Frames are prefixed with the payload length as a little-endian u16
+const FRAME_HEADER_LEN: usize = 2;
+
+// Protocol negotiated in SetupConnection
+pub const PROTOCOL_MINING: u8 = 0;
+pub const PROTOCOL_VERSION: u16 = 2;
+
+// SV2 message types
+pub const MSG_SETUP_CONNECTION: u8 = 0x00;
+pub const MSG_SETUP_CONNECTION_SUCCESS: u8 = 0x01;
+pub const MSG_SETUP_CONNECTION_ERROR: u8 = 0x02;
+pub const MSG_NEW_MINING_JOB: u8 = 0x15;
+pub const MSG_SUBMIT_SHARES_STANDARD: u8 = 0x1a;
+pub const MSG_SUBMIT_SHARES_SUCCESS: u8 = 0x1c;
+pub const MSG_SUBMIT_SHARES_ERROR: u8 = 0x1d;
+pub const MSG_SET_TARGET: u8 = 0x21;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupConnection {
+    pub protocol: u8,
+    pub min_version: u16,
+    pub max_version: u16,
+    pub flags: u32,
+    pub endpoint_host: String,
+    pub endpoint_port: u16,
+    pub vendor: String,
+    pub hardware_version: String,
+    pub firmware: String,
+    pub device_id: String,
+    pub username: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupConnectionSuccess {
+    pub used_version: u16,
+    pub flags: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupConnectionError {
+    pub flags: u32,
+    pub error_code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewMiningJob {
+    pub channel_id: u32,
+    pub job_id: u32,
+    pub min_ntime: Option<u32>,
+    pub version: u32,
+    pub merkle_root: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitSharesStandard {
+    pub channel_id: u32,
+    pub sequence_number: u32,
+    pub job_id: u32,
+    pub nonce: u32,
+    pub ntime: u32,
+    pub version: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitSharesSuccess {
+    pub channel_id: u32,
+    pub last_sequence_number: u32,
+    pub new_submits_accepted_count: u32,
+    pub new_shares_sum: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubmitSharesError {
+    pub channel_id: u32,
+    pub sequence_number: u32,
+    pub error_code: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetTarget {
+    pub channel_id: u32,
+    pub maximum_target: [u8; 32], // little-endian U256
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sv2Message {
+    SetupConnection(SetupConnection),
+    SetupConnectionSuccess(SetupConnectionSuccess),
+    SetupConnectionError(SetupConnectionError),
+    NewMiningJob(NewMiningJob),
+    SubmitSharesStandard(SubmitSharesStandard),
+    SubmitSharesSuccess(SubmitSharesSuccess),
+    SubmitSharesError(SubmitSharesError),
+    SetTarget(SetTarget),
+}
+
+impl Sv2Message {
+    pub fn msg_type(&self) -> u8 {
+        match self {
+            Sv2Message::SetupConnection(_) => MSG_SETUP_CONNECTION,
+            Sv2Message::SetupConnectionSuccess(_) => MSG_SETUP_CONNECTION_SUCCESS,
+            Sv2Message::SetupConnectionError(_) => MSG_SETUP_CONNECTION_ERROR,
+            Sv2Message::NewMiningJob(_) => MSG_NEW_MINING_JOB,
+            Sv2Message::SubmitSharesStandard(_) => MSG_SUBMIT_SHARES_STANDARD,
+            Sv2Message::SubmitSharesSuccess(_) => MSG_SUBMIT_SHARES_SUCCESS,
+            Sv2Message::SubmitSharesError(_) => MSG_SUBMIT_SHARES_ERROR,
+            Sv2Message::SetTarget(_) => MSG_SET_TARGET,
+        }
+    }
+
+    // Message type byte followed by the bincode-encoded body
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let body = match self {
+            Sv2Message::SetupConnection(m) => bincode::serialize(m),
+            Sv2Message::SetupConnectionSuccess(m) => bincode::serialize(m),
+            Sv2Message::SetupConnectionError(m) => bincode::serialize(m),
+            Sv2Message::NewMiningJob(m) => bincode::serialize(m),
+            Sv2Message::SubmitSharesStandard(m) => bincode::serialize(m),
+            Sv2Message::SubmitSharesSuccess(m) => bincode::serialize(m),
+            Sv2Message::SubmitSharesError(m) => bincode::serialize(m),
+            Sv2Message::SetTarget(m) => bincode::serialize(m),
+        }?;
+
+        let mut bytes = Vec::with_capacity(1 + body.len());
+        bytes.push(self.msg_type());
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let (&msg_type, body) = bytes.split_first().context("Empty SV2 message")?;
+
+        Ok(match msg_type {
+            MSG_SETUP_CONNECTION => Sv2Message::SetupConnection(decode_body(body)?),
+            MSG_SETUP_CONNECTION_SUCCESS => Sv2Message::SetupConnectionSuccess(decode_body(body)?),
+            MSG_SETUP_CONNECTION_ERROR => Sv2Message::SetupConnectionError(decode_body(body)?),
+            MSG_NEW_MINING_JOB => Sv2Message::NewMiningJob(decode_body(body)?),
+            MSG_SUBMIT_SHARES_STANDARD => Sv2Message::SubmitSharesStandard(decode_body(body)?),
+            MSG_SUBMIT_SHARES_SUCCESS => Sv2Message::SubmitSharesSuccess(decode_body(body)?),
+            MSG_SUBMIT_SHARES_ERROR => Sv2Message::SubmitSharesError(decode_body(body)?),
+            MSG_SET_TARGET => Sv2Message::SetTarget(decode_body(body)?),
+            other => bail!("Unknown SV2 message type {:#04x}", other),
+        })
+    }
+}
+
+fn decode_body<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    bincode::deserialize(body).context("Malformed SV2 message body")
+}
+
+/// Largest hash meeting a share difficulty: (2^256 - 1) / difficulty as a little-endian U256
+pub fn target_for_difficulty(difficulty: u64) -> [u8; 32] {
+    let divisor = difficulty.max(1) as u128;
+    let mut target = [0u8; 32];
+    let mut remainder = 0u128;
+
+    // Long division one 64-bit limb at a time, most significant first
+    for limb in (0..4).rev() {
+        let dividend = (remainder << 64) | u64::MAX as u128;
+        remainder = dividend % divisor;
+        target[limb * 8..limb * 8 + 8].copy_from_slice(&((dividend / divisor) as u64).to_le_bytes());
+    }
+    target
+}
+
+pub fn encode_frame(payload: &[u8]) -> Result<Vec<u8>> {
+    if payload.len() > MAX_FRAME_PAYLOAD {
+        bail!("Frame payload of {} bytes exceeds {}", payload.len(), MAX_FRAME_PAYLOAD);
+    }
+
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame.extend_from_slice(payload);
+    Ok(frame)
+}
+
+// Reassembles frames from websocket messages that may split or batch them
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn next_frame(&mut self) -> Option<Vec<u8>> {
+        if self.buffer.len() < FRAME_HEADER_LEN {
+            return None;
+        }
+
+        let len = u16::from_le_bytes([self.buffer[0], self.buffer[1]]) as usize;
+        if self.buffer.len() < FRAME_HEADER_LEN + len {
+            return None;
+        }
+
+        let frame = self.buffer[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len].to_vec();
+        self.buffer.drain(..FRAME_HEADER_LEN + len);
+        Some(frame)
+    }
+}
+
+// Pool's static Noise key, generated once per process
+pub fn pool_static_key() -> &'static [u8] {
+    static KEY: OnceLock<Vec<u8>> = OnceLock::new();
+    KEY.get_or_init(|| {
+        snow::Builder::new(NOISE_PARAMS.parse().expect("Valid Noise parameters"))
+            .generate_keypair()
+            .expect("Failed to generate Noise keypair")
+            .private
+    })
+}
+
+enum NoiseState {
+    Handshake(Box<HandshakeState>),
+    Transport(Box<TransportState>),
+}
+
+// What shares for a job are validated against
+#[derive(Debug, Clone)]
+struct JobContext {
+    prev_block_hash: String,
+    difficulty: u64,
+}
+
+// Pool components a session authenticates against and hands accepted work to
+#[derive(Clone, Copy)]
+pub struct PoolHandlers<'a> {
+    pub miner_auth: &'a MinerAuth,
+    pub share_processor: &'a dyn ShareProcessor,
+    pub difficulty_adjuster: &'a dyn DifficultyAdjuster,
+}
+
+// One miner's SV2 session: the pool is the Noise responder
+pub struct StratumV2Connection {
+    connection_id: String,
+    noise: Option<NoiseState>,
+    decoder: FrameDecoder,
+    setup: Option<SetupConnection>, // with the API key cleared
+    miner: Option<MinerIdentity>,
+    jobs: HashMap<(u32, u32), JobContext>, // (channel_id, job_id)
+}
+
+impl StratumV2Connection {
+    pub fn new(connection_id: String, static_private_key: &[u8]) -> Result<Self> {
+        let handshake = snow::Builder::new(NOISE_PARAMS.parse()?)
+            .local_private_key(static_private_key)
+            .build_responder()?;
+
+        Ok(Self {
+            connection_id,
+            noise: Some(NoiseState::Handshake(Box::new(handshake))),
+            decoder: FrameDecoder::default(),
+            setup: None,
+            miner: None,
+            jobs: HashMap::new(),
+        })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self.noise, Some(NoiseState::Transport(_)))
+    }
+
+    pub fn setup(&self) -> Option<&SetupConnection> {
+        self.setup.as_ref()
+    }
+
+    /// Miner authenticated by SetupConnection
+    pub fn miner(&self) -> Option<&MinerIdentity> {
+        self.miner.as_ref()
+    }
+
+    /// Feeds bytes received from the miner and returns the frames to send back. Any error
+    /// leaves the Noise session unusable, so the caller should close the connection.
+    pub async fn receive(
+        &mut self,
+        bytes: &[u8],
+        handlers: PoolHandlers<'_>,
+    ) -> Result<Vec<Vec<u8>>> {
+        self.decoder.push(bytes);
+
+        let mut outgoing = Vec::new();
+        while let Some(frame) = self.decoder.next_frame() {
+            outgoing.extend(self.handle_frame(&frame, handlers).await?);
+        }
+        Ok(outgoing)
+    }
+
+    /// Encrypts a job for the miner, remembering the block it belongs to for share validation.
+    /// Jobs for earlier blocks are forgotten.
+    pub fn new_mining_job(&mut self, job: NewMiningJob, prev_block_hash: String, difficulty: u64) -> Result<Vec<u8>> {
+        if self.setup.is_none() {
+            bail!("Connection has not been set up");
+        }
+
+        self.jobs.retain(|_, context| context.prev_block_hash == prev_block_hash);
+        self.jobs.insert((job.channel_id, job.job_id), JobContext { prev_block_hash, difficulty });
+        self.encrypt(&Sv2Message::NewMiningJob(job))
+    }
+
+    async fn handle_frame(&mut self, frame: &[u8], handlers: PoolHandlers<'_>) -> Result<Vec<Vec<u8>>> {
+        let mut buffer = vec![0u8; MAX_FRAME_PAYLOAD];
+
+        match self.noise.as_mut().context("Noise session closed")? {
+            NoiseState::Handshake(handshake) => {
+                handshake.read_message(frame, &mut buffer).context("Noise handshake failed")?;
+
+                let mut replies = Vec::new();
+                if !handshake.is_handshake_finished() {
+                    let len = handshake.write_message(&[], &mut buffer)?;
+                    replies.push(encode_frame(&buffer[..len])?);
+                }
+                if handshake.is_handshake_finished() {
+                    self.finish_handshake()?;
+                }
+                Ok(replies)
+            }
+            NoiseState::Transport(transport) => {
+                let len = transport.read_message(frame, &mut buffer).context("Failed to decrypt frame")?;
+                let message = Sv2Message::decode(&buffer[..len])?;
+
+                let replies = self.dispatch(message, handlers).await?;
+                replies.iter().map(|reply| self.encrypt(reply)).collect()
+            }
+        }
+    }
+
+    fn finish_handshake(&mut self) -> Result<()> {
+        match self.noise.take() {
+            Some(NoiseState::Handshake(handshake)) => {
+                self.noise = Some(NoiseState::Transport(Box::new(handshake.into_transport_mode()?)));
+                Ok(())
+            }
+            other => {
+                self.noise = other;
+                bail!("Noise handshake already complete")
+            }
+        }
+    }
+
+    async fn dispatch(&mut self, message: Sv2Message, handlers: PoolHandlers<'_>) -> Result<Vec<Sv2Message>> {
+        match message {
+            Sv2Message::SetupConnection(setup) => Ok(vec![self.setup_connection(setup, handlers.miner_auth).await?]),
+            Sv2Message::SubmitSharesStandard(submit) => self.submit_share(submit, handlers).await,
+            other => bail!("Unexpected SV2 message type {:#04x} from miner", other.msg_type()),
+        }
+    }
+
+    async fn setup_connection(&mut self, mut setup: SetupConnection, miner_auth: &MinerAuth) -> Result<Sv2Message> {
+        let reject = |error_code: &str| {
+            Sv2Message::SetupConnectionError(SetupConnectionError {
+                flags: 0,
+                error_code: error_code.to_string(),
+            })
+        };
+
+        if self.setup.is_some() {
+            return Ok(reject("connection-already-setup"));
+        }
+        if setup.protocol != PROTOCOL_MINING {
+            return Ok(reject("unsupported-protocol"));
+        }
+        if !(setup.min_version..=setup.max_version).contains(&PROTOCOL_VERSION) {
+            return Ok(reject("protocol-version-mismatch"));
+        }
+
+        let identity = match miner_auth.authenticate(&setup.username, &setup.api_key).await? {
+            AuthOutcome::Authenticated(identity) => identity,
+            AuthOutcome::InvalidCredentials => {
+                tracing::warn!("SV2 connection {} failed authentication as {}", self.connection_id, setup.username);
+                return Ok(reject("invalid-credentials"));
+            }
+            AuthOutcome::LockedOut { .. } => return Ok(reject("locked-out")),
+        };
+
+        tracing::info!(
+            "SV2 miner {} ({}) set up from {} {}",
+            identity.miner_id, identity.username, setup.vendor, setup.device_id
+        );
+        setup.api_key.clear();
+        self.setup = Some(setup);
+        self.miner = Some(identity);
+        Ok(Sv2Message::SetupConnectionSuccess(SetupConnectionSuccess {
+            used_version: PROTOCOL_VERSION,
+            flags: 0,
+        }))
+    }
+
+    async fn submit_share(&mut self, submit: SubmitSharesStandard, handlers: PoolHandlers<'_>) -> Result<Vec<Sv2Message>> {
+        let reject = |error_code: &str| {
+            vec![Sv2Message::SubmitSharesError(SubmitSharesError {
+                channel_id: submit.channel_id,
+                sequence_number: submit.sequence_number,
+                error_code: error_code.to_string(),
+            })]
+        };
+
+        let Some(miner_id) = self.miner.as_ref().map(|miner| miner.miner_id.clone()) else {
+            return Ok(reject("connection-not-setup"));
+        };
+        let Some(job) = self.jobs.get(&(submit.channel_id, submit.job_id)).cloned() else {
+            return Ok(reject("invalid-job-id"));
+        };
+
+        let share = Share {
+            miner_id: miner_id.clone(),
+            nonce: hex::encode(submit.nonce.to_le_bytes()),
+            prev_block_hash: job.prev_block_hash,
+            timestamp: submit.ntime.into(),
+            difficulty: job.difficulty,
+        };
+
+        let result = handlers.share_processor.process_share(share).await?;
+        match result.status {
+            ShareStatus::Valid => {}
+            ShareStatus::Stale => return Ok(reject("stale-share")),
+            ShareStatus::Invalid => return Ok(reject("invalid-share")),
+        }
+
+        let mut replies = vec![Sv2Message::SubmitSharesSuccess(SubmitSharesSuccess {
+            channel_id: submit.channel_id,
+            last_sequence_number: submit.sequence_number,
+            new_submits_accepted_count: 1,
+            new_shares_sum: job.difficulty,
+        })];
+
+        // A vardiff retarget applies to every job on the channel
+        if let Some(difficulty) = handlers.difficulty_adjuster.record_miner_share(&miner_id, Instant::now()).await {
+            for ((channel_id, _), context) in self.jobs.iter_mut() {
+                if *channel_id == submit.channel_id {
+                    context.difficulty = difficulty;
+                }
+            }
+            replies.push(Sv2Message::SetTarget(SetTarget {
+                channel_id: submit.channel_id,
+                maximum_target: target_for_difficulty(difficulty),
+            }));
+        }
+        Ok(replies)
+    }
+
+    fn encrypt(&mut self, message: &Sv2Message) -> Result<Vec<u8>> {
+        let Some(NoiseState::Transport(transport)) = self.noise.as_mut() else {
+            bail!("Noise handshake not complete");
+        };
+
+        let mut buffer = vec![0u8; MAX_FRAME_PAYLOAD];
+        let len = transport.write_message(&message.encode()?, &mut buffer)?;
+        encode_frame(&buffer[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mining::stubs::{StubApiKeyStore, StubDifficultyAdjuster, StubShareProcessor};
+    use std::sync::Arc;
+
+    const PREV_BLOCK_HASH: &str = "00000000000000000000000000000000000000000000000000000000000000ab";
+    const API_KEY: &str = "nock_test_key";
+
+    fn setup_message(min_version: u16, max_version: u16) -> Sv2Message {
+        setup_with_credentials(min_version, max_version, API_KEY)
+    }
+
+    fn setup_with_credentials(min_version: u16, max_version: u16, api_key: &str) -> Sv2Message {
+        Sv2Message::SetupConnection(SetupConnection {
+            protocol: PROTOCOL_MINING,
+            min_version,
+            max_version,
+            flags: 0,
+            endpoint_host: "pool.example".to_string(),
+            endpoint_port: 3336,
+            vendor: "test-vendor".to_string(),
+            hardware_version: "1".to_string(),
+            firmware: "1.0.0".to_string(),
+            device_id: "rig-1".to_string(),
+            username: "alice".to_string(),
+            api_key: api_key.to_string(),
+        })
+    }
+
+    fn submit_message(job_id: u32, sequence_number: u32) -> Sv2Message {
+        Sv2Message::SubmitSharesStandard(SubmitSharesStandard {
+            channel_id: 1,
+            sequence_number,
+            job_id,
+            nonce: 0xdeadbeef,
+            ntime: 1_700_000_000,
+            version: 2,
+        })
+    }
+
+    fn job(job_id: u32) -> NewMiningJob {
+        NewMiningJob {
+            channel_id: 1,
+            job_id,
+            min_ntime: Some(1_700_000_000),
+            version: 2,
+            merkle_root: [7; 32],
+        }
+    }
+
+    struct TestPool {
+        miner_auth: MinerAuth,
+        share_processor: StubShareProcessor,
+        difficulty_adjuster: StubDifficultyAdjuster,
+    }
+
+    impl TestPool {
+        fn new() -> Self {
+            Self::with_adjuster(StubDifficultyAdjuster::new(1_000))
+        }
+
+        fn with_adjuster(difficulty_adjuster: StubDifficultyAdjuster) -> Self {
+            Self {
+                miner_auth: MinerAuth::new(Arc::new(StubApiKeyStore::with_key("miner-alice", "alice", API_KEY))),
+                share_processor: StubShareProcessor::new(),
+                difficulty_adjuster,
+            }
+        }
+
+        fn handlers(&self) -> PoolHandlers<'_> {
+            PoolHandlers {
+                miner_auth: &self.miner_auth,
+                share_processor: &self.share_processor,
+                difficulty_adjuster: &self.difficulty_adjuster,
+            }
+        }
+    }
+
+    // Miner side of the session
+    struct Miner {
+        transport: TransportState,
+        decoder: FrameDecoder,
+    }
+
+    impl Miner {
+        fn send(&mut self, message: &Sv2Message) -> Vec<u8> {
+            let mut buffer = vec![0u8; MAX_FRAME_PAYLOAD];
+            let len = self.transport.write_message(&message.encode().unwrap(), &mut buffer).unwrap();
+            encode_frame(&buffer[..len]).unwrap()
+        }
+
+        fn receive(&mut self, frames: Vec<Vec<u8>>) -> Vec<Sv2Message> {
+            let mut messages = Vec::new();
+            for frame in frames {
+                self.decoder.push(&frame);
+                while let Some(payload) = self.decoder.next_frame() {
+                    let mut buffer = vec![0u8; MAX_FRAME_PAYLOAD];
+                    let len = self.transport.read_message(&payload, &mut buffer).unwrap();
+                    messages.push(Sv2Message::decode(&buffer[..len]).unwrap());
+                }
+            }
+            messages
+        }
+    }
+
+    async fn handshake(connection: &mut StratumV2Connection, pool: &TestPool) -> Miner {
+        let params = NOISE_PARAMS.parse().unwrap();
+        let keypair = snow::Builder::new(NOISE_PARAMS.parse().unwrap()).generate_keypair().unwrap();
+        let mut initiator = snow::Builder::new(params)
+            .local_private_key(&keypair.private)
+            .build_initiator()
+            .unwrap();
+        let mut buffer = vec![0u8; MAX_FRAME_PAYLOAD];
+
+        // -> e
+        let len = initiator.write_message(&[], &mut buffer).unwrap();
+        let replies = connection.receive(&encode_frame(&buffer[..len]).unwrap(), pool.handlers()).await.unwrap();
+        assert_eq!(replies.len(), 1);
+        assert!(!connection.is_encrypted());
+
+        // <- e, ee, s, es
+        let mut decoder = FrameDecoder::default();
+        decoder.push(&replies[0]);
+        initiator.read_message(&decoder.next_frame().unwrap(), &mut buffer).unwrap();
+
+        // -> s, se
+        let len = initiator.write_message(&[], &mut buffer).unwrap();
+        let replies = connection.receive(&encode_frame(&buffer[..len]).unwrap(), pool.handlers()).await.unwrap();
+        assert!(replies.is_empty());
+        assert!(connection.is_encrypted());
+
+        Miner { transport: initiator.into_transport_mode().unwrap(), decoder }
+    }
+
+    async fn connected() -> (StratumV2Connection, Miner, TestPool) {
+        connected_to(TestPool::new()).await
+    }
+
+    async fn connected_to(pool: TestPool) -> (StratumV2Connection, Miner, TestPool) {
+        let mut connection = StratumV2Connection::new("miner-1".to_string(), pool_static_key()).unwrap();
+        let mut miner = handshake(&mut connection, &pool).await;
+
+        let frames = connection.receive(&miner.send(&setup_message(2, 2)), pool.handlers()).await.unwrap();
+        assert!(matches!(miner.receive(frames)[..], [Sv2Message::SetupConnectionSuccess(_)]));
+        assert_eq!(connection.miner().unwrap().miner_id, "miner-alice");
+        assert!(connection.setup().unwrap().api_key.is_empty());
+        (connection, miner, pool)
+    }
+
+    #[test]
+    fn test_frame_has_le_length_prefix() {
+        let frame = encode_frame(&[1, 2, 3]).unwrap();
+        assert_eq!(frame, vec![3, 0, 1, 2, 3]);
+
+        assert!(encode_frame(&vec![0u8; MAX_FRAME_PAYLOAD]).is_ok());
+        assert!(encode_frame(&vec![0u8; MAX_FRAME_PAYLOAD + 1]).is_err());
+    }
+
+    #[test]
+    fn test_decoder_handles_split_and_batched_frames() {
+        let mut bytes = encode_frame(b"first").unwrap();
+        bytes.extend(encode_frame(b"second").unwrap());
+
+        let mut decoder = FrameDecoder::default();
+        decoder.push(&bytes[..1]);
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&bytes[1..9]);
+        assert_eq!(decoder.next_frame(), Some(b"first".to_vec()));
+        assert_eq!(decoder.next_frame(), None);
+        decoder.push(&bytes[9..]);
+        assert_eq!(decoder.next_frame(), Some(b"second".to_vec()));
+        assert_eq!(decoder.next_frame(), None);
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let messages = vec![
+            setup_message(2, 2),
+            Sv2Message::SetupConnectionSuccess(SetupConnectionSuccess { used_version: 2, flags: 0 }),
+            Sv2Message::SetupConnectionError(SetupConnectionError { flags: 0, error_code: "x".to_string() }),
+            Sv2Message::NewMiningJob(job(9)),
+            submit_message(9, 4),
+            Sv2Message::SubmitSharesSuccess(SubmitSharesSuccess {
+                channel_id: 1,
+                last_sequence_number: 4,
+                new_submits_accepted_count: 1,
+                new_shares_sum: 1_000,
+            }),
+            Sv2Message::SubmitSharesError(SubmitSharesError {
+                channel_id: 1,
+                sequence_number: 4,
+                error_code: "stale-share".to_string(),
+            }),
+            Sv2Message::SetTarget(SetTarget { channel_id: 1, maximum_target: target_for_difficulty(4_000) }),
+        ];
+
+        for message in messages {
+            let bytes = message.encode().unwrap();
+            assert_eq!(bytes[0], message.msg_type());
+            assert_eq!(Sv2Message::decode(&bytes).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_target_for_difficulty() {
+        assert_eq!(target_for_difficulty(1), [0xff; 32]);
+        assert_eq!(target_for_difficulty(0), [0xff; 32]);
+
+        let mut half = [0xff; 32];
+        half[31] = 0x7f;
+        assert_eq!(target_for_difficulty(2), half);
+
+        let mut shifted = [0xff; 32];
+        shifted[31] = 0x00;
+        assert_eq!(target_for_difficulty(256), shifted);
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_or_truncated_messages() {
+        assert!(Sv2Message::decode(&[]).is_err());
+        assert!(Sv2Message::decode(&[0x7f, 0, 0]).is_err());
+
+        let bytes = submit_message(1, 1).encode().unwrap();
+        assert!(Sv2Message::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_setup_rejects_unsupported_version() {
+        let pool = TestPool::new();
+        let mut connection = StratumV2Connection::new("miner-1".to_string(), pool_static_key()).unwrap();
+        let mut miner = handshake(&mut connection, &pool).await;
+
+        let frames = connection.receive(&miner.send(&setup_message(3, 4)), pool.handlers()).await.unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SetupConnectionError(error)] => assert_eq!(error.error_code, "protocol-version-mismatch"),
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+        assert!(connection.setup().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_setup_rejects_invalid_credentials() {
+        let pool = TestPool::new();
+        let mut connection = StratumV2Connection::new("connection-1".to_string(), pool_static_key()).unwrap();
+        let mut miner = handshake(&mut connection, &pool).await;
+
+        let frames = connection
+            .receive(&miner.send(&setup_with_credentials(2, 2, "nock_wrong")), pool.handlers())
+            .await
+            .unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SetupConnectionError(error)] => assert_eq!(error.error_code, "invalid-credentials"),
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+        assert!(connection.miner().is_none());
+
+        // Shares stay refused until the miner authenticates
+        let frames = connection.receive(&miner.send(&submit_message(1, 1)), pool.handlers()).await.unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SubmitSharesError(error)] => assert_eq!(error.error_code, "connection-not-setup"),
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+
+        let frames = connection.receive(&miner.send(&setup_message(2, 2)), pool.handlers()).await.unwrap();
+        assert!(matches!(miner.receive(frames)[..], [Sv2Message::SetupConnectionSuccess(_)]));
+    }
+
+    #[tokio::test]
+    async fn test_share_for_known_job_is_accepted() {
+        let (mut connection, mut miner, pool) = connected().await;
+
+        let frame = connection.new_mining_job(job(5), PREV_BLOCK_HASH.to_string(), 1_000).unwrap();
+        assert_eq!(miner.receive(vec![frame]), vec![Sv2Message::NewMiningJob(job(5))]);
+
+        let frames = connection.receive(&miner.send(&submit_message(5, 1)), pool.handlers()).await.unwrap();
+        assert_eq!(
+            miner.receive(frames),
+            vec![Sv2Message::SubmitSharesSuccess(SubmitSharesSuccess {
+                channel_id: 1,
+                last_sequence_number: 1,
+                new_submits_accepted_count: 1,
+                new_shares_sum: 1_000,
+            })]
+        );
+        assert_eq!(pool.share_processor.get_statistics().await.total_processed.into_inner(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_vardiff_retarget_sends_set_target() {
+        let pool = TestPool::with_adjuster(StubDifficultyAdjuster::retargeting_after(1_000, 2, 4_000));
+        let (mut connection, mut miner, pool) = connected_to(pool).await;
+        connection.new_mining_job(job(5), PREV_BLOCK_HASH.to_string(), 1_000).unwrap();
+
+        let frames = connection.receive(&miner.send(&submit_message(5, 1)), pool.handlers()).await.unwrap();
+        assert!(matches!(miner.receive(frames)[..], [Sv2Message::SubmitSharesSuccess(_)]));
+
+        let frames = connection.receive(&miner.send(&submit_message(5, 2)), pool.handlers()).await.unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SubmitSharesSuccess(_), Sv2Message::SetTarget(set_target)] => {
+                assert_eq!(set_target.channel_id, 1);
+                assert_eq!(set_target.maximum_target, target_for_difficulty(4_000));
+            }
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+
+        // Later shares on the channel are credited at the new difficulty
+        let frames = connection.receive(&miner.send(&submit_message(5, 3)), pool.handlers()).await.unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SubmitSharesSuccess(success)] => assert_eq!(success.new_shares_sum, 4_000),
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_share_for_unknown_or_superseded_job_is_rejected() {
+        let (mut connection, mut miner, pool) = connected().await;
+        connection.new_mining_job(job(5), PREV_BLOCK_HASH.to_string(), 1_000).unwrap();
+        // A job on a new block replaces the earlier ones
+        connection.new_mining_job(job(6), "ff".repeat(32), 1_000).unwrap();
+
+        for job_id in [5, 99] {
+            let frames = connection.receive(&miner.send(&submit_message(job_id, 2)), pool.handlers()).await.unwrap();
+            match &miner.receive(frames)[..] {
+                [Sv2Message::SubmitSharesError(error)] => assert_eq!(error.error_code, "invalid-job-id"),
+                other => panic!("Unexpected reply: {:?}", other),
+            }
+        }
+        assert_eq!(pool.share_processor.get_statistics().await.total_processed.into_inner(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_share_before_setup_is_rejected() {
+        let pool = TestPool::new();
+        let mut connection = StratumV2Connection::new("miner-1".to_string(), pool_static_key()).unwrap();
+        let mut miner = handshake(&mut connection, &pool).await;
+
+        assert!(connection.new_mining_job(job(1), PREV_BLOCK_HASH.to_string(), 1_000).is_err());
+
+        let frames = connection.receive(&miner.send(&submit_message(1, 1)), pool.handlers()).await.unwrap();
+        match &miner.receive(frames)[..] {
+            [Sv2Message::SubmitSharesError(error)] => assert_eq!(error.error_code, "connection-not-setup"),
+            other => panic!("Unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tampered_frame_is_an_error() {
+        let (mut connection, mut miner, pool) = connected().await;
+
+        let mut frame = miner.send(&submit_message(1, 1));
+        let last = frame.len() - 1;
+        frame[last] ^= 0x01;
+
+        assert!(connection.receive(&frame, pool.handlers()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_before_handshake_is_an_error() {
+        let pool = TestPool::new();
+        let mut connection = StratumV2Connection::new("miner-1".to_string(), pool_static_key()).unwrap();
+
+        let plaintext = encode_frame(&setup_message(2, 2).encode().unwrap()).unwrap();
+        assert!(connection.receive(&plaintext, pool.handlers()).await.is_err());
+    }
+}
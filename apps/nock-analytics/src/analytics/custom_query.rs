@@ -0,0 +1,684 @@
+// Custom Analytics Queries for NOCK Blockchain
+// A small select/where language over per-block metrics, evaluated in process rather than as SQL
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use pest::iterators::Pair;
+use pest::Parser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::analytics::BlockProofPower;
+
+/// Longest query text accepted
+pub const MAX_QUERY_LENGTH: usize = 2000;
+
+/// Most columns a query may select
+pub const MAX_PROJECTIONS: usize = 20;
+
+/// Longest time range a query may scan
+pub const MAX_QUERY_RANGE_DAYS: i64 = 31;
+
+/// Request header carrying the caller's subscription tier
+pub const USER_TIER_HEADER: &str = "user_tier";
+
+mod grammar {
+    #[derive(pest_derive::Parser)]
+    #[grammar = "analytics/custom_query.pest"]
+    pub struct QueryParser;
+}
+
+use grammar::{QueryParser, Rule};
+
+/// Query rejected before execution. Messages are shown to the caller as-is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    Invalid(String),
+    Forbidden(String),
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid(message) | Self::Forbidden(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Subscription tier, which decides the metrics a caller may query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserTier {
+    Basic,
+    Professional,
+    Enterprise,
+}
+
+impl UserTier {
+    /// Tier from the `user_tier` header; callers without one get basic access
+    pub fn from_header(value: Option<&str>) -> Result<Self, QueryError> {
+        value.map(str::parse).unwrap_or(Ok(Self::Basic))
+    }
+}
+
+impl FromStr for UserTier {
+    type Err = QueryError;
+
+    fn from_str(value: &str) -> Result<Self, QueryError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "basic" => Ok(Self::Basic),
+            "professional" => Ok(Self::Professional),
+            "enterprise" | "custom" => Ok(Self::Enterprise),
+            _ => Err(QueryError::Invalid("Unknown user tier".to_string())),
+        }
+    }
+}
+
+/// Per-block metrics a query can reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Height,
+    Difficulty,
+    BlockTimeSeconds,
+    ProofPower,
+    NockOpSteps,
+    HashSteps,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 6] = [
+        Self::Height,
+        Self::Difficulty,
+        Self::BlockTimeSeconds,
+        Self::ProofPower,
+        Self::NockOpSteps,
+        Self::HashSteps,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Height => "height",
+            Self::Difficulty => "difficulty",
+            Self::BlockTimeSeconds => "block_time_seconds",
+            Self::ProofPower => "proof_power",
+            Self::NockOpSteps => "nock_op_steps",
+            Self::HashSteps => "hash_steps",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+
+    /// Lowest tier allowed to read the metric
+    pub fn required_tier(&self) -> UserTier {
+        match self {
+            Self::Height | Self::Difficulty | Self::BlockTimeSeconds => UserTier::Basic,
+            Self::ProofPower => UserTier::Professional,
+            Self::NockOpSteps | Self::HashSteps => UserTier::Enterprise,
+        }
+    }
+
+    fn value(&self, block: &BlockProofPower) -> f64 {
+        match self {
+            Self::Height => block.height as f64,
+            Self::Difficulty => block.difficulty,
+            Self::BlockTimeSeconds => block.block_time_seconds,
+            Self::ProofPower => block.proof_power,
+            Self::NockOpSteps => block.nock_op_steps as f64,
+            Self::HashSteps => block.hash_steps as f64,
+        }
+    }
+}
+
+/// How matching blocks are grouped before aggregates are applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Block,
+    Hour,
+    Day,
+}
+
+impl FromStr for Granularity {
+    type Err = QueryError;
+
+    fn from_str(value: &str) -> Result<Self, QueryError> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" | "block" => Ok(Self::Block),
+            "hour" => Ok(Self::Hour),
+            "day" => Ok(Self::Day),
+            _ => Err(QueryError::Invalid("Aggregation must be one of none, hour or day".to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFn {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Count,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Metric(Metric),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Aggregate(AggregateFn, Box<Expr>),
+}
+
+impl Expr {
+    fn metrics(&self, found: &mut Vec<Metric>) {
+        match self {
+            Self::Number(_) => {}
+            Self::Metric(metric) => found.push(*metric),
+            Self::Negate(inner) | Self::Aggregate(_, inner) => inner.metrics(found),
+            Self::Binary(lhs, _, rhs) => {
+                lhs.metrics(found);
+                rhs.metrics(found);
+            }
+        }
+    }
+
+    fn has_aggregate(&self) -> bool {
+        match self {
+            Self::Number(_) | Self::Metric(_) => false,
+            Self::Aggregate(..) => true,
+            Self::Negate(inner) => inner.has_aggregate(),
+            Self::Binary(lhs, _, rhs) => lhs.has_aggregate() || rhs.has_aggregate(),
+        }
+    }
+
+    /// True when a metric is used outside any aggregate
+    fn has_bare_metric(&self) -> bool {
+        match self {
+            Self::Number(_) | Self::Aggregate(..) => false,
+            Self::Metric(_) => true,
+            Self::Negate(inner) => inner.has_bare_metric(),
+            Self::Binary(lhs, _, rhs) => lhs.has_bare_metric() || rhs.has_bare_metric(),
+        }
+    }
+
+    fn eval_block(&self, block: &BlockProofPower) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Metric(metric) => metric.value(block),
+            Self::Negate(inner) => -inner.eval_block(block),
+            Self::Binary(lhs, op, rhs) => op.apply(lhs.eval_block(block), rhs.eval_block(block)),
+            // Rejected by validation
+            Self::Aggregate(..) => f64::NAN,
+        }
+    }
+
+    fn eval_group(&self, blocks: &[&BlockProofPower]) -> f64 {
+        match self {
+            Self::Number(value) => *value,
+            // Rejected by validation
+            Self::Metric(_) => f64::NAN,
+            Self::Negate(inner) => -inner.eval_group(blocks),
+            Self::Binary(lhs, op, rhs) => op.apply(lhs.eval_group(blocks), rhs.eval_group(blocks)),
+            Self::Aggregate(function, inner) => {
+                let values = blocks.iter().map(|block| inner.eval_block(block));
+                match function {
+                    AggregateFn::Count => blocks.len() as f64,
+                    AggregateFn::Sum => values.sum(),
+                    AggregateFn::Avg if blocks.is_empty() => f64::NAN,
+                    AggregateFn::Avg => values.sum::<f64>() / blocks.len() as f64,
+                    AggregateFn::Min => values.fold(f64::NAN, f64::min),
+                    AggregateFn::Max => values.fold(f64::NAN, f64::max),
+                }
+            }
+        }
+    }
+}
+
+impl BinaryOp {
+    fn apply(&self, lhs: f64, rhs: f64) -> f64 {
+        match self {
+            Self::Add => lhs + rhs,
+            Self::Subtract => lhs - rhs,
+            Self::Multiply => lhs * rhs,
+            Self::Divide => lhs / rhs,
+        }
+    }
+}
+
+impl Comparison {
+    fn holds(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+            Self::Less => lhs < rhs,
+            Self::LessOrEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    /// Alias, or the expression as written
+    pub label: String,
+    pub expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub lhs: Expr,
+    pub comparison: Comparison,
+    pub rhs: Expr,
+}
+
+/// Parsed and validated query
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomQuery {
+    pub projections: Vec<Projection>,
+    pub conditions: Vec<Condition>,
+    pub granularity: Granularity,
+}
+
+impl CustomQuery {
+    /// Parses `text` and checks it only reads metrics `tier` may see and mixes aggregates
+    /// consistently with `granularity`
+    pub fn parse(text: &str, granularity: Granularity, tier: UserTier) -> Result<Self, QueryError> {
+        if text.len() > MAX_QUERY_LENGTH {
+            return Err(QueryError::Invalid(format!("Query is longer than {} characters", MAX_QUERY_LENGTH)));
+        }
+
+        let query = QueryParser::parse(Rule::query, text)
+            .map_err(syntax_error)?
+            .next()
+            .expect("query rule always produces a pair");
+
+        let mut projections = Vec::new();
+        let mut conditions = Vec::new();
+        for pair in query.into_inner() {
+            match pair.as_rule() {
+                Rule::projection => {
+                    let source = pair.as_str().trim().to_string();
+                    let mut inner = pair.into_inner();
+                    let expr = build_expr(inner.next().expect("projection has an expression"))?;
+                    let label = inner.find(|pair| pair.as_rule() == Rule::identifier)
+                        .map(|alias| alias.as_str().to_string())
+                        .unwrap_or(source);
+                    projections.push(Projection { label, expr });
+                }
+                Rule::where_clause => {
+                    for condition in pair.into_inner().filter(|pair| pair.as_rule() == Rule::condition) {
+                        let mut inner = condition.into_inner();
+                        let lhs = build_expr(inner.next().expect("condition has a left side"))?;
+                        let comparison = match inner.next().expect("condition has a comparison").as_str() {
+                            "=" => Comparison::Equal,
+                            "!=" => Comparison::NotEqual,
+                            "<" => Comparison::Less,
+                            "<=" => Comparison::LessOrEqual,
+                            ">" => Comparison::Greater,
+                            _ => Comparison::GreaterOrEqual,
+                        };
+                        let rhs = build_expr(inner.next().expect("condition has a right side"))?;
+                        conditions.push(Condition { lhs, comparison, rhs });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let query = Self { projections, conditions, granularity };
+        query.validate(tier)?;
+        Ok(query)
+    }
+
+    fn validate(&self, tier: UserTier) -> Result<(), QueryError> {
+        if self.projections.len() > MAX_PROJECTIONS {
+            return Err(QueryError::Invalid(format!("A query can select at most {} columns", MAX_PROJECTIONS)));
+        }
+
+        let mut metrics = Vec::new();
+        for projection in &self.projections {
+            projection.expr.metrics(&mut metrics);
+        }
+        for condition in &self.conditions {
+            condition.lhs.metrics(&mut metrics);
+            condition.rhs.metrics(&mut metrics);
+        }
+        if let Some(metric) = metrics.iter().find(|metric| metric.required_tier() > tier) {
+            return Err(QueryError::Forbidden(format!(
+                "Your plan does not include the {} metric",
+                metric.name()
+            )));
+        }
+
+        if self.conditions.iter().any(|condition| condition.lhs.has_aggregate() || condition.rhs.has_aggregate()) {
+            return Err(QueryError::Invalid("Aggregates cannot be used in WHERE".to_string()));
+        }
+        for projection in &self.projections {
+            if let Some(nested) = nested_aggregate(&projection.expr) {
+                return Err(QueryError::Invalid(format!("Aggregates cannot be nested: {}", nested)));
+            }
+        }
+
+        if self.is_grouped() {
+            if let Some(projection) = self.projections.iter().find(|projection| projection.expr.has_bare_metric()) {
+                return Err(QueryError::Invalid(format!(
+                    "'{}' must be wrapped in avg, sum, min, max or count when aggregating",
+                    projection.label
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Grouped queries produce one row per bucket instead of one per block
+    pub fn is_grouped(&self) -> bool {
+        self.granularity != Granularity::Block
+            || self.projections.iter().any(|projection| projection.expr.has_aggregate())
+    }
+
+    fn matches(&self, block: &BlockProofPower) -> bool {
+        self.conditions.iter()
+            .all(|condition| condition.comparison.holds(condition.lhs.eval_block(block), condition.rhs.eval_block(block)))
+    }
+
+    /// Runs the query over `blocks`. Grouped queries without a time bucket return a single row
+    /// stamped with `range_start`.
+    pub fn execute(&self, blocks: &[BlockProofPower], range_start: DateTime<Utc>) -> QueryOutput {
+        let matching: Vec<&BlockProofPower> = blocks.iter().filter(|block| self.matches(block)).collect();
+
+        let mut columns = vec!["timestamp".to_string()];
+        columns.extend(self.projections.iter().map(|projection| projection.label.clone()));
+
+        let rows = if self.is_grouped() {
+            let mut groups: BTreeMap<DateTime<Utc>, Vec<&BlockProofPower>> = BTreeMap::new();
+            if self.granularity == Granularity::Block {
+                groups.insert(range_start, matching);
+            } else {
+                let bucket = if self.granularity == Granularity::Hour { Duration::hours(1) } else { Duration::days(1) };
+                for block in matching {
+                    let start = block.timestamp.duration_trunc(bucket).unwrap_or(block.timestamp);
+                    groups.entry(start).or_default().push(block);
+                }
+            }
+
+            groups.into_iter()
+                .map(|(timestamp, blocks)| {
+                    row(timestamp, self.projections.iter().map(|projection| projection.expr.eval_group(&blocks)))
+                })
+                .collect()
+        } else {
+            matching.into_iter()
+                .map(|block| row(block.timestamp, self.projections.iter().map(|projection| projection.expr.eval_block(block))))
+                .collect()
+        };
+
+        QueryOutput { columns, rows }
+    }
+}
+
+/// Column-major result: a timestamp column followed by one column per projection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryOutput {
+    pub columns: Vec<String>,
+    /// Non-finite results, such as a division by zero, are null
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+fn row(timestamp: DateTime<Utc>, values: impl Iterator<Item = f64>) -> Vec<serde_json::Value> {
+    let mut row = vec![serde_json::Value::String(timestamp.to_rfc3339())];
+    row.extend(values.map(|value| {
+        serde_json::Number::from_f64(value).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null)
+    }));
+    row
+}
+
+fn nested_aggregate(expr: &Expr) -> Option<&'static str> {
+    match expr {
+        Expr::Number(_) | Expr::Metric(_) => None,
+        Expr::Negate(inner) => nested_aggregate(inner),
+        Expr::Binary(lhs, _, rhs) => nested_aggregate(lhs).or_else(|| nested_aggregate(rhs)),
+        Expr::Aggregate(function, inner) => inner.has_aggregate().then_some(match function {
+            AggregateFn::Avg => "avg",
+            AggregateFn::Sum => "sum",
+            AggregateFn::Min => "min",
+            AggregateFn::Max => "max",
+            AggregateFn::Count => "count",
+        }),
+    }
+}
+
+fn syntax_error(error: pest::error::Error<Rule>) -> QueryError {
+    let column = match error.line_col {
+        pest::error::LineColLocation::Pos((_, column)) | pest::error::LineColLocation::Span((_, column), _) => column,
+    };
+    let error = error.renamed_rules(|rule| match rule {
+        Rule::kw_select => "SELECT".to_string(),
+        Rule::kw_where => "WHERE".to_string(),
+        Rule::kw_and => "AND".to_string(),
+        Rule::kw_as => "AS".to_string(),
+        Rule::identifier => "a metric name".to_string(),
+        Rule::number => "a number".to_string(),
+        Rule::aggregate_fn => "an aggregate".to_string(),
+        Rule::comparison => "a comparison".to_string(),
+        Rule::add_op | Rule::mul_op => "an operator".to_string(),
+        Rule::EOI => "end of query".to_string(),
+        other => format!("{:?}", other).replace('_', " "),
+    });
+    QueryError::Invalid(format!("Syntax error at column {}: {}", column, error.variant.message()))
+}
+
+fn build_expr(pair: Pair<Rule>) -> Result<Expr, QueryError> {
+    match pair.as_rule() {
+        Rule::expr | Rule::term => {
+            let mut inner = pair.into_inner();
+            let mut lhs = build_expr(inner.next().expect("expression has an operand"))?;
+            while let Some(op) = inner.next() {
+                let op = match op.as_str() {
+                    "+" => BinaryOp::Add,
+                    "-" => BinaryOp::Subtract,
+                    "*" => BinaryOp::Multiply,
+                    _ => BinaryOp::Divide,
+                };
+                let rhs = build_expr(inner.next().expect("operator has a right operand"))?;
+                lhs = Expr::Binary(Box::new(lhs), op, Box::new(rhs));
+            }
+            Ok(lhs)
+        }
+        Rule::factor => build_expr(pair.into_inner().next().expect("factor has an operand")),
+        Rule::negation => Ok(Expr::Negate(Box::new(build_expr(
+            pair.into_inner().next().expect("negation has an operand"),
+        )?))),
+        Rule::aggregate => {
+            let mut inner = pair.into_inner();
+            let function = match inner.next().expect("aggregate has a function").as_str().to_ascii_lowercase().as_str() {
+                "avg" => AggregateFn::Avg,
+                "sum" => AggregateFn::Sum,
+                "min" => AggregateFn::Min,
+                "max" => AggregateFn::Max,
+                _ => AggregateFn::Count,
+            };
+            Ok(Expr::Aggregate(function, Box::new(build_expr(inner.next().expect("aggregate has an argument"))?)))
+        }
+        Rule::number => match pair.as_str().parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(Expr::Number(value)),
+            _ => Err(QueryError::Invalid(format!("Number out of range: {}", pair.as_str()))),
+        },
+        Rule::identifier => Metric::from_name(pair.as_str()).map(Expr::Metric).ok_or_else(|| {
+            let available: Vec<&str> = Metric::ALL.iter().map(Metric::name).collect();
+            QueryError::Invalid(format!(
+                "Unknown metric '{}'. Available metrics: {}",
+                pair.as_str(),
+                available.join(", ")
+            ))
+        }),
+        rule => unreachable!("unexpected rule in expression: {:?}", rule),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn block(height: u64, minute: i64, proof_power: f64, block_time_seconds: f64) -> BlockProofPower {
+        BlockProofPower {
+            height,
+            hash: format!("0x{:064x}", height),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap() + Duration::minutes(minute),
+            difficulty: 2.0e12,
+            block_time_seconds,
+            proof_power,
+            miner: "miner-a".to_string(),
+            nock_op_steps: 300,
+            hash_steps: 100,
+        }
+    }
+
+    fn blocks() -> Vec<BlockProofPower> {
+        vec![
+            block(1, 10, 1000.0, 600.0),
+            block(2, 40, 2000.0, 1800.0),
+            block(3, 70, 3000.0, 60.0),
+        ]
+    }
+
+    fn parse(text: &str, granularity: Granularity) -> Result<CustomQuery, QueryError> {
+        CustomQuery::parse(text, granularity, UserTier::Enterprise)
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_hourly_aggregates_with_expressions() {
+        let query = parse(
+            "SELECT avg(proof_power) AS pp, sum(nock_op_steps) / (sum(nock_op_steps) + sum(hash_steps)) * 100, count(height)",
+            Granularity::Hour,
+        ).unwrap();
+        let output = query.execute(&blocks(), start());
+
+        assert_eq!(output.columns[1], "pp");
+        assert_eq!(output.columns[2], "sum(nock_op_steps) / (sum(nock_op_steps) + sum(hash_steps)) * 100");
+        assert_eq!(output.rows.len(), 2);
+        assert_eq!(output.rows[0][0], "2024-06-10T00:00:00+00:00");
+        assert_eq!(output.rows[0][1], 1500.0);
+        assert_eq!(output.rows[0][2], 75.0);
+        assert_eq!(output.rows[1][3], 1.0);
+    }
+
+    #[test]
+    fn test_where_filters_blocks() {
+        let query = parse("select height, -proof_power / 1e3 where block_time_seconds >= 600 and height != 2", Granularity::Block).unwrap();
+        let output = query.execute(&blocks(), start());
+
+        assert_eq!(output.rows.len(), 1);
+        assert_eq!(output.rows[0][1], 1.0);
+        assert_eq!(output.rows[0][2], -1.0);
+    }
+
+    #[test]
+    fn test_ungrouped_aggregate_returns_one_row() {
+        let output = parse("SELECT max(proof_power), min(block_time_seconds), avg(difficulty) / 0", Granularity::Block)
+            .unwrap()
+            .execute(&blocks(), start());
+
+        assert_eq!(output.rows, vec![vec![
+            serde_json::json!("2024-06-10T00:00:00+00:00"),
+            serde_json::json!(3000.0),
+            serde_json::json!(60.0),
+            serde_json::Value::Null,
+        ]]);
+    }
+
+    #[test]
+    fn test_invalid_queries_have_readable_errors() {
+        let error = parse("SELECT proof_powr", Granularity::Block).unwrap_err();
+        assert!(error.to_string().starts_with("Unknown metric 'proof_powr'. Available metrics: height"));
+
+        let error = parse("SELECT proof_power +", Granularity::Block).unwrap_err();
+        assert!(error.to_string().starts_with("Syntax error at column"), "{}", error);
+
+        let error = parse("SELECT proof_power", Granularity::Hour).unwrap_err();
+        assert_eq!(
+            error,
+            QueryError::Invalid("'proof_power' must be wrapped in avg, sum, min, max or count when aggregating".to_string())
+        );
+
+        assert!(parse("SELECT avg(max(proof_power))", Granularity::Block).is_err());
+        assert!(parse("SELECT height WHERE avg(proof_power) > 1", Granularity::Block).is_err());
+        assert!(parse("SELECT height, avg(proof_power)", Granularity::Block).is_err());
+        assert!(parse(&format!("SELECT {}", "height + ".repeat(300) + "1"), Granularity::Block).is_err());
+    }
+
+    #[test]
+    fn test_column_access_by_tier() {
+        assert!(CustomQuery::parse("SELECT difficulty", Granularity::Block, UserTier::Basic).is_ok());
+        assert_eq!(
+            CustomQuery::parse("SELECT proof_power", Granularity::Block, UserTier::Basic).unwrap_err(),
+            QueryError::Forbidden("Your plan does not include the proof_power metric".to_string())
+        );
+        assert!(CustomQuery::parse("SELECT proof_power", Granularity::Block, UserTier::Professional).is_ok());
+        // Filtering on a metric counts as reading it
+        assert!(matches!(
+            CustomQuery::parse("SELECT height WHERE hash_steps > 0", Granularity::Block, UserTier::Professional),
+            Err(QueryError::Forbidden(_))
+        ));
+
+        assert_eq!(UserTier::from_header(None).unwrap(), UserTier::Basic);
+        assert_eq!(UserTier::from_header(Some("Enterprise")).unwrap(), UserTier::Enterprise);
+        assert!(UserTier::from_header(Some("admin")).is_err());
+    }
+
+    #[test]
+    fn test_injection_attempts_are_rejected() {
+        let attempts = [
+            "SELECT proof_power; DROP TABLE blocks",
+            "SELECT proof_power -- comment",
+            "SELECT proof_power' OR '1'='1",
+            "SELECT \"proof_power\"",
+            "SELECT `proof_power`",
+            "SELECT proof_power FROM blocks",
+            "SELECT height WHERE 1 = 1 OR 1 = 1",
+            "SELECT height UNION SELECT password FROM users",
+            "SELECT pg_sleep(10)",
+            "SELECT password",
+            "SELECT __proto__",
+            "SELECT proof_power/**/",
+            "SELECT proof_power\0",
+            "SELECT difficulty, current_user",
+            "SELECT avg(proof_power) AS \"x\"; --",
+            "SELECT height AS select",
+            "SELECT select",
+        ];
+        for attempt in attempts {
+            match parse(attempt, Granularity::Block) {
+                Err(QueryError::Invalid(_)) => {}
+                other => panic!("{:?} was not rejected: {:?}", attempt, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,464 @@
+// Eon Duration Modeling for NOCK Blockchain
+// Fits eon lengths to a shifted log-normal and finds regimes where hashrate growth shortened eons
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use statrs::distribution::{ContinuousCDF, LogNormal};
+
+use crate::{EonDurationAnalysis, TransitionPattern};
+
+/// Number of most recent eon transitions modeled
+pub const EONS_ANALYZED: i64 = 100;
+
+/// Shortest regime the changepoint search will split off
+pub const MIN_REGIME_EONS: usize = 5;
+
+/// Durations within this fraction of the mean count as "on schedule" for predictability
+pub const PREDICTABILITY_BAND: f64 = 0.1;
+
+/// Block at which a new eon began, with the network hashrate at that point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EonTransition {
+    pub eon_number: u64,
+    pub block_height: u64,
+    pub timestamp: DateTime<Utc>,
+    pub network_hashrate: f64,
+}
+
+/// Reads eon transitions recorded by the data collector in `eon_transitions`
+#[derive(Debug)]
+pub struct EonTransitionStore {
+    db_pool: PgPool,
+}
+
+impl EonTransitionStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Lazily connecting store for `DATABASE_URL`, or None when it is not configured
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        match PgPoolOptions::new().max_connections(5).connect_lazy(&url) {
+            Ok(pool) => Some(Self::new(pool)),
+            Err(e) => {
+                log::warn!("Invalid DATABASE_URL, eon analytics disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The most recent `limit` transitions, oldest first
+    pub async fn recent_transitions(&self, limit: i64) -> Result<Vec<EonTransition>> {
+        let rows: Vec<(i64, i64, DateTime<Utc>, f64)> = sqlx::query_as(
+            r#"
+            SELECT eon_number, block_height, transition_time, network_hashrate
+            FROM eon_transitions
+            ORDER BY eon_number DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to load eon transitions")?;
+
+        Ok(rows.into_iter()
+            .rev()
+            .map(|(eon_number, block_height, timestamp, network_hashrate)| EonTransition {
+                eon_number: eon_number as u64,
+                block_height: block_height as u64,
+                timestamp,
+                network_hashrate,
+            })
+            .collect())
+    }
+}
+
+/// Log-normal shifted by a floor: eon length minus `floor` is log-normally distributed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogNormalFit {
+    pub floor: f64,
+    pub mu: f64,
+    pub sigma: f64,
+}
+
+impl LogNormalFit {
+    /// Method-of-moments fit to durations in blocks, so the fitted mean and variance match
+    /// the sample. The floor is set so the fitted skew matches the sample's right skew;
+    /// durations without a right tail get no floor. Returns None for fewer than two eons
+    /// or when every eon had the same length.
+    pub fn fit(durations: &[f64]) -> Option<Self> {
+        let mean = mean(durations);
+        let variance = variance(durations, mean);
+        if durations.len() < 2 || variance <= 0.0 || mean <= 0.0 {
+            return None;
+        }
+        let std_dev = variance.sqrt();
+        let min = durations.iter().cloned().fold(f64::INFINITY, f64::min);
+
+        let skew = skewness(durations, mean);
+        let floor = if skew > 0.0 {
+            // Invert skew = (w + 2)·√(w - 1) for w = exp(σ²)
+            let a = 1.0 + skew.powi(2) / 2.0;
+            let b = skew * (1.0 + skew.powi(2) / 4.0).sqrt();
+            let w = (a + b).cbrt() + (a - b).cbrt() - 1.0;
+            let floor = (mean - std_dev / (w - 1.0).sqrt()).max(0.0);
+            if floor < min { floor } else { 0.0 }
+        } else {
+            0.0
+        };
+
+        let shifted_mean = mean - floor;
+        let sigma_sq = (1.0 + variance / shifted_mean.powi(2)).ln();
+        Some(Self {
+            floor,
+            mu: shifted_mean.ln() - sigma_sq / 2.0,
+            sigma: sigma_sq.sqrt(),
+        })
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.floor + (self.mu + self.sigma.powi(2) / 2.0).exp()
+    }
+
+    pub fn variance(&self) -> f64 {
+        let sigma_sq = self.sigma.powi(2);
+        (sigma_sq.exp() - 1.0) * (2.0 * self.mu + sigma_sq).exp()
+    }
+
+    pub fn cdf(&self, duration: f64) -> f64 {
+        if duration <= self.floor {
+            return 0.0;
+        }
+        LogNormal::new(self.mu, self.sigma)
+            .map(|distribution| distribution.cdf(duration - self.floor))
+            .unwrap_or(0.0)
+    }
+
+    /// Probability an eon lands within PREDICTABILITY_BAND of the fitted mean
+    pub fn predictability_score(&self) -> f64 {
+        let mean = self.mean();
+        self.cdf(mean * (1.0 + PREDICTABILITY_BAND)) - self.cdf(mean * (1.0 - PREDICTABILITY_BAND))
+    }
+}
+
+/// Length in blocks of each completed eon
+pub fn eon_durations(transitions: &[EonTransition]) -> Vec<f64> {
+    transitions.windows(2)
+        .map(|pair| pair[1].block_height.saturating_sub(pair[0].block_height) as f64)
+        .collect()
+}
+
+pub fn duration_analysis(transitions: &[EonTransition]) -> EonDurationAnalysis {
+    let durations = eon_durations(transitions);
+    let mean_duration_blocks = mean(&durations);
+    let duration_variance = variance(&durations, mean_duration_blocks);
+
+    let average_duration = match (transitions.first(), transitions.last()) {
+        (Some(first), Some(last)) if !durations.is_empty() => {
+            (last.timestamp - first.timestamp) / durations.len() as i32
+        }
+        _ => Duration::zero(),
+    };
+
+    let distribution = LogNormalFit::fit(&durations);
+    let predictability_score = match &distribution {
+        Some(fit) => fit.predictability_score(),
+        // Identical eon lengths are perfectly predictable
+        None if durations.len() >= 2 => 1.0,
+        None => 0.0,
+    };
+
+    let changepoints = detect_changepoints(&durations, MIN_REGIME_EONS);
+    let trend_analysis = match changepoints.last() {
+        _ if durations.len() < 2 => "insufficient_data",
+        None => "stable",
+        Some(&split) => {
+            let previous = changepoints.iter().rev().nth(1).copied().unwrap_or(0);
+            if mean(&durations[split..]) < mean(&durations[previous..split]) {
+                "shortening"
+            } else {
+                "lengthening"
+            }
+        }
+    };
+
+    EonDurationAnalysis {
+        eons_analyzed: durations.len() as u64,
+        mean_duration_blocks,
+        duration_variance,
+        average_duration,
+        distribution,
+        trend_analysis: trend_analysis.to_string(),
+        predictability_score,
+    }
+}
+
+/// Regimes in which eons got shorter while the network hashrate rose
+pub fn hashrate_shortening_regimes(transitions: &[EonTransition]) -> Vec<TransitionPattern> {
+    let durations = eon_durations(transitions);
+    let changepoints = detect_changepoints(&durations, MIN_REGIME_EONS);
+
+    let mut bounds = vec![0];
+    bounds.extend(&changepoints);
+    bounds.push(durations.len());
+
+    bounds.windows(3)
+        .filter_map(|window| {
+            let (before, after) = (window[0]..window[1], window[1]..window[2]);
+            let mean_duration_before = mean(&durations[before.clone()]);
+            let mean_duration_after = mean(&durations[after.clone()]);
+            let hashrate_before = mean_hashrate(&transitions[before]);
+            let hashrate_after = mean_hashrate(&transitions[after.clone()]);
+
+            if mean_duration_after >= mean_duration_before || hashrate_after <= hashrate_before {
+                return None;
+            }
+
+            let regime = &durations[after];
+            Some(TransitionPattern {
+                pattern_name: "hashrate_driven_shortening".to_string(),
+                start_eon: transitions[window[1]].eon_number,
+                mean_duration_before,
+                mean_duration_after,
+                hashrate_change: hashrate_after / hashrate_before - 1.0,
+                // Share of the analyzed eons that fall in this regime
+                frequency: regime.len() as f64 / durations.len() as f64,
+                predictability_score: share_within_band(regime),
+                // Relative change in eon length
+                market_impact: mean_duration_after / mean_duration_before - 1.0,
+            })
+        })
+        .collect()
+}
+
+/// Binary segmentation on mean shifts. A split is kept when it cuts the squared error by
+/// more than 2σ²·ln(n), with σ estimated from first differences so the shifts themselves
+/// do not inflate it. Returns sorted indices where a new regime starts.
+pub fn detect_changepoints(values: &[f64], min_segment: usize) -> Vec<usize> {
+    let min_segment = min_segment.max(1);
+    if values.len() < 2 * min_segment {
+        return Vec::new();
+    }
+
+    let mut differences: Vec<f64> = values.windows(2).map(|pair| (pair[1] - pair[0]).abs()).collect();
+    differences.sort_by(|a, b| a.total_cmp(b));
+    let sigma = median(&differences) / (0.6745 * std::f64::consts::SQRT_2);
+    // Noise-free series still need a positive penalty to stop splitting on rounding
+    let penalty = (2.0 * sigma.powi(2) * (values.len() as f64).ln()).max(f64::EPSILON);
+
+    let mut changepoints = Vec::new();
+    split_segment(values, 0, values.len(), min_segment, penalty, &mut changepoints);
+    changepoints.sort_unstable();
+    changepoints
+}
+
+fn split_segment(values: &[f64], start: usize, end: usize, min_segment: usize, penalty: f64, out: &mut Vec<usize>) {
+    if end - start < 2 * min_segment {
+        return;
+    }
+
+    let whole = squared_error(&values[start..end]);
+    let best = (start + min_segment..=end - min_segment)
+        .map(|split| (split, whole - squared_error(&values[start..split]) - squared_error(&values[split..end])))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some((split, gain)) = best {
+        if gain > penalty {
+            out.push(split);
+            split_segment(values, start, split, min_segment, penalty, out);
+            split_segment(values, split, end, min_segment, penalty, out);
+        }
+    }
+}
+
+fn squared_error(values: &[f64]) -> f64 {
+    let mean = mean(values);
+    values.iter().map(|value| (value - mean).powi(2)).sum()
+}
+
+fn share_within_band(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mean = mean(values);
+    let within = values.iter()
+        .filter(|value| (**value - mean).abs() <= mean * PREDICTABILITY_BAND)
+        .count();
+    within as f64 / values.len() as f64
+}
+
+fn mean_hashrate(transitions: &[EonTransition]) -> f64 {
+    mean(&transitions.iter().map(|transition| transition.network_hashrate).collect::<Vec<_>>())
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Sample variance
+fn variance(values: &[f64], mean: f64) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+/// Population skewness
+fn skewness(values: &[f64], mean: f64) -> f64 {
+    let n = values.len() as f64;
+    let m2 = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / n;
+    let m3 = values.iter().map(|value| (value - mean).powi(3)).sum::<f64>() / n;
+    if m2 <= 0.0 { 0.0 } else { m3 / m2.powf(1.5) }
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => sorted[len / 2],
+        len => (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Repeating jitter so fixtures are deterministic
+    const JITTER: [f64; 6] = [12.0, -8.0, 5.0, -15.0, 3.0, 3.0];
+
+    /// Transitions for consecutive eons with the given lengths (blocks) and hashrates,
+    /// one block per minute
+    fn transitions(regimes: &[(usize, f64, f64)]) -> Vec<EonTransition> {
+        let genesis = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut height = 0u64;
+        let mut eons = Vec::new();
+        let mut index = 0;
+        for &(count, duration, hashrate) in regimes {
+            for _ in 0..count {
+                eons.push(EonTransition {
+                    eon_number: index as u64 + 1,
+                    block_height: height,
+                    timestamp: genesis + Duration::minutes(height as i64),
+                    network_hashrate: hashrate,
+                });
+                height += (duration + JITTER[index % JITTER.len()]) as u64;
+                index += 1;
+            }
+        }
+        eons.push(EonTransition {
+            eon_number: index as u64 + 1,
+            block_height: height,
+            timestamp: genesis + Duration::minutes(height as i64),
+            network_hashrate: regimes.last().unwrap().2,
+        });
+        eons
+    }
+
+    #[test]
+    fn test_lognormal_fit_matches_sample_moments() {
+        let durations: Vec<f64> = (0..60).map(|i| 1000.0 + JITTER[i % 6] * 4.0 + (i % 7) as f64 * 20.0).collect();
+        let fit = LogNormalFit::fit(&durations).unwrap();
+
+        let sample_mean = mean(&durations);
+        let sample_variance = variance(&durations, sample_mean);
+        assert!((fit.mean() - sample_mean).abs() < 1e-6);
+        assert!((fit.variance() - sample_variance).abs() / sample_variance < 1e-9);
+        // No right tail, so no floor
+        assert_eq!(fit.floor, 0.0);
+        assert!(fit.cdf(sample_mean) > 0.4 && fit.cdf(sample_mean) < 0.6);
+    }
+
+    #[test]
+    fn test_heavy_tail_gets_a_floor() {
+        // Most eons end near 1000 blocks, a few run far longer
+        let durations: Vec<f64> = (0..60).map(|i| 900.0 + 20.0 * 2f64.powi(i % 6)).collect();
+        let fit = LogNormalFit::fit(&durations).unwrap();
+
+        assert!(fit.floor > 0.0 && fit.floor < 920.0, "floor {}", fit.floor);
+        assert_eq!(fit.cdf(fit.floor), 0.0);
+        assert!((fit.mean() - mean(&durations)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fit_requires_spread() {
+        assert_eq!(LogNormalFit::fit(&[1000.0]), None);
+        assert_eq!(LogNormalFit::fit(&[1000.0, 1000.0, 1000.0]), None);
+    }
+
+    #[test]
+    fn test_predictability_tracks_concentration() {
+        let tight: Vec<f64> = (0..50).map(|i| 1000.0 + JITTER[i % 6]).collect();
+        let loose: Vec<f64> = (0..50).map(|i| 1000.0 + JITTER[i % 6] * 30.0).collect();
+
+        let tight_score = LogNormalFit::fit(&tight).unwrap().predictability_score();
+        let loose_score = LogNormalFit::fit(&loose).unwrap().predictability_score();
+        assert!(tight_score > 0.95, "tight score {}", tight_score);
+        assert!(loose_score < tight_score);
+        assert!(loose_score > 0.0);
+    }
+
+    #[test]
+    fn test_duration_analysis() {
+        let eons = transitions(&[(40, 1000.0, 5.0e9)]);
+        let analysis = duration_analysis(&eons);
+
+        assert_eq!(analysis.eons_analyzed, 40);
+        assert!((analysis.mean_duration_blocks - 1000.0).abs() < 1.0);
+        assert!(analysis.duration_variance > 0.0);
+        // One block per minute
+        assert_eq!(analysis.average_duration.num_minutes(), analysis.mean_duration_blocks.floor() as i64);
+        assert_eq!(analysis.trend_analysis, "stable");
+        assert!(analysis.distribution.is_some());
+    }
+
+    #[test]
+    fn test_duration_analysis_without_history() {
+        let analysis = duration_analysis(&transitions(&[(0, 1000.0, 5.0e9)]));
+
+        assert_eq!(analysis.eons_analyzed, 0);
+        assert_eq!(analysis.trend_analysis, "insufficient_data");
+        assert_eq!(analysis.predictability_score, 0.0);
+    }
+
+    #[test]
+    fn test_changepoint_detection() {
+        let durations = eon_durations(&transitions(&[(30, 1000.0, 5.0e9), (30, 700.0, 8.0e9)]));
+        assert_eq!(detect_changepoints(&durations, MIN_REGIME_EONS), vec![30]);
+
+        let flat = eon_durations(&transitions(&[(60, 1000.0, 5.0e9)]));
+        assert!(detect_changepoints(&flat, MIN_REGIME_EONS).is_empty());
+    }
+
+    #[test]
+    fn test_hashrate_driven_shortening() {
+        let eons = transitions(&[(30, 1000.0, 5.0e9), (30, 700.0, 8.0e9), (30, 850.0, 6.0e9)]);
+
+        let patterns = hashrate_shortening_regimes(&eons);
+        assert_eq!(patterns.len(), 1);
+        let pattern = &patterns[0];
+        assert_eq!(pattern.start_eon, 31);
+        assert!((pattern.hashrate_change - 0.6).abs() < 1e-9);
+        assert!(pattern.mean_duration_after < pattern.mean_duration_before);
+        assert!((pattern.frequency - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(pattern.predictability_score, 1.0);
+
+        // The later lengthening regime is reported as the current trend
+        assert_eq!(duration_analysis(&eons).trend_analysis, "lengthening");
+    }
+
+    #[test]
+    fn test_shortening_without_hashrate_growth_is_ignored() {
+        let eons = transitions(&[(30, 1000.0, 5.0e9), (30, 700.0, 5.0e9)]);
+
+        assert!(hashrate_shortening_regimes(&eons).is_empty());
+        assert_eq!(duration_analysis(&eons).trend_analysis, "shortening");
+    }
+}
@@ -0,0 +1,389 @@
+// Analytics Export for NOCK Blockchain
+// Writes analytics series to Parquet and serves them from S3-compatible storage
+
+use anyhow::{anyhow, Context, Result};
+use arrow_array::{
+    ArrayRef, Float64Array, RecordBatch, StringArray, TimestampMicrosecondArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Duration, Utc};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, GzipLevel};
+use parquet::file::properties::WriterProperties;
+use parquet::schema::types::ColumnPath;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::{ExportResult, ProofPowerDataPoint, ROIDataPoint, TransactionRecord};
+
+/// How long a download link stays valid
+pub const EXPORT_URL_TTL_HOURS: i64 = 1;
+
+/// Columns written with dictionary encoding and gzip; every other column uses Snappy
+const STRING_COLUMNS: [&str; 2] = ["data_type", "mining_type"];
+
+/// Series that can be requested in `ExportRequest::data_types`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportDataType {
+    ProofPower,
+    Roi,
+    Transactions,
+}
+
+impl ExportDataType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ProofPower => "proof_power",
+            Self::Roi => "roi",
+            Self::Transactions => "transactions",
+        }
+    }
+}
+
+impl FromStr for ExportDataType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "proof_power" => Ok(Self::ProofPower),
+            "roi" => Ok(Self::Roi),
+            "transactions" => Ok(Self::Transactions),
+            other => Err(anyhow!("Unknown export data type: {}", other)),
+        }
+    }
+}
+
+/// One row of the export file. All data types share a single schema so they can live in one
+/// file; columns that do not apply to a row's `data_type` are null.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportRow {
+    pub timestamp: DateTime<Utc>,
+    pub proof_power: Option<f64>,
+    pub rolling_average_7d: Option<f64>,
+    pub hashrate: Option<f64>,
+    pub efficiency_score: Option<f64>,
+    pub miner_count: Option<u64>,
+    pub block_count: Option<u64>,
+    pub roi_percentage: Option<f64>,
+    pub mining_type: Option<String>,
+    pub fee: Option<f64>,
+    pub size_bytes: Option<u64>,
+    pub fee_rate: Option<f64>,
+    pub confirmation_seconds: Option<f64>,
+}
+
+impl From<&ProofPowerDataPoint> for ExportRow {
+    fn from(point: &ProofPowerDataPoint) -> Self {
+        Self {
+            timestamp: point.timestamp,
+            proof_power: Some(point.proof_power),
+            rolling_average_7d: Some(point.rolling_average_7d),
+            hashrate: Some(point.hashrate),
+            efficiency_score: Some(point.efficiency_score),
+            miner_count: Some(point.miner_count),
+            block_count: Some(point.block_count),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&ROIDataPoint> for ExportRow {
+    fn from(point: &ROIDataPoint) -> Self {
+        Self {
+            timestamp: point.timestamp,
+            roi_percentage: Some(point.roi_percentage),
+            mining_type: Some(point.mining_type.clone()),
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&TransactionRecord> for ExportRow {
+    fn from(record: &TransactionRecord) -> Self {
+        Self {
+            timestamp: record.confirmed_at,
+            fee: Some(record.fee),
+            size_bytes: Some(record.size_bytes),
+            fee_rate: Some(record.fee_rate),
+            confirmation_seconds: Some(record.confirmation_seconds),
+            ..Self::default()
+        }
+    }
+}
+
+pub fn export_schema() -> SchemaRef {
+    let utc = Some("UTC".into());
+    Arc::new(Schema::new(vec![
+        Field::new("data_type", DataType::Utf8, false),
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond, utc), false),
+        Field::new("proof_power", DataType::Float64, true),
+        Field::new("rolling_average_7d", DataType::Float64, true),
+        Field::new("hashrate", DataType::Float64, true),
+        Field::new("efficiency_score", DataType::Float64, true),
+        Field::new("miner_count", DataType::UInt64, true),
+        Field::new("block_count", DataType::UInt64, true),
+        Field::new("roi_percentage", DataType::Float64, true),
+        Field::new("mining_type", DataType::Utf8, true),
+        Field::new("fee", DataType::Float64, true),
+        Field::new("size_bytes", DataType::UInt64, true),
+        Field::new("fee_rate", DataType::Float64, true),
+        Field::new("confirmation_seconds", DataType::Float64, true),
+    ]))
+}
+
+fn record_batch(data_type: ExportDataType, rows: &[ExportRow]) -> Result<RecordBatch> {
+    let float = |get: fn(&ExportRow) -> Option<f64>| -> ArrayRef {
+        Arc::new(rows.iter().map(get).collect::<Float64Array>())
+    };
+    let unsigned = |get: fn(&ExportRow) -> Option<u64>| -> ArrayRef {
+        Arc::new(rows.iter().map(get).collect::<UInt64Array>())
+    };
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(vec![data_type.as_str(); rows.len()])),
+        Arc::new(
+            TimestampMicrosecondArray::from(rows.iter().map(|row| row.timestamp.timestamp_micros()).collect::<Vec<_>>())
+                .with_timezone("UTC"),
+        ),
+        float(|row| row.proof_power),
+        float(|row| row.rolling_average_7d),
+        float(|row| row.hashrate),
+        float(|row| row.efficiency_score),
+        unsigned(|row| row.miner_count),
+        unsigned(|row| row.block_count),
+        float(|row| row.roi_percentage),
+        Arc::new(rows.iter().map(|row| row.mining_type.as_deref()).collect::<StringArray>()),
+        float(|row| row.fee),
+        unsigned(|row| row.size_bytes),
+        float(|row| row.fee_rate),
+        float(|row| row.confirmation_seconds),
+    ];
+
+    RecordBatch::try_new(export_schema(), columns).context("Failed to build export batch")
+}
+
+fn writer_properties() -> WriterProperties {
+    let mut builder = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_dictionary_enabled(false);
+    for column in STRING_COLUMNS {
+        builder = builder
+            .set_column_compression(ColumnPath::from(column), Compression::GZIP(GzipLevel::default()))
+            .set_column_dictionary_enabled(ColumnPath::from(column), true);
+    }
+    builder.build()
+}
+
+/// Parquet file with one row group per data type, in request order. Data types without rows
+/// produce no row group.
+pub fn write_parquet(sections: &[(ExportDataType, Vec<ExportRow>)]) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buffer, export_schema(), Some(writer_properties()))?;
+    for (data_type, rows) in sections {
+        if rows.is_empty() {
+            continue;
+        }
+        writer.write(&record_batch(*data_type, rows)?)?;
+        // Close the row group so data types never share one
+        writer.flush()?;
+    }
+    writer.close()?;
+    Ok(buffer)
+}
+
+/// Bucket in S3 or an S3-compatible store that export files are uploaded to
+#[derive(Debug, Clone)]
+pub struct ExportStorage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl ExportStorage {
+    pub fn new(client: aws_sdk_s3::Client, bucket: String) -> Self {
+        Self { client, bucket }
+    }
+
+    /// Storage for `EXPORT_S3_BUCKET`, or None when it is not configured. `EXPORT_S3_ENDPOINT`
+    /// points at an S3-compatible store; credentials and region come from the usual AWS variables.
+    pub async fn from_env() -> Option<Self> {
+        let bucket = std::env::var("EXPORT_S3_BUCKET").ok()?;
+        let shared = aws_config::load_from_env().await;
+        let mut config = aws_sdk_s3::config::Builder::from(&shared);
+        if let Ok(endpoint) = std::env::var("EXPORT_S3_ENDPOINT") {
+            // S3-compatible stores generally need path-style addressing
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+        Some(Self::new(aws_sdk_s3::Client::from_conf(config.build()), bucket))
+    }
+
+    /// Uploads the file and returns a pre-signed download URL valid for `ttl`
+    pub async fn upload(&self, key: &str, body: Vec<u8>, ttl: Duration) -> Result<String> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type("application/vnd.apache.parquet")
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload export to s3://{}/{}", self.bucket, key))?;
+
+        let presigning = PresigningConfig::expires_in(ttl.to_std()?)?;
+        let request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await
+            .context("Failed to pre-sign export download")?;
+        Ok(request.uri().to_string())
+    }
+}
+
+/// Writes the sections to Parquet, uploads the file and returns where to download it
+pub async fn upload_export(storage: &ExportStorage, sections: &[(ExportDataType, Vec<ExportRow>)]) -> Result<ExportResult> {
+    let file = write_parquet(sections)?;
+    let file_size = file.len() as u64;
+
+    let export_id = uuid::Uuid::new_v4().to_string();
+    let ttl = Duration::hours(EXPORT_URL_TTL_HOURS);
+    let expires_at = Utc::now() + ttl;
+    let download_url = storage.upload(&format!("exports/{}.parquet", export_id), file, ttl).await?;
+
+    Ok(ExportResult {
+        export_id,
+        download_url,
+        file_size,
+        expires_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chrono::TimeZone;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use parquet::basic::Encoding;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    fn time(minute: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap() + Duration::minutes(minute)
+    }
+
+    fn sections() -> Vec<(ExportDataType, Vec<ExportRow>)> {
+        let proof_power = ProofPowerDataPoint {
+            timestamp: time(0),
+            proof_power: 1200.0,
+            rolling_average_7d: 1100.0,
+            hashrate: 2.0e9,
+            efficiency_score: 1.09,
+            miner_count: 4,
+            block_count: 6,
+        };
+        let roi = ROIDataPoint {
+            timestamp: time(60),
+            roi_percentage: 12.5,
+            mining_type: "software".to_string(),
+        };
+        let transactions: Vec<TransactionRecord> = (0..3)
+            .map(|i| TransactionRecord {
+                confirmed_at: time(i),
+                fee: 0.5,
+                size_bytes: 250,
+                fee_rate: 0.002,
+                confirmation_seconds: 90.0,
+            })
+            .collect();
+
+        vec![
+            (ExportDataType::ProofPower, vec![ExportRow::from(&proof_power)]),
+            (ExportDataType::Roi, vec![ExportRow::from(&roi), ExportRow::from(&roi)]),
+            (ExportDataType::Transactions, transactions.iter().map(ExportRow::from).collect()),
+        ]
+    }
+
+    #[test]
+    fn test_data_type_parsing() {
+        assert_eq!("proof_power".parse::<ExportDataType>().unwrap(), ExportDataType::ProofPower);
+        assert_eq!("transactions".parse::<ExportDataType>().unwrap(), ExportDataType::Transactions);
+        assert!("blocks".parse::<ExportDataType>().is_err());
+    }
+
+    #[test]
+    fn test_each_data_type_gets_its_own_row_group() {
+        let reader = SerializedFileReader::new(Bytes::from(write_parquet(&sections()).unwrap())).unwrap();
+        let metadata = reader.metadata();
+
+        let rows: Vec<i64> = metadata.row_groups().iter().map(|group| group.num_rows()).collect();
+        assert_eq!(rows, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_column_compression() {
+        let reader = SerializedFileReader::new(Bytes::from(write_parquet(&sections()).unwrap())).unwrap();
+
+        for column in reader.metadata().row_group(0).columns() {
+            let name = column.column_path().string();
+            if STRING_COLUMNS.contains(&name.as_str()) {
+                assert!(matches!(column.compression(), Compression::GZIP(_)), "{}", name);
+                assert!(column.encodings().contains(&Encoding::RLE_DICTIONARY), "{}", name);
+            } else {
+                assert_eq!(column.compression(), Compression::SNAPPY, "{}", name);
+                assert!(!column.encodings().contains(&Encoding::RLE_DICTIONARY), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_data_types_are_skipped() {
+        let sections = vec![
+            (ExportDataType::ProofPower, Vec::new()),
+            (ExportDataType::Roi, vec![ExportRow { timestamp: time(0), ..ExportRow::default() }]),
+        ];
+        let reader = SerializedFileReader::new(Bytes::from(write_parquet(&sections).unwrap())).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 1);
+    }
+
+    // Needs LocalStack: docker run -p 4566:4566 localstack/localstack
+    #[tokio::test]
+    #[ignore = "requires LocalStack"]
+    async fn test_upload_to_localstack() {
+        use aws_sdk_s3::config::{BehaviorVersion, Credentials, Region};
+
+        let endpoint = std::env::var("LOCALSTACK_ENDPOINT").unwrap_or_else(|_| "http://localhost:4566".to_string());
+        let config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "localstack"))
+            .endpoint_url(endpoint)
+            .force_path_style(true)
+            .build();
+        let client = aws_sdk_s3::Client::from_conf(config);
+        let bucket = format!("nock-exports-{}", uuid::Uuid::new_v4());
+        client.create_bucket().bucket(&bucket).send().await.unwrap();
+
+        let storage = ExportStorage::new(client, bucket);
+        let result = upload_export(&storage, &sections()).await.unwrap();
+        assert!(result.expires_at <= Utc::now() + Duration::hours(EXPORT_URL_TTL_HOURS));
+        assert!(result.download_url.contains("X-Amz-Expires=3600"));
+
+        let body = reqwest::get(&result.download_url).await.unwrap()
+            .error_for_status().unwrap()
+            .bytes().await.unwrap();
+        assert_eq!(body.len() as u64, result.file_size);
+
+        let batches: Vec<RecordBatch> = ParquetRecordBatchReaderBuilder::try_new(body).unwrap()
+            .build().unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        let data_types: Vec<String> = batches.iter()
+            .flat_map(|batch| {
+                let column = batch.column(0).as_any().downcast_ref::<StringArray>().unwrap();
+                column.iter().map(|value| value.unwrap().to_string()).collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(data_types, ["proof_power", "roi", "roi", "transactions", "transactions", "transactions"]);
+    }
+}
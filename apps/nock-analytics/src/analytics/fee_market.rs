@@ -0,0 +1,338 @@
+// Fee Market Analysis for NOCK Blockchain
+// Tracks fee rate distribution, confirmation latency and mempool pressure
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a computed report is served from cache
+pub const FEE_MARKET_CACHE_TTL: Duration = Duration::from_secs(120);
+
+/// Oldest data kept for analysis (7 days)
+pub const FEE_MARKET_RETENTION_HOURS: i64 = 168;
+
+/// Share of transactions that must confirm within the target for a fee rate to qualify
+pub const CONFIRMATION_CONFIDENCE: f64 = 0.9;
+
+/// Percentiles reported in `fee_rate_percentiles`
+pub const FEE_RATE_PERCENTILES: [f64; 5] = [10.0, 25.0, 50.0, 75.0, 90.0];
+
+/// A confirmed transaction observed by the data collector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmedTransaction {
+    pub fee: f64,             // NOCK
+    pub size_bytes: u64,
+    pub first_seen: DateTime<Utc>,
+    pub confirmed_at: DateTime<Utc>,
+}
+
+impl ConfirmedTransaction {
+    /// Fee rate in NOCK per byte
+    pub fn fee_rate(&self) -> f64 {
+        if self.size_bytes == 0 {
+            return 0.0;
+        }
+        self.fee / self.size_bytes as f64
+    }
+
+    pub fn time_to_confirm(&self) -> Duration {
+        (self.confirmed_at - self.first_seen).to_std().unwrap_or_default()
+    }
+}
+
+impl From<&ConfirmedTransaction> for crate::TransactionRecord {
+    fn from(tx: &ConfirmedTransaction) -> Self {
+        Self {
+            confirmed_at: tx.confirmed_at,
+            fee: tx.fee,
+            size_bytes: tx.size_bytes,
+            fee_rate: tx.fee_rate(),
+            confirmation_seconds: tx.time_to_confirm().as_secs_f64(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeMarketReport {
+    pub period_hours: u32,
+    pub transaction_count: u64,
+    pub fee_rate_percentiles: [f64; 5], // p10/p25/p50/p75/p90, NOCK per byte
+    pub median_time_to_confirm: Duration,
+    pub mempool_size_trend: Vec<(DateTime<Utc>, u64)>,
+    pub generated_at: DateTime<Utc>,
+    /// (fee rate, time to confirm) pairs sorted by fee rate, used for fee recommendations
+    #[serde(skip)]
+    confirmation_samples: Vec<(f64, Duration)>,
+}
+
+impl FeeMarketReport {
+    /// Lowest observed fee rate at which at least 90% of transactions paying that rate
+    /// or more confirmed within `target_minutes`. Falls back to the highest observed
+    /// rate when no rate meets the target, and 0.0 when there is no data.
+    pub fn optimal_fee_for_target_confirmation(&self, target_minutes: u32) -> f64 {
+        let samples = &self.confirmation_samples;
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let target = Duration::from_secs(target_minutes as u64 * 60);
+
+        // Walk from the most expensive transaction down, tracking how many at or above
+        // each rate confirmed in time
+        let mut best = None;
+        let mut within_target = 0usize;
+        for (count, (index, (fee_rate, time_to_confirm))) in samples.iter().enumerate().rev().enumerate() {
+            if *time_to_confirm <= target {
+                within_target += 1;
+            }
+
+            // Only evaluate at the lowest sample of a run of equal fee rates
+            let starts_run = index == 0 || samples[index - 1].0 < *fee_rate;
+            if starts_run && within_target as f64 / (count + 1) as f64 >= CONFIRMATION_CONFIDENCE {
+                best = Some(*fee_rate);
+            }
+        }
+
+        best.unwrap_or(samples[samples.len() - 1].0)
+    }
+}
+
+#[derive(Debug)]
+struct CachedReport {
+    computed_at: Instant,
+    report: FeeMarketReport,
+}
+
+/// Fee market analyzer fed by the blockchain data collector
+#[derive(Debug, Default)]
+pub struct FeeMarketAnalyzer {
+    transactions: Vec<ConfirmedTransaction>,
+    mempool_snapshots: Vec<(DateTime<Utc>, u64)>,
+    cache: Mutex<HashMap<u32, CachedReport>>,
+}
+
+impl FeeMarketAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_transaction(&mut self, transaction: ConfirmedTransaction) {
+        self.transactions.push(transaction);
+        self.prune(Utc::now());
+    }
+
+    pub fn record_mempool_snapshot(&mut self, timestamp: DateTime<Utc>, mempool_size: u64) {
+        self.mempool_snapshots.push((timestamp, mempool_size));
+        self.prune(Utc::now());
+    }
+
+    /// Retained transactions confirmed within `[start, end]`
+    pub fn transactions_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<ConfirmedTransaction> {
+        self.transactions.iter()
+            .filter(|tx| tx.confirmed_at >= start && tx.confirmed_at <= end)
+            .cloned()
+            .collect()
+    }
+
+    /// Fee market report for the last `hours`, cached for two minutes
+    pub fn compute(&self, hours: u32) -> FeeMarketReport {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(cached) = cache.get(&hours) {
+            if cached.computed_at.elapsed() < FEE_MARKET_CACHE_TTL {
+                return cached.report.clone();
+            }
+        }
+
+        let report = self.compute_at(hours, Utc::now());
+        cache.insert(hours, CachedReport {
+            computed_at: Instant::now(),
+            report: report.clone(),
+        });
+        report
+    }
+
+    fn compute_at(&self, hours: u32, now: DateTime<Utc>) -> FeeMarketReport {
+        let since = now - chrono::Duration::hours(hours as i64);
+
+        let mut confirmation_samples: Vec<(f64, Duration)> = self.transactions.iter()
+            .filter(|tx| tx.confirmed_at >= since && tx.confirmed_at <= now)
+            .map(|tx| (tx.fee_rate(), tx.time_to_confirm()))
+            .collect();
+        confirmation_samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let fee_rates: Vec<f64> = confirmation_samples.iter().map(|(rate, _)| *rate).collect();
+        let fee_rate_percentiles = FEE_RATE_PERCENTILES.map(|p| percentile(&fee_rates, p));
+
+        let mut confirm_times: Vec<Duration> = confirmation_samples.iter().map(|(_, time)| *time).collect();
+        confirm_times.sort();
+        let median_time_to_confirm = median_duration(&confirm_times);
+
+        let mut mempool_size_trend: Vec<(DateTime<Utc>, u64)> = self.mempool_snapshots.iter()
+            .filter(|(timestamp, _)| *timestamp >= since && *timestamp <= now)
+            .cloned()
+            .collect();
+        mempool_size_trend.sort_by_key(|(timestamp, _)| *timestamp);
+
+        FeeMarketReport {
+            period_hours: hours,
+            transaction_count: confirmation_samples.len() as u64,
+            fee_rate_percentiles,
+            median_time_to_confirm,
+            mempool_size_trend,
+            generated_at: now,
+            confirmation_samples,
+        }
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - chrono::Duration::hours(FEE_MARKET_RETENTION_HOURS);
+        self.transactions.retain(|tx| tx.confirmed_at >= cutoff);
+        self.mempool_snapshots.retain(|(timestamp, _)| *timestamp >= cutoff);
+    }
+}
+
+/// Linearly interpolated percentile of sorted values
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    match sorted.len() {
+        0 => 0.0,
+        1 => sorted[0],
+        len => {
+            let rank = p / 100.0 * (len - 1) as f64;
+            let lower = rank.floor() as usize;
+            let upper = rank.ceil() as usize;
+            sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+        }
+    }
+}
+
+fn median_duration(sorted: &[Duration]) -> Duration {
+    match sorted.len() {
+        0 => Duration::ZERO,
+        len if len % 2 == 1 => sorted[len / 2],
+        len => (sorted[len / 2 - 1] + sorted[len / 2]) / 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(now: DateTime<Utc>, fee_rate: f64, confirm_minutes: i64) -> ConfirmedTransaction {
+        let confirmed_at = now - chrono::Duration::minutes(5);
+        ConfirmedTransaction {
+            fee: fee_rate * 250.0,
+            size_bytes: 250,
+            first_seen: confirmed_at - chrono::Duration::minutes(confirm_minutes),
+            confirmed_at,
+        }
+    }
+
+    // Synthetic mempool: confirmation time falls as fee rate rises
+    fn synthetic_analyzer(now: DateTime<Utc>) -> FeeMarketAnalyzer {
+        let mut analyzer = FeeMarketAnalyzer::new();
+        for (fee_rate, confirm_minutes) in [
+            (1.0, 60), (1.0, 45), (2.0, 40), (2.0, 30), (3.0, 25),
+            (4.0, 12), (4.0, 9), (5.0, 8), (6.0, 4), (8.0, 2),
+        ] {
+            analyzer.transactions.push(transaction(now, fee_rate, confirm_minutes));
+        }
+        analyzer
+    }
+
+    #[test]
+    fn test_fee_rate_percentiles() {
+        let now = Utc::now();
+        let report = synthetic_analyzer(now).compute_at(24, now);
+
+        assert_eq!(report.transaction_count, 10);
+        assert_eq!(report.fee_rate_percentiles[0], 1.0);
+        assert_eq!(report.fee_rate_percentiles[2], 3.5);
+        assert!((report.fee_rate_percentiles[4] - 6.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_median_time_to_confirm() {
+        let now = Utc::now();
+        let report = synthetic_analyzer(now).compute_at(24, now);
+
+        // Sorted confirm times: 2,4,8,9,12,25,30,40,45,60 minutes
+        assert_eq!(report.median_time_to_confirm, Duration::from_secs(18 * 60 + 30));
+    }
+
+    #[test]
+    fn test_optimal_fee_for_target_confirmation() {
+        let now = Utc::now();
+        let report = synthetic_analyzer(now).compute_at(24, now);
+
+        // Every transaction at 4.0/byte or more confirmed within 12 minutes
+        assert_eq!(report.optimal_fee_for_target_confirmation(15), 4.0);
+        // Only 8.0/byte confirmed within 3 minutes
+        assert_eq!(report.optimal_fee_for_target_confirmation(3), 8.0);
+        // Within an hour everything qualifies, so the cheapest rate is enough
+        assert_eq!(report.optimal_fee_for_target_confirmation(60), 1.0);
+        // Nothing confirms that fast; recommend the highest observed rate
+        assert_eq!(report.optimal_fee_for_target_confirmation(1), 8.0);
+    }
+
+    #[test]
+    fn test_optimal_fee_tolerates_outliers() {
+        let now = Utc::now();
+        let mut analyzer = synthetic_analyzer(now);
+        // A single slow 7.0/byte transaction among sixteen at or above 4.0/byte
+        // stays within the 10% tolerance
+        analyzer.transactions.push(transaction(now, 7.0, 50));
+        for _ in 0..9 {
+            analyzer.transactions.push(transaction(now, 10.0, 1));
+        }
+
+        let report = analyzer.compute_at(24, now);
+        assert_eq!(report.optimal_fee_for_target_confirmation(15), 4.0);
+    }
+
+    #[test]
+    fn test_optimal_fee_without_data() {
+        let now = Utc::now();
+        let report = FeeMarketAnalyzer::new().compute_at(24, now);
+
+        assert_eq!(report.transaction_count, 0);
+        assert_eq!(report.optimal_fee_for_target_confirmation(10), 0.0);
+        assert_eq!(report.median_time_to_confirm, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_period_filters_transactions_and_mempool() {
+        let now = Utc::now();
+        let mut analyzer = synthetic_analyzer(now);
+        let mut old = transaction(now, 100.0, 1);
+        old.confirmed_at = now - chrono::Duration::hours(3);
+        analyzer.transactions.push(old);
+        analyzer.mempool_snapshots.push((now - chrono::Duration::minutes(30), 1200));
+        analyzer.mempool_snapshots.push((now - chrono::Duration::minutes(90), 800));
+        analyzer.mempool_snapshots.push((now - chrono::Duration::hours(5), 400));
+
+        let report = analyzer.compute_at(2, now);
+        assert_eq!(report.transaction_count, 10);
+        assert_eq!(
+            report.mempool_size_trend,
+            vec![
+                (now - chrono::Duration::minutes(90), 800),
+                (now - chrono::Duration::minutes(30), 1200),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compute_is_cached() {
+        let now = Utc::now();
+        let mut analyzer = synthetic_analyzer(now);
+        let first = analyzer.compute(24);
+
+        analyzer.transactions.push(transaction(now, 50.0, 1));
+        let second = analyzer.compute(24);
+
+        assert_eq!(first.transaction_count, second.transaction_count);
+        assert_eq!(first.generated_at, second.generated_at);
+    }
+}
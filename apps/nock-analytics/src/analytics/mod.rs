@@ -8,6 +8,21 @@ use log::{info, warn, error, debug};
 use std::collections::HashMap;
 use crate::*;
 
+pub mod fee_market;
+pub use fee_market::*;
+
+pub mod proof_power;
+pub use proof_power::*;
+
+pub mod eon_patterns;
+pub use eon_patterns::*;
+
+pub mod export;
+pub use export::*;
+
+pub mod custom_query;
+pub use custom_query::*;
+
 /// Core analytics engine for NOCK blockchain analysis
 #[derive(Debug)]
 pub struct AnalyticsEngine {
@@ -19,11 +34,13 @@ pub struct AnalyticsEngine {
     pub correlation_analyzer: CorrelationAnalyzer,
     pub anomaly_detector: AnomalyDetector,
     pub data_aggregator: DataAggregator,
+    pub export_storage: Option<ExportStorage>,
 }
 
 /// Advanced proof power analysis system
 #[derive(Debug)]
 pub struct ProofPowerAnalyzer {
+    pub header_client: BlockHeaderClient,
     pub software_mining_tracker: SoftwareMiningTracker,
     pub hardware_mining_tracker: HardwareMiningTracker,
     pub efficiency_calculator: EfficiencyCalculator,
@@ -52,7 +69,7 @@ pub struct HardwareMiningTracker {
 /// Eon pattern analysis for NOCK's unique architecture
 #[derive(Debug)]
 pub struct EonPatternAnalyzer {
-    pub eon_duration_tracker: EonDurationTracker,
+    pub transition_store: Option<EonTransitionStore>,
     pub transition_detector: TransitionDetector,
     pub reward_curve_analyzer: RewardCurveAnalyzer,
     pub difficulty_progression_analyzer: DifficultyProgressionAnalyzer,
@@ -124,6 +141,7 @@ impl AnalyticsEngine {
             correlation_analyzer: CorrelationAnalyzer::new().await,
             anomaly_detector: AnomalyDetector::new().await,
             data_aggregator: DataAggregator::new().await,
+            export_storage: ExportStorage::from_env().await,
         }
     }
 
@@ -170,20 +188,11 @@ impl AnalyticsEngine {
         debug!("Analyzing proof power trends");
 
         // Get time range from parameters
-        let time_range = self.parse_time_range(params)?;
-        
-        // Analyze software vs hardware mining
-        let software_percentage = self.proof_power_analyzer
-            .calculate_software_mining_percentage(&time_range).await?;
-        let hardware_percentage = 100.0 - software_percentage;
+        let time_range = self.parse_time_range(params).await?;
         
-        // Calculate average proof power
-        let average_proof_power = self.proof_power_analyzer
-            .calculate_average_proof_power(&time_range).await?;
-        
-        // Get proof power distribution data
-        let proof_power_distribution = self.proof_power_analyzer
-            .get_proof_power_distribution(&time_range).await?;
+        // Proof power, hourly distribution and software vs hardware split from block headers
+        let summary = self.proof_power_analyzer
+            .summarize_proof_power(&time_range).await?;
         
         // Analyze efficiency trends
         let efficiency_trends = self.proof_power_analyzer
@@ -194,10 +203,10 @@ impl AnalyticsEngine {
             .find_optimization_opportunities().await?;
 
         Ok(ProofPowerTrends {
-            software_mining_percentage: software_percentage,
-            hardware_mining_percentage: hardware_percentage,
-            average_proof_power,
-            proof_power_distribution,
+            software_mining_percentage: summary.software_mining_percentage,
+            hardware_mining_percentage: summary.hardware_mining_percentage,
+            average_proof_power: summary.average_proof_power,
+            proof_power_distribution: summary.distribution,
             efficiency_trends,
             optimization_opportunities,
         })
@@ -210,15 +219,22 @@ impl AnalyticsEngine {
     ) -> Result<EonAnalytics> {
         debug!("Analyzing eon patterns");
 
-        let current_eon = self.get_current_eon().await?;
+        // Last EONS_ANALYZED transitions, oldest first
+        let transitions = self.eon_pattern_analyzer
+            .recent_transitions().await?;
+
+        let current_eon = match transitions.last() {
+            Some(transition) => transition.eon_number,
+            None => self.get_current_eon().await?,
+        };
         
-        // Analyze eon durations
+        // Model eon durations
         let eon_duration_analysis = self.eon_pattern_analyzer
-            .analyze_eon_durations().await?;
+            .analyze_eon_durations(&transitions);
         
-        // Detect transition patterns
+        // Detect hashrate-driven shortening regimes
         let transition_patterns = self.eon_pattern_analyzer
-            .detect_transition_patterns().await?;
+            .detect_transition_patterns(&transitions);
         
         // Analyze reward curve
         let reward_curve_analysis = self.eon_pattern_analyzer
@@ -336,83 +352,128 @@ impl AnalyticsEngine {
         })
     }
 
-    /// Execute custom analytics query
+    /// Execute a custom analytics query over blocks in the query's time range. Query text is
+    /// parsed and checked against `user_tier` before any data is fetched.
     pub async fn execute_custom_query(
         &self,
         query: CustomAnalyticsQuery,
+        user_tier: UserTier,
     ) -> Result<CustomAnalyticsResult> {
-        info!("Executing custom analytics query: {}", query.query_type);
+        info!("Executing custom analytics query: {}", query.query);
 
         let start_time = std::time::Instant::now();
-        
-        // Process the custom query based on type
-        let result_data = match query.query_type.as_str() {
-            "proof_power_correlation" => {
-                self.analyze_proof_power_correlation(&query.parameters).await?
-            }
-            "eon_efficiency_analysis" => {
-                self.analyze_eon_efficiency(&query.parameters).await?
-            }
-            "mining_optimization_analysis" => {
-                self.analyze_mining_optimization(&query.parameters).await?
-            }
-            "network_security_analysis" => {
-                self.analyze_network_security(&query.parameters).await?
-            }
-            _ => {
-                return Err(anyhow::anyhow!("Unknown query type: {}", query.query_type));
-            }
-        };
+
+        let granularity: Granularity = query.aggregation.parse()?;
+        let parsed = CustomQuery::parse(&query.query, granularity, user_tier)?;
+
+        let time_range = &query.time_range;
+        if time_range.end <= time_range.start {
+            return Err(QueryError::Invalid("Time range end must be after its start".to_string()).into());
+        }
+        if time_range.end - time_range.start > Duration::days(MAX_QUERY_RANGE_DAYS) {
+            return Err(QueryError::Invalid(format!(
+                "Time range can span at most {} days", MAX_QUERY_RANGE_DAYS
+            )).into());
+        }
+
+        // Start an hour early so the first block in range has its parent for block time
+        let headers = self.proof_power_analyzer.header_client
+            .fetch_headers(time_range.start - Duration::hours(1), time_range.end)
+            .await?;
+        let blocks: Vec<BlockProofPower> = block_proof_power(&headers)
+            .into_iter()
+            .filter(|block| block.timestamp >= time_range.start && block.timestamp < time_range.end)
+            .collect();
+
+        let output = parsed.execute(&blocks, time_range.start);
 
         let execution_time = start_time.elapsed();
         let query_id = uuid::Uuid::new_v4().to_string();
 
         Ok(CustomAnalyticsResult {
             query_id,
-            result_data,
+            result_data: serde_json::to_value(output)?,
             metadata: QueryMetadata {
                 execution_time: Duration::from_std(execution_time).unwrap_or(Duration::zero()),
-                data_points: 1000, // Placeholder
-                accuracy_score: 0.95,
+                data_points: blocks.len() as u64,
+                // Computed from every block in range, not sampled
+                accuracy_score: 1.0,
                 cache_hit: false,
             },
         })
     }
 
-    /// Export analytics data
+    /// Export analytics data as a Parquet file with one row group per requested data type.
+    /// Transactions come from the fee market analyzer, which the engine does not own.
     pub async fn export_analytics_data(
         &self,
         export_request: ExportRequest,
+        transactions: Vec<TransactionRecord>,
     ) -> Result<ExportResult> {
         info!("Exporting analytics data: format={}", export_request.export_format);
 
-        // Generate export data based on request
-        let export_id = uuid::Uuid::new_v4().to_string();
-        let download_url = format!("/api/downloads/{}", export_id);
-        
-        // Calculate file size (placeholder)
-        let file_size = 1024 * 1024; // 1MB placeholder
-        
-        // Set expiration time
-        let expires_at = Utc::now() + Duration::hours(24);
-
-        Ok(ExportResult {
-            export_id,
-            download_url,
-            file_size,
-            expires_at,
-        })
+        if !export_request.export_format.eq_ignore_ascii_case("parquet") {
+            return Err(anyhow::anyhow!("Unsupported export format: {}", export_request.export_format));
+        }
+        let storage = self.export_storage.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Export storage is not configured, set EXPORT_S3_BUCKET"))?;
+
+        let mut data_types: Vec<ExportDataType> = Vec::new();
+        for data_type in &export_request.data_types {
+            let data_type: ExportDataType = data_type.parse()?;
+            if !data_types.contains(&data_type) {
+                data_types.push(data_type);
+            }
+        }
+
+        let time_range = &export_request.time_range;
+        let in_range = |timestamp: DateTime<Utc>| timestamp >= time_range.start && timestamp <= time_range.end;
+
+        let mut sections = Vec::with_capacity(data_types.len());
+        for data_type in data_types {
+            let rows: Vec<ExportRow> = match data_type {
+                ExportDataType::ProofPower => self.proof_power_analyzer
+                    .summarize_proof_power(time_range).await?
+                    .distribution
+                    .iter()
+                    .map(ExportRow::from)
+                    .collect(),
+                ExportDataType::Roi => self.mining_performance_analyzer
+                    .analyze_mining_profitability().await?
+                    .roi_distribution
+                    .iter()
+                    .filter(|point| in_range(point.timestamp))
+                    .map(ExportRow::from)
+                    .collect(),
+                ExportDataType::Transactions => transactions.iter()
+                    .filter(|record| in_range(record.confirmed_at))
+                    .map(ExportRow::from)
+                    .collect(),
+            };
+            debug!("Exporting {} {} rows", rows.len(), data_type.as_str());
+            sections.push((data_type, rows));
+        }
+
+        upload_export(storage, &sections).await
     }
 
     // Helper methods
     async fn parse_time_range(&self, params: &HashMap<String, String>) -> Result<TimeRange> {
-        let default_start = Utc::now() - Duration::days(30);
-        let default_end = Utc::now();
-        
-        Ok(TimeRange {
-            start: default_start,
-            end: default_end,
-        })
+        let parse = |key: &str| -> Result<Option<DateTime<Utc>>> {
+            params.get(key)
+                .map(|value| DateTime::parse_from_rfc3339(value)
+                    .map(|time| time.with_timezone(&Utc))
+                    .map_err(|e| anyhow::anyhow!("Invalid {} '{}': {}", key, value, e)))
+                .transpose()
+        };
+
+        let end = parse("end")?.unwrap_or_else(Utc::now);
+        let start = parse("start")?.unwrap_or(end - Duration::days(30));
+        if start >= end {
+            return Err(anyhow::anyhow!("Time range start {} is not before end {}", start, end));
+        }
+
+        Ok(TimeRange { start, end })
     }
 
     async fn get_current_eon(&self) -> Result<u64> {
@@ -482,69 +543,13 @@ impl AnalyticsEngine {
             market_cap_projection: 2500000000.0, // $2.5B projection
         })
     }
-
-    // Custom query handlers
-    async fn analyze_proof_power_correlation(
-        &self,
-        _parameters: &HashMap<String, serde_json::Value>,
-    ) -> Result<serde_json::Value> {
-        // Analyze correlation between proof power and various metrics
-        Ok(serde_json::json!({
-            "correlation_matrix": {
-                "proof_power_vs_profitability": 0.78,
-                "proof_power_vs_efficiency": 0.65,
-                "proof_power_vs_network_security": 0.82
-            }
-        }))
-    }
-
-    async fn analyze_eon_efficiency(
-        &self,
-        _parameters: &HashMap<String, serde_json::Value>,
-    ) -> Result<serde_json::Value> {
-        // Analyze efficiency across different eons
-        Ok(serde_json::json!({
-            "eon_efficiency_scores": [
-                {"eon": 40, "efficiency": 0.78},
-                {"eon": 41, "efficiency": 0.82},
-                {"eon": 42, "efficiency": 0.85}
-            ]
-        }))
-    }
-
-    async fn analyze_mining_optimization(
-        &self,
-        _parameters: &HashMap<String, serde_json::Value>,
-    ) -> Result<serde_json::Value> {
-        // Analyze mining optimization opportunities
-        Ok(serde_json::json!({
-            "optimization_opportunities": [
-                {
-                    "type": "software_optimization",
-                    "potential_improvement": 0.15,
-                    "implementation_cost": "low"
-                }
-            ]
-        }))
-    }
-
-    async fn analyze_network_security(
-        &self,
-        _parameters: &HashMap<String, serde_json::Value>,
-    ) -> Result<serde_json::Value> {
-        // Analyze network security metrics
-        Ok(serde_json::json!({
-            "security_score": 0.92,
-            "attack_vectors": ["51% attack", "eclipse attack"],
-            "resistance_levels": [0.95, 0.88]
-        }))
-    }
 }
 
 // Implementation stubs for the analyzer components
 impl ProofPowerAnalyzer {
     pub async fn new() -> Self {
         Self {
+            header_client: BlockHeaderClient::from_env(),
             software_mining_tracker: SoftwareMiningTracker::new(),
             hardware_mining_tracker: HardwareMiningTracker::new(),
             efficiency_calculator: EfficiencyCalculator::new(),
@@ -553,24 +558,14 @@ impl ProofPowerAnalyzer {
         }
     }
 
-    pub async fn calculate_software_mining_percentage(&self, _time_range: &TimeRange) -> Result<f64> {
-        Ok(65.7) // 65.7% software mining
-    }
-
-    pub async fn calculate_average_proof_power(&self, _time_range: &TimeRange) -> Result<f64> {
-        Ok(1250000.0) // Average proof power
-    }
+    /// Fetches headers for the range plus the preceding week, which feeds the rolling average
+    pub async fn summarize_proof_power(&self, time_range: &TimeRange) -> Result<ProofPowerSummary> {
+        let headers = self.header_client
+            .fetch_headers(time_range.start - Duration::days(ROLLING_AVERAGE_DAYS), time_range.end)
+            .await?;
+        debug!("Fetched {} block headers for proof power trends", headers.len());
 
-    pub async fn get_proof_power_distribution(&self, _time_range: &TimeRange) -> Result<Vec<ProofPowerDataPoint>> {
-        Ok(vec![
-            ProofPowerDataPoint {
-                timestamp: Utc::now() - Duration::hours(1),
-                proof_power: 1200000.0,
-                hashrate: 150000.0,
-                efficiency_score: 0.85,
-                miner_count: 1500,
-            }
-        ])
+        Ok(summarize(&block_proof_power(&headers), time_range))
     }
 
     pub async fn analyze_efficiency_trends(&self, _time_range: &TimeRange) -> Result<EfficiencyTrends> {
@@ -598,7 +593,6 @@ impl ProofPowerAnalyzer {
 #[derive(Debug)] pub struct EfficiencyCalculator;
 #[derive(Debug)] pub struct OptimizationFinder;
 #[derive(Debug)] pub struct TrendPredictor;
-#[derive(Debug)] pub struct EonPatternAnalyzer;
 #[derive(Debug)] pub struct MiningPerformanceAnalyzer;
 #[derive(Debug)] pub struct NetworkHealthAnalyzer;
 #[derive(Debug)] pub struct TrendAnalyzer;
@@ -643,16 +637,27 @@ impl EfficiencyCalculator { pub fn new() -> Self { Self } }
 impl OptimizationFinder { pub fn new() -> Self { Self } }
 impl TrendPredictor { pub fn new() -> Self { Self } }
 impl EonPatternAnalyzer { 
-    pub async fn new() -> Self { Self }
-    pub async fn analyze_eon_durations(&self) -> Result<EonDurationAnalysis> {
-        Ok(EonDurationAnalysis {
-            average_duration: Duration::days(14),
-            duration_variance: 0.12,
-            trend_analysis: "stable".to_string(),
-            prediction_accuracy: 0.78,
-        })
+    pub async fn new() -> Self {
+        Self {
+            transition_store: EonTransitionStore::from_env(),
+            transition_detector: TransitionDetector,
+            reward_curve_analyzer: RewardCurveAnalyzer,
+            difficulty_progression_analyzer: DifficultyProgressionAnalyzer,
+            participation_analyzer: ParticipationAnalyzer,
+        }
+    }
+    pub async fn recent_transitions(&self) -> Result<Vec<EonTransition>> {
+        match &self.transition_store {
+            Some(store) => store.recent_transitions(EONS_ANALYZED).await,
+            None => Err(anyhow::anyhow!("DATABASE_URL not set, eon transitions unavailable")),
+        }
+    }
+    pub fn analyze_eon_durations(&self, transitions: &[EonTransition]) -> EonDurationAnalysis {
+        duration_analysis(transitions)
+    }
+    pub fn detect_transition_patterns(&self, transitions: &[EonTransition]) -> Vec<TransitionPattern> {
+        hashrate_shortening_regimes(transitions)
     }
-    pub async fn detect_transition_patterns(&self) -> Result<Vec<TransitionPattern>> { Ok(Vec::new()) }
     pub async fn analyze_reward_curve(&self) -> Result<RewardCurveAnalysis> {
         Ok(RewardCurveAnalysis {
             steepness_factor: 2.5,
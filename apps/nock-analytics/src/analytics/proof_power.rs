@@ -0,0 +1,328 @@
+// Proof Power Trends for NOCK Blockchain
+// Derives per-block proof power from node block headers and buckets it into hourly trends
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{ProofPowerDataPoint, TimeRange};
+
+/// Divisor applied to difficulty × block time
+pub const PROOF_POWER_SCALE: f64 = 1e12;
+
+/// Window of the rolling average reported with each hourly bucket
+pub const ROLLING_AVERAGE_DAYS: i64 = 7;
+
+const RPC_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Block header as returned by the node, with the block's transaction work summed by kind
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub height: u64,
+    pub hash: String,
+    pub timestamp: DateTime<Utc>,
+    pub difficulty: f64,
+    pub miner: String,
+    /// Nock opcode reduction steps across the block's transactions
+    pub nock_op_steps: u64,
+    /// Hash-dominant steps across the block's transactions
+    pub hash_steps: u64,
+}
+
+/// Fetches block headers from the Nockchain node RPC
+#[derive(Debug)]
+pub struct BlockHeaderClient {
+    rpc_url: String,
+    client: reqwest::Client,
+}
+
+impl BlockHeaderClient {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc_url,
+            client: reqwest::Client::builder()
+                .timeout(RPC_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("NOCKCHAIN_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:9090".to_string()))
+    }
+
+    /// Headers of blocks mined in `[start, end)`
+    pub async fn fetch_headers(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<BlockHeader>> {
+        self.client
+            .get(format!("{}/blocks/headers", self.rpc_url.trim_end_matches('/')))
+            .query(&[("start", start.timestamp()), ("end", end.timestamp())])
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected block header request")?
+            .json()
+            .await
+            .context("Malformed block header response")
+    }
+
+    /// Header of the current chain tip
+    pub async fn fetch_tip(&self) -> Result<BlockHeader> {
+        self.client
+            .get(format!("{}/blocks/tip", self.rpc_url.trim_end_matches('/')))
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected chain tip request")?
+            .json()
+            .await
+            .context("Malformed chain tip response")
+    }
+}
+
+/// Proof power of a single block, measured against its parent
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockProofPower {
+    pub height: u64,
+    pub hash: String,
+    pub timestamp: DateTime<Utc>,
+    pub difficulty: f64,
+    pub block_time_seconds: f64,
+    pub proof_power: f64,
+    pub miner: String,
+    pub nock_op_steps: u64,
+    pub hash_steps: u64,
+}
+
+/// Proof power of every header that has its parent in `headers`. The oldest header only
+/// anchors the first block time and gets no entry of its own.
+pub fn block_proof_power(headers: &[BlockHeader]) -> Vec<BlockProofPower> {
+    let mut sorted: Vec<&BlockHeader> = headers.iter().collect();
+    sorted.sort_by_key(|header| header.height);
+    sorted.dedup_by_key(|header| header.height);
+
+    sorted.windows(2)
+        .filter(|pair| pair[1].height == pair[0].height + 1)
+        .map(|pair| {
+            let (parent, block) = (pair[0], pair[1]);
+            let block_time_seconds = (block.timestamp - parent.timestamp).num_seconds().max(0) as f64;
+            BlockProofPower {
+                height: block.height,
+                hash: block.hash.clone(),
+                timestamp: block.timestamp,
+                difficulty: block.difficulty,
+                block_time_seconds,
+                proof_power: block.difficulty * block_time_seconds / PROOF_POWER_SCALE,
+                miner: block.miner.clone(),
+                nock_op_steps: block.nock_op_steps,
+                hash_steps: block.hash_steps,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct ProofPowerSummary {
+    pub average_proof_power: f64,
+    pub software_mining_percentage: f64,
+    pub hardware_mining_percentage: f64,
+    pub distribution: Vec<ProofPowerDataPoint>,
+}
+
+/// Hourly proof power for blocks in `range`. Blocks up to seven days before the range
+/// only feed the rolling average.
+pub fn summarize(blocks: &[BlockProofPower], range: &TimeRange) -> ProofPowerSummary {
+    let in_range: Vec<&BlockProofPower> = blocks.iter()
+        .filter(|block| block.timestamp >= range.start && block.timestamp < range.end)
+        .collect();
+
+    let average_proof_power = mean(in_range.iter().map(|block| block.proof_power));
+    let software_mining_percentage = software_share(&in_range) * 100.0;
+    let hardware_mining_percentage = if in_range.iter().any(|block| block.nock_op_steps + block.hash_steps > 0) {
+        100.0 - software_mining_percentage
+    } else {
+        0.0
+    };
+
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<&BlockProofPower>> = BTreeMap::new();
+    for block in &in_range {
+        let hour = block.timestamp.duration_trunc(Duration::hours(1)).unwrap_or(block.timestamp);
+        buckets.entry(hour).or_default().push(block);
+    }
+
+    let distribution = buckets.into_iter()
+        .map(|(hour, bucket)| {
+            let bucket_end = hour + Duration::hours(1);
+            let window_start = bucket_end - Duration::days(ROLLING_AVERAGE_DAYS);
+            let rolling_average = mean(blocks.iter()
+                .filter(|block| block.timestamp >= window_start && block.timestamp < bucket_end)
+                .map(|block| block.proof_power));
+
+            let proof_power = mean(bucket.iter().map(|block| block.proof_power));
+            let total_difficulty: f64 = bucket.iter().map(|block| block.difficulty).sum();
+            let total_block_time: f64 = bucket.iter().map(|block| block.block_time_seconds).sum();
+            let miners: HashSet<&str> = bucket.iter().map(|block| block.miner.as_str()).collect();
+
+            ProofPowerDataPoint {
+                timestamp: hour,
+                proof_power,
+                rolling_average_7d: rolling_average,
+                // Expected hashes per second to find these blocks in the time taken
+                hashrate: if total_block_time > 0.0 { total_difficulty / total_block_time } else { 0.0 },
+                // Hour's proof power relative to its weekly trend
+                efficiency_score: if rolling_average > 0.0 { proof_power / rolling_average } else { 0.0 },
+                miner_count: miners.len() as u64,
+                block_count: bucket.len() as u64,
+            }
+        })
+        .collect();
+
+    ProofPowerSummary {
+        average_proof_power,
+        software_mining_percentage,
+        hardware_mining_percentage,
+        distribution,
+    }
+}
+
+/// Share of transaction work spent on Nock opcodes. Opcode-heavy work favours general
+/// purpose (software) provers while hash-dominant work is where dedicated hardware wins.
+fn software_share(blocks: &[&BlockProofPower]) -> f64 {
+    let nock_steps: u64 = blocks.iter().map(|block| block.nock_op_steps).sum();
+    let hash_steps: u64 = blocks.iter().map(|block| block.hash_steps).sum();
+    if nock_steps + hash_steps == 0 {
+        return 0.0;
+    }
+    nock_steps as f64 / (nock_steps + hash_steps) as f64
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { 0.0 } else { sum / count as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    // Replayed node response: heights 500-511, newest first, with blocks before the range
+    const FIXTURE: &str = include_str!("../../tests/fixtures/block_headers.json");
+
+    fn fixture_headers() -> Vec<BlockHeader> {
+        serde_json::from_str(FIXTURE).unwrap()
+    }
+
+    fn fixture_range() -> TimeRange {
+        TimeRange {
+            start: Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 6, 10, 3, 0, 0).unwrap(),
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_block_proof_power() {
+        let blocks = block_proof_power(&fixture_headers());
+
+        // Height 500 has no parent in the fixture
+        assert_eq!(blocks.len(), 11);
+        assert_eq!(blocks[0].height, 501);
+        // 1e12 difficulty over a 30 minute block
+        assert_close(blocks[0].proof_power, 1800.0);
+        // 2e12 difficulty over a 20 minute block
+        let block_505 = blocks.iter().find(|block| block.height == 505).unwrap();
+        assert_close(block_505.block_time_seconds, 1200.0);
+        assert_close(block_505.proof_power, 2400.0);
+    }
+
+    #[test]
+    fn test_hourly_buckets_and_rolling_average() {
+        let summary = summarize(&block_proof_power(&fixture_headers()), &fixture_range());
+
+        // Seven blocks in range; the 03:00 block falls on the exclusive end
+        assert_close(summary.average_proof_power, 3000.0);
+
+        let hours: Vec<u32> = summary.distribution.iter()
+            .map(|point| point.timestamp.format("%H").to_string().parse().unwrap())
+            .collect();
+        assert_eq!(hours, vec![0, 1, 2]);
+
+        let proof_power: Vec<f64> = summary.distribution.iter().map(|point| point.proof_power).collect();
+        assert_close(proof_power[0], 2800.0);
+        assert_close(proof_power[1], 4500.0);
+        assert_close(proof_power[2], 1800.0);
+
+        // Rolling averages include the pre-range blocks
+        let rolling: Vec<f64> = summary.distribution.iter().map(|point| point.rolling_average_7d).collect();
+        assert_close(rolling[0], 2300.0);
+        assert_close(rolling[1], 2850.0);
+        assert_close(rolling[2], 2640.0);
+
+        let miners: Vec<u64> = summary.distribution.iter().map(|point| point.miner_count).collect();
+        assert_eq!(miners, vec![2, 1, 2]);
+        let blocks: Vec<u64> = summary.distribution.iter().map(|point| point.block_count).collect();
+        assert_eq!(blocks, vec![3, 2, 2]);
+    }
+
+    #[test]
+    fn test_software_hardware_split() {
+        let summary = summarize(&block_proof_power(&fixture_headers()), &fixture_range());
+
+        // 3000 Nock opcode steps against 2000 hash steps inside the range
+        assert_close(summary.software_mining_percentage, 60.0);
+        assert_close(summary.hardware_mining_percentage, 40.0);
+    }
+
+    #[test]
+    fn test_empty_range() {
+        let range = TimeRange {
+            start: Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2024, 7, 2, 0, 0, 0).unwrap(),
+        };
+        let summary = summarize(&block_proof_power(&fixture_headers()), &range);
+
+        assert_eq!(summary.average_proof_power, 0.0);
+        assert_eq!(summary.software_mining_percentage, 0.0);
+        assert_eq!(summary.hardware_mining_percentage, 0.0);
+        assert!(summary.distribution.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_headers_from_rpc() {
+        let server = MockServer::start().await;
+        let range = fixture_range();
+        Mock::given(method("GET"))
+            .and(path("/blocks/headers"))
+            .and(query_param("start", range.start.timestamp().to_string()))
+            .and(query_param("end", range.end.timestamp().to_string()))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(FIXTURE, "application/json"))
+            .mount(&server)
+            .await;
+
+        let client = BlockHeaderClient::new(format!("{}/", server.uri()));
+        let headers = client.fetch_headers(range.start, range.end).await.unwrap();
+
+        assert_eq!(headers, fixture_headers());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_headers_rpc_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(502))
+            .mount(&server)
+            .await;
+
+        let range = fixture_range();
+        let client = BlockHeaderClient::new(server.uri());
+        assert!(client.fetch_headers(range.start, range.end).await.is_err());
+    }
+}
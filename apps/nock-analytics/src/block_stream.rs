@@ -0,0 +1,323 @@
+// Real-time block stream for the NOCK Analytics Dashboard
+// Watches the chain tip and fans new blocks out to WebSocket subscribers
+
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::analytics::{block_proof_power, BlockHeader, BlockHeaderClient};
+
+/// Events buffered per subscriber before a slow one starts losing them
+pub const BLOCK_EVENT_BUFFER: usize = 64;
+
+/// How often the node is asked for the chain tip
+pub const BLOCK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockEvent {
+    pub height: u64,
+    pub hash: String,
+    pub difficulty: f64,
+    pub proof_power: f64,
+    pub miner: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Message sent to `/ws/blocks` subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamMessage {
+    Block(BlockEvent),
+    /// The subscriber fell behind and this many blocks were skipped
+    Gap { dropped: u64 },
+}
+
+/// Shared handle for publishing block events to every connected subscriber
+#[derive(Debug, Clone)]
+pub struct BlockEventBroadcaster {
+    sender: broadcast::Sender<BlockEvent>,
+}
+
+impl BlockEventBroadcaster {
+    pub fn new() -> Self {
+        Self::with_capacity(BLOCK_EVENT_BUFFER)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Returns how many subscribers the event was queued for
+    pub fn publish(&self, event: BlockEvent) -> usize {
+        // No receivers just means nobody is connected
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BlockEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+/// Next message for a subscriber, turning buffer overflow into a gap notification.
+/// Returns None once the broadcaster is gone.
+pub async fn next_message(receiver: &mut broadcast::Receiver<BlockEvent>) -> Option<StreamMessage> {
+    match receiver.recv().await {
+        Ok(event) => Some(StreamMessage::Block(event)),
+        Err(RecvError::Lagged(dropped)) => Some(StreamMessage::Gap { dropped }),
+        Err(RecvError::Closed) => None,
+    }
+}
+
+/// Pumps block events into a WebSocket until either side goes away
+pub async fn stream_blocks(mut socket: WebSocket, mut receiver: broadcast::Receiver<BlockEvent>) {
+    loop {
+        tokio::select! {
+            message = next_message(&mut receiver) => {
+                let Some(message) = message else { break };
+                let text = match serde_json::to_string(&message) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!("Failed to encode block event: {}", e);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                // Pings are answered by axum; anything else from the client is ignored
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    debug!("Block stream subscriber disconnected");
+}
+
+/// Polls the node for the chain tip and publishes every block it has not seen yet
+pub struct BlockWatcher {
+    client: BlockHeaderClient,
+    events: BlockEventBroadcaster,
+    last_seen: Option<BlockHeader>,
+}
+
+impl BlockWatcher {
+    pub fn new(client: BlockHeaderClient, events: BlockEventBroadcaster) -> Self {
+        Self {
+            client,
+            events,
+            last_seen: None,
+        }
+    }
+
+    /// Runs until the task is dropped; RPC failures are retried on the next tick
+    pub async fn start(mut self) {
+        let mut interval = tokio::time::interval(BLOCK_POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.poll_once().await {
+                warn!("Block watcher poll failed: {:#}", e);
+            }
+        }
+    }
+
+    /// Publishes blocks above the last seen height and returns how many were published.
+    /// The first poll only records the tip, since its parent is needed for proof power.
+    pub async fn poll_once(&mut self) -> Result<usize> {
+        let tip = self.client.fetch_tip().await?;
+        let Some(last) = self.last_seen.as_ref() else {
+            self.last_seen = Some(tip);
+            return Ok(0);
+        };
+        if tip.height <= last.height {
+            return Ok(0);
+        }
+
+        // Fill in any blocks mined between polls
+        let mut headers = if tip.height == last.height + 1 {
+            Vec::new()
+        } else {
+            self.client.fetch_headers(last.timestamp, tip.timestamp + chrono::Duration::seconds(1)).await?
+        };
+        headers.push(last.clone());
+        headers.push(tip.clone());
+
+        let mut published = 0;
+        for block in block_proof_power(&headers).into_iter().filter(|block| block.height > last.height) {
+            self.events.publish(BlockEvent {
+                height: block.height,
+                hash: block.hash,
+                difficulty: block.difficulty,
+                proof_power: block.proof_power,
+                miner: block.miner,
+                timestamp: block.timestamp,
+            });
+            published += 1;
+        }
+
+        self.last_seen = Some(tip);
+        Ok(published)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{State, WebSocketUpgrade};
+    use axum::routing::get;
+    use axum::Router;
+    use chrono::TimeZone;
+    use futures_util::StreamExt;
+    use std::net::SocketAddr;
+    use tokio_tungstenite::tungstenite;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn event(height: u64) -> BlockEvent {
+        BlockEvent {
+            height,
+            hash: format!("0x{:064x}", height),
+            difficulty: 2.0e12,
+            proof_power: 1200.0,
+            miner: "miner-a".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap() + chrono::Duration::minutes(height as i64),
+        }
+    }
+
+    fn header(height: u64, minute: i64) -> BlockHeader {
+        BlockHeader {
+            height,
+            hash: format!("0x{:064x}", height),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap() + chrono::Duration::minutes(minute),
+            difficulty: 2.0e12,
+            miner: "miner-b".to_string(),
+            nock_op_steps: 0,
+            hash_steps: 0,
+        }
+    }
+
+    async fn serve(events: BlockEventBroadcaster) -> SocketAddr {
+        let app = Router::new()
+            .route("/ws/blocks", get(|ws: WebSocketUpgrade, State(events): State<BlockEventBroadcaster>| async move {
+                let receiver = events.subscribe();
+                ws.on_upgrade(move |socket| stream_blocks(socket, receiver))
+            }))
+            .with_state(events);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr
+    }
+
+    async fn next_stream_message<S>(socket: &mut S, within: Duration) -> StreamMessage
+    where
+        S: futures_util::Stream<Item = Result<tungstenite::Message, tungstenite::Error>> + Unpin,
+    {
+        match tokio::time::timeout(within, socket.next()).await.expect("No message received in time") {
+            Some(Ok(tungstenite::Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("Unexpected frame: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_receives_block_events() {
+        let events = BlockEventBroadcaster::new();
+        let addr = serve(events.clone()).await;
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws/blocks", addr)).await.unwrap();
+        // The handler subscribes before completing the handshake
+        assert_eq!(events.subscriber_count(), 1);
+
+        events.publish(event(101));
+        events.publish(event(102));
+
+        assert_eq!(
+            next_stream_message(&mut socket, Duration::from_millis(100)).await,
+            StreamMessage::Block(event(101))
+        );
+        assert_eq!(
+            next_stream_message(&mut socket, Duration::from_secs(1)).await,
+            StreamMessage::Block(event(102))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_message_format() {
+        let block = serde_json::to_value(StreamMessage::Block(event(7))).unwrap();
+        assert_eq!(block["type"], "block");
+        assert_eq!(block["height"], 7);
+        assert_eq!(block["miner"], "miner-a");
+
+        let gap = serde_json::to_value(StreamMessage::Gap { dropped: 3 }).unwrap();
+        assert_eq!(gap, serde_json::json!({ "type": "gap", "dropped": 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_gets_gap_notification() {
+        let events = BlockEventBroadcaster::with_capacity(4);
+        let mut receiver = events.subscribe();
+        for height in 1..=10 {
+            events.publish(event(height));
+        }
+
+        // Six oldest events were overwritten; the newest four are still delivered
+        assert_eq!(next_message(&mut receiver).await, Some(StreamMessage::Gap { dropped: 6 }));
+        for height in 7..=10 {
+            assert_eq!(next_message(&mut receiver).await, Some(StreamMessage::Block(event(height))));
+        }
+
+        drop(events);
+        assert_eq!(next_message(&mut receiver).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_publishes_new_blocks() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/blocks/tip"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(header(100, 0)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blocks/tip"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(header(102, 30)))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/blocks/headers"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(vec![header(101, 10), header(102, 30)]))
+            .mount(&server)
+            .await;
+
+        let events = BlockEventBroadcaster::new();
+        let mut receiver = events.subscribe();
+        let mut watcher = BlockWatcher::new(BlockHeaderClient::new(server.uri()), events);
+
+        assert_eq!(watcher.poll_once().await.unwrap(), 0);
+        assert_eq!(watcher.poll_once().await.unwrap(), 2);
+        // Tip unchanged
+        assert_eq!(watcher.poll_once().await.unwrap(), 0);
+
+        let Some(StreamMessage::Block(first)) = next_message(&mut receiver).await else { panic!("Expected block") };
+        let Some(StreamMessage::Block(second)) = next_message(&mut receiver).await else { panic!("Expected block") };
+        assert_eq!((first.height, second.height), (101, 102));
+        // 2e12 difficulty over 10 and 20 minute blocks
+        assert_eq!(first.proof_power, 1200.0);
+        assert_eq!(second.proof_power, 2400.0);
+        assert_eq!(second.miner, "miner-b");
+    }
+}
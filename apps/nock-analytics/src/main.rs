@@ -2,9 +2,9 @@
 // Advanced analytics platform for NOCK blockchain with proof power trends and comprehensive metrics
 
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, Json},
+    extract::{ws::WebSocketUpgrade, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, Json, Response},
     routing::{get, post},
     Router,
 };
@@ -23,6 +23,7 @@ mod data_collector;
 mod ml_analytics;
 mod visualization;
 mod api;
+mod block_stream;
 
 use analytics::*;
 use metrics::*;
@@ -31,6 +32,7 @@ use data_collector::*;
 use ml_analytics::*;
 use visualization::*;
 use api::*;
+use block_stream::*;
 
 /// Main application state for the analytics dashboard
 #[derive(Debug)]
@@ -42,6 +44,8 @@ pub struct AppState {
     pub ml_analytics: Arc<RwLock<MLAnalytics>>,
     pub visualization_engine: Arc<RwLock<VisualizationEngine>>,
     pub real_time_monitor: Arc<RwLock<RealTimeMonitor>>,
+    pub fee_market_analyzer: Arc<RwLock<FeeMarketAnalyzer>>,
+    pub block_events: BlockEventBroadcaster,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -71,6 +75,8 @@ impl AppState {
             ml_analytics: Arc::new(RwLock::new(MLAnalytics::new().await)),
             visualization_engine: Arc::new(RwLock::new(VisualizationEngine::new().await)),
             real_time_monitor: Arc::new(RwLock::new(RealTimeMonitor::new().await)),
+            fee_market_analyzer: Arc::new(RwLock::new(FeeMarketAnalyzer::new())),
+            block_events: BlockEventBroadcaster::new(),
         }
     }
 }
@@ -94,10 +100,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/api/eon-analytics", get(get_eon_analytics))
         .route("/api/mining-analytics", get(get_mining_analytics))
         .route("/api/network-health", get(get_network_health))
+        .route("/api/analytics/fee-market-analysis", get(get_fee_market_analysis))
         .route("/api/predictions", get(get_predictions))
         .route("/api/real-time", get(get_real_time_data))
         .route("/api/custom-query", post(custom_analytics_query))
         .route("/api/export", post(export_analytics_data))
+        .route("/ws/blocks", get(ws_blocks))
         .nest_service("/static", ServeDir::new("static"))
         .layer(CorsLayer::permissive())
         .with_state(app_state);
@@ -144,6 +152,9 @@ async fn start_background_services(app_state: AppState) {
         }
     });
 
+    // Stream new blocks to WebSocket subscribers
+    tokio::spawn(BlockWatcher::new(BlockHeaderClient::from_env(), app_state.block_events.clone()).start());
+
     // Start ML analytics processing
     tokio::spawn({
         let app_state = app_state.clone();
@@ -180,6 +191,15 @@ async fn dashboard_home() -> Html<&'static str> {
     Html(include_str!("../templates/dashboard.html"))
 }
 
+async fn ws_blocks(
+    ws: WebSocketUpgrade,
+    State(app_state): State<AppState>,
+) -> Response {
+    // Subscribe before the upgrade so no block is missed during the handshake
+    let receiver = app_state.block_events.subscribe();
+    ws.on_upgrade(move |socket| stream_blocks(socket, receiver))
+}
+
 async fn get_analytics(
     State(app_state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
@@ -254,6 +274,32 @@ async fn get_network_health(
     }
 }
 
+async fn get_fee_market_analysis(
+    State(app_state): State<AppState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<FeeMarketAnalysisResponse>, StatusCode> {
+    let hours: u32 = match params.get("hours") {
+        Some(hours) => hours.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => 24,
+    };
+    if hours == 0 || hours as i64 > FEE_MARKET_RETENTION_HOURS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let target_minutes: u32 = match params.get("target_minutes") {
+        Some(minutes) => minutes.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => 10,
+    };
+
+    let fee_market_analyzer = app_state.fee_market_analyzer.read().await;
+    let report = fee_market_analyzer.compute(hours);
+
+    Ok(Json(FeeMarketAnalysisResponse {
+        optimal_fee_rate: report.optimal_fee_for_target_confirmation(target_minutes),
+        target_minutes,
+        report,
+    }))
+}
+
 async fn get_predictions(
     State(app_state): State<AppState>,
     Query(params): Query<HashMap<String, String>>,
@@ -285,16 +331,28 @@ async fn get_real_time_data(
 
 async fn custom_analytics_query(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
     Json(query): Json<CustomAnalyticsQuery>,
-) -> Result<Json<CustomAnalyticsResult>, StatusCode> {
+) -> Result<Json<CustomAnalyticsResult>, (StatusCode, Json<serde_json::Value>)> {
+    let query_error = |status: StatusCode, message: String| (status, Json(serde_json::json!({ "error": message })));
+
+    let user_tier = headers.get(USER_TIER_HEADER)
+        .map(|value| value.to_str().unwrap_or_default());
+    let user_tier = UserTier::from_header(user_tier)
+        .map_err(|e| query_error(StatusCode::BAD_REQUEST, e.to_string()))?;
+
     let analytics_engine = app_state.analytics_engine.read().await;
     
-    match analytics_engine.execute_custom_query(query).await {
+    match analytics_engine.execute_custom_query(query, user_tier).await {
         Ok(result) => Ok(Json(result)),
-        Err(e) => {
-            error!("Custom query error: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        Err(e) => match e.downcast_ref::<QueryError>() {
+            Some(QueryError::Invalid(message)) => Err(query_error(StatusCode::BAD_REQUEST, message.clone())),
+            Some(QueryError::Forbidden(message)) => Err(query_error(StatusCode::FORBIDDEN, message.clone())),
+            None => {
+                error!("Custom query error: {}", e);
+                Err(query_error(StatusCode::INTERNAL_SERVER_ERROR, "Query execution failed".to_string()))
+            }
+        },
     }
 }
 
@@ -302,9 +360,14 @@ async fn export_analytics_data(
     State(app_state): State<AppState>,
     Json(export_request): Json<ExportRequest>,
 ) -> Result<Json<ExportResult>, StatusCode> {
+    let transactions: Vec<TransactionRecord> = app_state.fee_market_analyzer.read().await
+        .transactions_between(export_request.time_range.start, export_request.time_range.end)
+        .iter()
+        .map(TransactionRecord::from)
+        .collect();
     let analytics_engine = app_state.analytics_engine.read().await;
     
-    match analytics_engine.export_analytics_data(export_request).await {
+    match analytics_engine.export_analytics_data(export_request, transactions).await {
         Ok(result) => Ok(Json(result)),
         Err(e) => {
             error!("Export error: {}", e);
@@ -328,9 +391,11 @@ pub struct ProofPowerTrends {
 pub struct ProofPowerDataPoint {
     pub timestamp: DateTime<Utc>,
     pub proof_power: f64,
+    pub rolling_average_7d: f64,
     pub hashrate: f64,
     pub efficiency_score: f64,
     pub miner_count: u64,
+    pub block_count: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -361,15 +426,22 @@ pub struct EonAnalytics {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EonDurationAnalysis {
+    pub eons_analyzed: u64,
+    pub mean_duration_blocks: f64,
+    pub duration_variance: f64, // blocks²
     pub average_duration: Duration,
-    pub duration_variance: f64,
+    pub distribution: Option<LogNormalFit>,
     pub trend_analysis: String,
-    pub prediction_accuracy: f64,
+    pub predictability_score: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TransitionPattern {
     pub pattern_name: String,
+    pub start_eon: u64,
+    pub mean_duration_before: f64,
+    pub mean_duration_after: f64,
+    pub hashrate_change: f64,
     pub frequency: f64,
     pub predictability_score: f64,
     pub market_impact: f64,
@@ -489,6 +561,13 @@ pub struct ScalabilityMetrics {
     pub bandwidth_utilization: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeeMarketAnalysisResponse {
+    pub report: FeeMarketReport,
+    pub target_minutes: u32,
+    pub optimal_fee_rate: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PredictionResults {
     pub difficulty_predictions: Vec<DifficultyPrediction>,
@@ -497,7 +576,7 @@ pub struct PredictionResults {
     pub network_growth_predictions: NetworkGrowthPredictions,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DifficultyPrediction {
     pub timestamp: DateTime<Utc>,
     pub predicted_difficulty: f64,
@@ -521,7 +600,7 @@ pub struct ProfitabilityPrediction {
     pub confidence_score: f64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NetworkGrowthPredictions {
     pub hashrate_growth: f64,
     pub node_growth: f64,
@@ -542,9 +621,10 @@ pub struct RealTimeData {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomAnalyticsQuery {
-    pub query_type: String,
-    pub parameters: HashMap<String, serde_json::Value>,
+    /// e.g. `SELECT avg(proof_power) AS pp WHERE block_time_seconds > 60`
+    pub query: String,
     pub time_range: TimeRange,
+    /// `none`, `hour` or `day`
     pub aggregation: String,
 }
 
@@ -577,6 +657,16 @@ pub struct ExportRequest {
     pub include_predictions: bool,
 }
 
+/// Confirmed transaction as written to analytics exports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionRecord {
+    pub confirmed_at: DateTime<Utc>,
+    pub fee: f64,
+    pub size_bytes: u64,
+    pub fee_rate: f64,
+    pub confirmation_seconds: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExportResult {
     pub export_id: String,
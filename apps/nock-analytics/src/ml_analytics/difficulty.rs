@@ -0,0 +1,382 @@
+// Difficulty Forecasting for NOCK Blockchain
+// LSTM inference over recent block difficulties, with an exponential smoothing fallback
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use log::warn;
+use ort::session::{builder::GraphOptimizationLevel, Session};
+use ort::value::Tensor;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::DifficultyPrediction;
+
+/// Number of most recent blocks the forecast is based on
+pub const DIFFICULTY_HISTORY_BLOCKS: i64 = 500;
+
+/// Blocks per model input window
+pub const WINDOW_LENGTH: usize = 24;
+
+/// Forward passes with dropout active used to estimate model uncertainty
+pub const MC_DROPOUT_SAMPLES: usize = 50;
+
+/// Level smoothing factor for the fallback predictor
+pub const SMOOTHING_ALPHA: f64 = 0.3;
+
+/// Two-sided 95% normal quantile used for the confidence intervals
+const Z_95: f64 = 1.96;
+
+const DEFAULT_MODEL_PATH: &str = "models/difficulty_lstm.onnx";
+
+/// Difficulty of a single block
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultySample {
+    pub height: u64,
+    pub difficulty: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Reads block difficulties recorded by the data collector in `blocks`
+#[derive(Debug)]
+pub struct DifficultyHistoryStore {
+    db_pool: PgPool,
+}
+
+impl DifficultyHistoryStore {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    /// Lazily connecting store for `DATABASE_URL`, or None when it is not configured
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        match PgPoolOptions::new().max_connections(5).connect_lazy(&url) {
+            Ok(pool) => Some(Self::new(pool)),
+            Err(e) => {
+                warn!("Invalid DATABASE_URL, difficulty predictions disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The most recent `limit` blocks, oldest first
+    pub async fn recent_difficulties(&self, limit: i64) -> Result<Vec<DifficultySample>> {
+        let rows: Vec<(i64, f64, DateTime<Utc>)> = sqlx::query_as(
+            r#"
+            SELECT height, difficulty, timestamp
+            FROM blocks
+            ORDER BY height DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.db_pool)
+        .await
+        .context("Failed to load block difficulties")?;
+
+        Ok(rows.into_iter()
+            .rev()
+            .map(|(height, difficulty, timestamp)| DifficultySample {
+                height: height as u64,
+                difficulty,
+                timestamp,
+            })
+            .collect())
+    }
+}
+
+/// Z-score scaling fitted to the history being forecast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normalizer {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Normalizer {
+    pub fn fit(values: &[f64]) -> Self {
+        let mean = values.iter().sum::<f64>() / values.len().max(1) as f64;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len().max(1) as f64;
+        // A flat history still needs a usable scale
+        let std_dev = if variance > 0.0 { variance.sqrt() } else { mean.abs().max(1.0) };
+        Self { mean, std_dev }
+    }
+
+    pub fn normalize(&self, value: f64) -> f64 {
+        (value - self.mean) / self.std_dev
+    }
+
+    pub fn denormalize(&self, value: f64) -> f64 {
+        value * self.std_dev + self.mean
+    }
+
+    /// Converts a spread in normalised units back to difficulty units
+    pub fn denormalize_spread(&self, spread: f64) -> f64 {
+        spread * self.std_dev
+    }
+}
+
+/// Every window of `length` consecutive values, oldest first
+pub fn rolling_windows(values: &[f32], length: usize) -> Vec<&[f32]> {
+    if length == 0 {
+        return Vec::new();
+    }
+    values.windows(length).collect()
+}
+
+/// Forecast for one block ahead of the latest, in difficulty units
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForecastStep {
+    pub blocks_ahead: usize,
+    pub predicted: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Pre-trained LSTM exported to ONNX. It takes `[batch, WINDOW_LENGTH, 1]` normalised
+/// difficulties and returns the next normalised difficulty per batch row. The model must be
+/// exported with dropout left on at inference, so repeated passes sample its uncertainty.
+pub struct LstmDifficultyModel {
+    // `Session::run` needs exclusive access and forecasts are served through `&self`
+    session: Mutex<Session>,
+    path: PathBuf,
+}
+
+impl std::fmt::Debug for LstmDifficultyModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LstmDifficultyModel").field("path", &self.path).finish_non_exhaustive()
+    }
+}
+
+impl LstmDifficultyModel {
+    pub fn load(path: &Path) -> Result<Self> {
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level1)?
+            .commit_from_file(path)
+            .with_context(|| format!("Failed to load difficulty model {}", path.display()))?;
+
+        Ok(Self {
+            session: Mutex::new(session),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Next normalised value for each input window
+    fn infer(&self, windows: &[Vec<f32>]) -> Result<Vec<f32>> {
+        let data: Vec<f32> = windows.iter().flatten().copied().collect();
+        let input = Tensor::from_array(([windows.len(), WINDOW_LENGTH, 1], data))?;
+        let mut session = self.session.lock().unwrap();
+        let outputs = session.run(ort::inputs![input])?;
+        let output = outputs.values().next().context("Difficulty model produced no output")?;
+        let (_, predictions) = output.try_extract_tensor::<f32>()?;
+
+        let predictions = predictions.to_vec();
+        if predictions.len() != windows.len() {
+            bail!("Difficulty model returned {} values for {} windows", predictions.len(), windows.len());
+        }
+        Ok(predictions)
+    }
+
+    /// Rolls each dropout sample forward on its own predictions, then takes the spread of
+    /// the samples at every step as the model's uncertainty
+    pub fn forecast(&self, history: &[f64], horizon: usize) -> Result<Vec<ForecastStep>> {
+        if history.len() < WINDOW_LENGTH {
+            bail!("Need {} blocks of history, have {}", WINDOW_LENGTH, history.len());
+        }
+        let normalizer = Normalizer::fit(history);
+        let normalized: Vec<f32> = history.iter().map(|d| normalizer.normalize(*d) as f32).collect();
+        let seed = rolling_windows(&normalized, WINDOW_LENGTH)
+            .last()
+            .map(|window| window.to_vec())
+            .context("No input window")?;
+
+        let mut paths = vec![seed; MC_DROPOUT_SAMPLES];
+        let mut steps = Vec::with_capacity(horizon);
+        for blocks_ahead in 1..=horizon {
+            let windows: Vec<Vec<f32>> = paths.iter()
+                .map(|path| path[path.len() - WINDOW_LENGTH..].to_vec())
+                .collect();
+            let samples = self.infer(&windows)?;
+            for (path, sample) in paths.iter_mut().zip(&samples) {
+                path.push(*sample);
+            }
+
+            let samples: Vec<f64> = samples.iter().map(|s| *s as f64).collect();
+            let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+            let std_dev = (samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64).sqrt();
+            let predicted = normalizer.denormalize(mean);
+            let spread = normalizer.denormalize_spread(Z_95 * std_dev);
+            steps.push(ForecastStep {
+                blocks_ahead,
+                predicted,
+                lower: predicted - spread,
+                upper: predicted + spread,
+            });
+        }
+        Ok(steps)
+    }
+}
+
+/// Simple exponential smoothing: a flat forecast at the smoothed level, with intervals from
+/// the one-step-ahead errors widening with the horizon
+pub fn exponential_smoothing_forecast(history: &[f64], horizon: usize) -> Result<Vec<ForecastStep>> {
+    let Some((first, rest)) = history.split_first() else {
+        bail!("Need at least one block of history");
+    };
+
+    let mut level = *first;
+    let mut squared_errors = 0.0;
+    for value in rest {
+        squared_errors += (value - level).powi(2);
+        level += SMOOTHING_ALPHA * (value - level);
+    }
+    let residual_std = if rest.is_empty() { 0.0 } else { (squared_errors / rest.len() as f64).sqrt() };
+
+    Ok((1..=horizon)
+        .map(|blocks_ahead| {
+            let spread = Z_95 * residual_std
+                * (1.0 + (blocks_ahead as f64 - 1.0) * SMOOTHING_ALPHA.powi(2)).sqrt();
+            ForecastStep {
+                blocks_ahead,
+                predicted: level,
+                lower: level - spread,
+                upper: level + spread,
+            }
+        })
+        .collect())
+}
+
+/// Model used for difficulty predictions
+#[derive(Debug)]
+pub enum DifficultyForecaster {
+    Lstm(LstmDifficultyModel),
+    ExponentialSmoothing,
+}
+
+impl DifficultyForecaster {
+    /// Loads the model at `DIFFICULTY_MODEL_PATH` (default `models/difficulty_lstm.onnx`)
+    pub fn from_env() -> Self {
+        let path = std::env::var("DIFFICULTY_MODEL_PATH").unwrap_or_else(|_| DEFAULT_MODEL_PATH.to_string());
+        Self::load(Path::new(&path))
+    }
+
+    /// Falls back to exponential smoothing when the model is missing or unusable
+    pub fn load(path: &Path) -> Self {
+        if !path.exists() {
+            warn!(
+                "Difficulty model {} not found, falling back to exponential smoothing",
+                path.display()
+            );
+            return Self::ExponentialSmoothing;
+        }
+        match LstmDifficultyModel::load(path) {
+            Ok(model) => Self::Lstm(model),
+            Err(e) => {
+                warn!("{:#}, falling back to exponential smoothing", e);
+                Self::ExponentialSmoothing
+            }
+        }
+    }
+
+    /// Predictions for the next `horizon` blocks, timed at the history's average block interval
+    pub fn predict(&self, history: &[DifficultySample], horizon: usize) -> Result<Vec<DifficultyPrediction>> {
+        let (Some(first), Some(last)) = (history.first(), history.last()) else {
+            bail!("No difficulty history to forecast from");
+        };
+        let difficulties: Vec<f64> = history.iter().map(|sample| sample.difficulty).collect();
+
+        let steps = match self {
+            Self::Lstm(model) if history.len() >= WINDOW_LENGTH => model.forecast(&difficulties, horizon)?,
+            _ => exponential_smoothing_forecast(&difficulties, horizon)?,
+        };
+
+        let block_interval = if history.len() > 1 {
+            (last.timestamp - first.timestamp) / (history.len() as i32 - 1)
+        } else {
+            Duration::zero()
+        };
+
+        Ok(steps.into_iter()
+            .map(|step| {
+                let prediction_horizon = block_interval * step.blocks_ahead as i32;
+                DifficultyPrediction {
+                    timestamp: last.timestamp + prediction_horizon,
+                    predicted_difficulty: step.predicted,
+                    confidence_interval: (step.lower.max(0.0), step.upper),
+                    prediction_horizon,
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn history(difficulties: &[f64]) -> Vec<DifficultySample> {
+        let start = Utc.with_ymd_and_hms(2024, 6, 10, 0, 0, 0).unwrap();
+        difficulties.iter()
+            .enumerate()
+            .map(|(i, difficulty)| DifficultySample {
+                height: 1000 + i as u64,
+                difficulty: *difficulty,
+                timestamp: start + Duration::minutes(10 * i as i64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_normalizer_round_trip() {
+        let values = [1.0e12, 1.2e12, 0.9e12, 1.1e12];
+        let normalizer = Normalizer::fit(&values);
+        assert!((normalizer.mean - 1.05e12).abs() < 1.0);
+        for value in values {
+            assert!((normalizer.denormalize(normalizer.normalize(value)) - value).abs() < 1e-3);
+        }
+
+        let flat = Normalizer::fit(&[5.0, 5.0, 5.0]);
+        assert_eq!(flat.normalize(5.0), 0.0);
+    }
+
+    #[test]
+    fn test_rolling_windows() {
+        let values: Vec<f32> = (0..30).map(|v| v as f32).collect();
+        let windows = rolling_windows(&values, WINDOW_LENGTH);
+        assert_eq!(windows.len(), 7);
+        assert_eq!(windows[0][0], 0.0);
+        assert_eq!(windows[6], &values[6..30]);
+        assert!(rolling_windows(&values[..10], WINDOW_LENGTH).is_empty());
+    }
+
+    #[test]
+    fn test_exponential_smoothing_intervals_widen() {
+        let difficulties: Vec<f64> = (0..100).map(|i| 1.0e12 + if i % 2 == 0 { 1.0e10 } else { -1.0e10 }).collect();
+        let steps = exponential_smoothing_forecast(&difficulties, 24).unwrap();
+
+        assert_eq!(steps.len(), 24);
+        assert!((steps[0].predicted - 1.0e12).abs() < 2.0e10);
+        assert!(steps.iter().all(|step| step.lower < step.predicted && step.predicted < step.upper));
+        assert!(steps[23].upper - steps[23].lower > steps[0].upper - steps[0].lower);
+    }
+
+    #[test]
+    fn test_missing_model_falls_back_to_smoothing() {
+        let forecaster = DifficultyForecaster::load(Path::new("/nonexistent/difficulty_lstm.onnx"));
+        assert!(matches!(forecaster, DifficultyForecaster::ExponentialSmoothing));
+
+        let predictions = forecaster.predict(&history(&[2.0e12; 50]), 6).unwrap();
+        assert_eq!(predictions.len(), 6);
+        assert_eq!(predictions[0].predicted_difficulty, 2.0e12);
+        assert_eq!(predictions[0].confidence_interval, (2.0e12, 2.0e12));
+        // Ten minute blocks
+        assert_eq!(predictions[5].prediction_horizon, Duration::minutes(60));
+        assert_eq!(predictions[5].timestamp, history(&[2.0e12; 50])[49].timestamp + Duration::minutes(60));
+    }
+
+    #[test]
+    fn test_empty_history_is_an_error() {
+        assert!(DifficultyForecaster::ExponentialSmoothing.predict(&[], 6).is_err());
+    }
+}
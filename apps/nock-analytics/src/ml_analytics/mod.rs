@@ -0,0 +1,79 @@
+// Machine Learning Analytics for NOCK Blockchain
+// Forecasting models run over collected chain data
+
+use anyhow::Result;
+use log::{debug, info, warn};
+use std::collections::HashMap;
+
+use crate::{DifficultyPrediction, NetworkGrowthPredictions, PredictionResults};
+
+pub mod difficulty;
+
+pub use difficulty::*;
+
+/// Blocks ahead forecast when the request does not ask for a horizon
+pub const DEFAULT_HORIZON_BLOCKS: usize = 24;
+
+/// Longest horizon a request may ask for
+pub const MAX_HORIZON_BLOCKS: usize = 144;
+
+#[derive(Debug)]
+pub struct MLAnalytics {
+    pub history_store: Option<DifficultyHistoryStore>,
+    pub difficulty_forecaster: DifficultyForecaster,
+    /// Default-horizon forecast refreshed by the batch job
+    pub latest_difficulty_predictions: Vec<DifficultyPrediction>,
+}
+
+impl MLAnalytics {
+    pub async fn new() -> Self {
+        Self {
+            history_store: DifficultyHistoryStore::from_env(),
+            difficulty_forecaster: DifficultyForecaster::from_env(),
+            latest_difficulty_predictions: Vec::new(),
+        }
+    }
+
+    /// Refreshes the cached default-horizon difficulty forecast
+    pub async fn process_analytics_batch(&mut self) -> Result<()> {
+        self.latest_difficulty_predictions = self.predict_difficulty(DEFAULT_HORIZON_BLOCKS).await?;
+        debug!("Refreshed {} difficulty predictions", self.latest_difficulty_predictions.len());
+        Ok(())
+    }
+
+    /// Predictions for the dashboard. `horizon_blocks` selects how far ahead difficulty is
+    /// forecast; other prediction types are not modeled here yet and are left empty.
+    pub async fn generate_predictions(&self, params: &HashMap<String, String>) -> Result<PredictionResults> {
+        let horizon = params.get("horizon_blocks")
+            .and_then(|value| value.parse::<usize>().ok())
+            .map(|horizon| horizon.clamp(1, MAX_HORIZON_BLOCKS))
+            .unwrap_or(DEFAULT_HORIZON_BLOCKS);
+
+        let difficulty_predictions = if horizon == DEFAULT_HORIZON_BLOCKS && !self.latest_difficulty_predictions.is_empty() {
+            self.latest_difficulty_predictions.clone()
+        } else {
+            self.predict_difficulty(horizon).await?
+        };
+
+        Ok(PredictionResults {
+            difficulty_predictions,
+            eon_transition_predictions: Vec::new(),
+            mining_profitability_predictions: Vec::new(),
+            network_growth_predictions: NetworkGrowthPredictions::default(),
+        })
+    }
+
+    async fn predict_difficulty(&self, horizon: usize) -> Result<Vec<DifficultyPrediction>> {
+        let Some(store) = &self.history_store else {
+            warn!("DATABASE_URL not set, skipping difficulty predictions");
+            return Ok(Vec::new());
+        };
+
+        let history = store.recent_difficulties(DIFFICULTY_HISTORY_BLOCKS).await?;
+        if history.is_empty() {
+            info!("No block history yet, skipping difficulty predictions");
+            return Ok(Vec::new());
+        }
+        self.difficulty_forecaster.predict(&history, horizon)
+    }
+}
@@ -0,0 +1,462 @@
+// NOCK RPC Client
+// Sends requests to the highest-priority healthy endpoint and fails over when one goes down
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn, debug};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use super::offline_queue::{OfflineOperationQueue, QueuedOperation};
+
+/// Used when `NOCK_RPC_ENDPOINTS` is not set
+pub const DEFAULT_RPC_ENDPOINT: &str = "https://rpc.nockchain.com";
+
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connectivity summary shown in the app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkStatus {
+    pub online: bool,
+    pub block_height: Option<u64>,
+    /// Endpoint currently receiving requests
+    pub connected_endpoint: Option<Url>,
+    /// Round trip of the last health check against `connected_endpoint`
+    pub latency_ms: Option<f64>,
+    pub healthy_endpoints: usize,
+    pub total_endpoints: usize,
+    pub queued_operations: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NodeStatus {
+    block_height: u64,
+}
+
+/// Health of one configured endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    pub url: Url,
+    /// Endpoints start healthy so the first request does not wait for a health check
+    pub healthy: bool,
+    pub latency_ms: Option<f64>,
+    pub last_checked: Option<DateTime<Utc>>,
+}
+
+/// Result of submitting a write operation
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubmitOutcome {
+    Sent(serde_json::Value),
+    /// No endpoint was reachable; the operation will be replayed on reconnect
+    Queued(uuid::Uuid),
+}
+
+#[derive(Debug)]
+struct ClientInner {
+    http: reqwest::Client,
+    /// In priority order, highest first
+    endpoints: RwLock<Vec<EndpointHealth>>,
+    offline_queue: Mutex<OfflineOperationQueue>,
+}
+
+/// RPC client over a prioritized list of NOCK node endpoints
+#[derive(Debug)]
+pub struct NockClient {
+    inner: Arc<ClientInner>,
+    health_task: Option<JoinHandle<()>>,
+}
+
+impl NockClient {
+    /// Reads comma-separated endpoints from `NOCK_RPC_ENDPOINTS` (highest priority first)
+    /// and starts the background health checks
+    pub async fn new() -> Self {
+        let configured = std::env::var("NOCK_RPC_ENDPOINTS").unwrap_or_else(|_| DEFAULT_RPC_ENDPOINT.to_string());
+        let mut endpoints: Vec<Url> = configured.split(',')
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .filter_map(|endpoint| match Url::parse(endpoint) {
+                Ok(url) => Some(url),
+                Err(e) => {
+                    warn!("Ignoring invalid RPC endpoint {}: {}", endpoint, e);
+                    None
+                }
+            })
+            .collect();
+        if endpoints.is_empty() {
+            endpoints.push(Url::parse(DEFAULT_RPC_ENDPOINT).expect("default endpoint is valid"));
+        }
+
+        let mut client = Self::with_endpoints(endpoints);
+        client.start_health_checks(HEALTH_CHECK_INTERVAL);
+        client
+    }
+
+    /// Client without background health checks; endpoints are in priority order
+    pub fn with_endpoints(endpoints: Vec<Url>) -> Self {
+        let endpoints = endpoints.into_iter()
+            .map(|url| EndpointHealth { url, healthy: true, latency_ms: None, last_checked: None })
+            .collect();
+
+        Self {
+            inner: Arc::new(ClientInner {
+                http: reqwest::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                    .expect("HTTP client configuration is valid"),
+                endpoints: RwLock::new(endpoints),
+                offline_queue: Mutex::new(OfflineOperationQueue::new()),
+            }),
+            health_task: None,
+        }
+    }
+
+    /// Pings every endpoint each `interval`, replaying queued operations on reconnect
+    pub fn start_health_checks(&mut self, interval: Duration) {
+        if let Some(task) = self.health_task.take() {
+            task.abort();
+        }
+        let inner = Arc::clone(&self.inner);
+        self.health_task = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                inner.check_endpoints().await;
+            }
+        }));
+    }
+
+    /// Runs one round of health checks immediately
+    pub async fn check_endpoints(&self) {
+        self.inner.check_endpoints().await;
+    }
+
+    /// Highest-priority endpoint currently considered healthy
+    pub async fn active_endpoint(&self) -> Option<Url> {
+        self.inner.endpoints.read().await.iter()
+            .find(|endpoint| endpoint.healthy)
+            .map(|endpoint| endpoint.url.clone())
+    }
+
+    pub async fn endpoints(&self) -> Vec<EndpointHealth> {
+        self.inner.endpoints.read().await.clone()
+    }
+
+    pub async fn queued_operations(&self) -> usize {
+        self.inner.offline_queue.lock().await.len()
+    }
+
+    /// GET `path` from the first endpoint that answers
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.inner.send(reqwest::Method::GET, path, None).await
+    }
+
+    /// POST `path` to the first endpoint that answers, without offline queueing
+    pub async fn post<T: DeserializeOwned>(&self, path: &str, body: &serde_json::Value) -> Result<T> {
+        self.inner.send(reqwest::Method::POST, path, Some(body)).await
+    }
+
+    /// POSTs a write operation, queueing it if no endpoint is reachable. Operations queued
+    /// earlier are sent first so writes keep their submission order.
+    pub async fn submit(&self, path: &str, body: serde_json::Value) -> Result<SubmitOutcome> {
+        if !self.inner.offline_queue.lock().await.is_empty() {
+            self.inner.replay_offline_queue().await;
+        }
+
+        let mut queue = self.inner.offline_queue.lock().await;
+        if queue.is_empty() {
+            match self.inner.send(reqwest::Method::POST, path, Some(&body)).await {
+                Ok(response) => return Ok(SubmitOutcome::Sent(response)),
+                Err(e) if is_client_error(&e) => return Err(e),
+                Err(e) => warn!("All RPC endpoints failed, queueing operation to {}: {}", path, e),
+            }
+        }
+
+        let operation = QueuedOperation::new(path, body);
+        let id = operation.id;
+        queue.push(operation);
+        Ok(SubmitOutcome::Queued(id))
+    }
+
+    pub async fn get_network_status(&self) -> Result<NetworkStatus> {
+        let node_status = self.get::<NodeStatus>("/api/v1/network/status").await;
+        if let Err(e) = &node_status {
+            debug!("Network status unavailable: {}", e);
+        }
+
+        let endpoints = self.inner.endpoints.read().await;
+        let connected = endpoints.iter().find(|endpoint| endpoint.healthy);
+
+        Ok(NetworkStatus {
+            online: node_status.is_ok(),
+            block_height: node_status.ok().map(|status| status.block_height),
+            connected_endpoint: connected.map(|endpoint| endpoint.url.clone()),
+            latency_ms: connected.and_then(|endpoint| endpoint.latency_ms),
+            healthy_endpoints: endpoints.iter().filter(|endpoint| endpoint.healthy).count(),
+            total_endpoints: endpoints.len(),
+            queued_operations: self.inner.offline_queue.lock().await.len(),
+        })
+    }
+}
+
+impl Drop for NockClient {
+    fn drop(&mut self) {
+        if let Some(task) = self.health_task.take() {
+            task.abort();
+        }
+    }
+}
+
+impl ClientInner {
+    async fn check_endpoints(&self) {
+        let urls: Vec<Url> = self.endpoints.read().await.iter().map(|endpoint| endpoint.url.clone()).collect();
+
+        let mut results = Vec::with_capacity(urls.len());
+        for url in &urls {
+            results.push(self.ping(url).await);
+        }
+
+        let was_online;
+        let now_online;
+        {
+            let mut endpoints = self.endpoints.write().await;
+            was_online = endpoints.iter().any(|endpoint| endpoint.healthy);
+            for (endpoint, latency) in endpoints.iter_mut().zip(results) {
+                if endpoint.healthy && latency.is_none() {
+                    warn!("RPC endpoint {} is unhealthy", endpoint.url);
+                } else if !endpoint.healthy && latency.is_some() {
+                    info!("RPC endpoint {} recovered", endpoint.url);
+                }
+                endpoint.healthy = latency.is_some();
+                endpoint.latency_ms = latency;
+                endpoint.last_checked = Some(Utc::now());
+            }
+            now_online = endpoints.iter().any(|endpoint| endpoint.healthy);
+        }
+
+        if now_online && !was_online {
+            info!("Connectivity restored");
+        }
+        if now_online {
+            self.replay_offline_queue().await;
+        }
+    }
+
+    /// Round-trip time of `GET /health` in milliseconds, or None if the endpoint is down
+    async fn ping(&self, url: &Url) -> Option<f64> {
+        let health_url = url.join("/health").ok()?;
+        let started = Instant::now();
+        let response = self.http.get(health_url).timeout(HEALTH_CHECK_TIMEOUT).send().await.ok()?;
+        response.status().is_success().then(|| started.elapsed().as_secs_f64() * 1000.0)
+    }
+
+    /// Tries healthy endpoints in priority order, then the unhealthy ones in case they recovered
+    /// since the last health check. Endpoints that fail are marked unhealthy.
+    async fn send<T: DeserializeOwned>(&self, method: reqwest::Method, path: &str, body: Option<&serde_json::Value>) -> Result<T> {
+        let candidates: Vec<Url> = {
+            let endpoints = self.endpoints.read().await;
+            let healthy = endpoints.iter().filter(|endpoint| endpoint.healthy);
+            let unhealthy = endpoints.iter().filter(|endpoint| !endpoint.healthy);
+            healthy.chain(unhealthy).map(|endpoint| endpoint.url.clone()).collect()
+        };
+
+        let mut last_error = anyhow!("No RPC endpoints configured");
+        for url in candidates {
+            match self.send_to(&url, method.clone(), path, body).await {
+                Ok(response) => {
+                    self.set_healthy(&url, true).await;
+                    return Ok(response);
+                }
+                // The endpoint answered; another endpoint would reject the request too
+                Err(e) if is_client_error(&e) => return Err(e),
+                Err(e) => {
+                    debug!("Request to {} failed: {}", url, e);
+                    self.set_healthy(&url, false).await;
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn send_to<T: DeserializeOwned>(&self, url: &Url, method: reqwest::Method, path: &str, body: Option<&serde_json::Value>) -> Result<T> {
+        let target = url.join(path).with_context(|| format!("Invalid RPC path {}", path))?;
+        let mut request = self.http.request(method, target);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    async fn set_healthy(&self, url: &Url, healthy: bool) {
+        let mut endpoints = self.endpoints.write().await;
+        if let Some(endpoint) = endpoints.iter_mut().find(|endpoint| &endpoint.url == url) {
+            if endpoint.healthy && !healthy {
+                warn!("Failing over from RPC endpoint {}", url);
+            }
+            endpoint.healthy = healthy;
+        }
+    }
+
+    /// Sends queued operations in order, stopping at the first one that still fails
+    async fn replay_offline_queue(&self) {
+        let mut queue = self.offline_queue.lock().await;
+        while let Some(operation) = queue.front().cloned() {
+            match self.send::<serde_json::Value>(reqwest::Method::POST, &operation.path, Some(&operation.body)).await {
+                Ok(_) => {
+                    info!("Replayed queued operation {} to {}", operation.id, operation.path);
+                    queue.pop_front();
+                }
+                Err(e) if is_client_error(&e) => {
+                    warn!("Dropping queued operation {} rejected by the node: {}", operation.id, e);
+                    queue.pop_front();
+                }
+                Err(e) => {
+                    debug!("Replay paused, {} operations still queued: {}", queue.len(), e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 4xx responses mean the request itself was rejected, not that the endpoint is down
+pub fn is_client_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| status.is_client_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn healthy_node(block_height: u64) -> MockServer {
+        let server = MockServer::start().await;
+        mount_healthy(&server, block_height).await;
+        server
+    }
+
+    async fn mount_healthy(server: &MockServer, block_height: u64) {
+        Mock::given(method("GET")).and(path("/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(server).await;
+        Mock::given(method("GET")).and(path("/api/v1/network/status"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "block_height": block_height })))
+            .mount(server).await;
+    }
+
+    async fn failing_node() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server).await;
+        server
+    }
+
+    fn url(server: &MockServer) -> Url {
+        Url::parse(&server.uri()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_over_to_next_healthy_endpoint() {
+        let primary = failing_node().await;
+        let secondary = healthy_node(1200).await;
+        let client = NockClient::with_endpoints(vec![url(&primary), url(&secondary)]);
+
+        client.check_endpoints().await;
+        let status = client.get_network_status().await.unwrap();
+
+        assert!(status.online);
+        assert_eq!(status.block_height, Some(1200));
+        assert_eq!(status.connected_endpoint, Some(url(&secondary)));
+        assert!(status.latency_ms.is_some());
+        assert_eq!(status.healthy_endpoints, 1);
+    }
+
+    #[tokio::test]
+    async fn test_request_failure_marks_endpoint_unhealthy_without_health_check() {
+        let primary = failing_node().await;
+        let secondary = healthy_node(7).await;
+        let client = NockClient::with_endpoints(vec![url(&primary), url(&secondary)]);
+
+        // Both start healthy; the failed request itself triggers the failover
+        let status = client.get_network_status().await.unwrap();
+        assert_eq!(status.block_height, Some(7));
+        assert_eq!(client.active_endpoint().await, Some(url(&secondary)));
+    }
+
+    #[tokio::test]
+    async fn test_recovered_primary_is_preferred_again() {
+        let primary = failing_node().await;
+        let secondary = healthy_node(1).await;
+        let client = NockClient::with_endpoints(vec![url(&primary), url(&secondary)]);
+
+        client.check_endpoints().await;
+        assert_eq!(client.active_endpoint().await, Some(url(&secondary)));
+
+        primary.reset().await;
+        mount_healthy(&primary, 2).await;
+        client.check_endpoints().await;
+
+        assert_eq!(client.active_endpoint().await, Some(url(&primary)));
+        assert_eq!(client.get_network_status().await.unwrap().block_height, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_operations_queue_during_outage_and_replay_on_reconnect() {
+        let node = failing_node().await;
+        let client = NockClient::with_endpoints(vec![url(&node)]);
+
+        client.check_endpoints().await;
+        let status = client.get_network_status().await.unwrap();
+        assert!(!status.online);
+        assert_eq!(status.connected_endpoint, None);
+
+        let first = client.submit("/api/v1/transactions", json!({ "nonce": 1 })).await.unwrap();
+        let second = client.submit("/api/v1/transactions", json!({ "nonce": 2 })).await.unwrap();
+        assert!(matches!(first, SubmitOutcome::Queued(_)));
+        assert!(matches!(second, SubmitOutcome::Queued(_)));
+        assert_eq!(client.queued_operations().await, 2);
+
+        node.reset().await;
+        mount_healthy(&node, 10).await;
+        for nonce in [1, 2, 3] {
+            Mock::given(method("POST")).and(path("/api/v1/transactions")).and(body_json(json!({ "nonce": nonce })))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "accepted": true })))
+                .expect(1)
+                .mount(&node).await;
+        }
+
+        client.check_endpoints().await;
+        assert_eq!(client.queued_operations().await, 0);
+
+        let sent = client.submit("/api/v1/transactions", json!({ "nonce": 3 })).await.unwrap();
+        assert_eq!(sent, SubmitOutcome::Sent(json!({ "accepted": true })));
+        assert_eq!(client.queued_operations().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request_does_not_fail_over() {
+        let primary = healthy_node(1).await;
+        let secondary = healthy_node(1).await;
+        Mock::given(method("POST")).and(path("/api/v1/transactions"))
+            .respond_with(ResponseTemplate::new(422))
+            .mount(&primary).await;
+        let client = NockClient::with_endpoints(vec![url(&primary), url(&secondary)]);
+
+        assert!(client.submit("/api/v1/transactions", json!({ "nonce": 1 })).await.is_err());
+        assert_eq!(client.active_endpoint().await, Some(url(&primary)));
+        assert_eq!(client.queued_operations().await, 0);
+    }
+}
@@ -0,0 +1,8 @@
+// Core NOCK Client for Mobile
+// RPC access to NOCK nodes with endpoint failover and offline operation queueing
+
+pub mod client;
+pub mod offline_queue;
+
+pub use client::*;
+pub use offline_queue::*;
@@ -0,0 +1,69 @@
+// Offline Operation Queue
+// Holds write operations submitted while no RPC endpoint is reachable
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use log::warn;
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+/// Oldest operations are dropped once the queue reaches this size
+pub const MAX_QUEUED_OPERATIONS: usize = 500;
+
+/// A write request waiting to be sent to an RPC endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedOperation {
+    pub id: Uuid,
+    /// Path relative to the endpoint, e.g. `/api/v1/transactions`
+    pub path: String,
+    pub body: serde_json::Value,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl QueuedOperation {
+    pub fn new(path: impl Into<String>, body: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            path: path.into(),
+            body,
+            queued_at: Utc::now(),
+        }
+    }
+}
+
+/// FIFO of operations replayed in submission order once connectivity returns
+#[derive(Debug, Default)]
+pub struct OfflineOperationQueue {
+    operations: VecDeque<QueuedOperation>,
+}
+
+impl OfflineOperationQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, operation: QueuedOperation) {
+        if self.operations.len() >= MAX_QUEUED_OPERATIONS {
+            if let Some(dropped) = self.operations.pop_front() {
+                warn!("Offline queue full, dropping operation {} to {}", dropped.id, dropped.path);
+            }
+        }
+        self.operations.push_back(operation);
+    }
+
+    pub fn front(&self) -> Option<&QueuedOperation> {
+        self.operations.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<QueuedOperation> {
+        self.operations.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
@@ -0,0 +1,378 @@
+// Deep Link Handling for NOCK Mobile
+// Parses nock:// payment URIs used by QR codes and payment requests
+
+use serde::{Deserialize, Serialize};
+use log::{error, warn};
+use std::fmt;
+use tauri::Manager;
+use url::Url;
+
+/// URI scheme registered with the operating system
+pub const NOCK_URI_SCHEME: &str = "nock";
+
+/// Total NOCK supply, used as the upper bound for payment amounts
+pub const MAX_PAYMENT_AMOUNT: f64 = 4_294_967_296.0;
+
+/// Maximum memo length accepted from a deep link
+pub const MAX_MEMO_LENGTH: usize = 256;
+
+/// NOCK addresses are a hex-encoded 32-byte public key followed by a 4-byte blake3 checksum
+const ADDRESS_KEY_LENGTH: usize = 32;
+const ADDRESS_CHECKSUM_LENGTH: usize = 4;
+
+/// Event carrying a `DeepLinkPaymentRequest` to the frontend
+pub const PAYMENT_REQUEST_EVENT: &str = "deep-link-payment-request";
+
+/// Event carrying a user-facing message when a link cannot be opened
+pub const DEEP_LINK_ERROR_EVENT: &str = "deep-link-error";
+
+/// Action requested by a deep link
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DeepLinkAction {
+    InitiatePayment {
+        to: String,
+        amount: f64,
+        memo: Option<String>,
+    },
+}
+
+/// Errors produced while parsing a deep link
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeepLinkError {
+    MalformedUri(String),
+    MissingField(&'static str),
+    InvalidAddress(String),
+    AmountOutOfRange(f64),
+}
+
+impl fmt::Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLinkError::MalformedUri(reason) => write!(f, "Malformed URI: {}", reason),
+            DeepLinkError::MissingField(field) => write!(f, "Missing required field: {}", field),
+            DeepLinkError::InvalidAddress(address) => write!(f, "Invalid NOCK address: {}", address),
+            DeepLinkError::AmountOutOfRange(amount) => write!(f, "Amount out of range: {}", amount),
+        }
+    }
+}
+
+impl std::error::Error for DeepLinkError {}
+
+/// Payment request shown to the user for confirmation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeepLinkPaymentRequest {
+    pub uri: String,
+    pub to: String,
+    pub amount: f64,
+    pub note: Option<String>,
+}
+
+/// Receives nock:// URIs from the OS and hands them to the frontend
+#[derive(Clone)]
+pub struct DeepLinkHandler {
+    app: tauri::AppHandle,
+}
+
+impl DeepLinkHandler {
+    pub fn new(app: tauri::AppHandle) -> Self {
+        Self { app }
+    }
+
+    pub fn handle(&self, uri: &str) {
+        let action = match handle_deep_link(uri) {
+            Ok(action) => action,
+            Err(e) => {
+                warn!("Ignoring deep link {}: {}", uri, e);
+                if let Err(e) = self.app.emit_all(DEEP_LINK_ERROR_EVENT, format!("This payment link cannot be opened. {}", e)) {
+                    error!("Failed to report deep link error: {}", e);
+                }
+                return;
+            }
+        };
+
+        match action {
+            DeepLinkAction::InitiatePayment { to, amount, memo } => {
+                let request = DeepLinkPaymentRequest { uri: uri.to_string(), to, amount, note: memo };
+                if let Err(e) = self.app.emit_all(PAYMENT_REQUEST_EVENT, &request) {
+                    error!("Failed to emit payment request: {}", e);
+                }
+                self.open_confirmation_window(uri);
+            }
+        }
+    }
+
+    /// Payments are never submitted directly; the user confirms in a dedicated window
+    fn open_confirmation_window(&self, uri: &str) {
+        let encoded_uri: String = url::form_urlencoded::byte_serialize(uri.as_bytes()).collect();
+        let result = tauri::WindowBuilder::new(
+            &self.app,
+            "payment-confirm",
+            tauri::WindowUrl::App(format!("confirm-payment.html?uri={}", encoded_uri).into()),
+        )
+        .title("Confirm NOCK Payment")
+        .inner_size(420.0, 560.0)
+        .resizable(false)
+        .focused(true)
+        .build();
+
+        if let Err(e) = result {
+            error!("Failed to open payment confirmation window: {}", e);
+        }
+    }
+}
+
+/// Parse a `nock://pay?to=<address>&amount=<nock>&note=<text>` URI. `memo` is accepted as
+/// an alias for `note`.
+pub fn handle_deep_link(uri: &str) -> Result<DeepLinkAction, DeepLinkError> {
+    let url = Url::parse(uri).map_err(|e| DeepLinkError::MalformedUri(e.to_string()))?;
+
+    if url.scheme() != NOCK_URI_SCHEME {
+        return Err(DeepLinkError::MalformedUri(format!("unsupported scheme '{}'", url.scheme())));
+    }
+
+    match url.host_str() {
+        Some("pay") => parse_payment(&url),
+        Some(action) => Err(DeepLinkError::MalformedUri(format!("unsupported action '{}'", action))),
+        None => Err(DeepLinkError::MalformedUri("missing action".to_string())),
+    }
+}
+
+fn parse_payment(url: &Url) -> Result<DeepLinkAction, DeepLinkError> {
+    let mut to = None;
+    let mut amount = None;
+    let mut memo = None;
+
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "to" => to = Some(value.into_owned()),
+            "amount" => amount = Some(value.into_owned()),
+            "note" | "memo" => memo = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let to = to.filter(|v| !v.is_empty()).ok_or(DeepLinkError::MissingField("to"))?;
+    if !validate_nock_address(&to) {
+        return Err(DeepLinkError::InvalidAddress(to));
+    }
+
+    let raw_amount = amount
+        .filter(|v| !v.is_empty())
+        .ok_or(DeepLinkError::MissingField("amount"))?;
+    let amount: f64 = raw_amount
+        .parse()
+        .map_err(|_| DeepLinkError::MalformedUri("amount is not a number".to_string()))?;
+
+    if !amount.is_finite() || amount <= 0.0 || amount > MAX_PAYMENT_AMOUNT {
+        return Err(DeepLinkError::AmountOutOfRange(amount));
+    }
+    if !is_plain_decimal(&raw_amount) {
+        return Err(DeepLinkError::MalformedUri("amount must be a plain decimal".to_string()));
+    }
+
+    if let Some(ref text) = memo {
+        if text.len() > MAX_MEMO_LENGTH {
+            return Err(DeepLinkError::MalformedUri(format!(
+                "memo exceeds {} bytes",
+                MAX_MEMO_LENGTH
+            )));
+        }
+    }
+
+    Ok(DeepLinkAction::InitiatePayment {
+        to,
+        amount,
+        memo: memo.filter(|m| !m.is_empty()),
+    })
+}
+
+/// Digits with an optional fractional part, e.g. `12` or `0.5`; exponents and signs are rejected
+fn is_plain_decimal(value: &str) -> bool {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    !whole.is_empty() && digits(whole) && digits(fraction) && !value.ends_with('.')
+}
+
+/// `nock://pay` URI requesting `amount` NOCK to `to`
+pub fn payment_uri(to: &str, amount: f64, note: Option<&str>) -> Result<String, DeepLinkError> {
+    if !validate_nock_address(to) {
+        return Err(DeepLinkError::InvalidAddress(to.to_string()));
+    }
+    if !amount.is_finite() || amount <= 0.0 || amount > MAX_PAYMENT_AMOUNT {
+        return Err(DeepLinkError::AmountOutOfRange(amount));
+    }
+
+    let mut uri = Url::parse(&format!("{}://pay", NOCK_URI_SCHEME)).expect("static URI is valid");
+    {
+        let mut query = uri.query_pairs_mut();
+        query.append_pair("to", to);
+        query.append_pair("amount", &amount.to_string());
+        if let Some(note) = note.filter(|note| !note.is_empty()) {
+            if note.len() > MAX_MEMO_LENGTH {
+                return Err(DeepLinkError::MalformedUri(format!("note exceeds {} bytes", MAX_MEMO_LENGTH)));
+            }
+            query.append_pair("note", note);
+        }
+    }
+    Ok(uri.to_string())
+}
+
+/// SVG QR code encoding `uri`
+pub fn payment_qr_svg(uri: &str) -> Result<String, DeepLinkError> {
+    let code = qrcode::QrCode::with_error_correction_level(uri, qrcode::EcLevel::M)
+        .map_err(|e| DeepLinkError::MalformedUri(e.to_string()))?;
+    Ok(code.render::<qrcode::render::svg::Color>()
+        .min_dimensions(256, 256)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Validate a NOCK address and its trailing blake3 checksum
+pub fn validate_nock_address(address: &str) -> bool {
+    let bytes = match hex::decode(address) {
+        Ok(bytes) => bytes,
+        Err(_) => return false,
+    };
+
+    if bytes.len() != ADDRESS_KEY_LENGTH + ADDRESS_CHECKSUM_LENGTH {
+        return false;
+    }
+
+    let (key, checksum) = bytes.split_at(ADDRESS_KEY_LENGTH);
+    &blake3::hash(key).as_bytes()[..ADDRESS_CHECKSUM_LENGTH] == checksum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_address() -> String {
+        let key = [7u8; ADDRESS_KEY_LENGTH];
+        let mut bytes = key.to_vec();
+        bytes.extend_from_slice(&blake3::hash(&key).as_bytes()[..ADDRESS_CHECKSUM_LENGTH]);
+        hex::encode(bytes)
+    }
+
+    #[test]
+    fn test_valid_payment_uri() {
+        let address = test_address();
+        let uri = format!("nock://pay?to={}&amount=12.5&memo=Coffee%20beans", address);
+
+        let action = handle_deep_link(&uri).unwrap();
+        assert_eq!(
+            action,
+            DeepLinkAction::InitiatePayment {
+                to: address,
+                amount: 12.5,
+                memo: Some("Coffee beans".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_memo_is_optional() {
+        let uri = format!("nock://pay?to={}&amount=1", test_address());
+
+        match handle_deep_link(&uri).unwrap() {
+            DeepLinkAction::InitiatePayment { memo, .. } => assert!(memo.is_none()),
+        }
+    }
+
+    #[test]
+    fn test_missing_required_fields() {
+        let address = test_address();
+
+        assert_eq!(
+            handle_deep_link("nock://pay?amount=1"),
+            Err(DeepLinkError::MissingField("to"))
+        );
+        assert_eq!(
+            handle_deep_link(&format!("nock://pay?to={}", address)),
+            Err(DeepLinkError::MissingField("amount"))
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_amounts() {
+        let address = test_address();
+
+        for amount in ["0", "-5", "4294967297", "inf", "NaN"] {
+            let uri = format!("nock://pay?to={}&amount={}", address, amount);
+            assert!(
+                matches!(handle_deep_link(&uri), Err(DeepLinkError::AmountOutOfRange(_))),
+                "amount {} should be rejected",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_checksum_rejected() {
+        let mut address = test_address();
+        address.replace_range(address.len() - 2.., "00");
+        let uri = format!("nock://pay?to={}&amount=1", address);
+
+        assert!(matches!(handle_deep_link(&uri), Err(DeepLinkError::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn test_malformed_uris() {
+        let address = test_address();
+
+        assert!(matches!(handle_deep_link("not a uri"), Err(DeepLinkError::MalformedUri(_))));
+        assert!(matches!(
+            handle_deep_link(&format!("https://pay?to={}&amount=1", address)),
+            Err(DeepLinkError::MalformedUri(_))
+        ));
+        assert!(matches!(
+            handle_deep_link(&format!("nock://stake?to={}&amount=1", address)),
+            Err(DeepLinkError::MalformedUri(_))
+        ));
+        for amount in ["ten", "1e3", "+5", "1.", ".5", "0x10"] {
+            assert!(
+                matches!(
+                    handle_deep_link(&format!("nock://pay?to={}&amount={}", address, amount)),
+                    Err(DeepLinkError::MalformedUri(_))
+                ),
+                "amount {} should be malformed",
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn test_note_parameter() {
+        let uri = format!("nock://pay?to={}&amount=3&note=Rent", test_address());
+
+        match handle_deep_link(&uri).unwrap() {
+            DeepLinkAction::InitiatePayment { memo, .. } => assert_eq!(memo.as_deref(), Some("Rent")),
+        }
+    }
+
+    #[test]
+    fn test_payment_uri_roundtrip() {
+        let address = test_address();
+        let uri = payment_uri(&address, 2.75, Some("Lunch & coffee")).unwrap();
+
+        assert_eq!(
+            handle_deep_link(&uri).unwrap(),
+            DeepLinkAction::InitiatePayment {
+                to: address.clone(),
+                amount: 2.75,
+                memo: Some("Lunch & coffee".to_string()),
+            }
+        );
+        assert!(matches!(payment_uri(&address, 0.0, None), Err(DeepLinkError::AmountOutOfRange(_))));
+        assert!(matches!(payment_uri("abcd", 1.0, None), Err(DeepLinkError::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn test_payment_qr_svg() {
+        let uri = payment_uri(&test_address(), 1.0, None).unwrap();
+        let svg = payment_qr_svg(&uri).unwrap();
+
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<svg"));
+    }
+}
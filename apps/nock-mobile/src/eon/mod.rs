@@ -7,6 +7,12 @@ use anyhow::{Result, Error};
 use log::{info, warn, error, debug};
 use std::collections::HashMap;
 
+use crate::core::NockClient;
+
+pub mod prediction;
+
+pub use prediction::*;
+
 /// Eon monitoring and prediction system for mobile app
 #[derive(Debug)]
 pub struct EonMonitor {
@@ -16,6 +22,7 @@ pub struct EonMonitor {
     pub transition_detector: TransitionDetector,
     pub mobile_optimizer: MobileEonOptimizer,
     pub notification_scheduler: NotificationScheduler,
+    rpc: NockClient,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,7 +40,8 @@ pub struct EonStatus {
 pub struct EonTransitionPrediction {
     pub predicted_block: u64,
     pub confidence: f64,
-    pub estimated_time: DateTime<Utc>,
+    pub estimated_utc: DateTime<Utc>,
+    pub blocks_remaining: i64,
     pub expected_difficulty_change: f64,
     pub mining_impact: MiningImpact,
     pub recommended_actions: Vec<String>,
@@ -146,6 +154,10 @@ pub struct NotificationPreferences {
 
 impl EonMonitor {
     pub async fn new() -> Self {
+        Self::with_client(NockClient::new().await).await
+    }
+
+    pub async fn with_client(rpc: NockClient) -> Self {
         Self {
             current_eon: 0,
             monitoring_active: false,
@@ -153,6 +165,7 @@ impl EonMonitor {
             transition_detector: TransitionDetector::new().await,
             mobile_optimizer: MobileEonOptimizer::new().await,
             notification_scheduler: NotificationScheduler::new().await,
+            rpc,
         }
     }
 
@@ -178,34 +191,36 @@ impl EonMonitor {
 
     /// Get eon transition prediction
     pub async fn get_transition_prediction(&self) -> Result<EonTransitionPrediction> {
+        self.check_transition_prediction().await
+    }
+
+    /// Predict the eon transition from the proof-power of the last 144 blocks. Callers
+    /// decide which confidence is high enough to alert on.
+    pub async fn check_transition_prediction(&self) -> Result<EonTransitionPrediction> {
         debug!("Generating eon transition prediction");
-        
-        let prediction = self.prediction_engine.predict_next_transition().await?;
-        let mining_impact = self.analyze_transition_mining_impact(&prediction).await?;
-        let recommendations = self.generate_mobile_recommendations(&prediction).await?;
-        
+
+        let blocks: Vec<BlockSample> = self.rpc
+            .get(&format!("/api/v1/blocks/recent?limit={}", PREDICTION_SAMPLE_BLOCKS))
+            .await?;
+        let progress: EonProgress = self.rpc.get("/api/v1/eon/current").await?;
+
+        let projection = project_transition(&blocks, &progress)?;
+        debug!("Eon {} transition in {} blocks (confidence {:.2})", progress.eon, projection.blocks_remaining, projection.confidence);
+
+        let mining_impact = self.analyze_transition_mining_impact(&projection).await?;
+        let recommendations = self.generate_mobile_recommendations(&projection).await?;
+
         Ok(EonTransitionPrediction {
-            predicted_block: prediction.predicted_block,
-            confidence: prediction.confidence,
-            estimated_time: prediction.estimated_time,
-            expected_difficulty_change: prediction.difficulty_change,
+            predicted_block: projection.predicted_block,
+            confidence: projection.confidence,
+            estimated_utc: projection.estimated_utc,
+            blocks_remaining: projection.blocks_remaining,
+            expected_difficulty_change: projection.proof_power_change,
             mining_impact,
             recommended_actions: recommendations,
         })
     }
 
-    /// Check transition prediction with high confidence threshold
-    pub async fn check_transition_prediction(&self) -> Result<EonTransitionPrediction> {
-        let prediction = self.get_transition_prediction().await?;
-        
-        // Only return predictions with high confidence for mobile notifications
-        if prediction.confidence > 0.8 {
-            Ok(prediction)
-        } else {
-            Err(anyhow::anyhow!("Prediction confidence too low for mobile alert"))
-        }
-    }
-
     /// Start eon monitoring optimized for mobile
     pub async fn start_monitoring(&mut self) -> Result<()> {
         info!("Starting mobile-optimized eon monitoring");
@@ -268,7 +283,7 @@ impl EonMonitor {
         Ok(0.73) // 73% through current eon
     }
 
-    async fn analyze_transition_mining_impact(&self, _prediction: &TransitionPrediction) -> Result<MiningImpact> {
+    async fn analyze_transition_mining_impact(&self, _prediction: &TransitionProjection) -> Result<MiningImpact> {
         Ok(MiningImpact {
             profitability_change: -0.15, // 15% decrease expected
             optimal_mining_window: Duration::days(3),
@@ -277,7 +292,7 @@ impl EonMonitor {
         })
     }
 
-    async fn generate_mobile_recommendations(&self, _prediction: &TransitionPrediction) -> Result<Vec<String>> {
+    async fn generate_mobile_recommendations(&self, _prediction: &TransitionProjection) -> Result<Vec<String>> {
         Ok(vec![
             "Increase mining intensity before transition".to_string(),
             "Prepare for difficulty adjustment".to_string(),
@@ -301,16 +316,6 @@ impl EonPredictionEngine {
             accuracy_tracker: AccuracyTracker::new().await,
         }
     }
-
-    pub async fn predict_next_transition(&self) -> Result<TransitionPrediction> {
-        // Use ML models and historical patterns to predict transition
-        Ok(TransitionPrediction {
-            predicted_block: 1000000,
-            confidence: 0.85,
-            estimated_time: Utc::now() + Duration::days(7),
-            difficulty_change: -0.15,
-        })
-    }
 }
 
 impl TransitionDetector {
@@ -361,14 +366,6 @@ impl NotificationScheduler {
 }
 
 // Helper types and placeholder implementations
-#[derive(Debug)]
-pub struct TransitionPrediction {
-    pub predicted_block: u64,
-    pub confidence: f64,
-    pub estimated_time: DateTime<Utc>,
-    pub difficulty_change: f64,
-}
-
 #[derive(Debug)] pub struct RealTimeAnalyzer;
 #[derive(Debug)] pub struct AccuracyTracker;
 #[derive(Debug)] pub struct EarlyWarningSystem;
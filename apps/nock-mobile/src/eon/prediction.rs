@@ -0,0 +1,227 @@
+// Eon Transition Projection
+// Projects when accumulated proof-power reaches the eon threshold from recent block history
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc, Duration};
+use anyhow::{bail, Result};
+
+/// Blocks fetched for a prediction (roughly one day of blocks)
+pub const PREDICTION_SAMPLE_BLOCKS: usize = 144;
+
+/// Blocks per rolling window when measuring proof-power variance
+pub const ROLLING_WINDOW_BLOCKS: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockSample {
+    pub height: u64,
+    pub timestamp: DateTime<Utc>,
+    pub proof_power: f64,
+}
+
+/// Work accumulated so far in the current eon
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EonProgress {
+    pub eon: u64,
+    pub accumulated_work: f64,
+    pub work_threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionProjection {
+    pub predicted_block: u64,
+    /// Negative once the threshold has already been passed
+    pub blocks_remaining: i64,
+    pub estimated_utc: DateTime<Utc>,
+    pub confidence: f64,
+    /// Relative change in per-block proof-power between now and the transition
+    pub proof_power_change: f64,
+}
+
+/// Fits a linear trend to per-block proof-power and solves for the block where accumulated
+/// work meets the threshold. Confidence falls as the rolling-window predictions disagree.
+pub fn project_transition(blocks: &[BlockSample], progress: &EonProgress) -> Result<TransitionProjection> {
+    if blocks.len() < ROLLING_WINDOW_BLOCKS + 1 {
+        bail!("Need at least {} blocks to predict an eon transition, got {}", ROLLING_WINDOW_BLOCKS + 1, blocks.len());
+    }
+    let mut blocks = blocks.to_vec();
+    blocks.sort_by_key(|block| block.height);
+
+    let powers: Vec<f64> = blocks.iter().map(|block| block.proof_power).collect();
+    let (mean_power, power_std_dev) = mean_and_std_dev(&powers);
+    if mean_power <= 0.0 {
+        bail!("Recent blocks report no proof-power");
+    }
+    let block_interval = mean_block_interval(&blocks);
+    let last = blocks.last().expect("checked above");
+
+    let remaining_work = progress.work_threshold - progress.accumulated_work;
+    if remaining_work <= 0.0 {
+        let blocks_past = (-remaining_work / mean_power).round() as i64;
+        return Ok(TransitionProjection {
+            predicted_block: last.height.saturating_sub(blocks_past as u64),
+            blocks_remaining: -blocks_past,
+            estimated_utc: last.timestamp - scale(block_interval, blocks_past as f64),
+            confidence: 1.0,
+            proof_power_change: 0.0,
+        });
+    }
+
+    let (slope, intercept) = linear_trend(&powers);
+    let current_power = intercept + slope * (powers.len() - 1) as f64;
+    let blocks_ahead = blocks_until_work(remaining_work, current_power, slope)
+        .unwrap_or(remaining_work / mean_power)
+        .ceil()
+        .max(1.0);
+
+    // Spread of the projection implied by each rolling window's mean, plus the noise of
+    // summing `blocks_ahead` blocks with the observed per-block variance
+    let window_predictions: Vec<f64> = powers.windows(ROLLING_WINDOW_BLOCKS)
+        .map(|window| mean_and_std_dev(window).0)
+        .filter(|window_mean| *window_mean > 0.0)
+        .map(|window_mean| remaining_work / window_mean)
+        .collect();
+    let (mean_prediction, window_std_dev) = mean_and_std_dev(&window_predictions);
+    let summation_std_dev = power_std_dev * blocks_ahead.sqrt() / mean_power;
+    let prediction_std_dev = (window_std_dev.powi(2) + summation_std_dev.powi(2)).sqrt();
+    let confidence = if mean_prediction > 0.0 {
+        (1.0 - prediction_std_dev / mean_prediction).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+
+    let transition_power = (current_power + slope * blocks_ahead).max(0.0);
+    Ok(TransitionProjection {
+        predicted_block: last.height + blocks_ahead as u64,
+        blocks_remaining: blocks_ahead as i64,
+        estimated_utc: last.timestamp + scale(block_interval, blocks_ahead),
+        confidence,
+        proof_power_change: if current_power > 0.0 { transition_power / current_power - 1.0 } else { 0.0 },
+    })
+}
+
+/// Smallest `k` with `sum_{i=1..k} (power + slope * i) >= work`, or `None` if a declining
+/// trend reaches zero power first
+fn blocks_until_work(work: f64, power: f64, slope: f64) -> Option<f64> {
+    if slope.abs() < f64::EPSILON * power.abs().max(1.0) {
+        return (power > 0.0).then(|| work / power);
+    }
+    // slope/2 * k^2 + (power + slope/2) * k - work = 0
+    let linear = power + slope / 2.0;
+    let discriminant = linear * linear + 2.0 * slope * work;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let k = (-linear + discriminant.sqrt()) / slope;
+    (k > 0.0).then_some(k)
+}
+
+fn mean_and_std_dev(values: &[f64]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// Least-squares `(slope, intercept)` of values against their index
+fn linear_trend(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = values.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (x, y) in values.iter().enumerate() {
+        let dx = x as f64 - mean_x;
+        covariance += dx * (y - mean_y);
+        variance += dx * dx;
+    }
+    let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+    (slope, mean_y - slope * mean_x)
+}
+
+fn mean_block_interval(blocks: &[BlockSample]) -> Duration {
+    let first = blocks.first().expect("non-empty");
+    let last = blocks.last().expect("non-empty");
+    let span_blocks = last.height.saturating_sub(first.height).max(1) as i32;
+    (last.timestamp - first.timestamp) / span_blocks
+}
+
+fn scale(interval: Duration, blocks: f64) -> Duration {
+    Duration::milliseconds((interval.num_milliseconds() as f64 * blocks).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn series(powers: impl Fn(usize) -> f64, block_seconds: i64) -> Vec<BlockSample> {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..PREDICTION_SAMPLE_BLOCKS)
+            .map(|i| BlockSample {
+                height: 10_000 + i as u64,
+                timestamp: start + Duration::seconds(block_seconds * i as i64),
+                proof_power: powers(i),
+            })
+            .collect()
+    }
+
+    fn progress(remaining: f64) -> EonProgress {
+        EonProgress { eon: 3, accumulated_work: 1_000_000.0, work_threshold: 1_000_000.0 + remaining }
+    }
+
+    #[test]
+    fn test_constant_power_is_certain() {
+        let blocks = series(|_| 100.0, 600);
+        let projection = project_transition(&blocks, &progress(50_000.0)).unwrap();
+
+        assert_eq!(projection.blocks_remaining, 500);
+        assert_eq!(projection.predicted_block, 10_143 + 500);
+        assert_eq!(projection.estimated_utc, blocks.last().unwrap().timestamp + Duration::seconds(600 * 500));
+        assert_eq!(projection.confidence, 1.0);
+    }
+
+    #[test]
+    fn test_rising_trend_arrives_sooner() {
+        let flat = project_transition(&series(|_| 100.0, 600), &progress(50_000.0)).unwrap();
+        let rising = project_transition(&series(|i| 100.0 + i as f64, 600), &progress(50_000.0)).unwrap();
+
+        assert!(rising.blocks_remaining < flat.blocks_remaining);
+        assert!(rising.proof_power_change > 0.0);
+    }
+
+    #[test]
+    fn test_noisy_power_lowers_confidence() {
+        let steady = project_transition(&series(|i| 100.0 + (i % 2) as f64, 600), &progress(50_000.0)).unwrap();
+        let noisy = project_transition(&series(|i| if (i / 12) % 2 == 0 { 40.0 } else { 160.0 }, 600), &progress(50_000.0)).unwrap();
+
+        assert!(steady.confidence > 0.9);
+        assert!(noisy.confidence < steady.confidence);
+        assert!((0.0..=1.0).contains(&noisy.confidence));
+    }
+
+    #[test]
+    fn test_declining_power_falls_back_to_mean() {
+        // Power reaches zero long before the remaining work is done
+        let blocks = series(|i| 200.0 - i as f64, 600);
+        let projection = project_transition(&blocks, &progress(1_000_000.0)).unwrap();
+
+        assert!(projection.blocks_remaining > 0);
+        assert_eq!(projection.predicted_block, 10_143 + projection.blocks_remaining as u64);
+    }
+
+    #[test]
+    fn test_threshold_already_passed() {
+        let projection = project_transition(&series(|_| 100.0, 600), &progress(-1_000.0)).unwrap();
+
+        assert_eq!(projection.blocks_remaining, -10);
+        assert_eq!(projection.predicted_block, 10_133);
+    }
+
+    #[test]
+    fn test_requires_enough_blocks() {
+        let blocks = series(|_| 100.0, 600);
+        assert!(project_transition(&blocks[..ROLLING_WINDOW_BLOCKS], &progress(1.0)).is_err());
+    }
+}
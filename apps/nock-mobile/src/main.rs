@@ -16,6 +16,7 @@ mod eon;
 mod notifications;
 mod security;
 mod ui;
+mod deeplink;
 
 use core::*;
 use wallet::*;
@@ -23,6 +24,7 @@ use mining::*;
 use eon::*;
 use notifications::*;
 use security::*;
+use deeplink::{handle_deep_link, payment_qr_svg, payment_uri, DeepLinkAction, DeepLinkHandler, NOCK_URI_SCHEME};
 
 /// Main application state
 #[derive(Debug)]
@@ -52,6 +54,9 @@ fn main() {
     env_logger::init();
     info!("Starting NOCK Mobile Application");
 
+    // Must run before the builder so secondary instances forward nock:// URIs
+    tauri_plugin_deep_link::prepare("com.nock.mobile");
+
     let menu = create_app_menu();
     let tray = create_system_tray();
 
@@ -61,6 +66,14 @@ fn main() {
         .on_system_tray_event(handle_system_tray_event)
         .setup(|app| {
             let app_handle = app.handle();
+
+            // Route nock:// payment links to the confirmation window
+            let deep_link_handler = DeepLinkHandler::new(app.handle());
+            if let Err(e) = tauri_plugin_deep_link::register(NOCK_URI_SCHEME, move |uri| {
+                deep_link_handler.handle(&uri);
+            }) {
+                error!("Failed to register {}:// URI scheme: {}", NOCK_URI_SCHEME, e);
+            }
             
             // Initialize application state
             tauri::async_runtime::spawn(async move {
@@ -80,6 +93,10 @@ fn main() {
             get_wallet_balance,
             send_transaction,
             get_transaction_history,
+            get_transaction_history_page,
+            get_pending_transactions,
+            confirm_deep_link_payment,
+            generate_payment_qr,
             
             // Eon commands
             get_current_eon,
@@ -193,6 +210,30 @@ async fn start_background_services(app_handle: tauri::AppHandle) {
         let app_handle_clone = app_handle.clone();
         run_notification_service(app_handle_clone).await;
     });
+
+    // Broadcast transactions signed while offline
+    tokio::spawn(async move {
+        let app_handle_clone = app_handle.clone();
+        broadcast_pending_transactions(app_handle_clone).await;
+    });
+}
+
+async fn broadcast_pending_transactions(app_handle: tauri::AppHandle) {
+    info!("Starting pending transaction broadcaster");
+
+    loop {
+        if let Some(state) = app_handle.try_state::<AppState>() {
+            let wallet_manager = state.wallet_manager.lock().await;
+
+            match wallet_manager.broadcast_pending_transactions().await {
+                Ok(0) => {}
+                Ok(accepted) => info!("Broadcast {} pending transactions", accepted),
+                Err(e) => warn!("Failed to broadcast pending transactions: {}", e),
+            }
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+    }
 }
 
 async fn monitor_eon_transitions(app_handle: tauri::AppHandle) {
@@ -341,6 +382,50 @@ async fn send_transaction(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn confirm_deep_link_payment(
+    app_handle: tauri::AppHandle,
+    uri: String,
+    password: String
+) -> Result<TransactionResult, String> {
+    // Re-parse so the window cannot submit parameters that differ from the link
+    let DeepLinkAction::InitiatePayment { to, amount, .. } = handle_deep_link(&uri)
+        .map_err(|e| e.to_string())?;
+
+    let state = app_handle.state::<AppState>();
+    let mut wallet_manager = state.wallet_manager.lock().await;
+
+    let result = wallet_manager.send_transaction(to, amount, password)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(window) = app_handle.get_window("payment-confirm") {
+        let _ = window.close();
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_pending_transactions(app_handle: tauri::AppHandle) -> Result<Vec<PendingTransaction>, String> {
+    let state = app_handle.state::<AppState>();
+    let wallet_manager = state.wallet_manager.lock().await;
+
+    wallet_manager.get_pending_transactions()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn generate_payment_qr(app_handle: tauri::AppHandle, amount: f64, note: Option<String>) -> Result<String, String> {
+    let state = app_handle.state::<AppState>();
+    let wallet_manager = state.wallet_manager.lock().await;
+    let wallet = wallet_manager.current_wallet.as_ref().ok_or("No wallet loaded")?;
+
+    let uri = payment_uri(&wallet.address, amount, note.as_deref()).map_err(|e| e.to_string())?;
+    payment_qr_svg(&uri).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn get_transaction_history(app_handle: tauri::AppHandle) -> Result<Vec<TransactionHistory>, String> {
     let state = app_handle.state::<AppState>();
@@ -351,6 +436,21 @@ async fn get_transaction_history(app_handle: tauri::AppHandle) -> Result<Vec<Tra
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+async fn get_transaction_history_page(
+    app_handle: tauri::AppHandle,
+    page: u32,
+    page_size: u32,
+    filter: TransactionFilter
+) -> Result<TransactionPage, String> {
+    let state = app_handle.state::<AppState>();
+    let wallet_manager = state.wallet_manager.lock().await;
+
+    wallet_manager.get_transaction_history_page(page, page_size, filter)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn get_current_eon(app_handle: tauri::AppHandle) -> Result<EonStatus, String> {
     let state = app_handle.state::<AppState>();
@@ -455,21 +555,21 @@ async fn setup_biometric_auth(app_handle: tauri::AppHandle) -> Result<BiometricS
 }
 
 #[tauri::command]
-async fn encrypt_data(app_handle: tauri::AppHandle, data: String) -> Result<String, String> {
+async fn encrypt_data(app_handle: tauri::AppHandle, data: String, password: Option<String>) -> Result<String, String> {
     let state = app_handle.state::<AppState>();
     let security_manager = state.security_manager.lock().await;
     
-    security_manager.encrypt_sensitive_data(data)
+    security_manager.encrypt_sensitive_data(data, password)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn decrypt_data(app_handle: tauri::AppHandle, encrypted_data: String) -> Result<String, String> {
+async fn decrypt_data(app_handle: tauri::AppHandle, encrypted_data: String, password: Option<String>) -> Result<String, String> {
     let state = app_handle.state::<AppState>();
     let security_manager = state.security_manager.lock().await;
     
-    security_manager.decrypt_sensitive_data(encrypted_data)
+    security_manager.decrypt_sensitive_data(encrypted_data, password)
         .await
         .map_err(|e| e.to_string())
 }
@@ -0,0 +1,194 @@
+// Device Power and Thermal State
+// Battery and thermal readings that decide how many mining threads the device can afford
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+/// Mining stops below this battery level unless the device is charging
+pub const MIN_BATTERY_FOR_MINING: f64 = 0.2;
+
+/// Mining is limited to one thread up to this battery level unless the device is charging
+pub const LOW_BATTERY_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PowerState {
+    /// 0.0 to 1.0
+    pub battery_level: f64,
+    pub charging: bool,
+}
+
+/// Thermal pressure levels, following iOS `ProcessInfo.ThermalState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ThermalState {
+    Nominal,
+    Fair,
+    Serious,
+    Critical,
+}
+
+impl ThermalState {
+    /// Maps Android `PowerManager.THERMAL_STATUS_*` (0 = none to 6 = shutdown)
+    pub fn from_android_status(status: i32) -> Self {
+        match status {
+            i32::MIN..=0 => ThermalState::Nominal,
+            1 => ThermalState::Fair,
+            2 | 3 => ThermalState::Serious,
+            _ => ThermalState::Critical,
+        }
+    }
+
+    /// Desktop fallback: classifies a sensor temperature against its critical temperature
+    pub fn from_temperature(celsius: f64, critical_celsius: f64) -> Self {
+        let headroom = critical_celsius - celsius;
+        if headroom <= 5.0 {
+            ThermalState::Critical
+        } else if headroom <= 15.0 {
+            ThermalState::Serious
+        } else if headroom <= 30.0 {
+            ThermalState::Fair
+        } else {
+            ThermalState::Nominal
+        }
+    }
+}
+
+impl fmt::Display for ThermalState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ThermalState::Nominal => "nominal",
+            ThermalState::Fair => "fair",
+            ThermalState::Serious => "serious",
+            ThermalState::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Platform source of battery and thermal readings
+pub trait DeviceSensors: Send + Sync + fmt::Debug {
+    fn power_state(&self) -> Result<PowerState>;
+
+    fn thermal_state(&self) -> Result<ThermalState>;
+}
+
+/// Reads the battery through the OS power APIs and thermal state from hardware sensors
+#[derive(Debug, Default)]
+pub struct SystemSensors;
+
+impl DeviceSensors for SystemSensors {
+    fn power_state(&self) -> Result<PowerState> {
+        let manager = battery::Manager::new()?;
+        let battery = manager.batteries()?
+            .next()
+            .ok_or_else(|| anyhow!("No battery found"))??;
+
+        Ok(PowerState {
+            battery_level: f64::from(battery.state_of_charge().value),
+            charging: matches!(battery.state(), battery::State::Charging | battery::State::Full),
+        })
+    }
+
+    fn thermal_state(&self) -> Result<ThermalState> {
+        use sysinfo::{ComponentExt, System, SystemExt};
+
+        let mut system = System::new();
+        system.refresh_components_list();
+        system.components().iter()
+            .map(|component| {
+                let critical = component.critical().unwrap_or(100.0);
+                ThermalState::from_temperature(f64::from(component.temperature()), f64::from(critical))
+            })
+            .max()
+            .ok_or_else(|| anyhow!("No temperature sensors found"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleEvent {
+    pub timestamp: DateTime<Utc>,
+    pub threads: u8,
+    pub reason: String,
+}
+
+/// Mining threads for the current power and thermal state, out of `logical_cores`
+pub fn select_mining_threads(power: PowerState, thermal: ThermalState, logical_cores: u8) -> (u8, Option<String>) {
+    let battery_percent = (power.battery_level * 100.0).round();
+    let logical_cores = logical_cores.max(1);
+
+    if thermal == ThermalState::Critical {
+        return (0, Some("Thermal state is critical".to_string()));
+    }
+    if !power.charging && power.battery_level < MIN_BATTERY_FOR_MINING {
+        return (0, Some(format!("Battery at {}% and not charging", battery_percent)));
+    }
+    if thermal == ThermalState::Serious {
+        return (1, Some("Thermal state is serious".to_string()));
+    }
+    if !power.charging && power.battery_level <= LOW_BATTERY_THRESHOLD {
+        return (1, Some(format!("Battery at {}%", battery_percent)));
+    }
+    if power.charging && thermal == ThermalState::Nominal {
+        return (logical_cores, None);
+    }
+
+    let half = (logical_cores / 2).max(1);
+    let reason = if power.charging {
+        format!("Thermal state is {}", thermal)
+    } else {
+        "Running on battery".to_string()
+    };
+    (half, Some(reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power(battery_level: f64, charging: bool) -> PowerState {
+        PowerState { battery_level, charging }
+    }
+
+    #[test]
+    fn test_low_battery_stops_mining() {
+        let (threads, reason) = select_mining_threads(power(0.15, false), ThermalState::Nominal, 8);
+        assert_eq!(threads, 0);
+        assert_eq!(reason.unwrap(), "Battery at 15% and not charging");
+
+        // Charging lifts the hard stop
+        assert_eq!(select_mining_threads(power(0.15, true), ThermalState::Nominal, 8), (8, None));
+    }
+
+    #[test]
+    fn test_moderate_battery_or_serious_thermal_uses_one_thread() {
+        assert_eq!(select_mining_threads(power(0.2, false), ThermalState::Nominal, 8).0, 1);
+        assert_eq!(select_mining_threads(power(0.5, false), ThermalState::Nominal, 8).0, 1);
+        assert_eq!(select_mining_threads(power(1.0, true), ThermalState::Serious, 8).0, 1);
+    }
+
+    #[test]
+    fn test_charging_and_nominal_uses_all_cores() {
+        assert_eq!(select_mining_threads(power(0.9, true), ThermalState::Nominal, 8), (8, None));
+    }
+
+    #[test]
+    fn test_partial_throttle_between_extremes() {
+        let (threads, reason) = select_mining_threads(power(0.9, false), ThermalState::Nominal, 8);
+        assert_eq!((threads, reason.as_deref()), (4, Some("Running on battery")));
+
+        let (threads, reason) = select_mining_threads(power(0.9, true), ThermalState::Fair, 8);
+        assert_eq!((threads, reason.as_deref()), (4, Some("Thermal state is fair")));
+
+        assert_eq!(select_mining_threads(power(1.0, true), ThermalState::Critical, 8).0, 0);
+    }
+
+    #[test]
+    fn test_thermal_state_mapping() {
+        assert_eq!(ThermalState::from_android_status(0), ThermalState::Nominal);
+        assert_eq!(ThermalState::from_android_status(3), ThermalState::Serious);
+        assert_eq!(ThermalState::from_android_status(6), ThermalState::Critical);
+        assert_eq!(ThermalState::from_temperature(45.0, 100.0), ThermalState::Nominal);
+        assert_eq!(ThermalState::from_temperature(88.0, 100.0), ThermalState::Serious);
+    }
+}
@@ -5,9 +5,16 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, Duration};
 use anyhow::{Result, Error};
 use log::{info, warn, error, debug};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, Ordering};
 
+pub mod device_state;
+
+pub use device_state::*;
+
+/// Throttle events kept for diagnostics
+const MAX_THROTTLE_EVENTS: usize = 100;
+
 /// Mobile mining monitor and optimizer
 #[derive(Debug)]
 pub struct MiningMonitor {
@@ -17,6 +24,8 @@ pub struct MiningMonitor {
     pub device_optimizer: DeviceOptimizer,
     pub thermal_manager: ThermalManager,
     pub battery_manager: BatteryManager,
+    pub sensors: Box<dyn DeviceSensors>,
+    pub throttle_events: VecDeque<ThrottleEvent>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,18 +52,17 @@ pub struct MiningProfitability {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MiningOptimization {
-    pub optimal_thread_count: u32,
-    pub optimal_frequency: f64,
-    pub thermal_throttling_enabled: bool,
-    pub battery_protection_enabled: bool,
-    pub performance_mode: String,
-    pub estimated_improvement: f64,
+    pub threads: u8,
+    pub target_cpu_percent: f64,
+    /// Why fewer than all logical cores are used
+    pub throttle_reason: Option<String>,
 }
 
 /// Mobile-optimized mining engine
 #[derive(Debug)]
 pub struct MobileMiner {
     pub mining_algorithm: MiningAlgorithm,
+    pub thread_count: u8,
     pub thread_pool: ThreadPool,
     pub proof_power_optimizer: ProofPowerOptimizer,
     pub eon_aware_strategies: EonAwareStrategies,
@@ -156,6 +164,8 @@ impl MiningMonitor {
             device_optimizer: DeviceOptimizer::new().await,
             thermal_manager: ThermalManager::new().await,
             battery_manager: BatteryManager::new().await,
+            sensors: Box::new(SystemSensors),
+            throttle_events: VecDeque::new(),
         }
     }
 
@@ -235,28 +245,32 @@ impl MiningMonitor {
 
     /// Optimize mining settings for mobile device
     pub async fn optimize_for_mobile_device(&mut self) -> Result<MiningOptimization> {
-        info!("Optimizing mining for mobile device");
-        
-        // Analyze device capabilities
-        let device_analysis = self.device_optimizer.analyze_device_capabilities().await?;
-        
-        // Optimize based on current conditions
-        let thermal_optimization = self.thermal_manager.optimize_thermal_settings().await?;
-        let battery_optimization = self.battery_manager.optimize_power_settings().await?;
-        
-        // Calculate optimal mining parameters
+        debug!("Optimizing mining for mobile device");
+
+        // Devices without a battery are treated as permanently charging
+        let power = self.sensors.power_state().unwrap_or_else(|e| {
+            debug!("Battery state unavailable, assuming mains power: {}", e);
+            PowerState { battery_level: 1.0, charging: true }
+        });
+        let thermal = self.sensors.thermal_state().unwrap_or_else(|e| {
+            debug!("Thermal state unavailable, assuming nominal: {}", e);
+            ThermalState::Nominal
+        });
+        self.battery_manager.current_battery_level = power.battery_level;
+
+        let logical_cores = std::thread::available_parallelism()
+            .map(|cores| cores.get().min(u8::MAX as usize) as u8)
+            .unwrap_or(1);
+        let (threads, throttle_reason) = select_mining_threads(power, thermal, logical_cores);
+
         let optimization = MiningOptimization {
-            optimal_thread_count: device_analysis.recommended_threads,
-            optimal_frequency: device_analysis.recommended_frequency,
-            thermal_throttling_enabled: thermal_optimization.enable_throttling,
-            battery_protection_enabled: battery_optimization.enable_protection,
-            performance_mode: device_analysis.recommended_mode,
-            estimated_improvement: self.calculate_optimization_improvement().await?,
+            threads,
+            target_cpu_percent: f64::from(threads) / f64::from(logical_cores) * 100.0,
+            throttle_reason,
         };
-        
-        // Apply optimizations
+
         self.apply_optimizations(&optimization).await?;
-        
+
         Ok(optimization)
     }
 
@@ -294,12 +308,24 @@ impl MiningMonitor {
         Ok(Duration::minutes(0)) // Placeholder - would track actual uptime
     }
 
-    async fn calculate_optimization_improvement(&self) -> Result<f64> {
-        Ok(0.15) // 15% improvement placeholder
-    }
-
     async fn apply_optimizations(&mut self, optimization: &MiningOptimization) -> Result<()> {
-        // Apply the calculated optimizations to the mining system
+        let previous = self.mobile_miner.thread_count;
+        self.mobile_miner.set_thread_count(optimization.threads);
+
+        if let Some(reason) = &optimization.throttle_reason {
+            let changed = self.throttle_events.back()
+                .map_or(true, |last| last.threads != optimization.threads || &last.reason != reason);
+            if changed || previous != optimization.threads {
+                let event = ThrottleEvent { timestamp: Utc::now(), threads: optimization.threads, reason: reason.clone() };
+                info!("Mining throttled to {} threads at {}: {}", event.threads, event.timestamp.to_rfc3339(), event.reason);
+                if self.throttle_events.len() >= MAX_THROTTLE_EVENTS {
+                    self.throttle_events.pop_front();
+                }
+                self.throttle_events.push_back(event);
+            }
+        } else if previous != optimization.threads {
+            info!("Mining unthrottled, using {} threads", optimization.threads);
+        }
         Ok(())
     }
 }
@@ -308,6 +334,7 @@ impl MobileMiner {
     pub async fn new() -> Self {
         Self {
             mining_algorithm: MiningAlgorithm::new_mobile_optimized(),
+            thread_count: 1,
             thread_pool: ThreadPool::new().await,
             proof_power_optimizer: ProofPowerOptimizer::new().await,
             eon_aware_strategies: EonAwareStrategies::new().await,
@@ -325,6 +352,10 @@ impl MobileMiner {
         Ok(())
     }
 
+    pub fn set_thread_count(&mut self, threads: u8) {
+        self.thread_count = threads;
+    }
+
     pub async fn get_current_hashrate(&self) -> Result<f64> {
         // Get current hashrate - optimized for mobile
         Ok(150000.0) // 150 KH/s placeholder for mobile device
@@ -0,0 +1,156 @@
+// Notification Service for NOCK Mobile
+// Push alerts for eon transitions, mining rewards and large incoming payments
+
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc, Duration};
+use log::{debug, info, warn};
+use std::collections::VecDeque;
+
+use crate::eon::EonTransitionPrediction;
+
+pub mod push;
+
+pub use push::*;
+
+/// Minimum gap between eon transition alerts; the monitor re-predicts every few minutes
+const EON_ALERT_INTERVAL_HOURS: i64 = 6;
+
+/// Undelivered notifications kept for retry
+const MAX_PENDING_NOTIFICATIONS: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertConfig {
+    pub eon_transition_alerts: bool,
+    pub mining_reward_alerts: bool,
+    pub large_transaction_alerts: bool,
+    /// Incoming payments at or above this amount (in NOCK) trigger an alert
+    pub large_transaction_threshold: f64,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            eon_transition_alerts: true,
+            mining_reward_alerts: true,
+            large_transaction_alerts: true,
+            large_transaction_threshold: 1000.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NotificationService {
+    pub config: AlertConfig,
+    /// Owner of this device's push token, from `NOCK_USER_ID`
+    pub user_id: String,
+    dispatcher: Option<PushDispatcher>,
+    pending: VecDeque<NotificationTemplate>,
+    last_eon_alert: Option<DateTime<Utc>>,
+}
+
+impl NotificationService {
+    /// Stores device tokens in `NOTIFICATIONS_DATABASE_URL`, defaulting to a SQLite file in
+    /// the app data directory
+    pub async fn new() -> Self {
+        let database_url = std::env::var("NOTIFICATIONS_DATABASE_URL").unwrap_or_else(|_| {
+            let dir = tauri::api::path::data_dir()
+                .unwrap_or_else(|| std::path::PathBuf::from("."))
+                .join("com.nock.mobile");
+            let _ = std::fs::create_dir_all(&dir);
+            format!("sqlite://{}?mode=rwc", dir.join("notifications.db").display())
+        });
+
+        let dispatcher = match PushDispatcher::connect(&database_url).await {
+            Ok(dispatcher) => Some(dispatcher.with_backends_from_env()),
+            Err(e) => {
+                warn!("Push notifications unavailable: {}", e);
+                None
+            }
+        };
+
+        Self::with_dispatcher(dispatcher)
+    }
+
+    pub fn with_dispatcher(dispatcher: Option<PushDispatcher>) -> Self {
+        Self {
+            config: AlertConfig::default(),
+            user_id: std::env::var("NOCK_USER_ID").unwrap_or_else(|_| "default".to_string()),
+            dispatcher,
+            pending: VecDeque::new(),
+            last_eon_alert: None,
+        }
+    }
+
+    /// Registers this device's APNs or FCM token
+    pub async fn setup_push_notifications(&mut self, token: String) -> Result<()> {
+        let platform = DevicePlatform::current()
+            .ok_or_else(|| anyhow!("Push notifications are only supported on iOS and Android"))?;
+        let dispatcher = self.dispatcher.as_ref()
+            .ok_or_else(|| anyhow!("Push notifications are unavailable"))?;
+
+        dispatcher.register_device(&self.user_id, platform, &token).await
+    }
+
+    pub async fn configure_alerts(&mut self, config: AlertConfig) -> Result<()> {
+        info!("Updated alert configuration: {:?}", config);
+        self.config = config;
+        Ok(())
+    }
+
+    pub async fn send_eon_transition_alert(&mut self, prediction: EonTransitionPrediction) {
+        if !self.config.eon_transition_alerts {
+            return;
+        }
+        let now = Utc::now();
+        if self.last_eon_alert.map_or(false, |last| now - last < Duration::hours(EON_ALERT_INTERVAL_HOURS)) {
+            debug!("Eon transition alert already sent recently");
+            return;
+        }
+
+        self.last_eon_alert = Some(now);
+        self.notify(NotificationTemplate::EonTransition { blocks_remaining: prediction.blocks_remaining }).await;
+    }
+
+    pub async fn send_mining_reward_alert(&mut self, amount: f64, block_height: u64) {
+        if self.config.mining_reward_alerts {
+            self.notify(NotificationTemplate::MiningReward { amount, block_height }).await;
+        }
+    }
+
+    pub async fn send_incoming_transaction_alert(&mut self, amount: f64, from_address: String) {
+        if self.config.large_transaction_alerts && amount >= self.config.large_transaction_threshold {
+            self.notify(NotificationTemplate::LargeIncomingTransaction { amount, from_address }).await;
+        }
+    }
+
+    /// Retries notifications that could not be delivered earlier
+    pub async fn process_pending_notifications(&mut self) {
+        let retries = self.pending.len();
+        for _ in 0..retries {
+            let Some(template) = self.pending.pop_front() else { break };
+            self.notify(template).await;
+        }
+    }
+
+    pub async fn check_for_important_events(&mut self) {
+        // Rewards and payments arrive through the send_*_alert methods
+    }
+
+    async fn notify(&mut self, template: NotificationTemplate) {
+        let Some(dispatcher) = &self.dispatcher else {
+            return;
+        };
+
+        match dispatcher.send(&self.user_id, &template).await {
+            Ok(delivered) => debug!("Delivered {} notification to {} devices", template.category(), delivered),
+            Err(e) => {
+                warn!("Queueing {} notification for retry: {}", template.category(), e);
+                if self.pending.len() >= MAX_PENDING_NOTIFICATIONS {
+                    self.pending.pop_front();
+                }
+                self.pending.push_back(template);
+            }
+        }
+    }
+}
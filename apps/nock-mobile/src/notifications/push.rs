@@ -0,0 +1,405 @@
+// Push Notification Dispatch
+// Device token storage and delivery through APNs (iOS) and FCM (Android)
+
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{debug, info, warn};
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DevicePlatform {
+    Ios,
+    Android,
+}
+
+impl DevicePlatform {
+    /// Platform this build targets, if it receives push notifications
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "ios") {
+            Some(DevicePlatform::Ios)
+        } else if cfg!(target_os = "android") {
+            Some(DevicePlatform::Android)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DevicePlatform::Ios => "ios",
+            DevicePlatform::Android => "android",
+        }
+    }
+}
+
+impl FromStr for DevicePlatform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ios" => Ok(DevicePlatform::Ios),
+            "android" => Ok(DevicePlatform::Android),
+            other => bail!("Unknown device platform: {}", other),
+        }
+    }
+}
+
+/// Notifications the app can push to a user's devices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NotificationTemplate {
+    EonTransition { blocks_remaining: i64 },
+    MiningReward { amount: f64, block_height: u64 },
+    LargeIncomingTransaction { amount: f64, from_address: String },
+}
+
+impl NotificationTemplate {
+    pub fn title(&self) -> String {
+        match self {
+            NotificationTemplate::EonTransition { .. } => "Eon Transition Approaching".to_string(),
+            NotificationTemplate::MiningReward { .. } => "Mining Reward Found".to_string(),
+            NotificationTemplate::LargeIncomingTransaction { .. } => "Large Payment Received".to_string(),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            NotificationTemplate::EonTransition { blocks_remaining } => format!("~{} blocks remaining", blocks_remaining),
+            NotificationTemplate::MiningReward { amount, block_height } => format!("You mined {:.4} NOCK in block {}", amount, block_height),
+            NotificationTemplate::LargeIncomingTransaction { amount, from_address } => {
+                format!("{:.4} NOCK received from {}", amount, short_address(from_address))
+            }
+        }
+    }
+
+    /// Sent as custom data so the app can route a tap to the right screen
+    pub fn category(&self) -> &'static str {
+        match self {
+            NotificationTemplate::EonTransition { .. } => "eon_transition",
+            NotificationTemplate::MiningReward { .. } => "mining_reward",
+            NotificationTemplate::LargeIncomingTransaction { .. } => "incoming_transaction",
+        }
+    }
+}
+
+fn short_address(address: &str) -> String {
+    if address.len() <= 14 {
+        address.to_string()
+    } else {
+        format!("{}…{}", &address[..8], &address[address.len() - 6..])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// The push service no longer accepts this token; it should be forgotten
+    InvalidToken,
+}
+
+#[async_trait]
+pub trait PushBackend: Send + Sync + fmt::Debug {
+    async fn send(&self, token: &str, template: &NotificationTemplate) -> Result<DeliveryOutcome>;
+}
+
+/// Apple Push Notification service using token-based (.p8) authentication
+pub struct ApnsBackend {
+    client: a2::Client,
+    topic: String,
+}
+
+impl fmt::Debug for ApnsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApnsBackend").field("topic", &self.topic).finish()
+    }
+}
+
+impl ApnsBackend {
+    pub fn new(key_path: &str, key_id: &str, team_id: &str, topic: String, sandbox: bool) -> Result<Self> {
+        let endpoint = if sandbox { a2::Endpoint::Sandbox } else { a2::Endpoint::Production };
+        let mut key = std::fs::File::open(key_path)?;
+        let client = a2::Client::token(&mut key, key_id, team_id, a2::ClientConfig::new(endpoint))?;
+        Ok(Self { client, topic })
+    }
+
+    /// Reads `APNS_KEY_PATH`, `APNS_KEY_ID`, `APNS_TEAM_ID`, `APNS_TOPIC` and `APNS_SANDBOX`
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| std::env::var(name).map_err(|_| anyhow!("{} is not set", name));
+        let sandbox = std::env::var("APNS_SANDBOX").map(|value| value == "true").unwrap_or(false);
+        Self::new(&var("APNS_KEY_PATH")?, &var("APNS_KEY_ID")?, &var("APNS_TEAM_ID")?, var("APNS_TOPIC")?, sandbox)
+    }
+}
+
+#[async_trait]
+impl PushBackend for ApnsBackend {
+    async fn send(&self, token: &str, template: &NotificationTemplate) -> Result<DeliveryOutcome> {
+        use a2::NotificationBuilder;
+
+        let (title, body) = (template.title(), template.body());
+        let options = a2::NotificationOptions {
+            apns_topic: Some(&self.topic),
+            ..Default::default()
+        };
+        let mut payload = a2::DefaultNotificationBuilder::new()
+            .set_title(&title)
+            .set_body(&body)
+            .set_sound("default")
+            .build(token, options);
+        payload.add_custom_data("category", &template.category())?;
+
+        match self.client.send(payload).await {
+            Ok(_) => Ok(DeliveryOutcome::Delivered),
+            // 400 BadDeviceToken or 410 Unregistered
+            Err(a2::Error::ResponseError(response)) if matches!(response.code, 400 | 410) => {
+                debug!("APNs rejected token: {:?}", response.error);
+                Ok(DeliveryOutcome::InvalidToken)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// Firebase Cloud Messaging using a server key
+pub struct FcmBackend {
+    client: fcm::Client,
+    api_key: String,
+}
+
+impl fmt::Debug for FcmBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FcmBackend").finish_non_exhaustive()
+    }
+}
+
+impl FcmBackend {
+    pub fn new(api_key: String) -> Self {
+        Self { client: fcm::Client::new(), api_key }
+    }
+
+    /// Reads `FCM_API_KEY`
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("FCM_API_KEY").map_err(|_| anyhow!("FCM_API_KEY is not set"))?;
+        Ok(Self::new(api_key))
+    }
+}
+
+#[async_trait]
+impl PushBackend for FcmBackend {
+    async fn send(&self, token: &str, template: &NotificationTemplate) -> Result<DeliveryOutcome> {
+        let (title, body) = (template.title(), template.body());
+        let mut notification = fcm::NotificationBuilder::new();
+        notification.title(&title);
+        notification.body(&body);
+        notification.sound("default");
+
+        let mut message = fcm::MessageBuilder::new(&self.api_key, token);
+        message.notification(notification.finalize());
+        message.data(&serde_json::json!({ "category": template.category() }))?;
+
+        let response = self.client.send(message.finalize()).await?;
+        let rejected = response.results.iter().flatten().any(|result| {
+            matches!(result.error, Some(fcm::ErrorReason::NotRegistered | fcm::ErrorReason::InvalidRegistration))
+        });
+        if rejected {
+            return Ok(DeliveryOutcome::InvalidToken);
+        }
+        match response.error {
+            Some(reason) => Err(anyhow!("FCM rejected notification: {:?}", reason)),
+            None => Ok(DeliveryOutcome::Delivered),
+        }
+    }
+}
+
+/// Stores device tokens per user and platform and fans notifications out to them
+#[derive(Debug)]
+pub struct PushDispatcher {
+    pool: SqlitePool,
+    apns: Option<Box<dyn PushBackend>>,
+    fcm: Option<Box<dyn PushBackend>>,
+}
+
+impl PushDispatcher {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(database_url)
+            .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS device_tokens (
+                user_id TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                token TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, platform)
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool, apns: None, fcm: None })
+    }
+
+    /// Configures each backend whose environment variables are set
+    pub fn with_backends_from_env(self) -> Self {
+        let apns = ApnsBackend::from_env()
+            .map_err(|e| debug!("APNs disabled: {}", e))
+            .ok()
+            .map(|backend| Box::new(backend) as Box<dyn PushBackend>);
+        let fcm = FcmBackend::from_env()
+            .map_err(|e| debug!("FCM disabled: {}", e))
+            .ok()
+            .map(|backend| Box::new(backend) as Box<dyn PushBackend>);
+        self.with_backends(apns, fcm)
+    }
+
+    pub fn with_backends(mut self, apns: Option<Box<dyn PushBackend>>, fcm: Option<Box<dyn PushBackend>>) -> Self {
+        self.apns = apns;
+        self.fcm = fcm;
+        self
+    }
+
+    /// Saves the token for `user_id` on `platform`, replacing any earlier one
+    pub async fn register_device(&self, user_id: &str, platform: DevicePlatform, token: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO device_tokens (user_id, platform, token, updated_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT (user_id, platform) DO UPDATE SET token = excluded.token, updated_at = excluded.updated_at",
+        )
+        .bind(user_id)
+        .bind(platform.as_str())
+        .bind(token)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        info!("Registered {} push token for {}", platform.as_str(), user_id);
+        Ok(())
+    }
+
+    pub async fn device_tokens(&self, user_id: &str) -> Result<Vec<(DevicePlatform, String)>> {
+        let rows: Vec<(String, String)> = sqlx::query_as("SELECT platform, token FROM device_tokens WHERE user_id = ? ORDER BY platform")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|(platform, token)| Ok((platform.parse()?, token)))
+            .collect()
+    }
+
+    /// Sends `template` to every device registered for `user_id`, returning how many accepted
+    /// it. Tokens the push service reports as invalid are deleted.
+    pub async fn send(&self, user_id: &str, template: &NotificationTemplate) -> Result<usize> {
+        let mut delivered = 0;
+        let mut last_error = None;
+
+        for (platform, token) in self.device_tokens(user_id).await? {
+            let backend = match platform {
+                DevicePlatform::Ios => self.apns.as_deref(),
+                DevicePlatform::Android => self.fcm.as_deref(),
+            };
+            let Some(backend) = backend else {
+                warn!("No push backend configured for {}", platform.as_str());
+                continue;
+            };
+
+            match backend.send(&token, template).await {
+                Ok(DeliveryOutcome::Delivered) => delivered += 1,
+                Ok(DeliveryOutcome::InvalidToken) => {
+                    info!("Removing invalid {} push token for {}", platform.as_str(), user_id);
+                    sqlx::query("DELETE FROM device_tokens WHERE user_id = ? AND platform = ? AND token = ?")
+                        .bind(user_id)
+                        .bind(platform.as_str())
+                        .bind(&token)
+                        .execute(&self.pool)
+                        .await?;
+                }
+                Err(e) => {
+                    warn!("Failed to push {} notification to {}: {}", template.category(), platform.as_str(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last_error {
+            Some(e) if delivered == 0 => Err(e),
+            _ => Ok(delivered),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default, Clone)]
+    struct RecordingBackend {
+        sent: Arc<Mutex<Vec<(String, String)>>>,
+        invalid_tokens: Vec<String>,
+    }
+
+    #[async_trait]
+    impl PushBackend for RecordingBackend {
+        async fn send(&self, token: &str, template: &NotificationTemplate) -> Result<DeliveryOutcome> {
+            if self.invalid_tokens.iter().any(|invalid| invalid == token) {
+                return Ok(DeliveryOutcome::InvalidToken);
+            }
+            self.sent.lock().unwrap().push((token.to_string(), template.body()));
+            Ok(DeliveryOutcome::Delivered)
+        }
+    }
+
+    async fn dispatcher(apns: RecordingBackend, fcm: RecordingBackend) -> PushDispatcher {
+        PushDispatcher::connect("sqlite::memory:").await.unwrap()
+            .with_backends(Some(Box::new(apns)), Some(Box::new(fcm)))
+    }
+
+    #[test]
+    fn test_templates() {
+        let eon = NotificationTemplate::EonTransition { blocks_remaining: 1440 };
+        assert_eq!(eon.title(), "Eon Transition Approaching");
+        assert_eq!(eon.body(), "~1440 blocks remaining");
+
+        let payment = NotificationTemplate::LargeIncomingTransaction { amount: 2500.0, from_address: "ab".repeat(36) };
+        assert_eq!(payment.body(), "2500.0000 NOCK received from abababab…ababab");
+    }
+
+    #[tokio::test]
+    async fn test_routes_by_platform() {
+        let (apns, fcm) = (RecordingBackend::default(), RecordingBackend::default());
+        let push = dispatcher(apns.clone(), fcm.clone()).await;
+        push.register_device("alice", DevicePlatform::Ios, "ios-token").await.unwrap();
+        push.register_device("alice", DevicePlatform::Android, "android-token").await.unwrap();
+        push.register_device("bob", DevicePlatform::Android, "bob-token").await.unwrap();
+
+        let delivered = push.send("alice", &NotificationTemplate::EonTransition { blocks_remaining: 12 }).await.unwrap();
+
+        assert_eq!(delivered, 2);
+        assert_eq!(*apns.sent.lock().unwrap(), vec![("ios-token".to_string(), "~12 blocks remaining".to_string())]);
+        assert_eq!(*fcm.sent.lock().unwrap(), vec![("android-token".to_string(), "~12 blocks remaining".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_reregistering_replaces_token() {
+        let push = dispatcher(RecordingBackend::default(), RecordingBackend::default()).await;
+        push.register_device("alice", DevicePlatform::Ios, "old").await.unwrap();
+        push.register_device("alice", DevicePlatform::Ios, "new").await.unwrap();
+
+        assert_eq!(push.device_tokens("alice").await.unwrap(), vec![(DevicePlatform::Ios, "new".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_tokens_are_removed() {
+        let fcm = RecordingBackend { invalid_tokens: vec!["stale".to_string()], ..Default::default() };
+        let push = dispatcher(RecordingBackend::default(), fcm).await;
+        push.register_device("alice", DevicePlatform::Android, "stale").await.unwrap();
+
+        let template = NotificationTemplate::MiningReward { amount: 1.5, block_height: 42 };
+        assert_eq!(push.send("alice", &template).await.unwrap(), 0);
+        assert!(push.device_tokens("alice").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+// Security Management for NOCK Mobile
+// Biometric and keychain unlock of the data key, with password fallback and attempt limiting
+
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use aes_gcm::{aead::{Aead, KeyInit}, Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::{rngs::OsRng, RngCore};
+
+use crate::wallet::{derive_key, CipherSection, KdfParams, KdfSection};
+
+/// Failed unlocks allowed before the lockout starts
+pub const MAX_FAILED_ATTEMPTS: u32 = 5;
+
+pub const LOCKOUT_DURATION: Duration = Duration::from_secs(30);
+
+const KEYCHAIN_SERVICE: &str = "com.nock.mobile";
+const KEYCHAIN_ACCOUNT: &str = "wallet-data-key";
+const UNLOCK_REASON: &str = "Unlock your NOCK wallet";
+
+const DATA_KEY_LENGTH: usize = 32;
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BiometricType {
+    None,
+    Fingerprint,
+    Face,
+    Iris,
+    /// Desktop keychain, unlocked by the OS (Touch ID, Windows Hello or the login session)
+    OsKeychain,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BiometricSetup {
+    pub supported: bool,
+    /// A data key is stored behind the biometric prompt
+    pub enrolled: bool,
+    #[serde(rename = "type")]
+    pub type_: BiometricType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BiometricError {
+    Unavailable(String),
+    NotEnrolled,
+    /// Verification ran and failed; counts towards the lockout
+    Rejected,
+}
+
+impl fmt::Display for BiometricError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BiometricError::Unavailable(reason) => write!(f, "Biometric authentication unavailable: {}", reason),
+            BiometricError::NotEnrolled => write!(f, "No key enrolled for biometric unlock"),
+            BiometricError::Rejected => write!(f, "Biometric verification failed"),
+        }
+    }
+}
+
+impl std::error::Error for BiometricError {}
+
+/// Secure storage that releases the data key only after the platform verifies the user
+#[async_trait]
+pub trait BiometricKeyStore: Send + Sync + fmt::Debug {
+    fn biometric_type(&self) -> BiometricType;
+
+    fn has_key(&self) -> bool;
+
+    async fn store_key(&self, key: &[u8; DATA_KEY_LENGTH]) -> Result<(), BiometricError>;
+
+    async fn retrieve_key(&self, reason: &str) -> Result<[u8; DATA_KEY_LENGTH], BiometricError>;
+}
+
+/// OS keychain (macOS Keychain, Windows Credential Manager, Secret Service) via `keyring`
+#[derive(Debug)]
+pub struct OsKeychainStore {
+    service: String,
+    account: String,
+}
+
+impl OsKeychainStore {
+    pub fn new() -> Self {
+        Self { service: KEYCHAIN_SERVICE.to_string(), account: KEYCHAIN_ACCOUNT.to_string() }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, BiometricError> {
+        keyring::Entry::new(&self.service, &self.account)
+            .map_err(|e| BiometricError::Unavailable(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl BiometricKeyStore for OsKeychainStore {
+    fn biometric_type(&self) -> BiometricType {
+        // The Tauri biometric plugin needs Tauri 2, so only desktop keychains are supported
+        if cfg!(any(target_os = "macos", target_os = "windows", target_os = "linux")) {
+            BiometricType::OsKeychain
+        } else {
+            BiometricType::None
+        }
+    }
+
+    fn has_key(&self) -> bool {
+        self.entry().and_then(|entry| entry.get_password().map_err(|_| BiometricError::NotEnrolled)).is_ok()
+    }
+
+    async fn store_key(&self, key: &[u8; DATA_KEY_LENGTH]) -> Result<(), BiometricError> {
+        self.entry()?
+            .set_password(&BASE64.encode(key))
+            .map_err(|e| BiometricError::Unavailable(e.to_string()))
+    }
+
+    async fn retrieve_key(&self, _reason: &str) -> Result<[u8; DATA_KEY_LENGTH], BiometricError> {
+        let encoded = match self.entry()?.get_password() {
+            Ok(encoded) => encoded,
+            Err(keyring::Error::NoEntry) => return Err(BiometricError::NotEnrolled),
+            // The OS prompt was dismissed or failed
+            Err(keyring::Error::NoStorageAccess(_)) => return Err(BiometricError::Rejected),
+            Err(e) => return Err(BiometricError::Unavailable(e.to_string())),
+        };
+        BASE64.decode(encoded).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| BiometricError::Unavailable("Stored key is malformed".to_string()))
+    }
+}
+
+/// Counts consecutive failed unlocks and locks out further attempts for a while
+#[derive(Debug)]
+pub struct AttemptLimiter {
+    pub max_failures: u32,
+    pub lockout: Duration,
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+impl AttemptLimiter {
+    pub fn new(max_failures: u32, lockout: Duration) -> Self {
+        Self { max_failures, lockout, failures: 0, locked_until: None }
+    }
+
+    pub fn check(&mut self) -> Result<()> {
+        if let Some(until) = self.locked_until {
+            let now = Instant::now();
+            if now < until {
+                bail!("Too many failed attempts, try again in {}s", (until - now).as_secs() + 1);
+            }
+            self.locked_until = None;
+            self.failures = 0;
+        }
+        Ok(())
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+        if self.failures >= self.max_failures {
+            warn!("{} failed unlock attempts, locking for {}s", self.failures, self.lockout.as_secs());
+            self.locked_until = Some(Instant::now() + self.lockout);
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.locked_until = None;
+    }
+}
+
+/// Data key wrapped with a password-derived key, used when biometric unlock is unavailable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WrappedDataKey {
+    kdf: KdfSection,
+    cipher: CipherSection,
+}
+
+/// Encrypts sensitive app data under a data key unlocked by biometrics or the user's password
+#[derive(Debug)]
+pub struct SecurityManager {
+    pub biometric_enabled: bool,
+    pub kdf_params: KdfParams,
+    key_store: Box<dyn BiometricKeyStore>,
+    wrapped_key_path: PathBuf,
+    attempts: Mutex<AttemptLimiter>,
+}
+
+impl SecurityManager {
+    pub async fn new() -> Self {
+        let wrapped_key_path = tauri::api::path::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("com.nock.mobile")
+            .join("security")
+            .join("data_key.json");
+        Self::with_key_store(Box::new(OsKeychainStore::new()), wrapped_key_path, KdfParams::default())
+    }
+
+    pub fn with_key_store(key_store: Box<dyn BiometricKeyStore>, wrapped_key_path: PathBuf, kdf_params: KdfParams) -> Self {
+        Self {
+            biometric_enabled: false,
+            kdf_params,
+            key_store,
+            wrapped_key_path,
+            attempts: Mutex::new(AttemptLimiter::new(MAX_FAILED_ATTEMPTS, LOCKOUT_DURATION)),
+        }
+    }
+
+    /// Enables biometric unlock when the platform supports it. The data key is enrolled the
+    /// next time it is unlocked with the password.
+    pub async fn setup_biometric_authentication(&mut self) -> Result<BiometricSetup> {
+        let type_ = self.key_store.biometric_type();
+        let supported = type_ != BiometricType::None;
+        self.biometric_enabled = supported;
+
+        let setup = BiometricSetup {
+            supported,
+            enrolled: supported && self.key_store.has_key(),
+            type_,
+        };
+        info!("Biometric setup: {:?}", setup);
+        Ok(setup)
+    }
+
+    pub async fn encrypt_sensitive_data(&self, data: String, password: Option<String>) -> Result<String> {
+        let key = self.unlock_data_key(password.as_deref()).await?;
+
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = Aes256Gcm::new_from_slice(&key)?
+            .encrypt(Nonce::from_slice(&nonce), data.as_bytes())
+            .map_err(|_| anyhow!("Failed to encrypt data"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(BASE64.encode(sealed))
+    }
+
+    /// Tries biometric unlock first and falls back to `password` when biometrics are
+    /// unavailable or rejected
+    pub async fn decrypt_sensitive_data(&self, encrypted_data: String, password: Option<String>) -> Result<String> {
+        let sealed = BASE64.decode(encrypted_data.trim()).context("Encrypted data is not valid base64")?;
+        if sealed.len() < NONCE_LENGTH {
+            bail!("Encrypted data is too short");
+        }
+        let key = self.unlock_data_key(password.as_deref()).await?;
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        let plaintext = Aes256Gcm::new_from_slice(&key)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Encrypted data is corrupted or was sealed with another key"))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+
+    async fn unlock_data_key(&self, password: Option<&str>) -> Result<[u8; DATA_KEY_LENGTH]> {
+        self.attempts.lock().unwrap().check()?;
+
+        if self.biometric_enabled {
+            match self.key_store.retrieve_key(UNLOCK_REASON).await {
+                Ok(key) => {
+                    self.attempts.lock().unwrap().record_success();
+                    return Ok(key);
+                }
+                Err(BiometricError::Rejected) => {
+                    self.attempts.lock().unwrap().record_failure();
+                    if password.is_none() {
+                        bail!(BiometricError::Rejected);
+                    }
+                }
+                Err(e) => debug!("Falling back to password: {}", e),
+            }
+        }
+
+        let password = password.ok_or_else(|| anyhow!("Password required"))?;
+        let key = match self.read_wrapped_key()? {
+            Some(wrapped) => match unwrap_data_key(&wrapped, password) {
+                Ok(key) => key,
+                Err(e) => {
+                    self.attempts.lock().unwrap().record_failure();
+                    return Err(e);
+                }
+            },
+            None => self.create_data_key(password)?,
+        };
+        self.attempts.lock().unwrap().record_success();
+
+        if self.biometric_enabled && !self.key_store.has_key() {
+            match self.key_store.store_key(&key).await {
+                Ok(()) => info!("Enrolled data key for biometric unlock"),
+                Err(e) => warn!("Failed to enroll data key for biometric unlock: {}", e),
+            }
+        }
+        Ok(key)
+    }
+
+    fn read_wrapped_key(&self) -> Result<Option<WrappedDataKey>> {
+        match std::fs::read_to_string(&self.wrapped_key_path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Failed to read wrapped data key"),
+        }
+    }
+
+    /// First unlock: generates the data key and stores it wrapped with `password`
+    fn create_data_key(&self, password: &str) -> Result<[u8; DATA_KEY_LENGTH]> {
+        if password.is_empty() {
+            bail!("Password must not be empty");
+        }
+        let mut key = [0u8; DATA_KEY_LENGTH];
+        let mut salt = [0u8; SALT_LENGTH];
+        let mut nonce = [0u8; NONCE_LENGTH];
+        OsRng.fill_bytes(&mut key);
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = Aes256Gcm::new_from_slice(&derive_key(password, &salt, self.kdf_params)?)?
+            .encrypt(Nonce::from_slice(&nonce), key.as_slice())
+            .map_err(|_| anyhow!("Failed to wrap data key"))?;
+        let wrapped = WrappedDataKey {
+            kdf: KdfSection { algorithm: "argon2id".to_string(), params: self.kdf_params, salt: BASE64.encode(salt) },
+            cipher: CipherSection { algorithm: "aes-256-gcm".to_string(), nonce: BASE64.encode(nonce), ciphertext: BASE64.encode(ciphertext) },
+        };
+
+        if let Some(dir) = self.wrapped_key_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.wrapped_key_path, serde_json::to_vec_pretty(&wrapped)?)?;
+        info!("Created data key");
+        Ok(key)
+    }
+}
+
+fn unwrap_data_key(wrapped: &WrappedDataKey, password: &str) -> Result<[u8; DATA_KEY_LENGTH]> {
+    let salt = BASE64.decode(&wrapped.kdf.salt)?;
+    let nonce = BASE64.decode(&wrapped.cipher.nonce)?;
+    let ciphertext = BASE64.decode(&wrapped.cipher.ciphertext)?;
+    if nonce.len() != NONCE_LENGTH {
+        bail!("Invalid wrapped key nonce");
+    }
+
+    let key = Aes256Gcm::new_from_slice(&derive_key(password, &salt, wrapped.kdf.params)?)?
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| anyhow!("Incorrect password"))?;
+    key.try_into().map_err(|_| anyhow!("Wrapped data key has the wrong length"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const TEST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    #[derive(Debug, Default)]
+    struct MockKeyStore {
+        supported: bool,
+        reject: Arc<AtomicBool>,
+        key: Mutex<Option<[u8; DATA_KEY_LENGTH]>>,
+    }
+
+    #[async_trait]
+    impl BiometricKeyStore for MockKeyStore {
+        fn biometric_type(&self) -> BiometricType {
+            if self.supported { BiometricType::Fingerprint } else { BiometricType::None }
+        }
+
+        fn has_key(&self) -> bool {
+            self.key.lock().unwrap().is_some()
+        }
+
+        async fn store_key(&self, key: &[u8; DATA_KEY_LENGTH]) -> Result<(), BiometricError> {
+            *self.key.lock().unwrap() = Some(*key);
+            Ok(())
+        }
+
+        async fn retrieve_key(&self, _reason: &str) -> Result<[u8; DATA_KEY_LENGTH], BiometricError> {
+            if self.reject.load(Ordering::SeqCst) {
+                return Err(BiometricError::Rejected);
+            }
+            self.key.lock().unwrap().ok_or(BiometricError::NotEnrolled)
+        }
+    }
+
+    fn manager(dir: &tempfile::TempDir, key_store: MockKeyStore) -> SecurityManager {
+        SecurityManager::with_key_store(Box::new(key_store), dir.path().join("data_key.json"), TEST_KDF)
+    }
+
+    #[tokio::test]
+    async fn test_password_roundtrip_without_biometrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut security = manager(&dir, MockKeyStore::default());
+
+        let setup = security.setup_biometric_authentication().await.unwrap();
+        assert_eq!(setup, BiometricSetup { supported: false, enrolled: false, type_: BiometricType::None });
+
+        let sealed = security.encrypt_sensitive_data("seed backup".to_string(), Some("pw".to_string())).await.unwrap();
+        assert!(security.decrypt_sensitive_data(sealed.clone(), None).await.is_err());
+        assert_eq!(security.decrypt_sensitive_data(sealed, Some("pw".to_string())).await.unwrap(), "seed backup");
+    }
+
+    #[tokio::test]
+    async fn test_biometric_unlock_after_enrollment() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut security = manager(&dir, MockKeyStore { supported: true, ..Default::default() });
+
+        let setup = security.setup_biometric_authentication().await.unwrap();
+        assert!(setup.supported && !setup.enrolled);
+
+        // The first password unlock enrolls the data key
+        let sealed = security.encrypt_sensitive_data("secret".to_string(), Some("pw".to_string())).await.unwrap();
+        assert!(security.setup_biometric_authentication().await.unwrap().enrolled);
+
+        assert_eq!(security.decrypt_sensitive_data(sealed, None).await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_biometric_falls_back_to_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let reject = Arc::new(AtomicBool::new(false));
+        let mut security = manager(&dir, MockKeyStore { supported: true, reject: Arc::clone(&reject), ..Default::default() });
+        security.setup_biometric_authentication().await.unwrap();
+        let sealed = security.encrypt_sensitive_data("secret".to_string(), Some("pw".to_string())).await.unwrap();
+
+        reject.store(true, Ordering::SeqCst);
+        assert!(security.decrypt_sensitive_data(sealed.clone(), None).await.is_err());
+        assert_eq!(security.decrypt_sensitive_data(sealed, Some("pw".to_string())).await.unwrap(), "secret");
+    }
+
+    #[tokio::test]
+    async fn test_lockout_after_repeated_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut security = manager(&dir, MockKeyStore::default());
+        security.attempts = Mutex::new(AttemptLimiter::new(MAX_FAILED_ATTEMPTS, Duration::from_millis(200)));
+        let sealed = security.encrypt_sensitive_data("secret".to_string(), Some("pw".to_string())).await.unwrap();
+
+        for _ in 0..MAX_FAILED_ATTEMPTS {
+            let error = security.decrypt_sensitive_data(sealed.clone(), Some("wrong".to_string())).await.unwrap_err();
+            assert!(error.to_string().contains("Incorrect password"));
+        }
+
+        // Even the right password is refused while locked out
+        let error = security.decrypt_sensitive_data(sealed.clone(), Some("pw".to_string())).await.unwrap_err();
+        assert!(error.to_string().contains("Too many failed attempts"));
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(security.decrypt_sensitive_data(sealed, Some("pw".to_string())).await.unwrap(), "secret");
+    }
+}
@@ -0,0 +1,341 @@
+// Transaction History
+// Paged, filtered history fetched from the RPC with a local SQLite cache for offline viewing
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+
+use crate::core::NockClient;
+
+/// Transactions kept in the offline cache per address
+pub const CACHED_TRANSACTIONS: usize = 100;
+
+pub const MAX_PAGE_SIZE: u32 = 100;
+
+/// Transactions requested per RPC call while walking the cursor
+const RPC_BATCH_SIZE: usize = 100;
+
+/// Upper bound on transactions scanned to count filtered results
+const MAX_HISTORY_SCAN: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionDirection {
+    Sent,
+    Received,
+}
+
+impl TransactionDirection {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TransactionDirection::Sent => "sent",
+            TransactionDirection::Received => "received",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionHistory {
+    pub tx_hash: String,
+    pub direction: TransactionDirection,
+    pub amount: f64,
+    pub fee: f64,
+    pub counterparty: String,
+    /// None while the transaction is unconfirmed
+    pub block_height: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TransactionFilter {
+    pub direction: Option<TransactionDirection>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    /// Inclusive start and end
+    pub date_range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+impl TransactionFilter {
+    pub fn matches(&self, transaction: &TransactionHistory) -> bool {
+        self.direction.map_or(true, |direction| transaction.direction == direction)
+            && self.min_amount.map_or(true, |min| transaction.amount >= min)
+            && self.max_amount.map_or(true, |max| transaction.amount <= max)
+            && self.date_range.map_or(true, |(start, end)| transaction.timestamp >= start && transaction.timestamp <= end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub items: Vec<TransactionHistory>,
+    pub total_count: u64,
+    pub has_next_page: bool,
+    /// True when the RPC was unreachable and the page comes from the offline cache
+    pub from_cache: bool,
+}
+
+/// One batch of an address's history, newest first
+#[derive(Debug, Deserialize)]
+struct RpcTransactionBatch {
+    transactions: Vec<TransactionHistory>,
+    next_cursor: Option<String>,
+}
+
+/// Walks the address's history newest first, keeping the transactions that match `filter`
+pub async fn fetch_matching_transactions(rpc: &NockClient, address: &str, filter: &TransactionFilter) -> Result<(Vec<TransactionHistory>, Vec<TransactionHistory>)> {
+    let mut matching = Vec::new();
+    let mut newest = Vec::new();
+    let mut cursor: Option<String> = None;
+    let mut scanned = 0;
+
+    loop {
+        let mut path = format!("/api/v1/addresses/{}/transactions?limit={}", address, RPC_BATCH_SIZE);
+        if let Some(cursor) = &cursor {
+            path.push_str(&format!("&cursor={}", cursor));
+        }
+        let batch: RpcTransactionBatch = rpc.get(&path).await?;
+
+        scanned += batch.transactions.len();
+        for transaction in batch.transactions {
+            if newest.len() < CACHED_TRANSACTIONS {
+                newest.push(transaction.clone());
+            }
+            if filter.matches(&transaction) {
+                matching.push(transaction);
+            }
+        }
+
+        match batch.next_cursor {
+            Some(next) if scanned < MAX_HISTORY_SCAN => cursor = Some(next),
+            Some(_) => {
+                warn!("Transaction history for {} truncated at {} transactions", address, scanned);
+                break;
+            }
+            None => break,
+        }
+    }
+
+    Ok((matching, newest))
+}
+
+/// Slices one zero-based page out of `transactions`
+pub fn paginate(transactions: Vec<TransactionHistory>, page: u32, page_size: u32, from_cache: bool) -> Result<TransactionPage> {
+    if page_size == 0 || page_size > MAX_PAGE_SIZE {
+        bail!("Page size must be between 1 and {}", MAX_PAGE_SIZE);
+    }
+    let total_count = transactions.len() as u64;
+    let start = u64::from(page) * u64::from(page_size);
+    let items: Vec<TransactionHistory> = transactions.into_iter()
+        .skip(start.min(total_count) as usize)
+        .take(page_size as usize)
+        .collect();
+
+    Ok(TransactionPage {
+        has_next_page: start + (items.len() as u64) < total_count,
+        items,
+        total_count,
+        from_cache,
+    })
+}
+
+/// Newest transactions per address in a SQLite database. Connections are opened per call so
+/// the cache can be shared across threads.
+#[derive(Debug, Clone)]
+pub struct TransactionCache {
+    path: PathBuf,
+}
+
+impl TransactionCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn open(&self) -> Result<Connection> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(&self.path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS transactions (
+                address TEXT NOT NULL,
+                tx_hash TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                amount REAL NOT NULL,
+                fee REAL NOT NULL,
+                counterparty TEXT NOT NULL,
+                block_height INTEGER,
+                timestamp TEXT NOT NULL,
+                status TEXT NOT NULL,
+                PRIMARY KEY (address, tx_hash)
+            );",
+        )?;
+        Ok(connection)
+    }
+
+    /// Upserts `transactions` and trims the address down to the newest `CACHED_TRANSACTIONS`
+    pub fn store(&self, address: &str, transactions: &[TransactionHistory]) -> Result<()> {
+        let mut connection = self.open()?;
+        let tx = connection.transaction()?;
+        for transaction in transactions {
+            tx.execute(
+                "INSERT OR REPLACE INTO transactions
+                 (address, tx_hash, direction, amount, fee, counterparty, block_height, timestamp, status)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    address,
+                    transaction.tx_hash,
+                    transaction.direction.as_str(),
+                    transaction.amount,
+                    transaction.fee,
+                    transaction.counterparty,
+                    transaction.block_height.map(|height| height as i64),
+                    transaction.timestamp.to_rfc3339(),
+                    transaction.status,
+                ],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM transactions WHERE address = ?1 AND tx_hash NOT IN
+             (SELECT tx_hash FROM transactions WHERE address = ?1 ORDER BY timestamp DESC LIMIT ?2)",
+            params![address, CACHED_TRANSACTIONS as i64],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Cached transactions for `address`, newest first
+    pub fn load(&self, address: &str) -> Result<Vec<TransactionHistory>> {
+        let connection = self.open()?;
+        let mut statement = connection.prepare(
+            "SELECT tx_hash, direction, amount, fee, counterparty, block_height, timestamp, status
+             FROM transactions WHERE address = ?1 ORDER BY timestamp DESC",
+        )?;
+        let rows = statement.query_map(params![address], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut transactions = Vec::new();
+        for row in rows {
+            let (tx_hash, direction, amount, fee, counterparty, block_height, timestamp, status) = row?;
+            let direction = match direction.as_str() {
+                "sent" => TransactionDirection::Sent,
+                "received" => TransactionDirection::Received,
+                other => {
+                    debug!("Skipping cached transaction {} with direction {}", tx_hash, other);
+                    continue;
+                }
+            };
+            transactions.push(TransactionHistory {
+                tx_hash,
+                direction,
+                amount,
+                fee,
+                counterparty,
+                block_height: block_height.map(|height| height as u64),
+                timestamp: DateTime::parse_from_rfc3339(&timestamp)?.with_timezone(&Utc),
+                status,
+            });
+        }
+        Ok(transactions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+    use url::Url;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn transaction(index: usize) -> TransactionHistory {
+        TransactionHistory {
+            tx_hash: format!("tx{:04}", index),
+            direction: if index % 2 == 0 { TransactionDirection::Received } else { TransactionDirection::Sent },
+            amount: index as f64,
+            fee: 0.01,
+            counterparty: "ab".repeat(36),
+            block_height: Some(1_000 - index as u64),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap() - Duration::hours(index as i64),
+            status: "confirmed".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = TransactionFilter {
+            direction: Some(TransactionDirection::Received),
+            min_amount: Some(2.0),
+            max_amount: Some(10.0),
+            date_range: Some((transaction(8).timestamp, transaction(4).timestamp)),
+        };
+        let matching: Vec<String> = (0..20).map(transaction).filter(|tx| filter.matches(tx)).map(|tx| tx.tx_hash).collect();
+        assert_eq!(matching, vec!["tx0004", "tx0006", "tx0008"]);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let transactions: Vec<_> = (0..25).map(transaction).collect();
+
+        let first = paginate(transactions.clone(), 0, 10, false).unwrap();
+        assert_eq!((first.items.len(), first.total_count, first.has_next_page), (10, 25, true));
+
+        let last = paginate(transactions.clone(), 2, 10, false).unwrap();
+        assert_eq!((last.items.len(), last.has_next_page), (5, false));
+        assert_eq!(last.items[0].tx_hash, "tx0020");
+
+        assert!(paginate(transactions.clone(), 3, 10, false).unwrap().items.is_empty());
+        assert!(paginate(transactions, 0, 0, false).is_err());
+    }
+
+    #[test]
+    fn test_cache_keeps_newest_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = TransactionCache::new(dir.path().join("history.db"));
+
+        let transactions: Vec<_> = (0..150).map(transaction).collect();
+        cache.store("addr", &transactions[50..]).unwrap();
+        cache.store("addr", &transactions[..50]).unwrap();
+
+        let cached = cache.load("addr").unwrap();
+        assert_eq!(cached.len(), CACHED_TRANSACTIONS);
+        assert_eq!(cached[0], transactions[0]);
+        assert_eq!(cached[99], transactions[99]);
+        assert!(cache.load("other").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_follows_cursor() {
+        let server = MockServer::start().await;
+        let transactions: Vec<_> = (0..150).map(transaction).collect();
+        Mock::given(method("GET")).and(path("/api/v1/addresses/addr/transactions")).and(query_param("cursor", "page2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "transactions": transactions[100..], "next_cursor": null })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).and(path("/api/v1/addresses/addr/transactions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "transactions": transactions[..100], "next_cursor": "page2" })))
+            .mount(&server)
+            .await;
+
+        let rpc = NockClient::with_endpoints(vec![Url::parse(&server.uri()).unwrap()]);
+        let filter = TransactionFilter { direction: Some(TransactionDirection::Sent), ..Default::default() };
+        let (matching, newest) = fetch_matching_transactions(&rpc, "addr", &filter).await.unwrap();
+
+        assert_eq!(matching.len(), 75);
+        assert_eq!(matching.last().unwrap().tx_hash, "tx0149");
+        assert_eq!(newest.len(), CACHED_TRANSACTIONS);
+    }
+}
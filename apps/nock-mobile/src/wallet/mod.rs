@@ -0,0 +1,563 @@
+// Wallet Management for NOCK Mobile
+// BIP-39 mnemonics, BIP-32 key derivation and password-encrypted keystores
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, info, warn};
+use std::path::{Path, PathBuf};
+use aes_gcm::{aead::{Aead, KeyInit, Payload}, Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use bip39::{Language, Mnemonic};
+use rand::{rngs::OsRng, RngCore};
+use secp256k1::{Secp256k1, SecretKey};
+use tiny_hderive::bip32::ExtendedPrivKey;
+
+use crate::core::{is_client_error, NockClient};
+use crate::deeplink::validate_nock_address;
+
+pub mod history;
+pub mod pending;
+
+pub use history::*;
+pub use pending::*;
+
+/// BIP-44 path of the wallet's first account key
+pub const DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+/// 128 bits of entropy encode to a 12-word mnemonic
+pub const MNEMONIC_ENTROPY_BYTES: usize = 16;
+
+pub const KEYSTORE_VERSION: u32 = 1;
+
+// Address layout shared with nock:// payment links
+const ADDRESS_CHECKSUM_LENGTH: usize = 4;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Argon2id cost parameters, stored with each keystore so they can be raised later
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB of memory with three passes; memory-hard cost comes from the memory size,
+    /// so PBKDF2-style iteration counts would make unlocking take hours on a phone
+    fn default() -> Self {
+        Self { memory_kib: 64 * 1024, iterations: 3, parallelism: 1 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfSection {
+    pub algorithm: String,
+    #[serde(flatten)]
+    pub params: KdfParams,
+    pub salt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CipherSection {
+    pub algorithm: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+/// On-disk keystore; the address is authenticated as AES-GCM associated data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub address: String,
+    pub public_key: String,
+    pub derivation_path: String,
+    pub kdf: KdfSection,
+    pub cipher: CipherSection,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletInfo {
+    pub address: String,
+    pub public_key: String,
+    pub derivation_path: String,
+    /// Only returned when a wallet is created so the user can write it down
+    pub mnemonic: Option<String>,
+    pub keystore_path: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Creates, imports and unlocks wallets stored as encrypted keystores
+#[derive(Debug)]
+pub struct WalletManager {
+    pub keystore_dir: PathBuf,
+    pub kdf_params: KdfParams,
+    pub current_wallet: Option<WalletInfo>,
+    pub history_cache: TransactionCache,
+    pub pending_store: PendingTransactionStore,
+    rpc: Option<NockClient>,
+}
+
+impl WalletManager {
+    /// Keystores live under the platform's per-user application data directory
+    pub async fn new() -> Self {
+        let keystore_dir = tauri::api::path::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("com.nock.mobile")
+            .join("wallets");
+        Self::with_keystore_dir(keystore_dir, KdfParams::default())
+            .with_client(NockClient::new().await)
+    }
+
+    /// Offline manager; the transaction databases sit next to `keystore_dir`
+    pub fn with_keystore_dir(keystore_dir: impl Into<PathBuf>, kdf_params: KdfParams) -> Self {
+        let keystore_dir = keystore_dir.into();
+        let data_dir = keystore_dir.parent().unwrap_or(&keystore_dir).to_path_buf();
+        Self {
+            keystore_dir,
+            kdf_params,
+            current_wallet: None,
+            history_cache: TransactionCache::new(data_dir.join("history.db")),
+            pending_store: PendingTransactionStore::new(data_dir.join("pending.db")),
+            rpc: None,
+        }
+    }
+
+    pub fn with_client(mut self, rpc: NockClient) -> Self {
+        self.rpc = Some(rpc);
+        self
+    }
+
+    pub fn rpc(&self) -> Option<&NockClient> {
+        self.rpc.as_ref()
+    }
+
+    /// Generates a 12-word mnemonic and stores its first account key encrypted with `password`
+    pub async fn create_new_wallet(&mut self, password: String) -> Result<WalletInfo> {
+        let mut entropy = [0u8; MNEMONIC_ENTROPY_BYTES];
+        OsRng.fill_bytes(&mut entropy);
+        let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy)?;
+
+        let mut wallet = self.store_wallet(&mnemonic, &password)?;
+        wallet.mnemonic = Some(mnemonic.to_string());
+        info!("Created wallet {}", wallet.address);
+        Ok(wallet)
+    }
+
+    /// Restores a wallet from its mnemonic. The checksum is verified first; if a keystore for
+    /// the derived address already exists, `password` must decrypt it.
+    pub async fn import_wallet_from_mnemonic(&mut self, mnemonic: String, password: String) -> Result<WalletInfo> {
+        let mnemonic = Mnemonic::parse_in_normalized(Language::English, mnemonic.trim())
+            .map_err(|e| anyhow!("Invalid mnemonic: {}", e))?;
+
+        let secret = derive_secret_key(&mnemonic)?;
+        let (address, _) = address_for(&secret);
+        let path = self.keystore_path(&address);
+
+        if path.exists() {
+            let keystore = read_keystore(&path)?;
+            let stored = decrypt_private_key(&keystore, &password)
+                .context("Existing keystore could not be unlocked with this password")?;
+            if stored != secret {
+                bail!("Existing keystore for {} does not match this mnemonic", address);
+            }
+            let wallet = wallet_info(&keystore, path);
+            self.current_wallet = Some(wallet.clone());
+            info!("Imported existing wallet {}", address);
+            return Ok(wallet);
+        }
+
+        let wallet = self.store_wallet(&mnemonic, &password)?;
+        info!("Imported wallet {}", wallet.address);
+        Ok(wallet)
+    }
+
+    /// Decrypts the current wallet's private key
+    pub fn unlock(&self, password: &str) -> Result<SecretKey> {
+        let wallet = self.current_wallet.as_ref().ok_or_else(|| anyhow!("No wallet loaded"))?;
+        let keystore = read_keystore(&wallet.keystore_path)?;
+        decrypt_private_key(&keystore, password)
+    }
+
+    /// Signs the payment locally and broadcasts it if an RPC endpoint is reachable. Otherwise
+    /// the signed transaction is stored and `TransactionResult::Pending` is returned.
+    pub async fn send_transaction(&mut self, to_address: String, amount: f64, password: String) -> Result<TransactionResult> {
+        if !validate_nock_address(&to_address) {
+            bail!("Invalid recipient address: {}", to_address);
+        }
+        if !amount.is_finite() || amount <= 0.0 {
+            bail!("Amount must be a positive number");
+        }
+        let from = self.current_wallet.as_ref().ok_or_else(|| anyhow!("No wallet loaded"))?.address.clone();
+        let secret = self.unlock(&password)?;
+
+        let signed = UnsignedTransaction::new(from, to_address, amount, None).sign(&secret)?;
+        let local_id = self.pending_store.insert(&signed)?;
+        info!("Signed transaction {} ({} NOCK to {})", local_id, amount, signed.transaction.to);
+
+        Ok(match self.broadcast(local_id, &signed).await? {
+            Some(tx_hash) => TransactionResult::Broadcast { tx_hash },
+            None => TransactionResult::Pending(local_id),
+        })
+    }
+
+    /// Locally signed transactions not yet accepted by the network, for the current wallet
+    /// (or every wallet when none is loaded)
+    pub async fn get_pending_transactions(&self) -> Result<Vec<PendingTransaction>> {
+        let from = self.current_wallet.as_ref().map(|wallet| wallet.address.as_str());
+        self.pending_store.list(PendingStatus::Pending, from)
+    }
+
+    /// Broadcasts stored transactions oldest first while an endpoint is healthy, returning how
+    /// many were accepted. Stops at the first connectivity failure to keep their order.
+    pub async fn broadcast_pending_transactions(&self) -> Result<usize> {
+        let Some(rpc) = &self.rpc else { return Ok(0) };
+        if rpc.active_endpoint().await.is_none() {
+            return Ok(0);
+        }
+
+        let mut accepted = 0;
+        for pending in self.pending_store.list(PendingStatus::Pending, None)? {
+            match self.broadcast(pending.local_id, &pending.transaction).await {
+                Ok(Some(_)) => accepted += 1,
+                Ok(None) => break,
+                Err(e) => warn!("Pending transaction {} rejected: {}", pending.local_id, e),
+            }
+        }
+        Ok(accepted)
+    }
+
+    /// Some(tx_hash) once accepted, None if no endpoint was reachable. Rejected transactions
+    /// are marked failed.
+    async fn broadcast(&self, local_id: uuid::Uuid, signed: &SignedTransaction) -> Result<Option<String>> {
+        let Some(rpc) = &self.rpc else { return Ok(None) };
+
+        match rpc.post::<serde_json::Value>("/api/v1/transactions", &serde_json::to_value(signed)?).await {
+            Ok(response) => {
+                self.pending_store.set_status(local_id, PendingStatus::Confirmed, None)?;
+                let tx_hash = response.get("tx_hash")
+                    .and_then(|hash| hash.as_str())
+                    .unwrap_or(&signed.tx_hash)
+                    .to_string();
+                info!("Broadcast transaction {} as {}", local_id, tx_hash);
+                Ok(Some(tx_hash))
+            }
+            Err(e) if is_client_error(&e) => {
+                self.pending_store.set_status(local_id, PendingStatus::Failed, Some(&e.to_string()))?;
+                Err(e.context("Transaction rejected by the network"))
+            }
+            Err(e) => {
+                debug!("Broadcast of {} deferred: {}", local_id, e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Most recent transactions of the current wallet
+    pub async fn get_transaction_history(&self) -> Result<Vec<TransactionHistory>> {
+        let page = self.get_transaction_history_page(0, MAX_PAGE_SIZE, TransactionFilter::default()).await?;
+        Ok(page.items)
+    }
+
+    /// Zero-based page of the current wallet's transactions matching `filter`, newest first.
+    /// Falls back to the cached transactions when the RPC is unreachable.
+    pub async fn get_transaction_history_page(&self, page: u32, page_size: u32, filter: TransactionFilter) -> Result<TransactionPage> {
+        let address = &self.current_wallet.as_ref().ok_or_else(|| anyhow!("No wallet loaded"))?.address;
+
+        if let Some(rpc) = &self.rpc {
+            match fetch_matching_transactions(rpc, address, &filter).await {
+                Ok((matching, newest)) => {
+                    if let Err(e) = self.history_cache.store(address, &newest) {
+                        warn!("Failed to cache transaction history: {}", e);
+                    }
+                    return paginate(matching, page, page_size, false);
+                }
+                Err(e) => warn!("Transaction history unavailable, showing cached transactions: {}", e),
+            }
+        }
+
+        let cached = self.history_cache.load(address)?
+            .into_iter()
+            .filter(|transaction| filter.matches(transaction))
+            .collect();
+        paginate(cached, page, page_size, true)
+    }
+
+    pub async fn is_connected(&self) -> bool {
+        self.current_wallet.is_some()
+    }
+
+    fn keystore_path(&self, address: &str) -> PathBuf {
+        self.keystore_dir.join(format!("{}.json", address))
+    }
+
+    fn store_wallet(&mut self, mnemonic: &Mnemonic, password: &str) -> Result<WalletInfo> {
+        if password.is_empty() {
+            bail!("Wallet password must not be empty");
+        }
+        let secret = derive_secret_key(mnemonic)?;
+        let keystore = encrypt_private_key(&secret, password, self.kdf_params)?;
+        let path = self.keystore_path(&keystore.address);
+        write_keystore(&path, &keystore)?;
+
+        let wallet = wallet_info(&keystore, path);
+        self.current_wallet = Some(wallet.clone());
+        Ok(wallet)
+    }
+}
+
+/// Derives the account key at `DERIVATION_PATH` from the mnemonic's seed (empty passphrase)
+pub fn derive_secret_key(mnemonic: &Mnemonic) -> Result<SecretKey> {
+    let seed = mnemonic.to_seed("");
+    let extended = ExtendedPrivKey::derive(&seed, DERIVATION_PATH)
+        .map_err(|e| anyhow!("Key derivation failed: {:?}", e))?;
+    Ok(SecretKey::from_slice(&extended.secret())?)
+}
+
+/// NOCK address: hex of the x-only public key followed by a 4-byte blake3 checksum
+pub fn address_for(secret: &SecretKey) -> (String, String) {
+    let secp = Secp256k1::new();
+    let (public_key, _) = secret.x_only_public_key(&secp);
+    let key = public_key.serialize();
+
+    let mut bytes = key.to_vec();
+    bytes.extend_from_slice(&blake3::hash(&key).as_bytes()[..ADDRESS_CHECKSUM_LENGTH]);
+    (hex::encode(bytes), hex::encode(key))
+}
+
+pub fn encrypt_private_key(secret: &SecretKey, password: &str, params: KdfParams) -> Result<Keystore> {
+    let (address, public_key) = address_for(secret);
+
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(password, &salt, params)?)?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: &secret.secret_bytes(), aad: address.as_bytes() })
+        .map_err(|_| anyhow!("Failed to encrypt private key"))?;
+
+    Ok(Keystore {
+        version: KEYSTORE_VERSION,
+        address,
+        public_key,
+        derivation_path: DERIVATION_PATH.to_string(),
+        kdf: KdfSection {
+            algorithm: "argon2id".to_string(),
+            params,
+            salt: BASE64.encode(salt),
+        },
+        cipher: CipherSection {
+            algorithm: "aes-256-gcm".to_string(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        },
+        created_at: Utc::now(),
+    })
+}
+
+pub fn decrypt_private_key(keystore: &Keystore, password: &str) -> Result<SecretKey> {
+    if keystore.version != KEYSTORE_VERSION || keystore.kdf.algorithm != "argon2id" || keystore.cipher.algorithm != "aes-256-gcm" {
+        bail!("Unsupported keystore format");
+    }
+    let salt = BASE64.decode(&keystore.kdf.salt)?;
+    let nonce = BASE64.decode(&keystore.cipher.nonce)?;
+    let ciphertext = BASE64.decode(&keystore.cipher.ciphertext)?;
+    if nonce.len() != NONCE_LENGTH {
+        bail!("Invalid keystore nonce");
+    }
+
+    let cipher = Aes256Gcm::new_from_slice(&derive_key(password, &salt, keystore.kdf.params)?)?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: keystore.address.as_bytes() })
+        .map_err(|_| anyhow!("Incorrect password or corrupted keystore"))?;
+
+    let secret = SecretKey::from_slice(&plaintext)?;
+    if address_for(&secret).0 != keystore.address {
+        bail!("Keystore address does not match its key");
+    }
+    Ok(secret)
+}
+
+pub(crate) fn derive_key(password: &str, salt: &[u8], params: KdfParams) -> Result<[u8; 32]> {
+    let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(32))
+        .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+    Ok(key)
+}
+
+fn read_keystore(path: &Path) -> Result<Keystore> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read keystore {}", path.display()))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Writes via a temporary file so a crash never leaves a truncated keystore
+fn write_keystore(path: &Path, keystore: &Keystore) -> Result<()> {
+    let dir = path.parent().ok_or_else(|| anyhow!("Keystore path has no parent directory"))?;
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, serde_json::to_vec_pretty(keystore)?)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if let Err(e) = std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o600)) {
+            warn!("Failed to restrict keystore permissions: {}", e);
+        }
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn wallet_info(keystore: &Keystore, keystore_path: PathBuf) -> WalletInfo {
+    WalletInfo {
+        address: keystore.address.clone(),
+        public_key: keystore.public_key.clone(),
+        derivation_path: keystore.derivation_path.clone(),
+        mnemonic: None,
+        keystore_path,
+        created_at: keystore.created_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Standard BIP-39 test mnemonic; its m/44'/60'/0'/0/0 key is widely published
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const TEST_PRIVATE_KEY: &str = "1ab42cc412b618bdea3a599e3c9bae199ebf030895b039e9db1e30dafb12b727";
+
+    // Cheap parameters so tests do not spend seconds in Argon2
+    const TEST_KDF: KdfParams = KdfParams { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    fn manager(dir: &Path) -> WalletManager {
+        WalletManager::with_keystore_dir(dir, TEST_KDF)
+    }
+
+    #[tokio::test]
+    async fn test_create_wallet_writes_encrypted_keystore() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallets = manager(dir.path());
+
+        let wallet = wallets.create_new_wallet("correct horse".to_string()).await.unwrap();
+        let mnemonic = wallet.mnemonic.clone().unwrap();
+        assert_eq!(mnemonic.split_whitespace().count(), 12);
+        assert!(wallet.keystore_path.starts_with(dir.path()));
+
+        let contents = std::fs::read_to_string(&wallet.keystore_path).unwrap();
+        assert!(!contents.contains(&mnemonic));
+        let keystore: Keystore = serde_json::from_str(&contents).unwrap();
+        assert_eq!(keystore.address, wallet.address);
+        assert_eq!(keystore.kdf.params, TEST_KDF);
+
+        let secret = wallets.unlock("correct horse").unwrap();
+        let expected = derive_secret_key(&Mnemonic::parse(&mnemonic).unwrap()).unwrap();
+        assert_eq!(secret, expected);
+        assert!(wallets.unlock("wrong password").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_derives_bip44_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallets = manager(dir.path());
+
+        let wallet = wallets.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "pw".to_string()).await.unwrap();
+        assert!(wallet.mnemonic.is_none());
+        assert_eq!(wallet.derivation_path, DERIVATION_PATH);
+        assert_eq!(hex::encode(wallets.unlock("pw").unwrap().secret_bytes()), TEST_PRIVATE_KEY);
+        assert!(crate::deeplink::handle_deep_link(&format!("nock://pay?to={}&amount=1", wallet.address)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_bad_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallets = manager(dir.path());
+
+        let bad = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon";
+        let error = wallets.import_wallet_from_mnemonic(bad.to_string(), "pw".to_string()).await.unwrap_err();
+        assert!(error.to_string().contains("Invalid mnemonic"));
+        assert!(std::fs::read_dir(dir.path()).map(|mut entries| entries.next().is_none()).unwrap_or(true));
+    }
+
+    fn recipient() -> String {
+        address_for(&SecretKey::from_slice(&[5u8; 32]).unwrap()).0
+    }
+
+    #[tokio::test]
+    async fn test_offline_send_is_signed_and_queued() {
+        let dir = tempfile::tempdir().unwrap();
+        let unreachable = url::Url::parse("http://127.0.0.1:9").unwrap();
+        let mut wallets = manager(&dir.path().join("wallets")).with_client(NockClient::with_endpoints(vec![unreachable]));
+        wallets.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "pw".to_string()).await.unwrap();
+
+        let result = wallets.send_transaction(recipient(), 2.5, "pw".to_string()).await.unwrap();
+        let TransactionResult::Pending(local_id) = result else { panic!("expected pending, got {:?}", result) };
+
+        let pending = wallets.get_pending_transactions().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].local_id, local_id);
+        assert_eq!(pending[0].transaction.transaction.amount, 2.5);
+        assert!(wallets.send_transaction(recipient(), 1.0, "wrong".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pending_transactions_broadcast_when_online() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/health"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/api/v1/transactions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).and(path("/api/v1/transactions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "tx_hash": "abc" })))
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let rpc = NockClient::with_endpoints(vec![url::Url::parse(&server.uri()).unwrap()]);
+        let mut wallets = manager(&dir.path().join("wallets")).with_client(rpc);
+        wallets.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "pw".to_string()).await.unwrap();
+
+        let TransactionResult::Pending(local_id) = wallets.send_transaction(recipient(), 1.0, "pw".to_string()).await.unwrap() else {
+            panic!("first broadcast should fail");
+        };
+        // The failed broadcast marked the endpoint unhealthy until the next health check
+        assert_eq!(wallets.broadcast_pending_transactions().await.unwrap(), 0);
+
+        wallets.rpc().unwrap().check_endpoints().await;
+        assert_eq!(wallets.broadcast_pending_transactions().await.unwrap(), 1);
+        assert!(wallets.get_pending_transactions().await.unwrap().is_empty());
+        assert_eq!(wallets.pending_store.get(local_id).unwrap().unwrap().status, PendingStatus::Confirmed);
+
+        let result = wallets.send_transaction(recipient(), 1.0, "pw".to_string()).await.unwrap();
+        assert_eq!(result, TransactionResult::Broadcast { tx_hash: "abc".to_string() });
+    }
+
+    #[tokio::test]
+    async fn test_reimport_requires_original_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallets = manager(dir.path());
+        wallets.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "first".to_string()).await.unwrap();
+
+        let mut fresh = manager(dir.path());
+        assert!(fresh.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "second".to_string()).await.is_err());
+        assert!(fresh.import_wallet_from_mnemonic(TEST_MNEMONIC.to_string(), "first".to_string()).await.is_ok());
+    }
+}
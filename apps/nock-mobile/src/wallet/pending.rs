@@ -0,0 +1,272 @@
+// Offline Transaction Signing
+// Transactions signed on the device and stored until they can be broadcast
+
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use anyhow::{anyhow, bail, Result};
+use rand::{rngs::OsRng, RngCore};
+use rusqlite::{params, Connection, OptionalExtension};
+use secp256k1::{KeyPair, Message, Secp256k1, SecretKey};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::address_for;
+
+/// Flat network fee until fee estimation is available
+pub const DEFAULT_TRANSACTION_FEE: f64 = 0.001;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnsignedTransaction {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+    pub fee: f64,
+    pub memo: Option<String>,
+    /// Random, so two otherwise identical payments get different hashes
+    pub nonce: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UnsignedTransaction {
+    pub fn new(from: String, to: String, amount: f64, memo: Option<String>) -> Self {
+        Self {
+            from,
+            to,
+            amount,
+            fee: DEFAULT_TRANSACTION_FEE,
+            memo,
+            nonce: OsRng.next_u64(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// blake3 of the canonical JSON encoding
+    pub fn signing_hash(&self) -> Result<[u8; 32]> {
+        Ok(*blake3::hash(&serde_json::to_vec(self)?).as_bytes())
+    }
+
+    /// BIP-340 Schnorr signature by the key behind `from`
+    pub fn sign(self, secret: &SecretKey) -> Result<SignedTransaction> {
+        let (address, public_key) = address_for(secret);
+        if address != self.from {
+            bail!("Signing key does not belong to {}", self.from);
+        }
+
+        let hash = self.signing_hash()?;
+        let secp = Secp256k1::new();
+        let keypair = KeyPair::from_secret_key(&secp, secret);
+        let signature = secp.sign_schnorr_no_aux_rand(&Message::from_slice(&hash)?, &keypair);
+
+        Ok(SignedTransaction {
+            tx_hash: hex::encode(hash),
+            transaction: self,
+            public_key,
+            signature: hex::encode(signature.as_ref()),
+        })
+    }
+}
+
+/// Serialised form broadcast to the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    #[serde(flatten)]
+    pub transaction: UnsignedTransaction,
+    pub tx_hash: String,
+    pub public_key: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransactionResult {
+    /// Accepted by an RPC node
+    Broadcast { tx_hash: String },
+    /// Signed and stored locally; broadcast once the device is online
+    Pending(Uuid),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PendingStatus {
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+impl PendingStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            PendingStatus::Pending => "pending",
+            PendingStatus::Confirmed => "confirmed",
+            PendingStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "pending" => Ok(PendingStatus::Pending),
+            "confirmed" => Ok(PendingStatus::Confirmed),
+            "failed" => Ok(PendingStatus::Failed),
+            other => bail!("Unknown pending transaction status: {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingTransaction {
+    pub local_id: Uuid,
+    pub transaction: SignedTransaction,
+    pub status: PendingStatus,
+    /// Rejection reason for failed transactions
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// SQLite table of locally signed transactions. Connections are opened per call so the store
+/// can be shared across threads.
+#[derive(Debug, Clone)]
+pub struct PendingTransactionStore {
+    path: PathBuf,
+}
+
+impl PendingTransactionStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn open(&self) -> Result<Connection> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(&self.path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pending_transactions (
+                local_id TEXT PRIMARY KEY,
+                from_address TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                status TEXT NOT NULL,
+                error TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );",
+        )?;
+        Ok(connection)
+    }
+
+    pub fn insert(&self, transaction: &SignedTransaction) -> Result<Uuid> {
+        let local_id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        self.open()?.execute(
+            "INSERT INTO pending_transactions (local_id, from_address, payload, status, error, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, NULL, ?5, ?5)",
+            params![
+                local_id.to_string(),
+                transaction.transaction.from,
+                serde_json::to_string(transaction)?,
+                PendingStatus::Pending.as_str(),
+                now,
+            ],
+        )?;
+        Ok(local_id)
+    }
+
+    pub fn set_status(&self, local_id: Uuid, status: PendingStatus, error: Option<&str>) -> Result<()> {
+        let updated = self.open()?.execute(
+            "UPDATE pending_transactions SET status = ?1, error = ?2, updated_at = ?3 WHERE local_id = ?4",
+            params![status.as_str(), error, Utc::now().to_rfc3339(), local_id.to_string()],
+        )?;
+        if updated == 0 {
+            bail!("No pending transaction {}", local_id);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, local_id: Uuid) -> Result<Option<PendingTransaction>> {
+        let connection = self.open()?;
+        let row = connection.query_row(
+            "SELECT local_id, payload, status, error, updated_at FROM pending_transactions WHERE local_id = ?1",
+            params![local_id.to_string()],
+            read_row,
+        ).optional()?;
+        row.map(into_pending).transpose()
+    }
+
+    /// Transactions in `status`, oldest first; all addresses when `from` is None
+    pub fn list(&self, status: PendingStatus, from: Option<&str>) -> Result<Vec<PendingTransaction>> {
+        let connection = self.open()?;
+        let mut statement = connection.prepare(
+            "SELECT local_id, payload, status, error, updated_at FROM pending_transactions
+             WHERE status = ?1 AND (?2 IS NULL OR from_address = ?2) ORDER BY created_at",
+        )?;
+        let rows = statement.query_map(params![status.as_str(), from], read_row)?;
+        let transactions = rows.map(|row| into_pending(row?)).collect::<Result<Vec<_>>>()?;
+        Ok(transactions)
+    }
+}
+
+type PendingRow = (String, String, String, Option<String>, String);
+
+fn read_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<PendingRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn into_pending((local_id, payload, status, error, updated_at): PendingRow) -> Result<PendingTransaction> {
+    Ok(PendingTransaction {
+        local_id: Uuid::parse_str(&local_id).map_err(|e| anyhow!("Invalid local id {}: {}", local_id, e))?,
+        transaction: serde_json::from_str(&payload)?,
+        status: PendingStatus::parse(&status)?,
+        error,
+        updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{schnorr, XOnlyPublicKey};
+
+    fn signed(secret: &SecretKey, to: &str) -> SignedTransaction {
+        let from = address_for(secret).0;
+        UnsignedTransaction::new(from, to.to_string(), 5.0, None).sign(secret).unwrap()
+    }
+
+    #[test]
+    fn test_signature_verifies() {
+        let secret = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let transaction = signed(&secret, "recipient");
+
+        let secp = Secp256k1::verification_only();
+        let public_key = XOnlyPublicKey::from_slice(&hex::decode(&transaction.public_key).unwrap()).unwrap();
+        let signature = schnorr::Signature::from_slice(&hex::decode(&transaction.signature).unwrap()).unwrap();
+        let hash = transaction.transaction.signing_hash().unwrap();
+        assert_eq!(transaction.tx_hash, hex::encode(hash));
+        assert!(secp.verify_schnorr(&signature, &Message::from_slice(&hash).unwrap(), &public_key).is_ok());
+    }
+
+    #[test]
+    fn test_rejects_foreign_sender() {
+        let secret = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let transaction = UnsignedTransaction::new("someone-else".to_string(), "recipient".to_string(), 1.0, None);
+        assert!(transaction.sign(&secret).is_err());
+    }
+
+    #[test]
+    fn test_store_tracks_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PendingTransactionStore::new(dir.path().join("pending.db"));
+        let secret = SecretKey::from_slice(&[3u8; 32]).unwrap();
+        let from = address_for(&secret).0;
+
+        let first = store.insert(&signed(&secret, "a")).unwrap();
+        let second = store.insert(&signed(&secret, "b")).unwrap();
+        assert_eq!(store.list(PendingStatus::Pending, Some(&from)).unwrap().len(), 2);
+        assert!(store.list(PendingStatus::Pending, Some("other")).unwrap().is_empty());
+
+        store.set_status(first, PendingStatus::Confirmed, None).unwrap();
+        store.set_status(second, PendingStatus::Failed, Some("insufficient funds")).unwrap();
+
+        assert!(store.list(PendingStatus::Pending, None).unwrap().is_empty());
+        let failed = store.get(second).unwrap().unwrap();
+        assert_eq!(failed.status, PendingStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("insufficient funds"));
+        assert_eq!(failed.transaction.transaction.to, "b");
+    }
+}
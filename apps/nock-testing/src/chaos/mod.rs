@@ -0,0 +1,745 @@
+// Chaos Engineering for NOCK Ecosystem
+// Fault injection between mock services: latency, packet loss, TCP resets and crash-restarts
+
+use std::collections::HashMap;
+use std::fmt;
+use log::{info, warn, debug};
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use nock_bridge_math::SignatureError;
+use serde::{Deserialize, Serialize};
+use tokio::time::{sleep, Duration, Instant};
+
+/// Seed used unless `CHAOS_SEED` is set, so failures reproduce run to run
+pub const DEFAULT_CHAOS_SEED: u64 = 0x4e4f_434b;
+
+/// Status queries a relayer makes after its last attempt before reporting a deposit as stuck
+const RECONCILE_ATTEMPTS: u32 = 20;
+
+/// Deployment the mock bridge accepts attestations for
+pub const CHAOS_PROGRAM_ID: [u8; 32] = [0x42; 32];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LatencyDistribution {
+    Uniform { min_ms: u64, max_ms: u64 },
+    /// Heavy-tailed: most calls take about `scale_ms`, a few take far longer
+    Pareto { scale_ms: f64, shape: f64 },
+}
+
+impl LatencyDistribution {
+    pub fn sample(&self, rng: &mut impl Rng, cap_ms: u64) -> u64 {
+        let ms = match *self {
+            LatencyDistribution::Uniform { min_ms, max_ms } => rng.gen_range(min_ms..=max_ms.max(min_ms)),
+            LatencyDistribution::Pareto { scale_ms, shape } => {
+                // Inverse CDF; 1 - gen() lies in (0, 1]
+                let u = 1.0 - rng.gen::<f64>();
+                (scale_ms / u.powf(1.0 / shape)).round() as u64
+            }
+        };
+        ms.min(cap_ms)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    pub seed: u64,
+    pub latency: Option<LatencyDistribution>,
+    pub max_latency_ms: u64,
+    /// Requests dropped before reaching the service
+    pub packet_loss_rate: f64,
+    /// Requests handled by the service whose response is lost to a connection reset
+    pub tcp_reset_rate: f64,
+    /// Requests that crash the service; it refuses connections until restarted
+    pub crash_rate: f64,
+    pub restart_after_ms: u64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_CHAOS_SEED,
+            latency: Some(LatencyDistribution::Pareto { scale_ms: 2.0, shape: 1.5 }),
+            max_latency_ms: 250,
+            packet_loss_rate: 0.05,
+            tcp_reset_rate: 0.05,
+            crash_rate: 0.01,
+            restart_after_ms: 500,
+        }
+    }
+}
+
+impl ChaosConfig {
+    /// Defaults overridden by `CHAOS_SEED` and `CHAOS_LATENCY` (`uniform`, `pareto` or `none`)
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(seed) = std::env::var("CHAOS_SEED").ok().and_then(|value| value.parse().ok()) {
+            config.seed = seed;
+        }
+        match std::env::var("CHAOS_LATENCY").as_deref() {
+            Ok("uniform") => config.latency = Some(LatencyDistribution::Uniform { min_ms: 1, max_ms: 20 }),
+            Ok("none") => config.latency = None,
+            _ => {}
+        }
+        config
+    }
+
+    /// No faults at all
+    pub fn disabled() -> Self {
+        Self {
+            seed: DEFAULT_CHAOS_SEED,
+            latency: None,
+            max_latency_ms: 0,
+            packet_loss_rate: 0.0,
+            tcp_reset_rate: 0.0,
+            crash_rate: 0.0,
+            restart_after_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Fault {
+    Latency { ms: u64 },
+    PacketLoss,
+    TcpReset,
+    Crash { restart_after_ms: u64 },
+    /// Request refused while the service is down after a crash
+    Unavailable,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fault::Latency { ms } => write!(f, "{}ms latency", ms),
+            Fault::PacketLoss => write!(f, "packet loss"),
+            Fault::TcpReset => write!(f, "TCP reset after handling"),
+            Fault::Crash { restart_after_ms } => write!(f, "crash, restart in {}ms", restart_after_ms),
+            Fault::Unavailable => write!(f, "service down"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultRecord {
+    pub sequence: u64,
+    pub service: String,
+    pub operation: String,
+    pub fault: Fault,
+    /// How the caller handled the fault
+    pub response: Option<String>,
+}
+
+/// What the caller of a faulty request observes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkError {
+    Timeout,
+    ConnectionReset,
+    ConnectionRefused,
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Timeout => write!(f, "request timed out"),
+            NetworkError::ConnectionReset => write!(f, "connection reset"),
+            NetworkError::ConnectionRefused => write!(f, "connection refused"),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+/// Sits between a caller and a mock service and injects faults into each request
+#[derive(Debug)]
+pub struct ChaosInjector {
+    pub config: ChaosConfig,
+    rng: StdRng,
+    down_until: HashMap<String, Instant>,
+    faults: Vec<FaultRecord>,
+}
+
+impl ChaosInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            down_until: HashMap::new(),
+            faults: Vec::new(),
+        }
+    }
+
+    pub fn faults(&self) -> &[FaultRecord] {
+        &self.faults
+    }
+
+    /// Delivers a request to `service`, running `handler` if it gets there. With a TCP reset
+    /// the handler runs but its result is lost.
+    pub async fn transmit<T>(&mut self, service: &str, operation: &str, handler: impl FnOnce() -> T) -> Result<T, NetworkError> {
+        let now = Instant::now();
+        if let Some(&until) = self.down_until.get(service) {
+            if now < until {
+                self.record(service, operation, Fault::Unavailable);
+                return Err(NetworkError::ConnectionRefused);
+            }
+            self.down_until.remove(service);
+            info!("chaos: {} restarted", service);
+        }
+
+        if self.rng.gen_bool(self.config.crash_rate.clamp(0.0, 1.0)) {
+            let restart_after_ms = self.config.restart_after_ms;
+            self.down_until.insert(service.to_string(), now + Duration::from_millis(restart_after_ms));
+            self.record(service, operation, Fault::Crash { restart_after_ms });
+            return Err(NetworkError::ConnectionRefused);
+        }
+
+        if let Some(distribution) = self.config.latency {
+            let ms = distribution.sample(&mut self.rng, self.config.max_latency_ms);
+            if ms > 0 {
+                self.record(service, operation, Fault::Latency { ms });
+                sleep(Duration::from_millis(ms)).await;
+            }
+        }
+
+        let roll: f64 = self.rng.gen();
+        if roll < self.config.packet_loss_rate {
+            self.record(service, operation, Fault::PacketLoss);
+            return Err(NetworkError::Timeout);
+        }
+        if roll < self.config.packet_loss_rate + self.config.tcp_reset_rate {
+            let _ = handler();
+            self.record(service, operation, Fault::TcpReset);
+            return Err(NetworkError::ConnectionReset);
+        }
+
+        Ok(handler())
+    }
+
+    /// Attaches the caller's reaction to the most recent fault
+    pub fn record_response(&mut self, response: impl Into<String>) {
+        let response = response.into();
+        if let Some(fault) = self.faults.last_mut() {
+            info!("chaos #{}: response: {}", fault.sequence, response);
+            fault.response = Some(response);
+        }
+    }
+
+    fn record(&mut self, service: &str, operation: &str, fault: Fault) {
+        let sequence = self.faults.len() as u64 + 1;
+        match fault {
+            // Every request sees some latency; only log it at debug level
+            Fault::Latency { .. } => debug!("chaos #{}: {} on {}.{}", sequence, fault, service, operation),
+            _ => warn!("chaos #{}: {} on {}.{}", sequence, fault, service, operation),
+        }
+        self.faults.push(FaultRecord {
+            sequence,
+            service: service.to_string(),
+            operation: operation.to_string(),
+            fault,
+            response: None,
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChaosDeposit {
+    pub id: String,
+    pub tx_hash: [u8; 32],
+    pub recipient: [u8; 32],
+    pub amount: u64,
+    pub block_height: u64,
+}
+
+impl ChaosDeposit {
+    /// Attestation validators sign, built exactly as the bridge program builds it
+    pub fn message(&self, program_id: &[u8; 32]) -> Vec<u8> {
+        nock_bridge_math::create_deposit_message(program_id, &self.tx_hash, &self.recipient, self.amount, self.block_height, None)
+    }
+}
+
+/// A validator's ed25519 signature as passed to `deposit_nock`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidatorSignature {
+    pub validator: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+#[derive(Debug)]
+pub struct MockValidator {
+    pub index: usize,
+    signing_key: SigningKey,
+    pub public_key: [u8; 32],
+}
+
+impl MockValidator {
+    pub fn generate(index: usize, rng: &mut impl Rng) -> Self {
+        let signing_key = SigningKey::from_bytes(&rng.gen::<[u8; 32]>());
+        Self { index, public_key: signing_key.verifying_key().to_bytes(), signing_key }
+    }
+
+    pub fn sign(&self, program_id: &[u8; 32], deposit: &ChaosDeposit) -> ValidatorSignature {
+        ValidatorSignature {
+            validator: self.public_key,
+            signature: self.signing_key.sign(&deposit.message(program_id)).to_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeRejection {
+    Signatures(SignatureError),
+}
+
+impl BridgeRejection {
+    /// Name of the bridge program error the rejection corresponds to
+    pub fn code(&self) -> &'static str {
+        match self {
+            BridgeRejection::Signatures(SignatureError::InsufficientSignatures) => "InsufficientSignatures",
+            BridgeRejection::Signatures(SignatureError::DuplicateValidator) => "DuplicateValidator",
+            BridgeRejection::Signatures(SignatureError::InvalidSignature) => "InvalidSignature",
+        }
+    }
+}
+
+impl fmt::Display for BridgeRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BridgeRejection::Signatures(error) => write!(f, "{} ({})", error, self.code()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitOutcome {
+    Minted,
+    AlreadyProcessed,
+}
+
+#[derive(Debug, Clone)]
+pub struct MintRecord {
+    pub deposit: ChaosDeposit,
+    pub signers: Vec<[u8; 32]>,
+}
+
+/// Mint side of the bridge, applying the program's deposit rules: the attestation message
+/// from nock-bridge-math, bound to `program_id`, ed25519 signatures, and the program's
+/// quorum check. Each Nockchain transaction mints once, as the program's `ProcessedDeposit`
+/// account ensures. Minted deposits are durable and survive a crash-restart.
+#[derive(Debug)]
+pub struct MockBridgeService {
+    program_id: [u8; 32],
+    validators: Vec<[u8; 32]>,
+    pub threshold: u8,
+    minted: HashMap<[u8; 32], MintRecord>,
+    total_minted: u64,
+}
+
+impl MockBridgeService {
+    pub fn new(program_id: [u8; 32], validators: Vec<[u8; 32]>, threshold: u8) -> Self {
+        Self { program_id, validators, threshold, minted: HashMap::new(), total_minted: 0 }
+    }
+
+    /// Mints a deposit attested by at least `threshold` validators; a deposit that was
+    /// already minted is acknowledged without minting again
+    pub fn submit(&mut self, deposit: &ChaosDeposit, signatures: &[ValidatorSignature]) -> Result<SubmitOutcome, BridgeRejection> {
+        if self.minted.contains_key(&deposit.tx_hash) {
+            return Ok(SubmitOutcome::AlreadyProcessed);
+        }
+
+        let message = deposit.message(&self.program_id);
+        let signers: Vec<[u8; 32]> = signatures.iter().map(|sig| sig.validator).collect();
+        let signers = nock_bridge_math::check_quorum(&signers, &self.validators, self.threshold, |index| {
+            let sig = &signatures[index];
+            VerifyingKey::from_bytes(&sig.validator)
+                .is_ok_and(|key| key.verify_strict(&message, &Signature::from_bytes(&sig.signature)).is_ok())
+        })
+        .map_err(BridgeRejection::Signatures)?;
+
+        self.total_minted += deposit.amount;
+        self.minted.insert(deposit.tx_hash, MintRecord { deposit: deposit.clone(), signers });
+        Ok(SubmitOutcome::Minted)
+    }
+
+    pub fn status(&self, tx_hash: &[u8; 32]) -> Option<&MintRecord> {
+        self.minted.get(tx_hash)
+    }
+
+    pub fn total_minted(&self) -> u64 {
+        self.total_minted
+    }
+
+    pub fn mint_records(&self) -> impl Iterator<Item = &MintRecord> {
+        self.minted.values()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DepositOutcome {
+    Settled,
+    Failed(String),
+    /// The relayer could not find out whether the deposit was minted
+    InFlight,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChaosReport {
+    pub deposits: usize,
+    pub settled: usize,
+    pub failed: usize,
+    pub faults: Vec<FaultRecord>,
+    /// Broken invariants; empty when the bridge behaved correctly
+    pub violations: Vec<String>,
+}
+
+impl ChaosReport {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Relays deposits through a faulty network to a `MockBridgeService`, then checks that funds
+/// were neither double-counted nor lost and that the bridge's signature rules held
+#[derive(Debug, Clone)]
+pub struct ChaosBridgeScenario {
+    pub deposit_count: usize,
+    pub validator_count: usize,
+    pub threshold: u8,
+    pub max_attempts: u32,
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for ChaosBridgeScenario {
+    fn default() -> Self {
+        Self { deposit_count: 20, validator_count: 5, threshold: 3, max_attempts: 5, retry_backoff_ms: 50 }
+    }
+}
+
+impl ChaosBridgeScenario {
+    pub async fn run(&self, injector: &mut ChaosInjector) -> Result<ChaosReport> {
+        info!("Relaying {} deposits through chaos ({} of {} validators required)",
+              self.deposit_count, self.threshold, self.validator_count);
+
+        let mut rng = StdRng::seed_from_u64(injector.config.seed.wrapping_add(1));
+        let validators: Vec<MockValidator> = (0..self.validator_count)
+            .map(|index| MockValidator::generate(index, &mut rng))
+            .collect();
+        let mut bridge = MockBridgeService::new(
+            CHAOS_PROGRAM_ID,
+            validators.iter().map(|v| v.public_key).collect(),
+            self.threshold,
+        );
+
+        let deposits: Vec<ChaosDeposit> = (0..self.deposit_count)
+            .map(|i| ChaosDeposit {
+                id: format!("deposit-{}", i),
+                tx_hash: rng.gen(),
+                recipient: rng.gen(),
+                amount: rng.gen_range(1..=1_000_000),
+                block_height: 1_000 + i as u64,
+            })
+            .collect();
+
+        let mut outcomes = HashMap::new();
+        for deposit in &deposits {
+            let outcome = self.relay(deposit, &validators, &mut bridge, injector).await;
+            debug!("{}: {:?}", deposit.id, outcome);
+            outcomes.insert(deposit.id.clone(), outcome);
+        }
+
+        let forged = self.attempt_signature_bypass(&validators, &mut bridge, injector, &mut rng).await;
+        let violations = self.check_invariants(&deposits, &outcomes, &bridge, &forged);
+
+        for violation in &violations {
+            warn!("Chaos invariant violated: {}", violation);
+        }
+
+        Ok(ChaosReport {
+            deposits: deposits.len(),
+            settled: outcomes.values().filter(|o| **o == DepositOutcome::Settled).count(),
+            failed: outcomes.values().filter(|o| matches!(o, DepositOutcome::Failed(_))).count(),
+            faults: injector.faults().to_vec(),
+            violations,
+        })
+    }
+
+    async fn relay(
+        &self,
+        deposit: &ChaosDeposit,
+        validators: &[MockValidator],
+        bridge: &mut MockBridgeService,
+        injector: &mut ChaosInjector,
+    ) -> DepositOutcome {
+        let mut last_error = String::new();
+
+        for attempt in 1..=self.max_attempts {
+            if attempt > 1 {
+                sleep(Duration::from_millis(self.retry_backoff_ms * u64::from(attempt - 1))).await;
+            }
+
+            let mut signatures = Vec::new();
+            for validator in validators {
+                let service = format!("validator-{}", validator.index);
+                match injector.transmit(&service, "sign_deposit", || validator.sign(&CHAOS_PROGRAM_ID, deposit)).await {
+                    Ok(signature) => signatures.push(signature),
+                    Err(e) => injector.record_response(format!("{}: {} ({}), continuing without it", deposit.id, service, e)),
+                }
+            }
+            if signatures.len() < usize::from(self.threshold) {
+                last_error = format!("only {} of {} signatures collected", signatures.len(), self.threshold);
+                injector.record_response(format!("{}: {}, retrying", deposit.id, last_error));
+                continue;
+            }
+
+            match injector.transmit("bridge", "submit_deposit", || bridge.submit(deposit, &signatures)).await {
+                Ok(Ok(SubmitOutcome::Minted)) => return DepositOutcome::Settled,
+                Ok(Ok(SubmitOutcome::AlreadyProcessed)) => {
+                    injector.record_response(format!("{}: resubmission acknowledged as already minted", deposit.id));
+                    return DepositOutcome::Settled;
+                }
+                Ok(Err(rejection)) => return DepositOutcome::Failed(rejection.to_string()),
+                Err(e) => {
+                    last_error = format!("submit failed: {}", e);
+                    injector.record_response(format!("{}: {}, checking status before retrying", deposit.id, last_error));
+                    let status = injector.transmit("bridge", "deposit_status", || bridge.status(&deposit.tx_hash).is_some()).await;
+                    if status == Ok(true) {
+                        injector.record_response(format!("{}: already minted, settled without resubmitting", deposit.id));
+                        return DepositOutcome::Settled;
+                    }
+                }
+            }
+        }
+
+        // Out of attempts: find out whether an earlier submission landed before reporting failure
+        for _ in 0..RECONCILE_ATTEMPTS {
+            match injector.transmit("bridge", "deposit_status", || bridge.status(&deposit.tx_hash).is_some()).await {
+                Ok(true) => return DepositOutcome::Settled,
+                Ok(false) => {
+                    return DepositOutcome::Failed(format!("gave up after {} attempts: {}", self.max_attempts, last_error));
+                }
+                Err(e) => {
+                    injector.record_response(format!("{}: status check failed ({}), reconciling", deposit.id, e));
+                    sleep(Duration::from_millis(self.retry_backoff_ms)).await;
+                }
+            }
+        }
+        DepositOutcome::InFlight
+    }
+
+    /// Submits deposits that break the bridge's signature rules: one valid signature too
+    /// few padded with a key outside the validator set, a validator listed twice, and a
+    /// full quorum signed for another deployment
+    async fn attempt_signature_bypass(
+        &self,
+        validators: &[MockValidator],
+        bridge: &mut MockBridgeService,
+        injector: &mut ChaosInjector,
+        rng: &mut StdRng,
+    ) -> Vec<ChaosDeposit> {
+        let below_threshold = usize::from(self.threshold).saturating_sub(1);
+        let outsider = MockValidator::generate(validators.len(), rng);
+        let other_deployment = [0x24; 32];
+        let mut forged = Vec::new();
+
+        for (i, label) in ["outsider", "duplicate", "other-deployment"].into_iter().enumerate() {
+            let deposit = ChaosDeposit {
+                id: format!("forged-{}", label),
+                tx_hash: rng.gen(),
+                recipient: rng.gen(),
+                amount: 1_000_000,
+                block_height: 1_000,
+            };
+            let signatures: Vec<ValidatorSignature> = match i {
+                0 => validators.iter().take(below_threshold)
+                    .chain(std::iter::once(&outsider))
+                    .map(|validator| validator.sign(&CHAOS_PROGRAM_ID, &deposit))
+                    .collect(),
+                1 => validators.iter().take(below_threshold)
+                    .chain(validators.first())
+                    .map(|validator| validator.sign(&CHAOS_PROGRAM_ID, &deposit))
+                    .collect(),
+                _ => validators.iter().take(usize::from(self.threshold))
+                    .map(|validator| validator.sign(&other_deployment, &deposit))
+                    .collect(),
+            };
+
+            for _ in 0..RECONCILE_ATTEMPTS {
+                match injector.transmit("bridge", "submit_deposit", || bridge.submit(&deposit, &signatures)).await {
+                    Ok(Err(rejection)) => {
+                        info!("Signature bypass {} rejected: {}", deposit.id, rejection);
+                        break;
+                    }
+                    Ok(Ok(outcome)) => {
+                        warn!("Signature bypass {} accepted: {:?}", deposit.id, outcome);
+                        break;
+                    }
+                    Err(e) => injector.record_response(format!("{}: {}, retrying", deposit.id, e)),
+                }
+            }
+            forged.push(deposit);
+        }
+        forged
+    }
+
+    fn check_invariants(
+        &self,
+        deposits: &[ChaosDeposit],
+        outcomes: &HashMap<String, DepositOutcome>,
+        bridge: &MockBridgeService,
+        forged: &[ChaosDeposit],
+    ) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        // (a) No double counting: the running total matches the minted records, which match
+        // what the relayer believes it settled
+        let recorded: u64 = bridge.mint_records().map(|record| record.deposit.amount).sum();
+        if bridge.total_minted() != recorded {
+            violations.push(format!("total minted {} differs from minted records {}", bridge.total_minted(), recorded));
+        }
+        let settled: u64 = deposits.iter()
+            .filter(|deposit| outcomes.get(&deposit.id) == Some(&DepositOutcome::Settled))
+            .map(|deposit| deposit.amount)
+            .sum();
+        if settled != bridge.total_minted() {
+            violations.push(format!("relayer settled {} but bridge minted {}", settled, bridge.total_minted()));
+        }
+
+        // (b) Every deposit settled or failed explicitly, consistently with the bridge
+        for deposit in deposits {
+            let minted = bridge.status(&deposit.tx_hash);
+            match outcomes.get(&deposit.id) {
+                Some(DepositOutcome::Settled) if minted.is_none() => {
+                    violations.push(format!("{} reported settled but never minted", deposit.id));
+                }
+                Some(DepositOutcome::Failed(reason)) if minted.is_some() => {
+                    violations.push(format!("{} reported failed ({}) but was minted", deposit.id, reason));
+                }
+                Some(DepositOutcome::InFlight) | None => {
+                    violations.push(format!("{} never settled or failed", deposit.id));
+                }
+                _ => {}
+            }
+            if let Some(record) = minted {
+                if record.deposit != *deposit {
+                    violations.push(format!("{} minted with altered details", deposit.id));
+                }
+            }
+        }
+
+        // (c) The multi-sig threshold held for every mint
+        for record in bridge.mint_records() {
+            if record.signers.len() < usize::from(self.threshold) {
+                violations.push(format!("{} minted with {} signers", record.deposit.id, record.signers.len()));
+            }
+        }
+        for deposit in forged {
+            if bridge.status(&deposit.tx_hash).is_some() {
+                violations.push(format!("{} minted despite invalid signatures", deposit.id));
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bridge_with_validators(count: usize, threshold: u8) -> (Vec<MockValidator>, MockBridgeService) {
+        let mut rng = StdRng::seed_from_u64(7);
+        let validators: Vec<MockValidator> = (0..count).map(|i| MockValidator::generate(i, &mut rng)).collect();
+        let bridge = MockBridgeService::new(CHAOS_PROGRAM_ID, validators.iter().map(|v| v.public_key).collect(), threshold);
+        (validators, bridge)
+    }
+
+    fn deposit() -> ChaosDeposit {
+        ChaosDeposit { id: "deposit-1".to_string(), tx_hash: [9; 32], recipient: [5; 32], amount: 500, block_height: 50 }
+    }
+
+    #[test]
+    fn test_latency_distributions_respect_bounds() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let uniform = LatencyDistribution::Uniform { min_ms: 5, max_ms: 10 };
+        let pareto = LatencyDistribution::Pareto { scale_ms: 2.0, shape: 1.5 };
+
+        for _ in 0..1000 {
+            assert!((5..=10).contains(&uniform.sample(&mut rng, 100)));
+            assert!((2..=100).contains(&pareto.sample(&mut rng, 100)));
+        }
+    }
+
+    #[test]
+    fn test_bridge_enforces_signature_rules_and_idempotency() {
+        let (validators, mut bridge) = bridge_with_validators(5, 3);
+        let deposit = deposit();
+        let sign = |i: usize| validators[i].sign(&CHAOS_PROGRAM_ID, &deposit);
+        let rejected = |error| Err(BridgeRejection::Signatures(error));
+
+        // A validator listed twice fails the whole submission
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), sign(1)]), rejected(SignatureError::DuplicateValidator));
+
+        // Keys outside the validator set are not counted
+        let outsider = MockValidator::generate(5, &mut StdRng::seed_from_u64(8)).sign(&CHAOS_PROGRAM_ID, &deposit);
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), outsider]), rejected(SignatureError::InsufficientSignatures));
+
+        // Signatures over another amount or recipient, or for another deployment, do not verify
+        let other = ChaosDeposit { amount: 501, ..deposit.clone() };
+        let foreign = validators[2].sign(&CHAOS_PROGRAM_ID, &other);
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), foreign]), rejected(SignatureError::InvalidSignature));
+        let redirected = ChaosDeposit { recipient: [6; 32], ..deposit.clone() };
+        let stolen = [sign(0), sign(1), sign(4)];
+        assert_eq!(bridge.submit(&redirected, &stolen), rejected(SignatureError::InvalidSignature));
+        let replayed = validators[2].sign(&[0x24; 32], &deposit);
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), replayed]), rejected(SignatureError::InvalidSignature));
+
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), sign(4)]), Ok(SubmitOutcome::Minted));
+        assert_eq!(bridge.submit(&deposit, &[sign(0), sign(1), sign(4)]), Ok(SubmitOutcome::AlreadyProcessed));
+        assert_eq!(bridge.total_minted(), 500);
+        assert_eq!(
+            bridge.status(&deposit.tx_hash).unwrap().signers,
+            vec![validators[0].public_key, validators[1].public_key, validators[4].public_key]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tcp_reset_runs_handler_but_loses_response() {
+        let mut injector = ChaosInjector::new(ChaosConfig { tcp_reset_rate: 1.0, ..ChaosConfig::disabled() });
+        let mut handled = 0;
+
+        assert_eq!(injector.transmit("bridge", "submit_deposit", || handled += 1).await, Err(NetworkError::ConnectionReset));
+        assert_eq!(handled, 1);
+        assert_eq!(injector.faults()[0].fault, Fault::TcpReset);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_crashed_service_refuses_until_restart() {
+        let mut injector = ChaosInjector::new(ChaosConfig { crash_rate: 1.0, restart_after_ms: 100, ..ChaosConfig::disabled() });
+        assert_eq!(injector.transmit("bridge", "submit_deposit", || ()).await, Err(NetworkError::ConnectionRefused));
+
+        injector.config.crash_rate = 0.0;
+        assert_eq!(injector.transmit("bridge", "submit_deposit", || ()).await, Err(NetworkError::ConnectionRefused));
+        assert_eq!(injector.faults()[1].fault, Fault::Unavailable);
+
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(injector.transmit("bridge", "submit_deposit", || ()).await, Ok(()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bridge_invariants_hold_under_heavy_chaos() {
+        let config = ChaosConfig {
+            latency: Some(LatencyDistribution::Uniform { min_ms: 1, max_ms: 50 }),
+            max_latency_ms: 50,
+            packet_loss_rate: 0.2,
+            tcp_reset_rate: 0.2,
+            crash_rate: 0.05,
+            ..ChaosConfig::default()
+        };
+        let mut injector = ChaosInjector::new(config);
+
+        let report = ChaosBridgeScenario::default().run(&mut injector).await.unwrap();
+        assert!(report.passed(), "{:?}", report.violations);
+        assert_eq!(report.settled + report.failed, report.deposits);
+        assert!(report.faults.iter().any(|f| f.fault == Fault::TcpReset && f.response.is_some()));
+    }
+}
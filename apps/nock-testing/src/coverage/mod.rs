@@ -0,0 +1,398 @@
+// Code Coverage for NOCK Ecosystem
+// Line and branch coverage measured with cargo-llvm-cov, compared against the previous run
+
+use log::{info, warn, debug};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Line coverage below this fails the suite unless `COVERAGE_THRESHOLD` is set
+pub const DEFAULT_LINE_COVERAGE_THRESHOLD: f64 = 80.0;
+
+/// Rust crates measured by default, relative to the workspace root
+const DEFAULT_COVERAGE_CRATES: &[&str] = &[
+    "apps/nock-optimizer",
+    "apps/nock-analytics",
+    "apps/nock-mobile",
+    "apps/mining-pool",
+    "apps/bridge-revenue",
+    "apps/revenue-engine",
+    "apps/performance-optimizer",
+    "apps/solana-bridge/programs/nock-bridge",
+];
+
+/// Coverage changes smaller than this many percentage points are not reported
+const COVERAGE_DIFF_EPSILON: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageConfig {
+    pub workspace_root: PathBuf,
+    pub crates: Vec<String>,
+    pub line_threshold: f64,
+    /// Branch coverage needs a nightly toolchain
+    pub branch_coverage: bool,
+    pub cache_path: PathBuf,
+}
+
+impl CoverageConfig {
+    /// Reads `NOCK_WORKSPACE_ROOT`, `COVERAGE_CRATES` (comma separated), `COVERAGE_THRESHOLD`,
+    /// `COVERAGE_BRANCH` and `COVERAGE_CACHE_PATH`
+    pub fn from_env() -> Self {
+        let workspace_root = std::env::var("NOCK_WORKSPACE_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../.."));
+        let crates = match std::env::var("COVERAGE_CRATES") {
+            Ok(list) => list.split(',').map(str::trim).filter(|c| !c.is_empty()).map(String::from).collect(),
+            Err(_) => DEFAULT_COVERAGE_CRATES.iter().map(|c| c.to_string()).collect(),
+        };
+        let line_threshold = std::env::var("COVERAGE_THRESHOLD")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_LINE_COVERAGE_THRESHOLD);
+        let cache_path = std::env::var("COVERAGE_CACHE_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/coverage-report.json"));
+
+        Self {
+            workspace_root,
+            crates,
+            line_threshold,
+            branch_coverage: std::env::var("COVERAGE_BRANCH").map_or(false, |v| v == "1" || v == "true"),
+            cache_path,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateCoverage {
+    pub name: String,
+    pub line_coverage: f64,
+    pub branch_coverage: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Percentages over every measured crate, weighted by line and branch counts
+    pub line_coverage: f64,
+    pub branch_coverage: f64,
+    /// Files without a single covered line
+    pub uncovered_files: Vec<String>,
+    pub crates: Vec<CrateCoverage>,
+    /// Line coverage per file, keyed by path relative to the workspace root
+    pub files: BTreeMap<String, f64>,
+}
+
+/// A file whose line coverage fell since the cached run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageRegression {
+    pub file: String,
+    pub previous: f64,
+    /// None when the file is no longer measured
+    pub current: Option<f64>,
+}
+
+/// `cargo llvm-cov --json --summary-only` output
+#[derive(Debug, Deserialize)]
+struct LlvmCovExport {
+    data: Vec<LlvmCovData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlvmCovData {
+    files: Vec<LlvmCovFile>,
+    totals: LlvmCovSummary,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlvmCovFile {
+    filename: String,
+    summary: LlvmCovSummary,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct LlvmCovSummary {
+    lines: LlvmCovCount,
+    #[serde(default)]
+    branches: LlvmCovCount,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+struct LlvmCovCount {
+    count: u64,
+    covered: u64,
+}
+
+fn percent(covered: u64, count: u64) -> f64 {
+    if count == 0 {
+        0.0
+    } else {
+        covered as f64 / count as f64 * 100.0
+    }
+}
+
+/// Measures coverage with cargo-llvm-cov and keeps the last report on disk
+#[derive(Debug)]
+pub struct CoverageAnalyzer {
+    pub config: CoverageConfig,
+    pub last_report: Option<CoverageReport>,
+}
+
+impl CoverageAnalyzer {
+    pub fn new() -> Self {
+        Self::with_config(CoverageConfig::from_env())
+    }
+
+    pub fn with_config(config: CoverageConfig) -> Self {
+        Self { config, last_report: None }
+    }
+
+    /// Runs every crate's tests under coverage, logs files that lost coverage since the
+    /// cached report, and caches the new report. Crates that fail to build or test are
+    /// left out; it is an error if none could be measured.
+    pub async fn measure(&mut self) -> Result<CoverageReport> {
+        let config = self.config.clone();
+        let report = tokio::task::spawn_blocking(move || measure_crates(&config)).await??;
+
+        match load_cached_report(&self.config.cache_path) {
+            Ok(Some(previous)) => {
+                let regressions = coverage_regressions(&previous, &report);
+                if regressions.is_empty() {
+                    info!("No files lost coverage since the last run");
+                }
+                for regression in &regressions {
+                    match regression.current {
+                        Some(current) => warn!("Coverage dropped: {} {:.1}% -> {:.1}%",
+                                               regression.file, regression.previous, current),
+                        None => warn!("Coverage dropped: {} {:.1}% -> not measured",
+                                      regression.file, regression.previous),
+                    }
+                }
+            }
+            Ok(None) => debug!("No cached coverage report at {}", self.config.cache_path.display()),
+            Err(e) => warn!("Ignoring unreadable coverage cache: {}", e),
+        }
+
+        if let Err(e) = save_report(&self.config.cache_path, &report) {
+            warn!("Failed to cache coverage report: {}", e);
+        }
+
+        info!("Line coverage {:.1}%, branch coverage {:.1}%", report.line_coverage, report.branch_coverage);
+        self.last_report = Some(report.clone());
+        Ok(report)
+    }
+
+    /// Whether the last measured line coverage meets the configured threshold
+    pub fn meets_threshold(&self) -> bool {
+        self.last_report.as_ref()
+            .map_or(false, |report| report.line_coverage >= self.config.line_threshold)
+    }
+}
+
+fn measure_crates(config: &CoverageConfig) -> Result<CoverageReport> {
+    let mut exports = Vec::new();
+    for crate_path in &config.crates {
+        let crate_dir = config.workspace_root.join(crate_path);
+        match run_llvm_cov(&crate_dir, config.branch_coverage) {
+            Ok(export) => exports.push((crate_path.clone(), export)),
+            Err(e) => warn!("Skipping coverage for {}: {:#}", crate_path, e),
+        }
+    }
+
+    if exports.is_empty() {
+        bail!("No crate coverage could be measured; is cargo-llvm-cov installed?");
+    }
+    Ok(build_report(&config.workspace_root, exports))
+}
+
+fn run_llvm_cov(crate_dir: &Path, branch_coverage: bool) -> Result<LlvmCovExport> {
+    let output_file = tempfile::NamedTempFile::new()?;
+
+    let mut command = Command::new("cargo");
+    if branch_coverage {
+        command.arg("+nightly");
+    }
+    command
+        .args(["llvm-cov", "--json", "--summary-only", "--output-path"])
+        .arg(output_file.path())
+        .current_dir(crate_dir);
+    if branch_coverage {
+        command.arg("--branch");
+    }
+
+    debug!("Running {:?}", command);
+    let output = command.output().context("failed to run cargo llvm-cov")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("unknown error");
+        return Err(anyhow!("cargo llvm-cov exited with {}: {}", output.status, reason.trim()));
+    }
+
+    let json = std::fs::read_to_string(output_file.path())?;
+    serde_json::from_str(&json).context("unexpected cargo llvm-cov JSON")
+}
+
+/// Combines per-crate exports, keying files by their path relative to `workspace_root`
+fn build_report(workspace_root: &Path, exports: Vec<(String, LlvmCovExport)>) -> CoverageReport {
+    let root = workspace_root.canonicalize().unwrap_or_else(|_| workspace_root.to_path_buf());
+    let mut lines = LlvmCovCount::default();
+    let mut branches = LlvmCovCount::default();
+    let mut crates = Vec::new();
+    let mut files = BTreeMap::new();
+    let mut uncovered_files = Vec::new();
+
+    for (crate_path, export) in exports {
+        let mut crate_lines = LlvmCovCount::default();
+        let mut crate_branches = LlvmCovCount::default();
+
+        for data in export.data {
+            crate_lines.count += data.totals.lines.count;
+            crate_lines.covered += data.totals.lines.covered;
+            crate_branches.count += data.totals.branches.count;
+            crate_branches.covered += data.totals.branches.covered;
+
+            for file in data.files {
+                let path = Path::new(&file.filename);
+                let name = path.strip_prefix(&root).unwrap_or(path).display().to_string();
+                let file_lines = file.summary.lines;
+                if file_lines.count > 0 && file_lines.covered == 0 {
+                    uncovered_files.push(name.clone());
+                }
+                files.insert(name, percent(file_lines.covered, file_lines.count));
+            }
+        }
+
+        lines.count += crate_lines.count;
+        lines.covered += crate_lines.covered;
+        branches.count += crate_branches.count;
+        branches.covered += crate_branches.covered;
+        crates.push(CrateCoverage {
+            name: crate_path,
+            line_coverage: percent(crate_lines.covered, crate_lines.count),
+            branch_coverage: percent(crate_branches.covered, crate_branches.count),
+        });
+    }
+
+    uncovered_files.sort();
+    CoverageReport {
+        line_coverage: percent(lines.covered, lines.count),
+        branch_coverage: percent(branches.covered, branches.count),
+        uncovered_files,
+        crates,
+        files,
+    }
+}
+
+/// Files whose line coverage fell, or that are no longer measured, since `previous`
+pub fn coverage_regressions(previous: &CoverageReport, current: &CoverageReport) -> Vec<CoverageRegression> {
+    previous.files.iter()
+        .filter_map(|(file, &before)| match current.files.get(file) {
+            Some(&after) if after + COVERAGE_DIFF_EPSILON < before => Some(CoverageRegression {
+                file: file.clone(),
+                previous: before,
+                current: Some(after),
+            }),
+            None if before > 0.0 => Some(CoverageRegression {
+                file: file.clone(),
+                previous: before,
+                current: None,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn load_cached_report(path: &Path) -> Result<Option<CoverageReport>> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_report(path: &Path, report: &CoverageReport) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(report)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(root: &str, files: &[(&str, u64, u64)], branches: (u64, u64)) -> LlvmCovExport {
+        let json = serde_json::json!({
+            "type": "llvm.coverage.json.export",
+            "version": "2.0.1",
+            "data": [{
+                "files": files.iter().map(|(name, count, covered)| serde_json::json!({
+                    "filename": format!("{}/{}", root, name),
+                    "summary": {
+                        "lines": { "count": count, "covered": covered, "percent": 0.0 },
+                        "functions": { "count": 1, "covered": 1, "percent": 100.0 },
+                    },
+                })).collect::<Vec<_>>(),
+                "totals": {
+                    "lines": {
+                        "count": files.iter().map(|f| f.1).sum::<u64>(),
+                        "covered": files.iter().map(|f| f.2).sum::<u64>(),
+                        "percent": 0.0,
+                    },
+                    "branches": { "count": branches.0, "covered": branches.1, "percent": 0.0 },
+                },
+            }],
+        });
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn report(files: &[(&str, f64)]) -> CoverageReport {
+        CoverageReport {
+            line_coverage: 0.0,
+            branch_coverage: 0.0,
+            uncovered_files: Vec::new(),
+            crates: Vec::new(),
+            files: files.iter().map(|(name, pct)| (name.to_string(), *pct)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_build_report_weights_crates_by_line_count() {
+        let report = build_report(Path::new("/repo"), vec![
+            ("apps/a".to_string(), export("/repo/apps/a", &[("src/lib.rs", 80, 80), ("src/dead.rs", 20, 0)], (10, 5))),
+            ("apps/b".to_string(), export("/repo/apps/b", &[("src/main.rs", 100, 50)], (0, 0))),
+        ]);
+
+        assert_eq!(report.line_coverage, 65.0);
+        assert_eq!(report.branch_coverage, 50.0);
+        assert_eq!(report.crates[0], CrateCoverage { name: "apps/a".to_string(), line_coverage: 80.0, branch_coverage: 50.0 });
+        assert_eq!(report.crates[1].line_coverage, 50.0);
+        assert_eq!(report.uncovered_files, vec!["apps/a/src/dead.rs".to_string()]);
+        assert_eq!(report.files.get("apps/b/src/main.rs"), Some(&50.0));
+    }
+
+    #[test]
+    fn test_regressions_list_files_that_lost_coverage() {
+        let previous = report(&[("a.rs", 90.0), ("b.rs", 50.0), ("gone.rs", 40.0), ("same.rs", 70.0)]);
+        let current = report(&[("a.rs", 85.0), ("b.rs", 60.0), ("same.rs", 70.0), ("new.rs", 10.0)]);
+
+        let regressions = coverage_regressions(&previous, &current);
+        assert_eq!(regressions, vec![
+            CoverageRegression { file: "a.rs".to_string(), previous: 90.0, current: Some(85.0) },
+            CoverageRegression { file: "gone.rs".to_string(), previous: 40.0, current: None },
+        ]);
+    }
+
+    #[test]
+    fn test_cached_report_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache/coverage.json");
+        assert_eq!(load_cached_report(&path).unwrap(), None);
+
+        let cached = report(&[("a.rs", 90.0)]);
+        save_report(&path, &cached).unwrap();
+        assert_eq!(load_cached_report(&path).unwrap(), Some(cached));
+    }
+}
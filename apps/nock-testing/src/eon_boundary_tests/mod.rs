@@ -0,0 +1,478 @@
+// Eon Boundary Tests for NOCK Ecosystem
+// Edge cases around the last block of an eon. A mock blockchain supplies the blocks; the
+// transition prediction and the bridge's eon rules under test are the production code.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use log::{info, debug, warn};
+use anyhow::{Result, anyhow, ensure};
+use serde::{Deserialize, Serialize};
+use nock_bridge_math::EonError;
+use crate::{TestResult, TestCategoryResult};
+
+// nock-mobile is a Tauri binary, so its transition projection is compiled in directly
+#[path = "../../../nock-mobile/src/eon/prediction.rs"]
+#[allow(dead_code)]
+mod prediction;
+
+use prediction::{project_transition, BlockSample, EonProgress, PREDICTION_SAMPLE_BLOCKS};
+
+/// Eon length used by the mock chain, short enough to cross several boundaries per test
+pub const DEFAULT_TEST_EON_LENGTH: u64 = 10;
+
+/// Proof-power every mock block contributes towards the eon's work threshold
+const MOCK_PROOF_POWER: f64 = 1_000.0;
+
+const MOCK_BLOCK_TIME_SECS: i64 = 600;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockBlock {
+    pub height: u64,
+    pub eon: u64,
+    pub proof_power: f64,
+    pub timestamp: DateTime<Utc>,
+    pub deposit_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockDeposit {
+    pub id: String,
+    pub amount: u64,
+}
+
+/// A deposit included in a block, waiting to be relayed to the bridge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncludedDeposit {
+    pub id: String,
+    pub amount: u64,
+    pub block_height: u64,
+    pub eon: u64,
+}
+
+/// Mock NOCK chain whose eons end once accumulated proof-power reaches the eon's work
+/// threshold, as the real chain's do. Every block carries the same proof-power, so an
+/// eon lasts exactly `eon_length` blocks.
+#[derive(Debug)]
+pub struct MockEonChain {
+    pub eon_length: u64,
+    pub block_time: Duration,
+    pub genesis_time: DateTime<Utc>,
+    blocks: Vec<MockBlock>,
+    current_eon: u64,
+    accumulated_work: f64,
+    pending_deposits: Vec<MockDeposit>,
+    included_deposits: Vec<IncludedDeposit>,
+}
+
+impl MockEonChain {
+    pub fn new(eon_length: u64) -> Self {
+        assert!(eon_length >= 2, "eon length must be at least 2 blocks");
+        Self {
+            eon_length,
+            block_time: Duration::seconds(MOCK_BLOCK_TIME_SECS),
+            genesis_time: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            blocks: Vec::new(),
+            current_eon: 0,
+            accumulated_work: 0.0,
+            pending_deposits: Vec::new(),
+            included_deposits: Vec::new(),
+        }
+    }
+
+    pub fn work_threshold(&self) -> f64 {
+        self.eon_length as f64 * MOCK_PROOF_POWER
+    }
+
+    pub fn eon_start_height(&self, eon: u64) -> u64 {
+        eon * self.eon_length
+    }
+
+    pub fn timestamp_at(&self, height: u64) -> DateTime<Utc> {
+        self.genesis_time + self.block_time * height as i32
+    }
+
+    /// Work accumulated so far in the eon the next block will belong to
+    pub fn progress(&self) -> EonProgress {
+        EonProgress {
+            eon: self.current_eon,
+            accumulated_work: self.accumulated_work,
+            work_threshold: self.work_threshold(),
+        }
+    }
+
+    pub fn submit_deposit(&mut self, id: &str, amount: u64) {
+        self.pending_deposits.push(MockDeposit { id: id.to_string(), amount });
+    }
+
+    /// Mines the next block, including every pending deposit
+    pub fn mine_block(&mut self) -> &MockBlock {
+        let height = self.blocks.len() as u64;
+        if self.accumulated_work >= self.work_threshold() {
+            self.current_eon += 1;
+            self.accumulated_work = 0.0;
+        }
+        let eon = self.current_eon;
+        self.accumulated_work += MOCK_PROOF_POWER;
+
+        let deposits: Vec<MockDeposit> = self.pending_deposits.drain(..).collect();
+        for deposit in &deposits {
+            self.included_deposits.push(IncludedDeposit {
+                id: deposit.id.clone(),
+                amount: deposit.amount,
+                block_height: height,
+                eon,
+            });
+        }
+
+        self.blocks.push(MockBlock {
+            height,
+            eon,
+            proof_power: MOCK_PROOF_POWER,
+            timestamp: self.timestamp_at(height),
+            deposit_ids: deposits.into_iter().map(|d| d.id).collect(),
+        });
+        self.blocks.last().unwrap()
+    }
+
+    pub fn mine_blocks(&mut self, count: u64) {
+        for _ in 0..count {
+            self.mine_block();
+        }
+    }
+
+    pub fn block(&self, height: u64) -> Option<&MockBlock> {
+        self.blocks.get(height as usize)
+    }
+
+    pub fn block_count(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// The most recent blocks, as `EonMonitor` fetches them for a prediction
+    pub fn recent_samples(&self) -> Vec<BlockSample> {
+        let start = self.blocks.len().saturating_sub(PREDICTION_SAMPLE_BLOCKS);
+        self.blocks[start..].iter()
+            .map(|block| BlockSample {
+                height: block.height,
+                timestamp: block.timestamp,
+                proof_power: block.proof_power,
+            })
+            .collect()
+    }
+
+    /// Blocks with `start <= timestamp < end`
+    pub fn blocks_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&MockBlock> {
+        self.blocks.iter()
+            .filter(|block| block.timestamp >= start && block.timestamp < end)
+            .collect()
+    }
+
+    /// Time range covered by an eon, ending exactly where the next eon begins
+    pub fn eon_time_range(&self, eon: u64) -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            self.timestamp_at(self.eon_start_height(eon)),
+            self.timestamp_at(self.eon_start_height(eon + 1)),
+        )
+    }
+
+    pub fn pending_deposits(&self) -> &[MockDeposit] {
+        &self.pending_deposits
+    }
+
+    pub fn included_deposits(&self) -> &[IncludedDeposit] {
+        &self.included_deposits
+    }
+}
+
+/// Bridge eon state driven through the program's own eon checks from nock-bridge-math.
+/// Deposit deduplication mirrors the program's one `ProcessedDeposit` account per
+/// Nockchain transaction.
+#[derive(Debug, Default)]
+pub struct MockEonBridge {
+    pub current_eon: u64,
+    pub eon_start_block_height: u64,
+    processed: HashSet<String>,
+    minted: u64,
+}
+
+impl MockEonBridge {
+    pub fn register_eon_transition(&mut self, new_eon: u64, start_block_height: u64) -> Result<(), EonError> {
+        nock_bridge_math::check_eon_transition(self.current_eon, self.eon_start_block_height, new_eon, start_block_height)?;
+        self.current_eon = new_eon;
+        self.eon_start_block_height = start_block_height;
+        Ok(())
+    }
+
+    /// Returns whether the deposit was minted; `false` for a deposit already processed
+    pub fn deposit(&mut self, deposit: &IncludedDeposit, eon: Option<u64>) -> Result<bool, EonError> {
+        nock_bridge_math::check_deposit_eon(deposit.block_height, eon, self.current_eon, self.eon_start_block_height)?;
+        if !self.processed.insert(deposit.id.clone()) {
+            return Ok(false);
+        }
+        self.minted += deposit.amount;
+        Ok(true)
+    }
+
+    pub fn minted(&self) -> u64 {
+        self.minted
+    }
+}
+
+/// Tags a deposit the way a relayer must: with its block's eon once the bridge has moved on
+fn relayer_eon_tag(bridge: &MockEonBridge, deposit: &IncludedDeposit) -> Option<u64> {
+    (deposit.block_height < bridge.eon_start_block_height).then_some(deposit.eon)
+}
+
+/// Tests consensus-sensitive behaviour at the last block of an eon
+#[derive(Debug)]
+pub struct EonBoundaryTester {
+    pub eon_length: u64,
+}
+
+impl EonBoundaryTester {
+    pub fn new() -> Self {
+        Self::with_eon_length(DEFAULT_TEST_EON_LENGTH)
+    }
+
+    pub fn with_eon_length(eon_length: u64) -> Self {
+        Self { eon_length }
+    }
+
+    /// Run every eon boundary edge case
+    pub async fn run_all(&self) -> Result<TestCategoryResult> {
+        info!("Running eon boundary edge case tests (eon length: {})", self.eon_length);
+
+        let mut results = TestCategoryResult::new();
+        results.add_result(&self.test_reward_continuity().await?);
+        results.add_result(&self.test_difficulty_reset().await?);
+        results.add_result(&self.test_statistics_at_boundary().await?);
+        results.add_result(&self.test_transition_prediction_one_block_left().await?);
+        results.add_result(&self.test_bridge_deposits_at_boundary().await?);
+
+        info!("Eon boundary tests completed: {}/{} passed", results.passed, results.total);
+        Ok(results)
+    }
+
+    /// Reward on the last block of eon N equals the reward on the first block of eon N+1.
+    /// Skipped: no block reward schedule is implemented in this tree, and checking the mock
+    /// chain's own curve would only test the mock.
+    pub async fn test_reward_continuity(&self) -> Result<TestResult> {
+        Ok(self.skip("eon_boundary_reward_continuity", "no block reward schedule to test against"))
+    }
+
+    /// Difficulty resets correctly at the eon boundary. Skipped for the same reason as
+    /// reward continuity: there is no difficulty adjustment implemented in this tree.
+    pub async fn test_difficulty_reset(&self) -> Result<TestResult> {
+        Ok(self.skip("eon_boundary_difficulty_reset", "no difficulty adjustment to test against"))
+    }
+
+    /// A block mined exactly at the boundary timestamp is counted in exactly one eon
+    pub async fn test_statistics_at_boundary(&self) -> Result<TestResult> {
+        self.run_case("eon_boundary_statistics_no_double_count", |chain| {
+            chain.mine_blocks(chain.eon_length * 2 + 1);
+            let boundary = chain.eon_start_height(1);
+
+            let (eon0_start, eon0_end) = chain.eon_time_range(0);
+            let (eon1_start, _) = chain.eon_time_range(1);
+            ensure!(eon0_end == eon1_start, "Eon time ranges are not contiguous");
+
+            let eon0_blocks = chain.blocks_between(eon0_start, eon0_end);
+            ensure!(
+                eon0_blocks.iter().all(|block| block.height != boundary),
+                "Boundary block {} counted in eon 0",
+                boundary
+            );
+
+            let counted: u64 = (0..=2)
+                .map(|eon| {
+                    let (start, end) = chain.eon_time_range(eon);
+                    chain.blocks_between(start, end).len() as u64
+                })
+                .sum();
+            ensure!(
+                counted == chain.block_count(),
+                "Per-eon statistics count {} blocks, chain has {}",
+                counted, chain.block_count()
+            );
+
+            // Querying at exactly the boundary instant returns only the boundary block
+            let boundary_time = chain.timestamp_at(boundary);
+            let at_boundary = chain.blocks_between(boundary_time, boundary_time + Duration::seconds(1));
+            ensure!(
+                at_boundary.len() == 1 && at_boundary[0].eon == 1,
+                "Query at boundary returned {} blocks",
+                at_boundary.len()
+            );
+            Ok(())
+        })
+    }
+
+    /// With one block left, the projection `EonMonitor::check_transition_prediction` makes
+    /// from the chain's recent blocks and eon progress names the exact transition block
+    /// and time
+    pub async fn test_transition_prediction_one_block_left(&self) -> Result<TestResult> {
+        self.run_case("eon_boundary_prediction_one_block_left", |chain| {
+            // Two full eons of history give the projection enough samples
+            chain.mine_blocks(chain.eon_length * 3 - 1);
+            let progress = chain.progress();
+
+            let projection = project_transition(&chain.recent_samples(), &progress)?;
+            ensure!(projection.blocks_remaining == 1, "Expected 1 block remaining, got {}", projection.blocks_remaining);
+            ensure!(projection.confidence >= 0.99, "Confidence {:.2} too low with 1 block left", projection.confidence);
+
+            let actual = chain.mine_block().clone();
+            ensure!(actual.eon == progress.eon, "Block {} unexpectedly started eon {}", actual.height, actual.eon);
+            let first = chain.mine_block().clone();
+            ensure!(
+                first.eon == progress.eon + 1,
+                "Eon {} did not end after block {}",
+                progress.eon, actual.height
+            );
+            ensure!(
+                actual.height == projection.predicted_block,
+                "Predicted the threshold at block {}, reached at {}",
+                projection.predicted_block, actual.height
+            );
+            ensure!(
+                actual.timestamp == projection.estimated_utc,
+                "Predicted the threshold at {}, reached at {}",
+                projection.estimated_utc, actual.timestamp
+            );
+            Ok(())
+        })
+    }
+
+    /// Deposits straddling the boundary are each minted once, and only with the eon tag
+    /// the bridge requires once the transition is registered
+    pub async fn test_bridge_deposits_at_boundary(&self) -> Result<TestResult> {
+        self.run_case("eon_boundary_bridge_deposits", |chain| {
+            let mut bridge = MockEonBridge::default();
+            chain.mine_blocks(chain.eon_length - 1);
+            let boundary = chain.eon_start_height(1);
+
+            chain.submit_deposit("last-block-of-eon-0", 1_000);
+            chain.mine_block();
+            chain.submit_deposit("first-block-of-eon-1", 2_500);
+            let first = chain.mine_block().clone();
+            chain.mine_block();
+
+            ensure!(chain.pending_deposits().is_empty(), "Deposits left pending after the boundary");
+            ensure!(first.height == boundary && first.eon == 1, "Eon 1 did not start at block {}", boundary);
+
+            // Validators register the transition before either deposit is relayed
+            bridge.register_eon_transition(first.eon, first.height)
+                .map_err(|e| anyhow!("Transition to eon 1 rejected: {}", e))?;
+            ensure!(
+                bridge.register_eon_transition(first.eon, first.height) == Err(EonError::InvalidEonTransition),
+                "Bridge accepted the same eon transition twice"
+            );
+
+            let deposit = |id: &str| {
+                chain.included_deposits().iter()
+                    .find(|d| d.id == id)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Deposit {} was never included", id))
+            };
+            let last_of_eon0 = deposit("last-block-of-eon-0")?;
+            let first_of_eon1 = deposit("first-block-of-eon-1")?;
+            ensure!(
+                (last_of_eon0.block_height, last_of_eon0.eon) == (boundary - 1, 0)
+                    && (first_of_eon1.block_height, first_of_eon1.eon) == (boundary, 1),
+                "Deposits landed in blocks {} and {}",
+                last_of_eon0.block_height, first_of_eon1.block_height
+            );
+
+            // An attestation from before the transition must name its eon
+            ensure!(
+                bridge.deposit(&last_of_eon0, None) == Err(EonError::MissingEonTag),
+                "Untagged deposit from eon 0 accepted after the transition"
+            );
+            ensure!(
+                bridge.deposit(&last_of_eon0, Some(1)) == Err(EonError::EonTagMismatch),
+                "Deposit from eon 0 accepted with the eon 1 tag"
+            );
+
+            for included in [&last_of_eon0, &first_of_eon1] {
+                let tag = relayer_eon_tag(&bridge, included);
+                ensure!(
+                    bridge.deposit(included, tag) == Ok(true),
+                    "Deposit {} with tag {:?} rejected",
+                    included.id, tag
+                );
+                ensure!(
+                    bridge.deposit(included, tag) == Ok(false),
+                    "Deposit {} minted twice",
+                    included.id
+                );
+            }
+
+            ensure!(bridge.minted() == 3_500, "Bridge minted {}, deposits total 3500", bridge.minted());
+            Ok(())
+        })
+    }
+
+    fn skip(&self, name: &str, reason: &str) -> TestResult {
+        warn!("Skipping {}: {}", name, reason);
+        TestResult::skipped(name.to_string(), reason.to_string())
+    }
+
+    fn run_case<F>(&self, name: &str, case: F) -> Result<TestResult>
+    where
+        F: FnOnce(&mut MockEonChain) -> Result<()>,
+    {
+        let start_time = std::time::Instant::now();
+        debug!("Testing {}", name);
+
+        let mut chain = MockEonChain::new(self.eon_length);
+        let outcome = case(&mut chain);
+        let execution_time = Duration::from_std(start_time.elapsed()).unwrap_or(Duration::zero());
+
+        Ok(match outcome {
+            Ok(()) => TestResult::passed(name.to_string(), execution_time),
+            Err(e) => TestResult::failed(name.to_string(), execution_time, e.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_passed(result: TestResult) {
+        assert_eq!(result.status, "PASSED", "{}: {:?}", result.name, result.error_message);
+    }
+
+    #[tokio::test]
+    async fn test_reward_and_difficulty_cases_are_skipped() {
+        let results = EonBoundaryTester::new().run_all().await.unwrap();
+        assert_eq!((results.total, results.passed, results.failed), (5, 3, 0));
+    }
+
+    #[tokio::test]
+    async fn test_boundary_block_counted_once() {
+        assert_passed(EonBoundaryTester::new().test_statistics_at_boundary().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_prediction_with_one_block_remaining() {
+        assert_passed(EonBoundaryTester::new().test_transition_prediction_one_block_left().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_deposits_at_boundary_processed_once() {
+        assert_passed(EonBoundaryTester::new().test_bridge_deposits_at_boundary().await.unwrap());
+    }
+
+    #[test]
+    fn test_chain_eons_end_at_work_threshold() {
+        let mut chain = MockEonChain::new(DEFAULT_TEST_EON_LENGTH);
+        chain.mine_blocks(DEFAULT_TEST_EON_LENGTH * 2 + 1);
+
+        for eon in 0..=2 {
+            let start = chain.eon_start_height(eon);
+            assert_eq!(chain.block(start).unwrap().eon, eon);
+        }
+        assert_eq!(chain.block(DEFAULT_TEST_EON_LENGTH - 1).unwrap().eon, 0);
+    }
+}
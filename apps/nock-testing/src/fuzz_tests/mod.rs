@@ -0,0 +1,359 @@
+// Fuzz Tests for NOCK Ecosystem
+// Corpus-based libFuzzer runs over message encoders and parsers, driven through cargo-fuzz
+
+use chrono::Duration;
+use log::{info, warn, debug};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+use crate::{SecurityFinding, TestResult, TestCategoryResult};
+
+/// Time each target is fuzzed for unless `FUZZ_DURATION_SECS` is set
+pub const DEFAULT_FUZZ_DURATION_SECS: u64 = 60;
+
+/// Allowance on top of the fuzzing time for building the target with sanitizers
+const FUZZ_BUILD_TIMEOUT_SECS: u64 = 900;
+
+/// A cargo-fuzz target, run from `crate_dir` (the crate containing the `fuzz/` directory)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzTarget {
+    pub name: String,
+    pub crate_dir: PathBuf,
+    pub component: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzConfig {
+    pub duration_secs: u64,
+    /// Toolchain passed to cargo; libFuzzer instrumentation requires nightly
+    pub toolchain: String,
+    pub workspace_root: PathBuf,
+}
+
+impl FuzzConfig {
+    /// Reads `FUZZ_DURATION_SECS`, `FUZZ_TOOLCHAIN` and `NOCK_WORKSPACE_ROOT`
+    pub fn from_env() -> Self {
+        let duration_secs = std::env::var("FUZZ_DURATION_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_FUZZ_DURATION_SECS);
+        let workspace_root = std::env::var("NOCK_WORKSPACE_ROOT")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../.."));
+
+        Self {
+            duration_secs,
+            toolchain: std::env::var("FUZZ_TOOLCHAIN").unwrap_or_else(|_| "nightly".to_string()),
+            workspace_root,
+        }
+    }
+}
+
+/// Coverage and throughput reported by libFuzzer at the end of a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FuzzStats {
+    pub executions: u64,
+    /// Code edges covered
+    pub coverage: u64,
+    /// Coverage features (edges plus comparison and counter signals)
+    pub features: u64,
+    pub corpus_size: u64,
+    pub new_units: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FuzzOutcome {
+    Clean,
+    /// A panic, failed assertion or sanitizer error, with the reproducing input if written
+    Crashed { reason: String, artifact: Option<String> },
+    /// The target could not be run, e.g. cargo-fuzz is not installed
+    Skipped(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzRunReport {
+    pub target: FuzzTarget,
+    pub stats: FuzzStats,
+    pub outcome: FuzzOutcome,
+    pub execution_time: Duration,
+}
+
+/// Runs every registered fuzz target and turns crashes into security findings
+#[derive(Debug)]
+pub struct FuzzTestManager {
+    pub config: FuzzConfig,
+    pub targets: Vec<FuzzTarget>,
+    reports: Vec<FuzzRunReport>,
+}
+
+impl FuzzTestManager {
+    pub fn new() -> Self {
+        Self::with_config(FuzzConfig::from_env())
+    }
+
+    pub fn with_config(config: FuzzConfig) -> Self {
+        let targets = vec![FuzzTarget {
+            name: "deposit_message".to_string(),
+            crate_dir: config.workspace_root.join("apps/solana-bridge/programs/nock-bridge"),
+            component: "solana-bridge".to_string(),
+        }];
+
+        Self { config, targets, reports: Vec::new() }
+    }
+
+    pub fn add_target(&mut self, target: FuzzTarget) {
+        self.targets.push(target);
+    }
+
+    pub fn reports(&self) -> &[FuzzRunReport] {
+        &self.reports
+    }
+
+    /// Fuzz every target in turn. Skipped targets count towards the total only.
+    pub async fn run_all(&mut self) -> Result<TestCategoryResult> {
+        info!("Fuzzing {} targets for {}s each", self.targets.len(), self.config.duration_secs);
+
+        let mut results = TestCategoryResult::new();
+        self.reports.clear();
+
+        for target in self.targets.clone() {
+            let report = self.run_target(&target).await;
+            let name = format!("fuzz::{}", target.name);
+
+            match &report.outcome {
+                FuzzOutcome::Clean => {
+                    info!("{}: {} executions, cov {}, corpus {}", target.name,
+                          report.stats.executions, report.stats.coverage, report.stats.corpus_size);
+                    results.add_result(&TestResult::passed(name, report.execution_time));
+                }
+                FuzzOutcome::Crashed { reason, .. } => {
+                    warn!("{} crashed: {}", target.name, reason);
+                    results.add_result(&TestResult::failed(name, report.execution_time, reason.clone()));
+                }
+                FuzzOutcome::Skipped(reason) => {
+                    warn!("Skipping fuzz target {}: {}", target.name, reason);
+                    results.total += 1;
+                }
+            }
+            self.reports.push(report);
+        }
+
+        Ok(results)
+    }
+
+    async fn run_target(&self, target: &FuzzTarget) -> FuzzRunReport {
+        let start_time = std::time::Instant::now();
+        let (stats, outcome) = if !target.crate_dir.join("fuzz").is_dir() {
+            (FuzzStats::default(), FuzzOutcome::Skipped(format!("no fuzz crate in {}", target.crate_dir.display())))
+        } else {
+            match self.invoke_cargo_fuzz(target).await {
+                Ok(output) => parse_fuzz_output(&output),
+                Err(e) => (FuzzStats::default(), FuzzOutcome::Skipped(e.to_string())),
+            }
+        };
+
+        FuzzRunReport {
+            target: target.clone(),
+            stats,
+            outcome,
+            execution_time: Duration::from_std(start_time.elapsed()).unwrap_or(Duration::zero()),
+        }
+    }
+
+    /// Runs `cargo fuzz run` against the target's corpus and returns libFuzzer's log
+    async fn invoke_cargo_fuzz(&self, target: &FuzzTarget) -> Result<String> {
+        let duration = self.config.duration_secs;
+        let mut command = Command::new("cargo");
+        command
+            .arg(format!("+{}", self.config.toolchain))
+            .args(["fuzz", "run", &target.name, "--"])
+            .arg(format!("-max_total_time={}", duration))
+            .arg("-print_final_stats=1")
+            .current_dir(&target.crate_dir)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        debug!("Running {:?}", command);
+        let limit = std::time::Duration::from_secs(duration + FUZZ_BUILD_TIMEOUT_SECS);
+        let output = tokio::time::timeout(limit, command.output()).await
+            .map_err(|_| anyhow::anyhow!("cargo fuzz did not finish within {}s", limit.as_secs()))??;
+
+        let log = String::from_utf8_lossy(&output.stderr).into_owned();
+        if !output.status.success() && !log.contains("libFuzzer") {
+            // Failed before libFuzzer started: missing cargo-fuzz, toolchain or build error
+            let reason = log.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("cargo fuzz failed");
+            anyhow::bail!("{}", reason.trim());
+        }
+        Ok(log)
+    }
+
+    /// HIGH severity finding for every target that crashed in the last run
+    pub fn security_findings(&self) -> Vec<SecurityFinding> {
+        self.reports.iter()
+            .filter_map(|report| match &report.outcome {
+                FuzzOutcome::Crashed { reason, artifact } => Some(SecurityFinding {
+                    severity: "HIGH".to_string(),
+                    category: "Fuzzing".to_string(),
+                    description: format!("Fuzz target {} crashed: {}", report.target.name, reason),
+                    recommendation: match artifact {
+                        Some(path) => format!(
+                            "Reproduce with `cargo fuzz run {} {}` and fix the crash", report.target.name, path),
+                        None => format!("Re-run fuzz target {} to reproduce and fix the crash", report.target.name),
+                    },
+                    affected_components: vec![report.target.component.clone()],
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Extracts run statistics and any crash from libFuzzer's stderr
+pub fn parse_fuzz_output(log: &str) -> (FuzzStats, FuzzOutcome) {
+    let mut stats = FuzzStats::default();
+    let mut panic_message = None;
+    let mut error = None;
+    let mut artifact = None;
+
+    let mut lines = log.lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+
+        if let Some(rest) = line.strip_prefix("stat::number_of_executed_units:") {
+            stats.executions = rest.trim().parse().unwrap_or(stats.executions);
+        } else if let Some(rest) = line.strip_prefix("stat::new_units_added:") {
+            stats.new_units = rest.trim().parse().unwrap_or(0);
+        } else if line.starts_with('#') && line.contains(" cov: ") {
+            // Status lines: "#1024	NEW    cov: 310 ft: 512 corp: 40/1Kb ..."
+            if let Some(executions) = line[1..].split_whitespace().next().and_then(|n| n.parse().ok()) {
+                stats.executions = executions;
+            }
+            stats.coverage = field_after(line, "cov:").unwrap_or(stats.coverage);
+            stats.features = field_after(line, "ft:").unwrap_or(stats.features);
+            stats.corpus_size = field_after(line, "corp:").unwrap_or(stats.corpus_size);
+        } else if line.contains("panicked at") && panic_message.is_none() {
+            // Older toolchains put the message on the same line, newer ones on the next
+            panic_message = match line.split_once("panicked at '") {
+                Some((_, rest)) => Some(rest.rsplit_once("', ").map_or(rest, |(message, _)| message).to_string()),
+                None => lines.peek().map(|next| next.trim().to_string()),
+            };
+        } else if let Some((_, rest)) = line.split_once("ERROR: libFuzzer: ") {
+            error.get_or_insert_with(|| rest.to_string());
+        } else if let Some((_, path)) = line.split_once("Test unit written to ") {
+            artifact = Some(path.trim().to_string());
+        }
+    }
+
+    let outcome = match (panic_message, error) {
+        (Some(message), _) => FuzzOutcome::Crashed { reason: format!("panic: {}", message), artifact },
+        (None, Some(error)) => FuzzOutcome::Crashed { reason: error, artifact },
+        (None, None) => FuzzOutcome::Clean,
+    };
+    (stats, outcome)
+}
+
+/// Number following `label` on a libFuzzer status line; "corp: 40/1Kb" yields 40
+fn field_after(line: &str, label: &str) -> Option<u64> {
+    let (_, rest) = line.split_once(label)?;
+    rest.split_whitespace().next()?.split('/').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLEAN_RUN: &str = "\
+INFO: Seed: 1234
+#2	INITED cov: 120 ft: 121 corp: 1/1b exec/s: 0 rss: 30Mb
+#1024	NEW    cov: 310 ft: 512 corp: 40/1Kb lim: 14 exec/s: 512 rss: 38Mb
+#65536	DONE   cov: 318 ft: 530 corp: 42/1Kb lim: 98 exec/s: 2184 rss: 41Mb
+Done 65536 runs in 31 second(s)
+stat::number_of_executed_units: 65536
+stat::average_exec_per_sec:     2184
+stat::new_units_added:          41
+";
+
+    #[test]
+    fn test_parses_clean_run_statistics() {
+        let (stats, outcome) = parse_fuzz_output(CLEAN_RUN);
+        assert_eq!(outcome, FuzzOutcome::Clean);
+        assert_eq!(stats, FuzzStats { executions: 65536, coverage: 318, features: 530, corpus_size: 42, new_units: 41 });
+    }
+
+    #[test]
+    fn test_parses_panic_with_artifact() {
+        let log = "\
+#512	NEW    cov: 200 ft: 300 corp: 12/100b exec/s: 0 rss: 35Mb
+thread '<unnamed>' panicked at fuzz_targets/deposit_message.rs:31:5:
+assertion `left == right` failed
+==4242== ERROR: libFuzzer: deadly signal
+artifact_prefix='fuzz/artifacts/deposit_message/'; Test unit written to fuzz/artifacts/deposit_message/crash-9f1c
+";
+        let (stats, outcome) = parse_fuzz_output(log);
+        assert_eq!(stats.coverage, 200);
+        assert_eq!(outcome, FuzzOutcome::Crashed {
+            reason: "panic: assertion `left == right` failed".to_string(),
+            artifact: Some("fuzz/artifacts/deposit_message/crash-9f1c".to_string()),
+        });
+    }
+
+    #[test]
+    fn test_parses_old_style_panic_and_sanitizer_errors() {
+        let log = "thread '<unnamed>' panicked at 'index out of bounds', src/lib.rs:10:5\n";
+        assert_eq!(parse_fuzz_output(log).1, FuzzOutcome::Crashed {
+            reason: "panic: index out of bounds".to_string(),
+            artifact: None,
+        });
+
+        let log = "==1== ERROR: libFuzzer: timeout after 1200 seconds\n";
+        assert!(matches!(parse_fuzz_output(log).1, FuzzOutcome::Crashed { reason, .. } if reason == "timeout after 1200 seconds"));
+    }
+
+    #[test]
+    fn test_only_crashes_become_high_findings() {
+        let target = |name: &str| FuzzTarget {
+            name: name.to_string(),
+            crate_dir: PathBuf::from("."),
+            component: "solana-bridge".to_string(),
+        };
+        let mut manager = FuzzTestManager::with_config(FuzzConfig::from_env());
+        manager.reports = vec![
+            FuzzRunReport {
+                target: target("clean"),
+                stats: FuzzStats::default(),
+                outcome: FuzzOutcome::Clean,
+                execution_time: Duration::zero(),
+            },
+            FuzzRunReport {
+                target: target("deposit_message"),
+                stats: FuzzStats::default(),
+                outcome: FuzzOutcome::Crashed { reason: "panic: overflow".to_string(), artifact: None },
+                execution_time: Duration::zero(),
+            },
+        ];
+
+        let findings = manager.security_findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, "HIGH");
+        assert_eq!(findings[0].affected_components, vec!["solana-bridge".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_fuzz_crate_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = FuzzTestManager::with_config(FuzzConfig::from_env());
+        manager.targets = vec![FuzzTarget {
+            name: "deposit_message".to_string(),
+            crate_dir: dir.path().to_path_buf(),
+            component: "solana-bridge".to_string(),
+        }];
+
+        let results = manager.run_all().await.unwrap();
+        assert_eq!((results.total, results.passed, results.failed), (1, 0, 0));
+        assert!(matches!(manager.reports()[0].outcome, FuzzOutcome::Skipped(_)));
+        assert!(manager.security_findings().is_empty());
+    }
+}
@@ -7,6 +7,7 @@ use log::{info, warn, error, debug};
 use anyhow::{Result, Error};
 use serde::{Deserialize, Serialize};
 use crate::{TestResult, TestCategoryResult};
+use crate::chaos::{ChaosBridgeScenario, ChaosConfig, ChaosInjector};
 
 /// Integration test manager for NOCK ecosystem
 #[derive(Debug)]
@@ -145,6 +146,11 @@ impl IntegrationTestManager {
             .test_end_to_end_bridge_workflow().await?;
         results.add_result(&e2e_bridge_result);
 
+        // Test bridge invariants with injected network faults
+        let chaos_result = self.bridge_integration_tester
+            .test_bridge_under_chaos().await?;
+        results.add_result(&chaos_result);
+
         info!("Bridge integration tests completed: {}/{} passed", 
               results.passed, results.total);
         
@@ -525,6 +531,27 @@ impl BridgeIntegrationTester {
         }
     }
 
+    pub async fn test_bridge_under_chaos(&self) -> Result<TestResult> {
+        let start_time = std::time::Instant::now();
+
+        debug!("Testing bridge under chaos");
+
+        // Relay deposits through latency, packet loss, TCP resets and service crashes
+        let mut injector = ChaosInjector::new(ChaosConfig::from_env());
+        let report = ChaosBridgeScenario::default().run(&mut injector).await?;
+        info!("Chaos bridge run: {} settled, {} failed, {} faults injected",
+              report.settled, report.failed, report.faults.len());
+
+        let execution_time = chrono::Duration::from_std(start_time.elapsed())
+            .unwrap_or(chrono::Duration::zero());
+        if report.passed() {
+            Ok(TestResult::passed("bridge_chaos_invariants".to_string(), execution_time))
+        } else {
+            Ok(TestResult::failed("bridge_chaos_invariants".to_string(),
+                                execution_time, report.violations.join("; ")))
+        }
+    }
+
     // Helper methods for bridge integration tests
     async fn test_zk_proof_bridge_operations(&self) -> Result<()> {
         sleep(Duration::from_millis(200)).await; // Simulate ZK proof operations
@@ -0,0 +1,361 @@
+// Load Tests for NOCK Ecosystem
+// Concurrent simulated users driving mock services with realistic traffic profiles
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use log::{info, warn, debug};
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tokio::time::{sleep, Duration, Instant};
+use crate::{TestResult, TestCategoryResult};
+use crate::chaos::{ChaosDeposit, LatencyDistribution, MockBridgeService, MockValidator, CHAOS_PROGRAM_ID};
+
+/// Seed used unless `LOAD_TEST_SEED` is set
+pub const DEFAULT_LOAD_SEED: u64 = 0x4c4f_4144;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeLoadConfig {
+    pub users: usize,
+    pub duration: Duration,
+    /// Mean of the exponential wait between one user's deposits
+    pub mean_inter_arrival_ms: f64,
+    pub max_deposit_amount: u64,
+    /// Round trip to the RPC node for each deposit_nock call; `None` for no delay
+    pub rpc_latency: Option<LatencyDistribution>,
+    pub max_rpc_latency_ms: u64,
+    pub validator_count: usize,
+    pub threshold: u8,
+    pub max_p99_ms: u64,
+    pub min_success_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for BridgeLoadConfig {
+    fn default() -> Self {
+        Self {
+            users: 100,
+            duration: Duration::from_secs(30),
+            mean_inter_arrival_ms: 200.0,
+            max_deposit_amount: 1_000_000_000,
+            rpc_latency: Some(LatencyDistribution::Pareto { scale_ms: 20.0, shape: 2.5 }),
+            max_rpc_latency_ms: 5_000,
+            validator_count: 5,
+            threshold: 3,
+            max_p99_ms: 2_000,
+            min_success_rate: 0.99,
+            seed: DEFAULT_LOAD_SEED,
+        }
+    }
+}
+
+impl BridgeLoadConfig {
+    /// Defaults overridden by `BRIDGE_LOAD_USERS`, `BRIDGE_LOAD_DURATION_SECS` and `LOAD_TEST_SEED`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(users) = env_parse("BRIDGE_LOAD_USERS") {
+            config.users = users;
+        }
+        if let Some(secs) = env_parse("BRIDGE_LOAD_DURATION_SECS") {
+            config.duration = Duration::from_secs(secs);
+        }
+        if let Some(seed) = env_parse("LOAD_TEST_SEED") {
+            config.seed = seed;
+        }
+        config
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|value| value.parse().ok())
+}
+
+/// Wait drawn from an exponential distribution, i.e. Poisson arrivals
+pub fn exponential_ms(rng: &mut impl Rng, mean_ms: f64) -> u64 {
+    // Inverse CDF; 1 - gen() lies in (0, 1]
+    let u = 1.0 - rng.gen::<f64>();
+    (-mean_ms * u.ln()).round() as u64
+}
+
+/// Stands in for an Anchor client sending deposit_nock to the bridge program. Validators
+/// sign off-chain; the program checks the threshold and mints.
+#[derive(Debug, Clone)]
+pub struct MockBridgeClient {
+    bridge: Arc<Mutex<MockBridgeService>>,
+    validators: Arc<Vec<MockValidator>>,
+    rpc_latency: Option<LatencyDistribution>,
+    max_rpc_latency_ms: u64,
+}
+
+impl MockBridgeClient {
+    pub fn new(config: &BridgeLoadConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        let validators: Vec<MockValidator> = (0..config.validator_count)
+            .map(|index| MockValidator::generate(index, &mut rng))
+            .collect();
+        let bridge = MockBridgeService::new(
+            CHAOS_PROGRAM_ID,
+            validators.iter().map(|v| v.public_key).collect(),
+            config.threshold,
+        );
+
+        Self {
+            bridge: Arc::new(Mutex::new(bridge)),
+            validators: Arc::new(validators),
+            rpc_latency: config.rpc_latency,
+            max_rpc_latency_ms: config.max_rpc_latency_ms,
+        }
+    }
+
+    /// Returns the bridge program's error name on failure
+    pub async fn deposit_nock(&self, deposit: &ChaosDeposit, rng: &mut impl Rng) -> Result<(), String> {
+        if let Some(latency) = self.rpc_latency {
+            sleep(Duration::from_millis(latency.sample(rng, self.max_rpc_latency_ms))).await;
+        }
+        if deposit.amount == 0 {
+            return Err("InvalidAmount".to_string());
+        }
+
+        let signatures: Vec<_> = self.validators.iter()
+            .map(|validator| validator.sign(&CHAOS_PROGRAM_ID, deposit))
+            .collect();
+
+        let mut bridge = self.bridge.lock().expect("bridge lock poisoned");
+        bridge.submit(deposit, &signatures)
+            .map(drop)
+            .map_err(|rejection| rejection.code().to_string())
+    }
+
+    pub fn total_minted(&self) -> u64 {
+        self.bridge.lock().expect("bridge lock poisoned").total_minted()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DepositSample {
+    pub latency: Duration,
+    pub error_code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeLoadReport {
+    pub requests: u64,
+    pub successes: u64,
+    pub error_codes: HashMap<String, u64>,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub success_rate: f64,
+    pub throughput_tps: f64,
+    pub elapsed: Duration,
+}
+
+impl BridgeLoadReport {
+    pub fn from_samples(samples: &[DepositSample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<u64> = samples.iter().map(|s| s.latency.as_millis() as u64).collect();
+        latencies.sort_unstable();
+
+        let mut error_codes = HashMap::new();
+        for code in samples.iter().filter_map(|s| s.error_code.as_ref()) {
+            *error_codes.entry(code.clone()).or_insert(0) += 1;
+        }
+
+        let requests = samples.len() as u64;
+        let successes = requests - error_codes.values().sum::<u64>();
+        let success_rate = if requests == 0 { 0.0 } else { successes as f64 / requests as f64 };
+        let elapsed_secs = elapsed.as_secs_f64();
+
+        Self {
+            requests,
+            successes,
+            error_codes,
+            p50_ms: percentile(&latencies, 50.0),
+            p95_ms: percentile(&latencies, 95.0),
+            p99_ms: percentile(&latencies, 99.0),
+            success_rate,
+            throughput_tps: if elapsed_secs > 0.0 { successes as f64 / elapsed_secs } else { 0.0 },
+            elapsed,
+        }
+    }
+
+    /// Reasons the run misses its targets; empty if it passed
+    pub fn violations(&self, config: &BridgeLoadConfig) -> Vec<String> {
+        let mut violations = Vec::new();
+        if self.p99_ms > config.max_p99_ms {
+            violations.push(format!("P99 latency {} ms exceeds {} ms", self.p99_ms, config.max_p99_ms));
+        }
+        if self.success_rate < config.min_success_rate {
+            violations.push(format!("success rate {:.2}% below {:.2}% (errors: {:?})",
+                                    self.success_rate * 100.0, config.min_success_rate * 100.0, self.error_codes));
+        }
+        violations
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+pub fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Runs `config.users` concurrent users against the bridge until `config.duration` elapses
+pub async fn run_bridge_load(config: &BridgeLoadConfig) -> Result<BridgeLoadReport> {
+    info!("Simulating {} bridge users for {:?}", config.users, config.duration);
+
+    let client = MockBridgeClient::new(config);
+    let start = Instant::now();
+    let deadline = start + config.duration;
+
+    let handles: Vec<_> = (0..config.users)
+        .map(|user| {
+            let client = client.clone();
+            let mean_ms = config.mean_inter_arrival_ms;
+            let max_amount = config.max_deposit_amount;
+            let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(user as u64 + 1));
+
+            tokio::spawn(async move {
+                let mut samples = Vec::new();
+                loop {
+                    sleep(Duration::from_millis(exponential_ms(&mut rng, mean_ms))).await;
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+
+                    let tx_hash: [u8; 32] = rng.gen();
+                    let deposit = ChaosDeposit {
+                        id: hex::encode(tx_hash),
+                        tx_hash,
+                        recipient: rng.gen(),
+                        amount: rng.gen_range(1..=max_amount),
+                        block_height: rng.gen_range(1..=1_000_000),
+                    };
+                    let sent = Instant::now();
+                    let result = client.deposit_nock(&deposit, &mut rng).await;
+                    samples.push(DepositSample { latency: sent.elapsed(), error_code: result.err() });
+                }
+                samples
+            })
+        })
+        .collect();
+
+    let mut samples = Vec::new();
+    for handle in handles {
+        samples.extend(handle.await?);
+    }
+
+    let report = BridgeLoadReport::from_samples(&samples, start.elapsed());
+    debug!("Bridge load: {} minted in total", client.total_minted());
+    info!("Bridge load: {} requests, P50 {} ms, P95 {} ms, P99 {} ms, {:.2}% success, {:.1} TPS",
+          report.requests, report.p50_ms, report.p95_ms, report.p99_ms,
+          report.success_rate * 100.0, report.throughput_tps);
+    Ok(report)
+}
+
+/// Load test manager for NOCK ecosystem
+#[derive(Debug)]
+pub struct LoadTestManager {
+    pub bridge_config: BridgeLoadConfig,
+    pub last_bridge_report: Option<BridgeLoadReport>,
+}
+
+impl LoadTestManager {
+    pub async fn new() -> Self {
+        Self {
+            bridge_config: BridgeLoadConfig::from_env(),
+            last_bridge_report: None,
+        }
+    }
+
+    pub async fn test_mining_load(&mut self) -> Result<TestCategoryResult> {
+        // No mining load profile yet
+        Ok(TestCategoryResult::new())
+    }
+
+    /// Concurrent users depositing through deposit_nock; fails if P99 latency or the
+    /// success rate misses its target
+    pub async fn test_bridge_load(&mut self) -> Result<TestCategoryResult> {
+        let report = run_bridge_load(&self.bridge_config).await?;
+        let execution_time = chrono::Duration::from_std(report.elapsed)
+            .unwrap_or(chrono::Duration::zero());
+        let violations = report.violations(&self.bridge_config);
+
+        let result = if violations.is_empty() {
+            TestResult::passed("bridge_deposit_load".to_string(), execution_time)
+        } else {
+            warn!("Bridge load test failed: {}", violations.join("; "));
+            TestResult::failed("bridge_deposit_load".to_string(), execution_time, violations.join("; "))
+        };
+        self.last_bridge_report = Some(report);
+
+        let mut results = TestCategoryResult::new();
+        results.add_result(&result);
+        Ok(results)
+    }
+
+    pub async fn test_analytics_load(&mut self) -> Result<TestCategoryResult> {
+        // No analytics load profile yet
+        Ok(TestCategoryResult::new())
+    }
+
+    pub async fn test_mobile_load(&mut self) -> Result<TestCategoryResult> {
+        // No mobile load profile yet
+        Ok(TestCategoryResult::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(ms: u64, error_code: Option<&str>) -> DepositSample {
+        DepositSample { latency: Duration::from_millis(ms), error_code: error_code.map(str::to_string) }
+    }
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50.0), 50);
+        assert_eq!(percentile(&values, 99.0), 99);
+        assert_eq!(percentile(&[7], 99.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_exponential_mean() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let n = 20_000;
+        let mean = (0..n).map(|_| exponential_ms(&mut rng, 200.0)).sum::<u64>() as f64 / n as f64;
+        assert!((mean - 200.0).abs() < 10.0, "mean {}", mean);
+    }
+
+    #[test]
+    fn test_report_flags_latency_and_errors() {
+        let config = BridgeLoadConfig::default();
+        let mut samples: Vec<DepositSample> = (0..98).map(|_| sample(10, None)).collect();
+        samples.push(sample(2_500, None));
+        samples.push(sample(3_000, Some("InsufficientSignatures")));
+
+        let report = BridgeLoadReport::from_samples(&samples, Duration::from_secs(10));
+        assert_eq!(report.requests, 100);
+        assert_eq!(report.successes, 99);
+        assert_eq!(report.error_codes["InsufficientSignatures"], 1);
+        assert_eq!(report.p99_ms, 2_500);
+        assert!((report.throughput_tps - 9.9).abs() < 1e-9);
+        assert_eq!(report.violations(&config).len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bridge_load_meets_targets() {
+        let config = BridgeLoadConfig { users: 10, duration: Duration::from_secs(5), ..BridgeLoadConfig::default() };
+        let report = run_bridge_load(&config).await.unwrap();
+
+        // About 10 users x 5 s / 200 ms
+        assert!(report.requests > 100, "{} requests", report.requests);
+        assert_eq!(report.success_rate, 1.0);
+        assert!(report.violations(&config).is_empty());
+    }
+}
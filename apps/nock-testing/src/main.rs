@@ -18,6 +18,10 @@ mod validation_tests;
 mod test_runner;
 mod test_reporting;
 mod mock_services;
+mod eon_boundary_tests;
+mod fuzz_tests;
+mod coverage;
+mod chaos;
 
 use unit_tests::*;
 use integration_tests::*;
@@ -28,6 +32,10 @@ use validation_tests::*;
 use test_runner::*;
 use test_reporting::*;
 use mock_services::*;
+use eon_boundary_tests::*;
+use fuzz_tests::*;
+use coverage::*;
+use chaos::*;
 
 /// Main testing orchestrator for the NOCK ecosystem
 #[derive(Debug)]
@@ -41,6 +49,9 @@ pub struct NockTestSuite {
     pub validation_test_manager: ValidationTestManager,
     pub test_reporter: TestReporter,
     pub mock_service_manager: MockServiceManager,
+    pub eon_boundary_tester: EonBoundaryTester,
+    pub fuzz_test_manager: FuzzTestManager,
+    pub coverage_analyzer: CoverageAnalyzer,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -66,6 +77,7 @@ pub struct TestResults {
     pub performance_metrics: PerformanceMetrics,
     pub security_findings: Vec<SecurityFinding>,
     pub recommendations: Vec<String>,
+    pub coverage: Option<CoverageReport>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,7 +99,7 @@ pub struct PerformanceMetrics {
     pub bottlenecks: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityFinding {
     pub severity: String,
     pub category: String,
@@ -108,6 +120,9 @@ impl NockTestSuite {
             validation_test_manager: ValidationTestManager::new().await,
             test_reporter: TestReporter::new().await,
             mock_service_manager: MockServiceManager::new().await,
+            eon_boundary_tester: EonBoundaryTester::new(),
+            fuzz_test_manager: FuzzTestManager::new(),
+            coverage_analyzer: CoverageAnalyzer::new(),
         }
     }
 
@@ -138,7 +153,7 @@ impl NockTestSuite {
         let security_findings = self.run_security_analysis().await?;
         
         // Generate recommendations
-        let recommendations = self.generate_recommendations(&test_results, &security_findings).await?;
+        let mut recommendations = self.generate_recommendations(&test_results, &security_findings).await?;
 
         // Calculate overall results
         let total_tests = test_results.values().map(|r: &CategoryResults| r.total).sum();
@@ -146,7 +161,7 @@ impl NockTestSuite {
         let failed_tests = test_results.values().map(|r| r.failed).sum();
         let skipped_tests = total_tests - passed_tests - failed_tests;
         
-        let overall_status = if failed_tests == 0 {
+        let mut overall_status = if failed_tests == 0 {
             "PASSED".to_string()
         } else if passed_tests > failed_tests {
             "PARTIALLY_PASSED".to_string()
@@ -154,6 +169,18 @@ impl NockTestSuite {
             "FAILED".to_string()
         };
 
+        // Line coverage below the threshold fails the suite regardless of test outcomes
+        let coverage = self.coverage_analyzer.last_report.clone();
+        if let Some(report) = &coverage {
+            if !self.coverage_analyzer.meets_threshold() {
+                warn!("Line coverage {:.1}% is below the {:.1}% threshold",
+                      report.line_coverage, self.coverage_analyzer.config.line_threshold);
+                overall_status = "FAILED".to_string();
+                recommendations.push(format!("Raise line coverage from {:.1}% to at least {:.1}%",
+                                             report.line_coverage, self.coverage_analyzer.config.line_threshold));
+            }
+        }
+
         let execution_time = Duration::from_std(start_time.elapsed())
             .unwrap_or(Duration::zero());
 
@@ -168,6 +195,7 @@ impl NockTestSuite {
             performance_metrics,
             security_findings,
             recommendations,
+            coverage,
         };
 
         // Generate reports
@@ -231,7 +259,7 @@ impl NockTestSuite {
             passed: passed_tests,
             failed: failed_tests,
             execution_time,
-            coverage_percentage: self.calculate_code_coverage().await?,
+            coverage_percentage: self.calculate_code_coverage().await?.line_coverage,
         })
     }
 
@@ -256,10 +284,13 @@ impl NockTestSuite {
         // Test AI trading integrations
         let ai_trading_e2e = self.integration_test_manager.test_ai_trading_integrations().await?;
 
+        // Test eon boundary edge cases
+        let eon_boundary = self.test_eon_boundary_edge_cases().await?;
+
         let total_tests = mining_e2e.total + bridge_e2e.total + mobile_e2e.total + 
-                         analytics_e2e.total + ai_trading_e2e.total;
+                         analytics_e2e.total + ai_trading_e2e.total + eon_boundary.total;
         let passed_tests = mining_e2e.passed + bridge_e2e.passed + mobile_e2e.passed + 
-                          analytics_e2e.passed + ai_trading_e2e.passed;
+                          analytics_e2e.passed + ai_trading_e2e.passed + eon_boundary.passed;
         let failed_tests = total_tests - passed_tests;
 
         let execution_time = Duration::from_std(start_time.elapsed())
@@ -275,6 +306,13 @@ impl NockTestSuite {
         })
     }
 
+    /// Run eon boundary edge cases against a mock chain with 10-block eons
+    pub async fn test_eon_boundary_edge_cases(&mut self) -> Result<TestCategoryResult> {
+        info!("Running NOCK eon boundary edge case tests");
+
+        self.eon_boundary_tester.run_all().await
+    }
+
     /// Run performance tests
     pub async fn run_performance_tests(&mut self) -> Result<CategoryResults> {
         info!("Running NOCK performance tests");
@@ -371,11 +409,15 @@ impl NockTestSuite {
         // Test consensus security
         let consensus_security = self.security_test_manager.test_consensus_security().await?;
 
+        // Fuzz message encoders and parsers
+        let fuzz_security = self.fuzz_test_manager.run_all().await?;
+
         let total_tests = crypto_security.total + bridge_security.total + wallet_security.total + 
-                         api_security.total + consensus_security.total;
+                         api_security.total + consensus_security.total + fuzz_security.total;
         let passed_tests = crypto_security.passed + bridge_security.passed + wallet_security.passed + 
-                          api_security.passed + consensus_security.passed;
-        let failed_tests = total_tests - passed_tests;
+                          api_security.passed + consensus_security.passed + fuzz_security.passed;
+        let failed_tests = crypto_security.failed + bridge_security.failed + wallet_security.failed + 
+                          api_security.failed + consensus_security.failed + fuzz_security.failed;
 
         let execution_time = Duration::from_std(start_time.elapsed())
             .unwrap_or(Duration::zero());
@@ -457,7 +499,7 @@ impl NockTestSuite {
     }
 
     async fn run_security_analysis(&self) -> Result<Vec<SecurityFinding>> {
-        Ok(vec![
+        let mut findings = vec![
             SecurityFinding {
                 severity: "LOW".to_string(),
                 category: "Performance".to_string(),
@@ -465,7 +507,15 @@ impl NockTestSuite {
                 recommendation: "Implement additional CPU optimizations".to_string(),
                 affected_components: vec!["mining-optimizer".to_string()],
             }
-        ])
+        ];
+
+        // Crashes found while fuzzing during the security test run
+        findings.extend(self.fuzz_test_manager.security_findings());
+
+        // Bridge reentrancy and signature-binding checks that failed
+        findings.extend(self.security_test_manager.security_findings());
+
+        Ok(findings)
     }
 
     async fn generate_recommendations(
@@ -522,9 +572,9 @@ impl NockTestSuite {
         Ok(())
     }
 
-    async fn calculate_code_coverage(&self) -> Result<f64> {
-        // Calculate code coverage across all NOCK components
-        Ok(87.3) // Placeholder - would use actual coverage tools
+    async fn calculate_code_coverage(&mut self) -> Result<CoverageReport> {
+        // Measure line and branch coverage across all NOCK components
+        self.coverage_analyzer.measure().await
     }
 }
 
@@ -558,7 +608,7 @@ async fn main() -> Result<()> {
             info!("Total tests: {}, Passed: {}, Failed: {}", 
                   results.total_tests, results.passed_tests, results.failed_tests);
             
-            if results.failed_tests > 0 {
+            if results.failed_tests > 0 || results.overall_status == "FAILED" {
                 std::process::exit(1);
             }
         }
@@ -601,6 +651,17 @@ impl TestResult {
             metadata: HashMap::new(),
         }
     }
+
+    /// A test that could not run in this environment; counts towards the total only
+    pub fn skipped(name: String, reason: String) -> Self {
+        Self {
+            name,
+            status: "SKIPPED".to_string(),
+            execution_time: Duration::zero(),
+            error_message: Some(reason),
+            metadata: HashMap::new(),
+        }
+    }
 }
 
 // Test category aggregation structure
@@ -624,10 +685,10 @@ impl TestCategoryResult {
 
     pub fn add_result(&mut self, result: &TestResult) {
         self.total += 1;
-        if result.status == "PASSED" {
-            self.passed += 1;
-        } else {
-            self.failed += 1;
+        match result.status.as_str() {
+            "PASSED" => self.passed += 1,
+            "SKIPPED" => {}
+            _ => self.failed += 1,
         }
         self.execution_time = self.execution_time + result.execution_time;
     }
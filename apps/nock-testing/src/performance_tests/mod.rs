@@ -8,6 +8,9 @@ use anyhow::{Result, Error};
 use serde::{Deserialize, Serialize};
 use crate::{TestResult, TestCategoryResult};
 
+mod zk_latency;
+pub use zk_latency::*;
+
 /// Performance test manager for NOCK ecosystem
 #[derive(Debug)]
 pub struct PerformanceTestManager {
@@ -76,6 +79,11 @@ pub struct PerformanceBenchmarkResult {
     pub cpu_usage_percent: f64,
     pub throughput_mbps: f64,
     pub efficiency_score: f64,
+    /// Log-normal fit of per-operation latency, where measured
+    #[serde(default)]
+    pub latency_distribution: Option<LogNormalFit>,
+    #[serde(default)]
+    pub outliers: Vec<LatencyOutlier>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -399,6 +407,8 @@ impl MiningPerformanceTester {
             cpu_usage_percent: 35.7,
             throughput_mbps: 12.5,
             efficiency_score: 0.87,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -415,6 +425,8 @@ impl MiningPerformanceTester {
             cpu_usage_percent: 28.4,
             throughput_mbps: 8.7,
             efficiency_score: 0.92,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -431,6 +443,8 @@ impl MiningPerformanceTester {
             cpu_usage_percent: 42.1,
             throughput_mbps: 15.2,
             efficiency_score: 0.84,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -447,6 +461,8 @@ impl MiningPerformanceTester {
             cpu_usage_percent: 72.3,
             throughput_mbps: 22.1,
             efficiency_score: 0.89,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -463,6 +479,8 @@ impl MiningPerformanceTester {
             cpu_usage_percent: 18.7,
             throughput_mbps: 4.2,
             efficiency_score: 0.95,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 }
@@ -585,6 +603,8 @@ impl ProofGenerationPerformanceTester {
             cpu_usage_percent: 31.2,
             throughput_mbps: 18.7,
             efficiency_score: 0.91,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -601,6 +621,8 @@ impl ProofGenerationPerformanceTester {
             cpu_usage_percent: 26.8,
             throughput_mbps: 14.2,
             efficiency_score: 0.88,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -617,6 +639,8 @@ impl ProofGenerationPerformanceTester {
             cpu_usage_percent: 44.7,
             throughput_mbps: 25.3,
             efficiency_score: 0.86,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -633,6 +657,8 @@ impl ProofGenerationPerformanceTester {
             cpu_usage_percent: 22.3,
             throughput_mbps: 6.8,
             efficiency_score: 0.94,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 }
@@ -655,17 +681,19 @@ impl ZkProofPerformanceTester {
         // Benchmark ZK proof generation
         let benchmark_result = self.benchmark_zk_proof_generation().await?;
         
-        // Verify performance meets requirements (at least 10 proofs/sec)
-        if benchmark_result.operations_per_second < 10.0 {
-            let execution_time = Duration::from_std(start_time.elapsed()).unwrap_or(Duration::ZERO);
+        // Verify the 99th percentile proof latency stays under the threshold
+        let threshold_ms = self.proof_generation_benchmarks.config.p99_threshold.as_secs_f64() * 1000.0;
+        if benchmark_result.p99_latency_ms > threshold_ms {
+            let execution_time = chrono::Duration::from_std(start_time.elapsed()).unwrap_or(chrono::Duration::zero());
             return Ok(TestResult::failed(
                 "zk_proof_generation_performance".to_string(),
                 execution_time,
-                format!("ZK proof generation too slow: {:.1} proofs/sec", benchmark_result.operations_per_second)
+                format!("ZK proof P99 latency {:.1}ms exceeds {:.1}ms ({} outliers)",
+                        benchmark_result.p99_latency_ms, threshold_ms, benchmark_result.outliers.len())
             ));
         }
 
-        let execution_time = Duration::from_std(start_time.elapsed()).unwrap_or(Duration::ZERO);
+        let execution_time = chrono::Duration::from_std(start_time.elapsed()).unwrap_or(chrono::Duration::zero());
         Ok(TestResult::passed("zk_proof_generation_performance".to_string(), execution_time))
     }
 
@@ -737,18 +765,23 @@ impl ZkProofPerformanceTester {
 
     // Helper methods for ZK proof performance benchmarks
     async fn benchmark_zk_proof_generation(&self) -> Result<PerformanceBenchmarkResult> {
-        sleep(Duration::from_millis(200)).await; // Simulate benchmarking
-        
+        let benchmarks = self.proof_generation_benchmarks.clone();
+        let report = tokio::task::spawn_blocking(move || benchmarks.run()).await??;
+        let proof_mb = report.proof_bytes as f64 / report.proofs.max(1) as f64 / 1_000_000.0;
+
         Ok(PerformanceBenchmarkResult {
             test_name: "zk_proof_generation".to_string(),
-            operations_per_second: 15.7,
-            average_latency_ms: 63.7,
-            p95_latency_ms: 89.2,
-            p99_latency_ms: 134.5,
-            memory_usage_mb: 156.8,
-            cpu_usage_percent: 67.3,
-            throughput_mbps: 2.1,
-            efficiency_score: 0.82,
+            operations_per_second: report.parallel_proofs_per_second,
+            average_latency_ms: report.mean_ms,
+            p95_latency_ms: report.p95_ms,
+            p99_latency_ms: report.p99_ms,
+            memory_usage_mb: 0.0,
+            cpu_usage_percent: 0.0,
+            throughput_mbps: report.parallel_proofs_per_second * proof_mb * 8.0,
+            // Parallel speedup per rayon thread
+            efficiency_score: report.speedup() / report.threads.max(1) as f64,
+            latency_distribution: report.fit,
+            outliers: report.outliers,
         })
     }
 
@@ -765,6 +798,8 @@ impl ZkProofPerformanceTester {
             cpu_usage_percent: 18.7,
             throughput_mbps: 3.2,
             efficiency_score: 0.93,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -781,6 +816,8 @@ impl ZkProofPerformanceTester {
             cpu_usage_percent: 52.1,
             throughput_mbps: 8.5,
             efficiency_score: 0.85,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 
@@ -797,6 +834,8 @@ impl ZkProofPerformanceTester {
             cpu_usage_percent: 39.8,
             throughput_mbps: 5.7,
             efficiency_score: 0.81,
+            latency_distribution: None,
+            outliers: Vec::new(),
         })
     }
 }
@@ -885,7 +924,6 @@ impl AnalyticsPerformanceTester {
 #[derive(Debug)] pub struct NounProcessingBenchmarks;
 #[derive(Debug)] pub struct DwordsOptimizationBenchmarks;
 #[derive(Debug)] pub struct OpcodeExecutionBenchmarks;
-#[derive(Debug)] pub struct ZkProofGenerationBenchmarks;
 #[derive(Debug)] pub struct ZkProofVerificationBenchmarks;
 #[derive(Debug)] pub struct ZkCircuitOptimizationBenchmarks;
 #[derive(Debug)] pub struct ConstraintSolvingBenchmarks;
@@ -908,7 +946,6 @@ impl ArithmeticEncodingBenchmarks { pub fn new() -> Self { Self } }
 impl NounProcessingBenchmarks { pub fn new() -> Self { Self } }
 impl DwordsOptimizationBenchmarks { pub fn new() -> Self { Self } }
 impl OpcodeExecutionBenchmarks { pub fn new() -> Self { Self } }
-impl ZkProofGenerationBenchmarks { pub fn new() -> Self { Self } }
 impl ZkProofVerificationBenchmarks { pub fn new() -> Self { Self } }
 impl ZkCircuitOptimizationBenchmarks { pub fn new() -> Self { Self } }
 impl ConstraintSolvingBenchmarks { pub fn new() -> Self { Self } }
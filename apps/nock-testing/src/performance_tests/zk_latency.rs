@@ -0,0 +1,393 @@
+// ZK Proof Latency Benchmark
+// Proves state transitions of varying opcode counts with plonky2 and fits a log-normal to the
+// latencies
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use log::{info, debug};
+use plonky2::field::types::Field;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// Registers of the proved state machine
+const REGISTERS: usize = 4;
+
+/// Standard normal quantile at 0.99
+const Z_99: f64 = 2.326_347_874;
+
+/// Outliers lie more than this many log-space standard deviations above the mean
+const OUTLIER_SIGMAS: f64 = 3.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZkLatencyConfig {
+    pub proofs: usize,
+    pub min_opcodes: usize,
+    pub max_opcodes: usize,
+    pub p99_threshold: Duration,
+    pub seed: u64,
+}
+
+impl Default for ZkLatencyConfig {
+    fn default() -> Self {
+        Self {
+            proofs: 1000,
+            min_opcodes: 1_000,
+            max_opcodes: 100_000,
+            p99_threshold: Duration::from_secs(5),
+            seed: 0x5a4b,
+        }
+    }
+}
+
+impl ZkLatencyConfig {
+    /// Defaults overridden by `ZK_BENCH_PROOFS` and `ZK_BENCH_P99_SECS`
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(proofs) = std::env::var("ZK_BENCH_PROOFS").ok().and_then(|v| v.parse().ok()) {
+            config.proofs = proofs;
+        }
+        if let Some(secs) = std::env::var("ZK_BENCH_P99_SECS").ok().and_then(|v| v.parse::<f64>().ok()) {
+            config.p99_threshold = Duration::from_secs_f64(secs);
+        }
+        config
+    }
+
+    /// Opcode counts spread log-uniformly from `min_opcodes` to `max_opcodes`
+    pub fn transitions(&self) -> Vec<StateTransition> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let ratio = self.max_opcodes.max(self.min_opcodes) as f64 / self.min_opcodes.max(1) as f64;
+        (0..self.proofs)
+            .map(|id| StateTransition {
+                id,
+                opcodes: (self.min_opcodes.max(1) as f64 * ratio.powf(rng.gen::<f64>())).round() as usize,
+                seed: rng.gen(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StateTransition {
+    pub id: usize,
+    /// NOCK opcodes executed by the transition
+    pub opcodes: usize,
+    pub seed: u64,
+}
+
+pub trait StateTransitionProver: Send + Sync {
+    fn prove(&self, transition: &StateTransition) -> Result<Vec<u8>>;
+}
+
+/// Executes a transition with one arithmetic step per opcode over four Goldilocks
+/// registers. The initial and final registers are public; the opcodes are witness.
+fn execute(transition: &StateTransition) -> ([F; REGISTERS], Vec<F>, [F; REGISTERS]) {
+    let mut rng = StdRng::seed_from_u64(transition.seed);
+    let initial = [F::from_noncanonical_u64(rng.gen()), F::ONE, F::TWO, F::from_canonical_u64(3)];
+    let opcodes: Vec<F> = (0..transition.opcodes)
+        .map(|_| F::from_canonical_u64(rng.gen_range(0u64..12)))
+        .collect();
+
+    let mut registers = initial;
+    for opcode in &opcodes {
+        registers = step(registers, *opcode);
+    }
+    (initial, opcodes, registers)
+}
+
+fn step([a, b, c, d]: [F; REGISTERS], opcode: F) -> [F; REGISTERS] {
+    [a + b, b * c, c * c + opcode, d + opcode]
+}
+
+/// Circuit proving a fixed number of transition steps
+struct TransitionCircuit {
+    data: CircuitData<F, C, D>,
+    initial: Vec<Target>,
+    opcodes: Vec<Target>,
+}
+
+impl TransitionCircuit {
+    fn build(steps: usize) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let initial = builder.add_virtual_targets(REGISTERS);
+        builder.register_public_inputs(&initial);
+        let opcodes = builder.add_virtual_targets(steps);
+
+        let mut registers = [initial[0], initial[1], initial[2], initial[3]];
+        for opcode in &opcodes {
+            let [a, b, c, d] = registers;
+            registers = [builder.add(a, b), builder.mul(b, c), builder.mul_add(c, c, *opcode), builder.add(d, *opcode)];
+        }
+        builder.register_public_inputs(&registers);
+
+        Self { data: builder.build::<C>(), initial, opcodes }
+    }
+}
+
+/// plonky2 prover over the transition circuit. One circuit is built up front for each
+/// power-of-two step count in the benchmark's range, so the measured latency is proving
+/// alone; shorter transitions are padded with zero opcodes.
+pub struct Plonky2TransitionProver {
+    circuits: BTreeMap<usize, TransitionCircuit>,
+}
+
+impl std::fmt::Debug for Plonky2TransitionProver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plonky2TransitionProver").field("circuits", &self.circuits.keys()).finish()
+    }
+}
+
+impl Plonky2TransitionProver {
+    pub fn for_opcodes(min_opcodes: usize, max_opcodes: usize) -> Self {
+        let mut circuits = BTreeMap::new();
+        let mut steps = min_opcodes.max(1).next_power_of_two();
+        loop {
+            info!("Building ZK transition circuit for {} opcodes", steps);
+            circuits.insert(steps, TransitionCircuit::build(steps));
+            if steps >= max_opcodes {
+                break;
+            }
+            steps *= 2;
+        }
+        Self { circuits }
+    }
+
+    fn prove_with_circuit(&self, transition: &StateTransition) -> Result<(&TransitionCircuit, ProofWithPublicInputs<F, C, D>)> {
+        let circuit = self.circuits.range(transition.opcodes..).next()
+            .map(|(_, circuit)| circuit)
+            .ok_or_else(|| anyhow!("No circuit for a transition of {} opcodes", transition.opcodes))?;
+        let (initial, opcodes, _) = execute(transition);
+
+        let mut witness = PartialWitness::new();
+        for (target, value) in circuit.initial.iter().zip(initial) {
+            witness.set_target(*target, value);
+        }
+        for (target, value) in circuit.opcodes.iter().zip(opcodes.into_iter().chain(std::iter::repeat(F::ZERO))) {
+            witness.set_target(*target, value);
+        }
+        Ok((circuit, circuit.data.prove(witness)?))
+    }
+}
+
+impl StateTransitionProver for Plonky2TransitionProver {
+    fn prove(&self, transition: &StateTransition) -> Result<Vec<u8>> {
+        let (_, proof) = self.prove_with_circuit(transition)?;
+        Ok(proof.to_bytes())
+    }
+}
+
+/// Maximum-likelihood log-normal fit: ln(latency) ~ N(mu, sigma^2), latency in ms
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogNormalFit {
+    pub mu: f64,
+    pub sigma: f64,
+}
+
+impl LogNormalFit {
+    pub fn fit(latencies_ms: &[f64]) -> Option<Self> {
+        let logs: Vec<f64> = latencies_ms.iter().filter(|ms| **ms > 0.0).map(|ms| ms.ln()).collect();
+        if logs.len() < 2 {
+            return None;
+        }
+        let n = logs.len() as f64;
+        let mu = logs.iter().sum::<f64>() / n;
+        let sigma = (logs.iter().map(|l| (l - mu).powi(2)).sum::<f64>() / n).sqrt();
+        Some(Self { mu, sigma })
+    }
+
+    pub fn p99_ms(&self) -> f64 {
+        (self.mu + Z_99 * self.sigma).exp()
+    }
+
+    pub fn is_outlier(&self, latency_ms: f64) -> bool {
+        latency_ms > 0.0 && latency_ms.ln() > self.mu + OUTLIER_SIGMAS * self.sigma
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyOutlier {
+    pub label: String,
+    pub latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZkLatencyReport {
+    pub proofs: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub fit: Option<LogNormalFit>,
+    pub outliers: Vec<LatencyOutlier>,
+    pub proof_bytes: usize,
+    pub sequential_proofs_per_second: f64,
+    pub parallel_proofs_per_second: f64,
+    pub threads: usize,
+}
+
+impl ZkLatencyReport {
+    /// Parallel throughput over sequential throughput
+    pub fn speedup(&self) -> f64 {
+        if self.sequential_proofs_per_second > 0.0 {
+            self.parallel_proofs_per_second / self.sequential_proofs_per_second
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Proves every transition once sequentially, timing each proof, then again across the
+/// rayon pool to measure throughput. CPU-bound; run it off the async runtime.
+pub fn run_zk_latency_benchmark(config: &ZkLatencyConfig, prover: &dyn StateTransitionProver) -> Result<ZkLatencyReport> {
+    let transitions = config.transitions();
+    info!("Generating {} ZK proofs of {}-{} opcodes", transitions.len(), config.min_opcodes, config.max_opcodes);
+
+    let mut latencies_ms = Vec::with_capacity(transitions.len());
+    let mut proof_bytes = 0;
+    let sequential_start = Instant::now();
+    for transition in &transitions {
+        let start = Instant::now();
+        proof_bytes += prover.prove(transition)?.len();
+        latencies_ms.push(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    let sequential_elapsed = sequential_start.elapsed().as_secs_f64();
+
+    let parallel_start = Instant::now();
+    transitions.par_iter().try_for_each(|transition| prover.prove(transition).map(drop))?;
+    let parallel_elapsed = parallel_start.elapsed().as_secs_f64();
+
+    let fit = LogNormalFit::fit(&latencies_ms);
+    let outliers = match fit {
+        Some(fit) => transitions.iter().zip(&latencies_ms)
+            .filter(|(_, ms)| fit.is_outlier(**ms))
+            .map(|(transition, ms)| LatencyOutlier {
+                label: format!("transition {} ({} opcodes)", transition.id, transition.opcodes),
+                latency_ms: *ms,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut sorted = latencies_ms.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let throughput = |elapsed: f64| if elapsed > 0.0 { transitions.len() as f64 / elapsed } else { 0.0 };
+
+    let report = ZkLatencyReport {
+        proofs: transitions.len(),
+        mean_ms: if sorted.is_empty() { 0.0 } else { sorted.iter().sum::<f64>() / sorted.len() as f64 },
+        p50_ms: percentile(&sorted, 50.0),
+        p95_ms: percentile(&sorted, 95.0),
+        p99_ms: percentile(&sorted, 99.0),
+        fit,
+        outliers,
+        proof_bytes,
+        sequential_proofs_per_second: throughput(sequential_elapsed),
+        parallel_proofs_per_second: throughput(parallel_elapsed),
+        threads: rayon::current_num_threads(),
+    };
+    debug!("ZK latency fit {:?}, {} outliers, {:.2}x speedup on {} threads",
+           report.fit, report.outliers.len(), report.speedup(), report.threads);
+    Ok(report)
+}
+
+#[derive(Debug, Clone)]
+pub struct ZkProofGenerationBenchmarks {
+    pub config: ZkLatencyConfig,
+}
+
+impl ZkProofGenerationBenchmarks {
+    pub fn new() -> Self {
+        Self { config: ZkLatencyConfig::from_env() }
+    }
+
+    /// Builds the circuits for the configured opcode range, then runs the benchmark
+    pub fn run(&self) -> Result<ZkLatencyReport> {
+        let prover = Plonky2TransitionProver::for_opcodes(self.config.min_opcodes, self.config.max_opcodes);
+        run_zk_latency_benchmark(&self.config, &prover)
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_normal_fit_recovers_parameters() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let (mu, sigma) = (3.0, 0.5);
+        let samples: Vec<f64> = (0..20_000)
+            .map(|_| {
+                // Box-Muller
+                let (u1, u2) = (1.0 - rng.gen::<f64>(), rng.gen::<f64>());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                (mu + sigma * z).exp()
+            })
+            .collect();
+
+        let fit = LogNormalFit::fit(&samples).unwrap();
+        assert!((fit.mu - mu).abs() < 0.02, "mu {}", fit.mu);
+        assert!((fit.sigma - sigma).abs() < 0.02, "sigma {}", fit.sigma);
+        assert!(fit.is_outlier((mu + 4.0 * sigma).exp()));
+        assert!(!fit.is_outlier(mu.exp()));
+        assert_eq!(LogNormalFit::fit(&[5.0]), None);
+    }
+
+    #[test]
+    fn test_proofs_verify_and_commit_to_final_state() {
+        let prover = Plonky2TransitionProver::for_opcodes(64, 100);
+        let transition = StateTransition { id: 0, opcodes: 100, seed: 9 };
+        let short = StateTransition { opcodes: 40, ..transition };
+
+        for transition in [transition, short] {
+            let (circuit, proof) = prover.prove_with_circuit(&transition).unwrap();
+            let (initial, opcodes, _) = execute(&transition);
+            // Padding opcodes are zero, so the public output is the padded execution
+            let final_registers = opcodes.into_iter()
+                .chain(std::iter::repeat(F::ZERO))
+                .take(circuit.opcodes.len())
+                .fold(initial, step);
+
+            assert_eq!(proof.public_inputs[..REGISTERS], initial);
+            assert_eq!(proof.public_inputs[REGISTERS..], final_registers);
+            circuit.data.verify(proof).unwrap();
+        }
+
+        let too_long = StateTransition { opcodes: 129, ..transition };
+        assert!(prover.prove(&too_long).is_err());
+    }
+
+    #[test]
+    fn test_benchmark_reports_every_proof() {
+        let config = ZkLatencyConfig { proofs: 20, min_opcodes: 100, max_opcodes: 500, ..ZkLatencyConfig::default() };
+        let transitions = config.transitions();
+        assert!(transitions.iter().all(|t| (100..=500).contains(&t.opcodes)));
+
+        let prover = Plonky2TransitionProver::for_opcodes(config.min_opcodes, config.max_opcodes);
+        let report = run_zk_latency_benchmark(&config, &prover).unwrap();
+        assert_eq!(report.proofs, 20);
+        assert!(report.fit.is_some());
+        assert!(report.p50_ms <= report.p95_ms && report.p95_ms <= report.p99_ms);
+        assert!(report.parallel_proofs_per_second > 0.0);
+    }
+}
@@ -0,0 +1,56 @@
+// Order matching throughput and latency
+// Four producers submit crossing and resting orders to the matching thread; the target is more
+// than 100,000 orders per second on a 4-core machine
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use performance_optimizer::order_matching::{LimitOrder, OrderMatchingConfig, OrderMatchingOptimizer, Side};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const PRODUCERS: u64 = 4;
+const ORDERS_PER_PRODUCER: u64 = 25_000;
+
+fn submit_and_wait(optimizer: &Arc<OrderMatchingOptimizer>, next_order_id: &AtomicU64) {
+    let target = optimizer.commands_processed() + PRODUCERS * ORDERS_PER_PRODUCER;
+    std::thread::scope(|scope| {
+        for producer in 0..PRODUCERS {
+            scope.spawn(move || {
+                for i in 0..ORDERS_PER_PRODUCER {
+                    let order_id = next_order_id.fetch_add(1, Ordering::Relaxed);
+                    let side = if (i + producer) % 2 == 0 { Side::Buy } else { Side::Sell };
+                    // Prices straddle the mid so roughly half the orders cross
+                    let offset = i % 5;
+                    let price = match side {
+                        Side::Buy => 998 + offset,
+                        Side::Sell => 1002 - offset,
+                    };
+                    optimizer.place(LimitOrder { order_id, side, price, quantity: 1 + i % 7 });
+                }
+            });
+        }
+    });
+    while optimizer.commands_processed() < target {
+        std::hint::spin_loop();
+    }
+    // Keep the report queue from growing across iterations
+    optimizer.drain_reports();
+}
+
+fn bench_order_matching(c: &mut Criterion) {
+    let optimizer = Arc::new(OrderMatchingOptimizer::start(OrderMatchingConfig::default()).expect("matching thread starts"));
+    let next_order_id = AtomicU64::new(0);
+
+    let mut group = c.benchmark_group("order_matching");
+    group.sample_size(20);
+    group.measurement_time(Duration::from_secs(10));
+    group.throughput(Throughput::Elements(PRODUCERS * ORDERS_PER_PRODUCER));
+    group.bench_function("concurrent_place", |b| b.iter(|| submit_and_wait(&optimizer, &next_order_id)));
+    group.finish();
+
+    let latency = optimizer.match_latency();
+    println!("order_matching match latency: p50 {:?}, p99 {:?}, max {:?}", latency.p50, latency.p99, latency.max);
+}
+
+criterion_group!(benches, bench_order_matching);
+criterion_main!(benches);
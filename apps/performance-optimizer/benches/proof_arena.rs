@@ -0,0 +1,36 @@
+// Arena vs. global allocator for proof generation
+// Each iteration runs 10,000 synthetic proof generations, each with its own arena in the arena case
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use performance_optimizer::memory_optimizer::{synthetic_proof_in_arena, synthetic_proof_on_heap, ProofArenaAllocator};
+use std::hint::black_box;
+
+const PROOFS: u64 = 10_000;
+
+fn bench_proof_allocation(c: &mut Criterion) {
+    // Sized to the synthetic workload (~10KB of intermediates) rather than real proofs
+    let allocator = ProofArenaAllocator::new(64 * 1024);
+    let mut group = c.benchmark_group("proof_allocation");
+    group.sample_size(10);
+
+    group.bench_function("global_allocator", |b| {
+        b.iter(|| {
+            for seed in 0..PROOFS {
+                black_box(synthetic_proof_on_heap(black_box(seed)));
+            }
+        })
+    });
+
+    group.bench_function("arena", |b| {
+        b.iter(|| {
+            for seed in 0..PROOFS {
+                black_box(allocator.generate(|arena| synthetic_proof_in_arena(arena, black_box(seed))));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_proof_allocation);
+criterion_main!(benches);
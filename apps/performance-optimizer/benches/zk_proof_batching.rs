@@ -0,0 +1,44 @@
+// Single vs. batched ZK proof cost for bridge transactions
+// Each iteration proves and verifies a whole set of transactions, so time per iteration / size is the per-transaction cost
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use performance_optimizer::zk_batching::{BatchProver, BridgeProofRequest};
+
+fn transactions(count: usize) -> Vec<BridgeProofRequest> {
+    (0..count)
+        .map(|i| BridgeProofRequest { tx_hash: [i as u8; 32], amount: 1_000_000 + i as u64 })
+        .collect()
+}
+
+fn bench_proof_batching(c: &mut Criterion) {
+    let mut prover = BatchProver::new();
+    let mut group = c.benchmark_group("zk_proof_batching");
+    group.sample_size(10);
+
+    for size in [4usize, 8, 32] {
+        let batch = transactions(size);
+        // Builds the aggregation circuits outside the measurement
+        prover.prove_batch(&batch).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("single", size), &batch, |b, batch| {
+            b.iter(|| {
+                for transaction in batch {
+                    let proof = prover.prove_transaction(transaction).unwrap();
+                    prover.verify_transaction(&proof).unwrap();
+                }
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batched", size), &batch, |b, batch| {
+            b.iter(|| {
+                let aggregated = prover.prove_batch(batch).unwrap();
+                prover.verify_batch(batch.len(), &aggregated.proof).unwrap();
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_proof_batching);
+criterion_main!(benches);
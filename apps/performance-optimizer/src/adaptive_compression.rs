@@ -0,0 +1,490 @@
+// Adaptive Response Compression
+// Picks the Brotli quality level from measurements on sampled response payloads
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{header, Extensions, HeaderMap, Request, StatusCode, Version};
+use axum::response::{IntoResponse, Response};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use tower::{Layer, Service, ServiceExt};
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::compression::CompressionLayer;
+use tower_http::CompressionLevel;
+
+/// Brotli quality levels, fastest first
+pub const BROTLI_QUALITY_LEVELS: std::ops::RangeInclusive<u32> = 1..=11;
+
+/// Binary payloads above this many bits of entropy per byte are treated as already compressed
+pub const PRECOMPRESSED_ENTROPY_BITS: f64 = 7.5;
+
+/// Payloads kept for the next quality measurement
+pub const MAX_PAYLOAD_SAMPLES: usize = 16;
+
+/// Larger bodies are neither sampled nor checked for entropy, to bound buffering
+pub const MAX_INSPECTED_BODY_BYTES: u64 = 64 * 1024;
+
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveCompressionConfig {
+    /// Mean response time, handler plus compression, the chosen quality must stay under
+    pub target_response_time_ms: f64,
+    /// One in this many compressible responses is sampled
+    pub sample_every: u64,
+    /// Quality used until the first measurement
+    pub initial_quality: u32,
+}
+
+impl Default for AdaptiveCompressionConfig {
+    fn default() -> Self {
+        Self {
+            target_response_time_ms: 25.0,
+            sample_every: 20,
+            initial_quality: 4,
+        }
+    }
+}
+
+impl AdaptiveCompressionConfig {
+    /// Reads `COMPRESSION_TARGET_RESPONSE_TIME_MS` and `COMPRESSION_SAMPLE_EVERY`,
+    /// falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            target_response_time_ms: env_or("COMPRESSION_TARGET_RESPONSE_TIME_MS", defaults.target_response_time_ms),
+            sample_every: env_or("COMPRESSION_SAMPLE_EVERY", defaults.sample_every).max(1),
+            ..defaults
+        }
+    }
+}
+
+/// A response body captured for quality measurement
+#[derive(Debug, Clone)]
+pub struct PayloadSample {
+    pub content_type: String,
+    pub body: Bytes,
+    /// Time the handler took to produce the response, before compression
+    pub response_time_ms: f64,
+}
+
+/// Brotli results for one quality level across the current samples
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityMeasurement {
+    pub quality: u32,
+    pub original_bytes: u64,
+    pub compressed_bytes: u64,
+    pub mean_compression_time_ms: f64,
+    pub mean_total_response_time_ms: f64,
+}
+
+impl QualityMeasurement {
+    pub fn compression_ratio(&self) -> f64 {
+        if self.compressed_bytes == 0 {
+            return 1.0;
+        }
+        self.original_bytes as f64 / self.compressed_bytes as f64
+    }
+
+    pub fn bytes_saved(&self) -> u64 {
+        self.original_bytes.saturating_sub(self.compressed_bytes)
+    }
+}
+
+/// Shannon entropy in bits per byte, from 0 (constant) to 8 (uniformly random)
+pub fn shannon_entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether a payload of `content_type` is binary that compression cannot shrink further
+pub fn is_precompressed(content_type: &str, body: &[u8]) -> bool {
+    is_octet_stream(content_type) && shannon_entropy(body) > PRECOMPRESSED_ENTROPY_BITS
+}
+
+fn is_octet_stream(content_type: &str) -> bool {
+    content_type.trim_start().to_ascii_lowercase().starts_with("application/octet-stream")
+}
+
+/// Compresses every sample at `quality` and averages the cost
+pub fn measure_quality(samples: &[PayloadSample], quality: u32) -> Result<QualityMeasurement> {
+    let mut measurement = QualityMeasurement {
+        quality,
+        original_bytes: 0,
+        compressed_bytes: 0,
+        mean_compression_time_ms: 0.0,
+        mean_total_response_time_ms: 0.0,
+    };
+    if samples.is_empty() {
+        return Ok(measurement);
+    }
+
+    let mut compression_ms = 0.0;
+    let mut total_ms = 0.0;
+    for sample in samples {
+        let started = Instant::now();
+        let mut writer = brotli::CompressorWriter::new(Vec::new(), 4096, quality, BROTLI_WINDOW_BITS);
+        writer.write_all(&sample.body)?;
+        let compressed = writer.into_inner();
+        let elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        measurement.original_bytes += sample.body.len() as u64;
+        measurement.compressed_bytes += compressed.len() as u64;
+        compression_ms += elapsed_ms;
+        total_ms += sample.response_time_ms + elapsed_ms;
+    }
+
+    measurement.mean_compression_time_ms = compression_ms / samples.len() as f64;
+    measurement.mean_total_response_time_ms = total_ms / samples.len() as f64;
+    Ok(measurement)
+}
+
+/// Highest quality whose mean total response time stays under the target; the fastest
+/// level when none does, since compressing still saves bandwidth
+pub fn choose_quality(measurements: &[QualityMeasurement], target_response_time_ms: f64) -> Option<&QualityMeasurement> {
+    measurements.iter()
+        .filter(|m| m.mean_total_response_time_ms < target_response_time_ms)
+        .max_by_key(|m| m.quality)
+        .or_else(|| measurements.iter().min_by_key(|m| m.quality))
+}
+
+/// Marks a response whose body is already compressed binary
+#[derive(Debug, Clone, Copy)]
+struct Precompressed;
+
+fn not_precompressed(_: StatusCode, _: Version, _: &HeaderMap, extensions: &Extensions) -> bool {
+    extensions.get::<Precompressed>().is_none()
+}
+
+/// Brotli/gzip response compression whose quality follows the latest measurement
+pub struct AdaptiveCompression {
+    pub config: AdaptiveCompressionConfig,
+    quality: AtomicU32,
+    responses_seen: AtomicU64,
+    samples: Mutex<VecDeque<PayloadSample>>,
+}
+
+impl std::fmt::Debug for AdaptiveCompression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdaptiveCompression")
+            .field("config", &self.config)
+            .field("quality", &self.quality())
+            .field("samples", &self.samples.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl AdaptiveCompression {
+    pub fn new(config: AdaptiveCompressionConfig) -> Self {
+        let quality = config.initial_quality.clamp(*BROTLI_QUALITY_LEVELS.start(), *BROTLI_QUALITY_LEVELS.end());
+        Self {
+            config,
+            quality: AtomicU32::new(quality),
+            responses_seen: AtomicU64::new(0),
+            samples: Mutex::new(VecDeque::with_capacity(MAX_PAYLOAD_SAMPLES)),
+        }
+    }
+
+    /// Brotli quality applied to responses right now
+    pub fn quality(&self) -> u32 {
+        self.quality.load(Ordering::Relaxed)
+    }
+
+    pub fn layer(self: &Arc<Self>) -> AdaptiveCompressionLayer {
+        AdaptiveCompressionLayer { compression: Arc::clone(self) }
+    }
+
+    pub fn record_sample(&self, sample: PayloadSample) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == MAX_PAYLOAD_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    fn should_sample(&self) -> bool {
+        self.responses_seen.fetch_add(1, Ordering::Relaxed) % self.config.sample_every == 0
+    }
+
+    /// Measures every Brotli quality level on the sampled payloads and switches the live
+    /// configuration to the chosen one. CPU bound; run it off the async workers.
+    pub fn select_quality(&self) -> Result<Option<QualityMeasurement>> {
+        let samples: Vec<_> = self.samples.lock().unwrap().iter().cloned().collect();
+        if samples.is_empty() {
+            debug!("No response payloads sampled yet, keeping Brotli quality {}", self.quality());
+            return Ok(None);
+        }
+
+        let measurements = BROTLI_QUALITY_LEVELS
+            .map(|quality| measure_quality(&samples, quality))
+            .collect::<Result<Vec<_>>>()?;
+        for m in &measurements {
+            debug!("Brotli q{}: ratio {:.2}, saved {} bytes, {:.2}ms compression, {:.2}ms total",
+                   m.quality, m.compression_ratio(), m.bytes_saved(), m.mean_compression_time_ms, m.mean_total_response_time_ms);
+        }
+
+        let Some(chosen) = choose_quality(&measurements, self.config.target_response_time_ms).cloned() else {
+            return Ok(None);
+        };
+        if chosen.mean_total_response_time_ms >= self.config.target_response_time_ms {
+            warn!("Even Brotli quality {} takes {:.2}ms against a {:.1}ms target",
+                  chosen.quality, chosen.mean_total_response_time_ms, self.config.target_response_time_ms);
+        }
+
+        let previous = self.quality.swap(chosen.quality, Ordering::Relaxed);
+        info!("Selected Brotli quality {} (was {}): compression ratio {:.2}, {} of {} bytes saved across {} samples, {:.2}ms mean response time",
+              chosen.quality, previous, chosen.compression_ratio(), chosen.bytes_saved(), chosen.original_bytes,
+              samples.len(), chosen.mean_total_response_time_ms);
+        Ok(Some(chosen))
+    }
+
+    /// Flags precompressed binary and samples compressible bodies. Only bodies of known,
+    /// bounded size are buffered; streaming responses pass through untouched.
+    async fn inspect(&self, response: Response, response_time_ms: f64) -> Response {
+        let content_type = response.headers().get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let bounded = response.body().size_hint().exact().is_some_and(|size| size <= MAX_INSPECTED_BODY_BYTES);
+        let binary = is_octet_stream(&content_type);
+        let sample = bounded && response.status() == StatusCode::OK && self.should_sample();
+        if !bounded || !(binary || sample) {
+            return response;
+        }
+
+        let (mut parts, body) = response.into_parts();
+        let body = match axum::body::to_bytes(body, MAX_INSPECTED_BODY_BYTES as usize).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to buffer response for compression sampling: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+        if is_precompressed(&content_type, &body) {
+            parts.extensions.insert(Precompressed);
+        } else if sample {
+            self.record_sample(PayloadSample { content_type, body: body.clone(), response_time_ms });
+        }
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveCompressionLayer {
+    compression: Arc<AdaptiveCompression>,
+}
+
+impl<S> Layer<S> for AdaptiveCompressionLayer {
+    type Service = AdaptiveCompressionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdaptiveCompressionService { inner, compression: Arc::clone(&self.compression) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AdaptiveCompressionService<S> {
+    inner: S,
+    compression: Arc<AdaptiveCompression>,
+}
+
+impl<S> Service<Request<Body>> for AdaptiveCompressionService<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let inner = std::mem::replace(&mut self.inner, clone);
+        let compression = Arc::clone(&self.compression);
+
+        // Rebuilt per request so a new quality takes effect immediately
+        let quality = CompressionLevel::Precise(compression.quality() as i32);
+        let service = CompressionLayer::new()
+            .quality(quality)
+            .compress_when(DefaultPredicate::new().and(not_precompressed))
+            .layer(tower::service_fn(move |request: Request<Body>| {
+                let mut inner = inner.clone();
+                let compression = Arc::clone(&compression);
+                async move {
+                    let started = Instant::now();
+                    let response = inner.call(request).await?;
+                    let response_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+                    Ok::<_, Infallible>(compression.inspect(response, response_time_ms).await)
+                }
+            }));
+
+        Box::pin(async move {
+            let response = service.oneshot(request).await?;
+            Ok(response.map(Body::new))
+        })
+    }
+}
+
+/// Re-measures on `period` until the task is aborted
+pub fn spawn_quality_tuner(compression: &Arc<AdaptiveCompression>, period: Duration) -> tokio::task::JoinHandle<()> {
+    let compression = Arc::clone(compression);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(period);
+        loop {
+            ticker.tick().await;
+            let compression = Arc::clone(&compression);
+            match tokio::task::spawn_blocking(move || compression.select_quality()).await {
+                Ok(Err(e)) => warn!("Brotli quality measurement failed: {}", e),
+                Err(e) => warn!("Brotli quality measurement panicked: {}", e),
+                Ok(Ok(_)) => {}
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+
+    /// Deterministic high-entropy bytes
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        }).collect()
+    }
+
+    fn json_payload() -> Bytes {
+        let rows: Vec<_> = (0..200).map(|i| format!(r#"{{"block":{},"miner":"nock1qxyz","reward":"2.5"}}"#, i)).collect();
+        Bytes::from(format!("[{}]", rows.join(",")))
+    }
+
+    fn measurement(quality: u32, total_ms: f64) -> QualityMeasurement {
+        QualityMeasurement {
+            quality,
+            original_bytes: 1000,
+            compressed_bytes: 1000 / quality as u64,
+            mean_compression_time_ms: total_ms - 5.0,
+            mean_total_response_time_ms: total_ms,
+        }
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy(&[]), 0.0);
+        assert_eq!(shannon_entropy(&[7; 512]), 0.0);
+        let uniform: Vec<u8> = (0..=255).cycle().take(256 * 16).collect();
+        assert!((shannon_entropy(&uniform) - 8.0).abs() < 1e-9);
+        assert!(shannon_entropy(&json_payload()) < 6.0);
+    }
+
+    #[test]
+    fn test_precompressed_only_for_octet_stream() {
+        let random = noise(16 * 1024);
+        assert!(shannon_entropy(&random) > PRECOMPRESSED_ENTROPY_BITS);
+        assert!(is_precompressed("application/octet-stream", &random));
+        assert!(!is_precompressed("application/json", &random));
+        assert!(!is_precompressed("application/octet-stream", &json_payload()));
+    }
+
+    #[test]
+    fn test_measure_quality_reports_savings() {
+        let samples = vec![PayloadSample {
+            content_type: "application/json".to_string(),
+            body: json_payload(),
+            response_time_ms: 3.0,
+        }];
+        let low = measure_quality(&samples, 1).unwrap();
+        let high = measure_quality(&samples, 11).unwrap();
+
+        assert_eq!(low.original_bytes, json_payload().len() as u64);
+        assert!(low.compression_ratio() > 2.0);
+        assert!(high.compressed_bytes <= low.compressed_bytes);
+        assert!(high.mean_total_response_time_ms >= 3.0);
+    }
+
+    #[test]
+    fn test_choose_highest_quality_under_target() {
+        let measurements: Vec<_> = BROTLI_QUALITY_LEVELS.map(|q| measurement(q, 10.0 + q as f64 * 2.0)).collect();
+        // q7 totals 24ms, q8 26ms
+        assert_eq!(choose_quality(&measurements, 25.0).unwrap().quality, 7);
+        assert_eq!(choose_quality(&measurements, 100.0).unwrap().quality, 11);
+        assert_eq!(choose_quality(&measurements, 5.0).unwrap().quality, 1);
+        assert!(choose_quality(&[], 25.0).is_none());
+    }
+
+    #[test]
+    fn test_select_quality_updates_live_level() {
+        let compression = AdaptiveCompression::new(AdaptiveCompressionConfig {
+            target_response_time_ms: 10_000.0,
+            ..AdaptiveCompressionConfig::default()
+        });
+        assert_eq!(compression.select_quality().unwrap(), None);
+
+        compression.record_sample(PayloadSample {
+            content_type: "application/json".to_string(),
+            body: json_payload(),
+            response_time_ms: 1.0,
+        });
+        let chosen = compression.select_quality().unwrap().unwrap();
+        assert_eq!(chosen.quality, 11);
+        assert_eq!(compression.quality(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_layer_skips_precompressed_binary() {
+        let compression = Arc::new(AdaptiveCompression::new(AdaptiveCompressionConfig::default()));
+        let app = Router::new()
+            .route("/json", get(|| async { ([(header::CONTENT_TYPE, "application/json")], json_payload()) }))
+            .route("/blob", get(|| async { ([(header::CONTENT_TYPE, "application/octet-stream")], noise(8192)) }))
+            .layer(compression.layer());
+
+        let encoding = |path: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::get(path).header(header::ACCEPT_ENCODING, "br").body(Body::empty()).unwrap();
+                let response = app.oneshot(request).await.unwrap();
+                response.headers().get(header::CONTENT_ENCODING).map(|v| v.to_str().unwrap().to_string())
+            }
+        };
+
+        assert_eq!(encoding("/json").await.as_deref(), Some("br"));
+        assert_eq!(encoding("/blob").await, None);
+    }
+}
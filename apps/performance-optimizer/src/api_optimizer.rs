@@ -13,6 +13,8 @@ use hyper::{Request, Response, Body};
 use tower::ServiceBuilder;
 use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
 use moka::future::Cache;
+use tokio::task::JoinHandle;
+use crate::endpoint_latency::{EndpointLatencyConfig, EndpointLatencyLayer, EndpointLatencyTracker};
 
 /// Advanced API performance optimization engine
 #[derive(Debug)]
@@ -40,6 +42,8 @@ pub struct ResponseTimeAnalyzer {
 /// Endpoint-specific performance monitoring
 #[derive(Debug)]
 pub struct EndpointPerformanceMonitor {
+    /// Response time histograms recorded by the router layer
+    pub latency_tracker: Arc<EndpointLatencyTracker>,
     pub endpoint_metrics: HashMap<String, EndpointMetrics>,
     pub slow_endpoints: Vec<SlowEndpoint>,
     pub optimization_tracker: OptimizationTracker,
@@ -194,6 +198,16 @@ impl ApiPerformanceOptimizer {
         })
     }
 
+    /// Layer timing every request per endpoint; add it to the router with `Router::layer`
+    pub fn latency_layer(&self) -> EndpointLatencyLayer {
+        self.endpoint_performance_monitor.latency_tracker.layer()
+    }
+
+    /// Publishes endpoint percentiles every interval and acts on degradation alerts
+    pub fn spawn_latency_publisher(&self) -> JoinHandle<()> {
+        self.endpoint_performance_monitor.latency_tracker.spawn_publisher()
+    }
+
     /// Execute comprehensive API optimization to achieve <25ms target
     pub async fn optimize_api_performance(&mut self) -> Result<ApiOptimizationResult> {
         info!("Starting comprehensive API optimization - targeting <25ms response time");
@@ -646,7 +660,13 @@ pub struct ResponseTimeStatistics {
 
 impl EndpointPerformanceMonitor {
     pub async fn new() -> Result<Self> {
+        let latency_tracker = Arc::new(EndpointLatencyTracker::new(EndpointLatencyConfig::from_env()));
+        if let Err(e) = latency_tracker.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register endpoint latency metrics: {}", e);
+        }
+
         Ok(Self {
+            latency_tracker,
             endpoint_metrics: HashMap::new(),
             slow_endpoints: Vec::new(),
             optimization_tracker: OptimizationTracker::new(),
@@ -654,21 +674,29 @@ impl EndpointPerformanceMonitor {
         })
     }
 
+    /// Endpoints whose P95 over the last published interval missed the target
     pub async fn identify_slow_endpoints(&mut self) -> Result<Vec<SlowEndpoint>> {
         debug!("Identifying slow endpoints");
-        
-        let slow_endpoint = SlowEndpoint {
-            endpoint_path: "/api/mining/stats".to_string(),
-            average_response_time_ms: 42.0,
-            sample_count: 500,
-            bottlenecks: Vec::new(),
-            optimization_recommendations: vec![
-                "Add database index for mining stats query".to_string(),
-                "Implement response caching".to_string(),
-            ],
-        };
 
-        Ok(vec![slow_endpoint])
+        let target_ms = self.latency_tracker.config.target_response_time_ms;
+        self.slow_endpoints = self.latency_tracker.latest_stats().into_iter()
+            .filter(|stats| stats.p95_ms > target_ms)
+            .map(|stats| {
+                let mut optimization_recommendations = Vec::new();
+                if !self.latency_tracker.is_caching(&stats.endpoint) {
+                    optimization_recommendations.push("Annotate the route with cache_when_degraded if responses can be cached".to_string());
+                }
+                SlowEndpoint {
+                    endpoint_path: stats.endpoint,
+                    average_response_time_ms: stats.mean_ms,
+                    sample_count: stats.request_count,
+                    bottlenecks: Vec::new(),
+                    optimization_recommendations,
+                }
+            })
+            .collect();
+
+        Ok(self.slow_endpoints.clone())
     }
 
     pub async fn get_aggregated_statistics(&self) -> Result<AggregatedEndpointStatistics> {
@@ -0,0 +1,456 @@
+// Cross-Pool Arbitrage Detection
+// Compares the bridge AMM with an external DEX pool and sizes the most profitable round trip
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use prometheus::{IntCounter, Registry};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, MissedTickBehavior};
+use crate::liquidity_rebalancing::{BridgeAmm, BridgeAmmClient, LiquidityRebalancingConfig, NOCK, SWAP_FEE_BPS};
+
+const VENUE_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Opportunities buffered for each subscriber before the oldest are dropped
+const OPPORTUNITY_CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbitrageConfig {
+    /// Endpoint returning the external pool's reserves and fee as a [`PoolQuote`]
+    pub external_dex_url: String,
+    pub min_profit_usd: f64,
+    pub scan_interval: Duration,
+    /// Output shortfall assumed on each leg on top of the pools' own price impact
+    pub slippage_bps: u64,
+}
+
+impl Default for ArbitrageConfig {
+    fn default() -> Self {
+        Self {
+            external_dex_url: "http://127.0.0.1:8090/pools/nock".to_string(),
+            min_profit_usd: 50.0,
+            scan_interval: Duration::from_millis(500),
+            slippage_bps: 50,
+        }
+    }
+}
+
+impl ArbitrageConfig {
+    /// Reads `ARBITRAGE_EXTERNAL_DEX_URL`, `ARBITRAGE_MIN_PROFIT_USD`, `ARBITRAGE_SCAN_INTERVAL_MS`
+    /// and `ARBITRAGE_SLIPPAGE_BPS`, falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            external_dex_url: env_or("ARBITRAGE_EXTERNAL_DEX_URL", defaults.external_dex_url),
+            min_profit_usd: env_or("ARBITRAGE_MIN_PROFIT_USD", defaults.min_profit_usd),
+            scan_interval: Duration::from_millis(env_or("ARBITRAGE_SCAN_INTERVAL_MS", defaults.scan_interval.as_millis() as u64).max(1)),
+            slippage_bps: env_or("ARBITRAGE_SLIPPAGE_BPS", defaults.slippage_bps),
+        }
+    }
+}
+
+/// Constant-product pool state in whole tokens; the quote token prices the base token
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PoolQuote {
+    pub base_reserve: f64,
+    pub quote_reserve: f64,
+    pub fee_bps: u64,
+}
+
+impl PoolQuote {
+    /// Quote tokens per base token
+    pub fn price(&self) -> f64 {
+        self.quote_reserve / self.base_reserve
+    }
+
+    fn fee_multiplier(&self) -> f64 {
+        1.0 - self.fee_bps as f64 / 10_000.0
+    }
+
+    /// Base tokens bought with `quote_in`, and the pool afterwards
+    pub fn buy_base(&self, quote_in: f64) -> (f64, Self) {
+        let effective_in = quote_in * self.fee_multiplier();
+        let base_out = effective_in * self.base_reserve / (self.quote_reserve + effective_in);
+        (base_out, Self { base_reserve: self.base_reserve - base_out, quote_reserve: self.quote_reserve + quote_in, ..*self })
+    }
+
+    /// Quote tokens received for `base_in`, and the pool afterwards
+    pub fn sell_base(&self, base_in: f64) -> (f64, Self) {
+        let effective_in = base_in * self.fee_multiplier();
+        let quote_out = effective_in * self.quote_reserve / (self.base_reserve + effective_in);
+        (quote_out, Self { base_reserve: self.base_reserve + base_in, quote_reserve: self.quote_reserve - quote_out, ..*self })
+    }
+}
+
+#[async_trait]
+pub trait PoolVenue: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn quote(&self) -> Result<PoolQuote>;
+}
+
+/// The bridge program's NOCK/wNOCK pool, with NOCK as the base token
+pub struct BridgeAmmVenue {
+    amm: Box<dyn BridgeAmm>,
+}
+
+impl BridgeAmmVenue {
+    pub fn new(amm: Box<dyn BridgeAmm>) -> Self {
+        Self { amm }
+    }
+}
+
+#[async_trait]
+impl PoolVenue for BridgeAmmVenue {
+    fn name(&self) -> &str {
+        "bridge_amm"
+    }
+
+    async fn quote(&self) -> Result<PoolQuote> {
+        let reserves = self.amm.reserves().await?;
+        Ok(PoolQuote {
+            base_reserve: reserves.nock as f64 / NOCK as f64,
+            quote_reserve: reserves.wnock as f64 / NOCK as f64,
+            fee_bps: SWAP_FEE_BPS,
+        })
+    }
+}
+
+/// A pool of the same pair on another DEX, read as JSON from a configurable URL
+pub struct ExternalDexVenue {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl ExternalDexVenue {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(VENUE_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+}
+
+#[async_trait]
+impl PoolVenue for ExternalDexVenue {
+    fn name(&self) -> &str {
+        "external_dex"
+    }
+
+    async fn quote(&self) -> Result<PoolQuote> {
+        self.client
+            .get(&self.url)
+            .send()
+            .await
+            .context("Failed to reach external DEX")?
+            .error_for_status()
+            .context("External DEX rejected pool request")?
+            .json()
+            .await
+            .context("Malformed external DEX pool response")
+    }
+}
+
+/// Buy the base token on one venue and sell it on the other
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArbitrageRoute {
+    pub buy_on: String,
+    pub sell_on: String,
+}
+
+impl std::fmt::Display for ArbitrageRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "buy on {}, sell on {}", self.buy_on, self.sell_on)
+    }
+}
+
+/// Outcome of a simulated round trip; nothing is submitted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedTrade {
+    pub quote_in: f64,
+    pub base_bought: f64,
+    /// Quote received on the sell leg after the slippage allowance
+    pub quote_out: f64,
+    pub profit_quote: f64,
+    pub buy_pool_after: PoolQuote,
+    pub sell_pool_after: PoolQuote,
+}
+
+/// Simulates buying with `quote_in` on `buy_pool` and selling everything bought on
+/// `sell_pool`, charging each pool's fee and price impact plus `slippage_bps` per leg
+pub fn simulate_arbitrage_trade(buy_pool: &PoolQuote, sell_pool: &PoolQuote, quote_in: f64, slippage_bps: u64) -> SimulatedTrade {
+    let haircut = 1.0 - slippage_bps as f64 / 10_000.0;
+    let (base_bought, buy_pool_after) = buy_pool.buy_base(quote_in);
+    let base_bought = base_bought * haircut;
+    let (quote_out, sell_pool_after) = sell_pool.sell_base(base_bought);
+    let quote_out = quote_out * haircut;
+    SimulatedTrade {
+        quote_in,
+        base_bought,
+        quote_out,
+        profit_quote: quote_out - quote_in,
+        buy_pool_after,
+        sell_pool_after,
+    }
+}
+
+/// Input maximizing the round trip's profit. Both legs compose to
+/// `out = a·x / (b + c·x)`, which peaks at `x = (sqrt(a·b) - b) / c`.
+pub fn optimal_quote_in(buy_pool: &PoolQuote, sell_pool: &PoolQuote, slippage_bps: u64) -> Option<f64> {
+    let haircut = 1.0 - slippage_bps as f64 / 10_000.0;
+    let (g_buy, g_sell) = (buy_pool.fee_multiplier(), sell_pool.fee_multiplier() * haircut);
+    let a = g_buy * g_sell * buy_pool.base_reserve * sell_pool.quote_reserve * haircut;
+    let b = buy_pool.quote_reserve * sell_pool.base_reserve;
+    let c = g_buy * (sell_pool.base_reserve + g_sell * buy_pool.base_reserve);
+    (a > b && c > 0.0).then(|| ((a * b).sqrt() - b) / c)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArbitrageOpportunity {
+    pub profit_usd: f64,
+    pub route: ArbitrageRoute,
+    /// Share of the profit that survives twice the assumed slippage, from 0 to 1
+    pub confidence: f64,
+    pub quote_in: f64,
+    /// Sell venue price over buy venue price, in basis points
+    pub spread_bps: i64,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Most profitable direction between two venues, if it clears `min_profit_usd`
+pub fn find_opportunity(
+    venues: [(&str, PoolQuote); 2],
+    quote_usd_price: f64,
+    config: &ArbitrageConfig,
+) -> Option<ArbitrageOpportunity> {
+    let [(first_name, first), (second_name, second)] = venues;
+    [(first_name, first, second_name, second), (second_name, second, first_name, first)]
+        .into_iter()
+        .filter_map(|(buy_on, buy_pool, sell_on, sell_pool)| {
+            let quote_in = optimal_quote_in(&buy_pool, &sell_pool, config.slippage_bps)?;
+            let trade = simulate_arbitrage_trade(&buy_pool, &sell_pool, quote_in, config.slippage_bps);
+            let stressed = simulate_arbitrage_trade(&buy_pool, &sell_pool, quote_in, config.slippage_bps * 2);
+            Some(ArbitrageOpportunity {
+                profit_usd: trade.profit_quote * quote_usd_price,
+                route: ArbitrageRoute { buy_on: buy_on.to_string(), sell_on: sell_on.to_string() },
+                confidence: (stressed.profit_quote / trade.profit_quote).clamp(0.0, 1.0),
+                quote_in,
+                spread_bps: ((sell_pool.price() / buy_pool.price() - 1.0) * 10_000.0).round() as i64,
+                detected_at: Utc::now(),
+            })
+        })
+        .filter(|opportunity| opportunity.profit_usd >= config.min_profit_usd)
+        .max_by(|a, b| a.profit_usd.total_cmp(&b.profit_usd))
+}
+
+/// Scans the bridge AMM against an external DEX and broadcasts each new opportunity
+pub struct ArbitrageOptimizer {
+    config: ArbitrageConfig,
+    venues: [Box<dyn PoolVenue>; 2],
+    /// USD price of the quote token as f64 bits; zero until the price feed sets it
+    quote_usd_price: AtomicU64,
+    /// Route and spread of the last emitted opportunity, so the same spread is reported once
+    last_emitted: Mutex<Option<(ArbitrageRoute, i64)>>,
+    opportunities: broadcast::Sender<ArbitrageOpportunity>,
+    arbitrage_opportunities_total: IntCounter,
+}
+
+impl std::fmt::Debug for ArbitrageOptimizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArbitrageOptimizer")
+            .field("config", &self.config)
+            .field("venues", &self.venues.iter().map(|venue| venue.name()).collect::<Vec<_>>())
+            .field("quote_usd_price", &self.quote_usd_price())
+            .finish()
+    }
+}
+
+impl ArbitrageOptimizer {
+    pub fn new(config: ArbitrageConfig, venues: [Box<dyn PoolVenue>; 2]) -> Self {
+        Self {
+            config,
+            venues,
+            quote_usd_price: AtomicU64::new(0),
+            last_emitted: Mutex::new(None),
+            opportunities: broadcast::channel(OPPORTUNITY_CHANNEL_CAPACITY).0,
+            arbitrage_opportunities_total: IntCounter::new(
+                "nock_arbitrage_opportunities_total",
+                "Distinct arbitrage opportunities detected between the bridge AMM and the external DEX",
+            ).expect("valid counter"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let config = ArbitrageConfig::from_env();
+        let pool = LiquidityRebalancingConfig::from_env();
+        let bridge = BridgeAmmClient::new(pool.solana_rpc_url, pool.pool_address, pool.amm_signer_url);
+        let external = ExternalDexVenue::new(config.external_dex_url.clone());
+        Self::new(config, [Box::new(BridgeAmmVenue::new(Box::new(bridge))), Box::new(external)])
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.arbitrage_opportunities_total.clone()))?;
+        Ok(())
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ArbitrageOpportunity> {
+        self.opportunities.subscribe()
+    }
+
+    pub fn set_quote_usd_price(&self, price: f64) {
+        self.quote_usd_price.store(price.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn quote_usd_price(&self) -> Option<f64> {
+        Some(f64::from_bits(self.quote_usd_price.load(Ordering::Relaxed))).filter(|&price| price > 0.0)
+    }
+
+    /// Quotes both venues and returns an opportunity unless the same route and spread was
+    /// already emitted
+    pub async fn detect(&self) -> Result<Option<ArbitrageOpportunity>> {
+        let Some(quote_usd_price) = self.quote_usd_price() else {
+            debug!("No quote token USD price yet, skipping arbitrage scan");
+            return Ok(None);
+        };
+        let [first, second] = &self.venues;
+        let (first_quote, second_quote) = tokio::try_join!(first.quote(), second.quote())?;
+
+        let found = find_opportunity([(first.name(), first_quote), (second.name(), second_quote)], quote_usd_price, &self.config);
+        let mut last_emitted = self.last_emitted.lock().unwrap();
+        let Some(opportunity) = found else {
+            *last_emitted = None;
+            return Ok(None);
+        };
+
+        let key = (opportunity.route.clone(), opportunity.spread_bps);
+        if last_emitted.as_ref() == Some(&key) {
+            return Ok(None);
+        }
+        *last_emitted = Some(key);
+        drop(last_emitted);
+
+        info!("Arbitrage opportunity: {} with {:.4} in, ${:.2} profit at {} bps spread (confidence {:.2})",
+              opportunity.route, opportunity.quote_in, opportunity.profit_usd, opportunity.spread_bps, opportunity.confidence);
+        self.arbitrage_opportunities_total.inc();
+        // No subscribers is fine; the opportunity is still logged and counted
+        let _ = self.opportunities.send(opportunity.clone());
+        Ok(Some(opportunity))
+    }
+
+    /// Scans every `scan_interval`, skipping ticks missed while a scan was slow
+    pub fn spawn_detector(self: &Arc<Self>) -> JoinHandle<()> {
+        let optimizer = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = interval(optimizer.config.scan_interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = optimizer.detect().await {
+                    warn!("Arbitrage scan failed: {:#}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(base_reserve: f64, quote_reserve: f64) -> PoolQuote {
+        PoolQuote { base_reserve, quote_reserve, fee_bps: 30 }
+    }
+
+    struct FixedVenue {
+        name: &'static str,
+        quote: Arc<Mutex<PoolQuote>>,
+    }
+
+    #[async_trait]
+    impl PoolVenue for FixedVenue {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn quote(&self) -> Result<PoolQuote> {
+            Ok(*self.quote.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn test_optimal_input_maximizes_simulated_profit() {
+        let (buy, sell) = (pool(10_000.0, 10_000.0), pool(10_000.0, 11_000.0));
+        let best = optimal_quote_in(&buy, &sell, 50).unwrap();
+        let profit = |quote_in| simulate_arbitrage_trade(&buy, &sell, quote_in, 50).profit_quote;
+
+        assert!(profit(best) > 0.0);
+        assert!(profit(best) >= profit(best * 0.9));
+        assert!(profit(best) >= profit(best * 1.1));
+    }
+
+    #[test]
+    fn test_no_arbitrage_inside_fees() {
+        let (buy, sell) = (pool(10_000.0, 10_000.0), pool(10_000.0, 10_050.0));
+        assert_eq!(optimal_quote_in(&buy, &sell, 50), None);
+    }
+
+    #[test]
+    fn test_simulated_trade_moves_both_pools_towards_each_other() {
+        let (buy, sell) = (pool(10_000.0, 10_000.0), pool(10_000.0, 11_000.0));
+        let trade = simulate_arbitrage_trade(&buy, &sell, 200.0, 0);
+
+        assert!(trade.buy_pool_after.price() > buy.price());
+        assert!(trade.sell_pool_after.price() < sell.price());
+        assert!((trade.quote_out - trade.quote_in - trade.profit_quote).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_opportunity_respects_minimum_profit_and_picks_direction() {
+        let config = ArbitrageConfig::default();
+        let venues = [("bridge_amm", pool(10_000.0, 11_000.0)), ("external_dex", pool(10_000.0, 10_000.0))];
+
+        // About 8.2 wNOCK of profit, worth $82 at $10
+        let opportunity = find_opportunity(venues, 10.0, &config).unwrap();
+        assert_eq!(opportunity.route, ArbitrageRoute { buy_on: "external_dex".into(), sell_on: "bridge_amm".into() });
+        assert_eq!(opportunity.spread_bps, 1000);
+        assert!(opportunity.profit_usd >= 50.0);
+        assert!(opportunity.confidence > 0.0 && opportunity.confidence < 1.0);
+
+        assert_eq!(find_opportunity(venues, 1.0, &config), None);
+    }
+
+    #[tokio::test]
+    async fn test_same_spread_is_emitted_once() {
+        let bridge = Arc::new(Mutex::new(pool(10_000.0, 11_000.0)));
+        let external = Arc::new(Mutex::new(pool(10_000.0, 10_000.0)));
+        let optimizer = ArbitrageOptimizer::new(ArbitrageConfig::default(), [
+            Box::new(FixedVenue { name: "bridge_amm", quote: bridge.clone() }),
+            Box::new(FixedVenue { name: "external_dex", quote: external.clone() }),
+        ]);
+        let mut opportunities = optimizer.subscribe();
+
+        assert_eq!(optimizer.detect().await.unwrap(), None, "no USD price yet");
+        optimizer.set_quote_usd_price(10.0);
+        assert!(optimizer.detect().await.unwrap().is_some());
+        assert_eq!(optimizer.detect().await.unwrap(), None);
+
+        *bridge.lock().unwrap() = pool(10_000.0, 11_500.0);
+        assert!(optimizer.detect().await.unwrap().is_some());
+        assert_eq!(opportunities.try_recv().unwrap().spread_bps, 1000);
+        assert_eq!(opportunities.try_recv().unwrap().spread_bps, 1500);
+        assert_eq!(optimizer.arbitrage_opportunities_total.get(), 2);
+    }
+}
@@ -0,0 +1,137 @@
+// Continuous Performance Optimization Daemon
+// Runs platform optimization on a fixed interval and records every result
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use log::{info, warn, error};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::time::interval;
+
+use performance_optimizer::{PerformanceOptimizationCoordinator, PlatformOptimizationResult};
+
+/// Daemon settings read from the environment
+#[derive(Debug, Clone)]
+struct ContinuousOptimizerConfig {
+    interval: Duration,
+    history_path: PathBuf,
+    webhook_url: Option<String>,
+    improvement_threshold: f64,
+}
+
+impl ContinuousOptimizerConfig {
+    fn from_env() -> Result<Self> {
+        let interval_seconds: u64 = std::env::var("OPTIMIZATION_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "300".to_string())
+            .parse()
+            .context("Invalid OPTIMIZATION_INTERVAL_SECONDS")?;
+
+        if interval_seconds == 0 {
+            anyhow::bail!("OPTIMIZATION_INTERVAL_SECONDS must be greater than 0");
+        }
+
+        Ok(Self {
+            interval: Duration::from_secs(interval_seconds),
+            history_path: std::env::var("OPTIMIZATION_HISTORY_PATH")
+                .unwrap_or_else(|_| "optimization_history.jsonl".to_string())
+                .into(),
+            webhook_url: std::env::var("OPTIMIZATION_WEBHOOK_URL").ok(),
+            improvement_threshold: std::env::var("OPTIMIZATION_IMPROVEMENT_THRESHOLD")
+                .unwrap_or_else(|_| "5.0".to_string())
+                .parse()
+                .context("Invalid OPTIMIZATION_IMPROVEMENT_THRESHOLD")?,
+        })
+    }
+}
+
+/// Webhook payload sent when overall improvement falls below the threshold
+#[derive(Debug, Serialize)]
+struct LowImprovementAlert<'a> {
+    event: &'static str,
+    threshold: f64,
+    result: &'a PlatformOptimizationResult,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    env_logger::init();
+
+    let config = ContinuousOptimizerConfig::from_env()?;
+    info!(
+        "Starting continuous optimizer (interval: {}s, history: {})",
+        config.interval.as_secs(),
+        config.history_path.display()
+    );
+
+    let mut coordinator = PerformanceOptimizationCoordinator::new().await?;
+    let http_client = reqwest::Client::new();
+    let mut ticker = interval(config.interval);
+
+    loop {
+        ticker.tick().await;
+
+        let result = match coordinator.optimize_platform().await {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Optimization run failed: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = append_history(&config.history_path, &result) {
+            error!("Failed to write optimization history: {}", e);
+        }
+
+        if result.overall_improvement < config.improvement_threshold {
+            warn!(
+                "Overall improvement {:.1}% below threshold {:.1}%",
+                result.overall_improvement, config.improvement_threshold
+            );
+
+            if let Some(url) = &config.webhook_url {
+                if let Err(e) = send_alert(&http_client, url, config.improvement_threshold, &result).await {
+                    error!("Failed to deliver optimization webhook: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Appends a single result as one line of JSON
+fn append_history(path: &Path, result: &PlatformOptimizationResult) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let line = serde_json::to_string(result)?;
+    writeln!(file, "{}", line)?;
+    file.flush()?;
+    Ok(())
+}
+
+async fn send_alert(
+    client: &reqwest::Client,
+    url: &str,
+    threshold: f64,
+    result: &PlatformOptimizationResult,
+) -> Result<()> {
+    let alert = LowImprovementAlert {
+        event: "optimization.improvement_below_threshold",
+        threshold,
+        result,
+    };
+
+    client
+        .post(url)
+        .json(&alert)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,237 @@
+// Difficulty Prediction
+// Projects Nockchain's next retarget from recent block times, with a confidence interval
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Largest change in difficulty a single retarget can make, in either direction
+pub const MAX_ADJUSTMENT_FACTOR: f64 = 4.0;
+
+/// Two-sided 95% normal quantile
+const Z_95: f64 = 1.959_964;
+
+const BLOCKS_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyPredictorConfig {
+    pub rpc_url: String,
+    /// Blocks between retargets, and blocks averaged by each retarget
+    pub retarget_window: u64,
+    pub target_block_time_secs: f64,
+}
+
+impl Default for DifficultyPredictorConfig {
+    fn default() -> Self {
+        Self {
+            rpc_url: "http://127.0.0.1:9090".to_string(),
+            retarget_window: 144,
+            target_block_time_secs: 600.0,
+        }
+    }
+}
+
+impl DifficultyPredictorConfig {
+    /// Reads `NOCKCHAIN_RPC_URL`, `DIFFICULTY_RETARGET_WINDOW` and `TARGET_BLOCK_TIME`,
+    /// falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            rpc_url: env_or("NOCKCHAIN_RPC_URL", defaults.rpc_url),
+            retarget_window: env_or("DIFFICULTY_RETARGET_WINDOW", defaults.retarget_window).max(1),
+            target_block_time_secs: env_or("TARGET_BLOCK_TIME", defaults.target_block_time_secs),
+        }
+    }
+}
+
+/// Block header fields the prediction needs, as returned by the node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockSummary {
+    pub height: u64,
+    /// Unix seconds
+    pub timestamp: i64,
+    pub difficulty: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentBlocksResponse {
+    blocks: Vec<BlockSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyPrediction {
+    pub tip_height: u64,
+    pub horizon_blocks: u64,
+    pub current_difficulty: u64,
+    pub predicted_difficulty: u64,
+    /// 95% confidence interval for the difficulty at `tip_height + horizon_blocks`
+    pub lower_bound: u64,
+    pub upper_bound: u64,
+    pub geometric_mean_block_time_secs: f64,
+    /// Retargets between the tip and the horizon
+    pub retargets: u64,
+}
+
+#[derive(Debug)]
+pub struct DifficultyPredictor {
+    pub config: DifficultyPredictorConfig,
+    client: reqwest::Client,
+}
+
+impl DifficultyPredictor {
+    pub fn new() -> Self {
+        Self::with_config(DifficultyPredictorConfig::from_env())
+    }
+
+    pub fn with_config(config: DifficultyPredictorConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(BLOCKS_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+
+    /// Predicts the difficulty `horizon_blocks` past the chain tip, assuming the network's
+    /// hashrate stays where the last retarget window puts it
+    pub async fn predict_next_difficulty(&self, horizon_blocks: u64) -> Result<DifficultyPrediction> {
+        let blocks = self.fetch_recent_blocks(self.config.retarget_window + 1).await?;
+        predict_from_blocks(&blocks, horizon_blocks, &self.config)
+    }
+
+    async fn fetch_recent_blocks(&self, count: u64) -> Result<Vec<BlockSummary>> {
+        let response: RecentBlocksResponse = self.client
+            .get(format!("{}/blocks/recent", self.config.rpc_url.trim_end_matches('/')))
+            .query(&[("count", count)])
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected recent blocks request")?
+            .json()
+            .await
+            .context("Malformed recent blocks response")?;
+
+        Ok(response.blocks)
+    }
+}
+
+/// `new = old * target / geometric_mean(block_time)`, clamped to `MAX_ADJUSTMENT_FACTOR`.
+///
+/// The first retarget's uncertainty is the standard error of the observed mean log block
+/// time; every later retarget averages a fresh window and adds its own sampling error.
+pub fn predict_from_blocks(
+    blocks: &[BlockSummary],
+    horizon_blocks: u64,
+    config: &DifficultyPredictorConfig,
+) -> Result<DifficultyPrediction> {
+    let mut blocks = blocks.to_vec();
+    blocks.sort_by_key(|block| block.height);
+    if blocks.len() < 3 {
+        bail!("Need at least 3 blocks to predict difficulty, got {}", blocks.len());
+    }
+
+    // Timestamps only have second resolution; a zero interval would have no logarithm
+    let log_intervals: Vec<f64> = blocks.windows(2)
+        .map(|pair| ((pair[1].timestamp - pair[0].timestamp).max(1) as f64).ln())
+        .collect();
+    let n = log_intervals.len() as f64;
+    let mean = log_intervals.iter().sum::<f64>() / n;
+    let variance = log_intervals.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    let tip = blocks.last().expect("at least 3 blocks");
+    let window = config.retarget_window.max(1);
+    let retargets = (tip.height + horizon_blocks) / window - tip.height / window;
+
+    let geometric_mean = mean.exp();
+    debug!("Geometric mean block time {:.1}s over {} blocks, {} retargets ahead", geometric_mean, blocks.len(), retargets);
+
+    let (predicted, lower, upper) = if retargets == 0 {
+        (tip.difficulty, tip.difficulty, tip.difficulty)
+    } else {
+        // Once difficulty catches up with the hashrate, later retargets are centred on 1x
+        let log_factor = config.target_block_time_secs.ln() - mean;
+        let log_variance = variance / n + (retargets - 1) as f64 * variance / window as f64;
+        let margin = Z_95 * log_variance.sqrt();
+
+        let scale = |log_factor: f64| {
+            let factor = log_factor.exp().clamp(1.0 / MAX_ADJUSTMENT_FACTOR, MAX_ADJUSTMENT_FACTOR);
+            (tip.difficulty as f64 * factor).round() as u64
+        };
+        (scale(log_factor), scale(log_factor - margin), scale(log_factor + margin))
+    };
+
+    Ok(DifficultyPrediction {
+        tip_height: tip.height,
+        horizon_blocks,
+        current_difficulty: tip.difficulty,
+        predicted_difficulty: predicted,
+        lower_bound: lower,
+        upper_bound: upper,
+        geometric_mean_block_time_secs: geometric_mean,
+        retargets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DifficultyPredictorConfig {
+        DifficultyPredictorConfig { retarget_window: 10, ..DifficultyPredictorConfig::default() }
+    }
+
+    /// Blocks 95..=105 with the given intervals between them
+    fn chain(intervals: &[i64]) -> Vec<BlockSummary> {
+        let mut timestamp = 1_700_000_000;
+        let mut blocks = vec![BlockSummary { height: 95, timestamp, difficulty: 1_000_000 }];
+        for (i, interval) in intervals.iter().enumerate() {
+            timestamp += interval;
+            blocks.push(BlockSummary { height: 96 + i as u64, timestamp, difficulty: 1_000_000 });
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_blocks_twice_as_fast_double_difficulty() {
+        let blocks = chain(&[200, 450, 300, 250, 350, 300, 300, 280, 320, 300]);
+        let prediction = predict_from_blocks(&blocks, 10, &config()).unwrap();
+
+        assert_eq!(prediction.retargets, 1);
+        assert!((prediction.geometric_mean_block_time_secs - 300.0).abs() < 15.0);
+        assert!((1_950_000..=2_050_000).contains(&prediction.predicted_difficulty));
+        assert!(prediction.lower_bound < prediction.predicted_difficulty);
+        assert!(prediction.upper_bound > prediction.predicted_difficulty);
+    }
+
+    #[test]
+    fn test_adjustment_is_clamped_to_four_times() {
+        let fast = predict_from_blocks(&chain(&[10, 12, 9, 11, 10, 10]), 10, &config()).unwrap();
+        assert_eq!(fast.predicted_difficulty, 4_000_000);
+        assert_eq!(fast.upper_bound, 4_000_000);
+
+        let slow = predict_from_blocks(&chain(&[6_000, 7_000, 6_500, 5_500]), 10, &config()).unwrap();
+        assert_eq!(slow.predicted_difficulty, 250_000);
+        assert_eq!(slow.lower_bound, 250_000);
+    }
+
+    #[test]
+    fn test_interval_widens_with_horizon() {
+        let blocks = chain(&[400, 800, 600, 500, 700, 650, 550, 600, 600, 620]);
+        let none = predict_from_blocks(&blocks, 4, &config()).unwrap();
+        assert_eq!(none.retargets, 0);
+        assert_eq!((none.lower_bound, none.upper_bound), (1_000_000, 1_000_000));
+
+        let near = predict_from_blocks(&blocks, 5, &config()).unwrap();
+        let far = predict_from_blocks(&blocks, 100, &config()).unwrap();
+        assert_eq!(far.retargets, 10);
+        assert!(far.upper_bound - far.lower_bound > near.upper_bound - near.lower_bound);
+        assert!(predict_from_blocks(&blocks[..2], 100, &config()).is_err());
+    }
+}
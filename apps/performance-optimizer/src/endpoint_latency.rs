@@ -0,0 +1,528 @@
+// Endpoint Response Time Tracking
+// Records per-endpoint latency histograms from a tower layer and reacts to sustained degradation
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use anyhow::Result;
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::MatchedPath;
+use axum::http::{HeaderMap, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use hdrhistogram::Histogram;
+use log::{debug, info, warn};
+use moka::future::Cache;
+use moka::policy::EvictionPolicy;
+use once_cell::sync::Lazy;
+use prometheus::{GaugeVec, Opts, Registry};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Instant};
+use tower::util::MapResponseLayer;
+use tower::{Layer, Service};
+
+/// Intervals the slowest endpoint must stay over target before an alert
+pub const DEGRADED_INTERVALS_BEFORE_ALERT: u32 = 3;
+
+/// Responses larger than this are never cached
+pub const MAX_CACHED_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Histogram bounds in microseconds; slower responses are clamped to the upper bound
+const HISTOGRAM_MIN_MICROS: u64 = 1;
+const HISTOGRAM_MAX_MICROS: u64 = 60_000_000;
+
+/// Label for requests that matched no route, so 404 probes cannot create unbounded series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+// Shared by every tracker so optimizers created per optimization run report into one series
+static ENDPOINT_RESPONSE_TIME_MS: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new("endpoint_response_time_ms", "Endpoint response time percentile over the last interval"),
+        &["endpoint", "quantile"],
+    ).expect("valid gauge")
+});
+
+#[derive(Debug, Clone)]
+pub struct EndpointLatencyConfig {
+    /// P95 an endpoint should stay under
+    pub target_response_time_ms: f64,
+    /// How often percentiles are published and the histograms reset
+    pub publish_interval: Duration,
+}
+
+impl Default for EndpointLatencyConfig {
+    fn default() -> Self {
+        Self {
+            target_response_time_ms: 25.0,
+            publish_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl EndpointLatencyConfig {
+    /// Reads `API_TARGET_RESPONSE_TIME_MS` and `API_LATENCY_PUBLISH_INTERVAL_SECS`,
+    /// falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            target_response_time_ms: env_or("API_TARGET_RESPONSE_TIME_MS", defaults.target_response_time_ms),
+            publish_interval: Duration::from_secs(
+                env_or("API_LATENCY_PUBLISH_INTERVAL_SECS", defaults.publish_interval.as_secs()).max(1),
+            ),
+        }
+    }
+}
+
+/// Response cache a route opts into with [`cache_when_degraded`]. The cache only starts
+/// serving once the route has been flagged by an [`EndpointDegradationAlert`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteCachePolicy {
+    pub max_entries: u64,
+    pub ttl: Duration,
+}
+
+/// Route annotation marking a read-heavy route as safe to serve from an in-memory LRU cache
+/// when it degrades:
+///
+/// `.route("/stats", get(stats).layer(cache_when_degraded(RouteCachePolicy { .. })))`
+pub fn cache_when_degraded(policy: RouteCachePolicy) -> MapResponseLayer<impl Fn(Response) -> Response + Clone> {
+    MapResponseLayer::new(move |mut response: Response| {
+        response.extensions_mut().insert(policy.clone());
+        response
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndpointLatencyStats {
+    /// Method and route template, e.g. `GET /api/v1/stats/:id`
+    pub endpoint: String,
+    pub request_count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// Optimisation applied in response to a degradation alert
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DegradationAction {
+    EnabledResponseCache { max_entries: u64, ttl_secs: u64 },
+    /// The route carries no cache annotation, so there is nothing safe to apply automatically
+    NoAutomaticOptimization,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EndpointDegradationAlert {
+    pub endpoint: String,
+    pub p95_ms: f64,
+    pub target_response_time_ms: f64,
+    pub consecutive_intervals: u32,
+    pub action: DegradationAction,
+}
+
+#[derive(Debug, Clone)]
+struct CachedEndpointResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl IntoResponse for CachedEndpointResponse {
+    fn into_response(self) -> Response {
+        (self.status, self.headers, self.body).into_response()
+    }
+}
+
+/// Per-endpoint latency histograms fed by [`EndpointLatencyLayer`]
+pub struct EndpointLatencyTracker {
+    pub config: EndpointLatencyConfig,
+    /// Response times in microseconds since the last publish
+    histograms: Mutex<HashMap<String, Histogram<u64>>>,
+    /// Cache annotations seen on responses, by endpoint
+    cache_policies: Mutex<HashMap<String, RouteCachePolicy>>,
+    response_caches: RwLock<HashMap<String, Cache<String, CachedEndpointResponse>>>,
+    /// Slowest endpoint of the previous intervals and how many in a row it was over target
+    degraded_streak: Mutex<Option<(String, u32)>>,
+    latest: Mutex<Vec<EndpointLatencyStats>>,
+    endpoint_response_time_ms: GaugeVec,
+}
+
+impl std::fmt::Debug for EndpointLatencyTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EndpointLatencyTracker")
+            .field("config", &self.config)
+            .field("cached_endpoints", &self.response_caches.read().unwrap().keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl EndpointLatencyTracker {
+    pub fn new(config: EndpointLatencyConfig) -> Self {
+        Self {
+            config,
+            histograms: Mutex::new(HashMap::new()),
+            cache_policies: Mutex::new(HashMap::new()),
+            response_caches: RwLock::new(HashMap::new()),
+            degraded_streak: Mutex::new(None),
+            latest: Mutex::new(Vec::new()),
+            endpoint_response_time_ms: ENDPOINT_RESPONSE_TIME_MS.clone(),
+        }
+    }
+
+    /// Registers the per-endpoint gauge; registering it again is not an error
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        match registry.register(Box::new(self.endpoint_response_time_ms.clone())) {
+            Ok(()) | Err(prometheus::Error::AlreadyReg) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn layer(self: &Arc<Self>) -> EndpointLatencyLayer {
+        EndpointLatencyLayer { tracker: Arc::clone(self) }
+    }
+
+    pub fn record(&self, endpoint: &str, elapsed: Duration) {
+        let micros = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let mut histograms = self.histograms.lock().unwrap();
+        let histogram = histograms.entry(endpoint.to_string()).or_insert_with(|| {
+            Histogram::new_with_bounds(HISTOGRAM_MIN_MICROS, HISTOGRAM_MAX_MICROS, 3).expect("valid histogram bounds")
+        });
+        histogram.saturating_record(micros.max(HISTOGRAM_MIN_MICROS));
+    }
+
+    /// Percentiles published at the end of the last interval, slowest P95 first
+    pub fn latest_stats(&self) -> Vec<EndpointLatencyStats> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    pub fn is_caching(&self, endpoint: &str) -> bool {
+        self.response_caches.read().unwrap().contains_key(endpoint)
+    }
+
+    fn annotate(&self, endpoint: &str, policy: &RouteCachePolicy) {
+        let mut policies = self.cache_policies.lock().unwrap();
+        if !policies.contains_key(endpoint) {
+            policies.insert(endpoint.to_string(), policy.clone());
+        }
+    }
+
+    fn response_cache(&self, endpoint: &str) -> Option<Cache<String, CachedEndpointResponse>> {
+        self.response_caches.read().unwrap().get(endpoint).cloned()
+    }
+
+    /// Closes the current interval: publishes P50/P95/P99 for every endpoint that served
+    /// requests, resets the histograms and checks the slowest endpoint against the target
+    pub fn publish(&self) -> Option<EndpointDegradationAlert> {
+        let histograms = std::mem::take(&mut *self.histograms.lock().unwrap());
+        let mut stats: Vec<_> = histograms.iter()
+            .filter(|(_, histogram)| !histogram.is_empty())
+            .map(|(endpoint, histogram)| latency_stats(endpoint, histogram))
+            .collect();
+        stats.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+
+        for endpoint in &stats {
+            for (quantile, value) in [("p50", endpoint.p50_ms), ("p95", endpoint.p95_ms), ("p99", endpoint.p99_ms)] {
+                self.endpoint_response_time_ms.with_label_values(&[endpoint.endpoint.as_str(), quantile]).set(value);
+            }
+        }
+
+        let slowest = stats.first().cloned();
+        *self.latest.lock().unwrap() = stats;
+
+        let mut streak = self.degraded_streak.lock().unwrap();
+        let Some(slowest) = slowest.filter(|slowest| slowest.p95_ms > self.config.target_response_time_ms) else {
+            *streak = None;
+            return None;
+        };
+
+        let intervals = match streak.as_ref() {
+            Some((endpoint, intervals)) if *endpoint == slowest.endpoint => intervals + 1,
+            _ => 1,
+        };
+        *streak = Some((slowest.endpoint.clone(), intervals));
+        debug!("Slowest endpoint {} at P95 {:.1}ms ({} intervals over {:.1}ms)",
+               slowest.endpoint, slowest.p95_ms, intervals, self.config.target_response_time_ms);
+
+        if intervals != DEGRADED_INTERVALS_BEFORE_ALERT {
+            return None;
+        }
+        drop(streak);
+
+        let alert = EndpointDegradationAlert {
+            action: self.optimize_degraded_endpoint(&slowest.endpoint),
+            endpoint: slowest.endpoint,
+            p95_ms: slowest.p95_ms,
+            target_response_time_ms: self.config.target_response_time_ms,
+            consecutive_intervals: intervals,
+        };
+        warn!("⚠️ Endpoint {} degraded: P95 {:.1}ms over {:.1}ms target for {} intervals, action: {:?}",
+              alert.endpoint, alert.p95_ms, alert.target_response_time_ms, alert.consecutive_intervals, alert.action);
+        Some(alert)
+    }
+
+    /// Turns on the response cache for an annotated endpoint
+    fn optimize_degraded_endpoint(&self, endpoint: &str) -> DegradationAction {
+        let Some(policy) = self.cache_policies.lock().unwrap().get(endpoint).cloned() else {
+            return DegradationAction::NoAutomaticOptimization;
+        };
+
+        self.response_caches.write().unwrap()
+            .entry(endpoint.to_string())
+            .or_insert_with(|| {
+                info!("Enabling {}-entry LRU response cache for {} (ttl {:?})", policy.max_entries, endpoint, policy.ttl);
+                Cache::builder()
+                    .max_capacity(policy.max_entries)
+                    .time_to_live(policy.ttl)
+                    .eviction_policy(EvictionPolicy::lru())
+                    .build()
+            });
+
+        DegradationAction::EnabledResponseCache { max_entries: policy.max_entries, ttl_secs: policy.ttl.as_secs() }
+    }
+
+    /// Publishes every `publish_interval` until the task is aborted
+    pub fn spawn_publisher(self: &Arc<Self>) -> JoinHandle<()> {
+        let tracker = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = interval(tracker.config.publish_interval);
+            // The first tick completes immediately and would publish an empty interval
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                tracker.publish();
+            }
+        })
+    }
+}
+
+fn latency_stats(endpoint: &str, histogram: &Histogram<u64>) -> EndpointLatencyStats {
+    let millis = |micros: u64| micros as f64 / 1000.0;
+    EndpointLatencyStats {
+        endpoint: endpoint.to_string(),
+        request_count: histogram.len(),
+        mean_ms: histogram.mean() / 1000.0,
+        p50_ms: millis(histogram.value_at_quantile(0.50)),
+        p95_ms: millis(histogram.value_at_quantile(0.95)),
+        p99_ms: millis(histogram.value_at_quantile(0.99)),
+    }
+}
+
+/// Method and matched route template, so path parameters share one histogram
+fn endpoint_label<B>(request: &Request<B>) -> String {
+    let route = request.extensions().get::<MatchedPath>()
+        .map(MatchedPath::as_str)
+        .unwrap_or(UNMATCHED_ROUTE);
+    format!("{} {}", request.method(), route)
+}
+
+/// Times every request through the wrapped router. Add it with `Router::layer` so it runs
+/// after routing and sees the matched route.
+#[derive(Debug, Clone)]
+pub struct EndpointLatencyLayer {
+    tracker: Arc<EndpointLatencyTracker>,
+}
+
+impl<S> Layer<S> for EndpointLatencyLayer {
+    type Service = EndpointLatencyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EndpointLatencyService { inner, tracker: Arc::clone(&self.tracker) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EndpointLatencyService<S> {
+    inner: S,
+    tracker: Arc<EndpointLatencyTracker>,
+}
+
+impl<S> Service<Request<Body>> for EndpointLatencyService<S>
+where
+    S: Service<Request<Body>, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let started = Instant::now();
+        let endpoint = endpoint_label(&request);
+        let is_read = request.method() == Method::GET;
+        let cache = if is_read { self.tracker.response_cache(&endpoint) } else { None };
+        let cache_key = request.uri().to_string();
+        let tracker = Arc::clone(&self.tracker);
+        // The clone that was driven to readiness handles this request
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            if let Some(cache) = &cache {
+                if let Some(cached) = cache.get(&cache_key).await {
+                    tracker.record(&endpoint, started.elapsed());
+                    return Ok(cached.into_response());
+                }
+            }
+
+            let mut response = inner.call(request).await?;
+            if is_read {
+                if let Some(policy) = response.extensions().get::<RouteCachePolicy>() {
+                    tracker.annotate(&endpoint, policy);
+                }
+            }
+            if let Some(cache) = cache.filter(|_| response.status() == StatusCode::OK) {
+                response = store_response(&cache, cache_key, response).await;
+            }
+
+            tracker.record(&endpoint, started.elapsed());
+            Ok(response)
+        })
+    }
+}
+
+/// Buffers a response small enough to cache and returns it rebuilt from the buffer
+async fn store_response(cache: &Cache<String, CachedEndpointResponse>, key: String, response: Response) -> Response {
+    let cacheable = response.body().size_hint().exact().is_some_and(|size| size <= MAX_CACHED_BODY_BYTES);
+    if !cacheable {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    match axum::body::to_bytes(body, MAX_CACHED_BODY_BYTES as usize).await {
+        Ok(body) => {
+            let cached = CachedEndpointResponse { status: parts.status, headers: parts.headers, body };
+            cache.insert(key, cached.clone()).await;
+            cached.into_response()
+        }
+        Err(e) => {
+            warn!("Failed to buffer response for caching: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn tracker(target_ms: f64) -> Arc<EndpointLatencyTracker> {
+        Arc::new(EndpointLatencyTracker::new(EndpointLatencyConfig {
+            target_response_time_ms: target_ms,
+            publish_interval: Duration::from_secs(60),
+        }))
+    }
+
+    fn record_ms(tracker: &EndpointLatencyTracker, endpoint: &str, millis: impl IntoIterator<Item = u64>) {
+        for ms in millis {
+            tracker.record(endpoint, Duration::from_millis(ms));
+        }
+    }
+
+    #[test]
+    fn test_publish_reports_percentiles_slowest_first() {
+        let tracker = tracker(25.0);
+        record_ms(&tracker, "GET /fast", [1, 2, 3]);
+        record_ms(&tracker, "GET /slow", 1..=100);
+        tracker.publish();
+
+        let stats = tracker.latest_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].endpoint, "GET /slow");
+        assert_eq!(stats[0].request_count, 100);
+        assert!((stats[0].p50_ms - 50.0).abs() < 0.1);
+        assert!((stats[0].p95_ms - 95.0).abs() < 0.1);
+        assert!((stats[0].p99_ms - 99.0).abs() < 0.1);
+
+        // Histograms reset every interval
+        tracker.publish();
+        assert!(tracker.latest_stats().is_empty());
+    }
+
+    #[test]
+    fn test_alert_after_three_degraded_intervals() {
+        let tracker = tracker(25.0);
+        tracker.annotate("GET /stats", &RouteCachePolicy { max_entries: 100, ttl: Duration::from_secs(5) });
+
+        for _ in 0..DEGRADED_INTERVALS_BEFORE_ALERT - 1 {
+            record_ms(&tracker, "GET /stats", [40, 45, 50]);
+            assert_eq!(tracker.publish(), None);
+        }
+        record_ms(&tracker, "GET /stats", [40, 45, 50]);
+        let alert = tracker.publish().expect("third degraded interval alerts");
+
+        assert_eq!(alert.endpoint, "GET /stats");
+        assert_eq!(alert.consecutive_intervals, DEGRADED_INTERVALS_BEFORE_ALERT);
+        assert_eq!(alert.action, DegradationAction::EnabledResponseCache { max_entries: 100, ttl_secs: 5 });
+        assert!(tracker.is_caching("GET /stats"));
+    }
+
+    #[test]
+    fn test_streak_resets_when_slowest_endpoint_recovers_or_changes() {
+        let tracker = tracker(25.0);
+        record_ms(&tracker, "GET /a", [40]);
+        tracker.publish();
+        record_ms(&tracker, "GET /a", [40]);
+        tracker.publish();
+        record_ms(&tracker, "GET /a", [10]);
+        assert_eq!(tracker.publish(), None);
+
+        record_ms(&tracker, "GET /a", [40]);
+        tracker.publish();
+        record_ms(&tracker, "GET /b", [60]);
+        tracker.publish();
+        record_ms(&tracker, "GET /b", [60]);
+        assert_eq!(tracker.publish(), None, "only two intervals with /b slowest");
+
+        record_ms(&tracker, "GET /b", [60]);
+        let alert = tracker.publish().unwrap();
+        assert_eq!(alert.endpoint, "GET /b");
+        assert_eq!(alert.action, DegradationAction::NoAutomaticOptimization);
+        assert!(!tracker.is_caching("GET /b"));
+    }
+
+    #[tokio::test]
+    async fn test_layer_serves_annotated_route_from_cache_once_degraded() {
+        let tracker = tracker(0.0);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = Arc::clone(&calls);
+        let app = Router::new()
+            .route("/stats/:id", get(move || {
+                let calls = Arc::clone(&handler_calls);
+                async move { format!("call {}", calls.fetch_add(1, Ordering::SeqCst)) }
+            }).layer(cache_when_degraded(RouteCachePolicy { max_entries: 10, ttl: Duration::from_secs(60) })))
+            .layer(tracker.layer());
+
+        let get_stats = |app: Router| async move {
+            let response = app.oneshot(Request::get("/stats/7").body(Body::empty()).unwrap()).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), 1024).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        for _ in 0..DEGRADED_INTERVALS_BEFORE_ALERT {
+            get_stats(app.clone()).await;
+            tracker.publish();
+        }
+        assert!(tracker.is_caching("GET /stats/:id"));
+
+        let first = get_stats(app.clone()).await;
+        let second = get_stats(app.clone()).await;
+        assert_eq!(first, second);
+        assert_eq!(calls.load(Ordering::SeqCst), DEGRADED_INTERVALS_BEFORE_ALERT as usize + 1);
+    }
+}
@@ -0,0 +1,298 @@
+// Mining Energy Efficiency
+// Measures joules per share at each thread count from RAPL counters or a TDP estimate
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+use prometheus::{Gauge, Registry};
+use serde::{Deserialize, Serialize};
+use crate::hashrate_tuning::ShareRateObservation;
+
+/// Package 0 energy counter exposed by the Linux powercap RAPL driver
+pub const DEFAULT_RAPL_ENERGY_PATH: &str = "/sys/class/powercap/intel-rapl:0/energy_uj";
+
+/// Share of TDP an idle package is assumed to draw when estimating power
+pub const IDLE_POWER_FRACTION: f64 = 0.1;
+
+/// Shares a thread count needs before its joules per share is trusted
+pub const MIN_SHARES_FOR_COMPARISON: f64 = 5.0;
+
+#[derive(Debug, Clone)]
+pub struct EnergyEfficiencyConfig {
+    pub rapl_energy_path: PathBuf,
+    /// Thermal design power used when RAPL is unavailable
+    pub tdp_watts: f64,
+}
+
+impl Default for EnergyEfficiencyConfig {
+    fn default() -> Self {
+        Self {
+            rapl_energy_path: PathBuf::from(DEFAULT_RAPL_ENERGY_PATH),
+            tdp_watts: 65.0,
+        }
+    }
+}
+
+impl EnergyEfficiencyConfig {
+    /// Reads `MINING_RAPL_ENERGY_PATH` and `MINING_CPU_TDP_WATTS`, falling back to the
+    /// defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            rapl_energy_path: std::env::var("MINING_RAPL_ENERGY_PATH").map(PathBuf::from)
+                .unwrap_or(defaults.rapl_energy_path),
+            tdp_watts: std::env::var("MINING_CPU_TDP_WATTS").ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.tdp_watts),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnergyMetrics {
+    pub watts_current: f64,
+    pub joules_per_share: f64,
+    pub optimal_thread_count: u8,
+}
+
+/// Cumulative RAPL energy counter, which wraps at `max_energy_range_uj`
+#[derive(Debug, Clone)]
+pub struct RaplCounter {
+    pub energy_path: PathBuf,
+    pub max_energy_range_uj: u64,
+}
+
+impl RaplCounter {
+    pub fn open(energy_path: &Path) -> Result<Self> {
+        let max_range_path = energy_path.with_file_name("max_energy_range_uj");
+        let counter = Self {
+            energy_path: energy_path.to_path_buf(),
+            max_energy_range_uj: read_microjoules(&max_range_path)?,
+        };
+        // energy_uj is often root-only, so make sure it can actually be read
+        counter.read()?;
+        Ok(counter)
+    }
+
+    pub fn read(&self) -> Result<u64> {
+        read_microjoules(&self.energy_path)
+    }
+}
+
+fn read_microjoules(path: &Path) -> Result<u64> {
+    let raw = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    raw.trim().parse().with_context(|| format!("Invalid energy value in {}", path.display()))
+}
+
+#[derive(Debug, Clone)]
+pub enum PowerSource {
+    Rapl(RaplCounter),
+    /// Power estimated from TDP and CPU utilisation
+    TdpEstimate { tdp_watts: f64 },
+}
+
+impl PowerSource {
+    /// RAPL on Linux when the counter is readable, otherwise the TDP estimate
+    pub fn detect(config: &EnergyEfficiencyConfig) -> Self {
+        #[cfg(target_os = "linux")]
+        match RaplCounter::open(&config.rapl_energy_path) {
+            Ok(counter) => {
+                info!("Measuring mining power from RAPL counter {}", counter.energy_path.display());
+                return Self::Rapl(counter);
+            }
+            Err(e) => warn!("RAPL unavailable ({}), estimating power from {:.0}W TDP", e, config.tdp_watts),
+        }
+
+        Self::TdpEstimate { tdp_watts: config.tdp_watts }
+    }
+}
+
+/// Microjoules between two counter readings, accounting for one wrap-around
+pub fn energy_delta_uj(previous: u64, current: u64, max_energy_range_uj: u64) -> u64 {
+    if current >= previous {
+        current - previous
+    } else {
+        max_energy_range_uj.saturating_sub(previous) + current
+    }
+}
+
+/// Package power from TDP, scaling linearly from the idle floor at 0% to TDP at 100%
+pub fn estimate_watts(tdp_watts: f64, cpu_usage_percent: f64) -> f64 {
+    let utilisation = (cpu_usage_percent / 100.0).clamp(0.0, 1.0);
+    tdp_watts * (IDLE_POWER_FRACTION + (1.0 - IDLE_POWER_FRACTION) * utilisation)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct ThreadEnergy {
+    joules: f64,
+    shares: f64,
+}
+
+impl ThreadEnergy {
+    fn joules_per_share(&self) -> Option<f64> {
+        (self.shares >= MIN_SHARES_FOR_COMPARISON).then(|| self.joules / self.shares)
+    }
+}
+
+/// Accumulates energy and shares per mining thread count to find the most efficient one
+#[derive(Debug)]
+pub struct EnergyEfficiencyOptimizer {
+    pub power_source: PowerSource,
+    /// Time and RAPL reading of the previous sample
+    last_sample: Option<(Instant, Option<u64>)>,
+    by_thread_count: BTreeMap<u8, ThreadEnergy>,
+    pub latest: Option<EnergyMetrics>,
+    energy_efficiency_j_per_share: Gauge,
+}
+
+impl EnergyEfficiencyOptimizer {
+    pub fn new(power_source: PowerSource) -> Self {
+        Self {
+            power_source,
+            last_sample: None,
+            by_thread_count: BTreeMap::new(),
+            latest: None,
+            energy_efficiency_j_per_share: Gauge::new(
+                "nock_mining_energy_efficiency_j_per_share",
+                "Joules consumed per mining share found at the current thread count",
+            ).expect("valid gauge"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(PowerSource::detect(&EnergyEfficiencyConfig::from_env()))
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.energy_efficiency_j_per_share.clone()))?;
+        Ok(())
+    }
+
+    /// Attributes the energy used since the previous sample to the thread count and share
+    /// rate just observed. The first sample only starts the measurement.
+    pub fn sample(&mut self, observation: ShareRateObservation) -> Result<Option<EnergyMetrics>> {
+        let energy_uj = match &self.power_source {
+            PowerSource::Rapl(counter) => Some(counter.read()?),
+            PowerSource::TdpEstimate { .. } => None,
+        };
+        Ok(self.sample_at(observation, Instant::now(), energy_uj))
+    }
+
+    fn sample_at(&mut self, observation: ShareRateObservation, now: Instant, energy_uj: Option<u64>) -> Option<EnergyMetrics> {
+        let (previous_at, previous_uj) = self.last_sample.replace((now, energy_uj))?;
+        let elapsed_secs = now.saturating_duration_since(previous_at).as_secs_f64();
+        if elapsed_secs <= 0.0 {
+            return None;
+        }
+
+        let joules = match (&self.power_source, previous_uj, energy_uj) {
+            (PowerSource::Rapl(counter), Some(previous), Some(current)) => {
+                energy_delta_uj(previous, current, counter.max_energy_range_uj) as f64 / 1_000_000.0
+            }
+            (PowerSource::Rapl(_), _, _) => return None,
+            (PowerSource::TdpEstimate { tdp_watts }, _, _) => {
+                estimate_watts(*tdp_watts, observation.cpu_usage_percent) * elapsed_secs
+            }
+        };
+        let shares = observation.share_rate.max(0.0) * elapsed_secs;
+
+        let totals = self.by_thread_count.entry(observation.threads).or_default();
+        totals.joules += joules;
+        totals.shares += shares;
+
+        let interval_joules_per_share = if shares > 0.0 { joules / shares } else { f64::INFINITY };
+        let metrics = EnergyMetrics {
+            watts_current: joules / elapsed_secs,
+            joules_per_share: totals.joules_per_share().unwrap_or(interval_joules_per_share),
+            optimal_thread_count: self.optimal_thread_count().unwrap_or(observation.threads),
+        };
+        if metrics.joules_per_share.is_finite() {
+            self.energy_efficiency_j_per_share.set(metrics.joules_per_share);
+        }
+        debug!("Mining at {} threads: {:.1}W, {:.2} J/share (energy-optimal {} threads)",
+               observation.threads, metrics.watts_current, metrics.joules_per_share, metrics.optimal_thread_count);
+
+        self.latest = Some(metrics);
+        Some(metrics)
+    }
+
+    pub fn joules_per_share(&self, threads: u8) -> Option<f64> {
+        self.by_thread_count.get(&threads).and_then(ThreadEnergy::joules_per_share)
+    }
+
+    /// Thread count with the fewest joules per share among those with enough shares
+    pub fn optimal_thread_count(&self) -> Option<u8> {
+        self.by_thread_count.iter()
+            .filter_map(|(&threads, energy)| energy.joules_per_share().map(|j| (threads, j)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(threads, _)| threads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn observation(threads: u8, share_rate: f64, cpu_usage_percent: f64) -> ShareRateObservation {
+        ShareRateObservation { threads, share_rate, cpu_usage_percent }
+    }
+
+    fn rapl() -> EnergyEfficiencyOptimizer {
+        EnergyEfficiencyOptimizer::new(PowerSource::Rapl(RaplCounter {
+            energy_path: PathBuf::from(DEFAULT_RAPL_ENERGY_PATH),
+            max_energy_range_uj: 262_143_328_850,
+        }))
+    }
+
+    #[test]
+    fn test_energy_delta_handles_wrap() {
+        assert_eq!(energy_delta_uj(1_000, 5_000, 10_000), 4_000);
+        assert_eq!(energy_delta_uj(9_000, 500, 10_000), 1_500);
+    }
+
+    #[test]
+    fn test_estimate_watts_scales_with_utilisation() {
+        assert!((estimate_watts(100.0, 0.0) - 10.0).abs() < 1e-9);
+        assert!((estimate_watts(100.0, 50.0) - 55.0).abs() < 1e-9);
+        assert!((estimate_watts(100.0, 150.0) - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rapl_finds_energy_optimal_thread_count() {
+        let mut optimizer = rapl();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(optimizer.sample_at(observation(4, 1.0, 50.0), at(0), Some(0)), None);
+        // 4 threads: 40W for 10s, 10 shares -> 40 J/share
+        let metrics = optimizer.sample_at(observation(4, 1.0, 50.0), at(10), Some(400_000_000)).unwrap();
+        assert!((metrics.watts_current - 40.0).abs() < 1e-9);
+        assert!((metrics.joules_per_share - 40.0).abs() < 1e-9);
+
+        // 8 threads: 60W for 10s, 20 shares -> 30 J/share
+        optimizer.sample_at(observation(8, 2.0, 90.0), at(20), Some(1_000_000_000)).unwrap();
+        // 12 threads: 100W for 10s, 22 shares -> ~45 J/share
+        let metrics = optimizer.sample_at(observation(12, 2.2, 100.0), at(30), Some(2_000_000_000)).unwrap();
+
+        assert_eq!(metrics.optimal_thread_count, 8);
+        assert!((optimizer.joules_per_share(8).unwrap() - 30.0).abs() < 1e-9);
+        assert!((optimizer.energy_efficiency_j_per_share.get() - 1000.0 / 22.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_thread_counts_without_enough_shares_are_not_compared() {
+        let mut optimizer = EnergyEfficiencyOptimizer::new(PowerSource::TdpEstimate { tdp_watts: 100.0 });
+        let start = Instant::now();
+        optimizer.sample_at(observation(2, 0.1, 20.0), start, None);
+        let metrics = optimizer.sample_at(observation(2, 0.1, 20.0), start + Duration::from_secs(10), None).unwrap();
+
+        // 28W estimated over 10s for a single share
+        assert!((metrics.watts_current - 28.0).abs() < 1e-9);
+        assert!((metrics.joules_per_share - 280.0).abs() < 1e-9);
+        assert_eq!(optimizer.optimal_thread_count(), None);
+        assert_eq!(metrics.optimal_thread_count, 2);
+    }
+}
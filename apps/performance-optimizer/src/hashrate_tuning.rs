@@ -0,0 +1,241 @@
+// Mining Thread Tuning
+// Hill-climbs the mining thread count towards a target share rate within a thermal limit
+
+use std::collections::VecDeque;
+use log::{debug, info};
+use anyhow::Result;
+use prometheus::{Gauge, IntGauge, Registry};
+
+/// Share rates within this fraction of the target are close enough to hold
+pub const HOLD_TOLERANCE: f64 = 0.05;
+
+/// A thread that raises the share rate by less than this fraction is not worth running
+pub const MIN_MARGINAL_GAIN: f64 = 0.02;
+
+/// Observations kept for the diminishing-returns check
+pub const SHARE_RATE_HISTORY: usize = 256;
+
+#[derive(Debug, Clone)]
+pub struct HashrateTuningConfig {
+    /// Shares per second the tuner aims for
+    pub target_share_rate: f64,
+    /// CPU usage above which a thread is shed regardless of share rate
+    pub thermal_cpu_limit_percent: f64,
+    pub min_threads: u8,
+    pub max_threads: u8,
+}
+
+impl Default for HashrateTuningConfig {
+    fn default() -> Self {
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {
+            target_share_rate: 1.0,
+            thermal_cpu_limit_percent: 90.0,
+            min_threads: 1,
+            max_threads: cores.min(u8::MAX as usize) as u8,
+        }
+    }
+}
+
+impl HashrateTuningConfig {
+    /// Reads `MINING_TARGET_SHARE_RATE`, `MINING_THERMAL_CPU_LIMIT`, `MINING_MIN_THREADS` and
+    /// `MINING_MAX_THREADS`, falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        let min_threads = env_or("MINING_MIN_THREADS", defaults.min_threads).max(1);
+        Self {
+            target_share_rate: env_or("MINING_TARGET_SHARE_RATE", defaults.target_share_rate),
+            thermal_cpu_limit_percent: env_or("MINING_THERMAL_CPU_LIMIT", defaults.thermal_cpu_limit_percent),
+            min_threads,
+            max_threads: env_or("MINING_MAX_THREADS", defaults.max_threads).max(min_threads),
+        }
+    }
+}
+
+/// Share rate and CPU usage measured while mining with `threads` threads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShareRateObservation {
+    pub threads: u8,
+    pub share_rate: f64,
+    pub cpu_usage_percent: f64,
+}
+
+/// Picks the next mining thread count from observed share rates
+#[derive(Debug)]
+pub struct HashrateOptimizer {
+    pub config: HashrateTuningConfig,
+    /// Thread count from the last decision
+    pub threads: u8,
+    history: VecDeque<ShareRateObservation>,
+    mining_threads: IntGauge,
+    mining_thread_ceiling: IntGauge,
+    mining_share_rate: Gauge,
+    mining_target_share_rate: Gauge,
+}
+
+impl HashrateOptimizer {
+    pub fn new(config: HashrateTuningConfig) -> Self {
+        let threads = config.max_threads;
+        Self {
+            config,
+            threads,
+            history: VecDeque::with_capacity(SHARE_RATE_HISTORY),
+            mining_threads: IntGauge::new("mining_threads", "Mining threads chosen by the hashrate optimizer")
+                .expect("valid gauge"),
+            mining_thread_ceiling: IntGauge::new("mining_thread_ceiling", "Thread count beyond which extra threads stop paying off")
+                .expect("valid gauge"),
+            mining_share_rate: Gauge::new("mining_share_rate", "Observed mining shares per second")
+                .expect("valid gauge"),
+            mining_target_share_rate: Gauge::new("mining_target_share_rate", "Target mining shares per second")
+                .expect("valid gauge"),
+        }
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.mining_threads.clone()))?;
+        registry.register(Box::new(self.mining_thread_ceiling.clone()))?;
+        registry.register(Box::new(self.mining_share_rate.clone()))?;
+        registry.register(Box::new(self.mining_target_share_rate.clone()))?;
+        Ok(())
+    }
+
+    pub fn record_observation(&mut self, observation: ShareRateObservation) {
+        if self.history.len() == SHARE_RATE_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(observation);
+        self.mining_share_rate.set(observation.share_rate);
+    }
+
+    pub fn latest_observation(&self) -> Option<ShareRateObservation> {
+        self.history.back().copied()
+    }
+
+    /// Applies the configured target to the current thread count
+    pub fn tune(&mut self) -> u8 {
+        self.threads = self.optimize_thread_count(self.threads, self.config.target_share_rate);
+        self.threads
+    }
+
+    /// One hill-climbing step from the latest observation at `current_threads`: shed a thread
+    /// over the thermal limit, hold within 5% of the target, otherwise add a thread while
+    /// below target until another thread would add less than 2%
+    pub fn optimize_thread_count(&mut self, current_threads: u8, target_share_rate: f64) -> u8 {
+        self.mining_target_share_rate.set(target_share_rate);
+        let ceiling = self.thread_ceiling();
+        self.mining_thread_ceiling.set(ceiling as i64);
+
+        let Some(latest) = self.history.iter().rev().find(|o| o.threads == current_threads).copied() else {
+            debug!("No share rate observed at {} threads yet", current_threads);
+            return self.decide(current_threads, current_threads);
+        };
+
+        let next = if latest.cpu_usage_percent > self.config.thermal_cpu_limit_percent {
+            info!("CPU at {:.1}% exceeds thermal limit {:.1}%, shedding a mining thread",
+                  latest.cpu_usage_percent, self.config.thermal_cpu_limit_percent);
+            current_threads.saturating_sub(1)
+        } else if (latest.share_rate - target_share_rate).abs() <= target_share_rate * HOLD_TOLERANCE {
+            current_threads
+        } else if latest.share_rate < target_share_rate {
+            current_threads.saturating_add(1).min(ceiling)
+        } else {
+            current_threads
+        };
+
+        self.decide(current_threads, next)
+    }
+
+    fn decide(&self, current: u8, next: u8) -> u8 {
+        let next = next.clamp(self.config.min_threads, self.config.max_threads);
+        if next != current {
+            info!("Mining threads {} -> {}", current, next);
+        }
+        self.mining_threads.set(next as i64);
+        next
+    }
+
+    /// Largest thread count before the first thread that added less than `MIN_MARGINAL_GAIN`
+    fn thread_ceiling(&self) -> u8 {
+        let mut ceiling = self.config.max_threads;
+        for threads in (self.config.min_threads + 1)..=self.config.max_threads {
+            let (Some(with), Some(without)) = (self.mean_share_rate(threads), self.mean_share_rate(threads - 1)) else {
+                continue;
+            };
+            if without > 0.0 && (with - without) / without < MIN_MARGINAL_GAIN {
+                ceiling = threads - 1;
+                break;
+            }
+        }
+        ceiling
+    }
+
+    fn mean_share_rate(&self, threads: u8) -> Option<f64> {
+        let rates: Vec<f64> = self.history.iter().filter(|o| o.threads == threads).map(|o| o.share_rate).collect();
+        (!rates.is_empty()).then(|| rates.iter().sum::<f64>() / rates.len() as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn optimizer() -> HashrateOptimizer {
+        HashrateOptimizer::new(HashrateTuningConfig {
+            target_share_rate: 10.0,
+            thermal_cpu_limit_percent: 85.0,
+            min_threads: 1,
+            max_threads: 16,
+        })
+    }
+
+    fn observe(optimizer: &mut HashrateOptimizer, threads: u8, share_rate: f64, cpu_usage_percent: f64) {
+        optimizer.record_observation(ShareRateObservation { threads, share_rate, cpu_usage_percent });
+    }
+
+    #[test]
+    fn test_climbs_holds_and_sheds() {
+        let mut optimizer = optimizer();
+        observe(&mut optimizer, 4, 6.0, 50.0);
+        assert_eq!(optimizer.optimize_thread_count(4, 10.0), 5);
+
+        observe(&mut optimizer, 5, 9.6, 60.0);
+        assert_eq!(optimizer.optimize_thread_count(5, 10.0), 5, "within 5% of target");
+
+        observe(&mut optimizer, 5, 7.0, 92.0);
+        assert_eq!(optimizer.optimize_thread_count(5, 10.0), 4, "over the thermal limit");
+        assert_eq!(optimizer.mining_threads.get(), 4);
+        assert_eq!(optimizer.mining_target_share_rate.get(), 10.0);
+    }
+
+    #[test]
+    fn test_stops_at_diminishing_returns() {
+        let mut optimizer = optimizer();
+        observe(&mut optimizer, 6, 8.0, 60.0);
+        observe(&mut optimizer, 7, 8.9, 65.0);
+        assert_eq!(optimizer.optimize_thread_count(7, 10.0), 8);
+
+        // The eighth thread added about 1%
+        observe(&mut optimizer, 8, 8.98, 70.0);
+        assert_eq!(optimizer.optimize_thread_count(8, 10.0), 7);
+        assert_eq!(optimizer.mining_thread_ceiling.get(), 7);
+
+        observe(&mut optimizer, 7, 8.9, 65.0);
+        assert_eq!(optimizer.optimize_thread_count(7, 10.0), 7, "clamped below target");
+    }
+
+    #[test]
+    fn test_holds_without_observation_and_respects_bounds() {
+        let mut optimizer = optimizer();
+        assert_eq!(optimizer.optimize_thread_count(3, 10.0), 3);
+
+        observe(&mut optimizer, 1, 0.5, 99.0);
+        assert_eq!(optimizer.optimize_thread_count(1, 10.0), 1);
+
+        observe(&mut optimizer, 16, 5.0, 40.0);
+        assert_eq!(optimizer.optimize_thread_count(16, 10.0), 16);
+    }
+}
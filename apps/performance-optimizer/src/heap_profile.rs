@@ -0,0 +1,247 @@
+// jemalloc Heap Profiling
+// Triggers heap dumps through jemalloc's control API and summarizes them per allocating function
+
+use std::collections::HashMap;
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Number of allocation sites included in performance reports
+pub const REPORT_ALLOCATION_SITES: usize = 20;
+
+// Frames belonging to the allocator itself; the first frame outside these is the allocation site
+const ALLOCATOR_FRAME_PREFIXES: &[&str] = &[
+    "_rjem_", "je_", "prof_", "imalloc", "malloc", "calloc", "realloc", "__rust_alloc", "__rust_realloc",
+    "__rg_alloc", "__rg_realloc", "alloc::", "<alloc::", "tikv_jemallocator", "<tikv_jemallocator",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllocationSite {
+    pub function: String,
+    /// Estimated live bytes, scaled up from jemalloc's sampled counts
+    pub bytes: u64,
+    pub objects: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeapReport {
+    pub sample_period: u64,
+    pub total_bytes: u64,
+    pub total_objects: u64,
+    /// Sorted by `bytes`, largest first
+    pub sites: Vec<AllocationSite>,
+}
+
+impl HeapReport {
+    pub fn top(&self, n: usize) -> &[AllocationSite] {
+        &self.sites[..n.min(self.sites.len())]
+    }
+}
+
+/// Writes a heap profile to `output_path`. Requires the binary to be started with
+/// `_RJEM_MALLOC_CONF=prof:true`, otherwise jemalloc has no samples to dump.
+#[cfg(feature = "jemalloc")]
+pub fn dump_heap_profile(output_path: &Path) -> Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use tikv_jemalloc_ctl::{epoch, profiling, raw};
+
+    if !profiling::prof::read().context("Failed to read prof setting")? {
+        bail!("jemalloc heap profiling is disabled; start with _RJEM_MALLOC_CONF=prof:true");
+    }
+
+    // Refresh jemalloc's cached statistics before dumping
+    epoch::mib().context("Failed to look up epoch")?.advance().context("Failed to advance epoch")?;
+
+    let path = CString::new(output_path.as_os_str().as_bytes()).context("Heap profile path contains NUL")?;
+    // SAFETY: prof.dump takes a NUL-terminated path that only needs to outlive the call
+    unsafe { raw::write(b"prof.dump\0", path.as_ptr()) }
+        .with_context(|| format!("Failed to dump heap profile to {}", output_path.display()))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "jemalloc"))]
+pub fn dump_heap_profile(_output_path: &Path) -> Result<()> {
+    bail!("Heap profiling requires the jemalloc feature")
+}
+
+/// Parses a jemalloc `heap_v2` profile, attributing each sampled stack to its first
+/// non-allocator frame. Addresses are resolved against the running binary, so this only
+/// symbolizes profiles dumped by the current process.
+pub fn parse_heap_profile(path: &Path) -> Result<HeapReport> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read heap profile {}", path.display()))?;
+    parse_heap_profile_text(&text, resolve_symbol)
+}
+
+/// Parses heap profile text with a custom address symbolizer
+pub fn parse_heap_profile_text(text: &str, mut resolve: impl FnMut(u64) -> Option<String>) -> Result<HeapReport> {
+    let mut lines = text.lines();
+    let header = lines.next().context("Empty heap profile")?;
+    let sample_period: u64 = header.strip_prefix("heap_v2/")
+        .with_context(|| format!("Unsupported heap profile header: {}", header))?
+        .trim()
+        .parse()
+        .context("Invalid heap profile sample period")?;
+
+    let mut sites: HashMap<String, (f64, f64)> = HashMap::new();
+    let mut current_stack: Option<Vec<u64>> = None;
+
+    for line in lines {
+        let line = line.trim();
+        if line.starts_with("MAPPED_LIBRARIES") {
+            break;
+        }
+
+        if let Some(frames) = line.strip_prefix('@') {
+            let stack = frames.split_whitespace()
+                .map(|frame| u64::from_str_radix(frame.trim_start_matches("0x"), 16))
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("Invalid stack frame in: {}", line))?;
+            current_stack = Some(stack);
+        } else if let Some(counts) = line.strip_prefix("t*:") {
+            // Process-wide totals precede the stacks; only per-stack totals are attributed
+            let Some(stack) = current_stack.take() else {
+                continue;
+            };
+            let (objects, bytes) = parse_counts(counts)
+                .with_context(|| format!("Invalid allocation counts in: {}", line))?;
+            if objects == 0.0 {
+                continue;
+            }
+            let (objects, bytes) = unsample(objects, bytes, sample_period);
+
+            let function = allocation_site(&stack, &mut resolve);
+            let entry = sites.entry(function).or_insert((0.0, 0.0));
+            entry.0 += bytes;
+            entry.1 += objects;
+        }
+    }
+
+    let mut sites: Vec<AllocationSite> = sites.into_iter()
+        .map(|(function, (bytes, objects))| AllocationSite {
+            function,
+            bytes: bytes.round() as u64,
+            objects: objects.round() as u64,
+        })
+        .collect();
+    sites.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.function.cmp(&b.function)));
+
+    Ok(HeapReport {
+        sample_period,
+        total_bytes: sites.iter().map(|site| site.bytes).sum(),
+        total_objects: sites.iter().map(|site| site.objects).sum(),
+        sites,
+    })
+}
+
+/// Parses `<objects>: <bytes> [<alloc objects>: <alloc bytes>]`
+fn parse_counts(counts: &str) -> Option<(f64, f64)> {
+    let live = counts.split('[').next()?;
+    let mut fields = live.split(':').map(str::trim);
+    let objects = fields.next()?.parse().ok()?;
+    let bytes = fields.next()?.parse().ok()?;
+    Some((objects, bytes))
+}
+
+/// Scales sampled counts to estimated totals the same way jeprof does for heap_v2
+fn unsample(objects: f64, bytes: f64, sample_period: u64) -> (f64, f64) {
+    if objects == 0.0 || sample_period <= 1 {
+        return (objects, bytes);
+    }
+    let average_size = bytes / objects;
+    let scale = 1.0 / (1.0 - (-average_size / sample_period as f64).exp());
+    (objects * scale, bytes * scale)
+}
+
+fn allocation_site(stack: &[u64], resolve: &mut impl FnMut(u64) -> Option<String>) -> String {
+    let mut fallback = None;
+    for &address in stack {
+        match resolve(address) {
+            Some(name) if ALLOCATOR_FRAME_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) => {
+                fallback.get_or_insert(name);
+            }
+            Some(name) => return name,
+            None => return format!("{:#x}", address),
+        }
+    }
+    fallback.unwrap_or_else(|| "<unknown>".to_string())
+}
+
+fn resolve_symbol(address: u64) -> Option<String> {
+    let mut name = None;
+    // Frames are return addresses; step back into the calling instruction
+    backtrace::resolve(address.saturating_sub(1) as usize as *mut std::ffi::c_void, |symbol| {
+        if name.is_none() {
+            name = symbol.name().map(|n| format!("{:#}", n));
+        }
+    });
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "heap_v2/524288
+  t*: 30: 3145728 [0: 0]
+  t0: 30: 3145728 [0: 0]
+@ 0x10 0x20 0x30
+  t*: 10: 2097152 [0: 0]
+  t0: 10: 2097152 [0: 0]
+@ 0x10 0x40
+  t*: 20: 1048576 [0: 0]
+  t0: 20: 1048576 [0: 0]
+@ 0x10 0x20 0x50
+  t*: 1: 64 [0: 0]
+@ 0x10 0x20
+  t*: 0: 0 [0: 0]
+MAPPED_LIBRARIES:
+55d0c0000000-55d0c0100000 r-xp 00000000 08:01 123 /usr/bin/performance-optimizer
+";
+
+    fn symbols(address: u64) -> Option<String> {
+        match address {
+            0x10 => Some("_rjem_je_prof_backtrace".to_string()),
+            0x20 => Some("alloc::raw_vec::finish_grow".to_string()),
+            0x30 => Some("performance_optimizer::cache::CacheOptimizer::warm".to_string()),
+            0x40 => Some("hashbrown::raw::RawTable::reserve_rehash".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_attributes_stacks_to_first_non_allocator_frame() {
+        let report = parse_heap_profile_text(PROFILE, symbols).unwrap();
+        assert_eq!(report.sample_period, 524288);
+
+        let functions: Vec<_> = report.sites.iter().map(|site| site.function.as_str()).collect();
+        // Smaller allocations are sampled less often, so the hashbrown site scales past `warm`
+        assert_eq!(functions, vec![
+            "hashbrown::raw::RawTable::reserve_rehash",
+            "performance_optimizer::cache::CacheOptimizer::warm",
+            "0x50",
+        ]);
+        assert_eq!(report.top(1).len(), 1);
+        assert_eq!(report.top(50).len(), 3);
+    }
+
+    #[test]
+    fn test_parse_scales_sampled_counts() {
+        let report = parse_heap_profile_text(PROFILE, symbols).unwrap();
+        let warm = report.sites.iter()
+            .find(|site| site.function.ends_with("CacheOptimizer::warm"))
+            .unwrap();
+        // 200 KiB average allocations against a 512 KiB period: scale = 1 / (1 - e^-0.4)
+        let scale = 1.0 / (1.0 - (-0.4f64).exp());
+        assert_eq!(warm.bytes, (2097152.0 * scale).round() as u64);
+        assert_eq!(warm.objects, (10.0 * scale).round() as u64);
+        assert_eq!(report.total_bytes, report.sites.iter().map(|site| site.bytes).sum::<u64>());
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_format() {
+        assert!(parse_heap_profile_text("--- heapz 1 ---\n", symbols).is_err());
+        assert!(parse_heap_profile_text("heap_v2/524288\n@ 0xzz\n", symbols).is_err());
+    }
+}
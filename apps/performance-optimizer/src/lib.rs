@@ -5,11 +5,45 @@ pub mod database_optimizer;
 pub mod api_optimizer;
 pub mod memory_optimizer;
 pub mod network_optimizer;
+pub mod profiling;
+pub mod pool_tuning;
+pub mod heap_profile;
+pub mod tcp_tuning;
+pub mod query_analysis;
+pub mod difficulty;
+pub mod hashrate_tuning;
+pub mod zk_batching;
+pub mod optimistic_settlement;
+pub mod table_bloat;
+pub mod endpoint_latency;
+pub mod adaptive_compression;
+pub mod energy_efficiency;
+pub mod order_matching;
+pub mod liquidity_rebalancing;
+pub mod price_feed;
+pub mod arbitrage;
 
 pub use database_optimizer::DatabaseOptimizationEngine;
 pub use api_optimizer::ApiPerformanceOptimizer;
 pub use memory_optimizer::MemoryOptimizationEngine;
 pub use network_optimizer::NetworkOptimizationEngine;
+pub use profiling::FlamegraphProfiler;
+pub use pool_tuning::ConnectionPoolOptimizer;
+pub use heap_profile::{AllocationSite, HeapReport};
+pub use tcp_tuning::TcpTuningConfig;
+pub use query_analysis::{IndexSuggestion, QueryAnalyzer};
+pub use difficulty::{DifficultyPrediction, DifficultyPredictor};
+pub use hashrate_tuning::{HashrateOptimizer, ShareRateObservation};
+pub use zk_batching::{BatchProver, ZkProofBatchConfig, ZkProofBatcher};
+pub use optimistic_settlement::{CrossChainOptimizer, FraudDetector, PendingSettlement};
+pub use table_bloat::{TableBloatAnalyzer, TableBloatConfig};
+pub use endpoint_latency::{cache_when_degraded, EndpointDegradationAlert, EndpointLatencyLayer, RouteCachePolicy};
+pub use adaptive_compression::{AdaptiveCompression, AdaptiveCompressionLayer};
+pub use energy_efficiency::{EnergyEfficiencyOptimizer, EnergyMetrics};
+pub use order_matching::{OrderMatchingOptimizer, OrderBook};
+pub use liquidity_rebalancing::{LiquidityOptimizer, RebalancingStrategy};
+pub use price_feed::{PriceFeedDivergenceAlert, PriceFeedOptimizer, PriceSource};
+pub use arbitrage::{simulate_arbitrage_trade, ArbitrageOpportunity, ArbitrageOptimizer};
 
 // Re-export main optimization functionality
 use std::collections::HashMap;
@@ -0,0 +1,585 @@
+// Bridge AMM Liquidity Rebalancing
+// Watches the NOCK/wNOCK pool's reserve ratio and trades it back towards the target
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use log::{debug, error, info, warn};
+use prometheus::{Counter, Gauge, Registry};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::task::JoinHandle;
+use tokio::time::interval;
+
+/// Base units per NOCK and per wNOCK (8 decimals)
+pub const NOCK: u64 = 100_000_000;
+
+/// The bridge program's constant-product swap fee
+pub const SWAP_FEE_BPS: u64 = 30;
+
+/// Largest single trade either strategy makes, as a fraction of the input reserve
+pub const MAX_TRADE_FRACTION: f64 = 0.01;
+
+/// `LiquidityPool` account layout: discriminator and four pubkeys precede the reserves
+const NOCK_RESERVE_OFFSET: usize = 8 + 4 * 32;
+const WNOCK_RESERVE_OFFSET: usize = NOCK_RESERVE_OFFSET + 8;
+
+const RPC_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalancingStrategyKind {
+    Conservative,
+    Aggressive,
+}
+
+impl std::str::FromStr for RebalancingStrategyKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "conservative" => Ok(Self::Conservative),
+            "aggressive" => Ok(Self::Aggressive),
+            other => bail!("Unknown rebalancing strategy {:?}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiquidityRebalancingConfig {
+    pub solana_rpc_url: String,
+    /// Address of the bridge program's `LiquidityPool` account
+    pub pool_address: String,
+    /// Service holding the treasury key that signs rebalancing swaps
+    pub amm_signer_url: String,
+    /// NOCK reserve per wNOCK reserve the pool should hold
+    pub target_ratio: f64,
+    /// Relative deviation from the target ratio that triggers a rebalance
+    pub imbalance_threshold: f64,
+    pub check_interval: Duration,
+    pub strategy: RebalancingStrategyKind,
+    /// Shortfall from the quoted output tolerated on each swap
+    pub max_slippage_bps: u64,
+}
+
+impl Default for LiquidityRebalancingConfig {
+    fn default() -> Self {
+        Self {
+            solana_rpc_url: "http://127.0.0.1:8899".to_string(),
+            pool_address: String::new(),
+            amm_signer_url: "http://127.0.0.1:3000".to_string(),
+            // wNOCK is minted 1:1 against bridged NOCK
+            target_ratio: 1.0,
+            imbalance_threshold: 0.05,
+            check_interval: Duration::from_secs(10),
+            strategy: RebalancingStrategyKind::Conservative,
+            max_slippage_bps: 50,
+        }
+    }
+}
+
+impl LiquidityRebalancingConfig {
+    /// Reads `SOLANA_RPC_URL`, `BRIDGE_LIQUIDITY_POOL`, `BRIDGE_AMM_SIGNER_URL`,
+    /// `LIQUIDITY_TARGET_RATIO`, `LIQUIDITY_IMBALANCE_THRESHOLD`, `LIQUIDITY_CHECK_INTERVAL_SECS`,
+    /// `LIQUIDITY_REBALANCING_STRATEGY` and `LIQUIDITY_MAX_SLIPPAGE_BPS`, falling back to the
+    /// defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            solana_rpc_url: env_or("SOLANA_RPC_URL", defaults.solana_rpc_url),
+            pool_address: env_or("BRIDGE_LIQUIDITY_POOL", defaults.pool_address),
+            amm_signer_url: env_or("BRIDGE_AMM_SIGNER_URL", defaults.amm_signer_url),
+            target_ratio: env_or("LIQUIDITY_TARGET_RATIO", defaults.target_ratio),
+            imbalance_threshold: env_or("LIQUIDITY_IMBALANCE_THRESHOLD", defaults.imbalance_threshold),
+            check_interval: Duration::from_secs(env_or("LIQUIDITY_CHECK_INTERVAL_SECS", defaults.check_interval.as_secs()).max(1)),
+            strategy: env_or("LIQUIDITY_REBALANCING_STRATEGY", defaults.strategy),
+            max_slippage_bps: env_or("LIQUIDITY_MAX_SLIPPAGE_BPS", defaults.max_slippage_bps),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapDirection {
+    NockToWnock,
+    WnockToNock,
+}
+
+/// Pool reserves in base units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolReserves {
+    pub nock: u64,
+    pub wnock: u64,
+}
+
+impl PoolReserves {
+    /// Decodes the reserves from raw `LiquidityPool` account data
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        let read = |offset: usize| -> Result<u64> {
+            let bytes = data.get(offset..offset + 8).context("Liquidity pool account data too short")?;
+            Ok(u64::from_le_bytes(bytes.try_into().expect("slice is 8 bytes")))
+        };
+        Ok(Self { nock: read(NOCK_RESERVE_OFFSET)?, wnock: read(WNOCK_RESERVE_OFFSET)? })
+    }
+
+    /// NOCK reserve per wNOCK reserve
+    pub fn ratio(&self) -> f64 {
+        self.nock as f64 / self.wnock as f64
+    }
+
+    /// `|actual_ratio - target_ratio| / target_ratio`
+    pub fn imbalance(&self, target_ratio: f64) -> f64 {
+        (self.ratio() - target_ratio).abs() / target_ratio
+    }
+
+    /// (reserve_in, reserve_out) for a swap in `direction`
+    pub fn reserves(&self, direction: SwapDirection) -> (u64, u64) {
+        match direction {
+            SwapDirection::NockToWnock => (self.nock, self.wnock),
+            SwapDirection::WnockToNock => (self.wnock, self.nock),
+        }
+    }
+
+    /// Output of a swap, computed as the bridge program does
+    pub fn quote(&self, direction: SwapDirection, amount_in: u64) -> u64 {
+        let (reserve_in, reserve_out) = self.reserves(direction);
+        let in_after_fee = amount_in as u128 * (10_000 - SWAP_FEE_BPS) as u128;
+        (in_after_fee * reserve_out as u128 / (reserve_in as u128 * 10_000 + in_after_fee)) as u64
+    }
+
+    /// Reserves after a swap; the whole input, fee included, joins the input reserve
+    pub fn after_swap(&self, direction: SwapDirection, amount_in: u64) -> Self {
+        let amount_out = self.quote(direction, amount_in);
+        match direction {
+            SwapDirection::NockToWnock => Self { nock: self.nock + amount_in, wnock: self.wnock - amount_out },
+            SwapDirection::WnockToNock => Self { nock: self.nock - amount_out, wnock: self.wnock + amount_in },
+        }
+    }
+
+    /// Direction and input that bring the ratio to `target_ratio`, ignoring the fee. Keeping
+    /// the constant product, the balanced reserves are `sqrt(k * target)` NOCK and
+    /// `sqrt(k / target)` wNOCK.
+    pub fn rebalancing_input(&self, target_ratio: f64) -> Option<(SwapDirection, u64)> {
+        let k = self.nock as f64 * self.wnock as f64;
+        let (direction, amount_in) = if self.ratio() < target_ratio {
+            (SwapDirection::NockToWnock, (k * target_ratio).sqrt() - self.nock as f64)
+        } else {
+            (SwapDirection::WnockToNock, (k / target_ratio).sqrt() - self.wnock as f64)
+        };
+        (amount_in >= 1.0).then_some((direction, amount_in as u64))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebalanceTrade {
+    pub direction: SwapDirection,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+impl RebalanceTrade {
+    /// Swap fee in the input token's base units
+    pub fn fee(&self) -> u64 {
+        self.amount_in * SWAP_FEE_BPS / 10_000
+    }
+}
+
+pub trait RebalancingStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Trades that move the pool towards `target_ratio`, in execution order
+    fn plan(&self, reserves: PoolReserves, target_ratio: f64, max_slippage_bps: u64) -> Vec<RebalanceTrade>;
+}
+
+/// Splits the rebalancing input into trades of at most `MAX_TRADE_FRACTION` of the input
+/// reserve, stopping at `max_total_fraction` of it. Each trade's minimum output is quoted
+/// against the reserves left by the trades before it.
+fn plan_trades(reserves: PoolReserves, target_ratio: f64, max_total_fraction: f64, max_slippage_bps: u64) -> Vec<RebalanceTrade> {
+    let Some((direction, ideal_input)) = reserves.rebalancing_input(target_ratio) else {
+        return Vec::new();
+    };
+    let (reserve_in, _) = reserves.reserves(direction);
+    let max_trade = (reserve_in as f64 * MAX_TRADE_FRACTION).round() as u64;
+    let mut remaining = ideal_input.min((reserve_in as f64 * max_total_fraction).round() as u64);
+
+    let mut simulated = reserves;
+    let mut trades = Vec::new();
+    while remaining > 0 && max_trade > 0 {
+        let amount_in = remaining.min(max_trade);
+        let quoted = simulated.quote(direction, amount_in);
+        if quoted == 0 {
+            break;
+        }
+        trades.push(RebalanceTrade {
+            direction,
+            amount_in,
+            min_amount_out: quoted * (10_000 - max_slippage_bps.min(10_000)) / 10_000,
+        });
+        simulated = simulated.after_swap(direction, amount_in);
+        remaining -= amount_in;
+    }
+    trades
+}
+
+/// A single trade of at most 1% of the pool
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConservativeRebalancer;
+
+impl RebalancingStrategy for ConservativeRebalancer {
+    fn name(&self) -> &'static str {
+        "conservative"
+    }
+
+    fn plan(&self, reserves: PoolReserves, target_ratio: f64, max_slippage_bps: u64) -> Vec<RebalanceTrade> {
+        plan_trades(reserves, target_ratio, MAX_TRADE_FRACTION, max_slippage_bps)
+    }
+}
+
+/// Successive trades of at most 1% each, up to 5% of the pool in total
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AggressiveRebalancer;
+
+impl AggressiveRebalancer {
+    pub const MAX_TOTAL_FRACTION: f64 = 0.05;
+}
+
+impl RebalancingStrategy for AggressiveRebalancer {
+    fn name(&self) -> &'static str {
+        "aggressive"
+    }
+
+    fn plan(&self, reserves: PoolReserves, target_ratio: f64, max_slippage_bps: u64) -> Vec<RebalanceTrade> {
+        plan_trades(reserves, target_ratio, Self::MAX_TOTAL_FRACTION, max_slippage_bps)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwapReceipt {
+    pub signature: String,
+    pub amount_out: u64,
+}
+
+#[async_trait]
+pub trait BridgeAmm: Send + Sync {
+    async fn reserves(&self) -> Result<PoolReserves>;
+
+    /// Submits the swap and waits for it to confirm
+    async fn swap(&self, trade: &RebalanceTrade) -> Result<SwapReceipt>;
+}
+
+/// Reads the pool account over Solana JSON-RPC and submits swaps through the AMM signer
+pub struct BridgeAmmClient {
+    solana_rpc_url: String,
+    pool_address: String,
+    amm_signer_url: String,
+    client: reqwest::Client,
+}
+
+impl BridgeAmmClient {
+    pub fn new(solana_rpc_url: String, pool_address: String, amm_signer_url: String) -> Self {
+        Self {
+            solana_rpc_url,
+            pool_address,
+            amm_signer_url,
+            client: reqwest::Client::builder()
+                .timeout(RPC_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+}
+
+#[async_trait]
+impl BridgeAmm for BridgeAmmClient {
+    async fn reserves(&self) -> Result<PoolReserves> {
+        if self.pool_address.is_empty() {
+            bail!("BRIDGE_LIQUIDITY_POOL is not set");
+        }
+        let response: Value = self.client
+            .post(&self.solana_rpc_url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getAccountInfo",
+                "params": [self.pool_address, { "encoding": "base64", "commitment": "confirmed" }],
+            }))
+            .send()
+            .await
+            .context("Failed to reach Solana RPC")?
+            .error_for_status()
+            .context("Solana RPC rejected getAccountInfo")?
+            .json()
+            .await
+            .context("Malformed getAccountInfo response")?;
+
+        if let Some(error) = response.get("error") {
+            bail!("Solana RPC error: {}", error);
+        }
+        let data = response.pointer("/result/value/data/0").and_then(Value::as_str)
+            .with_context(|| format!("Liquidity pool account {} not found", self.pool_address))?;
+        let bytes = base64::engine::general_purpose::STANDARD.decode(data)
+            .context("Liquidity pool account data is not base64")?;
+        PoolReserves::from_account_data(&bytes)
+    }
+
+    async fn swap(&self, trade: &RebalanceTrade) -> Result<SwapReceipt> {
+        self.client
+            .post(format!("{}/amm/swap", self.amm_signer_url.trim_end_matches('/')))
+            .json(trade)
+            .send()
+            .await
+            .context("Failed to reach AMM signer")?
+            .error_for_status()
+            .context("AMM signer rejected swap")?
+            .json()
+            .await
+            .context("Malformed swap response")
+    }
+}
+
+/// One rebalance of the pool, with reserves read before and after it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebalanceOutcome {
+    pub strategy: String,
+    pub before: PoolReserves,
+    pub after: PoolReserves,
+    pub receipts: Vec<SwapReceipt>,
+    /// Swap fees paid, in NOCK
+    pub fee_nock: f64,
+}
+
+/// Monitors the bridge AMM pool and rebalances it when its reserve ratio drifts
+pub struct LiquidityOptimizer {
+    config: LiquidityRebalancingConfig,
+    amm: Box<dyn BridgeAmm>,
+    strategy: Box<dyn RebalancingStrategy>,
+    pub last_imbalance: Mutex<Option<f64>>,
+    pool_imbalance: Gauge,
+    rebalancing_fee_total: Counter,
+}
+
+impl std::fmt::Debug for LiquidityOptimizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LiquidityOptimizer")
+            .field("config", &self.config)
+            .field("strategy", &self.strategy.name())
+            .field("last_imbalance", &self.last_imbalance)
+            .finish()
+    }
+}
+
+impl LiquidityOptimizer {
+    pub fn new(config: LiquidityRebalancingConfig, amm: Box<dyn BridgeAmm>, strategy: Box<dyn RebalancingStrategy>) -> Self {
+        Self {
+            config,
+            amm,
+            strategy,
+            last_imbalance: Mutex::new(None),
+            pool_imbalance: Gauge::new(
+                "nock_liquidity_pool_imbalance_ratio",
+                "Relative deviation of the NOCK/wNOCK reserve ratio from its target",
+            ).expect("valid gauge"),
+            rebalancing_fee_total: Counter::new(
+                "rebalancing_fee_total",
+                "NOCK paid in swap fees by liquidity rebalancing trades",
+            ).expect("valid counter"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let config = LiquidityRebalancingConfig::from_env();
+        let amm = BridgeAmmClient::new(config.solana_rpc_url.clone(), config.pool_address.clone(), config.amm_signer_url.clone());
+        let strategy: Box<dyn RebalancingStrategy> = match config.strategy {
+            RebalancingStrategyKind::Conservative => Box::new(ConservativeRebalancer),
+            RebalancingStrategyKind::Aggressive => Box::new(AggressiveRebalancer),
+        };
+        Self::new(config, Box::new(amm), strategy)
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.pool_imbalance.clone()))?;
+        registry.register(Box::new(self.rebalancing_fee_total.clone()))?;
+        Ok(())
+    }
+
+    pub fn rebalancing_fee_total(&self) -> f64 {
+        self.rebalancing_fee_total.get()
+    }
+
+    /// Reads the pool and, if its imbalance exceeds the threshold, executes the strategy's
+    /// trades. A failed trade stops the rest; trades already made are still reported.
+    pub async fn check_and_rebalance(&self) -> Result<Option<RebalanceOutcome>> {
+        let before = self.amm.reserves().await.context("Failed to read pool reserves")?;
+        let imbalance = before.imbalance(self.config.target_ratio);
+        self.pool_imbalance.set(imbalance);
+        *self.last_imbalance.lock().unwrap() = Some(imbalance);
+
+        if imbalance <= self.config.imbalance_threshold {
+            debug!("NOCK/wNOCK pool imbalance {:.2}% within {:.2}%", imbalance * 100.0, self.config.imbalance_threshold * 100.0);
+            return Ok(None);
+        }
+
+        let trades = self.strategy.plan(before, self.config.target_ratio, self.config.max_slippage_bps);
+        if trades.is_empty() {
+            warn!("NOCK/wNOCK pool is {:.2}% imbalanced but the {} strategy planned no trades",
+                  imbalance * 100.0, self.strategy.name());
+            return Ok(None);
+        }
+
+        let mut receipts = Vec::new();
+        let mut fee_nock = 0.0;
+        for trade in &trades {
+            match self.amm.swap(trade).await {
+                Ok(receipt) => {
+                    let fee = match trade.direction {
+                        SwapDirection::NockToWnock => trade.fee() as f64,
+                        SwapDirection::WnockToNock => trade.fee() as f64 * before.ratio(),
+                    } / NOCK as f64;
+                    self.rebalancing_fee_total.inc_by(fee);
+                    fee_nock += fee;
+                    debug!("Rebalancing swap {:?} {} in, {} out ({})", trade.direction, trade.amount_in, receipt.amount_out, receipt.signature);
+                    receipts.push(receipt);
+                }
+                Err(e) if receipts.is_empty() => return Err(e.context("Rebalancing swap failed")),
+                Err(e) => {
+                    error!("Rebalancing swap failed after {} of {} trades: {:#}", receipts.len(), trades.len(), e);
+                    break;
+                }
+            }
+        }
+
+        let after = self.amm.reserves().await.context("Failed to read pool reserves after rebalancing")?;
+        info!("Rebalanced NOCK/wNOCK pool ({}, {} trades): reserves {} NOCK / {} wNOCK -> {} NOCK / {} wNOCK, \
+               imbalance {:.2}% -> {:.2}%, fees {:.8} NOCK",
+              self.strategy.name(), receipts.len(), before.nock, before.wnock, after.nock, after.wnock,
+              imbalance * 100.0, after.imbalance(self.config.target_ratio) * 100.0, fee_nock);
+
+        Ok(Some(RebalanceOutcome { strategy: self.strategy.name().to_string(), before, after, receipts, fee_nock }))
+    }
+
+    /// Checks the pool every `check_interval`
+    pub fn spawn_monitor(self: &Arc<Self>) -> JoinHandle<()> {
+        let optimizer = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = interval(optimizer.config.check_interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = optimizer.check_and_rebalance().await {
+                    warn!("Liquidity check failed: {:#}", e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Constant-product pool held in memory
+    struct MockAmm {
+        reserves: Arc<Mutex<PoolReserves>>,
+    }
+
+    #[async_trait]
+    impl BridgeAmm for MockAmm {
+        async fn reserves(&self) -> Result<PoolReserves> {
+            Ok(*self.reserves.lock().unwrap())
+        }
+
+        async fn swap(&self, trade: &RebalanceTrade) -> Result<SwapReceipt> {
+            let mut reserves = self.reserves.lock().unwrap();
+            let amount_out = reserves.quote(trade.direction, trade.amount_in);
+            if amount_out < trade.min_amount_out {
+                bail!("slippage exceeded");
+            }
+            *reserves = reserves.after_swap(trade.direction, trade.amount_in);
+            Ok(SwapReceipt { signature: format!("sig-{}", trade.amount_in), amount_out })
+        }
+    }
+
+    fn optimizer(reserves: PoolReserves, strategy: Box<dyn RebalancingStrategy>) -> (LiquidityOptimizer, Arc<Mutex<PoolReserves>>) {
+        let pool = Arc::new(Mutex::new(reserves));
+        let amm = MockAmm { reserves: pool.clone() };
+        (LiquidityOptimizer::new(LiquidityRebalancingConfig::default(), Box::new(amm), strategy), pool)
+    }
+
+    #[test]
+    fn test_reserves_decode_from_account_data() {
+        let mut data = vec![0u8; 8 + 4 * 32 + 3 * 8 + 1];
+        data[NOCK_RESERVE_OFFSET..NOCK_RESERVE_OFFSET + 8].copy_from_slice(&1_234u64.to_le_bytes());
+        data[WNOCK_RESERVE_OFFSET..WNOCK_RESERVE_OFFSET + 8].copy_from_slice(&5_678u64.to_le_bytes());
+        assert_eq!(PoolReserves::from_account_data(&data).unwrap(), PoolReserves { nock: 1_234, wnock: 5_678 });
+        assert!(PoolReserves::from_account_data(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn test_imbalance_is_relative_to_target() {
+        let reserves = PoolReserves { nock: 110 * NOCK, wnock: 100 * NOCK };
+        assert!((reserves.imbalance(1.0) - 0.10).abs() < 1e-9);
+        assert!(reserves.imbalance(1.1) < 1e-9);
+    }
+
+    #[test]
+    fn test_conservative_makes_one_trade_capped_at_one_percent() {
+        let reserves = PoolReserves { nock: 1_200_000 * NOCK, wnock: 1_000_000 * NOCK };
+        let trades = ConservativeRebalancer.plan(reserves, 1.0, 50);
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].direction, SwapDirection::WnockToNock);
+        assert_eq!(trades[0].amount_in, 10_000 * NOCK);
+    }
+
+    #[test]
+    fn test_aggressive_splits_up_to_five_percent() {
+        let reserves = PoolReserves { nock: 1_500_000 * NOCK, wnock: 1_000_000 * NOCK };
+        let trades = AggressiveRebalancer.plan(reserves, 1.0, 50);
+
+        assert_eq!(trades.len(), 5);
+        assert!(trades.iter().all(|trade| trade.amount_in <= 10_000 * NOCK));
+        assert_eq!(trades.iter().map(|trade| trade.amount_in).sum::<u64>(), 50_000 * NOCK);
+        // Later trades are quoted against a pool the earlier ones already moved
+        assert!(trades[4].min_amount_out < trades[0].min_amount_out);
+    }
+
+    #[test]
+    fn test_small_imbalance_plans_only_the_needed_input() {
+        let reserves = PoolReserves { nock: 1_000_000 * NOCK, wnock: 1_002_000 * NOCK };
+        let trades = AggressiveRebalancer.plan(reserves, 1.0, 50);
+
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].direction, SwapDirection::NockToWnock);
+        let after = reserves.after_swap(trades[0].direction, trades[0].amount_in);
+        assert!(after.imbalance(1.0) < 0.0001);
+    }
+
+    #[tokio::test]
+    async fn test_balanced_pool_is_left_alone() {
+        let reserves = PoolReserves { nock: 1_040_000 * NOCK, wnock: 1_000_000 * NOCK };
+        let (optimizer, pool) = optimizer(reserves, Box::new(AggressiveRebalancer));
+
+        assert_eq!(optimizer.check_and_rebalance().await.unwrap(), None);
+        assert_eq!(*pool.lock().unwrap(), reserves);
+        assert_eq!(optimizer.rebalancing_fee_total(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_imbalanced_pool_is_rebalanced_and_fees_tracked() {
+        let reserves = PoolReserves { nock: 1_200_000 * NOCK, wnock: 1_000_000 * NOCK };
+        let (optimizer, _pool) = optimizer(reserves, Box::new(AggressiveRebalancer));
+
+        let outcome = optimizer.check_and_rebalance().await.unwrap().expect("rebalanced");
+        assert_eq!(outcome.before, reserves);
+        assert_eq!(outcome.receipts.len(), 5);
+        assert!(outcome.after.imbalance(1.0) < reserves.imbalance(1.0));
+        // 0.3% of 50,000 wNOCK, valued at 1.2 NOCK each
+        assert!((outcome.fee_nock - 180.0).abs() < 1e-6);
+        assert!((optimizer.rebalancing_fee_total() - outcome.fee_nock).abs() < 1e-9);
+    }
+}
@@ -2,6 +2,7 @@
 // Advanced performance monitoring, profiling, and optimization across all components
 
 use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::time::{sleep, interval};
@@ -32,6 +33,8 @@ pub struct PerformanceOptimizer {
     pub dex_optimizer: Arc<Mutex<DexPerformanceOptimizer>>,
     pub monitoring_tasks: Vec<JoinHandle<()>>,
     pub optimization_scheduler: Arc<Mutex<OptimizationScheduler>>,
+    /// Set when `NOCK_PROFILING=1`
+    pub profiler: Option<FlamegraphProfiler>,
 }
 
 /// Real-time system monitoring and metrics collection
@@ -52,6 +55,8 @@ pub struct SystemMonitor {
 pub struct DatabaseOptimizer {
     pub query_analyzer: QueryAnalyzer,
     pub connection_pool_optimizer: ConnectionPoolOptimizer,
+    /// Pool tuned by `connection_pool_optimizer`; None when DATABASE_URL is not set
+    pub pool: Option<ResizablePgPool>,
     pub index_optimizer: IndexOptimizer,
     pub cache_optimizer: CacheOptimizer,
     pub slow_query_detector: SlowQueryDetector,
@@ -105,7 +110,7 @@ pub struct MiningPerformanceOptimizer {
 /// Bridge performance optimization
 #[derive(Debug)]
 pub struct BridgePerformanceOptimizer {
-    pub zk_proof_optimizer: ZkProofOptimizer,
+    pub zk_proof_optimizer: ZkProofBatcher,
     pub cross_chain_optimizer: CrossChainOptimizer,
     pub settlement_optimizer: SettlementOptimizer,
     pub validation_optimizer: ValidationOptimizer,
@@ -117,8 +122,8 @@ pub struct BridgePerformanceOptimizer {
 #[derive(Debug)]
 pub struct DexPerformanceOptimizer {
     pub order_matching_optimizer: OrderMatchingOptimizer,
-    pub liquidity_optimizer: LiquidityOptimizer,
-    pub arbitrage_optimizer: ArbitrageOptimizer,
+    pub liquidity_optimizer: Arc<LiquidityOptimizer>,
+    pub arbitrage_optimizer: Arc<ArbitrageOptimizer>,
     pub market_making_optimizer: MarketMakingOptimizer,
     pub price_feed_optimizer: PriceFeedOptimizer,
     pub trading_engine_optimizer: TradingEngineOptimizer,
@@ -177,6 +182,34 @@ pub struct OptimizationResult {
     pub details: String,
 }
 
+/// Optimization result with the flamegraph captured while it ran, when profiling is enabled
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfiledOptimizationResult {
+    pub result: OptimizationResult,
+    pub flamegraph: Option<PathBuf>,
+}
+
+impl ProfiledOptimizationResult {
+    pub fn new(mut result: OptimizationResult, flamegraph: Option<PathBuf>) -> Self {
+        if let Some(file_name) = flamegraph.as_ref().and_then(|path| path.file_name()) {
+            result.details = format!("{} (flamegraph: {})", result.details, file_name.to_string_lossy());
+        }
+        Self { result, flamegraph }
+    }
+}
+
+/// Finishes the capture started for an optimization and attaches its flamegraph
+fn with_profile(capture: Option<ProfileCapture>, result: OptimizationResult) -> ProfiledOptimizationResult {
+    let flamegraph = capture.and_then(|capture| match capture.finish() {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Failed to write flamegraph for {} optimization: {}", result.optimization_type, e);
+            None
+        }
+    });
+    ProfiledOptimizationResult::new(result, flamegraph)
+}
+
 impl PerformanceOptimizer {
     pub async fn new() -> Result<Self> {
         info!("Initializing Performance Optimization Engine");
@@ -202,6 +235,7 @@ impl PerformanceOptimizer {
             dex_optimizer,
             monitoring_tasks: Vec::new(),
             optimization_scheduler,
+            profiler: FlamegraphProfiler::from_env(),
         })
     }
 
@@ -289,7 +323,7 @@ impl PerformanceOptimizer {
     }
 
     /// Execute comprehensive platform optimization
-    pub async fn optimize_platform(&mut self) -> Result<Vec<OptimizationResult>> {
+    pub async fn optimize_platform(&mut self) -> Result<Vec<ProfiledOptimizationResult>> {
         info!("Starting comprehensive platform optimization");
         let mut results = Vec::new();
 
@@ -344,7 +378,8 @@ impl PerformanceOptimizer {
     }
 
     /// Optimize database performance
-    async fn optimize_database(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_database(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("database"));
         let before_metrics = self.collect_current_metrics().await?;
         
         // Use the dedicated database optimization engine
@@ -356,7 +391,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "database").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "database".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -364,11 +399,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement.max(db_result.improvement_percent),
             success: improvement > 0.0 || db_result.success,
             details: "Advanced database optimization with query analysis, connection pooling, and index optimization".to_string(),
-        })
+        }))
     }
 
     /// Optimize API response times
-    async fn optimize_api_performance(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_api_performance(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("api"));
         let before_metrics = self.collect_current_metrics().await?;
         
         // Use the dedicated API performance optimizer
@@ -381,7 +417,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "api").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "api".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -389,11 +425,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement.max(api_result.improvement_percent),
             success: improvement > 0.0 || api_result.target_achieved,
             details: "Advanced API optimization targeting <25ms response time with compression and caching".to_string(),
-        })
+        }))
     }
 
     /// Optimize memory usage
-    async fn optimize_memory_usage(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_memory_usage(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("memory"));
         let before_metrics = self.collect_current_metrics().await?;
         
         // Use the dedicated memory optimization engine
@@ -406,7 +443,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "memory").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "memory".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -414,11 +451,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement.max(memory_result.performance_improvement_percent),
             success: improvement > 0.0 || memory_result.success,
             details: "Advanced memory optimization with allocation patterns, GC tuning, and leak detection".to_string(),
-        })
+        }))
     }
 
     /// Optimize network performance
-    async fn optimize_network_performance(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_network_performance(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("network"));
         let before_metrics = self.collect_current_metrics().await?;
         
         // Use the dedicated network optimization engine
@@ -431,7 +469,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "network").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "network".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -439,11 +477,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement.max(network_result.throughput_improvement_percent),
             success: improvement > 0.0 || network_result.success,
             details: "Advanced network optimization with bandwidth efficiency, TCP tuning, and protocol optimization".to_string(),
-        })
+        }))
     }
 
     /// Optimize mining performance
-    async fn optimize_mining_performance(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_mining_performance(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("mining"));
         let before_metrics = self.collect_current_metrics().await?;
         
         if let Ok(mut optimizer) = self.mining_optimizer.lock() {
@@ -463,7 +502,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "mining").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "mining".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -471,11 +510,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement,
             success: improvement > 0.0,
             details: "Mining hashrate optimization, proof power improvements, and eon transition optimization".to_string(),
-        })
+        }))
     }
 
     /// Optimize bridge performance
-    async fn optimize_bridge_performance(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_bridge_performance(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("bridge"));
         let before_metrics = self.collect_current_metrics().await?;
         
         if let Ok(mut optimizer) = self.bridge_optimizer.lock() {
@@ -495,7 +535,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "bridge").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "bridge".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -503,11 +543,12 @@ impl PerformanceOptimizer {
             improvement_percent: improvement,
             success: improvement > 0.0,
             details: "Bridge ZK proof optimization, cross-chain performance, and settlement improvements".to_string(),
-        })
+        }))
     }
 
     /// Optimize DEX performance
-    async fn optimize_dex_performance(&mut self) -> Result<OptimizationResult> {
+    async fn optimize_dex_performance(&mut self) -> Result<ProfiledOptimizationResult> {
+        let capture = self.profiler.as_ref().and_then(|profiler| profiler.start("dex"));
         let before_metrics = self.collect_current_metrics().await?;
         
         if let Ok(mut optimizer) = self.dex_optimizer.lock() {
@@ -527,7 +568,7 @@ impl PerformanceOptimizer {
         let after_metrics = self.collect_current_metrics().await?;
         let improvement = self.calculate_improvement(&before_metrics, &after_metrics, "dex").await?;
 
-        Ok(OptimizationResult {
+        Ok(with_profile(capture, OptimizationResult {
             optimization_type: "dex".to_string(),
             timestamp: Utc::now(),
             before_metrics,
@@ -535,7 +576,7 @@ impl PerformanceOptimizer {
             improvement_percent: improvement,
             success: improvement > 0.0,
             details: "DEX order matching optimization, liquidity improvements, and trading engine performance".to_string(),
-        })
+        }))
     }
 
     /// Collect current system metrics
@@ -636,9 +677,28 @@ impl PerformanceOptimizer {
             optimization_history,
             performance_score: self.calculate_performance_score().await?,
             recommendations: self.generate_recommendations().await?,
+            top_allocation_sites: self.collect_allocation_sites(),
         })
     }
 
+    /// Dumps and summarizes the jemalloc heap; failures only cost the report its allocation sites
+    fn collect_allocation_sites(&self) -> Vec<AllocationSite> {
+        let path = std::env::temp_dir().join(format!("nock-heap-{}-{}.heap", std::process::id(), Utc::now().timestamp_millis()));
+        let report = match self.memory_optimizer.lock() {
+            Ok(optimizer) => optimizer.dump_heap_profile(&path).and_then(|_| parse_heap_profile(&path)),
+            Err(_) => return Vec::new(),
+        };
+        let _ = std::fs::remove_file(&path);
+
+        match report {
+            Ok(report) => report.top(REPORT_ALLOCATION_SITES).to_vec(),
+            Err(e) => {
+                debug!("Skipping allocation sites: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
     /// Calculate overall performance score
     async fn calculate_performance_score(&self) -> Result<f64> {
         let metrics = self.collect_current_metrics().await?;
@@ -688,6 +748,8 @@ pub struct PerformanceReport {
     pub optimization_history: Vec<OptimizationResult>,
     pub performance_score: f64,
     pub recommendations: Vec<String>,
+    /// Largest live allocation sites; empty unless jemalloc heap profiling is enabled
+    pub top_allocation_sites: Vec<AllocationSite>,
 }
 
 // Placeholder implementations for monitoring components
@@ -808,6 +870,13 @@ async fn main() -> Result<()> {
     info!("Starting Nockchain Performance Optimization Engine");
 
     let mut optimizer = PerformanceOptimizer::new().await?;
+
+    let network_optimizer = Arc::clone(&optimizer.network_optimizer);
+    tokio::spawn(async move {
+        if let Err(e) = serve_admin(network_optimizer).await {
+            error!("Admin API stopped: {}", e);
+        }
+    });
     
     // Start monitoring
     optimizer.start_monitoring().await?;
@@ -824,7 +893,7 @@ async fn main() -> Result<()> {
     info!("Optimizations performed: {}", optimization_results.len());
     
     for result in &optimization_results {
-        info!("  - {}: {:.1}% improvement", result.optimization_type, result.improvement_percent);
+        info!("  - {}: {:.1}% improvement", result.result.optimization_type, result.result.improvement_percent);
     }
     
     // Keep monitoring running
@@ -844,25 +913,107 @@ async fn main() -> Result<()> {
     }
 }
 
+/// Admin HTTP API on `OPTIMIZER_ADMIN_ADDR` (default 127.0.0.1:9095). Its listener is tuned
+/// with the network optimizer's TCP settings, which the API reports back. Prometheus
+/// metrics are served on `/metrics`, including the admin API's own per-endpoint latencies.
+/// Responses are compressed at the Brotli quality last measured on sampled payloads.
+async fn serve_admin(network_optimizer: Arc<Mutex<NetworkOptimizer>>) -> Result<()> {
+    let addr = std::env::var("OPTIMIZER_ADMIN_ADDR").unwrap_or_else(|_| "127.0.0.1:9095".to_string());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+
+    if let Ok(optimizer) = network_optimizer.lock() {
+        if let Err(e) = optimizer.tcp_tuner.tune_listener(&listener) {
+            warn!("Failed to tune admin listener: {}", e);
+        }
+    }
+
+    // A duplicate descriptor shares the socket, so options can be read while axum owns the listener
+    let socket = Arc::new(listener.as_fd().try_clone_to_owned()?);
+    let api_optimizer = ApiPerformanceOptimizer::new().await?;
+    api_optimizer.spawn_latency_publisher();
+    let compression = Arc::new(AdaptiveCompression::new(AdaptiveCompressionConfig::from_env()));
+    spawn_quality_tuner(&compression, Duration::from_secs(300));
+    let app = Router::new()
+        .route("/api/v1/admin/network/tcp-settings", get(tcp_settings)
+            .layer(cache_when_degraded(RouteCachePolicy { max_entries: 1, ttl: Duration::from_secs(5) })))
+        .route("/metrics", get(prometheus_metrics))
+        .layer(compression.layer())
+        .layer(api_optimizer.latency_layer())
+        .with_state(socket);
+
+    info!("Admin API listening on {}", addr);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn tcp_settings(State(socket): State<Arc<OwnedFd>>) -> Result<Json<TcpTuningConfig>, (StatusCode, String)> {
+    read_tcp_options(socket.as_ref())
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Everything registered with the default Prometheus registry, in text exposition format
+async fn prometheus_metrics() -> Result<String, (StatusCode, String)> {
+    use prometheus::Encoder;
+
+    let mut buffer = Vec::new();
+    prometheus::TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    String::from_utf8(buffer).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
 // Additional placeholder implementations for optimizers
 impl DatabaseOptimizer {
     pub async fn new() -> Result<Self> {
+        let config = PoolTuningConfig::from_env();
+        let pool = match std::env::var("DATABASE_URL") {
+            Ok(url) => Some(ResizablePgPool::connect_lazy(&url, config.min_connections)?),
+            Err(_) => {
+                warn!("DATABASE_URL not set, connection pool tuning disabled");
+                None
+            }
+        };
+
+        let connection_pool_optimizer = ConnectionPoolOptimizer::new(config);
+        if let Err(e) = connection_pool_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register connection pool metrics: {}", e);
+        }
+
         Ok(Self {
-            query_analyzer: QueryAnalyzer::new(),
-            connection_pool_optimizer: ConnectionPoolOptimizer::new(),
+            query_analyzer: QueryAnalyzer::new(QueryAnalyzerConfig::from_env()),
+            connection_pool_optimizer,
+            pool,
             index_optimizer: IndexOptimizer::new(),
             cache_optimizer: CacheOptimizer::new(),
-            slow_query_detector: SlowQueryDetector::new(),
+            slow_query_detector: SlowQueryDetector::from_env(),
             database_health_monitor: DatabaseHealthMonitor::new(),
         })
     }
 
     pub async fn monitor_and_optimize(&mut self) -> Result<()> {
         debug!("Database monitoring and optimization cycle");
+        if let Some(pool) = &self.pool {
+            self.connection_pool_optimizer.tune(pool).await?;
+            self.query_analyzer.analyze_slow_queries(&pool.pool(), &mut self.slow_query_detector).await?;
+        }
         sleep(Duration::from_millis(100)).await;
         Ok(())
     }
 
+    /// Reports a completed query; slow ones are explained on the next monitoring cycle
+    pub fn record_query(&mut self, sql: &str, params: Vec<QueryParam>, duration_ms: f64) {
+        if self.slow_query_detector.record(sql, params, duration_ms) {
+            debug!("Queued slow query ({:.0}ms) for analysis", duration_ms);
+        }
+    }
+
+    /// Checks out a connection, feeding its wait time into pool tuning
+    pub async fn acquire_connection(&self) -> Result<PoolConnection<Postgres>> {
+        let pool = self.pool.as_ref().ok_or_else(|| Error::msg("DATABASE_URL not set"))?;
+        self.connection_pool_optimizer.acquire(pool).await
+    }
+
     pub async fn optimize_slow_queries(&mut self) -> Result<()> {
         debug!("Optimizing slow queries");
         sleep(Duration::from_millis(50)).await;
@@ -889,18 +1040,12 @@ impl DatabaseOptimizer {
 }
 
 // Implement all other optimizer placeholders
-#[derive(Debug)] pub struct QueryAnalyzer;
-#[derive(Debug)] pub struct ConnectionPoolOptimizer;
 #[derive(Debug)] pub struct IndexOptimizer;
 #[derive(Debug)] pub struct CacheOptimizer;
-#[derive(Debug)] pub struct SlowQueryDetector;
 #[derive(Debug)] pub struct DatabaseHealthMonitor;
 
-impl QueryAnalyzer { pub fn new() -> Self { Self } }
-impl ConnectionPoolOptimizer { pub fn new() -> Self { Self } }
 impl IndexOptimizer { pub fn new() -> Self { Self } }
 impl CacheOptimizer { pub fn new() -> Self { Self } }
-impl SlowQueryDetector { pub fn new() -> Self { Self } }
 impl DatabaseHealthMonitor { pub fn new() -> Self { Self } }
 
 // Include optimizer modules
@@ -908,11 +1053,47 @@ mod database_optimizer;
 mod api_optimizer;
 mod memory_optimizer;
 mod network_optimizer;
+mod profiling;
+mod pool_tuning;
+mod heap_profile;
+mod tcp_tuning;
+mod query_analysis;
+mod difficulty;
+mod hashrate_tuning;
+mod energy_efficiency;
+mod order_matching;
+mod liquidity_rebalancing;
+mod price_feed;
+mod arbitrage;
+mod zk_batching;
+mod optimistic_settlement;
+mod table_bloat;
+mod endpoint_latency;
+mod adaptive_compression;
 
 use database_optimizer::DatabaseOptimizationEngine;
 use api_optimizer::ApiPerformanceOptimizer;
 use memory_optimizer::MemoryOptimizationEngine;
 use network_optimizer::NetworkOptimizationEngine;
+use profiling::{FlamegraphProfiler, ProfileCapture};
+use pool_tuning::{ConnectionPoolOptimizer, PoolTuningConfig, ResizablePgPool};
+use sqlx::{pool::PoolConnection, Postgres};
+use heap_profile::{parse_heap_profile, AllocationSite, REPORT_ALLOCATION_SITES};
+use tcp_tuning::{apply_tcp_tuning, read_tcp_options, TcpTuningConfig};
+use query_analysis::{QueryAnalyzer, QueryAnalyzerConfig, QueryParam, SlowQueryDetector};
+use difficulty::DifficultyPredictor;
+use hashrate_tuning::{HashrateOptimizer, HashrateTuningConfig};
+use energy_efficiency::EnergyEfficiencyOptimizer;
+use order_matching::OrderMatchingOptimizer;
+use liquidity_rebalancing::LiquidityOptimizer;
+use price_feed::{PriceFeedOptimizer, PriceFeedUpdate};
+use arbitrage::ArbitrageOptimizer;
+use zk_batching::{ZkProofBatchConfig, ZkProofBatcher};
+use optimistic_settlement::CrossChainOptimizer;
+use endpoint_latency::{cache_when_degraded, RouteCachePolicy};
+use adaptive_compression::{spawn_quality_tuner, AdaptiveCompression, AdaptiveCompressionConfig};
+use std::os::fd::{AsFd, OwnedFd};
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
 
 impl ApiOptimizer {
     pub async fn new() -> Result<Self> {
@@ -989,6 +1170,11 @@ impl MemoryOptimizer {
         Ok(())
     }
 
+    /// Writes a jemalloc heap profile to `output_path` for `parse_heap_profile`
+    pub fn dump_heap_profile(&self, output_path: &Path) -> Result<()> {
+        heap_profile::dump_heap_profile(output_path)
+    }
+
     pub async fn optimize_allocations(&mut self) -> Result<()> {
         debug!("Optimizing memory allocations");
         sleep(Duration::from_millis(40)).await;
@@ -1071,35 +1257,58 @@ impl NetworkOptimizer {
     }
 }
 
+/// Socket-level tuning for listeners opened by the optimizer
+#[derive(Debug)]
+pub struct TcpTuner {
+    pub config: TcpTuningConfig,
+}
+
+impl TcpTuner {
+    pub fn new() -> Self {
+        Self { config: TcpTuningConfig::from_env() }
+    }
+
+    pub fn tune_listener<S: AsFd>(&self, listener: &S) -> Result<()> {
+        apply_tcp_tuning(listener, &self.config)
+    }
+}
+
 #[derive(Debug)] pub struct BandwidthMonitor;
 #[derive(Debug)] pub struct ConnectionOptimizer;
 #[derive(Debug)] pub struct ProtocolOptimizer;
 #[derive(Debug)] pub struct PacketAnalyzer;
-#[derive(Debug)] pub struct TcpTuner;
 #[derive(Debug)] pub struct WebSocketOptimizer;
 
 impl BandwidthMonitor { pub fn new() -> Self { Self } }
 impl ConnectionOptimizer { pub fn new() -> Self { Self } }
 impl ProtocolOptimizer { pub fn new() -> Self { Self } }
 impl PacketAnalyzer { pub fn new() -> Self { Self } }
-impl TcpTuner { pub fn new() -> Self { Self } }
 impl WebSocketOptimizer { pub fn new() -> Self { Self } }
 
 impl MiningPerformanceOptimizer {
     pub async fn new() -> Result<Self> {
+        let hashrate_optimizer = HashrateOptimizer::new(HashrateTuningConfig::from_env());
+        if let Err(e) = hashrate_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register hashrate metrics: {}", e);
+        }
+        let energy_efficiency_optimizer = EnergyEfficiencyOptimizer::from_env();
+        if let Err(e) = energy_efficiency_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register energy efficiency metrics: {}", e);
+        }
+
         Ok(Self {
-            hashrate_optimizer: HashrateOptimizer::new(),
+            hashrate_optimizer,
             proof_power_optimizer: ProofPowerOptimizer::new(),
             eon_transition_optimizer: EonTransitionOptimizer::new(),
             difficulty_predictor: DifficultyPredictor::new(),
-            energy_efficiency_optimizer: EnergyEfficiencyOptimizer::new(),
+            energy_efficiency_optimizer,
             thermal_optimizer: ThermalOptimizer::new(),
         })
     }
 
     pub async fn optimize_hashrate(&mut self) -> Result<()> {
         debug!("Optimizing mining hashrate");
-        sleep(Duration::from_millis(40)).await;
+        self.hashrate_optimizer.tune();
         Ok(())
     }
 
@@ -1117,30 +1326,30 @@ impl MiningPerformanceOptimizer {
 
     pub async fn optimize_energy_efficiency(&mut self) -> Result<()> {
         debug!("Optimizing energy efficiency");
-        sleep(Duration::from_millis(45)).await;
+        let Some(observation) = self.hashrate_optimizer.latest_observation() else {
+            return Ok(());
+        };
+        if let Some(metrics) = self.energy_efficiency_optimizer.sample(observation)? {
+            info!("Mining power {:.1}W, {:.2} J/share; energy-optimal thread count is {}",
+                  metrics.watts_current, metrics.joules_per_share, metrics.optimal_thread_count);
+        }
         Ok(())
     }
 }
 
-#[derive(Debug)] pub struct HashrateOptimizer;
 #[derive(Debug)] pub struct ProofPowerOptimizer;
 #[derive(Debug)] pub struct EonTransitionOptimizer;
-#[derive(Debug)] pub struct DifficultyPredictor;
-#[derive(Debug)] pub struct EnergyEfficiencyOptimizer;
 #[derive(Debug)] pub struct ThermalOptimizer;
 
-impl HashrateOptimizer { pub fn new() -> Self { Self } }
 impl ProofPowerOptimizer { pub fn new() -> Self { Self } }
 impl EonTransitionOptimizer { pub fn new() -> Self { Self } }
-impl DifficultyPredictor { pub fn new() -> Self { Self } }
-impl EnergyEfficiencyOptimizer { pub fn new() -> Self { Self } }
 impl ThermalOptimizer { pub fn new() -> Self { Self } }
 
 impl BridgePerformanceOptimizer {
     pub async fn new() -> Result<Self> {
         Ok(Self {
-            zk_proof_optimizer: ZkProofOptimizer::new(),
-            cross_chain_optimizer: CrossChainOptimizer::new(),
+            zk_proof_optimizer: ZkProofBatcher::new(ZkProofBatchConfig::from_env()),
+            cross_chain_optimizer: CrossChainOptimizer::from_env().await?,
             settlement_optimizer: SettlementOptimizer::new(),
             validation_optimizer: ValidationOptimizer::new(),
             throughput_optimizer: ThroughputOptimizer::new(),
@@ -1148,15 +1357,25 @@ impl BridgePerformanceOptimizer {
         })
     }
 
+    /// Proves queued bridge transactions in aggregated batches. Proving is CPU-bound, so the
+    /// worker thread is handed over to it; this needs the multi-threaded runtime.
     pub async fn optimize_zk_proofs(&mut self) -> Result<()> {
-        debug!("Optimizing ZK proof generation");
-        sleep(Duration::from_millis(70)).await;
+        debug!("Optimizing ZK proof generation ({} transactions pending)", self.zk_proof_optimizer.pending());
+        let batches = tokio::task::block_in_place(|| self.zk_proof_optimizer.prove_ready_batches())?;
+        if batches > 0 {
+            info!("{} aggregated proofs ready for on-chain verification", self.zk_proof_optimizer.ready.len());
+        }
         Ok(())
     }
 
+    /// Checks optimistically released withdrawals against Solana and closes expired fraud-proof windows
     pub async fn optimize_cross_chain(&mut self) -> Result<()> {
-        debug!("Optimizing cross-chain operations");
-        sleep(Duration::from_millis(55)).await;
+        let sweep = self.cross_chain_optimizer.sweep().await?;
+        debug!("Checked {} optimistic settlements, {} settled", sweep.checked, sweep.settled);
+        if sweep.disputed > 0 {
+            error!("{} bridge withdrawals disputed, emergency pause {}", sweep.disputed,
+                   if sweep.pause_requested { "requested" } else { "request failed" });
+        }
         Ok(())
     }
 
@@ -1173,15 +1392,11 @@ impl BridgePerformanceOptimizer {
     }
 }
 
-#[derive(Debug)] pub struct ZkProofOptimizer;
-#[derive(Debug)] pub struct CrossChainOptimizer;
 #[derive(Debug)] pub struct SettlementOptimizer;
 #[derive(Debug)] pub struct ValidationOptimizer;
 #[derive(Debug)] pub struct ThroughputOptimizer;
 #[derive(Debug)] pub struct SecurityOptimizer;
 
-impl ZkProofOptimizer { pub fn new() -> Self { Self } }
-impl CrossChainOptimizer { pub fn new() -> Self { Self } }
 impl SettlementOptimizer { pub fn new() -> Self { Self } }
 impl ValidationOptimizer { pub fn new() -> Self { Self } }
 impl ThroughputOptimizer { pub fn new() -> Self { Self } }
@@ -1189,25 +1404,50 @@ impl SecurityOptimizer { pub fn new() -> Self { Self } }
 
 impl DexPerformanceOptimizer {
     pub async fn new() -> Result<Self> {
+        let order_matching_optimizer = OrderMatchingOptimizer::from_env()?;
+        if let Err(e) = order_matching_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register order matching metrics: {}", e);
+        }
+        let liquidity_optimizer = Arc::new(LiquidityOptimizer::from_env());
+        if let Err(e) = liquidity_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register liquidity metrics: {}", e);
+        }
+        liquidity_optimizer.spawn_monitor();
+        let price_feed_optimizer = PriceFeedOptimizer::from_env();
+        if let Err(e) = price_feed_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register price feed metrics: {}", e);
+        }
+        let arbitrage_optimizer = Arc::new(ArbitrageOptimizer::from_env());
+        if let Err(e) = arbitrage_optimizer.register_metrics(prometheus::default_registry()) {
+            warn!("Failed to register arbitrage metrics: {}", e);
+        }
+        arbitrage_optimizer.spawn_detector();
+
         Ok(Self {
-            order_matching_optimizer: OrderMatchingOptimizer::new(),
-            liquidity_optimizer: LiquidityOptimizer::new(),
-            arbitrage_optimizer: ArbitrageOptimizer::new(),
+            order_matching_optimizer,
+            liquidity_optimizer,
+            arbitrage_optimizer,
             market_making_optimizer: MarketMakingOptimizer::new(),
-            price_feed_optimizer: PriceFeedOptimizer::new(),
+            price_feed_optimizer,
             trading_engine_optimizer: TradingEngineOptimizer::new(),
         })
     }
 
     pub async fn optimize_order_matching(&mut self) -> Result<()> {
         debug!("Optimizing order matching engine");
-        sleep(Duration::from_millis(40)).await;
+        let latency = self.order_matching_optimizer.publish_metrics();
+        debug!("Order matching p99 latency {:?} over {} commands",
+               latency.p99, self.order_matching_optimizer.commands_processed());
         Ok(())
     }
 
     pub async fn optimize_liquidity(&mut self) -> Result<()> {
         debug!("Optimizing liquidity management");
-        sleep(Duration::from_millis(35)).await;
+        // The monitor rebalances on its own schedule; this only reports on it
+        if let Some(imbalance) = *self.liquidity_optimizer.last_imbalance.lock().unwrap() {
+            debug!("NOCK/wNOCK pool imbalance {:.2}%, {:.8} NOCK spent on rebalancing fees",
+                   imbalance * 100.0, self.liquidity_optimizer.rebalancing_fee_total());
+        }
         Ok(())
     }
 
@@ -1219,23 +1459,24 @@ impl DexPerformanceOptimizer {
 
     pub async fn optimize_price_feeds(&mut self) -> Result<()> {
         debug!("Optimizing price feed updates");
-        sleep(Duration::from_millis(30)).await;
+        match self.price_feed_optimizer.refresh().await {
+            Ok(PriceFeedUpdate::Price(price)) => {
+                debug!("NOCK/USD {} from {} sources", price.price, price.sources.len());
+                // wNOCK is redeemable 1:1, so it prices the arbitrage quote token too
+                self.arbitrage_optimizer.set_quote_usd_price(price.price);
+            }
+            Ok(PriceFeedUpdate::Diverged(alert)) => warn!("NOCK/USD price feeds diverged by {:.2}%", alert.divergence_percent),
+            Err(e) => warn!("Failed to refresh NOCK/USD price: {:#}", e),
+        }
+        self.order_matching_optimizer.set_trading_halted(self.price_feed_optimizer.trading_frozen());
         Ok(())
     }
 }
 
-#[derive(Debug)] pub struct OrderMatchingOptimizer;
-#[derive(Debug)] pub struct LiquidityOptimizer;
-#[derive(Debug)] pub struct ArbitrageOptimizer;
 #[derive(Debug)] pub struct MarketMakingOptimizer;
-#[derive(Debug)] pub struct PriceFeedOptimizer;
 #[derive(Debug)] pub struct TradingEngineOptimizer;
 
-impl OrderMatchingOptimizer { pub fn new() -> Self { Self } }
-impl LiquidityOptimizer { pub fn new() -> Self { Self } }
-impl ArbitrageOptimizer { pub fn new() -> Self { Self } }
 impl MarketMakingOptimizer { pub fn new() -> Self { Self } }
-impl PriceFeedOptimizer { pub fn new() -> Self { Self } }
 impl TradingEngineOptimizer { pub fn new() -> Self { Self } }
 
 impl OptimizationScheduler {
@@ -1288,4 +1529,41 @@ impl Default for OptimizationIntervals {
             network_optimization_minutes: 20,
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[tokio::test]
+    async fn test_profiling_writes_flamegraph() {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("NOCK_PROFILING", "1");
+        std::env::set_var("NOCK_PROFILING_DIR", dir.path());
+        let mut optimizer = PerformanceOptimizer::new().await.unwrap();
+        std::env::remove_var("NOCK_PROFILING");
+        assert!(optimizer.profiler.is_some());
+
+        // The optimizers mostly sleep, so keep a core busy for the profiler to sample
+        let running = Arc::new(AtomicBool::new(true));
+        let spinner = std::thread::spawn({
+            let running = Arc::clone(&running);
+            move || {
+                let mut state = 1u64;
+                while running.load(Ordering::Relaxed) {
+                    state = std::hint::black_box(state.wrapping_mul(6364136223846793005).wrapping_add(1));
+                }
+            }
+        });
+
+        let profiled = optimizer.optimize_api_performance().await.unwrap();
+        running.store(false, Ordering::Relaxed);
+        spinner.join().unwrap();
+
+        let path = profiled.flamegraph.expect("flamegraph should be written");
+        assert_eq!(path.parent(), Some(dir.path()));
+        assert_eq!(path.extension().unwrap(), "svg");
+        assert!(std::fs::read_to_string(&path).unwrap().contains("<svg"));
+        assert!(profiled.result.details.contains(path.file_name().unwrap().to_str().unwrap()));
+    }
+}
@@ -9,6 +9,8 @@ use log::{info, warn, error, debug};
 use anyhow::{Result, Error};
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
 
 #[cfg(feature = "jemalloc")]
 use tikv_jemallocator::Jemalloc;
@@ -24,6 +26,8 @@ pub struct MemoryOptimizationEngine {
     pub memory_pool_manager: MemoryPoolManager,
     pub cache_memory_optimizer: CacheMemoryOptimizer,
     pub memory_monitoring_system: MemoryMonitoringSystem,
+    /// Per-proof arenas for ZK proof generation
+    pub proof_arena_allocator: Arc<ProofArenaAllocator>,
 }
 
 /// Memory profiling and analysis
@@ -149,6 +153,7 @@ impl MemoryOptimizationEngine {
             memory_pool_manager: MemoryPoolManager::new().await?,
             cache_memory_optimizer: CacheMemoryOptimizer::new().await?,
             memory_monitoring_system: MemoryMonitoringSystem::new().await?,
+            proof_arena_allocator: Arc::new(ProofArenaAllocator::from_env()),
         })
     }
 
@@ -1033,4 +1038,219 @@ impl CacheMemoryOptimizer {
 
 impl MemoryMonitoringSystem {
     pub async fn new() -> Result<Self> { Ok(Self) }
-}
\ No newline at end of file
+}
+/// Default size of the arena backing one proof generation
+pub const DEFAULT_PROOF_ARENA_BYTES: usize = 4 * 1024 * 1024;
+
+/// Gives every proof generation its own bump arena. Intermediate values are allocated in
+/// the arena and freed together when the arena is dropped, right after the proof has been
+/// serialised; only the serialised bytes outlive the call.
+#[derive(Debug)]
+pub struct ProofArenaAllocator {
+    /// Bytes reserved up front for each arena
+    pub arena_capacity: usize,
+    proofs_generated: AtomicU64,
+    peak_arena_bytes: AtomicU64,
+    /// Release-build generations that outgrew their arena and had to grow it
+    arena_overflows: AtomicU64,
+}
+
+/// Allocation handle passed to a proof generation
+pub struct ProofArena<'bump> {
+    bump: &'bump Bump,
+    capacity: usize,
+    initial_bytes: usize,
+}
+
+impl<'bump> ProofArena<'bump> {
+    pub fn alloc<T>(&self, value: T) -> &'bump mut T {
+        self.check_capacity(std::mem::size_of::<T>());
+        self.bump.alloc(value)
+    }
+
+    pub fn alloc_slice_copy<T: Copy>(&self, src: &[T]) -> &'bump mut [T] {
+        self.check_capacity(std::mem::size_of_val(src));
+        self.bump.alloc_slice_copy(src)
+    }
+
+    pub fn alloc_slice_fill_with<T, F: FnMut(usize) -> T>(&self, len: usize, f: F) -> &'bump mut [T] {
+        self.check_capacity(std::mem::size_of::<T>().saturating_mul(len));
+        self.bump.alloc_slice_fill_with(len, f)
+    }
+
+    /// Growable vector inside the arena
+    pub fn vec_with_capacity<T>(&self, capacity: usize) -> BumpVec<'bump, T> {
+        self.check_capacity(std::mem::size_of::<T>().saturating_mul(capacity));
+        BumpVec::with_capacity_in(capacity, self.bump)
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.bump.allocated_bytes()
+    }
+
+    /// Whether the arena had to allocate beyond its reserved chunk
+    fn overflowed(&self) -> bool {
+        self.bump.allocated_bytes() > self.initial_bytes
+    }
+
+    /// Debug builds panic as soon as a proof outgrows its arena, so the capacity gets
+    /// raised instead of silently falling back to extra chunks
+    fn check_capacity(&self, bytes: usize) {
+        if cfg!(debug_assertions) && (self.overflowed() || bytes > self.bump.chunk_capacity()) {
+            panic!("Proof arena exhausted: {}-byte allocation with {} of {} bytes left; raise PROOF_ARENA_BYTES",
+                   bytes, self.bump.chunk_capacity(), self.capacity);
+        }
+    }
+}
+
+impl ProofArenaAllocator {
+    pub fn new(arena_capacity: usize) -> Self {
+        Self {
+            arena_capacity,
+            proofs_generated: AtomicU64::new(0),
+            peak_arena_bytes: AtomicU64::new(0),
+            arena_overflows: AtomicU64::new(0),
+        }
+    }
+
+    /// Reads `PROOF_ARENA_BYTES`, falling back to `DEFAULT_PROOF_ARENA_BYTES`
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("PROOF_ARENA_BYTES").ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_PROOF_ARENA_BYTES);
+        Self::new(capacity)
+    }
+
+    /// Runs `generate` with a fresh arena and drops the arena once it returns the
+    /// serialised proof. The result cannot borrow from the arena.
+    pub fn generate<R>(&self, generate: impl for<'bump> FnOnce(&ProofArena<'bump>) -> R) -> R {
+        let bump = Bump::with_capacity(self.arena_capacity);
+        let arena = ProofArena { bump: &bump, capacity: self.arena_capacity, initial_bytes: bump.allocated_bytes() };
+
+        let proof = generate(&arena);
+
+        if arena.overflowed() {
+            // Collections growing in place bypass the per-allocation check
+            if cfg!(debug_assertions) {
+                panic!("Proof arena of {} bytes grew to {} bytes; raise PROOF_ARENA_BYTES",
+                       self.arena_capacity, arena.allocated_bytes());
+            }
+            self.arena_overflows.fetch_add(1, Ordering::Relaxed);
+            warn!("Proof arena of {} bytes grew to {} bytes; consider raising PROOF_ARENA_BYTES",
+                  self.arena_capacity, arena.allocated_bytes());
+        }
+        self.peak_arena_bytes.fetch_max(arena.allocated_bytes() as u64, Ordering::Relaxed);
+        self.proofs_generated.fetch_add(1, Ordering::Relaxed);
+        proof
+    }
+
+    pub fn statistics(&self) -> ProofArenaStatistics {
+        ProofArenaStatistics {
+            arena_capacity_bytes: self.arena_capacity as u64,
+            proofs_generated: self.proofs_generated.load(Ordering::Relaxed),
+            peak_arena_bytes: self.peak_arena_bytes.load(Ordering::Relaxed),
+            arena_overflows: self.arena_overflows.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ProofArenaStatistics {
+    pub arena_capacity_bytes: u64,
+    pub proofs_generated: u64,
+    pub peak_arena_bytes: u64,
+    pub arena_overflows: u64,
+}
+
+/// Rows in the synthetic proof's execution trace
+pub const SYNTHETIC_TRACE_ROWS: usize = 64;
+
+/// Columns per synthetic trace row
+pub const SYNTHETIC_TRACE_WIDTH: usize = 16;
+
+fn mix(a: u64, b: u64) -> u64 {
+    (a ^ b.rotate_left(17)).wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(29)
+}
+
+fn serialise_proof(root: u64, openings: impl Iterator<Item = u64>) -> Vec<u8> {
+    let mut proof = root.to_le_bytes().to_vec();
+    for opening in openings {
+        proof.extend_from_slice(&opening.to_le_bytes());
+    }
+    proof
+}
+
+/// Proof-shaped workload with the allocation pattern of real proving: one short-lived
+/// buffer per trace row, then Merkle layers over the row commitments. Intermediates live
+/// in `arena`; only the serialised proof is heap allocated.
+pub fn synthetic_proof_in_arena(arena: &ProofArena<'_>, seed: u64) -> Vec<u8> {
+    let mut commitments = arena.vec_with_capacity(SYNTHETIC_TRACE_ROWS);
+    for row in 0..SYNTHETIC_TRACE_ROWS {
+        let cells = arena.alloc_slice_fill_with(SYNTHETIC_TRACE_WIDTH, |column| mix(seed, (row * SYNTHETIC_TRACE_WIDTH + column) as u64));
+        commitments.push(cells.iter().fold(seed, |acc, &cell| mix(acc, cell)));
+    }
+
+    let mut layer: &[u64] = arena.alloc_slice_copy(&commitments);
+    let openings = arena.alloc_slice_copy(&layer[..8]);
+    while layer.len() > 1 {
+        layer = arena.alloc_slice_fill_with(layer.len() / 2, |i| mix(layer[2 * i], layer[2 * i + 1]));
+    }
+    serialise_proof(layer[0], openings.iter().copied())
+}
+
+/// The same workload as `synthetic_proof_in_arena` on the global allocator
+pub fn synthetic_proof_on_heap(seed: u64) -> Vec<u8> {
+    let mut commitments = Vec::with_capacity(SYNTHETIC_TRACE_ROWS);
+    for row in 0..SYNTHETIC_TRACE_ROWS {
+        let cells: Vec<u64> = (0..SYNTHETIC_TRACE_WIDTH).map(|column| mix(seed, (row * SYNTHETIC_TRACE_WIDTH + column) as u64)).collect();
+        commitments.push(cells.iter().fold(seed, |acc, &cell| mix(acc, cell)));
+    }
+
+    let openings = commitments[..8].to_vec();
+    let mut layer = commitments;
+    while layer.len() > 1 {
+        layer = (0..layer.len() / 2).map(|i| mix(layer[2 * i], layer[2 * i + 1])).collect();
+    }
+    serialise_proof(layer[0], openings.into_iter())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arena_and_heap_proofs_match() {
+        let allocator = ProofArenaAllocator::new(DEFAULT_PROOF_ARENA_BYTES);
+        for seed in 0..10 {
+            let proof = allocator.generate(|arena| synthetic_proof_in_arena(arena, seed));
+            assert_eq!(proof, synthetic_proof_on_heap(seed));
+            assert_eq!(proof.len(), 8 * 9);
+        }
+
+        let stats = allocator.statistics();
+        assert_eq!(stats.proofs_generated, 10);
+        assert!(stats.peak_arena_bytes > 0);
+        assert_eq!(stats.arena_overflows, 0);
+    }
+
+    #[test]
+    fn test_each_generation_gets_a_fresh_arena() {
+        let allocator = ProofArenaAllocator::new(64 * 1024);
+        let first = allocator.generate(|arena| arena.allocated_bytes());
+        allocator.generate(|arena| {
+            arena.alloc_slice_fill_with(1024, |i| i as u64);
+        });
+        let third = allocator.generate(|arena| arena.allocated_bytes());
+        assert_eq!(first, third);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "raise PROOF_ARENA_BYTES")]
+    fn test_exceeding_arena_panics_in_debug() {
+        let allocator = ProofArenaAllocator::new(1024);
+        allocator.generate(|arena| {
+            arena.alloc_slice_fill_with(4096, |i| i as u8);
+        });
+    }
+}
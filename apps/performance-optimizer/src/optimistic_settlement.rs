@@ -0,0 +1,819 @@
+// Optimistic Withdrawal Settlement
+// Releases NOCK as soon as a Solana burn is seen, then watches the burn through a fraud-proof window
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use base64::Engine;
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use std::time::Duration;
+
+/// Nockchain blocks during which a released withdrawal can still be disputed
+pub const FRAUD_PROOF_WINDOW_BLOCKS: u64 = 7200;
+
+/// Anchor event discriminator, `sha256("event:WithdrawEvent")[..8]`
+const WITHDRAW_EVENT_DISCRIMINATOR: [u8; 8] = [22, 9, 133, 26, 160, 44, 71, 192];
+
+/// user, amount, fee, net_amount, nock_address, nonce, timestamp
+const WITHDRAW_EVENT_LEN: usize = 8 + 32 + 8 + 8 + 8 + 32 + 8 + 8;
+
+const RPC_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimisticSettlementConfig {
+    pub nockchain_rpc_url: String,
+    pub solana_rpc_url: String,
+    pub bridge_program_id: String,
+    pub bridge_validator_url: String,
+    pub window_blocks: u64,
+}
+
+impl Default for OptimisticSettlementConfig {
+    fn default() -> Self {
+        Self {
+            nockchain_rpc_url: "http://127.0.0.1:9090".to_string(),
+            solana_rpc_url: "http://127.0.0.1:8899".to_string(),
+            bridge_program_id: "BridGE1111111111111111111111111111111111111111".to_string(),
+            bridge_validator_url: "http://127.0.0.1:3000".to_string(),
+            window_blocks: FRAUD_PROOF_WINDOW_BLOCKS,
+        }
+    }
+}
+
+impl OptimisticSettlementConfig {
+    /// Reads `NOCKCHAIN_RPC_URL`, `SOLANA_RPC_URL`, `BRIDGE_PROGRAM_ID`, `BRIDGE_VALIDATOR_URL`
+    /// and `FRAUD_PROOF_WINDOW_BLOCKS`, falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            nockchain_rpc_url: env_or("NOCKCHAIN_RPC_URL", defaults.nockchain_rpc_url),
+            solana_rpc_url: env_or("SOLANA_RPC_URL", defaults.solana_rpc_url),
+            bridge_program_id: env_or("BRIDGE_PROGRAM_ID", defaults.bridge_program_id),
+            bridge_validator_url: env_or("BRIDGE_VALIDATOR_URL", defaults.bridge_validator_url),
+            window_blocks: env_or("FRAUD_PROOF_WINDOW_BLOCKS", defaults.window_blocks),
+        }
+    }
+}
+
+/// A wNOCK burn seen on Solana, before it is finalized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawalClaim {
+    pub solana_signature: String,
+    pub nonce: u64,
+    /// NOCK released on Nockchain, after the bridge fee
+    pub net_amount: u64,
+    pub nock_address: [u8; 32],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingSettlement {
+    pub claim: WithdrawalClaim,
+    pub release_tx: String,
+    pub released_at_height: u64,
+    pub challenge_deadline: u64,
+    /// Set once the burn is finalized on Solana and matches the claim; nothing can contradict it after that
+    pub burn_finalized: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisputedTransaction {
+    pub settlement: PendingSettlement,
+    pub reason: String,
+    pub detected_at_height: u64,
+}
+
+/// WithdrawEvent fields the claim is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BurnRecord {
+    pub net_amount: u64,
+    pub nock_address: [u8; 32],
+    pub nonce: u64,
+}
+
+/// What Solana says about a claimed burn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BurnEvidence {
+    Finalized(BurnRecord),
+    /// Seen at `confirmed` but not yet finalized
+    Unfinalized,
+    /// The transaction is unknown at every commitment level, e.g. dropped with a fork
+    Missing,
+    /// The transaction landed but failed, or did not emit a WithdrawEvent
+    Failed(String),
+}
+
+#[async_trait]
+pub trait BurnEvidenceSource: Send + Sync {
+    async fn burn_evidence(&self, signature: &str) -> Result<BurnEvidence>;
+}
+
+#[async_trait]
+pub trait NockchainReleaser: Send + Sync {
+    /// Pays the claim on Nockchain, returning the Nockchain transaction ID
+    async fn release(&self, claim: &WithdrawalClaim) -> Result<String>;
+    async fn tip_height(&self) -> Result<u64>;
+}
+
+#[async_trait]
+pub trait EmergencyPauser: Send + Sync {
+    /// Requests the validators' multi-sig emergency_pause, returning a proposal ID
+    async fn request_emergency_pause(&self, reason: &str) -> Result<String>;
+}
+
+#[async_trait]
+pub trait SettlementStore: Send + Sync {
+    async fn insert_pending(&self, settlement: &PendingSettlement) -> Result<()>;
+    async fn pending(&self) -> Result<Vec<PendingSettlement>>;
+    async fn mark_burn_finalized(&self, signature: &str) -> Result<()>;
+    /// Settles a withdrawal whose window closed without dispute
+    async fn remove_pending(&self, signature: &str) -> Result<()>;
+    /// Moves a pending settlement to the disputed set
+    async fn dispute(&self, disputed: &DisputedTransaction) -> Result<()>;
+    async fn disputed(&self) -> Result<Vec<DisputedTransaction>>;
+}
+
+/// Compares Solana's view of each pending burn with the claim that was paid
+pub struct FraudDetector {
+    source: Box<dyn BurnEvidenceSource>,
+}
+
+impl FraudDetector {
+    pub fn new(source: Box<dyn BurnEvidenceSource>) -> Self {
+        Self { source }
+    }
+
+    /// The reason the settlement is fraudulent, if Solana contradicts it
+    pub async fn check(&self, settlement: &PendingSettlement) -> Result<Option<String>> {
+        let claim = &settlement.claim;
+        Ok(match self.source.burn_evidence(&claim.solana_signature).await? {
+            BurnEvidence::Finalized(burn) if burn.net_amount != claim.net_amount => Some(format!(
+                "burn released {} NOCK but the finalized burn pays {}", claim.net_amount, burn.net_amount
+            )),
+            BurnEvidence::Finalized(burn) if burn.nock_address != claim.nock_address => {
+                Some("finalized burn pays a different Nockchain address".to_string())
+            }
+            BurnEvidence::Finalized(burn) if burn.nonce != claim.nonce => Some(format!(
+                "finalized burn has nonce {}, claim had {}", burn.nonce, claim.nonce
+            )),
+            BurnEvidence::Finalized(_) | BurnEvidence::Unfinalized => None,
+            BurnEvidence::Missing => Some("burn transaction no longer exists on Solana".to_string()),
+            BurnEvidence::Failed(reason) => Some(format!("burn transaction failed: {}", reason)),
+        })
+    }
+
+    pub async fn is_finalized(&self, signature: &str) -> Result<bool> {
+        Ok(matches!(self.source.burn_evidence(signature).await?, BurnEvidence::Finalized(_)))
+    }
+}
+
+/// Outcome of one pass over the pending settlements
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SettlementSweep {
+    pub checked: usize,
+    pub settled: usize,
+    pub disputed: usize,
+    pub pause_requested: bool,
+}
+
+/// Optimistic settlement for bridge withdrawals
+pub struct CrossChainOptimizer {
+    pub window_blocks: u64,
+    releaser: Box<dyn NockchainReleaser>,
+    store: Box<dyn SettlementStore>,
+    fraud_detector: FraudDetector,
+    pauser: Box<dyn EmergencyPauser>,
+    /// Proposal ID of the emergency pause already requested, so fraud found later in the
+    /// same incident does not request another
+    pub pause_proposal: Option<String>,
+}
+
+impl std::fmt::Debug for CrossChainOptimizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CrossChainOptimizer")
+            .field("window_blocks", &self.window_blocks)
+            .field("pause_proposal", &self.pause_proposal)
+            .finish()
+    }
+}
+
+impl CrossChainOptimizer {
+    pub fn new(
+        releaser: Box<dyn NockchainReleaser>,
+        store: Box<dyn SettlementStore>,
+        fraud_detector: FraudDetector,
+        pauser: Box<dyn EmergencyPauser>,
+    ) -> Self {
+        Self { window_blocks: FRAUD_PROOF_WINDOW_BLOCKS, releaser, store, fraud_detector, pauser, pause_proposal: None }
+    }
+
+    /// Talks to the configured RPC endpoints, keeping settlements in Postgres when
+    /// `DATABASE_URL` is set and in memory otherwise
+    pub async fn from_env() -> Result<Self> {
+        let config = OptimisticSettlementConfig::from_env();
+        let store: Box<dyn SettlementStore> = match std::env::var("DATABASE_URL") {
+            Ok(url) => Box::new(PgSettlementStore::new(PgPool::connect_lazy(&url)?).await?),
+            Err(_) => {
+                warn!("DATABASE_URL not set, pending settlements will not survive a restart");
+                Box::new(InMemorySettlementStore::default())
+            }
+        };
+
+        let mut optimizer = Self::new(
+            Box::new(NockchainRpcReleaser::new(config.nockchain_rpc_url)),
+            store,
+            FraudDetector::new(Box::new(SolanaRpcBurnSource::new(config.solana_rpc_url, config.bridge_program_id))),
+            Box::new(ValidatorPauseRequester::new(config.bridge_validator_url)),
+        );
+        optimizer.window_blocks = config.window_blocks;
+        Ok(optimizer)
+    }
+
+    /// Releases NOCK for a burn without waiting for its proof and opens its fraud-proof window
+    pub async fn settle_optimistically(&self, claim: WithdrawalClaim) -> Result<PendingSettlement> {
+        let height = self.releaser.tip_height().await?;
+        let release_tx = self.releaser.release(&claim).await
+            .with_context(|| format!("Failed to release withdrawal {}", claim.solana_signature))?;
+
+        let settlement = PendingSettlement {
+            claim,
+            release_tx,
+            released_at_height: height,
+            challenge_deadline: height + self.window_blocks,
+            burn_finalized: false,
+        };
+        self.store.insert_pending(&settlement).await?;
+        info!("Optimistically released {} NOCK for {} in {}, disputable until block {}",
+              settlement.claim.net_amount, settlement.claim.solana_signature,
+              settlement.release_tx, settlement.challenge_deadline);
+        Ok(settlement)
+    }
+
+    /// Checks every settlement still in its window, disputing contradicted ones and
+    /// settling those whose window has closed
+    pub async fn sweep(&mut self) -> Result<SettlementSweep> {
+        let height = self.releaser.tip_height().await?;
+        let mut sweep = SettlementSweep::default();
+
+        for settlement in self.store.pending().await? {
+            let signature = settlement.claim.solana_signature.clone();
+
+            if !settlement.burn_finalized {
+                sweep.checked += 1;
+                if let Some(reason) = self.fraud_detector.check(&settlement).await? {
+                    error!("Fraud detected on withdrawal {}: {}", signature, reason);
+                    self.store.dispute(&DisputedTransaction { settlement, reason: reason.clone(), detected_at_height: height }).await?;
+                    sweep.disputed += 1;
+                    self.request_pause(&reason).await;
+                    sweep.pause_requested |= self.pause_proposal.is_some();
+                    continue;
+                }
+                if self.fraud_detector.is_finalized(&signature).await? {
+                    debug!("Burn {} finalized", signature);
+                    self.store.mark_burn_finalized(&signature).await?;
+                }
+            }
+
+            if height >= settlement.challenge_deadline {
+                self.store.remove_pending(&signature).await?;
+                sweep.settled += 1;
+            }
+        }
+
+        Ok(sweep)
+    }
+
+    pub async fn disputed(&self) -> Result<Vec<DisputedTransaction>> {
+        self.store.disputed().await
+    }
+
+    async fn request_pause(&mut self, reason: &str) {
+        if self.pause_proposal.is_some() {
+            return;
+        }
+        match self.pauser.request_emergency_pause(reason).await {
+            Ok(proposal) => {
+                warn!("Requested bridge emergency pause, proposal {}", proposal);
+                self.pause_proposal = Some(proposal);
+            }
+            // The dispute is recorded either way; the next detection retries the pause
+            Err(e) => error!("Failed to request bridge emergency pause: {:#}", e),
+        }
+    }
+}
+
+/// Reads burns from Solana JSON-RPC, attributing WithdrawEvents to the bridge program
+pub struct SolanaRpcBurnSource {
+    rpc_url: String,
+    bridge_program_id: String,
+    client: reqwest::Client,
+}
+
+impl SolanaRpcBurnSource {
+    pub fn new(rpc_url: String, bridge_program_id: String) -> Self {
+        Self {
+            rpc_url,
+            bridge_program_id,
+            client: reqwest::Client::builder()
+                .timeout(RPC_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+
+    async fn get_transaction(&self, signature: &str, commitment: &str) -> Result<Option<Value>> {
+        let response: Value = self.client
+            .post(&self.rpc_url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "getTransaction",
+                "params": [signature, { "encoding": "json", "commitment": commitment, "maxSupportedTransactionVersion": 0 }],
+            }))
+            .send()
+            .await
+            .context("Failed to reach Solana RPC")?
+            .error_for_status()
+            .context("Solana RPC rejected getTransaction")?
+            .json()
+            .await
+            .context("Malformed getTransaction response")?;
+
+        if let Some(error) = response.get("error") {
+            bail!("Solana RPC error: {}", error);
+        }
+        Ok(response.get("result").filter(|result| !result.is_null()).cloned())
+    }
+}
+
+#[async_trait]
+impl BurnEvidenceSource for SolanaRpcBurnSource {
+    async fn burn_evidence(&self, signature: &str) -> Result<BurnEvidence> {
+        let Some(transaction) = self.get_transaction(signature, "finalized").await? else {
+            return Ok(match self.get_transaction(signature, "confirmed").await? {
+                Some(_) => BurnEvidence::Unfinalized,
+                None => BurnEvidence::Missing,
+            });
+        };
+
+        let meta = &transaction["meta"];
+        if !meta["err"].is_null() {
+            return Ok(BurnEvidence::Failed(meta["err"].to_string()));
+        }
+        let logs: Vec<&str> = meta["logMessages"].as_array()
+            .map(|logs| logs.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        Ok(match find_withdraw_event(&logs, &self.bridge_program_id) {
+            Some(burn) => BurnEvidence::Finalized(burn),
+            None => BurnEvidence::Failed("no WithdrawEvent from the bridge program".to_string()),
+        })
+    }
+}
+
+/// The WithdrawEvent emitted by `program_id` itself. Events logged while another program
+/// is executing, including one invoked by the bridge, are ignored so a look-alike
+/// program cannot forge one.
+pub fn find_withdraw_event(logs: &[&str], program_id: &str) -> Option<BurnRecord> {
+    let mut stack: Vec<&str> = Vec::new();
+    for line in logs {
+        let Some(rest) = line.strip_prefix("Program ") else { continue };
+        if let Some(data) = rest.strip_prefix("data: ") {
+            if stack.last() == Some(&program_id) {
+                if let Some(burn) = decode_withdraw_event(data) {
+                    return Some(burn);
+                }
+            }
+        } else if let Some((id, status)) = rest.split_once(' ') {
+            if status.starts_with("invoke [") {
+                stack.push(id);
+            } else if status == "success" || status.starts_with("failed") {
+                stack.pop();
+            }
+        }
+    }
+    None
+}
+
+fn decode_withdraw_event(data: &str) -> Option<BurnRecord> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(data.trim()).ok()?;
+    if bytes.len() != WITHDRAW_EVENT_LEN || bytes[..8] != WITHDRAW_EVENT_DISCRIMINATOR {
+        return None;
+    }
+    let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    // Skip the discriminator, user, amount and fee
+    let net_amount = u64_at(8 + 32 + 16);
+    let nock_address: [u8; 32] = bytes[8 + 32 + 24..8 + 32 + 56].try_into().ok()?;
+    let nonce = u64_at(8 + 32 + 56);
+    Some(BurnRecord { net_amount, nock_address, nonce })
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+    transaction_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentBlocksResponse {
+    blocks: Vec<crate::difficulty::BlockSummary>,
+}
+
+/// Pays withdrawals through the Nockchain bridge wallet RPC
+pub struct NockchainRpcReleaser {
+    rpc_url: String,
+    client: reqwest::Client,
+}
+
+impl NockchainRpcReleaser {
+    pub fn new(rpc_url: String) -> Self {
+        Self {
+            rpc_url,
+            client: reqwest::Client::builder()
+                .timeout(RPC_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+}
+
+#[async_trait]
+impl NockchainReleaser for NockchainRpcReleaser {
+    async fn release(&self, claim: &WithdrawalClaim) -> Result<String> {
+        let response: ReleaseResponse = self.client
+            .post(format!("{}/bridge/release", self.rpc_url.trim_end_matches('/')))
+            .json(claim)
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected release")?
+            .json()
+            .await
+            .context("Malformed release response")?;
+        Ok(response.transaction_hash)
+    }
+
+    async fn tip_height(&self) -> Result<u64> {
+        let response: RecentBlocksResponse = self.client
+            .get(format!("{}/blocks/recent", self.rpc_url.trim_end_matches('/')))
+            .query(&[("count", 1)])
+            .send()
+            .await
+            .context("Failed to reach Nockchain RPC")?
+            .error_for_status()
+            .context("Nockchain RPC rejected recent blocks request")?
+            .json()
+            .await
+            .context("Malformed recent blocks response")?;
+        response.blocks.iter().map(|block| block.height).max().context("Nockchain RPC returned no blocks")
+    }
+}
+
+/// Asks the bridge validators to co-sign emergency_pause
+pub struct ValidatorPauseRequester {
+    validator_url: String,
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct PauseProposalResponse {
+    proposal_id: String,
+}
+
+impl ValidatorPauseRequester {
+    pub fn new(validator_url: String) -> Self {
+        Self {
+            validator_url,
+            client: reqwest::Client::builder()
+                .timeout(RPC_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+        }
+    }
+}
+
+#[async_trait]
+impl EmergencyPauser for ValidatorPauseRequester {
+    async fn request_emergency_pause(&self, reason: &str) -> Result<String> {
+        let response: PauseProposalResponse = self.client
+            .post(format!("{}/governance/emergency-pause", self.validator_url.trim_end_matches('/')))
+            .json(&json!({ "reason": reason }))
+            .send()
+            .await
+            .context("Failed to reach bridge validator")?
+            .error_for_status()
+            .context("Bridge validator rejected emergency pause request")?
+            .json()
+            .await
+            .context("Malformed emergency pause response")?;
+        Ok(response.proposal_id)
+    }
+}
+
+/// Settlements kept in memory; they are lost on restart
+#[derive(Debug, Default)]
+pub struct InMemorySettlementStore {
+    pending: Mutex<HashMap<String, PendingSettlement>>,
+    disputed: Mutex<Vec<DisputedTransaction>>,
+}
+
+#[async_trait]
+impl SettlementStore for InMemorySettlementStore {
+    async fn insert_pending(&self, settlement: &PendingSettlement) -> Result<()> {
+        self.pending.lock().unwrap().insert(settlement.claim.solana_signature.clone(), settlement.clone());
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<Vec<PendingSettlement>> {
+        let mut pending: Vec<_> = self.pending.lock().unwrap().values().cloned().collect();
+        pending.sort_by_key(|settlement| settlement.released_at_height);
+        Ok(pending)
+    }
+
+    async fn mark_burn_finalized(&self, signature: &str) -> Result<()> {
+        if let Some(settlement) = self.pending.lock().unwrap().get_mut(signature) {
+            settlement.burn_finalized = true;
+        }
+        Ok(())
+    }
+
+    async fn remove_pending(&self, signature: &str) -> Result<()> {
+        self.pending.lock().unwrap().remove(signature);
+        Ok(())
+    }
+
+    async fn dispute(&self, disputed: &DisputedTransaction) -> Result<()> {
+        self.pending.lock().unwrap().remove(&disputed.settlement.claim.solana_signature);
+        self.disputed.lock().unwrap().push(disputed.clone());
+        Ok(())
+    }
+
+    async fn disputed(&self) -> Result<Vec<DisputedTransaction>> {
+        Ok(self.disputed.lock().unwrap().clone())
+    }
+}
+
+/// Settlements in the `pending_settlements` and `disputed_transactions` tables
+pub struct PgSettlementStore {
+    pool: PgPool,
+}
+
+impl PgSettlementStore {
+    pub async fn new(pool: PgPool) -> Result<Self> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS pending_settlements (
+                solana_signature TEXT PRIMARY KEY,
+                nonce BIGINT NOT NULL,
+                net_amount BIGINT NOT NULL,
+                nock_address BYTEA NOT NULL,
+                release_tx TEXT NOT NULL,
+                released_at_height BIGINT NOT NULL,
+                challenge_deadline BIGINT NOT NULL,
+                burn_finalized BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS disputed_transactions (
+                solana_signature TEXT PRIMARY KEY,
+                settlement JSONB NOT NULL,
+                reason TEXT NOT NULL,
+                detected_at_height BIGINT NOT NULL,
+                reviewed BOOLEAN NOT NULL DEFAULT FALSE
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl SettlementStore for PgSettlementStore {
+    async fn insert_pending(&self, settlement: &PendingSettlement) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO pending_settlements
+                (solana_signature, nonce, net_amount, nock_address, release_tx, released_at_height, challenge_deadline, burn_finalized)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(&settlement.claim.solana_signature)
+        .bind(settlement.claim.nonce as i64)
+        .bind(settlement.claim.net_amount as i64)
+        .bind(settlement.claim.nock_address.as_slice())
+        .bind(&settlement.release_tx)
+        .bind(settlement.released_at_height as i64)
+        .bind(settlement.challenge_deadline as i64)
+        .bind(settlement.burn_finalized)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn pending(&self) -> Result<Vec<PendingSettlement>> {
+        let rows = sqlx::query("SELECT * FROM pending_settlements ORDER BY released_at_height")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let nock_address: Vec<u8> = row.try_get("nock_address")?;
+                Ok(PendingSettlement {
+                    claim: WithdrawalClaim {
+                        solana_signature: row.try_get("solana_signature")?,
+                        nonce: row.try_get::<i64, _>("nonce")? as u64,
+                        net_amount: row.try_get::<i64, _>("net_amount")? as u64,
+                        nock_address: nock_address.try_into().map_err(|_| anyhow::anyhow!("nock_address is not 32 bytes"))?,
+                    },
+                    release_tx: row.try_get("release_tx")?,
+                    released_at_height: row.try_get::<i64, _>("released_at_height")? as u64,
+                    challenge_deadline: row.try_get::<i64, _>("challenge_deadline")? as u64,
+                    burn_finalized: row.try_get("burn_finalized")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn mark_burn_finalized(&self, signature: &str) -> Result<()> {
+        sqlx::query("UPDATE pending_settlements SET burn_finalized = TRUE WHERE solana_signature = $1")
+            .bind(signature)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_pending(&self, signature: &str) -> Result<()> {
+        sqlx::query("DELETE FROM pending_settlements WHERE solana_signature = $1")
+            .bind(signature)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn dispute(&self, disputed: &DisputedTransaction) -> Result<()> {
+        let signature = &disputed.settlement.claim.solana_signature;
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO disputed_transactions (solana_signature, settlement, reason, detected_at_height)
+             VALUES ($1, $2, $3, $4) ON CONFLICT (solana_signature) DO NOTHING",
+        )
+        .bind(signature)
+        .bind(serde_json::to_value(&disputed.settlement)?)
+        .bind(&disputed.reason)
+        .bind(disputed.detected_at_height as i64)
+        .execute(&mut *transaction)
+        .await?;
+        sqlx::query("DELETE FROM pending_settlements WHERE solana_signature = $1")
+            .bind(signature)
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn disputed(&self) -> Result<Vec<DisputedTransaction>> {
+        let rows = sqlx::query("SELECT * FROM disputed_transactions ORDER BY detected_at_height")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(DisputedTransaction {
+                    settlement: serde_json::from_value(row.try_get("settlement")?)?,
+                    reason: row.try_get("reason")?,
+                    detected_at_height: row.try_get::<i64, _>("detected_at_height")? as u64,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    const BRIDGE: &str = "BridGE1111111111111111111111111111111111111111";
+
+    struct MockChains {
+        height: AtomicU64,
+        evidence: Mutex<HashMap<String, BurnEvidence>>,
+        pauses: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl NockchainReleaser for Arc<MockChains> {
+        async fn release(&self, claim: &WithdrawalClaim) -> Result<String> {
+            Ok(format!("nock-{}", claim.solana_signature))
+        }
+
+        async fn tip_height(&self) -> Result<u64> {
+            Ok(self.height.load(Ordering::SeqCst))
+        }
+    }
+
+    #[async_trait]
+    impl BurnEvidenceSource for Arc<MockChains> {
+        async fn burn_evidence(&self, signature: &str) -> Result<BurnEvidence> {
+            Ok(self.evidence.lock().unwrap().get(signature).cloned().unwrap_or(BurnEvidence::Unfinalized))
+        }
+    }
+
+    #[async_trait]
+    impl EmergencyPauser for Arc<MockChains> {
+        async fn request_emergency_pause(&self, reason: &str) -> Result<String> {
+            let mut pauses = self.pauses.lock().unwrap();
+            pauses.push(reason.to_string());
+            Ok(format!("proposal-{}", pauses.len()))
+        }
+    }
+
+    fn claim(signature: &str, net_amount: u64) -> WithdrawalClaim {
+        WithdrawalClaim { solana_signature: signature.to_string(), nonce: 1, net_amount, nock_address: [3; 32] }
+    }
+
+    fn optimizer(chains: &Arc<MockChains>) -> CrossChainOptimizer {
+        CrossChainOptimizer::new(
+            Box::new(Arc::clone(chains)),
+            Box::new(InMemorySettlementStore::default()),
+            FraudDetector::new(Box::new(Arc::clone(chains))),
+            Box::new(Arc::clone(chains)),
+        )
+    }
+
+    fn event_log(net_amount: u64, nock_address: [u8; 32], nonce: u64) -> String {
+        let mut bytes = WITHDRAW_EVENT_DISCRIMINATOR.to_vec();
+        bytes.extend_from_slice(&[9; 32]);
+        bytes.extend_from_slice(&(net_amount + 10).to_le_bytes());
+        bytes.extend_from_slice(&10u64.to_le_bytes());
+        bytes.extend_from_slice(&net_amount.to_le_bytes());
+        bytes.extend_from_slice(&nock_address);
+        bytes.extend_from_slice(&nonce.to_le_bytes());
+        bytes.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        format!("Program data: {}", base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    #[tokio::test]
+    async fn test_fraud_within_window_disputes_and_pauses_once() {
+        let chains = Arc::new(MockChains { height: AtomicU64::new(100), evidence: Mutex::default(), pauses: Mutex::default() });
+        let mut optimizer = optimizer(&chains);
+
+        let honest = optimizer.settle_optimistically(claim("honest", 500)).await.unwrap();
+        assert_eq!(honest.challenge_deadline, 100 + FRAUD_PROOF_WINDOW_BLOCKS);
+        optimizer.settle_optimistically(claim("inflated", 900)).await.unwrap();
+        optimizer.settle_optimistically(claim("dropped", 100)).await.unwrap();
+
+        {
+            let mut evidence = chains.evidence.lock().unwrap();
+            evidence.insert("honest".into(), BurnEvidence::Finalized(BurnRecord { net_amount: 500, nock_address: [3; 32], nonce: 1 }));
+            evidence.insert("inflated".into(), BurnEvidence::Finalized(BurnRecord { net_amount: 90, nock_address: [3; 32], nonce: 1 }));
+            evidence.insert("dropped".into(), BurnEvidence::Missing);
+        }
+
+        let sweep = optimizer.sweep().await.unwrap();
+        assert_eq!(sweep, SettlementSweep { checked: 3, settled: 0, disputed: 2, pause_requested: true });
+        assert_eq!(chains.pauses.lock().unwrap().len(), 1, "one pause per incident");
+
+        let disputed: Vec<String> = optimizer.disputed().await.unwrap().into_iter().map(|d| d.settlement.claim.solana_signature).collect();
+        assert_eq!(disputed.len(), 2);
+        assert!(disputed.contains(&"inflated".to_string()) && disputed.contains(&"dropped".to_string()));
+
+        // The honest burn is final; it is no longer checked and settles when the window closes
+        chains.height.store(100 + FRAUD_PROOF_WINDOW_BLOCKS, Ordering::SeqCst);
+        let sweep = optimizer.sweep().await.unwrap();
+        assert_eq!(sweep, SettlementSweep { checked: 0, settled: 1, disputed: 0, pause_requested: false });
+    }
+
+    #[test]
+    fn test_withdraw_event_must_come_from_bridge_program() {
+        let event = event_log(500, [3; 32], 7);
+        let logs = [
+            format!("Program {} invoke [1]", BRIDGE),
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [2]".to_string(),
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success".to_string(),
+            event.clone(),
+            format!("Program {} success", BRIDGE),
+        ];
+        let logs: Vec<&str> = logs.iter().map(String::as_str).collect();
+        assert_eq!(find_withdraw_event(&logs, BRIDGE), Some(BurnRecord { net_amount: 500, nock_address: [3; 32], nonce: 7 }));
+
+        // The same event logged by another program is ignored
+        let forged = [
+            format!("Program {} invoke [1]", BRIDGE),
+            format!("Program {} success", BRIDGE),
+            "Program Fake111111111111111111111111111111111111111 invoke [1]".to_string(),
+            event,
+            "Program Fake111111111111111111111111111111111111111 success".to_string(),
+        ];
+        let forged: Vec<&str> = forged.iter().map(String::as_str).collect();
+        assert_eq!(find_withdraw_event(&forged, BRIDGE), None);
+    }
+}
@@ -0,0 +1,657 @@
+// DEX Order Matching
+// Lock-free order intake feeding a central limit order book matched on a dedicated thread
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use crossbeam::queue::SegQueue;
+use crossbeam::utils::Backoff;
+use dashmap::DashMap;
+use hdrhistogram::Histogram;
+use log::{debug, info, warn};
+use prometheus::{Gauge, IntGauge, Registry};
+use serde::{Deserialize, Serialize};
+
+/// Commands matched between latency publications while the queue stays busy
+pub const LATENCY_PUBLISH_EVERY: u64 = 1024;
+
+/// How long the idle matching thread parks before polling the queue again
+pub const IDLE_PARK: Duration = Duration::from_micros(50);
+
+const LATENCY_MAX_NANOS: u64 = 60_000_000_000;
+
+#[derive(Debug, Clone)]
+pub struct OrderMatchingConfig {
+    /// SCHED_FIFO priority of the matching thread on Linux; 0 keeps the default scheduler
+    pub sched_fifo_priority: i32,
+}
+
+impl Default for OrderMatchingConfig {
+    fn default() -> Self {
+        Self { sched_fifo_priority: 50 }
+    }
+}
+
+impl OrderMatchingConfig {
+    /// Reads `DEX_MATCHING_SCHED_PRIORITY`, falling back to the default if unset or unparsable
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            sched_fifo_priority: std::env::var("DEX_MATCHING_SCHED_PRIORITY").ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.sched_fifo_priority),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn opposite(self) -> Self {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitOrder {
+    /// Unique among resting orders
+    pub order_id: u64,
+    pub side: Side,
+    /// Price in ticks
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderCommand {
+    Place(LimitOrder),
+    Cancel { order_id: u64 },
+}
+
+/// A trade between a resting maker and an incoming taker, at the maker's price
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fill {
+    pub maker_order_id: u64,
+    pub taker_order_id: u64,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+/// Outcome of a command. Reports are emitted in the order the book applied commands, with each
+/// taker's fills following its acceptance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionReport {
+    Accepted { sequence: u64, order: LimitOrder },
+    /// Zero quantity, an id that is already resting, or trading is halted
+    Rejected { sequence: u64, order_id: u64 },
+    Filled(Fill),
+    Cancelled { sequence: u64, order_id: u64, remaining: u64 },
+    /// The order was never placed, already filled or already cancelled
+    CancelRejected { sequence: u64, order_id: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestingOrder {
+    pub order_id: u64,
+    /// Position in the book's command sequence, which sets time priority within a level
+    pub sequence: u64,
+    pub remaining: u64,
+}
+
+/// Resting orders by side and price, oldest first. Shared so depth can be read while the
+/// matching thread updates it.
+pub type PriceLevels = DashMap<(Side, u64), VecDeque<RestingOrder>>;
+
+/// Total resting quantity at one price level
+pub fn level_quantity(levels: &PriceLevels, side: Side, price: u64) -> u64 {
+    levels.get(&(side, price)).map_or(0, |level| level.iter().map(|order| order.remaining).sum())
+}
+
+/// Central limit order book with price-time priority. Only one thread applies commands; the
+/// price levels can be read from anywhere.
+#[derive(Debug)]
+pub struct OrderBook {
+    levels: Arc<PriceLevels>,
+    bid_prices: BTreeSet<u64>,
+    ask_prices: BTreeSet<u64>,
+    /// Side and price of every resting order, for cancels
+    resting: HashMap<u64, (Side, u64)>,
+    next_sequence: u64,
+    /// Rejects new orders while set; cancels are still applied
+    halted: bool,
+}
+
+impl OrderBook {
+    pub fn new(levels: Arc<PriceLevels>) -> Self {
+        Self {
+            levels,
+            bid_prices: BTreeSet::new(),
+            ask_prices: BTreeSet::new(),
+            resting: HashMap::new(),
+            next_sequence: 0,
+            halted: false,
+        }
+    }
+
+    pub fn set_halted(&mut self, halted: bool) {
+        self.halted = halted;
+    }
+
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bid_prices.last().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<u64> {
+        self.ask_prices.first().copied()
+    }
+
+    pub fn apply(&mut self, command: OrderCommand, report: &mut impl FnMut(ExecutionReport)) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        match command {
+            OrderCommand::Place(order) => self.place(sequence, order, report),
+            OrderCommand::Cancel { order_id } => self.cancel(sequence, order_id, report),
+        }
+    }
+
+    fn place(&mut self, sequence: u64, order: LimitOrder, report: &mut impl FnMut(ExecutionReport)) {
+        if self.halted || order.quantity == 0 || self.resting.contains_key(&order.order_id) {
+            report(ExecutionReport::Rejected { sequence, order_id: order.order_id });
+            return;
+        }
+        report(ExecutionReport::Accepted { sequence, order });
+
+        let remaining = self.match_against_book(order, report);
+        if remaining > 0 {
+            self.levels.entry((order.side, order.price)).or_default()
+                .push_back(RestingOrder { order_id: order.order_id, sequence, remaining });
+            self.prices_mut(order.side).insert(order.price);
+            self.resting.insert(order.order_id, (order.side, order.price));
+        }
+    }
+
+    /// Fills the taker against the best opposing levels, oldest order first within a level,
+    /// and returns the unfilled quantity
+    fn match_against_book(&mut self, taker: LimitOrder, report: &mut impl FnMut(ExecutionReport)) -> u64 {
+        let maker_side = taker.side.opposite();
+        let mut remaining = taker.quantity;
+
+        while remaining > 0 {
+            let best = match maker_side {
+                Side::Buy => self.best_bid().filter(|&price| price >= taker.price),
+                Side::Sell => self.best_ask().filter(|&price| price <= taker.price),
+            };
+            let Some(price) = best else { break };
+
+            let level_empty = {
+                let mut level = self.levels.get_mut(&(maker_side, price)).expect("tracked price level exists");
+                while remaining > 0 {
+                    let Some(maker) = level.front_mut() else { break };
+                    let quantity = remaining.min(maker.remaining);
+                    maker.remaining -= quantity;
+                    remaining -= quantity;
+                    report(ExecutionReport::Filled(Fill {
+                        maker_order_id: maker.order_id,
+                        taker_order_id: taker.order_id,
+                        price,
+                        quantity,
+                    }));
+                    if maker.remaining == 0 {
+                        let maker_order_id = maker.order_id;
+                        level.pop_front();
+                        self.resting.remove(&maker_order_id);
+                    }
+                }
+                level.is_empty()
+            };
+            if level_empty {
+                self.remove_level(maker_side, price);
+            }
+        }
+        remaining
+    }
+
+    fn cancel(&mut self, sequence: u64, order_id: u64, report: &mut impl FnMut(ExecutionReport)) {
+        let Some((side, price)) = self.resting.remove(&order_id) else {
+            report(ExecutionReport::CancelRejected { sequence, order_id });
+            return;
+        };
+
+        let (remaining, level_empty) = {
+            let mut level = self.levels.get_mut(&(side, price)).expect("tracked price level exists");
+            let position = level.iter().position(|order| order.order_id == order_id)
+                .expect("indexed order rests at its price level");
+            let order = level.remove(position).expect("position is in bounds");
+            (order.remaining, level.is_empty())
+        };
+        if level_empty {
+            self.remove_level(side, price);
+        }
+        report(ExecutionReport::Cancelled { sequence, order_id, remaining });
+    }
+
+    fn remove_level(&mut self, side: Side, price: u64) {
+        self.levels.remove(&(side, price));
+        self.prices_mut(side).remove(&price);
+    }
+
+    fn prices_mut(&mut self, side: Side) -> &mut BTreeSet<u64> {
+        match side {
+            Side::Buy => &mut self.bid_prices,
+            Side::Sell => &mut self.ask_prices,
+        }
+    }
+}
+
+/// Time from submission until the book finished applying a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchLatency {
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+#[derive(Debug)]
+struct Submission {
+    command: OrderCommand,
+    submitted_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct MatchingShared {
+    incoming: SegQueue<Submission>,
+    reports: SegQueue<ExecutionReport>,
+    levels: Arc<PriceLevels>,
+    running: AtomicBool,
+    trading_halted: AtomicBool,
+    commands_processed: AtomicU64,
+    latency_p50_nanos: AtomicU64,
+    latency_p99_nanos: AtomicU64,
+    latency_max_nanos: AtomicU64,
+}
+
+impl MatchingShared {
+    fn publish_latency(&self, histogram: &Histogram<u64>) {
+        self.latency_p50_nanos.store(histogram.value_at_quantile(0.5), Ordering::Relaxed);
+        self.latency_p99_nanos.store(histogram.value_at_quantile(0.99), Ordering::Relaxed);
+        self.latency_max_nanos.store(histogram.max(), Ordering::Relaxed);
+    }
+}
+
+/// Order book whose commands are queued lock-free by any thread and matched on a dedicated
+/// thread, which runs at SCHED_FIFO priority on Linux when permitted
+#[derive(Debug)]
+pub struct OrderMatchingOptimizer {
+    shared: Arc<MatchingShared>,
+    matching_thread: Option<JoinHandle<()>>,
+    match_latency_p99_seconds: Gauge,
+    commands_processed: IntGauge,
+}
+
+impl OrderMatchingOptimizer {
+    pub fn start(config: OrderMatchingConfig) -> Result<Self> {
+        let shared = Arc::new(MatchingShared { running: AtomicBool::new(true), ..Default::default() });
+        let thread_shared = shared.clone();
+        let matching_thread = std::thread::Builder::new()
+            .name("order-matching".to_string())
+            .spawn(move || run_matching_loop(&thread_shared, config.sched_fifo_priority))
+            .context("Failed to spawn order matching thread")?;
+
+        Ok(Self {
+            shared,
+            matching_thread: Some(matching_thread),
+            match_latency_p99_seconds: Gauge::new(
+                "nock_dex_match_latency_p99_seconds",
+                "99th percentile time from order submission until the book applied it",
+            ).expect("valid gauge"),
+            commands_processed: IntGauge::new(
+                "nock_dex_order_commands_processed",
+                "Order placements and cancels applied by the matching engine",
+            ).expect("valid gauge"),
+        })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::start(OrderMatchingConfig::from_env())
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.match_latency_p99_seconds.clone()))?;
+        registry.register(Box::new(self.commands_processed.clone()))?;
+        Ok(())
+    }
+
+    pub fn submit(&self, command: OrderCommand) {
+        self.shared.incoming.push(Submission { command, submitted_at: Instant::now() });
+    }
+
+    pub fn place(&self, order: LimitOrder) {
+        self.submit(OrderCommand::Place(order));
+    }
+
+    pub fn cancel(&self, order_id: u64) {
+        self.submit(OrderCommand::Cancel { order_id });
+    }
+
+    /// Halts or resumes trading; commands queued before the change may still see the old state
+    pub fn set_trading_halted(&self, halted: bool) {
+        if self.shared.trading_halted.swap(halted, Ordering::AcqRel) != halted {
+            warn!("Order matching {}", if halted { "halted, new orders will be rejected" } else { "resumed" });
+        }
+    }
+
+    pub fn trading_halted(&self) -> bool {
+        self.shared.trading_halted.load(Ordering::Acquire)
+    }
+
+    /// Reports produced since the last drain, in the order the book applied them
+    pub fn drain_reports(&self) -> Vec<ExecutionReport> {
+        std::iter::from_fn(|| self.shared.reports.pop()).collect()
+    }
+
+    /// Commands applied so far; every report for them has been queued by the time they count
+    pub fn commands_processed(&self) -> u64 {
+        self.shared.commands_processed.load(Ordering::Acquire)
+    }
+
+    pub fn level_quantity(&self, side: Side, price: u64) -> u64 {
+        level_quantity(&self.shared.levels, side, price)
+    }
+
+    pub fn match_latency(&self) -> MatchLatency {
+        let load = |nanos: &AtomicU64| Duration::from_nanos(nanos.load(Ordering::Relaxed));
+        MatchLatency {
+            p50: load(&self.shared.latency_p50_nanos),
+            p99: load(&self.shared.latency_p99_nanos),
+            max: load(&self.shared.latency_max_nanos),
+        }
+    }
+
+    pub fn publish_metrics(&self) -> MatchLatency {
+        let latency = self.match_latency();
+        self.match_latency_p99_seconds.set(latency.p99.as_secs_f64());
+        self.commands_processed.set(self.commands_processed() as i64);
+        latency
+    }
+}
+
+impl Drop for OrderMatchingOptimizer {
+    fn drop(&mut self) {
+        self.shared.running.store(false, Ordering::Release);
+        if let Some(handle) = self.matching_thread.take() {
+            handle.thread().unpark();
+            if handle.join().is_err() {
+                warn!("Order matching thread panicked");
+            }
+        }
+    }
+}
+
+fn run_matching_loop(shared: &MatchingShared, sched_fifo_priority: i32) {
+    #[cfg(target_os = "linux")]
+    if sched_fifo_priority > 0 {
+        match set_sched_fifo(sched_fifo_priority) {
+            Ok(()) => info!("Order matching thread running at SCHED_FIFO priority {}", sched_fifo_priority),
+            Err(e) => warn!("Failed to set SCHED_FIFO for order matching (needs CAP_SYS_NICE): {}", e),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    debug!("SCHED_FIFO unavailable, order matching thread priority {} ignored", sched_fifo_priority);
+
+    let mut book = OrderBook::new(shared.levels.clone());
+    let mut latency = Histogram::<u64>::new_with_bounds(1, LATENCY_MAX_NANOS, 3).expect("valid histogram bounds");
+    let backoff = Backoff::new();
+    let mut unpublished = 0u64;
+
+    while shared.running.load(Ordering::Acquire) {
+        let Some(submission) = shared.incoming.pop() else {
+            if backoff.is_completed() {
+                std::thread::park_timeout(IDLE_PARK);
+            } else {
+                backoff.snooze();
+            }
+            continue;
+        };
+        backoff.reset();
+
+        book.set_halted(shared.trading_halted.load(Ordering::Acquire));
+        book.apply(submission.command, &mut |report| shared.reports.push(report));
+        let elapsed = submission.submitted_at.elapsed().as_nanos().min(LATENCY_MAX_NANOS as u128) as u64;
+        latency.saturating_record(elapsed.max(1));
+
+        unpublished += 1;
+        if unpublished >= LATENCY_PUBLISH_EVERY || shared.incoming.is_empty() {
+            shared.publish_latency(&latency);
+            unpublished = 0;
+        }
+        shared.commands_processed.fetch_add(1, Ordering::Release);
+    }
+    debug!("Order matching thread stopped");
+}
+
+#[cfg(target_os = "linux")]
+fn set_sched_fifo(priority: i32) -> std::io::Result<()> {
+    // SAFETY: sched_param is plain data; SCHED_FIFO only reads sched_priority
+    let mut param: libc::sched_param = unsafe { std::mem::zeroed() };
+    param.sched_priority = priority;
+    // SAFETY: pthread_self is always a valid handle for the calling thread
+    match unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) } {
+        0 => Ok(()),
+        errno => Err(std::io::Error::from_raw_os_error(errno)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(order_id: u64, side: Side, price: u64, quantity: u64) -> LimitOrder {
+        LimitOrder { order_id, side, price, quantity }
+    }
+
+    fn apply(book: &mut OrderBook, command: OrderCommand) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        book.apply(command, &mut |report| reports.push(report));
+        reports
+    }
+
+    fn fills(reports: &[ExecutionReport]) -> Vec<(u64, u64, u64)> {
+        reports.iter().filter_map(|report| match report {
+            ExecutionReport::Filled(fill) => Some((fill.maker_order_id, fill.price, fill.quantity)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_better_price_fills_before_earlier_order() {
+        let mut book = OrderBook::new(Arc::default());
+        apply(&mut book, OrderCommand::Place(order(1, Side::Sell, 101, 5)));
+        apply(&mut book, OrderCommand::Place(order(2, Side::Sell, 100, 5)));
+        apply(&mut book, OrderCommand::Place(order(3, Side::Sell, 100, 5)));
+
+        let reports = apply(&mut book, OrderCommand::Place(order(4, Side::Buy, 101, 12)));
+        assert_eq!(fills(&reports), vec![(2, 100, 5), (3, 100, 5), (1, 101, 2)]);
+        assert_eq!(book.best_ask(), Some(101));
+        assert_eq!(level_quantity(&book.levels, Side::Sell, 101), 3);
+    }
+
+    #[test]
+    fn test_unfilled_remainder_rests_without_crossing() {
+        let mut book = OrderBook::new(Arc::default());
+        apply(&mut book, OrderCommand::Place(order(1, Side::Buy, 99, 4)));
+        let reports = apply(&mut book, OrderCommand::Place(order(2, Side::Sell, 100, 3)));
+
+        assert!(fills(&reports).is_empty());
+        assert_eq!((book.best_bid(), book.best_ask()), (Some(99), Some(100)));
+    }
+
+    #[test]
+    fn test_cancel_removes_order_and_keeps_queue_position_of_others() {
+        let mut book = OrderBook::new(Arc::default());
+        apply(&mut book, OrderCommand::Place(order(1, Side::Buy, 100, 5)));
+        apply(&mut book, OrderCommand::Place(order(2, Side::Buy, 100, 5)));
+        apply(&mut book, OrderCommand::Place(order(3, Side::Buy, 100, 5)));
+
+        let reports = apply(&mut book, OrderCommand::Cancel { order_id: 2 });
+        assert_eq!(reports, vec![ExecutionReport::Cancelled { sequence: 3, order_id: 2, remaining: 5 }]);
+        assert!(matches!(apply(&mut book, OrderCommand::Cancel { order_id: 2 }).as_slice(),
+                         [ExecutionReport::CancelRejected { order_id: 2, .. }]));
+
+        let reports = apply(&mut book, OrderCommand::Place(order(4, Side::Sell, 100, 7)));
+        assert_eq!(fills(&reports), vec![(1, 100, 5), (3, 100, 2)]);
+        assert_eq!(level_quantity(&book.levels, Side::Buy, 100), 3);
+    }
+
+    #[test]
+    fn test_halted_book_rejects_orders_but_applies_cancels() {
+        let mut book = OrderBook::new(Arc::default());
+        apply(&mut book, OrderCommand::Place(order(1, Side::Buy, 100, 5)));
+        book.set_halted(true);
+
+        let reports = apply(&mut book, OrderCommand::Place(order(2, Side::Sell, 100, 5)));
+        assert_eq!(reports, vec![ExecutionReport::Rejected { sequence: 1, order_id: 2 }]);
+        assert!(matches!(apply(&mut book, OrderCommand::Cancel { order_id: 1 }).as_slice(),
+                         [ExecutionReport::Cancelled { order_id: 1, .. }]));
+    }
+
+    #[test]
+    fn test_duplicate_resting_id_is_rejected() {
+        let mut book = OrderBook::new(Arc::default());
+        apply(&mut book, OrderCommand::Place(order(1, Side::Buy, 100, 5)));
+        let reports = apply(&mut book, OrderCommand::Place(order(1, Side::Buy, 100, 5)));
+        assert_eq!(reports, vec![ExecutionReport::Rejected { sequence: 1, order_id: 1 }]);
+    }
+
+    /// Scans every resting order for the best price, then the lowest sequence
+    #[derive(Default)]
+    struct ReferenceBook {
+        resting: Vec<(u64, LimitOrder)>,
+    }
+
+    impl ReferenceBook {
+        fn place(&mut self, sequence: u64, mut taker: LimitOrder) -> Vec<Fill> {
+            let mut fills = Vec::new();
+            while taker.quantity > 0 {
+                let best = self.resting.iter().enumerate()
+                    .filter(|(_, (_, maker))| maker.side != taker.side && match taker.side {
+                        Side::Buy => maker.price <= taker.price,
+                        Side::Sell => maker.price >= taker.price,
+                    })
+                    .min_by_key(|(_, (maker_sequence, maker))| {
+                        let price_rank = if taker.side == Side::Buy { maker.price } else { u64::MAX - maker.price };
+                        (price_rank, *maker_sequence)
+                    })
+                    .map(|(index, _)| index);
+                let Some(index) = best else { break };
+
+                let maker = &mut self.resting[index].1;
+                let quantity = taker.quantity.min(maker.quantity);
+                fills.push(Fill { maker_order_id: maker.order_id, taker_order_id: taker.order_id, price: maker.price, quantity });
+                maker.quantity -= quantity;
+                taker.quantity -= quantity;
+                if maker.quantity == 0 {
+                    self.resting.remove(index);
+                }
+            }
+            if taker.quantity > 0 {
+                self.resting.push((sequence, taker));
+            }
+            fills
+        }
+
+        fn cancel(&mut self, order_id: u64) -> Option<u64> {
+            let index = self.resting.iter().position(|(_, order)| order.order_id == order_id)?;
+            Some(self.resting.remove(index).1.quantity)
+        }
+    }
+
+    #[test]
+    fn test_price_time_priority_under_concurrent_insert_and_cancel() {
+        const PRODUCERS: u64 = 4;
+        const ORDERS_PER_PRODUCER: u64 = 2_000;
+
+        let optimizer = Arc::new(OrderMatchingOptimizer::start(OrderMatchingConfig { sched_fifo_priority: 0 }).unwrap());
+        let producers: Vec<_> = (0..PRODUCERS).map(|producer| {
+            let optimizer = optimizer.clone();
+            std::thread::spawn(move || {
+                let mut state = producer * 7919 + 1;
+                let mut next = move || {
+                    state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                    state >> 33
+                };
+                let mut commands = 0;
+                for i in 0..ORDERS_PER_PRODUCER {
+                    let order_id = producer * 1_000_000 + i;
+                    let side = if next() % 2 == 0 { Side::Buy } else { Side::Sell };
+                    optimizer.place(order(order_id, side, 95 + next() % 11, 1 + next() % 10));
+                    commands += 1;
+                    if i > 0 && next() % 3 == 0 {
+                        optimizer.cancel(producer * 1_000_000 + next() % i);
+                        commands += 1;
+                    }
+                }
+                commands
+            })
+        }).collect();
+        let total: u64 = producers.into_iter().map(|producer| producer.join().unwrap()).sum();
+
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while optimizer.commands_processed() < total {
+            assert!(Instant::now() < deadline, "matching engine stalled");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        // Replay the engine's own sequence through the reference book and expect identical fills
+        let reports = optimizer.drain_reports();
+        let mut reference = ReferenceBook::default();
+        let mut expected_fills = Vec::new().into_iter();
+        let mut last_sequence = None;
+        for report in &reports {
+            let sequence = match *report {
+                ExecutionReport::Accepted { sequence, order } => {
+                    assert_eq!(expected_fills.len(), 0, "taker was not filled in full");
+                    expected_fills = reference.place(sequence, order).into_iter();
+                    sequence
+                }
+                ExecutionReport::Filled(fill) => {
+                    assert_eq!(Some(fill), expected_fills.next());
+                    continue;
+                }
+                ExecutionReport::Cancelled { sequence, order_id, remaining } => {
+                    assert_eq!(reference.cancel(order_id), Some(remaining));
+                    sequence
+                }
+                ExecutionReport::CancelRejected { sequence, order_id } => {
+                    assert_eq!(reference.cancel(order_id), None);
+                    sequence
+                }
+                ExecutionReport::Rejected { order_id, .. } => panic!("unique order {} rejected", order_id),
+            };
+            assert!(last_sequence < Some(sequence));
+            last_sequence = Some(sequence);
+        }
+        assert_eq!(expected_fills.len(), 0);
+        assert_eq!(last_sequence, Some(total - 1));
+
+        for price in 95..=105 {
+            for side in [Side::Buy, Side::Sell] {
+                let reference_quantity: u64 = reference.resting.iter()
+                    .filter(|(_, order)| order.side == side && order.price == price)
+                    .map(|(_, order)| order.quantity)
+                    .sum();
+                assert_eq!(optimizer.level_quantity(side, price), reference_quantity);
+            }
+        }
+        assert!(optimizer.match_latency().p99 > Duration::ZERO);
+    }
+}
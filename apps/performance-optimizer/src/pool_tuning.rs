@@ -0,0 +1,355 @@
+// Connection Pool Auto-Tuning
+// Times every pool checkout and resizes the pool from the observed wait distribution
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use log::{debug, info};
+use anyhow::{Context, Result};
+use prometheus::{Gauge, IntGauge, Registry};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::Postgres;
+use tokio::time::Instant;
+
+/// Growth step applied when p95 wait exceeds the target
+pub const POOL_GROWTH_FACTOR: f64 = 0.10;
+
+/// Shrink step applied after a sustained calm period
+pub const POOL_SHRINK_FACTOR: f64 = 0.05;
+
+/// Waits below this fraction of the target count as calm
+pub const CALM_WAIT_FRACTION: f64 = 0.10;
+
+/// Consecutive calm measurements required before shrinking
+pub const CALM_MEASUREMENTS_BEFORE_SHRINK: u32 = 5;
+
+/// A pool whose size limit can be changed while it is in use
+#[async_trait]
+pub trait ResizablePool: Send + Sync {
+    type Connection: Send;
+
+    async fn acquire(&self) -> Result<Self::Connection>;
+
+    fn max_connections(&self) -> u32;
+
+    async fn resize(&self, max_connections: u32) -> Result<()>;
+}
+
+/// Postgres pool that is rebuilt with the new limit on resize. Connections checked out
+/// from the previous pool stay valid and are closed as they are returned.
+pub struct ResizablePgPool {
+    connect_options: PgConnectOptions,
+    pool: ArcSwap<PgPool>,
+    max_connections: AtomicU32,
+}
+
+impl ResizablePgPool {
+    pub fn connect_lazy(database_url: &str, max_connections: u32) -> Result<Self> {
+        let connect_options: PgConnectOptions = database_url.parse()
+            .context("Invalid DATABASE_URL")?;
+        let pool = Self::build(&connect_options, max_connections);
+
+        Ok(Self {
+            connect_options,
+            pool: ArcSwap::from_pointee(pool),
+            max_connections: AtomicU32::new(max_connections),
+        })
+    }
+
+    /// Current pool; clones are cheap and share connections
+    pub fn pool(&self) -> PgPool {
+        PgPool::clone(&self.pool.load())
+    }
+
+    fn build(connect_options: &PgConnectOptions, max_connections: u32) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect_lazy_with(connect_options.clone())
+    }
+}
+
+impl std::fmt::Debug for ResizablePgPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResizablePgPool")
+            .field("max_connections", &self.max_connections())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl ResizablePool for ResizablePgPool {
+    type Connection = PoolConnection<Postgres>;
+
+    async fn acquire(&self) -> Result<Self::Connection> {
+        let pool = self.pool.load_full();
+        pool.acquire().await.context("Failed to acquire database connection")
+    }
+
+    fn max_connections(&self) -> u32 {
+        self.max_connections.load(Ordering::Relaxed)
+    }
+
+    async fn resize(&self, max_connections: u32) -> Result<()> {
+        let previous = self.pool.swap(std::sync::Arc::new(Self::build(&self.connect_options, max_connections)));
+        self.max_connections.store(max_connections, Ordering::Relaxed);
+        // close() waits for checked-out connections, so run it off the tuning path
+        tokio::spawn(async move { previous.close().await });
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolTuningConfig {
+    /// p95 checkout wait the tuner tries to stay under
+    pub target_wait_ms: f64,
+    pub min_connections: u32,
+    /// Ceiling the pool never grows past
+    pub max_connections: u32,
+}
+
+impl Default for PoolTuningConfig {
+    fn default() -> Self {
+        Self {
+            target_wait_ms: 50.0,
+            min_connections: 5,
+            max_connections: 100,
+        }
+    }
+}
+
+impl PoolTuningConfig {
+    /// Reads `DB_POOL_TARGET_WAIT_MS`, `DB_POOL_MIN_CONNECTIONS` and `DB_POOL_MAX_CONNECTIONS`,
+    /// falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        let min_connections = env_or("DB_POOL_MIN_CONNECTIONS", defaults.min_connections).max(1);
+        Self {
+            target_wait_ms: env_or("DB_POOL_TARGET_WAIT_MS", defaults.target_wait_ms),
+            min_connections,
+            max_connections: env_or("DB_POOL_MAX_CONNECTIONS", defaults.max_connections).max(min_connections),
+        }
+    }
+}
+
+/// Outcome of one tuning measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolAdjustment {
+    Grew { from: u32, to: u32 },
+    Shrank { from: u32, to: u32 },
+    Unchanged,
+}
+
+/// Records checkout waits and grows or shrinks the pool once per measurement
+#[derive(Debug)]
+pub struct ConnectionPoolOptimizer {
+    pub config: PoolTuningConfig,
+    wait_samples_ms: Mutex<Vec<f64>>,
+    calm_measurements: u32,
+    current_pool_size: IntGauge,
+    pool_wait_p95_ms: Gauge,
+}
+
+impl ConnectionPoolOptimizer {
+    pub fn new(config: PoolTuningConfig) -> Self {
+        Self {
+            config,
+            wait_samples_ms: Mutex::new(Vec::new()),
+            calm_measurements: 0,
+            current_pool_size: IntGauge::new("current_pool_size", "Current database pool max_connections")
+                .expect("valid gauge"),
+            pool_wait_p95_ms: Gauge::new("pool_wait_p95_ms", "p95 database pool checkout wait in milliseconds")
+                .expect("valid gauge"),
+        }
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.current_pool_size.clone()))?;
+        registry.register(Box::new(self.pool_wait_p95_ms.clone()))?;
+        Ok(())
+    }
+
+    /// Acquires from `pool`, recording how long the checkout waited
+    pub async fn acquire<P: ResizablePool>(&self, pool: &P) -> Result<P::Connection> {
+        let started = Instant::now();
+        let connection = pool.acquire().await?;
+        self.record_wait(started.elapsed().as_secs_f64() * 1000.0);
+        Ok(connection)
+    }
+
+    pub fn record_wait(&self, wait_ms: f64) {
+        self.wait_samples_ms.lock().unwrap().push(wait_ms);
+    }
+
+    /// Takes one measurement from the waits recorded since the last call and resizes the
+    /// pool if needed. Intervals without any checkouts are not counted as measurements.
+    pub async fn tune<P: ResizablePool>(&mut self, pool: &P) -> Result<PoolAdjustment> {
+        let current = pool.max_connections();
+        self.current_pool_size.set(current as i64);
+
+        let samples = std::mem::take(&mut *self.wait_samples_ms.lock().unwrap());
+        let Some(p95) = percentile(samples, 0.95) else {
+            return Ok(PoolAdjustment::Unchanged);
+        };
+        self.pool_wait_p95_ms.set(p95);
+
+        let target = if p95 > self.config.target_wait_ms {
+            self.calm_measurements = 0;
+            (current + step(current, POOL_GROWTH_FACTOR)).min(self.config.max_connections)
+        } else if p95 < self.config.target_wait_ms * CALM_WAIT_FRACTION {
+            self.calm_measurements += 1;
+            if self.calm_measurements < CALM_MEASUREMENTS_BEFORE_SHRINK {
+                return Ok(PoolAdjustment::Unchanged);
+            }
+            self.calm_measurements = 0;
+            current.saturating_sub(step(current, POOL_SHRINK_FACTOR)).max(self.config.min_connections)
+        } else {
+            self.calm_measurements = 0;
+            return Ok(PoolAdjustment::Unchanged);
+        };
+
+        if target == current {
+            debug!("Pool size {} already at limit (p95 wait {:.1}ms)", current, p95);
+            return Ok(PoolAdjustment::Unchanged);
+        }
+
+        pool.resize(target).await?;
+        self.current_pool_size.set(target as i64);
+        info!("Resized database pool {} -> {} (p95 wait {:.1}ms, target {:.1}ms)",
+              current, target, p95, self.config.target_wait_ms);
+
+        Ok(if target > current {
+            PoolAdjustment::Grew { from: current, to: target }
+        } else {
+            PoolAdjustment::Shrank { from: current, to: target }
+        })
+    }
+}
+
+/// Resize step for `fraction` of the pool, always at least one connection
+fn step(size: u32, fraction: f64) -> u32 {
+    ((size as f64 * fraction).round() as u32).max(1)
+}
+
+/// Nearest-rank percentile
+fn percentile(mut samples: Vec<f64>, quantile: f64) -> Option<f64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let rank = ((quantile * samples.len() as f64).ceil() as usize).clamp(1, samples.len());
+    Some(samples[rank - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+    /// Pool backed by a semaphore; resizing adds permits so waiters see the new capacity
+    struct MockPool {
+        permits: Arc<Semaphore>,
+        size: AtomicU32,
+    }
+
+    impl MockPool {
+        fn new(size: u32) -> Self {
+            Self { permits: Arc::new(Semaphore::new(size as usize)), size: AtomicU32::new(size) }
+        }
+    }
+
+    #[async_trait]
+    impl ResizablePool for MockPool {
+        type Connection = OwnedSemaphorePermit;
+
+        async fn acquire(&self) -> Result<Self::Connection> {
+            Ok(Arc::clone(&self.permits).acquire_owned().await?)
+        }
+
+        fn max_connections(&self) -> u32 {
+            self.size.load(Ordering::Relaxed)
+        }
+
+        async fn resize(&self, max_connections: u32) -> Result<()> {
+            let current = self.size.swap(max_connections, Ordering::Relaxed);
+            if max_connections > current {
+                self.permits.add_permits((max_connections - current) as usize);
+            } else {
+                self.permits.forget_permits((current - max_connections) as usize);
+            }
+            Ok(())
+        }
+    }
+
+    /// Runs `workers` concurrent checkouts that each hold their connection for `hold`
+    async fn contend(optimizer: &Arc<ConnectionPoolOptimizer>, pool: &Arc<MockPool>, workers: usize, hold: Duration) {
+        let tasks: Vec<_> = (0..workers).map(|_| {
+            let optimizer = Arc::clone(optimizer);
+            let pool = Arc::clone(pool);
+            tokio::spawn(async move {
+                let _connection = optimizer.acquire(pool.as_ref()).await.unwrap();
+                tokio::time::sleep(hold).await;
+            })
+        }).collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pool_grows_under_contention() {
+        let config = PoolTuningConfig { target_wait_ms: 10.0, min_connections: 2, max_connections: 12 };
+        let mut optimizer = Arc::new(ConnectionPoolOptimizer::new(config));
+        let pool = Arc::new(MockPool::new(10));
+
+        // 40 checkouts holding 50ms each against 10 connections queue for up to 150ms
+        contend(&optimizer, &pool, 40, Duration::from_millis(50)).await;
+        let adjustment = Arc::get_mut(&mut optimizer).unwrap().tune(pool.as_ref()).await.unwrap();
+        assert_eq!(adjustment, PoolAdjustment::Grew { from: 10, to: 11 });
+        assert!(optimizer.pool_wait_p95_ms.get() > 10.0);
+
+        contend(&optimizer, &pool, 40, Duration::from_millis(50)).await;
+        Arc::get_mut(&mut optimizer).unwrap().tune(pool.as_ref()).await.unwrap();
+        contend(&optimizer, &pool, 40, Duration::from_millis(50)).await;
+        let adjustment = Arc::get_mut(&mut optimizer).unwrap().tune(pool.as_ref()).await.unwrap();
+
+        assert_eq!(adjustment, PoolAdjustment::Unchanged, "pool must stop at the ceiling");
+        assert_eq!(pool.max_connections(), 12);
+        assert_eq!(optimizer.current_pool_size.get(), 12);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pool_shrinks_after_sustained_calm() {
+        let config = PoolTuningConfig { target_wait_ms: 10.0, min_connections: 2, max_connections: 50 };
+        let mut optimizer = ConnectionPoolOptimizer::new(config);
+        let pool = MockPool::new(40);
+
+        for _ in 0..CALM_MEASUREMENTS_BEFORE_SHRINK - 1 {
+            optimizer.record_wait(0.2);
+            assert_eq!(optimizer.tune(&pool).await.unwrap(), PoolAdjustment::Unchanged);
+        }
+        optimizer.record_wait(0.2);
+        assert_eq!(optimizer.tune(&pool).await.unwrap(), PoolAdjustment::Shrank { from: 40, to: 38 });
+
+        // A moderate wait resets the calm streak
+        optimizer.record_wait(5.0);
+        optimizer.tune(&pool).await.unwrap();
+        optimizer.record_wait(0.2);
+        assert_eq!(optimizer.tune(&pool).await.unwrap(), PoolAdjustment::Unchanged);
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(samples, 0.95), Some(95.0));
+        assert_eq!(percentile(vec![3.0], 0.95), Some(3.0));
+        assert_eq!(percentile(Vec::new(), 0.95), None);
+    }
+}
@@ -0,0 +1,526 @@
+// NOCK/USD Price Feed
+// Aggregates Pyth, Switchboard and the mining pool's VWAP, rejecting outliers with Grubbs' test
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, error, info, warn};
+use prometheus::{Gauge, IntGauge, Registry};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+const SOURCE_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Two-sided Grubbs critical values at α = 0.05 for 3 to 12 observations (NIST/SEMATECH);
+/// more sources use the value for 12, which rejects slightly more readily
+const GRUBBS_CRITICAL_VALUES: [f64; 10] = [1.1543, 1.4812, 1.7150, 1.8871, 2.0200, 2.1266, 2.2150, 2.2900, 2.3547, 2.4116];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceFeedConfig {
+    pub pyth_hermes_url: String,
+    /// Hex ID of the NOCK/USD Pyth price feed
+    pub pyth_price_id: String,
+    pub switchboard_crossbar_url: String,
+    /// Hash of the NOCK/USD Switchboard on-demand feed
+    pub switchboard_feed_hash: String,
+    pub mining_pool_api_url: String,
+    /// How long an aggregated price is served before the sources are queried again
+    pub cache_ttl: Duration,
+    /// Largest relative difference at which two sources count as agreeing
+    pub agreement_tolerance: f64,
+}
+
+impl Default for PriceFeedConfig {
+    fn default() -> Self {
+        Self {
+            pyth_hermes_url: "https://hermes.pyth.network".to_string(),
+            pyth_price_id: String::new(),
+            switchboard_crossbar_url: "https://crossbar.switchboard.xyz".to_string(),
+            switchboard_feed_hash: String::new(),
+            mining_pool_api_url: "http://127.0.0.1:8080".to_string(),
+            cache_ttl: Duration::from_secs(5),
+            agreement_tolerance: 0.02,
+        }
+    }
+}
+
+impl PriceFeedConfig {
+    /// Reads `PYTH_HERMES_URL`, `PYTH_NOCK_USD_PRICE_ID`, `SWITCHBOARD_CROSSBAR_URL`,
+    /// `SWITCHBOARD_NOCK_USD_FEED_HASH`, `MINING_POOL_API_URL`, `PRICE_FEED_CACHE_TTL_SECS` and
+    /// `PRICE_FEED_AGREEMENT_TOLERANCE`, falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            pyth_hermes_url: env_or("PYTH_HERMES_URL", defaults.pyth_hermes_url),
+            pyth_price_id: env_or("PYTH_NOCK_USD_PRICE_ID", defaults.pyth_price_id),
+            switchboard_crossbar_url: env_or("SWITCHBOARD_CROSSBAR_URL", defaults.switchboard_crossbar_url),
+            switchboard_feed_hash: env_or("SWITCHBOARD_NOCK_USD_FEED_HASH", defaults.switchboard_feed_hash),
+            mining_pool_api_url: env_or("MINING_POOL_API_URL", defaults.mining_pool_api_url),
+            cache_ttl: Duration::from_secs(env_or("PRICE_FEED_CACHE_TTL_SECS", defaults.cache_ttl.as_secs())),
+            agreement_tolerance: env_or("PRICE_FEED_AGREEMENT_TOLERANCE", defaults.agreement_tolerance),
+        }
+    }
+}
+
+#[async_trait]
+pub trait PriceSource: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Current NOCK/USD price
+    async fn fetch_price(&self) -> Result<f64>;
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(SOURCE_REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+async fn get_json(client: &reqwest::Client, url: &str, source: &str) -> Result<Value> {
+    client.get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", source))?
+        .error_for_status()
+        .with_context(|| format!("{} rejected price request", source))?
+        .json()
+        .await
+        .with_context(|| format!("Malformed {} price response", source))
+}
+
+/// Latest price from Pyth's Hermes API
+pub struct PythPriceSource {
+    hermes_url: String,
+    price_id: String,
+    client: reqwest::Client,
+}
+
+impl PythPriceSource {
+    pub fn new(hermes_url: String, price_id: String) -> Self {
+        Self { hermes_url, price_id, client: http_client() }
+    }
+}
+
+#[async_trait]
+impl PriceSource for PythPriceSource {
+    fn name(&self) -> &str {
+        "pyth"
+    }
+
+    async fn fetch_price(&self) -> Result<f64> {
+        let url = format!("{}/v2/updates/price/latest?ids[]={}&parsed=true",
+                          self.hermes_url.trim_end_matches('/'), self.price_id);
+        let response = get_json(&self.client, &url, "Pyth Hermes").await?;
+        let price = response.pointer("/parsed/0/price").context("Pyth response has no parsed price")?;
+        let mantissa: i64 = price.get("price").and_then(Value::as_str).and_then(|p| p.parse().ok())
+            .context("Pyth price is not an integer string")?;
+        let exponent = price.get("expo").and_then(Value::as_i64).context("Pyth price has no exponent")?;
+        Ok(mantissa as f64 * 10f64.powi(exponent as i32))
+    }
+}
+
+/// Simulated result of a Switchboard on-demand feed from the Crossbar gateway
+pub struct SwitchboardPriceSource {
+    crossbar_url: String,
+    feed_hash: String,
+    client: reqwest::Client,
+}
+
+impl SwitchboardPriceSource {
+    pub fn new(crossbar_url: String, feed_hash: String) -> Self {
+        Self { crossbar_url, feed_hash, client: http_client() }
+    }
+}
+
+#[async_trait]
+impl PriceSource for SwitchboardPriceSource {
+    fn name(&self) -> &str {
+        "switchboard"
+    }
+
+    async fn fetch_price(&self) -> Result<f64> {
+        let url = format!("{}/simulate/{}", self.crossbar_url.trim_end_matches('/'), self.feed_hash);
+        let response = get_json(&self.client, &url, "Switchboard Crossbar").await?;
+        response.pointer("/0/results/0").and_then(Value::as_f64).context("Switchboard response has no result")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VwapResponse {
+    vwap_usd: f64,
+}
+
+/// Volume-weighted average NOCK/USD price computed by the mining pool
+pub struct MiningPoolVwapSource {
+    pool_api_url: String,
+    client: reqwest::Client,
+}
+
+impl MiningPoolVwapSource {
+    pub fn new(pool_api_url: String) -> Self {
+        Self { pool_api_url, client: http_client() }
+    }
+}
+
+#[async_trait]
+impl PriceSource for MiningPoolVwapSource {
+    fn name(&self) -> &str {
+        "mining_pool_vwap"
+    }
+
+    async fn fetch_price(&self) -> Result<f64> {
+        let url = format!("{}/pool/price/vwap", self.pool_api_url.trim_end_matches('/'));
+        let response: VwapResponse = serde_json::from_value(get_json(&self.client, &url, "mining pool").await?)
+            .context("Malformed mining pool VWAP response")?;
+        Ok(response.vwap_usd)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourcePrice {
+    pub source: String,
+    pub price: f64,
+}
+
+/// Critical value of the two-sided Grubbs test at α = 0.05, or `None` below three observations
+pub fn grubbs_critical_value(observations: usize) -> Option<f64> {
+    let index = observations.checked_sub(3)?;
+    Some(GRUBBS_CRITICAL_VALUES[index.min(GRUBBS_CRITICAL_VALUES.len() - 1)])
+}
+
+/// Repeatedly removes the price furthest from the mean while Grubbs' test flags it, returning
+/// the kept and rejected prices
+pub fn reject_outliers(mut prices: Vec<SourcePrice>) -> (Vec<SourcePrice>, Vec<SourcePrice>) {
+    let mut rejected = Vec::new();
+    while let Some(critical) = grubbs_critical_value(prices.len()) {
+        let n = prices.len() as f64;
+        let mean = prices.iter().map(|p| p.price).sum::<f64>() / n;
+        let std_dev = (prices.iter().map(|p| (p.price - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+        if std_dev == 0.0 {
+            break;
+        }
+
+        let (index, deviation) = prices.iter().enumerate()
+            .map(|(index, p)| (index, (p.price - mean).abs()))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("at least three prices");
+        if deviation / std_dev <= critical {
+            break;
+        }
+        rejected.push(prices.remove(index));
+    }
+    (prices, rejected)
+}
+
+pub fn median(prices: &[SourcePrice]) -> Option<f64> {
+    let mut values: Vec<f64> = prices.iter().map(|p| p.price).collect();
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        len if len % 2 == 1 => Some(values[middle]),
+        _ => Some((values[middle - 1] + values[middle]) / 2.0),
+    }
+}
+
+/// Whether at least two prices are within `tolerance` of each other, relative to their midpoint
+pub fn sources_agree(prices: &[SourcePrice], tolerance: f64) -> bool {
+    prices.iter().enumerate().any(|(i, a)| {
+        prices[i + 1..].iter().any(|b| (a.price - b.price).abs() <= tolerance * (a.price + b.price) / 2.0)
+    })
+}
+
+/// Spread between the highest and lowest price as a percentage of the median
+pub fn divergence_percent(prices: &[SourcePrice]) -> f64 {
+    let Some(median) = median(prices).filter(|&m| m > 0.0) else { return 0.0 };
+    let (min, max) = prices.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| (min.min(p.price), max.max(p.price)));
+    (max - min) / median * 100.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AggregatedPrice {
+    /// Median of the sources left after outlier rejection
+    pub price: f64,
+    pub sources: Vec<SourcePrice>,
+    pub rejected: Vec<SourcePrice>,
+    pub divergence_percent: f64,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceFeedDivergenceAlert {
+    pub prices: Vec<SourcePrice>,
+    pub divergence_percent: f64,
+    pub agreement_tolerance_percent: f64,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceFeedUpdate {
+    Price(AggregatedPrice),
+    /// Fewer than two sources agree; trading stays frozen until they do
+    Diverged(PriceFeedDivergenceAlert),
+}
+
+/// NOCK/USD price aggregated across sources, freezing trading while they disagree
+pub struct PriceFeedOptimizer {
+    config: PriceFeedConfig,
+    sources: Vec<Arc<dyn PriceSource>>,
+    cached: Option<(Instant, AggregatedPrice)>,
+    trading_frozen: bool,
+    price_feed_source_count: IntGauge,
+    price_feed_divergence_percent: Gauge,
+    price_feed_last_update_seconds: Gauge,
+}
+
+impl std::fmt::Debug for PriceFeedOptimizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PriceFeedOptimizer")
+            .field("sources", &self.sources.iter().map(|source| source.name()).collect::<Vec<_>>())
+            .field("cached", &self.cached.as_ref().map(|(_, price)| price.price))
+            .field("trading_frozen", &self.trading_frozen)
+            .finish()
+    }
+}
+
+impl PriceFeedOptimizer {
+    pub fn new(config: PriceFeedConfig, sources: Vec<Arc<dyn PriceSource>>) -> Self {
+        Self {
+            config,
+            sources,
+            cached: None,
+            trading_frozen: false,
+            price_feed_source_count: IntGauge::new(
+                "price_feed_source_count",
+                "NOCK/USD sources used in the last aggregated price",
+            ).expect("valid gauge"),
+            price_feed_divergence_percent: Gauge::new(
+                "price_feed_divergence_percent",
+                "Spread between the highest and lowest NOCK/USD source as a percentage of the median",
+            ).expect("valid gauge"),
+            price_feed_last_update_seconds: Gauge::new(
+                "price_feed_last_update_seconds",
+                "Unix time of the last aggregated NOCK/USD price",
+            ).expect("valid gauge"),
+        }
+    }
+
+    /// Pyth, Switchboard and the mining pool's VWAP, skipping feeds without a configured ID
+    pub fn from_env() -> Self {
+        let config = PriceFeedConfig::from_env();
+        let mut sources: Vec<Arc<dyn PriceSource>> = Vec::new();
+        if config.pyth_price_id.is_empty() {
+            warn!("PYTH_NOCK_USD_PRICE_ID not set, Pyth excluded from the price feed");
+        } else {
+            sources.push(Arc::new(PythPriceSource::new(config.pyth_hermes_url.clone(), config.pyth_price_id.clone())));
+        }
+        if config.switchboard_feed_hash.is_empty() {
+            warn!("SWITCHBOARD_NOCK_USD_FEED_HASH not set, Switchboard excluded from the price feed");
+        } else {
+            sources.push(Arc::new(SwitchboardPriceSource::new(config.switchboard_crossbar_url.clone(), config.switchboard_feed_hash.clone())));
+        }
+        sources.push(Arc::new(MiningPoolVwapSource::new(config.mining_pool_api_url.clone())));
+        Self::new(config, sources)
+    }
+
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        registry.register(Box::new(self.price_feed_source_count.clone()))?;
+        registry.register(Box::new(self.price_feed_divergence_percent.clone()))?;
+        registry.register(Box::new(self.price_feed_last_update_seconds.clone()))?;
+        Ok(())
+    }
+
+    pub fn trading_frozen(&self) -> bool {
+        self.trading_frozen
+    }
+
+    /// The aggregated price, queried afresh once the cached one is older than the TTL
+    pub async fn price(&mut self) -> Result<AggregatedPrice> {
+        if let Some((fetched_at, price)) = &self.cached {
+            if !self.trading_frozen && fetched_at.elapsed() < self.config.cache_ttl {
+                return Ok(price.clone());
+            }
+        }
+        match self.refresh().await? {
+            PriceFeedUpdate::Price(price) => Ok(price),
+            PriceFeedUpdate::Diverged(alert) => bail!("NOCK/USD sources diverged by {:.2}%, trading frozen", alert.divergence_percent),
+        }
+    }
+
+    /// Queries every source, rejects outliers and takes the median, freezing trading if fewer
+    /// than two of the remaining sources agree
+    pub async fn refresh(&mut self) -> Result<PriceFeedUpdate> {
+        let mut fetches = JoinSet::new();
+        for source in &self.sources {
+            let source = Arc::clone(source);
+            fetches.spawn(async move { (source.name().to_string(), source.fetch_price().await) });
+        }
+
+        let mut prices = Vec::new();
+        while let Some(joined) = fetches.join_next().await {
+            match joined.context("Price source task panicked")? {
+                (source, Ok(price)) if price.is_finite() && price > 0.0 => prices.push(SourcePrice { source, price }),
+                (source, Ok(price)) => warn!("Ignoring invalid NOCK/USD price {} from {}", price, source),
+                (source, Err(e)) => warn!("Failed to fetch NOCK/USD price from {}: {:#}", source, e),
+            }
+        }
+        prices.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let (sources, rejected) = reject_outliers(prices);
+        for outlier in &rejected {
+            warn!("Rejected NOCK/USD outlier {} from {}", outlier.price, outlier.source);
+        }
+        let divergence = divergence_percent(&sources);
+        self.price_feed_source_count.set(sources.len() as i64);
+        self.price_feed_divergence_percent.set(divergence);
+
+        if !sources_agree(&sources, self.config.agreement_tolerance) {
+            let alert = PriceFeedDivergenceAlert {
+                prices: sources,
+                divergence_percent: divergence,
+                agreement_tolerance_percent: self.config.agreement_tolerance * 100.0,
+                detected_at: Utc::now(),
+            };
+            if !self.trading_frozen {
+                error!("NOCK/USD price feeds diverged ({:?}); freezing trading until they reconverge", alert.prices);
+            }
+            self.trading_frozen = true;
+            return Ok(PriceFeedUpdate::Diverged(alert));
+        }
+
+        if self.trading_frozen {
+            info!("NOCK/USD price feeds reconverged, unfreezing trading");
+            self.trading_frozen = false;
+        }
+        let price = AggregatedPrice {
+            price: median(&sources).expect("agreeing sources are not empty"),
+            sources,
+            rejected,
+            divergence_percent: divergence,
+            updated_at: Utc::now(),
+        };
+        self.price_feed_last_update_seconds.set(price.updated_at.timestamp() as f64);
+        debug!("NOCK/USD {} from {} sources", price.price, price.sources.len());
+
+        self.cached = Some((Instant::now(), price.clone()));
+        Ok(PriceFeedUpdate::Price(price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    struct FixedSource {
+        name: &'static str,
+        price: Mutex<Option<f64>>,
+        fetches: AtomicUsize,
+    }
+
+    impl FixedSource {
+        fn new(name: &'static str, price: f64) -> Arc<Self> {
+            Arc::new(Self { name, price: Mutex::new(Some(price)), fetches: AtomicUsize::new(0) })
+        }
+
+        fn set(&self, price: Option<f64>) {
+            *self.price.lock().unwrap() = price;
+        }
+    }
+
+    #[async_trait]
+    impl PriceSource for FixedSource {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn fetch_price(&self) -> Result<f64> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            let price = *self.price.lock().unwrap();
+            price.context("source unavailable")
+        }
+    }
+
+    fn prices(values: &[f64]) -> Vec<SourcePrice> {
+        values.iter().enumerate().map(|(i, &price)| SourcePrice { source: format!("s{}", i), price }).collect()
+    }
+
+    fn feed(sources: &[Arc<FixedSource>]) -> PriceFeedOptimizer {
+        let sources = sources.iter().map(|source| Arc::clone(source) as Arc<dyn PriceSource>).collect();
+        PriceFeedOptimizer::new(PriceFeedConfig { cache_ttl: Duration::from_secs(60), ..PriceFeedConfig::default() }, sources)
+    }
+
+    #[test]
+    fn test_grubbs_rejects_far_outlier_only() {
+        let (kept, rejected) = reject_outliers(prices(&[1.00, 1.01, 100.0]));
+        assert_eq!(kept.len(), 2);
+        assert_eq!(rejected[0].price, 100.0);
+
+        let (kept, rejected) = reject_outliers(prices(&[1.00, 1.10, 1.25]));
+        assert_eq!(kept.len(), 3);
+        assert!(rejected.is_empty());
+
+        let (kept, _) = reject_outliers(prices(&[0.50, 0.51, 0.49, 0.50, 0.52, 0.80]));
+        assert!(kept.iter().all(|p| p.price < 0.6));
+    }
+
+    #[test]
+    fn test_median_and_agreement() {
+        assert_eq!(median(&prices(&[3.0, 1.0, 2.0])), Some(2.0));
+        assert_eq!(median(&prices(&[1.0, 2.0])), Some(1.5));
+        assert!(sources_agree(&prices(&[1.00, 1.15, 1.019]), 0.02));
+        assert!(!sources_agree(&prices(&[1.00, 1.10, 1.25]), 0.02));
+        assert!(!sources_agree(&prices(&[1.00]), 0.02));
+    }
+
+    #[tokio::test]
+    async fn test_outlier_is_excluded_from_median() {
+        let sources = [FixedSource::new("pyth", 0.100), FixedSource::new("switchboard", 0.101), FixedSource::new("vwap", 10.0)];
+        let mut feed = feed(&sources);
+
+        let price = feed.price().await.unwrap();
+        assert!((price.price - 0.1005).abs() < 1e-12);
+        assert_eq!(price.rejected.len(), 1);
+        assert_eq!(feed.price_feed_source_count.get(), 2);
+        assert!(!feed.trading_frozen());
+    }
+
+    #[tokio::test]
+    async fn test_price_is_cached_for_ttl() {
+        let sources = [FixedSource::new("pyth", 0.100), FixedSource::new("switchboard", 0.101), FixedSource::new("vwap", 0.102)];
+        let mut feed = feed(&sources);
+
+        feed.price().await.unwrap();
+        sources[0].set(Some(0.2));
+        assert!((feed.price().await.unwrap().price - 0.101).abs() < 1e-12);
+        assert_eq!(sources[0].fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_divergence_freezes_trading_until_reconvergence() {
+        let sources = [FixedSource::new("pyth", 1.00), FixedSource::new("switchboard", 1.10), FixedSource::new("vwap", 1.25)];
+        let mut feed = feed(&sources);
+
+        let PriceFeedUpdate::Diverged(alert) = feed.refresh().await.unwrap() else { panic!("expected divergence") };
+        assert!((alert.divergence_percent - 22.727).abs() < 0.01);
+        assert!(feed.trading_frozen());
+        assert!(feed.price().await.is_err());
+
+        // One source down and one still apart is not enough
+        sources[1].set(None);
+        assert!(matches!(feed.refresh().await.unwrap(), PriceFeedUpdate::Diverged(_)));
+
+        sources[1].set(Some(1.01));
+        assert!(matches!(feed.refresh().await.unwrap(), PriceFeedUpdate::Price(_)));
+        assert!(!feed.trading_frozen());
+    }
+}
@@ -0,0 +1,84 @@
+// CPU Flamegraph Profiling
+// Samples the process while an optimization runs and writes the result as an SVG flamegraph
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use log::{debug, warn};
+use anyhow::{Context, Result};
+use chrono::Utc;
+
+/// Sampling rate; a prime avoids lockstep with periodic work
+pub const PROFILING_FREQUENCY_HZ: i32 = 997;
+
+const DEFAULT_OUTPUT_DIR: &str = "profiles";
+
+/// Writes one flamegraph per profiled optimization into `output_dir`
+#[derive(Debug, Clone)]
+pub struct FlamegraphProfiler {
+    output_dir: PathBuf,
+}
+
+impl FlamegraphProfiler {
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self { output_dir: output_dir.into() }
+    }
+
+    /// Enabled only when `NOCK_PROFILING=1`; SVGs go to `NOCK_PROFILING_DIR` (default `profiles`).
+    /// Returning None keeps optimizations free of any profiling work.
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("NOCK_PROFILING").ok()? != "1" {
+            return None;
+        }
+        let output_dir = std::env::var("NOCK_PROFILING_DIR").unwrap_or_else(|_| DEFAULT_OUTPUT_DIR.to_string());
+        Some(Self::new(output_dir))
+    }
+
+    pub fn output_dir(&self) -> &Path {
+        &self.output_dir
+    }
+
+    /// Starts sampling for `label`. Only one capture can run at a time; if the profiler is
+    /// busy or unavailable the optimization simply runs unprofiled.
+    pub fn start(&self, label: &str) -> Option<ProfileCapture> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(PROFILING_FREQUENCY_HZ)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build();
+
+        match guard {
+            Ok(guard) => Some(ProfileCapture {
+                guard,
+                path: self.output_dir.join(format!("{}-{}.svg", label, Utc::now().format("%Y%m%dT%H%M%S%3fZ"))),
+            }),
+            Err(e) => {
+                warn!("Failed to start profiler for {}: {}", label, e);
+                None
+            }
+        }
+    }
+}
+
+/// Running capture; sampling stops when it is finished or dropped
+pub struct ProfileCapture {
+    guard: pprof::ProfilerGuard<'static>,
+    path: PathBuf,
+}
+
+impl ProfileCapture {
+    /// Writes the flamegraph and returns its path, or None if no CPU samples were taken
+    pub fn finish(self) -> Result<Option<PathBuf>> {
+        let report = self.guard.report().build().context("Failed to build profile report")?;
+        if report.data.is_empty() {
+            debug!("No CPU samples captured for {}", self.path.display());
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")))
+            .context("Failed to create profiling output directory")?;
+        let file = File::create(&self.path)
+            .with_context(|| format!("Failed to create {}", self.path.display()))?;
+        report.flamegraph(file).context("Failed to write flamegraph")?;
+
+        Ok(Some(self.path))
+    }
+}
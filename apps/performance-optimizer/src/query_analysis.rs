@@ -0,0 +1,467 @@
+// Slow Query Analysis
+// Explains slow queries, finds sequential scans on large tables and suggests (or creates) indexes
+
+use std::collections::VecDeque;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+
+/// Slow queries kept for analysis between monitoring cycles
+pub const MAX_PENDING_SLOW_QUERIES: usize = 100;
+
+/// Widest composite index that will be suggested
+pub const MAX_INDEX_COLUMNS: usize = 3;
+
+/// Postgres truncates identifiers beyond this length
+const MAX_IDENTIFIER_LENGTH: usize = 63;
+
+static STRING_LITERAL: Lazy<Regex> = Lazy::new(|| Regex::new(r"'(?:[^']|'')*'").unwrap());
+
+// A column reference, optionally parenthesised and cast, followed by a comparison operator
+static COMPARISON: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?:^|[^:\w.])\(*([a-z_][a-z0-9_]*)\)*(?:::[a-z ]+?)?\)*\s*(<>|!=|<=|>=|=|<|>|~~)").unwrap()
+});
+
+/// Bind parameter captured with a query so it can be explained with the same values
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QueryParam {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservedQuery {
+    pub sql: String,
+    pub params: Vec<QueryParam>,
+    pub duration_ms: f64,
+    pub observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexSuggestion {
+    pub table: String,
+    /// Equality columns first, then range columns
+    pub columns: Vec<String>,
+    /// Expected ratio of current to indexed execution time
+    pub estimated_speedup: f64,
+}
+
+impl IndexSuggestion {
+    pub fn index_name(&self) -> String {
+        let mut name = format!("idx_{}_{}", self.table.replace('.', "_"), self.columns.join("_"));
+        name.truncate(MAX_IDENTIFIER_LENGTH);
+        name
+    }
+
+    pub fn create_statement(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|column| quote_identifier(column)).collect();
+        let table: Vec<String> = self.table.split('.').map(quote_identifier).collect();
+        format!(
+            "CREATE INDEX CONCURRENTLY IF NOT EXISTS {} ON {} ({})",
+            quote_identifier(&self.index_name()),
+            table.join("."),
+            columns.join(", "),
+        )
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Collects queries slower than `slow_query_threshold_ms` for the analyzer
+#[derive(Debug)]
+pub struct SlowQueryDetector {
+    pub slow_query_threshold_ms: f64,
+    pending: VecDeque<ObservedQuery>,
+}
+
+impl SlowQueryDetector {
+    pub fn new(slow_query_threshold_ms: f64) -> Self {
+        Self { slow_query_threshold_ms, pending: VecDeque::new() }
+    }
+
+    /// Reads `SLOW_QUERY_THRESHOLD_MS` (default 100)
+    pub fn from_env() -> Self {
+        let threshold = std::env::var("SLOW_QUERY_THRESHOLD_MS").ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(100.0);
+        Self::new(threshold)
+    }
+
+    /// Records a completed query; returns true if it was slow enough to queue for analysis
+    pub fn record(&mut self, sql: &str, params: Vec<QueryParam>, duration_ms: f64) -> bool {
+        if duration_ms < self.slow_query_threshold_ms {
+            return false;
+        }
+        if self.pending.len() >= MAX_PENDING_SLOW_QUERIES {
+            self.pending.pop_front();
+        }
+        self.pending.push_back(ObservedQuery {
+            sql: sql.to_string(),
+            params,
+            duration_ms,
+            observed_at: Utc::now(),
+        });
+        true
+    }
+
+    pub fn drain(&mut self) -> Vec<ObservedQuery> {
+        self.pending.drain(..).collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryAnalyzerConfig {
+    /// Sequential scans reading fewer rows than this are left alone
+    pub large_table_rows: f64,
+    /// Run `CREATE INDEX CONCURRENTLY` for each suggestion
+    pub auto_index: bool,
+}
+
+impl Default for QueryAnalyzerConfig {
+    fn default() -> Self {
+        Self { large_table_rows: 10_000.0, auto_index: false }
+    }
+}
+
+impl QueryAnalyzerConfig {
+    /// Reads `INDEX_SUGGESTION_MIN_ROWS` and `AUTO_INDEX`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            large_table_rows: std::env::var("INDEX_SUGGESTION_MIN_ROWS").ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.large_table_rows),
+            auto_index: std::env::var("AUTO_INDEX").map(|value| value == "true").unwrap_or(defaults.auto_index),
+        }
+    }
+}
+
+/// Explains slow queries and turns sequential scans into index suggestions
+#[derive(Debug)]
+pub struct QueryAnalyzer {
+    pub config: QueryAnalyzerConfig,
+    pub suggestions: Vec<IndexSuggestion>,
+}
+
+impl QueryAnalyzer {
+    pub fn new(config: QueryAnalyzerConfig) -> Self {
+        Self { config, suggestions: Vec::new() }
+    }
+
+    /// Explains every queued slow query and records (and optionally creates) suggested indexes
+    pub async fn analyze_slow_queries(&mut self, pool: &PgPool, detector: &mut SlowQueryDetector) -> Result<Vec<IndexSuggestion>> {
+        let mut new_suggestions = Vec::new();
+
+        for query in detector.drain() {
+            let plan = match explain_analyze(pool, &query).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    warn!("Failed to explain slow query ({:.0}ms): {}", query.duration_ms, e);
+                    continue;
+                }
+            };
+
+            for suggestion in self.suggest_indexes(&plan) {
+                if self.suggestions.iter().any(|s| s.table == suggestion.table && s.columns == suggestion.columns) {
+                    continue;
+                }
+                info!("Index suggestion for {}: ({}) ~{:.1}x faster",
+                      suggestion.table, suggestion.columns.join(", "), suggestion.estimated_speedup);
+
+                if self.config.auto_index {
+                    if let Err(e) = create_index(pool, &suggestion, &query.sql).await {
+                        warn!("Failed to create {}: {}", suggestion.index_name(), e);
+                    }
+                }
+                self.suggestions.push(suggestion.clone());
+                new_suggestions.push(suggestion);
+            }
+        }
+
+        Ok(new_suggestions)
+    }
+
+    /// Finds filtered sequential scans over at least `large_table_rows` rows in an
+    /// `EXPLAIN (ANALYZE, FORMAT JSON)` result
+    pub fn suggest_indexes(&self, explain_output: &Value) -> Vec<IndexSuggestion> {
+        // FORMAT JSON wraps the result in a single-element array
+        let root = explain_output.get(0).unwrap_or(explain_output);
+        let Some(plan) = root.get("Plan") else {
+            return Vec::new();
+        };
+        let execution_ms = root.get("Execution Time").and_then(Value::as_f64)
+            .or_else(|| plan.get("Actual Total Time").and_then(Value::as_f64))
+            .unwrap_or(0.0);
+
+        let mut suggestions: Vec<IndexSuggestion> = Vec::new();
+        let mut nodes = vec![plan];
+        while let Some(node) = nodes.pop() {
+            if let Some(children) = node.get("Plans").and_then(Value::as_array) {
+                nodes.extend(children);
+            }
+            if let Some(suggestion) = self.suggest_for_node(node, execution_ms) {
+                if !suggestions.iter().any(|s| s.table == suggestion.table && s.columns == suggestion.columns) {
+                    suggestions.push(suggestion);
+                }
+            }
+        }
+
+        suggestions.sort_by(|a, b| b.estimated_speedup.total_cmp(&a.estimated_speedup));
+        suggestions
+    }
+
+    fn suggest_for_node(&self, node: &Value, execution_ms: f64) -> Option<IndexSuggestion> {
+        if node.get("Node Type").and_then(Value::as_str) != Some("Seq Scan") {
+            return None;
+        }
+        let relation = node.get("Relation Name").and_then(Value::as_str)?;
+        let filter = node.get("Filter").and_then(Value::as_str)?;
+
+        let number = |key: &str| node.get(key).and_then(Value::as_f64);
+        let loops = number("Actual Loops").unwrap_or(1.0).max(1.0);
+        let returned = number("Actual Rows").or_else(|| number("Plan Rows")).unwrap_or(0.0) * loops;
+        let removed = number("Rows Removed by Filter").unwrap_or(0.0) * loops;
+        let scanned = returned + removed;
+        if scanned < self.config.large_table_rows {
+            return None;
+        }
+
+        let columns = filter_columns(filter);
+        if columns.is_empty() {
+            return None;
+        }
+
+        let table = match node.get("Schema").and_then(Value::as_str) {
+            Some(schema) if schema != "public" => format!("{}.{}", schema, relation),
+            _ => relation.to_string(),
+        };
+        let node_ms = number("Actual Total Time").unwrap_or(0.0) * loops;
+
+        Some(IndexSuggestion {
+            table,
+            columns,
+            estimated_speedup: estimate_speedup(execution_ms, node_ms, returned, scanned),
+        })
+    }
+}
+
+/// Columns compared in a plan filter, equality comparisons first
+pub fn filter_columns(filter: &str) -> Vec<String> {
+    let without_literals = STRING_LITERAL.replace_all(filter, "''");
+    let mut equality = Vec::new();
+    let mut range = Vec::new();
+
+    for capture in COMPARISON.captures_iter(&without_literals) {
+        let column = capture[1].to_string();
+        match &capture[2] {
+            "=" => equality.push(column),
+            "<" | ">" | "<=" | ">=" | "~~" => range.push(column),
+            // Inequality cannot use a btree index
+            _ => {}
+        }
+    }
+
+    let mut columns: Vec<String> = Vec::new();
+    for column in equality.into_iter().chain(range) {
+        if !columns.contains(&column) && columns.len() < MAX_INDEX_COLUMNS {
+            columns.push(column);
+        }
+    }
+    columns
+}
+
+/// Amdahl-style estimate: the scan's share of execution time shrinks to the fraction of
+/// rows the index would still have to visit
+fn estimate_speedup(execution_ms: f64, node_ms: f64, returned: f64, scanned: f64) -> f64 {
+    let selectivity = (returned.max(1.0) / scanned.max(1.0)).min(1.0);
+    if execution_ms <= 0.0 || node_ms <= 0.0 {
+        return 1.0 / selectivity;
+    }
+    let node_ms = node_ms.min(execution_ms);
+    let indexed_ms = execution_ms - node_ms + node_ms * selectivity;
+    (execution_ms / indexed_ms.max(f64::EPSILON)).max(1.0)
+}
+
+/// Runs `EXPLAIN (ANALYZE, FORMAT JSON)` with the query's original parameters. ANALYZE
+/// executes the statement, so it runs in a transaction that is always rolled back.
+pub async fn explain_analyze(pool: &PgPool, query: &ObservedQuery) -> Result<Value> {
+    let sql = format!("EXPLAIN (ANALYZE, FORMAT JSON) {}", query.sql);
+    let mut explain = sqlx::query(&sql);
+    for param in &query.params {
+        explain = match param {
+            QueryParam::Null => explain.bind(None::<String>),
+            QueryParam::Bool(value) => explain.bind(*value),
+            QueryParam::Int(value) => explain.bind(*value),
+            QueryParam::Float(value) => explain.bind(*value),
+            QueryParam::Text(value) => explain.bind(value.as_str()),
+        };
+    }
+
+    let mut tx = pool.begin().await?;
+    let result = explain.fetch_one(&mut *tx).await;
+    tx.rollback().await?;
+
+    let row = result.context("EXPLAIN ANALYZE failed")?;
+    let plan: Value = row.try_get(0)?;
+    debug!("Explained slow query: {}", query.sql);
+    Ok(plan)
+}
+
+/// Creates the suggested index without blocking writes and records it in `index_audit_log`
+pub async fn create_index(pool: &PgPool, suggestion: &IndexSuggestion, source_query: &str) -> Result<()> {
+    // CONCURRENTLY cannot run inside a transaction, so this goes straight to the pool
+    sqlx::query(&suggestion.create_statement()).execute(pool).await?;
+
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS index_audit_log (
+            id BIGSERIAL PRIMARY KEY,
+            index_name TEXT NOT NULL,
+            table_name TEXT NOT NULL,
+            columns TEXT[] NOT NULL,
+            estimated_speedup DOUBLE PRECISION NOT NULL,
+            source_query TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+    "#).execute(pool).await?;
+
+    sqlx::query(r#"
+        INSERT INTO index_audit_log (index_name, table_name, columns, estimated_speedup, source_query)
+        VALUES ($1, $2, $3, $4, $5)
+    "#)
+        .bind(suggestion.index_name())
+        .bind(&suggestion.table)
+        .bind(&suggestion.columns)
+        .bind(suggestion.estimated_speedup)
+        .bind(source_query)
+        .execute(pool)
+        .await?;
+
+    info!("Created index {} on {}", suggestion.index_name(), suggestion.table);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn analyzer() -> QueryAnalyzer {
+        QueryAnalyzer::new(QueryAnalyzerConfig::default())
+    }
+
+    fn seq_scan(relation: &str, filter: &str, rows: f64, removed: f64, total_ms: f64) -> Value {
+        json!({
+            "Node Type": "Seq Scan",
+            "Relation Name": relation,
+            "Schema": "public",
+            "Filter": filter,
+            "Actual Rows": rows,
+            "Rows Removed by Filter": removed,
+            "Actual Loops": 1,
+            "Actual Total Time": total_ms
+        })
+    }
+
+    #[test]
+    fn test_seq_scan_on_large_table_suggests_index() {
+        let plan = json!([{
+            "Plan": seq_scan("shares", "((miner_id = 42) AND (submitted_at > '2024-01-01 00:00:00'::timestamp without time zone))", 50.0, 199_950.0, 180.0),
+            "Execution Time": 200.0
+        }]);
+
+        let suggestions = analyzer().suggest_indexes(&plan);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].table, "shares");
+        assert_eq!(suggestions[0].columns, vec!["miner_id", "submitted_at"]);
+        // 180 of 200ms drops to 180 * 50/200000: roughly 10x
+        assert!((suggestions[0].estimated_speedup - 200.0 / (20.0 + 180.0 * 50.0 / 200_000.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_small_tables_and_index_scans_are_ignored() {
+        let plan = json!([{
+            "Plan": {
+                "Node Type": "Hash Join",
+                "Actual Total Time": 40.0,
+                "Plans": [
+                    seq_scan("pools", "(active = true)", 10.0, 90.0, 0.5),
+                    {
+                        "Node Type": "Index Scan",
+                        "Relation Name": "blocks",
+                        "Index Name": "blocks_pkey",
+                        "Actual Rows": 1000,
+                        "Actual Loops": 1
+                    }
+                ]
+            },
+            "Execution Time": 41.0
+        }]);
+
+        assert!(analyzer().suggest_indexes(&plan).is_empty());
+    }
+
+    #[test]
+    fn test_nested_scans_are_found_and_ranked() {
+        let plan = json!([{
+            "Plan": {
+                "Node Type": "Nested Loop",
+                "Actual Total Time": 900.0,
+                "Plans": [
+                    seq_scan("payouts", "((status)::text = 'pending'::text)", 500.0, 49_500.0, 100.0),
+                    {
+                        "Node Type": "Materialize",
+                        "Plans": [seq_scan("shares", "(block_height >= 1000)", 10.0, 99_990.0, 700.0)]
+                    }
+                ]
+            },
+            "Execution Time": 950.0
+        }]);
+
+        let suggestions = analyzer().suggest_indexes(&plan);
+        let tables: Vec<_> = suggestions.iter().map(|s| s.table.as_str()).collect();
+        assert_eq!(tables, vec!["shares", "payouts"]);
+        assert_eq!(suggestions[1].columns, vec!["status"]);
+    }
+
+    #[test]
+    fn test_filter_columns_ignore_literals_and_inequality() {
+        assert_eq!(filter_columns("((note)::text = 'a = b'::text)"), vec!["note"]);
+        assert_eq!(filter_columns("((created_at < now()) AND (pool_id = 3) AND (state <> 'x'::text))"), vec!["pool_id", "created_at"]);
+        assert!(filter_columns("(state <> 'closed'::text)").is_empty());
+    }
+
+    #[test]
+    fn test_create_statement_quotes_identifiers() {
+        let suggestion = IndexSuggestion {
+            table: "mining.shares".to_string(),
+            columns: vec!["miner_id".to_string(), "submitted_at".to_string()],
+            estimated_speedup: 3.0,
+        };
+        assert_eq!(
+            suggestion.create_statement(),
+            r#"CREATE INDEX CONCURRENTLY IF NOT EXISTS "idx_mining_shares_miner_id_submitted_at" ON "mining"."shares" ("miner_id", "submitted_at")"#
+        );
+    }
+
+    #[test]
+    fn test_detector_queues_only_slow_queries() {
+        let mut detector = SlowQueryDetector::new(100.0);
+        assert!(!detector.record("SELECT 1", Vec::new(), 5.0));
+        assert!(detector.record("SELECT * FROM shares WHERE miner_id = $1", vec![QueryParam::Int(42)], 250.0));
+
+        let queued = detector.drain();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].params, vec![QueryParam::Int(42)]);
+        assert!(detector.drain().is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+// Table Bloat Analysis
+// Measures dead tuple ratios per table and vacuums bloated tables during the maintenance window
+
+use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Timelike, Utc};
+use log::{debug, info, warn};
+use once_cell::sync::Lazy;
+use prometheus::{GaugeVec, Opts, Registry};
+use sqlx::{Executor, PgPool, Row};
+
+/// Tables with a larger share of dead tuples are vacuumed
+pub const DEFAULT_BLOAT_THRESHOLD_PERCENT: f64 = 20.0;
+
+/// Upper half of every vacuum advisory lock key, so per-table keys cannot collide with
+/// advisory locks taken by other parts of the platform
+const VACUUM_LOCK_NAMESPACE: i64 = 0x7661_6375; // "vacu"
+
+// Shared by every analyzer so engines created per optimization run report into one series
+static TABLE_BLOAT_PERCENT: Lazy<GaugeVec> = Lazy::new(|| {
+    GaugeVec::new(
+        Opts::new("table_bloat_percent", "Dead tuples as a percentage of all tuples in the table"),
+        &["table"],
+    ).expect("valid gauge")
+});
+
+const TABLE_STATS_QUERY: &str = r#"
+    SELECT s.relid::bigint AS relid,
+           s.schemaname::text AS schema_name,
+           s.relname::text AS table_name,
+           s.n_live_tup AS live_tuples,
+           s.n_dead_tup AS dead_tuples,
+           pg_total_relation_size(c.oid) AS size_bytes,
+           s.last_vacuum,
+           s.last_autovacuum
+    FROM pg_stat_user_tables s
+    JOIN pg_class c ON c.oid = s.relid
+    WHERE c.relkind = 'r'
+"#;
+
+/// Hour of the day in UTC, 0-23
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HourOfDay(u8);
+
+impl HourOfDay {
+    pub fn new(hour: u8) -> Option<Self> {
+        (hour < 24).then_some(Self(hour))
+    }
+
+    pub fn hour(self) -> u8 {
+        self.0
+    }
+
+    pub fn of(time: DateTime<Utc>) -> Self {
+        Self(time.hour() as u8)
+    }
+}
+
+impl FromStr for HourOfDay {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let hour: u8 = s.trim().parse().with_context(|| format!("Invalid hour '{}'", s))?;
+        Self::new(hour).ok_or_else(|| anyhow!("Hour {} is outside 0-23", hour))
+    }
+}
+
+impl fmt::Display for HourOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:00", self.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TableBloatConfig {
+    /// Dead tuple percentage above which a table is vacuumed
+    pub bloat_threshold_percent: f64,
+    /// Start (inclusive) and end (exclusive) hour of the low-traffic window; the window
+    /// wraps past midnight when the start is later than the end
+    pub maintenance_window: [HourOfDay; 2],
+}
+
+impl Default for TableBloatConfig {
+    fn default() -> Self {
+        Self {
+            bloat_threshold_percent: DEFAULT_BLOAT_THRESHOLD_PERCENT,
+            maintenance_window: [HourOfDay(2), HourOfDay(5)],
+        }
+    }
+}
+
+impl TableBloatConfig {
+    /// Reads `DB_BLOAT_THRESHOLD_PERCENT` and `DB_MAINTENANCE_WINDOW` (e.g. `2-5`),
+    /// falling back to the defaults for anything unset or unparsable
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let maintenance_window = std::env::var("DB_MAINTENANCE_WINDOW").ok()
+            .and_then(|value| parse_maintenance_window(&value).ok())
+            .unwrap_or(defaults.maintenance_window);
+
+        Self {
+            bloat_threshold_percent: std::env::var("DB_BLOAT_THRESHOLD_PERCENT").ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.bloat_threshold_percent),
+            maintenance_window,
+        }
+    }
+
+    pub fn in_maintenance_window(&self, now: DateTime<Utc>) -> bool {
+        in_maintenance_window(self.maintenance_window, HourOfDay::of(now))
+    }
+}
+
+/// Parses `start-end` into a maintenance window
+pub fn parse_maintenance_window(value: &str) -> Result<[HourOfDay; 2]> {
+    let (start, end) = value.split_once('-')
+        .ok_or_else(|| anyhow!("Maintenance window '{}' must look like 'start-end'", value))?;
+    Ok([start.parse()?, end.parse()?])
+}
+
+/// Whether `hour` falls in `[start, end)`; equal bounds mean the whole day
+pub fn in_maintenance_window([start, end]: [HourOfDay; 2], hour: HourOfDay) -> bool {
+    if start == end {
+        true
+    } else if start < end {
+        start <= hour && hour < end
+    } else {
+        hour >= start || hour < end
+    }
+}
+
+/// Dead tuple statistics for one table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableBloatStats {
+    pub relid: u32,
+    pub schema: String,
+    pub table: String,
+    pub live_tuples: i64,
+    pub dead_tuples: i64,
+    pub size_bytes: i64,
+    pub last_vacuum: Option<DateTime<Utc>>,
+    pub last_autovacuum: Option<DateTime<Utc>>,
+}
+
+impl TableBloatStats {
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.schema, self.table)
+    }
+
+    pub fn dead_tuple_percent(&self) -> f64 {
+        dead_tuple_percent(self.live_tuples, self.dead_tuples)
+    }
+}
+
+impl fmt::Display for TableBloatStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} live, {} dead ({:.1}%), {} bytes",
+               self.live_tuples, self.dead_tuples, self.dead_tuple_percent(), self.size_bytes)
+    }
+}
+
+/// Dead tuples as a percentage of all tuples; an empty table has no bloat
+pub fn dead_tuple_percent(live_tuples: i64, dead_tuples: i64) -> f64 {
+    let total = live_tuples.max(0) + dead_tuples.max(0);
+    if total == 0 {
+        return 0.0;
+    }
+    dead_tuples.max(0) as f64 / total as f64 * 100.0
+}
+
+/// Advisory lock key for vacuuming the table with `relid`. Table OIDs are shared by every
+/// instance connected to the same database, so they all contend for the same key.
+pub fn vacuum_lock_key(relid: u32) -> i64 {
+    (VACUUM_LOCK_NAMESPACE << 32) | relid as i64
+}
+
+/// Double-quotes an identifier for interpolation into SQL
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Outcome of one bloat analysis pass
+#[derive(Debug, Clone, Default)]
+pub struct TableBloatReport {
+    pub tables_analyzed: usize,
+    /// Qualified names of tables over the bloat threshold
+    pub bloated_tables: Vec<String>,
+    pub vacuumed_tables: Vec<String>,
+    /// Bloated tables left alone because another instance held the lock
+    pub skipped_locked: Vec<String>,
+}
+
+/// Finds bloated tables from `pg_stat_user_tables` and vacuums them in the maintenance window
+#[derive(Debug)]
+pub struct TableBloatAnalyzer {
+    pub config: TableBloatConfig,
+    table_bloat_percent: GaugeVec,
+}
+
+impl TableBloatAnalyzer {
+    pub fn new(config: TableBloatConfig) -> Self {
+        Self {
+            config,
+            table_bloat_percent: TABLE_BLOAT_PERCENT.clone(),
+        }
+    }
+
+    /// Registers the per-table gauge; registering it again is not an error
+    pub fn register_metrics(&self, registry: &Registry) -> Result<()> {
+        match registry.register(Box::new(self.table_bloat_percent.clone())) {
+            Ok(()) | Err(prometheus::Error::AlreadyReg) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn table_stats(&self, pool: &PgPool) -> Result<Vec<TableBloatStats>> {
+        let rows = sqlx::query(TABLE_STATS_QUERY)
+            .fetch_all(pool)
+            .await
+            .context("Failed to read table statistics")?;
+
+        rows.iter().map(|row| Ok(TableBloatStats {
+            relid: row.try_get::<i64, _>("relid")? as u32,
+            schema: row.try_get("schema_name")?,
+            table: row.try_get("table_name")?,
+            live_tuples: row.try_get("live_tuples")?,
+            dead_tuples: row.try_get("dead_tuples")?,
+            size_bytes: row.try_get("size_bytes")?,
+            last_vacuum: row.try_get("last_vacuum")?,
+            last_autovacuum: row.try_get("last_autovacuum")?,
+        })).collect()
+    }
+
+    /// Updates the bloat gauges and returns the tables over the threshold, most bloated first
+    pub async fn find_bloated_tables(&self, pool: &PgPool) -> Result<(usize, Vec<TableBloatStats>)> {
+        let stats = self.table_stats(pool).await?;
+        for table in &stats {
+            self.table_bloat_percent
+                .with_label_values(&[&table.qualified_name()])
+                .set(table.dead_tuple_percent());
+        }
+
+        let analyzed = stats.len();
+        let mut bloated: Vec<_> = stats.into_iter()
+            .filter(|table| table.dead_tuple_percent() > self.config.bloat_threshold_percent)
+            .collect();
+        bloated.sort_by(|a, b| b.dead_tuple_percent().total_cmp(&a.dead_tuple_percent()));
+        Ok((analyzed, bloated))
+    }
+
+    /// Measures bloat for every table and, inside the maintenance window, runs
+    /// `VACUUM ANALYZE` on the bloated ones
+    pub async fn analyze(&self, pool: &PgPool, now: DateTime<Utc>) -> Result<TableBloatReport> {
+        let (tables_analyzed, bloated) = self.find_bloated_tables(pool).await?;
+        let mut report = TableBloatReport {
+            tables_analyzed,
+            bloated_tables: bloated.iter().map(TableBloatStats::qualified_name).collect(),
+            ..Default::default()
+        };
+
+        if bloated.is_empty() {
+            debug!("No tables above {:.0}% dead tuples", self.config.bloat_threshold_percent);
+            return Ok(report);
+        }
+
+        let [start, end] = self.config.maintenance_window;
+        if !self.config.in_maintenance_window(now) {
+            info!("{} bloated tables waiting for the {}-{} UTC maintenance window",
+                  bloated.len(), start, end);
+            return Ok(report);
+        }
+
+        for table in &bloated {
+            match self.vacuum_table(pool, table).await {
+                Ok(true) => report.vacuumed_tables.push(table.qualified_name()),
+                Ok(false) => report.skipped_locked.push(table.qualified_name()),
+                Err(e) => warn!("Failed to vacuum {}: {}", table.qualified_name(), e),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Runs `VACUUM ANALYZE` on `table` while holding its advisory lock. Returns false
+    /// without vacuuming when another instance holds the lock.
+    pub async fn vacuum_table(&self, pool: &PgPool, table: &TableBloatStats) -> Result<bool> {
+        let name = table.qualified_name();
+        let key = vacuum_lock_key(table.relid);
+        // Advisory locks belong to the session, so lock, vacuum and unlock on one connection
+        let mut conn = pool.acquire().await.context("Failed to acquire database connection")?;
+
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(key)
+            .fetch_one(&mut *conn)
+            .await?;
+        if !locked {
+            info!("Skipping VACUUM of {}, another instance holds its lock", name);
+            return Ok(false);
+        }
+
+        info!("VACUUM ANALYZE {} starting: {}", name, table);
+        let sql = format!("VACUUM ANALYZE {}.{}", quote_identifier(&table.schema), quote_identifier(&table.table));
+        // Unprepared so it runs outside any transaction block, which VACUUM requires
+        let vacuumed = conn.execute(sql.as_str()).await;
+
+        let unlocked = sqlx::query_scalar::<_, bool>("SELECT pg_advisory_unlock($1)")
+            .bind(key)
+            .fetch_one(&mut *conn)
+            .await;
+        if let Err(e) = unlocked {
+            // Closing the session releases the lock
+            warn!("Failed to release vacuum lock for {}: {}", name, e);
+            drop(conn.detach());
+        }
+        vacuumed.with_context(|| format!("VACUUM ANALYZE {} failed", name))?;
+
+        match self.table_stats(pool).await?.into_iter().find(|after| after.relid == table.relid) {
+            Some(after) => {
+                self.table_bloat_percent.with_label_values(&[&name]).set(after.dead_tuple_percent());
+                info!("VACUUM ANALYZE {} finished: {}", name, after);
+            }
+            None => info!("VACUUM ANALYZE {} finished", name),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn hour(h: u8) -> HourOfDay {
+        HourOfDay::new(h).unwrap()
+    }
+
+    #[test]
+    fn test_dead_tuple_percent() {
+        assert_eq!(dead_tuple_percent(800, 200), 20.0);
+        assert_eq!(dead_tuple_percent(0, 50), 100.0);
+        assert_eq!(dead_tuple_percent(0, 0), 0.0);
+        // n_live_tup can be a stale negative estimate right after truncation
+        assert_eq!(dead_tuple_percent(-5, 0), 0.0);
+    }
+
+    #[test]
+    fn test_maintenance_window_bounds() {
+        let window = [hour(2), hour(5)];
+        assert!(!in_maintenance_window(window, hour(1)));
+        assert!(in_maintenance_window(window, hour(2)));
+        assert!(in_maintenance_window(window, hour(4)));
+        assert!(!in_maintenance_window(window, hour(5)));
+    }
+
+    #[test]
+    fn test_maintenance_window_wraps_midnight() {
+        let window = [hour(22), hour(3)];
+        assert!(in_maintenance_window(window, hour(23)));
+        assert!(in_maintenance_window(window, hour(0)));
+        assert!(!in_maintenance_window(window, hour(3)));
+        assert!(!in_maintenance_window(window, hour(12)));
+        assert!(in_maintenance_window([hour(4), hour(4)], hour(17)));
+    }
+
+    #[test]
+    fn test_config_window_uses_utc_hour() {
+        let config = TableBloatConfig::default();
+        assert!(config.in_maintenance_window(Utc.with_ymd_and_hms(2024, 3, 1, 3, 30, 0).unwrap()));
+        assert!(!config.in_maintenance_window(Utc.with_ymd_and_hms(2024, 3, 1, 14, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_parse_maintenance_window() {
+        assert_eq!(parse_maintenance_window("22-3").unwrap(), [hour(22), hour(3)]);
+        assert_eq!(parse_maintenance_window(" 1 - 4 ").unwrap(), [hour(1), hour(4)]);
+        assert!(parse_maintenance_window("2-24").is_err());
+        assert!(parse_maintenance_window("2").is_err());
+        assert!(HourOfDay::new(24).is_none());
+    }
+
+    #[test]
+    fn test_vacuum_lock_key_is_per_table() {
+        assert_ne!(vacuum_lock_key(16384), vacuum_lock_key(16385));
+        assert_eq!(vacuum_lock_key(16384) & 0xffff_ffff, 16384);
+        assert_eq!(vacuum_lock_key(u32::MAX) >> 32, VACUUM_LOCK_NAMESPACE);
+    }
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("orders"), "\"orders\"");
+        assert_eq!(quote_identifier("we\"ird"), "\"we\"\"ird\"");
+    }
+}
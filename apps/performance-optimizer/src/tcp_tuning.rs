@@ -0,0 +1,143 @@
+// TCP Socket Tuning
+// Applies keepalive, Nagle and buffer settings to listener sockets with setsockopt
+
+#[cfg(unix)]
+use std::os::fd::AsFd;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Socket options applied to a listener; also used to report the options currently in effect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TcpTuningConfig {
+    pub nodelay: bool,
+    pub keepalive: bool,
+    pub keepalive_idle_secs: u32,
+    pub keepalive_interval_secs: u32,
+    pub keepalive_count: u32,
+    /// Linux reports twice the requested size to account for bookkeeping overhead
+    pub send_buffer_bytes: usize,
+    pub recv_buffer_bytes: usize,
+}
+
+impl Default for TcpTuningConfig {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: true,
+            keepalive_idle_secs: 60,
+            keepalive_interval_secs: 10,
+            keepalive_count: 6,
+            send_buffer_bytes: 256 * 1024,
+            recv_buffer_bytes: 256 * 1024,
+        }
+    }
+}
+
+impl TcpTuningConfig {
+    /// Reads `TCP_NODELAY`, `TCP_KEEPALIVE`, `TCP_KEEPIDLE_SECS`, `TCP_KEEPINTVL_SECS`,
+    /// `TCP_KEEPCNT`, `TCP_SNDBUF_BYTES` and `TCP_RCVBUF_BYTES`, keeping defaults for the rest
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            nodelay: env_or("TCP_NODELAY", defaults.nodelay),
+            keepalive: env_or("TCP_KEEPALIVE", defaults.keepalive),
+            keepalive_idle_secs: env_or("TCP_KEEPIDLE_SECS", defaults.keepalive_idle_secs).max(1),
+            keepalive_interval_secs: env_or("TCP_KEEPINTVL_SECS", defaults.keepalive_interval_secs).max(1),
+            keepalive_count: env_or("TCP_KEEPCNT", defaults.keepalive_count).max(1),
+            send_buffer_bytes: env_or("TCP_SNDBUF_BYTES", defaults.send_buffer_bytes),
+            recv_buffer_bytes: env_or("TCP_RCVBUF_BYTES", defaults.recv_buffer_bytes),
+        }
+    }
+}
+
+/// Applies `config` to a TCP socket. Accepted connections inherit these from the listener.
+#[cfg(target_os = "linux")]
+pub fn apply_tcp_tuning<S: AsFd>(socket: &S, config: &TcpTuningConfig) -> Result<()> {
+    use anyhow::Context;
+    use nix::sys::socket::{setsockopt, sockopt};
+
+    setsockopt(socket, sockopt::TcpNoDelay, &config.nodelay).context("Failed to set TCP_NODELAY")?;
+    setsockopt(socket, sockopt::KeepAlive, &config.keepalive).context("Failed to set SO_KEEPALIVE")?;
+    setsockopt(socket, sockopt::TcpKeepIdle, &config.keepalive_idle_secs).context("Failed to set TCP_KEEPIDLE")?;
+    setsockopt(socket, sockopt::TcpKeepInterval, &config.keepalive_interval_secs).context("Failed to set TCP_KEEPINTVL")?;
+    setsockopt(socket, sockopt::TcpKeepCount, &config.keepalive_count).context("Failed to set TCP_KEEPCNT")?;
+    setsockopt(socket, sockopt::SndBuf, &config.send_buffer_bytes).context("Failed to set SO_SNDBUF")?;
+    setsockopt(socket, sockopt::RcvBuf, &config.recv_buffer_bytes).context("Failed to set SO_RCVBUF")?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn apply_tcp_tuning<S: AsFd>(_socket: &S, _config: &TcpTuningConfig) -> Result<()> {
+    log::warn!("TCP socket tuning is only supported on Linux, skipping");
+    Ok(())
+}
+
+/// Reads the options currently in effect on a TCP socket
+#[cfg(target_os = "linux")]
+pub fn read_tcp_options<S: AsFd>(socket: &S) -> Result<TcpTuningConfig> {
+    use anyhow::Context;
+    use nix::sys::socket::{getsockopt, sockopt};
+
+    Ok(TcpTuningConfig {
+        nodelay: getsockopt(socket, sockopt::TcpNoDelay).context("Failed to read TCP_NODELAY")?,
+        keepalive: getsockopt(socket, sockopt::KeepAlive).context("Failed to read SO_KEEPALIVE")?,
+        keepalive_idle_secs: getsockopt(socket, sockopt::TcpKeepIdle).context("Failed to read TCP_KEEPIDLE")?,
+        keepalive_interval_secs: getsockopt(socket, sockopt::TcpKeepInterval).context("Failed to read TCP_KEEPINTVL")?,
+        keepalive_count: getsockopt(socket, sockopt::TcpKeepCount).context("Failed to read TCP_KEEPCNT")?,
+        send_buffer_bytes: getsockopt(socket, sockopt::SndBuf).context("Failed to read SO_SNDBUF")?,
+        recv_buffer_bytes: getsockopt(socket, sockopt::RcvBuf).context("Failed to read SO_RCVBUF")?,
+    })
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn read_tcp_options<S: AsFd>(_socket: &S) -> Result<TcpTuningConfig> {
+    anyhow::bail!("Reading TCP socket options is only supported on Linux")
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_tuning_is_applied_to_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = TcpTuningConfig {
+            nodelay: true,
+            keepalive: true,
+            keepalive_idle_secs: 45,
+            keepalive_interval_secs: 7,
+            keepalive_count: 4,
+            send_buffer_bytes: 64 * 1024,
+            recv_buffer_bytes: 128 * 1024,
+        };
+
+        apply_tcp_tuning(&listener, &config).unwrap();
+        let active = read_tcp_options(&listener).unwrap();
+
+        assert!(active.nodelay);
+        assert!(active.keepalive);
+        assert_eq!(active.keepalive_idle_secs, 45);
+        assert_eq!(active.keepalive_interval_secs, 7);
+        assert_eq!(active.keepalive_count, 4);
+        // The kernel doubles requested buffer sizes (and may clamp them to net.core.*mem_max)
+        assert!(active.send_buffer_bytes >= 64 * 1024);
+        assert!(active.recv_buffer_bytes >= 128 * 1024);
+    }
+
+    #[test]
+    fn test_disabling_nagle_and_keepalive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = TcpTuningConfig { nodelay: false, keepalive: false, ..TcpTuningConfig::default() };
+
+        apply_tcp_tuning(&listener, &config).unwrap();
+        let active = read_tcp_options(&listener).unwrap();
+
+        assert!(!active.nodelay);
+        assert!(!active.keepalive);
+    }
+}
@@ -0,0 +1,340 @@
+// ZK Proof Batching
+// Aggregates bridge transaction proofs with recursive plonky2 composition so a batch is verified once
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use anyhow::{ensure, Result};
+use log::{debug, info};
+use plonky2::field::types::Field;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::target::Target;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CircuitData};
+use plonky2::plonk::config::{GenericConfig, PoseidonGoldilocksConfig};
+use plonky2::plonk::proof::{ProofWithPublicInputs, ProofWithPublicInputsTarget};
+use serde::{Deserialize, Serialize};
+
+const D: usize = 2;
+type C = PoseidonGoldilocksConfig;
+type F = <C as GenericConfig<D>>::F;
+
+/// A transaction is witnessed as 32-bit limbs: eight for the hash, two for the amount
+const TRANSACTION_LIMBS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZkProofBatchConfig {
+    pub max_batch_size: usize,
+    /// Longest a transaction waits for its batch to fill before a partial batch is proved
+    pub max_batch_wait_ms: u64,
+}
+
+impl Default for ZkProofBatchConfig {
+    fn default() -> Self {
+        Self { max_batch_size: 32, max_batch_wait_ms: 2_000 }
+    }
+}
+
+impl ZkProofBatchConfig {
+    /// Reads `ZK_PROOF_BATCH_SIZE` and `ZK_PROOF_BATCH_WAIT_MS`, falling back to the defaults
+    /// for anything unset or unparsable
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            max_batch_size: env_or("ZK_PROOF_BATCH_SIZE", defaults.max_batch_size).max(1),
+            max_batch_wait_ms: env_or("ZK_PROOF_BATCH_WAIT_MS", defaults.max_batch_wait_ms),
+        }
+    }
+}
+
+/// Bridge transaction awaiting a proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeProofRequest {
+    pub tx_hash: [u8; 32],
+    pub amount: u64,
+}
+
+impl BridgeProofRequest {
+    fn limbs(&self) -> [F; TRANSACTION_LIMBS] {
+        let mut limbs = [F::ZERO; TRANSACTION_LIMBS];
+        for (limb, chunk) in limbs.iter_mut().zip(self.tx_hash.chunks(4)) {
+            *limb = F::from_canonical_u32(u32::from_le_bytes(chunk.try_into().expect("4-byte chunk")));
+        }
+        limbs[8] = F::from_canonical_u32(self.amount as u32);
+        limbs[9] = F::from_canonical_u32((self.amount >> 32) as u32);
+        limbs
+    }
+}
+
+/// Per-transaction cost of proving and of verifying on-chain
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProofCost {
+    pub prove: Duration,
+    pub verify: Duration,
+}
+
+impl ProofCost {
+    pub fn total(&self) -> Duration {
+        self.prove + self.verify
+    }
+}
+
+#[derive(Debug)]
+pub struct AggregatedBatchProof {
+    pub transactions: Vec<BridgeProofRequest>,
+    pub proof: ProofWithPublicInputs<F, C, D>,
+    /// Cost each transaction would have had with its own proof and verification
+    pub unbatched_cost_per_tx: ProofCost,
+    pub batched_cost_per_tx: ProofCost,
+}
+
+/// Circuit verifying two proofs of the level below and hashing their public inputs together
+struct AggregationLevel {
+    data: CircuitData<F, C, D>,
+    left: ProofWithPublicInputsTarget<D>,
+    right: ProofWithPublicInputsTarget<D>,
+}
+
+/// Proves each transaction with a leaf circuit, then folds the proofs pairwise up a binary
+/// tree of recursive circuits. Batches are padded to a power of two with a zero transaction.
+pub struct BatchProver {
+    leaf: CircuitData<F, C, D>,
+    leaf_inputs: Vec<Target>,
+    levels: Vec<AggregationLevel>,
+    /// Proof of the padding subtree at each level, built on first use
+    padding: Vec<ProofWithPublicInputs<F, C, D>>,
+}
+
+impl std::fmt::Debug for BatchProver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchProver").field("levels", &self.levels.len()).finish()
+    }
+}
+
+impl BatchProver {
+    pub fn new() -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+        let leaf_inputs = builder.add_virtual_targets(TRANSACTION_LIMBS);
+        for input in &leaf_inputs {
+            builder.range_check(*input, 32);
+        }
+        let digest = builder.hash_n_to_hash_no_pad::<PoseidonHash>(leaf_inputs.clone());
+        builder.register_public_inputs(&digest.elements);
+
+        Self { leaf: builder.build::<C>(), leaf_inputs, levels: Vec::new(), padding: Vec::new() }
+    }
+
+    /// Proof for a single transaction, without aggregation
+    pub fn prove_transaction(&self, transaction: &BridgeProofRequest) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let mut witness = PartialWitness::new();
+        for (target, value) in self.leaf_inputs.iter().zip(transaction.limbs()) {
+            witness.set_target(*target, value);
+        }
+        self.leaf.prove(witness)
+    }
+
+    pub fn verify_transaction(&self, proof: &ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        self.leaf.verify(proof.clone())
+    }
+
+    /// Proves `transactions` as one aggregated proof and measures the cost against proving and
+    /// verifying each transaction separately
+    pub fn prove_batch(&mut self, transactions: &[BridgeProofRequest]) -> Result<AggregatedBatchProof> {
+        ensure!(!transactions.is_empty(), "cannot prove an empty batch");
+        let n = transactions.len();
+        let depth = n.next_power_of_two().trailing_zeros() as usize;
+        self.build_levels(depth);
+
+        let started = Instant::now();
+        let mut proofs = transactions.iter()
+            .map(|transaction| self.prove_transaction(transaction))
+            .collect::<Result<Vec<_>>>()?;
+        let leaf_prove = started.elapsed();
+
+        // One leaf verification stands in for the on-chain verification each transaction
+        // would need without batching
+        let started = Instant::now();
+        self.verify_transaction(&proofs[0])?;
+        let leaf_verify = started.elapsed();
+
+        let started = Instant::now();
+        for level in 0..depth {
+            if proofs.len() % 2 == 1 {
+                proofs.push(self.padding_proof(level)?);
+            }
+            proofs = proofs.chunks(2)
+                .map(|pair| self.aggregate(level, &pair[0], &pair[1]))
+                .collect::<Result<Vec<_>>>()?;
+        }
+        let aggregation = started.elapsed();
+        let proof = proofs.pop().expect("one proof remains");
+
+        let started = Instant::now();
+        self.verify_aggregated(depth, &proof)?;
+        let batch_verify = started.elapsed();
+
+        let unbatched_cost_per_tx = ProofCost { prove: leaf_prove / n as u32, verify: leaf_verify };
+        let batched_cost_per_tx = ProofCost { prove: (leaf_prove + aggregation) / n as u32, verify: batch_verify / n as u32 };
+        debug!("Batch of {} proved in {:?} (+{:?} aggregation), verified in {:?}",
+               n, leaf_prove, aggregation, batch_verify);
+
+        Ok(AggregatedBatchProof { transactions: transactions.to_vec(), proof, unbatched_cost_per_tx, batched_cost_per_tx })
+    }
+
+    /// Verifies a proof produced by `prove_batch` for a batch of `batch_size` transactions
+    pub fn verify_batch(&self, batch_size: usize, proof: &ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        self.verify_aggregated(batch_size.next_power_of_two().trailing_zeros() as usize, proof)
+    }
+
+    fn verify_aggregated(&self, depth: usize, proof: &ProofWithPublicInputs<F, C, D>) -> Result<()> {
+        match depth {
+            0 => self.leaf.verify(proof.clone()),
+            depth => {
+                ensure!(depth <= self.levels.len(), "no circuit for batch depth {}", depth);
+                self.levels[depth - 1].data.verify(proof.clone())
+            }
+        }
+    }
+
+    fn build_levels(&mut self, depth: usize) {
+        while self.levels.len() < depth {
+            let inner = self.levels.last().map_or(&self.leaf, |level| &level.data);
+            let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let left = builder.add_virtual_proof_with_pis(&inner.common);
+            let right = builder.add_virtual_proof_with_pis(&inner.common);
+            let inner_verifier = builder.constant_verifier_data(&inner.verifier_only);
+            builder.verify_proof::<C>(&left, &inner_verifier, &inner.common);
+            builder.verify_proof::<C>(&right, &inner_verifier, &inner.common);
+
+            let preimage = left.public_inputs.iter().chain(&right.public_inputs).copied().collect();
+            let digest = builder.hash_n_to_hash_no_pad::<PoseidonHash>(preimage);
+            builder.register_public_inputs(&digest.elements);
+
+            info!("Built ZK aggregation circuit for batches of {}", 1usize << (self.levels.len() + 1));
+            self.levels.push(AggregationLevel { data: builder.build::<C>(), left, right });
+        }
+    }
+
+    fn aggregate(
+        &self,
+        level: usize,
+        left: &ProofWithPublicInputs<F, C, D>,
+        right: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<ProofWithPublicInputs<F, C, D>> {
+        let circuit = &self.levels[level];
+        let mut witness = PartialWitness::new();
+        witness.set_proof_with_pis_target(&circuit.left, left);
+        witness.set_proof_with_pis_target(&circuit.right, right);
+        circuit.data.prove(witness)
+    }
+
+    fn padding_proof(&mut self, level: usize) -> Result<ProofWithPublicInputs<F, C, D>> {
+        while self.padding.len() <= level {
+            let proof = match self.padding.last() {
+                None => self.prove_transaction(&BridgeProofRequest { tx_hash: [0; 32], amount: 0 })?,
+                Some(below) => self.aggregate(self.padding.len() - 1, below, below)?,
+            };
+            self.padding.push(proof);
+        }
+        Ok(self.padding[level].clone())
+    }
+}
+
+/// Queues bridge transactions and proves them in batches of up to `max_batch_size`
+#[derive(Debug)]
+pub struct ZkProofBatcher {
+    pub config: ZkProofBatchConfig,
+    pending: VecDeque<(BridgeProofRequest, Instant)>,
+    prover: BatchProver,
+    /// Aggregated proofs waiting to be submitted for on-chain verification
+    pub ready: VecDeque<AggregatedBatchProof>,
+}
+
+impl ZkProofBatcher {
+    pub fn new(config: ZkProofBatchConfig) -> Self {
+        Self { config, pending: VecDeque::new(), prover: BatchProver::new(), ready: VecDeque::new() }
+    }
+
+    pub fn enqueue(&mut self, transaction: BridgeProofRequest) {
+        self.pending.push_back((transaction, Instant::now()));
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// A full batch, or everything pending once the oldest transaction has waited too long
+    pub fn take_ready_batch(&mut self, now: Instant) -> Option<Vec<BridgeProofRequest>> {
+        let oldest = self.pending.front()?.1;
+        let waited = now.saturating_duration_since(oldest);
+        if self.pending.len() < self.config.max_batch_size && waited < Duration::from_millis(self.config.max_batch_wait_ms) {
+            return None;
+        }
+
+        let size = self.pending.len().min(self.config.max_batch_size);
+        Some(self.pending.drain(..size).map(|(transaction, _)| transaction).collect())
+    }
+
+    /// Proves every ready batch; returns how many batches were proved
+    pub fn prove_ready_batches(&mut self) -> Result<usize> {
+        let mut proved = 0;
+        while let Some(batch) = self.take_ready_batch(Instant::now()) {
+            let aggregated = self.prover.prove_batch(&batch)?;
+            info!("Proved batch of {} bridge transactions: {:.1}ms per transaction unbatched, {:.1}ms batched \
+                   (verification {:.1}ms -> {:.1}ms)",
+                  batch.len(),
+                  aggregated.unbatched_cost_per_tx.total().as_secs_f64() * 1000.0,
+                  aggregated.batched_cost_per_tx.total().as_secs_f64() * 1000.0,
+                  aggregated.unbatched_cost_per_tx.verify.as_secs_f64() * 1000.0,
+                  aggregated.batched_cost_per_tx.verify.as_secs_f64() * 1000.0);
+            self.ready.push_back(aggregated);
+            proved += 1;
+        }
+        Ok(proved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(seed: u8) -> BridgeProofRequest {
+        BridgeProofRequest { tx_hash: [seed; 32], amount: u64::from(seed) * 1_000_000_007 }
+    }
+
+    #[test]
+    fn test_batches_fill_or_time_out() {
+        let mut batcher = ZkProofBatcher::new(ZkProofBatchConfig { max_batch_size: 3, max_batch_wait_ms: 100 });
+        for seed in 0..4 {
+            batcher.enqueue(transaction(seed));
+        }
+
+        let now = Instant::now();
+        assert_eq!(batcher.take_ready_batch(now).unwrap().len(), 3);
+        assert_eq!(batcher.take_ready_batch(now), None, "one transaction has not waited long enough");
+        assert_eq!(batcher.take_ready_batch(now + Duration::from_millis(150)).unwrap(), vec![transaction(3)]);
+        assert_eq!(batcher.pending(), 0);
+    }
+
+    #[test]
+    fn test_aggregated_proof_verifies_and_binds_transactions() {
+        let mut prover = BatchProver::new();
+        let batch: Vec<_> = (1..=3).map(transaction).collect();
+
+        let aggregated = prover.prove_batch(&batch).unwrap();
+        prover.verify_batch(3, &aggregated.proof).unwrap();
+        assert_eq!(aggregated.proof.public_inputs.len(), 4);
+
+        // A different batch commits to different public inputs
+        let other = prover.prove_batch(&[transaction(1), transaction(2), transaction(4)]).unwrap();
+        assert_ne!(other.proof.public_inputs, aggregated.proof.public_inputs);
+
+        let single = prover.prove_batch(&batch[..1]).unwrap();
+        prover.verify_transaction(&single.proof).unwrap();
+        assert!(prover.prove_batch(&[]).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+// Integration tests for the continuous-optimizer binary
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use performance_optimizer::PlatformOptimizationResult;
+
+const BINARY: &str = env!("CARGO_BIN_EXE_continuous-optimizer");
+
+fn read_history(path: &std::path::Path) -> Vec<PlatformOptimizationResult> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("history line should be valid JSON"))
+        .collect()
+}
+
+#[test]
+fn test_continuous_optimizer_writes_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let history_path = dir.path().join("optimization_history.jsonl");
+
+    let mut child = Command::new(BINARY)
+        .env("OPTIMIZATION_INTERVAL_SECONDS", "1")
+        .env("OPTIMIZATION_HISTORY_PATH", &history_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to launch continuous-optimizer");
+
+    // Wait for at least two optimization runs to be recorded
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let mut entries = Vec::new();
+    while Instant::now() < deadline {
+        entries = read_history(&history_path);
+        if entries.len() >= 2 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert!(entries.len() >= 2, "expected at least 2 history entries, found {}", entries.len());
+    assert!(entries[0].timestamp <= entries[1].timestamp);
+}
+
+#[test]
+fn test_continuous_optimizer_rejects_zero_interval() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let status = Command::new(BINARY)
+        .env("OPTIMIZATION_INTERVAL_SECONDS", "0")
+        .env("OPTIMIZATION_HISTORY_PATH", dir.path().join("history.jsonl"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("failed to launch continuous-optimizer");
+
+    assert!(!status.success());
+}
@@ -26,6 +26,9 @@ use tracing::{info, error, warn};
 // Revenue stream integrations
 use revenue_engine::{RevenueEngine, RevenueStream, RevenueMetrics, RevenueProgress};
 
+mod rebalancer;
+use rebalancer::{RebalanceAction, RebalancerConfig, StreamRebalancer};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RevenueTarget {
     pub total_monthly: Decimal,
@@ -93,6 +96,7 @@ pub struct RevenueCoordinator {
     revenue_engine: Arc<RevenueEngine>,
     targets: RevenueTarget,
     revenue_cache: Arc<tokio::sync::RwLock<RevenueStatus>>,
+    rebalancer: StreamRebalancer,
 }
 
 impl RevenueCoordinator {
@@ -101,6 +105,7 @@ impl RevenueCoordinator {
 
         // Initialize revenue engine
         let revenue_engine = Arc::new(revenue_engine::initialize_revenue_engine().await?);
+        rebalancer::setup_rebalance_history(&revenue_engine.db_pool).await?;
         
         // Initial revenue status
         let initial_status = RevenueStatus {
@@ -119,6 +124,7 @@ impl RevenueCoordinator {
             revenue_engine,
             targets: RevenueTarget::default(),
             revenue_cache: Arc::new(tokio::sync::RwLock::new(initial_status)),
+            rebalancer: StreamRebalancer::new(RebalancerConfig::from_env()),
         })
     }
 
@@ -145,6 +151,7 @@ impl RevenueCoordinator {
             .route("/api/v1/revenue/analytics", get(get_revenue_analytics))
             .route("/api/v1/revenue/forecasting", get(get_revenue_forecasting))
             .route("/api/v1/revenue/optimization", post(trigger_optimization))
+            .route("/api/v1/revenue/optimization/actions", get(get_optimization_actions))
             .layer(
                 ServiceBuilder::new()
                     .layer(TraceLayer::new_for_http())
@@ -314,19 +321,16 @@ impl RevenueCoordinator {
         streams
     }
 
-    async fn optimize_revenue_streams(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let status = self.revenue_cache.read().await;
-        
-        // Identify underperforming streams and trigger optimizations
-        for stream in &status.top_performing_streams {
+    async fn optimize_revenue_streams(&self) -> Result<Vec<RebalanceAction>, Box<dyn std::error::Error>> {
+        let streams = self.revenue_cache.read().await.top_performing_streams.clone();
+
+        for stream in &streams {
             match stream.status {
                 StreamStatus::Critical => {
                     warn!("🔴 Critical stream: {} at {:.1}%", stream.stream_name, stream.progress_percentage);
-                    self.trigger_stream_optimization(&stream.stream_name).await?;
                 },
                 StreamStatus::Behind => {
                     warn!("🟡 Behind stream: {} at {:.1}%", stream.stream_name, stream.progress_percentage);
-                    self.suggest_stream_improvements(&stream.stream_name).await?;
                 },
                 StreamStatus::OnTrack => {
                     info!("🟢 On track: {} at {:.1}%", stream.stream_name, stream.progress_percentage);
@@ -338,44 +342,15 @@ impl RevenueCoordinator {
             }
         }
 
-        Ok(())
-    }
-
-    async fn trigger_stream_optimization(&self, stream_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        info!("⚡ Triggering optimization for stream: {}", stream_name);
-        
-        match stream_name {
-            "subscription" => {
-                // Trigger subscription optimization
-                // - Promotional campaigns
-                // - Feature enhancement
-                // - Pricing adjustments
-            },
-            "transaction" => {
-                // Trigger transaction optimization
-                // - Fee structure optimization
-                // - Performance improvements
-                // - Volume incentives
-            },
-            "enterprise" => {
-                // Trigger enterprise optimization
-                // - Sales team activation
-                // - Custom solutions
-                // - Partnership development
-            },
-            _ => {}
+        // Turn the gaps into actions, highest ROI stream first
+        let actions = self.rebalancer.plan(&streams, Utc::now());
+        for action in &actions {
+            info!("⚡ {} for {}: ${} gap, ~${} impact (ROI {:.0})",
+                action.action_type.as_str(), action.stream, action.revenue_gap, action.estimated_impact, action.roi);
         }
+        rebalancer::record_actions(&self.revenue_engine.db_pool, &actions).await?;
 
-        Ok(())
-    }
-
-    async fn suggest_stream_improvements(&self, stream_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        info!("💡 Suggesting improvements for stream: {}", stream_name);
-        
-        // Implementation would provide specific recommendations
-        // based on stream performance and market conditions
-
-        Ok(())
+        Ok(actions)
     }
 }
 
@@ -483,17 +458,40 @@ async fn get_revenue_forecasting(
 async fn trigger_optimization(
     Extension(coordinator): Extension<Arc<RevenueCoordinator>>
 ) -> Result<ResponseJson<serde_json::Value>, StatusCode> {
-    if let Err(e) = coordinator.optimize_revenue_streams().await {
-        error!("Failed to trigger optimization: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
+    let actions = match coordinator.optimize_revenue_streams().await {
+        Ok(actions) => actions,
+        Err(e) => {
+            error!("Failed to trigger optimization: {}", e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
 
     Ok(ResponseJson(serde_json::json!({
         "success": true,
-        "message": "Revenue optimization triggered"
+        "message": "Revenue optimization triggered",
+        "actions": actions
     })))
 }
 
+#[derive(Deserialize)]
+struct OptimizationActionsQuery {
+    limit: Option<i64>,
+}
+
+async fn get_optimization_actions(
+    Extension(coordinator): Extension<Arc<RevenueCoordinator>>,
+    Query(query): Query<OptimizationActionsQuery>
+) -> Result<ResponseJson<Vec<RebalanceAction>>, StatusCode> {
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    match rebalancer::recent_actions(&coordinator.revenue_engine.db_pool, limit).await {
+        Ok(actions) => Ok(ResponseJson(actions)),
+        Err(e) => {
+            error!("Failed to load optimization actions: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
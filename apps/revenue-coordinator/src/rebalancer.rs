@@ -0,0 +1,278 @@
+// Stream Rebalancer - Turns revenue gaps into concrete actions
+// Ranks streams by revenue per unit of engineering effort and decides where capacity goes
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use sqlx::PgPool;
+
+use crate::{StreamPerformance, StreamStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RebalanceActionType {
+    IncreaseFee,
+    RunPromotion,
+    ScaleInfrastructure,
+    EscalateToSales,
+}
+
+impl RebalanceActionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::IncreaseFee => "increase_fee",
+            Self::RunPromotion => "run_promotion",
+            Self::ScaleInfrastructure => "scale_infrastructure",
+            Self::EscalateToSales => "escalate_to_sales",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "increase_fee" => Some(Self::IncreaseFee),
+            "run_promotion" => Some(Self::RunPromotion),
+            "scale_infrastructure" => Some(Self::ScaleInfrastructure),
+            "escalate_to_sales" => Some(Self::EscalateToSales),
+            _ => None,
+        }
+    }
+
+    // Share of a stream's revenue gap the action is expected to close
+    pub fn gap_capture(&self) -> Decimal {
+        match self {
+            Self::IncreaseFee => Decimal::new(10, 2),
+            Self::RunPromotion => Decimal::new(25, 2),
+            Self::ScaleInfrastructure => Decimal::new(35, 2),
+            Self::EscalateToSales => Decimal::new(40, 2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RebalanceAction {
+    pub stream: String,
+    pub action_type: RebalanceActionType,
+    pub estimated_impact: Decimal,
+    pub revenue_gap: Decimal,
+    pub roi: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalancerConfig {
+    // Engineering effort allocated to each stream, in engineer-weeks per month
+    pub engineering_effort: HashMap<String, f64>,
+}
+
+impl Default for RebalancerConfig {
+    fn default() -> Self {
+        Self {
+            engineering_effort: HashMap::from([
+                ("subscription".to_string(), 4.0),
+                ("transaction".to_string(), 6.0),
+                ("enterprise".to_string(), 3.0),
+            ]),
+        }
+    }
+}
+
+impl RebalancerConfig {
+    // Reads `REBALANCER_ENGINEERING_EFFORT` as `stream=effort` pairs separated by commas,
+    // e.g. `subscription=4,transaction=6`. Streams it leaves out keep their default effort.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(value) = std::env::var("REBALANCER_ENGINEERING_EFFORT") {
+            for pair in value.split(',') {
+                let Some((stream, effort)) = pair.split_once('=') else { continue };
+                if let Ok(effort) = effort.trim().parse::<f64>() {
+                    config.engineering_effort.insert(stream.trim().to_string(), effort);
+                }
+            }
+        }
+        config
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamRebalancer {
+    pub config: RebalancerConfig,
+}
+
+impl StreamRebalancer {
+    pub fn new(config: RebalancerConfig) -> Self {
+        Self { config }
+    }
+
+    // Revenue per unit of allocated engineering effort. Streams without an allocation are
+    // treated as one unit so they still rank by revenue.
+    pub fn roi(&self, stream: &StreamPerformance) -> f64 {
+        let effort = self.config.engineering_effort.get(&stream.stream_name).copied()
+            .filter(|effort| *effort > 0.0)
+            .unwrap_or(1.0);
+        stream.current_revenue.to_f64().unwrap_or(0.0) / effort
+    }
+
+    // One action per stream short of its target, highest ROI first. Streams whose ROI is
+    // above the median of those behind get more capacity; the rest pull their own lever, and
+    // critical streams go to sales.
+    pub fn plan(&self, streams: &[StreamPerformance], now: DateTime<Utc>) -> Vec<RebalanceAction> {
+        let mut behind: Vec<(&StreamPerformance, f64)> = streams.iter()
+            .filter(|stream| stream.target_revenue > stream.current_revenue)
+            .map(|stream| (stream, self.roi(stream)))
+            .collect();
+        behind.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let median_roi = behind.get(behind.len().saturating_sub(1) / 2).map(|(_, roi)| *roi);
+
+        behind.into_iter()
+            .map(|(stream, roi)| {
+                let action_type = match stream.status {
+                    StreamStatus::Critical => RebalanceActionType::EscalateToSales,
+                    _ if median_roi.is_some_and(|median| roi > median) => RebalanceActionType::ScaleInfrastructure,
+                    _ => Self::stream_lever(&stream.stream_name),
+                };
+                let revenue_gap = stream.target_revenue - stream.current_revenue;
+
+                RebalanceAction {
+                    stream: stream.stream_name.clone(),
+                    action_type,
+                    estimated_impact: (revenue_gap * action_type.gap_capture()).round_dp(2),
+                    revenue_gap,
+                    roi,
+                    created_at: now,
+                }
+            })
+            .collect()
+    }
+
+    fn stream_lever(stream_name: &str) -> RebalanceActionType {
+        match stream_name {
+            "subscription" => RebalanceActionType::RunPromotion,
+            "transaction" => RebalanceActionType::IncreaseFee,
+            _ => RebalanceActionType::EscalateToSales,
+        }
+    }
+}
+
+pub async fn setup_rebalance_history(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(r#"
+        CREATE TABLE IF NOT EXISTS rebalance_history (
+            id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+            stream VARCHAR NOT NULL,
+            action_type VARCHAR NOT NULL,
+            estimated_impact DECIMAL(15,2) NOT NULL,
+            revenue_gap DECIMAL(15,2) NOT NULL,
+            roi DOUBLE PRECISION NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+        )
+    "#).execute(pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_rebalance_history_created ON rebalance_history (created_at)")
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn record_actions(pool: &PgPool, actions: &[RebalanceAction]) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    for action in actions {
+        sqlx::query(r#"
+            INSERT INTO rebalance_history (id, stream, action_type, estimated_impact, revenue_gap, roi, created_at)
+            VALUES ($1, $2, $3, $4::TEXT::DECIMAL, $5::TEXT::DECIMAL, $6, $7)
+        "#)
+            .bind(Uuid::new_v4())
+            .bind(&action.stream)
+            .bind(action.action_type.as_str())
+            .bind(action.estimated_impact.to_string())
+            .bind(action.revenue_gap.to_string())
+            .bind(action.roi)
+            .bind(action.created_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
+}
+
+pub async fn recent_actions(pool: &PgPool, limit: i64) -> Result<Vec<RebalanceAction>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, String, String, String, f64, DateTime<Utc>)>(r#"
+        SELECT stream, action_type, estimated_impact::TEXT, revenue_gap::TEXT, roi, created_at
+        FROM rebalance_history
+        ORDER BY created_at DESC
+        LIMIT $1
+    "#)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter()
+        .filter_map(|(stream, action_type, impact, gap, roi, created_at)| Some(RebalanceAction {
+            stream,
+            action_type: RebalanceActionType::parse(&action_type)?,
+            estimated_impact: impact.parse().ok()?,
+            revenue_gap: gap.parse().ok()?,
+            roi,
+            created_at,
+        }))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(name: &str, current: i64, target: i64, status: StreamStatus) -> StreamPerformance {
+        StreamPerformance {
+            stream_name: name.to_string(),
+            current_revenue: Decimal::new(current, 0),
+            target_revenue: Decimal::new(target, 0),
+            progress_percentage: current as f64 / target as f64 * 100.0,
+            growth_rate: 15.0,
+            projected_end_of_month: Decimal::new(current, 0),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_plan_ranks_by_roi_and_picks_actions() {
+        let rebalancer = StreamRebalancer::new(RebalancerConfig::default());
+        let streams = vec![
+            stream("subscription", 160_000, 195_000, StreamStatus::Behind),
+            stream("transaction", 480_000, 645_000, StreamStatus::Behind),
+            stream("enterprise", 90_000, 300_000, StreamStatus::Critical),
+            stream("trading", 1_300_000, 1_295_000, StreamStatus::OnTrack),
+        ];
+        let actions = rebalancer.plan(&streams, Utc::now());
+
+        let planned: Vec<_> = actions.iter().map(|a| (a.stream.as_str(), a.action_type)).collect();
+        assert_eq!(planned, vec![
+            ("transaction", RebalanceActionType::ScaleInfrastructure),
+            ("subscription", RebalanceActionType::RunPromotion),
+            ("enterprise", RebalanceActionType::EscalateToSales),
+        ]);
+        assert_eq!(actions[0].revenue_gap, Decimal::new(165_000, 0));
+        assert_eq!(actions[0].estimated_impact, Decimal::new(57_750, 0));
+        assert_eq!(actions[0].roi, 80_000.0);
+    }
+
+    #[test]
+    fn test_no_actions_when_on_target() {
+        let rebalancer = StreamRebalancer::new(RebalancerConfig::default());
+        let streams = vec![stream("subscription", 200_000, 195_000, StreamStatus::OnTrack)];
+        assert!(rebalancer.plan(&streams, Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn test_action_type_round_trips() {
+        for action_type in [
+            RebalanceActionType::IncreaseFee,
+            RebalanceActionType::RunPromotion,
+            RebalanceActionType::ScaleInfrastructure,
+            RebalanceActionType::EscalateToSales,
+        ] {
+            assert_eq!(RebalanceActionType::parse(action_type.as_str()), Some(action_type));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+// Response caching benchmarks against a synthetic axum server
+// Compares full responses with 304 revalidation and Brotli/gzip compressed payloads
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, HeaderValue, Request},
+    middleware::from_fn,
+    routing::get,
+    Router,
+};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use revenue_engine::http_cache::{cache_headers, compression_layer};
+use tower::{ServiceBuilder, ServiceExt};
+use tower_http::compression::CompressionLevel;
+
+/// Dashboard-sized JSON payload
+fn dashboard_payload() -> String {
+    let entries: Vec<_> = (0..500)
+        .map(|i| serde_json::json!({ "day": i, "revenue": 69_833.33 + i as f64, "stream": "mining_pool" }))
+        .collect();
+    serde_json::to_string(&entries).unwrap()
+}
+
+fn router(payload: String, cached: bool) -> Router {
+    let router = Router::new().route("/api/v1/revenue/dashboard", get(move || async move { payload }));
+    if cached {
+        router.layer(
+            ServiceBuilder::new()
+                .layer(compression_layer(CompressionLevel::Default))
+                .layer(from_fn(cache_headers)),
+        )
+    } else {
+        router
+    }
+}
+
+async fn fetch(router: Router, headers: &[(header::HeaderName, HeaderValue)]) -> usize {
+    let mut request = Request::builder().uri("/api/v1/revenue/dashboard");
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    to_bytes(response.into_body(), usize::MAX).await.unwrap().len()
+}
+
+fn bench_dashboard(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let payload = dashboard_payload();
+    let baseline = router(payload.clone(), false);
+    let cached = router(payload.clone(), true);
+
+    let etag = runtime.block_on(async {
+        let response = cached.clone()
+            .oneshot(Request::builder().uri("/api/v1/revenue/dashboard").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers()[header::ETAG].clone()
+    });
+
+    let mut group = c.benchmark_group("dashboard_response");
+    group.throughput(Throughput::Bytes(payload.len() as u64));
+
+    group.bench_function("uncached_identity", |b| {
+        b.to_async(&runtime).iter(|| fetch(baseline.clone(), &[]))
+    });
+    group.bench_function("etag_revalidation_304", |b| {
+        let headers = [(header::IF_NONE_MATCH, etag.clone())];
+        b.to_async(&runtime).iter(|| fetch(cached.clone(), &headers))
+    });
+    group.bench_function("brotli", |b| {
+        let headers = [(header::ACCEPT_ENCODING, HeaderValue::from_static("br"))];
+        b.to_async(&runtime).iter(|| fetch(cached.clone(), &headers))
+    });
+    group.bench_function("gzip", |b| {
+        let headers = [(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"))];
+        b.to_async(&runtime).iter(|| fetch(cached.clone(), &headers))
+    });
+    group.finish();
+
+    // Bytes on the wire are the main win; report them alongside the timings
+    runtime.block_on(async {
+        let identity = fetch(baseline.clone(), &[]).await;
+        let br = fetch(cached.clone(), &[(header::ACCEPT_ENCODING, HeaderValue::from_static("br"))]).await;
+        let gzip = fetch(cached.clone(), &[(header::ACCEPT_ENCODING, HeaderValue::from_static("gzip"))]).await;
+        println!("dashboard bytes: identity={} br={} gzip={} not_modified=0", identity, br, gzip);
+    });
+}
+
+criterion_group!(benches, bench_dashboard);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+-- Every processed revenue stream, one row per event
+CREATE TABLE IF NOT EXISTS revenue_records (
+    id UUID PRIMARY KEY,
+    stream_type TEXT NOT NULL,
+    user_id UUID,
+    amount NUMERIC NOT NULL,
+    fee NUMERIC NOT NULL DEFAULT 0,
+    timestamp TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    metadata JSONB NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX IF NOT EXISTS idx_revenue_records_stream_type ON revenue_records (stream_type);
+CREATE INDEX IF NOT EXISTS idx_revenue_records_user ON revenue_records (user_id);
+CREATE INDEX IF NOT EXISTS idx_revenue_records_timestamp ON revenue_records (timestamp);
+
+-- Per-stream totals, refreshed in the same transaction as each insert
+CREATE MATERIALIZED VIEW IF NOT EXISTS aggregate_revenue AS
+SELECT
+    stream_type,
+    COUNT(*) AS record_count,
+    SUM(amount) AS total_amount,
+    SUM(fee) AS total_fees,
+    MAX(timestamp) AS last_recorded_at
+FROM revenue_records
+GROUP BY stream_type;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_aggregate_revenue_stream_type ON aggregate_revenue (stream_type);
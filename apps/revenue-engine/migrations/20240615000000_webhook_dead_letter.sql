@@ -0,0 +1,13 @@
+-- Webhook deliveries that exhausted their retries, kept for manual replay
+CREATE TABLE IF NOT EXISTS webhook_dead_letter (
+    id UUID PRIMARY KEY,
+    endpoint_url TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    replayed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_dead_letter_pending ON webhook_dead_letter (created_at) WHERE replayed_at IS NULL;
@@ -0,0 +1,13 @@
+-- Re-engagement emails already sent, one per stage for each missed billing date
+CREATE TABLE IF NOT EXISTS churn_emails_sent (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    subscription_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    stage TEXT NOT NULL,
+    billing_date TIMESTAMPTZ NOT NULL,
+    recipient TEXT NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (subscription_id, stage, billing_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_churn_emails_sent_user ON churn_emails_sent (user_id);
@@ -0,0 +1,7 @@
+-- Stripe events already handled, keyed by event id so redeliveries are acknowledged without
+-- being applied twice
+CREATE TABLE IF NOT EXISTS processed_webhooks (
+    event_id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+-- Tier ordering matches SubscriptionTier, lowest first
+DO $$ BEGIN
+    CREATE TYPE subscription_tier AS ENUM ('basic', 'professional', 'enterprise', 'custom');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- Features gated by subscription tier; a flag is on for users at or above min_tier while enabled
+CREATE TABLE IF NOT EXISTS feature_flags (
+    flag_name TEXT PRIMARY KEY,
+    min_tier subscription_tier NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
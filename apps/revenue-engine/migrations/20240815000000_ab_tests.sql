@@ -0,0 +1,24 @@
+-- Subscription pricing experiments; variants is the ordered list of arms, control first
+CREATE TABLE IF NOT EXISTS ab_experiments (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    tier subscription_tier NOT NULL,
+    variants JSONB NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- At most one running experiment per tier, so a user's price is never ambiguous
+CREATE UNIQUE INDEX IF NOT EXISTS idx_ab_experiments_active_tier ON ab_experiments (tier) WHERE active;
+
+-- Exposure, conversion and churn events attributed to the variant the user was assigned
+CREATE TABLE IF NOT EXISTS ab_test_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    experiment_id UUID NOT NULL REFERENCES ab_experiments (id),
+    variant TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ab_test_events_experiment ON ab_test_events (experiment_id, variant);
@@ -0,0 +1,28 @@
+-- Standard VAT rate of each EU member state; countries not listed are billed sales tax
+CREATE TABLE IF NOT EXISTS vat_rates (
+    country_code CHAR(2) PRIMARY KEY,
+    standard_rate DECIMAL(5,4) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO vat_rates (country_code, standard_rate) VALUES
+    ('AT', 0.2000), ('BE', 0.2100), ('BG', 0.2000), ('HR', 0.2500), ('CY', 0.1900),
+    ('CZ', 0.2100), ('DK', 0.2500), ('EE', 0.2200), ('FI', 0.2550), ('FR', 0.2000),
+    ('DE', 0.1900), ('GR', 0.2400), ('HU', 0.2700), ('IE', 0.2300), ('IT', 0.2200),
+    ('LV', 0.2100), ('LT', 0.2100), ('LU', 0.1700), ('MT', 0.1800), ('NL', 0.2100),
+    ('PL', 0.2300), ('PT', 0.2300), ('RO', 0.1900), ('SK', 0.2000), ('SI', 0.2200),
+    ('ES', 0.2100), ('SE', 0.2500)
+ON CONFLICT (country_code) DO NOTHING;
+
+-- Where each customer is billed; vat_id is stored without separators, prefix included
+CREATE TABLE IF NOT EXISTS billing_addresses (
+    user_id UUID PRIMARY KEY,
+    name TEXT,
+    line1 TEXT NOT NULL,
+    line2 TEXT,
+    city TEXT NOT NULL,
+    postal_code TEXT,
+    country_code CHAR(2) NOT NULL,
+    vat_id TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,12 @@
+-- Latest BG/NBD lifetime value prediction per active subscriber; replaced wholesale on each refresh
+CREATE TABLE IF NOT EXISTS clv_predictions (
+    user_id UUID PRIMARY KEY,
+    expected_transactions DOUBLE PRECISION NOT NULL,
+    average_revenue DECIMAL(15,2) NOT NULL,
+    predicted_clv DECIMAL(15,2) NOT NULL,
+    probability_alive DOUBLE PRECISION NOT NULL,
+    horizon_months INTEGER NOT NULL,
+    predicted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_clv_predictions_clv ON clv_predictions (predicted_clv);
@@ -0,0 +1,28 @@
+-- Subscriptions, previously created by SubscriptionManager at startup
+CREATE TABLE IF NOT EXISTS subscriptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    tier VARCHAR NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'active',
+    billing_cycle VARCHAR NOT NULL DEFAULT 'monthly',
+    amount DECIMAL(10,2) NOT NULL,
+    currency VARCHAR(10) NOT NULL DEFAULT 'USD',
+    next_billing_date TIMESTAMP NOT NULL,
+    stripe_subscription_id VARCHAR UNIQUE,
+    stripe_customer_id VARCHAR,
+    trial_end_date TIMESTAMP,
+    cancelled_at TIMESTAMP,
+    paused_at TIMESTAMP,
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_subscriptions_user_id ON subscriptions(user_id);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_status ON subscriptions(status);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_tier ON subscriptions(tier);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_billing_date ON subscriptions(next_billing_date);
+CREATE INDEX IF NOT EXISTS idx_subscriptions_stripe ON subscriptions(stripe_subscription_id);
+
+-- Pause tracking; resume_date is NULL for an open-ended pause
+ALTER TABLE subscriptions ADD COLUMN IF NOT EXISTS resume_date TIMESTAMP;
@@ -0,0 +1,59 @@
+-- Enterprise contracts, previously created by EnterpriseRevenueManager at startup
+CREATE TABLE IF NOT EXISTS enterprise_contracts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL,
+    client_name VARCHAR NOT NULL,
+    contract_tier VARCHAR NOT NULL,
+    services JSONB NOT NULL DEFAULT '[]',
+    annual_value DECIMAL(15,2) NOT NULL,
+    monthly_value DECIMAL(15,2) NOT NULL,
+    payment_terms VARCHAR NOT NULL DEFAULT 'monthly',
+    start_date TIMESTAMP NOT NULL,
+    end_date TIMESTAMP NOT NULL,
+    auto_renewal BOOLEAN DEFAULT true,
+    status VARCHAR NOT NULL DEFAULT 'active',
+    service_credits DECIMAL(15,2) DEFAULT 0,
+    compliance_requirements JSONB DEFAULT '[]',
+    dedicated_resources JSONB DEFAULT '[]',
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_client ON enterprise_contracts(client_id);
+CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_status ON enterprise_contracts(status);
+CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_tier ON enterprise_contracts(contract_tier);
+CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_dates ON enterprise_contracts(start_date, end_date);
+
+-- SLA terms agreed in the contract, as a ContractDocument
+ALTER TABLE enterprise_contracts ADD COLUMN IF NOT EXISTS contract_document JSONB;
+
+-- Each contracted KPI with its latest measurement, and the violations credited against it
+CREATE TABLE IF NOT EXISTS sla_metrics (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    contract_id UUID NOT NULL REFERENCES enterprise_contracts(id),
+    kpi VARCHAR NOT NULL,
+    target DECIMAL(12,4) NOT NULL,
+    unit VARCHAR NOT NULL,
+    actual DECIMAL(12,4),
+    measured_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT NOW(),
+    updated_at TIMESTAMP DEFAULT NOW(),
+    UNIQUE (contract_id, kpi)
+);
+
+CREATE TABLE IF NOT EXISTS sla_violations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    contract_id UUID NOT NULL REFERENCES enterprise_contracts(id),
+    sla_metric_id UUID NOT NULL REFERENCES sla_metrics(id),
+    kpi VARCHAR NOT NULL,
+    target DECIMAL(12,4) NOT NULL,
+    actual DECIMAL(12,4) NOT NULL,
+    period_start TIMESTAMP NOT NULL,
+    penalty_credit DECIMAL(15,2) NOT NULL,
+    credited_invoice_id UUID,
+    detected_at TIMESTAMP DEFAULT NOW(),
+    UNIQUE (sla_metric_id, period_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sla_violations_contract ON sla_violations(contract_id, detected_at);
@@ -0,0 +1,58 @@
+-- RFQ desk: inventory, client collateral, quotes, executed trades and inventory rebalancing
+CREATE TABLE IF NOT EXISTS otc_inventory (
+    token VARCHAR PRIMARY KEY,
+    balance DECIMAL(30,18) NOT NULL DEFAULT 0,
+    mark_price DECIMAL(30,18) NOT NULL,
+    min_balance DECIMAL(30,18) NOT NULL DEFAULT 0,
+    target_balance DECIMAL(30,18) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS otc_client_collateral (
+    client_id UUID NOT NULL,
+    token VARCHAR NOT NULL,
+    available DECIMAL(30,18) NOT NULL DEFAULT 0,
+    locked DECIMAL(30,18) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (client_id, token)
+);
+
+CREATE TABLE IF NOT EXISTS otc_quotes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL,
+    side VARCHAR NOT NULL,
+    token_pair VARCHAR NOT NULL,
+    quantity DECIMAL(30,18) NOT NULL,
+    mid_price DECIMAL(30,18) NOT NULL,
+    price DECIMAL(30,18) NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'open', -- 'open', 'accepted', 'expired'
+    valid_until TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS otc_trades (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    quote_id UUID NOT NULL UNIQUE REFERENCES otc_quotes(id),
+    client_id UUID NOT NULL,
+    side VARCHAR NOT NULL,
+    token_pair VARCHAR NOT NULL,
+    quantity DECIMAL(30,18) NOT NULL,
+    price DECIMAL(30,18) NOT NULL,
+    notional DECIMAL(30,18) NOT NULL,
+    spread_revenue DECIMAL(30,18) NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'executed', -- 'executed', 'settled'
+    executed_at TIMESTAMP NOT NULL,
+    settlement_date TIMESTAMP NOT NULL,
+    settled_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS otc_rebalance_orders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    token VARCHAR NOT NULL,
+    amount DECIMAL(30,18) NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_otc_quotes_status ON otc_quotes(status, valid_until);
+CREATE INDEX IF NOT EXISTS idx_otc_trades_settlement ON otc_trades(status, settlement_date);
@@ -0,0 +1,35 @@
+-- Threshold custody: each client's key, custodian share commitments, withdrawals and their approvals
+CREATE TABLE IF NOT EXISTS custody_keys (
+    client_id UUID PRIMARY KEY,
+    public_key VARCHAR NOT NULL,
+    threshold SMALLINT NOT NULL,
+    custodian_count SMALLINT NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS custody_key_shares (
+    client_id UUID NOT NULL REFERENCES custody_keys(client_id),
+    custodian_id UUID NOT NULL,
+    share_commitment VARCHAR NOT NULL,
+    created_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (client_id, custodian_id)
+);
+
+CREATE TABLE IF NOT EXISTS custody_withdrawals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    client_id UUID NOT NULL REFERENCES custody_keys(client_id),
+    destination VARCHAR NOT NULL,
+    amount DECIMAL(30,18) NOT NULL,
+    required_approvals SMALLINT NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending', -- 'pending', 'executed'
+    signature VARCHAR,
+    created_at TIMESTAMP DEFAULT NOW(),
+    executed_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS custody_withdrawal_approvals (
+    withdrawal_id UUID NOT NULL REFERENCES custody_withdrawals(id),
+    custodian_id UUID NOT NULL,
+    approved_at TIMESTAMP DEFAULT NOW(),
+    PRIMARY KEY (withdrawal_id, custodian_id)
+);
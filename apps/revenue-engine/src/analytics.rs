@@ -7,12 +7,23 @@ use sqlx::PgPool;
 use redis::aio::ConnectionManager;
 use uuid::Uuid;
 use rust_decimal::Decimal;
-use chrono::{DateTime, Utc, Duration};
+use rust_decimal::prelude::ToPrimitive;
+use chrono::{DateTime, Utc, Duration, NaiveDate};
 use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use linfa::prelude::*;
+use linfa_linear::LinearRegression;
+use ndarray::{Array1, Array2};
 
 use crate::core::{RevenueError, RevenueResult};
 use crate::subscription::{SubscriptionTier, SubscriptionManager};
 
+pub mod clv;
+pub mod scenarios;
+
+use clv::ClvAnalysis;
+use scenarios::{RevenueScenario, ScenarioAnalysisResult, MONTHLY_REVENUE_TARGET, run_scenarios};
+
 // Analytics service tiers
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AnalyticsTier {
@@ -152,6 +163,7 @@ pub struct RevenueForecast {
     pub forecast_period: String,
     pub predicted_revenue: Decimal,
     pub confidence_interval: (Decimal, Decimal),
+    pub streams: Vec<StreamForecast>,
     pub growth_factors: Vec<GrowthFactor>,
     pub risk_factors: Vec<RiskFactor>,
     pub recommendations: Vec<String>,
@@ -159,6 +171,35 @@ pub struct RevenueForecast {
     pub last_updated: DateTime<Utc>,
 }
 
+// How a stream's forecast was produced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastMethod {
+    LinearRegression,
+    // Too little history to fit a trend; the last observed day is carried forward
+    LastValue,
+}
+
+// Per-stream daily revenue forecast
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamForecast {
+    pub stream_type: String,
+    pub method: ForecastMethod,
+    pub data_points: usize,
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: Option<f64>,
+    pub points: Vec<ForecastPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastPoint {
+    pub date: NaiveDate,
+    pub estimate: Decimal,
+    pub interval_80: (Decimal, Decimal),
+    pub interval_95: (Decimal, Decimal),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GrowthFactor {
     pub factor: String,
@@ -603,6 +644,12 @@ impl AnalyticsRevenueManager {
             Decimal::ZERO
         };
 
+        // Mean BG/NBD prediction once the CLV model has run; until then 24 months at the current ARPU
+        let customer_lifetime_value = match ClvAnalysis::new(self.db_pool.clone()).average_clv().await? {
+            Some(clv) => clv,
+            None => arpu * Decimal::new(24, 0),
+        };
+
         // Generate forecasts
         let (forecast_30d, forecast_90d, forecast_12m) = self.generate_revenue_forecasts().await?;
 
@@ -612,7 +659,7 @@ impl AnalyticsRevenueManager {
             annual_recurring_revenue: mrr_amount * Decimal::new(12, 0),
             revenue_by_stream,
             revenue_by_tier,
-            customer_lifetime_value: arpu * Decimal::new(24, 0), // Simplified: 24 months average
+            customer_lifetime_value,
             average_revenue_per_user: arpu,
             churn_rate: 5.0, // Simplified
             growth_rate: 15.0, // Simplified
@@ -749,6 +796,16 @@ impl AnalyticsRevenueManager {
     }
 }
 
+// Days of revenue_records history the forecast is fitted on
+pub const FORECAST_HISTORY_DAYS: i32 = 90;
+
+// Streams with fewer observed days fall back to the last known value
+pub const MIN_REGRESSION_POINTS: usize = 14;
+
+// Two-sided standard normal quantiles for the 80% and 95% intervals
+const Z_80: f64 = 1.281_551_565_545;
+const Z_95: f64 = 1.959_963_984_540;
+
 // Revenue forecasting engine using ML models
 #[derive(Debug)]
 pub struct RevenueForecasting {
@@ -765,21 +822,35 @@ impl RevenueForecasting {
     pub async fn generate_forecast(&self, period_days: i32) -> RevenueResult<RevenueForecast> {
         tracing::info!("🔮 Generating revenue forecast for {} days", period_days);
 
-        // Get historical data for model training
-        let historical_data = self.get_historical_revenue_data(90).await?;
-        
-        // Train or load forecasting model
-        let model_accuracy = self.train_forecasting_model(&historical_data).await?;
-        
-        // Generate predictions
-        let predicted_revenue = self.predict_revenue(period_days, &historical_data).await?;
-        
-        // Calculate confidence intervals
-        let confidence_interval = self.calculate_confidence_interval(predicted_revenue, model_accuracy);
-        
+        let forecast_days = u32::try_from(period_days)
+            .map_err(|_| RevenueError::Validation(format!("Invalid forecast period: {} days", period_days)))?;
+
+        // Fit one model per revenue stream
+        let history = self.get_historical_revenue_data(FORECAST_HISTORY_DAYS).await?;
+        let today = Utc::now().date_naive();
+        let streams = history
+            .iter()
+            .map(|(stream_type, observations)| forecast_stream(stream_type, observations, today, forecast_days))
+            .collect::<RevenueResult<Vec<_>>>()?;
+
+        // Totals over the horizon; summing per-day bounds gives a conservative interval
+        let predicted_revenue = streams.iter().flat_map(|s| &s.points).map(|p| p.estimate).sum();
+        let confidence_interval = streams.iter().flat_map(|s| &s.points).fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(lower, upper), p| (lower + p.interval_95.0, upper + p.interval_95.1),
+        );
+
+        let fitted: Vec<f64> = streams.iter().filter_map(|s| s.r_squared).collect();
+        let model_accuracy = if fitted.is_empty() {
+            0.0
+        } else {
+            fitted.iter().sum::<f64>() / fitted.len() as f64
+        };
+        tracing::info!("🤖 Fitted {} of {} streams, mean R²: {:.2}", fitted.len(), streams.len(), model_accuracy);
+
         // Identify growth and risk factors
-        let growth_factors = self.identify_growth_factors(&historical_data);
-        let risk_factors = self.identify_risk_factors(&historical_data);
+        let growth_factors = self.identify_growth_factors(&streams);
+        let risk_factors = self.identify_risk_factors(&streams);
         
         // Generate recommendations
         let recommendations = self.generate_recommendations(&growth_factors, &risk_factors);
@@ -788,6 +859,7 @@ impl RevenueForecasting {
             forecast_period: format!("{} days", period_days),
             predicted_revenue,
             confidence_interval,
+            streams,
             growth_factors,
             risk_factors,
             recommendations,
@@ -796,91 +868,48 @@ impl RevenueForecasting {
         })
     }
 
-    // Get historical revenue data
-    async fn get_historical_revenue_data(&self, days: i32) -> RevenueResult<Vec<(DateTime<Utc>, Decimal)>> {
+    // Monte Carlo trials of each scenario's monthly revenue, compared against the $2M target
+    pub fn scenario_analysis(
+        &self,
+        scenarios: Vec<RevenueScenario>,
+        simulations: u32
+    ) -> RevenueResult<ScenarioAnalysisResult> {
+        tracing::info!("🎲 Running {} simulations for {} revenue scenarios", simulations, scenarios.len());
+        run_scenarios(&scenarios, simulations, MONTHLY_REVENUE_TARGET, &mut rand::thread_rng())
+    }
+
+    // Daily platform revenue per stream, oldest day first
+    async fn get_historical_revenue_data(&self, days: i32) -> RevenueResult<Vec<(String, Vec<(NaiveDate, f64)>)>> {
         let records = sqlx::query!(
             r#"
             SELECT 
-                DATE_TRUNC('day', created_at) as day,
-                SUM(amount) as daily_revenue
-            FROM revenue_streams 
-            WHERE created_at >= NOW() - INTERVAL '%d days'
-            GROUP BY DATE_TRUNC('day', created_at)
-            ORDER BY day
+                stream_type,
+                (timestamp AT TIME ZONE 'UTC')::date as "day!",
+                SUM(fee) as "daily_revenue!"
+            FROM revenue_records 
+            WHERE timestamp >= NOW() - make_interval(days => $1)
+            GROUP BY stream_type, "day!"
+            ORDER BY stream_type, "day!"
             "#,
             days
         ).fetch_all(&self.db_pool).await?;
 
-        let data = records.into_iter()
-            .map(|row| (row.day, row.daily_revenue.unwrap_or(Decimal::ZERO)))
-            .collect();
-
-        Ok(data)
-    }
-
-    // Train forecasting model (simplified)
-    async fn train_forecasting_model(&self, data: &[(DateTime<Utc>, Decimal)]) -> RevenueResult<f64> {
-        // Simplified model training - in production, would use actual ML frameworks
-        let accuracy = if data.len() > 30 {
-            0.85 // Good accuracy with sufficient data
-        } else if data.len() > 10 {
-            0.70 // Medium accuracy
-        } else {
-            0.50 // Low accuracy with little data
-        };
-
-        tracing::info!("🤖 Model trained with accuracy: {:.2}%", accuracy * 100.0);
-        Ok(accuracy)
-    }
-
-    // Predict revenue (simplified linear model)
-    async fn predict_revenue(&self, days: i32, data: &[(DateTime<Utc>, Decimal)]) -> RevenueResult<Decimal> {
-        if data.is_empty() {
-            return Ok(Decimal::ZERO);
-        }
-
-        // Calculate average daily revenue
-        let total_revenue: Decimal = data.iter().map(|(_, revenue)| *revenue).sum();
-        let avg_daily_revenue = total_revenue / Decimal::new(data.len() as i64, 0);
-
-        // Apply growth trend
-        let growth_rate = self.calculate_trend(data);
-        let predicted_daily = avg_daily_revenue * (Decimal::ONE + growth_rate);
-        
-        Ok(predicted_daily * Decimal::new(days as i64, 0))
-    }
-
-    // Calculate trend from historical data
-    fn calculate_trend(&self, data: &[(DateTime<Utc>, Decimal)]) -> Decimal {
-        if data.len() < 2 {
-            return Decimal::ZERO;
-        }
-
-        let first_half: Decimal = data.iter()
-            .take(data.len() / 2)
-            .map(|(_, revenue)| *revenue)
-            .sum();
-        
-        let second_half: Decimal = data.iter()
-            .skip(data.len() / 2)
-            .map(|(_, revenue)| *revenue)
-            .sum();
-
-        if first_half == Decimal::ZERO {
-            return Decimal::ZERO;
+        let mut streams: Vec<(String, Vec<(NaiveDate, f64)>)> = Vec::new();
+        for row in records {
+            let revenue = row.daily_revenue.to_f64().unwrap_or(0.0);
+            match streams.last_mut() {
+                Some((stream_type, observations)) if *stream_type == row.stream_type => {
+                    observations.push((row.day, revenue));
+                }
+                _ => streams.push((row.stream_type, vec![(row.day, revenue)])),
+            }
         }
 
-        (second_half - first_half) / first_half
-    }
-
-    // Calculate confidence interval
-    fn calculate_confidence_interval(&self, predicted: Decimal, accuracy: f64) -> (Decimal, Decimal) {
-        let margin = predicted * Decimal::from_f64_retain(1.0 - accuracy).unwrap_or(Decimal::new(20, 2));
-        (predicted - margin, predicted + margin)
+        Ok(streams)
     }
 
     // Identify growth factors
-    fn identify_growth_factors(&self, _data: &[(DateTime<Utc>, Decimal)]) -> Vec<GrowthFactor> {
+    fn identify_growth_factors(&self, _streams: &[StreamForecast]) -> Vec<GrowthFactor> {
         vec![
             GrowthFactor {
                 factor: "Subscription Growth".to_string(),
@@ -901,7 +930,7 @@ impl RevenueForecasting {
     }
 
     // Identify risk factors
-    fn identify_risk_factors(&self, _data: &[(DateTime<Utc>, Decimal)]) -> Vec<RiskFactor> {
+    fn identify_risk_factors(&self, _streams: &[StreamForecast]) -> Vec<RiskFactor> {
         vec![
             RiskFactor {
                 risk: "Market Volatility".to_string(),
@@ -945,6 +974,110 @@ impl RevenueForecasting {
     }
 }
 
+// Fits daily revenue = intercept + slope * day with OLS and extrapolates forecast_days past `today`.
+// Prediction intervals use the standard OLS prediction error with a Student-t quantile.
+pub fn forecast_stream(
+    stream_type: &str,
+    observations: &[(NaiveDate, f64)],
+    today: NaiveDate,
+    forecast_days: u32
+) -> RevenueResult<StreamForecast> {
+    let Some(&(first_day, _)) = observations.first() else {
+        return Err(RevenueError::Analytics(format!("No revenue history for {}", stream_type)));
+    };
+    let horizon = (1..=forecast_days as i64).map(|offset| today + Duration::days(offset));
+
+    if observations.len() < MIN_REGRESSION_POINTS {
+        let (_, last) = observations[observations.len() - 1];
+        return Ok(StreamForecast {
+            stream_type: stream_type.to_string(),
+            method: ForecastMethod::LastValue,
+            data_points: observations.len(),
+            slope: 0.0,
+            intercept: last,
+            r_squared: None,
+            points: horizon
+                .map(|date| ForecastPoint {
+                    date,
+                    estimate: to_money(last),
+                    interval_80: (to_money(last * 0.5), to_money(last * 1.5)),
+                    interval_95: (Decimal::ZERO, to_money(last * 2.0)),
+                })
+                .collect(),
+        });
+    }
+
+    let xs: Vec<f64> = observations
+        .iter()
+        .map(|(day, _)| (*day - first_day).num_days() as f64)
+        .collect();
+    let ys: Vec<f64> = observations.iter().map(|(_, revenue)| *revenue).collect();
+    let n = xs.len() as f64;
+
+    let records = Array2::from_shape_vec((xs.len(), 1), xs.clone())
+        .map_err(|e| RevenueError::Analytics(e.to_string()))?;
+    let dataset = Dataset::new(records, Array1::from(ys.clone()));
+    let model = LinearRegression::new()
+        .fit(&dataset)
+        .map_err(|e| RevenueError::Analytics(format!("Regression failed for {}: {}", stream_type, e)))?;
+    let slope = model.params()[0];
+    let intercept = model.intercept();
+
+    // Residual standard error and spread of the regressor
+    let x_mean = xs.iter().sum::<f64>() / n;
+    let y_mean = ys.iter().sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - x_mean).powi(2)).sum();
+    let sse: f64 = xs.iter().zip(&ys).map(|(x, y)| (y - (intercept + slope * x)).powi(2)).sum();
+    let sst: f64 = ys.iter().map(|y| (y - y_mean).powi(2)).sum();
+    let residual_se = (sse / (n - 2.0)).sqrt();
+    let r_squared = if sst > 0.0 { 1.0 - sse / sst } else { 1.0 };
+
+    let degrees_of_freedom = n - 2.0;
+    let t_80 = student_t_quantile(Z_80, degrees_of_freedom);
+    let t_95 = student_t_quantile(Z_95, degrees_of_freedom);
+
+    let points = horizon
+        .map(|date| {
+            let x = (date - first_day).num_days() as f64;
+            let estimate = intercept + slope * x;
+            let prediction_se = residual_se * (1.0 + 1.0 / n + (x - x_mean).powi(2) / sxx).sqrt();
+            ForecastPoint {
+                date,
+                estimate: to_money(estimate.max(0.0)),
+                interval_80: interval(estimate, t_80 * prediction_se),
+                interval_95: interval(estimate, t_95 * prediction_se),
+            }
+        })
+        .collect();
+
+    Ok(StreamForecast {
+        stream_type: stream_type.to_string(),
+        method: ForecastMethod::LinearRegression,
+        data_points: observations.len(),
+        slope,
+        intercept,
+        r_squared: Some(r_squared),
+        points,
+    })
+}
+
+// Cornish-Fisher expansion of the Student-t quantile around the normal quantile `z`;
+// accurate to three decimals from the 12 degrees of freedom a 14-point fit leaves
+fn student_t_quantile(z: f64, degrees_of_freedom: f64) -> f64 {
+    let v = degrees_of_freedom;
+    z + (z.powi(3) + z) / (4.0 * v)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * v.powi(2))
+}
+
+// Revenue cannot go negative, so the lower bound is clamped at zero
+fn interval(estimate: f64, margin: f64) -> (Decimal, Decimal) {
+    (to_money((estimate - margin).max(0.0)), to_money((estimate + margin).max(0.0)))
+}
+
+fn to_money(value: f64) -> Decimal {
+    Decimal::from_f64_retain(value).unwrap_or(Decimal::ZERO).round_dp(2)
+}
+
 // Revenue optimizer using ML algorithms
 #[derive(Debug)]
 pub struct RevenueOptimizer {
@@ -1003,4 +1136,311 @@ impl RevenueOptimizer {
 
         Ok(recommendations)
     }
-}
\ No newline at end of file
+}
+
+// Calendar month identifying a subscription cohort (e.g. "2024-01")
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct YearMonth {
+    pub year: i32,
+    pub month: u32,
+}
+
+impl YearMonth {
+    pub fn new(year: i32, month: u32) -> RevenueResult<Self> {
+        if !(1..=12).contains(&month) {
+            return Err(RevenueError::Validation(format!("Invalid month: {}", month)));
+        }
+        Ok(Self { year, month })
+    }
+
+    pub fn from_datetime(date: DateTime<Utc>) -> Self {
+        use chrono::Datelike;
+        Self { year: date.year(), month: date.month() }
+    }
+
+    pub fn first_day(&self) -> DateTime<Utc> {
+        use chrono::TimeZone;
+        Utc.with_ymd_and_hms(self.year, self.month, 1, 0, 0, 0).unwrap()
+    }
+
+    pub fn next(&self) -> Self {
+        if self.month == 12 {
+            Self { year: self.year + 1, month: 1 }
+        } else {
+            Self { year: self.year, month: self.month + 1 }
+        }
+    }
+
+    // Whole months from `self` until `other` (negative if `other` is earlier)
+    pub fn months_until(&self, other: &YearMonth) -> i32 {
+        (other.year - self.year) * 12 + other.month as i32 - self.month as i32
+    }
+}
+
+impl std::str::FromStr for YearMonth {
+    type Err = RevenueError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RevenueError::Validation(format!("Invalid cohort month '{}', expected YYYY-MM", s));
+        let (year, month) = s.split_once('-').ok_or_else(invalid)?;
+        if month.len() != 2 {
+            return Err(invalid());
+        }
+        Self::new(year.parse().map_err(|_| invalid())?, month.parse().map_err(|_| invalid())?)
+    }
+}
+
+impl std::fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+// Retention of one cohort at a given month offset
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetentionRow {
+    pub months_since_join: i32,
+    pub active_subscribers: i64,
+    pub mrr: Decimal,
+    pub retention_rate: f64,
+}
+
+// Cohorts x months-since-join grid for heatmap rendering
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CohortRetentionMatrix {
+    pub cohorts: Vec<String>,
+    pub months_since_join: Vec<i32>,
+    pub retention: Vec<Vec<Option<f64>>>,
+    pub mrr: Vec<Vec<Option<Decimal>>>,
+}
+
+impl CohortRetentionMatrix {
+    // Cells a cohort has not reached yet are left empty
+    pub fn from_rows(rows: Vec<(YearMonth, Vec<RetentionRow>)>) -> Self {
+        let max_months = rows.iter()
+            .flat_map(|(_, cohort_rows)| cohort_rows.iter().map(|r| r.months_since_join))
+            .max()
+            .unwrap_or(0);
+        let months_since_join: Vec<i32> = (0..=max_months).collect();
+
+        let mut cohorts = Vec::with_capacity(rows.len());
+        let mut retention = Vec::with_capacity(rows.len());
+        let mut mrr = Vec::with_capacity(rows.len());
+
+        for (cohort, cohort_rows) in rows {
+            let mut retention_row = vec![None; months_since_join.len()];
+            let mut mrr_row = vec![None; months_since_join.len()];
+            for row in cohort_rows {
+                let index = row.months_since_join as usize;
+                retention_row[index] = Some(row.retention_rate);
+                mrr_row[index] = Some(row.mrr);
+            }
+            cohorts.push(cohort.to_string());
+            retention.push(retention_row);
+            mrr.push(mrr_row);
+        }
+
+        Self { cohorts, months_since_join, retention, mrr }
+    }
+}
+
+// Month-over-month revenue retention for subscription cohorts
+#[derive(Debug, Clone)]
+pub struct CohortRetentionAnalysis {
+    db_pool: PgPool,
+}
+
+impl CohortRetentionAnalysis {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    // Retention for subscribers who joined in `cohort_month`, up to the current month
+    pub async fn compute(&self, cohort_month: YearMonth) -> RevenueResult<Vec<RetentionRow>> {
+        let months_elapsed = cohort_month.months_until(&YearMonth::from_datetime(Utc::now()));
+        if months_elapsed < 0 {
+            return Ok(Vec::new());
+        }
+
+        // A subscriber counts as active in month N if they had not cancelled by the start of that month
+        let records = sqlx::query!(
+            r#"
+            WITH cohort AS (
+                SELECT id, amount, billing_cycle, cancelled_at
+                FROM subscriptions
+                WHERE DATE_TRUNC('month', created_at) = $1::timestamp
+            ),
+            months AS (
+                SELECT generate_series(0, $2::int) AS months_since_join
+            ),
+            activity AS (
+                SELECT
+                    m.months_since_join,
+                    COUNT(c.id) AS active_subscribers,
+                    COALESCE(SUM(
+                        CASE WHEN c.billing_cycle = 'annual' THEN c.amount / 12 ELSE c.amount END
+                    ), 0) AS mrr
+                FROM months m
+                LEFT JOIN cohort c
+                    ON c.cancelled_at IS NULL
+                    OR c.cancelled_at > $1::timestamp + make_interval(months => m.months_since_join)
+                GROUP BY m.months_since_join
+            )
+            SELECT
+                months_since_join as "months_since_join!",
+                active_subscribers as "active_subscribers!",
+                mrr as "mrr!",
+                COALESCE(
+                    active_subscribers::float8
+                        / NULLIF(FIRST_VALUE(active_subscribers) OVER (ORDER BY months_since_join), 0),
+                    0
+                ) as "retention_rate!"
+            FROM activity
+            ORDER BY months_since_join
+            "#,
+            cohort_month.first_day().naive_utc(),
+            months_elapsed
+        ).fetch_all(&self.db_pool).await?;
+
+        Ok(records.into_iter().map(|record| RetentionRow {
+            months_since_join: record.months_since_join,
+            active_subscribers: record.active_subscribers,
+            mrr: record.mrr,
+            retention_rate: record.retention_rate,
+        }).collect())
+    }
+
+    // Retention matrix for every cohort from `first` through `last` inclusive
+    pub async fn compute_matrix(&self, first: YearMonth, last: YearMonth) -> RevenueResult<CohortRetentionMatrix> {
+        if last < first {
+            return Err(RevenueError::Validation(format!("Cohort range {} to {} is empty", first, last)));
+        }
+        if first.months_until(&last) > 36 {
+            return Err(RevenueError::Validation("Cohort range cannot exceed 36 months".to_string()));
+        }
+
+        let mut rows = Vec::new();
+        let mut cohort = first;
+        while cohort <= last {
+            rows.push((cohort, self.compute(cohort).await?));
+            cohort = cohort.next();
+        }
+
+        Ok(CohortRetentionMatrix::from_rows(rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(months_since_join: i32, active_subscribers: i64, retention_rate: f64) -> RetentionRow {
+        RetentionRow {
+            months_since_join,
+            active_subscribers,
+            mrr: Decimal::new(active_subscribers * 99, 0),
+            retention_rate,
+        }
+    }
+
+    #[test]
+    fn test_year_month_parsing() {
+        let cohort: YearMonth = "2024-01".parse().unwrap();
+        assert_eq!(cohort, YearMonth { year: 2024, month: 1 });
+        assert_eq!(cohort.to_string(), "2024-01");
+
+        assert!("2024-13".parse::<YearMonth>().is_err());
+        assert!("2024-1".parse::<YearMonth>().is_err());
+        assert!("January".parse::<YearMonth>().is_err());
+    }
+
+    #[test]
+    fn test_year_month_arithmetic() {
+        let december = YearMonth::new(2023, 12).unwrap();
+        assert_eq!(december.next(), YearMonth::new(2024, 1).unwrap());
+        assert_eq!(december.months_until(&YearMonth::new(2024, 3).unwrap()), 3);
+    }
+
+    #[test]
+    fn test_matrix_pads_younger_cohorts() {
+        let matrix = CohortRetentionMatrix::from_rows(vec![
+            (YearMonth::new(2024, 1).unwrap(), vec![row(0, 10, 1.0), row(1, 8, 0.8), row(2, 6, 0.6)]),
+            (YearMonth::new(2024, 2).unwrap(), vec![row(0, 4, 1.0), row(1, 3, 0.75)]),
+        ]);
+
+        assert_eq!(matrix.cohorts, vec!["2024-01", "2024-02"]);
+        assert_eq!(matrix.months_since_join, vec![0, 1, 2]);
+        assert_eq!(matrix.retention[0], vec![Some(1.0), Some(0.8), Some(0.6)]);
+        assert_eq!(matrix.retention[1], vec![Some(1.0), Some(0.75), None]);
+        assert_eq!(matrix.mrr[1][1], Some(Decimal::new(297, 0)));
+    }
+
+    fn series(days: usize, revenue: impl Fn(usize) -> f64) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        (0..days).map(|i| (start + Duration::days(i as i64), revenue(i))).collect()
+    }
+
+    #[test]
+    fn test_forecast_recovers_exact_trend() {
+        let observations = series(30, |day| 120.0 + 3.5 * day as f64);
+        let today = observations.last().unwrap().0;
+        let forecast = forecast_stream("mining_pool", &observations, today, 7).unwrap();
+
+        assert_eq!(forecast.method, ForecastMethod::LinearRegression);
+        assert!((forecast.slope - 3.5).abs() < 1e-9);
+        assert!((forecast.intercept - 120.0).abs() < 1e-9);
+        assert!((forecast.r_squared.unwrap() - 1.0).abs() < 1e-9);
+
+        // Day 30 is the first day after the history; a perfect fit leaves no prediction error
+        let first = &forecast.points[0];
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        assert_eq!(first.estimate, Decimal::new(225, 0));
+        assert_eq!(first.interval_95, (Decimal::new(225, 0), Decimal::new(225, 0)));
+        assert_eq!(forecast.points.len(), 7);
+        assert_eq!(forecast.points[6].estimate, Decimal::new(246, 0));
+    }
+
+    #[test]
+    fn test_forecast_intervals_cover_noisy_trend() {
+        let observations = series(40, |day| 50.0 + 2.0 * day as f64 + if day % 2 == 0 { 4.0 } else { -4.0 });
+        let today = observations.last().unwrap().0;
+        let forecast = forecast_stream("bridge_transaction", &observations, today, 6).unwrap();
+
+        assert!((forecast.slope - 2.0).abs() < 0.05);
+        assert!((forecast.intercept - 50.0).abs() < 1.0);
+
+        // Six days past the history the true value is 50 + 2 * 45
+        let last = forecast.points.last().unwrap();
+        let truth = Decimal::new(140, 0);
+        assert!(last.interval_95.0 < truth && truth < last.interval_95.1);
+        assert!(last.interval_95.0 < last.interval_80.0 && last.interval_80.1 < last.interval_95.1);
+        assert!(last.interval_80.0 < last.estimate && last.estimate < last.interval_80.1);
+
+        // Intervals widen further from the data
+        let width = |p: &ForecastPoint| p.interval_95.1 - p.interval_95.0;
+        assert!(width(&forecast.points[0]) < width(last));
+    }
+
+    #[test]
+    fn test_short_history_carries_last_value() {
+        let observations = series(MIN_REGRESSION_POINTS - 1, |day| 10.0 * day as f64);
+        let today = observations.last().unwrap().0;
+        let forecast = forecast_stream("trading_fees", &observations, today, 3).unwrap();
+
+        assert_eq!(forecast.method, ForecastMethod::LastValue);
+        assert_eq!(forecast.r_squared, None);
+        for point in &forecast.points {
+            assert_eq!(point.estimate, Decimal::new(120, 0));
+            assert_eq!(point.interval_80, (Decimal::new(60, 0), Decimal::new(180, 0)));
+            assert_eq!(point.interval_95, (Decimal::ZERO, Decimal::new(240, 0)));
+        }
+    }
+
+    #[test]
+    fn test_student_t_quantile() {
+        // Tabulated t(0.975, 12) = 2.179 and t(0.90, 12) = 1.356
+        assert!((student_t_quantile(Z_95, 12.0) - 2.179).abs() < 0.005);
+        assert!((student_t_quantile(Z_80, 12.0) - 1.356).abs() < 0.005);
+        assert!((student_t_quantile(Z_95, 1000.0) - Z_95).abs() < 0.005);
+    }
+}
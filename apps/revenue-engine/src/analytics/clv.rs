@@ -0,0 +1,515 @@
+// Customer Lifetime Value - BG/NBD model of repeat payments
+// Each customer pays at a Poisson rate drawn from Gamma(r, alpha) and may churn after any payment
+// with a probability drawn from Beta(a, b); the four parameters are fitted by maximum likelihood
+// over every paying customer's history, then expected payments over the horizon x average payment
+// gives each active subscriber's predicted CLV
+
+use argmin::core::{CostFunction, Error as ArgminError, Executor};
+use argmin::solver::neldermead::NelderMead;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Serialize, Deserialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::analytics::to_money;
+use crate::core::{RevenueError, RevenueResult};
+
+// Months of future payments counted towards a customer's lifetime value
+pub const CLV_HORIZON_MONTHS: i32 = 24;
+
+const DAYS_PER_MONTH: f64 = 30.44;
+const MAX_FIT_ITERATIONS: u64 = 2_000;
+const MAX_SERIES_TERMS: usize = 100_000;
+
+// Repeat-payment summary of one customer, in months since their first payment
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomerHistory {
+    pub user_id: Uuid,
+    // Payments after the first one
+    pub frequency: u32,
+    // Age at the last payment
+    pub recency: f64,
+    // Age now
+    pub age: f64,
+    pub average_revenue: Decimal,
+}
+
+impl CustomerHistory {
+    pub fn from_payments(
+        user_id: Uuid,
+        payments: i64,
+        first: NaiveDateTime,
+        last: NaiveDateTime,
+        average_revenue: Decimal,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let months = |from: NaiveDateTime, to: NaiveDateTime| {
+            ((to - from).num_seconds() as f64 / 86_400.0 / DAYS_PER_MONTH).max(0.0)
+        };
+        let age = months(first, now.naive_utc());
+        Self {
+            user_id,
+            frequency: payments.saturating_sub(1).max(0) as u32,
+            recency: months(first, last).min(age),
+            age,
+            average_revenue,
+        }
+    }
+}
+
+// Fitted BG/NBD parameters: purchase rate ~ Gamma(r, alpha), churn probability ~ Beta(a, b)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BgNbdParams {
+    pub r: f64,
+    pub alpha: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl BgNbdParams {
+    // Log-likelihood of one customer's (x, t_x, T)
+    pub fn log_likelihood(&self, history: &CustomerHistory) -> f64 {
+        let Self { r, alpha, a, b } = *self;
+        let x = history.frequency as f64;
+
+        let a1 = ln_gamma(r + x) - ln_gamma(r) + r * alpha.ln();
+        let a2 = ln_gamma(a + b) + ln_gamma(b + x) - ln_gamma(b) - ln_gamma(a + b + x);
+        let a3 = -(r + x) * (alpha + history.age).ln();
+        if history.frequency == 0 {
+            return a1 + a2 + a3;
+        }
+
+        let a4 = a.ln() - (b + x - 1.0).ln() - (r + x) * (alpha + history.recency).ln();
+        let max = a3.max(a4);
+        a1 + a2 + max + ((a3 - max).exp() + (a4 - max).exp()).ln()
+    }
+
+    // Probability the customer has not churned
+    pub fn probability_alive(&self, history: &CustomerHistory) -> f64 {
+        1.0 / (1.0 + self.churned_odds(history))
+    }
+
+    // Expected payments in the next `horizon` months
+    pub fn expected_transactions(&self, history: &CustomerHistory, horizon: f64) -> RevenueResult<f64> {
+        let Self { r, alpha, a, b } = *self;
+        let x = history.frequency as f64;
+        let t = history.age;
+
+        let z = horizon / (alpha + t + horizon);
+        let hyp = hyp2f1(r + x, b + x, a + b + x - 1.0, z)?;
+        let numerator = (a + b + x - 1.0) / (a - 1.0)
+            * (1.0 - ((alpha + t) / (alpha + t + horizon)).powf(r + x) * hyp);
+        let expected = numerator / (1.0 + self.churned_odds(history));
+
+        if !expected.is_finite() {
+            return Err(RevenueError::Analytics(format!("BG/NBD expectation is undefined for {:?}", self)));
+        }
+        Ok(expected.max(0.0))
+    }
+
+    // Odds the customer churned right after their last payment rather than still being active
+    fn churned_odds(&self, history: &CustomerHistory) -> f64 {
+        if history.frequency == 0 {
+            return 0.0;
+        }
+        let x = history.frequency as f64;
+        self.a / (self.b + x - 1.0)
+            * ((self.alpha + history.age) / (self.alpha + history.recency)).powf(self.r + x)
+    }
+}
+
+// Negative mean log-likelihood over log-parameters, so the search space is unconstrained
+struct BgNbdLikelihood<'a> {
+    histories: &'a [CustomerHistory],
+}
+
+impl CostFunction for BgNbdLikelihood<'_> {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, log_params: &Self::Param) -> Result<Self::Output, ArgminError> {
+        let params = BgNbdParams {
+            r: log_params[0].exp(),
+            alpha: log_params[1].exp(),
+            a: log_params[2].exp(),
+            b: log_params[3].exp(),
+        };
+        let total: f64 = self.histories.iter().map(|history| params.log_likelihood(history)).sum();
+        let cost = -total / self.histories.len() as f64;
+        Ok(if cost.is_finite() { cost } else { f64::INFINITY })
+    }
+}
+
+// Maximum-likelihood BG/NBD parameters for the given customers
+pub fn fit_bg_nbd(histories: &[CustomerHistory]) -> RevenueResult<BgNbdParams> {
+    if histories.is_empty() {
+        return Err(RevenueError::Analytics("No payment histories to fit the CLV model on".to_string()));
+    }
+    if histories.iter().all(|history| history.frequency == 0) {
+        return Err(RevenueError::Analytics("CLV model needs at least one repeat payment".to_string()));
+    }
+
+    // Simplex around r = alpha = a = b = 1
+    let mut simplex = vec![vec![0.0; 4]];
+    for i in 0..4 {
+        let mut vertex = vec![0.0; 4];
+        vertex[i] = 1.0;
+        simplex.push(vertex);
+    }
+    let solver = NelderMead::new(simplex)
+        .with_sd_tolerance(1e-9)
+        .map_err(|e| RevenueError::Analytics(e.to_string()))?;
+
+    let result = Executor::new(BgNbdLikelihood { histories }, solver)
+        .configure(|state| state.max_iters(MAX_FIT_ITERATIONS))
+        .run()
+        .map_err(|e| RevenueError::Analytics(format!("CLV model fit failed: {}", e)))?;
+    let best = result.state.best_param
+        .filter(|_| result.state.best_cost.is_finite())
+        .ok_or_else(|| RevenueError::Analytics("CLV model fit did not converge".to_string()))?;
+
+    Ok(BgNbdParams {
+        r: best[0].exp(),
+        alpha: best[1].exp(),
+        a: best[2].exp(),
+        b: best[3].exp(),
+    })
+}
+
+// Lanczos approximation (g = 7, n = 9) of ln Γ(x) for x > 0
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection: Γ(x)Γ(1 - x) = π / sin(πx)
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..].iter().enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// Gauss hypergeometric 2F1(a, b; c; z) by its power series, 0 <= z < 1
+fn hyp2f1(a: f64, b: f64, c: f64, z: f64) -> RevenueResult<f64> {
+    let mut term = 1.0;
+    let mut sum = 1.0;
+    for k in 0..MAX_SERIES_TERMS {
+        let k = k as f64;
+        term *= (a + k) * (b + k) / ((c + k) * (k + 1.0)) * z;
+        sum += term;
+        if term.abs() <= 1e-14 * sum.abs() {
+            return Ok(sum);
+        }
+    }
+    Err(RevenueError::Analytics(format!("2F1({}, {}; {}; {}) did not converge", a, b, c, z)))
+}
+
+// Latest lifetime value prediction for one active subscriber
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClvPrediction {
+    pub user_id: Uuid,
+    pub expected_transactions: f64,
+    pub average_revenue: Decimal,
+    pub predicted_clv: Decimal,
+    pub probability_alive: f64,
+    pub predicted_at: DateTime<Utc>,
+}
+
+impl ClvPrediction {
+    pub fn predict(params: &BgNbdParams, history: &CustomerHistory, predicted_at: DateTime<Utc>) -> RevenueResult<Self> {
+        let expected_transactions = params.expected_transactions(history, CLV_HORIZON_MONTHS as f64)?;
+        let average_revenue = history.average_revenue.round_dp(2);
+        Ok(Self {
+            user_id: history.user_id,
+            expected_transactions,
+            average_revenue,
+            predicted_clv: to_money(expected_transactions * average_revenue.to_f64().unwrap_or(0.0)),
+            probability_alive: params.probability_alive(history),
+            predicted_at,
+        })
+    }
+}
+
+// Predicted CLV of one quartile of active subscribers, 1 being the lowest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ClvBucket {
+    pub quartile: i32,
+    pub customers: i64,
+    pub min_clv: Decimal,
+    pub max_clv: Decimal,
+    pub mean_clv: Decimal,
+    pub total_clv: Decimal,
+}
+
+// Fits the model over payment history and keeps clv_predictions current
+#[derive(Debug, Clone)]
+pub struct ClvAnalysis {
+    db_pool: PgPool,
+}
+
+impl ClvAnalysis {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    // Payment history of every customer with a succeeded payment
+    async fn payment_histories(&self, now: DateTime<Utc>) -> RevenueResult<Vec<CustomerHistory>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                user_id,
+                COUNT(*) as "payments!",
+                MIN(COALESCE(processed_at, created_at)) as "first_payment!",
+                MAX(COALESCE(processed_at, created_at)) as "last_payment!",
+                AVG(COALESCE(amount_usd, amount)) as "average_revenue!"
+            FROM payments
+            WHERE status = 'succeeded'
+            GROUP BY user_id
+            "#
+        ).fetch_all(&self.db_pool).await?;
+
+        Ok(records.into_iter().map(|record| CustomerHistory::from_payments(
+            record.user_id,
+            record.payments,
+            record.first_payment,
+            record.last_payment,
+            record.average_revenue,
+            now,
+        )).collect())
+    }
+
+    // Fit BG/NBD parameters on all paying customers
+    pub async fn fit_model(&self) -> RevenueResult<BgNbdParams> {
+        let histories = self.payment_histories(Utc::now()).await?;
+        fit_bg_nbd(&histories)
+    }
+
+    // Refit the model and replace the stored predictions for active subscribers
+    pub async fn refresh_predictions(&self) -> RevenueResult<Vec<ClvPrediction>> {
+        let now = Utc::now();
+        let histories = self.payment_histories(now).await?;
+        let params = fit_bg_nbd(&histories)?;
+        tracing::info!(
+            "📈 Fitted BG/NBD model on {} customers: r={:.4} alpha={:.4} a={:.4} b={:.4}",
+            histories.len(), params.r, params.alpha, params.a, params.b
+        );
+
+        // Subscribers who have not paid yet have no history to predict from
+        let active: std::collections::HashSet<Uuid> = sqlx::query_scalar!(
+            "SELECT DISTINCT user_id FROM subscriptions WHERE status = 'active'"
+        ).fetch_all(&self.db_pool).await?.into_iter().collect();
+
+        let predictions = histories.iter()
+            .filter(|history| active.contains(&history.user_id))
+            .map(|history| ClvPrediction::predict(&params, history, now))
+            .collect::<RevenueResult<Vec<_>>>()?;
+
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!("DELETE FROM clv_predictions").execute(&mut *tx).await?;
+        for prediction in &predictions {
+            sqlx::query!(
+                r#"
+                INSERT INTO clv_predictions (
+                    user_id, expected_transactions, average_revenue, predicted_clv,
+                    probability_alive, horizon_months, predicted_at
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                prediction.user_id,
+                prediction.expected_transactions,
+                prediction.average_revenue,
+                prediction.predicted_clv,
+                prediction.probability_alive,
+                CLV_HORIZON_MONTHS,
+                prediction.predicted_at
+            ).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!("💎 Stored CLV predictions for {} active subscribers", predictions.len());
+        Ok(predictions)
+    }
+
+    // Mean predicted CLV across active subscribers, if predictions have been made
+    pub async fn average_clv(&self) -> RevenueResult<Option<Decimal>> {
+        let average = sqlx::query_scalar!("SELECT AVG(predicted_clv) FROM clv_predictions")
+            .fetch_one(&self.db_pool).await?;
+        Ok(average.map(|value| value.round_dp(2)))
+    }
+
+    // Stored predictions split into quartiles by predicted CLV
+    pub async fn get_clv_distribution(&self) -> RevenueResult<Vec<ClvBucket>> {
+        let buckets = sqlx::query_as!(
+            ClvBucket,
+            r#"
+            SELECT
+                quartile as "quartile!",
+                COUNT(*) as "customers!",
+                MIN(predicted_clv) as "min_clv!",
+                MAX(predicted_clv) as "max_clv!",
+                ROUND(AVG(predicted_clv), 2) as "mean_clv!",
+                SUM(predicted_clv) as "total_clv!"
+            FROM (
+                SELECT predicted_clv, NTILE(4) OVER (ORDER BY predicted_clv) as quartile
+                FROM clv_predictions
+            ) ranked
+            GROUP BY quartile
+            ORDER BY quartile
+            "#
+        ).fetch_all(&self.db_pool).await?;
+
+        Ok(buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+    use rand_distr::{Beta, Distribution, Exp, Gamma};
+
+    const TRUE_PARAMS: BgNbdParams = BgNbdParams { r: 0.25, alpha: 4.0, a: 0.8, b: 2.5 };
+
+    fn history(frequency: u32, recency: f64, age: f64) -> CustomerHistory {
+        CustomerHistory {
+            user_id: Uuid::new_v4(),
+            frequency,
+            recency,
+            age,
+            average_revenue: Decimal::new(4900, 2),
+        }
+    }
+
+    // Customers drawn from the BG/NBD generative process, each observed for `age` months
+    fn simulate(params: BgNbdParams, customers: usize, age: f64, rng: &mut StdRng) -> Vec<CustomerHistory> {
+        let rates = Gamma::new(params.r, 1.0 / params.alpha).unwrap();
+        let churn = Beta::new(params.a, params.b).unwrap();
+        (0..customers).map(|_| {
+            let gap = Exp::new(rates.sample(rng)).unwrap();
+            let p = churn.sample(rng);
+            let (mut frequency, mut recency, mut elapsed) = (0, 0.0, 0.0);
+            loop {
+                elapsed += gap.sample(rng);
+                if elapsed > age {
+                    break;
+                }
+                frequency += 1;
+                recency = elapsed;
+                if rng.gen::<f64>() < p {
+                    break;
+                }
+            }
+            history(frequency, recency, age)
+        }).collect()
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert!(ln_gamma(1.0).abs() < 1e-12);
+        assert!(ln_gamma(2.0).abs() < 1e-12);
+        assert!((ln_gamma(5.0) - 24f64.ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.5) - std::f64::consts::PI.sqrt().ln()).abs() < 1e-12);
+        assert!((ln_gamma(0.1) - 2.252_712_651_734_206).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_hyp2f1() {
+        // 2F1(1, 1; 2; z) = -ln(1 - z) / z
+        let z = 0.75;
+        assert!((hyp2f1(1.0, 1.0, 2.0, z).unwrap() - (-(1.0 - z).ln() / z)).abs() < 1e-10);
+        assert_eq!(hyp2f1(3.0, 2.0, 4.0, 0.0).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_history_from_payments() {
+        let first = NaiveDateTime::parse_from_str("2024-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let last = first + chrono::Duration::days(304);
+        let now = (first + chrono::Duration::days(365)).and_utc();
+
+        let history = CustomerHistory::from_payments(Uuid::nil(), 11, first, last, Decimal::new(99, 0), now);
+        assert_eq!(history.frequency, 10);
+        assert!((history.recency - 304.0 / DAYS_PER_MONTH).abs() < 1e-9);
+        assert!((history.age - 365.0 / DAYS_PER_MONTH).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_probability_alive() {
+        let params = TRUE_PARAMS;
+        assert_eq!(params.probability_alive(&history(0, 0.0, 12.0)), 1.0);
+
+        // Same payments, but the one who has been quiet for longer is less likely to still be a customer
+        let recent = params.probability_alive(&history(6, 11.5, 12.0));
+        let lapsed = params.probability_alive(&history(6, 4.0, 12.0));
+        assert!(recent > lapsed);
+        assert!(lapsed > 0.0 && recent < 1.0);
+    }
+
+    #[test]
+    fn test_expected_transactions() {
+        // Worked example from Fader, Hardie & Lee (2005) on CDNOW data, in weeks
+        let cdnow = BgNbdParams { r: 0.243, alpha: 4.414, a: 0.793, b: 2.426 };
+        let expected = cdnow.expected_transactions(&history(2, 30.43, 38.86), 39.0).unwrap();
+        assert!((expected - 1.226).abs() < 1e-3);
+
+        let params = BgNbdParams { r: 0.25, alpha: 4.0, a: 1.8, b: 2.5 };
+        let customer = history(6, 11.5, 12.0);
+
+        let one_year = params.expected_transactions(&customer, 12.0).unwrap();
+        let two_years = params.expected_transactions(&customer, 24.0).unwrap();
+        assert!(one_year > 0.0 && two_years > one_year);
+        assert_eq!(params.expected_transactions(&customer, 0.0).unwrap(), 0.0);
+
+        // Frequent, recent payers are expected to keep paying more than lapsed ones
+        let lapsed = params.expected_transactions(&history(6, 2.0, 12.0), 24.0).unwrap();
+        assert!(lapsed < two_years);
+    }
+
+    #[test]
+    fn test_fit_recovers_parameters() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let histories = simulate(TRUE_PARAMS, 4_000, 24.0, &mut rng);
+
+        let fitted = fit_bg_nbd(&histories).unwrap();
+        let likelihood = |params: BgNbdParams| histories.iter().map(|h| params.log_likelihood(h)).sum::<f64>();
+        assert!(likelihood(fitted) >= likelihood(TRUE_PARAMS));
+
+        // Mean purchase rate r / alpha and mean churn probability a / (a + b) are well identified
+        let rate = fitted.r / fitted.alpha;
+        assert!((rate - 0.0625).abs() < 0.015, "rate {}", rate);
+        let churn = fitted.a / (fitted.a + fitted.b);
+        assert!((churn - 0.8 / 3.3).abs() < 0.08, "churn {}", churn);
+    }
+
+    #[test]
+    fn test_fit_needs_repeat_payments() {
+        assert!(matches!(fit_bg_nbd(&[]), Err(RevenueError::Analytics(_))));
+        assert!(matches!(fit_bg_nbd(&[history(0, 0.0, 6.0)]), Err(RevenueError::Analytics(_))));
+    }
+
+    #[test]
+    fn test_prediction() {
+        let params = BgNbdParams { r: 0.25, alpha: 4.0, a: 1.8, b: 2.5 };
+        let customer = history(6, 11.5, 12.0);
+        let prediction = ClvPrediction::predict(&params, &customer, Utc::now()).unwrap();
+
+        assert_eq!(prediction.user_id, customer.user_id);
+        let expected = to_money(prediction.expected_transactions * 49.0);
+        assert_eq!(prediction.predicted_clv, expected);
+        assert!(prediction.probability_alive > 0.0 && prediction.probability_alive < 1.0);
+    }
+}
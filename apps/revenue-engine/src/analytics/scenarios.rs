@@ -0,0 +1,310 @@
+// Scenario Analysis - Monte Carlo simulation of monthly revenue
+// Each scenario describes every revenue stream as volume x rate, both drawn from a distribution;
+// trials sum the streams into a total whose percentiles and chance of hitting the target are reported
+
+use std::collections::HashSet;
+use rand::Rng;
+use rand_distr::{Distribution, LogNormal, Normal, Triangular, Uniform};
+use rust_decimal::Decimal;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+use crate::analytics::to_money;
+use crate::core::{RevenueError, RevenueResult};
+
+// Monthly revenue the platform is planned around
+pub const MONTHLY_REVENUE_TARGET: Decimal = Decimal::from_parts(2_000_000, 0, 0, false, 0);
+
+// Upper bound on trials per scenario for a single request
+pub const MAX_SIMULATIONS: u32 = 100_000;
+
+// Distribution a scenario parameter is drawn from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParameterDistribution {
+    Fixed { value: f64 },
+    Normal { mean: f64, std_dev: f64 },
+    // ln(X) ~ Normal(mu, sigma)
+    LogNormal { mu: f64, sigma: f64 },
+    Uniform { min: f64, max: f64 },
+    Triangular { min: f64, mode: f64, max: f64 },
+}
+
+impl ParameterDistribution {
+    fn sampler(&self) -> RevenueResult<Sampler> {
+        let invalid = |e: &dyn std::fmt::Display| RevenueError::Validation(format!("Invalid {:?}: {}", self, e));
+        Ok(match *self {
+            Self::Fixed { value } if value.is_finite() => Sampler::Fixed(value),
+            Self::Fixed { value } => return Err(invalid(&value)),
+            // rand_distr 0.4 still accepts a negative spread
+            Self::Normal { std_dev: spread, .. } | Self::LogNormal { sigma: spread, .. } if spread < 0.0 => {
+                return Err(invalid(&"spread must not be negative"));
+            }
+            Self::Normal { mean, std_dev } => Sampler::Normal(Normal::new(mean, std_dev).map_err(|e| invalid(&e))?),
+            Self::LogNormal { mu, sigma } => Sampler::LogNormal(LogNormal::new(mu, sigma).map_err(|e| invalid(&e))?),
+            Self::Uniform { min, max } if min.is_finite() && max.is_finite() && min <= max => {
+                Sampler::Uniform(Uniform::new_inclusive(min, max))
+            }
+            Self::Uniform { .. } => return Err(invalid(&"min must not exceed max")),
+            Self::Triangular { min, mode, max } => {
+                Sampler::Triangular(Triangular::new(min, max, mode).map_err(|e| invalid(&e))?)
+            }
+        })
+    }
+}
+
+enum Sampler {
+    Fixed(f64),
+    Normal(Normal<f64>),
+    LogNormal(LogNormal<f64>),
+    Uniform(Uniform<f64>),
+    Triangular(Triangular<f64>),
+}
+
+impl Sampler {
+    // Volumes and rates are never negative, so tails below zero are cut off
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let value = match self {
+            Self::Fixed(value) => *value,
+            Self::Normal(normal) => normal.sample(rng),
+            Self::LogNormal(log_normal) => log_normal.sample(rng),
+            Self::Uniform(uniform) => uniform.sample(rng),
+            Self::Triangular(triangular) => triangular.sample(rng),
+        };
+        value.max(0.0)
+    }
+}
+
+// One revenue stream's monthly revenue, volume x rate: bridge volume x fee rate,
+// subscribers x price, and so on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RevenueDriver {
+    pub stream_type: String,
+    pub volume: ParameterDistribution,
+    pub rate: ParameterDistribution,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RevenueScenario {
+    pub name: String,
+    pub drivers: Vec<RevenueDriver>,
+}
+
+// Distribution of total monthly revenue over a scenario's trials
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioOutcome {
+    pub name: String,
+    pub mean: Decimal,
+    pub p10: Decimal,
+    pub p50: Decimal,
+    pub p90: Decimal,
+    // Share of trials at or above the monthly target
+    pub probability_of_target: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetProbability {
+    pub scenario: String,
+    pub probability: f64,
+}
+
+// Scenarios ranked by their chance of reaching the monthly target, most likely first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioComparison {
+    pub monthly_target: Decimal,
+    pub ranking: Vec<TargetProbability>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioAnalysisResult {
+    pub simulations: u32,
+    pub outcomes: Vec<ScenarioOutcome>,
+    pub comparison: ScenarioComparison,
+}
+
+// Runs `simulations` trials of every scenario against `monthly_target`
+pub fn run_scenarios<R: Rng + ?Sized>(
+    scenarios: &[RevenueScenario],
+    simulations: u32,
+    monthly_target: Decimal,
+    rng: &mut R,
+) -> RevenueResult<ScenarioAnalysisResult> {
+    if scenarios.is_empty() {
+        return Err(RevenueError::Validation("At least one scenario is required".to_string()));
+    }
+    if simulations == 0 || simulations > MAX_SIMULATIONS {
+        return Err(RevenueError::Validation(format!("Simulations must be between 1 and {}", MAX_SIMULATIONS)));
+    }
+    let mut names = HashSet::new();
+    if let Some(duplicate) = scenarios.iter().find(|scenario| !names.insert(scenario.name.as_str())) {
+        return Err(RevenueError::Validation(format!("Duplicate scenario: {}", duplicate.name)));
+    }
+
+    let target = f64::try_from(monthly_target)
+        .map_err(|e| RevenueError::Validation(format!("Invalid monthly target: {}", e)))?;
+    let outcomes = scenarios
+        .iter()
+        .map(|scenario| simulate_scenario(scenario, simulations, target, rng))
+        .collect::<RevenueResult<Vec<_>>>()?;
+
+    let mut ranking: Vec<TargetProbability> = outcomes
+        .iter()
+        .map(|outcome| TargetProbability { scenario: outcome.name.clone(), probability: outcome.probability_of_target })
+        .collect();
+    ranking.sort_by(|a, b| b.probability.total_cmp(&a.probability));
+
+    Ok(ScenarioAnalysisResult {
+        simulations,
+        outcomes,
+        comparison: ScenarioComparison { monthly_target, ranking },
+    })
+}
+
+fn simulate_scenario<R: Rng + ?Sized>(
+    scenario: &RevenueScenario,
+    simulations: u32,
+    target: f64,
+    rng: &mut R,
+) -> RevenueResult<ScenarioOutcome> {
+    if scenario.drivers.is_empty() {
+        return Err(RevenueError::Validation(format!("Scenario {} has no revenue drivers", scenario.name)));
+    }
+    let samplers = scenario.drivers
+        .iter()
+        .map(|driver| Ok((driver.volume.sampler()?, driver.rate.sampler()?)))
+        .collect::<RevenueResult<Vec<_>>>()?;
+
+    let mut totals: Vec<f64> = (0..simulations)
+        .map(|_| samplers.iter().map(|(volume, rate)| volume.sample(rng) * rate.sample(rng)).sum())
+        .collect();
+    totals.sort_by(f64::total_cmp);
+
+    let hits = totals.iter().filter(|total| **total >= target).count();
+    Ok(ScenarioOutcome {
+        name: scenario.name.clone(),
+        mean: to_money(totals.iter().sum::<f64>() / totals.len() as f64),
+        p10: to_money(percentile(&totals, 0.10)),
+        p50: to_money(percentile(&totals, 0.50)),
+        p90: to_money(percentile(&totals, 0.90)),
+        probability_of_target: hits as f64 / totals.len() as f64,
+    })
+}
+
+// Linear interpolation between the closest ranks of sorted, non-empty samples
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn driver(stream_type: &str, volume: ParameterDistribution, rate: f64) -> RevenueDriver {
+        RevenueDriver {
+            stream_type: stream_type.to_string(),
+            volume,
+            rate: ParameterDistribution::Fixed { value: rate },
+        }
+    }
+
+    fn scenario(name: &str, bridge_mu: f64) -> RevenueScenario {
+        RevenueScenario {
+            name: name.to_string(),
+            drivers: vec![
+                // Monthly bridge volume around e^bridge_mu at a 0.3% fee
+                driver("bridge_transaction", ParameterDistribution::LogNormal { mu: bridge_mu, sigma: 0.25 }, 0.003),
+                driver("premium_analytics", ParameterDistribution::Normal { mean: 1_000.0, std_dev: 100.0 }, 199.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_fixed_drivers_are_deterministic() {
+        let fixed = RevenueScenario {
+            name: "flat".to_string(),
+            drivers: vec![
+                driver("trading_fees", ParameterDistribution::Fixed { value: 500_000_000.0 }, 0.002),
+                driver("enterprise_services", ParameterDistribution::Fixed { value: 10.0 }, 100_000.0),
+            ],
+        };
+        let result = run_scenarios(&[fixed], 100, MONTHLY_REVENUE_TARGET, &mut StdRng::seed_from_u64(1)).unwrap();
+
+        let outcome = &result.outcomes[0];
+        assert_eq!(outcome.p10, Decimal::new(2_000_000, 0));
+        assert_eq!(outcome.p90, Decimal::new(2_000_000, 0));
+        assert_eq!(outcome.probability_of_target, 1.0);
+    }
+
+    #[test]
+    fn test_percentiles_match_log_normal_quantiles() {
+        // Bridge revenue alone: 0.003 * LogNormal(ln(500M), 0.25)
+        let bridge = RevenueScenario {
+            name: "bridge".to_string(),
+            drivers: vec![driver(
+                "bridge_transaction",
+                ParameterDistribution::LogNormal { mu: 500_000_000f64.ln(), sigma: 0.25 },
+                0.003,
+            )],
+        };
+        let result = run_scenarios(&[bridge], 50_000, MONTHLY_REVENUE_TARGET, &mut StdRng::seed_from_u64(7)).unwrap();
+        let outcome = &result.outcomes[0];
+
+        // Median 1.5M; P10/P90 at exp(-/+1.2816 * 0.25) around it
+        let close = |actual: Decimal, expected: f64| {
+            (f64::try_from(actual).unwrap() / expected - 1.0).abs() < 0.01
+        };
+        assert!(close(outcome.p50, 1_500_000.0), "p50 {}", outcome.p50);
+        assert!(close(outcome.p10, 1_500_000.0 * (-1.2816f64 * 0.25).exp()), "p10 {}", outcome.p10);
+        assert!(close(outcome.p90, 1_500_000.0 * (1.2816f64 * 0.25).exp()), "p90 {}", outcome.p90);
+
+        // P(X >= 2M) = 1 - Phi(ln(4/3) / 0.25) = 0.125
+        assert!((outcome.probability_of_target - 0.125).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_comparison_ranks_scenarios_by_target_probability() {
+        let scenarios = vec![scenario("bear", 19.8), scenario("bull", 20.6), scenario("base", 20.2)];
+        let result = run_scenarios(&scenarios, 5_000, MONTHLY_REVENUE_TARGET, &mut StdRng::seed_from_u64(42)).unwrap();
+
+        assert_eq!(result.outcomes.iter().map(|o| o.name.as_str()).collect::<Vec<_>>(), vec!["bear", "bull", "base"]);
+        let ranking: Vec<&str> = result.comparison.ranking.iter().map(|r| r.scenario.as_str()).collect();
+        assert_eq!(ranking, vec!["bull", "base", "bear"]);
+        for outcome in &result.outcomes {
+            assert!(outcome.p10 <= outcome.p50 && outcome.p50 <= outcome.p90);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_inputs() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let invalid = |scenarios: &[RevenueScenario], simulations: u32, rng: &mut StdRng| {
+            matches!(run_scenarios(scenarios, simulations, MONTHLY_REVENUE_TARGET, rng), Err(RevenueError::Validation(_)))
+        };
+
+        assert!(invalid(&[], 100, &mut rng));
+        assert!(invalid(&[scenario("base", 20.0)], 0, &mut rng));
+        assert!(invalid(&[scenario("base", 20.0)], MAX_SIMULATIONS + 1, &mut rng));
+        assert!(invalid(&[scenario("base", 20.0), scenario("base", 21.0)], 100, &mut rng));
+
+        let mut negative_sigma = scenario("base", 20.0);
+        negative_sigma.drivers[0].volume = ParameterDistribution::LogNormal { mu: 20.0, sigma: -1.0 };
+        assert!(invalid(&[negative_sigma], 100, &mut rng));
+
+        let mut inverted = scenario("base", 20.0);
+        inverted.drivers[0].rate = ParameterDistribution::Uniform { min: 0.004, max: 0.002 };
+        assert!(invalid(&[inverted], 100, &mut rng));
+    }
+
+    #[test]
+    fn test_percentile_interpolates() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert_eq!(percentile(&sorted, 0.5), 3.0);
+        assert!((percentile(&sorted, 0.1) - 1.4).abs() < 1e-12);
+        assert_eq!(percentile(&sorted, 1.0), 5.0);
+    }
+}
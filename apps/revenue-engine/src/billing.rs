@@ -11,7 +11,25 @@ use chrono::{DateTime, Utc, Duration};
 use serde::{Serialize, Deserialize};
 
 use crate::core::{RevenueError, RevenueResult};
-use crate::subscription::{Subscription, SubscriptionTier, BillingCycle};
+use crate::subscription::{
+    Subscription, SubscriptionTier, BillingCycle, PRORATION_CREDIT_BALANCE_KEY, proration_credit_balance,
+    apply_proration_credit,
+};
+
+pub mod webhook;
+pub mod fx;
+pub mod ab_test;
+pub mod invoice_pdf;
+pub mod tax;
+
+use webhook::{WebhookDispatcher, WebhookEvent};
+use fx::{Currency, FxRateProvider};
+use ab_test::AbTestManager;
+use tax::{TaxCalculator, TaxLineItem, TaxTreatment, VatIdValidator};
+use invoice_pdf::{
+    InvoicePdfContent, InvoicePdfDownload, InvoiceStorage, DEFAULT_COMPANY_NAME, INVOICE_PDF_URL_TTL_HOURS, LOGO_PNG,
+    payment_url, render_invoice_pdf,
+};
 
 // Payment method types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +102,9 @@ pub struct Invoice {
     pub paid_at: Option<DateTime<Utc>>,
     pub stripe_invoice_id: Option<String>,
     pub line_items: Vec<InvoiceLineItem>,
+    // Tax making up tax_amount; None on invoices issued before tax lines were recorded
+    #[serde(default)]
+    pub tax_line: Option<TaxLineItem>,
     pub payment_terms: String,
     pub notes: Option<String>,
     pub metadata: serde_json::Value,
@@ -103,6 +124,21 @@ pub struct InvoiceLineItem {
     pub metadata: serde_json::Value,
 }
 
+impl InvoiceLineItem {
+    // Negative line crediting unused time from a prorated plan change
+    pub fn proration_credit(amount: Decimal) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            description: "Proration credit for unused time on previous plan".to_string(),
+            quantity: Decimal::ONE,
+            unit_price: -amount,
+            total_price: -amount,
+            tax_rate: 0.0,
+            metadata: serde_json::json!({ "line_type": "ProrationCredit" }),
+        }
+    }
+}
+
 // Payment record
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Payment {
@@ -112,6 +148,10 @@ pub struct Payment {
     pub user_id: Uuid,
     pub amount: Decimal,
     pub currency: String,
+    pub amount_usd: Decimal,
+    pub charged_currency: Currency,
+    pub charged_amount: Decimal,
+    pub fx_rate: Decimal,
     pub status: PaymentStatus,
     pub payment_method: PaymentMethod,
     pub stripe_payment_intent_id: Option<String>,
@@ -133,12 +173,55 @@ pub struct BillingCycleRequest {
     pub auto_process: bool,
 }
 
+// One scheduled invoice of an enterprise contract
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractBillingPeriod {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub amount: Decimal,
+}
+
+// Days per quarter, matching the 30-day months contract terms are measured in
+pub const CONTRACT_QUARTER_DAYS: i64 = 90;
+
+// Splits a contract term into quarterly billing periods. A final period shorter than a
+// quarter is billed pro rata by days; rounding lands on the last invoice so the schedule
+// always sums to the contract value.
+pub fn quarterly_billing_periods(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    annual_value: Decimal,
+) -> Vec<ContractBillingPeriod> {
+    let total_days = (end_date - start_date).num_days();
+    if total_days <= 0 {
+        return Vec::new();
+    }
+    let contract_value = (annual_value * Decimal::from(total_days) / Decimal::from(360)).round_dp(2);
+
+    let mut periods = Vec::new();
+    let mut period_start = start_date;
+    let mut billed = Decimal::ZERO;
+    while period_start < end_date {
+        let period_end = (period_start + Duration::days(CONTRACT_QUARTER_DAYS)).min(end_date);
+        let amount = if period_end == end_date {
+            contract_value - billed
+        } else {
+            (annual_value * Decimal::from((period_end - period_start).num_days()) / Decimal::from(360)).round_dp(2)
+        };
+        billed += amount;
+        periods.push(ContractBillingPeriod { period_start, period_end, amount });
+        period_start = period_end;
+    }
+    periods
+}
+
 // Payment processing request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessPaymentRequest {
     pub invoice_id: Uuid,
     pub payment_method: PaymentMethod,
     pub amount: Option<Decimal>, // For partial payments
+    pub currency: Option<Currency>, // Defaults to the invoice currency
     pub auto_confirm: bool,
 }
 
@@ -219,14 +302,21 @@ pub struct BillingEngine {
     db_pool: PgPool,
     redis: ConnectionManager,
     payment_processor: Arc<PaymentProcessor>,
+    fx_rates: Arc<dyn FxRateProvider>,
     invoice_counter: Arc<tokio::sync::RwLock<u64>>,
+    webhooks: Arc<WebhookDispatcher>,
+    ab_tests: Arc<AbTestManager>,
+    tax: Arc<TaxCalculator>,
 }
 
 impl BillingEngine {
     pub async fn new(
         db_pool: PgPool,
         redis: ConnectionManager,
-        payment_processor: Arc<PaymentProcessor>
+        payment_processor: Arc<PaymentProcessor>,
+        fx_rates: Arc<dyn FxRateProvider>,
+        webhooks: Arc<WebhookDispatcher>,
+        vat_ids: Arc<dyn VatIdValidator>
     ) -> RevenueResult<Self> {
         // Setup billing tables
         Self::setup_billing_tables(&db_pool).await?;
@@ -234,15 +324,40 @@ impl BillingEngine {
         // Initialize invoice counter
         let invoice_counter = Arc::new(tokio::sync::RwLock::new(1));
 
+        let ab_tests = Arc::new(AbTestManager::new(db_pool.clone()));
+        let tax = Arc::new(TaxCalculator::new(db_pool.clone(), vat_ids));
+
         Ok(Self {
             db_pool,
             redis,
             payment_processor,
+            fx_rates,
             invoice_counter,
+            webhooks,
+            ab_tests,
+            tax,
         })
     }
 
-    async fn setup_billing_tables(pool: &PgPool) -> RevenueResult<()> {
+    pub(crate) fn db_pool(&self) -> &PgPool {
+        &self.db_pool
+    }
+
+    pub fn webhooks(&self) -> &Arc<WebhookDispatcher> {
+        &self.webhooks
+    }
+
+    pub fn ab_tests(&self) -> &Arc<AbTestManager> {
+        &self.ab_tests
+    }
+
+    pub fn tax(&self) -> &Arc<TaxCalculator> {
+        &self.tax
+    }
+
+    // Creates the billing schema on top of the subscription schema; run by `new`, and by the
+    // database tests
+    pub async fn setup_billing_tables(pool: &PgPool) -> RevenueResult<()> {
         // Invoices table
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS invoices (
@@ -317,6 +432,34 @@ impl BillingEngine {
             CREATE INDEX IF NOT EXISTS idx_payments_stripe_intent ON payments(stripe_payment_intent_id);
         "#).execute(pool).await?;
 
+        // EU VAT charged on the invoice, kept for VAT returns; NULL on sales tax invoices
+        sqlx::query(r#"
+            ALTER TABLE invoices
+                ADD COLUMN IF NOT EXISTS vat_country CHAR(2),
+                ADD COLUMN IF NOT EXISTS vat_rate DECIMAL(5,4),
+                ADD COLUMN IF NOT EXISTS vat_amount DECIMAL(15,2),
+                ADD COLUMN IF NOT EXISTS vat_reverse_charge BOOLEAN,
+                ADD COLUMN IF NOT EXISTS customer_vat_id VARCHAR;
+
+            CREATE INDEX IF NOT EXISTS idx_invoices_vat_country ON invoices(vat_country, created_at)
+                WHERE vat_country IS NOT NULL;
+        "#).execute(pool).await?;
+
+        // Charged currency columns; payments recorded before multi-currency support were charged in USD
+        sqlx::query(r#"
+            ALTER TABLE payments
+                ADD COLUMN IF NOT EXISTS amount_usd DECIMAL(15,2),
+                ADD COLUMN IF NOT EXISTS charged_currency VARCHAR(10) NOT NULL DEFAULT 'USD',
+                ADD COLUMN IF NOT EXISTS charged_amount DECIMAL(30,8),
+                ADD COLUMN IF NOT EXISTS fx_rate DECIMAL(30,10) NOT NULL DEFAULT 1;
+
+            UPDATE payments SET amount_usd = amount, charged_amount = amount WHERE amount_usd IS NULL;
+
+            ALTER TABLE payments
+                ALTER COLUMN amount_usd SET NOT NULL,
+                ALTER COLUMN charged_amount SET NOT NULL;
+        "#).execute(pool).await?;
+
         // Payment methods table
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS payment_methods (
@@ -356,10 +499,27 @@ impl BillingEngine {
         let invoice_id = Uuid::new_v4();
         let invoice_number = self.generate_invoice_number().await?;
 
-        // Calculate tax (simplified - would integrate with tax service)
-        let tax_rate = 0.0875; // 8.75% tax rate
-        let tax_amount = subscription.amount * Decimal::from_f64_retain(tax_rate).unwrap_or(Decimal::ZERO);
-        let total_amount = subscription.amount + tax_amount;
+        // Users in a pricing experiment on their tier are billed at their variant's price
+        let price = self.ab_tests.price_for(subscription.user_id, &subscription.tier, subscription.amount).await?;
+        let experiment_metadata = match &price.assignment {
+            Some((experiment_id, variant)) => serde_json::json!({
+                "ab_experiment_id": experiment_id,
+                "ab_variant": variant
+            }),
+            None => serde_json::json!({}),
+        };
+
+        // Apply outstanding proration credit; anything beyond this invoice carries over
+        let credit_balance = proration_credit_balance(&subscription.metadata);
+        let proration_credit = apply_proration_credit(price.amount, credit_balance);
+        let amount = price.amount - proration_credit;
+
+        // EU VAT or reverse charge by billing address, sales tax everywhere else
+        let tax_line = self.tax.calculate(subscription.user_id, amount).await?;
+        let tax_rate = f64::try_from(tax_line.rate).unwrap_or(0.0);
+        let tax_amount = tax_line.tax_amount;
+        let total_amount = amount + tax_amount;
+        let vat = Some(&tax_line).filter(|line| line.is_vat());
 
         // Create line item for subscription
         let line_item = InvoiceLineItem {
@@ -370,16 +530,22 @@ impl BillingEngine {
                 billing_period_end.format("%Y-%m-%d")
             ),
             quantity: Decimal::ONE,
-            unit_price: subscription.amount,
-            total_price: subscription.amount,
+            unit_price: price.amount,
+            total_price: price.amount,
             tax_rate,
             metadata: serde_json::json!({
                 "billing_period_start": billing_period_start,
                 "billing_period_end": billing_period_end,
-                "subscription_tier": subscription.tier.to_string()
+                "subscription_tier": subscription.tier.to_string(),
+                "pricing_experiment": experiment_metadata
             }),
         };
 
+        let mut line_items = vec![line_item];
+        if proration_credit > Decimal::ZERO {
+            line_items.push(InvoiceLineItem::proration_credit(proration_credit));
+        }
+
         // Due date (15 days from creation for subscriptions)
         let due_date = Utc::now() + Duration::days(15);
 
@@ -388,8 +554,9 @@ impl BillingEngine {
             r#"
             INSERT INTO invoices 
             (id, subscription_id, user_id, invoice_number, amount, tax_amount, total_amount, 
-             status, due_date, payment_terms, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+             status, due_date, payment_terms, metadata,
+             vat_country, vat_rate, vat_amount, vat_reverse_charge, customer_vat_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
             RETURNING id, subscription_id, user_id, client_id, invoice_number, amount, currency,
                      tax_amount, total_amount, status, due_date, paid_at, stripe_invoice_id,
                      payment_terms, notes, metadata, created_at, updated_at
@@ -398,7 +565,7 @@ impl BillingEngine {
             subscription.id,
             subscription.user_id,
             invoice_number,
-            subscription.amount,
+            amount,
             tax_amount,
             total_amount,
             "pending",
@@ -410,26 +577,43 @@ impl BillingEngine {
                 "billing_period": {
                     "start": billing_period_start,
                     "end": billing_period_end
-                }
-            })
+                },
+                "pricing_experiment": experiment_metadata,
+                "tax_line": tax_line
+            }),
+            vat.and_then(|line| line.country_code.clone()),
+            vat.map(|line| line.rate),
+            vat.map(|line| line.tax_amount),
+            vat.map(|line| line.treatment == TaxTreatment::ReverseCharge),
+            vat.and_then(|line| line.customer_vat_id.clone())
         ).fetch_one(&self.db_pool).await?;
 
-        // Insert line item
-        sqlx::query!(
-            r#"
-            INSERT INTO invoice_line_items 
-            (id, invoice_id, description, quantity, unit_price, total_price, tax_rate, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            "#,
-            line_item.id,
-            invoice_id,
-            line_item.description,
-            line_item.quantity,
-            line_item.unit_price,
-            line_item.total_price,
-            Decimal::from_f64_retain(line_item.tax_rate).unwrap_or(Decimal::ZERO),
-            line_item.metadata
-        ).execute(&self.db_pool).await?;
+        // Insert line items
+        for line_item in &line_items {
+            sqlx::query!(
+                r#"
+                INSERT INTO invoice_line_items 
+                (id, invoice_id, description, quantity, unit_price, total_price, tax_rate, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+                line_item.id,
+                invoice_id,
+                line_item.description,
+                line_item.quantity,
+                line_item.unit_price,
+                line_item.total_price,
+                Decimal::from_f64_retain(line_item.tax_rate).unwrap_or(Decimal::ZERO),
+                line_item.metadata
+            ).execute(&self.db_pool).await?;
+        }
+
+        if proration_credit > Decimal::ZERO {
+            sqlx::query!(
+                "UPDATE subscriptions SET metadata = metadata || $1 WHERE id = $2",
+                serde_json::json!({ PRORATION_CREDIT_BALANCE_KEY: (credit_balance - proration_credit).to_string() }),
+                subscription.id
+            ).execute(&self.db_pool).await?;
+        }
 
         let invoice = Invoice {
             id: invoice_record.id,
@@ -445,7 +629,8 @@ impl BillingEngine {
             due_date: invoice_record.due_date,
             paid_at: invoice_record.paid_at,
             stripe_invoice_id: invoice_record.stripe_invoice_id,
-            line_items: vec![line_item],
+            line_items,
+            tax_line: Some(tax_line),
             payment_terms: invoice_record.payment_terms,
             notes: invoice_record.notes,
             metadata: invoice_record.metadata,
@@ -453,6 +638,15 @@ impl BillingEngine {
             updated_at: invoice_record.updated_at,
         };
 
+        self.webhooks.dispatch(WebhookEvent::InvoiceGenerated {
+            invoice_id: invoice.id,
+            invoice_number: invoice.invoice_number.clone(),
+            user_id: invoice.user_id,
+            total_amount: invoice.total_amount,
+            currency: invoice.currency.clone(),
+            due_date: invoice.due_date,
+        });
+
         // Auto-process payment if subscription has payment method
         if let Some(_stripe_id) = &subscription.stripe_subscription_id {
             self.auto_process_subscription_payment(&invoice).await?;
@@ -472,10 +666,20 @@ impl BillingEngine {
         let invoice = self.get_invoice(request.invoice_id).await?;
         let payment_amount = request.amount.unwrap_or(invoice.total_amount);
 
+        // Convert into the currency the customer pays in
+        let invoice_currency: Currency = invoice.currency.parse()?;
+        let charged_currency = request.currency.unwrap_or(invoice_currency);
+        let conversion = fx::convert(
+            self.fx_rates.as_ref(),
+            payment_amount,
+            invoice_currency,
+            charged_currency
+        ).await?;
+
         // Create payment intent with Stripe
         let payment_intent_id = self.payment_processor.create_payment_intent(
-            payment_amount,
-            &invoice.currency,
+            conversion.charged_amount,
+            charged_currency.code(),
             None, // Customer ID would be fetched from user
             None  // Payment method ID from request
         ).await?;
@@ -485,10 +689,12 @@ impl BillingEngine {
         let payment_record = sqlx::query!(
             r#"
             INSERT INTO payments 
-            (id, invoice_id, subscription_id, user_id, amount, currency, status, 
-             payment_method_type, payment_method_details, stripe_payment_intent_id, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
-            RETURNING id, invoice_id, subscription_id, user_id, amount, currency, status,
+            (id, invoice_id, subscription_id, user_id, amount, currency, amount_usd, charged_currency,
+             charged_amount, fx_rate, status, payment_method_type, payment_method_details,
+             stripe_payment_intent_id, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING id, invoice_id, subscription_id, user_id, amount, currency, amount_usd,
+                     charged_currency, charged_amount, fx_rate, status,
                      payment_method_type, payment_method_details, stripe_payment_intent_id,
                      stripe_charge_id, failure_reason, processed_at, metadata, created_at, updated_at
             "#,
@@ -498,6 +704,10 @@ impl BillingEngine {
             invoice.user_id,
             payment_amount,
             invoice.currency,
+            conversion.amount_usd,
+            charged_currency.code(),
+            conversion.charged_amount,
+            conversion.rate,
             "processing",
             "credit_card", // Would be determined from request.payment_method
             serde_json::to_value(&request.payment_method)?,
@@ -524,6 +734,10 @@ impl BillingEngine {
             user_id: payment_record.user_id,
             amount: payment_record.amount,
             currency: payment_record.currency,
+            amount_usd: payment_record.amount_usd,
+            charged_currency: payment_record.charged_currency.parse()?,
+            charged_amount: payment_record.charged_amount,
+            fx_rate: payment_record.fx_rate,
             status: self.parse_payment_status(&payment_record.status)?,
             payment_method: request.payment_method,
             stripe_payment_intent_id: payment_record.stripe_payment_intent_id,
@@ -535,7 +749,10 @@ impl BillingEngine {
             updated_at: payment_record.updated_at,
         };
 
-        tracing::info!("✅ Payment processed: {} - ${}", payment_id, payment_amount);
+        tracing::info!(
+            "✅ Payment processed: {} - ${} charged as {} {}",
+            payment_id, conversion.amount_usd, conversion.charged_amount, charged_currency
+        );
         Ok(payment)
     }
 
@@ -552,7 +769,7 @@ impl BillingEngine {
     }
 
     // Mark invoice as paid
-    async fn mark_invoice_paid(&self, invoice_id: Uuid, amount: Decimal) -> RevenueResult<()> {
+    pub(crate) async fn mark_invoice_paid(&self, invoice_id: Uuid, amount: Decimal) -> RevenueResult<()> {
         sqlx::query!(
             r#"
             UPDATE invoices 
@@ -562,10 +779,188 @@ impl BillingEngine {
             invoice_id
         ).execute(&self.db_pool).await?;
 
+        self.webhooks.dispatch(WebhookEvent::PaymentSucceeded { invoice_id, amount });
+
         tracing::info!("✅ Invoice marked as paid: {} - ${}", invoice_id, amount);
         Ok(())
     }
 
+    // Stripe confirmed a payment intent; settles the payment and its invoice unless an
+    // earlier confirmation already did. Returns the invoice, or None for an unknown intent.
+    pub async fn confirm_payment(&self, payment_intent_id: &str) -> RevenueResult<Option<Uuid>> {
+        let payment = sqlx::query!(
+            r#"
+            SELECT id, invoice_id, amount, status
+            FROM payments
+            WHERE stripe_payment_intent_id = $1
+            "#,
+            payment_intent_id
+        ).fetch_optional(&self.db_pool).await?;
+
+        let Some(payment) = payment else {
+            tracing::warn!("⚠️ No payment recorded for Stripe payment intent {}", payment_intent_id);
+            return Ok(None);
+        };
+
+        if payment.status != "succeeded" {
+            self.mark_payment_succeeded(payment.id).await?;
+            self.mark_invoice_paid(payment.invoice_id, payment.amount).await?;
+        }
+
+        Ok(Some(payment.invoice_id))
+    }
+
+    // Schedule the invoices of an enterprise contract. They are held as drafts until their
+    // billing period starts and issue_scheduled_contract_invoices sends them out net 30.
+    pub async fn schedule_contract_invoices(
+        &self,
+        contract_id: Uuid,
+        client_id: Uuid,
+        periods: &[ContractBillingPeriod]
+    ) -> RevenueResult<Vec<Uuid>> {
+        let mut invoice_ids = Vec::with_capacity(periods.len());
+
+        for period in periods {
+            let invoice_id = Uuid::new_v4();
+            let invoice_number = self.generate_invoice_number().await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO invoices
+                (id, user_id, client_id, invoice_number, amount, tax_amount, total_amount,
+                 status, due_date, payment_terms, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+                invoice_id,
+                client_id,
+                client_id,
+                invoice_number,
+                period.amount,
+                Decimal::ZERO,
+                period.amount,
+                "draft",
+                period.period_start + Duration::days(30),
+                "net_30",
+                serde_json::json!({
+                    "contract_id": contract_id.to_string(),
+                    "billing_period": {
+                        "start": period.period_start,
+                        "end": period.period_end
+                    }
+                })
+            ).execute(&self.db_pool).await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO invoice_line_items
+                (id, invoice_id, description, quantity, unit_price, total_price, tax_rate, metadata)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+                Uuid::new_v4(),
+                invoice_id,
+                format!("Enterprise contract services - {} to {}",
+                    period.period_start.format("%Y-%m-%d"),
+                    period.period_end.format("%Y-%m-%d")
+                ),
+                Decimal::ONE,
+                period.amount,
+                period.amount,
+                Decimal::ZERO,
+                serde_json::json!({ "line_type": "ContractServices" })
+            ).execute(&self.db_pool).await?;
+
+            invoice_ids.push(invoice_id);
+        }
+
+        tracing::info!("📅 Scheduled {} invoices for enterprise contract {}", invoice_ids.len(), contract_id);
+        Ok(invoice_ids)
+    }
+
+    // Issue scheduled contract invoices whose billing period has started
+    pub async fn issue_scheduled_contract_invoices(&self, now: DateTime<Utc>) -> RevenueResult<usize> {
+        let issued = sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET status = 'pending', updated_at = NOW()
+            WHERE status = 'draft'
+              AND metadata ? 'contract_id'
+              AND (metadata->'billing_period'->>'start')::TIMESTAMPTZ <= $1
+            RETURNING id, user_id, invoice_number, total_amount, currency, due_date
+            "#,
+            now
+        ).fetch_all(&self.db_pool).await?;
+
+        for invoice in &issued {
+            self.webhooks.dispatch(WebhookEvent::InvoiceGenerated {
+                invoice_id: invoice.id,
+                invoice_number: invoice.invoice_number.clone(),
+                user_id: invoice.user_id,
+                total_amount: invoice.total_amount,
+                currency: invoice.currency.clone(),
+                due_date: invoice.due_date,
+            });
+        }
+
+        if !issued.is_empty() {
+            tracing::info!("📄 Issued {} scheduled contract invoices", issued.len());
+        }
+        Ok(issued.len())
+    }
+
+    // Deduct an SLA penalty credit from the contract's next scheduled invoice. Returns the
+    // credited invoice, or None when no scheduled invoice is left to absorb it.
+    pub async fn apply_contract_credit(
+        &self,
+        contract_id: Uuid,
+        credit: Decimal,
+        description: String,
+        metadata: serde_json::Value
+    ) -> RevenueResult<Option<Uuid>> {
+        let next_invoice = sqlx::query!(
+            r#"
+            SELECT id, amount
+            FROM invoices
+            WHERE status = 'draft' AND metadata->>'contract_id' = $1
+            ORDER BY due_date
+            LIMIT 1
+            "#,
+            contract_id.to_string()
+        ).fetch_optional(&self.db_pool).await?;
+
+        let Some(invoice) = next_invoice else {
+            return Ok(None);
+        };
+        let applied = credit.min(invoice.amount);
+
+        sqlx::query!(
+            r#"
+            UPDATE invoices
+            SET amount = amount - $2, total_amount = total_amount - $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            invoice.id,
+            applied
+        ).execute(&self.db_pool).await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO invoice_line_items
+            (id, invoice_id, description, quantity, unit_price, total_price, tax_rate, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            Uuid::new_v4(),
+            invoice.id,
+            description,
+            Decimal::ONE,
+            -applied,
+            -applied,
+            Decimal::ZERO,
+            metadata
+        ).execute(&self.db_pool).await?;
+
+        Ok(Some(invoice.id))
+    }
+
     // Mark payment as succeeded
     async fn mark_payment_succeeded(&self, payment_id: Uuid) -> RevenueResult<()> {
         sqlx::query!(
@@ -613,6 +1008,8 @@ impl BillingEngine {
                     paid_at: record.paid_at,
                     stripe_invoice_id: record.stripe_invoice_id,
                     line_items,
+                    tax_line: record.metadata.get("tax_line")
+                        .and_then(|line| serde_json::from_value(line.clone()).ok()),
                     payment_terms: record.payment_terms,
                     notes: record.notes,
                     metadata: record.metadata,
@@ -666,7 +1063,7 @@ impl BillingEngine {
         // Find subscriptions due for billing
         let due_subscriptions = sqlx::query!(
             r#"
-            SELECT id, user_id, tier, billing_cycle, amount, next_billing_date
+            SELECT id, user_id, tier, billing_cycle, amount, currency, next_billing_date, metadata
             FROM subscriptions 
             WHERE status = 'active' AND next_billing_date <= NOW()
             "#
@@ -683,17 +1080,17 @@ impl BillingEngine {
                 status: crate::subscription::SubscriptionStatus::Active,
                 billing_cycle: self.parse_billing_cycle(&sub_record.billing_cycle)?,
                 amount: sub_record.amount,
-                currency: "USD".to_string(),
+                currency: sub_record.currency,
                 next_billing_date: sub_record.next_billing_date,
                 stripe_subscription_id: None,
                 trial_end_date: None,
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
-                metadata: serde_json::json!({}),
+                metadata: sub_record.metadata.unwrap_or_else(|| serde_json::json!({})),
             };
 
             // Calculate billing period
-            let period_start = subscription.next_billing_date - Duration::days(30); // Assuming monthly
+            let period_start = subscription.next_billing_date - subscription.billing_cycle.period_length();
             let period_end = subscription.next_billing_date;
 
             // Create invoice
@@ -702,11 +1099,7 @@ impl BillingEngine {
                     invoices.push(invoice);
                     
                     // Update next billing date
-                    let next_billing = match subscription.billing_cycle {
-                        BillingCycle::Monthly => subscription.next_billing_date + Duration::days(30),
-                        BillingCycle::Annual => subscription.next_billing_date + Duration::days(365),
-                        BillingCycle::Custom => subscription.next_billing_date + Duration::days(30),
-                    };
+                    let next_billing = subscription.next_billing_date + subscription.billing_cycle.period_length();
 
                     sqlx::query!(
                         "UPDATE subscriptions SET next_billing_date = $1 WHERE id = $2",
@@ -822,11 +1215,58 @@ impl BillingEngine {
 #[derive(Debug)]
 pub struct InvoiceManager {
     billing_engine: Arc<BillingEngine>,
+    // None keeps PDFs render-only
+    storage: Option<InvoiceStorage>,
+    payment_base_url: String,
+    vat_registration_number: Option<String>,
 }
 
 impl InvoiceManager {
-    pub fn new(billing_engine: Arc<BillingEngine>) -> Self {
-        Self { billing_engine }
+    pub fn new(
+        billing_engine: Arc<BillingEngine>,
+        storage: Option<InvoiceStorage>,
+        payment_base_url: String,
+        vat_registration_number: Option<String>,
+    ) -> Self {
+        Self { billing_engine, storage, payment_base_url, vat_registration_number }
+    }
+
+    pub async fn generate_invoice_pdf(&self, invoice_id: Uuid) -> RevenueResult<Vec<u8>> {
+        let invoice = self.billing_engine.get_invoice(invoice_id).await?;
+        self.render_pdf(&invoice)
+    }
+
+    // Renders the invoice, stores it in S3 and announces it with `invoice.pdf_ready`
+    pub async fn publish_invoice_pdf(&self, invoice_id: Uuid) -> RevenueResult<InvoicePdfDownload> {
+        let storage = self.storage.as_ref()
+            .ok_or_else(|| RevenueError::Config("INVOICE_PDF_S3_BUCKET not set".to_string()))?;
+        let invoice = self.billing_engine.get_invoice(invoice_id).await?;
+        let pdf = self.render_pdf(&invoice)?;
+
+        let ttl = Duration::hours(INVOICE_PDF_URL_TTL_HOURS);
+        let expires_at = Utc::now() + ttl;
+        let download_url = storage.upload(&InvoiceStorage::object_key(&invoice.invoice_number), pdf, ttl).await?;
+
+        self.billing_engine.webhooks.dispatch(WebhookEvent::InvoicePdfReady {
+            invoice_id: invoice.id,
+            invoice_number: invoice.invoice_number.clone(),
+            user_id: invoice.user_id,
+            download_url: download_url.clone(),
+            expires_at,
+        });
+
+        tracing::info!("🧾 Invoice PDF ready: {}", invoice.invoice_number);
+        Ok(InvoicePdfDownload { invoice_id: invoice.id, download_url, expires_at })
+    }
+
+    fn render_pdf(&self, invoice: &Invoice) -> RevenueResult<Vec<u8>> {
+        let content = InvoicePdfContent::from_invoice(
+            invoice,
+            DEFAULT_COMPANY_NAME,
+            self.vat_registration_number.as_deref(),
+            payment_url(&self.payment_base_url, invoice.id),
+        );
+        render_invoice_pdf(&content, LOGO_PNG)
     }
 
     pub async fn create_custom_invoice(
@@ -869,4 +1309,30 @@ impl InvoiceManager {
     pub async fn get_analytics(&self) -> RevenueResult<BillingAnalytics> {
         self.billing_engine.get_billing_analytics().await
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_quarterly_billing_periods_for_annual_contract() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let periods = quarterly_billing_periods(start, start + Duration::days(360), Decimal::new(120_000, 0));
+
+        assert_eq!(periods.len(), 4);
+        assert!(periods.iter().all(|p| p.amount == Decimal::new(30_000, 0)));
+        assert_eq!(periods[1].period_start, start + Duration::days(90));
+        assert_eq!(periods[3].period_end, start + Duration::days(360));
+    }
+
+    #[test]
+    fn test_quarterly_billing_periods_prorates_final_quarter() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let periods = quarterly_billing_periods(start, start + Duration::days(210), Decimal::new(100_000, 0));
+
+        let amounts: Vec<_> = periods.iter().map(|p| p.amount).collect();
+        assert_eq!(amounts, vec![Decimal::new(2_500_000, 2), Decimal::new(2_500_000, 2), Decimal::new(833_333, 2)]);
+        assert_eq!(amounts.iter().sum::<Decimal>(), Decimal::new(5_833_333, 2));
+    }
+}
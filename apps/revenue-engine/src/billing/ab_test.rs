@@ -0,0 +1,513 @@
+// Pricing Experiments - A/B tests of subscription prices
+// Users are bucketed into variants by a hash of the experiment and user IDs, billed at their
+// variant's price, and compared on conversion with a two-proportion z-test
+
+use std::collections::HashSet;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
+use serde::{Serialize, Deserialize};
+
+use crate::core::{RevenueError, RevenueResult};
+use crate::subscription::SubscriptionTier;
+
+// Two-sided p-value below which a variant's difference from control is significant
+pub const SIGNIFICANCE_LEVEL: f64 = 0.05;
+
+// One arm of an experiment. The first variant of an experiment is the control.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExperimentVariant {
+    pub name: String,
+    // Relative share of users assigned to this variant
+    pub weight: u32,
+    // Applied to the subscription price, e.g. 1.10 for a 10% increase
+    pub price_multiplier: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub id: Uuid,
+    pub name: String,
+    pub tier: SubscriptionTier,
+    pub variants: Vec<ExperimentVariant>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Experiment {
+    pub fn control(&self) -> &ExperimentVariant {
+        &self.variants[0]
+    }
+
+    // Deterministic, so a user sees the same price for the life of the experiment, and
+    // independent between experiments because the experiment ID is part of the hash
+    pub fn assign(&self, user_id: Uuid) -> &ExperimentVariant {
+        let total_weight: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+        let mut bucket = assignment_hash(self.id, user_id) % total_weight;
+
+        for variant in &self.variants {
+            if bucket < variant.weight as u64 {
+                return variant;
+            }
+            bucket -= variant.weight as u64;
+        }
+        unreachable!("bucket is below the total weight")
+    }
+}
+
+fn assignment_hash(experiment_id: Uuid, user_id: Uuid) -> u64 {
+    let mut input = [0u8; 32];
+    input[..16].copy_from_slice(experiment_id.as_bytes());
+    input[16..].copy_from_slice(user_id.as_bytes());
+
+    let hash = digest(&SHA256, &input);
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_ref()[..8]);
+    u64::from_be_bytes(prefix)
+}
+
+pub fn validate_variants(variants: &[ExperimentVariant]) -> RevenueResult<()> {
+    if variants.len() < 2 {
+        return Err(RevenueError::Validation("An experiment needs a control and at least one variant".to_string()));
+    }
+
+    let mut names = HashSet::new();
+    for variant in variants {
+        if !names.insert(variant.name.as_str()) {
+            return Err(RevenueError::Validation(format!("Duplicate variant name: {}", variant.name)));
+        }
+        if variant.weight == 0 {
+            return Err(RevenueError::Validation(format!("Variant {} has zero weight", variant.name)));
+        }
+        if variant.price_multiplier <= Decimal::ZERO {
+            return Err(RevenueError::Validation(format!("Variant {} must have a positive price multiplier", variant.name)));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbTestEventType {
+    // User was shown the variant's price
+    Exposure,
+    Conversion,
+    Churn,
+}
+
+impl AbTestEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exposure => "exposure",
+            Self::Conversion => "conversion",
+            Self::Churn => "churn",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbTestEvent {
+    pub user_id: Uuid,
+    pub experiment_id: Uuid,
+    pub variant: String,
+    pub event_type: AbTestEventType,
+    pub occurred_at: DateTime<Utc>,
+}
+
+// Price billed for a subscription, with the experiment arm that set it if any
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExperimentPrice {
+    pub amount: Decimal,
+    pub assignment: Option<(Uuid, String)>,
+}
+
+// Distinct users per variant; every user with an event counts as exposed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantCounts {
+    pub variant: String,
+    pub users: u64,
+    pub conversions: u64,
+    pub churns: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariantStats {
+    pub variant: String,
+    pub users: u64,
+    pub conversions: u64,
+    pub churns: u64,
+    pub conversion_rate: f64,
+    // Relative to control; None for the control and when control has no conversions
+    pub lift: Option<f64>,
+    pub z_score: Option<f64>,
+    pub p_value: Option<f64>,
+    pub significant: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExperimentStats {
+    pub experiment_id: Uuid,
+    pub control: String,
+    pub variants: Vec<VariantStats>,
+}
+
+// Two-sided two-proportion z-test with pooled variance. None when either group is empty
+// or nobody or everybody converted, where the test is undefined.
+pub fn two_proportion_z_test(control_conversions: u64, control_users: u64, conversions: u64, users: u64) -> Option<(f64, f64)> {
+    if control_users == 0 || users == 0 {
+        return None;
+    }
+
+    let p_control = control_conversions as f64 / control_users as f64;
+    let p_variant = conversions as f64 / users as f64;
+    let pooled = (control_conversions + conversions) as f64 / (control_users + users) as f64;
+    let standard_error = (pooled * (1.0 - pooled) * (1.0 / control_users as f64 + 1.0 / users as f64)).sqrt();
+    if standard_error == 0.0 {
+        return None;
+    }
+
+    let z = (p_variant - p_control) / standard_error;
+    let p_value = (2.0 * (1.0 - standard_normal_cdf(z.abs()))).clamp(0.0, 1.0);
+    Some((z, p_value))
+}
+
+pub fn standard_normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+// Abramowitz & Stegun 7.1.26, absolute error below 1.5e-7
+fn erf(x: f64) -> f64 {
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+// Variants without any events are reported with zero users
+pub fn compute_experiment_stats(experiment: &Experiment, counts: &[VariantCounts]) -> ExperimentStats {
+    let counts_for = |name: &str| {
+        counts.iter().find(|c| c.variant == name).cloned().unwrap_or(VariantCounts {
+            variant: name.to_string(),
+            users: 0,
+            conversions: 0,
+            churns: 0,
+        })
+    };
+    let rate = |c: &VariantCounts| if c.users == 0 { 0.0 } else { c.conversions as f64 / c.users as f64 };
+
+    let control = counts_for(&experiment.control().name);
+    let control_rate = rate(&control);
+
+    let variants = experiment.variants.iter().enumerate().map(|(index, variant)| {
+        let c = counts_for(&variant.name);
+        let conversion_rate = rate(&c);
+        let (lift, test) = if index == 0 {
+            (None, None)
+        } else {
+            let lift = (control_rate > 0.0).then(|| (conversion_rate - control_rate) / control_rate);
+            (lift, two_proportion_z_test(control.conversions, control.users, c.conversions, c.users))
+        };
+
+        VariantStats {
+            variant: c.variant,
+            users: c.users,
+            conversions: c.conversions,
+            churns: c.churns,
+            conversion_rate,
+            lift,
+            z_score: test.map(|(z, _)| z),
+            p_value: test.map(|(_, p)| p),
+            significant: test.map_or(false, |(_, p)| p < SIGNIFICANCE_LEVEL),
+        }
+    }).collect();
+
+    ExperimentStats {
+        experiment_id: experiment.id,
+        control: experiment.control().name.clone(),
+        variants,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AbTestManager {
+    db_pool: PgPool,
+}
+
+impl AbTestManager {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    // Starts an experiment on a tier's price; a tier runs at most one active experiment
+    pub async fn create_experiment(
+        &self,
+        name: &str,
+        tier: SubscriptionTier,
+        variants: Vec<ExperimentVariant>,
+    ) -> RevenueResult<Experiment> {
+        validate_variants(&variants)?;
+
+        if self.active_experiment_for_tier(&tier).await?.is_some() {
+            return Err(RevenueError::Validation(format!(
+                "{} tier already has an active pricing experiment", tier.to_string()
+            )));
+        }
+
+        let id = Uuid::new_v4();
+        let created_at = sqlx::query_scalar!(
+            r#"
+            INSERT INTO ab_experiments (id, name, tier, variants, active)
+            VALUES ($1, $2, $3, $4, TRUE)
+            RETURNING created_at
+            "#,
+            id,
+            name,
+            tier.clone() as SubscriptionTier,
+            serde_json::to_value(&variants).map_err(|e| RevenueError::Billing(e.to_string()))?
+        ).fetch_one(&self.db_pool).await?;
+
+        let experiment = Experiment {
+            id,
+            name: name.to_string(),
+            tier,
+            variants,
+            active: true,
+            created_at,
+        };
+        tracing::info!("🧪 Started pricing experiment {} on {} tier", experiment.name, experiment.tier.to_string());
+        Ok(experiment)
+    }
+
+    pub async fn end_experiment(&self, experiment_id: Uuid) -> RevenueResult<()> {
+        sqlx::query!("UPDATE ab_experiments SET active = FALSE WHERE id = $1", experiment_id)
+            .execute(&self.db_pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_experiment(&self, experiment_id: Uuid) -> RevenueResult<Experiment> {
+        let record = sqlx::query!(
+            r#"
+            SELECT id, name, tier as "tier: SubscriptionTier", variants, active, created_at
+            FROM ab_experiments
+            WHERE id = $1
+            "#,
+            experiment_id
+        ).fetch_optional(&self.db_pool).await?
+            .ok_or_else(|| RevenueError::Validation(format!("Unknown experiment: {}", experiment_id)))?;
+
+        Ok(Experiment {
+            id: record.id,
+            name: record.name,
+            tier: record.tier,
+            variants: serde_json::from_value(record.variants).map_err(|e| RevenueError::Billing(e.to_string()))?,
+            active: record.active,
+            created_at: record.created_at,
+        })
+    }
+
+    pub async fn active_experiment_for_tier(&self, tier: &SubscriptionTier) -> RevenueResult<Option<Experiment>> {
+        let id = sqlx::query_scalar!(
+            "SELECT id FROM ab_experiments WHERE tier = $1 AND active",
+            tier.clone() as SubscriptionTier
+        ).fetch_optional(&self.db_pool).await?;
+
+        match id {
+            Some(id) => Ok(Some(self.get_experiment(id).await?)),
+            None => Ok(None),
+        }
+    }
+
+    // Price for a user on a tier: the variant's multiple of the base price while the tier
+    // has an active experiment, the base price otherwise
+    pub async fn price_for(&self, user_id: Uuid, tier: &SubscriptionTier, base_price: Decimal) -> RevenueResult<ExperimentPrice> {
+        Ok(match self.active_experiment_for_tier(tier).await? {
+            Some(experiment) => variant_price(&experiment, user_id, base_price),
+            None => ExperimentPrice { amount: base_price, assignment: None },
+        })
+    }
+
+    // The variant is derived from the assignment, never taken from the caller
+    pub async fn record_event(
+        &self,
+        user_id: Uuid,
+        experiment_id: Uuid,
+        event_type: AbTestEventType,
+    ) -> RevenueResult<AbTestEvent> {
+        let experiment = self.get_experiment(experiment_id).await?;
+        let variant = experiment.assign(user_id).name.clone();
+
+        let occurred_at = sqlx::query_scalar!(
+            r#"
+            INSERT INTO ab_test_events (user_id, experiment_id, variant, event_type)
+            VALUES ($1, $2, $3, $4)
+            RETURNING occurred_at
+            "#,
+            user_id,
+            experiment_id,
+            variant,
+            event_type.as_str()
+        ).fetch_one(&self.db_pool).await?;
+
+        Ok(AbTestEvent {
+            user_id,
+            experiment_id,
+            variant,
+            event_type,
+            occurred_at,
+        })
+    }
+
+    pub async fn get_experiment_results(&self, experiment_id: Uuid) -> RevenueResult<ExperimentStats> {
+        let experiment = self.get_experiment(experiment_id).await?;
+
+        let records = sqlx::query!(
+            r#"
+            SELECT variant,
+                   COUNT(DISTINCT user_id) as "users!",
+                   COUNT(DISTINCT user_id) FILTER (WHERE event_type = 'conversion') as "conversions!",
+                   COUNT(DISTINCT user_id) FILTER (WHERE event_type = 'churn') as "churns!"
+            FROM ab_test_events
+            WHERE experiment_id = $1
+            GROUP BY variant
+            "#,
+            experiment_id
+        ).fetch_all(&self.db_pool).await?;
+
+        let counts: Vec<VariantCounts> = records.into_iter().map(|r| VariantCounts {
+            variant: r.variant,
+            users: r.users as u64,
+            conversions: r.conversions as u64,
+            churns: r.churns as u64,
+        }).collect();
+
+        Ok(compute_experiment_stats(&experiment, &counts))
+    }
+}
+
+pub fn variant_price(experiment: &Experiment, user_id: Uuid, base_price: Decimal) -> ExperimentPrice {
+    let variant = experiment.assign(user_id);
+    ExperimentPrice {
+        amount: (base_price * variant.price_multiplier).round_dp(2),
+        assignment: Some((experiment.id, variant.name.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment() -> Experiment {
+        Experiment {
+            id: Uuid::new_v4(),
+            name: "professional_plus_10".to_string(),
+            tier: SubscriptionTier::Professional,
+            variants: vec![
+                ExperimentVariant { name: "control".to_string(), weight: 1, price_multiplier: Decimal::ONE },
+                ExperimentVariant { name: "plus_10".to_string(), weight: 1, price_multiplier: Decimal::new(110, 2) },
+            ],
+            active: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn counts(variant: &str, users: u64, conversions: u64) -> VariantCounts {
+        VariantCounts { variant: variant.to_string(), users, conversions, churns: 0 }
+    }
+
+    #[test]
+    fn test_assignment_is_sticky_and_follows_weights() {
+        let mut experiment = experiment();
+        experiment.variants[0].weight = 3;
+
+        let users: Vec<Uuid> = (0..10_000).map(|_| Uuid::new_v4()).collect();
+        let control = users.iter().filter(|u| experiment.assign(**u).name == "control").count();
+        assert!((7_200..7_800).contains(&control), "{} of 10000 in control", control);
+
+        for user in &users[..100] {
+            assert_eq!(experiment.assign(*user), experiment.assign(*user));
+        }
+    }
+
+    #[test]
+    fn test_variant_price_applies_multiplier() {
+        let experiment = experiment();
+        let base = SubscriptionTier::Professional.monthly_price();
+
+        let user = (0..).map(|_| Uuid::new_v4()).find(|u| experiment.assign(*u).name == "plus_10").unwrap();
+        let price = variant_price(&experiment, user, base);
+        assert_eq!(price.amount, Decimal::new(21890, 2));
+        assert_eq!(price.assignment, Some((experiment.id, "plus_10".to_string())));
+
+        let user = (0..).map(|_| Uuid::new_v4()).find(|u| experiment.assign(*u).name == "control").unwrap();
+        assert_eq!(variant_price(&experiment, user, base).amount, base);
+    }
+
+    #[test]
+    fn test_variants_validated() {
+        let mut variants = experiment().variants;
+        assert!(validate_variants(&variants).is_ok());
+        assert!(validate_variants(&variants[..1]).is_err());
+
+        variants[1].weight = 0;
+        assert!(validate_variants(&variants).is_err());
+        variants[1].weight = 1;
+        variants[1].name = "control".to_string();
+        assert!(validate_variants(&variants).is_err());
+    }
+
+    #[test]
+    fn test_two_proportion_z_test() {
+        // 20% vs 25% on 1000 users each: pooled 22.5%, z ≈ 2.68
+        let (z, p) = two_proportion_z_test(200, 1000, 250, 1000).unwrap();
+        assert!((z - 2.677).abs() < 0.001, "z = {}", z);
+        assert!((p - 0.00743).abs() < 0.0001, "p = {}", p);
+
+        let (z, p) = two_proportion_z_test(100, 1000, 100, 1000).unwrap();
+        assert_eq!(z, 0.0);
+        assert!((p - 1.0).abs() < 1e-6);
+
+        assert_eq!(two_proportion_z_test(0, 1000, 0, 1000), None);
+        assert_eq!(two_proportion_z_test(10, 100, 0, 0), None);
+    }
+
+    #[test]
+    fn test_standard_normal_cdf() {
+        assert!((standard_normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((standard_normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((standard_normal_cdf(-1.96) - 0.025).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_experiment_stats_lift_and_significance() {
+        let experiment = experiment();
+
+        let stats = compute_experiment_stats(&experiment, &[counts("control", 1000, 250), counts("plus_10", 1000, 200)]);
+        assert_eq!(stats.control, "control");
+        assert_eq!(stats.variants[0].lift, None);
+        assert!(!stats.variants[0].significant);
+
+        let treatment = &stats.variants[1];
+        assert!((treatment.conversion_rate - 0.2).abs() < 1e-9);
+        assert!((treatment.lift.unwrap() + 0.2).abs() < 1e-9);
+        assert!(treatment.z_score.unwrap() < 0.0);
+        assert!(treatment.significant);
+
+        // A small sample with the same rates is not significant
+        let stats = compute_experiment_stats(&experiment, &[counts("control", 40, 10), counts("plus_10", 40, 8)]);
+        assert!(!stats.variants[1].significant);
+    }
+
+    #[test]
+    fn test_experiment_stats_without_events() {
+        let stats = compute_experiment_stats(&experiment(), &[]);
+
+        assert_eq!(stats.variants.len(), 2);
+        assert_eq!(stats.variants[1].users, 0);
+        assert_eq!(stats.variants[1].lift, None);
+        assert_eq!(stats.variants[1].p_value, None);
+    }
+}
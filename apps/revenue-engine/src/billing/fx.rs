@@ -0,0 +1,320 @@
+// FX Conversion - Multi-currency payment support
+// Prices are held in USD; payments are charged in the payer's currency at the latest cached rate
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use rust_decimal::{Decimal, RoundingStrategy};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::core::{RevenueError, RevenueResult};
+
+// How often live rates are pulled from the FX API
+pub const FX_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// Live rates older than this fall back to the configured rates
+pub const FX_MAX_RATE_AGE_SECS: i64 = 600;
+
+const FX_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Currencies accepted for payment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum Currency {
+    USD,
+    EUR,
+    GBP,
+    BTC,
+    ETH,
+    USDC,
+}
+
+impl Currency {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::USD => "USD",
+            Self::EUR => "EUR",
+            Self::GBP => "GBP",
+            Self::BTC => "BTC",
+            Self::ETH => "ETH",
+            Self::USDC => "USDC",
+        }
+    }
+
+    // Decimal places a charge in this currency is rounded to
+    pub fn minor_units(&self) -> u32 {
+        match self {
+            Self::USD | Self::EUR | Self::GBP => 2,
+            Self::USDC => 6,
+            Self::BTC | Self::ETH => 8,
+        }
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = RevenueError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code.to_ascii_uppercase().as_str() {
+            "USD" => Ok(Self::USD),
+            "EUR" => Ok(Self::EUR),
+            "GBP" => Ok(Self::GBP),
+            "BTC" => Ok(Self::BTC),
+            "ETH" => Ok(Self::ETH),
+            "USDC" => Ok(Self::USDC),
+            _ => Err(RevenueError::Validation(format!("Unsupported currency: {}", code))),
+        }
+    }
+}
+
+// Source of exchange rates quoted against USD
+#[async_trait::async_trait]
+pub trait FxRateProvider: std::fmt::Debug + Send + Sync {
+    // Units of `currency` bought by one USD
+    async fn usd_rate(&self, currency: Currency) -> RevenueResult<Decimal>;
+}
+
+// Result of converting an amount into the currency it is charged in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FxConversion {
+    pub amount_usd: Decimal,
+    pub charged_currency: Currency,
+    pub charged_amount: Decimal,
+    // Units of the charged currency per unit of the source currency
+    pub rate: Decimal,
+}
+
+pub async fn convert(
+    provider: &dyn FxRateProvider,
+    amount: Decimal,
+    from: Currency,
+    to: Currency,
+) -> RevenueResult<FxConversion> {
+    let from_rate = rate_or_one(provider, from).await?;
+    let to_rate = rate_or_one(provider, to).await?;
+
+    let usd = amount / from_rate;
+    Ok(FxConversion {
+        amount_usd: usd.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero),
+        charged_currency: to,
+        charged_amount: (usd * to_rate)
+            .round_dp_with_strategy(to.minor_units(), RoundingStrategy::MidpointAwayFromZero),
+        rate: to_rate / from_rate,
+    })
+}
+
+async fn rate_or_one(provider: &dyn FxRateProvider, currency: Currency) -> RevenueResult<Decimal> {
+    if currency == Currency::USD {
+        return Ok(Decimal::ONE);
+    }
+    let rate = provider.usd_rate(currency).await?;
+    if rate <= Decimal::ZERO {
+        return Err(RevenueError::External(format!("Invalid FX rate for {}: {}", currency, rate)));
+    }
+    Ok(rate)
+}
+
+// Fallback rates file: {"EUR": "0.92", "GBP": "0.79", ...}
+pub fn load_fallback_rates(path: impl AsRef<Path>) -> RevenueResult<HashMap<Currency, Decimal>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| RevenueError::Config(format!("Failed to read FX fallback rates {}: {}", path.display(), e)))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| RevenueError::Config(format!("Invalid FX fallback rates {}: {}", path.display(), e)))
+}
+
+// FX API response; unknown currency codes are ignored
+#[derive(Debug, Deserialize)]
+struct FxApiResponse {
+    rates: HashMap<String, Decimal>,
+}
+
+#[derive(Debug)]
+struct LiveRates {
+    rates: HashMap<Currency, Decimal>,
+    fetched_at: DateTime<Utc>,
+}
+
+// Polls the FX API and serves the latest rates, falling back to configured rates when
+// the API has been unreachable for longer than FX_MAX_RATE_AGE_SECS
+#[derive(Debug)]
+pub struct CachedFxRateProvider {
+    client: reqwest::Client,
+    api_url: String,
+    live: RwLock<Option<LiveRates>>,
+    fallback: HashMap<Currency, Decimal>,
+}
+
+impl CachedFxRateProvider {
+    pub fn new(api_url: String, fallback: HashMap<Currency, Decimal>) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(FX_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            api_url,
+            live: RwLock::new(None),
+            fallback,
+        }
+    }
+
+    // Refreshes rates every FX_REFRESH_INTERVAL; failures keep the previous rates
+    pub async fn start(&self) {
+        let mut interval = tokio::time::interval(FX_REFRESH_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.refresh().await {
+                tracing::warn!("⚠️ FX rate refresh failed: {}", e);
+            }
+        }
+    }
+
+    // Fetches the latest rates and returns how many supported currencies were quoted
+    pub async fn refresh(&self) -> RevenueResult<usize> {
+        let response: FxApiResponse = self.client
+            .get(&self.api_url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RevenueError::External(format!("FX API request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| RevenueError::External(format!("Malformed FX API response: {}", e)))?;
+
+        let rates: HashMap<Currency, Decimal> = response.rates
+            .into_iter()
+            .filter(|(_, rate)| *rate > Decimal::ZERO)
+            .filter_map(|(code, rate)| code.parse().ok().map(|currency| (currency, rate)))
+            .collect();
+        let quoted = rates.len();
+
+        *self.live.write().await = Some(LiveRates { rates, fetched_at: Utc::now() });
+        tracing::debug!("💱 Refreshed {} FX rates", quoted);
+        Ok(quoted)
+    }
+}
+
+#[async_trait::async_trait]
+impl FxRateProvider for CachedFxRateProvider {
+    async fn usd_rate(&self, currency: Currency) -> RevenueResult<Decimal> {
+        if currency == Currency::USD {
+            return Ok(Decimal::ONE);
+        }
+
+        if let Some(live) = self.live.read().await.as_ref() {
+            let fresh = (Utc::now() - live.fetched_at).num_seconds() <= FX_MAX_RATE_AGE_SECS;
+            if let Some(rate) = live.rates.get(&currency).filter(|_| fresh) {
+                return Ok(*rate);
+            }
+        }
+
+        match self.fallback.get(&currency) {
+            Some(rate) => {
+                tracing::warn!("⚠️ No live FX rate for {}, using fallback {}", currency, rate);
+                Ok(*rate)
+            }
+            None => Err(RevenueError::External(format!("No FX rate available for {}", currency))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn fallback() -> HashMap<Currency, Decimal> {
+        HashMap::from([(Currency::EUR, Decimal::new(90, 2)), (Currency::BTC, Decimal::new(2, 5))])
+    }
+
+    async fn fx_api(rates: serde_json::Value) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/latest"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "base": "USD",
+                "rates": rates,
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[test]
+    fn test_currency_codes_round_trip() {
+        for currency in [Currency::USD, Currency::EUR, Currency::GBP, Currency::BTC, Currency::ETH, Currency::USDC] {
+            assert_eq!(currency.code().parse::<Currency>().unwrap(), currency);
+        }
+        assert_eq!("eur".parse::<Currency>().unwrap(), Currency::EUR);
+        assert!(matches!("JPY".parse::<Currency>(), Err(RevenueError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_live_rates_are_used_after_refresh() {
+        let server = fx_api(serde_json::json!({ "EUR": 0.92, "GBP": 0.79, "JPY": 151.2, "BTC": 0.000016 })).await;
+        let provider = CachedFxRateProvider::new(format!("{}/latest", server.uri()), fallback());
+
+        assert_eq!(provider.refresh().await.unwrap(), 3);
+        assert_eq!(provider.usd_rate(Currency::EUR).await.unwrap(), Decimal::new(92, 2));
+        assert_eq!(provider.usd_rate(Currency::USD).await.unwrap(), Decimal::ONE);
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_when_api_unavailable() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let provider = CachedFxRateProvider::new(format!("{}/latest", server.uri()), fallback());
+
+        assert!(provider.refresh().await.is_err());
+        assert_eq!(provider.usd_rate(Currency::EUR).await.unwrap(), Decimal::new(90, 2));
+        assert!(matches!(provider.usd_rate(Currency::GBP).await, Err(RevenueError::External(_))));
+    }
+
+    #[tokio::test]
+    async fn test_convert_rounds_to_charged_currency() {
+        let server = fx_api(serde_json::json!({ "EUR": "0.92", "GBP": "0.79", "BTC": "0.0000163" })).await;
+        let provider = CachedFxRateProvider::new(format!("{}/latest", server.uri()), HashMap::new());
+        provider.refresh().await.unwrap();
+
+        let eur = convert(&provider, Decimal::new(29999, 2), Currency::USD, Currency::EUR).await.unwrap();
+        assert_eq!(eur.amount_usd, Decimal::new(29999, 2));
+        assert_eq!(eur.charged_amount, Decimal::new(27599, 2));
+        assert_eq!(eur.rate, Decimal::new(92, 2));
+
+        let btc = convert(&provider, Decimal::new(1000, 0), Currency::USD, Currency::BTC).await.unwrap();
+        assert_eq!(btc.charged_amount, Decimal::new(1630000, 8));
+
+        // Non-USD invoices are priced back to USD through the same table
+        let gbp = convert(&provider, Decimal::new(92, 0), Currency::EUR, Currency::GBP).await.unwrap();
+        assert_eq!(gbp.amount_usd, Decimal::new(100, 0));
+        assert_eq!(gbp.charged_amount, Decimal::new(79, 0));
+    }
+
+    #[test]
+    fn test_load_fallback_rates() {
+        let path = std::env::temp_dir().join(format!("fx_fallback_{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"EUR": "0.91", "USDC": "1"}"#).unwrap();
+
+        let rates = load_fallback_rates(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(rates.get(&Currency::EUR), Some(&Decimal::new(91, 2)));
+        assert_eq!(rates.get(&Currency::USDC), Some(&Decimal::ONE));
+        assert!(matches!(load_fallback_rates(&path), Err(RevenueError::Config(_))));
+    }
+}
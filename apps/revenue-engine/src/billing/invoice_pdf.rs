@@ -0,0 +1,457 @@
+// Invoice PDF - Printable invoice documents
+// Renders an A4 invoice with the platform logo and a pay-by-QR code, and stores it in S3 for
+// pre-signed download
+
+use std::io::Cursor;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Duration, Utc};
+use printpdf::{
+    image_crate::codecs::png::PngDecoder, BuiltinFont, Color, Image, ImageTransform, IndirectFontRef, Mm,
+    PdfDocument, PdfLayerReference, Rect, Rgb,
+};
+use qrcode::QrCode;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::billing::Invoice;
+use crate::billing::tax::{TaxTreatment, REVERSE_CHARGE_NOTICE};
+use crate::core::{RevenueError, RevenueResult};
+
+pub const DEFAULT_COMPANY_NAME: &str = "Nockchain";
+
+// Opaque RGB; printpdf 0.7 writes PNG alpha channels as an invalid inline soft mask
+pub const LOGO_PNG: &[u8] = include_bytes!("../../assets/nock-logo.png");
+
+// Download links stay valid for the longest period S3 allows a pre-signed URL
+pub const INVOICE_PDF_URL_TTL_HOURS: i64 = 7 * 24;
+
+const PAGE_WIDTH_MM: f32 = 210.0;
+const PAGE_HEIGHT_MM: f32 = 297.0;
+const MARGIN_MM: f32 = 20.0;
+const LOGO_SIZE_MM: f32 = 20.0;
+const QR_SIZE_MM: f32 = 35.0;
+const ROW_HEIGHT_MM: f32 = 7.0;
+// Longest line item description that fits the description column at 10pt
+const MAX_DESCRIPTION_CHARS: usize = 60;
+
+// Column x positions for the line item table
+const QUANTITY_X_MM: f32 = 115.0;
+const UNIT_PRICE_X_MM: f32 = 135.0;
+const AMOUNT_X_MM: f32 = 165.0;
+
+pub fn payment_url(base_url: &str, invoice_id: Uuid) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), invoice_id)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoicePdfLine {
+    pub description: String,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub amount: Decimal,
+}
+
+// Everything printed on the invoice, in display order
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoicePdfContent {
+    pub company_name: String,
+    // Printed on VAT invoices only
+    pub seller_vat_number: Option<String>,
+    pub invoice_number: String,
+    pub issued_at: DateTime<Utc>,
+    pub due_date: DateTime<Utc>,
+    pub billing_period: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pub bill_to: Uuid,
+    pub customer_vat_id: Option<String>,
+    pub currency: String,
+    pub lines: Vec<InvoicePdfLine>,
+    pub subtotal: Decimal,
+    pub fee: Decimal,
+    pub tax_label: String,
+    pub tax: Decimal,
+    pub total: Decimal,
+    pub tax_notice: Option<String>,
+    pub payment_url: String,
+}
+
+impl InvoicePdfContent {
+    // The fee is whatever the total carries beyond the subtotal and tax
+    pub fn from_invoice(
+        invoice: &Invoice,
+        company_name: &str,
+        seller_vat_number: Option<&str>,
+        payment_url: String,
+    ) -> Self {
+        let vat = invoice.tax_line.as_ref().filter(|line| line.is_vat());
+        let billing_period = invoice.metadata.get("billing_period").and_then(|period| {
+            let start = serde_json::from_value(period.get("start")?.clone()).ok()?;
+            let end = serde_json::from_value(period.get("end")?.clone()).ok()?;
+            Some((start, end))
+        });
+
+        Self {
+            company_name: company_name.to_string(),
+            seller_vat_number: vat.and(seller_vat_number).map(str::to_string),
+            invoice_number: invoice.invoice_number.clone(),
+            issued_at: invoice.created_at,
+            due_date: invoice.due_date,
+            billing_period,
+            bill_to: invoice.client_id.unwrap_or(invoice.user_id),
+            customer_vat_id: vat.and_then(|line| line.customer_vat_id.clone()),
+            currency: invoice.currency.clone(),
+            lines: invoice.line_items.iter().map(|item| InvoicePdfLine {
+                description: item.description.clone(),
+                quantity: item.quantity,
+                unit_price: item.unit_price,
+                amount: item.total_price,
+            }).collect(),
+            subtotal: invoice.amount,
+            fee: invoice.total_amount - invoice.amount - invoice.tax_amount,
+            tax_label: invoice.tax_line.as_ref().map_or_else(|| "Tax".to_string(), |line| line.label()),
+            tax: invoice.tax_amount,
+            total: invoice.total_amount,
+            tax_notice: vat
+                .filter(|line| line.treatment == TaxTreatment::ReverseCharge)
+                .map(|_| REVERSE_CHARGE_NOTICE.to_string()),
+            payment_url,
+        }
+    }
+
+    fn money(&self, amount: Decimal) -> String {
+        format!("{} {:.2}", self.currency, amount)
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max_chars - 3).collect();
+    format!("{}...", kept)
+}
+
+fn render_error(e: impl std::fmt::Display) -> RevenueError {
+    RevenueError::Billing(format!("Failed to render invoice PDF: {}", e))
+}
+
+// QR code drawn as filled squares, lower left corner at (x, y)
+fn draw_qr_code(layer: &PdfLayerReference, data: &str, x: f32, y: f32) -> RevenueResult<()> {
+    let code = QrCode::new(data.as_bytes()).map_err(render_error)?;
+    let width = code.width();
+    let module = QR_SIZE_MM / width as f32;
+
+    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    for (index, color) in code.to_colors().iter().enumerate() {
+        if *color != qrcode::Color::Dark {
+            continue;
+        }
+        let left = x + (index % width) as f32 * module;
+        let top = y + QR_SIZE_MM - (index / width) as f32 * module;
+        layer.add_rect(Rect::new(Mm(left), Mm(top - module), Mm(left + module), Mm(top)));
+    }
+    Ok(())
+}
+
+pub fn render_invoice_pdf(content: &InvoicePdfContent, logo_png: &[u8]) -> RevenueResult<Vec<u8>> {
+    let (doc, page, layer) = PdfDocument::new(
+        format!("Invoice {}", content.invoice_number),
+        Mm(PAGE_WIDTH_MM),
+        Mm(PAGE_HEIGHT_MM),
+        "Invoice",
+    );
+    let layer = doc.get_page(page).get_layer(layer);
+    let regular: IndirectFontRef = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(render_error)?;
+    let bold: IndirectFontRef = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(render_error)?;
+
+    // Header: logo, company name and invoice details
+    let top = PAGE_HEIGHT_MM - MARGIN_MM;
+    let logo = Image::try_from(PngDecoder::new(Cursor::new(logo_png)).map_err(render_error)?).map_err(render_error)?;
+    let logo_dpi = logo.image.width.0 as f32 * 25.4 / LOGO_SIZE_MM;
+    logo.add_to_layer(layer.clone(), ImageTransform {
+        translate_x: Some(Mm(MARGIN_MM)),
+        translate_y: Some(Mm(top - LOGO_SIZE_MM)),
+        dpi: Some(logo_dpi),
+        ..Default::default()
+    });
+    layer.use_text(&content.company_name, 18.0, Mm(MARGIN_MM + LOGO_SIZE_MM + 5.0), Mm(top - 12.0), &bold);
+    if let Some(vat_number) = &content.seller_vat_number {
+        let text = format!("VAT No: {}", vat_number);
+        layer.use_text(text, 9.0, Mm(MARGIN_MM + LOGO_SIZE_MM + 5.0), Mm(top - 18.0), &regular);
+    }
+    layer.use_text("INVOICE", 20.0, Mm(UNIT_PRICE_X_MM + 15.0), Mm(top - 12.0), &bold);
+
+    let mut y = top - LOGO_SIZE_MM - 10.0;
+    let mut details = vec![
+        format!("Invoice number: {}", content.invoice_number),
+        format!("Issue date: {}", content.issued_at.format("%Y-%m-%d")),
+        format!("Due date: {}", content.due_date.format("%Y-%m-%d")),
+    ];
+    if let Some((start, end)) = content.billing_period {
+        details.push(format!("Billing period: {} to {}", start.format("%Y-%m-%d"), end.format("%Y-%m-%d")));
+    }
+    layer.use_text("Bill to", 10.0, Mm(MARGIN_MM), Mm(y), &bold);
+    layer.use_text(format!("Customer {}", content.bill_to), 10.0, Mm(MARGIN_MM), Mm(y - 5.0), &regular);
+    if let Some(vat_id) = &content.customer_vat_id {
+        layer.use_text(format!("VAT ID: {}", vat_id), 10.0, Mm(MARGIN_MM), Mm(y - 10.0), &regular);
+    }
+    for detail in &details {
+        layer.use_text(detail, 10.0, Mm(QUANTITY_X_MM), Mm(y), &regular);
+        y -= 5.0;
+    }
+
+    // Line items
+    y -= 12.0;
+    layer.use_text("Description", 10.0, Mm(MARGIN_MM), Mm(y), &bold);
+    layer.use_text("Qty", 10.0, Mm(QUANTITY_X_MM), Mm(y), &bold);
+    layer.use_text("Unit price", 10.0, Mm(UNIT_PRICE_X_MM), Mm(y), &bold);
+    layer.use_text("Amount", 10.0, Mm(AMOUNT_X_MM), Mm(y), &bold);
+    layer.add_rect(Rect::new(Mm(MARGIN_MM), Mm(y - 2.5), Mm(PAGE_WIDTH_MM - MARGIN_MM), Mm(y - 2.2)));
+    for line in &content.lines {
+        y -= ROW_HEIGHT_MM;
+        layer.use_text(truncate(&line.description, MAX_DESCRIPTION_CHARS), 10.0, Mm(MARGIN_MM), Mm(y), &regular);
+        layer.use_text(line.quantity.normalize().to_string(), 10.0, Mm(QUANTITY_X_MM), Mm(y), &regular);
+        layer.use_text(content.money(line.unit_price), 10.0, Mm(UNIT_PRICE_X_MM), Mm(y), &regular);
+        layer.use_text(content.money(line.amount), 10.0, Mm(AMOUNT_X_MM), Mm(y), &regular);
+    }
+
+    // Totals
+    y -= ROW_HEIGHT_MM + 3.0;
+    for (label, amount) in [("Subtotal", content.subtotal), ("Fee", content.fee), (content.tax_label.as_str(), content.tax)] {
+        layer.use_text(label, 10.0, Mm(UNIT_PRICE_X_MM), Mm(y), &regular);
+        layer.use_text(content.money(amount), 10.0, Mm(AMOUNT_X_MM), Mm(y), &regular);
+        y -= 6.0;
+    }
+    layer.use_text("Total", 12.0, Mm(UNIT_PRICE_X_MM), Mm(y - 1.0), &bold);
+    layer.use_text(content.money(content.total), 12.0, Mm(AMOUNT_X_MM), Mm(y - 1.0), &bold);
+    if let Some(notice) = &content.tax_notice {
+        layer.use_text(notice, 8.0, Mm(MARGIN_MM), Mm(y - 12.0), &regular);
+    }
+
+    // Payment QR code
+    draw_qr_code(&layer, &content.payment_url, MARGIN_MM, MARGIN_MM + 5.0)?;
+    layer.use_text("Scan to pay or visit:", 10.0, Mm(MARGIN_MM + QR_SIZE_MM + 5.0), Mm(MARGIN_MM + 25.0), &bold);
+    layer.use_text(&content.payment_url, 9.0, Mm(MARGIN_MM + QR_SIZE_MM + 5.0), Mm(MARGIN_MM + 19.0), &regular);
+
+    doc.save_to_bytes().map_err(render_error)
+}
+
+// Where a stored invoice PDF can be downloaded until the link expires
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct InvoicePdfDownload {
+    pub invoice_id: Uuid,
+    pub download_url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+// S3 or S3-compatible bucket holding rendered invoices
+#[derive(Debug, Clone)]
+pub struct InvoiceStorage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl InvoiceStorage {
+    pub fn new(client: aws_sdk_s3::Client, bucket: String) -> Self {
+        Self { client, bucket }
+    }
+
+    // None unless INVOICE_PDF_S3_BUCKET is set; INVOICE_PDF_S3_ENDPOINT points at an
+    // S3-compatible store, credentials and region come from the usual AWS variables
+    pub async fn from_env() -> Option<Self> {
+        let bucket = std::env::var("INVOICE_PDF_S3_BUCKET").ok().filter(|bucket| !bucket.is_empty())?;
+        let shared = aws_config::load_from_env().await;
+        let mut config = aws_sdk_s3::config::Builder::from(&shared);
+        if let Ok(endpoint) = std::env::var("INVOICE_PDF_S3_ENDPOINT") {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+        Some(Self::new(aws_sdk_s3::Client::from_conf(config.build()), bucket))
+    }
+
+    pub fn object_key(invoice_number: &str) -> String {
+        format!("invoices/{}.pdf", invoice_number)
+    }
+
+    // Uploads the PDF and returns a pre-signed download URL valid for `ttl`
+    pub async fn upload(&self, key: &str, pdf: Vec<u8>, ttl: Duration) -> RevenueResult<String> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type("application/pdf")
+            .body(ByteStream::from(pdf))
+            .send()
+            .await
+            .map_err(|e| RevenueError::External(format!("Failed to upload s3://{}/{}: {}", self.bucket, key, e)))?;
+
+        let presigning = ttl.to_std()
+            .map_err(|e| RevenueError::Config(e.to_string()))
+            .and_then(|ttl| PresigningConfig::expires_in(ttl).map_err(|e| RevenueError::Config(e.to_string())))?;
+        let request = self.client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await
+            .map_err(|e| RevenueError::External(format!("Failed to pre-sign invoice download: {}", e)))?;
+        Ok(request.uri().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::billing::{InvoiceLineItem, InvoiceStatus};
+    use crate::billing::tax::TaxLineItem;
+    use chrono::TimeZone;
+    use lopdf::content::Content;
+
+    fn invoice() -> Invoice {
+        let issued_at = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let line = |description: &str, amount: Decimal| InvoiceLineItem {
+            id: Uuid::new_v4(),
+            description: description.to_string(),
+            quantity: Decimal::ONE,
+            unit_price: amount,
+            total_price: amount,
+            tax_rate: 0.0875,
+            metadata: serde_json::json!({}),
+        };
+
+        Invoice {
+            id: Uuid::new_v4(),
+            subscription_id: Some(Uuid::new_v4()),
+            user_id: Uuid::new_v4(),
+            client_id: None,
+            invoice_number: "INV-20240601-000042".to_string(),
+            amount: Decimal::new(17900, 2),
+            currency: "USD".to_string(),
+            tax_amount: Decimal::new(1566, 2),
+            total_amount: Decimal::new(19466, 2),
+            status: InvoiceStatus::Pending,
+            due_date: issued_at + Duration::days(15),
+            paid_at: None,
+            stripe_invoice_id: None,
+            line_items: vec![
+                line("Professional Subscription - 2024-06-01 to 2024-07-01", Decimal::new(19900, 2)),
+                InvoiceLineItem::proration_credit(Decimal::new(2000, 2)),
+            ],
+            tax_line: Some(TaxLineItem::sales_tax(Some("US".to_string()), Decimal::new(17900, 2))),
+            payment_terms: "net_15".to_string(),
+            notes: None,
+            metadata: serde_json::json!({
+                "billing_period": { "start": issued_at, "end": issued_at + Duration::days(30) }
+            }),
+            created_at: issued_at,
+            updated_at: issued_at,
+        }
+    }
+
+    fn render(invoice: &Invoice) -> lopdf::Document {
+        let content = InvoicePdfContent::from_invoice(
+            invoice,
+            DEFAULT_COMPANY_NAME,
+            Some("EU372000041"),
+            payment_url("https://pay.nockchain.com/invoices/", invoice.id),
+        );
+        let pdf = render_invoice_pdf(&content, LOGO_PNG).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+        lopdf::Document::load_mem(&pdf).unwrap()
+    }
+
+    #[test]
+    fn test_pdf_contains_mandatory_fields() {
+        let invoice = invoice();
+        let text = render(&invoice).extract_text(&[1]).unwrap();
+
+        for field in [
+            "Nockchain",
+            "INVOICE",
+            "Invoice number: INV-20240601-000042",
+            "Due date: 2024-06-16",
+            "Billing period: 2024-06-01 to 2024-07-01",
+            "Professional Subscription - 2024-06-01 to 2024-07-01",
+            "Proration credit for unused time on previous plan",
+            "USD 199.00",
+            "USD -20.00",
+            "Subtotal",
+            "USD 179.00",
+            "Fee",
+            "USD 0.00",
+            "Tax 8.75%",
+            "USD 15.66",
+            "Total",
+            "USD 194.66",
+        ] {
+            assert!(text.contains(field), "{:?} missing from invoice PDF:\n{}", field, text);
+        }
+        assert!(text.contains(&format!("https://pay.nockchain.com/invoices/{}", invoice.id)));
+        assert!(text.contains(&invoice.user_id.to_string()));
+        assert!(!text.contains("VAT"));
+    }
+
+    #[test]
+    fn test_vat_invoice_shows_registration_numbers() {
+        let mut invoice = invoice();
+        invoice.currency = "EUR".to_string();
+        invoice.tax_line = Some(TaxLineItem::vat("NL".to_string(), Decimal::new(21, 2), invoice.amount));
+        invoice.tax_amount = Decimal::new(3759, 2);
+        invoice.total_amount = Decimal::new(21659, 2);
+        let text = render(&invoice).extract_text(&[1]).unwrap();
+
+        for field in ["VAT No: EU372000041", "VAT 21%", "EUR 37.59", "EUR 216.59"] {
+            assert!(text.contains(field), "{:?} missing from invoice PDF:\n{}", field, text);
+        }
+        assert!(!text.contains("VAT ID"));
+        assert!(!text.contains("Reverse charge"));
+    }
+
+    #[test]
+    fn test_reverse_charge_invoice_shows_customer_vat_id() {
+        let mut invoice = invoice();
+        invoice.currency = "EUR".to_string();
+        invoice.tax_line = Some(TaxLineItem::reverse_charge("DE".to_string(), "DE123456789".to_string(), invoice.amount));
+        invoice.tax_amount = Decimal::ZERO;
+        invoice.total_amount = invoice.amount;
+        let text = render(&invoice).extract_text(&[1]).unwrap();
+
+        for field in ["VAT No: EU372000041", "VAT ID: DE123456789", "VAT 0% (reverse charge)", "EUR 0.00", "Reverse charge"] {
+            assert!(text.contains(field), "{:?} missing from invoice PDF:\n{}", field, text);
+        }
+    }
+
+    #[test]
+    fn test_pdf_embeds_logo_and_qr_code() {
+        let doc = render(&invoice());
+
+        let images = doc.objects.values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| matches!(stream.dict.get(b"Subtype").and_then(|v| v.as_name()), Ok(b"Image")))
+            .count();
+        assert_eq!(images, 1);
+
+        // The QR code is drawn as filled squares, far more than the single table rule
+        let page = *doc.get_pages().get(&1).unwrap();
+        let operations = Content::decode(&doc.get_page_content(page).unwrap()).unwrap().operations;
+        let rects = operations.iter().filter(|op| op.operator == "re").count();
+        assert!(rects > 100, "only {} rectangles drawn", rects);
+    }
+
+    #[test]
+    fn test_fee_is_total_beyond_subtotal_and_tax() {
+        let mut invoice = invoice();
+        invoice.total_amount += Decimal::new(250, 2);
+        invoice.metadata = serde_json::json!({});
+
+        let content = InvoicePdfContent::from_invoice(&invoice, DEFAULT_COMPANY_NAME, None, String::new());
+        assert_eq!(content.fee, Decimal::new(250, 2));
+        assert_eq!(content.billing_period, None);
+    }
+
+    #[test]
+    fn test_long_descriptions_truncated() {
+        let long = "x".repeat(100);
+        assert_eq!(truncate(&long, MAX_DESCRIPTION_CHARS).chars().count(), MAX_DESCRIPTION_CHARS);
+        assert_eq!(truncate("short", MAX_DESCRIPTION_CHARS), "short");
+    }
+}
@@ -0,0 +1,507 @@
+// Tax Calculation - EU VAT and sales tax on subscription invoices
+// Customers billed to an EU country pay that country's VAT rate from the vat_rates table;
+// businesses with a VAT ID confirmed by VIES are invoiced under the reverse charge instead
+
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, TimeZone, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Serialize, Deserialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::core::{RevenueError, RevenueResult};
+
+// VIES REST endpoint of the European Commission
+pub const VIES_API_URL: &str = "https://ec.europa.eu/taxation_customs/vies/rest-api/check-vat-number";
+
+// Rate charged outside the EU and to customers without a billing address
+pub const DEFAULT_SALES_TAX_RATE: Decimal = Decimal::from_parts(875, 0, 0, false, 4);
+
+// Printed on invoices where the customer accounts for the VAT
+pub const REVERSE_CHARGE_NOTICE: &str =
+    "Reverse charge: VAT to be accounted for by the recipient (Article 196, Council Directive 2006/112/EC)";
+
+const VIES_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxTreatment {
+    SalesTax,
+    Vat,
+    // Zero-rated B2B supply, VAT self-assessed by the customer
+    ReverseCharge,
+}
+
+// Tax charged on an invoice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLineItem {
+    pub treatment: TaxTreatment,
+    pub country_code: Option<String>,
+    pub rate: Decimal,
+    pub taxable_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub customer_vat_id: Option<String>,
+}
+
+impl TaxLineItem {
+    pub fn sales_tax(country_code: Option<String>, taxable_amount: Decimal) -> Self {
+        Self {
+            treatment: TaxTreatment::SalesTax,
+            country_code,
+            rate: DEFAULT_SALES_TAX_RATE,
+            taxable_amount,
+            tax_amount: tax_on(taxable_amount, DEFAULT_SALES_TAX_RATE),
+            customer_vat_id: None,
+        }
+    }
+
+    pub fn vat(country_code: String, rate: Decimal, taxable_amount: Decimal) -> Self {
+        Self {
+            treatment: TaxTreatment::Vat,
+            country_code: Some(country_code),
+            rate,
+            taxable_amount,
+            tax_amount: tax_on(taxable_amount, rate),
+            customer_vat_id: None,
+        }
+    }
+
+    pub fn reverse_charge(country_code: String, customer_vat_id: String, taxable_amount: Decimal) -> Self {
+        Self {
+            treatment: TaxTreatment::ReverseCharge,
+            country_code: Some(country_code),
+            rate: Decimal::ZERO,
+            taxable_amount,
+            tax_amount: Decimal::ZERO,
+            customer_vat_id: Some(customer_vat_id),
+        }
+    }
+
+    pub fn is_vat(&self) -> bool {
+        self.treatment != TaxTreatment::SalesTax
+    }
+
+    // Label for the tax row of the invoice totals, e.g. "VAT 21%"
+    pub fn label(&self) -> String {
+        let percent = (self.rate * Decimal::ONE_HUNDRED).normalize();
+        match self.treatment {
+            TaxTreatment::SalesTax => format!("Tax {}%", percent),
+            TaxTreatment::Vat => format!("VAT {}%", percent),
+            TaxTreatment::ReverseCharge => "VAT 0% (reverse charge)".to_string(),
+        }
+    }
+}
+
+pub fn tax_on(amount: Decimal, rate: Decimal) -> Decimal {
+    (amount * rate).round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct BillingAddress {
+    pub user_id: Uuid,
+    pub name: Option<String>,
+    pub line1: String,
+    pub line2: Option<String>,
+    pub city: String,
+    pub postal_code: Option<String>,
+    // ISO 3166-1 alpha-2
+    pub country_code: String,
+    pub vat_id: Option<String>,
+}
+
+// Splits a VAT ID into its member state prefix and number, ignoring the spaces, dots and
+// dashes customers tend to type
+pub fn parse_vat_id(vat_id: &str) -> Option<(String, String)> {
+    let compact: String = vat_id
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '.' && *c != '-')
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if compact.len() < 4 || !compact.is_ascii() {
+        return None;
+    }
+
+    let (prefix, number) = compact.split_at(2);
+    if !prefix.chars().all(|c| c.is_ascii_alphabetic()) || !number.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some((prefix.to_string(), number.to_string()))
+}
+
+// VAT IDs use ISO country codes except Greece, which registers under EL
+pub fn vat_prefix(country_code: &str) -> &str {
+    match country_code {
+        "GR" => "EL",
+        code => code,
+    }
+}
+
+// Source of truth for whether a VAT ID is registered
+#[async_trait::async_trait]
+pub trait VatIdValidator: std::fmt::Debug + Send + Sync {
+    async fn is_valid(&self, prefix: &str, number: &str) -> RevenueResult<bool>;
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ViesRequest<'a> {
+    country_code: &'a str,
+    vat_number: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ViesResponse {
+    valid: bool,
+    // VALID or INVALID, otherwise why the member state could not answer
+    user_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ViesClient {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl ViesClient {
+    pub fn new(api_url: String) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(VIES_REQUEST_TIMEOUT)
+                .build()
+                .expect("Failed to build HTTP client"),
+            api_url,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VatIdValidator for ViesClient {
+    async fn is_valid(&self, prefix: &str, number: &str) -> RevenueResult<bool> {
+        let response: ViesResponse = self.client
+            .post(&self.api_url)
+            .json(&ViesRequest { country_code: prefix, vat_number: number })
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RevenueError::External(format!("VIES request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| RevenueError::External(format!("Malformed VIES response: {}", e)))?;
+
+        match response.user_error.as_deref() {
+            None | Some("VALID") | Some("INVALID") => Ok(response.valid),
+            Some(error) => Err(RevenueError::External(format!("VIES could not check {}{}: {}", prefix, number, error))),
+        }
+    }
+}
+
+// Picks the tax treatment for an invoice. `vat_rate` is the rate of the billing country when
+// it charges EU VAT, and `vat_id_confirmed` whether the customer's VAT ID passed VIES.
+pub fn tax_line(
+    address: Option<&BillingAddress>,
+    vat_rate: Option<Decimal>,
+    vat_id_confirmed: bool,
+    amount: Decimal,
+) -> TaxLineItem {
+    let Some(address) = address else {
+        return TaxLineItem::sales_tax(None, amount);
+    };
+    let Some(rate) = vat_rate else {
+        return TaxLineItem::sales_tax(Some(address.country_code.clone()), amount);
+    };
+
+    match address.vat_id.as_ref().filter(|_| vat_id_confirmed) {
+        Some(vat_id) => TaxLineItem::reverse_charge(address.country_code.clone(), vat_id.clone(), amount),
+        None => TaxLineItem::vat(address.country_code.clone(), rate, amount),
+    }
+}
+
+// First instant of the quarter and of the quarter after it
+pub fn quarter_bounds(year: i32, quarter: u32) -> RevenueResult<(DateTime<Utc>, DateTime<Utc>)> {
+    if !(1..=4).contains(&quarter) {
+        return Err(RevenueError::Validation(format!("Invalid quarter: {}", quarter)));
+    }
+    let start_month = (quarter - 1) * 3 + 1;
+    let (end_year, end_month) = if quarter == 4 { (year + 1, 1) } else { (year, start_month + 3) };
+
+    let start = Utc.with_ymd_and_hms(year, start_month, 1, 0, 0, 0).single();
+    let end = Utc.with_ymd_and_hms(end_year, end_month, 1, 0, 0, 0).single();
+    start.zip(end).ok_or_else(|| RevenueError::Validation(format!("Invalid year: {}", year)))
+}
+
+// VAT charged in one country at one rate, or reverse charged to its businesses
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VatReportLine {
+    pub country_code: String,
+    pub rate: Decimal,
+    pub reverse_charge: bool,
+    pub invoice_count: i64,
+    pub taxable_amount: Decimal,
+    pub vat_amount: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VatReport {
+    pub year: i32,
+    pub quarter: u32,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub lines: Vec<VatReportLine>,
+    pub total_taxable_amount: Decimal,
+    pub total_vat_amount: Decimal,
+}
+
+#[derive(Debug)]
+pub struct TaxCalculator {
+    db_pool: PgPool,
+    vat_ids: Arc<dyn VatIdValidator>,
+}
+
+impl TaxCalculator {
+    pub fn new(db_pool: PgPool, vat_ids: Arc<dyn VatIdValidator>) -> Self {
+        Self { db_pool, vat_ids }
+    }
+
+    pub async fn billing_address(&self, user_id: Uuid) -> RevenueResult<Option<BillingAddress>> {
+        let address = sqlx::query_as!(
+            BillingAddress,
+            r#"
+            SELECT user_id, name, line1, line2, city, postal_code, country_code, vat_id
+            FROM billing_addresses
+            WHERE user_id = $1
+            "#,
+            user_id
+        ).fetch_optional(&self.db_pool).await?;
+
+        Ok(address)
+    }
+
+    pub async fn set_billing_address(&self, address: BillingAddress) -> RevenueResult<BillingAddress> {
+        let country_code = address.country_code.trim().to_ascii_uppercase();
+        if country_code.len() != 2 || !country_code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(RevenueError::Validation(format!("Invalid country code: {}", address.country_code)));
+        }
+        let vat_id = match address.vat_id.as_deref().map(str::trim).filter(|id| !id.is_empty()) {
+            Some(vat_id) => {
+                let (prefix, number) = parse_vat_id(vat_id)
+                    .ok_or_else(|| RevenueError::Validation(format!("Invalid VAT ID: {}", vat_id)))?;
+                Some(format!("{}{}", prefix, number))
+            }
+            None => None,
+        };
+
+        let address = sqlx::query_as!(
+            BillingAddress,
+            r#"
+            INSERT INTO billing_addresses (user_id, name, line1, line2, city, postal_code, country_code, vat_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (user_id) DO UPDATE
+            SET name = EXCLUDED.name, line1 = EXCLUDED.line1, line2 = EXCLUDED.line2, city = EXCLUDED.city,
+                postal_code = EXCLUDED.postal_code, country_code = EXCLUDED.country_code,
+                vat_id = EXCLUDED.vat_id, updated_at = NOW()
+            RETURNING user_id, name, line1, line2, city, postal_code, country_code, vat_id
+            "#,
+            address.user_id,
+            address.name,
+            address.line1,
+            address.line2,
+            address.city,
+            address.postal_code,
+            country_code,
+            vat_id
+        ).fetch_one(&self.db_pool).await?;
+
+        Ok(address)
+    }
+
+    // None when the country does not charge EU VAT
+    pub async fn vat_rate(&self, country_code: &str) -> RevenueResult<Option<Decimal>> {
+        let rate = sqlx::query_scalar!(
+            "SELECT standard_rate FROM vat_rates WHERE country_code = $1",
+            country_code
+        ).fetch_optional(&self.db_pool).await?;
+
+        Ok(rate)
+    }
+
+    pub async fn calculate(&self, user_id: Uuid, amount: Decimal) -> RevenueResult<TaxLineItem> {
+        let address = self.billing_address(user_id).await?;
+        let vat_rate = match &address {
+            Some(address) => self.vat_rate(&address.country_code).await?,
+            None => None,
+        };
+
+        let vat_id_confirmed = match (&address, vat_rate) {
+            (Some(address), Some(_)) => self.confirm_vat_id(address).await,
+            _ => false,
+        };
+
+        Ok(tax_line(address.as_ref(), vat_rate, vat_id_confirmed, amount))
+    }
+
+    // The VAT ID must belong to the billing country. VIES outages bill VAT rather than risk
+    // reverse charging an unregistered customer.
+    async fn confirm_vat_id(&self, address: &BillingAddress) -> bool {
+        let Some((prefix, number)) = address.vat_id.as_deref().and_then(parse_vat_id) else {
+            return false;
+        };
+        if prefix != vat_prefix(&address.country_code) {
+            tracing::warn!("⚠️ VAT ID {}{} does not match billing country {}", prefix, number, address.country_code);
+            return false;
+        }
+
+        match self.vat_ids.is_valid(&prefix, &number).await {
+            Ok(valid) => valid,
+            Err(e) => {
+                tracing::warn!("⚠️ Could not validate VAT ID {}{}, charging VAT: {}", prefix, number, e);
+                false
+            }
+        }
+    }
+
+    // VAT per country and rate on invoices issued in the quarter
+    pub async fn quarterly_vat_report(&self, year: i32, quarter: u32) -> RevenueResult<VatReport> {
+        let (period_start, period_end) = quarter_bounds(year, quarter)?;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT vat_country as "country_code!", vat_rate as "rate!", vat_reverse_charge as "reverse_charge!",
+                   COUNT(*) as "invoice_count!", SUM(amount) as "taxable_amount!", SUM(vat_amount) as "vat_amount!"
+            FROM invoices
+            WHERE vat_country IS NOT NULL
+              AND created_at >= $1 AND created_at < $2
+              AND status NOT IN ('draft', 'cancelled', 'refunded')
+            GROUP BY vat_country, vat_rate, vat_reverse_charge
+            ORDER BY vat_country, vat_reverse_charge, vat_rate
+            "#,
+            period_start.naive_utc(),
+            period_end.naive_utc()
+        ).fetch_all(&self.db_pool).await?;
+
+        let lines: Vec<VatReportLine> = rows
+            .into_iter()
+            .map(|row| VatReportLine {
+                country_code: row.country_code,
+                rate: row.rate,
+                reverse_charge: row.reverse_charge,
+                invoice_count: row.invoice_count,
+                taxable_amount: row.taxable_amount,
+                vat_amount: row.vat_amount,
+            })
+            .collect();
+
+        tracing::info!("🧾 VAT report for {} Q{}: {} lines", year, quarter, lines.len());
+        Ok(VatReport {
+            year,
+            quarter,
+            period_start,
+            period_end,
+            total_taxable_amount: lines.iter().map(|line| line.taxable_amount).sum(),
+            total_vat_amount: lines.iter().map(|line| line.vat_amount).sum(),
+            lines,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn address(country_code: &str, vat_id: Option<&str>) -> BillingAddress {
+        BillingAddress {
+            user_id: Uuid::new_v4(),
+            name: Some("Example GmbH".to_string()),
+            line1: "Hauptstrasse 1".to_string(),
+            line2: None,
+            city: "Berlin".to_string(),
+            postal_code: Some("10115".to_string()),
+            country_code: country_code.to_string(),
+            vat_id: vat_id.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_eu_consumer_pays_country_rate() {
+        let line = tax_line(Some(&address("DE", None)), Some(Decimal::new(19, 2)), false, Decimal::new(9999, 2));
+
+        assert_eq!(line.treatment, TaxTreatment::Vat);
+        assert_eq!(line.country_code.as_deref(), Some("DE"));
+        assert_eq!(line.tax_amount, Decimal::new(1900, 2));
+        assert_eq!(line.label(), "VAT 19%");
+    }
+
+    #[test]
+    fn test_confirmed_business_is_reverse_charged() {
+        let business = address("DE", Some("DE123456789"));
+        let line = tax_line(Some(&business), Some(Decimal::new(19, 2)), true, Decimal::new(9999, 2));
+
+        assert_eq!(line.treatment, TaxTreatment::ReverseCharge);
+        assert_eq!(line.tax_amount, Decimal::ZERO);
+        assert_eq!(line.customer_vat_id.as_deref(), Some("DE123456789"));
+
+        // An unconfirmed VAT ID is billed like a consumer
+        let line = tax_line(Some(&business), Some(Decimal::new(19, 2)), false, Decimal::new(9999, 2));
+        assert_eq!(line.treatment, TaxTreatment::Vat);
+    }
+
+    #[test]
+    fn test_outside_eu_pays_sales_tax() {
+        let line = tax_line(Some(&address("US", None)), None, false, Decimal::new(29900, 2));
+        assert_eq!(line.treatment, TaxTreatment::SalesTax);
+        assert_eq!(line.country_code.as_deref(), Some("US"));
+        assert_eq!(line.tax_amount, Decimal::new(2616, 2));
+        assert_eq!(line.label(), "Tax 8.75%");
+
+        assert_eq!(tax_line(None, None, false, Decimal::ONE_HUNDRED).country_code, None);
+    }
+
+    #[test]
+    fn test_parse_vat_id() {
+        assert_eq!(parse_vat_id("de 123.456.789"), Some(("DE".to_string(), "123456789".to_string())));
+        assert_eq!(parse_vat_id("NL-8596.57.302.B01"), Some(("NL".to_string(), "859657302B01".to_string())));
+        assert_eq!(parse_vat_id("123456789"), None);
+        assert_eq!(parse_vat_id("DE"), None);
+        assert_eq!(vat_prefix("GR"), "EL");
+        assert_eq!(vat_prefix("FR"), "FR");
+    }
+
+    #[test]
+    fn test_quarter_bounds() {
+        let (start, end) = quarter_bounds(2024, 4).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 10, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+
+        let (start, end) = quarter_bounds(2024, 1).unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(end, Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap());
+
+        assert!(matches!(quarter_bounds(2024, 5), Err(RevenueError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_vies_client() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_json(serde_json::json!({ "countryCode": "DE", "vatNumber": "123456789" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "countryCode": "DE", "vatNumber": "123456789", "valid": true, "userError": "VALID"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_json(serde_json::json!({ "countryCode": "FR", "vatNumber": "00000000000" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "countryCode": "FR", "vatNumber": "00000000000", "valid": false, "userError": "MS_UNAVAILABLE"
+            })))
+            .mount(&server)
+            .await;
+        let client = ViesClient::new(server.uri());
+
+        assert!(client.is_valid("DE", "123456789").await.unwrap());
+        assert!(matches!(client.is_valid("FR", "00000000000").await, Err(RevenueError::External(_))));
+    }
+}
@@ -0,0 +1,430 @@
+// Webhook Dispatcher - Signed revenue event callbacks for operators
+// Delivers billing events with exponential backoff and parks exhausted deliveries in a dead-letter table
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::core::{RevenueError, RevenueResult};
+
+// Header carrying `t=<unix timestamp>,v1=<hex HMAC-SHA256>` over "<timestamp>.<body>"
+pub const SIGNATURE_HEADER: &str = "X-Revenue-Signature";
+pub const EVENT_TYPE_HEADER: &str = "X-Revenue-Event";
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Receiver of webhook callbacks; the secret is only ever used for signing
+#[derive(Clone, PartialEq, Eq)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    pub secret: String,
+}
+
+impl fmt::Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookEndpoint")
+            .field("url", &self.url)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+// Delay before retry n is base_delay * 2^n
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.base_delay.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+// Revenue events operators can subscribe to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum WebhookEvent {
+    #[serde(rename = "payment.succeeded")]
+    PaymentSucceeded {
+        invoice_id: Uuid,
+        amount: Decimal,
+    },
+    #[serde(rename = "subscription.created")]
+    SubscriptionCreated {
+        subscription_id: Uuid,
+        user_id: Uuid,
+        tier: String,
+        billing_cycle: String,
+        amount: Decimal,
+        currency: String,
+    },
+    #[serde(rename = "invoice.generated")]
+    InvoiceGenerated {
+        invoice_id: Uuid,
+        invoice_number: String,
+        user_id: Uuid,
+        total_amount: Decimal,
+        currency: String,
+        due_date: DateTime<Utc>,
+    },
+    #[serde(rename = "invoice.pdf_ready")]
+    InvoicePdfReady {
+        invoice_id: Uuid,
+        invoice_number: String,
+        user_id: Uuid,
+        download_url: String,
+        expires_at: DateTime<Utc>,
+    },
+}
+
+impl WebhookEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            WebhookEvent::PaymentSucceeded { .. } => "payment.succeeded",
+            WebhookEvent::SubscriptionCreated { .. } => "subscription.created",
+            WebhookEvent::InvoiceGenerated { .. } => "invoice.generated",
+            WebhookEvent::InvoicePdfReady { .. } => "invoice.pdf_ready",
+        }
+    }
+}
+
+// JSON body POSTed to every endpoint; the id lets receivers deduplicate retries and replays
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+// Delivery that exhausted its retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: Uuid,
+    pub endpoint_url: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub last_error: String,
+    pub created_at: DateTime<Utc>,
+    pub replayed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    db_pool: PgPool,
+    client: reqwest::Client,
+    endpoints: Arc<Vec<WebhookEndpoint>>,
+    retry_policy: RetryPolicy,
+}
+
+impl WebhookDispatcher {
+    pub fn new(db_pool: PgPool, endpoints: Vec<WebhookEndpoint>) -> Self {
+        Self::with_retry_policy(db_pool, endpoints, RetryPolicy::default())
+    }
+
+    pub fn with_retry_policy(
+        db_pool: PgPool,
+        endpoints: Vec<WebhookEndpoint>,
+        retry_policy: RetryPolicy
+    ) -> Self {
+        Self {
+            db_pool,
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .expect("Failed to build webhook HTTP client"),
+            endpoints: Arc::new(endpoints),
+            retry_policy,
+        }
+    }
+
+    // Fire-and-forget delivery so billing is never held up by a slow receiver
+    pub fn dispatch(&self, event: WebhookEvent) {
+        if self.endpoints.is_empty() {
+            return;
+        }
+
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            dispatcher.send(event).await;
+        });
+    }
+
+    // Delivers to every endpoint, dead-lettering the ones that still fail after all retries
+    pub async fn send(&self, event: WebhookEvent) {
+        let payload = WebhookPayload {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            event,
+        };
+        let body = match serde_json::to_string(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                tracing::error!("❌ Failed to serialize {} webhook: {}", payload.event.event_type(), e);
+                return;
+            }
+        };
+
+        for endpoint in self.endpoints.iter() {
+            if let Err(last_error) = self.deliver_with_retries(endpoint, payload.event.event_type(), &body).await {
+                tracing::error!(
+                    "❌ Webhook {} to {} failed after {} attempts: {}",
+                    payload.id, endpoint.url, self.retry_policy.max_retries + 1, last_error
+                );
+                if let Err(e) = self.record_dead_letter(endpoint, &payload, &last_error).await {
+                    tracing::error!("❌ Failed to dead-letter webhook {}: {}", payload.id, e);
+                }
+            }
+        }
+    }
+
+    // Redelivers a dead-lettered payload once, unchanged apart from a fresh signature
+    pub async fn replay(&self, dead_letter_id: Uuid) -> RevenueResult<DeadLetter> {
+        let dead_letter = self.get_dead_letter(dead_letter_id).await?;
+        if dead_letter.replayed_at.is_some() {
+            return Err(RevenueError::Validation(format!(
+                "Webhook {} was already replayed", dead_letter_id
+            )));
+        }
+
+        let endpoint = self.endpoints
+            .iter()
+            .find(|endpoint| endpoint.url == dead_letter.endpoint_url)
+            .ok_or_else(|| RevenueError::Config(format!(
+                "Webhook endpoint {} is no longer configured", dead_letter.endpoint_url
+            )))?;
+
+        let body = dead_letter.payload.to_string();
+        match self.deliver(endpoint, &dead_letter.event_type, &body).await {
+            Ok(()) => {
+                sqlx::query!(
+                    "UPDATE webhook_dead_letter SET replayed_at = NOW(), attempts = attempts + 1 WHERE id = $1",
+                    dead_letter_id
+                ).execute(&self.db_pool).await?;
+
+                tracing::info!("✅ Replayed webhook {} to {}", dead_letter_id, endpoint.url);
+                self.get_dead_letter(dead_letter_id).await
+            }
+            Err(e) => {
+                sqlx::query!(
+                    "UPDATE webhook_dead_letter SET attempts = attempts + 1, last_error = $2 WHERE id = $1",
+                    dead_letter_id,
+                    e
+                ).execute(&self.db_pool).await?;
+
+                Err(RevenueError::External(format!("Webhook replay failed: {}", e)))
+            }
+        }
+    }
+
+    pub async fn get_dead_letter(&self, dead_letter_id: Uuid) -> RevenueResult<DeadLetter> {
+        sqlx::query_as!(
+            DeadLetter,
+            r#"
+            SELECT id, endpoint_url, event_type, payload, attempts, last_error, created_at, replayed_at
+            FROM webhook_dead_letter
+            WHERE id = $1
+            "#,
+            dead_letter_id
+        )
+            .fetch_optional(&self.db_pool)
+            .await?
+            .ok_or_else(|| RevenueError::Billing(format!("Webhook dead letter not found: {}", dead_letter_id)))
+    }
+
+    async fn deliver_with_retries(
+        &self,
+        endpoint: &WebhookEndpoint,
+        event_type: &str,
+        body: &str
+    ) -> Result<(), String> {
+        let mut retry = 0;
+        loop {
+            match self.deliver(endpoint, event_type, body).await {
+                Ok(()) => return Ok(()),
+                Err(e) if retry >= self.retry_policy.max_retries => return Err(e),
+                Err(e) => {
+                    let delay = self.retry_policy.backoff(retry);
+                    tracing::warn!(
+                        "⚠️ Webhook delivery to {} failed ({}), retrying in {:?}",
+                        endpoint.url, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    retry += 1;
+                }
+            }
+        }
+    }
+
+    async fn deliver(&self, endpoint: &WebhookEndpoint, event_type: &str, body: &str) -> Result<(), String> {
+        let signature = sign_payload(body, &endpoint.secret, Utc::now().timestamp());
+
+        let response = self.client
+            .post(&endpoint.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(EVENT_TYPE_HEADER, event_type)
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("endpoint responded with {}", response.status()))
+        }
+    }
+
+    async fn record_dead_letter(
+        &self,
+        endpoint: &WebhookEndpoint,
+        payload: &WebhookPayload,
+        last_error: &str
+    ) -> RevenueResult<Uuid> {
+        let dead_letter_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_dead_letter
+            (id, endpoint_url, event_type, payload, attempts, last_error)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            dead_letter_id,
+            endpoint.url,
+            payload.event.event_type(),
+            serde_json::to_value(payload).map_err(|e| RevenueError::Billing(e.to_string()))?,
+            (self.retry_policy.max_retries + 1) as i32,
+            last_error
+        ).execute(&self.db_pool).await?;
+
+        Ok(dead_letter_id)
+    }
+}
+
+// Same scheme as Stripe's signatures so receivers can reuse their verification code
+pub fn sign_payload(payload: &str, secret: &str, timestamp: i64) -> String {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = ring::hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("t={},v1={}", timestamp, hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stripe_export::verify_stripe_signature;
+    use wiremock::matchers::{header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn payment_succeeded() -> WebhookEvent {
+        WebhookEvent::PaymentSucceeded {
+            invoice_id: Uuid::parse_str("6f1c1b8e-2f0a-4b8a-9d62-3f1c2b4d5e6f").unwrap(),
+            amount: Decimal::new(32516, 2),
+        }
+    }
+
+    fn dispatcher(url: String, max_retries: u32) -> WebhookDispatcher {
+        // Never connects; these tests only exercise the successful delivery path
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        WebhookDispatcher::with_retry_policy(
+            pool,
+            vec![WebhookEndpoint { url, secret: "whsec_operator".to_string() }],
+            RetryPolicy { max_retries, base_delay: Duration::from_millis(1) },
+        )
+    }
+
+    #[test]
+    fn test_backoff_doubles() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<u64> = (0..policy.max_retries).map(|n| policy.backoff(n).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16]);
+    }
+
+    #[test]
+    fn test_payload_shape() {
+        let payload = WebhookPayload {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            event: payment_succeeded(),
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+
+        assert_eq!(json["type"], "payment.succeeded");
+        assert_eq!(json["data"]["invoice_id"], "6f1c1b8e-2f0a-4b8a-9d62-3f1c2b4d5e6f");
+        assert_eq!(json["data"]["amount"], "325.16");
+        assert_eq!(serde_json::from_value::<WebhookPayload>(json).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_invoice_pdf_ready_payload() {
+        let event = WebhookEvent::InvoicePdfReady {
+            invoice_id: Uuid::new_v4(),
+            invoice_number: "INV-20240601-000042".to_string(),
+            user_id: Uuid::new_v4(),
+            download_url: "https://invoices.s3.amazonaws.com/invoices/INV-20240601-000042.pdf".to_string(),
+            expires_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(event.event_type(), "invoice.pdf_ready");
+        assert_eq!(json["type"], "invoice.pdf_ready");
+        assert_eq!(json["data"]["invoice_number"], "INV-20240601-000042");
+    }
+
+    #[test]
+    fn test_signature_verifies_with_stripe_scheme() {
+        let body = r#"{"type":"invoice.generated"}"#;
+        let now = 1_700_000_000;
+        let signature = sign_payload(body, "whsec_operator", now);
+
+        assert!(verify_stripe_signature(body, &signature, "whsec_operator", now).is_ok());
+        assert!(verify_stripe_signature(body, &signature, "whsec_other", now).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries_until_success() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hooks"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/hooks"))
+            .and(header(EVENT_TYPE_HEADER, "payment.succeeded"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dispatcher = dispatcher(format!("{}/hooks", server.uri()), 5);
+        dispatcher.send(payment_succeeded()).await;
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+
+        let delivered = requests.last().unwrap();
+        let body = String::from_utf8(delivered.body.clone()).unwrap();
+        let signature = delivered.headers.get(SIGNATURE_HEADER).unwrap().to_str().unwrap();
+        assert!(verify_stripe_signature(&body, signature, "whsec_operator", Utc::now().timestamp()).is_ok());
+    }
+}
@@ -12,6 +12,8 @@ use serde::{Serialize, Deserialize};
 
 use crate::core::{RevenueError, RevenueResult};
 
+pub mod pool_revenue;
+
 // Bridge transaction types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BridgeTransactionType {
@@ -0,0 +1,382 @@
+// Mining Pool Revenue - Fee revenue collected from the mining pool's payout database
+// Turns each miner payout into a revenue record and estimates fees while the pool database is down
+
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::core::{RevenueError, RevenueRecord, RevenueResult};
+use crate::{RevenueMetrics, RevenueStream};
+
+// How long a pool query may wait for a connection before the collector falls back to an estimate
+const POOL_ACQUIRE_TIMEOUT_SECS: u64 = 5;
+
+// Block found by the pool. `fee_rate` is the fraction of `reward` the pool kept (POOL_FEE)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolBlock {
+    pub height: i64,
+    pub reward: Decimal,
+    pub fee_rate: Decimal,
+    pub found_at: DateTime<Utc>,
+}
+
+// Payout to a miner, net of the pool fee of the block it was paid from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolPayout {
+    pub id: Uuid,
+    pub miner_id: Uuid,
+    pub block_height: i64,
+    pub amount: Decimal,
+    pub fee_rate: Decimal,
+    pub paid_at: DateTime<Utc>,
+}
+
+impl PoolPayout {
+    // Miner's share of the block reward before the pool took its fee
+    pub fn gross_amount(&self) -> Decimal {
+        if self.fee_rate >= Decimal::ONE {
+            return self.amount;
+        }
+        self.amount / (Decimal::ONE - self.fee_rate)
+    }
+
+    pub fn revenue_stream(&self) -> RevenueResult<RevenueStream> {
+        let fee_percentage = (self.fee_rate * Decimal::new(100, 0)).to_f64()
+            .ok_or_else(|| RevenueError::Validation(format!("Invalid pool fee rate: {}", self.fee_rate)))?;
+        Ok(RevenueStream::MiningPool {
+            amount: self.gross_amount(),
+            fee_percentage,
+            user_id: self.miner_id,
+        })
+    }
+
+    pub fn revenue_record(&self) -> RevenueResult<RevenueRecord> {
+        let mut record = RevenueRecord::from_stream(&self.revenue_stream()?)?;
+        record.timestamp = self.paid_at;
+        record.metadata["pool_payout_id"] = serde_json::json!(self.id);
+        record.metadata["block_height"] = serde_json::json!(self.block_height);
+        Ok(record)
+    }
+}
+
+// sum(block reward * fee rate)
+pub fn fee_revenue(blocks: &[PoolBlock]) -> Decimal {
+    blocks.iter().map(|block| block.reward * block.fee_rate).sum()
+}
+
+// Fee rate and earning power from the last period the pool database could be read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub fee_rate: Decimal,
+    // Average pool hashrate over the period, in hashes per second
+    pub hashrate: f64,
+    // Gross block reward earned per hash the pool computed
+    pub reward_per_hash: f64,
+    pub observed_at: DateTime<Utc>,
+}
+
+impl PoolSnapshot {
+    // None while the period has no blocks or no hashrate samples to learn from
+    pub fn from_period(blocks: &[PoolBlock], hashrate: f64, period_secs: i64, observed_at: DateTime<Utc>) -> Option<Self> {
+        let latest = blocks.iter().max_by_key(|block| block.found_at)?;
+        if hashrate <= 0.0 || period_secs <= 0 {
+            return None;
+        }
+
+        let rewards: Decimal = blocks.iter().map(|block| block.reward).sum();
+        Some(Self {
+            fee_rate: latest.fee_rate,
+            hashrate,
+            reward_per_hash: rewards.to_f64()? / (hashrate * period_secs as f64),
+            observed_at,
+        })
+    }
+
+    // Fee revenue the pool would have earned over `elapsed_secs` at the last known fee rate
+    // and hashrate
+    pub fn estimate_fee_revenue(&self, elapsed_secs: i64) -> Decimal {
+        let gross = self.hashrate * self.reward_per_hash * elapsed_secs.max(0) as f64;
+        Decimal::from_f64_retain(gross).unwrap_or(Decimal::ZERO).round_dp(8) * self.fee_rate
+    }
+}
+
+// First instant of the calendar month `now` falls in
+pub fn billing_period_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .expect("first of the month at midnight is unambiguous in UTC")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolRevenueSummary {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    // Pool fees for the billing period, or the estimate accrued since the pool database
+    // was last readable when `estimated` is set
+    pub fee_revenue: Decimal,
+    pub records_created: usize,
+    pub estimated: bool,
+}
+
+#[derive(Debug)]
+struct CollectorState {
+    last_known: Option<PoolSnapshot>,
+    last_collected_at: DateTime<Utc>,
+    // Estimated revenue added to the metrics while the pool database was unreachable,
+    // backed out once real payouts are recorded
+    outstanding_estimate: Decimal,
+}
+
+// Reads blocks and payouts from the mining pool's database
+#[derive(Debug)]
+pub struct MiningPoolRevenueCollector {
+    pool_db: PgPool,
+    revenue_db: PgPool,
+    metrics: Arc<RwLock<RevenueMetrics>>,
+    state: RwLock<CollectorState>,
+}
+
+impl MiningPoolRevenueCollector {
+    pub fn new(pool_database_url: &str, revenue_db: PgPool, metrics: Arc<RwLock<RevenueMetrics>>) -> RevenueResult<Self> {
+        // Connect lazily so an unreachable pool database does not stop the engine starting
+        let pool_db = PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(std::time::Duration::from_secs(POOL_ACQUIRE_TIMEOUT_SECS))
+            .connect_lazy(pool_database_url)
+            .map_err(|e| RevenueError::Config(format!("Invalid POOL_DATABASE_URL: {}", e)))?;
+
+        let now = Utc::now();
+        Ok(Self {
+            pool_db,
+            revenue_db,
+            metrics,
+            state: RwLock::new(CollectorState {
+                last_known: None,
+                last_collected_at: billing_period_start(now),
+                outstanding_estimate: Decimal::ZERO,
+            }),
+        })
+    }
+
+    // Records every payout not yet in revenue_records and returns the billing period's fee
+    // revenue. Falls back to an estimate when the pool database cannot be read.
+    pub async fn collect(&self, now: DateTime<Utc>) -> RevenueResult<PoolRevenueSummary> {
+        let period_start = billing_period_start(now);
+        let since = self.state.read().await.last_collected_at.min(period_start);
+
+        let (blocks, payouts, hashrate) = match self.read_pool(period_start, since, now).await {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::warn!("⚠️ Mining pool database unavailable, estimating fee revenue: {}", e);
+                return self.estimate(period_start, now).await;
+            }
+        };
+
+        let recorded = self.recorded_payout_ids(since).await?;
+        let mut records_created = 0;
+        let mut recorded_fees = Decimal::ZERO;
+        for payout in payouts.iter().filter(|payout| !recorded.contains(&payout.id.to_string())) {
+            let record = payout.revenue_record()?;
+            record.insert(&self.revenue_db).await?;
+            recorded_fees += record.fee;
+            records_created += 1;
+        }
+
+        let mut state = self.state.write().await;
+        {
+            let mut metrics = self.metrics.write().await;
+            if !state.outstanding_estimate.is_zero() {
+                metrics.record_revenue("mining_pool", -state.outstanding_estimate);
+            }
+            metrics.record_revenue("mining_pool", recorded_fees);
+        }
+        state.outstanding_estimate = Decimal::ZERO;
+        state.last_collected_at = now;
+        if let Some(snapshot) = hashrate.and_then(|h| PoolSnapshot::from_period(&blocks, h, (now - period_start).num_seconds(), now)) {
+            state.last_known = Some(snapshot);
+        }
+
+        let fee_revenue = fee_revenue(&blocks);
+        tracing::info!("⛏️ Mining pool fee revenue ${} this period, {} new payouts recorded", fee_revenue, records_created);
+
+        Ok(PoolRevenueSummary {
+            period_start,
+            period_end: now,
+            fee_revenue,
+            records_created,
+            estimated: false,
+        })
+    }
+
+    async fn estimate(&self, period_start: DateTime<Utc>, now: DateTime<Utc>) -> RevenueResult<PoolRevenueSummary> {
+        let mut state = self.state.write().await;
+        let snapshot = state.last_known.clone().ok_or_else(|| RevenueError::External(
+            "Mining pool database unavailable and no fee rate observed yet".to_string()
+        ))?;
+
+        let estimate = snapshot.estimate_fee_revenue((now - state.last_collected_at).num_seconds());
+        self.metrics.write().await.record_revenue("mining_pool", estimate);
+        state.outstanding_estimate += estimate;
+        state.last_collected_at = now;
+
+        Ok(PoolRevenueSummary {
+            period_start,
+            period_end: now,
+            fee_revenue: state.outstanding_estimate,
+            records_created: 0,
+            estimated: true,
+        })
+    }
+
+    async fn read_pool(
+        &self,
+        period_start: DateTime<Utc>,
+        since: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> RevenueResult<(Vec<PoolBlock>, Vec<PoolPayout>, Option<f64>)> {
+        // The pool's schema is not known to this crate at compile time, so these queries are
+        // checked at runtime. Numerics come back as text to keep them exact.
+        let blocks = sqlx::query_as::<_, (i64, String, String, DateTime<Utc>)>(
+            r#"
+            SELECT height, reward::TEXT, fee_rate::TEXT, found_at
+            FROM blocks
+            WHERE found_at >= $1 AND found_at < $2
+            "#
+        )
+        .bind(period_start)
+        .bind(now)
+        .fetch_all(&self.pool_db)
+        .await?
+        .into_iter()
+        .map(|(height, reward, fee_rate, found_at)| Ok(PoolBlock {
+            height,
+            reward: parse_decimal(&reward)?,
+            fee_rate: parse_decimal(&fee_rate)?,
+            found_at,
+        }))
+        .collect::<RevenueResult<Vec<_>>>()?;
+
+        let payouts = sqlx::query_as::<_, (Uuid, Uuid, i64, String, String, DateTime<Utc>)>(
+            r#"
+            SELECT p.id, p.miner_id, p.block_height, p.amount::TEXT, b.fee_rate::TEXT, p.paid_at
+            FROM payouts p
+            JOIN blocks b ON b.height = p.block_height
+            WHERE p.paid_at >= $1 AND p.paid_at < $2
+            ORDER BY p.paid_at
+            "#
+        )
+        .bind(since)
+        .bind(now)
+        .fetch_all(&self.pool_db)
+        .await?
+        .into_iter()
+        .map(|(id, miner_id, block_height, amount, fee_rate, paid_at)| Ok(PoolPayout {
+            id,
+            miner_id,
+            block_height,
+            amount: parse_decimal(&amount)?,
+            fee_rate: parse_decimal(&fee_rate)?,
+            paid_at,
+        }))
+        .collect::<RevenueResult<Vec<_>>>()?;
+
+        let hashrate = sqlx::query_scalar::<_, Option<f64>>(
+            "SELECT AVG(hashrate) FROM pool_hashrate WHERE recorded_at >= $1 AND recorded_at < $2"
+        )
+        .bind(period_start)
+        .bind(now)
+        .fetch_one(&self.pool_db)
+        .await?;
+
+        Ok((blocks, payouts, hashrate))
+    }
+
+    async fn recorded_payout_ids(&self, since: DateTime<Utc>) -> RevenueResult<Vec<String>> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT metadata->>'pool_payout_id' AS "payout_id"
+            FROM revenue_records
+            WHERE stream_type = 'mining_pool' AND timestamp >= $1
+            "#,
+            since
+        ).fetch_all(&self.revenue_db).await?;
+
+        Ok(ids.into_iter().flatten().collect())
+    }
+}
+
+fn parse_decimal(value: &str) -> RevenueResult<Decimal> {
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .map_err(|e| RevenueError::Validation(format!("Invalid pool amount {}: {}", value, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: i64, reward: i64, fee_rate: Decimal, hour: u32) -> PoolBlock {
+        PoolBlock {
+            height,
+            reward: Decimal::new(reward, 0),
+            fee_rate,
+            found_at: Utc.with_ymd_and_hms(2024, 6, 3, hour, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_fee_revenue_sums_reward_times_rate() {
+        let blocks = vec![
+            block(100, 65536, Decimal::new(2, 2), 1),
+            block(101, 65536, Decimal::new(1, 2), 2),
+        ];
+        assert_eq!(fee_revenue(&blocks), Decimal::new(196608, 2));
+        assert_eq!(fee_revenue(&[]), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_payout_record_restores_gross_share() {
+        let payout = PoolPayout {
+            id: Uuid::new_v4(),
+            miner_id: Uuid::new_v4(),
+            block_height: 100,
+            amount: Decimal::new(98, 0),
+            fee_rate: Decimal::new(2, 2),
+            paid_at: Utc.with_ymd_and_hms(2024, 6, 3, 4, 0, 0).unwrap(),
+        };
+        let record = payout.revenue_record().unwrap();
+
+        assert_eq!(record.stream_type, "mining_pool");
+        assert_eq!(record.amount, Decimal::new(100, 0));
+        assert_eq!(record.fee.round_dp(8), Decimal::new(2, 0));
+        assert_eq!(record.timestamp, payout.paid_at);
+        assert_eq!(record.metadata["pool_payout_id"], serde_json::json!(payout.id));
+    }
+
+    #[test]
+    fn test_estimate_applies_last_fee_rate_to_hashrate() {
+        let blocks = vec![block(100, 65536, Decimal::new(1, 2), 1), block(101, 65536, Decimal::new(2, 2), 5)];
+        let observed_at = Utc.with_ymd_and_hms(2024, 6, 3, 6, 0, 0).unwrap();
+        let snapshot = PoolSnapshot::from_period(&blocks, 1_000_000.0, 3600 * 64, observed_at).unwrap();
+
+        assert_eq!(snapshot.fee_rate, Decimal::new(2, 2));
+        // Two blocks every 64 hours: 2048 NOCK of rewards per hour, 2% of it to the pool
+        assert_eq!(snapshot.estimate_fee_revenue(3600), Decimal::new(4096, 2));
+        assert_eq!(snapshot.estimate_fee_revenue(-5), Decimal::ZERO);
+        assert!(PoolSnapshot::from_period(&[], 1_000_000.0, 3600, observed_at).is_none());
+        assert!(PoolSnapshot::from_period(&blocks, 0.0, 3600, observed_at).is_none());
+    }
+
+    #[test]
+    fn test_billing_period_starts_on_the_first() {
+        let now = Utc.with_ymd_and_hms(2024, 2, 29, 13, 45, 0).unwrap();
+        assert_eq!(billing_period_start(now), Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+    }
+}
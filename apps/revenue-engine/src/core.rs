@@ -11,10 +11,22 @@ use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 use crate::subscription::{SubscriptionManager, SubscriptionService};
-use crate::billing::{BillingEngine, PaymentProcessor};
+use crate::subscription::churn::{
+    ChurnPredictor, EmailProvider, ReEngagementEmailer, SendGridEmailProvider, StubEmailProvider,
+};
+use crate::subscription::feature_flags::FeatureFlagService;
+use crate::billing::{BillingEngine, InvoiceManager, PaymentProcessor};
+use crate::billing::invoice_pdf::InvoiceStorage;
+use crate::billing::tax::{ViesClient, VIES_API_URL};
+use crate::billing::webhook::{WebhookDispatcher, WebhookEndpoint};
+use crate::billing::fx::{CachedFxRateProvider, load_fallback_rates};
 use crate::analytics::{RevenueAnalytics, RevenueForecasting};
+use crate::analytics::clv::ClvAnalysis;
 use crate::bridge::{BridgeRevenueManager, TransactionFeeProcessor};
+use crate::bridge::pool_revenue::{MiningPoolRevenueCollector, PoolRevenueSummary};
 use crate::enterprise::{EnterpriseRevenueManager, CustodyService};
+use crate::enterprise::otc::{OtcDeskConfig, OtcQuoteDesk};
+use crate::enterprise::sla::{SalesAlerter, SlackWebhookAlerter, SlaMonitor, StubSalesAlerter};
 use crate::{RevenueStream, RevenueTargets, RevenueMetrics};
 
 // Core revenue engine error types
@@ -23,6 +35,9 @@ pub enum RevenueError {
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
     
+    #[error("Migration error: {0}")]
+    Migration(#[from] sqlx::migrate::MigrateError),
+    
     #[error("Redis error: {0}")]
     Redis(#[from] redis::RedisError),
     
@@ -69,6 +84,18 @@ pub struct RevenueConfig {
     pub enterprise_features: EnterpriseConfig,
     pub analytics_config: AnalyticsConfig,
     pub security_config: SecurityConfig,
+    pub webhook_endpoints: Vec<WebhookEndpoint>,
+    pub sendgrid_api_key: Option<String>,
+    pub email_from_address: String,
+    pub fx_api_url: String,
+    pub fx_fallback_rates_path: String,
+    pub pool_database_url: Option<String>,
+    pub slack_sales_webhook_url: Option<String>,
+    pub otc_desk: OtcDeskConfig,
+    pub invoice_payment_base_url: String,
+    pub vies_api_url: String,
+    // Our own VAT registration, printed on invoices to EU customers
+    pub vat_registration_number: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -100,6 +127,15 @@ pub struct SecurityConfig {
 
 impl RevenueConfig {
     pub fn from_env() -> RevenueResult<Self> {
+        let webhook_endpoints = match std::env::var("REVENUE_WEBHOOK_URL") {
+            Ok(url) if !url.is_empty() => vec![WebhookEndpoint {
+                url,
+                secret: std::env::var("REVENUE_WEBHOOK_SECRET")
+                    .map_err(|_| RevenueError::Config("REVENUE_WEBHOOK_SECRET not set".to_string()))?,
+            }],
+            _ => Vec::new(),
+        };
+
         Ok(Self {
             database_url: std::env::var("DATABASE_URL")
                 .map_err(|_| RevenueError::Config("DATABASE_URL not set".to_string()))?,
@@ -135,6 +171,30 @@ impl RevenueConfig {
                 compliance_reporting: true,
                 hsm_integration: true,
             },
+            webhook_endpoints,
+            sendgrid_api_key: std::env::var("SENDGRID_API_KEY").ok().filter(|key| !key.is_empty()),
+            email_from_address: std::env::var("EMAIL_FROM_ADDRESS")
+                .unwrap_or_else(|_| "support@nockchain.com".to_string()),
+            fx_api_url: std::env::var("FX_API_URL")
+                .unwrap_or_else(|_| "https://api.exchangerate.host/latest?base=USD".to_string()),
+            fx_fallback_rates_path: std::env::var("FX_FALLBACK_RATES_PATH")
+                .unwrap_or_else(|_| "config/fx_fallback_rates.json".to_string()),
+            pool_database_url: std::env::var("POOL_DATABASE_URL").ok().filter(|url| !url.is_empty()),
+            slack_sales_webhook_url: std::env::var("SLACK_SALES_WEBHOOK_URL").ok().filter(|url| !url.is_empty()),
+            otc_desk: OtcDeskConfig {
+                quote_sla: std::time::Duration::from_secs(
+                    std::env::var("OTC_QUOTE_SLA_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60)
+                ),
+                quote_ttl: chrono::Duration::seconds(
+                    std::env::var("OTC_QUOTE_TTL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(30)
+                ),
+                ..OtcDeskConfig::default()
+            },
+            invoice_payment_base_url: std::env::var("INVOICE_PAYMENT_BASE_URL")
+                .unwrap_or_else(|_| "https://pay.nockchain.com/invoices".to_string()),
+            vies_api_url: std::env::var("VIES_API_URL")
+                .unwrap_or_else(|_| VIES_API_URL.to_string()),
+            vat_registration_number: std::env::var("VAT_REGISTRATION_NUMBER").ok().filter(|number| !number.is_empty()),
         })
     }
 }
@@ -147,13 +207,22 @@ pub struct RevenueEngine {
     pub redis: ConnectionManager,
     pub subscription_manager: Arc<SubscriptionManager>,
     pub billing_engine: Arc<BillingEngine>,
+    pub invoice_manager: Arc<InvoiceManager>,
     pub payment_processor: Arc<PaymentProcessor>,
+    pub fx_rates: Arc<CachedFxRateProvider>,
     pub revenue_analytics: Arc<RevenueAnalytics>,
     pub revenue_forecasting: Arc<RevenueForecasting>,
     pub bridge_revenue: Arc<BridgeRevenueManager>,
     pub enterprise_revenue: Arc<EnterpriseRevenueManager>,
+    pub sla_monitor: Arc<SlaMonitor>,
+    pub otc_desk: Arc<OtcQuoteDesk>,
+    pub custody: Arc<CustodyService>,
     pub current_metrics: Arc<RwLock<RevenueMetrics>>,
     pub optimization_engine: Arc<RevenueOptimizationEngine>,
+    pub churn_predictor: Arc<ChurnPredictor>,
+    pub re_engagement_emailer: Arc<ReEngagementEmailer>,
+    pub feature_flags: Arc<FeatureFlagService>,
+    pub pool_revenue: Option<Arc<MiningPoolRevenueCollector>>,
 }
 
 impl RevenueEngine {
@@ -167,23 +236,50 @@ impl RevenueEngine {
         let redis_client = redis::Client::open(config.redis_url.as_str())?;
         let redis = ConnectionManager::new(redis_client).await?;
 
+        // Revenue event callbacks shared by subscriptions and billing
+        let webhooks = Arc::new(
+            WebhookDispatcher::new(db_pool.clone(), config.webhook_endpoints.clone())
+        );
+
         // Initialize core components
         let subscription_manager = Arc::new(
-            SubscriptionManager::new(db_pool.clone(), redis.clone()).await?
+            SubscriptionManager::new(db_pool.clone(), redis.clone(), webhooks.clone()).await?
         );
         
         let payment_processor = Arc::new(
             PaymentProcessor::new(config.stripe_secret_key.clone()).await?
         );
         
+        // Exchange rates for non-USD payments
+        let fallback_rates = load_fallback_rates(&config.fx_fallback_rates_path).unwrap_or_else(|e| {
+            tracing::warn!("⚠️ {}, FX conversion will rely on live rates only", e);
+            Default::default()
+        });
+        let fx_rates = Arc::new(CachedFxRateProvider::new(config.fx_api_url.clone(), fallback_rates));
+
         let billing_engine = Arc::new(
             BillingEngine::new(
                 db_pool.clone(), 
                 redis.clone(), 
-                payment_processor.clone()
+                payment_processor.clone(),
+                fx_rates.clone(),
+                webhooks.clone(),
+                Arc::new(ViesClient::new(config.vies_api_url.clone()))
             ).await?
         );
 
+        // Invoice PDFs, stored in S3 when a bucket is configured
+        let invoice_storage = InvoiceStorage::from_env().await;
+        if invoice_storage.is_none() {
+            tracing::warn!("⚠️ INVOICE_PDF_S3_BUCKET not set, invoice PDFs can be rendered but not published");
+        }
+        let invoice_manager = Arc::new(InvoiceManager::new(
+            billing_engine.clone(),
+            invoice_storage,
+            config.invoice_payment_base_url.clone(),
+            config.vat_registration_number.clone(),
+        ));
+
         let revenue_analytics = Arc::new(
             RevenueAnalytics::new(db_pool.clone(), redis.clone()).await?
         );
@@ -201,13 +297,39 @@ impl RevenueEngine {
         );
 
         let enterprise_revenue = Arc::new(
-            EnterpriseRevenueManager::new(db_pool.clone(), redis.clone()).await?
+            EnterpriseRevenueManager::new(db_pool.clone(), redis.clone(), billing_engine.clone()).await?
         );
 
+        // Enterprise SLA enforcement and sales alerts
+        let sales_alerter: Arc<dyn SalesAlerter> = match &config.slack_sales_webhook_url {
+            Some(url) => Arc::new(SlackWebhookAlerter::new(url.clone())),
+            None => {
+                tracing::warn!("⚠️ SLACK_SALES_WEBHOOK_URL not set, at-risk contract alerts will only be logged");
+                Arc::new(StubSalesAlerter)
+            }
+        };
+        let sla_monitor = Arc::new(SlaMonitor::new(db_pool.clone(), billing_engine.clone(), sales_alerter));
+        let otc_desk = Arc::new(OtcQuoteDesk::new(enterprise_revenue.clone(), config.otc_desk.clone()));
+        let custody = Arc::new(CustodyService::new(enterprise_revenue.clone()));
+
         let optimization_engine = Arc::new(
             RevenueOptimizationEngine::new(db_pool.clone(), redis.clone()).await?
         );
 
+        // Churn detection and re-engagement emails
+        let email_provider: Arc<dyn EmailProvider> = match &config.sendgrid_api_key {
+            Some(api_key) => Arc::new(SendGridEmailProvider::new(api_key.clone(), config.email_from_address.clone())),
+            None => {
+                tracing::warn!("⚠️ SENDGRID_API_KEY not set, re-engagement emails will only be logged");
+                Arc::new(StubEmailProvider)
+            }
+        };
+        let churn_predictor = Arc::new(ChurnPredictor::new(db_pool.clone()));
+        let re_engagement_emailer = Arc::new(ReEngagementEmailer::new(db_pool.clone(), email_provider));
+
+        // Tier-gated feature access
+        let feature_flags = Arc::new(FeatureFlagService::new(db_pool.clone()));
+
         // Initialize metrics
         let initial_metrics = RevenueMetrics {
             total_monthly_revenue: Decimal::ZERO,
@@ -224,6 +346,17 @@ impl RevenueEngine {
 
         let current_metrics = Arc::new(RwLock::new(initial_metrics));
 
+        // Mining pool fee collection
+        let pool_revenue = match &config.pool_database_url {
+            Some(url) => Some(Arc::new(
+                MiningPoolRevenueCollector::new(url, db_pool.clone(), current_metrics.clone())?
+            )),
+            None => {
+                tracing::warn!("⚠️ POOL_DATABASE_URL not set, mining pool fees will not be collected");
+                None
+            }
+        };
+
         // Setup database schema
         Self::setup_database(&db_pool).await?;
 
@@ -233,13 +366,22 @@ impl RevenueEngine {
             redis,
             subscription_manager,
             billing_engine,
+            invoice_manager,
             payment_processor,
+            fx_rates,
             revenue_analytics,
             revenue_forecasting,
             bridge_revenue,
             enterprise_revenue,
+            sla_monitor,
+            otc_desk,
+            custody,
             current_metrics,
             optimization_engine,
+            churn_predictor,
+            re_engagement_emailer,
+            feature_flags,
+            pool_revenue,
         };
 
         // Start background tasks
@@ -255,6 +397,9 @@ impl RevenueEngine {
     async fn setup_database(pool: &PgPool) -> RevenueResult<()> {
         tracing::info!("📊 Setting up revenue database schema");
 
+        // Versioned schema (revenue_records, aggregate_revenue)
+        sqlx::migrate!().run(pool).await?;
+
         // Revenue streams table
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS revenue_streams (
@@ -380,6 +525,12 @@ impl RevenueEngine {
     async fn start_background_tasks(&self) -> RevenueResult<()> {
         tracing::info!("🔄 Starting revenue processing background tasks");
 
+        // FX rate refresh task
+        let fx_rates = self.fx_rates.clone();
+        tokio::spawn(async move {
+            fx_rates.start().await;
+        });
+
         let engine_clone = self.clone_for_background();
 
         // Revenue metrics collection task
@@ -429,6 +580,79 @@ impl RevenueEngine {
             }
         });
 
+        // Churn detection task
+        let engine_clone = self.clone_for_background();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // Daily
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine_clone.run_churn_detection().await {
+                    tracing::error!("❌ Churn detection error: {}", e);
+                }
+            }
+        });
+
+        // Customer lifetime value model refit task
+        let engine_clone = self.clone_for_background();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // Daily
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine_clone.refresh_clv_predictions().await {
+                    tracing::error!("❌ CLV prediction refresh error: {}", e);
+                }
+            }
+        });
+
+        // Paused subscription resume/expiry task
+        let engine_clone = self.clone_for_background();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // Daily
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine_clone.process_paused_subscriptions().await {
+                    tracing::error!("❌ Paused subscription processing error: {}", e);
+                }
+            }
+        });
+
+        // Enterprise SLA compliance task
+        let engine_clone = self.clone_for_background();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(86400)); // Daily
+            loop {
+                interval.tick().await;
+                if let Err(e) = engine_clone.check_sla_compliance().await {
+                    tracing::error!("❌ SLA compliance check error: {}", e);
+                }
+            }
+        });
+
+        // OTC quote expiry, settlement and inventory rebalancing task
+        let otc_desk = self.otc_desk.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+            loop {
+                interval.tick().await;
+                if let Err(e) = otc_desk.run_sweep(Utc::now()).await {
+                    tracing::error!("❌ OTC desk sweep error: {}", e);
+                }
+            }
+        });
+
+        // Mining pool fee collection task
+        if let Some(pool_revenue) = self.pool_revenue.clone() {
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(900)); // 15 minutes
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = pool_revenue.collect(Utc::now()).await {
+                        tracing::error!("❌ Mining pool revenue collection error: {}", e);
+                    }
+                }
+            });
+        }
+
         tracing::info!("✅ Background revenue processing tasks started");
         Ok(())
     }
@@ -440,9 +664,13 @@ impl RevenueEngine {
             redis: self.redis.clone(),
             revenue_analytics: self.revenue_analytics.clone(),
             revenue_forecasting: self.revenue_forecasting.clone(),
+            subscription_manager: self.subscription_manager.clone(),
             billing_engine: self.billing_engine.clone(),
             optimization_engine: self.optimization_engine.clone(),
             current_metrics: self.current_metrics.clone(),
+            churn_predictor: self.churn_predictor.clone(),
+            re_engagement_emailer: self.re_engagement_emailer.clone(),
+            sla_monitor: self.sla_monitor.clone(),
             config: self.config.clone(),
         }
     }
@@ -451,89 +679,32 @@ impl RevenueEngine {
     pub async fn process_revenue_stream(&self, stream: RevenueStream) -> RevenueResult<Uuid> {
         tracing::info!("💰 Processing revenue stream: {:?}", stream);
 
-        let revenue_id = Uuid::new_v4();
-        let (stream_type, amount, user_id, metadata) = match &stream {
-            RevenueStream::MiningPool { amount, fee_percentage, user_id } => {
-                ("mining_pool".to_string(), *amount, Some(*user_id), 
-                 serde_json::json!({"fee_percentage": fee_percentage}))
-            },
-            RevenueStream::PremiumAnalytics { subscription_tier, monthly_amount, user_id } => {
-                ("premium_analytics".to_string(), *monthly_amount, Some(*user_id),
-                 serde_json::json!({"subscription_tier": subscription_tier}))
-            },
-            RevenueStream::BridgeTransaction { from_token, to_token, amount, fee_amount, user_id } => {
-                ("bridge_transaction".to_string(), *fee_amount, Some(*user_id),
-                 serde_json::json!({"from_token": from_token, "to_token": to_token, "amount": amount}))
-            },
-            RevenueStream::TradingFees { trading_pair, volume, fee_amount, user_id } => {
-                ("trading_fees".to_string(), *fee_amount, Some(*user_id),
-                 serde_json::json!({"trading_pair": trading_pair, "volume": volume}))
-            },
-            RevenueStream::EnterpriseServices { service_type, contract_value, client_id } => {
-                ("enterprise_services".to_string(), *contract_value, Some(*client_id),
-                 serde_json::json!({"service_type": service_type}))
-            },
-            RevenueStream::APILicensing { tier, monthly_value, client_id } => {
-                ("api_licensing".to_string(), *monthly_value, Some(*client_id),
-                 serde_json::json!({"tier": tier}))
-            },
-            RevenueStream::PerformanceOptimization { service_type, project_value, client_id } => {
-                ("performance_optimization".to_string(), *project_value, Some(*client_id),
-                 serde_json::json!({"service_type": service_type}))
-            },
-            RevenueStream::CustomSolutions { project_type, total_value, client_id } => {
-                ("custom_solutions".to_string(), *total_value, Some(*client_id),
-                 serde_json::json!({"project_type": project_type}))
-            },
-        };
-
-        // Insert revenue record
-        sqlx::query!(
-            r#"
-            INSERT INTO revenue_streams 
-            (id, stream_type, amount, user_id, metadata, processed_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
-            "#,
-            revenue_id,
-            stream_type,
-            amount,
-            user_id,
-            metadata
-        ).execute(&self.db_pool).await?;
+        let record = RevenueRecord::from_stream(&stream)?;
+        let revenue_id = record.insert(&self.db_pool).await?;
 
         // Update real-time metrics
-        self.update_real_time_metrics(&stream_type, amount).await?;
+        self.update_real_time_metrics(&record.stream_type, record.fee).await?;
 
         // Trigger optimization if significant revenue
-        if amount > Decimal::new(1000, 0) { // $1000+
-            self.optimization_engine.trigger_optimization(&stream_type).await?;
+        if record.fee > Decimal::new(1000, 0) { // $1000+
+            self.optimization_engine.trigger_optimization(&record.stream_type).await?;
         }
 
-        tracing::info!("✅ Revenue stream processed: ${} from {}", amount, stream_type);
+        tracing::info!("✅ Revenue stream processed: ${} from {}", record.fee, record.stream_type);
         Ok(revenue_id)
     }
 
+    // Reads the mining pool's payouts for the current billing period and records its fees
+    pub async fn compute_mining_pool_fee_revenue(&self) -> RevenueResult<PoolRevenueSummary> {
+        let collector = self.pool_revenue.as_ref().ok_or_else(|| RevenueError::Config(
+            "POOL_DATABASE_URL not set".to_string()
+        ))?;
+        collector.collect(Utc::now()).await
+    }
+
     // Update real-time revenue metrics
     async fn update_real_time_metrics(&self, stream_type: &str, amount: Decimal) -> RevenueResult<()> {
-        let mut metrics = self.current_metrics.write().await;
-        
-        metrics.total_monthly_revenue += amount;
-        
-        match stream_type {
-            "premium_analytics" | "api_licensing" => {
-                metrics.subscription_revenue += amount;
-            },
-            "bridge_transaction" | "trading_fees" | "mining_pool" => {
-                metrics.transaction_revenue += amount;
-            },
-            "enterprise_services" | "custom_solutions" | "performance_optimization" => {
-                metrics.enterprise_revenue += amount;
-            },
-            _ => {}
-        }
-        
-        metrics.timestamp = Utc::now();
-        
+        self.current_metrics.write().await.record_revenue(stream_type, amount);
         Ok(())
     }
 
@@ -582,6 +753,107 @@ impl RevenueEngine {
     }
 }
 
+// A single row of revenue_records. `amount` is the gross value of the event and `fee`
+// the part the platform keeps, which is what counts towards revenue targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevenueRecord {
+    pub id: Uuid,
+    pub stream_type: String,
+    pub user_id: Option<Uuid>,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub timestamp: DateTime<Utc>,
+    pub metadata: serde_json::Value,
+}
+
+impl RevenueRecord {
+    pub fn from_stream(stream: &RevenueStream) -> RevenueResult<Self> {
+        let (stream_type, user_id, amount, fee, metadata) = match stream {
+            RevenueStream::MiningPool { amount, fee_percentage, user_id } => {
+                let percentage = Decimal::from_f64_retain(*fee_percentage)
+                    .filter(|p| *p >= Decimal::ZERO && *p <= Decimal::new(100, 0))
+                    .ok_or_else(|| RevenueError::Validation(
+                        format!("Invalid mining pool fee percentage: {}", fee_percentage)
+                    ))?;
+                ("mining_pool", *user_id, *amount, *amount * percentage / Decimal::new(100, 0),
+                 serde_json::json!({"fee_percentage": fee_percentage}))
+            },
+            RevenueStream::PremiumAnalytics { subscription_tier, monthly_amount, user_id } => {
+                ("premium_analytics", *user_id, *monthly_amount, *monthly_amount,
+                 serde_json::json!({"subscription_tier": subscription_tier}))
+            },
+            RevenueStream::BridgeTransaction { from_token, to_token, amount, fee_amount, user_id } => {
+                ("bridge_transaction", *user_id, *amount, *fee_amount,
+                 serde_json::json!({"from_token": from_token, "to_token": to_token}))
+            },
+            RevenueStream::TradingFees { trading_pair, volume, fee_amount, user_id } => {
+                ("trading_fees", *user_id, *volume, *fee_amount,
+                 serde_json::json!({"trading_pair": trading_pair}))
+            },
+            RevenueStream::EnterpriseServices { service_type, contract_value, client_id } => {
+                ("enterprise_services", *client_id, *contract_value, *contract_value,
+                 serde_json::json!({"service_type": service_type}))
+            },
+            RevenueStream::APILicensing { tier, monthly_value, client_id } => {
+                ("api_licensing", *client_id, *monthly_value, *monthly_value,
+                 serde_json::json!({"tier": tier}))
+            },
+            RevenueStream::PerformanceOptimization { service_type, project_value, client_id } => {
+                ("performance_optimization", *client_id, *project_value, *project_value,
+                 serde_json::json!({"service_type": service_type}))
+            },
+            RevenueStream::CustomSolutions { project_type, total_value, client_id } => {
+                ("custom_solutions", *client_id, *total_value, *total_value,
+                 serde_json::json!({"project_type": project_type}))
+            },
+        };
+
+        if amount < Decimal::ZERO || fee < Decimal::ZERO {
+            return Err(RevenueError::Validation(format!(
+                "Negative {} revenue: amount {}, fee {}", stream_type, amount, fee
+            )));
+        }
+
+        Ok(Self {
+            id: Uuid::new_v4(),
+            stream_type: stream_type.to_string(),
+            user_id: Some(user_id),
+            amount,
+            fee,
+            timestamp: Utc::now(),
+            metadata,
+        })
+    }
+
+    // Inserts the record and refreshes aggregate_revenue in one transaction, so the
+    // view never lags behind a committed row
+    pub async fn insert(&self, pool: &PgPool) -> RevenueResult<Uuid> {
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO revenue_records
+            (id, stream_type, user_id, amount, fee, timestamp, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            self.id,
+            self.stream_type,
+            self.user_id,
+            self.amount,
+            self.fee,
+            self.timestamp,
+            self.metadata
+        ).execute(&mut *tx).await?;
+
+        sqlx::query("REFRESH MATERIALIZED VIEW aggregate_revenue")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(self.id)
+    }
+}
+
 // Revenue progress tracking
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RevenueProgress {
@@ -601,9 +873,13 @@ struct RevenueEngineBackground {
     redis: ConnectionManager,
     revenue_analytics: Arc<RevenueAnalytics>,
     revenue_forecasting: Arc<RevenueForecasting>,
+    subscription_manager: Arc<SubscriptionManager>,
     billing_engine: Arc<BillingEngine>,
     optimization_engine: Arc<RevenueOptimizationEngine>,
     current_metrics: Arc<RwLock<RevenueMetrics>>,
+    churn_predictor: Arc<ChurnPredictor>,
+    re_engagement_emailer: Arc<ReEngagementEmailer>,
+    sla_monitor: Arc<SlaMonitor>,
     config: RevenueConfig,
 }
 
@@ -619,7 +895,7 @@ impl RevenueEngineBackground {
     }
 
     async fn process_billing_cycle(&self) -> RevenueResult<()> {
-        // Implementation in billing engine
+        self.billing_engine.issue_scheduled_contract_invoices(Utc::now()).await?;
         Ok(())
     }
 
@@ -627,6 +903,27 @@ impl RevenueEngineBackground {
         // Implementation in forecasting engine
         Ok(())
     }
+
+    async fn run_churn_detection(&self) -> RevenueResult<()> {
+        let sent = self.re_engagement_emailer.run(&self.churn_predictor, Utc::now()).await?;
+        tracing::info!("📧 Sent {} re-engagement emails", sent);
+        Ok(())
+    }
+
+    async fn refresh_clv_predictions(&self) -> RevenueResult<()> {
+        ClvAnalysis::new(self.db_pool.clone()).refresh_predictions().await?;
+        Ok(())
+    }
+
+    async fn process_paused_subscriptions(&self) -> RevenueResult<()> {
+        self.subscription_manager.process_paused_subscriptions(Utc::now()).await?;
+        Ok(())
+    }
+
+    async fn check_sla_compliance(&self) -> RevenueResult<()> {
+        self.sla_monitor.run(Utc::now()).await?;
+        Ok(())
+    }
 }
 
 // Revenue optimization engine
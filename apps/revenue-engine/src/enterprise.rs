@@ -11,6 +11,15 @@ use chrono::{DateTime, Utc, Duration};
 use serde::{Serialize, Deserialize};
 
 use crate::core::{RevenueError, RevenueResult};
+use crate::billing::{BillingEngine, quarterly_billing_periods};
+
+pub mod sla;
+pub mod otc;
+pub mod custody;
+
+pub use custody::CustodyService;
+
+use sla::{ContractDocument, SlaKpi};
 
 // Enterprise service types
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +63,25 @@ impl EnterpriseContractTier {
             Self::Custom => "99.99% uptime, 15-minute response, 24/7 dedicated team",
         }
     }
+
+    // Agreed target for each tracked KPI: uptime in percent, response time in minutes and
+    // monthly report delivery in days after month end
+    pub fn sla_target(&self, kpi: SlaKpi) -> Decimal {
+        match (self, kpi) {
+            (Self::Standard, SlaKpi::Uptime) => Decimal::new(9950, 2),
+            (Self::Premium, SlaKpi::Uptime) => Decimal::new(9990, 2),
+            (Self::Enterprise, SlaKpi::Uptime) => Decimal::new(99950, 3),
+            (Self::Custom, SlaKpi::Uptime) => Decimal::new(9999, 2),
+            (Self::Standard, SlaKpi::ResponseTime) => Decimal::new(240, 0),
+            (Self::Premium, SlaKpi::ResponseTime) => Decimal::new(120, 0),
+            (Self::Enterprise, SlaKpi::ResponseTime) => Decimal::new(60, 0),
+            (Self::Custom, SlaKpi::ResponseTime) => Decimal::new(15, 0),
+            (Self::Standard, SlaKpi::MonthlyReportDelivery) => Decimal::new(10, 0),
+            (Self::Premium, SlaKpi::MonthlyReportDelivery) => Decimal::new(7, 0),
+            (Self::Enterprise, SlaKpi::MonthlyReportDelivery) => Decimal::new(5, 0),
+            (Self::Custom, SlaKpi::MonthlyReportDelivery) => Decimal::new(3, 0),
+        }
+    }
 }
 
 // Enterprise contract
@@ -74,6 +102,7 @@ pub struct EnterpriseContract {
     pub service_credits: Decimal,
     pub compliance_requirements: Vec<String>,
     pub dedicated_resources: Vec<DedicatedResource>,
+    pub contract_document: Option<ContractDocument>,
     pub metadata: serde_json::Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
@@ -150,9 +179,9 @@ pub enum OTCOrderStatus {
     Failed,
 }
 
-// Custody service record
+// Custody account record
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CustodyService {
+pub struct CustodyAccount {
     pub id: Uuid,
     pub client_id: Uuid,
     pub asset_type: String,
@@ -203,49 +232,22 @@ pub struct EnterpriseAnalytics {
 pub struct EnterpriseRevenueManager {
     db_pool: PgPool,
     redis: ConnectionManager,
+    billing_engine: Arc<BillingEngine>,
 }
 
 impl EnterpriseRevenueManager {
-    pub async fn new(db_pool: PgPool, redis: ConnectionManager) -> RevenueResult<Self> {
+    pub async fn new(db_pool: PgPool, redis: ConnectionManager, billing_engine: Arc<BillingEngine>) -> RevenueResult<Self> {
         // Setup enterprise tables
         Self::setup_enterprise_tables(&db_pool).await?;
 
         Ok(Self {
             db_pool,
             redis,
+            billing_engine,
         })
     }
 
     async fn setup_enterprise_tables(pool: &PgPool) -> RevenueResult<()> {
-        // Enterprise contracts table
-        sqlx::query(r#"
-            CREATE TABLE IF NOT EXISTS enterprise_contracts (
-                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                client_id UUID NOT NULL,
-                client_name VARCHAR NOT NULL,
-                contract_tier VARCHAR NOT NULL,
-                services JSONB NOT NULL DEFAULT '[]',
-                annual_value DECIMAL(15,2) NOT NULL,
-                monthly_value DECIMAL(15,2) NOT NULL,
-                payment_terms VARCHAR NOT NULL DEFAULT 'monthly',
-                start_date TIMESTAMP NOT NULL,
-                end_date TIMESTAMP NOT NULL,
-                auto_renewal BOOLEAN DEFAULT true,
-                status VARCHAR NOT NULL DEFAULT 'active',
-                service_credits DECIMAL(15,2) DEFAULT 0,
-                compliance_requirements JSONB DEFAULT '[]',
-                dedicated_resources JSONB DEFAULT '[]',
-                metadata JSONB DEFAULT '{}',
-                created_at TIMESTAMP DEFAULT NOW(),
-                updated_at TIMESTAMP DEFAULT NOW()
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_client ON enterprise_contracts(client_id);
-            CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_status ON enterprise_contracts(status);
-            CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_tier ON enterprise_contracts(contract_tier);
-            CREATE INDEX IF NOT EXISTS idx_enterprise_contracts_dates ON enterprise_contracts(start_date, end_date);
-        "#).execute(pool).await?;
-
         // OTC trading orders table
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS otc_trading_orders (
@@ -350,8 +352,8 @@ impl EnterpriseRevenueManager {
             r#"
             INSERT INTO enterprise_contracts 
             (id, client_id, client_name, contract_tier, services, annual_value, monthly_value,
-             start_date, end_date, compliance_requirements, dedicated_resources, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+             payment_terms, start_date, end_date, compliance_requirements, dedicated_resources, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             RETURNING id, client_id, client_name, contract_tier, services, annual_value, monthly_value,
                      payment_terms, start_date, end_date, auto_renewal, status, service_credits,
                      compliance_requirements, dedicated_resources, metadata, created_at, updated_at
@@ -363,6 +365,7 @@ impl EnterpriseRevenueManager {
             serde_json::to_value(&services)?,
             annual_value,
             monthly_value,
+            "quarterly",
             start_date,
             end_date,
             serde_json::json!(["SOC2", "ISO27001"]), // Default compliance
@@ -382,7 +385,7 @@ impl EnterpriseRevenueManager {
             annual_value
         ).await?;
 
-        let contract = EnterpriseContract {
+        let mut contract = EnterpriseContract {
             id: record.id,
             client_id: record.client_id,
             client_name: record.client_name,
@@ -390,7 +393,7 @@ impl EnterpriseRevenueManager {
             services,
             annual_value: record.annual_value,
             monthly_value: record.monthly_value,
-            payment_terms: PaymentTerms::Monthly,
+            payment_terms: PaymentTerms::Quarterly,
             start_date: record.start_date,
             end_date: record.end_date,
             auto_renewal: record.auto_renewal,
@@ -398,11 +401,27 @@ impl EnterpriseRevenueManager {
             service_credits: record.service_credits,
             compliance_requirements: serde_json::from_value(record.compliance_requirements)?,
             dedicated_resources,
+            contract_document: None,
             metadata: record.metadata,
             created_at: record.created_at,
             updated_at: record.updated_at,
         };
 
+        // SLA tracking for every KPI the contract document commits to
+        sla::create_sla_metrics(&self.db_pool, contract_id, &contract.contract_tier).await?;
+
+        // Quarterly invoices over the whole term
+        let billing_periods = quarterly_billing_periods(contract.start_date, contract.end_date, contract.annual_value);
+        self.billing_engine.schedule_contract_invoices(contract_id, client_id, &billing_periods).await?;
+
+        let document = ContractDocument::for_contract(&contract, billing_periods, Utc::now());
+        sqlx::query!(
+            "UPDATE enterprise_contracts SET contract_document = $1 WHERE id = $2",
+            serde_json::to_value(&document)?,
+            contract_id
+        ).execute(&self.db_pool).await?;
+        contract.contract_document = Some(document);
+
         tracing::info!("✅ Enterprise contract created: {} - ${}/year", contract_id, annual_value);
         Ok(contract)
     }
@@ -540,15 +559,7 @@ impl EnterpriseRevenueManager {
 
     // Calculate OTC commission rate based on volume
     fn calculate_otc_commission_rate(&self, total_value: Decimal) -> Decimal {
-        if total_value >= Decimal::new(10000000, 0) {       // $10M+
-            Decimal::new(10, 4)  // 0.10%
-        } else if total_value >= Decimal::new(1000000, 0) { // $1M+
-            Decimal::new(25, 4)  // 0.25%
-        } else if total_value >= Decimal::new(100000, 0) {  // $100K+
-            Decimal::new(50, 4)  // 0.50%
-        } else {
-            Decimal::new(100, 4) // 1.00%
-        }
+        otc::otc_spread(total_value)
     }
 
     // Get market price (simplified - would integrate with price feeds)
@@ -565,7 +576,7 @@ impl EnterpriseRevenueManager {
         insurance_coverage: Decimal,
         storage_type: CustodyStorageType,
         security_level: SecurityLevel
-    ) -> RevenueResult<CustodyService> {
+    ) -> RevenueResult<CustodyAccount> {
         tracing::info!("🔐 Setting up custody service for client: {} - Asset: {}", client_id, asset_type);
 
         let service_id = Uuid::new_v4();
@@ -591,7 +602,7 @@ impl EnterpriseRevenueManager {
             serde_json::json!(["SOC2", "ISO27001", "FIPS140-2"])
         ).fetch_one(&self.db_pool).await?;
 
-        let service = CustodyService {
+        let service = CustodyAccount {
             id: record.id,
             client_id: record.client_id,
             asset_type: record.asset_type,
@@ -776,54 +787,6 @@ impl EnterpriseRevenueManager {
     }
 }
 
-// Custody service for institutional-grade asset management
-#[derive(Debug)]
-pub struct CustodyService {
-    enterprise_manager: Arc<EnterpriseRevenueManager>,
-}
-
-impl CustodyService {
-    pub fn new(enterprise_manager: Arc<EnterpriseRevenueManager>) -> Self {
-        Self { enterprise_manager }
-    }
-
-    // Update assets under management and calculate fees
-    pub async fn update_assets_under_management(
-        &self,
-        client_id: Uuid,
-        asset_type: &str,
-        new_aum: Decimal
-    ) -> RevenueResult<Decimal> {
-        // Update AUM in database
-        let result = sqlx::query!(
-            r#"
-            UPDATE custody_services 
-            SET total_aum = $1, updated_at = NOW()
-            WHERE client_id = $2 AND asset_type = $3
-            RETURNING custody_fee_rate
-            "#,
-            new_aum,
-            client_id,
-            asset_type
-        ).fetch_one(&self.enterprise_manager.db_pool).await?;
-
-        // Calculate monthly fee based on AUM
-        let monthly_fee = new_aum * result.custody_fee_rate / Decimal::new(12, 0) / Decimal::new(100, 0);
-
-        // Log revenue event
-        self.enterprise_manager.log_revenue_event(
-            client_id,
-            None,
-            "custody_fee",
-            Some("CustodyServices"),
-            monthly_fee
-        ).await?;
-
-        tracing::info!("📊 Updated AUM for {}: ${} - Monthly fee: ${}", client_id, new_aum, monthly_fee);
-        Ok(monthly_fee)
-    }
-}
-
 // OTC trading desk for large transactions
 #[derive(Debug)]
 pub struct OTCTradingDesk {
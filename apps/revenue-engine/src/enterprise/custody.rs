@@ -0,0 +1,457 @@
+// Custody Service - Threshold key custody and multi-party withdrawal approval
+// Client keys are split with Shamir's Secret Sharing across custodians and only exist whole
+// in memory while a withdrawal is being signed
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sharks::{Share, Sharks};
+use solana_sdk::signature::{Keypair, Signer};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::core::{RevenueError, RevenueResult};
+use super::EnterpriseRevenueManager;
+
+// Share handed to one custodian, hex encoded. Shown once at key creation and never stored.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CustodianShare {
+    pub custodian_id: Uuid,
+    pub share: String,
+}
+
+impl std::fmt::Debug for CustodianShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustodianShare").field("custodian_id", &self.custodian_id).finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustodyKey {
+    pub client_id: Uuid,
+    pub public_key: String,
+    pub threshold: u8,
+    pub shares: Vec<CustodianShare>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WithdrawalStatus {
+    Pending,
+    Executed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingWithdrawal {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub destination: String,
+    pub amount: Decimal,
+    pub required_approvals: u8,
+    pub approvals: Vec<Uuid>,
+    pub status: WithdrawalStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedWithdrawal {
+    pub withdrawal_id: Uuid,
+    pub public_key: String,
+    pub message: String,
+    pub signature: String,
+}
+
+pub fn encode_share(share: &[u8]) -> String {
+    share.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn decode_share(share: &str) -> RevenueResult<Zeroizing<Vec<u8>>> {
+    let invalid = || RevenueError::Validation("Share fragment is not valid hex".to_string());
+    if share.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    let bytes = (0..share.len())
+        .step_by(2)
+        .map(|i| share.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(invalid)?;
+    Ok(Zeroizing::new(bytes))
+}
+
+// SHA-256 of a share; stored in place of the share so approvals can be checked
+pub fn share_commitment(share: &[u8]) -> String {
+    encode_share(ring::digest::digest(&ring::digest::SHA256, share).as_ref())
+}
+
+pub fn split_secret(secret: &[u8], threshold: u8, share_count: usize) -> RevenueResult<Vec<Zeroizing<Vec<u8>>>> {
+    if threshold < 2 || share_count < threshold as usize || share_count > u8::MAX as usize {
+        return Err(RevenueError::Validation(format!(
+            "Invalid custody scheme: {} of {} shares", threshold, share_count
+        )));
+    }
+    Ok(Sharks(threshold)
+        .dealer(secret)
+        .take(share_count)
+        .map(|share| Zeroizing::new(Vec::from(&share)))
+        .collect())
+}
+
+pub fn recover_secret(threshold: u8, fragments: &[Zeroizing<Vec<u8>>]) -> RevenueResult<Zeroizing<Vec<u8>>> {
+    let shares = fragments.iter()
+        .map(|fragment| Share::try_from(fragment.as_slice()))
+        .collect::<Result<Vec<Share>, _>>()
+        .map_err(|e| RevenueError::Enterprise(format!("Malformed key share: {}", e)))?;
+
+    Sharks(threshold)
+        .recover(&shares)
+        .map(Zeroizing::new)
+        .map_err(|e| RevenueError::Enterprise(format!("Could not reconstruct custody key: {}", e)))
+}
+
+// Canonical payload signed for a withdrawal
+pub fn withdrawal_message(withdrawal_id: Uuid, client_id: Uuid, destination: &str, amount: Decimal) -> String {
+    format!("nockchain-custody-withdrawal:{}:{}:{}:{}", withdrawal_id, client_id, destination, amount)
+}
+
+// Custody service for institutional-grade asset management
+pub struct CustodyService {
+    enterprise_manager: Arc<EnterpriseRevenueManager>,
+    // Share fragments submitted with approvals, held in memory only until execution. A restart
+    // drops them and custodians have to approve again.
+    approved_shares: Mutex<HashMap<Uuid, HashMap<Uuid, Zeroizing<Vec<u8>>>>>,
+}
+
+impl std::fmt::Debug for CustodyService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustodyService").finish_non_exhaustive()
+    }
+}
+
+impl CustodyService {
+    pub fn new(enterprise_manager: Arc<EnterpriseRevenueManager>) -> Self {
+        Self {
+            enterprise_manager,
+            approved_shares: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Update assets under management and calculate fees
+    pub async fn update_assets_under_management(
+        &self,
+        client_id: Uuid,
+        asset_type: &str,
+        new_aum: Decimal
+    ) -> RevenueResult<Decimal> {
+        // Update AUM in database
+        let result = sqlx::query!(
+            r#"
+            UPDATE custody_services
+            SET total_aum = $1, updated_at = NOW()
+            WHERE client_id = $2 AND asset_type = $3
+            RETURNING custody_fee_rate
+            "#,
+            new_aum,
+            client_id,
+            asset_type
+        ).fetch_one(&self.enterprise_manager.db_pool).await?;
+
+        // Calculate monthly fee based on AUM
+        let monthly_fee = new_aum * result.custody_fee_rate / Decimal::new(12, 0) / Decimal::new(100, 0);
+
+        // Log revenue event
+        self.enterprise_manager.log_revenue_event(
+            client_id,
+            None,
+            "custody_fee",
+            Some("CustodyServices"),
+            monthly_fee
+        ).await?;
+
+        tracing::info!("📊 Updated AUM for {}: ${} - Monthly fee: ${}", client_id, new_aum, monthly_fee);
+        Ok(monthly_fee)
+    }
+
+    // Generate a custody keypair for the client and split it across the custodians. The
+    // returned shares are the only copy of the key and must be handed to their custodians.
+    pub async fn create_custody_key(&self, client_id: Uuid, custodians: &[Uuid], threshold: u8) -> RevenueResult<CustodyKey> {
+        let keypair = Keypair::new();
+        let secret = Zeroizing::new(keypair.to_bytes().to_vec());
+        self.split_client_key(client_id, secret, custodians, threshold).await
+    }
+
+    // Split an existing client keypair (64-byte ed25519) across the custodians
+    pub async fn split_client_key(
+        &self,
+        client_id: Uuid,
+        private_key: Zeroizing<Vec<u8>>,
+        custodians: &[Uuid],
+        threshold: u8
+    ) -> RevenueResult<CustodyKey> {
+        if custodians.iter().collect::<HashSet<_>>().len() != custodians.len() {
+            return Err(RevenueError::Validation("Each custodian can hold only one share".to_string()));
+        }
+        let public_key = Keypair::from_bytes(&private_key)
+            .map_err(|_| RevenueError::Validation("Private key is not a valid ed25519 keypair".to_string()))?
+            .pubkey()
+            .to_string();
+        let shares = split_secret(&private_key, threshold, custodians.len())?;
+        drop(private_key);
+
+        let mut tx = self.enterprise_manager.db_pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO custody_keys (client_id, public_key, threshold, custodian_count)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            client_id,
+            public_key,
+            threshold as i16,
+            custodians.len() as i16
+        ).execute(&mut *tx).await?;
+
+        for (custodian_id, share) in custodians.iter().zip(&shares) {
+            sqlx::query!(
+                "INSERT INTO custody_key_shares (client_id, custodian_id, share_commitment) VALUES ($1, $2, $3)",
+                client_id,
+                custodian_id,
+                share_commitment(share)
+            ).execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!("🔐 Custody key {} for client {} split {}-of-{}", public_key, client_id, threshold, custodians.len());
+        Ok(CustodyKey {
+            client_id,
+            public_key,
+            threshold,
+            shares: custodians.iter()
+                .zip(&shares)
+                .map(|(custodian_id, share)| CustodianShare { custodian_id: *custodian_id, share: encode_share(share) })
+                .collect(),
+        })
+    }
+
+    pub async fn initiate_custody_withdrawal(
+        &self,
+        client_id: Uuid,
+        destination: String,
+        amount: Decimal
+    ) -> RevenueResult<PendingWithdrawal> {
+        if amount <= Decimal::ZERO {
+            return Err(RevenueError::Validation("Withdrawal amount must be positive".to_string()));
+        }
+        if destination.trim().is_empty() {
+            return Err(RevenueError::Validation("Withdrawal destination is required".to_string()));
+        }
+
+        let key = sqlx::query!("SELECT threshold FROM custody_keys WHERE client_id = $1", client_id)
+            .fetch_optional(&self.enterprise_manager.db_pool).await?
+            .ok_or_else(|| RevenueError::Validation(format!("Client {} has no custody key", client_id)))?;
+
+        let record = sqlx::query!(
+            r#"
+            INSERT INTO custody_withdrawals (client_id, destination, amount, required_approvals)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, created_at
+            "#,
+            client_id,
+            destination,
+            amount,
+            key.threshold
+        ).fetch_one(&self.enterprise_manager.db_pool).await?;
+
+        tracing::info!("🏦 Custody withdrawal {} of {} to {} awaiting {} approvals", record.id, amount, destination, key.threshold);
+        Ok(PendingWithdrawal {
+            id: record.id,
+            client_id,
+            destination,
+            amount,
+            required_approvals: key.threshold as u8,
+            approvals: Vec::new(),
+            status: WithdrawalStatus::Pending,
+            created_at: record.created_at,
+        })
+    }
+
+    // Record a custodian's approval. The fragment must be the share issued to that custodian;
+    // it is checked against the stored commitment and kept in memory for execution.
+    pub async fn approve_withdrawal(
+        &self,
+        withdrawal_id: Uuid,
+        custodian_id: Uuid,
+        share_fragment: &str
+    ) -> RevenueResult<PendingWithdrawal> {
+        let fragment = decode_share(share_fragment)?;
+        let pool = &self.enterprise_manager.db_pool;
+
+        let withdrawal = sqlx::query!(
+            r#"
+            SELECT w.client_id, w.status, s.share_commitment AS "share_commitment?"
+            FROM custody_withdrawals w
+            LEFT JOIN custody_key_shares s ON s.client_id = w.client_id AND s.custodian_id = $2
+            WHERE w.id = $1
+            "#,
+            withdrawal_id,
+            custodian_id
+        ).fetch_optional(pool).await?
+            .ok_or_else(|| RevenueError::Validation(format!("Unknown withdrawal {}", withdrawal_id)))?;
+
+        if withdrawal.status != "pending" {
+            return Err(RevenueError::Validation(format!("Withdrawal {} is {}", withdrawal_id, withdrawal.status)));
+        }
+        match withdrawal.share_commitment {
+            None => return Err(RevenueError::Validation(format!(
+                "Custodian {} holds no share for client {}", custodian_id, withdrawal.client_id
+            ))),
+            Some(commitment) if commitment != share_commitment(&fragment) => {
+                tracing::warn!("⚠️ Custodian {} submitted a share that does not match its commitment", custodian_id);
+                return Err(RevenueError::Validation("Share fragment does not match the custodian's share".to_string()));
+            }
+            Some(_) => {}
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO custody_withdrawal_approvals (withdrawal_id, custodian_id)
+            VALUES ($1, $2)
+            ON CONFLICT (withdrawal_id, custodian_id) DO NOTHING
+            "#,
+            withdrawal_id,
+            custodian_id
+        ).execute(pool).await?;
+
+        self.approved_shares.lock().await
+            .entry(withdrawal_id)
+            .or_default()
+            .insert(custodian_id, fragment);
+
+        tracing::info!("✍️ Custodian {} approved withdrawal {}", custodian_id, withdrawal_id);
+        self.get_withdrawal(withdrawal_id).await
+    }
+
+    pub async fn get_withdrawal(&self, withdrawal_id: Uuid) -> RevenueResult<PendingWithdrawal> {
+        let pool = &self.enterprise_manager.db_pool;
+        let record = sqlx::query!(
+            r#"
+            SELECT id, client_id, destination, amount, required_approvals, status, created_at
+            FROM custody_withdrawals
+            WHERE id = $1
+            "#,
+            withdrawal_id
+        ).fetch_optional(pool).await?
+            .ok_or_else(|| RevenueError::Validation(format!("Unknown withdrawal {}", withdrawal_id)))?;
+
+        let approvals = sqlx::query_scalar!(
+            "SELECT custodian_id FROM custody_withdrawal_approvals WHERE withdrawal_id = $1 ORDER BY approved_at",
+            withdrawal_id
+        ).fetch_all(pool).await?;
+
+        Ok(PendingWithdrawal {
+            id: record.id,
+            client_id: record.client_id,
+            destination: record.destination,
+            amount: record.amount,
+            required_approvals: record.required_approvals as u8,
+            approvals,
+            status: if record.status == "executed" { WithdrawalStatus::Executed } else { WithdrawalStatus::Pending },
+            created_at: record.created_at,
+        })
+    }
+
+    // Reconstruct the key from the approved shares, sign the withdrawal and wipe the key.
+    // The shares are consumed whether or not signing succeeds.
+    pub async fn execute_withdrawal(&self, withdrawal_id: Uuid) -> RevenueResult<SignedWithdrawal> {
+        let withdrawal = self.get_withdrawal(withdrawal_id).await?;
+        if withdrawal.status != WithdrawalStatus::Pending {
+            return Err(RevenueError::Validation(format!("Withdrawal {} was already executed", withdrawal_id)));
+        }
+
+        let public_key = sqlx::query_scalar!("SELECT public_key FROM custody_keys WHERE client_id = $1", withdrawal.client_id)
+            .fetch_one(&self.enterprise_manager.db_pool).await?;
+
+        let fragments: Vec<Zeroizing<Vec<u8>>> = {
+            let mut approved = self.approved_shares.lock().await;
+            let held = approved.get(&withdrawal_id).map_or(0, HashMap::len);
+            if held < withdrawal.required_approvals as usize {
+                return Err(RevenueError::Validation(format!(
+                    "Withdrawal {} has {} of {} required approvals", withdrawal_id, held, withdrawal.required_approvals
+                )));
+            }
+            approved.remove(&withdrawal_id).unwrap_or_default().into_values().collect()
+        };
+
+        let message = withdrawal_message(withdrawal.id, withdrawal.client_id, &withdrawal.destination, withdrawal.amount);
+        let signature = {
+            let mut secret = recover_secret(withdrawal.required_approvals, &fragments)?;
+            drop(fragments);
+            let keypair = Keypair::from_bytes(&secret);
+            secret.zeroize();
+            let keypair = keypair
+                .map_err(|_| RevenueError::Enterprise("Reconstructed custody key is invalid".to_string()))?;
+            if keypair.pubkey().to_string() != public_key {
+                return Err(RevenueError::Enterprise("Reconstructed custody key does not match the client's key".to_string()));
+            }
+            keypair.sign_message(message.as_bytes()).to_string()
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE custody_withdrawals
+            SET status = 'executed', signature = $2, executed_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            "#,
+            withdrawal_id,
+            signature
+        ).execute(&self.enterprise_manager.db_pool).await?;
+
+        tracing::info!("✅ Custody withdrawal {} signed by {}", withdrawal_id, public_key);
+        Ok(SignedWithdrawal { withdrawal_id, public_key, message, signature })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_shares_reconstruct_key() {
+        let keypair = Keypair::new();
+        let secret = keypair.to_bytes();
+        let shares = split_secret(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        let subset = vec![shares[4].clone(), shares[0].clone(), shares[2].clone()];
+        let recovered = recover_secret(3, &subset).unwrap();
+        assert_eq!(recovered.as_slice(), secret.as_slice());
+        assert_eq!(Keypair::from_bytes(&recovered).unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn test_below_threshold_cannot_reconstruct() {
+        let secret = Keypair::new().to_bytes();
+        let shares = split_secret(&secret, 3, 5).unwrap();
+        assert!(recover_secret(3, &shares[..2]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_schemes_rejected() {
+        let secret = [7u8; 64];
+        assert!(split_secret(&secret, 1, 3).is_err());
+        assert!(split_secret(&secret, 4, 3).is_err());
+        assert!(split_secret(&secret, 2, 256).is_err());
+    }
+
+    #[test]
+    fn test_share_encoding_round_trips() {
+        let share = vec![1u8, 0xab, 0x00, 0xff];
+        let encoded = encode_share(&share);
+        assert_eq!(encoded, "01ab00ff");
+        assert_eq!(decode_share(&encoded).unwrap().as_slice(), share.as_slice());
+        assert!(decode_share("abc").is_err());
+        assert!(decode_share("zz").is_err());
+        assert_eq!(share_commitment(&share), share_commitment(&decode_share(&encoded).unwrap()));
+    }
+}
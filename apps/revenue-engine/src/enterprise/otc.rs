@@ -0,0 +1,536 @@
+// OTC Request-for-Quote Desk - Quotes, collateral locking and T+2 settlement
+// Clients request a quote, accept it before it expires, and trade against the desk's own inventory
+
+use std::sync::Arc;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
+use serde::{Serialize, Deserialize};
+
+use crate::core::{RevenueError, RevenueResult};
+use super::EnterpriseRevenueManager;
+
+// Client side of the trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtcSide {
+    Buy,
+    Sell,
+}
+
+impl OtcSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Buy => "buy",
+            Self::Sell => "sell",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "buy" => Some(Self::Buy),
+            "sell" => Some(Self::Sell),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestForQuote {
+    pub client_id: Uuid,
+    pub side: OtcSide,
+    pub quantity: Decimal,
+    // BASE/QUOTE, e.g. NOCK/USDC
+    pub token_pair: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub quote_id: Uuid,
+    pub client_id: Uuid,
+    pub side: OtcSide,
+    pub token_pair: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub valid_until: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptQuote {
+    pub quote_id: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtcTrade {
+    pub trade_id: Uuid,
+    pub quote_id: Uuid,
+    pub client_id: Uuid,
+    pub side: OtcSide,
+    pub token_pair: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub notional: Decimal,
+    pub spread_revenue: Decimal,
+    pub executed_at: DateTime<Utc>,
+    pub settlement_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OtcDeskConfig {
+    // Time the desk has to price a request before it is rejected
+    pub quote_sla: std::time::Duration,
+    // How long a client can accept a quote for
+    pub quote_ttl: Duration,
+    // Business days from execution to settlement
+    pub settlement_days: u32,
+}
+
+impl Default for OtcDeskConfig {
+    fn default() -> Self {
+        Self {
+            quote_sla: std::time::Duration::from_secs(60),
+            quote_ttl: Duration::seconds(30),
+            settlement_days: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OtcSweepSummary {
+    pub quotes_expired: u64,
+    pub trades_settled: usize,
+    pub rebalance_orders: usize,
+}
+
+pub fn parse_token_pair(token_pair: &str) -> RevenueResult<(String, String)> {
+    match token_pair.split_once('/') {
+        Some((base, quote)) if !base.trim().is_empty() && !quote.trim().is_empty() && base.trim() != quote.trim() => {
+            Ok((base.trim().to_uppercase(), quote.trim().to_uppercase()))
+        }
+        _ => Err(RevenueError::Validation(format!("Invalid token pair: {}", token_pair))),
+    }
+}
+
+// Tiered desk spread by notional; larger blocks trade tighter
+pub fn otc_spread(notional: Decimal) -> Decimal {
+    if notional >= Decimal::new(10000000, 0) {       // $10M+
+        Decimal::new(10, 4)  // 0.10%
+    } else if notional >= Decimal::new(1000000, 0) { // $1M+
+        Decimal::new(25, 4)  // 0.25%
+    } else if notional >= Decimal::new(100000, 0) {  // $100K+
+        Decimal::new(50, 4)  // 0.50%
+    } else {
+        Decimal::new(100, 4) // 1.00%
+    }
+}
+
+// Price quoted to the client: above mid when they buy, below when they sell
+pub fn quote_price(side: OtcSide, mid_price: Decimal, spread: Decimal) -> Decimal {
+    match side {
+        OtcSide::Buy => mid_price * (Decimal::ONE + spread),
+        OtcSide::Sell => mid_price * (Decimal::ONE - spread),
+    }
+}
+
+// Token and amount the client must lock to accept: the quote token for buys, the base for sells
+pub fn collateral_requirement<'a>(side: OtcSide, base: &'a str, quote: &'a str, quantity: Decimal, price: Decimal) -> (&'a str, Decimal) {
+    match side {
+        OtcSide::Buy => (quote, quantity * price),
+        OtcSide::Sell => (base, quantity),
+    }
+}
+
+// Adds business days, skipping weekends
+pub fn settlement_date(executed_at: DateTime<Utc>, business_days: u32) -> DateTime<Utc> {
+    let mut date = executed_at;
+    let mut remaining = business_days;
+    while remaining > 0 {
+        date += Duration::days(1);
+        if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            remaining -= 1;
+        }
+    }
+    date
+}
+
+// Amount to buy back once inventory falls below its floor, restoring the target balance
+pub fn rebalance_amount(balance: Decimal, min_balance: Decimal, target_balance: Decimal) -> Option<Decimal> {
+    (balance < min_balance && target_balance > balance).then(|| target_balance - balance)
+}
+
+// RFQ desk trading against internal inventory
+#[derive(Debug)]
+pub struct OtcQuoteDesk {
+    enterprise_manager: Arc<EnterpriseRevenueManager>,
+    config: OtcDeskConfig,
+}
+
+impl OtcQuoteDesk {
+    pub fn new(enterprise_manager: Arc<EnterpriseRevenueManager>, config: OtcDeskConfig) -> Self {
+        Self { enterprise_manager, config }
+    }
+
+    // Price an RFQ off the inventory marks. Pricing that overruns the quote SLA is rejected
+    // rather than answered late.
+    pub async fn request_quote(&self, request: RequestForQuote) -> RevenueResult<Quote> {
+        if request.quantity <= Decimal::ZERO {
+            return Err(RevenueError::Validation("Quote quantity must be positive".to_string()));
+        }
+        let (base, quote) = parse_token_pair(&request.token_pair)?;
+        let token_pair = format!("{}/{}", base, quote);
+
+        let mid_price = tokio::time::timeout(self.config.quote_sla, self.mid_price(&base, &quote))
+            .await
+            .map_err(|_| RevenueError::Enterprise(format!("Could not price {} within the quote SLA", token_pair)))??;
+
+        let spread = otc_spread(request.quantity * mid_price);
+        let price = quote_price(request.side, mid_price, spread);
+        let valid_until = Utc::now() + self.config.quote_ttl;
+
+        let record = sqlx::query!(
+            r#"
+            INSERT INTO otc_quotes (client_id, side, token_pair, quantity, mid_price, price, valid_until)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id
+            "#,
+            request.client_id,
+            request.side.as_str(),
+            token_pair,
+            request.quantity,
+            mid_price,
+            price,
+            valid_until
+        ).fetch_one(&self.enterprise_manager.db_pool).await?;
+
+        tracing::info!("💬 OTC quote {} for {} {} {} at {}", record.id, request.side.as_str(), request.quantity, token_pair, price);
+        Ok(Quote {
+            quote_id: record.id,
+            client_id: request.client_id,
+            side: request.side,
+            token_pair,
+            quantity: request.quantity,
+            price,
+            valid_until,
+        })
+    }
+
+    async fn mid_price(&self, base: &str, quote: &str) -> RevenueResult<Decimal> {
+        let marks = sqlx::query!(
+            "SELECT token, mark_price FROM otc_inventory WHERE token = $1 OR token = $2",
+            base,
+            quote
+        ).fetch_all(&self.enterprise_manager.db_pool).await?;
+
+        let mark = |token: &str| marks.iter()
+            .find(|row| row.token == token)
+            .map(|row| row.mark_price)
+            .filter(|price| *price > Decimal::ZERO)
+            .ok_or_else(|| RevenueError::Enterprise(format!("Desk does not carry {}", token)));
+
+        Ok(mark(base)? / mark(quote)?)
+    }
+
+    // Accept an open quote: lock the client's collateral and fill from desk inventory at the
+    // quoted price, all in one transaction. Settlement follows T+2.
+    pub async fn accept_quote(&self, accept: AcceptQuote) -> RevenueResult<OtcTrade> {
+        let now = Utc::now();
+        let mut tx = self.enterprise_manager.db_pool.begin().await?;
+
+        let quote = sqlx::query!(
+            r#"
+            SELECT id, client_id, side, token_pair, quantity, mid_price, price, status, valid_until
+            FROM otc_quotes
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            accept.quote_id
+        ).fetch_optional(&mut *tx).await?
+            .ok_or_else(|| RevenueError::Validation(format!("Unknown quote {}", accept.quote_id)))?;
+
+        if quote.status != "open" {
+            return Err(RevenueError::Validation(format!("Quote {} is {}", quote.id, quote.status)));
+        }
+        if quote.valid_until < now {
+            sqlx::query!("UPDATE otc_quotes SET status = 'expired' WHERE id = $1", quote.id)
+                .execute(&mut *tx).await?;
+            tx.commit().await?;
+            return Err(RevenueError::Validation(format!("Quote {} expired at {}", quote.id, quote.valid_until)));
+        }
+
+        let side = OtcSide::parse(&quote.side)
+            .ok_or_else(|| RevenueError::Enterprise(format!("Invalid side on quote {}", quote.id)))?;
+        let (base, quote_token) = parse_token_pair(&quote.token_pair)?;
+        let notional = quote.quantity * quote.price;
+        let spread_revenue = (quote.price - quote.mid_price).abs() * quote.quantity;
+
+        // Lock the client's side of the trade
+        let (collateral_token, collateral) = collateral_requirement(side, &base, &quote_token, quote.quantity, quote.price);
+        let locked = sqlx::query!(
+            r#"
+            UPDATE otc_client_collateral
+            SET available = available - $3, locked = locked + $3, updated_at = NOW()
+            WHERE client_id = $1 AND token = $2 AND available >= $3
+            "#,
+            quote.client_id,
+            collateral_token,
+            collateral
+        ).execute(&mut *tx).await?;
+        if locked.rows_affected() == 0 {
+            return Err(RevenueError::Validation(format!(
+                "Insufficient {} collateral to lock {}", collateral_token, collateral
+            )));
+        }
+
+        // Fill from desk inventory: the desk pays out one token now and is owed the other
+        let (outgoing, outgoing_amount, incoming, incoming_amount) = match side {
+            OtcSide::Buy => (base.as_str(), quote.quantity, quote_token.as_str(), notional),
+            OtcSide::Sell => (quote_token.as_str(), notional, base.as_str(), quote.quantity),
+        };
+        let filled = sqlx::query!(
+            r#"
+            UPDATE otc_inventory
+            SET balance = balance - $2, updated_at = NOW()
+            WHERE token = $1 AND balance >= $2
+            "#,
+            outgoing,
+            outgoing_amount
+        ).execute(&mut *tx).await?;
+        if filled.rows_affected() == 0 {
+            return Err(RevenueError::Enterprise(format!("Insufficient desk {} inventory", outgoing)));
+        }
+        sqlx::query!(
+            "UPDATE otc_inventory SET balance = balance + $2, updated_at = NOW() WHERE token = $1",
+            incoming,
+            incoming_amount
+        ).execute(&mut *tx).await?;
+
+        sqlx::query!("UPDATE otc_quotes SET status = 'accepted' WHERE id = $1", quote.id)
+            .execute(&mut *tx).await?;
+
+        let settles_on = settlement_date(now, self.config.settlement_days);
+        let trade = sqlx::query!(
+            r#"
+            INSERT INTO otc_trades
+            (quote_id, client_id, side, token_pair, quantity, price, notional, spread_revenue, executed_at, settlement_date)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id
+            "#,
+            quote.id,
+            quote.client_id,
+            side.as_str(),
+            quote.token_pair,
+            quote.quantity,
+            quote.price,
+            notional,
+            spread_revenue,
+            now,
+            settles_on
+        ).fetch_one(&mut *tx).await?;
+
+        // Keep the desk's order history and volume analytics in step
+        sqlx::query!(
+            r#"
+            INSERT INTO otc_trading_orders
+            (client_id, order_type, base_currency, quote_currency, amount, price, total_value,
+             commission_rate, commission_amount, status, execution_date, settlement_date, counterparty, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, 'executed', $10, $11, 'internal_inventory', $12)
+            "#,
+            quote.client_id,
+            side.as_str(),
+            base,
+            quote_token,
+            quote.quantity,
+            quote.price,
+            notional,
+            otc_spread(quote.quantity * quote.mid_price),
+            spread_revenue,
+            now,
+            settles_on,
+            serde_json::json!({ "quote_id": quote.id, "otc_trade_id": trade.id })
+        ).execute(&mut *tx).await?;
+
+        tx.commit().await?;
+
+        self.enterprise_manager.log_revenue_event(
+            quote.client_id,
+            None,
+            "otc_commission",
+            Some("OTCTrading"),
+            spread_revenue
+        ).await?;
+
+        self.rebalance_inventory(outgoing).await?;
+
+        tracing::info!("✅ OTC trade {} executed: {} {} {} at {}, settles {}",
+            trade.id, side.as_str(), quote.quantity, quote.token_pair, quote.price, settles_on.format("%Y-%m-%d"));
+
+        Ok(OtcTrade {
+            trade_id: trade.id,
+            quote_id: quote.id,
+            client_id: quote.client_id,
+            side,
+            token_pair: quote.token_pair,
+            quantity: quote.quantity,
+            price: quote.price,
+            notional,
+            spread_revenue,
+            executed_at: now,
+            settlement_date: settles_on,
+        })
+    }
+
+    // Queue a buy-back when a token falls below its floor, unless one is already pending
+    async fn rebalance_inventory(&self, token: &str) -> RevenueResult<bool> {
+        let inventory = sqlx::query!(
+            "SELECT balance, min_balance, target_balance FROM otc_inventory WHERE token = $1",
+            token
+        ).fetch_one(&self.enterprise_manager.db_pool).await?;
+
+        let Some(amount) = rebalance_amount(inventory.balance, inventory.min_balance, inventory.target_balance) else {
+            return Ok(false);
+        };
+
+        let queued = sqlx::query!(
+            r#"
+            INSERT INTO otc_rebalance_orders (token, amount)
+            SELECT $1, $2
+            WHERE NOT EXISTS (SELECT 1 FROM otc_rebalance_orders WHERE token = $1 AND status = 'pending')
+            "#,
+            token,
+            amount
+        ).execute(&self.enterprise_manager.db_pool).await?;
+
+        if queued.rows_affected() > 0 {
+            tracing::warn!("⚖️ OTC inventory {} at {} below floor {}, rebalancing {}", token, inventory.balance, inventory.min_balance, amount);
+        }
+        Ok(queued.rows_affected() > 0)
+    }
+
+    // Expire stale quotes, settle trades that reached their settlement date and top up
+    // inventory below its floor
+    pub async fn run_sweep(&self, now: DateTime<Utc>) -> RevenueResult<OtcSweepSummary> {
+        let pool = &self.enterprise_manager.db_pool;
+
+        let quotes_expired = sqlx::query!(
+            "UPDATE otc_quotes SET status = 'expired' WHERE status = 'open' AND valid_until < $1",
+            now
+        ).execute(pool).await?.rows_affected();
+        let mut summary = OtcSweepSummary { quotes_expired, ..Default::default() };
+
+        let due = sqlx::query!(
+            r#"
+            SELECT id, client_id, side, token_pair, quantity, notional
+            FROM otc_trades
+            WHERE status = 'executed' AND settlement_date <= $1
+            "#,
+            now
+        ).fetch_all(pool).await?;
+
+        for trade in due {
+            let Some(side) = OtcSide::parse(&trade.side) else { continue };
+            let (base, quote_token) = parse_token_pair(&trade.token_pair)?;
+            let (locked_token, locked_amount, received_token, received_amount) = match side {
+                OtcSide::Buy => (quote_token, trade.notional, base, trade.quantity),
+                OtcSide::Sell => (base, trade.quantity, quote_token, trade.notional),
+            };
+
+            let mut tx = pool.begin().await?;
+            let settled = sqlx::query!(
+                "UPDATE otc_trades SET status = 'settled', settled_at = $2 WHERE id = $1 AND status = 'executed'",
+                trade.id,
+                now
+            ).execute(&mut *tx).await?;
+            if settled.rows_affected() == 0 {
+                continue;
+            }
+            sqlx::query!(
+                r#"
+                UPDATE otc_client_collateral
+                SET locked = locked - $3, updated_at = NOW()
+                WHERE client_id = $1 AND token = $2
+                "#,
+                trade.client_id,
+                locked_token,
+                locked_amount
+            ).execute(&mut *tx).await?;
+            sqlx::query!(
+                r#"
+                INSERT INTO otc_client_collateral (client_id, token, available)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (client_id, token)
+                DO UPDATE SET available = otc_client_collateral.available + $3, updated_at = NOW()
+                "#,
+                trade.client_id,
+                received_token,
+                received_amount
+            ).execute(&mut *tx).await?;
+            sqlx::query!(
+                "UPDATE otc_trading_orders SET status = 'settled' WHERE metadata->>'otc_trade_id' = $1",
+                trade.id.to_string()
+            ).execute(&mut *tx).await?;
+            tx.commit().await?;
+
+            summary.trades_settled += 1;
+        }
+
+        let tokens = sqlx::query!("SELECT token FROM otc_inventory WHERE balance < min_balance")
+            .fetch_all(pool).await?;
+        for row in tokens {
+            if self.rebalance_inventory(&row.token).await? {
+                summary.rebalance_orders += 1;
+            }
+        }
+
+        if summary != OtcSweepSummary::default() {
+            tracing::info!("🔄 OTC sweep: {} quotes expired, {} trades settled, {} rebalance orders",
+                summary.quotes_expired, summary.trades_settled, summary.rebalance_orders);
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_token_pair() {
+        assert_eq!(parse_token_pair("nock/usdc").unwrap(), ("NOCK".to_string(), "USDC".to_string()));
+        assert!(parse_token_pair("NOCK").is_err());
+        assert!(parse_token_pair("NOCK/NOCK").is_err());
+        assert!(parse_token_pair("/USDC").is_err());
+    }
+
+    #[test]
+    fn test_quote_price_applies_spread_against_client() {
+        let mid = Decimal::new(2, 0);
+        let spread = otc_spread(Decimal::new(250_000, 0));
+        assert_eq!(spread, Decimal::new(50, 4));
+        assert_eq!(quote_price(OtcSide::Buy, mid, spread), Decimal::new(2010, 3));
+        assert_eq!(quote_price(OtcSide::Sell, mid, spread), Decimal::new(1990, 3));
+    }
+
+    #[test]
+    fn test_collateral_requirement() {
+        let quantity = Decimal::new(1_000, 0);
+        let price = Decimal::new(15, 1);
+        assert_eq!(collateral_requirement(OtcSide::Buy, "NOCK", "USDC", quantity, price), ("USDC", Decimal::new(1_500, 0)));
+        assert_eq!(collateral_requirement(OtcSide::Sell, "NOCK", "USDC", quantity, price), ("NOCK", quantity));
+    }
+
+    #[test]
+    fn test_settlement_skips_weekends() {
+        let thursday = Utc.with_ymd_and_hms(2024, 6, 13, 15, 0, 0).unwrap();
+        assert_eq!(settlement_date(thursday, 2), Utc.with_ymd_and_hms(2024, 6, 17, 15, 0, 0).unwrap());
+        let monday = Utc.with_ymd_and_hms(2024, 6, 17, 15, 0, 0).unwrap();
+        assert_eq!(settlement_date(monday, 2), Utc.with_ymd_and_hms(2024, 6, 19, 15, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_rebalance_amount() {
+        let (min, target) = (Decimal::new(100, 0), Decimal::new(500, 0));
+        assert_eq!(rebalance_amount(Decimal::new(150, 0), min, target), None);
+        assert_eq!(rebalance_amount(Decimal::new(40, 0), min, target), Some(Decimal::new(460, 0)));
+    }
+}
@@ -0,0 +1,535 @@
+// Enterprise SLA Tracking - Contract documents, KPI metrics and penalty credits
+// The daily SLA monitor compares measured KPIs with each contract's targets, credits breaches
+// against the next scheduled invoice and warns sales about contracts at risk
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::billing::{BillingEngine, ContractBillingPeriod};
+use crate::bridge::pool_revenue::billing_period_start;
+use crate::core::{RevenueError, RevenueResult};
+use super::{EnterpriseContract, EnterpriseContractTier, EnterpriseServiceType};
+
+// Trailing window over which violations put a contract at risk
+pub const AT_RISK_WINDOW_DAYS: i64 = 90;
+pub const AT_RISK_VIOLATION_COUNT: i64 = 3;
+
+// Credits worth this share of the monthly value within the window also put it at risk
+pub fn at_risk_credit_share() -> Decimal {
+    Decimal::new(50, 2)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaKpi {
+    Uptime,
+    ResponseTime,
+    MonthlyReportDelivery,
+}
+
+impl SlaKpi {
+    pub const ALL: [SlaKpi; 3] = [Self::Uptime, Self::ResponseTime, Self::MonthlyReportDelivery];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Uptime => "uptime",
+            Self::ResponseTime => "response_time",
+            Self::MonthlyReportDelivery => "monthly_report_delivery",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "uptime" => Some(Self::Uptime),
+            "response_time" => Some(Self::ResponseTime),
+            "monthly_report_delivery" => Some(Self::MonthlyReportDelivery),
+            _ => None,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Self::Uptime => "percent",
+            Self::ResponseTime => "minutes",
+            Self::MonthlyReportDelivery => "days_after_month_end",
+        }
+    }
+
+    // Uptime has to stay at or above target; response time and report delay at or below
+    pub fn is_met(&self, target: Decimal, actual: Decimal) -> bool {
+        match self {
+            Self::Uptime => actual >= target,
+            Self::ResponseTime | Self::MonthlyReportDelivery => actual <= target,
+        }
+    }
+
+    // Share of the contract's monthly value credited back for a breach, None when met
+    pub fn penalty_rate(&self, target: Decimal, actual: Decimal) -> Option<Decimal> {
+        if self.is_met(target, actual) {
+            return None;
+        }
+        Some(match self {
+            Self::Uptime => {
+                let shortfall = target - actual;
+                if shortfall < Decimal::new(5, 1) {
+                    Decimal::new(10, 2)
+                } else if shortfall < Decimal::ONE {
+                    Decimal::new(25, 2)
+                } else {
+                    Decimal::new(50, 2)
+                }
+            }
+            Self::ResponseTime if actual <= target * Decimal::TWO => Decimal::new(10, 2),
+            Self::ResponseTime => Decimal::new(25, 2),
+            Self::MonthlyReportDelivery => Decimal::new(5, 2),
+        })
+    }
+
+    pub fn penalty_terms(&self) -> &'static str {
+        match self {
+            Self::Uptime => "10% of monthly value below target, 25% at 0.5 points short, 50% at 1 point short",
+            Self::ResponseTime => "10% of monthly value above target, 25% above twice the target",
+            Self::MonthlyReportDelivery => "5% of monthly value when the report is late",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlaTerm {
+    pub kpi: SlaKpi,
+    pub target: Decimal,
+    pub unit: String,
+    pub penalty_terms: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceSla {
+    pub service: EnterpriseServiceType,
+    pub slas: Vec<SlaTerm>,
+}
+
+// Structured contract document generated when the contract is signed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractDocument {
+    pub contract_id: Uuid,
+    pub client_id: Uuid,
+    pub client_name: String,
+    pub contract_tier: EnterpriseContractTier,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub annual_value: Decimal,
+    pub payment_terms: String,
+    pub services: Vec<ServiceSla>,
+    pub invoice_schedule: Vec<ContractBillingPeriod>,
+    pub generated_at: DateTime<Utc>,
+}
+
+impl ContractDocument {
+    pub fn for_contract(
+        contract: &EnterpriseContract,
+        invoice_schedule: Vec<ContractBillingPeriod>,
+        generated_at: DateTime<Utc>,
+    ) -> Self {
+        let slas: Vec<SlaTerm> = SlaKpi::ALL.iter()
+            .map(|kpi| SlaTerm {
+                kpi: *kpi,
+                target: contract.contract_tier.sla_target(*kpi),
+                unit: kpi.unit().to_string(),
+                penalty_terms: kpi.penalty_terms().to_string(),
+            })
+            .collect();
+
+        Self {
+            contract_id: contract.id,
+            client_id: contract.client_id,
+            client_name: contract.client_name.clone(),
+            contract_tier: contract.contract_tier.clone(),
+            start_date: contract.start_date,
+            end_date: contract.end_date,
+            annual_value: contract.annual_value,
+            payment_terms: "quarterly".to_string(),
+            services: contract.services.iter()
+                .map(|service| ServiceSla { service: service.clone(), slas: slas.clone() })
+                .collect(),
+            invoice_schedule,
+            generated_at,
+        }
+    }
+}
+
+// A KPI breach for one calendar month
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlaBreach {
+    pub actual: Decimal,
+    pub period_start: DateTime<Utc>,
+    pub penalty_rate: Decimal,
+}
+
+// Checks a metric's latest measurement against its target. A monthly report that has not been
+// delivered by its due day counts as late by the days elapsed so far, even without a
+// measurement. Breaches are keyed by calendar month so each KPI is credited at most once a month.
+pub fn detect_breach(
+    kpi: SlaKpi,
+    target: Decimal,
+    actual: Option<Decimal>,
+    measured_at: Option<DateTime<Utc>>,
+    contract_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<SlaBreach> {
+    if let (Some(actual), Some(measured_at)) = (actual, measured_at) {
+        if let Some(penalty_rate) = kpi.penalty_rate(target, actual) {
+            return Some(SlaBreach { actual, period_start: billing_period_start(measured_at), penalty_rate });
+        }
+    }
+
+    if kpi == SlaKpi::MonthlyReportDelivery {
+        let month_start = billing_period_start(now);
+        let days_elapsed = Decimal::from(now.day());
+        let delivered_this_month = measured_at.is_some_and(|at| at >= month_start);
+        if contract_start < month_start && !delivered_this_month && days_elapsed > target {
+            return Some(SlaBreach {
+                actual: days_elapsed,
+                period_start: month_start,
+                penalty_rate: kpi.penalty_rate(target, days_elapsed)?,
+            });
+        }
+    }
+
+    None
+}
+
+pub fn contract_at_risk(recent_violations: i64, recent_credits: Decimal, monthly_value: Decimal) -> bool {
+    recent_violations >= AT_RISK_VIOLATION_COUNT
+        || (monthly_value > Decimal::ZERO && recent_credits >= monthly_value * at_risk_credit_share())
+}
+
+pub async fn create_sla_metrics(pool: &PgPool, contract_id: Uuid, tier: &EnterpriseContractTier) -> RevenueResult<()> {
+    for kpi in SlaKpi::ALL {
+        sqlx::query!(
+            r#"
+            INSERT INTO sla_metrics (contract_id, kpi, target, unit)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (contract_id, kpi) DO NOTHING
+            "#,
+            contract_id,
+            kpi.as_str(),
+            tier.sla_target(kpi),
+            kpi.unit()
+        ).execute(pool).await?;
+    }
+    Ok(())
+}
+
+// Message sent to sales when a contract's SLA record puts the account at risk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractRiskAlert {
+    pub contract_id: Uuid,
+    pub client_name: String,
+    pub recent_violations: i64,
+    pub recent_credits: Decimal,
+    pub monthly_value: Decimal,
+}
+
+impl ContractRiskAlert {
+    pub fn message(&self) -> String {
+        format!(
+            ":warning: Enterprise contract at risk: {} ({}) has {} SLA violations and ${} in penalty credits over the last {} days (monthly value ${})",
+            self.client_name, self.contract_id, self.recent_violations, self.recent_credits,
+            AT_RISK_WINDOW_DAYS, self.monthly_value
+        )
+    }
+}
+
+// Outbound channel for sales alerts
+#[async_trait::async_trait]
+pub trait SalesAlerter: Send + Sync {
+    async fn alert(&self, alert: &ContractRiskAlert) -> RevenueResult<()>;
+}
+
+// Logs instead of posting; used when no Slack webhook is configured
+#[derive(Debug, Default)]
+pub struct StubSalesAlerter;
+
+#[async_trait::async_trait]
+impl SalesAlerter for StubSalesAlerter {
+    async fn alert(&self, alert: &ContractRiskAlert) -> RevenueResult<()> {
+        tracing::info!("📣 [stub] Sales alert: {}", alert.message());
+        Ok(())
+    }
+}
+
+// Slack incoming webhook
+#[derive(Debug)]
+pub struct SlackWebhookAlerter {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+impl SlackWebhookAlerter {
+    pub fn new(webhook_url: String) -> Self {
+        Self { client: reqwest::Client::new(), webhook_url }
+    }
+}
+
+#[async_trait::async_trait]
+impl SalesAlerter for SlackWebhookAlerter {
+    async fn alert(&self, alert: &ContractRiskAlert) -> RevenueResult<()> {
+        let response = self.client
+            .post(&self.webhook_url)
+            .json(&serde_json::json!({ "text": alert.message() }))
+            .send()
+            .await
+            .map_err(|e| RevenueError::External(format!("Slack request failed: {}", e)))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(RevenueError::External(format!("Slack rejected alert: {}", response.status())))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SlaCheckSummary {
+    pub metrics_checked: usize,
+    pub violations: usize,
+    pub credits_applied: Decimal,
+    pub alerts_sent: usize,
+}
+
+// Daily SLA enforcement for active enterprise contracts
+pub struct SlaMonitor {
+    db_pool: PgPool,
+    billing_engine: Arc<BillingEngine>,
+    alerter: Arc<dyn SalesAlerter>,
+}
+
+impl std::fmt::Debug for SlaMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SlaMonitor").finish_non_exhaustive()
+    }
+}
+
+impl SlaMonitor {
+    pub fn new(db_pool: PgPool, billing_engine: Arc<BillingEngine>, alerter: Arc<dyn SalesAlerter>) -> Self {
+        Self { db_pool, billing_engine, alerter }
+    }
+
+    // Store the latest measured value of a KPI. Returns false when the contract does not
+    // track that KPI.
+    pub async fn record_measurement(
+        &self,
+        contract_id: Uuid,
+        kpi: SlaKpi,
+        actual: Decimal,
+        measured_at: DateTime<Utc>
+    ) -> RevenueResult<bool> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE sla_metrics
+            SET actual = $3, measured_at = $4, updated_at = NOW()
+            WHERE contract_id = $1 AND kpi = $2
+            "#,
+            contract_id,
+            kpi.as_str(),
+            actual,
+            measured_at
+        ).execute(&self.db_pool).await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn run(&self, now: DateTime<Utc>) -> RevenueResult<SlaCheckSummary> {
+        tracing::info!("📏 Checking enterprise SLA compliance");
+
+        let metrics = sqlx::query!(
+            r#"
+            SELECT m.id, m.contract_id, m.kpi, m.target, m.actual, m.measured_at,
+                   c.start_date, c.monthly_value
+            FROM sla_metrics m
+            JOIN enterprise_contracts c ON c.id = m.contract_id
+            WHERE c.status = 'active'
+            "#
+        ).fetch_all(&self.db_pool).await?;
+
+        let mut summary = SlaCheckSummary { metrics_checked: metrics.len(), ..Default::default() };
+        let mut breached_contracts = HashSet::new();
+
+        for metric in metrics {
+            let Some(kpi) = SlaKpi::parse(&metric.kpi) else {
+                tracing::warn!("⚠️ Unknown SLA KPI {} on metric {}", metric.kpi, metric.id);
+                continue;
+            };
+            let Some(breach) = detect_breach(kpi, metric.target, metric.actual, metric.measured_at, metric.start_date, now) else {
+                continue;
+            };
+            let credit = (metric.monthly_value * breach.penalty_rate).round_dp(2);
+
+            // One violation per KPI per month; later runs find it already recorded
+            let violation = sqlx::query!(
+                r#"
+                INSERT INTO sla_violations
+                (contract_id, sla_metric_id, kpi, target, actual, period_start, penalty_credit)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (sla_metric_id, period_start) DO NOTHING
+                RETURNING id
+                "#,
+                metric.contract_id,
+                metric.id,
+                kpi.as_str(),
+                metric.target,
+                breach.actual,
+                breach.period_start,
+                credit
+            ).fetch_optional(&self.db_pool).await?;
+
+            let Some(violation) = violation else { continue };
+
+            self.apply_penalty_credit(metric.contract_id, violation.id, kpi, &breach, credit).await?;
+            tracing::warn!("🚨 SLA violation on contract {}: {} at {} (target {}), credited ${}",
+                metric.contract_id, kpi.as_str(), breach.actual, metric.target, credit);
+
+            summary.violations += 1;
+            summary.credits_applied += credit;
+            breached_contracts.insert(metric.contract_id);
+        }
+
+        for contract_id in breached_contracts {
+            if self.alert_if_at_risk(contract_id, now).await? {
+                summary.alerts_sent += 1;
+            }
+        }
+
+        tracing::info!("✅ SLA check complete: {} violations, ${} credited", summary.violations, summary.credits_applied);
+        Ok(summary)
+    }
+
+    async fn apply_penalty_credit(
+        &self,
+        contract_id: Uuid,
+        violation_id: Uuid,
+        kpi: SlaKpi,
+        breach: &SlaBreach,
+        credit: Decimal
+    ) -> RevenueResult<()> {
+        sqlx::query!(
+            "UPDATE enterprise_contracts SET service_credits = service_credits + $1, updated_at = NOW() WHERE id = $2",
+            credit,
+            contract_id
+        ).execute(&self.db_pool).await?;
+
+        let credited_invoice = self.billing_engine.apply_contract_credit(
+            contract_id,
+            credit,
+            format!("SLA credit: {} {}", kpi.as_str(), breach.period_start.format("%Y-%m")),
+            serde_json::json!({ "line_type": "SlaCredit", "sla_violation_id": violation_id })
+        ).await?;
+
+        match credited_invoice {
+            Some(invoice_id) => {
+                sqlx::query!(
+                    "UPDATE sla_violations SET credited_invoice_id = $1 WHERE id = $2",
+                    invoice_id,
+                    violation_id
+                ).execute(&self.db_pool).await?;
+            }
+            None => tracing::warn!("⚠️ No scheduled invoice left on contract {} for ${} SLA credit", contract_id, credit),
+        }
+
+        Ok(())
+    }
+
+    async fn alert_if_at_risk(&self, contract_id: Uuid, now: DateTime<Utc>) -> RevenueResult<bool> {
+        let record = sqlx::query!(
+            r#"
+            SELECT c.client_name, c.monthly_value,
+                   COUNT(v.id) AS "recent_violations!",
+                   COALESCE(SUM(v.penalty_credit), 0) AS "recent_credits!"
+            FROM enterprise_contracts c
+            LEFT JOIN sla_violations v ON v.contract_id = c.id AND v.detected_at >= $2
+            WHERE c.id = $1
+            GROUP BY c.id
+            "#,
+            contract_id,
+            now - Duration::days(AT_RISK_WINDOW_DAYS)
+        ).fetch_one(&self.db_pool).await?;
+
+        if !contract_at_risk(record.recent_violations, record.recent_credits, record.monthly_value) {
+            return Ok(false);
+        }
+
+        let alert = ContractRiskAlert {
+            contract_id,
+            client_name: record.client_name,
+            recent_violations: record.recent_violations,
+            recent_credits: record.recent_credits,
+            monthly_value: record.monthly_value,
+        };
+        if let Err(e) = self.alerter.alert(&alert).await {
+            tracing::error!("❌ Failed to alert sales about contract {}: {}", contract_id, e);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_penalty_rate_scales_with_shortfall() {
+        let uptime = Decimal::new(9990, 2);
+        assert_eq!(SlaKpi::Uptime.penalty_rate(uptime, Decimal::new(9995, 2)), None);
+        assert_eq!(SlaKpi::Uptime.penalty_rate(uptime, Decimal::new(9980, 2)), Some(Decimal::new(10, 2)));
+        assert_eq!(SlaKpi::Uptime.penalty_rate(uptime, Decimal::new(9930, 2)), Some(Decimal::new(25, 2)));
+        assert_eq!(SlaKpi::Uptime.penalty_rate(uptime, Decimal::new(9850, 2)), Some(Decimal::new(50, 2)));
+
+        let response = Decimal::new(60, 0);
+        assert_eq!(SlaKpi::ResponseTime.penalty_rate(response, Decimal::new(60, 0)), None);
+        assert_eq!(SlaKpi::ResponseTime.penalty_rate(response, Decimal::new(90, 0)), Some(Decimal::new(10, 2)));
+        assert_eq!(SlaKpi::ResponseTime.penalty_rate(response, Decimal::new(150, 0)), Some(Decimal::new(25, 2)));
+    }
+
+    #[test]
+    fn test_measured_breach_is_keyed_by_month() {
+        let breach = detect_breach(
+            SlaKpi::Uptime, Decimal::new(9990, 2), Some(Decimal::new(9970, 2)), Some(at(14)), at(1) - Duration::days(60), at(20)
+        ).unwrap();
+        assert_eq!(breach.period_start, Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap());
+        assert_eq!(breach.penalty_rate, Decimal::new(10, 2));
+    }
+
+    #[test]
+    fn test_missing_monthly_report_is_late_after_due_day() {
+        let target = Decimal::new(5, 0);
+        let contract_start = at(1) - Duration::days(60);
+        let last_delivery = Some(at(1) - Duration::days(27));
+
+        assert_eq!(detect_breach(SlaKpi::MonthlyReportDelivery, target, Some(Decimal::new(4, 0)), last_delivery, contract_start, at(5)), None);
+        let breach = detect_breach(SlaKpi::MonthlyReportDelivery, target, Some(Decimal::new(4, 0)), last_delivery, contract_start, at(8)).unwrap();
+        assert_eq!(breach.actual, Decimal::new(8, 0));
+
+        // Delivered on time this month, or the contract only started this month
+        assert_eq!(detect_breach(SlaKpi::MonthlyReportDelivery, target, Some(Decimal::new(3, 0)), Some(at(3)), contract_start, at(8)), None);
+        assert_eq!(detect_breach(SlaKpi::MonthlyReportDelivery, target, None, None, at(2), at(8)), None);
+    }
+
+    #[test]
+    fn test_contract_at_risk() {
+        let monthly_value = Decimal::new(10_000, 0);
+        assert!(!contract_at_risk(1, Decimal::new(1_000, 0), monthly_value));
+        assert!(contract_at_risk(3, Decimal::new(1_000, 0), monthly_value));
+        assert!(contract_at_risk(2, Decimal::new(5_000, 0), monthly_value));
+    }
+}
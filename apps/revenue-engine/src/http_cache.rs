@@ -0,0 +1,107 @@
+// HTTP Response Caching
+// Per-endpoint Cache-Control, ETag revalidation and response compression for the API router
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ring::digest::{digest, SHA256};
+use tower_http::compression::{CompressionLayer, CompressionLevel};
+use tracing::error;
+
+// Upper bound on the body buffered to compute an ETag
+pub const MAX_ETAG_BODY_BYTES: usize = 8 * 1024 * 1024;
+
+pub const NO_STORE: &str = "no-store";
+
+/// Cache-Control policy for a request path; None leaves the response untouched
+pub fn cache_control_for(path: &str) -> Option<&'static str> {
+    match path {
+        "/health" => Some(NO_STORE),
+        "/api/v1/revenue/dashboard" => Some("private, max-age=5"),
+        p if p == "/api/v1/analytics" || p.starts_with("/api/v1/analytics/") => Some("private, max-age=30"),
+        _ => None,
+    }
+}
+
+/// Weak ETag over the uncompressed body, so every encoding of a response shares one tag
+pub fn etag_for(body: &[u8]) -> HeaderValue {
+    let hash = digest(&SHA256, body);
+    let hex: String = hash.as_ref()[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    HeaderValue::from_str(&format!("W/\"{}\"", hex)).expect("hex etag is a valid header value")
+}
+
+/// Weak comparison of an If-None-Match header against a response ETag (RFC 9110 §13.1.2)
+pub fn if_none_match_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let Ok(candidates) = if_none_match.to_str() else {
+        return false;
+    };
+    let Ok(etag) = etag.to_str() else {
+        return false;
+    };
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+
+    candidates.split(',').any(|candidate| {
+        candidate.trim() == "*" || opaque(candidate) == opaque(etag)
+    })
+}
+
+/// Middleware adding Cache-Control and answering If-None-Match with 304 Not Modified.
+/// Must sit inside the compression layer so tags are computed on the identity body.
+pub async fn cache_headers(request: Request, next: Next) -> Response {
+    let is_get = request.method() == Method::GET;
+    let cache_control = cache_control_for(request.uri().path());
+    let if_none_match = request.headers().get(header::IF_NONE_MATCH).cloned();
+
+    let mut response = next.run(request).await;
+    if !is_get {
+        return response;
+    }
+    if let Some(policy) = cache_control {
+        response.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static(policy));
+    }
+    if cache_control == Some(NO_STORE) || response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_ETAG_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("❌ Failed to buffer response for ETag: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = etag_for(&bytes);
+    parts.headers.insert(header::ETAG, etag.clone());
+
+    if if_none_match.is_some_and(|value| if_none_match_matches(&value, &etag)) {
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.remove(header::CONTENT_TYPE);
+        return Response::from_parts(parts, Body::empty());
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Brotli when the client accepts it, gzip otherwise
+pub fn compression_layer(level: CompressionLevel) -> CompressionLayer {
+    CompressionLayer::new()
+        .br(true)
+        .gzip(true)
+        .quality(level)
+}
+
+/// Reads RESPONSE_COMPRESSION_QUALITY (algorithm-specific, e.g. 0-11 for Brotli, 1-9 for gzip)
+pub fn compression_level_from_env() -> CompressionLevel {
+    std::env::var("RESPONSE_COMPRESSION_QUALITY")
+        .ok()
+        .and_then(|value| value.parse::<i32>().ok())
+        .map(CompressionLevel::Precise)
+        .unwrap_or_default()
+}
@@ -7,14 +7,38 @@ pub mod billing;
 pub mod analytics;
 pub mod bridge;
 pub mod enterprise;
+pub mod stripe_export;
+pub mod http_cache;
 
 // Core revenue engine components
-pub use core::{RevenueEngine, RevenueConfig, RevenueError, RevenueResult};
+pub use core::{RevenueEngine, RevenueConfig, RevenueError, RevenueRecord, RevenueResult};
 pub use subscription::{SubscriptionManager, SubscriptionTier, SubscriptionService};
+pub use subscription::feature_flags::{EnabledFeatureFlags, FeatureFlag, FeatureFlagService};
 pub use billing::{BillingEngine, PaymentProcessor, InvoiceManager};
-pub use analytics::{RevenueAnalytics, RevenueForecasting, RevenueOptimizer};
+pub use billing::fx::{Currency, FxRateProvider, CachedFxRateProvider, FxConversion};
+pub use billing::tax::{BillingAddress, TaxCalculator, TaxLineItem, TaxTreatment, VatIdValidator, ViesClient, VatReport};
+pub use billing::ab_test::{AbTestManager, AbTestEvent, AbTestEventType, Experiment, ExperimentVariant, ExperimentStats};
+pub use billing::webhook::{WebhookDispatcher, WebhookEndpoint, WebhookEvent, WebhookPayload, DeadLetter};
+pub use analytics::{
+    RevenueAnalytics, RevenueForecasting, RevenueOptimizer,
+    CohortRetentionAnalysis, CohortRetentionMatrix, RetentionRow, YearMonth,
+};
+pub use analytics::clv::{BgNbdParams, ClvAnalysis, ClvBucket, ClvPrediction, CLV_HORIZON_MONTHS};
+pub use analytics::scenarios::{
+    ParameterDistribution, RevenueDriver, RevenueScenario, ScenarioAnalysisResult, ScenarioComparison, ScenarioOutcome,
+};
 pub use bridge::{BridgeRevenueManager, TransactionFeeProcessor, LiquidityRewardManager};
-pub use enterprise::{EnterpriseRevenueManager, CustodyService, OTCTradingDesk};
+pub use bridge::pool_revenue::{MiningPoolRevenueCollector, PoolRevenueSummary, PoolSnapshot};
+pub use enterprise::{
+    EnterpriseRevenueManager, CustodyService, CustodyAccount, OTCTradingDesk,
+    EnterpriseContractTier, EnterpriseServiceType, CustodyStorageType, SecurityLevel,
+};
+pub use enterprise::custody::{CustodyKey, CustodianShare, PendingWithdrawal, SignedWithdrawal};
+pub use enterprise::otc::{OtcQuoteDesk, OtcDeskConfig, OtcSide, RequestForQuote, Quote, AcceptQuote, OtcTrade};
+pub use enterprise::sla::{ContractDocument, SlaKpi, SlaMonitor, SlaCheckSummary};
+pub use stripe_export::{
+    StripeInvoiceExporter, StripeInvoicePayload, StripeWebhookReceiver, StripeWebhookOutcome, ProcessedWebhooks,
+};
 
 // Revenue stream types
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -107,6 +131,28 @@ pub struct RevenueMetrics {
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
+impl RevenueMetrics {
+    // Adds revenue from one record to the monthly total and its stream's category
+    pub fn record_revenue(&mut self, stream_type: &str, amount: rust_decimal::Decimal) {
+        self.total_monthly_revenue += amount;
+
+        match stream_type {
+            "premium_analytics" | "api_licensing" => {
+                self.subscription_revenue += amount;
+            },
+            "bridge_transaction" | "trading_fees" | "mining_pool" => {
+                self.transaction_revenue += amount;
+            },
+            "enterprise_services" | "custom_solutions" | "performance_optimization" => {
+                self.enterprise_revenue += amount;
+            },
+            _ => {}
+        }
+
+        self.timestamp = chrono::Utc::now();
+    }
+}
+
 // Revenue optimization algorithms
 #[derive(Debug, Clone)]
 pub struct RevenueOptimization {
@@ -3,6 +3,7 @@
 
 use std::sync::Arc;
 use std::net::SocketAddr;
+use std::num::NonZeroU32;
 use axum::{
     routing::{get, post, put, delete},
     Router, Extension, Json,
@@ -14,47 +15,64 @@ use tower::ServiceBuilder;
 use tower_http::{
     cors::{CorsLayer, Any},
     trace::TraceLayer,
-    compression::CompressionLayer,
 };
 use tokio::signal;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use tracing::{info, error};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use rust_decimal::Decimal;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+use utoipa_swagger_ui::SwaggerUi;
 
 use revenue_engine::{
     RevenueEngine, RevenueConfig, RevenueResult, RevenueError,
     SubscriptionTier, SubscriptionService, CreateSubscriptionRequest, UpgradeSubscriptionRequest,
-    BillingEngine, ProcessPaymentRequest,
+    BillingEngine, ProcessPaymentRequest, Currency,
     AnalyticsRevenueManager, AnalyticsTier,
     BridgeRevenueManager, BridgeTransactionType,
-    EnterpriseRevenueManager, EnterpriseContractTier, EnterpriseServiceType,
+    EnterpriseRevenueManager, EnterpriseContractTier, EnterpriseServiceType, SlaKpi,
+    OtcSide, RequestForQuote, AcceptQuote, CustodyStorageType, SecurityLevel,
+    StripeInvoiceExporter, StripeInvoicePayload, StripeWebhookReceiver,
+    CohortRetentionAnalysis, CohortRetentionMatrix, YearMonth, BillingAddress, RevenueScenario, ClvAnalysis, ClvBucket,
     initialize_revenue_engine,
 };
+use revenue_engine::subscription::feature_flags::inject_feature_flags;
+use revenue_engine::http_cache::{cache_headers, compression_layer, compression_level_from_env};
 
 // API request/response types
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateSubscriptionApiRequest {
     tier: String,
     billing_cycle: String,
     trial_days: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct UpgradeSubscriptionApiRequest {
     new_tier: String,
     new_billing_cycle: Option<String>,
     prorate: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct PauseSubscriptionApiRequest {
+    // Resume automatically on this date; omitted pauses until resumed, at most 90 days
+    resume_date: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 struct ProcessPaymentApiRequest {
     payment_method: String,
     amount: Option<Decimal>,
+    currency: Option<Currency>,
     auto_confirm: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateEnterpriseContractRequest {
     client_name: String,
     contract_tier: String,
@@ -63,7 +81,54 @@ struct CreateEnterpriseContractRequest {
     duration_months: i32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct RecordSlaMeasurementRequest {
+    // uptime, response_time or monthly_report_delivery
+    kpi: String,
+    actual: Decimal,
+    measured_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct RequestForQuoteApiRequest {
+    client_id: Uuid,
+    // buy or sell, from the client's side
+    side: String,
+    quantity: Decimal,
+    // BASE/QUOTE, e.g. NOCK/USDC
+    token_pair: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SetupCustodyApiRequest {
+    client_id: Uuid,
+    asset_type: String,
+    custody_fee_rate: Decimal,
+    insurance_coverage: Decimal,
+    // cold_storage, hot_wallet, multi_sig or hsm
+    storage_type: String,
+    // standard, enhanced, institutional or military
+    security_level: String,
+    custodians: Vec<Uuid>,
+    // Shares needed to reconstruct the key
+    threshold: u8,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct InitiateWithdrawalApiRequest {
+    client_id: Uuid,
+    destination: String,
+    amount: Decimal,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ApproveWithdrawalApiRequest {
+    custodian_id: Uuid,
+    // Hex-encoded key share issued to the custodian
+    share_fragment: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 struct ProcessBridgeTransactionRequest {
     transaction_hash: String,
     transaction_type: String,
@@ -75,13 +140,65 @@ struct ProcessBridgeTransactionRequest {
     to_address: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateAnalyticsSubscriptionRequest {
     tier: String,
     duration_months: i32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct SetFeatureFlagApiRequest {
+    // basic, professional, enterprise or custom; the lowest tier that gets the flag
+    min_tier: String,
+    enabled: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct SetBillingAddressApiRequest {
+    name: Option<String>,
+    line1: String,
+    line2: Option<String>,
+    city: String,
+    postal_code: Option<String>,
+    // ISO 3166-1 alpha-2
+    country_code: String,
+    // EU VAT ID with its country prefix; verified businesses are invoiced under the reverse charge
+    vat_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+struct ScenarioAnalysisApiRequest {
+    scenarios: Vec<RevenueScenario>,
+    // Trials per scenario, 10,000 by default
+    simulations: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct VatReportQuery {
+    year: i32,
+    /// Calendar quarter, 1-4
+    quarter: u32,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CohortRetentionQuery {
+    /// First cohort month, "YYYY-MM"
+    cohort: String,
+    /// Last cohort month, "YYYY-MM"; defaults to `cohort`
+    until: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[aliases(
+    ApiResponseJson = ApiResponse<serde_json::Value>,
+    ApiResponseJsonList = ApiResponse<Vec<serde_json::Value>>,
+    ApiResponseBool = ApiResponse<bool>,
+    ApiResponseCohortRetention = ApiResponse<CohortRetentionMatrix>,
+    ApiResponseClvDistribution = ApiResponse<Vec<ClvBucket>>,
+    ApiResponseStripeInvoice = ApiResponse<StripeInvoicePayload>,
+)]
 struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
@@ -109,6 +226,63 @@ impl<T> ApiResponse<T> {
     }
 }
 
+// OpenAPI document served at /api/openapi.json
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Revenue Engine API"),
+    paths(
+        health_check,
+        revenue_dashboard, revenue_analytics, revenue_forecasting, revenue_scenarios, revenue_progress,
+        create_subscription, get_subscription, upgrade_subscription, cancel_subscription, get_user_subscriptions,
+        pause_subscription, resume_subscription,
+        list_invoices, get_invoice, export_invoice_to_stripe, publish_invoice_pdf, set_billing_address, stripe_webhook, receive_stripe_webhook, process_payment, billing_analytics,
+        create_analytics_subscription, get_analytics_subscription, track_analytics_usage, cohort_retention, clv_distribution,
+        process_bridge_transaction, confirm_bridge_transaction, bridge_analytics, add_liquidity_provision,
+        create_enterprise_contract, get_enterprise_contract, record_sla_measurement, request_otc_quote, accept_otc_quote, setup_custody_service,
+        initiate_custody_withdrawal, approve_custody_withdrawal, execute_custody_withdrawal, enterprise_analytics,
+        process_billing_cycles, optimize_revenue, replay_webhook, list_feature_flags, set_feature_flag, vat_report,
+    ),
+    components(schemas(
+        CreateSubscriptionApiRequest, UpgradeSubscriptionApiRequest, PauseSubscriptionApiRequest, ProcessPaymentApiRequest,
+        CreateEnterpriseContractRequest, RecordSlaMeasurementRequest, RequestForQuoteApiRequest,
+        SetupCustodyApiRequest, InitiateWithdrawalApiRequest, ApproveWithdrawalApiRequest, ProcessBridgeTransactionRequest, CreateAnalyticsSubscriptionRequest,
+        SetFeatureFlagApiRequest, SetBillingAddressApiRequest, ScenarioAnalysisApiRequest,
+        RevenueScenario, revenue_engine::RevenueDriver, revenue_engine::ParameterDistribution,
+        ApiResponseJson, ApiResponseJsonList, ApiResponseBool, ApiResponseCohortRetention, ApiResponseClvDistribution, ApiResponseStripeInvoice,
+        CohortRetentionMatrix, ClvBucket, Currency,
+        StripeInvoicePayload,
+        revenue_engine::stripe_export::StripeInvoiceLine,
+        revenue_engine::stripe_export::StripeTaxAmount,
+        revenue_engine::stripe_export::StripeTaxRateData,
+    )),
+    modifiers(&ApiKeySecurity),
+    security(("api_key" = [])),
+    tags(
+        (name = "health", description = "Service health"),
+        (name = "revenue", description = "Revenue dashboard, analytics and forecasting"),
+        (name = "subscriptions", description = "Subscription management"),
+        (name = "billing", description = "Invoices, payments and Stripe integration"),
+        (name = "analytics", description = "Analytics subscriptions, cohort and lifetime value reporting"),
+        (name = "bridge", description = "Bridge transaction and liquidity revenue"),
+        (name = "enterprise", description = "Enterprise contracts, OTC and custody"),
+        (name = "admin", description = "Administrative operations"),
+    ),
+)]
+struct ApiDoc;
+
+// API key passed in the X-API-Key header
+struct ApiKeySecurity;
+
+impl Modify for ApiKeySecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+    }
+}
+
 // Application state
 #[derive(Clone)]
 struct AppState {
@@ -118,8 +292,17 @@ struct AppState {
     analytics_manager: Arc<AnalyticsRevenueManager>,
     bridge_manager: Arc<BridgeRevenueManager>,
     enterprise_manager: Arc<EnterpriseRevenueManager>,
+    stripe_exporter: Arc<StripeInvoiceExporter>,
+    stripe_webhooks: Arc<StripeWebhookReceiver>,
+    stripe_webhook_limiter: Arc<DefaultDirectRateLimiter>,
 }
 
+// Monte Carlo trials per scenario when a request does not say
+const DEFAULT_SCENARIO_SIMULATIONS: u32 = 10_000;
+
+// Stripe webhook deliveries accepted per second before callers get 429s
+const DEFAULT_STRIPE_WEBHOOK_RATE_LIMIT: u32 = 25;
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
@@ -139,6 +322,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let analytics_manager = revenue_engine.revenue_analytics.clone();
     let bridge_manager = revenue_engine.bridge_revenue.clone();
     let enterprise_manager = revenue_engine.enterprise_revenue.clone();
+    let stripe_exporter = Arc::new(StripeInvoiceExporter::new(billing_engine.clone()));
+    let stripe_webhooks = Arc::new(StripeWebhookReceiver::new(
+        billing_engine.clone(),
+        revenue_engine.subscription_manager.clone(),
+        revenue_engine.churn_predictor.clone(),
+        revenue_engine.db_pool.clone(),
+        revenue_engine.config.stripe_webhook_secret.clone(),
+    ));
+    let stripe_webhook_rate_limit = std::env::var("STRIPE_WEBHOOK_RATE_LIMIT")
+        .ok()
+        .and_then(|value| value.parse::<u32>().ok())
+        .and_then(NonZeroU32::new)
+        .unwrap_or(NonZeroU32::new(DEFAULT_STRIPE_WEBHOOK_RATE_LIMIT).unwrap());
+    let stripe_webhook_limiter = Arc::new(RateLimiter::direct(Quota::per_second(stripe_webhook_rate_limit)));
 
     // Create application state
     let state = AppState {
@@ -148,6 +345,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         analytics_manager,
         bridge_manager,
         enterprise_manager,
+        stripe_exporter,
+        stripe_webhooks,
+        stripe_webhook_limiter,
     };
 
     // Build application router
@@ -168,6 +368,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 fn create_router(state: AppState) -> Router {
+    let feature_flags = state.revenue_engine.feature_flags.clone();
+
     Router::new()
         // Health check
         .route("/health", get(health_check))
@@ -176,6 +378,7 @@ fn create_router(state: AppState) -> Router {
         .route("/api/v1/revenue/dashboard", get(revenue_dashboard))
         .route("/api/v1/revenue/analytics", get(revenue_analytics))
         .route("/api/v1/revenue/forecasting", get(revenue_forecasting))
+        .route("/api/v1/revenue/forecasting/scenarios", post(revenue_scenarios))
         .route("/api/v1/revenue/progress", get(revenue_progress))
         
         // Subscription management
@@ -183,11 +386,18 @@ fn create_router(state: AppState) -> Router {
         .route("/api/v1/subscriptions/:id", get(get_subscription))
         .route("/api/v1/subscriptions/:id/upgrade", put(upgrade_subscription))
         .route("/api/v1/subscriptions/:id/cancel", delete(cancel_subscription))
+        .route("/api/v1/subscriptions/:id/pause", put(pause_subscription))
+        .route("/api/v1/subscriptions/:id/resume", put(resume_subscription))
         .route("/api/v1/subscriptions/user/:user_id", get(get_user_subscriptions))
         
         // Billing and payments
         .route("/api/v1/billing/invoices", get(list_invoices))
         .route("/api/v1/billing/invoices/:id", get(get_invoice))
+        .route("/api/v1/billing/invoices/:id/stripe-export", get(export_invoice_to_stripe))
+        .route("/api/v1/billing/invoices/:id/pdf", post(publish_invoice_pdf))
+        .route("/api/v1/billing/addresses/:user_id", put(set_billing_address))
+        .route("/api/v1/billing/webhooks/stripe", post(stripe_webhook))
+        .route("/webhooks/stripe", post(receive_stripe_webhook))
         .route("/api/v1/billing/payments", post(process_payment))
         .route("/api/v1/billing/analytics", get(billing_analytics))
         
@@ -195,6 +405,8 @@ fn create_router(state: AppState) -> Router {
         .route("/api/v1/analytics/subscriptions", post(create_analytics_subscription))
         .route("/api/v1/analytics/subscriptions/user/:user_id", get(get_analytics_subscription))
         .route("/api/v1/analytics/usage", post(track_analytics_usage))
+        .route("/api/v1/analytics/cohort-retention", get(cohort_retention))
+        .route("/api/v1/analytics/clv-distribution", get(clv_distribution))
         
         // Bridge revenue
         .route("/api/v1/bridge/transactions", post(process_bridge_transaction))
@@ -205,24 +417,47 @@ fn create_router(state: AppState) -> Router {
         // Enterprise services
         .route("/api/v1/enterprise/contracts", post(create_enterprise_contract))
         .route("/api/v1/enterprise/contracts/:id", get(get_enterprise_contract))
-        .route("/api/v1/enterprise/otc", post(process_otc_order))
+        .route("/api/v1/enterprise/contracts/:id/sla-measurements", post(record_sla_measurement))
+        .route("/api/v1/enterprise/otc/rfq", post(request_otc_quote))
+        .route("/api/v1/enterprise/otc/quotes/:id/accept", post(accept_otc_quote))
         .route("/api/v1/enterprise/custody", post(setup_custody_service))
+        .route("/api/v1/enterprise/custody/withdrawals", post(initiate_custody_withdrawal))
+        .route("/api/v1/enterprise/custody/withdrawals/:id/approvals", post(approve_custody_withdrawal))
+        .route("/api/v1/enterprise/custody/withdrawals/:id/execute", post(execute_custody_withdrawal))
         .route("/api/v1/enterprise/analytics", get(enterprise_analytics))
         
         // Admin endpoints
         .route("/api/v1/admin/billing/process", post(process_billing_cycles))
         .route("/api/v1/admin/revenue/optimize", post(optimize_revenue))
+        .route("/api/v1/admin/webhooks/replay/:id", post(replay_webhook))
+        .route("/api/v1/admin/feature-flags", get(list_feature_flags))
+        .route("/api/v1/admin/feature-flags/:flag_name", put(set_feature_flag))
+        .route("/api/v1/admin/vat-reports", get(vat_report))
+
+        // API documentation
+        .merge(SwaggerUi::new("/docs").url("/api/openapi.json", ApiDoc::openapi()))
         
         .layer(
             ServiceBuilder::new()
                 .layer(TraceLayer::new_for_http())
-                .layer(CompressionLayer::new())
+                .layer(compression_layer(compression_level_from_env()))
+                .layer(axum::middleware::from_fn(cache_headers))
                 .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
+                .layer(axum::middleware::from_fn_with_state(feature_flags, inject_feature_flags))
                 .layer(Extension(state))
         )
 }
 
 // Health check endpoint
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (status = 200, description = "Service health status", body = ApiResponseJson),
+    ),
+    security(()),
+)]
 async fn health_check() -> ResponseJson<ApiResponse<serde_json::Value>> {
     ResponseJson(ApiResponse::success(serde_json::json!({
         "status": "healthy",
@@ -233,6 +468,15 @@ async fn health_check() -> ResponseJson<ApiResponse<serde_json::Value>> {
 }
 
 // Revenue dashboard
+#[utoipa::path(
+    get,
+    path = "/api/v1/revenue/dashboard",
+    tag = "revenue",
+    responses(
+        (status = 200, description = "Current revenue metrics and target progress", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn revenue_dashboard(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -264,6 +508,15 @@ async fn revenue_dashboard(
 }
 
 // Revenue analytics
+#[utoipa::path(
+    get,
+    path = "/api/v1/revenue/analytics",
+    tag = "revenue",
+    responses(
+        (status = 200, description = "Revenue analytics", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn revenue_analytics(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -277,6 +530,15 @@ async fn revenue_analytics(
 }
 
 // Revenue forecasting
+#[utoipa::path(
+    get,
+    path = "/api/v1/revenue/forecasting",
+    tag = "revenue",
+    responses(
+        (status = 200, description = "30-day revenue forecast", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn revenue_forecasting(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -289,7 +551,56 @@ async fn revenue_forecasting(
     }
 }
 
+// Monte Carlo comparison of revenue scenarios against the monthly target
+#[utoipa::path(
+    post,
+    path = "/api/v1/revenue/forecasting/scenarios",
+    tag = "revenue",
+    request_body = ScenarioAnalysisApiRequest,
+    responses(
+        (status = 200, description = "P10/P50/P90 monthly revenue and probability of hitting the target per scenario", body = ApiResponseJson),
+        (status = 400, description = "Invalid scenario or simulation count"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn revenue_scenarios(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<ScenarioAnalysisApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    let forecasting = state.revenue_engine.revenue_forecasting.clone();
+    let simulations = request.simulations.unwrap_or(DEFAULT_SCENARIO_SIMULATIONS);
+
+    // Simulation is CPU-bound; keep it off the async workers
+    let result = tokio::task::spawn_blocking(move || forecasting.scenario_analysis(request.scenarios, simulations))
+        .await
+        .map_err(|e| {
+            error!("Scenario analysis task failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    match result {
+        Ok(analysis) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(analysis)))),
+        Err(RevenueError::Validation(e)) => {
+            error!("Invalid scenario analysis request: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(e) => {
+            error!("Failed to run scenario analysis: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 // Revenue progress
+#[utoipa::path(
+    get,
+    path = "/api/v1/revenue/progress",
+    tag = "revenue",
+    responses(
+        (status = 200, description = "Progress towards the monthly revenue target", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn revenue_progress(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -303,6 +614,16 @@ async fn revenue_progress(
 }
 
 // Create subscription
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions",
+    tag = "subscriptions",
+    request_body = CreateSubscriptionApiRequest,
+    responses(
+        (status = 200, description = "Created subscription", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn create_subscription(
     Path(user_id): Path<Uuid>,
     Extension(state): Extension<AppState>,
@@ -348,6 +669,18 @@ async fn create_subscription(
 }
 
 // Get subscription
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscriptions/{id}",
+    tag = "subscriptions",
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Subscription details", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn get_subscription(
     Path(subscription_id): Path<Uuid>,
     Extension(state): Extension<AppState>
@@ -362,6 +695,19 @@ async fn get_subscription(
 }
 
 // Upgrade subscription
+#[utoipa::path(
+    put,
+    path = "/api/v1/subscriptions/{id}/upgrade",
+    tag = "subscriptions",
+    request_body = UpgradeSubscriptionApiRequest,
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Upgraded subscription", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn upgrade_subscription(
     Path(subscription_id): Path<Uuid>,
     Extension(state): Extension<AppState>,
@@ -401,6 +747,18 @@ async fn upgrade_subscription(
 }
 
 // Cancel subscription
+#[utoipa::path(
+    delete,
+    path = "/api/v1/subscriptions/{id}/cancel",
+    tag = "subscriptions",
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Subscription cancelled", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn cancel_subscription(
     Path(subscription_id): Path<Uuid>,
     Extension(state): Extension<AppState>
@@ -414,7 +772,73 @@ async fn cancel_subscription(
     }
 }
 
+// Pause subscription billing
+#[utoipa::path(
+    put,
+    path = "/api/v1/subscriptions/{id}/pause",
+    tag = "subscriptions",
+    request_body = PauseSubscriptionApiRequest,
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Paused subscription", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn pause_subscription(
+    Path(subscription_id): Path<Uuid>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<PauseSubscriptionApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.subscription_service.pause_subscription(subscription_id, request.resume_date).await {
+        Ok(subscription) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(subscription)))),
+        Err(e) => {
+            error!("Failed to pause subscription: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Resume a paused subscription
+#[utoipa::path(
+    put,
+    path = "/api/v1/subscriptions/{id}/resume",
+    tag = "subscriptions",
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Resumed subscription with its adjusted billing date", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn resume_subscription(
+    Path(subscription_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.subscription_service.resume_subscription(subscription_id).await {
+        Ok(subscription) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(subscription)))),
+        Err(e) => {
+            error!("Failed to resume subscription: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
 // Get user subscriptions
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscriptions/user/{user_id}",
+    tag = "subscriptions",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+    ),
+    responses(
+        (status = 200, description = "Subscriptions held by the user", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn get_user_subscriptions(
     Path(user_id): Path<Uuid>,
     Extension(state): Extension<AppState>
@@ -429,6 +853,16 @@ async fn get_user_subscriptions(
 }
 
 // Process payment
+#[utoipa::path(
+    post,
+    path = "/api/v1/billing/payments",
+    tag = "billing",
+    request_body = ProcessPaymentApiRequest,
+    responses(
+        (status = 200, description = "Payment result", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn process_payment(
     Path(invoice_id): Path<Uuid>,
     Extension(state): Extension<AppState>,
@@ -446,6 +880,7 @@ async fn process_payment(
         invoice_id,
         payment_method,
         amount: request.amount,
+        currency: request.currency,
         auto_confirm: request.auto_confirm,
     };
 
@@ -459,6 +894,15 @@ async fn process_payment(
 }
 
 // Get billing analytics
+#[utoipa::path(
+    get,
+    path = "/api/v1/billing/analytics",
+    tag = "billing",
+    responses(
+        (status = 200, description = "Billing analytics", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn billing_analytics(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -472,6 +916,16 @@ async fn billing_analytics(
 }
 
 // Create analytics subscription
+#[utoipa::path(
+    post,
+    path = "/api/v1/analytics/subscriptions",
+    tag = "analytics",
+    request_body = CreateAnalyticsSubscriptionRequest,
+    responses(
+        (status = 200, description = "Created analytics subscription", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn create_analytics_subscription(
     Path(user_id): Path<Uuid>,
     Extension(state): Extension<AppState>,
@@ -495,6 +949,18 @@ async fn create_analytics_subscription(
 }
 
 // Get analytics subscription
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/subscriptions/user/{user_id}",
+    tag = "analytics",
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+    ),
+    responses(
+        (status = 200, description = "Analytics subscription for the user", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn get_analytics_subscription(
     Path(user_id): Path<Uuid>,
     Extension(state): Extension<AppState>
@@ -509,6 +975,16 @@ async fn get_analytics_subscription(
 }
 
 // Track analytics usage
+#[utoipa::path(
+    post,
+    path = "/api/v1/analytics/usage",
+    tag = "analytics",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "Whether usage is within plan limits", body = ApiResponseBool),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn track_analytics_usage(
     Path(user_id): Path<Uuid>,
     Extension(state): Extension<AppState>,
@@ -527,7 +1003,73 @@ async fn track_analytics_usage(
     }
 }
 
+// Cohort retention heatmap, optionally spanning cohorts up to `until`
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/cohort-retention",
+    tag = "analytics",
+    params(CohortRetentionQuery),
+    responses(
+        (status = 200, description = "Cohort retention matrix", body = ApiResponseCohortRetention),
+        (status = 400, description = "Invalid cohort range"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn cohort_retention(
+    Query(query): Query<CohortRetentionQuery>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<CohortRetentionMatrix>>, StatusCode> {
+    let first: YearMonth = query.cohort.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let last: YearMonth = match query.until {
+        Some(until) => until.parse().map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => first,
+    };
+
+    let analysis = CohortRetentionAnalysis::new(state.revenue_engine.db_pool.clone());
+    match analysis.compute_matrix(first, last).await {
+        Ok(matrix) => Ok(ResponseJson(ApiResponse::success(matrix))),
+        Err(RevenueError::Validation(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to compute cohort retention: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Predicted customer lifetime value of active subscribers by quartile
+#[utoipa::path(
+    get,
+    path = "/api/v1/analytics/clv-distribution",
+    tag = "analytics",
+    responses(
+        (status = 200, description = "BG/NBD lifetime value quartiles, lowest first; empty until the model has run", body = ApiResponseClvDistribution),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn clv_distribution(
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<Vec<ClvBucket>>>, StatusCode> {
+    let analysis = ClvAnalysis::new(state.revenue_engine.db_pool.clone());
+    match analysis.get_clv_distribution().await {
+        Ok(buckets) => Ok(ResponseJson(ApiResponse::success(buckets))),
+        Err(e) => {
+            error!("Failed to get CLV distribution: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 // Process bridge transaction
+#[utoipa::path(
+    post,
+    path = "/api/v1/bridge/transactions",
+    tag = "bridge",
+    request_body = ProcessBridgeTransactionRequest,
+    responses(
+        (status = 200, description = "Recorded bridge transaction", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn process_bridge_transaction(
     Extension(state): Extension<AppState>,
     Json(request): Json<ProcessBridgeTransactionRequest>
@@ -560,6 +1102,19 @@ async fn process_bridge_transaction(
 }
 
 // Confirm bridge transaction
+#[utoipa::path(
+    put,
+    path = "/api/v1/bridge/transactions/{hash}/confirm",
+    tag = "bridge",
+    request_body = serde_json::Value,
+    params(
+        ("hash" = String, Path, description = "Transaction hash"),
+    ),
+    responses(
+        (status = 200, description = "Transaction confirmed", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn confirm_bridge_transaction(
     Path(transaction_hash): Path<String>,
     Extension(state): Extension<AppState>,
@@ -577,6 +1132,15 @@ async fn confirm_bridge_transaction(
 }
 
 // Get bridge analytics
+#[utoipa::path(
+    get,
+    path = "/api/v1/bridge/analytics",
+    tag = "bridge",
+    responses(
+        (status = 200, description = "Bridge revenue analytics", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn bridge_analytics(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -590,6 +1154,16 @@ async fn bridge_analytics(
 }
 
 // Add liquidity provision
+#[utoipa::path(
+    post,
+    path = "/api/v1/bridge/liquidity",
+    tag = "bridge",
+    request_body = serde_json::Value,
+    responses(
+        (status = 200, description = "Recorded liquidity provision", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn add_liquidity_provision(
     Extension(state): Extension<AppState>,
     Json(request): Json<serde_json::Value>
@@ -616,6 +1190,16 @@ async fn add_liquidity_provision(
 }
 
 // Create enterprise contract
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/contracts",
+    tag = "enterprise",
+    request_body = CreateEnterpriseContractRequest,
+    responses(
+        (status = 200, description = "Created enterprise contract", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn create_enterprise_contract(
     Extension(state): Extension<AppState>,
     Json(request): Json<CreateEnterpriseContractRequest>
@@ -664,6 +1248,15 @@ async fn create_enterprise_contract(
 }
 
 // Get enterprise analytics
+#[utoipa::path(
+    get,
+    path = "/api/v1/enterprise/analytics",
+    tag = "enterprise",
+    responses(
+        (status = 200, description = "Enterprise revenue analytics", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn enterprise_analytics(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -677,6 +1270,15 @@ async fn enterprise_analytics(
 }
 
 // Admin: Process billing cycles
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/billing/process",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Billing cycles processed", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn process_billing_cycles(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -693,6 +1295,15 @@ async fn process_billing_cycles(
 }
 
 // Admin: Optimize revenue
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/revenue/optimize",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Revenue optimization result", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn optimize_revenue(
     Extension(state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
@@ -703,7 +1314,59 @@ async fn optimize_revenue(
     }))))
 }
 
+// Admin: Redeliver a dead-lettered webhook
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/webhooks/replay/{id}",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "Dead letter ID"),
+    ),
+    responses(
+        (status = 200, description = "Webhook redelivered", body = ApiResponseJson),
+        (status = 404, description = "Dead letter not found"),
+        (status = 409, description = "Dead letter already replayed"),
+        (status = 502, description = "Endpoint rejected the redelivery"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn replay_webhook(
+    Path(dead_letter_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.billing_engine.webhooks().replay(dead_letter_id).await {
+        Ok(dead_letter) => Ok(ResponseJson(ApiResponse::success(serde_json::json!({
+            "dead_letter": dead_letter
+        })))),
+        Err(RevenueError::Billing(e)) => {
+            error!("Webhook replay failed: {}", e);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(RevenueError::Validation(e)) => {
+            error!("Webhook replay rejected: {}", e);
+            Err(StatusCode::CONFLICT)
+        }
+        Err(RevenueError::External(e)) => {
+            error!("Webhook replay failed: {}", e);
+            Err(StatusCode::BAD_GATEWAY)
+        }
+        Err(e) => {
+            error!("Webhook replay failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 // Additional endpoints would be implemented here...
+#[utoipa::path(
+    get,
+    path = "/api/v1/billing/invoices",
+    tag = "billing",
+    responses(
+        (status = 200, description = "Invoices", body = ApiResponseJsonList),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn list_invoices(
     Extension(_state): Extension<AppState>
 ) -> Result<ResponseJson<ApiResponse<Vec<serde_json::Value>>>, StatusCode> {
@@ -711,6 +1374,18 @@ async fn list_invoices(
     Ok(ResponseJson(ApiResponse::success(vec![])))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/billing/invoices/{id}",
+    tag = "billing",
+    params(
+        ("id" = Uuid, Path, description = "Invoice ID"),
+    ),
+    responses(
+        (status = 200, description = "Invoice details", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn get_invoice(
     Path(_invoice_id): Path<Uuid>,
     Extension(_state): Extension<AppState>
@@ -719,6 +1394,198 @@ async fn get_invoice(
     Ok(ResponseJson(ApiResponse::success(serde_json::json!({}))))
 }
 
+// Export invoice in Stripe's POST /v1/invoices format
+#[utoipa::path(
+    get,
+    path = "/api/v1/billing/invoices/{id}/stripe-export",
+    tag = "billing",
+    params(
+        ("id" = Uuid, Path, description = "Invoice ID"),
+    ),
+    responses(
+        (status = 200, description = "Invoice in Stripe POST /v1/invoices format", body = ApiResponseStripeInvoice),
+        (status = 404, description = "Invoice not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn export_invoice_to_stripe(
+    Path(invoice_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<StripeInvoicePayload>>, StatusCode> {
+    match state.stripe_exporter.export(invoice_id).await {
+        Ok(payload) => Ok(ResponseJson(ApiResponse::success(payload))),
+        Err(RevenueError::Billing(e)) => {
+            error!("Stripe export failed: {}", e);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            error!("Stripe export failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Render the invoice PDF and return a time-limited download link
+#[utoipa::path(
+    post,
+    path = "/api/v1/billing/invoices/{id}/pdf",
+    tag = "billing",
+    params(
+        ("id" = Uuid, Path, description = "Invoice ID"),
+    ),
+    responses(
+        (status = 200, description = "Signed download URL for the invoice PDF", body = ApiResponseJson),
+        (status = 404, description = "Invoice not found"),
+        (status = 503, description = "Invoice PDF storage not configured"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn publish_invoice_pdf(
+    Path(invoice_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.invoice_manager.publish_invoice_pdf(invoice_id).await {
+        Ok(download) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(download)))),
+        Err(RevenueError::Billing(e)) => {
+            error!("Invoice PDF failed: {}", e);
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(RevenueError::Config(e)) => {
+            error!("Invoice PDF failed: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+        Err(e) => {
+            error!("Invoice PDF failed: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Billing address used to work out the tax on the user's invoices
+#[utoipa::path(
+    put,
+    path = "/api/v1/billing/addresses/{user_id}",
+    tag = "billing",
+    request_body = SetBillingAddressApiRequest,
+    params(
+        ("user_id" = Uuid, Path, description = "User ID"),
+    ),
+    responses(
+        (status = 200, description = "Stored billing address", body = ApiResponseJson),
+        (status = 400, description = "Invalid country code or VAT ID"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn set_billing_address(
+    Path(user_id): Path<Uuid>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<SetBillingAddressApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    let address = BillingAddress {
+        user_id,
+        name: request.name,
+        line1: request.line1,
+        line2: request.line2,
+        city: request.city,
+        postal_code: request.postal_code,
+        country_code: request.country_code,
+        vat_id: request.vat_id,
+    };
+
+    match state.billing_engine.tax().set_billing_address(address).await {
+        Ok(address) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(address)))),
+        Err(RevenueError::Validation(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to set billing address for {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Stripe webhook receiver
+#[utoipa::path(
+    post,
+    path = "/webhooks/stripe",
+    tag = "billing",
+    request_body(content = String, content_type = "application/json", description = "Raw Stripe event payload"),
+    responses(
+        (status = 200, description = "Webhook processed, or already processed for a redelivered event id", body = ApiResponseJson),
+        (status = 400, description = "Missing or invalid Stripe signature"),
+        (status = 429, description = "Too many webhook deliveries"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(()),
+)]
+async fn receive_stripe_webhook(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    body: String
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    handle_stripe_webhook(&state, &headers, &body).await
+}
+
+// Stripe webhook receiver at its original billing path
+#[utoipa::path(
+    post,
+    path = "/api/v1/billing/webhooks/stripe",
+    tag = "billing",
+    request_body(content = String, content_type = "application/json", description = "Raw Stripe event payload"),
+    responses(
+        (status = 200, description = "Webhook processed", body = ApiResponseJson),
+        (status = 400, description = "Missing or invalid Stripe signature"),
+        (status = 429, description = "Too many webhook deliveries"),
+        (status = 500, description = "Internal server error"),
+    ),
+    security(()),
+)]
+async fn stripe_webhook(
+    Extension(state): Extension<AppState>,
+    headers: HeaderMap,
+    body: String
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    handle_stripe_webhook(&state, &headers, &body).await
+}
+
+async fn handle_stripe_webhook(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: &str
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    // Stripe retries with backoff, so shedding a burst loses nothing
+    if state.stripe_webhook_limiter.check().is_err() {
+        return Err(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let signature = headers
+        .get("stripe-signature")
+        .and_then(|v| v.to_str().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    match state.stripe_webhooks.handle(body, signature).await {
+        Ok(outcome) => Ok(ResponseJson(ApiResponse::success(serde_json::json!({ "outcome": outcome })))),
+        Err(RevenueError::Validation(e)) => {
+            error!("Rejected Stripe webhook: {}", e);
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(e) => {
+            error!("Failed to process Stripe webhook: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/enterprise/contracts/{id}",
+    tag = "enterprise",
+    params(
+        ("id" = Uuid, Path, description = "Contract ID"),
+    ),
+    responses(
+        (status = 200, description = "Enterprise contract details", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn get_enterprise_contract(
     Path(_contract_id): Path<Uuid>,
     Extension(_state): Extension<AppState>
@@ -727,20 +1594,319 @@ async fn get_enterprise_contract(
     Ok(ResponseJson(ApiResponse::success(serde_json::json!({}))))
 }
 
-async fn process_otc_order(
-    Extension(_state): Extension<AppState>,
-    Json(_request): Json<serde_json::Value>
+// Record a measured SLA KPI for a contract; the daily SLA check compares it with the target
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/contracts/{id}/sla-measurements",
+    tag = "enterprise",
+    request_body = RecordSlaMeasurementRequest,
+    params(
+        ("id" = Uuid, Path, description = "Contract ID"),
+    ),
+    responses(
+        (status = 200, description = "Measurement recorded", body = ApiResponseJson),
+        (status = 404, description = "Contract does not track the KPI"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn record_sla_measurement(
+    Path(contract_id): Path<Uuid>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<RecordSlaMeasurementRequest>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
-    // Implementation would process OTC trading order
-    Ok(ResponseJson(ApiResponse::success(serde_json::json!({}))))
+    let Some(kpi) = SlaKpi::parse(&request.kpi) else {
+        return Ok(ResponseJson(ApiResponse::error(format!("Invalid SLA KPI: {}", request.kpi))));
+    };
+    let measured_at = request.measured_at.unwrap_or_else(chrono::Utc::now);
+
+    match state.revenue_engine.sla_monitor.record_measurement(contract_id, kpi, request.actual, measured_at).await {
+        Ok(true) => Ok(ResponseJson(ApiResponse::success(serde_json::json!({
+            "contract_id": contract_id,
+            "kpi": kpi,
+            "actual": request.actual,
+            "measured_at": measured_at
+        })))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to record SLA measurement: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Request an OTC quote; it can be accepted until valid_until
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/otc/rfq",
+    tag = "enterprise",
+    request_body = RequestForQuoteApiRequest,
+    responses(
+        (status = 200, description = "Quote from the desk", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn request_otc_quote(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<RequestForQuoteApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    let Some(side) = OtcSide::parse(&request.side) else {
+        return Ok(ResponseJson(ApiResponse::error(format!("Invalid side: {}", request.side))));
+    };
+    let rfq = RequestForQuote {
+        client_id: request.client_id,
+        side,
+        quantity: request.quantity,
+        token_pair: request.token_pair,
+    };
+
+    match state.revenue_engine.otc_desk.request_quote(rfq).await {
+        Ok(quote) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(quote)))),
+        Err(e) => {
+            error!("Failed to quote OTC request: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Accept an OTC quote: locks collateral, executes against desk inventory and settles T+2
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/otc/quotes/{id}/accept",
+    tag = "enterprise",
+    params(
+        ("id" = Uuid, Path, description = "Quote ID"),
+    ),
+    responses(
+        (status = 200, description = "Executed OTC trade", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn accept_otc_quote(
+    Path(quote_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.otc_desk.accept_quote(AcceptQuote { quote_id }).await {
+        Ok(trade) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(trade)))),
+        Err(e) => {
+            error!("Failed to accept OTC quote: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
 }
 
+// Open a custody account and split a new custody key across the custodians. The response is
+// the only time the shares are returned.
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/custody",
+    tag = "enterprise",
+    request_body = SetupCustodyApiRequest,
+    responses(
+        (status = 200, description = "Custody account and custodian key shares", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
 async fn setup_custody_service(
-    Extension(_state): Extension<AppState>,
-    Json(_request): Json<serde_json::Value>
+    Extension(state): Extension<AppState>,
+    Json(request): Json<SetupCustodyApiRequest>
 ) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
-    // Implementation would setup custody service
-    Ok(ResponseJson(ApiResponse::success(serde_json::json!({}))))
+    let storage_type = match request.storage_type.as_str() {
+        "cold_storage" => CustodyStorageType::ColdStorage,
+        "hot_wallet" => CustodyStorageType::HotWallet,
+        "multi_sig" => CustodyStorageType::MultiSig,
+        "hsm" => CustodyStorageType::HSM,
+        _ => return Ok(ResponseJson(ApiResponse::error("Invalid storage type".to_string()))),
+    };
+    let security_level = match request.security_level.as_str() {
+        "standard" => SecurityLevel::Standard,
+        "enhanced" => SecurityLevel::Enhanced,
+        "institutional" => SecurityLevel::Institutional,
+        "military" => SecurityLevel::Military,
+        _ => return Ok(ResponseJson(ApiResponse::error("Invalid security level".to_string()))),
+    };
+
+    let account = match state.enterprise_manager.setup_custody_service(
+        request.client_id,
+        request.asset_type,
+        request.custody_fee_rate,
+        request.insurance_coverage,
+        storage_type,
+        security_level
+    ).await {
+        Ok(account) => account,
+        Err(e) => {
+            error!("Failed to setup custody service: {}", e);
+            return Ok(ResponseJson(ApiResponse::error(e.to_string())));
+        }
+    };
+
+    match state.revenue_engine.custody.create_custody_key(request.client_id, &request.custodians, request.threshold).await {
+        Ok(custody_key) => Ok(ResponseJson(ApiResponse::success(serde_json::json!({
+            "account": account,
+            "custody_key": custody_key
+        })))),
+        Err(e) => {
+            error!("Failed to create custody key: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Start a custody withdrawal; it needs threshold custodian approvals before execution
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/custody/withdrawals",
+    tag = "enterprise",
+    request_body = InitiateWithdrawalApiRequest,
+    responses(
+        (status = 200, description = "Pending withdrawal", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn initiate_custody_withdrawal(
+    Extension(state): Extension<AppState>,
+    Json(request): Json<InitiateWithdrawalApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.custody.initiate_custody_withdrawal(request.client_id, request.destination, request.amount).await {
+        Ok(withdrawal) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(withdrawal)))),
+        Err(e) => {
+            error!("Failed to initiate custody withdrawal: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Custodian approval carrying the custodian's key share
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/custody/withdrawals/{id}/approvals",
+    tag = "enterprise",
+    request_body = ApproveWithdrawalApiRequest,
+    params(
+        ("id" = Uuid, Path, description = "Withdrawal ID"),
+    ),
+    responses(
+        (status = 200, description = "Withdrawal with its approvals so far", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn approve_custody_withdrawal(
+    Path(withdrawal_id): Path<Uuid>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<ApproveWithdrawalApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.custody.approve_withdrawal(withdrawal_id, request.custodian_id, &request.share_fragment).await {
+        Ok(withdrawal) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(withdrawal)))),
+        Err(e) => {
+            error!("Failed to approve custody withdrawal: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Sign an approved withdrawal with the reconstructed custody key
+#[utoipa::path(
+    post,
+    path = "/api/v1/enterprise/custody/withdrawals/{id}/execute",
+    tag = "enterprise",
+    params(
+        ("id" = Uuid, Path, description = "Withdrawal ID"),
+    ),
+    responses(
+        (status = 200, description = "Signed withdrawal", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn execute_custody_withdrawal(
+    Path(withdrawal_id): Path<Uuid>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.custody.execute_withdrawal(withdrawal_id).await {
+        Ok(signed) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(signed)))),
+        Err(e) => {
+            error!("Failed to execute custody withdrawal: {}", e);
+            Ok(ResponseJson(ApiResponse::error(e.to_string())))
+        }
+    }
+}
+
+// Admin: List feature flags and the minimum tier each requires
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/feature-flags",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All feature flags", body = ApiResponseJson),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn list_feature_flags(
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.revenue_engine.feature_flags.list_flags().await {
+        Ok(flags) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(flags)))),
+        Err(e) => {
+            error!("Failed to list feature flags: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Admin: Create a feature flag or change the tier it is gated at and whether it is enabled
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/feature-flags/{flag_name}",
+    tag = "admin",
+    request_body = SetFeatureFlagApiRequest,
+    params(
+        ("flag_name" = String, Path, description = "Feature flag name"),
+    ),
+    responses(
+        (status = 200, description = "Updated feature flag", body = ApiResponseJson),
+        (status = 400, description = "Invalid subscription tier"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn set_feature_flag(
+    Path(flag_name): Path<String>,
+    Extension(state): Extension<AppState>,
+    Json(request): Json<SetFeatureFlagApiRequest>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    let min_tier = request.min_tier.parse::<SubscriptionTier>().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    match state.revenue_engine.feature_flags.set_flag(&flag_name, min_tier, request.enabled).await {
+        Ok(flag) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(flag)))),
+        Err(e) => {
+            error!("Failed to set feature flag {}: {}", flag_name, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+// Admin: EU VAT charged and reverse charged per country for a calendar quarter
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/vat-reports",
+    tag = "admin",
+    params(VatReportQuery),
+    responses(
+        (status = 200, description = "Quarterly VAT report", body = ApiResponseJson),
+        (status = 400, description = "Invalid quarter"),
+        (status = 500, description = "Internal server error"),
+    ),
+)]
+async fn vat_report(
+    Query(query): Query<VatReportQuery>,
+    Extension(state): Extension<AppState>
+) -> Result<ResponseJson<ApiResponse<serde_json::Value>>, StatusCode> {
+    match state.billing_engine.tax().quarterly_vat_report(query.year, query.quarter).await {
+        Ok(report) => Ok(ResponseJson(ApiResponse::success(serde_json::json!(report)))),
+        Err(RevenueError::Validation(_)) => Err(StatusCode::BAD_REQUEST),
+        Err(e) => {
+            error!("Failed to build VAT report for {} Q{}: {}", query.year, query.quarter, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
 }
 
 // Graceful shutdown handler
@@ -768,4 +1934,106 @@ async fn shutdown_signal() {
     }
 
     info!("💰 Revenue Engine Server shutting down gracefully...");
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Committed spec; regenerate with `UPDATE_OPENAPI_SPEC=1 cargo test --bin revenue-server`
+    const OPENAPI_SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    fn spec() -> serde_json::Value {
+        serde_json::to_value(ApiDoc::openapi()).unwrap()
+    }
+
+    #[test]
+    fn test_key_endpoints_documented() {
+        let spec = spec();
+        let paths = spec["paths"].as_object().unwrap();
+
+        for (path, method) in [
+            ("/health", "get"),
+            ("/api/v1/revenue/dashboard", "get"),
+            ("/api/v1/revenue/forecasting/scenarios", "post"),
+            ("/api/v1/subscriptions", "post"),
+            ("/api/v1/subscriptions/{id}/upgrade", "put"),
+            ("/api/v1/billing/payments", "post"),
+            ("/api/v1/billing/invoices/{id}/stripe-export", "get"),
+            ("/api/v1/billing/invoices/{id}/pdf", "post"),
+            ("/api/v1/billing/webhooks/stripe", "post"),
+            ("/webhooks/stripe", "post"),
+            ("/api/v1/analytics/cohort-retention", "get"),
+            ("/api/v1/analytics/clv-distribution", "get"),
+            ("/api/v1/bridge/transactions", "post"),
+            ("/api/v1/enterprise/contracts", "post"),
+            ("/api/v1/enterprise/contracts/{id}/sla-measurements", "post"),
+            ("/api/v1/enterprise/otc/rfq", "post"),
+            ("/api/v1/enterprise/otc/quotes/{id}/accept", "post"),
+            ("/api/v1/enterprise/custody/withdrawals/{id}/approvals", "post"),
+            ("/api/v1/admin/feature-flags/{flag_name}", "put"),
+            ("/api/v1/billing/addresses/{user_id}", "put"),
+            ("/api/v1/admin/vat-reports", "get"),
+        ] {
+            assert!(
+                paths.get(path).and_then(|p| p.get(method)).is_some(),
+                "{} {} missing from OpenAPI spec",
+                method.to_uppercase(),
+                path
+            );
+        }
+
+        let params = spec["paths"]["/api/v1/analytics/cohort-retention"]["get"]["parameters"]
+            .as_array()
+            .unwrap();
+        assert!(params.iter().any(|p| p["name"] == "cohort" && p["in"] == "query" && p["required"] == true));
+    }
+
+    #[test]
+    fn test_api_key_security() {
+        let spec = spec();
+
+        let scheme = &spec["components"]["securitySchemes"]["api_key"];
+        assert_eq!(scheme["type"], "apiKey");
+        assert_eq!(scheme["in"], "header");
+        assert_eq!(scheme["name"], "X-API-Key");
+        assert_eq!(spec["security"][0]["api_key"], serde_json::json!([]));
+
+        // Health checks and Stripe webhooks (signature-verified) do not take an API key
+        assert_eq!(spec["paths"]["/health"]["get"]["security"], serde_json::json!([{}]));
+        assert_eq!(spec["paths"]["/api/v1/billing/webhooks/stripe"]["post"]["security"], serde_json::json!([{}]));
+        assert_eq!(spec["paths"]["/webhooks/stripe"]["post"]["security"], serde_json::json!([{}]));
+        assert!(spec["paths"]["/api/v1/revenue/dashboard"]["get"].get("security").is_none());
+    }
+
+    #[test]
+    fn test_request_schemas_documented() {
+        let spec = spec();
+        let schemas = &spec["components"]["schemas"];
+
+        let required = schemas["CreateSubscriptionApiRequest"]["required"].as_array().unwrap();
+        assert!(required.contains(&serde_json::json!("tier")));
+        assert!(!required.contains(&serde_json::json!("trial_days")));
+        assert!(schemas["StripeInvoicePayload"]["properties"]["lines"].is_object());
+        assert!(schemas["ApiResponseCohortRetention"].is_object());
+    }
+
+    // Spec lock: fails when the API surface changes without the committed spec being updated.
+    // The spec file is only rewritten when UPDATE_OPENAPI_SPEC is set.
+    #[test]
+    fn test_openapi_spec_locked() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+
+        if std::env::var_os("UPDATE_OPENAPI_SPEC").is_some() {
+            std::fs::write(OPENAPI_SPEC_PATH, &generated).unwrap();
+            return;
+        }
+
+        let committed = std::fs::read_to_string(OPENAPI_SPEC_PATH).ok();
+
+        let committed = committed.expect("openapi.json is missing; run `UPDATE_OPENAPI_SPEC=1 cargo test --bin revenue-server`");
+        assert!(
+            committed == generated,
+            "OpenAPI spec is out of date; run `UPDATE_OPENAPI_SPEC=1 cargo test --bin revenue-server` and commit openapi.json"
+        );
+    }
+}
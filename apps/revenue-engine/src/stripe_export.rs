@@ -0,0 +1,576 @@
+// Stripe Invoice Export - Finance System Integration
+// Maps internal invoices to Stripe's invoice API format and ingests Stripe payment events
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+use crate::core::{RevenueError, RevenueResult};
+use crate::billing::{BillingEngine, Invoice, InvoiceLineItem};
+use crate::subscription::SubscriptionManager;
+use crate::subscription::churn::ChurnPredictor;
+
+// Currencies Stripe expects in whole units rather than cents
+const ZERO_DECIMAL_CURRENCIES: &[&str] = &["jpy", "krw", "vnd", "clp", "pyg", "ugx", "xaf", "xof"];
+
+// Maximum age of a webhook signature before it is rejected (seconds)
+const WEBHOOK_TOLERANCE_SECONDS: i64 = 300;
+
+// Stripe POST /v1/invoices request body with the invoice lines attached
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StripeInvoicePayload {
+    pub customer: Option<String>,
+    pub currency: String,
+    pub collection_method: String,
+    pub auto_advance: bool,
+    pub due_date: Option<i64>,
+    pub description: Option<String>,
+    pub default_payment_method: Option<String>,
+    pub metadata: HashMap<String, String>,
+    pub lines: Vec<StripeInvoiceLine>,
+}
+
+// Invoice line in Stripe's add_lines format
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StripeInvoiceLine {
+    pub description: String,
+    pub quantity: i64,
+    pub amount: i64,
+    pub tax_amounts: Vec<StripeTaxAmount>,
+    pub metadata: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StripeTaxAmount {
+    pub amount: i64,
+    pub taxable_amount: i64,
+    pub tax_rate_data: StripeTaxRateData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StripeTaxRateData {
+    pub display_name: String,
+    pub inclusive: bool,
+    pub percentage: f64,
+}
+
+// Exports internal invoices for clients using Stripe as their ERP
+#[derive(Debug)]
+pub struct StripeInvoiceExporter {
+    billing_engine: Arc<BillingEngine>,
+}
+
+impl StripeInvoiceExporter {
+    pub fn new(billing_engine: Arc<BillingEngine>) -> Self {
+        Self { billing_engine }
+    }
+
+    pub async fn export(&self, invoice_id: Uuid) -> RevenueResult<StripeInvoicePayload> {
+        let invoice = self.billing_engine.get_invoice(invoice_id).await?;
+
+        let billing_details = sqlx::query!(
+            r#"
+            SELECT i.stripe_customer_id, pm.stripe_payment_method_id as "stripe_payment_method_id?"
+            FROM invoices i
+            LEFT JOIN payment_methods pm
+                ON pm.user_id = i.user_id AND pm.is_default = true AND pm.is_active = true
+            WHERE i.id = $1
+            LIMIT 1
+            "#,
+            invoice_id
+        ).fetch_one(self.billing_engine.db_pool()).await?;
+
+        tracing::info!("📤 Exported invoice {} in Stripe format", invoice.invoice_number);
+
+        Ok(to_stripe_payload(
+            &invoice,
+            billing_details.stripe_customer_id,
+            billing_details.stripe_payment_method_id,
+        ))
+    }
+}
+
+// Map an internal invoice to Stripe's request body
+pub fn to_stripe_payload(
+    invoice: &Invoice,
+    customer: Option<String>,
+    default_payment_method: Option<String>,
+) -> StripeInvoicePayload {
+    let currency = invoice.currency.to_lowercase();
+    let tax_name = match &invoice.tax_line {
+        Some(line) if line.is_vat() => "VAT",
+        _ => "Sales Tax",
+    };
+
+    let mut metadata = flatten_metadata(&invoice.metadata);
+    metadata.insert("internal_invoice_id".to_string(), invoice.id.to_string());
+    metadata.insert("invoice_number".to_string(), invoice.invoice_number.clone());
+    metadata.insert("payment_terms".to_string(), invoice.payment_terms.clone());
+    if let Some(subscription_id) = invoice.subscription_id {
+        metadata.insert("subscription_id".to_string(), subscription_id.to_string());
+    }
+
+    StripeInvoicePayload {
+        customer,
+        collection_method: if default_payment_method.is_some() {
+            "charge_automatically".to_string()
+        } else {
+            "send_invoice".to_string()
+        },
+        auto_advance: false,
+        // Stripe only accepts due_date for send_invoice collection
+        due_date: if default_payment_method.is_none() {
+            Some(invoice.due_date.timestamp())
+        } else {
+            None
+        },
+        description: invoice.notes.clone(),
+        default_payment_method,
+        metadata,
+        lines: invoice.line_items
+            .iter()
+            .map(|item| to_stripe_line(item, &currency, tax_name))
+            .collect(),
+        currency,
+    }
+}
+
+fn to_stripe_line(item: &InvoiceLineItem, currency: &str, tax_name: &str) -> StripeInvoiceLine {
+    let amount = to_minor_units(item.total_price, currency);
+    let tax = item.total_price * Decimal::from_f64_retain(item.tax_rate).unwrap_or(Decimal::ZERO);
+
+    let tax_amounts = if item.tax_rate > 0.0 {
+        vec![StripeTaxAmount {
+            amount: to_minor_units(tax, currency),
+            taxable_amount: amount,
+            tax_rate_data: StripeTaxRateData {
+                display_name: tax_name.to_string(),
+                inclusive: false,
+                percentage: (item.tax_rate * 10_000.0).round() / 100.0,
+            },
+        }]
+    } else {
+        Vec::new()
+    };
+
+    let mut metadata = flatten_metadata(&item.metadata);
+    metadata.insert("internal_line_item_id".to_string(), item.id.to_string());
+
+    StripeInvoiceLine {
+        description: item.description.clone(),
+        quantity: item.quantity.round().to_i64().unwrap_or(1).max(1),
+        amount,
+        tax_amounts,
+        metadata,
+    }
+}
+
+// Convert a decimal amount into Stripe's smallest currency unit
+pub fn to_minor_units(amount: Decimal, currency: &str) -> i64 {
+    let scaled = if ZERO_DECIMAL_CURRENCIES.contains(&currency.to_lowercase().as_str()) {
+        amount
+    } else {
+        amount * Decimal::ONE_HUNDRED
+    };
+    scaled.round().to_i64().unwrap_or(0)
+}
+
+// Stripe metadata only supports flat string values
+fn flatten_metadata(value: &serde_json::Value) -> HashMap<String, String> {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (key.clone(), value)
+            })
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+// Minimal view of a Stripe event envelope
+#[derive(Debug, Clone, Deserialize)]
+pub struct StripeEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: StripeEventData,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StripeEventData {
+    pub object: serde_json::Value,
+}
+
+// Outcome of handling a webhook event
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum StripeWebhookOutcome {
+    InvoicePaid { invoice_id: Uuid },
+    PaymentConfirmed { payment_intent_id: String, invoice_id: Option<Uuid> },
+    PaymentFailed { subscription_id: Option<Uuid> },
+    SubscriptionCancelled { subscription_id: Option<Uuid> },
+    Duplicate { event_id: String },
+    Ignored { event_type: String },
+}
+
+// Stripe event ids already handled. An event is claimed before it is applied and released
+// if applying it fails, so Stripe's retry gets another go.
+#[derive(Debug, Clone)]
+pub struct ProcessedWebhooks {
+    db_pool: PgPool,
+}
+
+impl ProcessedWebhooks {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    // False when the event was already claimed
+    pub async fn claim(&self, event: &StripeEvent) -> RevenueResult<bool> {
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO processed_webhooks (event_id, event_type)
+            VALUES ($1, $2)
+            ON CONFLICT (event_id) DO NOTHING
+            "#,
+            event.id,
+            event.event_type
+        ).execute(&self.db_pool).await?;
+
+        Ok(claimed.rows_affected() == 1)
+    }
+
+    pub async fn release(&self, event_id: &str) -> RevenueResult<()> {
+        sqlx::query!("DELETE FROM processed_webhooks WHERE event_id = $1", event_id)
+            .execute(&self.db_pool)
+            .await?;
+        Ok(())
+    }
+}
+
+// Receives Stripe webhooks and applies them to invoices, payments and subscriptions
+#[derive(Debug)]
+pub struct StripeWebhookReceiver {
+    billing_engine: Arc<BillingEngine>,
+    subscription_manager: Arc<SubscriptionManager>,
+    churn_predictor: Arc<ChurnPredictor>,
+    processed: ProcessedWebhooks,
+    webhook_secret: String,
+}
+
+impl StripeWebhookReceiver {
+    pub fn new(
+        billing_engine: Arc<BillingEngine>,
+        subscription_manager: Arc<SubscriptionManager>,
+        churn_predictor: Arc<ChurnPredictor>,
+        db_pool: PgPool,
+        webhook_secret: String,
+    ) -> Self {
+        Self {
+            billing_engine,
+            subscription_manager,
+            churn_predictor,
+            processed: ProcessedWebhooks::new(db_pool),
+            webhook_secret,
+        }
+    }
+
+    pub async fn handle(&self, payload: &str, signature_header: &str) -> RevenueResult<StripeWebhookOutcome> {
+        verify_stripe_signature(payload, signature_header, &self.webhook_secret, Utc::now().timestamp())?;
+
+        let event: StripeEvent = serde_json::from_str(payload)
+            .map_err(|e| RevenueError::Validation(format!("Invalid Stripe event: {}", e)))?;
+
+        if !self.processed.claim(&event).await? {
+            tracing::info!("Stripe event {} already processed", event.id);
+            return Ok(StripeWebhookOutcome::Duplicate { event_id: event.id });
+        }
+
+        match self.dispatch(&event).await {
+            Ok(outcome) => Ok(outcome),
+            Err(e) => {
+                self.processed.release(&event.id).await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn dispatch(&self, event: &StripeEvent) -> RevenueResult<StripeWebhookOutcome> {
+        match event.event_type.as_str() {
+            "invoice.payment_succeeded" => {
+                let invoice_id = internal_invoice_id(event)?;
+                let amount_paid = event.data.object
+                    .get("amount_paid")
+                    .and_then(|v| v.as_i64())
+                    .unwrap_or(0);
+                let currency = event.data.object
+                    .get("currency")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("usd");
+
+                self.billing_engine
+                    .mark_invoice_paid(invoice_id, from_minor_units(amount_paid, currency))
+                    .await?;
+
+                tracing::info!("✅ Stripe event {} marked invoice {} paid", event.id, invoice_id);
+                Ok(StripeWebhookOutcome::InvoicePaid { invoice_id })
+            }
+            "payment_intent.succeeded" => {
+                let payment_intent_id = event_object_str(event, "id")?;
+                let invoice_id = self.billing_engine.confirm_payment(payment_intent_id).await?;
+
+                tracing::info!("✅ Stripe event {} confirmed payment intent {}", event.id, payment_intent_id);
+                Ok(StripeWebhookOutcome::PaymentConfirmed {
+                    payment_intent_id: payment_intent_id.to_string(),
+                    invoice_id,
+                })
+            }
+            "invoice.payment_failed" => {
+                let subscription_id = match event.data.object.get("subscription").and_then(|v| v.as_str()) {
+                    Some(stripe_subscription_id) => {
+                        self.churn_predictor.record_payment_failure(stripe_subscription_id).await?
+                    }
+                    None => None,
+                };
+                Ok(StripeWebhookOutcome::PaymentFailed { subscription_id })
+            }
+            "customer.subscription.deleted" => {
+                let stripe_subscription_id = event_object_str(event, "id")?;
+                let subscription_id = self.subscription_manager.find_by_stripe_id(stripe_subscription_id).await?;
+                if let Some(subscription_id) = subscription_id {
+                    self.subscription_manager.cancel_subscription(subscription_id, true).await?;
+                } else {
+                    tracing::warn!("⚠️ Stripe deleted unknown subscription {}", stripe_subscription_id);
+                }
+                Ok(StripeWebhookOutcome::SubscriptionCancelled { subscription_id })
+            }
+            _ => {
+                tracing::debug!("Ignoring Stripe event {} ({})", event.id, event.event_type);
+                Ok(StripeWebhookOutcome::Ignored { event_type: event.event_type.clone() })
+            }
+        }
+    }
+}
+
+fn event_object_str<'a>(event: &'a StripeEvent, field: &str) -> RevenueResult<&'a str> {
+    event.data.object
+        .get(field)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RevenueError::Validation(format!(
+            "Stripe event {} has no data.object.{}", event.id, field
+        )))
+}
+
+// Resolve the internal invoice from the metadata written by the exporter
+pub fn internal_invoice_id(event: &StripeEvent) -> RevenueResult<Uuid> {
+    event.data.object
+        .get("metadata")
+        .and_then(|m| m.get("internal_invoice_id"))
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())
+        .ok_or_else(|| RevenueError::Validation(format!(
+            "Stripe event {} has no internal_invoice_id metadata", event.id
+        )))
+}
+
+fn from_minor_units(amount: i64, currency: &str) -> Decimal {
+    if ZERO_DECIMAL_CURRENCIES.contains(&currency.to_lowercase().as_str()) {
+        Decimal::from(amount)
+    } else {
+        Decimal::new(amount, 2)
+    }
+}
+
+// Verify a `Stripe-Signature: t=<ts>,v1=<hex>` header
+pub fn verify_stripe_signature(payload: &str, header: &str, secret: &str, now: i64) -> RevenueResult<()> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+
+    let timestamp = timestamp
+        .ok_or_else(|| RevenueError::Validation("Missing Stripe signature timestamp".to_string()))?;
+
+    if (now - timestamp).abs() > WEBHOOK_TOLERANCE_SECONDS {
+        return Err(RevenueError::Validation("Stripe signature timestamp outside tolerance".to_string()));
+    }
+
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    let signed_payload = format!("{}.{}", timestamp, payload);
+
+    let verified = signatures.iter().any(|signature| {
+        hex_decode(signature)
+            .map(|bytes| ring::hmac::verify(&key, signed_payload.as_bytes(), &bytes).is_ok())
+            .unwrap_or(false)
+    });
+
+    if verified {
+        Ok(())
+    } else {
+        Err(RevenueError::Validation("Invalid Stripe signature".to_string()))
+    }
+}
+
+fn hex_decode(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::billing::InvoiceStatus;
+
+    fn sample_invoice() -> Invoice {
+        let created = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        Invoice {
+            id: Uuid::parse_str("6f1c1b8e-2f0a-4b8a-9d62-3f1c2b4d5e6f").unwrap(),
+            subscription_id: None,
+            user_id: Uuid::new_v4(),
+            client_id: None,
+            invoice_number: "INV-000042".to_string(),
+            amount: Decimal::new(29900, 2),
+            currency: "USD".to_string(),
+            tax_amount: Decimal::new(2616, 2),
+            total_amount: Decimal::new(32516, 2),
+            status: InvoiceStatus::Pending,
+            due_date: created + Duration::days(15),
+            paid_at: None,
+            stripe_invoice_id: None,
+            line_items: vec![InvoiceLineItem {
+                id: Uuid::new_v4(),
+                description: "Professional Subscription".to_string(),
+                quantity: Decimal::ONE,
+                unit_price: Decimal::new(29900, 2),
+                total_price: Decimal::new(29900, 2),
+                tax_rate: 0.0875,
+                metadata: serde_json::json!({ "subscription_tier": "professional" }),
+            }],
+            tax_line: None,
+            payment_terms: "net_15".to_string(),
+            notes: Some("March billing".to_string()),
+            metadata: serde_json::json!({ "billing_cycle": "monthly", "seats": 5 }),
+            created_at: created,
+            updated_at: created,
+        }
+    }
+
+    fn sign(payload: &str, secret: &str, timestamp: i64) -> String {
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+        let tag = ring::hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+        let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+        format!("t={},v1={}", timestamp, hex)
+    }
+
+    // Shape of Stripe's `stripe trigger invoice.payment_succeeded` test event
+    const PAYMENT_SUCCEEDED_EVENT: &str = r#"{
+        "id": "evt_1NG8Du2eZvKYlo2CUI79vXWy",
+        "object": "event",
+        "api_version": "2022-11-15",
+        "livemode": false,
+        "type": "invoice.payment_succeeded",
+        "data": {
+            "object": {
+                "id": "in_1NG8Dt2eZvKYlo2C8y0Pqe3E",
+                "object": "invoice",
+                "amount_paid": 32516,
+                "currency": "usd",
+                "status": "paid",
+                "metadata": { "internal_invoice_id": "6f1c1b8e-2f0a-4b8a-9d62-3f1c2b4d5e6f" }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_payload_maps_invoice_fields() {
+        let payload = to_stripe_payload(&sample_invoice(), Some("cus_NffrFeUfNV2Hib".to_string()), None);
+
+        assert_eq!(payload.currency, "usd");
+        assert_eq!(payload.collection_method, "send_invoice");
+        assert_eq!(payload.due_date, Some(sample_invoice().due_date.timestamp()));
+        assert_eq!(payload.metadata["invoice_number"], "INV-000042");
+        assert_eq!(payload.metadata["billing_cycle"], "monthly");
+        assert_eq!(payload.metadata["seats"], "5");
+
+        let line = &payload.lines[0];
+        assert_eq!(line.amount, 29900);
+        assert_eq!(line.quantity, 1);
+        assert_eq!(line.tax_amounts[0].amount, 2616);
+        assert_eq!(line.tax_amounts[0].tax_rate_data.percentage, 8.75);
+    }
+
+    #[test]
+    fn test_payload_with_payment_method_charges_automatically() {
+        let payload = to_stripe_payload(
+            &sample_invoice(),
+            Some("cus_NffrFeUfNV2Hib".to_string()),
+            Some("pm_card_visa".to_string()),
+        );
+
+        assert_eq!(payload.collection_method, "charge_automatically");
+        assert_eq!(payload.default_payment_method.as_deref(), Some("pm_card_visa"));
+        assert!(payload.due_date.is_none());
+    }
+
+    #[test]
+    fn test_zero_decimal_currency() {
+        assert_eq!(to_minor_units(Decimal::new(1500, 0), "JPY"), 1500);
+        assert_eq!(to_minor_units(Decimal::new(1999, 2), "usd"), 1999);
+    }
+
+    #[test]
+    fn test_payment_succeeded_event_resolves_invoice() {
+        let event: StripeEvent = serde_json::from_str(PAYMENT_SUCCEEDED_EVENT).unwrap();
+
+        assert_eq!(event.event_type, "invoice.payment_succeeded");
+        assert_eq!(internal_invoice_id(&event).unwrap(), sample_invoice().id);
+        assert_eq!(from_minor_units(32516, "usd"), Decimal::new(32516, 2));
+    }
+
+    #[test]
+    fn test_event_object_fields() {
+        let event: StripeEvent = serde_json::from_str(r#"{
+            "id": "evt_3NG8Du2eZvKYlo2C0tFJ9d1Y",
+            "type": "payment_intent.succeeded",
+            "data": { "object": { "id": "pi_3NG8Du2eZvKYlo2C0wHj6gKq", "amount_received": 32516 } }
+        }"#).unwrap();
+
+        assert_eq!(event_object_str(&event, "id").unwrap(), "pi_3NG8Du2eZvKYlo2C0wHj6gKq");
+        assert!(event_object_str(&event, "amount_received").is_err());
+        assert!(event_object_str(&event, "subscription").is_err());
+    }
+
+    #[test]
+    fn test_signature_verification() {
+        let secret = "whsec_test_secret";
+        let now = 1_700_000_000;
+        let header = sign(PAYMENT_SUCCEEDED_EVENT, secret, now);
+
+        assert!(verify_stripe_signature(PAYMENT_SUCCEEDED_EVENT, &header, secret, now).is_ok());
+        assert!(verify_stripe_signature(PAYMENT_SUCCEEDED_EVENT, &header, "whsec_other", now).is_err());
+        assert!(verify_stripe_signature("{}", &header, secret, now).is_err());
+        assert!(verify_stripe_signature(PAYMENT_SUCCEEDED_EVENT, &header, secret, now + 600).is_err());
+    }
+}
@@ -11,9 +11,14 @@ use serde::{Serialize, Deserialize};
 
 use crate::core::{RevenueError, RevenueResult};
 use crate::billing::{PaymentProcessor, PaymentMethod};
+use crate::billing::webhook::{WebhookDispatcher, WebhookEvent};
 
-// Subscription tiers with pricing
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+pub mod churn;
+pub mod feature_flags;
+
+// Subscription tiers with pricing, declared from lowest to highest so the derived ordering
+// is the tier ordering
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "subscription_tier", rename_all = "lowercase")]
 pub enum SubscriptionTier {
     Basic,      // $49/month  - Basic analytics, mobile features
@@ -115,6 +120,16 @@ pub enum BillingCycle {
     Custom,
 }
 
+impl BillingCycle {
+    // Length of one billing period, matching how next_billing_date is advanced
+    pub fn period_length(&self) -> Duration {
+        match self {
+            Self::Monthly | Self::Custom => Duration::days(30),
+            Self::Annual => Duration::days(365),
+        }
+    }
+}
+
 // Subscription model
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Subscription {
@@ -153,6 +168,136 @@ pub struct UpgradeSubscriptionRequest {
     pub prorate: bool,
 }
 
+// Subscription metadata key holding credit not yet applied to an invoice
+pub const PRORATION_CREDIT_BALANCE_KEY: &str = "proration_credit_balance";
+
+// When a proration credit is taken off an invoice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProrationTiming {
+    // Upgrades start a new billing period now and the credit reduces its invoice
+    Immediate,
+    // Downgrades keep the current period; the overpayment discounts the next invoice
+    NextPeriod,
+}
+
+// Credit for the unused part of the current billing period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProrationCredit {
+    pub unused_days: i64,
+    pub period_days: i64,
+    pub amount: Decimal,
+    pub timing: ProrationTiming,
+}
+
+// Only whole unused days are credited, so a change on the last day of a period credits nothing.
+// A plan is a downgrade when its daily rate is lower, which makes annual-to-monthly an upgrade
+// whose credit may exceed the first monthly invoice; the remainder carries over.
+pub fn calculate_proration(
+    current: &Subscription,
+    new_amount: Decimal,
+    new_billing_cycle: &BillingCycle,
+    now: DateTime<Utc>
+) -> ProrationCredit {
+    let period_days = current.billing_cycle.period_length().num_days();
+    let unused_days = (current.next_billing_date - now).num_days().clamp(0, period_days);
+
+    let current_daily = current.amount / Decimal::from(period_days);
+    let new_daily = new_amount / Decimal::from(new_billing_cycle.period_length().num_days());
+
+    let (timing, credited_daily) = if new_daily < current_daily {
+        (ProrationTiming::NextPeriod, current_daily - new_daily)
+    } else {
+        (ProrationTiming::Immediate, current_daily)
+    };
+
+    ProrationCredit {
+        unused_days,
+        period_days,
+        amount: (credited_daily * Decimal::from(unused_days)).round_dp(2),
+        timing,
+    }
+}
+
+// Credit carried in subscription metadata, zero if none
+pub fn proration_credit_balance(metadata: &serde_json::Value) -> Decimal {
+    metadata
+        .get(PRORATION_CREDIT_BALANCE_KEY)
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(Decimal::ZERO)
+}
+
+// Part of the credit balance taken off an invoice for `amount`; never more than the invoice
+pub fn apply_proration_credit(amount: Decimal, credit_balance: Decimal) -> Decimal {
+    credit_balance.min(amount).max(Decimal::ZERO)
+}
+
+// Longest a subscription may stay paused before it is cancelled
+pub const MAX_PAUSE_DAYS: i64 = 90;
+
+// A pause must end in the future and within the maximum pause duration
+pub fn validate_resume_date(resume_date: Option<DateTime<Utc>>, now: DateTime<Utc>) -> RevenueResult<()> {
+    match resume_date {
+        Some(date) if date <= now => Err(RevenueError::Validation(
+            format!("Resume date {} is not in the future", date)
+        )),
+        Some(date) if date > now + Duration::days(MAX_PAUSE_DAYS) => Err(RevenueError::Validation(
+            format!("Subscriptions can be paused for at most {} days", MAX_PAUSE_DAYS)
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Billing shift for a pause ending now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PauseAdjustment {
+    pub paused_days: i64,
+    pub next_billing_date: DateTime<Utc>,
+}
+
+// The period already paid for is not consumed while paused, so the next billing date moves
+// out by the whole days spent paused, never more than the maximum pause
+pub fn calculate_pause_adjustment(
+    next_billing_date: DateTime<Utc>,
+    paused_at: DateTime<Utc>,
+    now: DateTime<Utc>
+) -> PauseAdjustment {
+    let paused_days = (now - paused_at).num_days().clamp(0, MAX_PAUSE_DAYS);
+    PauseAdjustment {
+        paused_days,
+        next_billing_date: next_billing_date + Duration::days(paused_days),
+    }
+}
+
+// What the daily job does with a paused subscription
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PausedSubscriptionAction {
+    Resume,
+    Cancel,
+}
+
+pub fn paused_subscription_action(
+    paused_at: DateTime<Utc>,
+    resume_date: Option<DateTime<Utc>>,
+    now: DateTime<Utc>
+) -> Option<PausedSubscriptionAction> {
+    if resume_date.is_some_and(|date| date <= now) {
+        Some(PausedSubscriptionAction::Resume)
+    } else if now - paused_at >= Duration::days(MAX_PAUSE_DAYS) {
+        Some(PausedSubscriptionAction::Cancel)
+    } else {
+        None
+    }
+}
+
+// Outcome of the daily paused subscription sweep
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PausedSubscriptionSweep {
+    pub resumed: Vec<Uuid>,
+    pub cancelled: Vec<Uuid>,
+}
+
 // Subscription analytics
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscriptionAnalytics {
@@ -173,12 +318,14 @@ pub struct SubscriptionManager {
     db_pool: PgPool,
     redis: ConnectionManager,
     payment_processor: Arc<PaymentProcessor>,
+    webhooks: Arc<WebhookDispatcher>,
 }
 
 impl SubscriptionManager {
     pub async fn new(
         db_pool: PgPool, 
-        redis: ConnectionManager
+        redis: ConnectionManager,
+        webhooks: Arc<WebhookDispatcher>
     ) -> RevenueResult<Self> {
         // Setup subscription tables
         Self::setup_subscription_tables(&db_pool).await?;
@@ -192,38 +339,13 @@ impl SubscriptionManager {
             db_pool,
             redis,
             payment_processor,
+            webhooks,
         })
     }
 
-    async fn setup_subscription_tables(pool: &PgPool) -> RevenueResult<()> {
-        // Enhanced subscriptions table with all features
-        sqlx::query(r#"
-            CREATE TABLE IF NOT EXISTS subscriptions (
-                id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
-                user_id UUID NOT NULL,
-                tier VARCHAR NOT NULL,
-                status VARCHAR NOT NULL DEFAULT 'active',
-                billing_cycle VARCHAR NOT NULL DEFAULT 'monthly',
-                amount DECIMAL(10,2) NOT NULL,
-                currency VARCHAR(10) NOT NULL DEFAULT 'USD',
-                next_billing_date TIMESTAMP NOT NULL,
-                stripe_subscription_id VARCHAR UNIQUE,
-                stripe_customer_id VARCHAR,
-                trial_end_date TIMESTAMP,
-                cancelled_at TIMESTAMP,
-                paused_at TIMESTAMP,
-                metadata JSONB DEFAULT '{}',
-                created_at TIMESTAMP DEFAULT NOW(),
-                updated_at TIMESTAMP DEFAULT NOW()
-            );
-            
-            CREATE INDEX IF NOT EXISTS idx_subscriptions_user_id ON subscriptions(user_id);
-            CREATE INDEX IF NOT EXISTS idx_subscriptions_status ON subscriptions(status);
-            CREATE INDEX IF NOT EXISTS idx_subscriptions_tier ON subscriptions(tier);
-            CREATE INDEX IF NOT EXISTS idx_subscriptions_billing_date ON subscriptions(next_billing_date);
-            CREATE INDEX IF NOT EXISTS idx_subscriptions_stripe ON subscriptions(stripe_subscription_id);
-        "#).execute(pool).await?;
-
+    // Creates the usage and event tables on top of the migrated subscriptions table; run by `new`,
+    // and by the database tests
+    pub async fn setup_subscription_tables(pool: &PgPool) -> RevenueResult<()> {
         // Subscription usage tracking
         sqlx::query(r#"
             CREATE TABLE IF NOT EXISTS subscription_usage (
@@ -294,11 +416,7 @@ impl SubscriptionManager {
         let next_billing_date = if trial_days > 0 {
             now + Duration::days(trial_days)
         } else {
-            match request.billing_cycle {
-                BillingCycle::Monthly => now + Duration::days(30),
-                BillingCycle::Annual => now + Duration::days(365),
-                BillingCycle::Custom => now + Duration::days(30), // Default to monthly
-            }
+            now + request.billing_cycle.period_length()
         };
 
         // Create Stripe subscription
@@ -367,6 +485,15 @@ impl SubscriptionManager {
             metadata: subscription.metadata,
         };
 
+        self.webhooks.dispatch(WebhookEvent::SubscriptionCreated {
+            subscription_id: result.id,
+            user_id: result.user_id,
+            tier: result.tier.to_string(),
+            billing_cycle: result.billing_cycle.to_string(),
+            amount: result.amount,
+            currency: result.currency.clone(),
+        });
+
         tracing::info!("✅ Subscription created: {} - ${}/month", subscription_id, amount);
         Ok(result)
     }
@@ -386,31 +513,52 @@ impl SubscriptionManager {
             BillingCycle::Custom => current.amount, // Keep current amount for custom
         };
 
+        let billing_cycle = request.new_billing_cycle.unwrap_or_else(|| current.billing_cycle.clone());
+
+        // Credit the unused part of the current period; upgrades are invoiced right away
+        let now = Utc::now();
+        let proration = request.prorate.then(|| calculate_proration(&current, new_amount, &billing_cycle, now));
+        let credit_balance = proration_credit_balance(&current.metadata)
+            + proration.as_ref().map_or(Decimal::ZERO, |credit| credit.amount);
+        let next_billing_date = match &proration {
+            Some(credit) if credit.timing == ProrationTiming::Immediate => now,
+            _ => current.next_billing_date,
+        };
+
         // Update Stripe subscription
         if let Some(stripe_id) = &current.stripe_subscription_id {
             self.update_stripe_subscription(stripe_id, &request.new_tier, new_amount).await?;
         }
 
         // Update database record
-        let billing_cycle = request.new_billing_cycle.unwrap_or(current.billing_cycle);
         sqlx::query!(
             r#"
             UPDATE subscriptions 
-            SET tier = $1, billing_cycle = $2, amount = $3, updated_at = NOW(),
-                metadata = metadata || $4
-            WHERE id = $5
+            SET tier = $1, billing_cycle = $2, amount = $3, next_billing_date = $4, updated_at = NOW(),
+                metadata = metadata || $5
+            WHERE id = $6
             "#,
             request.new_tier.to_string(),
             billing_cycle.to_string(),
             new_amount,
+            next_billing_date,
             serde_json::json!({
                 "tier_features": request.new_tier.features(),
                 "api_rate_limit": request.new_tier.api_rate_limit(),
-                "upgraded_at": Utc::now()
+                "upgraded_at": now,
+                "last_proration": proration,
+                PRORATION_CREDIT_BALANCE_KEY: credit_balance.to_string()
             }),
             request.subscription_id
         ).execute(&self.db_pool).await?;
 
+        if let Some(credit) = &proration {
+            tracing::info!(
+                "🧮 Prorated {} of {} unused days: ${} credit ({:?})",
+                credit.unused_days, credit.period_days, credit.amount, credit.timing
+            );
+        }
+
         // Log subscription event
         self.log_subscription_event(
             request.subscription_id,
@@ -423,8 +571,8 @@ impl SubscriptionManager {
         ).await?;
 
         // Update cache
+        self.clear_subscription_cache(request.subscription_id).await?;
         let updated_subscription = self.get_subscription(request.subscription_id).await?;
-        self.cache_subscription_raw(&updated_subscription).await?;
 
         tracing::info!("✅ Subscription upgraded: {} -> {}", current.tier.to_string(), request.new_tier.to_string());
         Ok(updated_subscription)
@@ -482,6 +630,16 @@ impl SubscriptionManager {
         Ok(subscriptions)
     }
 
+    // Internal id of the subscription Stripe knows as `stripe_subscription_id`
+    pub async fn find_by_stripe_id(&self, stripe_subscription_id: &str) -> RevenueResult<Option<Uuid>> {
+        let id = sqlx::query_scalar!(
+            "SELECT id FROM subscriptions WHERE stripe_subscription_id = $1",
+            stripe_subscription_id
+        ).fetch_optional(&self.db_pool).await?;
+
+        Ok(id)
+    }
+
     // Cancel subscription
     pub async fn cancel_subscription(
         &self,
@@ -530,6 +688,143 @@ impl SubscriptionManager {
         Ok(())
     }
 
+    // Suspend billing until `resume_date`, or until resumed by hand. A pause that reaches
+    // MAX_PAUSE_DAYS cancels the subscription.
+    pub async fn pause_subscription(
+        &self,
+        subscription_id: Uuid,
+        resume_date: Option<DateTime<Utc>>
+    ) -> RevenueResult<Subscription> {
+        tracing::info!("⏸️ Pausing subscription: {}", subscription_id);
+
+        let now = Utc::now();
+        validate_resume_date(resume_date, now)?;
+
+        let subscription = self.get_subscription(subscription_id).await?;
+        if !matches!(subscription.status, SubscriptionStatus::Active) {
+            return Err(RevenueError::Subscription(format!(
+                "Only active subscriptions can be paused, {} is {:?}", subscription_id, subscription.status
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE subscriptions
+            SET status = 'paused', paused_at = $1, resume_date = $2, updated_at = NOW()
+            WHERE id = $3
+            "#,
+            now,
+            resume_date,
+            subscription_id
+        ).execute(&self.db_pool).await?;
+
+        self.log_subscription_event(
+            subscription_id,
+            subscription.user_id,
+            "paused",
+            Some(&subscription.tier),
+            None,
+            Some(subscription.amount),
+            None
+        ).await?;
+
+        self.clear_subscription_cache(subscription_id).await?;
+
+        tracing::info!("✅ Subscription paused: {} until {:?}", subscription_id, resume_date);
+        self.get_subscription(subscription_id).await
+    }
+
+    // Restart billing, moving the next billing date out by the days spent paused
+    pub async fn resume_subscription(&self, subscription_id: Uuid) -> RevenueResult<Subscription> {
+        tracing::info!("▶️ Resuming subscription: {}", subscription_id);
+
+        let subscription = self.get_subscription(subscription_id).await?;
+        if !matches!(subscription.status, SubscriptionStatus::Paused) {
+            return Err(RevenueError::Subscription(format!(
+                "Subscription {} is not paused", subscription_id
+            )));
+        }
+
+        let pause = sqlx::query!(
+            "SELECT paused_at FROM subscriptions WHERE id = $1",
+            subscription_id
+        ).fetch_one(&self.db_pool).await?;
+
+        let now = Utc::now();
+        let paused_at = pause.paused_at.unwrap_or(now);
+        let adjustment = calculate_pause_adjustment(subscription.next_billing_date, paused_at, now);
+
+        sqlx::query!(
+            r#"
+            UPDATE subscriptions
+            SET status = 'active', next_billing_date = $1, paused_at = NULL, resume_date = NULL,
+                updated_at = NOW(), metadata = metadata || $2
+            WHERE id = $3
+            "#,
+            adjustment.next_billing_date,
+            serde_json::json!({
+                "last_pause": {
+                    "paused_at": paused_at,
+                    "resumed_at": now,
+                    "paused_days": adjustment.paused_days
+                }
+            }),
+            subscription_id
+        ).execute(&self.db_pool).await?;
+
+        self.log_subscription_event(
+            subscription_id,
+            subscription.user_id,
+            "resumed",
+            None,
+            Some(&subscription.tier),
+            None,
+            Some(subscription.amount)
+        ).await?;
+
+        self.clear_subscription_cache(subscription_id).await?;
+
+        tracing::info!(
+            "✅ Subscription resumed: {} after {} days, next billing {}",
+            subscription_id, adjustment.paused_days, adjustment.next_billing_date
+        );
+        self.get_subscription(subscription_id).await
+    }
+
+    // Daily pass over paused subscriptions: resume those whose resume date has come and
+    // cancel those paused for MAX_PAUSE_DAYS
+    pub async fn process_paused_subscriptions(&self, now: DateTime<Utc>) -> RevenueResult<PausedSubscriptionSweep> {
+        let paused = sqlx::query!(
+            r#"
+            SELECT id, paused_at, resume_date
+            FROM subscriptions
+            WHERE status = 'paused'
+            "#
+        ).fetch_all(&self.db_pool).await?;
+
+        let mut sweep = PausedSubscriptionSweep::default();
+        for record in paused {
+            let paused_at = record.paused_at.unwrap_or(now);
+            let result = match paused_subscription_action(paused_at, record.resume_date, now) {
+                Some(PausedSubscriptionAction::Resume) => self.resume_subscription(record.id).await
+                    .map(|_| sweep.resumed.push(record.id)),
+                Some(PausedSubscriptionAction::Cancel) => self.cancel_subscription(record.id, true).await
+                    .map(|_| sweep.cancelled.push(record.id)),
+                None => Ok(()),
+            };
+
+            if let Err(e) = result {
+                tracing::error!("❌ Failed to process paused subscription {}: {}", record.id, e);
+            }
+        }
+
+        tracing::info!(
+            "⏯️ Resumed {} and cancelled {} paused subscriptions",
+            sweep.resumed.len(), sweep.cancelled.len()
+        );
+        Ok(sweep)
+    }
+
     // Get subscription analytics
     pub async fn get_subscription_analytics(&self) -> RevenueResult<SubscriptionAnalytics> {
         let analytics = sqlx::query!(
@@ -630,10 +925,6 @@ impl SubscriptionManager {
         Ok(())
     }
 
-    async fn cache_subscription_raw(&self, subscription: &Subscription) -> RevenueResult<()> {
-        self.cache_subscription(subscription).await
-    }
-
     async fn get_cached_subscription(&self, subscription_id: Uuid) -> RevenueResult<Subscription> {
         let mut redis = self.redis.clone();
         let key = format!("subscription:{}", subscription_id);
@@ -806,6 +1097,18 @@ impl SubscriptionService {
         self.manager.cancel_subscription(id, immediate).await
     }
 
+    pub async fn pause_subscription(
+        &self,
+        id: Uuid,
+        resume_date: Option<DateTime<Utc>>
+    ) -> RevenueResult<Subscription> {
+        self.manager.pause_subscription(id, resume_date).await
+    }
+
+    pub async fn resume_subscription(&self, id: Uuid) -> RevenueResult<Subscription> {
+        self.manager.resume_subscription(id).await
+    }
+
     pub async fn get_analytics(&self) -> RevenueResult<SubscriptionAnalytics> {
         self.manager.get_subscription_analytics().await
     }
@@ -823,6 +1126,20 @@ impl ToString for SubscriptionTier {
     }
 }
 
+impl std::str::FromStr for SubscriptionTier {
+    type Err = RevenueError;
+
+    fn from_str(tier: &str) -> Result<Self, Self::Err> {
+        match tier {
+            "basic" => Ok(Self::Basic),
+            "professional" => Ok(Self::Professional),
+            "enterprise" => Ok(Self::Enterprise),
+            "custom" => Ok(Self::Custom),
+            _ => Err(RevenueError::Validation(format!("Invalid subscription tier: {}", tier))),
+        }
+    }
+}
+
 impl ToString for BillingCycle {
     fn to_string(&self) -> String {
         match self {
@@ -831,4 +1148,131 @@ impl ToString for BillingCycle {
             Self::Custom => "custom".to_string(),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn subscription(tier: SubscriptionTier, billing_cycle: BillingCycle, next_billing_date: DateTime<Utc>) -> Subscription {
+        let amount = match billing_cycle {
+            BillingCycle::Annual => tier.annual_price(),
+            _ => tier.monthly_price(),
+        };
+        Subscription {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            tier,
+            status: SubscriptionStatus::Active,
+            billing_cycle,
+            amount,
+            currency: "USD".to_string(),
+            next_billing_date,
+            stripe_subscription_id: None,
+            trial_end_date: None,
+            created_at: next_billing_date,
+            updated_at: next_billing_date,
+            metadata: serde_json::json!({}),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_mid_period_upgrade_credits_unused_days() {
+        let current = subscription(SubscriptionTier::Basic, BillingCycle::Monthly, now() + Duration::hours(20 * 24 + 6));
+        let credit = calculate_proration(&current, Decimal::new(199, 0), &BillingCycle::Monthly, now());
+
+        assert_eq!(credit.unused_days, 20);
+        assert_eq!(credit.period_days, 30);
+        assert_eq!(credit.amount, Decimal::new(3267, 2));
+        assert_eq!(credit.timing, ProrationTiming::Immediate);
+    }
+
+    #[test]
+    fn test_last_day_of_period_credits_nothing() {
+        let current = subscription(SubscriptionTier::Basic, BillingCycle::Monthly, now() + Duration::hours(10));
+        let credit = calculate_proration(&current, Decimal::new(199, 0), &BillingCycle::Monthly, now());
+
+        assert_eq!(credit.unused_days, 0);
+        assert_eq!(credit.amount, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_downgrade_discounts_next_period() {
+        let current = subscription(SubscriptionTier::Professional, BillingCycle::Monthly, now() + Duration::days(15));
+        let credit = calculate_proration(&current, Decimal::new(49, 0), &BillingCycle::Monthly, now());
+
+        // Only the difference between the two plans over the remaining days is credited
+        assert_eq!(credit.amount, Decimal::new(75, 0));
+        assert_eq!(credit.timing, ProrationTiming::NextPeriod);
+    }
+
+    #[test]
+    fn test_annual_to_monthly_credit_exceeds_first_invoice() {
+        let current = subscription(SubscriptionTier::Professional, BillingCycle::Annual, now() + Duration::days(200));
+        let credit = calculate_proration(&current, Decimal::new(199, 0), &BillingCycle::Monthly, now());
+
+        assert_eq!(credit.period_days, 365);
+        assert_eq!(credit.amount, Decimal::new(109041, 2));
+        assert_eq!(credit.timing, ProrationTiming::Immediate);
+        assert!(credit.amount > Decimal::new(199, 0));
+    }
+
+    #[test]
+    fn test_overdue_subscription_credits_nothing() {
+        let current = subscription(SubscriptionTier::Basic, BillingCycle::Monthly, now() - Duration::days(3));
+        let credit = calculate_proration(&current, Decimal::new(199, 0), &BillingCycle::Monthly, now());
+
+        assert_eq!(credit.unused_days, 0);
+        assert_eq!(credit.amount, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_credit_balance_from_metadata() {
+        assert_eq!(proration_credit_balance(&serde_json::json!({})), Decimal::ZERO);
+        assert_eq!(
+            proration_credit_balance(&serde_json::json!({ PRORATION_CREDIT_BALANCE_KEY: "891.41" })),
+            Decimal::new(89141, 2)
+        );
+    }
+
+    #[test]
+    fn test_resume_date_limits() {
+        assert!(validate_resume_date(None, now()).is_ok());
+        assert!(validate_resume_date(Some(now() + Duration::days(14)), now()).is_ok());
+        assert!(validate_resume_date(Some(now() + Duration::days(MAX_PAUSE_DAYS)), now()).is_ok());
+        assert!(validate_resume_date(Some(now() + Duration::days(MAX_PAUSE_DAYS + 1)), now()).is_err());
+        assert!(validate_resume_date(Some(now() - Duration::hours(1)), now()).is_err());
+    }
+
+    #[test]
+    fn test_resume_moves_billing_by_paused_days() {
+        let next_billing_date = now() + Duration::days(12);
+        let paused_at = now() - Duration::days(10) - Duration::hours(5);
+        let adjustment = calculate_pause_adjustment(next_billing_date, paused_at, now());
+
+        assert_eq!(adjustment.paused_days, 10);
+        assert_eq!(adjustment.next_billing_date, now() + Duration::days(22));
+
+        let capped = calculate_pause_adjustment(next_billing_date, now() - Duration::days(120), now());
+        assert_eq!(capped.paused_days, MAX_PAUSE_DAYS);
+    }
+
+    #[test]
+    fn test_paused_subscription_actions() {
+        let paused_at = now() - Duration::days(30);
+        assert_eq!(paused_subscription_action(paused_at, Some(now() + Duration::days(1)), now()), None);
+        assert_eq!(paused_subscription_action(paused_at, None, now()), None);
+        assert_eq!(
+            paused_subscription_action(paused_at, Some(now() - Duration::minutes(1)), now()),
+            Some(PausedSubscriptionAction::Resume)
+        );
+        assert_eq!(
+            paused_subscription_action(now() - Duration::days(MAX_PAUSE_DAYS), None, now()),
+            Some(PausedSubscriptionAction::Cancel)
+        );
+    }
+}
@@ -0,0 +1,352 @@
+// Churn Detection - Overdue subscription monitoring and re-engagement campaigns
+// Flags subscriptions that missed renewal and walks their owners through tiered reminder emails
+
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Utc, Duration};
+use serde::{Serialize, Deserialize};
+
+use crate::core::{RevenueError, RevenueResult};
+use crate::subscription::SubscriptionTier;
+
+// Days past the billing date before a subscription counts as at risk
+pub const CHURN_GRACE_DAYS: i64 = 3;
+
+// Discount offered in the 7-day email
+pub const REENGAGEMENT_DISCOUNT_PERCENT: u32 = 20;
+
+const SENDGRID_SEND_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+// Subscription that has not renewed within the grace period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChurnRiskEvent {
+    pub subscription_id: Uuid,
+    pub user_id: Uuid,
+    pub email: Option<String>,
+    pub tier: SubscriptionTier,
+    pub days_overdue: i64,
+    pub billing_date: DateTime<Utc>,
+}
+
+// Re-engagement emails in the order they are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReEngagementStage {
+    Reminder,
+    DiscountOffer,
+    FinalNotice,
+}
+
+impl ReEngagementStage {
+    // Latest stage reached; a missed earlier email is skipped rather than sent late
+    pub fn for_days_overdue(days_overdue: i64) -> Option<Self> {
+        match days_overdue {
+            d if d >= 14 => Some(Self::FinalNotice),
+            d if d >= 7 => Some(Self::DiscountOffer),
+            d if d >= CHURN_GRACE_DAYS => Some(Self::Reminder),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reminder => "reminder",
+            Self::DiscountOffer => "discount_offer",
+            Self::FinalNotice => "final_notice",
+        }
+    }
+
+    pub fn render(&self, event: &ChurnRiskEvent, recipient: &str) -> EmailMessage {
+        let tier = event.tier.to_string();
+        let (subject, body) = match self {
+            Self::Reminder => (
+                format!("Your Nockchain {} subscription payment is overdue", tier),
+                format!(
+                    "Your {} subscription renewal is {} days overdue. Update your payment method \
+                     to keep your analytics and mining insights running without interruption.",
+                    tier, event.days_overdue
+                ),
+            ),
+            Self::DiscountOffer => (
+                format!("{}% off to keep your {} subscription", REENGAGEMENT_DISCOUNT_PERCENT, tier),
+                format!(
+                    "We noticed your {} subscription has not renewed. Renew within the next 7 days \
+                     and your next billing period is {}% off.",
+                    tier, REENGAGEMENT_DISCOUNT_PERCENT
+                ),
+            ),
+            Self::FinalNotice => (
+                format!("Final notice: your {} subscription will be cancelled", tier),
+                format!(
+                    "Your {} subscription is {} days overdue and will be cancelled soon. \
+                     Renew now to keep your account history and settings.",
+                    tier, event.days_overdue
+                ),
+            ),
+        };
+
+        EmailMessage {
+            to: recipient.to_string(),
+            subject,
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// Outbound email transport
+#[async_trait::async_trait]
+pub trait EmailProvider: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> RevenueResult<()>;
+}
+
+// Logs instead of sending; used when no provider is configured
+#[derive(Debug, Default)]
+pub struct StubEmailProvider;
+
+#[async_trait::async_trait]
+impl EmailProvider for StubEmailProvider {
+    async fn send(&self, message: &EmailMessage) -> RevenueResult<()> {
+        tracing::info!("📧 [stub] Email to {}: {}", message.to, message.subject);
+        Ok(())
+    }
+}
+
+// SendGrid v3 mail send API
+#[derive(Debug)]
+pub struct SendGridEmailProvider {
+    client: reqwest::Client,
+    api_key: String,
+    from_address: String,
+    send_url: String,
+}
+
+impl SendGridEmailProvider {
+    pub fn new(api_key: String, from_address: String) -> Self {
+        Self::with_send_url(api_key, from_address, SENDGRID_SEND_URL.to_string())
+    }
+
+    pub fn with_send_url(api_key: String, from_address: String, send_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+            from_address,
+            send_url,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailProvider for SendGridEmailProvider {
+    async fn send(&self, message: &EmailMessage) -> RevenueResult<()> {
+        let response = self.client
+            .post(&self.send_url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "personalizations": [{ "to": [{ "email": message.to }] }],
+                "from": { "email": self.from_address },
+                "subject": message.subject,
+                "content": [{ "type": "text/plain", "value": message.body }]
+            }))
+            .send()
+            .await
+            .map_err(|e| RevenueError::External(format!("SendGrid request failed: {}", e)))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(RevenueError::External(format!("SendGrid rejected email: {}", response.status())))
+        }
+    }
+}
+
+// Finds subscriptions that missed renewal by more than the grace period
+#[derive(Debug)]
+pub struct ChurnPredictor {
+    db_pool: PgPool,
+}
+
+impl ChurnPredictor {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    pub async fn detect(&self, now: DateTime<Utc>) -> RevenueResult<Vec<ChurnRiskEvent>> {
+        let records = sqlx::query!(
+            r#"
+            SELECT id, user_id, tier, next_billing_date, metadata->>'email' as email
+            FROM subscriptions
+            WHERE status IN ('active', 'past_due') AND next_billing_date <= $1
+            ORDER BY next_billing_date
+            "#,
+            (now - Duration::days(CHURN_GRACE_DAYS)).naive_utc()
+        ).fetch_all(&self.db_pool).await?;
+
+        let mut events = Vec::with_capacity(records.len());
+        for record in records {
+            let tier = match record.tier.as_str() {
+                "basic" => SubscriptionTier::Basic,
+                "professional" => SubscriptionTier::Professional,
+                "enterprise" => SubscriptionTier::Enterprise,
+                "custom" => SubscriptionTier::Custom,
+                _ => {
+                    tracing::warn!("⚠️ Skipping subscription {} with unknown tier {}", record.id, record.tier);
+                    continue;
+                }
+            };
+
+            // next_billing_date is a UTC timestamp without time zone
+            let billing_date = record.next_billing_date.and_utc();
+            events.push(ChurnRiskEvent {
+                subscription_id: record.id,
+                user_id: record.user_id,
+                email: record.email,
+                tier,
+                days_overdue: (now - billing_date).num_days(),
+                billing_date,
+            });
+        }
+
+        tracing::info!("📉 Detected {} subscriptions at risk of churning", events.len());
+        Ok(events)
+    }
+
+    // A failed renewal charge moves the subscription to past_due, which keeps it in
+    // `detect` once the grace period runs out. Returns None for an unknown or ended subscription.
+    pub async fn record_payment_failure(&self, stripe_subscription_id: &str) -> RevenueResult<Option<Uuid>> {
+        let subscription_id = sqlx::query_scalar!(
+            r#"
+            UPDATE subscriptions
+            SET status = 'past_due', updated_at = NOW()
+            WHERE stripe_subscription_id = $1 AND status IN ('active', 'past_due')
+            RETURNING id
+            "#,
+            stripe_subscription_id
+        ).fetch_optional(&self.db_pool).await?;
+
+        match subscription_id {
+            Some(id) => tracing::warn!("⚠️ Payment failed for subscription {}, marked past due", id),
+            None => tracing::warn!("⚠️ Payment failed for unknown Stripe subscription {}", stripe_subscription_id),
+        }
+        Ok(subscription_id)
+    }
+}
+
+// Sends each at-risk subscription the email for its stage, once per missed billing date
+pub struct ReEngagementEmailer {
+    db_pool: PgPool,
+    provider: Arc<dyn EmailProvider>,
+}
+
+impl std::fmt::Debug for ReEngagementEmailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReEngagementEmailer").finish_non_exhaustive()
+    }
+}
+
+impl ReEngagementEmailer {
+    pub fn new(db_pool: PgPool, provider: Arc<dyn EmailProvider>) -> Self {
+        Self { db_pool, provider }
+    }
+
+    // Returns the stage emailed, or None if nothing was due or it was already sent
+    pub async fn handle(&self, event: &ChurnRiskEvent) -> RevenueResult<Option<ReEngagementStage>> {
+        let Some(stage) = ReEngagementStage::for_days_overdue(event.days_overdue) else {
+            return Ok(None);
+        };
+        let Some(recipient) = event.email.as_deref() else {
+            tracing::warn!("⚠️ No email on file for at-risk subscription {}", event.subscription_id);
+            return Ok(None);
+        };
+
+        // Claim the send first so concurrent runs cannot email twice
+        let claimed = sqlx::query!(
+            r#"
+            INSERT INTO churn_emails_sent (subscription_id, user_id, stage, billing_date, recipient)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (subscription_id, stage, billing_date) DO NOTHING
+            RETURNING id
+            "#,
+            event.subscription_id,
+            event.user_id,
+            stage.as_str(),
+            event.billing_date,
+            recipient
+        ).fetch_optional(&self.db_pool).await?;
+
+        let Some(claimed) = claimed else {
+            return Ok(None);
+        };
+
+        if let Err(e) = self.provider.send(&stage.render(event, recipient)).await {
+            // Release the claim so tomorrow's run retries
+            sqlx::query!("DELETE FROM churn_emails_sent WHERE id = $1", claimed.id)
+                .execute(&self.db_pool)
+                .await?;
+            return Err(e);
+        }
+
+        tracing::info!(
+            "📧 Sent {} email for subscription {} ({} days overdue)",
+            stage.as_str(), event.subscription_id, event.days_overdue
+        );
+        Ok(Some(stage))
+    }
+
+    // Daily pass: detect at-risk subscriptions and email each one. Individual send failures
+    // are logged so one bad address does not stop the run.
+    pub async fn run(&self, predictor: &ChurnPredictor, now: DateTime<Utc>) -> RevenueResult<usize> {
+        let mut sent = 0;
+        for event in predictor.detect(now).await? {
+            match self.handle(&event).await {
+                Ok(Some(_)) => sent += 1,
+                Ok(None) => {}
+                Err(e) => tracing::error!(
+                    "❌ Re-engagement email failed for subscription {}: {}", event.subscription_id, e
+                ),
+            }
+        }
+        Ok(sent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(days_overdue: i64) -> ChurnRiskEvent {
+        ChurnRiskEvent {
+            subscription_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            email: Some("miner@example.com".to_string()),
+            tier: SubscriptionTier::Professional,
+            days_overdue,
+            billing_date: Utc::now() - Duration::days(days_overdue),
+        }
+    }
+
+    #[test]
+    fn test_stage_thresholds() {
+        assert_eq!(ReEngagementStage::for_days_overdue(2), None);
+        assert_eq!(ReEngagementStage::for_days_overdue(3), Some(ReEngagementStage::Reminder));
+        assert_eq!(ReEngagementStage::for_days_overdue(6), Some(ReEngagementStage::Reminder));
+        assert_eq!(ReEngagementStage::for_days_overdue(7), Some(ReEngagementStage::DiscountOffer));
+        assert_eq!(ReEngagementStage::for_days_overdue(14), Some(ReEngagementStage::FinalNotice));
+        assert_eq!(ReEngagementStage::for_days_overdue(40), Some(ReEngagementStage::FinalNotice));
+    }
+
+    #[test]
+    fn test_discount_offer_mentions_discount() {
+        let message = ReEngagementStage::DiscountOffer.render(&event(7), "miner@example.com");
+        assert_eq!(message.to, "miner@example.com");
+        assert!(message.subject.contains("20% off"));
+        assert!(message.body.contains("20% off"));
+    }
+}
@@ -0,0 +1,259 @@
+// Feature Flags - Tier-gated access control backed by the feature_flags table
+// A flag is on for a user when it is enabled and their highest active subscription tier
+// is at or above the flag's minimum tier
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::core::RevenueResult;
+use crate::subscription::SubscriptionTier;
+
+// Header carrying the authenticated user, set by the API gateway
+pub const USER_ID_HEADER: &str = "x-user-id";
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FeatureFlag {
+    pub flag_name: String,
+    pub min_tier: SubscriptionTier,
+    pub enabled: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeatureFlag {
+    // Users without an active subscription never get a flag, even one gated at Basic
+    pub fn allows(&self, tier: Option<&SubscriptionTier>) -> bool {
+        self.enabled && tier.map_or(false, |tier| *tier >= self.min_tier)
+    }
+}
+
+// Flags on for the current request's user; empty when the request carries no user
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EnabledFeatureFlags {
+    pub tier: Option<SubscriptionTier>,
+    pub flags: BTreeSet<String>,
+}
+
+impl EnabledFeatureFlags {
+    pub fn resolve(tier: Option<SubscriptionTier>, flags: &[FeatureFlag]) -> Self {
+        let enabled = flags
+            .iter()
+            .filter(|flag| flag.allows(tier.as_ref()))
+            .map(|flag| flag.flag_name.clone())
+            .collect();
+        Self { tier, flags: enabled }
+    }
+
+    pub fn is_enabled(&self, flag_name: &str) -> bool {
+        self.flags.contains(flag_name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeatureFlagService {
+    db_pool: PgPool,
+}
+
+impl FeatureFlagService {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    // Highest tier among the user's active and trialing subscriptions
+    pub async fn active_tier(&self, user_id: Uuid) -> RevenueResult<Option<SubscriptionTier>> {
+        let tiers = sqlx::query_scalar!(
+            "SELECT tier FROM subscriptions WHERE user_id = $1 AND status IN ('active', 'trialing')",
+            user_id
+        ).fetch_all(&self.db_pool).await?;
+
+        Ok(tiers
+            .iter()
+            .filter_map(|tier| match tier.parse::<SubscriptionTier>() {
+                Ok(tier) => Some(tier),
+                Err(_) => {
+                    tracing::warn!("⚠️ Ignoring subscription with unknown tier {} for user {}", tier, user_id);
+                    None
+                }
+            })
+            .max())
+    }
+
+    // Fails closed: an unknown flag or a lookup error denies access
+    pub async fn check(&self, user_id: Uuid, flag_name: &str) -> bool {
+        let flag = match self.get_flag(flag_name).await {
+            Ok(Some(flag)) => flag,
+            Ok(None) => return false,
+            Err(e) => {
+                tracing::error!("❌ Failed to load feature flag {}: {}", flag_name, e);
+                return false;
+            }
+        };
+
+        match self.active_tier(user_id).await {
+            Ok(tier) => flag.allows(tier.as_ref()),
+            Err(e) => {
+                tracing::error!("❌ Failed to load subscription tier for user {}: {}", user_id, e);
+                false
+            }
+        }
+    }
+
+    pub async fn enabled_flags(&self, user_id: Uuid) -> RevenueResult<EnabledFeatureFlags> {
+        let tier = self.active_tier(user_id).await?;
+        let flags = self.list_flags().await?;
+        Ok(EnabledFeatureFlags::resolve(tier, &flags))
+    }
+
+    pub async fn get_flag(&self, flag_name: &str) -> RevenueResult<Option<FeatureFlag>> {
+        let flag = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            SELECT flag_name, min_tier as "min_tier: SubscriptionTier", enabled, updated_at
+            FROM feature_flags
+            WHERE flag_name = $1
+            "#,
+            flag_name
+        ).fetch_optional(&self.db_pool).await?;
+
+        Ok(flag)
+    }
+
+    pub async fn list_flags(&self) -> RevenueResult<Vec<FeatureFlag>> {
+        let flags = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            SELECT flag_name, min_tier as "min_tier: SubscriptionTier", enabled, updated_at
+            FROM feature_flags
+            ORDER BY flag_name
+            "#
+        ).fetch_all(&self.db_pool).await?;
+
+        Ok(flags)
+    }
+
+    // Creates the flag or moves it to a new minimum tier and enabled state
+    pub async fn set_flag(&self, flag_name: &str, min_tier: SubscriptionTier, enabled: bool) -> RevenueResult<FeatureFlag> {
+        let flag = sqlx::query_as!(
+            FeatureFlag,
+            r#"
+            INSERT INTO feature_flags (flag_name, min_tier, enabled)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (flag_name) DO UPDATE
+            SET min_tier = EXCLUDED.min_tier, enabled = EXCLUDED.enabled, updated_at = NOW()
+            RETURNING flag_name, min_tier as "min_tier: SubscriptionTier", enabled, updated_at
+            "#,
+            flag_name,
+            min_tier as SubscriptionTier,
+            enabled
+        ).fetch_one(&self.db_pool).await?;
+
+        tracing::info!("🚩 Feature flag {} set to min tier {} (enabled: {})", flag.flag_name, flag.min_tier.to_string(), flag.enabled);
+        Ok(flag)
+    }
+}
+
+/// Middleware inserting the caller's `EnabledFeatureFlags` into the request extensions.
+/// Requests without a valid user ID header, or whose lookup fails, get no flags.
+pub async fn inject_feature_flags(
+    State(service): State<Arc<FeatureFlagService>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let user_id = request
+        .headers()
+        .get(USER_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| Uuid::parse_str(value).ok());
+
+    let flags = match user_id {
+        Some(user_id) => service.enabled_flags(user_id).await.unwrap_or_else(|e| {
+            tracing::error!("❌ Failed to resolve feature flags for user {}: {}", user_id, e);
+            EnabledFeatureFlags::default()
+        }),
+        None => EnabledFeatureFlags::default(),
+    };
+
+    request.extensions_mut().insert(flags);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(min_tier: SubscriptionTier, enabled: bool) -> FeatureFlag {
+        FeatureFlag {
+            flag_name: "predictive_analytics".to_string(),
+            min_tier,
+            enabled,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tiers_ordered_by_price() {
+        assert!(SubscriptionTier::Basic < SubscriptionTier::Professional);
+        assert!(SubscriptionTier::Professional < SubscriptionTier::Enterprise);
+        assert!(SubscriptionTier::Enterprise < SubscriptionTier::Custom);
+    }
+
+    #[test]
+    fn test_min_tier_boundary() {
+        let professional = flag(SubscriptionTier::Professional, true);
+
+        assert!(!professional.allows(Some(&SubscriptionTier::Basic)));
+        assert!(professional.allows(Some(&SubscriptionTier::Professional)));
+        assert!(professional.allows(Some(&SubscriptionTier::Enterprise)));
+        assert!(professional.allows(Some(&SubscriptionTier::Custom)));
+    }
+
+    #[test]
+    fn test_lowest_and_highest_min_tiers() {
+        let basic = flag(SubscriptionTier::Basic, true);
+        assert!(basic.allows(Some(&SubscriptionTier::Basic)));
+        assert!(!basic.allows(None));
+
+        let custom = flag(SubscriptionTier::Custom, true);
+        assert!(!custom.allows(Some(&SubscriptionTier::Enterprise)));
+        assert!(custom.allows(Some(&SubscriptionTier::Custom)));
+    }
+
+    #[test]
+    fn test_disabled_flag_denies_every_tier() {
+        let disabled = flag(SubscriptionTier::Basic, false);
+
+        for tier in [
+            SubscriptionTier::Basic,
+            SubscriptionTier::Professional,
+            SubscriptionTier::Enterprise,
+            SubscriptionTier::Custom,
+        ] {
+            assert!(!disabled.allows(Some(&tier)));
+        }
+    }
+
+    #[test]
+    fn test_resolve_keeps_only_allowed_flags() {
+        let flags = vec![
+            FeatureFlag { flag_name: "custom_dashboards".to_string(), ..flag(SubscriptionTier::Professional, true) },
+            FeatureFlag { flag_name: "sso".to_string(), ..flag(SubscriptionTier::Enterprise, true) },
+            FeatureFlag { flag_name: "real_time_alerts".to_string(), ..flag(SubscriptionTier::Basic, false) },
+        ];
+
+        let enabled = EnabledFeatureFlags::resolve(Some(SubscriptionTier::Professional), &flags);
+        assert_eq!(enabled.tier, Some(SubscriptionTier::Professional));
+        assert!(enabled.is_enabled("custom_dashboards"));
+        assert!(!enabled.is_enabled("sso"));
+        assert!(!enabled.is_enabled("real_time_alerts"));
+
+        assert!(EnabledFeatureFlags::resolve(None, &flags).flags.is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+// Accounting invariants for revenue recording and subscription proration
+// Property-based: 1000 generated cases per property, shrunk to a minimal failing input on error
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use proptest::prelude::*;
+use revenue_engine::subscription::{
+    apply_proration_credit, calculate_proration, BillingCycle, ProrationTiming, Subscription,
+    SubscriptionStatus, SubscriptionTier,
+};
+use revenue_engine::{RevenueMetrics, RevenueRecord, RevenueStream};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+/// Largest generated amount, in cents ($10M)
+const MAX_CENTS: i64 = 1_000_000_000;
+
+fn config() -> ProptestConfig {
+    ProptestConfig::with_cases(1000)
+}
+
+fn arb_user_id() -> impl Strategy<Value = Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128)
+}
+
+fn arb_amount() -> BoxedStrategy<Decimal> {
+    (0..=MAX_CENTS).prop_map(|cents| Decimal::new(cents, 2)).boxed()
+}
+
+/// A gross amount and a fee no larger than it
+fn arb_amount_with_fee() -> BoxedStrategy<(Decimal, Decimal)> {
+    (0..=MAX_CENTS)
+        .prop_flat_map(|cents| (Just(cents), 0..=cents))
+        .prop_map(|(amount, fee)| (Decimal::new(amount, 2), Decimal::new(fee, 2)))
+        .boxed()
+}
+
+fn arb_subscription_tier() -> impl Strategy<Value = SubscriptionTier> {
+    prop_oneof![
+        Just(SubscriptionTier::Basic),
+        Just(SubscriptionTier::Professional),
+        Just(SubscriptionTier::Enterprise),
+        Just(SubscriptionTier::Custom),
+    ]
+}
+
+fn arb_billing_cycle() -> impl Strategy<Value = BillingCycle> {
+    prop_oneof![
+        Just(BillingCycle::Monthly),
+        Just(BillingCycle::Annual),
+        Just(BillingCycle::Custom),
+    ]
+}
+
+fn arb_revenue_stream() -> impl Strategy<Value = RevenueStream> {
+    let label = "[a-z_]{3,12}";
+    prop_oneof![
+        // Fee percentages in quarter-percent steps from 0% to 100%
+        (arb_amount(), 0u32..=400, arb_user_id()).prop_map(|(amount, quarters, user_id)| {
+            RevenueStream::MiningPool { amount, fee_percentage: f64::from(quarters) / 4.0, user_id }
+        }),
+        (arb_subscription_tier(), arb_amount(), arb_user_id()).prop_map(|(subscription_tier, monthly_amount, user_id)| {
+            RevenueStream::PremiumAnalytics { subscription_tier, monthly_amount, user_id }
+        }),
+        ("[A-Z]{3,5}", "[A-Z]{3,5}", arb_amount_with_fee(), arb_user_id()).prop_map(|(from_token, to_token, (amount, fee_amount), user_id)| {
+            RevenueStream::BridgeTransaction { from_token, to_token, amount, fee_amount, user_id }
+        }),
+        ("[A-Z]{3,5}/[A-Z]{3,5}", arb_amount_with_fee(), arb_user_id()).prop_map(|(trading_pair, (volume, fee_amount), user_id)| {
+            RevenueStream::TradingFees { trading_pair, volume, fee_amount, user_id }
+        }),
+        (label, arb_amount(), arb_user_id()).prop_map(|(service_type, contract_value, client_id)| {
+            RevenueStream::EnterpriseServices { service_type, contract_value, client_id }
+        }),
+        (label, arb_amount(), arb_user_id()).prop_map(|(tier, monthly_value, client_id)| {
+            RevenueStream::APILicensing { tier, monthly_value, client_id }
+        }),
+        (label, arb_amount(), arb_user_id()).prop_map(|(service_type, project_value, client_id)| {
+            RevenueStream::PerformanceOptimization { service_type, project_value, client_id }
+        }),
+        (label, arb_amount(), arb_user_id()).prop_map(|(project_type, total_value, client_id)| {
+            RevenueStream::CustomSolutions { project_type, total_value, client_id }
+        }),
+    ]
+}
+
+fn empty_metrics() -> RevenueMetrics {
+    RevenueMetrics {
+        total_monthly_revenue: Decimal::ZERO,
+        subscription_revenue: Decimal::ZERO,
+        transaction_revenue: Decimal::ZERO,
+        enterprise_revenue: Decimal::ZERO,
+        conversion_rate: 0.0,
+        customer_lifetime_value: Decimal::ZERO,
+        churn_rate: 0.0,
+        average_revenue_per_user: Decimal::ZERO,
+        revenue_growth_rate: 0.0,
+        timestamp: now(),
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap()
+}
+
+/// Price of a plan, as upgrade_subscription charges it
+fn plan_price(tier: &SubscriptionTier, cycle: &BillingCycle) -> Decimal {
+    match cycle {
+        BillingCycle::Annual => tier.annual_price(),
+        _ => tier.monthly_price(),
+    }
+}
+
+fn daily_rate(tier: &SubscriptionTier, cycle: &BillingCycle) -> Decimal {
+    plan_price(tier, cycle) / Decimal::from(cycle.period_length().num_days())
+}
+
+fn subscription(tier: SubscriptionTier, billing_cycle: BillingCycle, next_billing_date: DateTime<Utc>) -> Subscription {
+    Subscription {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        amount: plan_price(&tier, &billing_cycle),
+        tier,
+        status: SubscriptionStatus::Active,
+        billing_cycle,
+        currency: "USD".to_string(),
+        next_billing_date,
+        stripe_subscription_id: None,
+        trial_end_date: None,
+        created_at: now(),
+        updated_at: now(),
+        metadata: serde_json::json!({}),
+    }
+}
+
+proptest! {
+    #![proptest_config(config())]
+
+    // process_revenue_stream records `fee` as revenue; the rest of each gross amount is
+    // passed through to the user
+    #[test]
+    fn revenue_totals_match_recorded_fees(streams in prop::collection::vec(arb_revenue_stream(), 0..50)) {
+        let mut metrics = empty_metrics();
+        let mut total_amount = Decimal::ZERO;
+        let mut total_fees = Decimal::ZERO;
+
+        for stream in &streams {
+            let record = RevenueRecord::from_stream(stream).unwrap();
+            prop_assert!(record.fee <= record.amount);
+
+            metrics.record_revenue(&record.stream_type, record.fee);
+            total_amount += record.amount;
+            total_fees += record.fee;
+        }
+
+        prop_assert_eq!(metrics.total_monthly_revenue, total_fees);
+        prop_assert!(total_amount - total_fees >= Decimal::ZERO);
+        prop_assert_eq!(
+            metrics.subscription_revenue + metrics.transaction_revenue + metrics.enterprise_revenue,
+            metrics.total_monthly_revenue
+        );
+    }
+
+    #[test]
+    fn mid_cycle_upgrade_credit_and_invoice_sum_to_new_price(
+        plan_a in (arb_subscription_tier(), arb_billing_cycle()),
+        plan_b in (arb_subscription_tier(), arb_billing_cycle()),
+        elapsed_fraction in 0.0f64..1.0,
+        extra_hours in 0i64..24,
+    ) {
+        // The cheaper daily rate is the current plan, so the change is always an upgrade
+        let ((current_tier, current_cycle), (new_tier, new_cycle)) =
+            if daily_rate(&plan_a.0, &plan_a.1) <= daily_rate(&plan_b.0, &plan_b.1) {
+                (plan_a, plan_b)
+            } else {
+                (plan_b, plan_a)
+            };
+
+        let period_days = current_cycle.period_length().num_days();
+        let unused_days = 1 + ((period_days - 2) as f64 * elapsed_fraction) as i64;
+        let current = subscription(
+            current_tier,
+            current_cycle,
+            now() + Duration::days(unused_days) + Duration::hours(extra_hours),
+        );
+
+        let new_price = plan_price(&new_tier, &new_cycle);
+        let credit = calculate_proration(&current, new_price, &new_cycle, now());
+        prop_assert_eq!(credit.timing, ProrationTiming::Immediate);
+        prop_assert_eq!(credit.unused_days, unused_days);
+        prop_assert!(credit.amount <= current.amount);
+
+        let applied = apply_proration_credit(new_price, credit.amount);
+        let new_invoice_amount = new_price - applied;
+        prop_assert!(new_invoice_amount >= Decimal::ZERO);
+        prop_assert_eq!(applied + new_invoice_amount, new_price);
+
+        // The whole credit is used unless it exceeds the new price; the excess carries over
+        if credit.amount <= new_price {
+            prop_assert_eq!(credit.amount + new_invoice_amount, new_price);
+        } else {
+            prop_assert_eq!(new_invoice_amount, Decimal::ZERO);
+            prop_assert_eq!(applied + (credit.amount - new_price), credit.amount);
+        }
+    }
+}
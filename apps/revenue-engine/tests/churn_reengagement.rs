@@ -0,0 +1,132 @@
+// Churn detection and re-engagement emails against seeded subscriptions
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use revenue_engine::subscription::churn::{
+    ChurnPredictor, EmailMessage, EmailProvider, ReEngagementEmailer, ReEngagementStage,
+};
+use revenue_engine::{RevenueError, RevenueResult};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Records every message instead of sending it; can be told to fail
+#[derive(Default)]
+struct FakeEmailProvider {
+    sent: Mutex<Vec<EmailMessage>>,
+    failing: Mutex<bool>,
+}
+
+#[async_trait::async_trait]
+impl EmailProvider for FakeEmailProvider {
+    async fn send(&self, message: &EmailMessage) -> RevenueResult<()> {
+        if *self.failing.lock().unwrap() {
+            return Err(RevenueError::External("provider unavailable".to_string()));
+        }
+        self.sent.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 5, 20, 9, 0, 0).unwrap()
+}
+
+async fn seed_subscriptions(pool: &PgPool) {
+    common::setup_schema(pool).await;
+}
+
+async fn insert_subscription(pool: &PgPool, email: &str, tier: &str, status: &str, days_overdue: i64) -> Uuid {
+    sqlx::query_scalar(r#"
+        INSERT INTO subscriptions (user_id, tier, status, amount, next_billing_date, metadata)
+        VALUES (gen_random_uuid(), $1, $2, 29.00, $3, $4)
+        RETURNING id
+    "#)
+        .bind(tier)
+        .bind(status)
+        .bind((now() - Duration::days(days_overdue) - Duration::hours(1)).naive_utc())
+        .bind(serde_json::json!({ "email": email }))
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+fn emailer(pool: &PgPool) -> (ReEngagementEmailer, Arc<FakeEmailProvider>) {
+    let provider = Arc::new(FakeEmailProvider::default());
+    (ReEngagementEmailer::new(pool.clone(), provider.clone()), provider)
+}
+
+#[sqlx::test]
+async fn test_detects_only_subscriptions_past_grace_period(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    insert_subscription(&pool, "due@example.com", "basic", "active", 1).await;
+    insert_subscription(&pool, "cancelled@example.com", "basic", "cancelled", 20).await;
+    let at_risk = insert_subscription(&pool, "late@example.com", "professional", "past_due", 8).await;
+
+    let events = ChurnPredictor::new(pool).detect(now()).await.unwrap();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].subscription_id, at_risk);
+    assert_eq!(events[0].days_overdue, 8);
+    assert_eq!(events[0].tier.to_string(), "professional");
+    assert_eq!(events[0].email.as_deref(), Some("late@example.com"));
+}
+
+#[sqlx::test]
+async fn test_each_stage_sends_its_template(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    insert_subscription(&pool, "three@example.com", "basic", "active", 3).await;
+    insert_subscription(&pool, "seven@example.com", "professional", "active", 7).await;
+    insert_subscription(&pool, "fourteen@example.com", "enterprise", "past_due", 14).await;
+
+    let (emailer, provider) = emailer(&pool);
+    let sent = emailer.run(&ChurnPredictor::new(pool.clone()), now()).await.unwrap();
+    assert_eq!(sent, 3);
+
+    let messages = provider.sent.lock().unwrap().clone();
+    let by_recipient = |to: &str| messages.iter().find(|m| m.to == to).unwrap().clone();
+    assert!(by_recipient("three@example.com").subject.contains("overdue"));
+    assert!(by_recipient("seven@example.com").subject.contains("20% off"));
+    assert!(by_recipient("fourteen@example.com").subject.starts_with("Final notice"));
+}
+
+#[sqlx::test]
+async fn test_emails_are_not_repeated(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    insert_subscription(&pool, "late@example.com", "basic", "active", 4).await;
+    let predictor = ChurnPredictor::new(pool.clone());
+    let (emailer, provider) = emailer(&pool);
+
+    assert_eq!(emailer.run(&predictor, now()).await.unwrap(), 1);
+    assert_eq!(emailer.run(&predictor, now() + Duration::days(1)).await.unwrap(), 0);
+
+    // Crossing into the next stage sends the next template once
+    assert_eq!(emailer.run(&predictor, now() + Duration::days(3)).await.unwrap(), 1);
+    assert_eq!(emailer.run(&predictor, now() + Duration::days(4)).await.unwrap(), 0);
+
+    let stages: Vec<String> = sqlx::query_scalar("SELECT stage FROM churn_emails_sent ORDER BY sent_at")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stages, vec!["reminder", "discount_offer"]);
+    assert_eq!(provider.sent.lock().unwrap().len(), 2);
+}
+
+#[sqlx::test]
+async fn test_failed_send_is_retried(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    insert_subscription(&pool, "late@example.com", "basic", "active", 5).await;
+    let predictor = ChurnPredictor::new(pool.clone());
+    let (emailer, provider) = emailer(&pool);
+
+    *provider.failing.lock().unwrap() = true;
+    let event = predictor.detect(now()).await.unwrap().remove(0);
+    assert!(emailer.handle(&event).await.is_err());
+
+    *provider.failing.lock().unwrap() = false;
+    assert_eq!(emailer.handle(&event).await.unwrap(), Some(ReEngagementStage::Reminder));
+    assert_eq!(provider.sent.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,84 @@
+// BG/NBD lifetime value predictions against a real database
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use revenue_engine::{ClvAnalysis, CLV_HORIZON_MONTHS};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// A subscriber who paid `amount` every month for `months` months, the last payment `lapsed` months ago.
+// The payments settle one invoice, which is enough for the model.
+async fn customer(pool: &PgPool, status: &str, months: i32, lapsed: i32, amount: Decimal) -> Uuid {
+    let user_id = Uuid::new_v4();
+    sqlx::query(r#"
+        INSERT INTO subscriptions (user_id, tier, status, amount, next_billing_date)
+        VALUES ($1, 'basic', $2, $3, NOW())
+    "#).bind(user_id).bind(status).bind(amount)
+        .execute(pool).await.unwrap();
+    sqlx::query(r#"
+        INSERT INTO invoices (user_id, invoice_number, amount, total_amount, due_date)
+        VALUES ($1, 'INV-' || $1::text, $2, $2, NOW())
+    "#).bind(user_id).bind(amount)
+        .execute(pool).await.unwrap();
+    sqlx::query(r#"
+        INSERT INTO payments (invoice_id, user_id, amount, amount_usd, charged_amount, status, payment_method_type, processed_at)
+        SELECT invoices.id, $1, $2, $2, $2, 'succeeded', 'card', NOW() - make_interval(months => $3 + n)
+        FROM invoices, generate_series(0, $4 - 1) AS n
+        WHERE invoices.user_id = $1
+    "#).bind(user_id).bind(amount).bind(lapsed).bind(months)
+        .execute(pool).await.unwrap();
+    user_id
+}
+
+#[sqlx::test]
+async fn test_refresh_predictions_and_distribution(pool: PgPool) {
+    common::setup_schema(&pool).await;
+    let loyal = customer(&pool, "active", 12, 0, Decimal::new(99, 0)).await;
+    let lapsed = customer(&pool, "active", 3, 8, Decimal::new(99, 0)).await;
+    for i in 0..10 {
+        customer(&pool, "active", 2 + i % 6, i % 3, Decimal::new(29, 0)).await;
+        customer(&pool, "cancelled", 1 + i % 4, 6, Decimal::new(29, 0)).await;
+    }
+    // Failed payments are not transactions
+    sqlx::query(r#"
+        INSERT INTO payments (invoice_id, user_id, amount, amount_usd, charged_amount, status, payment_method_type)
+        SELECT id, user_id, 99.00, 99.00, 99.00, 'failed', 'card' FROM invoices WHERE user_id = $1
+    "#).bind(lapsed).execute(&pool).await.unwrap();
+
+    let analysis = ClvAnalysis::new(pool.clone());
+    let predictions = analysis.refresh_predictions().await.unwrap();
+
+    // Only active subscribers are predicted
+    assert_eq!(predictions.len(), 12);
+    let prediction = |user_id| predictions.iter().find(|p| p.user_id == user_id).unwrap();
+    assert_eq!(prediction(loyal).average_revenue, Decimal::new(9900, 2));
+    assert!(prediction(loyal).predicted_clv > prediction(lapsed).predicted_clv);
+    assert!(prediction(loyal).probability_alive > prediction(lapsed).probability_alive);
+
+    let horizon: i32 = sqlx::query_scalar("SELECT DISTINCT horizon_months FROM clv_predictions")
+        .fetch_one(&pool).await.unwrap();
+    assert_eq!(horizon, CLV_HORIZON_MONTHS);
+
+    let buckets = analysis.get_clv_distribution().await.unwrap();
+    assert_eq!(buckets.iter().map(|b| b.quartile).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert_eq!(buckets.iter().map(|b| b.customers).sum::<i64>(), 12);
+    assert!(buckets.windows(2).all(|pair| pair[0].max_clv <= pair[1].min_clv));
+    let total: Decimal = predictions.iter().map(|p| p.predicted_clv).sum();
+    assert_eq!(buckets.iter().map(|b| b.total_clv).sum::<Decimal>(), total);
+
+    // A refresh replaces rather than accumulates
+    analysis.refresh_predictions().await.unwrap();
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clv_predictions").fetch_one(&pool).await.unwrap();
+    assert_eq!(stored, 12);
+    assert!(analysis.average_clv().await.unwrap().is_some());
+}
+
+#[sqlx::test]
+async fn test_distribution_empty_before_first_refresh(pool: PgPool) {
+    let analysis = ClvAnalysis::new(pool);
+    assert!(analysis.get_clv_distribution().await.unwrap().is_empty());
+    assert_eq!(analysis.average_clv().await.unwrap(), None);
+}
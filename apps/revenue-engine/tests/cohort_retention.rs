@@ -0,0 +1,66 @@
+// Cohort retention against seeded subscriptions
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use revenue_engine::{CohortRetentionAnalysis, YearMonth};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+async fn seed_subscriptions(pool: &PgPool) {
+    common::setup_schema(pool).await;
+
+    // January 2024 cohort: two long-lived subscribers, one churned in January, one in February
+    sqlx::query(r#"
+        INSERT INTO subscriptions (user_id, tier, amount, billing_cycle, next_billing_date, cancelled_at, created_at) VALUES
+            (gen_random_uuid(), 'basic', 100.00, 'monthly', NOW(), NULL, '2024-01-03'),
+            (gen_random_uuid(), 'basic', 50.00, 'monthly', NOW(), '2024-01-20', '2024-01-05'),
+            (gen_random_uuid(), 'basic', 30.00, 'monthly', NOW(), '2024-02-10', '2024-01-12'),
+            (gen_random_uuid(), 'professional', 1200.00, 'annual', NOW(), NULL, '2024-01-28'),
+            (gen_random_uuid(), 'enterprise', 999.00, 'monthly', NOW(), NULL, '2024-02-01')
+    "#).execute(pool).await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_january_cohort_retention(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+
+    let analysis = CohortRetentionAnalysis::new(pool);
+    let rows = analysis.compute("2024-01".parse().unwrap()).await.unwrap();
+
+    assert_eq!(rows[0].months_since_join, 0);
+    assert_eq!(rows[0].active_subscribers, 4);
+    assert_eq!(rows[0].mrr, Decimal::new(280, 0));
+    assert_eq!(rows[0].retention_rate, 1.0);
+
+    assert_eq!(rows[1].active_subscribers, 3);
+    assert_eq!(rows[1].mrr, Decimal::new(230, 0));
+    assert_eq!(rows[1].retention_rate, 0.75);
+
+    assert_eq!(rows[2].active_subscribers, 2);
+    assert_eq!(rows[2].mrr, Decimal::new(200, 0));
+    assert_eq!(rows[2].retention_rate, 0.5);
+
+    // No further churn after February
+    assert!(rows[3..].iter().all(|row| row.retention_rate == 0.5));
+}
+
+#[sqlx::test]
+async fn test_retention_matrix_spans_cohorts(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+
+    let analysis = CohortRetentionAnalysis::new(pool);
+    let matrix = analysis
+        .compute_matrix(YearMonth::new(2024, 1).unwrap(), YearMonth::new(2024, 3).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(matrix.cohorts, vec!["2024-01", "2024-02", "2024-03"]);
+    assert_eq!(matrix.retention[0][1], Some(0.75));
+    assert_eq!(matrix.retention[1][0], Some(1.0));
+    // Empty cohorts report zero retention rather than dividing by zero
+    assert_eq!(matrix.retention[2][0], Some(0.0));
+    // The younger cohort has one fewer observed month than the oldest
+    assert_eq!(matrix.retention[1].last(), Some(&None));
+}
@@ -0,0 +1,11 @@
+// Schema shared by the database integration tests: sqlx::test applies ./migrations, then
+// `setup_schema` runs the same table setup the subscription and billing services run at startup,
+// so seeded rows and the queries under test see the production columns and constraints
+
+use revenue_engine::{BillingEngine, SubscriptionManager};
+use sqlx::PgPool;
+
+pub async fn setup_schema(pool: &PgPool) {
+    SubscriptionManager::setup_subscription_tables(pool).await.unwrap();
+    BillingEngine::setup_billing_tables(pool).await.unwrap();
+}
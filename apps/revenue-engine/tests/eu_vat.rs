@@ -0,0 +1,121 @@
+// EU VAT calculation and quarterly reporting against a real database
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use std::sync::Arc;
+use revenue_engine::billing::tax::{TaxCalculator, TaxTreatment, VatIdValidator};
+use revenue_engine::{BillingAddress, RevenueError, RevenueResult};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+// Confirms DE123456789, has no answer for French numbers and rejects everything else
+#[derive(Debug)]
+struct FakeVies;
+
+#[async_trait::async_trait]
+impl VatIdValidator for FakeVies {
+    async fn is_valid(&self, prefix: &str, number: &str) -> RevenueResult<bool> {
+        match prefix {
+            "FR" => Err(RevenueError::External("MS_UNAVAILABLE".to_string())),
+            _ => Ok(prefix == "DE" && number == "123456789"),
+        }
+    }
+}
+
+fn calculator(pool: PgPool) -> TaxCalculator {
+    TaxCalculator::new(pool, Arc::new(FakeVies))
+}
+
+async fn billed_to(calculator: &TaxCalculator, country_code: &str, vat_id: Option<&str>) -> Uuid {
+    let user_id = Uuid::new_v4();
+    calculator.set_billing_address(BillingAddress {
+        user_id,
+        name: None,
+        line1: "1 Main Street".to_string(),
+        line2: None,
+        city: "Capital".to_string(),
+        postal_code: None,
+        country_code: country_code.to_string(),
+        vat_id: vat_id.map(str::to_string),
+    }).await.unwrap();
+    user_id
+}
+
+#[sqlx::test]
+async fn test_vat_by_billing_country(pool: PgPool) {
+    let calculator = calculator(pool);
+    let amount = Decimal::new(29900, 2);
+
+    let german = billed_to(&calculator, "de", None).await;
+    let line = calculator.calculate(german, amount).await.unwrap();
+    assert_eq!(line.treatment, TaxTreatment::Vat);
+    assert_eq!(line.country_code.as_deref(), Some("DE"));
+    assert_eq!(line.rate, Decimal::new(19, 2));
+    assert_eq!(line.tax_amount, Decimal::new(5681, 2));
+
+    let american = billed_to(&calculator, "US", None).await;
+    assert_eq!(calculator.calculate(american, amount).await.unwrap().treatment, TaxTreatment::SalesTax);
+    assert_eq!(calculator.calculate(Uuid::new_v4(), amount).await.unwrap().treatment, TaxTreatment::SalesTax);
+}
+
+#[sqlx::test]
+async fn test_reverse_charge_needs_confirmed_vat_id(pool: PgPool) {
+    let calculator = calculator(pool);
+    let amount = Decimal::new(29900, 2);
+
+    let business = billed_to(&calculator, "DE", Some("DE 123 456 789")).await;
+    let address = calculator.billing_address(business).await.unwrap().unwrap();
+    assert_eq!(address.vat_id.as_deref(), Some("DE123456789"));
+    let line = calculator.calculate(business, amount).await.unwrap();
+    assert_eq!(line.treatment, TaxTreatment::ReverseCharge);
+    assert_eq!(line.tax_amount, Decimal::ZERO);
+
+    // Unknown to VIES, registered in another country, or VIES unavailable: VAT is charged
+    for (country, vat_id) in [("DE", "DE999999999"), ("AT", "DE123456789"), ("FR", "FR12345678901")] {
+        let user_id = billed_to(&calculator, country, Some(vat_id)).await;
+        assert_eq!(calculator.calculate(user_id, amount).await.unwrap().treatment, TaxTreatment::Vat);
+    }
+
+    let invalid = calculator.set_billing_address(BillingAddress {
+        vat_id: Some("123".to_string()),
+        ..address
+    }).await;
+    assert!(matches!(invalid, Err(RevenueError::Validation(_))));
+}
+
+#[sqlx::test]
+async fn test_quarterly_vat_report(pool: PgPool) {
+    common::setup_schema(&pool).await;
+    sqlx::query(r#"
+        INSERT INTO invoices (
+            user_id, invoice_number, amount, total_amount, due_date, status, created_at,
+            vat_country, vat_rate, vat_amount, vat_reverse_charge
+        )
+        SELECT gen_random_uuid(), 'INV-' || n, amount, amount + COALESCE(vat_amount, 0), created_at + INTERVAL '30 days',
+            status, created_at, vat_country, vat_rate, vat_amount, vat_reverse_charge
+        FROM (VALUES
+            (1, 100.00, 'paid', TIMESTAMP '2024-07-03', 'DE', 0.1900, 19.00, false),
+            (2, 200.00, 'pending', TIMESTAMP '2024-09-30 23:59:59', 'DE', 0.1900, 38.00, false),
+            (3, 500.00, 'paid', TIMESTAMP '2024-08-15', 'DE', 0.0000, 0.00, true),
+            (4, 100.00, 'paid', TIMESTAMP '2024-08-15', 'NL', 0.2100, 21.00, false),
+            (5, 100.00, 'cancelled', TIMESTAMP '2024-08-15', 'NL', 0.2100, 21.00, false),
+            (6, 100.00, 'paid', TIMESTAMP '2024-10-01', 'NL', 0.2100, 21.00, false),
+            (7, 100.00, 'paid', TIMESTAMP '2024-08-15', NULL, NULL, NULL, NULL)
+        ) AS seeded (n, amount, status, created_at, vat_country, vat_rate, vat_amount, vat_reverse_charge)
+    "#).execute(&pool).await.unwrap();
+
+    let report = calculator(pool).quarterly_vat_report(2024, 3).await.unwrap();
+
+    assert_eq!(report.lines.len(), 3);
+    let german = &report.lines[0];
+    assert_eq!((german.country_code.as_str(), german.reverse_charge), ("DE", false));
+    assert_eq!(german.invoice_count, 2);
+    assert_eq!(german.vat_amount, Decimal::new(5700, 2));
+    assert!(report.lines[1].reverse_charge);
+    assert_eq!(report.lines[2].country_code, "NL");
+    assert_eq!(report.total_taxable_amount, Decimal::new(90000, 2));
+    assert_eq!(report.total_vat_amount, Decimal::new(7800, 2));
+}
@@ -0,0 +1,119 @@
+// Tier-gated feature flags against seeded subscriptions and flags
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use std::sync::Arc;
+use axum::{body::Body, http::Request, routing::get, Extension, Router};
+use revenue_engine::subscription::feature_flags::{inject_feature_flags, USER_ID_HEADER};
+use revenue_engine::{EnabledFeatureFlags, FeatureFlagService, SubscriptionTier};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+async fn seed_subscriptions(pool: &PgPool) {
+    common::setup_schema(pool).await;
+}
+
+async fn subscribe(pool: &PgPool, tier: &str, status: &str) -> Uuid {
+    let user_id = Uuid::new_v4();
+    add_subscription(pool, user_id, tier, status).await;
+    user_id
+}
+
+async fn add_subscription(pool: &PgPool, user_id: Uuid, tier: &str, status: &str) {
+    sqlx::query(r#"
+        INSERT INTO subscriptions (user_id, tier, status, amount, next_billing_date)
+        VALUES ($1, $2, $3, 99.00, NOW() + INTERVAL '1 month')
+    "#)
+        .bind(user_id)
+        .bind(tier)
+        .bind(status)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test]
+async fn test_check_at_tier_boundaries(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    let service = FeatureFlagService::new(pool.clone());
+    service.set_flag("predictive_analytics", SubscriptionTier::Professional, true).await.unwrap();
+
+    let basic = subscribe(&pool, "basic", "active").await;
+    let professional = subscribe(&pool, "professional", "active").await;
+    let enterprise = subscribe(&pool, "enterprise", "trialing").await;
+    let custom = subscribe(&pool, "custom", "active").await;
+
+    assert!(!service.check(basic, "predictive_analytics").await);
+    assert!(service.check(professional, "predictive_analytics").await);
+    assert!(service.check(enterprise, "predictive_analytics").await);
+    assert!(service.check(custom, "predictive_analytics").await);
+
+    // Unknown flags and users without a subscription are denied
+    assert!(!service.check(custom, "unknown_flag").await);
+    assert!(!service.check(Uuid::new_v4(), "predictive_analytics").await);
+}
+
+#[sqlx::test]
+async fn test_only_active_subscriptions_count(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    let service = FeatureFlagService::new(pool.clone());
+    service.set_flag("sso", SubscriptionTier::Enterprise, true).await.unwrap();
+
+    let user_id = subscribe(&pool, "enterprise", "cancelled").await;
+    add_subscription(&pool, user_id, "basic", "active").await;
+    assert_eq!(service.active_tier(user_id).await.unwrap(), Some(SubscriptionTier::Basic));
+    assert!(!service.check(user_id, "sso").await);
+
+    // The highest active tier wins
+    add_subscription(&pool, user_id, "enterprise", "active").await;
+    assert_eq!(service.active_tier(user_id).await.unwrap(), Some(SubscriptionTier::Enterprise));
+    assert!(service.check(user_id, "sso").await);
+}
+
+#[sqlx::test]
+async fn test_admin_changes_apply_immediately(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    let service = FeatureFlagService::new(pool.clone());
+    let user_id = subscribe(&pool, "professional", "active").await;
+
+    service.set_flag("custom_dashboards", SubscriptionTier::Professional, true).await.unwrap();
+    assert!(service.check(user_id, "custom_dashboards").await);
+
+    service.set_flag("custom_dashboards", SubscriptionTier::Professional, false).await.unwrap();
+    assert!(!service.check(user_id, "custom_dashboards").await);
+
+    let flag = service.set_flag("custom_dashboards", SubscriptionTier::Enterprise, true).await.unwrap();
+    assert_eq!(flag.min_tier, SubscriptionTier::Enterprise);
+    assert!(!service.check(user_id, "custom_dashboards").await);
+    assert_eq!(service.list_flags().await.unwrap().len(), 1);
+}
+
+#[sqlx::test]
+async fn test_middleware_injects_enabled_flags(pool: PgPool) {
+    seed_subscriptions(&pool).await;
+    let service = Arc::new(FeatureFlagService::new(pool.clone()));
+    service.set_flag("real_time_alerts", SubscriptionTier::Basic, true).await.unwrap();
+    service.set_flag("sso", SubscriptionTier::Enterprise, true).await.unwrap();
+    let user_id = subscribe(&pool, "professional", "active").await;
+
+    let app = Router::new()
+        .route("/", get(|Extension(flags): Extension<EnabledFeatureFlags>| async move {
+            flags.flags.into_iter().collect::<Vec<_>>().join(",")
+        }))
+        .layer(axum::middleware::from_fn_with_state(service, inject_feature_flags));
+
+    let body = |response: axum::response::Response| async move {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+
+    let request = Request::get("/").header(USER_ID_HEADER, user_id.to_string()).body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(body(response).await, "real_time_alerts");
+
+    let anonymous = app.oneshot(Request::get("/").body(Body::empty()).unwrap()).await.unwrap();
+    assert_eq!(body(anonymous).await, "");
+}
@@ -0,0 +1,103 @@
+// Cache-Control, ETag revalidation and compression on a router using the production layers
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    middleware::from_fn,
+    routing::{get, post},
+    Router,
+};
+use revenue_engine::http_cache::{cache_headers, compression_layer, cache_control_for};
+use tower::{ServiceBuilder, ServiceExt};
+use tower_http::compression::CompressionLevel;
+
+fn router() -> Router {
+    let payload = "revenue ".repeat(512);
+    Router::new()
+        .route("/health", get(|| async { "ok" }))
+        .route("/api/v1/revenue/dashboard", get(move || async move { payload }))
+        .route("/api/v1/analytics/usage", post(|| async { "tracked" }))
+        .layer(
+            ServiceBuilder::new()
+                .layer(compression_layer(CompressionLevel::Default))
+                .layer(from_fn(cache_headers)),
+        )
+}
+
+fn get_request(path: &str) -> Request<Body> {
+    Request::builder().uri(path).body(Body::empty()).unwrap()
+}
+
+#[test]
+fn test_cache_control_policies() {
+    assert_eq!(cache_control_for("/health"), Some("no-store"));
+    assert_eq!(cache_control_for("/api/v1/revenue/dashboard"), Some("private, max-age=5"));
+    assert_eq!(cache_control_for("/api/v1/analytics/cohort-retention"), Some("private, max-age=30"));
+    assert_eq!(cache_control_for("/api/v1/analyticsx"), None);
+    assert_eq!(cache_control_for("/api/v1/billing/invoices"), None);
+}
+
+#[tokio::test]
+async fn test_unchanged_response_returns_not_modified() {
+    let first = router().oneshot(get_request("/api/v1/revenue/dashboard")).await.unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(first.headers()[header::CACHE_CONTROL], "private, max-age=5");
+    let etag = first.headers()[header::ETAG].clone();
+    assert!(etag.to_str().unwrap().starts_with("W/\""));
+
+    let revalidate = Request::builder()
+        .uri("/api/v1/revenue/dashboard")
+        .header(header::IF_NONE_MATCH, etag.clone())
+        .body(Body::empty())
+        .unwrap();
+    let second = router().oneshot(revalidate).await.unwrap();
+    assert_eq!(second.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(second.headers()[header::ETAG], etag);
+    assert!(to_bytes(second.into_body(), usize::MAX).await.unwrap().is_empty());
+
+    let stale = Request::builder()
+        .uri("/api/v1/revenue/dashboard")
+        .header(header::IF_NONE_MATCH, "W/\"0000\"")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(router().oneshot(stale).await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_health_is_never_cached() {
+    let response = router().oneshot(get_request("/health")).await.unwrap();
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+    assert!(response.headers().get(header::ETAG).is_none());
+}
+
+#[tokio::test]
+async fn test_non_get_requests_are_not_tagged() {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/v1/analytics/usage")
+        .body(Body::empty())
+        .unwrap();
+    let response = router().oneshot(request).await.unwrap();
+    assert!(response.headers().get(header::ETAG).is_none());
+    assert!(response.headers().get(header::CACHE_CONTROL).is_none());
+}
+
+#[tokio::test]
+async fn test_brotli_preferred_with_gzip_fallback() {
+    let etag_for = |encoding: &'static str| async move {
+        let request = Request::builder()
+            .uri("/api/v1/revenue/dashboard")
+            .header(header::ACCEPT_ENCODING, encoding)
+            .body(Body::empty())
+            .unwrap();
+        let response = router().oneshot(request).await.unwrap();
+        (response.headers()[header::CONTENT_ENCODING].clone(), response.headers()[header::ETAG].clone())
+    };
+
+    let (br_encoding, br_etag) = etag_for("br").await;
+    let (gzip_encoding, gzip_etag) = etag_for("gzip").await;
+    assert_eq!(br_encoding, "br");
+    assert_eq!(gzip_encoding, "gzip");
+    // Tags are computed before compression, so both encodings revalidate against one ETag
+    assert_eq!(br_etag, gzip_etag);
+}
@@ -0,0 +1,53 @@
+// Subscription pricing experiments against a real database
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+use revenue_engine::{AbTestEventType, AbTestManager, ExperimentVariant, SubscriptionTier};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn variants() -> Vec<ExperimentVariant> {
+    vec![
+        ExperimentVariant { name: "control".to_string(), weight: 1, price_multiplier: Decimal::ONE },
+        ExperimentVariant { name: "plus_10".to_string(), weight: 1, price_multiplier: Decimal::new(110, 2) },
+    ]
+}
+
+#[sqlx::test]
+async fn test_one_active_experiment_per_tier(pool: PgPool) {
+    let manager = AbTestManager::new(pool);
+    let experiment = manager.create_experiment("pro_price", SubscriptionTier::Professional, variants()).await.unwrap();
+
+    assert!(manager.create_experiment("pro_price_2", SubscriptionTier::Professional, variants()).await.is_err());
+    assert!(manager.create_experiment("ent_price", SubscriptionTier::Enterprise, variants()).await.is_ok());
+
+    manager.end_experiment(experiment.id).await.unwrap();
+    let base = SubscriptionTier::Professional.monthly_price();
+    let price = manager.price_for(Uuid::new_v4(), &SubscriptionTier::Professional, base).await.unwrap();
+    assert_eq!(price.amount, base);
+    assert_eq!(price.assignment, None);
+}
+
+#[sqlx::test]
+async fn test_events_attributed_to_assigned_variant(pool: PgPool) {
+    let manager = AbTestManager::new(pool);
+    let experiment = manager.create_experiment("pro_price", SubscriptionTier::Professional, variants()).await.unwrap();
+
+    let users: Vec<Uuid> = (0..200).map(|_| Uuid::new_v4()).collect();
+    for (i, user) in users.iter().enumerate() {
+        let exposure = manager.record_event(*user, experiment.id, AbTestEventType::Exposure).await.unwrap();
+        assert_eq!(exposure.variant, experiment.assign(*user).name);
+        if i % 4 == 0 {
+            manager.record_event(*user, experiment.id, AbTestEventType::Conversion).await.unwrap();
+        }
+    }
+    // Repeated events from one user count once
+    manager.record_event(users[0], experiment.id, AbTestEventType::Conversion).await.unwrap();
+
+    let stats = manager.get_experiment_results(experiment.id).await.unwrap();
+    assert_eq!(stats.control, "control");
+    assert_eq!(stats.variants.iter().map(|v| v.users).sum::<u64>(), 200);
+    assert_eq!(stats.variants.iter().map(|v| v.conversions).sum::<u64>(), 50);
+    assert!(stats.variants[1].p_value.is_some());
+}
@@ -0,0 +1,99 @@
+// Revenue record persistence against the migrated schema
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+use revenue_engine::{RevenueError, RevenueRecord, RevenueStream};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+fn bridge_stream(user_id: Uuid, amount: i64, fee: i64) -> RevenueStream {
+    RevenueStream::BridgeTransaction {
+        from_token: "NOCK".to_string(),
+        to_token: "wNOCK".to_string(),
+        amount: Decimal::new(amount, 0),
+        fee_amount: Decimal::new(fee, 0),
+        user_id,
+    }
+}
+
+#[sqlx::test]
+async fn test_insert_persists_record(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    let record = RevenueRecord::from_stream(&bridge_stream(user_id, 1000, 3)).unwrap();
+    let id = record.insert(&pool).await.unwrap();
+    assert_eq!(id, record.id);
+
+    let row = sqlx::query(
+        "SELECT stream_type, user_id, amount, fee, metadata FROM revenue_records WHERE id = $1"
+    )
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+    assert_eq!(row.get::<String, _>("stream_type"), "bridge_transaction");
+    assert_eq!(row.get::<Option<Uuid>, _>("user_id"), Some(user_id));
+    assert_eq!(row.get::<Decimal, _>("amount"), Decimal::new(1000, 0));
+    assert_eq!(row.get::<Decimal, _>("fee"), Decimal::new(3, 0));
+    assert_eq!(row.get::<serde_json::Value, _>("metadata")["to_token"], "wNOCK");
+}
+
+#[sqlx::test]
+async fn test_aggregate_revenue_refreshed_on_insert(pool: PgPool) {
+    let user_id = Uuid::new_v4();
+    for (amount, fee) in [(1000, 3), (500, 2)] {
+        RevenueRecord::from_stream(&bridge_stream(user_id, amount, fee))
+            .unwrap()
+            .insert(&pool)
+            .await
+            .unwrap();
+    }
+    RevenueRecord::from_stream(&RevenueStream::MiningPool {
+        amount: Decimal::new(200, 0),
+        fee_percentage: 2.5,
+        user_id,
+    })
+        .unwrap()
+        .insert(&pool)
+        .await
+        .unwrap();
+
+    let rows = sqlx::query(
+        "SELECT stream_type, record_count, total_amount, total_fees FROM aggregate_revenue ORDER BY stream_type"
+    )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get::<String, _>("stream_type"), "bridge_transaction");
+    assert_eq!(rows[0].get::<i64, _>("record_count"), 2);
+    assert_eq!(rows[0].get::<Decimal, _>("total_amount"), Decimal::new(1500, 0));
+    assert_eq!(rows[0].get::<Decimal, _>("total_fees"), Decimal::new(5, 0));
+    assert_eq!(rows[1].get::<String, _>("stream_type"), "mining_pool");
+    assert_eq!(rows[1].get::<Decimal, _>("total_fees"), Decimal::new(5, 0));
+}
+
+#[sqlx::test]
+async fn test_duplicate_insert_rolls_back(pool: PgPool) {
+    let record = RevenueRecord::from_stream(&bridge_stream(Uuid::new_v4(), 1000, 3)).unwrap();
+    record.insert(&pool).await.unwrap();
+    assert!(matches!(record.insert(&pool).await, Err(RevenueError::Database(_))));
+
+    let count: i64 = sqlx::query_scalar("SELECT record_count FROM aggregate_revenue")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn test_invalid_fee_percentage_rejected() {
+    let stream = RevenueStream::MiningPool {
+        amount: Decimal::new(200, 0),
+        fee_percentage: f64::NAN,
+        user_id: Uuid::new_v4(),
+    };
+    assert!(matches!(RevenueRecord::from_stream(&stream), Err(RevenueError::Validation(_))));
+}
@@ -0,0 +1,71 @@
+// Stripe webhook idempotency and payment failure handling
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+mod common;
+
+use revenue_engine::stripe_export::StripeEvent;
+use revenue_engine::subscription::churn::ChurnPredictor;
+use revenue_engine::ProcessedWebhooks;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn event(id: &str, event_type: &str) -> StripeEvent {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "type": event_type,
+        "data": { "object": {} }
+    })).unwrap()
+}
+
+async fn seed_subscription(pool: &PgPool, stripe_subscription_id: &str, status: &str) -> Uuid {
+    sqlx::query_scalar(r#"
+        INSERT INTO subscriptions (user_id, tier, status, amount, next_billing_date, stripe_subscription_id)
+        VALUES (gen_random_uuid(), 'professional', $1, 99.00, NOW(), $2)
+        RETURNING id
+    "#)
+        .bind(status)
+        .bind(stripe_subscription_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_event_is_claimed_once(pool: PgPool) {
+    let processed = ProcessedWebhooks::new(pool.clone());
+    let delivery = event("evt_1NG8Du2eZvKYlo2CUI79vXWy", "payment_intent.succeeded");
+
+    assert!(processed.claim(&delivery).await.unwrap());
+    assert!(!processed.claim(&delivery).await.unwrap(), "redelivery must be recognised");
+    assert!(processed.claim(&event("evt_other", "payment_intent.succeeded")).await.unwrap());
+}
+
+#[sqlx::test]
+async fn test_released_event_can_be_retried(pool: PgPool) {
+    let processed = ProcessedWebhooks::new(pool.clone());
+    let delivery = event("evt_1NG8Du2eZvKYlo2CUI79vXWy", "invoice.payment_failed");
+
+    assert!(processed.claim(&delivery).await.unwrap());
+    processed.release(&delivery.id).await.unwrap();
+    assert!(processed.claim(&delivery).await.unwrap());
+}
+
+#[sqlx::test]
+async fn test_payment_failure_marks_subscription_past_due(pool: PgPool) {
+    common::setup_schema(&pool).await;
+    let active = seed_subscription(&pool, "sub_active", "active").await;
+    seed_subscription(&pool, "sub_cancelled", "cancelled").await;
+    let predictor = ChurnPredictor::new(pool.clone());
+
+    assert_eq!(predictor.record_payment_failure("sub_active").await.unwrap(), Some(active));
+    assert_eq!(predictor.record_payment_failure("sub_cancelled").await.unwrap(), None);
+    assert_eq!(predictor.record_payment_failure("sub_unknown").await.unwrap(), None);
+
+    let status: String = sqlx::query_scalar("SELECT status FROM subscriptions WHERE id = $1")
+        .bind(active)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(status, "past_due");
+}
@@ -0,0 +1,103 @@
+// Webhook dead-lettering and replay against the migrated schema
+// Requires DATABASE_URL pointing at a PostgreSQL server; sqlx::test creates a fresh database
+// per test and applies ./migrations
+
+use std::time::Duration;
+use revenue_engine::billing::webhook::RetryPolicy;
+use revenue_engine::{RevenueError, WebhookDispatcher, WebhookEndpoint, WebhookEvent};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn dispatcher(pool: PgPool, server: &MockServer) -> WebhookDispatcher {
+    WebhookDispatcher::with_retry_policy(
+        pool,
+        vec![WebhookEndpoint {
+            url: format!("{}/hooks", server.uri()),
+            secret: "whsec_operator".to_string(),
+        }],
+        RetryPolicy { max_retries: 5, base_delay: Duration::from_millis(1) },
+    )
+}
+
+fn invoice_generated() -> WebhookEvent {
+    WebhookEvent::InvoiceGenerated {
+        invoice_id: Uuid::new_v4(),
+        invoice_number: "INV-000042".to_string(),
+        user_id: Uuid::new_v4(),
+        total_amount: Decimal::new(32516, 2),
+        currency: "USD".to_string(),
+        due_date: chrono::Utc::now(),
+    }
+}
+
+async fn only_dead_letter(pool: &PgPool) -> Uuid {
+    sqlx::query_scalar("SELECT id FROM webhook_dead_letter")
+        .fetch_one(pool)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test]
+async fn test_exhausted_delivery_is_dead_lettered(pool: PgPool) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+
+    let dispatcher = dispatcher(pool.clone(), &server);
+    dispatcher.send(invoice_generated()).await;
+
+    // One initial attempt plus five retries
+    assert_eq!(server.received_requests().await.unwrap().len(), 6);
+
+    let dead_letter = dispatcher.get_dead_letter(only_dead_letter(&pool).await).await.unwrap();
+    assert_eq!(dead_letter.event_type, "invoice.generated");
+    assert_eq!(dead_letter.attempts, 6);
+    assert_eq!(dead_letter.payload["data"]["invoice_number"], "INV-000042");
+    assert!(dead_letter.last_error.contains("500"));
+    assert!(dead_letter.replayed_at.is_none());
+}
+
+#[sqlx::test]
+async fn test_replay_redelivers_original_payload(pool: PgPool) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(6)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    let dispatcher = dispatcher(pool.clone(), &server);
+    dispatcher.send(invoice_generated()).await;
+    let dead_letter_id = only_dead_letter(&pool).await;
+
+    let replayed = dispatcher.replay(dead_letter_id).await.unwrap();
+    assert!(replayed.replayed_at.is_some());
+    assert_eq!(replayed.attempts, 7);
+
+    let requests = server.received_requests().await.unwrap();
+    let original: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let redelivered: serde_json::Value = serde_json::from_slice(&requests[6].body).unwrap();
+    assert_eq!(original, redelivered);
+
+    assert!(matches!(dispatcher.replay(dead_letter_id).await, Err(RevenueError::Validation(_))));
+}
+
+#[sqlx::test]
+async fn test_replay_unknown_dead_letter(pool: PgPool) {
+    let server = MockServer::start().await;
+    let dispatcher = dispatcher(pool, &server);
+
+    assert!(matches!(dispatcher.replay(Uuid::new_v4()).await, Err(RevenueError::Billing(_))));
+}
@@ -0,0 +1,183 @@
+// Bridge configuration file
+// Parameters for `initialize_bridge`, read from TOML or JSON and checked against the
+// program's own constraints before any transaction is built
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeConfig {
+    /// Validator public keys, base58
+    pub validators: Vec<String>,
+    pub threshold: u8,
+    /// Basis points
+    pub fee_rate: u16,
+    pub daily_limit: u64,
+    /// Seconds
+    pub emergency_delay: i64,
+}
+
+/// A config that passed validation, with validator keys parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatedConfig {
+    pub validators: Vec<Pubkey>,
+    pub threshold: u8,
+    pub fee_rate: u16,
+    pub daily_limit: u64,
+    pub emergency_delay: i64,
+}
+
+impl BridgeConfig {
+    /// Loads a config, choosing the format from the file extension
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => bail!("Unsupported config format for {}: expected .toml or .json", path.display()),
+        }
+        .with_context(|| format!("Invalid config {}", path.display()))
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self> {
+        Ok(serde_json::from_str(contents)?)
+    }
+
+    pub fn validate(&self) -> Result<ValidatedConfig> {
+        let validators = self
+            .validators
+            .iter()
+            .enumerate()
+            .map(|(index, key)| {
+                Pubkey::from_str(key).with_context(|| format!("validators[{}]: invalid public key {:?}", index, key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        nock_bridge::check_bridge_params(
+            &validators,
+            self.threshold,
+            self.fee_rate,
+            self.daily_limit,
+            self.emergency_delay,
+        )
+        .map_err(describe_program_error)?;
+
+        Ok(ValidatedConfig {
+            validators,
+            threshold: self.threshold,
+            fee_rate: self.fee_rate,
+            daily_limit: self.daily_limit,
+            emergency_delay: self.emergency_delay,
+        })
+    }
+}
+
+/// The program's error message, without Anchor's on-chain log framing
+fn describe_program_error(error: anchor_lang::error::Error) -> anyhow::Error {
+    match error {
+        anchor_lang::error::Error::AnchorError(e) => anyhow::anyhow!("{} ({})", e.error_msg, e.error_name),
+        other => anyhow::anyhow!("{}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BridgeConfig {
+        BridgeConfig {
+            validators: (0..3).map(|_| Pubkey::new_unique().to_string()).collect(),
+            threshold: 2,
+            fee_rate: 10,
+            daily_limit: 1_000_000,
+            emergency_delay: 3600,
+        }
+    }
+
+    #[test]
+    fn test_parses_toml_and_json() {
+        let toml = r#"
+            validators = ["11111111111111111111111111111111"]
+            threshold = 2
+            fee_rate = 10
+            daily_limit = 1_000_000
+            emergency_delay = 3600
+        "#;
+        let json = r#"{
+            "validators": ["11111111111111111111111111111111"],
+            "threshold": 2,
+            "fee_rate": 10,
+            "daily_limit": 1000000,
+            "emergency_delay": 3600
+        }"#;
+        assert_eq!(BridgeConfig::from_toml(toml).unwrap(), BridgeConfig::from_json(json).unwrap());
+
+        // Misspelled keys would otherwise silently fall back to nothing
+        assert!(BridgeConfig::from_json(&json.replace("fee_rate", "fee_bps")).is_err());
+    }
+
+    #[test]
+    fn test_load_picks_format_from_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml_path = dir.path().join("bridge.toml");
+        std::fs::write(&toml_path, toml::to_string(&config()).unwrap()).unwrap();
+        assert!(BridgeConfig::load(&toml_path).is_ok());
+
+        let yaml_path = dir.path().join("bridge.yaml");
+        std::fs::write(&yaml_path, "threshold: 2").unwrap();
+        assert!(BridgeConfig::load(&yaml_path).is_err());
+
+        let example = Path::new(env!("CARGO_MANIFEST_DIR")).join("bridge.example.toml");
+        BridgeConfig::load(&example).unwrap().validate().unwrap();
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let validated = config().validate().unwrap();
+        assert_eq!(validated.validators.len(), 3);
+        assert_eq!(validated.validators[0].to_string(), config().validators[0]);
+    }
+
+    #[test]
+    fn test_rejects_what_the_program_rejects() {
+        let mut too_few = config();
+        too_few.validators.pop();
+        let err = too_few.validate().unwrap_err().to_string();
+        assert!(err.contains("InvalidValidatorCount"), "{}", err);
+
+        let mut low_threshold = config();
+        low_threshold.threshold = 1;
+        assert!(low_threshold.validate().is_err());
+
+        let mut high_fee = config();
+        high_fee.fee_rate = 10_001;
+        assert!(high_fee.validate().is_err());
+
+        let mut no_limit = config();
+        no_limit.daily_limit = 0;
+        assert!(no_limit.validate().is_err());
+
+        let mut short_delay = config();
+        short_delay.emergency_delay = 3599;
+        assert!(short_delay.validate().is_err());
+    }
+
+    #[test]
+    fn test_rejects_malformed_validator_key() {
+        let mut bad_key = config();
+        bad_key.validators[1] = "not-a-key".to_string();
+        let err = bad_key.validate().unwrap_err().to_string();
+        assert!(err.contains("validators[1]"), "{}", err);
+    }
+}
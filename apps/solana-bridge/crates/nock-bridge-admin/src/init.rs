@@ -0,0 +1,196 @@
+// `init` command
+// Builds and signs the `initialize_bridge` transaction for a validated config; in dry-run
+// mode the signed transaction is returned for inspection instead of being submitted
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::lamports_to_sol;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+
+use nock_bridge::BridgeState;
+
+use crate::config::ValidatedConfig;
+
+pub const BRIDGE_SEED: &[u8] = b"bridge";
+
+pub fn bridge_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[BRIDGE_SEED], program_id).0
+}
+
+pub fn initialize_bridge_instruction(program_id: &Pubkey, authority: &Pubkey, config: &ValidatedConfig) -> Instruction {
+    let accounts = nock_bridge::accounts::InitializeBridge {
+        bridge_state: bridge_pda(program_id),
+        authority: *authority,
+        system_program: system_program::ID,
+    };
+    let data = nock_bridge::instruction::InitializeBridge {
+        validators: config.validators.clone(),
+        threshold: config.threshold,
+        fee_rate: config.fee_rate,
+        daily_limit: config.daily_limit,
+        emergency_delay: config.emergency_delay,
+    };
+
+    Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+pub fn build_initialize_transaction(
+    program_id: &Pubkey,
+    authority: &Keypair,
+    config: &ValidatedConfig,
+    recent_blockhash: Hash,
+) -> Transaction {
+    let instruction = initialize_bridge_instruction(program_id, &authority.pubkey(), config);
+    Transaction::new_signed_with_payer(&[instruction], Some(&authority.pubkey()), &[authority], recent_blockhash)
+}
+
+#[derive(Debug)]
+pub struct InitReport {
+    pub bridge_pda: Pubkey,
+    pub rent_lamports: u64,
+    pub fee_lamports: u64,
+    pub transaction: Transaction,
+    /// `None` for a dry run
+    pub signature: Option<Signature>,
+}
+
+impl InitReport {
+    /// Signed transaction, bincode then base64, as accepted by `solana decode-transaction`
+    pub fn encoded_transaction(&self) -> Result<String> {
+        let bytes = bincode::serialize(&self.transaction).context("Failed to serialize transaction")?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    pub fn print(&self) -> Result<()> {
+        println!("Bridge PDA: {}", self.bridge_pda);
+        println!(
+            "Rent exemption: {} SOL ({} lamports, {} bytes)",
+            lamports_to_sol(self.rent_lamports),
+            self.rent_lamports,
+            BridgeState::SPACE
+        );
+        println!("Transaction fee: {} lamports", self.fee_lamports);
+
+        match self.signature {
+            Some(signature) => println!("Signature: {}", signature),
+            None => {
+                println!("Dry run, transaction not submitted:");
+                println!("{}", self.encoded_transaction()?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Initializes the bridge, or with `dry_run` builds and signs the transaction only.
+/// Refuses to run when the bridge account already exists or the authority cannot pay
+/// for it.
+pub fn run_init(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    authority: &Keypair,
+    config: &ValidatedConfig,
+    dry_run: bool,
+) -> Result<InitReport> {
+    let bridge_pda = bridge_pda(program_id);
+    let existing = client
+        .get_account_with_commitment(&bridge_pda, CommitmentConfig::confirmed())
+        .context("Failed to fetch bridge account")?
+        .value;
+    if existing.is_some() {
+        bail!("Bridge already initialized at {}", bridge_pda);
+    }
+
+    let rent_lamports = client
+        .get_minimum_balance_for_rent_exemption(BridgeState::SPACE)
+        .context("Failed to fetch rent exemption")?;
+    let recent_blockhash = client.get_latest_blockhash().context("Failed to fetch blockhash")?;
+    let transaction = build_initialize_transaction(program_id, authority, config, recent_blockhash);
+    let fee_lamports = client
+        .get_fee_for_message(&transaction.message)
+        .context("Failed to fetch transaction fee")?;
+
+    let balance = client.get_balance(&authority.pubkey()).context("Failed to fetch authority balance")?;
+    let required = rent_lamports.saturating_add(fee_lamports);
+    if balance < required {
+        bail!(
+            "Authority {} holds {} SOL but initialization needs {} SOL",
+            authority.pubkey(),
+            lamports_to_sol(balance),
+            lamports_to_sol(required)
+        );
+    }
+
+    let signature = if dry_run {
+        None
+    } else {
+        Some(
+            client
+                .send_and_confirm_transaction(&transaction)
+                .context("initialize_bridge transaction failed")?,
+        )
+    };
+
+    Ok(InitReport {
+        bridge_pda,
+        rent_lamports,
+        fee_lamports,
+        transaction,
+        signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    fn config() -> ValidatedConfig {
+        ValidatedConfig {
+            validators: (0..3).map(|_| Pubkey::new_unique()).collect(),
+            threshold: 2,
+            fee_rate: 10,
+            daily_limit: 1_000_000,
+            emergency_delay: 3600,
+        }
+    }
+
+    #[test]
+    fn test_instruction_targets_bridge_pda() {
+        let authority = Pubkey::new_unique();
+        let instruction = initialize_bridge_instruction(&nock_bridge::ID, &authority, &config());
+
+        assert_eq!(instruction.program_id, nock_bridge::ID);
+        assert_eq!(instruction.accounts[0].pubkey, bridge_pda(&nock_bridge::ID));
+        assert!(instruction.accounts[0].is_writable);
+        assert_eq!(instruction.accounts[1].pubkey, authority);
+        assert!(instruction.accounts[1].is_signer);
+        assert_eq!(instruction.accounts[2].pubkey, system_program::ID);
+        assert_eq!(
+            &instruction.data[..8],
+            &nock_bridge::instruction::InitializeBridge::discriminator()
+        );
+    }
+
+    #[test]
+    fn test_transaction_signed_by_authority() {
+        let authority = Keypair::new();
+        let transaction = build_initialize_transaction(&nock_bridge::ID, &authority, &config(), Hash::new_unique());
+
+        assert!(transaction.is_signed());
+        assert_eq!(transaction.message.account_keys[0], authority.pubkey());
+        transaction.verify().unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+// NOCK bridge administration
+// Off-chain tooling for deploying and configuring the bridge program
+
+pub mod config;
+pub mod init;
+
+pub use config::{BridgeConfig, ValidatedConfig};
+pub use init::{bridge_pda, run_init, InitReport};
@@ -0,0 +1,77 @@
+// nock-bridge-admin
+// Usage: nock-bridge-admin init --config bridge.toml [--dry-run]
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::read_keypair_file;
+
+use nock_bridge_admin::{run_init, BridgeConfig};
+
+#[derive(Debug, Parser)]
+#[command(name = "nock-bridge-admin", version, about = "NOCK bridge administration")]
+struct Cli {
+    /// RPC endpoint of the cluster
+    #[arg(long, short = 'u', global = true, default_value = "http://127.0.0.1:8899")]
+    url: String,
+
+    /// Authority keypair; pays for and owns the bridge
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<PathBuf>,
+
+    /// Bridge program ID, if not the built-in one
+    #[arg(long, global = true)]
+    program_id: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Validate a bridge config and initialize the bridge from it
+    Init {
+        /// Bridge config, .toml or .json
+        #[arg(long)]
+        config: PathBuf,
+
+        /// Validate and print the signed transaction without submitting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// The Solana CLI's default keypair location
+fn default_keypair_path() -> Result<PathBuf> {
+    let home = std::env::var("HOME").context("HOME is not set; pass --keypair")?;
+    Ok(PathBuf::from(home).join(".config/solana/id.json"))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Init { config, dry_run } => {
+            let config = BridgeConfig::load(&config)?.validate()?;
+
+            let program_id = match &cli.program_id {
+                Some(id) => Pubkey::from_str(id).with_context(|| format!("Invalid program ID {}", id))?,
+                None => nock_bridge::ID,
+            };
+            let keypair_path = match cli.keypair {
+                Some(path) => path,
+                None => default_keypair_path()?,
+            };
+            let authority = read_keypair_file(&keypair_path)
+                .map_err(|e| anyhow!("Failed to read keypair {}: {}", keypair_path.display(), e))?;
+
+            let client = RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed());
+            run_init(&client, &program_id, &authority, &config, dry_run)?.print()
+        }
+    }
+}
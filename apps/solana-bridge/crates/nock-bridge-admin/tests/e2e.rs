@@ -0,0 +1,166 @@
+// End-to-end tests for `nock-bridge-admin init`
+// The validator-backed test needs solana-test-validator on PATH and the program built with
+// `anchor build`; NOCK_BRIDGE_SO overrides where the program binary is looked up.
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+use anchor_lang::AccountDeserialize;
+use nock_bridge::BridgeState;
+use nock_bridge_admin::bridge_pda;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+
+const RPC_PORT: u16 = 18899;
+const FAUCET_PORT: u16 = 19900;
+
+/// Kills the validator when the test ends, passing or not
+struct TestValidator {
+    process: Child,
+    url: String,
+}
+
+impl TestValidator {
+    fn start(ledger: &Path) -> Self {
+        let program = std::env::var("NOCK_BRIDGE_SO").map(PathBuf::from).unwrap_or_else(|_| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/nock_bridge.so")
+        });
+        assert!(program.exists(), "{} not found; run `anchor build` first", program.display());
+
+        let process = Command::new("solana-test-validator")
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(ledger)
+            .arg("--rpc-port")
+            .arg(RPC_PORT.to_string())
+            .arg("--faucet-port")
+            .arg(FAUCET_PORT.to_string())
+            .arg("--bpf-program")
+            .arg(nock_bridge::ID.to_string())
+            .arg(&program)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start solana-test-validator");
+
+        let validator = Self {
+            process,
+            url: format!("http://127.0.0.1:{}", RPC_PORT),
+        };
+        let client = validator.client();
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while client.get_health().is_err() {
+            assert!(Instant::now() < deadline, "solana-test-validator did not become healthy");
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        validator
+    }
+
+    fn client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.url.clone(), CommitmentConfig::confirmed())
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn write_config(dir: &Path, validators: &[Pubkey], threshold: u8) -> PathBuf {
+    let path = dir.join("bridge.json");
+    let config = serde_json::json!({
+        "validators": validators.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+        "threshold": threshold,
+        "fee_rate": 10,
+        "daily_limit": 1_000_000_000_000u64,
+        "emergency_delay": 7200,
+    });
+    std::fs::write(&path, config.to_string()).unwrap();
+    path
+}
+
+fn admin(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nock-bridge-admin"))
+        .args(args)
+        .output()
+        .expect("failed to run nock-bridge-admin")
+}
+
+#[test]
+fn test_invalid_config_rejected_before_any_rpc() {
+    let dir = tempfile::tempdir().unwrap();
+    let validators: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let config = write_config(dir.path(), &validators, 1);
+
+    // Nothing listens on this port; validation has to fail first
+    let output = admin(&["init", "--config", config.to_str().unwrap(), "--url", "http://127.0.0.1:1"]);
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("InvalidThreshold"), "{}", stderr);
+}
+
+#[test]
+#[ignore = "requires solana-test-validator and a built nock_bridge.so"]
+fn test_init_against_test_validator() {
+    let dir = tempfile::tempdir().unwrap();
+    let validator = TestValidator::start(&dir.path().join("ledger"));
+    let client = validator.client();
+
+    let authority = Keypair::new();
+    let keypair_path = dir.path().join("authority.json");
+    write_keypair_file(&authority, &keypair_path).unwrap();
+    let signature = client.request_airdrop(&authority.pubkey(), 10 * LAMPORTS_PER_SOL).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while !client.confirm_transaction(&signature).unwrap() {
+        assert!(Instant::now() < deadline, "airdrop not confirmed");
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    let validators: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let config = write_config(dir.path(), &validators, 3);
+    let args = [
+        "init",
+        "--config",
+        config.to_str().unwrap(),
+        "--url",
+        validator.url.as_str(),
+        "--keypair",
+        keypair_path.to_str().unwrap(),
+    ];
+    let pda = bridge_pda(&nock_bridge::ID);
+
+    // Dry run prints the PDA and transaction but leaves the chain untouched
+    let dry_run = admin(&[&args[..], &["--dry-run"]].concat());
+    assert!(dry_run.status.success(), "{}", String::from_utf8_lossy(&dry_run.stderr));
+    let stdout = String::from_utf8_lossy(&dry_run.stdout);
+    assert!(stdout.contains(&pda.to_string()), "{}", stdout);
+    assert!(stdout.contains("Dry run"), "{}", stdout);
+    assert!(client.get_account_with_commitment(&pda, CommitmentConfig::confirmed()).unwrap().value.is_none());
+
+    let init = admin(&args);
+    assert!(init.status.success(), "{}", String::from_utf8_lossy(&init.stderr));
+    assert!(String::from_utf8_lossy(&init.stdout).contains(&pda.to_string()));
+
+    let account = client.get_account(&pda).unwrap();
+    assert_eq!(account.owner, nock_bridge::ID);
+    assert_eq!(account.data.len(), BridgeState::SPACE);
+    let state = BridgeState::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(state.authority, authority.pubkey());
+    assert_eq!(state.validators, validators);
+    assert_eq!(state.threshold, 3);
+    assert_eq!(state.fee_rate, 10);
+    assert_eq!(state.emergency_delay, 7200);
+
+    // A second init is refused before a transaction is sent
+    let again = admin(&args);
+    assert!(!again.status.success());
+    assert!(String::from_utf8_lossy(&again.stderr).contains("already initialized"));
+}
@@ -0,0 +1,310 @@
+// Bridge fee, signed-message and eon-tagging helpers
+// Shared by the on-chain program and, compiled to wasm32, by TypeScript clients that build
+// and simulate transactions before submitting them. Nothing here depends on Solana crates,
+// so both sides produce byte-identical fees, messages and hashes, and off-chain test
+// harnesses apply the same eon rules as the program.
+
+use std::collections::BTreeSet;
+
+use sha2::{Digest, Sha256};
+
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+// Domain tag for validator-signed deposit attestations
+pub const DEPOSIT_DOMAIN: &[u8] = b"NOCK_BRIDGE_DEPOSIT";
+
+// Fee rates and tier discounts are both in basis points
+pub const BASIS_POINTS: u128 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    ArithmeticOverflow,
+}
+
+impl std::fmt::Display for MathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MathError::ArithmeticOverflow => write!(f, "Arithmetic overflow"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EonError {
+    MissingEonTag,
+    EonTagMismatch,
+    InvalidEonTransition,
+}
+
+impl std::fmt::Display for EonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EonError::MissingEonTag => write!(f, "Deposit from a previous eon must be tagged with its eon"),
+            EonError::EonTagMismatch => write!(f, "Deposit eon tag does not match its block"),
+            EonError::InvalidEonTransition => write!(f, "Invalid eon transition"),
+        }
+    }
+}
+
+impl std::error::Error for EonError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    InsufficientSignatures,
+    DuplicateValidator,
+    InvalidSignature,
+}
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::InsufficientSignatures => write!(f, "Insufficient validator signatures"),
+            SignatureError::DuplicateValidator => write!(f, "Validator listed more than once"),
+            SignatureError::InvalidSignature => write!(f, "Invalid validator signature"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+/// Bridge fee on `amount` at `fee_rate` basis points, reduced by a tier `discount` in
+/// basis points of the fee. Rounds down; a discount above 100% is an error.
+pub fn calculate_fee(amount: u64, fee_rate: u16, discount: u16) -> Result<u64, MathError> {
+    (amount as u128)
+        .checked_mul(fee_rate as u128)
+        .and_then(|x| x.checked_mul(BASIS_POINTS.checked_sub(discount as u128)?))
+        .and_then(|x| x.checked_div(BASIS_POINTS * BASIS_POINTS))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(MathError::ArithmeticOverflow)
+}
+
+/// Deposit attestation signed by validators, prefixed with the program ID so that
+/// signatures cannot be replayed against another deployment. The Solana recipient is
+/// signed so that nobody else can submit the attestation and mint to themselves. A
+/// tagged deposit has its eon appended.
+pub fn create_deposit_message(
+    program_id: &[u8; 32],
+    tx_hash: &[u8; 32],
+    recipient: &[u8; 32],
+    amount: u64,
+    block_height: u64,
+    eon: Option<u64>,
+) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(program_id);
+    message.extend_from_slice(DEPOSIT_DOMAIN);
+    message.extend_from_slice(tx_hash);
+    message.extend_from_slice(recipient);
+    message.extend_from_slice(&amount.to_le_bytes());
+    message.extend_from_slice(&block_height.to_le_bytes());
+    if let Some(eon) = eon {
+        message.extend_from_slice(&eon.to_le_bytes());
+    }
+    message
+}
+
+/// Returns the validators among `signers` that count towards `threshold`. A key listed
+/// twice, or a validator whose signature `is_verified` rejects, fails the whole set; keys
+/// outside `validators` are not counted. `is_verified` receives the index into `signers`.
+pub fn check_quorum<K: Ord + Copy>(
+    signers: &[K],
+    validators: &[K],
+    threshold: u8,
+    mut is_verified: impl FnMut(usize) -> bool,
+) -> Result<Vec<K>, SignatureError> {
+    if signers.len() < threshold as usize {
+        return Err(SignatureError::InsufficientSignatures);
+    }
+
+    let mut seen = BTreeSet::new();
+    if !signers.iter().all(|signer| seen.insert(*signer)) {
+        return Err(SignatureError::DuplicateValidator);
+    }
+
+    let mut counted = Vec::with_capacity(signers.len());
+    for (index, signer) in signers.iter().enumerate() {
+        if !validators.contains(signer) {
+            continue;
+        }
+        if !is_verified(index) {
+            return Err(SignatureError::InvalidSignature);
+        }
+        counted.push(*signer);
+    }
+
+    if counted.len() < threshold as usize {
+        return Err(SignatureError::InsufficientSignatures);
+    }
+    Ok(counted)
+}
+
+/// An eon transition must move to the very next eon and start after the current one
+pub fn check_eon_transition(
+    current_eon: u64,
+    eon_start_block_height: u64,
+    new_eon: u64,
+    start_block_height: u64,
+) -> Result<(), EonError> {
+    if current_eon.checked_add(1) != Some(new_eon) || start_block_height <= eon_start_block_height {
+        return Err(EonError::InvalidEonTransition);
+    }
+    Ok(())
+}
+
+/// Deposits from blocks before the current eon began must be tagged with the previous
+/// eon, so attestations signed before a transition cannot be replayed across it. Deposits
+/// from the current eon may omit the tag.
+pub fn check_deposit_eon(
+    block_height: u64,
+    eon: Option<u64>,
+    current_eon: u64,
+    eon_start_block_height: u64,
+) -> Result<(), EonError> {
+    if block_height < eon_start_block_height {
+        let tag = eon.ok_or(EonError::MissingEonTag)?;
+        if current_eon.checked_sub(1) != Some(tag) {
+            return Err(EonError::EonTagMismatch);
+        }
+    } else if eon.is_some_and(|tag| tag != current_eon) {
+        return Err(EonError::EonTagMismatch);
+    }
+    Ok(())
+}
+
+/// SHA-256 over the fields a config update sets, in declaration order, each preceded by
+/// its tag (its position in the argument list) so that equal bytes in different fields
+/// hash differently; unset fields contribute nothing
+pub fn hash_config_update(
+    fee_rate: &Option<u16>,
+    daily_limit: &Option<u64>,
+    threshold: &Option<u8>,
+    large_withdrawal_threshold: &Option<u64>,
+    tier_discounts: &Option<[u16; 4]>,
+    whitelist_enabled: &Option<bool>,
+    rotation_delay: &Option<u32>,
+) -> [u8; 32] {
+    let mut data = Vec::new();
+
+    if let Some(rate) = fee_rate {
+        data.push(0);
+        data.extend_from_slice(&rate.to_le_bytes());
+    }
+    if let Some(limit) = daily_limit {
+        data.push(1);
+        data.extend_from_slice(&limit.to_le_bytes());
+    }
+    if let Some(thresh) = threshold {
+        data.push(2);
+        data.push(*thresh);
+    }
+    if let Some(limit) = large_withdrawal_threshold {
+        data.push(3);
+        data.extend_from_slice(&limit.to_le_bytes());
+    }
+    if let Some(discounts) = tier_discounts {
+        data.push(4);
+        for discount in discounts {
+            data.extend_from_slice(&discount.to_le_bytes());
+        }
+    }
+    if let Some(enabled) = whitelist_enabled {
+        data.push(5);
+        data.push(*enabled as u8);
+    }
+    if let Some(delay) = rotation_delay {
+        data.push(6);
+        data.extend_from_slice(&delay.to_le_bytes());
+    }
+
+    Sha256::digest(&data).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM_ID: [u8; 32] = [7; 32];
+    const TX_HASH: [u8; 32] = [9; 32];
+    const RECIPIENT: [u8; 32] = [5; 32];
+
+    #[test]
+    fn test_fee_rounds_down_and_applies_discount() {
+        assert_eq!(calculate_fee(1_000_000, 30, 0), Ok(3_000));
+        assert_eq!(calculate_fee(1_900, 10, 4000), Ok(1));
+        assert_eq!(calculate_fee(15_000, 10, 5000), Ok(7));
+        assert_eq!(calculate_fee(15_000, 10, 10000), Ok(0));
+        assert_eq!(calculate_fee(u64::MAX, 10000, 0), Ok(u64::MAX));
+        assert_eq!(calculate_fee(1_000, 10, 10001), Err(MathError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn test_deposit_message_layout() {
+        let message = create_deposit_message(&PROGRAM_ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let fixed_len = 32 + DEPOSIT_DOMAIN.len() + 32 + 32 + 8 + 8;
+        assert_eq!(message.len(), fixed_len);
+        assert_eq!(&message[..32], &PROGRAM_ID);
+        assert_eq!(&message[32..32 + DEPOSIT_DOMAIN.len()], DEPOSIT_DOMAIN);
+        assert_eq!(&message[fixed_len - 48..fixed_len - 16], &RECIPIENT);
+        assert_eq!(&message[fixed_len - 16..fixed_len - 8], &1_000u64.to_le_bytes());
+        assert_ne!(message, create_deposit_message(&PROGRAM_ID, &TX_HASH, &[6; 32], 1_000, 50, None));
+
+        let tagged = create_deposit_message(&PROGRAM_ID, &TX_HASH, &RECIPIENT, 1_000, 50, Some(3));
+        assert_eq!(&tagged[..fixed_len], &message[..]);
+        assert_eq!(&tagged[fixed_len..], &3u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_quorum_counts_each_validator_once() {
+        let validators = [1u8, 2, 3, 4, 5];
+        let all_valid = |_| true;
+
+        assert_eq!(check_quorum(&[1, 2, 3], &validators, 3, all_valid), Ok(vec![1, 2, 3]));
+        assert_eq!(check_quorum(&[1, 2, 2], &validators, 3, all_valid), Err(SignatureError::DuplicateValidator));
+        assert_eq!(check_quorum(&[1, 2], &validators, 3, all_valid), Err(SignatureError::InsufficientSignatures));
+        // Outsiders pad the list but are not counted
+        assert_eq!(check_quorum(&[1, 2, 9], &validators, 3, all_valid), Err(SignatureError::InsufficientSignatures));
+        assert_eq!(check_quorum(&[1, 9, 2, 3], &validators, 3, |i| i != 1), Ok(vec![1, 2, 3]));
+        assert_eq!(check_quorum(&[1, 2, 3, 4], &validators, 3, |i| i != 3), Err(SignatureError::InvalidSignature));
+    }
+
+    #[test]
+    fn test_eon_transition_moves_forward_one_eon() {
+        assert_eq!(check_eon_transition(3, 1_000, 4, 2_000), Ok(()));
+        assert_eq!(check_eon_transition(3, 1_000, 5, 2_000), Err(EonError::InvalidEonTransition));
+        assert_eq!(check_eon_transition(3, 1_000, 4, 1_000), Err(EonError::InvalidEonTransition));
+        assert_eq!(check_eon_transition(u64::MAX, 1_000, 0, 2_000), Err(EonError::InvalidEonTransition));
+    }
+
+    #[test]
+    fn test_deposit_eon_tag_required_only_before_eon_start() {
+        assert_eq!(check_deposit_eon(999, None, 4, 1_000), Err(EonError::MissingEonTag));
+        assert_eq!(check_deposit_eon(999, Some(3), 4, 1_000), Ok(()));
+        assert_eq!(check_deposit_eon(999, Some(4), 4, 1_000), Err(EonError::EonTagMismatch));
+        assert_eq!(check_deposit_eon(1_000, None, 4, 1_000), Ok(()));
+        assert_eq!(check_deposit_eon(1_000, Some(3), 4, 1_000), Err(EonError::EonTagMismatch));
+        assert_eq!(check_deposit_eon(0, None, 0, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_config_hash_covers_only_set_fields() {
+        // SHA-256 of the empty string
+        let empty = hash_config_update(&None, &None, &None, &None, &None, &None, &None);
+        assert_eq!(empty[..4], [0xe3, 0xb0, 0xc4, 0x42]);
+
+        let fee_only = hash_config_update(&Some(25), &None, &None, &None, &None, &None, &None);
+        let expected: [u8; 32] = Sha256::digest([0, 25, 0]).into();
+        assert_eq!(fee_only, expected);
+
+        // Each field is bound into the hash, so the same bytes in another field of the same
+        // width differ
+        let daily_limit = hash_config_update(&None, &Some(1_000), &None, &None, &None, &None, &None);
+        let large_withdrawal = hash_config_update(&None, &None, &None, &Some(1_000), &None, &None, &None);
+        assert_ne!(daily_limit, large_withdrawal);
+        // Four discounts take the same eight bytes as a daily limit
+        let discounts = hash_config_update(&None, &None, &None, &None, &Some([1_000, 0, 0, 0]), &None, &None);
+        assert_ne!(daily_limit, discounts);
+    }
+}
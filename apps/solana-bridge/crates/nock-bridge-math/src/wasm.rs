@@ -0,0 +1,66 @@
+// JavaScript bindings, built with wasm-pack
+// u64 values cross the boundary as bigint; byte arrays as Uint8Array
+
+use wasm_bindgen::prelude::*;
+
+fn to_bytes32(name: &str, bytes: &[u8]) -> Result<[u8; 32], JsError> {
+    bytes
+        .try_into()
+        .map_err(|_| JsError::new(&format!("{} must be 32 bytes, got {}", name, bytes.len())))
+}
+
+#[wasm_bindgen(js_name = calculateFee)]
+pub fn calculate_fee(amount: u64, fee_rate: u16, discount: u16) -> Result<u64, JsError> {
+    crate::calculate_fee(amount, fee_rate, discount).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = createDepositMessage)]
+pub fn create_deposit_message(
+    program_id: &[u8],
+    tx_hash: &[u8],
+    recipient: &[u8],
+    amount: u64,
+    block_height: u64,
+    eon: Option<u64>,
+) -> Result<Vec<u8>, JsError> {
+    let program_id = to_bytes32("programId", program_id)?;
+    let tx_hash = to_bytes32("txHash", tx_hash)?;
+    let recipient = to_bytes32("recipient", recipient)?;
+    Ok(crate::create_deposit_message(&program_id, &tx_hash, &recipient, amount, block_height, eon))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen(js_name = hashConfigUpdate)]
+pub fn hash_config_update(
+    fee_rate: Option<u16>,
+    daily_limit: Option<u64>,
+    threshold: Option<u8>,
+    large_withdrawal_threshold: Option<u64>,
+    tier_discounts: Option<Vec<u16>>,
+    whitelist_enabled: Option<bool>,
+    rotation_delay: Option<u32>,
+) -> Result<Vec<u8>, JsError> {
+    let tier_discounts = tier_discounts
+        .map(|discounts| {
+            <[u16; 4]>::try_from(discounts.as_slice()).map_err(|_| {
+                JsError::new(&format!("tierDiscounts must have 4 entries, got {}", discounts.len()))
+            })
+        })
+        .transpose()?;
+
+    Ok(crate::hash_config_update(
+        &fee_rate,
+        &daily_limit,
+        &threshold,
+        &large_withdrawal_threshold,
+        &tier_discounts,
+        &whitelist_enabled,
+        &rotation_delay,
+    )
+    .to_vec())
+}
+
+#[wasm_bindgen(js_name = depositDomain)]
+pub fn deposit_domain() -> Vec<u8> {
+    crate::DEPOSIT_DOMAIN.to_vec()
+}
@@ -0,0 +1,40 @@
+#![cfg(target_arch = "wasm32")]
+
+// Run with `wasm-pack test --node`
+
+use nock_bridge_math::wasm::{calculate_fee, create_deposit_message, deposit_domain, hash_config_update};
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn fee_matches_native() {
+    assert_eq!(calculate_fee(15_000, 10, 5000).unwrap(), 7);
+    assert_eq!(calculate_fee(u64::MAX, 10000, 0).unwrap(), u64::MAX);
+    assert!(calculate_fee(1_000, 10, 10001).is_err());
+}
+
+#[wasm_bindgen_test]
+fn deposit_message_matches_native() {
+    let message = create_deposit_message(&[7; 32], &[9; 32], &[5; 32], 1_000, 50, Some(3)).unwrap();
+    assert_eq!(message, nock_bridge_math::create_deposit_message(&[7; 32], &[9; 32], &[5; 32], 1_000, 50, Some(3)));
+    assert_eq!(deposit_domain(), nock_bridge_math::DEPOSIT_DOMAIN);
+
+    assert!(create_deposit_message(&[7; 31], &[9; 32], &[5; 32], 1_000, 50, None).is_err());
+    assert!(create_deposit_message(&[7; 32], &[9; 32], &[5; 31], 1_000, 50, None).is_err());
+}
+
+#[wasm_bindgen_test]
+fn config_hash_matches_native() {
+    let hash = hash_config_update(Some(25), None, Some(3), None, Some(vec![0, 500, 1000, 2000]), Some(true), None).unwrap();
+    let native = nock_bridge_math::hash_config_update(
+        &Some(25),
+        &None,
+        &Some(3),
+        &None,
+        &Some([0, 500, 1000, 2000]),
+        &Some(true),
+        &None,
+    );
+    assert_eq!(hash, native.to_vec());
+
+    assert!(hash_config_update(None, None, None, None, Some(vec![0, 500]), None, None).is_err());
+}
@@ -0,0 +1,54 @@
+// Fuzz target for validator deposit attestations
+// Every field must be recoverable from the signed bytes, so no two deposits share a message
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use nock_bridge::{create_deposit_message, DEPOSIT_DOMAIN};
+use solana_program::pubkey::Pubkey;
+
+#[derive(Debug, Arbitrary)]
+struct DepositInput {
+    program_id: [u8; 32],
+    tx_hash: [u8; 32],
+    recipient: [u8; 32],
+    amount: u64,
+    block_height: u64,
+    eon: Option<u64>,
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes.try_into().expect("8-byte field"))
+}
+
+fuzz_target!(|input: DepositInput| {
+    let program_id = Pubkey::new_from_array(input.program_id);
+    let recipient = Pubkey::new_from_array(input.recipient);
+    let message = create_deposit_message(&program_id, &input.tx_hash, &recipient, input.amount, input.block_height, input.eon);
+
+    let fixed_len = 32 + DEPOSIT_DOMAIN.len() + 32 + 32 + 8 + 8;
+    let expected_len = fixed_len + if input.eon.is_some() { 8 } else { 0 };
+    assert_eq!(message.len(), expected_len);
+
+    let (program, rest) = message.split_at(32);
+    let (domain, rest) = rest.split_at(DEPOSIT_DOMAIN.len());
+    let (tx_hash, rest) = rest.split_at(32);
+    let (recipient_bytes, rest) = rest.split_at(32);
+    let (amount, rest) = rest.split_at(8);
+    let (block_height, eon) = rest.split_at(8);
+
+    assert_eq!(program, input.program_id.as_slice());
+    assert_eq!(domain, DEPOSIT_DOMAIN);
+    assert_eq!(tx_hash, input.tx_hash.as_slice());
+    assert_eq!(recipient_bytes, input.recipient.as_slice());
+    assert_eq!(read_u64(amount), input.amount);
+    assert_eq!(read_u64(block_height), input.block_height);
+    assert_eq!(input.eon, (!eon.is_empty()).then(|| read_u64(eon)));
+
+    // An untagged attestation must never be valid for the tagged deposit
+    if input.eon.is_some() {
+        let untagged = create_deposit_message(&program_id, &input.tx_hash, &recipient, input.amount, input.block_height, None);
+        assert_ne!(untagged, message);
+    }
+});
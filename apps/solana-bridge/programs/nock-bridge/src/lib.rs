@@ -2,11 +2,51 @@
 // Enterprise security with 5-of-9 multi-sig validation
 
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use std::collections::BTreeSet;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    self as sysvar_instructions, load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::{AssociatedToken, Create};
+use anchor_spl::associated_token::{get_associated_token_address, AssociatedToken, Create};
 
 declare_id!("BridGE1111111111111111111111111111111111111111");
 
+// Domain tags for validator-signed messages
+pub use nock_bridge_math::DEPOSIT_DOMAIN;
+const EMERGENCY_PAUSE_DOMAIN: &[u8] = b"NOCK_BRIDGE_EMERGENCY_PAUSE";
+const UNPAUSE_DOMAIN: &[u8] = b"NOCK_BRIDGE_UNPAUSE";
+const CONFIG_UPDATE_DOMAIN: &[u8] = b"NOCK_BRIDGE_CONFIG_UPDATE";
+const EON_TRANSITION_DOMAIN: &[u8] = b"NOCK_BRIDGE_EON_TRANSITION";
+const WHITELIST_DOMAIN: &[u8] = b"NOCK_BRIDGE_WHITELIST";
+const EMERGENCY_DRAIN_DOMAIN: &[u8] = b"NOCK_BRIDGE_EMERGENCY_DRAIN";
+const ROTATION_PROPOSAL_DOMAIN: &[u8] = b"NOCK_BRIDGE_ROTATION_PROPOSAL";
+const ROTATION_EXECUTION_DOMAIN: &[u8] = b"NOCK_BRIDGE_ROTATION_EXECUTION";
+const ROTATION_CANCELLATION_DOMAIN: &[u8] = b"NOCK_BRIDGE_ROTATION_CANCELLATION";
+const STATE_UPGRADE_DOMAIN: &[u8] = b"NOCK_BRIDGE_STATE_UPGRADE";
+const MULTI_HOP_ROUTE_DOMAIN: &[u8] = b"NOCK_BRIDGE_MULTI_HOP_ROUTE";
+const FEE_DISTRIBUTION_DOMAIN: &[u8] = b"NOCK_BRIDGE_FEE_DISTRIBUTION";
+const FEE_DISTRIBUTION_CONFIG_DOMAIN: &[u8] = b"NOCK_BRIDGE_FEE_DISTRIBUTION_CONFIG";
+
+// wNOCK base units per NOCK (8 decimals)
+const NOCK: u64 = 100_000_000;
+
+// Constant-product pool swap fee, left in the reserves for LP holders
+const SWAP_FEE_BPS: u64 = 30;
+
+// LP tokens locked forever by the first deposit so the pool can never be fully drained
+const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// Shortest wait between proposing and executing a validator rotation (48 hours)
+const MIN_ROTATION_DELAY: i64 = 48 * 3600;
+
+// Ed25519 program instruction layout
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_OFFSETS_SIZE: usize = 14;
+
 #[program]
 pub mod nock_bridge {
     use super::*;
@@ -20,13 +60,11 @@ pub mod nock_bridge {
         daily_limit: u64,
         emergency_delay: i64,
     ) -> Result<()> {
-        require!(validators.len() >= 3 && validators.len() <= 15, BridgeError::InvalidValidatorCount);
-        require!(threshold >= (validators.len() as u8 + 1) / 2, BridgeError::InvalidThreshold);
-        require!(fee_rate <= 10000, BridgeError::InvalidFeeRate); // Max 100%
-        require!(daily_limit > 0, BridgeError::InvalidDailyLimit);
-        require!(emergency_delay >= 3600, BridgeError::InvalidEmergencyDelay); // Min 1 hour
+        check_bridge_params(&validators, threshold, fee_rate, daily_limit, emergency_delay)?;
+        let validator_count = validators.len();
 
         let bridge = &mut ctx.accounts.bridge_state;
+        bridge.version = BridgeState::CURRENT_VERSION;
         bridge.authority = ctx.accounts.authority.key();
         bridge.validators = validators;
         bridge.threshold = threshold;
@@ -39,8 +77,20 @@ pub mod nock_bridge {
         bridge.total_fees_collected = 0;
         bridge.last_reset_timestamp = Clock::get()?.unix_timestamp;
         bridge.daily_volume = 0;
+        bridge.last_processed_block_height = 0;
+        bridge.reorg_depth = BridgeState::DEFAULT_REORG_DEPTH;
+        bridge.migrated_at = 0;
+        bridge.governance_nonce = 0;
+        bridge.large_withdrawal_threshold = 0;
+        bridge.tier_discounts = BridgeState::DEFAULT_TIER_DISCOUNTS;
+        bridge.current_eon = 0;
+        bridge.eon_start_block_height = 0;
+        bridge.whitelist_enabled = false;
+        bridge.drain_nonce = 0;
+        bridge.rotation_delay = 0;
+        bridge.reserved = [0; BridgeState::RESERVED_SPACE];
 
-        msg!("Bridge initialized with {} validators, threshold: {}", validators.len(), threshold);
+        msg!("Bridge initialized with {} validators, threshold: {}", validator_count, threshold);
         Ok(())
     }
 
@@ -52,50 +102,101 @@ pub mod nock_bridge {
         require!(decimals <= 9, BridgeError::InvalidDecimals);
 
         let bridge = &ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
         require!(!bridge.is_paused, BridgeError::BridgePaused);
 
         msg!("wNOCK mint initialized with {} decimals", decimals);
         Ok(())
     }
 
+    /// Create the daily submission counter for a validator
+    pub fn initialize_validator_rate_limit(
+        ctx: Context<InitializeValidatorRateLimit>,
+        validator: Pubkey,
+    ) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        // Incoming validators need their counters before a proposed rotation is executed
+        let is_pending = ctx.accounts.pending_rotation
+            .as_ref()
+            .is_some_and(|pending| pending.new_validators.contains(&validator));
+        require!(bridge.validators.contains(&validator) || is_pending, BridgeError::Unauthorized);
+
+        let rate_limit = &mut ctx.accounts.validator_rate_limit;
+        rate_limit.validator = validator;
+        rate_limit.window_start = Clock::get()?.unix_timestamp;
+        rate_limit.submissions = 0;
+
+        msg!("Rate limit initialized for validator {}", validator);
+        Ok(())
+    }
+
     /// Deposit NOCK tokens and mint wNOCK (Nockchain → Solana)
-    pub fn deposit_nock(
-        ctx: Context<DepositNock>,
+    pub fn deposit_nock<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositNock<'info>>,
         amount: u64,
         nock_tx_hash: [u8; 32],
         block_height: u64,
+        eon: Option<u64>,
         signatures: Vec<ValidatorSignature>,
     ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
         let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
         require!(!bridge.is_paused, BridgeError::BridgePaused);
         require!(amount > 0, BridgeError::InvalidAmount);
 
+        // Whitelist mode restricts the bridge to approved addresses with current KYC
+        check_whitelist(
+            bridge,
+            ctx.accounts.whitelist_entry.as_deref_mut(),
+            ctx.accounts.user.key(),
+            amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        // Blocks mined before the current eon began must be attested with the eon they belong to
+        check_deposit_eon(block_height, eon, bridge.current_eon, bridge.eon_start_block_height)?;
+
+        // Reject Nockchain blocks older than the reorg window
+        let last_processed_block_height = check_block_height(
+            block_height,
+            bridge.last_processed_block_height,
+            bridge.reorg_depth,
+        )?;
+
         // Verify multi-sig validation
-        verify_validator_signatures(
+        let signers = verify_validator_signatures(
             &signatures,
             &bridge.validators,
             bridge.threshold,
+            &verified,
             &nock_tx_hash,
+            &ctx.accounts.user.key(),
             amount,
             block_height,
+            eon,
         )?;
 
+        // Count the submission against each signing validator's daily limit
+        record_validator_submissions(ctx.remaining_accounts, &signers, Clock::get()?.unix_timestamp)?;
+
+        // Each Nockchain transaction can only be minted once
+        record_processed_deposit(&mut ctx.accounts.processed_deposit, nock_tx_hash, block_height)?;
+
         // Check daily limits
         reset_daily_volume_if_needed(bridge)?;
-        require!(
-            bridge.daily_volume.saturating_add(amount) <= bridge.daily_limit,
-            BridgeError::DailyLimitExceeded
-        );
+        check_daily_limit(bridge, amount)?;
 
-        // Calculate fees
-        let fee = calculate_fee(amount, bridge.fee_rate)?;
-        let net_amount = amount.saturating_sub(fee);
+        // Calculate fees at the user's current tier
+        let user_volume = &mut ctx.accounts.user_volume;
+        let fee = calculate_fee(amount, bridge.fee_rate, bridge.tier_discount(user_volume.tier))?;
+        let net_amount = checked_sub(amount, fee)?;
 
         // Update bridge state
-        bridge.nonce += 1;
-        bridge.total_locked = bridge.total_locked.saturating_add(amount);
-        bridge.total_fees_collected = bridge.total_fees_collected.saturating_add(fee);
-        bridge.daily_volume = bridge.daily_volume.saturating_add(amount);
+        apply_deposit(bridge, amount, fee)?;
+        bridge.last_processed_block_height = last_processed_block_height;
+        record_user_volume(user_volume, ctx.accounts.user.key(), amount)?;
 
         // Mint wNOCK tokens to user
         let seeds = &[
@@ -151,19 +252,24 @@ pub mod nock_bridge {
         nock_address: [u8; 32],
     ) -> Result<()> {
         let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
         require!(!bridge.is_paused, BridgeError::BridgePaused);
         require!(amount > 0, BridgeError::InvalidAmount);
 
-        // Check daily limits
-        reset_daily_volume_if_needed(bridge)?;
-        require!(
-            bridge.daily_volume.saturating_add(amount) <= bridge.daily_limit,
-            BridgeError::DailyLimitExceeded
-        );
+        // Whitelist mode restricts the bridge to approved addresses with current KYC
+        check_whitelist(
+            bridge,
+            ctx.accounts.whitelist_entry.as_deref_mut(),
+            ctx.accounts.user.key(),
+            amount,
+            Clock::get()?.unix_timestamp,
+        )?;
 
-        // Calculate fees
-        let fee = calculate_fee(amount, bridge.fee_rate)?;
-        let net_amount = amount.saturating_sub(fee);
+        // A queued withdrawal above the daily limit could never execute
+        let large = is_large_withdrawal(amount, bridge.large_withdrawal_threshold);
+        if large {
+            require!(amount <= bridge.daily_limit, BridgeError::DailyLimitExceeded);
+        }
 
         // Burn user's wNOCK tokens
         let burn_ctx = CpiContext::new(
@@ -176,6 +282,44 @@ pub mod nock_bridge {
         );
         token::burn(burn_ctx, amount)?;
 
+        // Large withdrawals wait out the timelock; the burned wNOCK is re-minted if cancelled
+        if large {
+            let now = Clock::get()?.unix_timestamp;
+            let id = bridge.nonce;
+            bridge.nonce = checked_add(bridge.nonce, 1)?;
+
+            let request = ctx.accounts.withdrawal_request
+                .as_mut()
+                .ok_or(error!(BridgeError::WithdrawalRequestRequired))?;
+            request.id = id;
+            request.requester = ctx.accounts.user.key();
+            request.amount = amount;
+            request.nock_address = nock_address;
+            request.created_at = now;
+            request.executed = false;
+
+            emit!(WithdrawalQueued {
+                id,
+                requester: request.requester,
+                amount,
+                nock_address,
+                executable_at: withdrawal_unlocks_at(request, bridge.emergency_delay)?,
+                timestamp: now,
+            });
+
+            msg!("Queued withdrawal {} of {} wNOCK behind the timelock", id, amount);
+            return Ok(());
+        }
+
+        // Check daily limits
+        reset_daily_volume_if_needed(bridge)?;
+        check_daily_limit(bridge, amount)?;
+
+        // Calculate fees at the user's current tier
+        let user_volume = &mut ctx.accounts.user_volume;
+        let fee = calculate_fee(amount, bridge.fee_rate, bridge.tier_discount(user_volume.tier))?;
+        let net_amount = checked_sub(amount, fee)?;
+
         // Mint fee to collector if applicable
         if fee > 0 {
             let seeds = &[
@@ -197,10 +341,8 @@ pub mod nock_bridge {
         }
 
         // Update bridge state
-        bridge.nonce += 1;
-        bridge.total_locked = bridge.total_locked.saturating_sub(net_amount);
-        bridge.total_fees_collected = bridge.total_fees_collected.saturating_add(fee);
-        bridge.daily_volume = bridge.daily_volume.saturating_add(amount);
+        apply_withdrawal(bridge, amount, fee, net_amount)?;
+        record_user_volume(user_volume, ctx.accounts.user.key(), amount)?;
 
         emit!(WithdrawEvent {
             user: ctx.accounts.user.key(),
@@ -216,18 +358,449 @@ pub mod nock_bridge {
         Ok(())
     }
 
+    /// Execute a queued large withdrawal once the timelock has elapsed; the request account
+    /// is closed and its rent returned to the requester, as on cancellation
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        require!(!bridge.is_paused, BridgeError::BridgePaused);
+
+        let now = Clock::get()?.unix_timestamp;
+        let request = &mut ctx.accounts.withdrawal_request;
+        check_withdrawal_executable(request, now, bridge.emergency_delay)?;
+        let amount = request.amount;
+
+        // Check daily limits
+        reset_daily_volume_if_needed(bridge)?;
+        check_daily_limit(bridge, amount)?;
+
+        // Calculate fees at the requester's tier when the withdrawal executes
+        let user_volume = &mut ctx.accounts.user_volume;
+        let fee = calculate_fee(amount, bridge.fee_rate, bridge.tier_discount(user_volume.tier))?;
+        let net_amount = checked_sub(amount, fee)?;
+
+        // Mint fee to collector if applicable
+        if fee > 0 {
+            let seeds = &[
+                b"bridge",
+                &[*ctx.bumps.get("bridge_state").unwrap()],
+            ];
+            let signer = &[&seeds[..]];
+
+            let fee_mint_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.wnock_mint.to_account_info(),
+                    to: ctx.accounts.fee_collector.to_account_info(),
+                    authority: ctx.accounts.bridge_state.to_account_info(),
+                },
+                signer,
+            );
+            token::mint_to(fee_mint_ctx, fee)?;
+        }
+
+        // Update bridge state
+        apply_withdrawal(bridge, amount, fee, net_amount)?;
+        record_user_volume(user_volume, request.requester, amount)?;
+        request.executed = true;
+
+        emit!(WithdrawEvent {
+            user: request.requester,
+            amount,
+            fee,
+            net_amount,
+            nock_address: request.nock_address,
+            nonce: bridge.nonce,
+            timestamp: now,
+        });
+
+        msg!("Executed withdrawal {}, releasing {} NOCK to Nockchain (fee: {})", request.id, net_amount, fee);
+        Ok(())
+    }
+
+    /// Cancel a queued large withdrawal during the timelock, or once the daily limit has been
+    /// lowered below its amount, and return the wNOCK
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawal>) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let request = &ctx.accounts.withdrawal_request;
+        check_withdrawal_cancellable(request, now, bridge.emergency_delay, bridge.daily_limit)?;
+
+        let seeds = &[
+            b"bridge",
+            &[*ctx.bumps.get("bridge_state").unwrap()],
+        ];
+        let signer = &[&seeds[..]];
+
+        let refund_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.wnock_mint.to_account_info(),
+                to: ctx.accounts.requester_wnock_account.to_account_info(),
+                authority: ctx.accounts.bridge_state.to_account_info(),
+            },
+            signer,
+        );
+        token::mint_to(refund_ctx, request.amount)?;
+
+        emit!(WithdrawalCancelled {
+            id: request.id,
+            requester: request.requester,
+            amount: request.amount,
+            timestamp: now,
+        });
+
+        msg!("Cancelled withdrawal {}, returned {} wNOCK", request.id, request.amount);
+        Ok(())
+    }
+
+    /// Create the NOCK/wNOCK liquidity pool with its LP mint and token vaults
+    pub fn initialize_liquidity_pool(ctx: Context<InitializeLiquidityPool>) -> Result<()> {
+        check_bridge_version(&ctx.accounts.bridge_state)?;
+
+        let pool = &mut ctx.accounts.liquidity_pool;
+        pool.nock_mint = ctx.accounts.nock_mint.key();
+        pool.lp_mint = ctx.accounts.lp_mint.key();
+        pool.nock_vault = ctx.accounts.nock_vault.key();
+        pool.wnock_vault = ctx.accounts.wnock_vault.key();
+        pool.nock_reserve = 0;
+        pool.wnock_reserve = 0;
+        pool.lp_supply = 0;
+        pool.bump = *ctx.bumps.get("liquidity_pool").unwrap();
+
+        msg!("Liquidity pool initialized for NOCK mint {}", pool.nock_mint);
+        Ok(())
+    }
+
+    /// Deposit NOCK and wNOCK at the pool ratio and mint LP tokens. Only the amounts
+    /// matching the current ratio are taken; the first deposit sets the ratio.
+    pub fn add_liquidity(
+        ctx: Context<AddLiquidity>,
+        amount_nock: u64,
+        amount_wnock: u64,
+    ) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        require!(!bridge.is_paused, BridgeError::BridgePaused);
+
+        let pool = &mut ctx.accounts.liquidity_pool;
+        let deposit = quote_add_liquidity(amount_nock, amount_wnock, pool)?;
+
+        for (from, to, amount) in [
+            (&ctx.accounts.user_nock_account, &ctx.accounts.nock_vault, deposit.nock),
+            (&ctx.accounts.user_wnock_account, &ctx.accounts.wnock_vault, deposit.wnock),
+        ] {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: from.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+        }
+
+        let seeds = &[LiquidityPool::SEED, &[pool.bump]];
+        let signer = &[&seeds[..]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::MintTo {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    to: ctx.accounts.user_lp_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer,
+            ),
+            deposit.lp_tokens,
+        )?;
+
+        pool.apply_deposit(&deposit)?;
+
+        emit!(LiquidityAdded {
+            provider: ctx.accounts.user.key(),
+            amount_nock: deposit.nock,
+            amount_wnock: deposit.wnock,
+            lp_tokens: deposit.lp_tokens,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Added {} NOCK and {} wNOCK for {} LP tokens", deposit.nock, deposit.wnock, deposit.lp_tokens);
+        Ok(())
+    }
+
+    /// Burn LP tokens for a pro-rata share of both reserves, accrued swap fees included.
+    /// Allowed while the bridge is paused so providers can always exit.
+    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, lp_tokens: u64) -> Result<()> {
+        check_bridge_version(&ctx.accounts.bridge_state)?;
+
+        let pool = &mut ctx.accounts.liquidity_pool;
+        let (amount_nock, amount_wnock) = quote_remove_liquidity(lp_tokens, pool)?;
+
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Burn {
+                    mint: ctx.accounts.lp_mint.to_account_info(),
+                    from: ctx.accounts.user_lp_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            lp_tokens,
+        )?;
+
+        let seeds = &[LiquidityPool::SEED, &[pool.bump]];
+        let signer = &[&seeds[..]];
+        for (from, to, amount) in [
+            (&ctx.accounts.nock_vault, &ctx.accounts.user_nock_account, amount_nock),
+            (&ctx.accounts.wnock_vault, &ctx.accounts.user_wnock_account, amount_wnock),
+        ] {
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: from.to_account_info(),
+                        to: to.to_account_info(),
+                        authority: pool.to_account_info(),
+                    },
+                    signer,
+                ),
+                amount,
+            )?;
+        }
+
+        pool.apply_withdrawal(lp_tokens, amount_nock, amount_wnock)?;
+
+        emit!(LiquidityRemoved {
+            provider: ctx.accounts.user.key(),
+            amount_nock,
+            amount_wnock,
+            lp_tokens,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Removed {} NOCK and {} wNOCK for {} LP tokens", amount_nock, amount_wnock, lp_tokens);
+        Ok(())
+    }
+
+    /// Swap between NOCK and wNOCK against the pool, failing if the output would be
+    /// below `min_amount_out`
+    pub fn swap(
+        ctx: Context<Swap>,
+        amount_in: u64,
+        min_amount_out: u64,
+        direction: SwapDirection,
+    ) -> Result<()> {
+        let bridge = &ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        require!(!bridge.is_paused, BridgeError::BridgePaused);
+
+        let pool = &mut ctx.accounts.liquidity_pool;
+        let (reserve_in, reserve_out) = pool.reserves(direction);
+        let amount_out = quote_swap(amount_in, min_amount_out, reserve_in, reserve_out)?;
+
+        let (user_in, vault_in, vault_out, user_out) = match direction {
+            SwapDirection::NockToWnock => (
+                &ctx.accounts.user_nock_account,
+                &ctx.accounts.nock_vault,
+                &ctx.accounts.wnock_vault,
+                &ctx.accounts.user_wnock_account,
+            ),
+            SwapDirection::WnockToNock => (
+                &ctx.accounts.user_wnock_account,
+                &ctx.accounts.wnock_vault,
+                &ctx.accounts.nock_vault,
+                &ctx.accounts.user_nock_account,
+            ),
+        };
+
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: user_in.to_account_info(),
+                    to: vault_in.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            amount_in,
+        )?;
+
+        let seeds = &[LiquidityPool::SEED, &[pool.bump]];
+        let signer = &[&seeds[..]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: vault_out.to_account_info(),
+                    to: user_out.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer,
+            ),
+            amount_out,
+        )?;
+
+        pool.apply_swap(direction, amount_in, amount_out)?;
+
+        emit!(SwapEvent {
+            user: ctx.accounts.user.key(),
+            direction,
+            amount_in,
+            amount_out,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!("Swapped {} for {} ({:?})", amount_in, amount_out, direction);
+        Ok(())
+    }
+
+    /// Set the DEX program `swap_and_bridge` routes through - requires multi-sig
+    pub fn configure_multi_hop_route(
+        ctx: Context<ConfigureMultiHopRoute>,
+        dex_program: Pubkey,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let nock_mint = ctx.accounts.nock_mint.key();
+        let route_hash = hash_multi_hop_route(&dex_program, &nock_mint);
+        let message = create_governance_message(MULTI_HOP_ROUTE_DOMAIN, bridge.governance_nonce, &route_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let route = &mut ctx.accounts.multi_hop_route;
+        route.dex_program = dex_program;
+        route.nock_mint = nock_mint;
+        route.bump = *ctx.bumps.get("multi_hop_route").unwrap();
+
+        msg!("Multi-hop route set to DEX {}", dex_program);
+        Ok(())
+    }
+
+    /// Bridge NOCK to Nockchain through an intermediate SPL token. The bridge fee is
+    /// taken from the NOCK at the standard `fee_rate`. The rest is swapped for
+    /// `intermediate_token` on the configured DEX, whose own fee is reflected in its
+    /// output. The output is locked in the bridge's vault for release to `nock_address`.
+    /// Accounts the DEX needs are passed as remaining accounts.
+    pub fn swap_and_bridge<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapAndBridge<'info>>,
+        amount: u64,
+        intermediate_token: Pubkey,
+        min_out: u64,
+        nock_address: [u8; 32],
+    ) -> Result<()> {
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        require!(!bridge.is_paused, BridgeError::BridgePaused);
+        require!(amount > 0, BridgeError::InvalidAmount);
+        require_keys_neq!(intermediate_token, ctx.accounts.nock_mint.key(), BridgeError::InvalidIntermediateToken);
+
+        check_whitelist(
+            bridge,
+            ctx.accounts.whitelist_entry.as_deref_mut(),
+            ctx.accounts.user.key(),
+            amount,
+            Clock::get()?.unix_timestamp,
+        )?;
+        reset_daily_volume_if_needed(bridge)?;
+        check_daily_limit(bridge, amount)?;
+
+        // NOCK leg: the standard bridge fee, paid in NOCK
+        let fee = calculate_fee(amount, bridge.fee_rate, 0)?;
+        let net_amount = checked_sub(amount, fee)?;
+        if fee > 0 {
+            token::transfer(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.user_nock_account.to_account_info(),
+                        to: ctx.accounts.fee_collector.to_account_info(),
+                        authority: ctx.accounts.user.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+            ctx.accounts.user_nock_account.reload()?;
+        }
+
+        // Swap leg: balances are measured around the CPI rather than trusting the DEX
+        let nock_before = ctx.accounts.user_nock_account.amount;
+        let vault_before = ctx.accounts.intermediate_vault.amount;
+
+        let metas: Vec<AccountMeta> = ctx.remaining_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect();
+        let instruction = dex_swap_instruction(ctx.accounts.dex_program.key(), metas, net_amount, min_out);
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.dex_program.to_account_info());
+        anchor_lang::solana_program::program::invoke(&instruction, &account_infos)?;
+
+        ctx.accounts.user_nock_account.reload()?;
+        ctx.accounts.intermediate_vault.reload()?;
+        let (nock_swapped, intermediate_amount) = settle_dex_swap(
+            nock_before,
+            ctx.accounts.user_nock_account.amount,
+            vault_before,
+            ctx.accounts.intermediate_vault.amount,
+            net_amount,
+            min_out,
+        )?;
+
+        // Bridge leg: the intermediate tokens stay locked in the vault
+        let bridge = &mut ctx.accounts.bridge_state;
+        apply_multi_hop(bridge, amount, fee)?;
+
+        emit!(MultiHopBridgeEvent {
+            user: ctx.accounts.user.key(),
+            nock_amount: amount,
+            fee,
+            nock_swapped,
+            intermediate_mint: intermediate_token,
+            intermediate_amount,
+            dex_program: ctx.accounts.dex_program.key(),
+            nock_address,
+            nonce: bridge.nonce,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        msg!(
+            "Swapped {} NOCK for {} of {} and bridged it (fee: {})",
+            nock_swapped,
+            intermediate_amount,
+            intermediate_token,
+            fee
+        );
+        Ok(())
+    }
+
     /// Emergency pause - requires multi-sig
     pub fn emergency_pause(
         ctx: Context<EmergencyPause>,
         signatures: Vec<ValidatorSignature>,
     ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
         let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
         require!(!bridge.is_paused, BridgeError::AlreadyPaused);
 
         // Verify multi-sig authorization
-        let message = format!("EMERGENCY_PAUSE_{}", Clock::get()?.unix_timestamp);
-        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, message.as_bytes())?;
+        let message = create_governance_message(EMERGENCY_PAUSE_DOMAIN, bridge.governance_nonce, &[]);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
 
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
         bridge.is_paused = true;
         bridge.pause_timestamp = Some(Clock::get()?.unix_timestamp);
 
@@ -245,21 +818,24 @@ pub mod nock_bridge {
         ctx: Context<UnpauseBridge>,
         signatures: Vec<ValidatorSignature>,
     ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
         let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
         require!(bridge.is_paused, BridgeError::NotPaused);
 
         let current_time = Clock::get()?.unix_timestamp;
         if let Some(pause_time) = bridge.pause_timestamp {
-            require!(
-                current_time >= pause_time + bridge.emergency_delay,
-                BridgeError::EmergencyDelayNotMet
-            );
+            let unlocks_at = pause_time
+                .checked_add(bridge.emergency_delay)
+                .ok_or(BridgeError::ArithmeticOverflow)?;
+            require!(current_time >= unlocks_at, BridgeError::EmergencyDelayNotMet);
         }
 
         // Verify multi-sig authorization
-        let message = format!("UNPAUSE_{}", current_time);
-        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, message.as_bytes())?;
+        let message = create_governance_message(UNPAUSE_DOMAIN, bridge.governance_nonce, &[]);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
 
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
         bridge.is_paused = false;
         bridge.pause_timestamp = None;
 
@@ -272,20 +848,92 @@ pub mod nock_bridge {
         Ok(())
     }
 
-    /// Update bridge parameters - requires multi-sig
+    /// Burn wNOCK from the bridge reserve so validators release the NOCK behind it to
+    /// `destination` on Nockchain. Only for a bridge that has been paused for three
+    /// emergency delays, and requires a two-thirds super-majority of validators.
+    pub fn emergency_drain(
+        ctx: Context<EmergencyDrain>,
+        destination: [u8; 32],
+        amount: u64,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+        require!(amount > 0, BridgeError::InvalidAmount);
+
+        let now = Clock::get()?.unix_timestamp;
+        check_drain_delay(bridge, now)?;
+
+        // Verify super-majority authorization; drains have their own nonce
+        let drain_hash = hash_emergency_drain(&destination, amount);
+        let message = create_governance_message(EMERGENCY_DRAIN_DOMAIN, bridge.drain_nonce, &drain_hash);
+        let threshold = drain_threshold(bridge.validators.len(), bridge.threshold);
+        verify_emergency_signatures(&signatures, &bridge.validators, threshold, &verified, &message)?;
+
+        let drain_nonce = bridge.drain_nonce;
+        bridge.drain_nonce = checked_add(bridge.drain_nonce, 1)?;
+        bridge.total_locked = checked_sub(bridge.total_locked, amount)?;
+
+        let seeds = &[
+            b"bridge",
+            &[*ctx.bumps.get("bridge_state").unwrap()],
+        ];
+        let signer = &[&seeds[..]];
+
+        let burn_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::Burn {
+                mint: ctx.accounts.wnock_mint.to_account_info(),
+                from: ctx.accounts.bridge_reserve.to_account_info(),
+                authority: ctx.accounts.bridge_state.to_account_info(),
+            },
+            signer,
+        );
+        token::burn(burn_ctx, amount)?;
+
+        emit!(EmergencyDrainEvent {
+            destination,
+            amount,
+            drain_nonce,
+            timestamp: now,
+            authorized_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Emergency drained {} wNOCK from the bridge reserve", amount);
+        Ok(())
+    }
+
+    /// Update bridge parameters - requires multi-sig. The validator set is changed through
+    /// the time-locked rotation proposal instead.
     pub fn update_bridge_config(
         ctx: Context<UpdateBridgeConfig>,
         new_fee_rate: Option<u16>,
         new_daily_limit: Option<u64>,
-        new_validators: Option<Vec<Pubkey>>,
         new_threshold: Option<u8>,
+        new_large_withdrawal_threshold: Option<u64>,
+        new_tier_discounts: Option<[u16; 4]>,
+        new_whitelist_enabled: Option<bool>,
+        new_rotation_delay: Option<u32>,
         signatures: Vec<ValidatorSignature>,
     ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
         let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
 
         // Verify multi-sig authorization
-        let config_hash = hash_config_update(&new_fee_rate, &new_daily_limit, &new_validators, &new_threshold);
-        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &config_hash)?;
+        let config_hash = hash_config_update(
+            &new_fee_rate,
+            &new_daily_limit,
+            &new_threshold,
+            &new_large_withdrawal_threshold,
+            &new_tier_discounts,
+            &new_whitelist_enabled,
+            &new_rotation_delay,
+        );
+        let message = create_governance_message(CONFIG_UPDATE_DOMAIN, bridge.governance_nonce, &config_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
 
         if let Some(fee_rate) = new_fee_rate {
             require!(fee_rate <= 10000, BridgeError::InvalidFeeRate);
@@ -297,16 +945,29 @@ pub mod nock_bridge {
             bridge.daily_limit = daily_limit;
         }
 
-        if let Some(validators) = new_validators {
-            require!(validators.len() >= 3 && validators.len() <= 15, BridgeError::InvalidValidatorCount);
-            bridge.validators = validators;
-        }
-
         if let Some(threshold) = new_threshold {
-            require!(threshold >= (bridge.validators.len() as u8 + 1) / 2, BridgeError::InvalidThreshold);
+            check_threshold(bridge.validators.len(), threshold)?;
             bridge.threshold = threshold;
         }
 
+        if let Some(large_withdrawal_threshold) = new_large_withdrawal_threshold {
+            bridge.large_withdrawal_threshold = large_withdrawal_threshold;
+        }
+
+        if let Some(tier_discounts) = new_tier_discounts {
+            require!(tier_discounts.iter().all(|&d| d <= 10000), BridgeError::InvalidTierDiscount);
+            bridge.tier_discounts = tier_discounts;
+        }
+
+        if let Some(whitelist_enabled) = new_whitelist_enabled {
+            bridge.whitelist_enabled = whitelist_enabled;
+        }
+
+        if let Some(rotation_delay) = new_rotation_delay {
+            require!(rotation_delay as i64 >= MIN_ROTATION_DELAY, BridgeError::InvalidRotationDelay);
+            bridge.rotation_delay = rotation_delay;
+        }
+
         emit!(ConfigUpdateEvent {
             timestamp: Clock::get()?.unix_timestamp,
             updated_by: ctx.accounts.authority.key(),
@@ -315,11 +976,400 @@ pub mod nock_bridge {
         msg!("Bridge configuration updated");
         Ok(())
     }
-}
 
-// Account structures
-#[derive(Accounts)]
-pub struct InitializeBridge<'info> {
+    /// Propose a new validator set - requires multi-sig from the current set. The rotation
+    /// can be executed once the bridge's rotation delay has passed.
+    pub fn propose_validator_rotation(
+        ctx: Context<ProposeValidatorRotation>,
+        new_validators: Vec<Pubkey>,
+        new_threshold: u8,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+        check_validator_set(&new_validators, new_threshold)?;
+
+        // Verify multi-sig authorization
+        let rotation_hash = hash_validator_rotation(&new_validators, new_threshold);
+        let message = create_governance_message(ROTATION_PROPOSAL_DOMAIN, bridge.governance_nonce, &rotation_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let executable_at = stage_validator_rotation(
+            &mut ctx.accounts.pending_rotation,
+            bridge,
+            new_validators.clone(),
+            new_threshold,
+            clock.unix_timestamp,
+        )?;
+
+        emit!(ValidatorRotationProposed {
+            new_validators,
+            new_threshold,
+            executable_at,
+            timestamp: clock.unix_timestamp,
+            proposed_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Validator rotation proposed, executable at {}", executable_at);
+        Ok(())
+    }
+
+    /// Swap in the proposed validator set once the rotation delay has passed - requires
+    /// multi-sig from the current (outgoing) set
+    pub fn execute_validator_rotation(
+        ctx: Context<ExecuteValidatorRotation>,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+
+        let pending = &ctx.accounts.pending_rotation;
+        check_rotation_executable(pending, clock.unix_timestamp)?;
+
+        // Verify multi-sig authorization
+        let rotation_hash = hash_validator_rotation(&pending.new_validators, pending.new_threshold);
+        let message = create_governance_message(ROTATION_EXECUTION_DOMAIN, bridge.governance_nonce, &rotation_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let previous_validators = std::mem::replace(&mut bridge.validators, pending.new_validators.clone());
+        bridge.threshold = pending.new_threshold;
+
+        emit!(ValidatorRotationEvent {
+            previous_validators,
+            new_validators: bridge.validators.clone(),
+            epoch: clock.epoch,
+            timestamp: clock.unix_timestamp,
+        });
+
+        msg!("Validator set rotated, {} validators with threshold {}", bridge.validators.len(), bridge.threshold);
+        Ok(())
+    }
+
+    /// Withdraw a pending validator rotation - requires multi-sig from the current set
+    pub fn cancel_validator_rotation(
+        ctx: Context<CancelValidatorRotation>,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+
+        // Verify multi-sig authorization
+        let pending = &ctx.accounts.pending_rotation;
+        let rotation_hash = hash_validator_rotation(&pending.new_validators, pending.new_threshold);
+        let message = create_governance_message(ROTATION_CANCELLATION_DOMAIN, bridge.governance_nonce, &rotation_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        emit!(ValidatorRotationCancelled {
+            new_validators: pending.new_validators.clone(),
+            new_threshold: pending.new_threshold,
+            timestamp: clock.unix_timestamp,
+            cancelled_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Validator rotation cancelled");
+        Ok(())
+    }
+
+    /// Record the start of a new Nockchain eon - requires multi-sig. An optional validator
+    /// set for the new eon is proposed as a `PendingRotation` with the current threshold, and
+    /// waits out the rotation delay like any other proposal.
+    pub fn register_eon_transition(
+        ctx: Context<RegisterEonTransition>,
+        new_eon: u64,
+        start_block_height: u64,
+        validator_rotation: Option<Vec<Pubkey>>,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+        check_eon_transition(bridge, new_eon, start_block_height)?;
+        require!(
+            validator_rotation.is_some() == ctx.accounts.pending_rotation.is_some(),
+            BridgeError::PendingRotationMismatch
+        );
+        if let Some(validators) = &validator_rotation {
+            // The proposed set keeps the current threshold, so it must be able to reach it
+            check_validator_set(validators, bridge.threshold)?;
+        }
+
+        // Verify multi-sig authorization
+        let transition_hash = hash_eon_transition(new_eon, start_block_height, &validator_rotation);
+        let message = create_governance_message(EON_TRANSITION_DOMAIN, bridge.governance_nonce, &transition_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let previous_eon = bridge.current_eon;
+        advance_eon(bridge, new_eon, start_block_height);
+
+        if let (Some(new_validators), Some(pending)) = (validator_rotation, ctx.accounts.pending_rotation.as_mut()) {
+            let executable_at = stage_validator_rotation(
+                pending,
+                bridge,
+                new_validators.clone(),
+                bridge.threshold,
+                clock.unix_timestamp,
+            )?;
+
+            emit!(ValidatorRotationProposed {
+                new_validators,
+                new_threshold: bridge.threshold,
+                executable_at,
+                timestamp: clock.unix_timestamp,
+                proposed_by: ctx.accounts.authority.key(),
+            });
+        }
+
+        emit!(EonTransitionEvent {
+            previous_eon,
+            new_eon,
+            start_block_height,
+            timestamp: clock.unix_timestamp,
+            registered_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Eon {} began at Nockchain block {}", new_eon, start_block_height);
+        Ok(())
+    }
+
+    /// Approve an address for whitelist mode, or update its entry - requires multi-sig.
+    /// The entry's daily volume carries over when an existing entry is updated.
+    pub fn add_to_whitelist(
+        ctx: Context<AddToWhitelist>,
+        user: Pubkey,
+        daily_limit_override: Option<u64>,
+        kyc_expiry: i64,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+
+        require!(kyc_expiry > clock.unix_timestamp, BridgeError::KycExpired);
+        require!(daily_limit_override != Some(0), BridgeError::InvalidDailyLimit);
+
+        // Verify multi-sig authorization
+        let update_hash = hash_whitelist_update(&user, Some((daily_limit_override, kyc_expiry)));
+        let message = create_governance_message(WHITELIST_DOMAIN, bridge.governance_nonce, &update_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let entry = &mut ctx.accounts.whitelist_entry;
+        entry.user = user;
+        entry.approved = true;
+        entry.daily_limit_override = daily_limit_override;
+        entry.kyc_expiry = kyc_expiry;
+
+        emit!(WhitelistUpdatedEvent {
+            user,
+            approved: true,
+            daily_limit_override,
+            kyc_expiry,
+            timestamp: clock.unix_timestamp,
+            updated_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Whitelisted {} until {}", user, kyc_expiry);
+        Ok(())
+    }
+
+    /// Remove an address from the whitelist, closing its entry - requires multi-sig
+    pub fn remove_from_whitelist(
+        ctx: Context<RemoveFromWhitelist>,
+        user: Pubkey,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+
+        // Verify multi-sig authorization
+        let update_hash = hash_whitelist_update(&user, None);
+        let message = create_governance_message(WHITELIST_DOMAIN, bridge.governance_nonce, &update_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        emit!(WhitelistUpdatedEvent {
+            user,
+            approved: false,
+            daily_limit_override: None,
+            kyc_expiry: 0,
+            timestamp: clock.unix_timestamp,
+            updated_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Removed {} from the whitelist", user);
+        Ok(())
+    }
+
+    /// Split the collected wNOCK fees between `recipients` by basis-point share - requires
+    /// multi-sig. Recipients' associated token accounts are passed as remaining accounts in
+    /// the same order. Only runs once a distribution is due: a week after the last one, or
+    /// earlier when the collected fees reach `auto_distribute_threshold`.
+    pub fn distribute_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, DistributeFees<'info>>,
+        recipients: Vec<FeeRecipient>,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let clock = Clock::get()?;
+        check_fee_recipients(&recipients)?;
+
+        // Verify multi-sig authorization
+        let distribution_hash = hash_fee_distribution(&recipients);
+        let message = create_governance_message(FEE_DISTRIBUTION_DOMAIN, bridge.governance_nonce, &distribution_hash);
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let balance = ctx.accounts.fee_collector.amount;
+        let distribution = &mut ctx.accounts.fee_distribution;
+        require!(distribution.is_due(balance, clock.unix_timestamp), BridgeError::FeeDistributionNotDue);
+        let amounts = split_fees(balance, &recipients)?;
+
+        require!(ctx.remaining_accounts.len() == recipients.len(), BridgeError::FeeRecipientAccountMismatch);
+        let wnock_mint = ctx.accounts.wnock_mint.key();
+        let seeds = &[
+            b"bridge".as_ref(),
+            &[*ctx.bumps.get("bridge_state").unwrap()],
+        ];
+        let signer = &[&seeds[..]];
+
+        for ((recipient, amount), account) in recipients.iter().zip(&amounts).zip(ctx.remaining_accounts) {
+            require_keys_eq!(
+                account.key(),
+                get_associated_token_address(&recipient.recipient, &wnock_mint),
+                BridgeError::FeeRecipientAccountMismatch
+            );
+            if *amount == 0 {
+                continue;
+            }
+
+            token::transfer(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.fee_collector.to_account_info(),
+                        to: account.clone(),
+                        authority: ctx.accounts.bridge_state.to_account_info(),
+                    },
+                    signer,
+                ),
+                *amount,
+            )?;
+        }
+
+        let distributed = amounts.iter().try_fold(0u64, |total, &amount| checked_add(total, amount))?;
+        let distribution = &mut ctx.accounts.fee_distribution;
+        distribution.last_distribution_at = clock.unix_timestamp;
+        distribution.total_distributed = checked_add(distribution.total_distributed, distributed)?;
+        distribution.bump = *ctx.bumps.get("fee_distribution").unwrap();
+
+        emit!(FeeDistributionEvent {
+            recipients: recipients.iter().map(|r| r.recipient).collect(),
+            shares_bps: recipients.iter().map(|r| r.share_bps).collect(),
+            amounts,
+            total: distributed,
+            timestamp: clock.unix_timestamp,
+            distributed_by: ctx.accounts.authority.key(),
+        });
+
+        msg!("Distributed {} wNOCK of fees to {} recipients", distributed, recipients.len());
+        Ok(())
+    }
+
+    /// Set the fee balance that makes a distribution due before the weekly deadline;
+    /// 0 leaves only the weekly deadline - requires multi-sig
+    pub fn configure_fee_distribution(
+        ctx: Context<ConfigureFeeDistribution>,
+        auto_distribute_threshold: u64,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge = &mut ctx.accounts.bridge_state;
+        check_bridge_version(bridge)?;
+
+        let message = create_governance_message(
+            FEE_DISTRIBUTION_CONFIG_DOMAIN,
+            bridge.governance_nonce,
+            &auto_distribute_threshold.to_le_bytes(),
+        );
+        verify_emergency_signatures(&signatures, &bridge.validators, bridge.threshold, &verified, &message)?;
+        bridge.governance_nonce = checked_add(bridge.governance_nonce, 1)?;
+
+        let distribution = &mut ctx.accounts.fee_distribution;
+        distribution.auto_distribute_threshold = auto_distribute_threshold;
+        distribution.bump = *ctx.bumps.get("fee_distribution").unwrap();
+
+        msg!("Fee auto-distribution threshold set to {}", auto_distribute_threshold);
+        Ok(())
+    }
+
+    /// Upgrade a v1 bridge account to the current layout with validator-approved values
+    /// for the fields v1 lacks. The account is grown in place, so it keeps its
+    /// `b"bridge"` address and no client or PDA derivation has to change.
+    pub fn upgrade_bridge_state(
+        ctx: Context<UpgradeBridgeState>,
+        new_fields: BridgeStateV2Fields,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<()> {
+        let verified = load_verified_signatures(&ctx.accounts.instructions)?;
+        let bridge_info = ctx.accounts.bridge_state.to_account_info();
+        let now = Clock::get()?.unix_timestamp;
+
+        let upgraded = {
+            let data = bridge_info.try_borrow_data()?;
+            upgrade_bridge_state_data(&data, &new_fields, now)?
+        };
+        require_keys_eq!(upgraded.authority, ctx.accounts.authority.key(), BridgeError::Unauthorized);
+
+        // v1 has no governance nonce. Only a v1 account can be upgraded, so a signed
+        // upgrade cannot be replayed and nonce 0 is safe.
+        let message = create_governance_message(STATE_UPGRADE_DOMAIN, 0, &hash_state_upgrade(&new_fields));
+        verify_emergency_signatures(&signatures, &upgraded.validators, upgraded.threshold, &verified, &message)?;
+
+        store_migrated_bridge_state(
+            &bridge_info,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+        )?;
+
+        emit!(BridgeStateUpgradeEvent {
+            from_version: BridgeState::V1,
+            to_version: upgraded.version,
+            upgraded_by: ctx.accounts.authority.key(),
+            timestamp: now,
+        });
+
+        msg!("Bridge state upgraded from v{} to v{}", BridgeState::V1, upgraded.version);
+        Ok(())
+    }
+}
+
+// Account structures
+#[derive(Accounts)]
+pub struct InitializeBridge<'info> {
     #[account(
         init,
         payer = authority,
@@ -363,6 +1413,40 @@ pub struct InitializeWNockMint<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(validator: Pubkey)]
+pub struct InitializeValidatorRateLimit<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ValidatorRateLimit::SPACE,
+        seeds = [ValidatorRateLimit::SEED, validator.as_ref()],
+        bump
+    )]
+    pub validator_rate_limit: Account<'info, ValidatorRateLimit>,
+
+    /// Required when `validator` is only part of a proposed rotation
+    #[account(
+        seeds = [PendingRotation::SEED],
+        bump
+    )]
+    pub pending_rotation: Option<Account<'info, PendingRotation>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Remaining accounts: the writable `ValidatorRateLimit` PDA of every signing validator
+#[derive(Accounts)]
+#[instruction(amount: u64, nock_tx_hash: [u8; 32])]
 pub struct DepositNock<'info> {
     #[account(
         mut,
@@ -371,6 +1455,32 @@ pub struct DepositNock<'info> {
     )]
     pub bridge_state: Account<'info, BridgeState>,
 
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = ProcessedDeposit::SPACE,
+        seeds = [b"deposit", nock_tx_hash.as_ref()],
+        bump
+    )]
+    pub processed_deposit: Account<'info, ProcessedDeposit>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserVolumeAccount::SPACE,
+        seeds = [UserVolumeAccount::SEED, user.key().as_ref()],
+        bump
+    )]
+    pub user_volume: Account<'info, UserVolumeAccount>,
+
+    /// Required while `whitelist_enabled`
+    #[account(
+        mut,
+        seeds = [WhitelistEntry::SEED, user.key().as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
     #[account(
         mut,
         seeds = [b"wnock_mint"],
@@ -401,6 +1511,10 @@ pub struct DepositNock<'info> {
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -412,6 +1526,33 @@ pub struct WithdrawNock<'info> {
     )]
     pub bridge_state: Account<'info, BridgeState>,
 
+    /// Only required for withdrawals above `large_withdrawal_threshold`
+    #[account(
+        init,
+        payer = user,
+        space = WithdrawalRequest::SPACE,
+        seeds = [WithdrawalRequest::SEED, bridge_state.nonce.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub withdrawal_request: Option<Account<'info, WithdrawalRequest>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserVolumeAccount::SPACE,
+        seeds = [UserVolumeAccount::SEED, user.key().as_ref()],
+        bump
+    )]
+    pub user_volume: Account<'info, UserVolumeAccount>,
+
+    /// Required while `whitelist_enabled`
+    #[account(
+        mut,
+        seeds = [WhitelistEntry::SEED, user.key().as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
     #[account(
         mut,
         seeds = [b"wnock_mint"],
@@ -437,267 +1578,3784 @@ pub struct WithdrawNock<'info> {
     pub user: Signer<'info>,
 
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct EmergencyPause<'info> {
+pub struct ExecuteWithdrawal<'info> {
     #[account(
         mut,
         seeds = [b"bridge"],
-        bump,
-        has_one = authority
+        bump
     )]
     pub bridge_state: Account<'info, BridgeState>,
 
+    #[account(
+        mut,
+        seeds = [WithdrawalRequest::SEED, withdrawal_request.id.to_le_bytes().as_ref()],
+        bump,
+        has_one = requester,
+        close = requester
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(
+        init_if_needed,
+        payer = requester,
+        space = UserVolumeAccount::SPACE,
+        seeds = [UserVolumeAccount::SEED, requester.key().as_ref()],
+        bump
+    )]
+    pub user_volume: Account<'info, UserVolumeAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"wnock_mint"],
+        bump
+    )]
+    pub wnock_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = wnock_mint,
+        associated_token::authority = bridge_state
+    )]
+    pub fee_collector: Account<'info, TokenAccount>,
+
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub requester: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UnpauseBridge<'info> {
+pub struct CancelWithdrawal<'info> {
     #[account(
-        mut,
         seeds = [b"bridge"],
-        bump,
-        has_one = authority
+        bump
     )]
     pub bridge_state: Account<'info, BridgeState>,
 
+    #[account(
+        mut,
+        seeds = [WithdrawalRequest::SEED, withdrawal_request.id.to_le_bytes().as_ref()],
+        bump,
+        has_one = requester,
+        close = requester
+    )]
+    pub withdrawal_request: Account<'info, WithdrawalRequest>,
+
+    #[account(
+        mut,
+        seeds = [b"wnock_mint"],
+        bump
+    )]
+    pub wnock_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = wnock_mint,
+        associated_token::authority = requester
+    )]
+    pub requester_wnock_account: Account<'info, TokenAccount>,
+
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub requester: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateBridgeConfig<'info> {
+pub struct InitializeLiquidityPool<'info> {
     #[account(
-        mut,
         seeds = [b"bridge"],
         bump,
         has_one = authority
     )]
     pub bridge_state: Account<'info, BridgeState>,
 
-    #[account(mut)]
+    #[account(
+        init,
+        payer = authority,
+        space = LiquidityPool::SPACE,
+        seeds = [LiquidityPool::SEED],
+        bump
+    )]
+    pub liquidity_pool: Account<'info, LiquidityPool>,
+
+    pub nock_mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [b"wnock_mint"],
+        bump
+    )]
+    pub wnock_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        mint::decimals = 8,
+        mint::authority = liquidity_pool,
+        seeds = [LiquidityPool::LP_MINT_SEED],
+        bump
+    )]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = nock_mint,
+        token::authority = liquidity_pool,
+        seeds = [LiquidityPool::VAULT_SEED, nock_mint.key().as_ref()],
+        bump
+    )]
+    pub nock_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = wnock_mint,
+        token::authority = liquidity_pool,
+        seeds = [LiquidityPool::VAULT_SEED, wnock_mint.key().as_ref()],
+        bump
+    )]
+    pub wnock_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AddLiquidity<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [LiquidityPool::SEED],
+        bump = liquidity_pool.bump,
+        has_one = lp_mint,
+        has_one = nock_vault,
+        has_one = wnock_vault
+    )]
+    pub liquidity_pool: Account<'info, LiquidityPool>,
+
+    #[account(mut)]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub nock_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub wnock_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = liquidity_pool.nock_mint,
+        token::authority = user
+    )]
+    pub user_nock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wnock_vault.mint,
+        token::authority = user
+    )]
+    pub user_wnock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user
+    )]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [LiquidityPool::SEED],
+        bump = liquidity_pool.bump,
+        has_one = lp_mint,
+        has_one = nock_vault,
+        has_one = wnock_vault
+    )]
+    pub liquidity_pool: Account<'info, LiquidityPool>,
+
+    #[account(mut)]
+    pub lp_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub nock_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub wnock_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = liquidity_pool.nock_mint,
+        token::authority = user
+    )]
+    pub user_nock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wnock_vault.mint,
+        token::authority = user
+    )]
+    pub user_wnock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = user
+    )]
+    pub user_lp_account: Account<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Swap<'info> {
+    #[account(
+        seeds = [b"bridge"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [LiquidityPool::SEED],
+        bump = liquidity_pool.bump,
+        has_one = nock_vault,
+        has_one = wnock_vault
+    )]
+    pub liquidity_pool: Account<'info, LiquidityPool>,
+
+    #[account(mut)]
+    pub nock_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub wnock_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = liquidity_pool.nock_mint,
+        token::authority = user
+    )]
+    pub user_nock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = wnock_vault.mint,
+        token::authority = user
+    )]
+    pub user_wnock_account: Account<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureMultiHopRoute<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = MultiHopRoute::SPACE,
+        seeds = [MultiHopRoute::SEED],
+        bump
+    )]
+    pub multi_hop_route: Account<'info, MultiHopRoute>,
+
+    pub nock_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(amount: u64, intermediate_token: Pubkey)]
+pub struct SwapAndBridge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        seeds = [MultiHopRoute::SEED],
+        bump = multi_hop_route.bump,
+        has_one = nock_mint,
+        has_one = dex_program @ BridgeError::InvalidDexProgram
+    )]
+    pub multi_hop_route: Account<'info, MultiHopRoute>,
+
+    /// CHECK: the configured DEX, invoked with the remaining accounts
+    #[account(executable)]
+    pub dex_program: UncheckedAccount<'info>,
+
+    pub nock_mint: Account<'info, Mint>,
+
+    #[account(address = intermediate_token @ BridgeError::InvalidIntermediateToken)]
+    pub intermediate_mint: Account<'info, Mint>,
+
+    /// Holds the bridged intermediate tokens; the DEX must deliver its output here
+    #[account(
+        init_if_needed,
+        payer = user,
+        token::mint = intermediate_mint,
+        token::authority = multi_hop_route,
+        seeds = [MultiHopRoute::VAULT_SEED, intermediate_mint.key().as_ref()],
+        bump
+    )]
+    pub intermediate_vault: Account<'info, TokenAccount>,
+
+    /// Required while `whitelist_enabled`
+    #[account(
+        mut,
+        seeds = [WhitelistEntry::SEED, user.key().as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Option<Account<'info, WhitelistEntry>>,
+
+    #[account(
+        mut,
+        token::mint = nock_mint,
+        token::authority = user
+    )]
+    pub user_nock_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = nock_mint,
+        associated_token::authority = bridge_state
+    )]
+    pub fee_collector: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EmergencyPause<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnpauseBridge<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(mut)]
     pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
 }
 
-// State structures
-#[account]
-pub struct BridgeState {
-    pub authority: Pubkey,
-    pub validators: Vec<Pubkey>,
-    pub threshold: u8,
-    pub fee_rate: u16,               // basis points
-    pub daily_limit: u64,
-    pub emergency_delay: i64,        // seconds
-    pub is_paused: bool,
-    pub nonce: u64,
-    pub total_locked: u64,
-    pub total_fees_collected: u64,
-    pub last_reset_timestamp: i64,
-    pub daily_volume: u64,
-    pub pause_timestamp: Option<i64>,
-}
+#[derive(Accounts)]
+pub struct EmergencyDrain<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        seeds = [b"wnock_mint"],
+        bump
+    )]
+    pub wnock_mint: Account<'info, Mint>,
+
+    /// The bridge's own wNOCK account, which bridge fees are minted to
+    #[account(
+        mut,
+        associated_token::mint = wnock_mint,
+        associated_token::authority = bridge_state
+    )]
+    pub bridge_reserve: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBridgeConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+/// Only one rotation can be pending; proposing another fails until it is executed or cancelled
+#[derive(Accounts)]
+pub struct ProposeValidatorRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = PendingRotation::SPACE,
+        seeds = [PendingRotation::SEED],
+        bump
+    )]
+    pub pending_rotation: Account<'info, PendingRotation>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteValidatorRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [PendingRotation::SEED],
+        bump
+    )]
+    pub pending_rotation: Account<'info, PendingRotation>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelValidatorRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [PendingRotation::SEED],
+        bump
+    )]
+    pub pending_rotation: Account<'info, PendingRotation>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterEonTransition<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    /// Passed only when the transition proposes a validator set; like
+    /// `propose_validator_rotation`, it fails while another rotation is pending
+    #[account(
+        init,
+        payer = authority,
+        space = PendingRotation::SPACE,
+        seeds = [PendingRotation::SEED],
+        bump
+    )]
+    pub pending_rotation: Option<Account<'info, PendingRotation>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct AddToWhitelist<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = WhitelistEntry::SPACE,
+        seeds = [WhitelistEntry::SEED, user.as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(user: Pubkey)]
+pub struct RemoveFromWhitelist<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [WhitelistEntry::SEED, user.as_ref()],
+        bump
+    )]
+    pub whitelist_entry: Account<'info, WhitelistEntry>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeFees<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = FeeDistribution::SPACE,
+        seeds = [FeeDistribution::SEED],
+        bump
+    )]
+    pub fee_distribution: Account<'info, FeeDistribution>,
+
+    #[account(
+        seeds = [b"wnock_mint"],
+        bump
+    )]
+    pub wnock_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = wnock_mint,
+        associated_token::authority = bridge_state
+    )]
+    pub fee_collector: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureFeeDistribution<'info> {
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        has_one = authority
+    )]
+    pub bridge_state: Account<'info, BridgeState>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = FeeDistribution::SPACE,
+        seeds = [FeeDistribution::SEED],
+        bump
+    )]
+    pub fee_distribution: Account<'info, FeeDistribution>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeBridgeState<'info> {
+    /// CHECK: still in the v1 layout, so it is validated and decoded by hand in the handler
+    #[account(
+        mut,
+        seeds = [b"bridge"],
+        bump,
+        owner = crate::ID
+    )]
+    pub bridge_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+
+    /// CHECK: instructions sysvar, read to find the Ed25519 signature checks
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+// State structures
+#[account]
+pub struct BridgeState {
+    pub version: u8,                 // absent in v1
+    pub authority: Pubkey,
+    pub validators: Vec<Pubkey>,
+    pub threshold: u8,
+    pub fee_rate: u16,               // basis points
+    pub daily_limit: u64,
+    pub emergency_delay: i64,        // seconds
+    pub is_paused: bool,
+    pub nonce: u64,
+    pub total_locked: u64,
+    pub total_fees_collected: u64,
+    pub last_reset_timestamp: i64,
+    pub daily_volume: u64,
+    pub pause_timestamp: Option<i64>,
+    // v2 fields
+    pub last_processed_block_height: u64,
+    pub reorg_depth: u64,            // blocks
+    pub migrated_at: i64,            // 0 if created at v2
+    pub governance_nonce: u64,       // bumped by each pause, unpause and config update
+    pub large_withdrawal_threshold: u64, // withdrawals above this are timelocked; 0 disables
+    pub tier_discounts: [u16; 4],    // fee discount per FeeTier, basis points of the fee
+    pub current_eon: u64,
+    pub eon_start_block_height: u64, // first Nockchain block of current_eon
+    pub whitelist_enabled: bool,     // deposits and withdrawals need an approved WhitelistEntry
+    pub drain_nonce: u64,            // bumped by each emergency drain
+    pub rotation_delay: u32,         // seconds before a proposed rotation can execute; 0 means the minimum
+    pub reserved: [u8; BridgeState::RESERVED_SPACE],
+}
+
+/// Version 1 layout of `BridgeState`, as deployed before versioning, kept for migration.
+/// It has no version byte; a v1 account is recognised by its size, `BridgeState::V1_SPACE`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BridgeStateV1 {
+    pub authority: Pubkey,
+    pub validators: Vec<Pubkey>,
+    pub threshold: u8,
+    pub fee_rate: u16,
+    pub daily_limit: u64,
+    pub emergency_delay: i64,
+    pub is_paused: bool,
+    pub nonce: u64,
+    pub total_locked: u64,
+    pub total_fees_collected: u64,
+    pub last_reset_timestamp: i64,
+    pub daily_volume: u64,
+    pub pause_timestamp: Option<i64>,
+}
+
+/// Values for the fields a v1 bridge account lacks, approved by the validators that sign
+/// `upgrade_bridge_state`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BridgeStateV2Fields {
+    pub large_withdrawal_threshold: u64,
+    pub tier_discounts: [u16; 4],
+    pub whitelist_enabled: bool,
+    pub rotation_delay: u32,
+}
+
+/// Values for the fields v1 lacks that a new bridge starts with
+impl Default for BridgeStateV2Fields {
+    fn default() -> Self {
+        Self {
+            large_withdrawal_threshold: 0,
+            tier_discounts: BridgeState::DEFAULT_TIER_DISCOUNTS,
+            whitelist_enabled: false,
+            rotation_delay: 0,
+        }
+    }
+}
+
+impl BridgeState {
+    pub const V1: u8 = 1;
+    pub const V2: u8 = 2;
+    pub const CURRENT_VERSION: u8 = Self::V2;
+
+    pub const DEFAULT_REORG_DEPTH: u64 = 6;
+
+    /// Bronze pays the full fee; Silver, Gold and Platinum get 10%, 25% and 50% off
+    pub const DEFAULT_TIER_DISCOUNTS: [u16; 4] = [0, 1000, 2500, 5000];
+
+    pub const V1_SPACE: usize = 8 + // discriminator
+        32 + // authority
+        4 + (32 * 15) + // validators (max 15)
+        1 + // threshold
+        2 + // fee_rate
+        8 + // daily_limit
+        8 + // emergency_delay
+        1 + // is_paused
+        8 + // nonce
+        8 + // total_locked
+        8 + // total_fees_collected
+        8 + // last_reset_timestamp
+        8 + // daily_volume
+        1 + 8; // pause_timestamp (Option<i64>)
+
+    pub const RESERVED_SPACE: usize = 2;
+
+    pub const SPACE: usize = Self::V1_SPACE +
+        1 + // version
+        8 + // last_processed_block_height
+        8 + // reorg_depth
+        8 + // migrated_at
+        8 + // governance_nonce
+        8 + // large_withdrawal_threshold
+        2 * 4 + // tier_discounts
+        8 + // current_eon
+        8 + // eon_start_block_height
+        1 + // whitelist_enabled
+        8 + // drain_nonce
+        4 + // rotation_delay
+        Self::RESERVED_SPACE;
+
+    /// Builds the v2 state from a v1 account, defaulting the new fields
+    pub fn from_v1(v1: BridgeStateV1, migrated_at: i64) -> Self {
+        Self {
+            version: Self::V2,
+            authority: v1.authority,
+            validators: v1.validators,
+            threshold: v1.threshold,
+            fee_rate: v1.fee_rate,
+            daily_limit: v1.daily_limit,
+            emergency_delay: v1.emergency_delay,
+            is_paused: v1.is_paused,
+            nonce: v1.nonce,
+            total_locked: v1.total_locked,
+            total_fees_collected: v1.total_fees_collected,
+            last_reset_timestamp: v1.last_reset_timestamp,
+            daily_volume: v1.daily_volume,
+            pause_timestamp: v1.pause_timestamp,
+            last_processed_block_height: 0,
+            reorg_depth: Self::DEFAULT_REORG_DEPTH,
+            migrated_at,
+            governance_nonce: 0,
+            large_withdrawal_threshold: 0,
+            tier_discounts: Self::DEFAULT_TIER_DISCOUNTS,
+            current_eon: 0,
+            eon_start_block_height: 0,
+            whitelist_enabled: false,
+            drain_nonce: 0,
+            rotation_delay: 0,
+            reserved: [0; Self::RESERVED_SPACE],
+        }
+    }
+
+    /// Sets the fields v1 lacks, with the same bounds `update_bridge_config` enforces;
+    /// a rotation delay of 0 keeps the minimum
+    pub fn apply_v2_fields(&mut self, fields: &BridgeStateV2Fields) -> Result<()> {
+        require!(fields.tier_discounts.iter().all(|&d| d <= 10000), BridgeError::InvalidTierDiscount);
+        require!(
+            fields.rotation_delay == 0 || fields.rotation_delay as i64 >= MIN_ROTATION_DELAY,
+            BridgeError::InvalidRotationDelay
+        );
+
+        self.large_withdrawal_threshold = fields.large_withdrawal_threshold;
+        self.tier_discounts = fields.tier_discounts;
+        self.whitelist_enabled = fields.whitelist_enabled;
+        self.rotation_delay = fields.rotation_delay;
+        Ok(())
+    }
+
+    pub fn tier_discount(&self, tier: FeeTier) -> u16 {
+        self.tier_discounts[tier as usize]
+    }
+
+    /// Seconds a proposed validator rotation waits before it can execute
+    pub fn effective_rotation_delay(&self) -> i64 {
+        (self.rotation_delay as i64).max(MIN_ROTATION_DELAY)
+    }
+}
+
+/// Marks a Nockchain transaction as minted; one account per `nock_tx_hash`
+#[account]
+pub struct ProcessedDeposit {
+    pub nock_tx_hash: [u8; 32],
+    pub block_height: u64,
+}
+
+impl ProcessedDeposit {
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // nock_tx_hash
+        8; // block_height
+}
+
+/// Fee tier earned through lifetime bridged volume
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeeTier {
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+impl FeeTier {
+    /// Lifetime volume (base units, 8 decimals) needed to reach each tier
+    pub const VOLUME_THRESHOLDS: [u64; 4] = [
+        0,
+        100_000 * NOCK,
+        1_000_000 * NOCK,
+        10_000_000 * NOCK,
+    ];
+
+    const ALL: [FeeTier; 4] = [FeeTier::Bronze, FeeTier::Silver, FeeTier::Gold, FeeTier::Platinum];
+
+    pub fn for_volume(lifetime_volume: u64) -> Self {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|tier| lifetime_volume >= Self::VOLUME_THRESHOLDS[*tier as usize])
+            .unwrap_or(FeeTier::Bronze)
+    }
+}
+
+/// Lifetime deposit and withdrawal volume of a user; one account per user
+#[account]
+pub struct UserVolumeAccount {
+    pub user: Pubkey,
+    pub lifetime_volume: u64,
+    pub tier: FeeTier,
+}
+
+impl UserVolumeAccount {
+    pub const SEED: &'static [u8] = b"user_volume";
+
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // user
+        8 + // lifetime_volume
+        1; // tier
+
+    /// Adds bridged volume and returns the previous tier if this crossed into a higher one
+    pub fn record_volume(&mut self, amount: u64) -> Result<Option<FeeTier>> {
+        self.lifetime_volume = checked_add(self.lifetime_volume, amount)?;
+
+        let previous = self.tier;
+        self.tier = FeeTier::for_volume(self.lifetime_volume);
+        Ok((self.tier > previous).then_some(previous))
+    }
+}
+
+/// Large withdrawal waiting out the timelock; its wNOCK is burned when queued
+#[account]
+pub struct WithdrawalRequest {
+    pub id: u64,
+    pub requester: Pubkey,
+    pub amount: u64,
+    pub nock_address: [u8; 32],
+    pub created_at: i64,
+    pub executed: bool,
+}
+
+impl WithdrawalRequest {
+    pub const SEED: &'static [u8] = b"withdrawal";
+
+    pub const SPACE: usize = 8 + // discriminator
+        8 + // id
+        32 + // requester
+        8 + // amount
+        32 + // nock_address
+        8 + // created_at
+        1; // executed
+}
+
+/// Validator set waiting out the rotation delay; at most one exists at a time
+#[account]
+pub struct PendingRotation {
+    pub new_validators: Vec<Pubkey>,
+    pub new_threshold: u8,
+    pub proposed_at: i64,
+    pub executable_at: i64,
+}
+
+impl PendingRotation {
+    pub const SEED: &'static [u8] = b"pending_rotation";
+
+    pub const SPACE: usize = 8 + // discriminator
+        4 + (32 * 15) + // new_validators (max 15)
+        1 + // new_threshold
+        8 + // proposed_at
+        8; // executable_at
+}
+
+/// Schedule and totals for splitting collected fees
+#[account]
+pub struct FeeDistribution {
+    pub last_distribution_at: i64,   // 0 before the first distribution
+    pub auto_distribute_threshold: u64, // fee balance that makes a distribution due early; 0 disables
+    pub total_distributed: u64,
+    pub bump: u8,
+}
+
+impl FeeDistribution {
+    pub const SEED: &'static [u8] = b"fee_distribution";
+    pub const INTERVAL_SECONDS: i64 = 7 * 86400;
+    pub const MAX_RECIPIENTS: usize = 10;
+
+    pub const SPACE: usize = 8 + // discriminator
+        8 + // last_distribution_at
+        8 + // auto_distribute_threshold
+        8 + // total_distributed
+        1; // bump
+
+    /// Due a week after the last distribution, or as soon as the collected fees reach
+    /// the threshold
+    pub fn is_due(&self, fee_balance: u64, now: i64) -> bool {
+        now.saturating_sub(self.last_distribution_at) >= Self::INTERVAL_SECONDS
+            || (self.auto_distribute_threshold > 0 && fee_balance >= self.auto_distribute_threshold)
+    }
+}
+
+/// A fee recipient and its share of each distribution
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeRecipient {
+    pub recipient: Pubkey,
+    pub share_bps: u16,
+}
+
+/// KYC approval of an address for whitelist mode; one account per user
+#[account]
+pub struct WhitelistEntry {
+    pub user: Pubkey,
+    pub approved: bool,
+    pub daily_limit_override: Option<u64>, // per-address daily limit; the bridge's daily_limit if None
+    pub kyc_expiry: i64,
+    pub daily_volume: u64,
+    pub last_reset_timestamp: i64,
+}
+
+impl WhitelistEntry {
+    pub const SEED: &'static [u8] = b"whitelist";
+    pub const WINDOW_SECONDS: i64 = 86400;
+
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // user
+        1 + // approved
+        1 + 8 + // daily_limit_override (Option<u64>)
+        8 + // kyc_expiry
+        8 + // daily_volume
+        8; // last_reset_timestamp
+
+    /// Counts a transfer against the address's daily limit, starting a new day when the
+    /// last one has ended
+    pub fn record_volume(&mut self, amount: u64, bridge_daily_limit: u64, now: i64) -> Result<()> {
+        let day_ends_at = self.last_reset_timestamp
+            .checked_add(Self::WINDOW_SECONDS)
+            .ok_or(BridgeError::ArithmeticOverflow)?;
+        let (daily_volume, last_reset_timestamp) = if now >= day_ends_at {
+            (0, now)
+        } else {
+            (self.daily_volume, self.last_reset_timestamp)
+        };
+
+        let daily_volume = checked_add(daily_volume, amount)?;
+        require!(
+            daily_volume <= self.daily_limit_override.unwrap_or(bridge_daily_limit),
+            BridgeError::AddressDailyLimitExceeded
+        );
+
+        self.daily_volume = daily_volume;
+        self.last_reset_timestamp = last_reset_timestamp;
+        Ok(())
+    }
+}
+
+/// Deposits a validator has signed during the current day
+#[account]
+pub struct ValidatorRateLimit {
+    pub validator: Pubkey,
+    pub window_start: i64,
+    pub submissions: u32,
+}
+
+impl ValidatorRateLimit {
+    pub const SEED: &'static [u8] = b"validator_rate_limit";
+    pub const WINDOW_SECONDS: i64 = 86400;
+    pub const MAX_DAILY_SUBMISSIONS: u32 = 1_000;
+
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // validator
+        8 + // window_start
+        4; // submissions
+
+    /// Counts one submission, starting a new window once the current one has expired
+    pub fn record_submission(&mut self, now: i64) -> Result<()> {
+        let window_ends_at = self.window_start
+            .checked_add(Self::WINDOW_SECONDS)
+            .ok_or(BridgeError::ArithmeticOverflow)?;
+        if now >= window_ends_at {
+            self.window_start = now;
+            self.submissions = 0;
+        }
+
+        require!(
+            self.submissions < Self::MAX_DAILY_SUBMISSIONS,
+            BridgeError::ValidatorRateLimitExceeded
+        );
+        self.submissions += 1;
+        Ok(())
+    }
+}
+
+/// Constant-product NOCK/wNOCK pool. Reserves are tracked here rather than read from the
+/// vaults, so tokens sent to a vault directly do not move the price.
+#[account]
+pub struct LiquidityPool {
+    pub nock_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub nock_vault: Pubkey,
+    pub wnock_vault: Pubkey,
+    pub nock_reserve: u64,
+    pub wnock_reserve: u64,
+    pub lp_supply: u64,              // includes the locked MINIMUM_LIQUIDITY
+    pub bump: u8,
+}
+
+impl LiquidityPool {
+    pub const SEED: &'static [u8] = b"liquidity_pool";
+    pub const LP_MINT_SEED: &'static [u8] = b"lp_mint";
+    pub const VAULT_SEED: &'static [u8] = b"pool_vault";
+
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // nock_mint
+        32 + // lp_mint
+        32 + // nock_vault
+        32 + // wnock_vault
+        8 + // nock_reserve
+        8 + // wnock_reserve
+        8 + // lp_supply
+        1; // bump
+
+    /// (reserve_in, reserve_out) for a swap in `direction`
+    pub fn reserves(&self, direction: SwapDirection) -> (u64, u64) {
+        match direction {
+            SwapDirection::NockToWnock => (self.nock_reserve, self.wnock_reserve),
+            SwapDirection::WnockToNock => (self.wnock_reserve, self.nock_reserve),
+        }
+    }
+
+    fn apply_deposit(&mut self, deposit: &LiquidityDeposit) -> Result<()> {
+        let nock_reserve = checked_add(self.nock_reserve, deposit.nock)?;
+        let wnock_reserve = checked_add(self.wnock_reserve, deposit.wnock)?;
+        let lp_supply = checked_add(self.lp_supply, deposit.lp_supply_increase)?;
+
+        self.nock_reserve = nock_reserve;
+        self.wnock_reserve = wnock_reserve;
+        self.lp_supply = lp_supply;
+        Ok(())
+    }
+
+    fn apply_withdrawal(&mut self, lp_tokens: u64, amount_nock: u64, amount_wnock: u64) -> Result<()> {
+        let nock_reserve = checked_sub(self.nock_reserve, amount_nock)?;
+        let wnock_reserve = checked_sub(self.wnock_reserve, amount_wnock)?;
+        let lp_supply = checked_sub(self.lp_supply, lp_tokens)?;
+
+        self.nock_reserve = nock_reserve;
+        self.wnock_reserve = wnock_reserve;
+        self.lp_supply = lp_supply;
+        Ok(())
+    }
+
+    /// The whole input, fee included, joins the input reserve
+    fn apply_swap(&mut self, direction: SwapDirection, amount_in: u64, amount_out: u64) -> Result<()> {
+        let (reserve_in, reserve_out) = self.reserves(direction);
+        let reserve_in = checked_add(reserve_in, amount_in)?;
+        let reserve_out = checked_sub(reserve_out, amount_out)?;
+
+        match direction {
+            SwapDirection::NockToWnock => {
+                self.nock_reserve = reserve_in;
+                self.wnock_reserve = reserve_out;
+            }
+            SwapDirection::WnockToNock => {
+                self.wnock_reserve = reserve_in;
+                self.nock_reserve = reserve_out;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// DEX that `swap_and_bridge` swaps NOCK through. Intermediate tokens bridged that way
+/// are held in per-mint vaults owned by this account.
+#[account]
+pub struct MultiHopRoute {
+    pub dex_program: Pubkey,
+    pub nock_mint: Pubkey,
+    pub bump: u8,
+}
+
+impl MultiHopRoute {
+    pub const SEED: &'static [u8] = b"multi_hop_route";
+    pub const VAULT_SEED: &'static [u8] = b"multi_hop_vault";
+
+    pub const SPACE: usize = 8 + // discriminator
+        32 + // dex_program
+        32 + // nock_mint
+        1; // bump
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapDirection {
+    NockToWnock,
+    WnockToNock,
+}
+
+/// Amounts taken from a liquidity provider and the LP tokens minted for them
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LiquidityDeposit {
+    nock: u64,
+    wnock: u64,
+    lp_tokens: u64,
+    lp_supply_increase: u64, // lp_tokens plus any newly locked minimum liquidity
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ValidatorSignature {
+    pub validator: Pubkey,
+    pub signature: [u8; 64],
+}
+
+// Events
+#[event]
+pub struct DepositEvent {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub net_amount: u64,
+    pub nock_tx_hash: [u8; 32],
+    pub block_height: u64,
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawEvent {
+    pub user: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub net_amount: u64,
+    pub nock_address: [u8; 32],
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalQueued {
+    pub id: u64,
+    pub requester: Pubkey,
+    pub amount: u64,
+    pub nock_address: [u8; 32],
+    pub executable_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub id: u64,
+    pub requester: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TierUpgradeEvent {
+    pub user: Pubkey,
+    pub previous_tier: FeeTier,
+    pub new_tier: FeeTier,
+    pub lifetime_volume: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityAdded {
+    pub provider: Pubkey,
+    pub amount_nock: u64,
+    pub amount_wnock: u64,
+    pub lp_tokens: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub provider: Pubkey,
+    pub amount_nock: u64,
+    pub amount_wnock: u64,
+    pub lp_tokens: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SwapEvent {
+    pub user: Pubkey,
+    pub direction: SwapDirection,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct FeeDistributionEvent {
+    pub recipients: Vec<Pubkey>,
+    pub shares_bps: Vec<u16>,
+    pub amounts: Vec<u64>,
+    pub total: u64,
+    pub timestamp: i64,
+    pub distributed_by: Pubkey,
+}
+
+/// Both legs of a `swap_and_bridge`: NOCK swapped on the DEX, then the intermediate
+/// token bridged to `nock_address`
+#[event]
+pub struct MultiHopBridgeEvent {
+    pub user: Pubkey,
+    pub nock_amount: u64,            // fee included
+    pub fee: u64,
+    pub nock_swapped: u64,
+    pub intermediate_mint: Pubkey,
+    pub intermediate_amount: u64,
+    pub dex_program: Pubkey,
+    pub nock_address: [u8; 32],
+    pub nonce: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EmergencyPauseEvent {
+    pub timestamp: i64,
+    pub triggered_by: Pubkey,
+}
+
+#[event]
+pub struct UnpauseEvent {
+    pub timestamp: i64,
+    pub authorized_by: Pubkey,
+}
+
+#[event]
+pub struct EmergencyDrainEvent {
+    pub destination: [u8; 32],
+    pub amount: u64,
+    pub drain_nonce: u64,
+    pub timestamp: i64,
+    pub authorized_by: Pubkey,
+}
+
+#[event]
+pub struct ConfigUpdateEvent {
+    pub timestamp: i64,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct BridgeStateUpgradeEvent {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub upgraded_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EonTransitionEvent {
+    pub previous_eon: u64,
+    pub new_eon: u64,
+    pub start_block_height: u64,
+    pub timestamp: i64,
+    pub registered_by: Pubkey,
+}
+
+#[event]
+pub struct WhitelistUpdatedEvent {
+    pub user: Pubkey,
+    pub approved: bool,
+    pub daily_limit_override: Option<u64>,
+    pub kyc_expiry: i64,
+    pub timestamp: i64,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct ValidatorRotationProposed {
+    pub new_validators: Vec<Pubkey>,
+    pub new_threshold: u8,
+    pub executable_at: i64,
+    pub timestamp: i64,
+    pub proposed_by: Pubkey,
+}
+
+#[event]
+pub struct ValidatorRotationCancelled {
+    pub new_validators: Vec<Pubkey>,
+    pub new_threshold: u8,
+    pub timestamp: i64,
+    pub cancelled_by: Pubkey,
+}
+
+#[event]
+pub struct ValidatorRotationEvent {
+    pub previous_validators: Vec<Pubkey>,
+    pub new_validators: Vec<Pubkey>,
+    pub epoch: u64,
+    pub timestamp: i64,
+}
+
+// Error codes
+#[error_code]
+pub enum BridgeError {
+    #[msg("Invalid validator count")]
+    InvalidValidatorCount,
+    #[msg("Invalid threshold")]
+    InvalidThreshold,
+    #[msg("Invalid fee rate")]
+    InvalidFeeRate,
+    #[msg("Invalid daily limit")]
+    InvalidDailyLimit,
+    #[msg("Invalid emergency delay")]
+    InvalidEmergencyDelay,
+    #[msg("Invalid decimals")]
+    InvalidDecimals,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Bridge is paused")]
+    BridgePaused,
+    #[msg("Bridge is not paused")]
+    NotPaused,
+    #[msg("Bridge is already paused")]
+    AlreadyPaused,
+    #[msg("Daily limit exceeded")]
+    DailyLimitExceeded,
+    #[msg("Insufficient validator signatures")]
+    InsufficientSignatures,
+    #[msg("Invalid validator signature")]
+    InvalidSignature,
+    #[msg("Emergency delay not met")]
+    EmergencyDelayNotMet,
+    #[msg("Arithmetic overflow")]
+    ArithmeticOverflow,
+    #[msg("Block height is older than the reorg window")]
+    StaleBlockHeight,
+    #[msg("Incompatible bridge state version")]
+    IncompatibleBridgeVersion,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Nockchain transaction has already been deposited")]
+    DuplicateDeposit,
+    #[msg("Validator appears more than once in the signature set")]
+    DuplicateValidator,
+    #[msg("Validator has reached its daily submission limit")]
+    ValidatorRateLimitExceeded,
+    #[msg("Missing validator rate limit account")]
+    MissingValidatorRateLimit,
+    #[msg("Withdrawal request account required for large withdrawals")]
+    WithdrawalRequestRequired,
+    #[msg("Withdrawal timelock has not elapsed")]
+    WithdrawalTimelockActive,
+    #[msg("Withdrawal timelock has elapsed")]
+    WithdrawalTimelockExpired,
+    #[msg("Withdrawal has already been executed")]
+    WithdrawalAlreadyExecuted,
+    #[msg("Invalid fee tier discount")]
+    InvalidTierDiscount,
+    #[msg("Eon transition must advance to the next eon")]
+    InvalidEonTransition,
+    #[msg("Deposit from a previous eon must carry its eon tag")]
+    MissingEonTag,
+    #[msg("Deposit eon tag does not match its block height")]
+    EonTagMismatch,
+    #[msg("Liquidity pool has no liquidity")]
+    ZeroLiquidity,
+    #[msg("Deposit is too small to mint LP tokens")]
+    InsufficientLiquidityMinted,
+    #[msg("Withdrawal is too small to return any tokens")]
+    InsufficientLiquidityBurned,
+    #[msg("Swap output is below the minimum amount out")]
+    SlippageExceeded,
+    #[msg("Address is not whitelisted")]
+    NotWhitelisted,
+    #[msg("Address KYC approval has expired")]
+    KycExpired,
+    #[msg("Address daily limit exceeded")]
+    AddressDailyLimitExceeded,
+    #[msg("Rotation delay is shorter than the minimum")]
+    InvalidRotationDelay,
+    #[msg("Validator rotation delay has not elapsed")]
+    RotationDelayNotMet,
+    #[msg("DEX program does not match the configured multi-hop route")]
+    InvalidDexProgram,
+    #[msg("Invalid intermediate token")]
+    InvalidIntermediateToken,
+    #[msg("DEX spent more NOCK than the swap allowed")]
+    DexOverspent,
+    #[msg("Fee recipients must be distinct with shares summing to 10000 basis points")]
+    InvalidFeeRecipients,
+    #[msg("Fee recipient token account does not match the recipient")]
+    FeeRecipientAccountMismatch,
+    #[msg("Fee distribution is not due yet")]
+    FeeDistributionNotDue,
+    #[msg("Pending rotation account must be passed exactly when a validator rotation is proposed")]
+    PendingRotationMismatch,
+}
+
+// Helper functions
+
+/// Fee at `fee_rate` basis points, reduced by `discount` basis points of the fee.
+/// Rounded down once at the end so the discount never costs the user a base unit.
+fn calculate_fee(amount: u64, fee_rate: u16, discount: u16) -> Result<u64> {
+    nock_bridge_math::calculate_fee(amount, fee_rate, discount)
+        .map_err(|_| BridgeError::ArithmeticOverflow.into())
+}
+
+/// Adds bridged volume to the user's account, claiming it on first use, and emits
+/// `TierUpgradeEvent` when the user reaches a new tier
+fn record_user_volume(user_volume: &mut UserVolumeAccount, user: Pubkey, amount: u64) -> Result<()> {
+    user_volume.user = user;
+
+    if let Some(previous_tier) = user_volume.record_volume(amount)? {
+        emit!(TierUpgradeEvent {
+            user,
+            previous_tier,
+            new_tier: user_volume.tier,
+            lifetime_volume: user_volume.lifetime_volume,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+fn checked_add(a: u64, b: u64) -> Result<u64> {
+    a.checked_add(b).ok_or(BridgeError::ArithmeticOverflow.into())
+}
+
+fn checked_sub(a: u64, b: u64) -> Result<u64> {
+    a.checked_sub(b).ok_or(BridgeError::ArithmeticOverflow.into())
+}
+
+fn check_daily_limit(bridge: &BridgeState, amount: u64) -> Result<()> {
+    require!(
+        checked_add(bridge.daily_volume, amount)? <= bridge.daily_limit,
+        BridgeError::DailyLimitExceeded
+    );
+    Ok(())
+}
+
+/// In whitelist mode, requires an approved entry with unexpired KYC for `user` and counts
+/// `amount` against its daily limit. Does nothing while whitelist mode is off.
+fn check_whitelist(
+    bridge: &BridgeState,
+    entry: Option<&mut WhitelistEntry>,
+    user: Pubkey,
+    amount: u64,
+    now: i64,
+) -> Result<()> {
+    if !bridge.whitelist_enabled {
+        return Ok(());
+    }
+
+    let entry = entry.ok_or(error!(BridgeError::NotWhitelisted))?;
+    require!(entry.approved && entry.user == user, BridgeError::NotWhitelisted);
+    require!(now < entry.kyc_expiry, BridgeError::KycExpired);
+    entry.record_volume(amount, bridge.daily_limit, now)
+}
+
+/// Adds a deposit to the bridge totals. All new values are computed before any field is
+/// written, so an overflow leaves the state untouched.
+fn apply_deposit(bridge: &mut BridgeState, amount: u64, fee: u64) -> Result<()> {
+    let nonce = checked_add(bridge.nonce, 1)?;
+    let total_locked = checked_add(bridge.total_locked, amount)?;
+    let total_fees_collected = checked_add(bridge.total_fees_collected, fee)?;
+    let daily_volume = checked_add(bridge.daily_volume, amount)?;
+
+    bridge.nonce = nonce;
+    bridge.total_locked = total_locked;
+    bridge.total_fees_collected = total_fees_collected;
+    bridge.daily_volume = daily_volume;
+    Ok(())
+}
+
+/// Removes a withdrawal from the bridge totals, leaving the state untouched on overflow
+fn apply_withdrawal(bridge: &mut BridgeState, amount: u64, fee: u64, net_amount: u64) -> Result<()> {
+    let nonce = checked_add(bridge.nonce, 1)?;
+    let total_locked = checked_sub(bridge.total_locked, net_amount)?;
+    let total_fees_collected = checked_add(bridge.total_fees_collected, fee)?;
+    let daily_volume = checked_add(bridge.daily_volume, amount)?;
+
+    bridge.nonce = nonce;
+    bridge.total_locked = total_locked;
+    bridge.total_fees_collected = total_fees_collected;
+    bridge.daily_volume = daily_volume;
+    Ok(())
+}
+
+fn is_large_withdrawal(amount: u64, large_withdrawal_threshold: u64) -> bool {
+    large_withdrawal_threshold > 0 && amount > large_withdrawal_threshold
+}
+
+fn withdrawal_unlocks_at(request: &WithdrawalRequest, emergency_delay: i64) -> Result<i64> {
+    request.created_at
+        .checked_add(emergency_delay)
+        .ok_or(BridgeError::ArithmeticOverflow.into())
+}
+
+fn check_withdrawal_executable(request: &WithdrawalRequest, now: i64, emergency_delay: i64) -> Result<()> {
+    require!(!request.executed, BridgeError::WithdrawalAlreadyExecuted);
+    require!(now >= withdrawal_unlocks_at(request, emergency_delay)?, BridgeError::WithdrawalTimelockActive);
+    Ok(())
+}
+
+/// Cancellation is possible while the withdrawal is still timelocked, and afterwards only if
+/// the daily limit has since been lowered below its amount so that it can never execute
+fn check_withdrawal_cancellable(
+    request: &WithdrawalRequest,
+    now: i64,
+    emergency_delay: i64,
+    daily_limit: u64,
+) -> Result<()> {
+    require!(!request.executed, BridgeError::WithdrawalAlreadyExecuted);
+    require!(
+        now < withdrawal_unlocks_at(request, emergency_delay)? || request.amount > daily_limit,
+        BridgeError::WithdrawalTimelockExpired
+    );
+    Ok(())
+}
+
+/// Validates a Nockchain block height against the reorg window and returns the
+/// new `last_processed_block_height`
+fn check_block_height(block_height: u64, last_processed: u64, reorg_depth: u64) -> Result<u64> {
+    require!(
+        block_height >= last_processed.saturating_sub(reorg_depth),
+        BridgeError::StaleBlockHeight
+    );
+    Ok(last_processed.max(block_height))
+}
+
+fn mul_div(a: u64, b: u64, denominator: u64) -> Result<u64> {
+    (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|x| x.checked_div(denominator as u128))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(BridgeError::ArithmeticOverflow.into())
+}
+
+fn mul_div_ceil(a: u64, b: u64, denominator: u64) -> Result<u64> {
+    (a as u128)
+        .checked_mul(b as u128)
+        .and_then(|x| x.checked_add(denominator.checked_sub(1)? as u128))
+        .and_then(|x| x.checked_div(denominator as u128))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(BridgeError::ArithmeticOverflow.into())
+}
+
+fn integer_sqrt(n: u128) -> u128 {
+    if n == 0 {
+        return 0;
+    }
+
+    let mut x = n;
+    let mut y = x / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+/// LP tokens for a deposit of up to `amount_nock` and `amount_wnock`. The first deposit
+/// mints the geometric mean less `MINIMUM_LIQUIDITY`, which stays locked in the supply.
+/// Later deposits are limited by the scarcer side at the current ratio, and the other
+/// side is rounded up in the pool's favour.
+fn quote_add_liquidity(amount_nock: u64, amount_wnock: u64, pool: &LiquidityPool) -> Result<LiquidityDeposit> {
+    require!(amount_nock > 0 && amount_wnock > 0, BridgeError::InvalidAmount);
+
+    if pool.lp_supply == 0 {
+        let liquidity = (amount_nock as u128)
+            .checked_mul(amount_wnock as u128)
+            .map(integer_sqrt)
+            .and_then(|x| u64::try_from(x).ok())
+            .ok_or(BridgeError::ArithmeticOverflow)?;
+        require!(liquidity > MINIMUM_LIQUIDITY, BridgeError::InsufficientLiquidityMinted);
+
+        return Ok(LiquidityDeposit {
+            nock: amount_nock,
+            wnock: amount_wnock,
+            lp_tokens: liquidity - MINIMUM_LIQUIDITY,
+            lp_supply_increase: liquidity,
+        });
+    }
+
+    require!(pool.nock_reserve > 0 && pool.wnock_reserve > 0, BridgeError::ZeroLiquidity);
+
+    let nock_limited = (amount_nock as u128) * (pool.wnock_reserve as u128)
+        <= (amount_wnock as u128) * (pool.nock_reserve as u128);
+    let (nock, wnock, lp_tokens) = if nock_limited {
+        (
+            amount_nock,
+            mul_div_ceil(amount_nock, pool.wnock_reserve, pool.nock_reserve)?,
+            mul_div(amount_nock, pool.lp_supply, pool.nock_reserve)?,
+        )
+    } else {
+        (
+            mul_div_ceil(amount_wnock, pool.nock_reserve, pool.wnock_reserve)?,
+            amount_wnock,
+            mul_div(amount_wnock, pool.lp_supply, pool.wnock_reserve)?,
+        )
+    };
+    require!(lp_tokens > 0, BridgeError::InsufficientLiquidityMinted);
+
+    Ok(LiquidityDeposit { nock, wnock, lp_tokens, lp_supply_increase: lp_tokens })
+}
+
+/// Pro-rata (NOCK, wNOCK) returned for burning `lp_tokens`, rounded down
+fn quote_remove_liquidity(lp_tokens: u64, pool: &LiquidityPool) -> Result<(u64, u64)> {
+    require!(lp_tokens > 0, BridgeError::InvalidAmount);
+    require!(pool.lp_supply > 0, BridgeError::ZeroLiquidity);
+    require!(lp_tokens <= pool.lp_supply, BridgeError::InvalidAmount);
+
+    let amount_nock = mul_div(lp_tokens, pool.nock_reserve, pool.lp_supply)?;
+    let amount_wnock = mul_div(lp_tokens, pool.wnock_reserve, pool.lp_supply)?;
+    require!(amount_nock > 0 || amount_wnock > 0, BridgeError::InsufficientLiquidityBurned);
+    Ok((amount_nock, amount_wnock))
+}
+
+/// Constant-product output for `amount_in` after the swap fee:
+/// `out = in_after_fee * reserve_out / (reserve_in + in_after_fee)`
+fn quote_swap(amount_in: u64, min_amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    require!(amount_in > 0, BridgeError::InvalidAmount);
+    require!(reserve_in > 0 && reserve_out > 0, BridgeError::ZeroLiquidity);
+
+    let in_after_fee = (amount_in as u128) * ((10_000 - SWAP_FEE_BPS) as u128);
+    let amount_out = in_after_fee
+        .checked_mul(reserve_out as u128)
+        .and_then(|x| x.checked_div((reserve_in as u128) * 10_000 + in_after_fee))
+        .and_then(|x| u64::try_from(x).ok())
+        .ok_or(BridgeError::ArithmeticOverflow)?;
+
+    require!(amount_out > 0 && amount_out >= min_amount_out, BridgeError::SlippageExceeded);
+    Ok(amount_out)
+}
+
+/// Swap call made to the configured DEX: Anchor's `global:swap` discriminator followed
+/// by `amount_in` and `min_amount_out`, little-endian
+fn dex_swap_instruction(dex_program: Pubkey, accounts: Vec<AccountMeta>, amount_in: u64, min_amount_out: u64) -> Instruction {
+    use solana_program::hash::hash;
+
+    let mut data = hash(b"global:swap").to_bytes()[..8].to_vec();
+    data.extend_from_slice(&amount_in.to_le_bytes());
+    data.extend_from_slice(&min_amount_out.to_le_bytes());
+
+    Instruction {
+        program_id: dex_program,
+        accounts,
+        data,
+    }
+}
+
+/// Checks the balances around a DEX swap and returns `(nock_swapped, amount_out)`.
+/// The DEX may spend less than `max_nock_in` but never more, and must deliver at least
+/// `min_out` to the vault whatever its own slippage check did.
+fn settle_dex_swap(
+    nock_before: u64,
+    nock_after: u64,
+    vault_before: u64,
+    vault_after: u64,
+    max_nock_in: u64,
+    min_out: u64,
+) -> Result<(u64, u64)> {
+    let nock_swapped = nock_before.checked_sub(nock_after).ok_or(BridgeError::DexOverspent)?;
+    require!(nock_swapped <= max_nock_in, BridgeError::DexOverspent);
+
+    let amount_out = vault_after.saturating_sub(vault_before);
+    require!(amount_out > 0 && amount_out >= min_out, BridgeError::SlippageExceeded);
+    Ok((nock_swapped, amount_out))
+}
+
+/// Counts a multi-hop bridge against the daily volume and fee totals. `total_locked`
+/// tracks NOCK only and is unchanged, since the NOCK left through the DEX.
+fn apply_multi_hop(bridge: &mut BridgeState, amount: u64, fee: u64) -> Result<()> {
+    let nonce = checked_add(bridge.nonce, 1)?;
+    let total_fees_collected = checked_add(bridge.total_fees_collected, fee)?;
+    let daily_volume = checked_add(bridge.daily_volume, amount)?;
+
+    bridge.nonce = nonce;
+    bridge.total_fees_collected = total_fees_collected;
+    bridge.daily_volume = daily_volume;
+    Ok(())
+}
+
+/// 1 to `FeeDistribution::MAX_RECIPIENTS` distinct recipients, each with a share, the
+/// shares summing to 100%
+fn check_fee_recipients(recipients: &[FeeRecipient]) -> Result<()> {
+    require!(
+        !recipients.is_empty() && recipients.len() <= FeeDistribution::MAX_RECIPIENTS,
+        BridgeError::InvalidFeeRecipients
+    );
+    let distinct: BTreeSet<&Pubkey> = recipients.iter().map(|r| &r.recipient).collect();
+    require!(distinct.len() == recipients.len(), BridgeError::InvalidFeeRecipients);
+    require!(recipients.iter().all(|r| r.share_bps > 0), BridgeError::InvalidFeeRecipients);
+    let total: u32 = recipients.iter().map(|r| r.share_bps as u32).sum();
+    require!(total == 10000, BridgeError::InvalidFeeRecipients);
+    Ok(())
+}
+
+/// Each recipient's share of `balance`, rounded down. The rounding remainder stays in
+/// the fee collector for the next distribution.
+fn split_fees(balance: u64, recipients: &[FeeRecipient]) -> Result<Vec<u64>> {
+    recipients
+        .iter()
+        .map(|r| {
+            u64::try_from((balance as u128) * (r.share_bps as u128) / 10000)
+                .map_err(|_| error!(BridgeError::ArithmeticOverflow))
+        })
+        .collect()
+}
+
+/// Parameters accepted by `initialize_bridge`. Public so off-chain tooling rejects a
+/// bad bridge config before building the transaction.
+pub fn check_bridge_params(
+    validators: &[Pubkey],
+    threshold: u8,
+    fee_rate: u16,
+    daily_limit: u64,
+    emergency_delay: i64,
+) -> Result<()> {
+    require!(validators.len() >= 3 && validators.len() <= 15, BridgeError::InvalidValidatorCount);
+    require!(threshold >= (validators.len() as u8 + 1) / 2, BridgeError::InvalidThreshold);
+    require!(fee_rate <= 10000, BridgeError::InvalidFeeRate); // Max 100%
+    require!(daily_limit > 0, BridgeError::InvalidDailyLimit);
+    require!(emergency_delay >= 3600, BridgeError::InvalidEmergencyDelay); // Min 1 hour
+    Ok(())
+}
+
+/// A validator set of 3 to 15 distinct keys, with a threshold of at least half of them
+fn check_validator_set(validators: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(validators.len() >= 3 && validators.len() <= 15, BridgeError::InvalidValidatorCount);
+    let distinct: BTreeSet<&Pubkey> = validators.iter().collect();
+    require!(distinct.len() == validators.len(), BridgeError::DuplicateValidator);
+    check_threshold(validators.len(), threshold)
+}
+
+/// A threshold needs at least half of the validators, rounded up, and no more than all of them
+fn check_threshold(validator_count: usize, threshold: u8) -> Result<()> {
+    require!(
+        threshold as usize >= (validator_count + 1) / 2 && threshold as usize <= validator_count,
+        BridgeError::InvalidThreshold
+    );
+    Ok(())
+}
+
+/// A drain is only possible once the bridge has been paused for three emergency delays
+fn check_drain_delay(bridge: &BridgeState, now: i64) -> Result<()> {
+    require!(bridge.is_paused, BridgeError::NotPaused);
+    let pause_time = bridge.pause_timestamp.ok_or(BridgeError::NotPaused)?;
+    let delay = bridge.emergency_delay.checked_mul(3).ok_or(BridgeError::ArithmeticOverflow)?;
+    let unlocks_at = pause_time.checked_add(delay).ok_or(BridgeError::ArithmeticOverflow)?;
+    require!(now >= unlocks_at, BridgeError::EmergencyDelayNotMet);
+    Ok(())
+}
+
+/// Signatures needed to drain: two thirds of the validators, rounded up, and never fewer
+/// than the regular threshold
+fn drain_threshold(validator_count: usize, threshold: u8) -> u8 {
+    let super_majority = (2 * validator_count + 2) / 3;
+    (super_majority as u8).max(threshold)
+}
+
+/// A transition must move to the eon directly after the current one, starting no earlier
+/// than the current eon did
+fn check_eon_transition(bridge: &BridgeState, new_eon: u64, start_block_height: u64) -> Result<()> {
+    nock_bridge_math::check_eon_transition(bridge.current_eon, bridge.eon_start_block_height, new_eon, start_block_height)
+        .map_err(eon_error)
+}
+
+/// Moves the bridge into `new_eon`. The validator set is untouched; a set for the new eon
+/// only takes over through a `PendingRotation`.
+fn advance_eon(bridge: &mut BridgeState, new_eon: u64, start_block_height: u64) {
+    bridge.current_eon = new_eon;
+    bridge.eon_start_block_height = start_block_height;
+}
+
+/// Fills in a proposed rotation and returns when it becomes executable, one rotation
+/// delay from `now`
+fn stage_validator_rotation(
+    pending: &mut PendingRotation,
+    bridge: &BridgeState,
+    new_validators: Vec<Pubkey>,
+    new_threshold: u8,
+    now: i64,
+) -> Result<i64> {
+    let executable_at = now
+        .checked_add(bridge.effective_rotation_delay())
+        .ok_or(BridgeError::ArithmeticOverflow)?;
+
+    pending.new_validators = new_validators;
+    pending.new_threshold = new_threshold;
+    pending.proposed_at = now;
+    pending.executable_at = executable_at;
+    Ok(executable_at)
+}
+
+/// A proposed rotation can only be executed once its rotation delay has passed
+fn check_rotation_executable(pending: &PendingRotation, now: i64) -> Result<()> {
+    require!(now >= pending.executable_at, BridgeError::RotationDelayNotMet);
+    Ok(())
+}
+
+/// Deposits from blocks before the current eon began must be tagged with the previous
+/// eon, so attestations signed before a transition cannot be replayed across it. Deposits
+/// from the current eon may omit the tag.
+fn check_deposit_eon(block_height: u64, eon: Option<u64>, current_eon: u64, eon_start_block_height: u64) -> Result<()> {
+    nock_bridge_math::check_deposit_eon(block_height, eon, current_eon, eon_start_block_height)
+        .map_err(eon_error)
+}
+
+fn eon_error(error: nock_bridge_math::EonError) -> Error {
+    match error {
+        nock_bridge_math::EonError::MissingEonTag => BridgeError::MissingEonTag.into(),
+        nock_bridge_math::EonError::EonTagMismatch => BridgeError::EonTagMismatch.into(),
+        nock_bridge_math::EonError::InvalidEonTransition => BridgeError::InvalidEonTransition.into(),
+    }
+}
+
+/// Rejects bridge accounts that have not been migrated to the current layout. The expected
+/// and actual versions are logged alongside the error.
+///
+/// Instructions only read the current layout: a v1 account already fails to deserialize
+/// as `BridgeState`, and it has to go through `upgrade_bridge_state` before any other
+/// instruction accepts it. There is no per-instruction fallback to the v1 layout.
+fn check_bridge_version(bridge: &BridgeState) -> Result<()> {
+    require_eq!(
+        bridge.version,
+        BridgeState::CURRENT_VERSION,
+        BridgeError::IncompatibleBridgeVersion
+    );
+    Ok(())
+}
+
+/// Decodes a v1 bridge account (discriminator included) into the v2 layout. v1 has no
+/// version byte, so the account size tells it apart: v1 accounts were created with
+/// exactly `V1_SPACE` bytes, and upgraded ones are grown to `SPACE`.
+fn migrate_bridge_state_data(data: &[u8], migrated_at: i64) -> Result<BridgeState> {
+    require!(
+        data.len() >= 8 && data[..8] == BridgeState::DISCRIMINATOR,
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+    require_eq!(data.len(), BridgeState::V1_SPACE, BridgeError::IncompatibleBridgeVersion);
+
+    let v1 = BridgeStateV1::deserialize(&mut &data[8..])
+        .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))?;
+
+    Ok(BridgeState::from_v1(v1, migrated_at))
+}
+
+/// Decodes a v1 bridge account into the current layout with `fields` in place of the
+/// v2 defaults
+fn upgrade_bridge_state_data(data: &[u8], fields: &BridgeStateV2Fields, upgraded_at: i64) -> Result<BridgeState> {
+    let mut upgraded = migrate_bridge_state_data(data, upgraded_at)?;
+    upgraded.apply_v2_fields(fields)?;
+    Ok(upgraded)
+}
+
+/// Grows a migrated bridge account to `BridgeState::SPACE`, topping up rent from `payer`,
+/// and writes `state` over the old layout
+fn store_migrated_bridge_state<'info>(
+    bridge_info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    state: &BridgeState,
+) -> Result<()> {
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(BridgeState::SPACE);
+    let shortfall = rent_exempt_minimum.saturating_sub(bridge_info.lamports());
+    if shortfall > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: bridge_info.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+    bridge_info.realloc(BridgeState::SPACE, false)?;
+
+    let mut data = bridge_info.try_borrow_mut_data()?;
+    state.try_serialize(&mut &mut data[..])?;
+    Ok(())
+}
+
+/// Fills in a deposit's processed record. The account is created empty on first use, so a
+/// record already holding this hash means the deposit was minted before. An all-zero hash
+/// is indistinguishable from an empty record and is never accepted.
+fn record_processed_deposit(record: &mut ProcessedDeposit, nock_tx_hash: [u8; 32], block_height: u64) -> Result<()> {
+    require!(record.nock_tx_hash != nock_tx_hash, BridgeError::DuplicateDeposit);
+
+    record.nock_tx_hash = nock_tx_hash;
+    record.block_height = block_height;
+    Ok(())
+}
+
+fn reset_daily_volume_if_needed(bridge: &mut BridgeState) -> Result<()> {
+    let current_time = Clock::get()?.unix_timestamp;
+    let seconds_in_day = 86400;
+    let day_ends_at = bridge.last_reset_timestamp
+        .checked_add(seconds_in_day)
+        .ok_or(BridgeError::ArithmeticOverflow)?;
+
+    if current_time >= day_ends_at {
+        bridge.daily_volume = 0;
+        bridge.last_reset_timestamp = current_time;
+    }
+    
+    Ok(())
+}
+
+/// Ed25519 signature checked by the native Ed25519 program earlier in the transaction.
+/// The runtime fails the whole transaction if any of those checks fail, so every
+/// entry here is a valid signature by `pubkey` over `message`.
+#[derive(Debug, Clone, PartialEq)]
+struct VerifiedSignature {
+    pubkey: Pubkey,
+    signature: [u8; 64],
+    message: Vec<u8>,
+}
+
+/// Collects the signatures verified by Ed25519 program instructions preceding the current one
+fn load_verified_signatures(instructions_sysvar: &AccountInfo) -> Result<Vec<VerifiedSignature>> {
+    let current_index = load_current_index_checked(instructions_sysvar)? as usize;
+    let mut verified = Vec::new();
+
+    for index in 0..current_index {
+        let instruction = load_instruction_at_checked(index, instructions_sysvar)?;
+        if instruction.program_id == ed25519_program::ID {
+            verified.extend(parse_ed25519_instruction(&instruction.data)?);
+        }
+    }
+
+    Ok(verified)
+}
+
+/// Decodes Ed25519 program instruction data: a signature count, a padding byte, then one
+/// 14-byte offsets record per signature. Only signatures whose key, signature and message
+/// are embedded in the same instruction are accepted.
+fn parse_ed25519_instruction(data: &[u8]) -> Result<Vec<VerifiedSignature>> {
+    require!(data.len() >= ED25519_OFFSETS_START, BridgeError::InvalidSignature);
+
+    let count = data[0] as usize;
+    let mut verified = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let start = ED25519_OFFSETS_START + i * ED25519_OFFSETS_SIZE;
+        let offsets = data
+            .get(start..start + ED25519_OFFSETS_SIZE)
+            .ok_or(error!(BridgeError::InvalidSignature))?;
+        let field = |at: usize| u16::from_le_bytes([offsets[at], offsets[at + 1]]);
+
+        let signature_offset = field(0);
+        let public_key_offset = field(4);
+        let message_offset = field(8);
+        let message_size = field(10);
+        require!(
+            field(2) == u16::MAX && field(6) == u16::MAX && field(12) == u16::MAX,
+            BridgeError::InvalidSignature
+        );
+
+        let slice = |offset: u16, len: usize| {
+            data.get(offset as usize..offset as usize + len)
+                .ok_or(error!(BridgeError::InvalidSignature))
+        };
+        verified.push(VerifiedSignature {
+            pubkey: Pubkey::try_from(slice(public_key_offset, 32)?)
+                .map_err(|_| error!(BridgeError::InvalidSignature))?,
+            signature: slice(signature_offset, 64)?
+                .try_into()
+                .map_err(|_| error!(BridgeError::InvalidSignature))?,
+            message: slice(message_offset, message_size as usize)?.to_vec(),
+        });
+    }
+
+    Ok(verified)
+}
+
+fn verify_validator_signatures(
+    signatures: &[ValidatorSignature],
+    validators: &[Pubkey],
+    threshold: u8,
+    verified: &[VerifiedSignature],
+    tx_hash: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    block_height: u64,
+    eon: Option<u64>,
+) -> Result<Vec<Pubkey>> {
+    let message = create_deposit_message(&crate::ID, tx_hash, recipient, amount, block_height, eon);
+    verify_signatures(signatures, validators, threshold, verified, &message)
+}
+
+fn verify_emergency_signatures(
+    signatures: &[ValidatorSignature],
+    validators: &[Pubkey],
+    threshold: u8,
+    verified: &[VerifiedSignature],
+    message: &[u8],
+) -> Result<()> {
+    verify_signatures(signatures, validators, threshold, verified, message)?;
+    Ok(())
+}
+
+/// Returns the validators whose signature over `message` was checked by the Ed25519
+/// program. A key listed twice, or a validator signature that was not checked, fails the
+/// whole instruction; signatures from keys outside the validator set are not counted.
+fn verify_signatures(
+    signatures: &[ValidatorSignature],
+    validators: &[Pubkey],
+    threshold: u8,
+    verified: &[VerifiedSignature],
+    message: &[u8],
+) -> Result<Vec<Pubkey>> {
+    let signers: Vec<Pubkey> = signatures.iter().map(|sig| sig.validator).collect();
+    nock_bridge_math::check_quorum(&signers, validators, threshold, |index| {
+        let sig = &signatures[index];
+        verified.iter().any(|v| {
+            v.pubkey == sig.validator && v.signature == sig.signature && v.message == message
+        })
+    })
+    .map_err(|error| match error {
+        nock_bridge_math::SignatureError::InsufficientSignatures => BridgeError::InsufficientSignatures.into(),
+        nock_bridge_math::SignatureError::DuplicateValidator => BridgeError::DuplicateValidator.into(),
+        nock_bridge_math::SignatureError::InvalidSignature => BridgeError::InvalidSignature.into(),
+    })
+}
+
+/// Bumps the daily counter of each signer, found by PDA among `rate_limits`
+fn record_validator_submissions<'info>(
+    rate_limits: &[AccountInfo<'info>],
+    signers: &[Pubkey],
+    now: i64,
+) -> Result<()> {
+    for signer in signers {
+        let (address, _) = Pubkey::find_program_address(&[ValidatorRateLimit::SEED, signer.as_ref()], &crate::ID);
+        let info = rate_limits
+            .iter()
+            .find(|account| account.key() == address)
+            .ok_or(error!(BridgeError::MissingValidatorRateLimit))?;
+
+        let mut rate_limit: Account<ValidatorRateLimit> = Account::try_from(info)?;
+        rate_limit.record_submission(now)?;
+        rate_limit.exit(&crate::ID)?;
+    }
+    Ok(())
+}
+
+/// Deposit attestation signed by validators, prefixed with the program ID so that
+/// signatures cannot be replayed against another deployment. The recipient is the wallet
+/// minted to, so an attestation cannot be front-run to another account. A tagged deposit
+/// has its eon appended.
+pub fn create_deposit_message(
+    program_id: &Pubkey,
+    tx_hash: &[u8; 32],
+    recipient: &Pubkey,
+    amount: u64,
+    block_height: u64,
+    eon: Option<u64>,
+) -> Vec<u8> {
+    nock_bridge_math::create_deposit_message(&program_id.to_bytes(), tx_hash, &recipient.to_bytes(), amount, block_height, eon)
+}
+
+/// Governance message bound to the program ID and the bridge's governance nonce, so
+/// each set of signatures authorizes a single action
+fn create_governance_message(domain: &[u8], governance_nonce: u64, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::new();
+    message.extend_from_slice(crate::ID.as_ref());
+    message.extend_from_slice(domain);
+    message.extend_from_slice(&governance_nonce.to_le_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+fn hash_config_update(
+    fee_rate: &Option<u16>,
+    daily_limit: &Option<u64>,
+    threshold: &Option<u8>,
+    large_withdrawal_threshold: &Option<u64>,
+    tier_discounts: &Option<[u16; 4]>,
+    whitelist_enabled: &Option<bool>,
+    rotation_delay: &Option<u32>,
+) -> [u8; 32] {
+    // SHA-256, the same digest as solana_program::hash
+    nock_bridge_math::hash_config_update(
+        fee_rate,
+        daily_limit,
+        threshold,
+        large_withdrawal_threshold,
+        tier_discounts,
+        whitelist_enabled,
+        rotation_delay,
+    )
+}
+
+fn hash_eon_transition(new_eon: u64, start_block_height: u64, validator_rotation: &Option<Vec<Pubkey>>) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&new_eon.to_le_bytes());
+    data.extend_from_slice(&start_block_height.to_le_bytes());
+    if let Some(validators) = validator_rotation {
+        for validator in validators {
+            data.extend_from_slice(validator.as_ref());
+        }
+    }
+
+    hash(&data).to_bytes()
+}
+
+fn hash_validator_rotation(validators: &[Pubkey], threshold: u8) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    for validator in validators {
+        data.extend_from_slice(validator.as_ref());
+    }
+    data.push(threshold);
+
+    hash(&data).to_bytes()
+}
+
+fn hash_fee_distribution(recipients: &[FeeRecipient]) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    for recipient in recipients {
+        data.extend_from_slice(recipient.recipient.as_ref());
+        data.extend_from_slice(&recipient.share_bps.to_le_bytes());
+    }
+
+    hash(&data).to_bytes()
+}
+
+fn hash_multi_hop_route(dex_program: &Pubkey, nock_mint: &Pubkey) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(dex_program.as_ref());
+    data.extend_from_slice(nock_mint.as_ref());
+
+    hash(&data).to_bytes()
+}
+
+fn hash_state_upgrade(fields: &BridgeStateV2Fields) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&fields.large_withdrawal_threshold.to_le_bytes());
+    for discount in &fields.tier_discounts {
+        data.extend_from_slice(&discount.to_le_bytes());
+    }
+    data.push(fields.whitelist_enabled as u8);
+    data.extend_from_slice(&fields.rotation_delay.to_le_bytes());
+
+    hash(&data).to_bytes()
+}
+
+fn hash_emergency_drain(destination: &[u8; 32], amount: u64) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(destination);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    hash(&data).to_bytes()
+}
+
+/// Hash of a whitelist change; `None` removes the address. Every field is tagged so an
+/// approval can never hash the same as a removal or another approval.
+fn hash_whitelist_update(user: &Pubkey, approval: Option<(Option<u64>, i64)>) -> [u8; 32] {
+    use solana_program::hash::hash;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(user.as_ref());
+    match approval {
+        Some((daily_limit_override, kyc_expiry)) => {
+            data.push(1);
+            match daily_limit_override {
+                Some(limit) => {
+                    data.push(1);
+                    data.extend_from_slice(&limit.to_le_bytes());
+                }
+                None => data.push(0),
+            }
+            data.extend_from_slice(&kyc_expiry.to_le_bytes());
+        }
+        None => data.push(0),
+    }
+
+    hash(&data).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_height_advances() {
+        assert_eq!(check_block_height(101, 100, 6).unwrap(), 101);
+        assert_eq!(check_block_height(100, 100, 6).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_block_height_within_reorg_window() {
+        // Out-of-order heights inside the window are accepted without rewinding
+        assert_eq!(check_block_height(98, 100, 6).unwrap(), 100);
+        assert_eq!(check_block_height(94, 100, 6).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_block_height_outside_reorg_window() {
+        assert!(check_block_height(93, 100, 6).is_err());
+        assert!(check_block_height(0, 100, 6).is_err());
+    }
+
+    #[test]
+    fn test_block_height_near_genesis() {
+        assert_eq!(check_block_height(0, 3, 6).unwrap(), 3);
+        assert_eq!(check_block_height(0, 0, 6).unwrap(), 0);
+    }
+
+    fn v1_state() -> BridgeStateV1 {
+        BridgeStateV1 {
+            authority: Pubkey::new_unique(),
+            validators: vec![Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()],
+            threshold: 2,
+            fee_rate: 10,
+            daily_limit: 1_000_000,
+            emergency_delay: 3600,
+            is_paused: false,
+            nonce: 42,
+            total_locked: 500_000,
+            total_fees_collected: 500,
+            last_reset_timestamp: 1_700_000_000,
+            daily_volume: 25_000,
+            pause_timestamp: None,
+        }
+    }
+
+    /// A v1 account as `initialize_bridge` allocated it, zero-padded to `V1_SPACE`
+    fn v1_account_data(v1: &BridgeStateV1) -> Vec<u8> {
+        let mut data = BridgeState::DISCRIMINATOR.to_vec();
+        v1.serialize(&mut data).unwrap();
+        assert!(data.len() <= BridgeState::V1_SPACE);
+        data.resize(BridgeState::V1_SPACE, 0);
+        data
+    }
+
+    #[test]
+    fn test_migrate_v1_to_v2() {
+        let v1 = v1_state();
+        let migrated = migrate_bridge_state_data(&v1_account_data(&v1), 1_700_000_500).unwrap();
+
+        assert_eq!(migrated.version, BridgeState::V2);
+        assert_eq!(migrated.authority, v1.authority);
+        assert_eq!(migrated.validators, v1.validators);
+        assert_eq!(migrated.nonce, 42);
+        assert_eq!(migrated.total_locked, 500_000);
+        assert_eq!(migrated.last_processed_block_height, 0);
+        assert_eq!(migrated.reorg_depth, BridgeState::DEFAULT_REORG_DEPTH);
+        assert_eq!(migrated.migrated_at, 1_700_000_500);
+        assert_eq!(migrated.governance_nonce, 0);
+        assert_eq!(migrated.large_withdrawal_threshold, 0);
+        assert_eq!(migrated.tier_discounts, BridgeState::DEFAULT_TIER_DISCOUNTS);
+        assert_eq!(migrated.current_eon, 0);
+        assert_eq!(migrated.reserved, [0; BridgeState::RESERVED_SPACE]);
+        assert!(check_bridge_version(&migrated).is_ok());
+
+        // The migrated account round-trips through the v2 account type and fits in SPACE
+        let mut data = vec![0u8; BridgeState::SPACE];
+        migrated.try_serialize(&mut &mut data[..]).unwrap();
+        let decoded = BridgeState::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(decoded.version, BridgeState::V2);
+        assert_eq!(decoded.migrated_at, 1_700_000_500);
+        assert_eq!(decoded.daily_volume, 25_000);
+    }
+
+    fn v2_account_data(state: &BridgeState) -> Vec<u8> {
+        let mut data = vec![0u8; BridgeState::SPACE];
+        state.try_serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_migrate_rejects_already_migrated_account() {
+        let migrated = BridgeState::from_v1(v1_state(), 0);
+
+        assert_eq!(
+            migrate_bridge_state_data(&v2_account_data(&migrated), 0).unwrap_err(),
+            BridgeError::IncompatibleBridgeVersion.into()
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_foreign_account() {
+        let mut data = v1_account_data(&v1_state());
+        data[0] ^= 0xff;
+
+        assert!(migrate_bridge_state_data(&data, 0).is_err());
+    }
+
+    #[test]
+    fn test_v1_account_fails_version_check() {
+        let state = BridgeState {
+            version: BridgeState::V1,
+            ..BridgeState::from_v1(v1_state(), 0)
+        };
+
+        assert!(check_bridge_version(&state).is_err());
+    }
+
+    #[test]
+    fn test_v1_account_does_not_decode_as_current_layout() {
+        assert!(BridgeState::try_deserialize(&mut &v1_account_data(&v1_state())[..]).is_err());
+    }
+
+    fn validator_keypair(seed: u8) -> ed25519_dalek::Keypair {
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        ed25519_dalek::Keypair { secret, public }
+    }
+
+    fn validator_pubkey(keypair: &ed25519_dalek::Keypair) -> Pubkey {
+        Pubkey::new_from_array(keypair.public.to_bytes())
+    }
+
+    /// Signs `message` and returns the relayer-supplied signature together with what the
+    /// Ed25519 program instruction for it would have verified
+    fn sign(keypair: &ed25519_dalek::Keypair, message: &[u8]) -> (ValidatorSignature, Vec<VerifiedSignature>) {
+        let instruction = solana_sdk::ed25519_instruction::new_ed25519_instruction(keypair, message);
+        let verified = parse_ed25519_instruction(&instruction.data).unwrap();
+        let signature = ValidatorSignature {
+            validator: validator_pubkey(keypair),
+            signature: verified[0].signature,
+        };
+        (signature, verified)
+    }
+
+    fn sign_all(keypairs: &[ed25519_dalek::Keypair], message: &[u8]) -> (Vec<ValidatorSignature>, Vec<VerifiedSignature>) {
+        let mut signatures = Vec::new();
+        let mut verified = Vec::new();
+        for keypair in keypairs {
+            let (signature, checked) = sign(keypair, message);
+            signatures.push(signature);
+            verified.extend(checked);
+        }
+        (signatures, verified)
+    }
+
+    fn validator_set() -> (Vec<ed25519_dalek::Keypair>, Vec<Pubkey>) {
+        let keypairs: Vec<_> = (1..=3).map(validator_keypair).collect();
+        let validators = keypairs.iter().map(validator_pubkey).collect();
+        (keypairs, validators)
+    }
+
+    const TX_HASH: [u8; 32] = [7; 32];
+    const RECIPIENT: Pubkey = Pubkey::new_from_array([5; 32]);
+
+    #[test]
+    fn test_parse_ed25519_instruction() {
+        let keypair = validator_keypair(1);
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (_, verified) = sign(&keypair, &message);
+
+        assert_eq!(verified.len(), 1);
+        assert_eq!(verified[0].pubkey, validator_pubkey(&keypair));
+        assert_eq!(verified[0].message, message);
+    }
+
+    #[test]
+    fn test_parse_signatures_sharing_one_message() {
+        use ed25519_dalek::Signer;
+
+        // Same layout the client builds: offsets, then (pubkey, signature) pairs, then the message
+        let keypairs: Vec<_> = (1..=3).map(validator_keypair).collect();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let header = ED25519_OFFSETS_START + ED25519_OFFSETS_SIZE * keypairs.len();
+        let message_offset = header + keypairs.len() * 96;
+
+        let mut data = vec![0u8; message_offset + message.len()];
+        data[0] = keypairs.len() as u8;
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let public_key_offset = header + i * 96;
+            let signature_offset = public_key_offset + 32;
+            let at = ED25519_OFFSETS_START + i * ED25519_OFFSETS_SIZE;
+            for (field, value) in [
+                signature_offset, u16::MAX as usize, public_key_offset, u16::MAX as usize,
+                message_offset, message.len(), u16::MAX as usize,
+            ].into_iter().enumerate() {
+                data[at + field * 2..at + field * 2 + 2].copy_from_slice(&(value as u16).to_le_bytes());
+            }
+            data[public_key_offset..signature_offset].copy_from_slice(&keypair.public.to_bytes());
+            data[signature_offset..signature_offset + 64].copy_from_slice(&keypair.sign(&message).to_bytes());
+        }
+        data[message_offset..].copy_from_slice(&message);
+
+        let verified = parse_ed25519_instruction(&data).unwrap();
+        assert_eq!(verified.len(), 3);
+        assert!(verified.iter().all(|v| v.message == message));
+        assert_eq!(verified[2].pubkey, validator_pubkey(&keypairs[2]));
+    }
+
+    #[test]
+    fn test_parse_rejects_data_from_other_instructions() {
+        let keypair = validator_keypair(1);
+        let mut data = solana_sdk::ed25519_instruction::new_ed25519_instruction(&keypair, b"message").data;
+        // Point the message at instruction 0 instead of the Ed25519 instruction itself
+        data[ED25519_OFFSETS_START + 12..ED25519_OFFSETS_START + 14].copy_from_slice(&0u16.to_le_bytes());
+
+        assert_eq!(parse_ed25519_instruction(&data).unwrap_err(), BridgeError::InvalidSignature.into());
+        assert!(parse_ed25519_instruction(&[1]).is_err());
+        assert!(parse_ed25519_instruction(&[1, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_valid_deposit_signatures() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (signatures, verified) = sign_all(&keypairs[..2], &message);
+
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_signature_rejected() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (mut signatures, verified) = sign_all(&keypairs[..2], &message);
+        signatures[1].signature[0] ^= 0x01;
+
+        assert_eq!(
+            verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).unwrap_err(),
+            BridgeError::InvalidSignature.into()
+        );
+    }
+
+    #[test]
+    fn test_tampered_deposit_fields_rejected() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (signatures, verified) = sign_all(&keypairs[..2], &message);
+
+        // Signatures over one deposit cannot mint a different amount, hash or height, or to
+        // another recipient
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000_000, 50, None).is_err());
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &Pubkey::new_unique(), 1_000, 50, None).is_err());
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &[8; 32], &RECIPIENT, 1_000, 50, None).is_err());
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 51, None).is_err());
+    }
+
+    #[test]
+    fn test_wrong_validator_keys_not_counted() {
+        let (_, validators) = validator_set();
+        let outsiders: Vec<_> = (10..=11).map(validator_keypair).collect();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+
+        let (signatures, verified) = sign_all(&outsiders, &message);
+        assert_eq!(
+            verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).unwrap_err(),
+            BridgeError::InsufficientSignatures.into()
+        );
+
+        // A validator's key with an outsider's signature is not accepted either
+        let (mut signatures, verified) = sign_all(&[validator_keypair(1), validator_keypair(10)], &message);
+        signatures[1].validator = validators[1];
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_validator_rejected() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (signature, verified) = sign(&keypairs[0], &message);
+
+        assert_eq!(
+            verify_validator_signatures(&[signature.clone(), signature.clone()], &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None)
+                .unwrap_err(),
+            BridgeError::DuplicateValidator.into()
+        );
+
+        // Repeating a validator alongside enough distinct signers is still rejected
+        let (mut signatures, verified) = sign_all(&keypairs, &message);
+        signatures.push(signature);
+        assert_eq!(
+            verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).unwrap_err(),
+            BridgeError::DuplicateValidator.into()
+        );
+    }
+
+    #[test]
+    fn test_security_validator_signature_over_other_message_rejected() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let other = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000_000, 50, None);
+
+        // A listed validator's genuine signature, checked by the Ed25519 program, but over another deposit
+        let (mut signatures, mut verified) = sign_all(&keypairs[..2], &message);
+        let (mismatched, mismatched_verified) = sign(&keypairs[2], &other);
+        signatures.push(mismatched);
+        verified.extend(mismatched_verified);
+
+        // It is neither counted towards the threshold nor skipped: the whole instruction fails
+        for threshold in [2, 3] {
+            assert_eq!(
+                verify_validator_signatures(&signatures, &validators, threshold, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None)
+                    .unwrap_err(),
+                BridgeError::InvalidSignature.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_only_unique_valid_signers_returned() {
+        let (keypairs, validators) = validator_set();
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (mut signatures, mut verified) = sign_all(&keypairs[..2], &message);
+        let (outsider, outsider_verified) = sign(&validator_keypair(10), &message);
+        signatures.push(outsider);
+        verified.extend(outsider_verified);
+
+        let signers = verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).unwrap();
+        assert_eq!(signers, validators[..2].to_vec());
+    }
+
+    fn rate_limit(window_start: i64, submissions: u32) -> ValidatorRateLimit {
+        ValidatorRateLimit { validator: Pubkey::new_unique(), window_start, submissions }
+    }
+
+    #[test]
+    fn test_validator_rate_limit_counts_submissions() {
+        let mut limit = rate_limit(1_000, 0);
+        limit.record_submission(1_500).unwrap();
+        limit.record_submission(1_600).unwrap();
+
+        assert_eq!(limit.submissions, 2);
+        assert_eq!(limit.window_start, 1_000);
+    }
+
+    #[test]
+    fn test_validator_rate_limit_exceeded() {
+        let mut limit = rate_limit(1_000, ValidatorRateLimit::MAX_DAILY_SUBMISSIONS - 1);
+        limit.record_submission(2_000).unwrap();
+
+        assert_eq!(limit.record_submission(2_001).unwrap_err(), BridgeError::ValidatorRateLimitExceeded.into());
+        assert_eq!(limit.submissions, ValidatorRateLimit::MAX_DAILY_SUBMISSIONS);
+    }
+
+    #[test]
+    fn test_validator_rate_limit_resets_daily() {
+        let mut limit = rate_limit(1_000, ValidatorRateLimit::MAX_DAILY_SUBMISSIONS);
+        assert!(limit.record_submission(1_000 + ValidatorRateLimit::WINDOW_SECONDS - 1).is_err());
+
+        limit.record_submission(1_000 + ValidatorRateLimit::WINDOW_SECONDS).unwrap();
+        assert_eq!(limit.submissions, 1);
+        assert_eq!(limit.window_start, 1_000 + ValidatorRateLimit::WINDOW_SECONDS);
+    }
+
+    #[test]
+    fn test_validator_rate_limit_window_overflow_rejected() {
+        let mut limit = rate_limit(i64::MAX, 0);
+        assert_eq!(limit.record_submission(i64::MAX).unwrap_err(), BridgeError::ArithmeticOverflow.into());
+    }
+
+    #[test]
+    fn test_deposit_message_domain_separated() {
+        let message = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, None);
+
+        assert!(message.starts_with(crate::ID.as_ref()));
+        assert_ne!(message, create_deposit_message(&Pubkey::new_unique(), &TX_HASH, &RECIPIENT, 1_000, 50, None));
+
+        // Signatures for another deployment of the program do not verify here
+        let (keypairs, validators) = validator_set();
+        let foreign = create_deposit_message(&Pubkey::new_unique(), &TX_HASH, &RECIPIENT, 1_000, 50, None);
+        let (signatures, verified) = sign_all(&keypairs[..2], &foreign);
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).is_err());
+    }
+
+    fn bridge_state() -> BridgeState {
+        BridgeState {
+            daily_limit: u64::MAX,
+            daily_volume: 0,
+            ..BridgeState::from_v1(v1_state(), 0)
+        }
+    }
+
+    fn accounting(bridge: &BridgeState) -> (u64, u64, u64, u64) {
+        (bridge.nonce, bridge.total_locked, bridge.total_fees_collected, bridge.daily_volume)
+    }
+
+    #[test]
+    fn test_apply_deposit() {
+        let mut bridge = bridge_state();
+        apply_deposit(&mut bridge, 1_000, 10).unwrap();
+
+        assert_eq!(accounting(&bridge), (43, 501_000, 510, 1_000));
+    }
+
+    #[test]
+    fn test_deposit_overflow_leaves_state_untouched() {
+        let mut bridge = bridge_state();
+        bridge.total_locked = u64::MAX - 10;
+        let before = accounting(&bridge);
+
+        let amount = 11;
+        let fee = calculate_fee(amount, bridge.fee_rate, 0).unwrap();
+        assert!(check_daily_limit(&bridge, amount).is_ok());
+        assert_eq!(apply_deposit(&mut bridge, amount, fee).unwrap_err(), BridgeError::ArithmeticOverflow.into());
+        assert_eq!(accounting(&bridge), before);
+
+        // Exactly reaching u64::MAX is still accepted
+        apply_deposit(&mut bridge, 10, 0).unwrap();
+        assert_eq!(bridge.total_locked, u64::MAX);
+    }
+
+    #[test]
+    fn test_deposit_overflow_in_later_field_leaves_state_untouched() {
+        // Overflow in the last computed field must not leave the earlier ones written
+        let mut bridge = bridge_state();
+        bridge.daily_volume = u64::MAX - 1;
+        let before = accounting(&bridge);
+
+        assert!(apply_deposit(&mut bridge, 2, 0).is_err());
+        assert_eq!(accounting(&bridge), before);
+    }
+
+    #[test]
+    fn test_daily_limit_overflow_is_an_error() {
+        let mut bridge = bridge_state();
+        bridge.daily_volume = u64::MAX - 5;
+
+        assert_eq!(check_daily_limit(&bridge, 6).unwrap_err(), BridgeError::ArithmeticOverflow.into());
+        assert!(check_daily_limit(&bridge, 5).is_ok());
+
+        bridge.daily_limit = 100;
+        bridge.daily_volume = 90;
+        assert_eq!(check_daily_limit(&bridge, 11).unwrap_err(), BridgeError::DailyLimitExceeded.into());
+    }
+
+    #[test]
+    fn test_withdrawal_underflow_leaves_state_untouched() {
+        let mut bridge = bridge_state();
+        bridge.total_locked = 100;
+        let before = accounting(&bridge);
+
+        assert_eq!(
+            apply_withdrawal(&mut bridge, 1_000, 10, 990).unwrap_err(),
+            BridgeError::ArithmeticOverflow.into()
+        );
+        assert_eq!(accounting(&bridge), before);
+
+        apply_withdrawal(&mut bridge, 100, 1, 99).unwrap();
+        assert_eq!(accounting(&bridge), (43, 1, 501, 100));
+    }
+
+    #[test]
+    fn test_fee_tier_boundaries() {
+        let tiers = [FeeTier::Bronze, FeeTier::Silver, FeeTier::Gold, FeeTier::Platinum];
+        for pair in tiers.windows(2) {
+            let threshold = FeeTier::VOLUME_THRESHOLDS[pair[1] as usize];
+            assert_eq!(FeeTier::for_volume(threshold - 1), pair[0]);
+            assert_eq!(FeeTier::for_volume(threshold), pair[1]);
+        }
+        assert_eq!(FeeTier::for_volume(0), FeeTier::Bronze);
+        assert_eq!(FeeTier::for_volume(u64::MAX), FeeTier::Platinum);
+    }
+
+    fn user_volume(lifetime_volume: u64) -> UserVolumeAccount {
+        UserVolumeAccount {
+            user: Pubkey::new_unique(),
+            lifetime_volume,
+            tier: FeeTier::for_volume(lifetime_volume),
+        }
+    }
+
+    #[test]
+    fn test_record_volume_reports_upgrade_at_each_threshold() {
+        let tiers = [FeeTier::Bronze, FeeTier::Silver, FeeTier::Gold, FeeTier::Platinum];
+        for pair in tiers.windows(2) {
+            let threshold = FeeTier::VOLUME_THRESHOLDS[pair[1] as usize];
+            let mut account = user_volume(threshold - 2);
+
+            assert_eq!(account.record_volume(1).unwrap(), None);
+            assert_eq!(account.tier, pair[0]);
+            assert_eq!(account.record_volume(1).unwrap(), Some(pair[0]));
+            assert_eq!(account.tier, pair[1]);
+            assert_eq!(account.lifetime_volume, threshold);
+        }
+    }
+
+    #[test]
+    fn test_record_volume_can_skip_tiers() {
+        let mut account = user_volume(0);
+
+        assert_eq!(account.record_volume(FeeTier::VOLUME_THRESHOLDS[3]).unwrap(), Some(FeeTier::Bronze));
+        assert_eq!(account.tier, FeeTier::Platinum);
+        assert_eq!(account.record_volume(1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_record_volume_overflow_is_an_error() {
+        let mut account = user_volume(u64::MAX);
+
+        assert_eq!(account.record_volume(1).unwrap_err(), BridgeError::ArithmeticOverflow.into());
+        assert_eq!(account.lifetime_volume, u64::MAX);
+    }
+
+    #[test]
+    fn test_user_volume_fits_space() {
+        let mut data = vec![0u8; UserVolumeAccount::SPACE];
+        user_volume(u64::MAX).try_serialize(&mut &mut data[..]).unwrap();
+    }
+
+    #[test]
+    fn test_discounted_fee_per_tier() {
+        let bridge = bridge_state();
+        let amount = 1_000_000 * NOCK;
+        let fees: Vec<u64> = [FeeTier::Bronze, FeeTier::Silver, FeeTier::Gold, FeeTier::Platinum]
+            .into_iter()
+            .map(|tier| calculate_fee(amount, bridge.fee_rate, bridge.tier_discount(tier)).unwrap())
+            .collect();
+
+        // 0.1% fee on 1M NOCK, less 0%, 10%, 25% and 50%
+        assert_eq!(fees, vec![1_000 * NOCK, 900 * NOCK, 750 * NOCK, 500 * NOCK]);
+    }
+
+    #[test]
+    fn test_discounted_fee_rounds_down_once() {
+        // 10 bps of 1_900 is 1.9; 40% off gives 1.14, where rounding the base fee first would give 0
+        assert_eq!(calculate_fee(1_900, 10, 4000).unwrap(), 1);
+        assert_eq!(calculate_fee(15_000, 10, 5000).unwrap(), 7);
+        assert_eq!(calculate_fee(15_000, 10, 10000).unwrap(), 0);
+        assert_eq!(calculate_fee(u64::MAX, 10000, 0).unwrap(), u64::MAX);
+        assert!(calculate_fee(1_000, 10, 10001).is_err());
+    }
+
+    #[test]
+    fn test_config_hash_matches_solana_hash() {
+        // Clients compute this hash off-chain with nock-bridge-math, so it must stay SHA-256
+        use solana_program::hash::hash;
+
+        let mut data = vec![0];
+        data.extend_from_slice(&25u16.to_le_bytes());
+        data.extend_from_slice(&[2, 3, 4]);
+        for discount in [0u16, 500, 1000, 2000] {
+            data.extend_from_slice(&discount.to_le_bytes());
+        }
+        data.extend_from_slice(&[5, 1]);
+
+        let config_hash = hash_config_update(
+            &Some(25),
+            &None,
+            &Some(3),
+            &None,
+            &Some([0, 500, 1000, 2000]),
+            &Some(true),
+            &None,
+        );
+        assert_eq!(config_hash, hash(&data).to_bytes());
+    }
+
+    fn withdrawal_request(created_at: i64) -> WithdrawalRequest {
+        WithdrawalRequest {
+            id: 7,
+            requester: Pubkey::new_unique(),
+            amount: 5_000_000,
+            nock_address: [3; 32],
+            created_at,
+            executed: false,
+        }
+    }
+
+    #[test]
+    fn test_large_withdrawal_threshold() {
+        assert!(!is_large_withdrawal(1_000_000, 0));
+        assert!(!is_large_withdrawal(1_000, 1_000));
+        assert!(is_large_withdrawal(1_001, 1_000));
+    }
+
+    #[test]
+    fn test_withdrawal_executes_only_after_timelock() {
+        let request = withdrawal_request(1_000);
+
+        assert_eq!(
+            check_withdrawal_executable(&request, 1_000 + 3599, 3600).unwrap_err(),
+            BridgeError::WithdrawalTimelockActive.into()
+        );
+        assert!(check_withdrawal_executable(&request, 1_000 + 3600, 3600).is_ok());
+    }
+
+    #[test]
+    fn test_withdrawal_cancellable_within_timelock_or_when_stranded() {
+        let request = withdrawal_request(1_000);
+        let daily_limit = request.amount;
+
+        assert!(check_withdrawal_cancellable(&request, 1_000, 3600, daily_limit).is_ok());
+        assert!(check_withdrawal_cancellable(&request, 1_000 + 3599, 3600, daily_limit).is_ok());
+        assert_eq!(
+            check_withdrawal_cancellable(&request, 1_000 + 3600, 3600, daily_limit).unwrap_err(),
+            BridgeError::WithdrawalTimelockExpired.into()
+        );
+        // A daily limit lowered below the amount leaves the request unexecutable
+        assert!(check_withdrawal_cancellable(&request, 1_000 + 3600, 3600, daily_limit - 1).is_ok());
+    }
+
+    #[test]
+    fn test_executed_withdrawal_cannot_run_or_cancel_again() {
+        let mut request = withdrawal_request(1_000);
+        request.executed = true;
+
+        assert_eq!(
+            check_withdrawal_executable(&request, 10_000, 3600).unwrap_err(),
+            BridgeError::WithdrawalAlreadyExecuted.into()
+        );
+        assert_eq!(
+            check_withdrawal_cancellable(&request, 1_000, 3600, 0).unwrap_err(),
+            BridgeError::WithdrawalAlreadyExecuted.into()
+        );
+    }
+
+    #[test]
+    fn test_withdrawal_request_fits_space() {
+        let mut data = vec![0u8; WithdrawalRequest::SPACE];
+        withdrawal_request(i64::MAX).try_serialize(&mut &mut data[..]).unwrap();
+        assert!(withdrawal_unlocks_at(&withdrawal_request(i64::MAX), 1).is_err());
+    }
+
+    fn empty_processed_deposit() -> ProcessedDeposit {
+        ProcessedDeposit { nock_tx_hash: [0; 32], block_height: 0 }
+    }
+
+    fn processed_deposit_address(nock_tx_hash: &[u8; 32]) -> Pubkey {
+        Pubkey::find_program_address(&[b"deposit", nock_tx_hash.as_ref()], &crate::ID).0
+    }
+
+    #[test]
+    fn test_deposit_processed_once() {
+        let mut record = empty_processed_deposit();
+
+        record_processed_deposit(&mut record, TX_HASH, 50).unwrap();
+        assert_eq!(record.nock_tx_hash, TX_HASH);
+        assert_eq!(record.block_height, 50);
+
+        assert_eq!(
+            record_processed_deposit(&mut record, TX_HASH, 50).unwrap_err(),
+            BridgeError::DuplicateDeposit.into()
+        );
+        // A replay cannot rewrite the recorded height either
+        assert!(record_processed_deposit(&mut record, TX_HASH, 51).is_err());
+        assert_eq!(record.block_height, 50);
+    }
+
+    #[test]
+    fn test_deposits_differing_by_one_byte_are_independent() {
+        let mut other_hash = TX_HASH;
+        other_hash[31] ^= 0x01;
+
+        assert_ne!(processed_deposit_address(&TX_HASH), processed_deposit_address(&other_hash));
+
+        let mut first = empty_processed_deposit();
+        let mut second = empty_processed_deposit();
+        record_processed_deposit(&mut first, TX_HASH, 50).unwrap();
+        record_processed_deposit(&mut second, other_hash, 50).unwrap();
+    }
+
+    #[test]
+    fn test_zero_tx_hash_never_processed() {
+        assert_eq!(
+            record_processed_deposit(&mut empty_processed_deposit(), [0; 32], 50).unwrap_err(),
+            BridgeError::DuplicateDeposit.into()
+        );
+    }
+
+    #[test]
+    fn test_replayed_governance_nonce_rejected() {
+        let (keypairs, validators) = validator_set();
+        let paused_at_nonce_0 = create_governance_message(EMERGENCY_PAUSE_DOMAIN, 0, &[]);
+        let (signatures, verified) = sign_all(&keypairs[..2], &paused_at_nonce_0);
+
+        assert!(verify_emergency_signatures(&signatures, &validators, 2, &verified, &paused_at_nonce_0).is_ok());
+
+        // Once the nonce has advanced the same signatures no longer authorize a pause
+        let current = create_governance_message(EMERGENCY_PAUSE_DOMAIN, 1, &[]);
+        assert_eq!(
+            verify_emergency_signatures(&signatures, &validators, 2, &verified, &current).unwrap_err(),
+            BridgeError::InvalidSignature.into()
+        );
+
+        // Nor can they be reused for a different action at the same nonce
+        let unpause = create_governance_message(UNPAUSE_DOMAIN, 0, &[]);
+        assert!(verify_emergency_signatures(&signatures, &validators, 2, &verified, &unpause).is_err());
+    }
+
+    fn upgrade_fields() -> BridgeStateV2Fields {
+        BridgeStateV2Fields {
+            large_withdrawal_threshold: 50_000_000,
+            tier_discounts: [0, 500, 1500, 3000],
+            whitelist_enabled: true,
+            rotation_delay: MIN_ROTATION_DELAY as u32 * 2,
+        }
+    }
+
+    #[test]
+    fn test_upgrade_applies_approved_fields() {
+        let v1 = v1_state();
+        let upgraded = upgrade_bridge_state_data(&v1_account_data(&v1), &upgrade_fields(), 1_700_000_500).unwrap();
+
+        assert_eq!(upgraded.version, BridgeState::CURRENT_VERSION);
+        assert_eq!(upgraded.validators, v1.validators);
+        assert_eq!(upgraded.total_locked, 500_000);
+        assert_eq!(upgraded.migrated_at, 1_700_000_500);
+        assert_eq!(upgraded.large_withdrawal_threshold, 50_000_000);
+        assert_eq!(upgraded.tier_discounts, [0, 500, 1500, 3000]);
+        assert!(upgraded.whitelist_enabled);
+        assert_eq!(upgraded.effective_rotation_delay(), MIN_ROTATION_DELAY * 2);
+
+        // Default fields give exactly what the authority-only migration produces
+        let defaulted = upgrade_bridge_state_data(&v1_account_data(&v1), &BridgeStateV2Fields::default(), 0).unwrap();
+        let migrated = migrate_bridge_state_data(&v1_account_data(&v1), 0).unwrap();
+        assert_eq!(defaulted.try_to_vec().unwrap(), migrated.try_to_vec().unwrap());
+    }
+
+    #[test]
+    fn test_upgrade_rejects_out_of_range_fields() {
+        let data = v1_account_data(&v1_state());
+
+        let mut discounts = upgrade_fields();
+        discounts.tier_discounts[3] = 10_001;
+        assert_eq!(
+            upgrade_bridge_state_data(&data, &discounts, 0).unwrap_err(),
+            BridgeError::InvalidTierDiscount.into()
+        );
+
+        let mut delay = upgrade_fields();
+        delay.rotation_delay = 3600;
+        assert_eq!(
+            upgrade_bridge_state_data(&data, &delay, 0).unwrap_err(),
+            BridgeError::InvalidRotationDelay.into()
+        );
+
+        let upgraded = upgrade_bridge_state_data(&data, &upgrade_fields(), 0).unwrap();
+        assert!(upgrade_bridge_state_data(&v2_account_data(&upgraded), &upgrade_fields(), 0).is_err());
+    }
+
+    #[test]
+    fn test_upgrade_signatures_bound_to_fields() {
+        let (keypairs, validators) = validator_set();
+        let approved = create_governance_message(STATE_UPGRADE_DOMAIN, 0, &hash_state_upgrade(&upgrade_fields()));
+        let (signatures, verified) = sign_all(&keypairs[..2], &approved);
+
+        assert!(verify_emergency_signatures(&signatures, &validators, 2, &verified, &approved).is_ok());
+
+        let mut tampered = upgrade_fields();
+        tampered.whitelist_enabled = false;
+        let message = create_governance_message(STATE_UPGRADE_DOMAIN, 0, &hash_state_upgrade(&tampered));
+        assert_eq!(
+            verify_emergency_signatures(&signatures, &validators, 2, &verified, &message).unwrap_err(),
+            BridgeError::InvalidSignature.into()
+        );
+    }
+
+    fn eon_bridge(current_eon: u64, eon_start_block_height: u64) -> BridgeState {
+        BridgeState {
+            current_eon,
+            eon_start_block_height,
+            ..bridge_state()
+        }
+    }
+
+    #[test]
+    fn test_eon_transition_advances_one_eon() {
+        let bridge = eon_bridge(3, 1_000);
+
+        assert!(check_eon_transition(&bridge, 4, 2_000).is_ok());
+        assert_eq!(check_eon_transition(&bridge, 3, 2_000).unwrap_err(), BridgeError::InvalidEonTransition.into());
+        assert_eq!(check_eon_transition(&bridge, 5, 2_000).unwrap_err(), BridgeError::InvalidEonTransition.into());
+        assert_eq!(check_eon_transition(&bridge, 4, 1_000).unwrap_err(), BridgeError::InvalidEonTransition.into());
+        assert!(check_eon_transition(&eon_bridge(u64::MAX, 1_000), 0, 2_000).is_err());
+    }
+
+    #[test]
+    fn test_previous_eon_deposit_requires_tag() {
+        // Eon 4 began at block 1_000
+        assert_eq!(check_deposit_eon(999, None, 4, 1_000).unwrap_err(), BridgeError::MissingEonTag.into());
+        assert!(check_deposit_eon(999, Some(3), 4, 1_000).is_ok());
+        assert_eq!(check_deposit_eon(999, Some(4), 4, 1_000).unwrap_err(), BridgeError::EonTagMismatch.into());
+        assert!(check_deposit_eon(999, Some(2), 4, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_current_eon_deposit_tag_optional() {
+        assert!(check_deposit_eon(1_000, None, 4, 1_000).is_ok());
+        assert!(check_deposit_eon(1_500, Some(4), 4, 1_000).is_ok());
+        assert_eq!(check_deposit_eon(1_500, Some(3), 4, 1_000).unwrap_err(), BridgeError::EonTagMismatch.into());
+
+        // Before any transition every height belongs to eon 0
+        assert!(check_deposit_eon(0, None, 0, 0).is_ok());
+    }
+
+    #[test]
+    fn test_eon_tag_is_signed() {
+        let (keypairs, validators) = validator_set();
+        let tagged = create_deposit_message(&crate::ID, &TX_HASH, &RECIPIENT, 1_000, 50, Some(3));
+        let (signatures, verified) = sign_all(&keypairs[..2], &tagged);
+
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, Some(3)).is_ok());
+        // Untagged or retagged submissions of the same attestation do not verify
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, None).is_err());
+        assert!(verify_validator_signatures(&signatures, &validators, 2, &verified, &TX_HASH, &RECIPIENT, 1_000, 50, Some(4)).is_err());
+    }
+
+    #[test]
+    fn test_eon_rotation_waits_out_rotation_delay() {
+        let mut bridge = eon_bridge(0, 0);
+        let original = bridge.validators.clone();
+        let rotation: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut pending = PendingRotation {
+            new_validators: Vec::new(),
+            new_threshold: 0,
+            proposed_at: 0,
+            executable_at: 0,
+        };
+
+        advance_eon(&mut bridge, 1, 1_000);
+        let executable_at =
+            stage_validator_rotation(&mut pending, &bridge, rotation.clone(), bridge.threshold, 1_700_000_000).unwrap();
+
+        assert_eq!(bridge.current_eon, 1);
+        assert_eq!(bridge.eon_start_block_height, 1_000);
+        assert_eq!(bridge.validators, original);
+        assert_eq!(pending.new_validators, rotation);
+        assert_eq!(pending.new_threshold, bridge.threshold);
+        assert_eq!(executable_at, 1_700_000_000 + MIN_ROTATION_DELAY);
+        assert_eq!(
+            check_rotation_executable(&pending, executable_at - 1).unwrap_err(),
+            BridgeError::RotationDelayNotMet.into()
+        );
+        assert!(check_rotation_executable(&pending, executable_at).is_ok());
+    }
+
+    #[test]
+    fn test_rotated_set_must_reach_current_threshold() {
+        let mut bridge = eon_bridge(0, 0);
+        bridge.validators = (0..15).map(|_| Pubkey::new_unique()).collect();
+        bridge.threshold = 8;
+
+        let too_small: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        assert_eq!(
+            check_validator_set(&too_small, bridge.threshold).unwrap_err(),
+            BridgeError::InvalidThreshold.into()
+        );
+        let duplicated = vec![Pubkey::new_unique(); 8];
+        assert_eq!(
+            check_validator_set(&duplicated, bridge.threshold).unwrap_err(),
+            BridgeError::DuplicateValidator.into()
+        );
+        let replacement: Vec<_> = (0..15).map(|_| Pubkey::new_unique()).collect();
+        assert!(check_validator_set(&replacement, bridge.threshold).is_ok());
+    }
+
+    #[test]
+    fn test_eon_transition_signatures_bound_to_contents() {
+        let rotation = Some(vec![Pubkey::new_unique(); 3]);
+        let transition = hash_eon_transition(1, 1_000, &rotation);
+
+        assert_ne!(transition, hash_eon_transition(1, 1_000, &None));
+        assert_ne!(transition, hash_eon_transition(1, 1_001, &rotation));
+        assert_ne!(transition, hash_eon_transition(2, 1_000, &rotation));
+    }
+
+    #[test]
+    fn test_max_validators_fit_space() {
+        let mut bridge = eon_bridge(1, 1_000);
+        bridge.validators = vec![Pubkey::new_unique(); 15];
+
+        let mut data = vec![0u8; BridgeState::SPACE];
+        bridge.try_serialize(&mut &mut data[..]).unwrap();
+        let decoded = BridgeState::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(decoded.validators.len(), 15);
+    }
+
+    fn empty_pool() -> LiquidityPool {
+        LiquidityPool {
+            nock_mint: Pubkey::new_unique(),
+            lp_mint: Pubkey::new_unique(),
+            nock_vault: Pubkey::new_unique(),
+            wnock_vault: Pubkey::new_unique(),
+            nock_reserve: 0,
+            wnock_reserve: 0,
+            lp_supply: 0,
+            bump: 255,
+        }
+    }
+
+    fn seeded_pool(nock: u64, wnock: u64) -> LiquidityPool {
+        let mut pool = empty_pool();
+        let deposit = quote_add_liquidity(nock, wnock, &pool).unwrap();
+        pool.apply_deposit(&deposit).unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_first_deposit_locks_minimum_liquidity() {
+        let pool = empty_pool();
+        let deposit = quote_add_liquidity(4_000_000, 1_000_000, &pool).unwrap();
+
+        // sqrt(4e6 * 1e6) = 2e6
+        assert_eq!(deposit.lp_tokens, 2_000_000 - MINIMUM_LIQUIDITY);
+        assert_eq!(deposit.lp_supply_increase, 2_000_000);
+        assert_eq!((deposit.nock, deposit.wnock), (4_000_000, 1_000_000));
+
+        assert_eq!(
+            quote_add_liquidity(1_000, 1_000, &pool).unwrap_err(),
+            BridgeError::InsufficientLiquidityMinted.into()
+        );
+        assert_eq!(quote_add_liquidity(0, 1_000_000, &pool).unwrap_err(), BridgeError::InvalidAmount.into());
+    }
+
+    #[test]
+    fn test_later_deposit_takes_current_ratio() {
+        let pool = seeded_pool(4_000_000, 1_000_000);
+
+        // Excess wNOCK is left with the provider
+        let deposit = quote_add_liquidity(400_000, 500_000, &pool).unwrap();
+        assert_eq!((deposit.nock, deposit.wnock, deposit.lp_tokens), (400_000, 100_000, 200_000));
+
+        // Excess NOCK is left with the provider, and the NOCK side rounds up
+        let deposit = quote_add_liquidity(4_000_000, 333, &pool).unwrap();
+        assert_eq!((deposit.nock, deposit.wnock, deposit.lp_tokens), (1_332, 333, 666));
+    }
 
-impl BridgeState {
-    pub const SPACE: usize = 8 + // discriminator
-        32 + // authority
-        4 + (32 * 15) + // validators (max 15)
-        1 + // threshold
-        2 + // fee_rate
-        8 + // daily_limit
-        8 + // emergency_delay
-        1 + // is_paused
-        8 + // nonce
-        8 + // total_locked
-        8 + // total_fees_collected
-        8 + // last_reset_timestamp
-        8 + // daily_volume
-        1 + 8; // pause_timestamp (Option<i64>)
-}
+    #[test]
+    fn test_dust_deposit_rejected() {
+        let pool = seeded_pool(4_000_000, 1_000_000);
+        assert_eq!(
+            quote_add_liquidity(1, 1, &pool).unwrap_err(),
+            BridgeError::InsufficientLiquidityMinted.into()
+        );
+    }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct ValidatorSignature {
-    pub validator: Pubkey,
-    pub signature: [u8; 64],
-}
+    #[test]
+    fn test_lp_round_trip_without_swaps() {
+        let mut pool = seeded_pool(4_000_000, 1_000_000);
+        let deposit = quote_add_liquidity(400_000, 100_000, &pool).unwrap();
+        pool.apply_deposit(&deposit).unwrap();
 
-// Events
-#[event]
-pub struct DepositEvent {
-    pub user: Pubkey,
-    pub amount: u64,
-    pub fee: u64,
-    pub net_amount: u64,
-    pub nock_tx_hash: [u8; 32],
-    pub block_height: u64,
-    pub nonce: u64,
-    pub timestamp: i64,
-}
+        let (nock, wnock) = quote_remove_liquidity(deposit.lp_tokens, &pool).unwrap();
+        assert_eq!((nock, wnock), (400_000, 100_000));
 
-#[event]
-pub struct WithdrawEvent {
-    pub user: Pubkey,
-    pub amount: u64,
-    pub fee: u64,
-    pub net_amount: u64,
-    pub nock_address: [u8; 32],
-    pub nonce: u64,
-    pub timestamp: i64,
-}
+        pool.apply_withdrawal(deposit.lp_tokens, nock, wnock).unwrap();
+        assert_eq!((pool.nock_reserve, pool.wnock_reserve, pool.lp_supply), (4_000_000, 1_000_000, 2_000_000));
+    }
 
-#[event]
-pub struct EmergencyPauseEvent {
-    pub timestamp: i64,
-    pub triggered_by: Pubkey,
-}
+    #[test]
+    fn test_round_trip_never_returns_more_than_deposited() {
+        let mut pool = seeded_pool(3_000_007, 1_000_003);
+        let deposit = quote_add_liquidity(123_457, 99_999, &pool).unwrap();
+        pool.apply_deposit(&deposit).unwrap();
 
-#[event]
-pub struct UnpauseEvent {
-    pub timestamp: i64,
-    pub authorized_by: Pubkey,
-}
+        let (nock, wnock) = quote_remove_liquidity(deposit.lp_tokens, &pool).unwrap();
+        assert!(nock <= deposit.nock);
+        assert!(wnock <= deposit.wnock);
+    }
 
-#[event]
-pub struct ConfigUpdateEvent {
-    pub timestamp: i64,
-    pub updated_by: Pubkey,
-}
+    #[test]
+    fn test_swap_output_and_fee() {
+        // 1_000 in after a 0.3% fee is 997: 997 * 1_000_000 / (1_000_000 + 997) = 996,
+        // where a fee-free swap would return 999
+        assert_eq!(quote_swap(1_000, 0, 1_000_000, 1_000_000).unwrap(), 996);
+        assert_eq!(quote_swap(1_000_000, 0, 1_000_000, 1_000_000).unwrap(), 499_248);
+    }
 
-// Error codes
-#[error_code]
-pub enum BridgeError {
-    #[msg("Invalid validator count")]
-    InvalidValidatorCount,
-    #[msg("Invalid threshold")]
-    InvalidThreshold,
-    #[msg("Invalid fee rate")]
-    InvalidFeeRate,
-    #[msg("Invalid daily limit")]
-    InvalidDailyLimit,
-    #[msg("Invalid emergency delay")]
-    InvalidEmergencyDelay,
-    #[msg("Invalid decimals")]
-    InvalidDecimals,
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Bridge is paused")]
-    BridgePaused,
-    #[msg("Bridge is not paused")]
-    NotPaused,
-    #[msg("Bridge is already paused")]
-    AlreadyPaused,
-    #[msg("Daily limit exceeded")]
-    DailyLimitExceeded,
-    #[msg("Insufficient validator signatures")]
-    InsufficientSignatures,
-    #[msg("Invalid validator signature")]
-    InvalidSignature,
-    #[msg("Emergency delay not met")]
-    EmergencyDelayNotMet,
-    #[msg("Arithmetic overflow")]
-    ArithmeticOverflow,
-}
+    #[test]
+    fn test_swap_slippage_protection() {
+        let quoted = quote_swap(10_000, 0, 4_000_000, 1_000_000).unwrap();
 
-// Helper functions
-fn calculate_fee(amount: u64, fee_rate: u16) -> Result<u64> {
-    (amount as u128)
-        .checked_mul(fee_rate as u128)
-        .and_then(|x| x.checked_div(10000))
-        .and_then(|x| u64::try_from(x).ok())
-        .ok_or(BridgeError::ArithmeticOverflow.into())
-}
+        assert_eq!(quote_swap(10_000, quoted, 4_000_000, 1_000_000).unwrap(), quoted);
+        assert_eq!(
+            quote_swap(10_000, quoted + 1, 4_000_000, 1_000_000).unwrap_err(),
+            BridgeError::SlippageExceeded.into()
+        );
+        // An input too small to buy anything is rejected rather than swapped for nothing
+        assert_eq!(quote_swap(1, 0, 1_000_000, 1_000_000).unwrap_err(), BridgeError::SlippageExceeded.into());
+    }
 
-fn reset_daily_volume_if_needed(bridge: &mut BridgeState) -> Result<()> {
-    let current_time = Clock::get()?.unix_timestamp;
-    let seconds_in_day = 86400;
-    
-    if current_time >= bridge.last_reset_timestamp + seconds_in_day {
-        bridge.daily_volume = 0;
-        bridge.last_reset_timestamp = current_time;
+    #[test]
+    fn test_zero_liquidity_pool() {
+        let pool = empty_pool();
+
+        assert_eq!(quote_swap(1_000, 0, 0, 0).unwrap_err(), BridgeError::ZeroLiquidity.into());
+        assert_eq!(quote_swap(1_000, 0, 1_000, 0).unwrap_err(), BridgeError::ZeroLiquidity.into());
+        assert_eq!(quote_remove_liquidity(1, &pool).unwrap_err(), BridgeError::ZeroLiquidity.into());
+
+        // A pool drained of one side cannot take proportional deposits
+        let drained = LiquidityPool { nock_reserve: 0, ..seeded_pool(4_000_000, 1_000_000) };
+        assert_eq!(quote_add_liquidity(1_000, 1_000, &drained).unwrap_err(), BridgeError::ZeroLiquidity.into());
     }
-    
-    Ok(())
-}
 
-fn verify_validator_signatures(
-    signatures: &[ValidatorSignature],
-    validators: &[Pubkey],
-    threshold: u8,
-    tx_hash: &[u8; 32],
-    amount: u64,
-    block_height: u64,
-) -> Result<()> {
-    require!(signatures.len() >= threshold as usize, BridgeError::InsufficientSignatures);
+    #[test]
+    fn test_swap_fees_accrue_to_liquidity_providers() {
+        let mut pool = seeded_pool(4_000_000, 1_000_000);
+        let lp_tokens = pool.lp_supply - MINIMUM_LIQUIDITY;
+        let (nock_before, wnock_before) = quote_remove_liquidity(lp_tokens, &pool).unwrap();
 
-    let message = create_deposit_message(tx_hash, amount, block_height);
-    let mut valid_signatures = 0;
+        // Round trips through the pool leave their fees behind
+        for _ in 0..10 {
+            let (reserve_in, reserve_out) = pool.reserves(SwapDirection::NockToWnock);
+            let wnock_out = quote_swap(100_000, 0, reserve_in, reserve_out).unwrap();
+            pool.apply_swap(SwapDirection::NockToWnock, 100_000, wnock_out).unwrap();
 
-    for sig in signatures {
-        if validators.contains(&sig.validator) {
-            // In production, verify ed25519 signature here
-            // For now, we'll assume signature verification
-            valid_signatures += 1;
+            let (reserve_in, reserve_out) = pool.reserves(SwapDirection::WnockToNock);
+            let nock_out = quote_swap(wnock_out, 0, reserve_in, reserve_out).unwrap();
+            pool.apply_swap(SwapDirection::WnockToNock, wnock_out, nock_out).unwrap();
+            assert!(nock_out < 100_000);
         }
+
+        // No LP tokens were minted for the fees; the same tokens now redeem for more
+        assert_eq!(pool.lp_supply, 2_000_000);
+        let (nock_after, wnock_after) = quote_remove_liquidity(lp_tokens, &pool).unwrap();
+        assert!(nock_after > nock_before);
+        assert_eq!(wnock_after, wnock_before);
     }
 
-    require!(valid_signatures >= threshold, BridgeError::InsufficientSignatures);
-    Ok(())
-}
+    #[test]
+    fn test_swap_cannot_drain_output_reserve() {
+        let quoted = quote_swap(u64::MAX / 10_000, 0, 1_000_000, 1_000_000).unwrap();
+        assert!(quoted < 1_000_000);
+    }
 
-fn verify_emergency_signatures(
-    signatures: &[ValidatorSignature],
-    validators: &[Pubkey],
-    threshold: u8,
-    message: &[u8],
-) -> Result<()> {
-    require!(signatures.len() >= threshold as usize, BridgeError::InsufficientSignatures);
+    #[test]
+    fn test_liquidity_pool_fits_space() {
+        let mut data = vec![0u8; LiquidityPool::SPACE];
+        seeded_pool(u64::MAX, 1).try_serialize(&mut &mut data[..]).unwrap();
+    }
+
+    /// Stands in for the DEX program behind the CPI: decodes the swap instruction the
+    /// bridge builds and moves balances like a constant-product pool with its own fee
+    struct MockDex {
+        reserve_in: u64,
+        reserve_out: u64,
+        fee_bps: u64,
+        /// A misbehaving DEX pulls this much extra from the user and ignores `min_amount_out`
+        overdraw: u64,
+    }
+
+    impl MockDex {
+        fn honest(fee_bps: u64) -> Self {
+            Self { reserve_in: 10_000_000, reserve_out: 5_000_000, fee_bps, overdraw: 0 }
+        }
+
+        /// Applies the swap to (user NOCK, bridge vault) balances
+        fn invoke(&mut self, instruction: &Instruction, user_nock: &mut u64, vault: &mut u64) -> Result<()> {
+            use solana_program::hash::hash;
 
-    let mut valid_signatures = 0;
+            require!(instruction.data[..8] == hash(b"global:swap").to_bytes()[..8], ErrorCode::InstructionFallbackNotFound);
+            let amount_in = u64::from_le_bytes(instruction.data[8..16].try_into().unwrap());
+            let min_amount_out = u64::from_le_bytes(instruction.data[16..24].try_into().unwrap());
 
-    for sig in signatures {
-        if validators.contains(&sig.validator) {
-            // In production, verify ed25519 signature here
-            valid_signatures += 1;
+            let in_after_fee = amount_in as u128 * (10_000 - self.fee_bps) as u128 / 10_000;
+            let amount_out = (in_after_fee * self.reserve_out as u128 / (self.reserve_in as u128 + in_after_fee)) as u64;
+            if self.overdraw == 0 {
+                require!(amount_out >= min_amount_out, BridgeError::SlippageExceeded);
+            }
+
+            *user_nock = checked_sub(*user_nock, amount_in + self.overdraw)?;
+            *vault = checked_add(*vault, amount_out)?;
+            self.reserve_in += amount_in;
+            self.reserve_out -= amount_out;
+            Ok(())
         }
     }
 
-    require!(valid_signatures >= threshold, BridgeError::InsufficientSignatures);
-    Ok(())
-}
+    /// Runs the NOCK and swap legs of `swap_and_bridge` against `dex`, returning
+    /// `(fee, nock_swapped, amount_out)`
+    fn swap_and_bridge_legs(dex: &mut MockDex, fee_rate: u16, amount: u64, min_out: u64) -> Result<(u64, u64, u64)> {
+        let fee = calculate_fee(amount, fee_rate, 0)?;
+        let net_amount = checked_sub(amount, fee)?;
+        let (mut user_nock, mut vault) = (amount * 2 - fee, 1_000);
+        let (nock_before, vault_before) = (user_nock, vault);
 
-fn create_deposit_message(tx_hash: &[u8; 32], amount: u64, block_height: u64) -> Vec<u8> {
-    let mut message = Vec::new();
-    message.extend_from_slice(tx_hash);
-    message.extend_from_slice(&amount.to_le_bytes());
-    message.extend_from_slice(&block_height.to_le_bytes());
-    message
-}
+        let instruction = dex_swap_instruction(Pubkey::new_unique(), Vec::new(), net_amount, min_out);
+        dex.invoke(&instruction, &mut user_nock, &mut vault)?;
 
-fn hash_config_update(
-    fee_rate: &Option<u16>,
-    daily_limit: &Option<u64>,
-    validators: &Option<Vec<Pubkey>>,
-    threshold: &Option<u8>,
-) -> [u8; 32] {
-    use solana_program::hash::{hash, Hash};
-    
-    let mut data = Vec::new();
-    
-    if let Some(rate) = fee_rate {
-        data.extend_from_slice(&rate.to_le_bytes());
+        let (nock_swapped, amount_out) = settle_dex_swap(nock_before, user_nock, vault_before, vault, net_amount, min_out)?;
+        Ok((fee, nock_swapped, amount_out))
+    }
+
+    #[test]
+    fn test_dex_swap_instruction_layout() {
+        let dex = Pubkey::new_unique();
+        let user = AccountMeta::new(Pubkey::new_unique(), true);
+        let instruction = dex_swap_instruction(dex, vec![user.clone()], 1_000, 990);
+
+        assert_eq!(instruction.program_id, dex);
+        assert_eq!(instruction.accounts, vec![user]);
+        assert_eq!(instruction.data.len(), 24);
+        assert_eq!(&instruction.data[8..16], &1_000u64.to_le_bytes());
+        assert_eq!(&instruction.data[16..], &990u64.to_le_bytes());
+    }
+
+    #[test]
+    fn test_multi_hop_fee_on_nock_leg_and_dex_fee_passed_through() {
+        let mut free_dex = MockDex::honest(0);
+        let mut dex = MockDex::honest(30);
+
+        // 10 bps bridge fee on 1_000_000 NOCK, swapped at 2:1
+        let (fee, nock_swapped, fee_free_out) = swap_and_bridge_legs(&mut free_dex, 10, 1_000_000, 0).unwrap();
+        assert_eq!(fee, 1_000);
+        assert_eq!(nock_swapped, 999_000);
+
+        let (_, _, amount_out) = swap_and_bridge_legs(&mut dex, 10, 1_000_000, 0).unwrap();
+        assert!(amount_out < fee_free_out);
+    }
+
+    #[test]
+    fn test_multi_hop_slippage_protection() {
+        let (_, _, quoted) = swap_and_bridge_legs(&mut MockDex::honest(30), 10, 1_000_000, 0).unwrap();
+
+        assert!(swap_and_bridge_legs(&mut MockDex::honest(30), 10, 1_000_000, quoted).is_ok());
+        assert_eq!(
+            swap_and_bridge_legs(&mut MockDex::honest(30), 10, 1_000_000, quoted + 1).unwrap_err(),
+            BridgeError::SlippageExceeded.into()
+        );
+    }
+
+    #[test]
+    fn test_security_multi_hop_misbehaving_dex_rejected() {
+        // Pulling more NOCK than approved fails, even though the output met min_out
+        let mut greedy = MockDex { overdraw: 1, ..MockDex::honest(30) };
+        assert_eq!(
+            swap_and_bridge_legs(&mut greedy, 10, 1_000_000, 0).unwrap_err(),
+            BridgeError::DexOverspent.into()
+        );
+
+        // An output short of min_out is caught by the bridge when the DEX skips its own check
+        assert_eq!(settle_dex_swap(1_000, 0, 50, 149, 1_000, 100).unwrap_err(), BridgeError::SlippageExceeded.into());
+        assert_eq!(settle_dex_swap(1_000, 0, 50, 50, 1_000, 0).unwrap_err(), BridgeError::SlippageExceeded.into());
+        assert_eq!(settle_dex_swap(1_000, 0, 50, 150, 1_000, 100).unwrap(), (1_000, 100));
+    }
+
+    #[test]
+    fn test_multi_hop_accounting() {
+        let mut bridge = bridge_state();
+        let (nonce, total_locked, fees, volume) = accounting(&bridge);
+
+        apply_multi_hop(&mut bridge, 1_000_000, 1_000).unwrap();
+
+        assert_eq!(accounting(&bridge), (nonce + 1, total_locked, fees + 1_000, volume + 1_000_000));
+        assert!(check_daily_limit(&BridgeState { daily_limit: 1_000_000, ..bridge_state() }, 1_000_001).is_err());
+    }
+
+    fn fee_recipients(shares: &[u16]) -> Vec<FeeRecipient> {
+        shares
+            .iter()
+            .map(|&share_bps| FeeRecipient { recipient: Pubkey::new_unique(), share_bps })
+            .collect()
+    }
+
+    #[test]
+    fn test_fee_recipient_shares_must_sum_to_whole() {
+        assert!(check_fee_recipients(&fee_recipients(&[10000])).is_ok());
+        assert!(check_fee_recipients(&fee_recipients(&[5000, 3000, 2000])).is_ok());
+
+        for shares in [vec![], vec![5000, 4999], vec![5000, 5001], vec![10000, 0], vec![1000; 11]] {
+            assert_eq!(
+                check_fee_recipients(&fee_recipients(&shares)).unwrap_err(),
+                BridgeError::InvalidFeeRecipients.into(),
+                "{:?}",
+                shares
+            );
+        }
+
+        let mut duplicated = fee_recipients(&[5000, 5000]);
+        duplicated[1].recipient = duplicated[0].recipient;
+        assert!(check_fee_recipients(&duplicated).is_err());
+    }
+
+    #[test]
+    fn test_split_fees_rounds_down() {
+        let recipients = fee_recipients(&[5000, 3000, 2000]);
+        assert_eq!(split_fees(1_000_000, &recipients).unwrap(), vec![500_000, 300_000, 200_000]);
+
+        // 1/3 each of 100 leaves 1 behind for the next distribution
+        let thirds = fee_recipients(&[3334, 3333, 3333]);
+        let amounts = split_fees(100, &thirds).unwrap();
+        assert_eq!(amounts, vec![33, 33, 33]);
+
+        assert_eq!(split_fees(u64::MAX, &fee_recipients(&[10000])).unwrap(), vec![u64::MAX]);
+    }
+
+    #[test]
+    fn test_fee_distribution_due_weekly_or_above_threshold() {
+        let now = 1_700_000_000;
+        let mut distribution = FeeDistribution {
+            last_distribution_at: 0,
+            auto_distribute_threshold: 0,
+            total_distributed: 0,
+            bump: 255,
+        };
+        // The first distribution is always due
+        assert!(distribution.is_due(0, now));
+
+        distribution.last_distribution_at = now - FeeDistribution::INTERVAL_SECONDS + 1;
+        assert!(!distribution.is_due(u64::MAX, now));
+        assert!(distribution.is_due(0, now + 1));
+
+        distribution.auto_distribute_threshold = 1_000;
+        assert!(!distribution.is_due(999, now));
+        assert!(distribution.is_due(1_000, now));
+    }
+
+    #[test]
+    fn test_fee_distribution_signatures_bound_to_shares() {
+        let (keypairs, validators) = validator_set();
+        let recipients = fee_recipients(&[6000, 4000]);
+        let approved = create_governance_message(FEE_DISTRIBUTION_DOMAIN, 3, &hash_fee_distribution(&recipients));
+        let (signatures, verified) = sign_all(&keypairs[..2], &approved);
+
+        assert!(verify_emergency_signatures(&signatures, &validators, 2, &verified, &approved).is_ok());
+
+        let mut reshuffled = recipients.clone();
+        reshuffled[0].share_bps = 4000;
+        reshuffled[1].share_bps = 6000;
+        let message = create_governance_message(FEE_DISTRIBUTION_DOMAIN, 3, &hash_fee_distribution(&reshuffled));
+        assert!(verify_emergency_signatures(&signatures, &validators, 2, &verified, &message).is_err());
+    }
+
+    #[test]
+    fn test_fee_distribution_fits_space() {
+        let distribution = FeeDistribution {
+            last_distribution_at: i64::MAX,
+            auto_distribute_threshold: u64::MAX,
+            total_distributed: u64::MAX,
+            bump: 255,
+        };
+        let mut data = vec![0u8; FeeDistribution::SPACE];
+        distribution.try_serialize(&mut &mut data[..]).unwrap();
+    }
+
+    #[test]
+    fn test_multi_hop_route_fits_space() {
+        let route = MultiHopRoute {
+            dex_program: Pubkey::new_unique(),
+            nock_mint: Pubkey::new_unique(),
+            bump: 255,
+        };
+        let mut data = vec![0u8; MultiHopRoute::SPACE];
+        route.try_serialize(&mut &mut data[..]).unwrap();
     }
-    if let Some(limit) = daily_limit {
-        data.extend_from_slice(&limit.to_le_bytes());
+
+    #[test]
+    fn test_security_malicious_token_program_rejected() {
+        // deposit_nock mints through `token_program`; a look-alike program that would call back
+        // into the bridge during the CPI is refused when the accounts are deserialized
+        let loader = anchor_lang::solana_program::bpf_loader::ID;
+        let malicious = Pubkey::new_unique();
+        let (mut lamports, mut data) = (1, Vec::new());
+        let info = AccountInfo::new(&malicious, false, false, &mut lamports, &mut data, &loader, true, 0);
+        assert_eq!(Program::<Token>::try_from(&info).unwrap_err(), ErrorCode::InvalidProgramId.into());
+
+        let token_program = Token::id();
+        let (mut lamports, mut data) = (1, Vec::new());
+        let info = AccountInfo::new(&token_program, false, false, &mut lamports, &mut data, &loader, true, 0);
+        assert!(Program::<Token>::try_from(&info).is_ok());
     }
-    if let Some(vals) = validators {
-        for val in vals {
-            data.extend_from_slice(val.as_ref());
+
+    fn whitelist_entry(user: Pubkey, daily_limit_override: Option<u64>) -> WhitelistEntry {
+        WhitelistEntry {
+            user,
+            approved: true,
+            daily_limit_override,
+            kyc_expiry: 2_000_000,
+            daily_volume: 0,
+            last_reset_timestamp: 0,
         }
     }
-    if let Some(thresh) = threshold {
-        data.push(*thresh);
+
+    #[test]
+    fn test_whitelist_mode_requires_approved_entry() {
+        let user = Pubkey::new_unique();
+        let mut bridge = bridge_state();
+        assert!(check_whitelist(&bridge, None, user, 1_000, 1_000_000).is_ok());
+
+        bridge.whitelist_enabled = true;
+        assert_eq!(check_whitelist(&bridge, None, user, 1_000, 1_000_000).unwrap_err(), BridgeError::NotWhitelisted.into());
+
+        let mut entry = whitelist_entry(user, None);
+        assert!(check_whitelist(&bridge, Some(&mut entry), user, 1_000, 1_000_000).is_ok());
+        assert_eq!(entry.daily_volume, 1_000);
+
+        let mut other = whitelist_entry(Pubkey::new_unique(), None);
+        assert_eq!(check_whitelist(&bridge, Some(&mut other), user, 1_000, 1_000_000).unwrap_err(), BridgeError::NotWhitelisted.into());
+
+        entry.approved = false;
+        assert_eq!(check_whitelist(&bridge, Some(&mut entry), user, 1_000, 1_000_000).unwrap_err(), BridgeError::NotWhitelisted.into());
+
+        entry.approved = true;
+        assert_eq!(check_whitelist(&bridge, Some(&mut entry), user, 1_000, 2_000_000).unwrap_err(), BridgeError::KycExpired.into());
     }
-    
-    hash(&data).to_bytes()
-}
\ No newline at end of file
+
+    #[test]
+    fn test_whitelist_daily_limit_override() {
+        let user = Pubkey::new_unique();
+        let mut entry = whitelist_entry(user, Some(5_000));
+
+        entry.record_volume(4_000, u64::MAX, 1_000_000).unwrap();
+        assert_eq!(entry.record_volume(1_001, u64::MAX, 1_000_100).unwrap_err(), BridgeError::AddressDailyLimitExceeded.into());
+        assert_eq!(entry.daily_volume, 4_000);
+
+        // A new day starts from zero
+        entry.record_volume(5_000, u64::MAX, 1_000_000 + WhitelistEntry::WINDOW_SECONDS).unwrap();
+        assert_eq!(entry.daily_volume, 5_000);
+
+        // Without an override the bridge-wide limit applies per address
+        let mut entry = whitelist_entry(user, None);
+        assert_eq!(entry.record_volume(2_001, 2_000, 1_000_000).unwrap_err(), BridgeError::AddressDailyLimitExceeded.into());
+    }
+
+    #[test]
+    fn test_whitelist_entry_fits_space() {
+        let mut data = vec![0u8; WhitelistEntry::SPACE];
+        let entry = WhitelistEntry { daily_volume: u64::MAX, ..whitelist_entry(Pubkey::new_unique(), Some(u64::MAX)) };
+        entry.try_serialize(&mut &mut data[..]).unwrap();
+    }
+
+    #[test]
+    fn test_whitelist_update_hash_distinguishes_changes() {
+        let user = Pubkey::new_unique();
+        let hashes = [
+            hash_whitelist_update(&user, None),
+            hash_whitelist_update(&user, Some((None, 0))),
+            hash_whitelist_update(&user, Some((Some(0), 0))),
+            hash_whitelist_update(&Pubkey::new_unique(), None),
+        ];
+        let distinct: BTreeSet<_> = hashes.iter().collect();
+        assert_eq!(distinct.len(), hashes.len());
+    }
+
+    #[test]
+    fn test_drain_needs_three_emergency_delays_of_pause() {
+        let mut bridge = bridge_state();
+        bridge.emergency_delay = 3_600;
+        assert_eq!(check_drain_delay(&bridge, 1_000_000).unwrap_err(), BridgeError::NotPaused.into());
+
+        bridge.is_paused = true;
+        bridge.pause_timestamp = Some(1_000_000);
+        assert_eq!(check_drain_delay(&bridge, 1_000_000 + 3 * 3_600 - 1).unwrap_err(), BridgeError::EmergencyDelayNotMet.into());
+        assert!(check_drain_delay(&bridge, 1_000_000 + 3 * 3_600).is_ok());
+    }
+
+    #[test]
+    fn test_drain_threshold_is_two_thirds_rounded_up() {
+        assert_eq!(drain_threshold(3, 2), 2);
+        assert_eq!(drain_threshold(9, 5), 6);
+        assert_eq!(drain_threshold(10, 5), 7);
+        assert_eq!(drain_threshold(15, 8), 10);
+        assert_eq!(drain_threshold(9, 8), 8);
+    }
+
+    #[test]
+    fn test_drain_signatures_bound_to_drain_nonce() {
+        let destination = [7u8; 32];
+        let drain_hash = hash_emergency_drain(&destination, 1_000);
+        let first = create_governance_message(EMERGENCY_DRAIN_DOMAIN, 0, &drain_hash);
+
+        assert_ne!(first, create_governance_message(EMERGENCY_DRAIN_DOMAIN, 1, &drain_hash));
+        assert_ne!(first, create_governance_message(EMERGENCY_DRAIN_DOMAIN, 0, &hash_emergency_drain(&destination, 1_001)));
+        assert_ne!(first, create_governance_message(EMERGENCY_PAUSE_DOMAIN, 0, &drain_hash));
+    }
+
+    #[test]
+    fn test_rotation_delay_has_a_floor() {
+        let mut bridge = bridge_state();
+        assert_eq!(bridge.effective_rotation_delay(), 48 * 3600);
+
+        bridge.rotation_delay = 72 * 3600;
+        assert_eq!(bridge.effective_rotation_delay(), 72 * 3600);
+    }
+
+    #[test]
+    fn test_proposed_validator_set_is_checked() {
+        let validators: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        assert!(check_validator_set(&validators, 3).is_ok());
+        assert_eq!(check_validator_set(&validators, 2).unwrap_err(), BridgeError::InvalidThreshold.into());
+        assert_eq!(check_validator_set(&validators, 6).unwrap_err(), BridgeError::InvalidThreshold.into());
+        assert_eq!(check_validator_set(&validators[..2], 1).unwrap_err(), BridgeError::InvalidValidatorCount.into());
+
+        let repeated = vec![validators[0], validators[0], validators[1]];
+        assert_eq!(check_validator_set(&repeated, 2).unwrap_err(), BridgeError::DuplicateValidator.into());
+    }
+
+    #[test]
+    fn test_config_threshold_bounded_by_validator_count() {
+        // update_bridge_config keeps the validator set, so only the threshold is checked
+        assert!(check_threshold(5, 3).is_ok());
+        assert!(check_threshold(5, 5).is_ok());
+        assert_eq!(check_threshold(5, 2).unwrap_err(), BridgeError::InvalidThreshold.into());
+        assert_eq!(check_threshold(5, 6).unwrap_err(), BridgeError::InvalidThreshold.into());
+    }
+
+    #[test]
+    fn test_rotation_stages_sign_distinct_messages() {
+        let validators: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let rotation_hash = hash_validator_rotation(&validators, 2);
+        let messages: BTreeSet<Vec<u8>> = [ROTATION_PROPOSAL_DOMAIN, ROTATION_EXECUTION_DOMAIN, ROTATION_CANCELLATION_DOMAIN]
+            .into_iter()
+            .map(|domain| create_governance_message(domain, 0, &rotation_hash))
+            .collect();
+        assert_eq!(messages.len(), 3);
+        assert_ne!(rotation_hash, hash_validator_rotation(&validators, 3));
+    }
+
+    #[test]
+    fn test_pending_rotation_fits_max_validators() {
+        let pending = PendingRotation {
+            new_validators: vec![Pubkey::new_unique(); 15],
+            new_threshold: 8,
+            proposed_at: 0,
+            executable_at: MIN_ROTATION_DELAY,
+        };
+        let mut data = vec![0u8; PendingRotation::SPACE];
+        pending.try_serialize(&mut &mut data[..]).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 // TypeScript client SDK for NOCK bridge integration
 
 import {
+  AccountMeta,
   Connection,
   PublicKey,
   Transaction,
@@ -8,6 +9,8 @@ import {
   Keypair,
   SystemProgram,
   SYSVAR_RENT_PUBKEY,
+  SYSVAR_INSTRUCTIONS_PUBKEY,
+  Ed25519Program,
   sendAndConfirmTransaction,
   ConfirmOptions,
 } from '@solana/web3.js';
@@ -18,6 +21,7 @@ import {
   createAssociatedTokenAccountInstruction,
 } from '@solana/spl-token';
 import { Program, Provider, BN, web3 } from '@coral-xyz/anchor';
+import { createHash } from 'crypto';
 import { NockBridge } from '../../target/types/nock_bridge';
 import { IDL } from '../../target/types/nock_bridge';
 
@@ -32,6 +36,8 @@ export interface DepositParams {
   amount: BN;
   nockTxHash: number[];
   blockHeight: BN;
+  // Required for blocks mined before the current eon began
+  eon?: BN;
   signatures: ValidatorSignature[];
   user: Keypair;
 }
@@ -42,6 +48,24 @@ export interface WithdrawParams {
   user: Keypair;
 }
 
+export interface SwapAndBridgeParams {
+  amount: BN;
+  intermediateMint: PublicKey;
+  // Least intermediate output accepted from the DEX
+  minOut: BN;
+  nockAddress: number[];
+  // Accounts the configured DEX's swap instruction needs, in its order; output goes to
+  // multiHopVaultAddress(intermediateMint)
+  dexAccounts: AccountMeta[];
+  user: Keypair;
+}
+
+export interface FeeRecipient {
+  recipient: PublicKey;
+  // Basis points; all recipients' shares sum to 10000
+  shareBps: number;
+}
+
 export interface ValidatorSignature {
   validator: PublicKey;
   signature: number[];
@@ -61,6 +85,35 @@ export interface BridgeState {
   lastResetTimestamp: BN;
   dailyVolume: BN;
   pauseTimestamp?: BN;
+  governanceNonce: BN;
+  largeWithdrawalThreshold: BN;
+  tierDiscounts: number[];
+  currentEon: BN;
+  eonStartBlockHeight: BN;
+}
+
+export type FeeTier =
+  | { bronze: {} }
+  | { silver: {} }
+  | { gold: {} }
+  | { platinum: {} };
+
+export interface UserVolume {
+  user: PublicKey;
+  lifetimeVolume: BN;
+  tier: FeeTier;
+}
+
+export type SwapDirection = { nockToWnock: {} } | { wnockToNock: {} };
+
+export interface LiquidityPoolState {
+  nockMint: PublicKey;
+  lpMint: PublicKey;
+  nockVault: PublicKey;
+  wnockVault: PublicKey;
+  nockReserve: BN;
+  wnockReserve: BN;
+  lpSupply: BN;
 }
 
 export interface PriceInfo {
@@ -81,6 +134,8 @@ export class NockBridgeClient {
   public wnockMint: PublicKey;
   public priceOracle: PublicKey;
   public liquidityPool: PublicKey;
+  public multiHopRoute: PublicKey;
+  public feeDistribution: PublicKey;
 
   constructor(config: BridgeConfig) {
     this.connection = config.connection;
@@ -117,6 +172,16 @@ export class NockBridgeClient {
       [Buffer.from('liquidity_pool')],
       config.programId
     );
+
+    [this.multiHopRoute] = PublicKey.findProgramAddressSync(
+      [Buffer.from('multi_hop_route')],
+      config.programId
+    );
+
+    [this.feeDistribution] = PublicKey.findProgramAddressSync(
+      [Buffer.from('fee_distribution')],
+      config.programId
+    );
   }
 
   /**
@@ -206,15 +271,30 @@ export class NockBridgeClient {
       );
     }
 
+    // Validator signatures are checked by the Ed25519 program ahead of the deposit
+    const message = createDepositMessage(
+      this.program.programId,
+      params.nockTxHash,
+      params.user.publicKey,
+      params.amount,
+      params.blockHeight,
+      params.eon
+    );
+    instructions.push(createEd25519Instruction(params.signatures, message));
+
     const depositInstruction = await this.program.methods
       .depositNock(
         params.amount,
         params.nockTxHash,
         params.blockHeight,
+        params.eon ?? null,
         params.signatures
       )
       .accounts({
         bridgeState: this.bridgeState,
+        processedDeposit: this.processedDepositAddress(params.nockTxHash),
+        userVolume: this.userVolumeAddress(params.user.publicKey),
+        whitelistEntry: await this.transferWhitelistEntry(params.user.publicKey),
         wnockMint: this.wnockMint,
         userWnockAccount,
         feeCollector,
@@ -223,7 +303,15 @@ export class NockBridgeClient {
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: SystemProgram.programId,
         rent: SYSVAR_RENT_PUBKEY,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
       })
+      .remainingAccounts(
+        params.signatures.map((sig) => ({
+          pubkey: this.validatorRateLimitAddress(sig.validator),
+          isSigner: false,
+          isWritable: true,
+        }))
+      )
       .instruction();
 
     instructions.push(depositInstruction);
@@ -252,15 +340,27 @@ export class NockBridgeClient {
       true
     );
 
+    // Withdrawals above the threshold are queued behind the timelock
+    const bridgeState = await this.getBridgeState();
+    const threshold = bridgeState.largeWithdrawalThreshold;
+    const withdrawalRequest =
+      !threshold.isZero() && params.amount.gt(threshold)
+        ? this.withdrawalRequestAddress(bridgeState.nonce)
+        : null;
+
     const tx = await this.program.methods
       .withdrawNock(params.amount, params.nockAddress)
       .accounts({
         bridgeState: this.bridgeState,
+        withdrawalRequest,
+        userVolume: this.userVolumeAddress(params.user.publicKey),
+        whitelistEntry: bridgeState.whitelistEnabled ? this.whitelistEntryAddress(params.user.publicKey) : null,
         wnockMint: this.wnockMint,
         userWnockAccount,
         feeCollector,
         user: params.user.publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
       })
       .signers([params.user])
       .rpc(this.confirmOptions);
@@ -268,6 +368,372 @@ export class NockBridgeClient {
     return tx;
   }
 
+  /**
+   * Execute a queued large withdrawal after the timelock
+   */
+  async executeWithdrawal(id: BN, requester: Keypair): Promise<string> {
+    const feeCollector = await getAssociatedTokenAddress(
+      this.wnockMint,
+      this.bridgeState,
+      true
+    );
+
+    const tx = await this.program.methods
+      .executeWithdrawal()
+      .accounts({
+        bridgeState: this.bridgeState,
+        withdrawalRequest: this.withdrawalRequestAddress(id),
+        userVolume: this.userVolumeAddress(requester.publicKey),
+        wnockMint: this.wnockMint,
+        feeCollector,
+        requester: requester.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([requester])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Cancel a queued large withdrawal during the timelock, returning the wNOCK
+   */
+  async cancelWithdrawal(id: BN, requester: Keypair): Promise<string> {
+    const requesterWnockAccount = await getAssociatedTokenAddress(
+      this.wnockMint,
+      requester.publicKey
+    );
+
+    const tx = await this.program.methods
+      .cancelWithdrawal()
+      .accounts({
+        bridgeState: this.bridgeState,
+        withdrawalRequest: this.withdrawalRequestAddress(id),
+        wnockMint: this.wnockMint,
+        requesterWnockAccount,
+        requester: requester.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([requester])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Pending large withdrawal, keyed by the bridge nonce it was queued at
+   */
+  withdrawalRequestAddress(id: BN): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('withdrawal'), id.toArrayLike(Buffer, 'le', 8)],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Lifetime bridged volume and fee tier of a user
+   */
+  userVolumeAddress(user: PublicKey): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('user_volume'), user.toBuffer()],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Whitelist approval of a user, required for transfers while whitelist mode is on
+   */
+  whitelistEntryAddress(user: PublicKey): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('whitelist'), user.toBuffer()],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Whitelist entry to pass to a transfer: the user's entry in whitelist mode, otherwise none
+   */
+  async transferWhitelistEntry(user: PublicKey): Promise<PublicKey | null> {
+    const bridgeState = await this.getBridgeState();
+    return bridgeState.whitelistEnabled ? this.whitelistEntryAddress(user) : null;
+  }
+
+  /**
+   * Get a user's bridged volume, or null before their first transfer
+   */
+  async getUserVolume(user: PublicKey): Promise<UserVolume | null> {
+    return await this.program.account.userVolumeAccount.fetchNullable(this.userVolumeAddress(user));
+  }
+
+  /**
+   * Daily submission counter for a validator
+   */
+  validatorRateLimitAddress(validator: PublicKey): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('validator_rate_limit'), validator.toBuffer()],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Create a validator's rate limit account (requires authority); needed before
+   * deposits carrying that validator's signature
+   */
+  async initializeValidatorRateLimit(validator: PublicKey): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required to initialize validator rate limits');
+    }
+
+    // Validators only named by a proposed rotation are authorized through the proposal
+    const pendingRotation = this.pendingRotationAddress();
+    const pending = await this.program.account.pendingRotation.fetchNullable(pendingRotation);
+
+    const tx = await this.program.methods
+      .initializeValidatorRateLimit(validator)
+      .accounts({
+        bridgeState: this.bridgeState,
+        validatorRateLimit: this.validatorRateLimitAddress(validator),
+        pendingRotation: pending ? pendingRotation : null,
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * LP token mint of the NOCK/wNOCK pool
+   */
+  lpMintAddress(): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('lp_mint')],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Pool-owned token account holding the reserve of `mint`
+   */
+  poolVaultAddress(mint: PublicKey): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('pool_vault'), mint.toBuffer()],
+      this.program.programId
+    );
+    return address;
+  }
+
+  async getLiquidityPool(): Promise<LiquidityPoolState | null> {
+    return await this.program.account.liquidityPool.fetchNullable(this.liquidityPool);
+  }
+
+  /**
+   * Create the NOCK/wNOCK liquidity pool (requires authority)
+   */
+  async initializeLiquidityPool(nockMint: PublicKey): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required to initialize the liquidity pool');
+    }
+
+    const tx = await this.program.methods
+      .initializeLiquidityPool()
+      .accounts({
+        bridgeState: this.bridgeState,
+        liquidityPool: this.liquidityPool,
+        nockMint,
+        wnockMint: this.wnockMint,
+        lpMint: this.lpMintAddress(),
+        nockVault: this.poolVaultAddress(nockMint),
+        wnockVault: this.poolVaultAddress(this.wnockMint),
+        authority: this.authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Token accounts a user trades through: their NOCK, wNOCK and LP associated accounts
+   */
+  private async poolUserAccounts(nockMint: PublicKey, user: PublicKey) {
+    return {
+      userNockAccount: await getAssociatedTokenAddress(nockMint, user),
+      userWnockAccount: await getAssociatedTokenAddress(this.wnockMint, user),
+      userLpAccount: await getAssociatedTokenAddress(this.lpMintAddress(), user),
+    };
+  }
+
+  /**
+   * Deposit up to the given amounts at the pool ratio for LP tokens
+   */
+  async addLiquidity(amountNock: BN, amountWnock: BN, user: Keypair): Promise<string> {
+    const pool = await this.program.account.liquidityPool.fetch(this.liquidityPool);
+    const accounts = await this.poolUserAccounts(pool.nockMint, user.publicKey);
+
+    return await this.program.methods
+      .addLiquidity(amountNock, amountWnock)
+      .accounts({
+        bridgeState: this.bridgeState,
+        liquidityPool: this.liquidityPool,
+        lpMint: pool.lpMint,
+        nockVault: pool.nockVault,
+        wnockVault: pool.wnockVault,
+        ...accounts,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .signers([user])
+      .rpc(this.confirmOptions);
+  }
+
+  /**
+   * Burn LP tokens for a share of both reserves
+   */
+  async removeLiquidity(lpTokens: BN, user: Keypair): Promise<string> {
+    const pool = await this.program.account.liquidityPool.fetch(this.liquidityPool);
+    const accounts = await this.poolUserAccounts(pool.nockMint, user.publicKey);
+
+    return await this.program.methods
+      .removeLiquidity(lpTokens)
+      .accounts({
+        bridgeState: this.bridgeState,
+        liquidityPool: this.liquidityPool,
+        lpMint: pool.lpMint,
+        nockVault: pool.nockVault,
+        wnockVault: pool.wnockVault,
+        ...accounts,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc(this.confirmOptions);
+  }
+
+  /**
+   * Swap against the pool; fails if fewer than `minAmountOut` tokens would be received
+   */
+  async swap(amountIn: BN, minAmountOut: BN, direction: SwapDirection, user: Keypair): Promise<string> {
+    const pool = await this.program.account.liquidityPool.fetch(this.liquidityPool);
+    const { userNockAccount, userWnockAccount } = await this.poolUserAccounts(pool.nockMint, user.publicKey);
+
+    return await this.program.methods
+      .swap(amountIn, minAmountOut, direction)
+      .accounts({
+        bridgeState: this.bridgeState,
+        liquidityPool: this.liquidityPool,
+        nockVault: pool.nockVault,
+        wnockVault: pool.wnockVault,
+        userNockAccount,
+        userWnockAccount,
+        user: user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([user])
+      .rpc(this.confirmOptions);
+  }
+
+  /**
+   * Bridge vault holding an intermediate token bridged by `swapAndBridge`
+   */
+  multiHopVaultAddress(intermediateMint: PublicKey): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('multi_hop_vault'), intermediateMint.toBuffer()],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Set the DEX program multi-hop bridging swaps through (requires authority)
+   */
+  async configureMultiHopRoute(
+    dexProgram: PublicKey,
+    nockMint: PublicKey,
+    signatures: ValidatorSignature[] = []
+  ): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required to configure the multi-hop route');
+    }
+
+    const message = await this.governanceMessage(MULTI_HOP_ROUTE_DOMAIN, hashMultiHopRoute(dexProgram, nockMint));
+
+    const tx = await this.program.methods
+      .configureMultiHopRoute(dexProgram, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        multiHopRoute: this.multiHopRoute,
+        nockMint,
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Swap NOCK for an intermediate token on the configured DEX and bridge it to Nockchain
+   */
+  async swapAndBridge(params: SwapAndBridgeParams): Promise<string> {
+    const route = await this.program.account.multiHopRoute.fetch(this.multiHopRoute);
+    const bridgeState = await this.getBridgeState();
+
+    return await this.program.methods
+      .swapAndBridge(params.amount, params.intermediateMint, params.minOut, params.nockAddress)
+      .accounts({
+        bridgeState: this.bridgeState,
+        multiHopRoute: this.multiHopRoute,
+        dexProgram: route.dexProgram,
+        nockMint: route.nockMint,
+        intermediateMint: params.intermediateMint,
+        intermediateVault: this.multiHopVaultAddress(params.intermediateMint),
+        whitelistEntry: bridgeState.whitelistEnabled ? this.whitelistEntryAddress(params.user.publicKey) : null,
+        userNockAccount: await getAssociatedTokenAddress(route.nockMint, params.user.publicKey),
+        feeCollector: await getAssociatedTokenAddress(route.nockMint, this.bridgeState, true),
+        user: params.user.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .remainingAccounts(params.dexAccounts)
+      .signers([params.user])
+      .rpc(this.confirmOptions);
+  }
+
+  /**
+   * Record marking a Nockchain transaction as deposited
+   */
+  processedDepositAddress(nockTxHash: number[]): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('deposit'), Buffer.from(nockTxHash)],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Whether a Nockchain transaction has already been deposited
+   */
+  async isDepositProcessed(nockTxHash: number[]): Promise<boolean> {
+    const account = await this.connection.getAccountInfo(this.processedDepositAddress(nockTxHash));
+    return account !== null;
+  }
+
   /**
    * Get bridge state information
    */
@@ -342,12 +808,16 @@ export class NockBridgeClient {
       throw new Error('Authority keypair required for emergency pause');
     }
 
+    const message = await this.governanceMessage(EMERGENCY_PAUSE_DOMAIN);
+
     const tx = await this.program.methods
       .emergencyPause(signatures)
       .accounts({
         bridgeState: this.bridgeState,
         authority: this.authority.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
       })
+      .preInstructions([createEd25519Instruction(signatures, message)])
       .signers([this.authority])
       .rpc(this.confirmOptions);
 
@@ -362,12 +832,56 @@ export class NockBridgeClient {
       throw new Error('Authority keypair required to unpause');
     }
 
+    const message = await this.governanceMessage(UNPAUSE_DOMAIN);
+
     const tx = await this.program.methods
       .unpauseBridge(signatures)
       .accounts({
         bridgeState: this.bridgeState,
         authority: this.authority.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Burn wNOCK from the bridge reserve so validators release the NOCK behind it to a
+   * Nockchain address; only once the bridge has been paused for three emergency delays,
+   * and signed by two thirds of the validators (requires authority)
+   */
+  async emergencyDrain(
+    destination: number[],
+    amount: BN,
+    signatures: ValidatorSignature[]
+  ): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for emergency drain');
+    }
+
+    const bridgeState = await this.getBridgeState();
+    const message = createGovernanceMessage(
+      this.program.programId,
+      EMERGENCY_DRAIN_DOMAIN,
+      bridgeState.drainNonce,
+      hashEmergencyDrain(destination, amount)
+    );
+    const bridgeReserve = await getAssociatedTokenAddress(this.wnockMint, this.bridgeState, true);
+
+    const tx = await this.program.methods
+      .emergencyDrain(destination, amount, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        wnockMint: this.wnockMint,
+        bridgeReserve,
+        authority: this.authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
       })
+      .preInstructions([createEd25519Instruction(signatures, message)])
       .signers([this.authority])
       .rpc(this.confirmOptions);
 
@@ -380,32 +894,318 @@ export class NockBridgeClient {
   async updateBridgeConfig(
     newFeeRate?: number,
     newDailyLimit?: BN,
-    newValidators?: PublicKey[],
     newThreshold?: number,
-    signatures: ValidatorSignature[] = []
+    signatures: ValidatorSignature[] = [],
+    newLargeWithdrawalThreshold?: BN,
+    newTierDiscounts?: number[],
+    newWhitelistEnabled?: boolean,
+    newRotationDelay?: number
   ): Promise<string> {
     if (!this.authority) {
       throw new Error('Authority keypair required for configuration update');
     }
 
+    const message = await this.governanceMessage(
+      CONFIG_UPDATE_DOMAIN,
+      hashConfigUpdate(
+        newFeeRate,
+        newDailyLimit,
+        newThreshold,
+        newLargeWithdrawalThreshold,
+        newTierDiscounts,
+        newWhitelistEnabled,
+        newRotationDelay
+      )
+    );
+
     const tx = await this.program.methods
       .updateBridgeConfig(
         newFeeRate ?? null,
         newDailyLimit ?? null,
-        newValidators ?? null,
         newThreshold ?? null,
+        newLargeWithdrawalThreshold ?? null,
+        newTierDiscounts ?? null,
+        newWhitelistEnabled ?? null,
+        newRotationDelay ?? null,
         signatures
       )
       .accounts({
         bridgeState: this.bridgeState,
         authority: this.authority.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
       })
+      .preInstructions([createEd25519Instruction(signatures, message)])
       .signers([this.authority])
       .rpc(this.confirmOptions);
 
     return tx;
   }
 
+  /**
+   * Pending validator rotation, a single account for the whole bridge
+   */
+  pendingRotationAddress(): PublicKey {
+    const [address] = PublicKey.findProgramAddressSync(
+      [Buffer.from('pending_rotation')],
+      this.program.programId
+    );
+    return address;
+  }
+
+  /**
+   * Propose a new validator set; it can be executed once the rotation delay (at least
+   * 48 hours) has passed (requires authority)
+   */
+  async proposeValidatorRotation(
+    newValidators: PublicKey[],
+    newThreshold: number,
+    signatures: ValidatorSignature[]
+  ): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for validator rotation');
+    }
+
+    const message = await this.governanceMessage(
+      ROTATION_PROPOSAL_DOMAIN,
+      hashValidatorRotation(newValidators, newThreshold)
+    );
+
+    const tx = await this.program.methods
+      .proposeValidatorRotation(newValidators, newThreshold, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        pendingRotation: this.pendingRotationAddress(),
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Execute or cancel the pending validator rotation; either is signed by the outgoing set
+   * (requires authority)
+   */
+  async finishValidatorRotation(execute: boolean, signatures: ValidatorSignature[]): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for validator rotation');
+    }
+
+    const pendingRotation = this.pendingRotationAddress();
+    const pending = await this.program.account.pendingRotation.fetch(pendingRotation);
+    const message = await this.governanceMessage(
+      execute ? ROTATION_EXECUTION_DOMAIN : ROTATION_CANCELLATION_DOMAIN,
+      hashValidatorRotation(pending.newValidators, pending.newThreshold)
+    );
+
+    const method = execute
+      ? this.program.methods.executeValidatorRotation(signatures)
+      : this.program.methods.cancelValidatorRotation(signatures);
+
+    const tx = await method
+      .accounts({
+        bridgeState: this.bridgeState,
+        pendingRotation,
+        authority: this.authority.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Register the start of a new Nockchain eon, optionally proposing a validator set for it
+   * (requires authority). The set keeps the current threshold and can be executed or
+   * cancelled like any proposed rotation once the rotation delay has passed.
+   */
+  async registerEonTransition(
+    newEon: BN,
+    startBlockHeight: BN,
+    validatorRotation?: PublicKey[],
+    signatures: ValidatorSignature[] = []
+  ): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for eon transition');
+    }
+
+    const message = await this.governanceMessage(
+      EON_TRANSITION_DOMAIN,
+      hashEonTransition(newEon, startBlockHeight, validatorRotation)
+    );
+
+    const tx = await this.program.methods
+      .registerEonTransition(newEon, startBlockHeight, validatorRotation ?? null, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        pendingRotation: validatorRotation ? this.pendingRotationAddress() : null,
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Approve a user for whitelist mode, or update their entry (requires authority)
+   */
+  async addToWhitelist(
+    user: PublicKey,
+    kycExpiry: BN,
+    dailyLimitOverride?: BN,
+    signatures: ValidatorSignature[] = []
+  ): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for whitelist update');
+    }
+
+    const message = await this.governanceMessage(
+      WHITELIST_DOMAIN,
+      hashWhitelistUpdate(user, { dailyLimitOverride, kycExpiry })
+    );
+
+    const tx = await this.program.methods
+      .addToWhitelist(user, dailyLimitOverride ?? null, kycExpiry, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        whitelistEntry: this.whitelistEntryAddress(user),
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Remove a user from the whitelist (requires authority)
+   */
+  async removeFromWhitelist(user: PublicKey, signatures: ValidatorSignature[] = []): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for whitelist update');
+    }
+
+    const message = await this.governanceMessage(WHITELIST_DOMAIN, hashWhitelistUpdate(user));
+
+    const tx = await this.program.methods
+      .removeFromWhitelist(user, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        whitelistEntry: this.whitelistEntryAddress(user),
+        authority: this.authority.publicKey,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Split the collected wNOCK fees between recipients (requires authority)
+   */
+  async distributeFees(recipients: FeeRecipient[], signatures: ValidatorSignature[] = []): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for fee distribution');
+    }
+
+    const message = await this.governanceMessage(FEE_DISTRIBUTION_DOMAIN, hashFeeDistribution(recipients));
+    const recipientAccounts = await Promise.all(
+      recipients.map(async ({ recipient }) => ({
+        pubkey: await getAssociatedTokenAddress(this.wnockMint, recipient, true),
+        isSigner: false,
+        isWritable: true,
+      }))
+    );
+
+    const tx = await this.program.methods
+      .distributeFees(recipients, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        feeDistribution: this.feeDistribution,
+        wnockMint: this.wnockMint,
+        feeCollector: await getAssociatedTokenAddress(this.wnockMint, this.bridgeState, true),
+        authority: this.authority.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .remainingAccounts(recipientAccounts)
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Whether distributeFees can run now: a week after the last distribution, or once the
+   * collected fees reach the auto-distribution threshold. The program rejects it otherwise.
+   */
+  async isFeeDistributionDue(now: number = Math.floor(Date.now() / 1000)): Promise<boolean> {
+    const distribution = await this.program.account.feeDistribution.fetchNullable(this.feeDistribution);
+    if (!distribution) {
+      return true;
+    }
+
+    const feeCollector = await getAssociatedTokenAddress(this.wnockMint, this.bridgeState, true);
+    const balance = new BN((await this.connection.getTokenAccountBalance(feeCollector)).value.amount);
+    const threshold = distribution.autoDistributeThreshold as BN;
+    return now - (distribution.lastDistributionAt as BN).toNumber() >= FEE_DISTRIBUTION_INTERVAL_SECONDS
+      || (!threshold.isZero() && balance.gte(threshold));
+  }
+
+  /**
+   * Set the fee balance that makes a distribution due before the weekly deadline (requires authority)
+   */
+  async configureFeeDistribution(autoDistributeThreshold: BN, signatures: ValidatorSignature[] = []): Promise<string> {
+    if (!this.authority) {
+      throw new Error('Authority keypair required for fee distribution config');
+    }
+
+    const message = await this.governanceMessage(
+      FEE_DISTRIBUTION_CONFIG_DOMAIN,
+      autoDistributeThreshold.toArrayLike(Buffer, 'le', 8)
+    );
+
+    const tx = await this.program.methods
+      .configureFeeDistribution(autoDistributeThreshold, signatures)
+      .accounts({
+        bridgeState: this.bridgeState,
+        feeDistribution: this.feeDistribution,
+        authority: this.authority.publicKey,
+        systemProgram: SystemProgram.programId,
+        instructions: SYSVAR_INSTRUCTIONS_PUBKEY,
+      })
+      .preInstructions([createEd25519Instruction(signatures, message)])
+      .signers([this.authority])
+      .rpc(this.confirmOptions);
+
+    return tx;
+  }
+
+  /**
+   * Message validators sign for the next governance action
+   */
+  async governanceMessage(domain: string, payload: Buffer = Buffer.alloc(0)): Promise<Buffer> {
+    const bridgeState = await this.getBridgeState();
+    return createGovernanceMessage(this.program.programId, domain, bridgeState.governanceNonce, payload);
+  }
+
   /**
    * Monitor bridge events
    */
@@ -553,7 +1353,209 @@ export class NockBridgeClient {
   }
 }
 
+// Domain tags for validator-signed messages (must match the program)
+export const DEPOSIT_DOMAIN = 'NOCK_BRIDGE_DEPOSIT';
+export const EMERGENCY_PAUSE_DOMAIN = 'NOCK_BRIDGE_EMERGENCY_PAUSE';
+export const UNPAUSE_DOMAIN = 'NOCK_BRIDGE_UNPAUSE';
+export const CONFIG_UPDATE_DOMAIN = 'NOCK_BRIDGE_CONFIG_UPDATE';
+export const EON_TRANSITION_DOMAIN = 'NOCK_BRIDGE_EON_TRANSITION';
+export const WHITELIST_DOMAIN = 'NOCK_BRIDGE_WHITELIST';
+export const EMERGENCY_DRAIN_DOMAIN = 'NOCK_BRIDGE_EMERGENCY_DRAIN';
+export const ROTATION_PROPOSAL_DOMAIN = 'NOCK_BRIDGE_ROTATION_PROPOSAL';
+export const ROTATION_EXECUTION_DOMAIN = 'NOCK_BRIDGE_ROTATION_EXECUTION';
+export const ROTATION_CANCELLATION_DOMAIN = 'NOCK_BRIDGE_ROTATION_CANCELLATION';
+export const MULTI_HOP_ROUTE_DOMAIN = 'NOCK_BRIDGE_MULTI_HOP_ROUTE';
+export const FEE_DISTRIBUTION_DOMAIN = 'NOCK_BRIDGE_FEE_DISTRIBUTION';
+export const FEE_DISTRIBUTION_CONFIG_DOMAIN = 'NOCK_BRIDGE_FEE_DISTRIBUTION_CONFIG';
+
+// Fee distribution becomes due this long after the last one, mirroring FeeDistribution::INTERVAL_SECONDS
+export const FEE_DISTRIBUTION_INTERVAL_SECONDS = 7 * 86400;
+
 // Utility functions
+export function createDepositMessage(
+  programId: PublicKey,
+  nockTxHash: number[],
+  recipient: PublicKey,
+  amount: BN,
+  blockHeight: BN,
+  eon?: BN
+): Buffer {
+  return Buffer.concat([
+    programId.toBuffer(),
+    Buffer.from(DEPOSIT_DOMAIN),
+    Buffer.from(nockTxHash),
+    recipient.toBuffer(),
+    amount.toArrayLike(Buffer, 'le', 8),
+    blockHeight.toArrayLike(Buffer, 'le', 8),
+    eon ? eon.toArrayLike(Buffer, 'le', 8) : Buffer.alloc(0),
+  ]);
+}
+
+export function createGovernanceMessage(
+  programId: PublicKey,
+  domain: string,
+  governanceNonce: BN,
+  payload: Buffer = Buffer.alloc(0)
+): Buffer {
+  return Buffer.concat([
+    programId.toBuffer(),
+    Buffer.from(domain),
+    governanceNonce.toArrayLike(Buffer, 'le', 8),
+    payload,
+  ]);
+}
+
+export function hashConfigUpdate(
+  feeRate?: number,
+  dailyLimit?: BN,
+  threshold?: number,
+  largeWithdrawalThreshold?: BN,
+  tierDiscounts?: number[],
+  whitelistEnabled?: boolean,
+  rotationDelay?: number
+): Buffer {
+  // Each set field is preceded by its tag, its position in the argument list
+  const parts: Buffer[] = [];
+  if (feeRate !== undefined) {
+    parts.push(Buffer.from([0]), new BN(feeRate).toArrayLike(Buffer, 'le', 2));
+  }
+  if (dailyLimit !== undefined) {
+    parts.push(Buffer.from([1]), dailyLimit.toArrayLike(Buffer, 'le', 8));
+  }
+  if (threshold !== undefined) {
+    parts.push(Buffer.from([2, threshold]));
+  }
+  if (largeWithdrawalThreshold !== undefined) {
+    parts.push(Buffer.from([3]), largeWithdrawalThreshold.toArrayLike(Buffer, 'le', 8));
+  }
+  if (tierDiscounts !== undefined) {
+    parts.push(Buffer.from([4]), ...tierDiscounts.map((discount) => new BN(discount).toArrayLike(Buffer, 'le', 2)));
+  }
+  if (whitelistEnabled !== undefined) {
+    parts.push(Buffer.from([5, whitelistEnabled ? 1 : 0]));
+  }
+  if (rotationDelay !== undefined) {
+    parts.push(Buffer.from([6]), new BN(rotationDelay).toArrayLike(Buffer, 'le', 4));
+  }
+  return createHash('sha256').update(Buffer.concat(parts)).digest();
+}
+
+export const SWAP_FEE_BPS = 30;
+
+/**
+ * Constant-product swap output after the pool fee, matching the program's rounding
+ */
+export function quoteSwap(amountIn: BN, reserveIn: BN, reserveOut: BN): BN {
+  const inAfterFee = amountIn.muln(10000 - SWAP_FEE_BPS);
+  return inAfterFee.mul(reserveOut).div(reserveIn.muln(10000).add(inAfterFee));
+}
+
+export function hashValidatorRotation(validators: PublicKey[], threshold: number): Buffer {
+  return createHash('sha256')
+    .update(Buffer.concat([...validators.map((validator) => validator.toBuffer()), Buffer.from([threshold])]))
+    .digest();
+}
+
+export function hashEmergencyDrain(destination: number[], amount: BN): Buffer {
+  return createHash('sha256')
+    .update(Buffer.concat([Buffer.from(destination), amount.toArrayLike(Buffer, 'le', 8)]))
+    .digest();
+}
+
+/**
+ * Hash of a whitelist change, matching the program; omit `approval` to remove the user
+ */
+export function hashWhitelistUpdate(
+  user: PublicKey,
+  approval?: { dailyLimitOverride?: BN; kycExpiry: BN }
+): Buffer {
+  const parts: Buffer[] = [user.toBuffer()];
+  if (approval === undefined) {
+    parts.push(Buffer.from([0]));
+  } else {
+    parts.push(Buffer.from([1]));
+    if (approval.dailyLimitOverride === undefined) {
+      parts.push(Buffer.from([0]));
+    } else {
+      parts.push(Buffer.from([1]), approval.dailyLimitOverride.toArrayLike(Buffer, 'le', 8));
+    }
+    parts.push(approval.kycExpiry.toTwos(64).toArrayLike(Buffer, 'le', 8));
+  }
+  return createHash('sha256').update(Buffer.concat(parts)).digest();
+}
+
+export function hashFeeDistribution(recipients: FeeRecipient[]): Buffer {
+  const parts = recipients.flatMap(({ recipient, shareBps }) => {
+    const share = Buffer.alloc(2);
+    share.writeUInt16LE(shareBps);
+    return [recipient.toBuffer(), share];
+  });
+  return createHash('sha256').update(Buffer.concat(parts)).digest();
+}
+
+export function hashMultiHopRoute(dexProgram: PublicKey, nockMint: PublicKey): Buffer {
+  return createHash('sha256').update(Buffer.concat([dexProgram.toBuffer(), nockMint.toBuffer()])).digest();
+}
+
+export function hashEonTransition(
+  newEon: BN,
+  startBlockHeight: BN,
+  validatorRotation?: PublicKey[]
+): Buffer {
+  const parts: Buffer[] = [
+    newEon.toArrayLike(Buffer, 'le', 8),
+    startBlockHeight.toArrayLike(Buffer, 'le', 8),
+  ];
+  if (validatorRotation !== undefined) {
+    parts.push(...validatorRotation.map((validator) => validator.toBuffer()));
+  }
+  return createHash('sha256').update(Buffer.concat(parts)).digest();
+}
+
+// Ed25519 program instruction layout
+const ED25519_OFFSETS_START = 2;
+const ED25519_OFFSETS_SIZE = 14;
+const ED25519_CURRENT_INSTRUCTION = 0xffff;
+
+/**
+ * Single Ed25519 program instruction verifying every validator signature over
+ * a shared message; the bridge program only counts signatures checked here
+ */
+export function createEd25519Instruction(
+  signatures: ValidatorSignature[],
+  message: Buffer
+): TransactionInstruction {
+  const count = signatures.length;
+  const headerSize = ED25519_OFFSETS_START + ED25519_OFFSETS_SIZE * count;
+  const messageOffset = headerSize + count * 96;
+  const data = Buffer.alloc(messageOffset + message.length);
+
+  data.writeUInt8(count, 0);
+  signatures.forEach((sig, i) => {
+    const publicKeyOffset = headerSize + i * 96;
+    const signatureOffset = publicKeyOffset + 32;
+    const at = ED25519_OFFSETS_START + i * ED25519_OFFSETS_SIZE;
+
+    data.writeUInt16LE(signatureOffset, at);
+    data.writeUInt16LE(ED25519_CURRENT_INSTRUCTION, at + 2);
+    data.writeUInt16LE(publicKeyOffset, at + 4);
+    data.writeUInt16LE(ED25519_CURRENT_INSTRUCTION, at + 6);
+    data.writeUInt16LE(messageOffset, at + 8);
+    data.writeUInt16LE(message.length, at + 10);
+    data.writeUInt16LE(ED25519_CURRENT_INSTRUCTION, at + 12);
+
+    sig.validator.toBuffer().copy(data, publicKeyOffset);
+    Buffer.from(sig.signature).copy(data, signatureOffset);
+  });
+  message.copy(data, messageOffset);
+
+  return new TransactionInstruction({
+    keys: [],
+    programId: Ed25519Program.programId,
+    data,
+  });
+}
+
 export function createValidatorSignature(
   validator: PublicKey,
   signature: Uint8Array